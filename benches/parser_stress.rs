@@ -0,0 +1,76 @@
+//! 语法分析器压力测试
+//!
+//! 运行：`cargo bench --bench parser_stress`
+//!
+//! 构造嵌套括号、类型转换、Lambda 和大量变量声明等需要前瞻的输入，
+//! 按规模翻倍测量词法+语法分析耗时。解析时间应随输入规模线性增长，
+//! 出现平方或指数增长说明前瞻判断又引入了回退重解析。
+
+use std::time::{Duration, Instant};
+
+use cavvy::lexer::lex;
+use cavvy::parser::parse;
+
+/// 嵌套括号包裹的标识符：((((a))))，每层都要判断是否是类型转换或 Lambda
+fn nested_parens(depth: usize) -> String {
+    format!("{}a{}", "(".repeat(depth), ")".repeat(depth))
+}
+
+/// 嵌套类型转换：(int) (long) (int) ... a
+fn nested_casts(depth: usize) -> String {
+    let casts: String = (0..depth).map(|i| if i % 2 == 0 { "(int) " } else { "(long) " }).collect();
+    format!("{}a", casts)
+}
+
+/// 括号中嵌套的 Lambda：((x) -> ((x) -> ... x))
+fn nested_lambdas(depth: usize) -> String {
+    format!("{}x{}", "((x) -> ".repeat(depth), ")".repeat(depth))
+}
+
+/// 大量变量声明与表达式语句交替（语句级的"类型 + 名称"前瞻）
+fn many_statements(count: usize) -> String {
+    (0..count)
+        .map(|i| format!("int v{} = (v{} + 1) * (a); a = (a) + v{};", i, i.saturating_sub(1), i))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn program(body: &str) -> String {
+    format!("public class Main {{ public static void main() {{ int a = 1; int v0 = 0;\n{}\n }} }}", body)
+}
+
+fn time_parse(source: &str) -> Duration {
+    let start = Instant::now();
+    let tokens = lex(source).expect("stress input should lex");
+    parse(tokens).expect("stress input should parse");
+    start.elapsed()
+}
+
+fn run_case(name: &str, sizes: &[usize], make: impl Fn(usize) -> String) {
+    println!("{}:", name);
+    let mut previous: Option<Duration> = None;
+    for &size in sizes {
+        let source = program(&make(size));
+        let elapsed = time_parse(&source);
+        let ratio = previous
+            .map(|p| format!("  x{:.1}", elapsed.as_secs_f64() / p.as_secs_f64().max(1e-9)))
+            .unwrap_or_default();
+        println!("  n = {:>6}  {:>10.3} ms{}", size, elapsed.as_secs_f64() * 1000.0, ratio);
+        previous = Some(elapsed);
+    }
+}
+
+fn main() {
+    // 深层嵌套在独立线程中运行，避免递归下降耗尽主线程栈
+    let handle = std::thread::Builder::new()
+        .stack_size(512 * 1024 * 1024)
+        .spawn(|| {
+            let depths = [250, 500, 1000, 2000];
+            run_case("nested parens", &depths, |n| format!("int x = {};", nested_parens(n)));
+            run_case("nested casts", &depths, |n| format!("long x = {};", nested_casts(n)));
+            run_case("nested lambdas", &depths, |n| format!("var f = {};", nested_lambdas(n)));
+            run_case("statements", &[2500, 5000, 10000, 20000], many_statements);
+        })
+        .expect("failed to spawn bench thread");
+    handle.join().expect("bench thread panicked");
+}
@@ -2,6 +2,7 @@ use std::env;
 use std::fs;
 use std::path::PathBuf;
 use std::collections::HashMap;
+use std::process::Command;
 
 fn parse_verinfo() -> Result<HashMap<String, HashMap<String, String>>, String> {
     let content = fs::read_to_string(".verinfo")
@@ -66,6 +67,48 @@ fn main() {
                 }
             }
             
+            if let Some(reduce_section) = verinfo.get("CAY-REDUCE")
+                && let Some(version) = reduce_section.get("version")
+            {
+                println!("cargo:rustc-env=CAY_REDUCE_VERSION={}", version);
+            }
+
+            if let Some(lsp_section) = verinfo.get("CAY-LSP")
+                && let Some(version) = lsp_section.get("version")
+            {
+                println!("cargo:rustc-env=CAY_LSP_VERSION={}", version);
+            }
+
+            if let Some(watch_section) = verinfo.get("CAY-WATCH")
+                && let Some(version) = watch_section.get("version")
+            {
+                println!("cargo:rustc-env=CAY_WATCH_VERSION={}", version);
+            }
+
+            if let Some(repl_section) = verinfo.get("CAY-REPL")
+                && let Some(version) = repl_section.get("version")
+            {
+                println!("cargo:rustc-env=CAY_REPL_VERSION={}", version);
+            }
+
+            if let Some(refactor_section) = verinfo.get("CAY-REFACTOR")
+                && let Some(version) = refactor_section.get("version")
+            {
+                println!("cargo:rustc-env=CAY_REFACTOR_VERSION={}", version);
+            }
+
+            if let Some(run_section) = verinfo.get("CAY-RUN")
+                && let Some(version) = run_section.get("version")
+            {
+                println!("cargo:rustc-env=CAY_RUN_VERSION={}", version);
+            }
+            
+            if let Some(llvm_section) = verinfo.get("LLVM-MINIMAL")
+                && let Some(version) = llvm_section.get("version")
+            {
+                println!("cargo:rustc-env=LLVM_MINIMAL_VERSION={}", version);
+            }
+            
             // 设置通用版本（使用EOLC的版本）
             if let Some(eolc_section) = verinfo.get("EOLC") {
                 if let Some(version) = eolc_section.get("version") {
@@ -80,6 +123,12 @@ fn main() {
             println!("cargo:rustc-env=CAY-IR_VERSION=0.3.2.0");
             println!("cargo:rustc-env=IR2EXE_VERSION=0.3.2.0");
             println!("cargo:rustc-env=CAY_CHECK_VERSION=0.3.2.0");
+            println!("cargo:rustc-env=CAY_REDUCE_VERSION=0.3.2.0");
+            println!("cargo:rustc-env=CAY_LSP_VERSION=0.3.2.0");
+            println!("cargo:rustc-env=CAY_WATCH_VERSION=0.3.2.0");
+            println!("cargo:rustc-env=CAY_REPL_VERSION=0.3.2.0");
+            println!("cargo:rustc-env=CAY_REFACTOR_VERSION=0.3.2.0");
+            println!("cargo:rustc-env=CAY_RUN_VERSION=0.3.2.0");
             println!("cargo:rustc-env=VERSION=0.3.2.0");
         }
     }
@@ -109,6 +158,7 @@ fn main() {
     println!("cargo:rerun-if-changed=lib/");
     println!("cargo:rerun-if-changed=mingw-minimal/");
     println!("cargo:rerun-if-changed=third-party/");
+    println!("cargo:rerun-if-changed=runtime/");
     
     if is_windows {
         // Windows平台：复制Windows版LLVM工具
@@ -150,6 +200,42 @@ fn main() {
     // 复制 third-party 目录 (许可证文件) - 所有平台都需要
     copy_dir_all("third-party", &target_dir.join("third-party"))
         .expect("Failed to copy third-party directory");
+
+    // 构建原生运行时库并复制到编译器所在目录，ir2exe 链接时自动使用
+    let runtime_lib = build_runtime(&out_path).expect("Failed to build runtime library");
+    let runtime_name = runtime_lib.file_name().expect("runtime library has no file name");
+    fs::copy(&runtime_lib, target_dir.join(runtime_name))
+        .expect("Failed to copy runtime library");
+}
+
+/// 用独立的 target 目录构建 `runtime/` 静态库（避免与外层构建争用目录锁），返回库文件路径
+///
+/// 运行时库总是以 release 配置构建：它随生成的程序一起链接，与编译器自身的 profile 无关。
+fn build_runtime(out_path: &std::path::Path) -> Result<PathBuf, String> {
+    let target = env::var("TARGET").map_err(|e| e.to_string())?;
+    let runtime_target_dir = out_path.join("runtime");
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+
+    let status = Command::new(cargo)
+        .args(["build", "--release", "--manifest-path", "runtime/Cargo.toml", "--target", &target])
+        .arg("--target-dir").arg(&runtime_target_dir)
+        // 外层构建的编译参数不适用于运行时库
+        .env_remove("CARGO_ENCODED_RUSTFLAGS")
+        .env_remove("RUSTFLAGS")
+        .env_remove("CARGO_TARGET_DIR")
+        .status()
+        .map_err(|e| format!("failed to run cargo: {}", e))?;
+    if !status.success() {
+        return Err(format!("cargo build for runtime/ failed with {}", status));
+    }
+
+    let lib_name = if target.contains("msvc") { "cavvy_runtime.lib" } else { "libcavvy_runtime.a" };
+    let lib = runtime_target_dir.join(&target).join("release").join(lib_name);
+    if lib.exists() {
+        Ok(lib)
+    } else {
+        Err(format!("{} was not produced", lib.display()))
+    }
 }
 
 fn copy_dir_all(src: impl AsRef<std::path::Path>, dst: impl AsRef<std::path::Path>) -> std::io::Result<()> {
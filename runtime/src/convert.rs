@@ -0,0 +1,117 @@
+//! 数值、布尔值、字符转字符串，以及 UTF-8 编解码
+//!
+//! char 保存 Unicode 码点（i32），按 UTF-8 编码；不是合法码点的值（负数、代理项、超过 U+10FFFF）
+//! 编码为替换字符 U+FFFD。解码时首字节是续字节或无效字节、续字节缺失时同样返回 U+FFFD。
+
+use core::ffi::c_char;
+
+use crate::libc;
+
+/// 替换字符 U+FFFD
+pub const REPLACEMENT: i32 = 0xFFFD;
+
+static TRUE_STR: [u8; 5] = *b"true\0";
+static FALSE_STR: [u8; 6] = *b"false\0";
+
+/// 整数转十进制字符串
+#[unsafe(no_mangle)]
+pub extern "C" fn __cay_int_to_string(value: i64) -> *mut c_char {
+    // 20 位数字加符号，32 字节足够
+    let buf = libc::alloc(32);
+    if buf.is_null() {
+        return buf as *mut c_char;
+    }
+    let mut digits = [0u8; 20];
+    let mut magnitude = value.unsigned_abs();
+    let mut count = 0;
+    loop {
+        digits[count] = b'0' + (magnitude % 10) as u8;
+        count += 1;
+        magnitude /= 10;
+        if magnitude == 0 {
+            break;
+        }
+    }
+    let mut pos = 0;
+    unsafe {
+        if value < 0 {
+            *buf = b'-';
+            pos = 1;
+        }
+        for &digit in digits[..count].iter().rev() {
+            *buf.add(pos) = digit;
+            pos += 1;
+        }
+    }
+    buf as *mut c_char
+}
+
+/// 布尔值转 "true" / "false"（返回常量字符串）
+///
+/// 参数按字节接收：调用方传入的是 LLVM 的 `i1`，只有最低位有意义。
+#[unsafe(no_mangle)]
+pub extern "C" fn __cay_bool_to_string(value: u8) -> *mut c_char {
+    if value & 1 != 0 {
+        TRUE_STR.as_ptr() as *mut c_char
+    } else {
+        FALSE_STR.as_ptr() as *mut c_char
+    }
+}
+
+/// 字符（码点）转 UTF-8 字符串
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __cay_char_to_string(value: i32) -> *mut c_char {
+    let buf = libc::alloc(5);
+    if !buf.is_null() {
+        unsafe { __cay_utf8_encode(value, buf) };
+    }
+    buf as *mut c_char
+}
+
+/// 把码点按 UTF-8 写入 `out`（至少 4 字节），返回写入的字节数
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __cay_utf8_encode(value: i32, out: *mut u8) -> i64 {
+    let value = value as u32;
+    let code = if value > 0x10FFFF || value & 0xFFFF_F800 == 0xD800 { REPLACEMENT as u32 } else { value };
+    let cont = |shift: u32| (((code >> shift) & 0x3F) | 0x80) as u8;
+    let encoded: ([u8; 4], usize) = if code < 0x80 {
+        ([code as u8, 0, 0, 0], 1)
+    } else if code < 0x800 {
+        ([((code >> 6) | 0xC0) as u8, cont(0), 0, 0], 2)
+    } else if code < 0x10000 {
+        ([((code >> 12) | 0xE0) as u8, cont(6), cont(0), 0], 3)
+    } else {
+        ([((code >> 18) | 0xF0) as u8, cont(12), cont(6), cont(0)], 4)
+    };
+    let (bytes, len) = encoded;
+    unsafe { core::ptr::copy_nonoverlapping(bytes.as_ptr(), out, len) };
+    len as i64
+}
+
+/// 解码从 `ptr` 开始的一个 UTF-8 序列，返回码点
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __cay_utf8_decode(ptr: *const u8) -> i32 {
+    let lead = unsafe { *ptr } as i32;
+    let Some((count, mut code)) = utf8_lead(lead) else {
+        return if lead < 0x80 { lead } else { REPLACEMENT };
+    };
+    // 遇到终止符或其他非续字节时停止，不会越过字符串末尾
+    for i in 1..=count {
+        let byte = unsafe { *ptr.add(i) } as i32;
+        if byte & 0xC0 != 0x80 {
+            return REPLACEMENT;
+        }
+        code = (code << 6) | (byte & 0x3F);
+    }
+    code
+}
+
+/// 多字节序列的首字节：返回续字节个数和首字节携带的码点位；ASCII 和无效首字节返回 `None`
+pub fn utf8_lead(lead: i32) -> Option<(usize, i32)> {
+    match lead {
+        0xC0..=0xDF => Some((1, lead & 0x1F)),
+        0xE0..=0xEF => Some((2, lead & 0x0F)),
+        0xF0..=0xF7 => Some((3, lead & 0x07)),
+        _ => None,
+    }
+}
@@ -0,0 +1,17 @@
+//! 运行时错误报告
+
+use core::ffi::c_char;
+
+use crate::libc;
+
+/// 打印运行时错误并以退出码 1 结束程序
+///
+/// `format` 是 printf 格式串，最多引用两个 64 位整数参数 `a`、`b`（未使用的参数传 0）。
+/// 与程序输出使用同一个 stdout，`exit` 会先刷新缓冲区，错误信息出现在已有输出之后。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __cay_runtime_error(format: *const c_char, a: i64, b: i64) -> ! {
+    unsafe {
+        libc::printf(format, a, b);
+        libc::exit(1)
+    }
+}
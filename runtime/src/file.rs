@@ -0,0 +1,97 @@
+//! 文件读写运行时函数
+//!
+//! 内置 File 类的静态方法展开为这里的函数，只使用 C 标准库的 fopen/fread/fputs/remove。
+//! 文件以二进制模式打开，内容按字节原样读写，不转换行尾。
+//! 路径为 null 或文件无法打开时不终止程序：readAll 返回 null，其余函数返回 false。
+
+use core::ffi::{CStr, c_char};
+use core::ptr;
+
+use crate::libc;
+
+/// File.readAll：读取整个文件，缓冲区满时容量翻倍，不依赖 ftell 求文件大小
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __cay_file_read_all(path: *const c_char) -> *mut c_char {
+    if path.is_null() {
+        return ptr::null_mut();
+    }
+    let file = unsafe { libc::fopen(path, c"rb".as_ptr()) };
+    if file.is_null() {
+        return ptr::null_mut();
+    }
+    let mut cap = 4096;
+    let mut buf = libc::alloc(cap);
+    let mut len = 0;
+    loop {
+        // 为终止符留出一个字节
+        let n = unsafe { libc::fread(buf.add(len).cast(), 1, cap - len - 1, file) };
+        len += n;
+        if n == 0 {
+            break;
+        }
+        if len + 1 >= cap {
+            let grown = libc::alloc(cap * 2);
+            unsafe { ptr::copy_nonoverlapping(buf, grown, len) };
+            buf = grown;
+            cap *= 2;
+        }
+    }
+    unsafe {
+        libc::fclose(file);
+        *buf.add(len) = 0;
+    }
+    buf as *mut c_char
+}
+
+/// File.writeAll / File.appendLine 的公共部分：以 `mode` 打开文件写入文本，null 文本写入 "null"
+///
+/// `newline` 为 true 时在文本后追加换行符。关闭文件成功（数据已写出）时返回 true。
+unsafe fn write_text(path: *const c_char, text: *const c_char, mode: &CStr, newline: bool) -> bool {
+    if path.is_null() {
+        return false;
+    }
+    let file = unsafe { libc::fopen(path, mode.as_ptr()) };
+    if file.is_null() {
+        return false;
+    }
+    let content = if text.is_null() { c"null".as_ptr() } else { text };
+    unsafe {
+        libc::fputs(content, file);
+        if newline {
+            libc::fputc(b'\n' as i32, file);
+        }
+        libc::fclose(file) == 0
+    }
+}
+
+/// File.writeAll：覆盖写入
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __cay_file_write_all(path: *const c_char, text: *const c_char) -> bool {
+    unsafe { write_text(path, text, c"wb", false) }
+}
+
+/// File.appendLine：追加一行
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __cay_file_append_line(path: *const c_char, text: *const c_char) -> bool {
+    unsafe { write_text(path, text, c"ab", true) }
+}
+
+/// File.exists：文件能以只读方式打开时返回 true
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __cay_file_exists(path: *const c_char) -> bool {
+    if path.is_null() {
+        return false;
+    }
+    let file = unsafe { libc::fopen(path, c"rb".as_ptr()) };
+    if file.is_null() {
+        return false;
+    }
+    unsafe { libc::fclose(file) };
+    true
+}
+
+/// File.delete：删除文件，成功时返回 true
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __cay_file_delete(path: *const c_char) -> bool {
+    !path.is_null() && unsafe { libc::remove(path) } == 0
+}
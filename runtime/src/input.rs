@@ -0,0 +1,83 @@
+//! 读取标准输入的运行时函数
+
+use core::ffi::c_char;
+
+use crate::convert::{REPLACEMENT, utf8_lead};
+use crate::libc;
+
+/// readLine：读取到换行或 EOF，行的长度不受限制
+///
+/// 返回堆上的字符串，不含换行符（Windows 的 "\r\n" 一并去掉）；EOF 时返回空字符串。
+#[unsafe(no_mangle)]
+pub extern "C" fn __cay_read_line() -> *mut c_char {
+    let mut cap = 64;
+    let mut buf = libc::alloc(cap);
+    let mut len = 0;
+    loop {
+        let c = unsafe { libc::getchar() };
+        if c == -1 || c == b'\n' as i32 {
+            break;
+        }
+        // 为新字符和终止符留出空间，缓冲区满时容量翻倍
+        if len + 2 > cap {
+            let grown = libc::alloc(cap * 2);
+            unsafe { core::ptr::copy_nonoverlapping(buf, grown, len) };
+            buf = grown;
+            cap *= 2;
+        }
+        unsafe { *buf.add(len) = c as u8 };
+        len += 1;
+    }
+    if len > 0 && unsafe { *buf.add(len - 1) } == b'\r' {
+        len -= 1;
+    }
+    unsafe { *buf.add(len) = 0 };
+    buf as *mut c_char
+}
+
+/// readBool：读取下一个单词（最多 7 个字符），等于 "true" 时返回 true
+#[unsafe(no_mangle)]
+pub extern "C" fn __cay_read_bool() -> bool {
+    let word = libc::alloc(8) as *mut c_char;
+    unsafe {
+        libc::scanf(c"%7s".as_ptr(), word);
+        libc::strcmp(word, c"true".as_ptr()) == 0
+    }
+}
+
+/// readChar：跳过空白后读取一个 UTF-8 字符，返回其码点；EOF 时返回 0
+#[unsafe(no_mangle)]
+pub extern "C" fn __cay_read_char() -> i32 {
+    loop {
+        let c = unsafe { libc::getchar() };
+        if c < 0 {
+            return 0;
+        }
+        // 空格以及 \t \n \v \f \r
+        if c != b' ' as i32 && !(9..=13).contains(&c) {
+            return __cay_read_utf8_tail(c);
+        }
+    }
+}
+
+/// 已读入 UTF-8 序列的首字节 `lead`，从标准输入读取其余的续字节并返回码点
+///
+/// 首字节是续字节或无效字节、续字节缺失（EOF 或不是 10xxxxxx）时返回替换字符 U+FFFD，
+/// 与 `charAt` 的解码一致。
+#[unsafe(no_mangle)]
+pub extern "C" fn __cay_read_utf8_tail(lead: i32) -> i32 {
+    if (0..0x80).contains(&lead) {
+        return lead;
+    }
+    let Some((count, mut code)) = utf8_lead(lead) else {
+        return REPLACEMENT;
+    };
+    for _ in 0..count {
+        let byte = unsafe { libc::getchar() };
+        if byte & !0x3F != 0x80 {
+            return REPLACEMENT;
+        }
+        code = (code << 6) | (byte & 0x3F);
+    }
+    code
+}
@@ -0,0 +1,26 @@
+//! Cavvy 原生运行时库
+//!
+//! 以 C ABI 导出生成的代码调用的 `__cay_*` 运行时函数，编译为静态库 `libcavvy_runtime.a`。
+//! 编译器的 build.rs 构建本库并复制到编译器所在目录，ir2exe 链接可执行文件时自动链接。
+//!
+//! 覆盖字符串操作、标准输入和文件读写、运行时错误报告；其余运行时函数（浮点格式化、
+//! 反射、Map 等）仍由编译器以 LLVM IR 生成（见 `src/codegen/runtime/`）。
+//! 每个函数的签名和语义与编译器内联生成的同名函数一致：使用 `--external-runtime` 时
+//! 程序模块只包含声明，这里的实现与独立运行时模块一起提供全部定义。
+//!
+//! 不依赖 Rust 标准库，只调用 C 标准库，与生成的程序共用同一份 stdio 缓冲区。
+
+#![no_std]
+
+mod convert;
+mod error;
+mod file;
+mod input;
+mod libc;
+mod string;
+
+/// 运行时函数的 panic 只可能来自实现缺陷，直接终止进程
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    unsafe { libc::abort() }
+}
@@ -0,0 +1,47 @@
+//! C 标准库声明和运行时内部共用的辅助函数
+
+use core::ffi::{c_char, c_int, c_void};
+
+unsafe extern "C" {
+    pub fn strlen(s: *const c_char) -> usize;
+    pub fn strcmp(a: *const c_char, b: *const c_char) -> c_int;
+    pub fn calloc(count: usize, size: usize) -> *mut c_void;
+    pub fn printf(format: *const c_char, ...) -> c_int;
+    pub fn scanf(format: *const c_char, ...) -> c_int;
+    pub fn getchar() -> c_int;
+    pub fn exit(code: c_int) -> !;
+    pub fn abort() -> !;
+    pub fn fopen(path: *const c_char, mode: *const c_char) -> *mut c_void;
+    pub fn fclose(file: *mut c_void) -> c_int;
+    pub fn fread(buf: *mut c_void, size: usize, count: usize, file: *mut c_void) -> usize;
+    pub fn fputs(s: *const c_char, file: *mut c_void) -> c_int;
+    pub fn fputc(c: c_int, file: *mut c_void) -> c_int;
+    pub fn remove(path: *const c_char) -> c_int;
+}
+
+/// 空字符串，null 安全的函数以它代替 null 返回
+static EMPTY: [u8; 1] = [0];
+
+/// 空字符串常量的指针（调用方不会写入）
+pub fn empty_str() -> *mut c_char {
+    EMPTY.as_ptr() as *mut c_char
+}
+
+/// 分配 `size` 字节并清零，与生成的代码一样使用 calloc
+pub fn alloc(size: usize) -> *mut u8 {
+    unsafe { calloc(1, size) as *mut u8 }
+}
+
+/// 以字节切片查看 C 字符串（不含终止符），`s` 不能为 null
+pub unsafe fn bytes<'a>(s: *const c_char) -> &'a [u8] {
+    unsafe { core::slice::from_raw_parts(s as *const u8, strlen(s)) }
+}
+
+/// 把 `content` 复制到新分配的字符串中；分配失败时返回 null
+pub fn new_string(content: &[u8]) -> *mut c_char {
+    let buf = alloc(content.len() + 1);
+    if !buf.is_null() {
+        unsafe { core::ptr::copy_nonoverlapping(content.as_ptr(), buf, content.len()) };
+    }
+    buf as *mut c_char
+}
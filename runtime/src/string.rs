@@ -0,0 +1,179 @@
+//! 字符串运行时函数
+//!
+//! 字符串是以 `\0` 结尾的 UTF-8 字节序列，下标和长度都按字节计算。
+//! 参数为 null 时不终止程序：返回新字符串的函数返回空字符串，判断函数返回 false，查找返回 -1。
+
+use core::ffi::c_char;
+
+use crate::convert::__cay_utf8_decode;
+use crate::libc::{self, bytes, empty_str, new_string};
+
+/// null 视为空字符串
+unsafe fn bytes_or_empty<'a>(s: *const c_char) -> &'a [u8] {
+    if s.is_null() { &[] } else { unsafe { bytes(s) } }
+}
+
+/// `needle` 在 `haystack` 中第一次出现的字节下标；空串出现在 0
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.len() > haystack.len() {
+        return None;
+    }
+    (0..=haystack.len() - needle.len()).find(|&i| &haystack[i..i + needle.len()] == needle)
+}
+
+/// 字符串拼接，null 按空字符串处理；分配失败时返回空字符串
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __cay_string_concat(a: *const c_char, b: *const c_char) -> *mut c_char {
+    let (a, b) = unsafe { (bytes_or_empty(a), bytes_or_empty(b)) };
+    let result = libc::alloc(a.len() + b.len() + 1);
+    if result.is_null() {
+        return empty_str();
+    }
+    unsafe {
+        core::ptr::copy_nonoverlapping(a.as_ptr(), result, a.len());
+        core::ptr::copy_nonoverlapping(b.as_ptr(), result.add(a.len()), b.len());
+    }
+    result as *mut c_char
+}
+
+/// 字符串的字节长度
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __cay_string_length(s: *const c_char) -> i32 {
+    unsafe { bytes_or_empty(s) }.len() as i32
+}
+
+/// 按内容比较；同一指针（包括两个 null）相等，只有一方为 null 时不相等
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __cay_string_equals(a: *const c_char, b: *const c_char) -> bool {
+    if a == b {
+        return true;
+    }
+    if a.is_null() || b.is_null() {
+        return false;
+    }
+    unsafe { libc::strcmp(a, b) == 0 }
+}
+
+/// substring(begin, end)：下标先截到 [0, length] 内，begin 大于 end 时结果为空串
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __cay_string_substring(s: *const c_char, begin: i32, end: i32) -> *mut c_char {
+    if s.is_null() {
+        return empty_str();
+    }
+    let content = unsafe { bytes(s) };
+    let len = content.len() as i32;
+    let end = end.clamp(0, len) as usize;
+    let begin = begin.clamp(0, len) as usize;
+    new_string(&content[begin.min(end)..end])
+}
+
+/// 子串第一次出现的字节下标，找不到时返回 -1
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __cay_string_indexof(s: *const c_char, sub: *const c_char) -> i32 {
+    if s.is_null() || sub.is_null() {
+        return -1;
+    }
+    unsafe { find(bytes(s), bytes(sub)) }.map_or(-1, |i| i as i32)
+}
+
+/// contains：任一参数为 null 时返回 false
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __cay_string_contains(s: *const c_char, sub: *const c_char) -> bool {
+    unsafe { __cay_string_indexof(s, sub) >= 0 }
+}
+
+/// startsWith：任一参数为 null 时返回 false
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __cay_string_starts_with(s: *const c_char, affix: *const c_char) -> bool {
+    !s.is_null() && !affix.is_null() && unsafe { bytes(s).starts_with(bytes(affix)) }
+}
+
+/// endsWith：任一参数为 null 时返回 false
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __cay_string_ends_with(s: *const c_char, affix: *const c_char) -> bool {
+    !s.is_null() && !affix.is_null() && unsafe { bytes(s).ends_with(bytes(affix)) }
+}
+
+/// charAt：解码从字节下标 `index` 开始的 UTF-8 字符；下标越界时返回 0
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __cay_string_charat(s: *const c_char, index: i32) -> i32 {
+    let content = unsafe { bytes_or_empty(s) };
+    if index < 0 || index >= content.len() as i32 {
+        return 0;
+    }
+    unsafe { __cay_utf8_decode(content.as_ptr().add(index as usize)) }
+}
+
+/// replace：从左到右替换所有不重叠的 `old`；`old` 为空或任一参数为 null 时返回原串的副本
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __cay_string_replace(s: *const c_char, old: *const c_char, new: *const c_char) -> *mut c_char {
+    let content = unsafe { bytes_or_empty(s) };
+    if old.is_null() || new.is_null() || unsafe { *old } == 0 {
+        return new_string(content);
+    }
+    let (old, new) = unsafe { (bytes(old), bytes(new)) };
+
+    let mut count = 0;
+    let mut pos = 0;
+    while let Some(i) = find(&content[pos..], old) {
+        count += 1;
+        pos += i + old.len();
+    }
+
+    let result = libc::alloc(content.len() - count * old.len() + count * new.len() + 1);
+    if result.is_null() {
+        return empty_str();
+    }
+    let mut src = 0;
+    let mut dst = 0;
+    let copy = |part: &[u8], dst: &mut usize| {
+        unsafe { core::ptr::copy_nonoverlapping(part.as_ptr(), result.add(*dst), part.len()) };
+        *dst += part.len();
+    };
+    while let Some(i) = find(&content[src..], old) {
+        copy(&content[src..src + i], &mut dst);
+        copy(new, &mut dst);
+        src += i + old.len();
+    }
+    copy(&content[src..], &mut dst);
+    result as *mut c_char
+}
+
+/// trim：与 Java 一致，去除首尾所有码值不大于空格（0x20）的字节
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __cay_string_trim(s: *const c_char) -> *mut c_char {
+    if s.is_null() {
+        return empty_str();
+    }
+    let content = unsafe { bytes(s) };
+    let begin = content.iter().position(|&b| b > b' ').unwrap_or(content.len());
+    let end = content.iter().rposition(|&b| b > b' ').map_or(begin, |i| i + 1);
+    new_string(&content[begin..end])
+}
+
+/// toUpperCase / toLowerCase 的公共部分：只转换 ASCII 字母，其余字节原样复制
+unsafe fn map_ascii(s: *const c_char, convert: fn(&u8) -> u8) -> *mut c_char {
+    if s.is_null() {
+        return empty_str();
+    }
+    let content = unsafe { bytes(s) };
+    let result = libc::alloc(content.len() + 1);
+    if !result.is_null() {
+        for (i, b) in content.iter().enumerate() {
+            unsafe { *result.add(i) = convert(b) };
+        }
+    }
+    result as *mut c_char
+}
+
+/// toUpperCase
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __cay_string_to_upper(s: *const c_char) -> *mut c_char {
+    unsafe { map_ascii(s, u8::to_ascii_uppercase) }
+}
+
+/// toLowerCase
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __cay_string_to_lower(s: *const c_char) -> *mut c_char {
+    unsafe { map_ascii(s, u8::to_ascii_lowercase) }
+}
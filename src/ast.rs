@@ -1,15 +1,59 @@
 use crate::types::{Type, ParameterInfo, ClassInfo, MethodInfo};
 use crate::error::SourceLocation;
+use serde::Serialize;
 
-#[derive(Debug, Clone)]
+/// AST 节点 ID
+///
+/// 在语法分析时按节点构造顺序依次分配，同一份源码每次解析得到的 ID 完全相同。
+/// 后续各阶段可以用它作为旁路表（推导出的类型、常量值、捕获变量等）的键，
+/// 而不必修改 AST 或重复遍历。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub struct NodeId(pub u32);
+
+impl NodeId {
+    /// 编译器内部合成、不对应源码的节点
+    pub const DUMMY: NodeId = NodeId(u32::MAX);
+}
+
+/// 以节点 ID 为键的旁路表
+pub type NodeMap<T> = std::collections::HashMap<NodeId, T>;
+
+#[derive(Debug, Clone, Serialize)]
 pub struct Program {
+    /// 文件开头的 import 声明，由模块加载器解析并合并被导入的模块
+    pub imports: Vec<ImportDecl>,
     pub classes: Vec<ClassDecl>,
     pub interfaces: Vec<InterfaceDecl>,
     pub top_level_functions: Vec<TopLevelFunction>,
 }
 
+/// import 声明
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportDecl {
+    pub target: ImportTarget,
+    pub loc: SourceLocation,
+}
+
+/// import 声明导入的目标
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum ImportTarget {
+    /// `import "util/math.cay";`：相对导入者所在目录的文件路径
+    File(String),
+    /// `import util.math;`：按点分隔的模块名，对应 `util/math.cay`（或 `.cavvy`）
+    Module(Vec<String>),
+}
+
+impl std::fmt::Display for ImportTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportTarget::File(path) => write!(f, "\"{}\"", path),
+            ImportTarget::Module(parts) => write!(f, "{}", parts.join(".")),
+        }
+    }
+}
+
 /// 顶层函数声明（类外函数）
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct TopLevelFunction {
     pub name: String,
     pub modifiers: Vec<Modifier>,
@@ -17,27 +61,31 @@ pub struct TopLevelFunction {
     pub params: Vec<ParameterInfo>,
     pub body: Block,
     pub loc: SourceLocation,
+    pub id: NodeId,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct InterfaceDecl {
     pub name: String,
     pub modifiers: Vec<Modifier>,
     pub methods: Vec<MethodDecl>,
     pub loc: SourceLocation,
+    pub id: NodeId,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ClassDecl {
     pub name: String,
     pub modifiers: Vec<Modifier>,
+    pub type_params: Vec<String>,  // 泛型类型参数 class Box<T>
     pub parent: Option<String>,
     pub interfaces: Vec<String>,  // 实现的接口列表
     pub members: Vec<ClassMember>,
     pub loc: SourceLocation,
+    pub id: NodeId,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub enum ClassMember {
     Method(MethodDecl),
     Field(FieldDecl),
@@ -47,51 +95,56 @@ pub enum ClassMember {
     StaticInitializer(Block),    // 静态初始化块 static { ... }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct MethodDecl {
     pub name: String,
     pub modifiers: Vec<Modifier>,
+    pub type_params: Vec<String>,  // 泛型方法类型参数 <T> T identity(T x)
     pub return_type: Type,
     pub params: Vec<ParameterInfo>,
     pub body: Option<Block>,
     pub loc: SourceLocation,
+    pub id: NodeId,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct FieldDecl {
     pub name: String,
     pub field_type: Type,
     pub modifiers: Vec<Modifier>,
     pub initializer: Option<Expr>,
     pub loc: SourceLocation,
+    pub id: NodeId,
 }
 
 /// 构造函数声明
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ConstructorDecl {
     pub modifiers: Vec<Modifier>,
     pub params: Vec<crate::types::ParameterInfo>,
     pub body: Block,
     pub constructor_call: Option<ConstructorCall>, // this() 或 super() 调用
     pub loc: SourceLocation,
+    pub id: NodeId,
 }
 
 /// 构造函数调用（this() 或 super()）
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub enum ConstructorCall {
     This(Vec<Expr>),   // this(args)
     Super(Vec<Expr>),  // super(args)
 }
 
 /// 析构函数声明
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct DestructorDecl {
     pub modifiers: Vec<Modifier>,
     pub body: Block,
     pub loc: SourceLocation,
+    pub id: NodeId,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub enum Modifier {
     Public,
     Private,
@@ -102,87 +155,135 @@ pub enum Modifier {
     Native,
     Main,      // 标记主类，用于解决多main冲突
     Override,  // @Override 注解，标记方法重写
+    Symbol(String),  // @Symbol("name") 注解，native 方法绑定的 C 函数名
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Block {
     pub statements: Vec<Stmt>,
     pub loc: SourceLocation,
+    pub id: NodeId,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub enum Stmt {
     Expr(Expr),
     VarDecl(VarDecl),
-    Return(Option<Expr>),
+    Return(ReturnStmt),
     If(IfStmt),
     While(WhileStmt),
     For(ForStmt),
+    ForEach(ForEachStmt),
     DoWhile(DoWhileStmt),
     Switch(SwitchStmt),
     Block(Block),
-    Break,
-    Continue,
+    Labeled(LabeledStmt),
+    Break(JumpStmt),
+    Continue(JumpStmt),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct VarDecl {
     pub name: String,
     pub var_type: Type,
     pub initializer: Option<Expr>,
     pub is_final: bool,
     pub loc: SourceLocation,
+    pub id: NodeId,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct IfStmt {
     pub condition: Expr,
     pub then_branch: Box<Stmt>,
     pub else_branch: Option<Box<Stmt>>,
     pub loc: SourceLocation,
+    pub id: NodeId,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct WhileStmt {
     pub condition: Expr,
     pub body: Box<Stmt>,
     pub loc: SourceLocation,
+    pub id: NodeId,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ForStmt {
     pub init: Option<Box<Stmt>>,
     pub condition: Option<Expr>,
     pub update: Option<Expr>,
     pub body: Box<Stmt>,
     pub loc: SourceLocation,
+    pub id: NodeId,
+}
+
+/// for-each 循环语句 `for (Type x : expr)` / `for (var x : expr)`
+///
+/// `expr` 是数组，或提供 `int size()` 和 `get(int)` 方法的对象（如内置的 `List<T>`）。
+/// 使用 var/let/auto 时 `var_type` 为 `Type::Auto`，由语义分析取元素类型。
+#[derive(Debug, Clone, Serialize)]
+pub struct ForEachStmt {
+    pub var_name: String,
+    pub var_type: Type,
+    pub is_final: bool,
+    pub iterable: Expr,
+    pub body: Box<Stmt>,
+    pub loc: SourceLocation,
+    pub id: NodeId,
 }
 
 /// do-while 循环语句
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct DoWhileStmt {
     pub condition: Expr,
     pub body: Box<Stmt>,
     pub loc: SourceLocation,
+    pub id: NodeId,
+}
+
+/// 带标签的语句 `outer: for (...) { ... }`
+#[derive(Debug, Clone, Serialize)]
+pub struct LabeledStmt {
+    pub label: String,
+    pub body: Box<Stmt>,
+    pub loc: SourceLocation,
+    pub id: NodeId,
+}
+
+/// return 语句，`value` 为返回值（`return;` 时为 `None`）
+#[derive(Debug, Clone, Serialize)]
+pub struct ReturnStmt {
+    pub value: Option<Expr>,
+    pub loc: SourceLocation,
+}
+
+/// break / continue，`label` 为跳转目标的标签（`break outer;`）
+#[derive(Debug, Clone, Serialize)]
+pub struct JumpStmt {
+    pub label: Option<String>,
+    pub loc: SourceLocation,
 }
 
 /// switch case 分支
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Case {
     pub value: i64,
     pub body: Vec<Stmt>,
 }
 
 /// switch 语句
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SwitchStmt {
     pub expr: Expr,
     pub cases: Vec<Case>,
     pub default: Option<Vec<Stmt>>,
     pub loc: SourceLocation,
+    pub id: NodeId,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub enum Expr {
     Literal(LiteralValue),
     Identifier(String),
@@ -200,9 +301,11 @@ pub enum Expr {
     Lambda(LambdaExpr),        // Lambda 表达式: (params) -> { body }
     Ternary(TernaryExpr),      // 三元运算符: condition ? true_expr : false_expr
     InstanceOf(InstanceOfExpr), // instanceof 运算符: obj instanceof Type
+    NullCoalesce(NullCoalesceExpr), // 空值合并: a ?? b 或 a ?: b
+    InlineIr(InlineIrExpr),    // 内联 IR: __llvm__<T>("template", args...)
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub enum LiteralValue {
     Int32(i32),
     Int64(i64),
@@ -214,15 +317,16 @@ pub enum LiteralValue {
     Null,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct BinaryExpr {
     pub left: Box<Expr>,
     pub op: BinaryOp,
     pub right: Box<Expr>,
     pub loc: SourceLocation,
+    pub id: NodeId,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 pub enum BinaryOp {
     Add,
     Sub,
@@ -245,14 +349,63 @@ pub enum BinaryOp {
     UnsignedShr,
 }
 
-#[derive(Debug, Clone)]
+impl BinaryOp {
+    /// 运算符在源代码中的写法
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Mod => "%",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::And => "&&",
+            BinaryOp::Or => "||",
+            BinaryOp::BitAnd => "&",
+            BinaryOp::BitOr => "|",
+            BinaryOp::BitXor => "^",
+            BinaryOp::Shl => "<<",
+            BinaryOp::Shr => ">>",
+            BinaryOp::UnsignedShr => ">>>",
+        }
+    }
+
+    /// 运算符的优先级（数值越大结合越紧），与解析器的优先级层次一致
+    pub fn precedence(&self) -> u8 {
+        match self {
+            BinaryOp::Or => 3,
+            BinaryOp::And => 4,
+            BinaryOp::BitOr => 5,
+            BinaryOp::BitXor => 6,
+            BinaryOp::BitAnd => 7,
+            BinaryOp::Eq | BinaryOp::Ne => 8,
+            BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => 9,
+            BinaryOp::Shl | BinaryOp::Shr | BinaryOp::UnsignedShr => 10,
+            BinaryOp::Add | BinaryOp::Sub => 11,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => 12,
+        }
+    }
+
+    /// 是否是比较运算符（==, !=, <, <=, >, >=）
+    pub fn is_comparison(&self) -> bool {
+        matches!(self, BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct UnaryExpr {
     pub op: UnaryOp,
     pub operand: Box<Expr>,
     pub loc: SourceLocation,
+    pub id: NodeId,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 pub enum UnaryOp {
     Neg,
     Not,
@@ -263,36 +416,42 @@ pub enum UnaryOp {
     PostDec,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct CallExpr {
     pub callee: Box<Expr>,
     pub args: Vec<Expr>,
     pub loc: SourceLocation,
+    pub id: NodeId,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct MemberAccessExpr {
     pub object: Box<Expr>,
     pub member: String,
+    pub null_safe: bool,  // 安全导航 obj?.member：对象为 null 时结果为 null
     pub loc: SourceLocation,
+    pub id: NodeId,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct NewExpr {
     pub class_name: String,
+    pub type_args: Option<Vec<Type>>,  // 泛型实参：None 表示非泛型写法，空列表表示菱形 new Box<>()
     pub args: Vec<Expr>,
     pub loc: SourceLocation,
+    pub id: NodeId,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct AssignmentExpr {
     pub target: Box<Expr>,
     pub value: Box<Expr>,
     pub op: AssignOp,
     pub loc: SourceLocation,
+    pub id: NodeId,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 pub enum AssignOp {
     Assign,
     AddAssign,
@@ -302,83 +461,411 @@ pub enum AssignOp {
     ModAssign,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct CastExpr {
     pub expr: Box<Expr>,
     pub target_type: Type,
     pub loc: SourceLocation,
+    pub id: NodeId,
 }
 
 /// 数组创建表达式: new Type[size] 或 new Type[size1][size2]... 或 new Type[size]()
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ArrayCreationExpr {
     pub element_type: Type,
     pub sizes: Vec<Expr>,  // 支持多维数组，每个维度的大小
     pub zero_init: bool,   // 是否零初始化 new Type[size]()
     pub loc: SourceLocation,
+    pub id: NodeId,
 }
 
 /// 数组初始化表达式: {1, 2, 3}
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ArrayInitExpr {
     pub elements: Vec<Expr>,
     pub loc: SourceLocation,
+    pub id: NodeId,
 }
 
 /// 数组访问表达式: arr[index]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ArrayAccessExpr {
     pub array: Box<Expr>,
     pub index: Box<Expr>,
     pub loc: SourceLocation,
+    pub id: NodeId,
 }
 
 /// 方法引用表达式: ClassName::methodName 或 obj::methodName
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct MethodRefExpr {
     pub class_name: Option<String>,  // 类名（静态方法引用）
     pub object: Option<Box<Expr>>,   // 对象表达式（实例方法引用）
     pub method_name: String,
     pub loc: SourceLocation,
+    pub id: NodeId,
 }
 
 /// Lambda 表达式: (params) -> { body }
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct LambdaExpr {
     pub params: Vec<LambdaParam>,
     pub body: LambdaBody,
     pub loc: SourceLocation,
+    pub id: NodeId,
 }
 
 /// Lambda 参数
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct LambdaParam {
     pub name: String,
     pub param_type: Option<Type>,  // 可选的类型注解
 }
 
 /// Lambda 体（可以是表达式或语句块）
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub enum LambdaBody {
     Expr(Box<Expr>),      // 单表达式: (x) -> x * 2
     Block(Block),         // 语句块: (x) -> { return x * 2; }
 }
 
 /// 三元运算符表达式: condition ? true_expr : false_expr
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct TernaryExpr {
     pub condition: Box<Expr>,
     pub true_branch: Box<Expr>,
     pub false_branch: Box<Expr>,
     pub loc: SourceLocation,
+    pub id: NodeId,
 }
 
 /// instanceof 表达式: obj instanceof Type
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct InstanceOfExpr {
     pub expr: Box<Expr>,
     pub target_type: crate::types::Type,
     pub loc: SourceLocation,
+    pub id: NodeId,
+}
+
+/// 空值合并表达式: value ?? default（`?:` 为同义写法）
+///
+/// 左侧不为 null 时取左侧的值，否则求值并返回右侧。
+#[derive(Debug, Clone, Serialize)]
+pub struct NullCoalesceExpr {
+    pub value: Box<Expr>,
+    pub default: Box<Expr>,
+    pub loc: SourceLocation,
+    pub id: NodeId,
+}
+
+/// 内联 IR 表达式: `__llvm__<T>("template", args...)`
+///
+/// 模板中的 `$0`、`$1` 替换为实参的值，`$T0` 替换为实参的 LLVM 类型，`$out` 为结果寄存器，`$T` 为结果的 LLVM 类型，
+/// `$$` 为 `$` 本身。没有 `<T>` 时结果类型为 void。
+#[derive(Debug, Clone, Serialize)]
+pub struct InlineIrExpr {
+    pub result_type: Type,
+    pub template: String,
+    pub args: Vec<Expr>,
+    pub loc: SourceLocation,
+    pub id: NodeId,
+}
+
+/// 内联 IR 模板的片段
+#[derive(Debug, Clone, PartialEq)]
+pub enum IrTemplatePiece {
+    Text(String),
+    /// `$N`：第 N 个实参的值
+    Arg(usize),
+    /// `$TN`：第 N 个实参的 LLVM 类型
+    ArgType(usize),
+    /// `$out`：结果寄存器
+    Out,
+    /// `$T`：结果的 LLVM 类型
+    OutType,
+}
+
+impl InlineIrExpr {
+    /// 把模板拆分为文本和占位符，遇到无法识别的 `$` 时返回错误信息
+    pub fn pieces(&self) -> Result<Vec<IrTemplatePiece>, String> {
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        let mut rest = self.template.as_str();
+        while let Some(at) = rest.find('$') {
+            text.push_str(&rest[..at]);
+            rest = &rest[at + 1..];
+            let digits = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+            let piece = if let Some(after) = rest.strip_prefix('$') {
+                text.push('$');
+                rest = after;
+                continue;
+            } else if let Some(after) = rest.strip_prefix("out") {
+                rest = after;
+                IrTemplatePiece::Out
+            } else if let Some(after) = rest.strip_prefix('T') {
+                let len = digits(after);
+                rest = &after[len..];
+                match after[..len].parse() {
+                    Ok(index) => IrTemplatePiece::ArgType(index),
+                    Err(_) => IrTemplatePiece::OutType,
+                }
+            } else {
+                let len = digits(rest);
+                let Ok(index) = rest[..len].parse() else {
+                    let shown: String = rest.chars().take_while(|c| c.is_ascii_alphanumeric()).collect();
+                    return Err(format!("Unknown placeholder '${}' in inline IR template", shown));
+                };
+                rest = &rest[len..];
+                IrTemplatePiece::Arg(index)
+            };
+            if !text.is_empty() {
+                pieces.push(IrTemplatePiece::Text(std::mem::take(&mut text)));
+            }
+            pieces.push(piece);
+        }
+        text.push_str(rest);
+        if !text.is_empty() {
+            pieces.push(IrTemplatePiece::Text(text));
+        }
+        Ok(pieces)
+    }
+}
+
+impl Expr {
+    /// 获取表达式节点的 ID
+    ///
+    /// 字面量和标识符不携带位置信息，因此没有 ID。
+    pub fn node_id(&self) -> Option<NodeId> {
+        match self {
+            Expr::Literal(_) | Expr::Identifier(_) => None,
+            Expr::Binary(e) => Some(e.id),
+            Expr::Unary(e) => Some(e.id),
+            Expr::Call(e) => Some(e.id),
+            Expr::MemberAccess(e) => Some(e.id),
+            Expr::New(e) => Some(e.id),
+            Expr::Assignment(e) => Some(e.id),
+            Expr::Cast(e) => Some(e.id),
+            Expr::ArrayCreation(e) => Some(e.id),
+            Expr::ArrayAccess(e) => Some(e.id),
+            Expr::ArrayInit(e) => Some(e.id),
+            Expr::MethodRef(e) => Some(e.id),
+            Expr::Lambda(e) => Some(e.id),
+            Expr::Ternary(e) => Some(e.id),
+            Expr::InstanceOf(e) => Some(e.id),
+            Expr::NullCoalesce(e) => Some(e.id),
+            Expr::InlineIr(e) => Some(e.id),
+        }
+    }
+
+    /// 获取表达式的源代码位置（字面量和标识符没有位置信息）
+    pub fn location(&self) -> Option<&SourceLocation> {
+        match self {
+            Expr::Literal(_) | Expr::Identifier(_) => None,
+            Expr::Binary(e) => Some(&e.loc),
+            Expr::Unary(e) => Some(&e.loc),
+            Expr::Call(e) => Some(&e.loc),
+            Expr::MemberAccess(e) => Some(&e.loc),
+            Expr::New(e) => Some(&e.loc),
+            Expr::Assignment(e) => Some(&e.loc),
+            Expr::Cast(e) => Some(&e.loc),
+            Expr::ArrayCreation(e) => Some(&e.loc),
+            Expr::ArrayAccess(e) => Some(&e.loc),
+            Expr::ArrayInit(e) => Some(&e.loc),
+            Expr::MethodRef(e) => Some(&e.loc),
+            Expr::Lambda(e) => Some(&e.loc),
+            Expr::Ternary(e) => Some(&e.loc),
+            Expr::InstanceOf(e) => Some(&e.loc),
+            Expr::NullCoalesce(e) => Some(&e.loc),
+            Expr::InlineIr(e) => Some(&e.loc),
+        }
+    }
+
+    /// 若表达式是安全导航（`obj?.member` 或 `obj?.method(...)`），返回其成员访问节点
+    pub fn null_safe_member(&self) -> Option<&MemberAccessExpr> {
+        match self {
+            Expr::MemberAccess(member) if member.null_safe => Some(member),
+            Expr::Call(call) => match call.callee.as_ref() {
+                Expr::MemberAccess(member) if member.null_safe => Some(member),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// 把表达式还原为源代码文本（用于 debugPrint 等需要显示表达式的场合）
+///
+/// 只在子表达式的优先级低于所在位置时加括号，因此结果可能与原始写法的括号和空白不同；
+/// 块体 lambda 的函数体省略为 `{ ... }`。
+impl std::fmt::Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expr::Literal(literal) => match literal {
+                LiteralValue::Int32(n) => write!(f, "{}", n),
+                LiteralValue::Int64(n) => write!(f, "{}L", n),
+                LiteralValue::Float32(x) => write!(f, "{:?}f", x),
+                LiteralValue::Float64(x) => write!(f, "{:?}", x),
+                LiteralValue::String(s) => write!(f, "{:?}", s),
+                LiteralValue::Bool(b) => write!(f, "{}", b),
+                LiteralValue::Char(c) => write!(f, "{:?}", c),
+                LiteralValue::Null => write!(f, "null"),
+            },
+            Expr::Identifier(name) => write!(f, "{}", name),
+            Expr::Binary(bin) => {
+                let precedence = bin.op.precedence();
+                write_operand(f, &bin.left, precedence)?;
+                write!(f, " {} ", bin.op.symbol())?;
+                // 同级运算符左结合，右侧需要更高的优先级才能省略括号
+                write_operand(f, &bin.right, precedence + 1)
+            }
+            Expr::Unary(unary) => match unary.op {
+                UnaryOp::PostInc | UnaryOp::PostDec => {
+                    write_operand(f, &unary.operand, UNARY_PRECEDENCE)?;
+                    write!(f, "{}", if unary.op == UnaryOp::PostInc { "++" } else { "--" })
+                }
+                op => {
+                    let symbol = match op {
+                        UnaryOp::Neg => "-",
+                        UnaryOp::Not => "!",
+                        UnaryOp::BitNot => "~",
+                        UnaryOp::PreInc => "++",
+                        _ => "--",
+                    };
+                    write!(f, "{}", symbol)?;
+                    write_operand(f, &unary.operand, UNARY_PRECEDENCE)
+                }
+            },
+            Expr::Call(call) => {
+                write_operand(f, &call.callee, POSTFIX_PRECEDENCE)?;
+                write_args(f, &call.args)
+            }
+            Expr::MemberAccess(member) => {
+                write_operand(f, &member.object, POSTFIX_PRECEDENCE)?;
+                write!(f, "{}{}", if member.null_safe { "?." } else { "." }, member.member)
+            }
+            Expr::New(new) => {
+                write!(f, "new {}", new.class_name)?;
+                if let Some(type_args) = &new.type_args {
+                    let args: Vec<String> = type_args.iter().map(Type::source_name).collect();
+                    write!(f, "<{}>", args.join(", "))?;
+                }
+                write_args(f, &new.args)
+            }
+            Expr::Assignment(assign) => {
+                let op = match assign.op {
+                    AssignOp::Assign => "=",
+                    AssignOp::AddAssign => "+=",
+                    AssignOp::SubAssign => "-=",
+                    AssignOp::MulAssign => "*=",
+                    AssignOp::DivAssign => "/=",
+                    AssignOp::ModAssign => "%=",
+                };
+                write!(f, "{} {} {}", assign.target, op, assign.value)
+            }
+            Expr::Cast(cast) => {
+                write!(f, "({}) ", cast.target_type.source_name())?;
+                write_operand(f, &cast.expr, UNARY_PRECEDENCE)
+            }
+            Expr::ArrayCreation(creation) => {
+                write!(f, "new {}", creation.element_type.source_name())?;
+                for size in &creation.sizes {
+                    write!(f, "[{}]", size)?;
+                }
+                if creation.zero_init {
+                    write!(f, "()")?;
+                }
+                Ok(())
+            }
+            Expr::ArrayAccess(access) => {
+                write_operand(f, &access.array, POSTFIX_PRECEDENCE)?;
+                write!(f, "[{}]", access.index)
+            }
+            Expr::ArrayInit(init) => {
+                let elements: Vec<String> = init.elements.iter().map(|e| e.to_string()).collect();
+                write!(f, "{{{}}}", elements.join(", "))
+            }
+            Expr::MethodRef(method_ref) => {
+                match (&method_ref.class_name, &method_ref.object) {
+                    (_, Some(object)) => write_operand(f, object, POSTFIX_PRECEDENCE)?,
+                    (Some(class_name), None) => write!(f, "{}", class_name)?,
+                    (None, None) => {}
+                }
+                write!(f, "::{}", method_ref.method_name)
+            }
+            Expr::Lambda(lambda) => {
+                let params: Vec<String> = lambda.params.iter()
+                    .map(|p| match &p.param_type {
+                        Some(ty) => format!("{} {}", ty.source_name(), p.name),
+                        None => p.name.clone(),
+                    })
+                    .collect();
+                write!(f, "({}) -> ", params.join(", "))?;
+                match &lambda.body {
+                    LambdaBody::Expr(body) => write!(f, "{}", body),
+                    LambdaBody::Block(_) => write!(f, "{{ ... }}"),
+                }
+            }
+            Expr::Ternary(ternary) => {
+                write_operand(f, &ternary.condition, TERNARY_PRECEDENCE + 1)?;
+                write!(f, " ? {} : ", ternary.true_branch)?;
+                write_operand(f, &ternary.false_branch, TERNARY_PRECEDENCE)
+            }
+            Expr::InstanceOf(instance_of) => {
+                write_operand(f, &instance_of.expr, BinaryOp::Lt.precedence())?;
+                write!(f, " instanceof {}", instance_of.target_type.source_name())
+            }
+            Expr::NullCoalesce(coalesce) => {
+                write_operand(f, &coalesce.value, NULL_COALESCE_PRECEDENCE + 1)?;
+                write!(f, " ?? ")?;
+                write_operand(f, &coalesce.default, NULL_COALESCE_PRECEDENCE)
+            }
+            Expr::InlineIr(inline) => {
+                write!(f, "__llvm__")?;
+                if inline.result_type != Type::Void {
+                    write!(f, "<{}>", inline.result_type.source_name())?;
+                }
+                write!(f, "({:?}", inline.template)?;
+                for arg in &inline.args {
+                    write!(f, ", {}", arg)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+const TERNARY_PRECEDENCE: u8 = 1;
+const NULL_COALESCE_PRECEDENCE: u8 = 2;
+const UNARY_PRECEDENCE: u8 = 20;
+const POSTFIX_PRECEDENCE: u8 = 21;
+
+/// 表达式作为运算对象时的优先级（数值越大结合越紧）
+fn expr_precedence(expr: &Expr) -> u8 {
+    match expr {
+        Expr::Assignment(_) | Expr::Lambda(_) => 0,
+        Expr::Ternary(_) => TERNARY_PRECEDENCE,
+        Expr::NullCoalesce(_) => NULL_COALESCE_PRECEDENCE,
+        Expr::Binary(bin) => bin.op.precedence(),
+        Expr::InstanceOf(_) => BinaryOp::Lt.precedence(),
+        Expr::Unary(unary) if !matches!(unary.op, UnaryOp::PostInc | UnaryOp::PostDec) => UNARY_PRECEDENCE,
+        Expr::Cast(_) => UNARY_PRECEDENCE,
+        _ => POSTFIX_PRECEDENCE,
+    }
+}
+
+/// 写出运算对象，优先级低于 `min_precedence` 时加括号
+fn write_operand(f: &mut std::fmt::Formatter<'_>, expr: &Expr, min_precedence: u8) -> std::fmt::Result {
+    if expr_precedence(expr) < min_precedence {
+        write!(f, "({})", expr)
+    } else {
+        write!(f, "{}", expr)
+    }
+}
+
+fn write_args(f: &mut std::fmt::Formatter<'_>, args: &[Expr]) -> std::fmt::Result {
+    let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    write!(f, "({})", args.join(", "))
 }
 
 impl Program {
@@ -389,8 +876,8 @@ impl Program {
                     method.name == "main"
                         && method.modifiers.contains(&Modifier::Public)
                         && method.modifiers.contains(&Modifier::Static)
-                        && method.params.is_empty()
-                        && method.return_type == Type::Void
+                        && method.params.len() <= 1
+                        && matches!(method.return_type, Type::Void | Type::Int32)
                 } else {
                     false
                 }
@@ -402,6 +889,7 @@ impl Program {
 impl Default for Program {
     fn default() -> Self {
         Self {
+            imports: Vec::new(),
             classes: Vec::new(),
             interfaces: Vec::new(),
             top_level_functions: Vec::new(),
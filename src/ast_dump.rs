@@ -0,0 +1,406 @@
+//! AST 输出（`--emit=ast` / `--emit=ast-json`）
+//!
+//! 文本格式每个节点占一行，子节点缩进两格；节点后的 `@行:列` 是源代码位置，
+//! `#n` 是节点 ID（与语义分析的旁路表对应）。带标签的子节点（如 `cond:`、`else:`）
+//! 标明它在父节点中的位置。JSON 格式直接序列化 AST 结构，字段名与 `ast.rs` 一致。
+//!
+//! 调试语法分析问题时，这份输出可以直接看出运算符的结合方式和语句的嵌套层次。
+
+use crate::ast::*;
+use crate::error::{cayError, cayResult, SourceLocation};
+use crate::types::ParameterInfo;
+
+/// 把程序格式化为缩进文本
+pub fn to_text(program: &Program) -> String {
+    let mut printer = AstPrinter::default();
+    printer.program(program);
+    printer.out
+}
+
+/// 把程序序列化为 JSON（带缩进）
+pub fn to_json(program: &Program) -> cayResult<String> {
+    serde_json::to_string_pretty(program)
+        .map_err(|e| cayError::Io(format!("无法序列化 AST: {}", e)))
+}
+
+#[derive(Default)]
+struct AstPrinter {
+    out: String,
+    indent: usize,
+}
+
+impl AstPrinter {
+    /// 输出一行：`label` 非空时写在节点前面
+    fn line(&mut self, label: &str, text: &str) {
+        self.out.push_str(&"  ".repeat(self.indent));
+        if !label.is_empty() {
+            self.out.push_str(label);
+            self.out.push_str(": ");
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    /// 输出一行并在缩进一层后输出子节点
+    fn node(&mut self, label: &str, text: &str, children: impl FnOnce(&mut Self)) {
+        self.line(label, text);
+        self.indent += 1;
+        children(self);
+        self.indent -= 1;
+    }
+
+    fn program(&mut self, program: &Program) {
+        self.node("", "Program", |p| {
+            for import in &program.imports {
+                p.line("", &format!("Import {}{}", import.target, at(&import.loc)));
+            }
+            for interface in &program.interfaces {
+                p.interface(interface);
+            }
+            for class in &program.classes {
+                p.class(class);
+            }
+            for function in &program.top_level_functions {
+                let text = format!("Function {}{}{}{}{}", function.name, signature(&function.params, Some(&function.return_type)),
+                    modifiers(&function.modifiers), at(&function.loc), id(function.id));
+                p.node("", &text, |p| p.block("", &function.body));
+            }
+        });
+    }
+
+    fn interface(&mut self, interface: &InterfaceDecl) {
+        let text = format!("Interface {}{}{}{}", interface.name, modifiers(&interface.modifiers), at(&interface.loc), id(interface.id));
+        self.node("", &text, |p| {
+            for method in &interface.methods {
+                p.method(method);
+            }
+        });
+    }
+
+    fn class(&mut self, class: &ClassDecl) {
+        let mut text = format!("Class {}", class.name);
+        if !class.type_params.is_empty() {
+            text.push_str(&format!("<{}>", class.type_params.join(", ")));
+        }
+        if let Some(parent) = &class.parent {
+            text.push_str(&format!(" extends {}", parent));
+        }
+        if !class.interfaces.is_empty() {
+            text.push_str(&format!(" implements {}", class.interfaces.join(", ")));
+        }
+        text.push_str(&format!("{}{}{}", modifiers(&class.modifiers), at(&class.loc), id(class.id)));
+        self.node("", &text, |p| {
+            for member in &class.members {
+                p.member(member);
+            }
+        });
+    }
+
+    fn member(&mut self, member: &ClassMember) {
+        match member {
+            ClassMember::Method(method) => self.method(method),
+            ClassMember::Field(field) => {
+                let text = format!("Field {} {}{}{}{}", field.field_type.source_name(), field.name,
+                    modifiers(&field.modifiers), at(&field.loc), id(field.id));
+                self.node("", &text, |p| {
+                    if let Some(init) = &field.initializer {
+                        p.expr("init", init);
+                    }
+                });
+            }
+            ClassMember::Constructor(ctor) => {
+                let text = format!("Constructor{}{}{}{}", signature(&ctor.params, None), modifiers(&ctor.modifiers), at(&ctor.loc), id(ctor.id));
+                self.node("", &text, |p| {
+                    match &ctor.constructor_call {
+                        Some(ConstructorCall::This(args)) => p.node("", "ThisCall", |p| p.exprs("arg", args)),
+                        Some(ConstructorCall::Super(args)) => p.node("", "SuperCall", |p| p.exprs("arg", args)),
+                        None => {}
+                    }
+                    p.block("", &ctor.body);
+                });
+            }
+            ClassMember::Destructor(dtor) => {
+                let text = format!("Destructor{}{}{}", modifiers(&dtor.modifiers), at(&dtor.loc), id(dtor.id));
+                self.node("", &text, |p| p.block("", &dtor.body));
+            }
+            ClassMember::InstanceInitializer(block) => self.node("", "InstanceInitializer", |p| p.block("", block)),
+            ClassMember::StaticInitializer(block) => self.node("", "StaticInitializer", |p| p.block("", block)),
+        }
+    }
+
+    fn method(&mut self, method: &MethodDecl) {
+        let type_params = if method.type_params.is_empty() {
+            String::new()
+        } else {
+            format!("<{}> ", method.type_params.join(", "))
+        };
+        let text = format!("Method {}{}{}{}{}{}", type_params, method.name, signature(&method.params, Some(&method.return_type)),
+            modifiers(&method.modifiers), at(&method.loc), id(method.id));
+        self.node("", &text, |p| {
+            if let Some(body) = &method.body {
+                p.block("", body);
+            }
+        });
+    }
+
+    fn block(&mut self, label: &str, block: &Block) {
+        self.node(label, &format!("Block{}{}", at(&block.loc), id(block.id)), |p| {
+            for stmt in &block.statements {
+                p.stmt("", stmt);
+            }
+        });
+    }
+
+    fn stmts(&mut self, label: &str, text: &str, stmts: &[Stmt]) {
+        self.node(label, text, |p| {
+            for stmt in stmts {
+                p.stmt("", stmt);
+            }
+        });
+    }
+
+    fn stmt(&mut self, label: &str, stmt: &Stmt) {
+        match stmt {
+            Stmt::Expr(expr) => self.node(label, "ExprStmt", |p| p.expr("", expr)),
+            Stmt::VarDecl(decl) => {
+                let modifier = if decl.is_final { "final " } else { "" };
+                let text = format!("VarDecl {}{} {}{}{}", modifier, decl.var_type.source_name(), decl.name, at(&decl.loc), id(decl.id));
+                self.node(label, &text, |p| {
+                    if let Some(init) = &decl.initializer {
+                        p.expr("init", init);
+                    }
+                });
+            }
+            Stmt::Return(ret) => self.node(label, &format!("Return{}", at(&ret.loc)), |p| {
+                if let Some(value) = &ret.value {
+                    p.expr("", value);
+                }
+            }),
+            Stmt::If(if_stmt) => self.node(label, &format!("If{}{}", at(&if_stmt.loc), id(if_stmt.id)), |p| {
+                p.expr("cond", &if_stmt.condition);
+                p.stmt("then", &if_stmt.then_branch);
+                if let Some(else_branch) = &if_stmt.else_branch {
+                    p.stmt("else", else_branch);
+                }
+            }),
+            Stmt::While(while_stmt) => self.node(label, &format!("While{}{}", at(&while_stmt.loc), id(while_stmt.id)), |p| {
+                p.expr("cond", &while_stmt.condition);
+                p.stmt("body", &while_stmt.body);
+            }),
+            Stmt::DoWhile(do_while) => self.node(label, &format!("DoWhile{}{}", at(&do_while.loc), id(do_while.id)), |p| {
+                p.stmt("body", &do_while.body);
+                p.expr("cond", &do_while.condition);
+            }),
+            Stmt::For(for_stmt) => self.node(label, &format!("For{}{}", at(&for_stmt.loc), id(for_stmt.id)), |p| {
+                if let Some(init) = &for_stmt.init {
+                    p.stmt("init", init);
+                }
+                if let Some(condition) = &for_stmt.condition {
+                    p.expr("cond", condition);
+                }
+                if let Some(update) = &for_stmt.update {
+                    p.expr("update", update);
+                }
+                p.stmt("body", &for_stmt.body);
+            }),
+            Stmt::ForEach(for_each) => {
+                let modifier = if for_each.is_final { "final " } else { "" };
+                let text = format!("ForEach {}{} {}{}{}", modifier, for_each.var_type.source_name(), for_each.var_name, at(&for_each.loc), id(for_each.id));
+                self.node(label, &text, |p| {
+                    p.expr("iterable", &for_each.iterable);
+                    p.stmt("body", &for_each.body);
+                });
+            }
+            Stmt::Switch(switch) => self.node(label, &format!("Switch{}{}", at(&switch.loc), id(switch.id)), |p| {
+                p.expr("value", &switch.expr);
+                for case in &switch.cases {
+                    p.stmts("", &format!("Case {}", case.value), &case.body);
+                }
+                if let Some(default) = &switch.default {
+                    p.stmts("", "Default", default);
+                }
+            }),
+            Stmt::Block(block) => self.block(label, block),
+            Stmt::Labeled(labeled) => self.node(label, &format!("Labeled {}{}{}", labeled.label, at(&labeled.loc), id(labeled.id)), |p| {
+                p.stmt("body", &labeled.body);
+            }),
+            Stmt::Break(jump) => self.line(label, &jump_text("Break", jump)),
+            Stmt::Continue(jump) => self.line(label, &jump_text("Continue", jump)),
+        }
+    }
+
+    fn exprs(&mut self, label: &str, exprs: &[Expr]) {
+        for expr in exprs {
+            self.expr(label, expr);
+        }
+    }
+
+    fn expr(&mut self, label: &str, expr: &Expr) {
+        let suffix = match (expr.location(), expr.node_id()) {
+            (Some(loc), Some(node)) => format!("{}{}", at(loc), id(node)),
+            _ => String::new(),
+        };
+        match expr {
+            Expr::Literal(_) => self.line(label, &format!("Literal {}", expr)),
+            Expr::Identifier(name) => self.line(label, &format!("Identifier {}", name)),
+            Expr::Binary(bin) => self.node(label, &format!("Binary {}{}", bin.op.symbol(), suffix), |p| {
+                p.expr("", &bin.left);
+                p.expr("", &bin.right);
+            }),
+            Expr::Unary(unary) => {
+                let op = match unary.op {
+                    UnaryOp::Neg => "-",
+                    UnaryOp::Not => "!",
+                    UnaryOp::BitNot => "~",
+                    UnaryOp::PreInc => "++ (prefix)",
+                    UnaryOp::PreDec => "-- (prefix)",
+                    UnaryOp::PostInc => "++ (postfix)",
+                    UnaryOp::PostDec => "-- (postfix)",
+                };
+                self.node(label, &format!("Unary {}{}", op, suffix), |p| p.expr("", &unary.operand));
+            }
+            Expr::Call(call) => self.node(label, &format!("Call{}", suffix), |p| {
+                p.expr("callee", &call.callee);
+                p.exprs("arg", &call.args);
+            }),
+            Expr::MemberAccess(member) => {
+                let op = if member.null_safe { "?." } else { "." };
+                self.node(label, &format!("Member {}{}{}", op, member.member, suffix), |p| p.expr("", &member.object));
+            }
+            Expr::New(new) => {
+                let type_args = match &new.type_args {
+                    Some(args) => format!("<{}>", args.iter().map(|t| t.source_name()).collect::<Vec<_>>().join(", ")),
+                    None => String::new(),
+                };
+                self.node(label, &format!("New {}{}{}", new.class_name, type_args, suffix), |p| p.exprs("arg", &new.args));
+            }
+            Expr::Assignment(assign) => {
+                let op = match assign.op {
+                    AssignOp::Assign => "=",
+                    AssignOp::AddAssign => "+=",
+                    AssignOp::SubAssign => "-=",
+                    AssignOp::MulAssign => "*=",
+                    AssignOp::DivAssign => "/=",
+                    AssignOp::ModAssign => "%=",
+                };
+                self.node(label, &format!("Assign {}{}", op, suffix), |p| {
+                    p.expr("target", &assign.target);
+                    p.expr("value", &assign.value);
+                });
+            }
+            Expr::Cast(cast) => {
+                self.node(label, &format!("Cast {}{}", cast.target_type.source_name(), suffix), |p| p.expr("", &cast.expr));
+            }
+            Expr::ArrayCreation(creation) => {
+                let zero_init = if creation.zero_init { " ()" } else { "" };
+                let text = format!("NewArray {}{}{}", creation.element_type.source_name(), zero_init, suffix);
+                self.node(label, &text, |p| p.exprs("size", &creation.sizes));
+            }
+            Expr::ArrayAccess(access) => self.node(label, &format!("Index{}", suffix), |p| {
+                p.expr("", &access.array);
+                p.expr("index", &access.index);
+            }),
+            Expr::ArrayInit(init) => self.node(label, &format!("ArrayInit{}", suffix), |p| p.exprs("", &init.elements)),
+            Expr::MethodRef(method_ref) => {
+                let text = match &method_ref.class_name {
+                    Some(class) if method_ref.object.is_none() => format!("MethodRef {}::{}{}", class, method_ref.method_name, suffix),
+                    _ => format!("MethodRef ::{}{}", method_ref.method_name, suffix),
+                };
+                self.node(label, &text, |p| {
+                    if let Some(object) = &method_ref.object {
+                        p.expr("object", object);
+                    }
+                });
+            }
+            Expr::Lambda(lambda) => {
+                let params: Vec<String> = lambda.params.iter()
+                    .map(|param| match &param.param_type {
+                        Some(ty) => format!("{} {}", ty.source_name(), param.name),
+                        None => param.name.clone(),
+                    })
+                    .collect();
+                self.node(label, &format!("Lambda ({}){}", params.join(", "), suffix), |p| match &lambda.body {
+                    LambdaBody::Expr(body) => p.expr("body", body),
+                    LambdaBody::Block(block) => p.block("body", block),
+                });
+            }
+            Expr::Ternary(ternary) => self.node(label, &format!("Ternary{}", suffix), |p| {
+                p.expr("cond", &ternary.condition);
+                p.expr("then", &ternary.true_branch);
+                p.expr("else", &ternary.false_branch);
+            }),
+            Expr::InstanceOf(instance_of) => {
+                let text = format!("InstanceOf {}{}", instance_of.target_type.source_name(), suffix);
+                self.node(label, &text, |p| p.expr("", &instance_of.expr));
+            }
+            Expr::NullCoalesce(coalesce) => self.node(label, &format!("NullCoalesce{}", suffix), |p| {
+                p.expr("", &coalesce.value);
+                p.expr("default", &coalesce.default);
+            }),
+            Expr::InlineIr(inline) => {
+                let text = format!("InlineIr {:?}: {}{}", inline.template, inline.result_type.source_name(), suffix);
+                self.node(label, &text, |p| {
+                    for arg in &inline.args {
+                        p.expr("", arg);
+                    }
+                });
+            }
+        }
+    }
+}
+
+fn at(loc: &SourceLocation) -> String {
+    format!(" @{}", loc)
+}
+
+fn id(node: NodeId) -> String {
+    format!(" #{}", node.0)
+}
+
+/// break/continue 及其目标标签，如 `Break outer @3:9`
+fn jump_text(kind: &str, jump: &JumpStmt) -> String {
+    match &jump.label {
+        Some(label) => format!("{} {}{}", kind, label, at(&jump.loc)),
+        None => format!("{}{}", kind, at(&jump.loc)),
+    }
+}
+
+/// 修饰符列表，如 ` [public, static]`；没有修饰符时为空
+fn modifiers(modifiers: &[Modifier]) -> String {
+    if modifiers.is_empty() {
+        return String::new();
+    }
+    let names: Vec<String> = modifiers.iter()
+        .map(|m| match m {
+            Modifier::Public => "public".to_string(),
+            Modifier::Private => "private".to_string(),
+            Modifier::Protected => "protected".to_string(),
+            Modifier::Static => "static".to_string(),
+            Modifier::Final => "final".to_string(),
+            Modifier::Abstract => "abstract".to_string(),
+            Modifier::Native => "native".to_string(),
+            Modifier::Main => "@main".to_string(),
+            Modifier::Override => "@Override".to_string(),
+            Modifier::Symbol(name) => format!("@Symbol(\"{}\")", name),
+        })
+        .collect();
+    format!(" [{}]", names.join(", "))
+}
+
+/// 参数列表和返回类型，如 `(int a, String... rest): void`
+fn signature(params: &[ParameterInfo], return_type: Option<&crate::types::Type>) -> String {
+    let params: Vec<String> = params.iter()
+        .map(|param| {
+            let modifier = if param.is_final { "final " } else { "" };
+            let ty = match (&param.param_type, param.is_varargs) {
+                (crate::types::Type::Array(elem), true) => format!("{}...", elem.source_name()),
+                (ty, _) => ty.source_name(),
+            };
+            format!("{}{} {}", modifier, ty, param.name)
+        })
+        .collect();
+    match return_type {
+        Some(ty) => format!("({}): {}", params.join(", "), ty.source_name()),
+        None => format!("({})", params.join(", ")),
+    }
+}
@@ -1,10 +1,12 @@
 use std::env;
-use std::fs;
+use std::path::{Path, PathBuf};
 use std::process;
-use cavvy::error::print_error_with_context;
+use cavvy::Compiler;
+use cavvy::error::{print_error_with_context, exit_codes};
 use cavvy::lexer;
-use cavvy::parser;
+use cavvy::modules::ModuleLoader;
 use cavvy::semantic;
+use cavvy::stack_usage;
 
 const VERSION: &str = env!("CAY_CHECK_VERSION");
 
@@ -15,13 +17,15 @@ fn print_usage() {
     println!("Options:");
     println!("  --lex-only            只进行词法分析");
     println!("  --parse-only          进行词法和语法分析（不进行语义分析）");
-    println!("  --version, -v         显示版本号");
+    println!("  --stack               完整检查后估算各函数的栈使用，并报告递归调用环");
+    println!("  --version, -v         显示版本号 (加 --verbose 显示详细信息)");
     println!("  --help, -h            显示帮助信息");
     println!("");
     println!("Examples:");
     println!("  cay-check hello.cay");
     println!("  cay-check --lex-only hello.cay");
     println!("  cay-check --parse-only hello.cay");
+    println!("  cay-check --stack recursion.cay");
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -39,12 +43,14 @@ impl Default for CheckLevel {
 
 struct CheckOptions {
     level: CheckLevel,
+    stack: bool,    // --stack: 估算栈使用
 }
 
 impl Default for CheckOptions {
     fn default() -> Self {
         CheckOptions {
             level: CheckLevel::default(),
+            stack: false,
         }
     }
 }
@@ -59,9 +65,16 @@ fn parse_args(args: &[String]) -> Result<(CheckOptions, String), String> {
 
         match arg.as_str() {
             "--version" | "-v" => {
-                println!("Cavvy Check v{}", VERSION);
+                if args.iter().any(|a| a == "--verbose") {
+                    print!("{}", cavvy::version::verbose_version_info("Cavvy Check", VERSION));
+                } else {
+                    println!("Cavvy Check v{}", VERSION);
+                }
                 process::exit(0);
             }
+            "--verbose" => {
+                // 与 --version 一起使用
+            }
             "--help" | "-h" => {
                 print_usage();
                 process::exit(0);
@@ -72,6 +85,9 @@ fn parse_args(args: &[String]) -> Result<(CheckOptions, String), String> {
             "--parse-only" => {
                 options.level = CheckLevel::ParseOnly;
             }
+            "--stack" => {
+                options.stack = true;
+            }
             _ => {
                 if arg.starts_with('-') {
                     return Err(format!("未知选项: {}", arg));
@@ -87,10 +103,33 @@ fn parse_args(args: &[String]) -> Result<(CheckOptions, String), String> {
     }
 
     let input_file = input_file.ok_or("需要指定输入文件")?;
+    if options.stack && !matches!(options.level, CheckLevel::Full) {
+        return Err("--stack 需要完整检查，不能与 --lex-only/--parse-only 一起使用".to_string());
+    }
 
     Ok((options, input_file))
 }
 
+/// 生成 IR 并打印静态栈使用估算
+fn print_stack_report(source: &str, source_path: &str) {
+    let base_dir = Path::new(source_path)
+        .parent()
+        .map(|p| p.to_path_buf())
+        .unwrap_or_else(|| PathBuf::from("."));
+    let ir = cavvy::preprocessor::preprocess(source, source_path, base_dir)
+        .and_then(|preprocessed| Compiler::new().compile_module_to_ir(&preprocessed, Some(source_path)));
+    match ir {
+        Ok(ir) => {
+            let report = stack_usage::analyze_ir(&ir);
+            print!("{}", report.format_report());
+        }
+        Err(e) => {
+            print_error_with_context(&e, source, source_path);
+            process::exit(e.exit_code());
+        }
+    }
+}
+
 fn main() {
     let args: Vec<String> = env::args().collect();
 
@@ -99,7 +138,7 @@ fn main() {
         Err(e) => {
             eprintln!("错误: {}", e);
             print_usage();
-            process::exit(1);
+            process::exit(exit_codes::USAGE);
         }
     };
 
@@ -112,11 +151,11 @@ fn main() {
     });
     println!("");
 
-    let source = match fs::read_to_string(&source_path) {
+    let source = match cavvy::source::read_source_file(&source_path) {
         Ok(content) => content,
         Err(e) => {
-            eprintln!("错误读取源文件 '{}': {}", source_path, e);
-            process::exit(1);
+            eprintln!("错误: {}", e);
+            process::exit(e.exit_code());
         }
     };
 
@@ -135,7 +174,7 @@ fn main() {
                 }
                 Err(e) => {
                     print_error_with_context(&e, &source, &source_path);
-                    process::exit(1);
+                    process::exit(e.exit_code());
                 }
             }
         }
@@ -148,13 +187,13 @@ fn main() {
                 }
                 Err(e) => {
                     print_error_with_context(&e, &source, &source_path);
-                    process::exit(1);
+                    process::exit(e.exit_code());
                 }
             };
 
             println!("");
             println!("[2] 语法分析...");
-            match parser::parse(tokens) {
+            match ModuleLoader::new(Some(&source_path)).parse_program(tokens) {
                 Ok(ast) => {
                     let elapsed = start_time.elapsed();
                     println!("  [+] 语法分析通过");
@@ -164,7 +203,7 @@ fn main() {
                 }
                 Err(e) => {
                     print_error_with_context(&e, &source, &source_path);
-                    process::exit(1);
+                    process::exit(e.exit_code());
                 }
             }
         }
@@ -177,36 +216,48 @@ fn main() {
                 }
                 Err(e) => {
                     print_error_with_context(&e, &source, &source_path);
-                    process::exit(1);
+                    process::exit(e.exit_code());
                 }
             };
 
             println!("");
             println!("[2] 语法分析...");
-            let ast = match parser::parse(tokens) {
+            let ast = match ModuleLoader::new(Some(&source_path)).parse_program(tokens) {
                 Ok(ast) => {
                     println!("  [+] 语法分析通过");
                     ast
                 }
                 Err(e) => {
                     print_error_with_context(&e, &source, &source_path);
-                    process::exit(1);
+                    process::exit(e.exit_code());
                 }
             };
 
             println!("");
             println!("[3] 语义分析...");
+            let ast = match cavvy::generics::monomorphize(ast) {
+                Ok(ast) => ast,
+                Err(e) => {
+                    print_error_with_context(&e, &source, &source_path);
+                    process::exit(e.exit_code());
+                }
+            };
             let mut analyzer = semantic::SemanticAnalyzer::new();
             match analyzer.analyze(&ast) {
                 Ok(_) => {
-                    let elapsed = start_time.elapsed();
                     println!("  [+] 语义分析通过");
+                    if options.stack {
+                        println!();
+                        println!("[4] 栈使用估算...");
+                        print_stack_report(&source, &source_path);
+                    }
+                    let elapsed = start_time.elapsed();
                     println!("");
                     println!("[+] 语法检查完成! (耗时: {:?})", elapsed);
                 }
                 Err(e) => {
                     print_error_with_context(&e, &source, &source_path);
-                    process::exit(1);
+                    process::exit(e.exit_code());
                 }
             }
         }
@@ -1,41 +1,17 @@
 use std::env;
 use std::fs;
 use std::process;
-use std::path::{Path, PathBuf};
-use cavvy::Compiler;
-use cavvy::error::print_error_with_context;
-
-/// 查找 clang 可执行文件
-/// 1. 首先尝试直接调用 "clang"（系统 PATH 中）
-/// 2. 如果失败，尝试查找编译器所在目录下的 llvm-minimal/bin/clang.exe
-/// 3. 如果都找不到，返回错误
-fn find_clang() -> Result<PathBuf, String> {
-    // 1. 首先尝试系统 PATH 中的 clang
-    if let Ok(output) = process::Command::new("clang").arg("--version").output() {
-        if output.status.success() {
-            return Ok(PathBuf::from("clang"));
-        }
-    }
-    
-    // 2. 尝试编译器所在目录下的 llvm-minimal
-    if let Ok(exe_path) = env::current_exe() {
-        if let Some(exe_dir) = exe_path.parent() {
-            let bundled_clang = exe_dir.join("llvm-minimal/bin/clang.exe");
-            if bundled_clang.exists() {
-                return Ok(bundled_clang);
-            }
-        }
-    }
-    
-    // 3. 都找不到，返回错误
-    Err("找不到 clang 编译器。请确保 clang 已安装并在 PATH 中，或将 llvm-minimal 放在编译器同目录下。".to_string())
-}
+use std::time::Duration;
+use cavvy::{Compiler, ice};
+use cavvy::toolchain::{self, DEFAULT_TOOL_TIMEOUT_SECS};
+use cavvy::error::{print_error_with_context, exit_codes};
 
 const VERSION: &str = env!("CAY-IR_VERSION");
 
 struct CompileOptions {
     optimization: String,    // -O0, -O1, -O2, -O3, -Os, -Oz
-    optimize_ir: bool,       // --opt-ir: 使用 clang 优化 IR
+    optimize_ir: bool,       // --opt-ir: 使用 opt 优化 IR
+    verify_ir: bool,         // --verify-ir: 自检生成的 IR
     emit_optimized: bool,    // --emit-optimized: 输出发优化后的 IR
     target_os: String,       // --target: 目标操作系统
     features: Vec<String>,   // -f:XX 或 --feature:XX 开启特性
@@ -43,6 +19,18 @@ struct CompileOptions {
     defines: Vec<String>,    // -D:XX 定义宏
     undefines: Vec<String>,  // -U:XX 取消定义宏
     obfuscate: bool,         // --obfuscate 混淆 IR 代码
+    external_runtime: bool,  // --external-runtime 不内联运行时
+    emit_runtime: Option<String>, // --emit-runtime 输出运行时模块
+    imports: Vec<String>,    // --import 导入 .cavi 接口文件
+    emit_interface: Option<String>, // --emit-interface 输出 .cavi 接口文件
+    stack_guard_depth: Option<u32>, // --stack-guard[=<depth>] 递归深度保护
+    max_steps: Option<u64>,         // --max-steps=<n> 步数上限
+    max_heap_bytes: Option<u64>,    // --max-heap=<size> 堆分配上限
+    jobs: usize,             // --jobs <n>, -j <n> 并行编译的线程数
+    cache_dir: Option<String>, // --cache-dir <dir> 编译缓存目录
+    no_main: bool,           // --no-main 库模式，不生成程序入口
+    llvm_path: Option<String>, // --llvm-path 指定工具链目录
+    tool_timeout: Duration,  // --tool-timeout 外部工具超时
 }
 
 impl Default for CompileOptions {
@@ -50,6 +38,7 @@ impl Default for CompileOptions {
         CompileOptions {
             optimization: "-O2".to_string(),
             optimize_ir: false,
+            verify_ir: false,
             emit_optimized: false,
             target_os: std::env::consts::OS.to_string(),
             features: Vec::new(),
@@ -57,6 +46,18 @@ impl Default for CompileOptions {
             defines: Vec::new(),
             undefines: Vec::new(),
             obfuscate: false,
+            external_runtime: false,
+            emit_runtime: None,
+            imports: Vec::new(),
+            emit_interface: None,
+            stack_guard_depth: None,
+            max_steps: None,
+            max_heap_bytes: None,
+            jobs: 1,
+            cache_dir: None,
+            no_main: false,
+            llvm_path: None,
+            tool_timeout: Duration::from_secs(DEFAULT_TOOL_TIMEOUT_SECS),
         }
     }
 }
@@ -66,17 +67,30 @@ fn print_usage() {
     println!("Usage: cay-ir [options] <source_file.cay> [output_file.ll]");
     println!("");
     println!("Options:");
-    println!("  -O0, -O1, -O2, -O3    编译器优化级别 (默认: -O2)");
+    println!("  -O0, -O1, -O2, -O3    编译器优化级别 (默认: -O2，-O0 同时关闭源码级常量折叠)");
     println!("  -Os, -Oz              优化代码大小");
     println!("  --opt-ir              使用 LLVM 优化 IR (增加编译时间，提高运行时性能)");
     println!("  --emit-optimized      输出优化后的 IR (与 --opt-ir 一起使用)");
+    println!("  --verify-ir           生成每个函数后自检 IR 的值和类型，出错时报告对应的源码行");
     println!("  --target <os>         目标操作系统 (windows, linux, macos)");
     println!("  --obfuscate           混淆 IR 代码");
     println!("  -f:XX, --feature:XX   启用特定功能");
     println!("  -No:XX                禁用特定功能");
     println!("  -D:XX                 定义宏");
     println!("  -U:XX                 取消定义宏");
-    println!("  --version, -v         显示版本号");
+    println!("  --external-runtime    不内联运行时函数，链接时需提供运行时模块");
+    println!("  --emit-runtime <file> 输出内置的运行时模块 (可不指定源文件)");
+    println!("  --import <file.cavi>  导入其他编译单元的接口文件 (可重复)");
+    println!("  --emit-interface <f>  输出本编译单元的 .cavi 接口文件");
+    println!("  --no-main             库模式: 不要求 main，也不生成程序入口 (用于分别编译和库)");
+    println!("  --stack-guard[=<n>]   插入递归深度保护，超过 n 层调用时报告 StackOverflow (默认: {})", cavvy::DEFAULT_STACK_GUARD_DEPTH);
+    println!("  --max-steps=<n>       执行限制: 函数调用和循环迭代总数超过 n 时终止程序");
+    println!("  --max-heap=<size>     执行限制: 累计堆分配超过 size 字节时终止程序 (可带 K/M/G 后缀)");
+    println!("  --jobs <n>, -j <n>    并行类型检查和生成各个类使用的线程数 (默认: 1)");
+    println!("  --cache-dir <dir>     编译缓存目录，源文件和导入的模块未变化时复用上次的 IR");
+    println!("  --llvm-path <dir>     指定 LLVM 工具链目录 (包含 bin/clang、bin/opt)");
+    println!("  --tool-timeout <secs> 外部工具超时时间 (默认: {} 秒)", DEFAULT_TOOL_TIMEOUT_SECS);
+    println!("  --version, -v         显示版本号 (加 --verbose 显示详细信息)");
     println!("  --help, -h            显示帮助信息");
     println!("");
     println!("Examples:");
@@ -84,6 +98,8 @@ fn print_usage() {
     println!("  cay-ir -O3 hello.cay hello.ll");
     println!("  cay-ir --opt-ir -O3 hello.cay         # 生成优化后的 IR");
     println!("  cay-ir --opt-ir --emit-optimized -O3 hello.cay  # 输出优化后的 IR");
+    println!("  cay-ir --external-runtime --emit-runtime cay_runtime.ll hello.cay");
+    println!("  cay-ir --no-main --emit-interface shapes.cavi shapes.cay");
 }
 
 fn parse_args(args: &[String]) -> Result<(CompileOptions, String, String), String> {
@@ -97,9 +113,16 @@ fn parse_args(args: &[String]) -> Result<(CompileOptions, String, String), Strin
 
         match arg.as_str() {
             "--version" | "-v" => {
-                println!("Cavvy IR Generator v{}", VERSION);
+                if args.iter().any(|a| a == "--verbose") {
+                    print!("{}", cavvy::version::verbose_version_info("Cavvy IR Generator", VERSION));
+                } else {
+                    println!("Cavvy IR Generator v{}", VERSION);
+                }
                 process::exit(0);
             }
+            "--verbose" => {
+                // 与 --version 一起使用
+            }
             "--help" | "-h" => {
                 print_usage();
                 process::exit(0);
@@ -113,6 +136,9 @@ fn parse_args(args: &[String]) -> Result<(CompileOptions, String, String), Strin
             "--emit-optimized" => {
                 options.emit_optimized = true;
             }
+            "--verify-ir" => {
+                options.verify_ir = true;
+            }
             "--target" => {
                 if i + 1 < args.len() {
                     options.target_os = args[i + 1].clone();
@@ -124,6 +150,80 @@ fn parse_args(args: &[String]) -> Result<(CompileOptions, String, String), Strin
             "--obfuscate" => {
                 options.obfuscate = true;
             }
+            "--external-runtime" => {
+                options.external_runtime = true;
+            }
+            "--no-main" => {
+                options.no_main = true;
+            }
+            "--emit-runtime" => {
+                if i + 1 < args.len() {
+                    options.emit_runtime = Some(args[i + 1].clone());
+                    i += 1;
+                } else {
+                    return Err("--emit-runtime 需要一个输出文件参数".to_string());
+                }
+            }
+            "--import" => {
+                if i + 1 < args.len() {
+                    options.imports.push(args[i + 1].clone());
+                    i += 1;
+                } else {
+                    return Err("--import 需要一个 .cavi 文件参数".to_string());
+                }
+            }
+            "--emit-interface" => {
+                if i + 1 < args.len() {
+                    options.emit_interface = Some(args[i + 1].clone());
+                    i += 1;
+                } else {
+                    return Err("--emit-interface 需要一个输出文件参数".to_string());
+                }
+            }
+            "--jobs" | "-j" => {
+                if i + 1 < args.len() {
+                    options.jobs = toolchain::parse_jobs(&args[i + 1])?;
+                    i += 1;
+                } else {
+                    return Err(format!("{} 需要一个线程数参数", arg));
+                }
+            }
+            "--cache-dir" => {
+                if i + 1 < args.len() {
+                    options.cache_dir = Some(args[i + 1].clone());
+                    i += 1;
+                } else {
+                    return Err("--cache-dir 需要一个目录参数".to_string());
+                }
+            }
+            "--llvm-path" => {
+                if i + 1 < args.len() {
+                    options.llvm_path = Some(args[i + 1].clone());
+                    i += 1;
+                } else {
+                    return Err("--llvm-path 需要一个目录参数".to_string());
+                }
+            }
+            "--tool-timeout" => {
+                if i + 1 < args.len() {
+                    options.tool_timeout = toolchain::parse_timeout(&args[i + 1])?;
+                    i += 1;
+                } else {
+                    return Err("--tool-timeout 需要一个秒数参数".to_string());
+                }
+            }
+            "--stack-guard" => {
+                options.stack_guard_depth = Some(cavvy::DEFAULT_STACK_GUARD_DEPTH);
+            }
+            arg if arg.starts_with("--stack-guard=") => {
+                options.stack_guard_depth = Some(toolchain::parse_stack_guard_depth(&arg[14..])?);
+            }
+            arg if arg.starts_with("--max-steps=") => {
+                options.max_steps = Some(toolchain::parse_max_steps(&arg[12..])?);
+            }
+            arg if arg.starts_with("--max-heap=") => {
+                options.max_heap_bytes = Some(toolchain::parse_max_heap(&arg[11..])?);
+            }
             arg if arg.starts_with("-f:") || arg.starts_with("--feature:") => {
                 let feature = if arg.starts_with("-f:") {
                     &arg[3..]
@@ -160,7 +260,12 @@ fn parse_args(args: &[String]) -> Result<(CompileOptions, String, String), Strin
         i += 1;
     }
 
-    let input_file = input_file.ok_or("需要指定输入文件")?;
+    // 只输出运行时模块时可以不指定源文件
+    let input_file = match input_file {
+        Some(file) => file,
+        None if options.emit_runtime.is_some() => String::new(),
+        None => return Err("需要指定输入文件".to_string()),
+    };
     let output_file = output_file.unwrap_or_else(|| {
         if input_file.ends_with(".cay") {
             input_file.replace(".cay", ".ll")
@@ -172,31 +277,13 @@ fn parse_args(args: &[String]) -> Result<(CompileOptions, String, String), Strin
     Ok((options, input_file, output_file))
 }
 
-fn optimize_ir(ir_file: &str, opt_level: &str) -> Result<String, String> {
-    let clang_exe = find_clang()?;
-
+fn optimize_ir(ir_file: &str, options: &CompileOptions) -> Result<String, toolchain::ToolError> {
     // 生成临时优化后的文件名
     let optimized_file = format!("{}.opt.ll", ir_file.trim_end_matches(".ll"));
 
-    // 使用 clang 优化 IR
-    // -S -emit-llvm: 输出 LLVM IR
-    // -x ir: 输入类型为 IR
-    let mut cmd = process::Command::new(&clang_exe);
-    cmd.arg("-x")
-        .arg("ir")
-        .arg(ir_file)
-        .arg("-S")
-        .arg("-emit-llvm")
-        .arg(opt_level)
-        .arg("-o")
-        .arg(&optimized_file);
-
-    let output = cmd.output()
-        .map_err(|e| format!("执行 clang 优化失败: {}", e))?;
-
-    if !output.status.success() {
-        let error_msg = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("IR 优化失败: {}", error_msg));
+    if let Err(e) = toolchain::optimize_ir(ir_file, &optimized_file, &options.optimization, options.llvm_path.as_deref(), options.tool_timeout) {
+        let _ = fs::remove_file(&optimized_file);
+        return Err(e);
     }
 
     Ok(optimized_file)
@@ -210,16 +297,32 @@ fn main() {
         Err(e) => {
             eprintln!("错误: {}", e);
             print_usage();
-            process::exit(1);
+            process::exit(exit_codes::USAGE);
         }
     };
 
+    // 输出内置的运行时模块
+    if let Some(ref runtime_path) = options.emit_runtime {
+        let compiler = Compiler::with_options(cavvy::CompilerOptions {
+            target_os: options.target_os.clone(),
+            ..Default::default()
+        });
+        if let Err(e) = compiler.compile_runtime(runtime_path) {
+            eprintln!("错误: {}", e);
+            process::exit(e.exit_code());
+        }
+        println!("Generated runtime: {}", runtime_path);
+        if source_path.is_empty() {
+            return;
+        }
+    }
+
     // 读取源文件
-    let source = match fs::read_to_string(&source_path) {
+    let source = match cavvy::source::read_source_file(&source_path) {
         Ok(content) => content,
         Err(e) => {
-            eprintln!("错误读取源文件 '{}': {}", source_path, e);
-            process::exit(1);
+            eprintln!("错误: {}", e);
+            process::exit(e.exit_code());
         }
     };
 
@@ -233,26 +336,43 @@ fn main() {
 
     // 创建多平台编译器配置
     let compiler_options = cavvy::CompilerOptions {
-        target_os: options.target_os,
-        features: options.features,
-        no_features: options.no_features,
-        defines: options.defines,
-        undefines: options.undefines,
+        target_os: options.target_os.clone(),
+        features: options.features.clone(),
+        no_features: options.no_features.clone(),
+        defines: options.defines.clone(),
+        undefines: options.undefines.clone(),
         obfuscate: options.obfuscate,
+        external_runtime: options.external_runtime,
+        interface_imports: options.imports.clone(),
+        emit_interface: options.emit_interface.clone(),
+        stack_guard_depth: options.stack_guard_depth,
+        max_steps: options.max_steps,
+        max_heap_bytes: options.max_heap_bytes,
+        optimize: options.optimization != "-O0",
+        jobs: options.jobs,
+        cache_dir: options.cache_dir.clone(),
+        no_main: options.no_main,
+        verify_ir: options.verify_ir,
     };
 
     // 编译 Cavvy → IR
     let compiler = Compiler::with_options(compiler_options);
     let temp_ir_file = format!("{}.tmp.ll", output_path.trim_end_matches(".ll"));
 
-    match compiler.compile_file(&source_path, &temp_ir_file) {
-        Ok(_) => {
+    let result = ice::run_guarded("Cavvy IR Generator", VERSION, &source_path, || compiler.compile_file(&source_path, &temp_ir_file));
+    match result {
+        Ok(Ok(_)) => {
             println!("  [+] Cavvy → IR 编译成功");
         }
-        Err(e) => {
+        Ok(Err(e)) => {
             print_error_with_context(&e, &source, &source_path);
             let _ = fs::remove_file(&temp_ir_file);
-            process::exit(1);
+            process::exit(e.exit_code());
+        }
+        Err(internal) => {
+            eprint!("{}", internal.report_instructions(&source_path));
+            let _ = fs::remove_file(&temp_ir_file);
+            process::exit(exit_codes::CODEGEN);
         }
     }
 
@@ -260,13 +380,17 @@ fn main() {
     let final_ir_file = if options.optimize_ir {
         println!("");
         println!("[2] 优化 IR ({})...", options.optimization);
-        match optimize_ir(&temp_ir_file, &options.optimization) {
+        match optimize_ir(&temp_ir_file, &options) {
             Ok(optimized_file) => {
                 println!("  [+] IR 优化完成");
                 // 删除临时文件
                 let _ = fs::remove_file(&temp_ir_file);
                 optimized_file
             }
+            Err(e @ toolchain::ToolError::InvalidIr { .. }) => {
+                eprintln!("错误: {}", e);
+                process::exit(e.exit_code());
+            }
             Err(e) => {
                 eprintln!("  [W] IR 优化失败: {}", e);
                 eprintln!("  [I] 使用未优化的 IR");
@@ -293,7 +417,7 @@ fn main() {
             if let Err(e2) = fs::copy(&final_ir_file, &final_output) {
                 eprintln!("错误: 无法创建输出文件 '{}': {} / {}", final_output, e, e2);
                 let _ = fs::remove_file(&final_ir_file);
-                process::exit(1);
+                process::exit(exit_codes::IO);
             }
             let _ = fs::remove_file(&final_ir_file);
         }
@@ -0,0 +1,55 @@
+use std::env;
+use std::io;
+use std::process;
+use cavvy::error::exit_codes;
+
+const VERSION: &str = env!("CAY_LSP_VERSION");
+
+fn print_usage() {
+    println!("Cavvy Language Server v{}", VERSION);
+    println!("Usage: cay-lsp [options]");
+    println!();
+    println!("通过标准输入输出提供语言服务器协议 (LSP)：实时诊断、跳转到定义和悬停类型信息。");
+    println!("由编辑器启动，例如在 VS Code 的语言客户端配置中把服务器命令设为 cay-lsp。");
+    println!();
+    println!("Options:");
+    println!("  --stdio                  使用标准输入输出通信 (默认)");
+    println!("  --version, -v            显示版本号 (加 --verbose 显示详细信息)");
+    println!("  --help, -h               显示帮助信息");
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    for arg in &args[1..] {
+        match arg.as_str() {
+            "--version" | "-v" => {
+                if args.iter().any(|a| a == "--verbose") {
+                    print!("{}", cavvy::version::verbose_version_info("Cavvy Language Server", VERSION));
+                } else {
+                    println!("Cavvy Language Server v{}", VERSION);
+                }
+                process::exit(0);
+            }
+            "--help" | "-h" => {
+                print_usage();
+                process::exit(0);
+            }
+            // --stdio 是编辑器启动语言服务器时的常用参数；--verbose 与 --version 一起使用
+            "--stdio" | "--verbose" => {}
+            _ => {
+                eprintln!("错误: 未知选项: {}", arg);
+                print_usage();
+                process::exit(exit_codes::USAGE);
+            }
+        }
+    }
+
+    // 标准输出专用于协议消息，日志只能写到标准错误
+    match cavvy::lsp::run(io::stdin().lock(), io::stdout().lock()) {
+        Ok(code) => process::exit(code),
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            process::exit(exit_codes::IO);
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+use cavvy::reduce;
+use cavvy::error::exit_codes;
+
+const VERSION: &str = env!("CAY_REDUCE_VERSION");
+
+fn print_usage() {
+    println!("Cavvy Reduce v{}", VERSION);
+    println!("Usage: cay-reduce [options] <source_file.cay>");
+    println!();
+    println!("反复删除类、方法和语句并重新编译，把触发错误的源文件缩减为最小复现程序。");
+    println!();
+    println!("Options:");
+    println!("  --error-contains <text>  错误信息需包含的文本 (默认使用原始错误去掉位置后的内容)");
+    println!("  -o <file>                输出文件 (默认 <源文件名>.min.cay)");
+    println!("  --version, -v            显示版本号 (加 --verbose 显示详细信息)");
+    println!("  --help, -h               显示帮助信息");
+    println!();
+    println!("Examples:");
+    println!("  cay-reduce crash.cay");
+    println!("  cay-reduce crash.cay --error-contains \"internal compiler error\"");
+    println!("  cay-reduce bad.cay --error-contains \"类型不匹配\" -o min.cay");
+}
+
+struct ReduceOptions {
+    error_contains: Option<String>,
+    output_file: Option<String>,
+}
+
+fn parse_args(args: &[String]) -> Result<(ReduceOptions, String), String> {
+    let mut options = ReduceOptions {
+        error_contains: None,
+        output_file: None,
+    };
+    let mut input_file: Option<String> = None;
+    let mut i = 1;
+
+    while i < args.len() {
+        let arg = &args[i];
+        match arg.as_str() {
+            "--version" | "-v" => {
+                if args.iter().any(|a| a == "--verbose") {
+                    print!("{}", cavvy::version::verbose_version_info("Cavvy Reduce", VERSION));
+                } else {
+                    println!("Cavvy Reduce v{}", VERSION);
+                }
+                process::exit(0);
+            }
+            "--verbose" => {
+                // 与 --version 一起使用
+            }
+            "--help" | "-h" => {
+                print_usage();
+                process::exit(0);
+            }
+            "--error-contains" => {
+                i += 1;
+                let text = args.get(i).ok_or("--error-contains 需要参数")?;
+                options.error_contains = Some(text.clone());
+            }
+            "-o" => {
+                i += 1;
+                let file = args.get(i).ok_or("-o 需要参数")?;
+                options.output_file = Some(file.clone());
+            }
+            _ => {
+                if arg.starts_with('-') {
+                    return Err(format!("未知选项: {}", arg));
+                }
+                if input_file.is_none() {
+                    input_file = Some(arg.clone());
+                } else {
+                    return Err(format!("多余参数: {}", arg));
+                }
+            }
+        }
+        i += 1;
+    }
+
+    let input_file = input_file.ok_or("需要指定输入文件")?;
+
+    Ok((options, input_file))
+}
+
+/// 默认输出文件: 与源文件同目录的 <stem>.min.cay
+fn default_output_path(source_path: &str) -> String {
+    let path = Path::new(source_path);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("reduced");
+    path.with_file_name(format!("{}.min.cay", stem))
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    let (options, source_path) = match parse_args(&args) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            print_usage();
+            process::exit(exit_codes::USAGE);
+        }
+    };
+
+    let source = match cavvy::source::read_source_file(&source_path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            process::exit(e.exit_code());
+        }
+    };
+
+    println!("Cavvy Reduce v{}", VERSION);
+    println!("缩减文件: {}", source_path);
+
+    // 先确认原始文件能复现问题
+    let original_error = match reduce::pipeline_error(&source, &source_path) {
+        Some(message) => message,
+        None => {
+            eprintln!("错误: 源文件编译成功，没有可复现的错误");
+            process::exit(exit_codes::USAGE);
+        }
+    };
+
+    let error_contains = match options.error_contains {
+        Some(text) => {
+            if !original_error.contains(&text) {
+                eprintln!("错误: 原始错误信息不包含 \"{}\"", text);
+                eprintln!("原始错误: {}", original_error);
+                process::exit(exit_codes::USAGE);
+            }
+            text
+        }
+        None => reduce::error_signature(&original_error),
+    };
+    println!("匹配错误: {}", error_contains);
+    println!();
+
+    let start_time = std::time::Instant::now();
+    let (reduced, stats) = reduce::reduce_file(&source, &source_path, &error_contains);
+    let elapsed = start_time.elapsed();
+
+    let output_path = options.output_file.unwrap_or_else(|| default_output_path(&source_path));
+    if let Err(e) = fs::write(&output_path, &reduced) {
+        eprintln!("错误写入输出文件 '{}': {}", output_path, e);
+        process::exit(exit_codes::IO);
+    }
+
+    println!("[+] 缩减完成! (耗时: {:?})", elapsed);
+    println!("    行数: {} -> {}", stats.original_lines, stats.reduced_lines);
+    println!("    编译次数: {}", stats.tests_run);
+    println!("    输出文件: {}", output_path);
+}
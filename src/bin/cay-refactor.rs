@@ -0,0 +1,127 @@
+use std::env;
+use std::fs;
+use std::process;
+use cavvy::error::{exit_codes, print_error_with_context};
+use cavvy::refactor;
+
+const VERSION: &str = env!("CAY_REFACTOR_VERSION");
+
+fn print_usage() {
+    println!("Cavvy Refactor v{}", VERSION);
+    println!("Usage: cay-refactor rename [options] <source_file.cay> <line>:<column> <new_name>");
+    println!();
+    println!("重命名光标位置 (行、列从 1 开始) 处的变量、参数、字段、方法、类或函数及其所有引用。");
+    println!("方法连同重载和重写的同名方法一起改名，类连同构造函数一起改名；");
+    println!("改名会使某处引用指向别的声明或使程序无法编译时报错，不修改文件。");
+    println!();
+    println!("Options:");
+    println!("  --write, -w              把修改写回源文件 (默认只列出修改)");
+    println!("  --version, -v            显示版本号 (加 --verbose 显示详细信息)");
+    println!("  --help, -h               显示帮助信息");
+    println!();
+    println!("Examples:");
+    println!("  cay-refactor rename hello.cay 5:13 total");
+    println!("  cay-refactor rename --write shapes.cay 12:17 area");
+}
+
+struct RenameOptions {
+    write: bool,
+    file: String,
+    position: (usize, usize),
+    new_name: String,
+}
+
+fn parse_position(text: &str) -> Result<(usize, usize), String> {
+    let invalid = || format!("无效的位置: {} (需要 <行>:<列>，均从 1 开始)", text);
+    let (line, column) = text.split_once(':').ok_or_else(invalid)?;
+    match (line.parse::<usize>(), column.parse::<usize>()) {
+        (Ok(line), Ok(column)) if line > 0 && column > 0 => Ok((line, column)),
+        _ => Err(invalid()),
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<RenameOptions, String> {
+    let mut write = false;
+    let mut positional = Vec::new();
+    for arg in &args[1..] {
+        match arg.as_str() {
+            "--version" | "-v" => {
+                if args.iter().any(|a| a == "--verbose") {
+                    print!("{}", cavvy::version::verbose_version_info("Cavvy Refactor", VERSION));
+                } else {
+                    println!("Cavvy Refactor v{}", VERSION);
+                }
+                process::exit(0);
+            }
+            "--verbose" => {
+                // 与 --version 一起使用
+            }
+            "--help" | "-h" => {
+                print_usage();
+                process::exit(0);
+            }
+            "--write" | "-w" => write = true,
+            _ if arg.starts_with('-') => return Err(format!("未知选项: {}", arg)),
+            _ => positional.push(arg.clone()),
+        }
+    }
+
+    match positional.as_slice() {
+        [command, ..] if command != "rename" => Err(format!("未知命令: {} (目前支持 rename)", command)),
+        [_, file, position, new_name] => Ok(RenameOptions {
+            write,
+            file: file.clone(),
+            position: parse_position(position)?,
+            new_name: new_name.clone(),
+        }),
+        [] => Err("缺少命令".to_string()),
+        [_, rest @ ..] if rest.len() > 3 => Err(format!("多余参数: {}", rest[3])),
+        _ => Err("rename 需要源文件、位置和新名称".to_string()),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            print_usage();
+            process::exit(exit_codes::USAGE);
+        }
+    };
+
+    let source = match cavvy::source::read_source_file(&options.file) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            process::exit(e.exit_code());
+        }
+    };
+    let edits = match refactor::rename_in_source(&source, Some(&options.file), options.position, &options.new_name) {
+        Ok(edits) => edits,
+        Err(e) => {
+            print_error_with_context(&e, &source, &options.file);
+            process::exit(e.exit_code());
+        }
+    };
+    if edits.is_empty() {
+        println!("新名称与原名称相同，无需修改");
+        return;
+    }
+
+    if !options.write {
+        let lines: Vec<&str> = source.lines().collect();
+        for edit in &edits {
+            let old: String = lines[edit.start.0 - 1].chars().skip(edit.start.1 - 1).take(edit.end.1 - edit.start.1).collect();
+            println!("{}:{}:{}: {} -> {}", options.file, edit.start.0, edit.start.1, old, edit.new_text);
+        }
+        println!("共 {} 处修改 (加 --write 写回文件)", edits.len());
+        return;
+    }
+    if let Err(e) = fs::write(&options.file, refactor::apply_edits(&source, &edits)) {
+        eprintln!("错误: 无法写入 {}: {}", options.file, e);
+        process::exit(exit_codes::IO);
+    }
+    println!("已重命名 {} 处: {}", edits.len(), options.file);
+}
@@ -0,0 +1,176 @@
+use std::env;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::process;
+use std::thread;
+use cavvy::error::exit_codes;
+use cavvy::interp::{self, EvalError, Outcome, Session};
+use cavvy::{toolchain, CompilerOptions};
+
+const VERSION: &str = env!("CAY_REPL_VERSION");
+
+/// 解释器线程的栈大小：树遍历解释器的每层调用占用较多的栈
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+fn print_usage() {
+    println!("Cavvy REPL v{}", VERSION);
+    println!("Usage: cay-repl [options]");
+    println!();
+    println!("交互式执行 Cavvy 代码：输入语句或表达式立即执行并显示表达式的值，");
+    println!("也可以声明类、接口和函数供之后的输入使用。括号未闭合时继续读入下一行。");
+    println!();
+    println!("Options:");
+    println!("  --max-steps <n>          每次输入最多执行 n 步 (避免死循环)");
+    println!("  --max-heap <size>        每次输入最多分配 size 字节的堆内存 (可带 K/M/G 后缀)");
+    println!("  --max-depth <n>          调用深度超过 n 层时终止 (避免无穷递归)");
+    println!("  --version, -v            显示版本号 (加 --verbose 显示详细信息)");
+    println!("  --help, -h               显示帮助信息");
+    println!();
+    print_commands();
+}
+
+fn print_commands() {
+    println!("Commands:");
+    println!("  :vars                    列出会话变量");
+    println!("  :reset                   清空会话中的声明和变量");
+    println!("  :help                    显示命令列表");
+    println!("  :quit                    退出 (也可以按 Ctrl+D)");
+}
+
+/// 解析命令行，返回执行限制（使用 `CompilerOptions` 中与 cayc 相同的限制字段）
+fn parse_args(args: &[String]) -> Result<CompilerOptions, String> {
+    let mut limits = CompilerOptions::default();
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--version" | "-v" => {
+                if args.iter().any(|a| a == "--verbose") {
+                    print!("{}", cavvy::version::verbose_version_info("Cavvy REPL", VERSION));
+                } else {
+                    println!("Cavvy REPL v{}", VERSION);
+                }
+                process::exit(0);
+            }
+            "--verbose" => {
+                // 与 --version 一起使用
+            }
+            "--help" | "-h" => {
+                print_usage();
+                process::exit(0);
+            }
+            "--max-steps" => {
+                i += 1;
+                let value = args.get(i).ok_or("--max-steps 需要参数")?;
+                limits.max_steps = Some(toolchain::parse_max_steps(value)?);
+            }
+            "--max-heap" => {
+                i += 1;
+                let value = args.get(i).ok_or("--max-heap 需要参数")?;
+                limits.max_heap_bytes = Some(toolchain::parse_max_heap(value)?);
+            }
+            "--max-depth" => {
+                i += 1;
+                let value = args.get(i).ok_or("--max-depth 需要参数")?;
+                limits.stack_guard_depth = Some(toolchain::parse_stack_guard_depth(value)?);
+            }
+            arg => return Err(format!("未知选项: {}", arg)),
+        }
+        i += 1;
+    }
+    Ok(limits)
+}
+
+fn new_session(limits: &CompilerOptions) -> Session<io::Stdout> {
+    let mut session = Session::new(io::stdout());
+    session.set_limits(limits);
+    session
+}
+
+/// 读入并执行输入，直到输入结束或 `:quit`
+fn repl(limits: CompilerOptions) {
+    let interactive = io::stdin().is_terminal();
+    if interactive {
+        println!("Cavvy REPL v{} (输入 :help 查看命令)", VERSION);
+    }
+    let mut session = new_session(&limits);
+    let mut input = String::new();
+    let stdin = io::stdin();
+
+    loop {
+        if interactive {
+            print!("{}", if input.is_empty() { "cay> " } else { "...> " });
+            let _ = io::stdout().flush();
+        }
+        let mut line = String::new();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("错误: {}", e);
+                break;
+            }
+        }
+
+        if input.is_empty() {
+            match line.trim() {
+                ":quit" | ":q" | ":exit" => break,
+                ":help" => {
+                    print_commands();
+                    continue;
+                }
+                ":vars" => {
+                    for variable in session.variables() {
+                        println!("{}", variable);
+                    }
+                    continue;
+                }
+                ":reset" => {
+                    session = new_session(&limits);
+                    println!("会话已清空");
+                    continue;
+                }
+                command if command.starts_with(':') => {
+                    eprintln!("未知命令: {} (输入 :help 查看命令)", command);
+                    continue;
+                }
+                _ => {}
+            }
+        }
+
+        input.push_str(&line);
+        if !interp::is_complete(&input) {
+            continue;
+        }
+        match session.eval(&input) {
+            Ok(Outcome::Defined(names)) => println!("已定义 {}", names.join(", ")),
+            Ok(Outcome::Executed(Some(value))) => println!("{}", value),
+            Ok(Outcome::Executed(None) | Outcome::Empty) => {}
+            // exit(code) 结束会话，退出码与编译后的程序相同
+            Err(EvalError::Exit(code)) => {
+                io::stdout().flush().ok();
+                process::exit(code);
+            }
+            Err(error) => eprint!("{}", error.render(input.trim())),
+        }
+        input.clear();
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let limits = match parse_args(&args) {
+        Ok(limits) => limits,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            print_usage();
+            process::exit(exit_codes::USAGE);
+        }
+    };
+
+    let worker = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || repl(limits));
+    match worker.map(|handle| handle.join()) {
+        Ok(Ok(())) => {}
+        _ => process::exit(exit_codes::CODEGEN),
+    }
+}
@@ -0,0 +1,212 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{self, Command};
+use std::time::Duration;
+use cavvy::{Compiler, CompilerOptions};
+use cavvy::error::{exit_codes, print_error_with_context};
+use cavvy::toolchain::{self, DEFAULT_TOOL_TIMEOUT_SECS};
+
+const VERSION: &str = env!("CAY_RUN_VERSION");
+
+fn print_usage() {
+    println!("Cavvy Run v{}", VERSION);
+    println!("Usage: cay-run [options] <source_file.cay> [-- <程序参数>...]");
+    println!();
+    println!("编译源文件并立即运行。`--` 之后的参数原样传给程序，程序直接使用 cay-run 的标准输入、");
+    println!("标准输出和标准错误，退出码与程序相同。编译过程不输出任何信息，编译错误输出到标准错误。");
+    println!();
+    println!("Options:");
+    println!("  -O0, -O1, -O2, -O3       优化级别 (默认: -O2)");
+    println!("  --max-steps <n>          程序超过 n 步后终止 (避免死循环)");
+    println!("  --llvm-path <dir>        外部 LLVM 工具链目录 (传给 ir2exe)");
+    println!("  --tool-timeout <secs>    ir2exe 超时时间 (默认: {} 秒)", DEFAULT_TOOL_TIMEOUT_SECS);
+    println!("  --version, -v            显示版本号 (加 --verbose 显示详细信息)");
+    println!("  --help, -h               显示帮助信息");
+    println!();
+    println!("Examples:");
+    println!("  cay-run hello.cay");
+    println!("  cay-run sort.cay -- --reverse data.txt < input.txt > output.txt");
+}
+
+struct RunOptions {
+    optimization: String,
+    max_steps: Option<u64>,
+    llvm_path: Option<String>,
+    tool_timeout: Duration,
+    program_args: Vec<String>,
+}
+
+fn parse_args(args: &[String]) -> Result<(RunOptions, String), String> {
+    let mut options = RunOptions {
+        optimization: "-O2".to_string(),
+        max_steps: None,
+        llvm_path: None,
+        tool_timeout: Duration::from_secs(DEFAULT_TOOL_TIMEOUT_SECS),
+        program_args: Vec::new(),
+    };
+    let mut input_file: Option<String> = None;
+    let mut i = 1;
+
+    while i < args.len() {
+        let arg = &args[i];
+        match arg.as_str() {
+            "--version" | "-v" => {
+                if args.iter().any(|a| a == "--verbose") {
+                    print!("{}", cavvy::version::verbose_version_info("Cavvy Run", VERSION));
+                } else {
+                    println!("Cavvy Run v{}", VERSION);
+                }
+                process::exit(0);
+            }
+            "--verbose" => {
+                // 与 --version 一起使用
+            }
+            "--help" | "-h" => {
+                print_usage();
+                process::exit(0);
+            }
+            "-O0" | "-O1" | "-O2" | "-O3" => options.optimization = arg.clone(),
+            "--max-steps" => {
+                i += 1;
+                let value = args.get(i).ok_or("--max-steps 需要参数")?;
+                options.max_steps = Some(toolchain::parse_max_steps(value)?);
+            }
+            "--llvm-path" => {
+                i += 1;
+                let dir = args.get(i).ok_or("--llvm-path 需要参数")?;
+                options.llvm_path = Some(dir.clone());
+            }
+            "--tool-timeout" => {
+                i += 1;
+                let value = args.get(i).ok_or("--tool-timeout 需要参数")?;
+                options.tool_timeout = toolchain::parse_timeout(value)?;
+            }
+            "--" => {
+                options.program_args = args[i + 1..].to_vec();
+                break;
+            }
+            _ => {
+                if arg.starts_with('-') {
+                    return Err(format!("未知选项: {}", arg));
+                }
+                if input_file.is_none() {
+                    input_file = Some(arg.clone());
+                } else {
+                    return Err(format!("多余参数: {} (传给程序的参数需要写在 -- 之后)", arg));
+                }
+            }
+        }
+        i += 1;
+    }
+
+    let input = input_file.ok_or("缺少源文件")?;
+    Ok((options, input))
+}
+
+/// 编译运行使用的临时目录，每个进程独立，同时运行同一源文件互不影响
+struct Workspace {
+    dir: PathBuf,
+}
+
+impl Workspace {
+    fn new() -> Self {
+        Self { dir: env::temp_dir().join(format!("cay-run-{}", process::id())) }
+    }
+
+    fn file(&self, name: &str) -> String {
+        self.dir.join(name).to_string_lossy().to_string()
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// 与 cay-run 同目录的 ir2exe
+fn find_ir2exe() -> Result<PathBuf, String> {
+    let exe = env::current_exe().map_err(|e| format!("无法获取当前执行路径: {}", e))?;
+    let bin_dir = exe.parent().ok_or("无法获取执行目录")?;
+    [bin_dir.join("ir2exe"), bin_dir.join("ir2exe.exe")]
+        .into_iter()
+        .find(|path| path.exists())
+        .ok_or_else(|| format!("找不到 ir2exe (在 {} 中)", bin_dir.display()))
+}
+
+/// 编译并运行，返回退出码；临时目录在返回前清理
+fn run(source: &str, options: &RunOptions) -> i32 {
+    let workspace = Workspace::new();
+    if let Err(e) = fs::create_dir_all(&workspace.dir) {
+        eprintln!("错误: 无法创建临时目录: {}", e);
+        return exit_codes::IO;
+    }
+    let ir_file = workspace.file("program.ll");
+    let exe_file = workspace.file(if cfg!(windows) { "program.exe" } else { "program" });
+
+    let compiler = Compiler::with_options(CompilerOptions {
+        max_steps: options.max_steps,
+        optimize: options.optimization != "-O0",
+        ..Default::default()
+    });
+    if let Err(e) = compiler.compile_file(source, &ir_file) {
+        let text = cavvy::source::read_source_file(source).unwrap_or_default();
+        print_error_with_context(&e, &text, source);
+        return e.exit_code();
+    }
+
+    let ir2exe = match find_ir2exe() {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            return exit_codes::TOOL;
+        }
+    };
+    // ir2exe 的输出被捕获，只在失败时显示
+    let mut cmd = Command::new(ir2exe);
+    cmd.arg(&options.optimization);
+    if let Some(dir) = &options.llvm_path {
+        cmd.arg("--llvm-path").arg(dir);
+    }
+    cmd.arg("--tool-timeout").arg(options.tool_timeout.as_secs().to_string());
+    cmd.arg(&ir_file).arg(&exe_file);
+    if let Err(e) = toolchain::run_tool(&mut cmd, "ir2exe", options.tool_timeout + Duration::from_secs(30)) {
+        eprintln!("错误: {}", e);
+        return e.exit_code();
+    }
+
+    // 程序继承标准输入输出，退出码原样返回；被信号终止时按 shell 的惯例返回 128 + 信号值
+    match Command::new(&exe_file).args(&options.program_args).status() {
+        Ok(status) => status.code().unwrap_or_else(|| 128 + signal_of(&status)),
+        Err(e) => {
+            eprintln!("错误: 无法启动程序: {}", e);
+            exit_codes::IO
+        }
+    }
+}
+
+#[cfg(unix)]
+fn signal_of(status: &process::ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal().unwrap_or(0)
+}
+
+#[cfg(not(unix))]
+fn signal_of(_status: &process::ExitStatus) -> i32 {
+    0
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let (options, source) = match parse_args(&args) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            print_usage();
+            process::exit(exit_codes::USAGE);
+        }
+    };
+
+    process::exit(run(&source, &options));
+}
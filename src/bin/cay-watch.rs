@@ -0,0 +1,281 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Child, Command};
+use std::thread;
+use std::time::Duration;
+use cavvy::{Compiler, CompilerOptions};
+use cavvy::diagnostics::Diagnostic;
+use cavvy::error::exit_codes;
+use cavvy::toolchain;
+use cavvy::watch::{self, DiagnosticDelta};
+
+const VERSION: &str = env!("CAY_WATCH_VERSION");
+
+/// 默认的轮询间隔（毫秒）
+const DEFAULT_INTERVAL_MS: u64 = 300;
+/// 调用 ir2exe 的超时时间
+const TOOL_TIMEOUT: Duration = Duration::from_secs(300);
+
+fn print_usage() {
+    println!("Cavvy Watch v{}", VERSION);
+    println!("Usage: cay-watch [options] <source_file.cay> [-- <程序参数>...]");
+    println!();
+    println!("监视源文件及其导入的模块，保存后立即重新分析并输出诊断，没有错误时重新编译运行。");
+    println!("程序仍在运行时源文件又被修改，会先终止它再重新运行。按 Ctrl+C 退出。");
+    println!();
+    println!("Options:");
+    println!("  --interval <ms>          检查文件变化的间隔 (默认 {} 毫秒)", DEFAULT_INTERVAL_MS);
+    println!("  --check-only             只分析并输出诊断，不编译运行");
+    println!("  --once                   只执行一轮 (分析、编译运行) 后退出，退出码与程序相同");
+    println!("  --max-steps <n>          运行的程序超过 n 步后终止 (避免死循环)");
+    println!("  --llvm-path <dir>        外部 LLVM 工具链目录 (传给 ir2exe)");
+    println!("  --version, -v            显示版本号 (加 --verbose 显示详细信息)");
+    println!("  --help, -h               显示帮助信息");
+    println!();
+    println!("Examples:");
+    println!("  cay-watch hello.cay");
+    println!("  cay-watch --check-only lesson1.cay");
+    println!("  cay-watch --max-steps 1000000 loop.cay -- input.txt");
+}
+
+struct WatchOptions {
+    interval: Duration,
+    check_only: bool,
+    once: bool,
+    max_steps: Option<u64>,
+    llvm_path: Option<String>,
+    program_args: Vec<String>,
+}
+
+fn parse_args(args: &[String]) -> Result<(WatchOptions, String), String> {
+    let mut options = WatchOptions {
+        interval: Duration::from_millis(DEFAULT_INTERVAL_MS),
+        check_only: false,
+        once: false,
+        max_steps: None,
+        llvm_path: None,
+        program_args: Vec::new(),
+    };
+    let mut input_file: Option<String> = None;
+    let mut i = 1;
+
+    while i < args.len() {
+        let arg = &args[i];
+        match arg.as_str() {
+            "--version" | "-v" => {
+                if args.iter().any(|a| a == "--verbose") {
+                    print!("{}", cavvy::version::verbose_version_info("Cavvy Watch", VERSION));
+                } else {
+                    println!("Cavvy Watch v{}", VERSION);
+                }
+                process::exit(0);
+            }
+            "--verbose" => {
+                // 与 --version 一起使用
+            }
+            "--help" | "-h" => {
+                print_usage();
+                process::exit(0);
+            }
+            "--interval" => {
+                i += 1;
+                let value = args.get(i).ok_or("--interval 需要参数")?;
+                match value.parse::<u64>() {
+                    Ok(ms) if ms > 0 => options.interval = Duration::from_millis(ms),
+                    _ => return Err(format!("无效的间隔: {} (需要正整数毫秒数)", value)),
+                }
+            }
+            "--check-only" => options.check_only = true,
+            "--once" => options.once = true,
+            "--max-steps" => {
+                i += 1;
+                let value = args.get(i).ok_or("--max-steps 需要参数")?;
+                options.max_steps = Some(toolchain::parse_max_steps(value)?);
+            }
+            "--llvm-path" => {
+                i += 1;
+                let dir = args.get(i).ok_or("--llvm-path 需要参数")?;
+                options.llvm_path = Some(dir.clone());
+            }
+            "--" => {
+                options.program_args = args[i + 1..].to_vec();
+                break;
+            }
+            _ => {
+                if arg.starts_with('-') {
+                    return Err(format!("未知选项: {}", arg));
+                }
+                if input_file.is_none() {
+                    input_file = Some(arg.clone());
+                } else {
+                    return Err(format!("多余参数: {}", arg));
+                }
+            }
+        }
+        i += 1;
+    }
+
+    let input = input_file.ok_or("缺少源文件")?;
+    Ok((options, input))
+}
+
+/// 编译运行使用的临时文件
+///
+/// 目录名由源文件路径决定：按 Ctrl+C 退出时来不及清理，下次监视同一文件时复用。
+struct Workspace {
+    dir: PathBuf,
+    ir_file: String,
+    exe_file: String,
+}
+
+impl Workspace {
+    fn new(source: &str) -> Self {
+        let path = fs::canonicalize(source).unwrap_or_else(|_| PathBuf::from(source));
+        let hash = cavvy::cache::content_hash(path.to_string_lossy().as_bytes());
+        let dir = env::temp_dir().join(format!("cay-watch-{:016x}", hash));
+        let exe_name = if cfg!(windows) { "program.exe" } else { "program" };
+        Self {
+            ir_file: dir.join("program.ll").to_string_lossy().to_string(),
+            exe_file: dir.join(exe_name).to_string_lossy().to_string(),
+            dir,
+        }
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// 与 cay-watch 同目录的 ir2exe
+fn find_ir2exe() -> Result<PathBuf, String> {
+    let exe = env::current_exe().map_err(|e| format!("无法获取当前执行路径: {}", e))?;
+    let bin_dir = exe.parent().ok_or("无法获取执行目录")?;
+    [bin_dir.join("ir2exe"), bin_dir.join("ir2exe.exe")]
+        .into_iter()
+        .find(|path| path.exists())
+        .ok_or_else(|| format!("找不到 ir2exe (在 {} 中)", bin_dir.display()))
+}
+
+/// 编译源文件并启动程序，标准输入输出与 cay-watch 共用
+fn build_and_start(source: &str, options: &WatchOptions, workspace: &Workspace) -> Result<Child, (String, i32)> {
+    fs::create_dir_all(&workspace.dir).map_err(|e| (format!("无法创建临时目录: {}", e), exit_codes::IO))?;
+    let compiler = Compiler::with_options(CompilerOptions {
+        max_steps: options.max_steps,
+        ..Default::default()
+    });
+    compiler.compile_file(source, &workspace.ir_file).map_err(|e| (e.to_string(), e.exit_code()))?;
+
+    let ir2exe = find_ir2exe().map_err(|e| (e, exit_codes::TOOL))?;
+    let mut cmd = Command::new(ir2exe);
+    if let Some(dir) = &options.llvm_path {
+        cmd.arg("--llvm-path").arg(dir);
+    }
+    cmd.arg("--tool-timeout").arg(TOOL_TIMEOUT.as_secs().to_string());
+    cmd.arg(&workspace.ir_file).arg(&workspace.exe_file);
+    toolchain::run_tool(&mut cmd, "ir2exe", TOOL_TIMEOUT + Duration::from_secs(30))
+        .map_err(|e| (e.to_string(), e.exit_code()))?;
+
+    Command::new(&workspace.exe_file)
+        .args(&options.program_args)
+        .spawn()
+        .map_err(|e| (format!("无法启动程序: {}", e), exit_codes::IO))
+}
+
+/// 终止仍在运行的程序
+fn stop(child: &mut Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// 诊断对应的退出码：含有预处理、词法或语法错误时为语法错误，否则为语义错误
+fn diagnostics_exit_code(diagnostics: &[Diagnostic]) -> i32 {
+    let syntax = ["预处理", "词法分析", "语法分析"];
+    if diagnostics.iter().any(|d| syntax.contains(&d.phase)) {
+        exit_codes::SYNTAX
+    } else {
+        exit_codes::SEMANTIC
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let (options, source) = match parse_args(&args) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            print_usage();
+            process::exit(exit_codes::USAGE);
+        }
+    };
+    if !Path::new(&source).exists() {
+        eprintln!("错误: 找不到源文件: {}", source);
+        process::exit(exit_codes::IO);
+    }
+
+    process::exit(watch_loop(&source, &options));
+}
+
+/// 监视循环：只有 `--once` 时返回退出码，否则一直运行到进程被终止
+fn watch_loop(source: &str, options: &WatchOptions) -> i32 {
+    let workspace = Workspace::new(source);
+    let mut files = vec![source.to_string()];
+    let mut last_fingerprint = None;
+    let mut previous: Vec<Diagnostic> = Vec::new();
+    let mut running: Option<Child> = None;
+
+    loop {
+        let fingerprint = watch::fingerprint(&files);
+        if last_fingerprint != Some(fingerprint) {
+            if let Some(child) = running.as_mut() {
+                stop(child);
+                running = None;
+                println!("[watch] 文件已修改，已终止正在运行的程序");
+            }
+            println!("[watch] 分析 {} ...", source);
+            let analysis = watch::analyze(source);
+            files = analysis.files.clone();
+            last_fingerprint = Some(watch::fingerprint(&files));
+
+            print!("{}", DiagnosticDelta::between(&previous, &analysis.diagnostics).render());
+            previous = analysis.diagnostics;
+            if previous.is_empty() {
+                println!("[watch] 没有错误");
+                if !options.check_only {
+                    match build_and_start(source, options, &workspace) {
+                        Ok(child) => {
+                            println!("[watch] 运行程序");
+                            running = Some(child);
+                        }
+                        Err((message, code)) => {
+                            eprintln!("[watch] 编译失败: {}", message);
+                            if options.once {
+                                return code;
+                            }
+                        }
+                    }
+                }
+            }
+            if options.once && running.is_none() {
+                return if previous.is_empty() { exit_codes::SUCCESS } else { diagnostics_exit_code(&previous) };
+            }
+            if !options.once {
+                println!("[watch] 等待文件变化... (Ctrl+C 退出)");
+            }
+        }
+
+        if let Some(child) = running.as_mut()
+            && let Ok(Some(status)) = child.try_wait()
+        {
+            running = None;
+            let code = status.code().unwrap_or(1);
+            println!("[watch] 程序已退出 (退出码 {})", code);
+            if options.once {
+                return code;
+            }
+        }
+        thread::sleep(options.interval);
+    }
+}
@@ -1,51 +1,14 @@
 use std::env;
 use std::fs;
 use std::process;
-use std::path::{Path, PathBuf};
-use cavvy::Compiler;
-use cavvy::error::{print_error_with_context, cayError};
-
-/// 根据平台获取 llvm-minimal 下的 clang 路径
-#[cfg(target_os = "windows")]
-fn get_bundled_clang_path(exe_dir: &Path) -> PathBuf {
-    exe_dir.join("llvm-minimal/bin/clang.exe")
-}
-
-#[cfg(target_os = "linux")]
-fn get_bundled_clang_path(exe_dir: &Path) -> PathBuf {
-    exe_dir.join("llvm-minimal/bin-linux/clang-21")
-}
-
-#[cfg(not(any(target_os = "windows", target_os = "linux")))]
-fn get_bundled_clang_path(exe_dir: &Path) -> PathBuf {
-    exe_dir.join("llvm-minimal/bin/clang")
-}
-
-/// 查找 clang 可执行文件
-/// 1. 首先尝试直接调用 "clang"（系统 PATH 中）
-/// 2. 如果失败，尝试查找编译器所在目录下的 llvm-minimal/bin/clang
-/// 3. 如果都找不到，返回错误
-fn find_clang() -> Result<PathBuf, String> {
-    // 1. 首先尝试系统 PATH 中的 clang
-    if let Ok(output) = process::Command::new("clang").arg("--version").output() {
-        if output.status.success() {
-            return Ok(PathBuf::from("clang"));
-        }
-    }
-    
-    // 2. 尝试编译器所在目录下的 llvm-minimal
-    if let Ok(exe_path) = env::current_exe() {
-        if let Some(exe_dir) = exe_path.parent() {
-            let bundled_clang = get_bundled_clang_path(exe_dir);
-            if bundled_clang.exists() {
-                return Ok(bundled_clang);
-            }
-        }
-    }
-    
-    // 3. 都找不到，返回错误
-    Err("找不到 clang 编译器。请确保 clang 已安装并在 PATH 中，或将 llvm-minimal 放在编译器同目录下。".to_string())
-}
+use std::path::Path;
+use std::time::Duration;
+use cavvy::{Compiler, CompilerOptions};
+use cavvy::{cli, ice};
+use clap::error::ErrorKind;
+use clap_complete::Shell;
+use cavvy::toolchain::{self, DEFAULT_TOOL_TIMEOUT_SECS};
+use cavvy::error::{print_error_with_context, exit_codes};
 
 const VERSION: &str = env!("CAYC_VERSION");
 
@@ -53,8 +16,11 @@ struct CompileOptions {
     // 基础优化
     optimization: String,         // -O0, -O1, -O2, -O3, -Os, -Oz
     opt_ir: bool,                 // --opt-ir: 优化 IR 阶段
+    verify_ir: bool,              // --verify-ir: 自检生成的 IR
     debug: bool,                  // -g
     keep_ir: bool,                // --keep-ir
+    external_runtime: bool,       // --external-runtime
+    no_main: bool,                // --no-main
     extra_lib_paths: Vec<String>, // -L<path>
     extra_libs: Vec<String>,      // -l<lib>
     extra_ldflags: Vec<String>,   // --ldflags
@@ -62,6 +28,12 @@ struct CompileOptions {
     target: String,               // --target
     static_link: bool,            // --static
     position_independent: bool,   // -fPIC/-fPIE
+    stack_size: Option<u64>,      // --stack-size <bytes>
+    stack_guard_depth: Option<u32>, // --stack-guard[=<depth>]
+    max_steps: Option<u64>,       // --max-steps <n>
+    max_heap_bytes: Option<u64>,  // --max-heap <size>
+    jobs: usize,                  // --jobs <n>, -j <n>
+    cache_dir: Option<String>,    // --cache-dir <dir>
     // LTO 选项
     lto: bool,                    // --lto, --lto=full
     lto_thin: bool,               // --lto=thin
@@ -83,6 +55,11 @@ struct CompileOptions {
     funroll_loops: bool,          // -funroll-loops
     fvectorize: bool,             // -fvectorize
     fslp_vectorize: bool,         // -fslp-vectorize
+    // 外部工具链
+    llvm_path: Option<String>,    // --llvm-path <dir>
+    tool_timeout: Duration,       // --tool-timeout <secs>
+    // 只输出中间结果
+    emit: Option<String>,         // --emit=<kind>
 }
 
 /// 根据当前操作系统自动选择默认目标平台
@@ -113,8 +90,11 @@ impl Default for CompileOptions {
         CompileOptions {
             optimization: "-O2".to_string(),
             opt_ir: false,
+            verify_ir: false,
             debug: false,
             keep_ir: false,
+            external_runtime: false,
+            no_main: false,
             extra_lib_paths: Vec::new(),
             extra_libs: Vec::new(),
             extra_ldflags: Vec::new(),
@@ -122,6 +102,12 @@ impl Default for CompileOptions {
             target: get_default_target(),
             static_link: false,
             position_independent: false,
+            stack_size: None,
+            stack_guard_depth: None,
+            max_steps: None,
+            max_heap_bytes: None,
+            jobs: 1,
+            cache_dir: None,
             lto: false,
             lto_thin: false,
             march: None,
@@ -139,266 +125,187 @@ impl Default for CompileOptions {
             funroll_loops: false,
             fvectorize: false,
             fslp_vectorize: false,
+            llvm_path: None,
+            tool_timeout: Duration::from_secs(DEFAULT_TOOL_TIMEOUT_SECS),
+            emit: None,
         }
     }
 }
 
-fn print_usage() {
-    println!("Cavvy Compiler v{}", VERSION);
-    println!("Usage: cayc [options] <source_file.cay> [output_file.exe]");
-    println!("");
-    println!("Optimization Options:");
-    println!("  -O0, -O1, -O2, -O3    优化级别 (默认: -O2)");
-    println!("  -Os, -Oz              优化代码大小");
-    println!("  --opt-ir              启用 IR 阶段优化 (使用 LLVM 优化 IR)");
-    println!("  --lto[=<type>]        链接时优化 (full/thin)");
-    println!("  -march=<arch>         目标 CPU 架构 (如 x86-64-v3, native)");
-    println!("  -mtune=<cpu>          针对特定 CPU 优化 (如 intel, znver3)");
-    println!("  -mcpu=<cpu>           针对 ARM/AArch64 CPU 优化");
-    println!("  -msse=<ver>           SSE 版本 (1/2/3/4.1/4.2)");
-    println!("  -mavx=<ver>           AVX 版本 (avx/avx2/avx512f)");
-    println!("  --mneon               启用 ARM NEON");
-    println!("  -funroll-loops        循环展开");
-    println!("  -fvectorize           启用自动向量化");
-    println!("  -fslp-vectorize       启用 SLP 向量化");
-    println!("  -fomit-frame-pointer  省略帧指针");
-    println!("");
-    println!("PGO (Profile Guided Optimization):");
-    println!("  -fprofile-generate     生成性能分析数据");
-    println!("  -fprofile-use=<path>   使用性能分析数据优化");
-    println!("  -fcs-profile-generate  上下文敏感的性能分析");
-    println!("");
-    println!("Code Generation:");
-    println!("  -g                    生成调试信息");
-    println!("  --keep-ir             保留中间 IR 文件 (.ll)");
-    println!("  -L<path>              添加库搜索路径");
-    println!("  -l<lib>               链接额外的库");
-    println!("  --ldflags <flags>     传递额外的链接器标志");
-    println!("  --cflags <flags>      传递额外的编译器标志");
-    println!("  --static              静态链接");
-    println!("  -fPIC                 生成位置无关代码");
-    println!("  -fno-exceptions       禁用异常处理");
-    println!("  -fno-rtti             禁用运行时类型信息");
-    println!("");
-    println!("Other Options:");
-    println!("  --version, -v         显示版本号");
-    println!("  --help, -h            显示帮助信息");
-    println!("");
-    println!("Examples:");
-    println!("  cayc hello.cay");
-    println!("  cayc -O3 hello.cay hello.exe");
-    println!("  cayc --opt-ir -O3 --lto=full hello.cay");
-    println!("  cayc -O3 -march=native -mtune=native -fvectorize hello.cay");
-    println!("  cayc --static -O2 -L./libs -lmylib app.cay app.exe");
-}
+fn parse_args(args: &[String]) -> Result<(CompileOptions, String, String), clap::Error> {
+    let matches = cli::cayc_command().try_get_matches_from(args)?;
+    let invalid = |message: String| cli::cayc_command().error(ErrorKind::ValueValidation, message);
+
+    if matches.get_flag("version") {
+        if matches.get_flag("verbose") {
+            print!("{}", cavvy::version::verbose_version_info("Cavvy Compiler", VERSION));
+        } else {
+            println!("Cavvy Compiler v{}", VERSION);
+        }
+        process::exit(0);
+    }
+    if let Some(&shell) = matches.get_one::<Shell>("completions") {
+        cli::write_completions(shell, &mut std::io::stdout());
+        process::exit(0);
+    }
 
-fn parse_args(args: &[String]) -> Result<(CompileOptions, String, String), String> {
     let mut options = CompileOptions::default();
-    let mut input_file: Option<String> = None;
-    let mut output_file: Option<String> = None;
-    let mut i = 1;
+    let strings = |id: &str| matches.get_many::<String>(id).into_iter().flatten().cloned();
+
+    if let Some(level) = matches.get_one::<String>("opt-level") {
+        options.optimization = format!("-O{}", level);
+    }
+    options.opt_ir = matches.get_flag("opt-ir");
+    options.verify_ir = matches.get_flag("verify-ir");
+    options.debug = matches.get_flag("debug");
+    options.keep_ir = matches.get_flag("keep-ir");
+    options.external_runtime = matches.get_flag("external-runtime");
+    options.no_main = matches.get_flag("no-main");
+    options.static_link = matches.get_flag("static");
+    options.mneon = matches.get_flag("mneon");
+
+    if let Some(lto_type) = matches.get_one::<String>("lto") {
+        options.lto = true;
+        options.lto_thin = lto_type == "thin";
+    }
+
+    // -m<option>=<value>，取值已由命令行定义校验
+    for machine in strings("machine") {
+        let (option, value) = machine.split_once('=').unwrap_or((machine.as_str(), ""));
+        let value = Some(value.to_string());
+        match option {
+            "arch" => options.march = value,
+            "tune" => options.mtune = value,
+            "cpu" => options.mcpu = value,
+            "sse" => options.msse = value,
+            "avx" => options.mavx = value,
+            _ => unreachable!("未知的 -m 选项: {}", machine),
+        }
+    }
 
-    while i < args.len() {
-        let arg = &args[i];
+    // -f<flag>
+    for flag in strings("codegen-flag") {
+        match flag.as_str() {
+            "PIC" | "pic" => options.position_independent = true,
+            "no-exceptions" => options.fno_exceptions = true,
+            "no-rtti" => options.fno_rtti = true,
+            "omit-frame-pointer" => options.fomit_frame_pointer = true,
+            "unroll-loops" => options.funroll_loops = true,
+            "vectorize" => options.fvectorize = true,
+            "slp-vectorize" => options.fslp_vectorize = true,
+            "profile-generate" => options.pgo_gen = true,
+            "cs-profile-generate" => options.pgo_cs = true,
+            _ => match flag.strip_prefix("profile-use=") {
+                Some(path) => options.pgo_use = Some(path.to_string()),
+                None => unreachable!("未知的 -f 选项: {}", flag),
+            },
+        }
+    }
 
-        match arg.as_str() {
-            "--version" | "-v" => {
-                println!("Cavvy Compiler v{}", VERSION);
-                process::exit(0);
-            }
-            "--help" | "-h" => {
-                print_usage();
-                process::exit(0);
-            }
-            "-O0" | "-O1" | "-O2" | "-O3" | "-Os" | "-Oz" => {
-                options.optimization = arg.clone();
-            }
-            "--opt-ir" => {
-                options.opt_ir = true;
-            }
-            "-g" => {
-                options.debug = true;
-            }
-            "--keep-ir" => {
-                options.keep_ir = true;
-            }
-            "--static" => {
-                options.static_link = true;
-            }
-            "-fPIC" | "-fpic" => {
-                options.position_independent = true;
-            }
-            "-fno-exceptions" => {
-                options.fno_exceptions = true;
-            }
-            "-fno-rtti" => {
-                options.fno_rtti = true;
-            }
-            "-fomit-frame-pointer" => {
-                options.fomit_frame_pointer = true;
-            }
-            "-funroll-loops" => {
-                options.funroll_loops = true;
-            }
-            "-fvectorize" => {
-                options.fvectorize = true;
-            }
-            "-fslp-vectorize" => {
-                options.fslp_vectorize = true;
-            }
-            "--mneon" => {
-                options.mneon = true;
-            }
-            "-fprofile-generate" => {
-                options.pgo_gen = true;
-            }
-            "-fcs-profile-generate" => {
-                options.pgo_cs = true;
-            }
-            "--lto" => {
-                options.lto = true;
-            }
-            "--target" => {
-                i += 1;
-                if i >= args.len() {
-                    return Err("--target 需要参数".to_string());
-                }
-                options.target = args[i].clone();
-            }
-            "--ldflags" => {
-                i += 1;
-                if i >= args.len() {
-                    return Err("--ldflags 需要参数".to_string());
-                }
-                for flag in args[i].split_whitespace() {
-                    options.extra_ldflags.push(flag.to_string());
-                }
-            }
-            "--cflags" => {
-                i += 1;
-                if i >= args.len() {
-                    return Err("--cflags 需要参数".to_string());
-                }
-                for flag in args[i].split_whitespace() {
-                    options.extra_cflags.push(flag.to_string());
-                }
-            }
-            _ if arg.starts_with("--lto=") => {
-                let lto_type = &arg[6..];
-                match lto_type {
-                    "full" => {
-                        options.lto = true;
-                        options.lto_thin = false;
-                    }
-                    "thin" => {
-                        options.lto = true;
-                        options.lto_thin = true;
-                    }
-                    _ => return Err(format!("未知的 LTO 类型: {}", lto_type)),
-                }
-            }
-            _ if arg.starts_with("-march=") => {
-                options.march = Some(arg[7..].to_string());
-            }
-            _ if arg.starts_with("-mtune=") => {
-                options.mtune = Some(arg[7..].to_string());
-            }
-            _ if arg.starts_with("-mcpu=") => {
-                options.mcpu = Some(arg[6..].to_string());
-            }
-            _ if arg.starts_with("-msse=") => {
-                options.msse = Some(arg[6..].to_string());
-            }
-            _ if arg.starts_with("-mavx=") => {
-                options.mavx = Some(arg[6..].to_string());
-            }
-            _ if arg.starts_with("-fprofile-use=") => {
-                options.pgo_use = Some(arg[14..].to_string());
-            }
-            _ if arg.starts_with("-L") => {
-                let path = if arg.len() > 2 {
-                    arg[2..].to_string()
-                } else {
-                    i += 1;
-                    if i >= args.len() {
-                        return Err("-L 需要路径参数".to_string());
-                    }
-                    args[i].clone()
-                };
-                options.extra_lib_paths.push(path);
-            }
-            _ if arg.starts_with("-l") => {
-                let lib = if arg.len() > 2 {
-                    arg[2..].to_string()
-                } else {
-                    i += 1;
-                    if i >= args.len() {
-                        return Err("-l 需要库名参数".to_string());
-                    }
-                    args[i].clone()
-                };
-                options.extra_libs.push(lib);
-            }
-            _ => {
-                if arg.starts_with('-') {
-                    return Err(format!("未知选项: {}", arg));
-                }
-                if input_file.is_none() {
-                    input_file = Some(arg.clone());
-                } else if output_file.is_none() {
-                    output_file = Some(arg.clone());
-                } else {
-                    return Err(format!("多余参数: {}", arg));
-                }
+    if let Some(target) = matches.get_one::<String>("target") {
+        options.target = target.clone();
+    }
+    options.llvm_path = matches.get_one::<String>("llvm-path").cloned();
+    if let Some(timeout) = matches.get_one::<String>("tool-timeout") {
+        options.tool_timeout = toolchain::parse_timeout(timeout).map_err(invalid)?;
+    }
+    if let Some(size) = matches.get_one::<String>("stack-size") {
+        options.stack_size = Some(toolchain::parse_stack_size(size).map_err(invalid)?);
+    }
+    if matches.contains_id("stack-guard") {
+        options.stack_guard_depth = Some(match matches.get_one::<String>("stack-guard") {
+            Some(depth) => toolchain::parse_stack_guard_depth(depth).map_err(invalid)?,
+            None => cavvy::DEFAULT_STACK_GUARD_DEPTH,
+        });
+    }
+    if let Some(steps) = matches.get_one::<String>("max-steps") {
+        options.max_steps = Some(toolchain::parse_max_steps(steps).map_err(invalid)?);
+    }
+    if let Some(size) = matches.get_one::<String>("max-heap") {
+        options.max_heap_bytes = Some(toolchain::parse_max_heap(size).map_err(invalid)?);
+    }
+    if let Some(jobs) = matches.get_one::<String>("jobs") {
+        options.jobs = toolchain::parse_jobs(jobs).map_err(invalid)?;
+    }
+    options.cache_dir = matches.get_one::<String>("cache-dir").cloned();
+
+    options.extra_lib_paths = strings("lib-path").collect();
+    options.extra_libs = strings("lib").collect();
+    options.extra_ldflags = strings("ldflags").flat_map(|f| f.split_whitespace().map(str::to_string).collect::<Vec<_>>()).collect();
+    options.extra_cflags = strings("cflags").flat_map(|f| f.split_whitespace().map(str::to_string).collect::<Vec<_>>()).collect();
+
+    let input_file = matches.get_one::<String>("input").cloned().expect("命令行定义要求输入文件");
+    options.emit = matches.get_one::<String>("emit").cloned();
+    let output = matches.get_one::<String>("output-file").or(matches.get_one::<String>("output")).cloned();
+    let stem = Path::new(&input_file)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("output");
+    let output_file = match options.emit.as_deref().unwrap_or("exe") {
+        // 语法树未指定输出文件时输出到标准输出
+        "ast" | "ast-json" => output.unwrap_or_else(|| "-".to_string()),
+        kind if output.as_deref() == Some("-") => {
+            return Err(invalid(format!("--emit={} 不能输出到标准输出", kind)));
+        }
+        "llvm-ir" => output.unwrap_or_else(|| format!("{}.ll", stem)),
+        "obj" => {
+            if options.external_runtime {
+                return Err(invalid("--emit=obj 不能与 --external-runtime 一起使用".to_string()));
             }
+            output.unwrap_or_else(|| format!("{}.o", stem))
         }
-        i += 1;
-    }
-
-    let input_file = input_file.ok_or("需要指定输入文件")?;
-    let output_file = output_file.unwrap_or_else(|| {
-        let stem = Path::new(&input_file)
-            .file_stem()
-            .and_then(|stem| stem.to_str())
-            .unwrap_or("output");
-        
-        // 根据目标平台选择扩展名
-        if options.target.contains("windows") || options.target.contains("mingw") {
-            format!("{}.exe", stem)
-        } else {
-            // Linux和其他系统不使用.exe扩展名
-            stem.to_string()
+        _ if options.no_main => {
+            return Err(invalid("--no-main 生成的库没有程序入口，需与 --emit=obj 或 --emit=llvm-ir 一起使用".to_string()));
         }
-    });
+        // 根据目标平台选择扩展名，Linux 和其他系统不使用 .exe 扩展名
+        _ if options.target.contains("windows") || options.target.contains("mingw") => {
+            output.unwrap_or_else(|| format!("{}.exe", stem))
+        }
+        _ => output.unwrap_or_else(|| stem.to_string()),
+    };
 
     Ok((options, input_file, output_file))
 }
 
-fn optimize_ir(ir_file: &str, opt_level: &str) -> Result<(), String> {
-    let clang_exe = find_clang()?;
-
+/// 用 opt 按当前优化级别原地优化 IR 文件
+fn optimize_ir(ir_file: &str, options: &CompileOptions) -> Result<(), toolchain::ToolError> {
     let temp_file = format!("{}.opt.tmp", ir_file);
-
-    let output = process::Command::new(&clang_exe)
-        .arg("-x").arg("ir")
-        .arg(ir_file)
-        .arg("-S")
-        .arg("-emit-llvm")
-        .arg(opt_level)
-        .arg("-o").arg(&temp_file)
-        .output()
-        .map_err(|e| format!("执行 clang 失败: {}", e))?;
-
-    if !output.status.success() {
-        let error_msg = String::from_utf8_lossy(&output.stderr);
+    if let Err(e) = toolchain::optimize_ir(ir_file, &temp_file, &options.optimization, options.llvm_path.as_deref(), options.tool_timeout) {
         let _ = fs::remove_file(&temp_file);
-        return Err(format!("IR 优化失败: {}", error_msg));
+        return Err(e);
     }
+    fs::rename(&temp_file, ir_file).map_err(|e| toolchain::ToolError::Spawn {
+        tool: "opt".to_string(),
+        message: format!("无法替换 IR 文件: {}", e),
+    })
+}
 
-    fs::rename(&temp_file, ir_file)
-        .map_err(|e| format!("无法替换 IR 文件: {}", e))?;
-
-    Ok(())
+/// `--emit=ast`、`--emit=ast-json`：只输出语法树，返回退出码
+fn emit(kind: &str, source_path: &str, output: &str) -> i32 {
+    let compiler = Compiler::new();
+    let result = compiler.parse_file(source_path).and_then(|program| match kind {
+        "ast-json" => cavvy::ast_dump::to_json(&program),
+        _ => Ok(cavvy::ast_dump::to_text(&program)),
+    });
+    let text = match result {
+        Ok(text) => text,
+        Err(e) => {
+            let source = cavvy::source::read_source_file(source_path).unwrap_or_default();
+            print_error_with_context(&e, &source, source_path);
+            return e.exit_code();
+        }
+    };
+    if output == "-" {
+        print!("{}", text);
+        return exit_codes::SUCCESS;
+    }
+    match fs::write(output, text) {
+        Ok(()) => exit_codes::SUCCESS,
+        Err(e) => {
+            eprintln!("错误: 无法写入 {}: {}", output, e);
+            exit_codes::IO
+        }
+    }
 }
 
 fn main() {
@@ -407,16 +314,26 @@ fn main() {
     let (options, source_path, exe_output) = match parse_args(&args) {
         Ok(result) => result,
         Err(e) => {
-            eprintln!("错误: {}", e);
-            print_usage();
-            process::exit(1);
+            // --help 输出到 stdout 并正常退出，用法错误输出到 stderr
+            let _ = e.print();
+            process::exit(if e.use_stderr() { exit_codes::USAGE } else { exit_codes::SUCCESS });
         }
     };
 
-    let ir_file = Path::new(&exe_output)
-        .with_extension("ll")
-        .to_string_lossy()
-        .to_string();
+    let emit_kind = options.emit.as_deref().unwrap_or("exe");
+    if matches!(emit_kind, "ast" | "ast-json") {
+        process::exit(emit(emit_kind, &source_path, &exe_output));
+    }
+
+    // --emit=llvm-ir 时 IR 文件就是输出文件
+    let ir_file = if emit_kind == "llvm-ir" {
+        exe_output.clone()
+    } else {
+        Path::new(&exe_output)
+            .with_extension("ll")
+            .to_string_lossy()
+            .to_string()
+    };
 
     println!("Cavvy 编译器 v{}", VERSION);
     println!("源文件: {}", source_path);
@@ -483,33 +400,70 @@ fn main() {
 
     // 1. Cavvy → IR
     println!("[1] Cavvy → IR 编译...");
-    let source = match fs::read_to_string(&source_path) {
+    let source = match cavvy::source::read_source_file(&source_path) {
         Ok(content) => content,
         Err(e) => {
-            eprintln!("错误读取源文件 '{}': {}", source_path, e);
-            process::exit(1);
+            eprintln!("错误: {}", e);
+            process::exit(e.exit_code());
         }
     };
 
-    let compiler = Compiler::new();
-    match compiler.compile_file(&source_path, &ir_file) {
-        Ok(_) => {
+    let compiler = Compiler::with_options(CompilerOptions {
+        external_runtime: options.external_runtime,
+        no_main: options.no_main,
+        verify_ir: options.verify_ir,
+        stack_guard_depth: options.stack_guard_depth,
+        max_steps: options.max_steps,
+        max_heap_bytes: options.max_heap_bytes,
+        optimize: options.optimization != "-O0",
+        jobs: options.jobs,
+        cache_dir: options.cache_dir.clone(),
+        ..Default::default()
+    });
+    let result = ice::run_guarded("Cavvy Compiler", VERSION, &source_path, || compiler.compile_file(&source_path, &ir_file));
+    match result {
+        Ok(Ok(_)) => {
             println!("  [+] Cavvy 编译成功");
         }
-        Err(e) => {
+        Ok(Err(e)) => {
             print_error_with_context(&e, &source, &source_path);
-            process::exit(1);
+            let _ = fs::remove_file(&ir_file);
+            process::exit(e.exit_code());
+        }
+        Err(internal) => {
+            eprint!("{}", internal.report_instructions(&source_path));
+            let _ = fs::remove_file(&ir_file);
+            process::exit(exit_codes::CODEGEN);
+        }
+    }
+
+    // 外部运行时：输出内置的运行时模块，链接时一并交给 ir2exe
+    let runtime_file = Path::new(&exe_output)
+        .with_extension("runtime.ll")
+        .to_string_lossy()
+        .to_string();
+    if options.external_runtime {
+        if let Err(e) = compiler.compile_runtime(&runtime_file) {
+            eprintln!("错误: 无法生成运行时模块: {}", e);
+            let _ = fs::remove_file(&ir_file);
+            process::exit(e.exit_code());
         }
+        println!("  [+] 运行时模块: {}", runtime_file);
     }
 
     // 2. IR 优化 (如果启用)
     if options.opt_ir {
         println!("");
         println!("[2] IR 优化 ({})...", options.optimization);
-        match optimize_ir(&ir_file, &options.optimization) {
+        match optimize_ir(&ir_file, &options) {
             Ok(_) => {
                 println!("  [+] IR 优化完成");
             }
+            // IR 不合法时继续编译也只会在 clang 中失败，直接报告并保留 IR 文件
+            Err(e @ toolchain::ToolError::InvalidIr { .. }) => {
+                eprintln!("错误: {}", e);
+                process::exit(e.exit_code());
+            }
             Err(e) => {
                 eprintln!("  [W] IR 优化失败: {}", e);
                 eprintln!("  [I] 继续编译未优化的 IR");
@@ -517,22 +471,33 @@ fn main() {
         }
     }
 
-    // 3. IR → EXE (调用ir2exe)
+    if emit_kind == "llvm-ir" {
+        println!();
+        println!("[+] 编译完成!");
+        println!("生成: {}", exe_output);
+        return;
+    }
+
+    // 3. IR → EXE/目标文件 (调用ir2exe)
     println!("");
     let step_num = if options.opt_ir { "[3]" } else { "[2]" };
-    println!("{} IR → EXE 编译...", step_num);
+    if emit_kind == "obj" {
+        println!("{} IR → 目标文件编译...", step_num);
+    } else {
+        println!("{} IR → EXE 编译...", step_num);
+    }
 
     let current_exe = match env::current_exe() {
         Ok(path) => path,
         Err(_) => {
             eprintln!("无法获取当前执行路径");
-            process::exit(1);
+            process::exit(exit_codes::IO);
         }
     };
 
     let bin_dir = current_exe.parent().unwrap_or_else(|| {
         eprintln!("无法获取执行目录");
-        process::exit(1);
+        process::exit(exit_codes::IO);
     });
 
     // 尝试搜索 ir2exe 和 ir2exe.exe 两个文件名
@@ -549,7 +514,7 @@ fn main() {
                 eprintln!("  {:?}", path);
             }
             let _ = fs::remove_file(&ir_file);
-            process::exit(1);
+            process::exit(exit_codes::TOOL);
         }
     };
 
@@ -619,6 +584,12 @@ fn main() {
         ir2exe_args.push("--static".to_string());
     }
 
+    // 主线程栈大小
+    if let Some(size) = options.stack_size {
+        ir2exe_args.push("--stack-size".to_string());
+        ir2exe_args.push(size.to_string());
+    }
+
     // 代码生成选项
     if options.fno_exceptions {
         ir2exe_args.push("-fno-exceptions".to_string());
@@ -661,6 +632,25 @@ fn main() {
         ir2exe_args.push(options.extra_ldflags.join(" "));
     }
 
+    // 只编译为目标文件
+    if emit_kind == "obj" {
+        ir2exe_args.push("-c".to_string());
+    }
+
+    // 外部运行时模块
+    if options.external_runtime {
+        ir2exe_args.push("--runtime".to_string());
+        ir2exe_args.push(runtime_file.clone());
+    }
+
+    // 外部工具链
+    if let Some(ref llvm_path) = options.llvm_path {
+        ir2exe_args.push("--llvm-path".to_string());
+        ir2exe_args.push(llvm_path.clone());
+    }
+    ir2exe_args.push("--tool-timeout".to_string());
+    ir2exe_args.push(options.tool_timeout.as_secs().to_string());
+
     // 输入输出文件
     ir2exe_args.push(ir_file.clone());
     ir2exe_args.push(exe_output.clone());
@@ -668,31 +658,20 @@ fn main() {
     // 调试：显示实际调用的命令
     println!("  [D] 调用: {} {}", ir2exe_path.display(), ir2exe_args.join(" "));
     
-    // 调试：显示实际调用的命令
-    println!("  [D] 调用: {} {}", ir2exe_path.display(), ir2exe_args.join(" "));
-    
-    // 调用ir2exe
-    let output = process::Command::new(&ir2exe_path)
-        .args(&ir2exe_args)
-        .output()
-        .unwrap_or_else(|e| {
-            eprintln!("执行ir2exe失败: {}", e);
-            if !options.keep_ir {
-                let _ = fs::remove_file(&ir_file);
-            }
-            process::exit(1);
-        });
-
-    if !output.status.success() {
-        let error_msg = String::from_utf8_lossy(&output.stderr);
-        eprintln!("IR→EXE编译失败");
-        if !error_msg.is_empty() {
-            eprintln!("错误: {}", error_msg);
-        }
+    // 调用ir2exe（ir2exe 内部对 clang 也有超时，这里多留一些余量）
+    let mut ir2exe_cmd = process::Command::new(ir2exe_path);
+    ir2exe_cmd.args(&ir2exe_args);
+    let ir2exe_timeout = options.tool_timeout + Duration::from_secs(30);
+    if let Err(e) = toolchain::run_tool(&mut ir2exe_cmd, "ir2exe", ir2exe_timeout) {
+        eprintln!("IR→{}编译失败", if emit_kind == "obj" { "目标文件" } else { "EXE" });
+        eprintln!("错误: {}", e);
         if !options.keep_ir {
             let _ = fs::remove_file(&ir_file);
+            if options.external_runtime {
+                let _ = fs::remove_file(&runtime_file);
+            }
         }
-        process::exit(1);
+        process::exit(e.exit_code());
     }
 
     // 清理IR文件（如果不保留）
@@ -700,6 +679,9 @@ fn main() {
         if let Err(e) = fs::remove_file(&ir_file) {
             eprintln!("警告: 无法清理临时文件 {}: {}", ir_file, e);
         }
+        if options.external_runtime {
+            let _ = fs::remove_file(&runtime_file);
+        }
     } else {
         println!("");
         println!("[I] 保留 IR 文件: {}", ir_file);
@@ -1,64 +1,9 @@
 use std::env;
 use std::process;
 use std::path::{Path, PathBuf};
-
-/// 根据平台获取 clang 可执行文件名
-#[cfg(target_os = "windows")]
-fn get_clang_exe_name() -> &'static str {
-    "clang.exe"
-}
-
-#[cfg(target_os = "linux")]
-fn get_clang_exe_name() -> &'static str {
-    "clang-21"
-}
-
-#[cfg(not(any(target_os = "windows", target_os = "linux")))]
-fn get_clang_exe_name() -> &'static str {
-    "clang"
-}
-
-/// 根据平台获取 llvm-minimal 下的 clang 路径
-#[cfg(target_os = "windows")]
-fn get_bundled_clang_path(exe_dir: &Path) -> PathBuf {
-    exe_dir.join("llvm-minimal/bin/clang.exe")
-}
-
-#[cfg(target_os = "linux")]
-fn get_bundled_clang_path(exe_dir: &Path) -> PathBuf {
-    exe_dir.join("llvm-minimal/bin-linux/clang-21")
-}
-
-#[cfg(not(any(target_os = "windows", target_os = "linux")))]
-fn get_bundled_clang_path(exe_dir: &Path) -> PathBuf {
-    exe_dir.join("llvm-minimal/bin/clang")
-}
-
-/// 查找 clang 可执行文件
-/// 1. 首先尝试直接调用 "clang"（系统 PATH 中）
-/// 2. 如果失败，尝试查找编译器所在目录下的 llvm-minimal/bin/clang
-/// 3. 如果都找不到，返回错误
-fn find_clang() -> Result<PathBuf, String> {
-    // 1. 首先尝试系统 PATH 中的 clang
-    if let Ok(output) = process::Command::new("clang").arg("--version").output() {
-        if output.status.success() {
-            return Ok(PathBuf::from("clang"));
-        }
-    }
-    
-    // 2. 尝试编译器所在目录下的 llvm-minimal
-    if let Ok(exe_path) = env::current_exe() {
-        if let Some(exe_dir) = exe_path.parent() {
-            let bundled_clang = get_bundled_clang_path(exe_dir);
-            if bundled_clang.exists() {
-                return Ok(bundled_clang);
-            }
-        }
-    }
-    
-    // 3. 都找不到，返回错误
-    Err("找不到 clang 编译器。请确保 clang 已安装并在 PATH 中，或将 llvm-minimal 放在编译器同目录下。".to_string())
-}
+use std::time::Duration;
+use cavvy::toolchain::{self, DEFAULT_TOOL_TIMEOUT_SECS};
+use cavvy::error::exit_codes;
 
 const VERSION: &str = env!("IR2EXE_VERSION");
 
@@ -72,6 +17,7 @@ struct CompileOptions {
     target: String,               // --target
     static_link: bool,            // --static
     position_independent: bool,   // -fPIC/-fPIE
+    stack_size: Option<u64>,      // --stack-size <bytes>
     // LTO 选项
     lto: bool,                    // --lto, --lto=full
     lto_thin: bool,               // --lto=thin
@@ -93,6 +39,11 @@ struct CompileOptions {
     funroll_loops: bool,          // -funroll-loops
     fvectorize: bool,             // -fvectorize
     fslp_vectorize: bool,         // -fslp-vectorize
+    runtime_modules: Vec<String>, // --runtime <file.ll>
+    compile_only: bool,           // -c: 只生成目标文件，不链接
+    // 外部工具链
+    llvm_path: Option<String>,    // --llvm-path <dir>
+    tool_timeout: Duration,       // --tool-timeout <secs>
 }
 
 /// 根据当前操作系统自动选择默认目标平台
@@ -130,6 +81,7 @@ impl Default for CompileOptions {
             target: get_default_target(),
             static_link: false,
             position_independent: false,
+            stack_size: None,
             lto: false,
             lto_thin: false,
             march: None,
@@ -147,6 +99,10 @@ impl Default for CompileOptions {
             funroll_loops: false,
             fvectorize: false,
             fslp_vectorize: false,
+            runtime_modules: Vec::new(),
+            compile_only: false,
+            llvm_path: None,
+            tool_timeout: Duration::from_secs(DEFAULT_TOOL_TIMEOUT_SECS),
         }
     }
 }
@@ -207,18 +163,26 @@ fn print_usage() {
     println!("  --ldflags <flags>     传递额外的链接器标志");
     println!("  --cflags <flags>      传递额外的编译器标志");
     println!("  --static              静态链接");
+    println!("  --stack-size <size>   主线程栈大小 (字节，可带 K/M/G 后缀，如 8M)");
     println!("  -fPIC                 生成位置无关代码");
     println!("  --target <target>     指定目标平台 (默认: {})", default_target);
     println!("  --fno-exceptions      禁用异常处理");
     println!("  --fno-rtti            禁用运行时类型信息");
+    println!("  --runtime <file.ll>   链接外部运行时模块 (配合 cay-ir --external-runtime)");
+    println!("  -c, --compile-only    只编译为目标文件 (默认输出 <输入文件名>.o)，不链接");
+    println!("");
+    println!("Toolchain Options:");
+    println!("  --llvm-path <dir>     指定 LLVM 工具链目录 (包含 bin/clang，有 bin/opt 时先校验 IR)");
+    println!("  --tool-timeout <secs> clang 超时时间 (默认: {} 秒)", DEFAULT_TOOL_TIMEOUT_SECS);
     println!("");
     println!("Other Options:");
-    println!("  --version, -v         显示版本号");
+    println!("  --version, -v         显示版本号 (加 --verbose 显示详细信息)");
     println!("  --help, -h            显示帮助信息");
     println!("");
     println!("Examples:");
     println!("  ir2exe input.ll {}", output_ext);
     println!("  ir2exe -O3 --lto input.ll {}", output_ext);
+    println!("  ir2exe -c input.ll input.o");
     println!("  ir2exe -O3 --march=native --mtune=native input.ll {}", output_ext);
     println!("  ir2exe -O3 --mavx2 --fvectorize input.ll {}", output_ext);
     println!("  ir2exe --pgo-gen -O2 input.ll {}      # 编译分析版本", output_ext);
@@ -238,9 +202,16 @@ fn parse_args(args: &[String]) -> Result<(CompileOptions, String, String), Strin
 
         match arg.as_str() {
             "--version" | "-v" => {
-                println!("ir2exe v{}", VERSION);
+                if args.iter().any(|a| a == "--verbose") {
+                    print!("{}", cavvy::version::verbose_version_info("ir2exe", VERSION));
+                } else {
+                    println!("ir2exe v{}", VERSION);
+                }
                 process::exit(0);
             }
+            "--verbose" => {
+                // 与 --version 一起使用
+            }
             "--help" | "-h" => {
                 print_usage();
                 process::exit(0);
@@ -257,6 +228,9 @@ fn parse_args(args: &[String]) -> Result<(CompileOptions, String, String), Strin
             "-fPIC" | "-fpic" => {
                 options.position_independent = true;
             }
+            "-c" | "--compile-only" => {
+                options.compile_only = true;
+            }
             "--fno-exceptions" | "-fno-exceptions" => {
                 options.fno_exceptions = true;
             }
@@ -336,6 +310,34 @@ fn parse_args(args: &[String]) -> Result<(CompileOptions, String, String), Strin
                 }
                 options.pgo_use = Some(args[i].clone());
             }
+            "--runtime" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("--runtime 需要参数".to_string());
+                }
+                options.runtime_modules.push(args[i].clone());
+            }
+            "--llvm-path" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("--llvm-path 需要参数".to_string());
+                }
+                options.llvm_path = Some(args[i].clone());
+            }
+            "--tool-timeout" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("--tool-timeout 需要参数".to_string());
+                }
+                options.tool_timeout = toolchain::parse_timeout(&args[i])?;
+            }
+            "--stack-size" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("--stack-size 需要参数".to_string());
+                }
+                options.stack_size = Some(toolchain::parse_stack_size(&args[i])?);
+            }
             "--ldflags" => {
                 i += 1;
                 if i >= args.len() {
@@ -433,6 +435,10 @@ fn parse_args(args: &[String]) -> Result<(CompileOptions, String, String), Strin
     }
 
     let input_file = input_file.ok_or("需要指定输入文件")?;
+    if options.compile_only && !options.runtime_modules.is_empty() {
+        // 多个输入无法编译到同一个目标文件
+        return Err("-c 不能与 --runtime 一起使用，请分别编译运行时模块".to_string());
+    }
     let output_file = output_file.unwrap_or_else(|| {
         let stem = Path::new(&input_file)
             .file_stem()
@@ -440,7 +446,9 @@ fn parse_args(args: &[String]) -> Result<(CompileOptions, String, String), Strin
             .unwrap_or("output");
         
         // 根据目标平台选择扩展名
-        if options.target.contains("windows") || options.target.contains("mingw") {
+        if options.compile_only {
+            format!("{}.o", stem)
+        } else if options.target.contains("windows") || options.target.contains("mingw") {
             format!("{}.exe", stem)
         } else if options.target.contains("darwin") {
             // macOS 通常没有扩展名，或者使用 .app
@@ -462,7 +470,7 @@ fn main() {
         Err(e) => {
             eprintln!("错误: {}", e);
             print_usage();
-            process::exit(1);
+            process::exit(exit_codes::USAGE);
         }
     };
 
@@ -555,15 +563,30 @@ fn main() {
     }
     println!("");
 
-    let clang_exe = match find_clang() {
+    let clang_exe = match toolchain::find_clang(options.llvm_path.as_deref()) {
         Ok(path) => path,
         Err(e) => {
             eprintln!("错误: {}", e);
-            process::exit(1);
+            process::exit(e.exit_code());
         }
     };
 
-    println!("[I] 正在编译 IR → EXE...");
+    // 交给 clang 之前先校验 IR，不合法的 IR 在这里给出可读的错误；没有 opt 时跳过
+    if let Ok(opt_exe) = toolchain::find_opt(options.llvm_path.as_deref()) {
+        println!("[I] 正在校验 IR...");
+        for ir_file in std::iter::once(&input_file).chain(&options.runtime_modules) {
+            if let Err(e) = toolchain::verify_ir(&opt_exe, ir_file, options.tool_timeout) {
+                eprintln!("错误: {}", e);
+                process::exit(e.exit_code());
+            }
+        }
+    }
+
+    if options.compile_only {
+        println!("[I] 正在编译 IR → 目标文件...");
+    } else {
+        println!("[I] 正在编译 IR → EXE...");
+    }
 
     // 设置库路径 - 先获取可执行文件所在目录
     let exe_dir = env::current_exe()
@@ -588,10 +611,14 @@ fn main() {
     // 构建 clang 命令
     let mut cmd = process::Command::new(&clang_exe);
     cmd.arg(&input_file)
+        .args(&options.runtime_modules)
         .arg("-o").arg(&output_file)
         .arg("-target").arg(&options.target)
         .arg(&options.optimization)
         .arg("-Wno-override-module");
+    if options.compile_only {
+        cmd.arg("-c");
+    }
 
     // LTO 设置
     if options.lto {
@@ -650,11 +677,6 @@ fn main() {
         cmd.arg("-fPIC");
     }
 
-    // 静态链接
-    if options.static_link {
-        cmd.arg("-static");
-    }
-
     // 代码生成选项
     if options.fno_exceptions {
         cmd.arg("-fno-exceptions");
@@ -675,70 +697,89 @@ fn main() {
         cmd.arg("-fslp-vectorize");
     }
 
-    // 添加库路径
-    for lib_path in &lib_paths {
-        if lib_path.exists() {
-            cmd.arg("-L").arg(lib_path);
-        }
-    }
-
-    // 额外库路径
-    for path in &options.extra_lib_paths {
-        cmd.arg("-L").arg(path);
-    }
-
     // 额外 cflags
     for flag in &options.extra_cflags {
         cmd.arg(flag);
     }
 
-    // 使用 lld 链接器
-    cmd.arg("-fuse-ld=lld");
+    // 以下为链接选项，只生成目标文件时不需要
+    if !options.compile_only {
+        // 静态链接
+        if options.static_link {
+            cmd.arg("-static");
+        }
 
-    // 根据目标平台选择默认库
-    if options.target.contains("windows") || options.target.contains("mingw") {
-        // Windows 平台库
-        cmd.arg("-lkernel32")
-            .arg("-lmsvcrt")
-            .arg("-ladvapi32");
-    } else if options.target.contains("linux") {
-        // Linux 平台库
-        cmd.arg("-lc")
-            .arg("-lm")
-            .arg("-lpthread");
-    } else if options.target.contains("darwin") {
-        // macOS 平台库
-        cmd.arg("-lc")
-            .arg("-lm");
-    } else {
-        // 通用库
-        cmd.arg("-lc")
-            .arg("-lm");
-    }
+        // 添加库路径
+        for lib_path in &lib_paths {
+            if lib_path.exists() {
+                cmd.arg("-L").arg(lib_path);
+            }
+        }
 
-    // 额外库
-    for lib in &options.extra_libs {
-        cmd.arg(format!("-l{}", lib));
-    }
+        // 额外库路径
+        for path in &options.extra_lib_paths {
+            cmd.arg("-L").arg(path);
+        }
 
-    // 额外的链接器标志
-    for flag in &options.extra_ldflags {
-        cmd.arg(flag);
+        // 使用 lld 链接器
+        cmd.arg("-fuse-ld=lld");
+
+        // 原生运行时库：放在 C 库之前；只有程序引用了其中的函数（--external-runtime）
+        // 时才会被链接器拉入，内联运行时的程序不受影响
+        match toolchain::find_runtime_library() {
+            Some(runtime_lib) => {
+                cmd.arg(runtime_lib);
+            }
+            None if !options.runtime_modules.is_empty() => {
+                eprintln!("  [W] 找不到原生运行时库 ({})，外部运行时模块中声明的函数可能无法链接",
+                    toolchain::RUNTIME_LIBRARY_NAMES.join(" / "));
+            }
+            None => {}
+        }
+
+        // 根据目标平台选择默认库
+        if options.target.contains("windows") || options.target.contains("mingw") {
+            // Windows 平台库
+            cmd.arg("-lkernel32")
+                .arg("-lmsvcrt")
+                .arg("-ladvapi32");
+        } else if options.target.contains("linux") {
+            // Linux 平台库
+            cmd.arg("-lc")
+                .arg("-lm")
+                .arg("-lpthread");
+        } else if options.target.contains("darwin") {
+            // macOS 平台库
+            cmd.arg("-lc")
+                .arg("-lm");
+        } else {
+            // 通用库
+            cmd.arg("-lc")
+                .arg("-lm");
+        }
+
+        // 额外库
+        for lib in &options.extra_libs {
+            cmd.arg(format!("-l{}", lib));
+        }
+
+        // 主线程栈大小
+        if let Some(size) = options.stack_size {
+            cmd.arg(toolchain::stack_size_link_flag(&options.target, size));
+        }
+
+        // 额外的链接器标志
+        for flag in &options.extra_ldflags {
+            cmd.arg(flag);
+        }
     }
 
-    let output = cmd.output()
+    let output = toolchain::run_tool(&mut cmd, "clang", options.tool_timeout)
         .unwrap_or_else(|e| {
-            eprintln!("执行clang失败: {}", e);
-            process::exit(1);
+            eprintln!("编译失败: {}", e);
+            process::exit(e.exit_code());
         });
 
-    if !output.status.success() {
-        let error_msg = String::from_utf8_lossy(&output.stderr);
-        eprintln!("编译失败 (clang exit code: {})", output.status.code().unwrap_or(-1));
-        eprintln!("错误: {}", error_msg);
-        process::exit(1);
-    }
-
     if !output.stderr.is_empty() {
         let warn_msg = String::from_utf8_lossy(&output.stderr);
         println!("  [W] {}", warn_msg);
@@ -759,8 +800,10 @@ fn main() {
     }
 
     println!("");
-    println!("[I] 提示: 使用 './{}' 可直接运行并测速", output_file);
-    println!("");
+    if !options.compile_only {
+        println!("[I] 提示: 使用 './{}' 可直接运行并测速", output_file);
+        println!("");
+    }
     
     // 根据目标平台显示完成消息
     let mode_str = if options.target.contains("windows") || options.target.contains("mingw") {
@@ -0,0 +1,78 @@
+//! 编译缓存
+//!
+//! 以内容哈希为键缓存 `compile_file` 生成的 LLVM IR（`--cache-dir DIR`）：
+//! - 键由预处理后的入口源码、影响输出的编译选项、编译器版本以及导入的 .cavi 接口文件内容计算
+//! - `<键>.ll` 保存生成的 IR，`<键>.deps` 每行记录一个被导入模块：`<内容哈希> <路径>`
+//!
+//! 命中要求键相同且每个被导入模块预处理后的内容哈希都未变化，因此修改任何被导入的模块
+//! （或它 `#include` 的文件）都会重新编译。缓存只是加速手段：读写失败都按未命中处理，不影响编译结果。
+
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use crate::error::cayResult;
+use crate::modules::preprocess_module;
+use crate::CompilerOptions;
+
+/// 64 位 FNV-1a 哈希，结果不依赖平台和编译器版本，可以跨进程比较
+pub fn content_hash(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    bytes.iter().fold(OFFSET_BASIS, |hash, &byte| (hash ^ byte as u64).wrapping_mul(PRIME))
+}
+
+/// 磁盘上的编译缓存目录
+pub struct CompilationCache {
+    dir: PathBuf,
+}
+
+impl CompilationCache {
+    pub fn new(dir: &str) -> Self {
+        Self { dir: PathBuf::from(dir) }
+    }
+
+    /// 计算一次编译的缓存键
+    ///
+    /// `--jobs` 和缓存目录本身不影响生成的 IR，不参与计算。
+    pub fn key(source: &str, options: &CompilerOptions) -> cayResult<String> {
+        let mut input = String::new();
+        let _ = writeln!(input, "cavvy {}", crate::cavvy_version());
+        let keyed_options = CompilerOptions { jobs: 1, cache_dir: None, ..options.clone() };
+        let _ = writeln!(input, "{:?}", keyed_options);
+        for path in &options.interface_imports {
+            let bytes = std::fs::read(path)
+                .map_err(|e| crate::error::cayError::Io(format!("无法读取接口文件 '{}': {}", path, e)))?;
+            let _ = writeln!(input, "{} {:016x}", path, content_hash(&bytes));
+        }
+        input.push_str(source);
+        Ok(format!("{:016x}", content_hash(input.as_bytes())))
+    }
+
+    /// 查找缓存的 IR；被导入的模块有任何变化（或无法读取）时返回 `None`
+    pub fn lookup(&self, key: &str) -> Option<String> {
+        let deps = std::fs::read_to_string(self.path(key, "deps")).ok()?;
+        for line in deps.lines() {
+            let (hash, path) = line.split_once(' ')?;
+            let current = preprocess_module(path).ok()?;
+            if format!("{:016x}", content_hash(current.as_bytes())) != hash {
+                return None;
+            }
+        }
+        std::fs::read_to_string(self.path(key, "ll")).ok()
+    }
+
+    /// 保存生成的 IR 及其依赖的模块（模块路径和预处理后内容的哈希）
+    pub fn store(&self, key: &str, ir: &str, dependencies: &[(String, u64)]) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let mut deps = String::new();
+        for (path, hash) in dependencies {
+            let _ = writeln!(deps, "{:016x} {}", hash, path);
+        }
+        // 先写 IR 再写依赖清单：清单存在即表示 IR 已完整写入
+        std::fs::write(self.path(key, "ll"), ir)?;
+        std::fs::write(self.path(key, "deps"), deps)
+    }
+
+    fn path(&self, key: &str, extension: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", key, extension))
+    }
+}
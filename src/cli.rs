@@ -0,0 +1,330 @@
+//! cayc 命令行定义
+//!
+//! cayc 的所有选项都在 [`cayc_command`] 中声明一次：参数解析、`--help` 长帮助和
+//! bash/zsh/fish 补全脚本都由这份定义生成，新增选项时只需要在这里添加。
+//!
+//! 为兼容 GCC 风格的写法，`-O3`、`-L<path>`、`-l<lib>` 是带附加值的短选项，
+//! `-f<flag>` 和 `-m<option>=<value>` 分别是取值受限的 `-f`、`-m` 短选项。
+
+use std::ffi::OsStr;
+use std::io::Write;
+
+use clap::builder::{PossibleValue, TypedValueParser};
+use clap::error::ErrorKind;
+use clap::{Arg, ArgAction, Command};
+use clap_complete::Shell;
+
+use crate::toolchain::DEFAULT_TOOL_TIMEOUT_SECS;
+use crate::version::SUPPORTED_TARGETS;
+
+const VERSION: &str = env!("CAYC_VERSION");
+
+/// 优化级别 (`-O<level>`)
+pub const OPT_LEVELS: &[(&str, &str)] = &[
+    ("0", "不优化"),
+    ("1", "基本优化"),
+    ("2", "标准优化 (默认)"),
+    ("3", "激进优化"),
+    ("s", "优化代码大小"),
+    ("z", "进一步优化代码大小"),
+];
+
+/// `-f<flag>` 代码生成开关，以 `=` 结尾的项需要附加值
+pub const CODEGEN_FLAGS: &[(&str, &str)] = &[
+    ("PIC", "生成位置无关代码"),
+    ("pic", "同 -fPIC"),
+    ("no-exceptions", "禁用异常处理"),
+    ("no-rtti", "禁用运行时类型信息"),
+    ("omit-frame-pointer", "省略帧指针"),
+    ("unroll-loops", "循环展开"),
+    ("vectorize", "启用自动向量化"),
+    ("slp-vectorize", "启用 SLP 向量化"),
+    ("profile-generate", "PGO: 生成性能分析数据"),
+    ("cs-profile-generate", "PGO: 上下文敏感的性能分析"),
+    ("profile-use=", "PGO: 使用性能分析数据优化 (-fprofile-use=<path>)"),
+];
+
+/// `-m<option>=<value>` 目标 CPU 选项
+pub const MACHINE_OPTIONS: &[(&str, &str)] = &[
+    ("arch=", "目标 CPU 架构 (如 x86-64-v3, native)"),
+    ("tune=", "针对特定 CPU 优化 (如 intel, znver3)"),
+    ("cpu=", "针对 ARM/AArch64 CPU 优化"),
+    ("sse=", "SSE 版本 (1/2/3/4.1/4.2)"),
+    ("avx=", "AVX 版本 (avx/avx2/avx512f)"),
+];
+
+/// `--emit=<kind>` 输出内容：指定时只输出该内容，不生成可执行文件
+pub const EMIT_KINDS: &[(&str, &str)] = &[
+    ("ast", "语法树 (缩进文本)"),
+    ("ast-json", "语法树 (JSON)"),
+    ("llvm-ir", "LLVM IR (.ll)"),
+    ("obj", "目标文件 (.o)"),
+    ("exe", "可执行文件 (默认)"),
+];
+
+/// 带候选值的参数解析器
+///
+/// 候选值用于帮助信息和补全脚本。`strict` 为真时只接受候选值（以 `=` 结尾的候选值
+/// 按前缀匹配，如 `arch=` 接受 `arch=native`）；否则接受任意值，候选值仅作提示，
+/// 例如 `--target` 也可以是列表之外的目标三元组。
+#[derive(Clone)]
+pub struct CandidateValues {
+    /// (候选值, 说明)
+    values: Vec<(&'static str, &'static str)>,
+    strict: bool,
+}
+
+impl CandidateValues {
+    pub fn strict(values: &[(&'static str, &'static str)]) -> Self {
+        CandidateValues { values: values.to_vec(), strict: true }
+    }
+
+    pub fn suggested(values: &[(&'static str, &'static str)]) -> Self {
+        CandidateValues { values: values.to_vec(), strict: false }
+    }
+
+    fn accepts(&self, value: &str) -> bool {
+        !self.strict || self.values.iter().any(|(candidate, _)| match candidate.strip_suffix('=') {
+            Some(prefix) => value.strip_prefix(prefix).is_some_and(|rest| rest.len() > 1 && rest.starts_with('=')),
+            None => value == *candidate,
+        })
+    }
+}
+
+impl TypedValueParser for CandidateValues {
+    type Value = String;
+
+    fn parse_ref(&self, cmd: &Command, arg: Option<&Arg>, value: &OsStr) -> Result<String, clap::Error> {
+        let Some(value) = value.to_str() else {
+            return Err(clap::Error::new(ErrorKind::InvalidUtf8).with_cmd(cmd));
+        };
+        if self.accepts(value) {
+            return Ok(value.to_string());
+        }
+        let arg = arg.map(|a| a.to_string()).unwrap_or_default();
+        let expected: Vec<&str> = self.values.iter().map(|(v, _)| *v).collect();
+        Err(cmd.clone().error(
+            ErrorKind::InvalidValue,
+            format!("{} 的值 '{}' 无效，可选值: {}", arg, value, expected.join(", ")),
+        ))
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = PossibleValue> + '_>> {
+        Some(Box::new(self.values.iter().map(|(value, help)| PossibleValue::new(*value).help(*help))))
+    }
+}
+
+/// 目标平台候选值：(目标三元组, 平台名称)
+fn target_candidates() -> Vec<(&'static str, &'static str)> {
+    SUPPORTED_TARGETS.iter().map(|(name, triple)| (*triple, *name)).collect()
+}
+
+fn flag(id: &'static str) -> Arg {
+    Arg::new(id).action(ArgAction::SetTrue)
+}
+
+/// cayc 的命令行定义
+pub fn cayc_command() -> Command {
+    Command::new("cayc")
+        .version(VERSION)
+        .about("Cavvy 一站式编译器: Cavvy 源文件 → 可执行文件")
+        .long_about(
+            "Cavvy 一站式编译器: Cavvy 源文件 → LLVM IR → 可执行文件。\n\n\
+             先把源文件编译为 LLVM IR，再调用同目录下的 ir2exe 和 LLVM 工具链生成可执行文件。\
+             优化、目标 CPU 和链接选项与 GCC/Clang 的写法一致，会原样传给 ir2exe。",
+        )
+        .disable_version_flag(true)
+        .after_help("使用 --help 查看完整说明和示例")
+        .after_long_help(
+            "Examples:\n  \
+             cayc hello.cay\n  \
+             cayc -O3 hello.cay hello.exe\n  \
+             cayc --opt-ir -O3 --lto=full hello.cay\n  \
+             cayc -O3 -march=native -mtune=native -fvectorize hello.cay\n  \
+             cayc --static -O2 -L./libs -lmylib app.cay app.exe\n  \
+             cayc --stack-size 64M --stack-guard=100000 recursion.cay\n  \
+             cayc --max-steps 1000000 --max-heap 64M --stack-guard snippet.cay\n  \
+             cayc --emit=ast hello.cay\n  \
+             cayc --emit=obj -o hello.o hello.cay\n  \
+             cayc --completions bash > /etc/bash_completion.d/cayc",
+        )
+        .arg(Arg::new("input")
+            .value_name("SOURCE")
+            .help("Cavvy 源文件 (.cay)")
+            .value_hint(clap::ValueHint::FilePath)
+            .required_unless_present_any(["version", "completions"]))
+        .arg(Arg::new("output")
+            .value_name("OUTPUT")
+            .help("输出文件 (默认: 源文件名，Windows 目标加 .exe)")
+            .value_hint(clap::ValueHint::FilePath))
+        .arg(Arg::new("output-file")
+            .short('o')
+            .long("output")
+            .value_name("FILE")
+            .help("输出文件，与位置参数 OUTPUT 相同")
+            .value_hint(clap::ValueHint::FilePath)
+            .conflicts_with("output"))
+        .arg(Arg::new("emit")
+            .long("emit")
+            .value_name("KIND")
+            .help("输出的内容: ast、ast-json、llvm-ir、obj、exe (默认: exe)")
+            .long_help("选择输出的内容：\n  \
+                        ast       缩进文本形式的语法树 (解析后、语义分析前)\n  \
+                        ast-json  JSON 形式的语法树\n  \
+                        llvm-ir   LLVM IR 文本 (默认输出 <源文件名>.ll)\n  \
+                        obj       目标文件，包含内置运行时，可交给链接器与其他目标文件一起链接 (默认输出 <源文件名>.o)\n  \
+                        exe       链接后的可执行文件 (默认)\n\
+                        ast 和 ast-json 未指定输出文件时输出到标准输出")
+            .value_parser(CandidateValues::strict(EMIT_KINDS)))
+        // 优化
+        .next_help_heading("Optimization Options")
+        .arg(Arg::new("opt-level")
+            .short('O')
+            .value_name("LEVEL")
+            .help("优化级别 (默认: 2)")
+            .long_help("优化级别，写作 -O0、-O1、-O2、-O3、-Os 或 -Oz (默认: -O2)。\n\
+                        -O1 及以上在生成 IR 前进行源码级常量折叠、常量传播和死分支消除")
+            .value_parser(CandidateValues::strict(OPT_LEVELS))
+            .hide_possible_values(true))
+        .arg(flag("opt-ir")
+            .long("opt-ir")
+            .help("启用 IR 阶段优化 (使用 LLVM 优化 IR)")
+            .long_help("在链接前先用 opt 按当前优化级别优化生成的 IR（找不到 opt 时使用 clang）。\n\
+                        IR 没有通过 LLVM 校验时报告错误并停止；其他原因导致优化失败时继续编译未优化的 IR"))
+        .arg(flag("verify-ir")
+            .long("verify-ir")
+            .help("自检生成的 IR")
+            .long_help("生成每个函数后检查 IR 中的值是否先定义后使用、操作数类型是否与指令一致。\n\
+                        发现问题时报告内部错误和对应的 Cavvy 源码行，不需要 LLVM 工具链"))
+        .arg(Arg::new("lto")
+            .long("lto")
+            .value_name("TYPE")
+            .help("链接时优化 (full/thin)")
+            .num_args(0..=1)
+            .require_equals(true)
+            .default_missing_value("full")
+            .value_parser(["full", "thin"]))
+        .arg(Arg::new("machine")
+            .short('m')
+            .value_name("OPTION=VALUE")
+            .help("目标 CPU 选项: -march=, -mtune=, -mcpu=, -msse=, -mavx=")
+            .long_help("目标 CPU 选项，写作 -march=<arch>、-mtune=<cpu>、-mcpu=<cpu>、-msse=<ver> 或 -mavx=<ver>")
+            .action(ArgAction::Append)
+            .value_parser(CandidateValues::strict(MACHINE_OPTIONS)))
+        .arg(flag("mneon")
+            .long("mneon")
+            .help("启用 ARM NEON"))
+        .arg(Arg::new("codegen-flag")
+            .short('f')
+            .value_name("FLAG")
+            .help("代码生成与 PGO 开关，如 -fPIC、-funroll-loops、-fprofile-use=<path>")
+            .long_help("代码生成与 PGO (Profile Guided Optimization) 开关，写作 -f<flag>")
+            .action(ArgAction::Append)
+            .value_parser(CandidateValues::strict(CODEGEN_FLAGS)))
+        // 代码生成
+        .next_help_heading("Code Generation")
+        .arg(flag("debug")
+            .short('g')
+            .help("生成调试信息"))
+        .arg(flag("keep-ir")
+            .long("keep-ir")
+            .help("保留中间 IR 文件 (.ll)"))
+        .arg(flag("external-runtime")
+            .long("external-runtime")
+            .help("运行时作为独立模块链接，而非内联到程序 IR"))
+        .arg(flag("no-main")
+            .long("no-main")
+            .help("库模式: 不要求 main，也不生成程序入口 (配合 --emit=obj 或 --emit=llvm-ir)"))
+        .arg(Arg::new("stack-size")
+            .long("stack-size")
+            .value_name("SIZE")
+            .help("主线程栈大小 (字节，可带 K/M/G 后缀，如 8M)"))
+        .arg(Arg::new("stack-guard")
+            .long("stack-guard")
+            .value_name("N")
+            .help(format!("插入递归深度保护，超过 N 层调用时报告 StackOverflow (默认: {})", crate::DEFAULT_STACK_GUARD_DEPTH))
+            .num_args(0..=1)
+            .require_equals(true))
+        .arg(Arg::new("max-steps")
+            .long("max-steps")
+            .value_name("N")
+            .help("执行限制: 函数调用和循环迭代总数超过 N 时报告 Execution limit exceeded 并终止"))
+        .arg(Arg::new("max-heap")
+            .long("max-heap")
+            .value_name("SIZE")
+            .help("执行限制: 累计堆分配超过 SIZE 字节时报告 Execution limit exceeded 并终止 (可带 K/M/G 后缀)"))
+        .arg(Arg::new("jobs")
+            .short('j')
+            .long("jobs")
+            .value_name("N")
+            .help("并行类型检查和生成各个类使用的线程数 (默认: 1)"))
+        .arg(Arg::new("cache-dir")
+            .long("cache-dir")
+            .value_name("DIR")
+            .help("编译缓存目录，源文件和导入的模块未变化时复用上次生成的 IR")
+            .value_hint(clap::ValueHint::DirPath))
+        // 链接
+        .next_help_heading("Linking")
+        .arg(Arg::new("target")
+            .long("target")
+            .value_name("TRIPLE")
+            .help("目标平台三元组 (默认: 当前系统)")
+            .value_parser(CandidateValues::suggested(&target_candidates())))
+        .arg(Arg::new("lib-path")
+            .short('L')
+            .value_name("PATH")
+            .help("添加库搜索路径")
+            .action(ArgAction::Append)
+            .value_hint(clap::ValueHint::DirPath))
+        .arg(Arg::new("lib")
+            .short('l')
+            .value_name("LIB")
+            .help("链接额外的库")
+            .action(ArgAction::Append))
+        .arg(Arg::new("ldflags")
+            .long("ldflags")
+            .value_name("FLAGS")
+            .help("传递额外的链接器标志 (以空格分隔)")
+            .action(ArgAction::Append)
+            .allow_hyphen_values(true))
+        .arg(Arg::new("cflags")
+            .long("cflags")
+            .value_name("FLAGS")
+            .help("传递额外的编译器标志 (以空格分隔)")
+            .action(ArgAction::Append)
+            .allow_hyphen_values(true))
+        .arg(flag("static")
+            .long("static")
+            .help("静态链接"))
+        // 工具链
+        .next_help_heading("Toolchain Options")
+        .arg(Arg::new("llvm-path")
+            .long("llvm-path")
+            .value_name("DIR")
+            .help("指定 LLVM 工具链目录 (包含 bin/clang)")
+            .value_hint(clap::ValueHint::DirPath))
+        .arg(Arg::new("tool-timeout")
+            .long("tool-timeout")
+            .value_name("SECS")
+            .help(format!("外部工具超时时间 (默认: {} 秒)", DEFAULT_TOOL_TIMEOUT_SECS)))
+        // 其他
+        .next_help_heading("Other Options")
+        .arg(flag("version")
+            .short('v')
+            .long("version")
+            .help("显示版本号 (加 --verbose 显示详细信息)"))
+        .arg(flag("verbose")
+            .long("verbose")
+            .help("与 --version 一起使用，显示详细版本信息"))
+        .arg(Arg::new("completions")
+            .long("completions")
+            .value_name("SHELL")
+            .help("输出 shell 补全脚本 (bash/zsh/fish/elvish/powershell)")
+            .value_parser(clap::value_parser!(Shell)))
+}
+
+/// 输出 cayc 的补全脚本
+pub fn write_completions(shell: Shell, out: &mut dyn Write) {
+    let mut command = cayc_command();
+    clap_complete::generate(shell, &mut command, "cayc", out);
+}
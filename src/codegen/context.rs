@@ -1,13 +1,29 @@
 //! IR生成上下文和状态管理
-use std::collections::HashMap;
-use crate::types::TypeRegistry;
+use std::collections::{HashMap, HashSet};
+use crate::ast::{NodeId, NodeMap};
+use crate::types::{Type, FunctionType, MethodInfo, ParameterInfo, TypeRegistry};
 use crate::codegen::platform::PlatformConfig;
-
-/// 循环上下文，用于支持 break/continue
+use crate::codegen::ir::{FunctionBody, Instruction};
+use crate::codegen::verify::{Signature, verify_values};
+use crate::codegen::mangle;
+use crate::error::{cayResult, codegen_error};
+use crate::target::DataLayout;
+use crate::semantic::FormatPiece;
+
+/// 实例方法中保存 this 指针的内部变量名
+///
+/// 包含 `.`，不可能与用户声明的变量同名。
+pub const THIS_PTR_VAR: &str = "this.ptr";
+
+/// 跳转上下文，用于支持 break/continue
+///
+/// 循环、switch 和标签语句各对应一个上下文。
 #[derive(Debug, Clone)]
 pub struct LoopContext {
-    pub cond_label: String,  // continue 跳转的目标（条件检查）
+    pub cond_label: Option<String>,  // continue 跳转的目标（条件检查），switch 和非循环的标签语句没有
     pub end_label: String,   // break 跳转的目标（循环结束）
+    pub label: Option<String>,  // 标记该语句的标签
+    pub unlabeled: bool,  // 是否为不带标签的 break/continue 的目标
 }
 
 /// 静态字段信息
@@ -28,6 +44,7 @@ pub struct InstanceFieldInfo {
     pub name: String,           // 字段名
     pub llvm_type: String,      // LLVM 类型
     pub field_type: crate::types::Type,  // 原始类型
+    pub index: usize,           // 在类结构体类型中的成员索引
     pub offset: usize,          // 在对象中的偏移量（字节）
     pub size: usize,            // 大小（字节）
 }
@@ -36,8 +53,12 @@ pub struct InstanceFieldInfo {
 #[derive(Debug, Clone)]
 pub struct ClassLayoutInfo {
     pub class_name: String,
+    pub struct_type: String,    // LLVM 结构体类型名，如 %class.Point
+    pub parent: Option<String>, // 父类名（结构体以父类的全部成员开头）
+    pub members: Vec<String>,   // 结构体成员的 LLVM 类型（前两个成员为 type_id 和虚表指针）
     pub total_size: usize,      // 对象总大小（字节）
-    pub fields: HashMap<String, InstanceFieldInfo>,  // 字段名 -> 字段信息
+    pub align: usize,           // 结构体对齐（成员的最大对齐）
+    pub fields: HashMap<String, InstanceFieldInfo>,  // 字段名 -> 字段信息（包含继承的字段）
 }
 
 impl ClassLayoutInfo {
@@ -47,15 +68,25 @@ impl ClassLayoutInfo {
     }
 }
 
+/// 虚表槽位
+#[derive(Debug, Clone)]
+pub struct VtableSlot {
+    pub key: String,                // 方法名 + 参数签名（不含类名）
+    pub fn_name: Option<String>,    // 槽位中的函数（抽象方法为 None）
+    pub fn_type: String,            // 函数指针类型，如 i8* (i8*, i32)*
+}
+
 /// 变量作用域信息
 #[derive(Debug, Clone)]
 pub struct VarScope {
     pub name: String,           // 原始变量名
     pub llvm_name: String,      // LLVM 中的唯一名称（带作用域后缀）
     pub var_type: String,       // 变量类型
+    pub lang_type: Type,        // 变量在源语言中的类型
 }
 
 /// 作用域栈管理
+#[derive(Clone)]
 pub struct ScopeManager {
     scopes: Vec<HashMap<String, VarScope>>,  // 作用域栈
     scope_counter: usize,                     // 作用域计数器（用于生成唯一名称）
@@ -83,7 +114,7 @@ impl ScopeManager {
     }
 
     /// 声明变量（在当前作用域）
-    pub fn declare_var(&mut self, name: &str, var_type: &str) -> String {
+    pub fn declare_var(&mut self, name: &str, var_type: &str, lang_type: &Type) -> String {
         let llvm_name = if self.scopes.len() == 1 {
             // 全局作用域，使用原始名称
             name.to_string()
@@ -96,6 +127,7 @@ impl ScopeManager {
             name: name.to_string(),
             llvm_name: llvm_name.clone(),
             var_type: var_type.to_string(),
+            lang_type: lang_type.clone(),
         };
 
         if let Some(scope) = self.scopes.last_mut() {
@@ -120,6 +152,11 @@ impl ScopeManager {
         self.lookup_var(name).map(|v| v.var_type.clone())
     }
 
+    /// 获取变量在源语言中的类型
+    pub fn get_var_lang_type(&self, name: &str) -> Option<Type> {
+        self.lookup_var(name).map(|v| v.lang_type.clone())
+    }
+
     /// 获取变量的 LLVM 名称
     pub fn get_llvm_name(&self, name: &str) -> Option<String> {
         self.lookup_var(name).map(|v| v.llvm_name.clone())
@@ -148,6 +185,7 @@ pub struct TypeIdInfo {
 }
 
 /// IR生成器核心上下文
+#[derive(Clone)]
 pub struct IRGenerator {
     pub output: String,
     pub indent: usize,
@@ -161,7 +199,9 @@ pub struct IRGenerator {
     pub var_types: HashMap<String, String>,
     pub var_class_map: HashMap<String, String>,
     pub loop_stack: Vec<LoopContext>,
+    pub pending_label: Option<String>,  // 标签语句标记的循环或 switch 尚未取走的标签
     pub target_triple: String,
+    pub data_layout: DataLayout,  // 目标数据布局，决定类型大小和对齐
     pub static_fields: Vec<StaticFieldInfo>,
     pub static_field_map: HashMap<String, StaticFieldInfo>,
     pub type_registry: Option<TypeRegistry>,
@@ -172,7 +212,33 @@ pub struct IRGenerator {
     pub type_id_map: HashMap<String, TypeIdInfo>,
     pub type_id_counter: usize,
     pub class_layouts: HashMap<String, ClassLayoutInfo>,  // 类实例布局信息
+    pub vtables: HashMap<String, Vec<VtableSlot>>,  // 类名 -> 虚表槽位（包含继承的槽位）
     pub platform_config: Option<PlatformConfig>, 
+    pub inferred_var_types: NodeMap<Type>,  // 语义分析推断出的 auto/var/let 变量类型
+    pub null_safe_types: NodeMap<Type>,  // 语义分析记录的 ?. 对象类型和 ?? 结果类型
+    pub ternary_types: NodeMap<Type>,  // 语义分析记录的三元运算符结果类型
+    pub field_access_classes: NodeMap<String>,  // 语义分析记录的实例字段访问和实例方法调用的对象类名
+    pub stack_allocated_objects: HashSet<NodeId>,  // 逃逸分析确定可以栈上分配的 new 表达式
+    pub string_comparisons: HashSet<NodeId>,  // 语义分析确定两侧都是 String 的 ==/!= 表达式
+    pub print_arg_types: NodeMap<Type>,  // 语义分析记录的 print/println 参数类型
+    pub string_conversions: NodeMap<(Type, Type)>,  // 语义分析记录的字符串拼接操作数类型
+    pub lambda_captures: NodeMap<Vec<String>>,  // 语义分析计算的 lambda 捕获集合
+    pub function_types: NodeMap<FunctionType>,  // 语义分析记录的 lambda/方法引用/闭包调用的函数类型
+    pub method_refs: NodeMap<MethodInfo>,  // 语义分析为方法引用选中的目标方法
+    pub debug_prints: NodeMap<(String, Type)>,  // 语义分析记录的 debugPrint 参数文本和类型
+    pub format_calls: NodeMap<(Vec<FormatPiece>, Vec<Type>)>,  // 语义分析解析的格式字符串和参数类型
+    pub for_each_types: NodeMap<(Type, Type)>,  // 语义分析记录的 for-each 遍历对象类型和循环变量类型
+    pub call_overloads: NodeMap<Vec<ParameterInfo>>,  // 语义分析为方法调用选中的重载的参数
+    pub cast_types: NodeMap<Type>,  // 语义分析记录的类型转换操作数类型
+    pub array_element_types: NodeMap<Type>,  // 语义分析记录的数组下标访问的元素类型
+    pub expr_types: NodeMap<Type>,  // 语义分析记录的表达式静态类型
+    pub function_body: Option<FunctionBody>,  // 正在生成的函数体，函数结束时校验并序列化到 code
+    pub global_ptr_cache: HashMap<(String, usize), String>,  // 当前函数中 (全局数组, 下标) -> 已计算的元素指针
+    pub stack_guard_active: bool,  // 当前函数入口处已增加调用深度计数，返回前需要减回
+    pub jobs: usize,  // 代码生成使用的线程数，大于 1 时各个类并行生成
+    pub string_namespace: Option<usize>,  // 并行生成时本副本新建字符串常量的命名空间
+    pub duplicate_strings: Vec<(String, String)>,  // 合并并行结果时内容重复、名称不同的字符串常量
+    pub uses_reflection: bool,  // 生成的代码引用了反射元数据表，模块中需要输出类元数据
 }
 
 impl IRGenerator {
@@ -194,6 +260,8 @@ impl IRGenerator {
             var_types: HashMap::new(),
             var_class_map: HashMap::new(),
             loop_stack: Vec::new(),
+            pending_label: None,
+            data_layout: DataLayout::for_triple(&target_triple),
             target_triple,
             static_fields: Vec::new(),
             static_field_map: HashMap::new(),
@@ -205,7 +273,33 @@ impl IRGenerator {
             type_id_map: HashMap::new(),
             type_id_counter: 0,
             class_layouts: HashMap::new(),
+            vtables: HashMap::new(),
             platform_config: None,
+            inferred_var_types: NodeMap::new(),
+            null_safe_types: NodeMap::new(),
+            ternary_types: NodeMap::new(),
+            field_access_classes: NodeMap::new(),
+            stack_allocated_objects: HashSet::new(),
+            string_comparisons: HashSet::new(),
+            print_arg_types: NodeMap::new(),
+            string_conversions: NodeMap::new(),
+            lambda_captures: NodeMap::new(),
+            function_types: NodeMap::new(),
+            method_refs: NodeMap::new(),
+            debug_prints: NodeMap::new(),
+            format_calls: NodeMap::new(),
+            for_each_types: NodeMap::new(),
+            call_overloads: NodeMap::new(),
+            cast_types: NodeMap::new(),
+            array_element_types: NodeMap::new(),
+            expr_types: NodeMap::new(),
+            function_body: None,
+            global_ptr_cache: HashMap::new(),
+            stack_guard_active: false,
+            jobs: 1,
+            string_namespace: None,
+            duplicate_strings: Vec::new(),
+            uses_reflection: false,
         }
     }
 
@@ -214,6 +308,90 @@ impl IRGenerator {
         self.type_registry = Some(registry);
     }
 
+    /// 设置语义分析推断出的变量类型（以变量声明的 NodeId 为键）
+    pub fn set_inferred_var_types(&mut self, types: NodeMap<Type>) {
+        self.inferred_var_types = types;
+    }
+
+    /// 设置语义分析记录的空值安全运算符类型（`?.` 的对象类型、`??` 的结果类型）
+    pub fn set_null_safe_types(&mut self, types: NodeMap<Type>) {
+        self.null_safe_types = types;
+    }
+
+    /// 设置语义分析记录的三元运算符结果类型
+    pub fn set_ternary_types(&mut self, types: NodeMap<Type>) {
+        self.ternary_types = types;
+    }
+
+    /// 设置语义分析记录的实例字段访问的对象类名（以成员访问节点的 NodeId 为键）
+    pub fn set_field_access_classes(&mut self, classes: NodeMap<String>) {
+        self.field_access_classes = classes;
+    }
+
+    /// 设置语义分析记录的字符串相等比较（以二元表达式的 NodeId 为键）
+    pub fn set_string_comparisons(&mut self, comparisons: HashSet<NodeId>) {
+        self.string_comparisons = comparisons;
+    }
+
+    /// 设置语义分析记录的 print/println 参数类型（以调用节点的 NodeId 为键）
+    pub fn set_print_arg_types(&mut self, types: NodeMap<Type>) {
+        self.print_arg_types = types;
+    }
+
+    /// 设置语义分析记录的字符串拼接操作数类型（以二元表达式的 NodeId 为键）
+    pub fn set_string_conversions(&mut self, conversions: NodeMap<(Type, Type)>) {
+        self.string_conversions = conversions;
+    }
+
+    /// 设置语义分析计算的 lambda 捕获集合（以 lambda 节点的 NodeId 为键）
+    pub fn set_lambda_captures(&mut self, captures: NodeMap<Vec<String>>) {
+        self.lambda_captures = captures;
+    }
+
+    /// 设置语义分析记录的函数类型（lambda、方法引用和闭包调用节点的 NodeId 为键）
+    pub fn set_function_types(&mut self, types: NodeMap<FunctionType>) {
+        self.function_types = types;
+    }
+
+    /// 设置语义分析为方法引用选中的目标方法（以方法引用节点的 NodeId 为键）
+    pub fn set_method_refs(&mut self, methods: NodeMap<MethodInfo>) {
+        self.method_refs = methods;
+    }
+
+    /// 设置语义分析记录的 debugPrint 参数文本和静态类型（以调用节点的 NodeId 为键）
+    pub fn set_debug_prints(&mut self, prints: NodeMap<(String, Type)>) {
+        self.debug_prints = prints;
+    }
+
+    /// 设置语义分析解析的格式字符串和参数的静态类型（以调用节点的 NodeId 为键）
+    pub fn set_format_calls(&mut self, calls: NodeMap<(Vec<FormatPiece>, Vec<Type>)>) {
+        self.format_calls = calls;
+    }
+
+    pub fn set_for_each_types(&mut self, types: NodeMap<(Type, Type)>) {
+        self.for_each_types = types;
+    }
+
+    /// 设置语义分析为方法调用选中的重载的参数（以调用节点的 NodeId 为键）
+    pub fn set_call_overloads(&mut self, overloads: NodeMap<Vec<ParameterInfo>>) {
+        self.call_overloads = overloads;
+    }
+
+    /// 设置语义分析记录的类型转换操作数的静态类型（以转换表达式的 NodeId 为键）
+    pub fn set_cast_types(&mut self, types: NodeMap<Type>) {
+        self.cast_types = types;
+    }
+
+    /// 设置语义分析记录的数组下标访问的元素类型（以访问表达式的 NodeId 为键）
+    pub fn set_array_element_types(&mut self, types: NodeMap<Type>) {
+        self.array_element_types = types;
+    }
+
+    /// 设置语义分析记录的表达式静态类型（以表达式的 NodeId 为键）
+    pub fn set_expr_types(&mut self, types: NodeMap<Type>) {
+        self.expr_types = types;
+    }
+
     /// 检查是否是 Windows 目标平台
     pub fn is_windows_target(&self) -> bool {
         if let Some(config) = &self.platform_config {
@@ -233,8 +411,21 @@ impl IRGenerator {
         }
     }
 
-    /// 发射一行代码到当前代码缓冲区
+    /// 发射一行代码
+    ///
+    /// 函数体中的代码加入当前函数的基本块（标签行开始新块，其余为指令），
+    /// 函数体外（模块级定义、`define` 行）直接写入代码缓冲区。
     pub fn emit_line(&mut self, line: &str) {
+        if let Some(body) = self.function_body.as_mut() {
+            body.location = crate::ice::current_context().location;
+            for piece in line.split('\n').map(str::trim).filter(|piece| !piece.is_empty()) {
+                match piece.strip_suffix(':') {
+                    Some(label) if !piece.starts_with(';') && !label.contains(char::is_whitespace) => body.start_block(label),
+                    _ => body.push(Instruction::parse(piece)),
+                }
+            }
+            return;
+        }
         if !line.is_empty() {
             self.code.push_str(&"  ".repeat(self.indent));
         }
@@ -242,6 +433,110 @@ impl IRGenerator {
         self.code.push('\n');
     }
 
+    /// 向当前函数体追加结构化指令；不在函数体中时按文本发射
+    pub fn emit_instruction(&mut self, inst: Instruction) {
+        match self.function_body.as_mut() {
+            Some(body) => {
+                body.location = crate::ice::current_context().location;
+                body.push(inst);
+            }
+            None => self.emit_line(&format!("  {}", inst)),
+        }
+    }
+
+    /// 当前基本块是否已经以终止指令结束
+    pub fn current_block_terminated(&self) -> bool {
+        self.function_body.as_ref().is_some_and(FunctionBody::is_terminated)
+    }
+
+    /// 开始生成函数体（从入口块开始）
+    pub fn emit_entry_label(&mut self) {
+        self.function_body = Some(FunctionBody::new());
+        self.global_ptr_cache.clear();
+    }
+
+    /// 在当前函数的入口块中发射 alloca
+    ///
+    /// 循环体中的栈分配如果原地 alloca，每次迭代都会增长栈，因此统一放到入口块开头。
+    pub fn emit_entry_alloca(&mut self, line: &str) {
+        match self.function_body.as_mut() {
+            Some(body) => body.entry_prologue.push(Instruction::parse(line)),
+            None => self.emit_line(line),
+        }
+    }
+
+    /// 结束当前函数体：补齐未终止的基本块，校验后序列化到代码缓冲区
+    ///
+    /// 校验失败说明代码生成器本身有缺陷，报告为内部错误而不是交给 llc 报出难以理解的错误。
+    /// `--verify-ir` 时还检查值的定义、使用和类型，并指出生成出错指令的源码位置。
+    pub fn finish_function_body(&mut self) -> cayResult<()> {
+        self.global_ptr_cache.clear();
+        let Some(mut body) = self.function_body.take() else {
+            return Ok(());
+        };
+        body.finish();
+        body.verify().map_err(|message| {
+            codegen_error(format!("internal IR error in '{}': {}", self.current_function, message))
+        })?;
+        if self.platform_config.as_ref().is_some_and(|config| config.verify_ir) {
+            // 函数头已经写入代码缓冲区，是其中最后一个 define 行
+            let header = self.code.rfind("define ").map(|start| self.code[start..].lines().next().unwrap_or(""));
+            let signature = header.and_then(Signature::parse_define).unwrap_or_default();
+            verify_values(&signature, &body).map_err(|issue| {
+                let at = issue.location.map(|loc| format!(" at line {}", loc)).unwrap_or_default();
+                codegen_error(format!("internal IR error in '{}'{}: {}", self.current_function, at, issue.message))
+            })?;
+        }
+        body.write_to(&mut self.code);
+        Ok(())
+    }
+
+    /// 递归深度保护的最大调用深度（未启用时为 `None`）
+    pub fn stack_guard_depth(&self) -> Option<u32> {
+        self.platform_config.as_ref().and_then(|c| c.stack_guard_depth)
+    }
+
+    /// 在函数入口增加调用深度计数，超过上限时报告 StackOverflow 并退出
+    ///
+    /// 未启用 `--stack-guard` 时不生成任何代码。
+    pub fn emit_stack_guard_enter(&mut self, method_name: &str) {
+        let Some(max_depth) = self.stack_guard_depth() else {
+            self.stack_guard_active = false;
+            return;
+        };
+        self.stack_guard_active = true;
+
+        let depth = self.new_temp();
+        let next_depth = self.new_temp();
+        let overflow = self.new_temp();
+        let overflow_label = self.new_label("stack.overflow");
+        let ok_label = self.new_label("stack.ok");
+        self.emit_load_into(&depth, "i32", "@.cay_call_depth");
+        self.emit_line(&format!("  {} = add i32 {}, 1", next_depth, depth));
+        self.emit_store("i32", &next_depth, "@.cay_call_depth");
+        self.emit_line(&format!("  {} = icmp sgt i32 {}, {}", overflow, next_depth, max_depth));
+        self.emit_cond_br(&overflow, &overflow_label, &ok_label);
+
+        self.emit_label(&overflow_label);
+        let message = format!("StackOverflow in method {} (call depth exceeded {})\n", method_name, max_depth);
+        let message_ptr = self.get_or_create_string_constant(&message);
+        self.emit_runtime_error(&message_ptr, &[]);
+
+        self.emit_label(&ok_label);
+    }
+
+    /// 在函数返回前减少调用深度计数（仅在当前函数启用了保护时）
+    pub fn emit_stack_guard_exit(&mut self) {
+        if !self.stack_guard_active {
+            return;
+        }
+        let depth = self.new_temp();
+        let prev_depth = self.new_temp();
+        self.emit_load_into(&depth, "i32", "@.cay_call_depth");
+        self.emit_line(&format!("  {} = sub i32 {}, 1", prev_depth, depth));
+        self.emit_store("i32", &prev_depth, "@.cay_call_depth");
+    }
+
     /// 发射代码但不添加缩进（用于全局声明）
     pub fn emit_raw(&mut self, line: &str) {
         self.output.push_str(line);
@@ -251,14 +546,12 @@ impl IRGenerator {
 
     /// 获取类型的 LLVM 对齐字节数
     pub fn get_type_align(&self, llvm_type: &str) -> u32 {
-        match llvm_type {
-            "i1" | "i8" => 1,
-            "i16" => 2,
-            "i32" | "float" => 4,  // float 是 4 字节对齐！
-            "i64" | "double" => 8,
-            t if t.ends_with("*") => 8,  // 所有指针都是 8 字节（64位系统）
-            _ => 8, // 默认 8 字节
-        }
+        self.data_layout.align_of(llvm_type)
+    }
+
+    /// 获取 LLVM 类型的大小（字节），由目标数据布局决定
+    pub fn get_type_size(&self, llvm_type: &str) -> u32 {
+        self.data_layout.size_of(llvm_type)
     }
 
     /// 创建新标签
@@ -275,9 +568,19 @@ impl IRGenerator {
         temp
     }
 
-    /// 进入循环上下文
-    pub fn enter_loop(&mut self, cond_label: String, end_label: String) {
-        self.loop_stack.push(LoopContext { cond_label, end_label });
+    /// 进入循环上下文，`label` 为标记循环的标签
+    pub fn enter_loop(&mut self, cond_label: String, end_label: String, label: Option<String>) {
+        self.loop_stack.push(LoopContext { cond_label: Some(cond_label), end_label, label, unlabeled: true });
+    }
+
+    /// 进入 switch 上下文，break 跳出 switch，continue 交给外层循环
+    pub fn enter_switch(&mut self, end_label: String, label: Option<String>) {
+        self.loop_stack.push(LoopContext { cond_label: None, end_label, label, unlabeled: true });
+    }
+
+    /// 进入非循环的标签语句，只有 `break label;` 跳出它
+    pub fn enter_labeled(&mut self, label: String, end_label: String) {
+        self.loop_stack.push(LoopContext { cond_label: None, end_label, label: Some(label), unlabeled: false });
     }
 
     /// 退出循环上下文
@@ -285,9 +588,14 @@ impl IRGenerator {
         self.loop_stack.pop();
     }
 
-    /// 获取当前循环上下文（用于 break/continue）
-    pub fn current_loop(&self) -> Option<&LoopContext> {
-        self.loop_stack.last()
+    /// 查找 break/continue 的目标：带标签时是同名的上下文，否则是最内层的循环（break 也可以是 switch）
+    pub fn jump_target(&self, label: Option<&str>, is_continue: bool) -> Option<&LoopContext> {
+        self.loop_stack.iter().rev()
+            .filter(|ctx| !is_continue || ctx.cond_label.is_some())
+            .find(|ctx| match label {
+                Some(label) => ctx.label.as_deref() == Some(label),
+                None => ctx.unlabeled,
+            })
     }
 
     /// 获取或创建字符串常量
@@ -296,44 +604,64 @@ impl IRGenerator {
             return name.clone();
         }
 
-        let name = format!("@.str.{}", self.global_counter);
+        let name = match self.string_namespace {
+            Some(ns) => format!("@.str.{}.{}", ns, self.global_counter),
+            None => format!("@.str.{}", self.global_counter),
+        };
         self.global_counter += 1;
 
-        // 转义字符串
-        let escaped = s.replace("\\", "\\\\")
-            .replace("\"", "\\\"")
-            .replace("\n", "\\0A")
-            .replace("\r", "\\0D")
-            .replace("\t", "\\09");
-
         // 存储以便稍后输出到全局区
         self.global_strings.insert(s.to_string(), name.clone());
 
         name
     }
 
-    /// 获取字符串常量的声明
-    pub fn get_string_declarations(&self) -> String {
-        let mut result = String::new();
-        for (s, name) in &self.global_strings {
-            // 计算实际字节数：使用UTF-8字节长度
-            let actual_len = s.as_bytes().len();
-            
-            // 转义特殊字符用于LLVM IR输出
-            // 在LLVM IR中，特殊字符使用十六进制转义序列
-            let escaped = s.replace("\\", "\\5C")
-                .replace("\"", "\\22")
-                .replace("\n", "\\0A")
-                .replace("\r", "\\0D")
-                .replace("\t", "\\09")
-                .replace("\0", "\\00");
-            let len = actual_len + 1; // +1 for null terminator
-            result.push_str(&format!(
-                "{} = private unnamed_addr constant [{} x i8] c\"{}\\00\", align 1\n",
-                name, len, escaped
-            ));
+    /// 获取指向字符串常量首字符的 i8* 指针
+    ///
+    /// 同一函数内对同一常量的 getelementptr 只计算一次：指令放在入口块中，
+    /// 因此对函数内所有基本块都可见，后续使用直接复用缓存的临时变量。
+    pub fn get_string_constant_ptr(&mut self, s: &str) -> String {
+        let global_name = self.get_or_create_string_constant(s);
+        self.get_global_array_ptr(&global_name, s.len() + 1, 0)
+    }
+
+    /// 获取全局 `[len x i8]` 数组中第 `index` 个元素的指针（按函数缓存）
+    pub fn get_global_array_ptr(&mut self, global_name: &str, len: usize, index: usize) -> String {
+        let key = (global_name.to_string(), index);
+        if let Some(ptr) = self.global_ptr_cache.get(&key) {
+            return ptr.clone();
+        }
+
+        let ptr = self.new_temp();
+        let line = format!("  {} = getelementptr [{} x i8], [{} x i8]* {}, i64 0, i64 {}",
+            ptr, len, len, global_name, index);
+        if self.function_body.is_some() {
+            self.emit_entry_alloca(&line);
+            self.global_ptr_cache.insert(key, ptr.clone());
+        } else {
+            // 没有入口块（不在函数体中），无法保证支配关系，不缓存
+            self.emit_line(&line);
+        }
+        ptr
+    }
+
+    /// 将字符串常量的声明写入 `out`
+    ///
+    /// 常量内容可能很大（数 MB 的字面量），因此逐段直接写入输出缓冲区：
+    /// 不需要转义的连续字节整段复制，只对特殊字节输出十六进制转义，不产生中间字符串。
+    pub fn write_string_declarations(&self, out: &mut String) {
+        let duplicates = self.duplicate_strings.iter().map(|(s, name)| (s, name));
+        for (s, name) in self.global_strings.iter().chain(duplicates) {
+            // 实际字节数使用 UTF-8 字节长度，+1 为结尾的 \0
+            let len = s.len() + 1;
+            out.reserve(s.len() + name.len() + 64);
+            out.push_str(name);
+            out.push_str(" = private unnamed_addr constant [");
+            out.push_str(&len.to_string());
+            out.push_str(" x i8] c\"");
+            write_escaped_ir_string(out, s);
+            out.push_str("\\00\", align 1\n");
         }
-        result
     }
 
     /// 获取全局字符串映射（用于后处理）
@@ -341,40 +669,17 @@ impl IRGenerator {
         &self.global_strings
     }
 
-    /// 生成带参数签名的方法名以支持方法重载
-    /// 格式: ClassName.__methodName_param1Type_param2Type
-    /// 注意：LLVM IR 中函数名不能包含 @ 符号，使用 __ 作为分隔符
-    /// 注意：函数名不包含 this 参数，this 在 IR 层面处理
+    /// 方法的函数名，格式见 `mangle` 模块
     pub fn generate_method_name(&self, class_name: &str, method: &crate::ast::MethodDecl) -> String {
-        if method.params.is_empty() {
-            // 无参数方法，使用简单名称
-            format!("{}.{}", class_name, method.name)
-        } else {
-            // 有参数方法，添加参数类型签名
-            let param_types: Vec<String> = method.params.iter()
-                .map(|p| self.type_to_signature(&p.param_type))
-                .collect();
-            format!("{}.__{}_{}", class_name, method.name, param_types.join("_"))
-        }
+        let param_types: Vec<Type> = method.params.iter().map(|p| p.param_type.clone()).collect();
+        mangle::mangle(class_name, &method.name, &param_types)
     }
 
-    /// 将类型转换为方法签名的一部分
-    pub fn type_to_signature(&self, ty: &crate::types::Type) -> String {
-        use crate::types::Type;
-        match ty {
-            Type::Void => "v".to_string(),
-            Type::Int32 => "i".to_string(),
-            Type::Int64 => "l".to_string(),
-            Type::Float32 => "f".to_string(),
-            Type::Float64 => "d".to_string(),
-            Type::Bool => "b".to_string(),
-            Type::String => "s".to_string(),
-            Type::Char => "c".to_string(),
-            Type::Object(name) => format!("o{}", name),
-            Type::Array(inner) => format!("a{}", self.type_to_signature(inner)),
-            Type::Function(_) => "fn".to_string(),
-            Type::Auto => panic!("Type::Auto should have been resolved before code generation"),
-        }
+    /// 静态字段的全局变量名: @ClassName.fieldName.static
+    ///
+    /// 函数名中最多只有一个 `.`，因此静态字段不会与同名方法冲突。
+    pub fn static_field_symbol(&self, class_name: &str, field_name: &str) -> String {
+        format!("@{}.{}.static", class_name, field_name)
     }
 
     /// 生成顶层函数名称
@@ -443,7 +748,10 @@ impl IRGenerator {
     /// 生成类型标识符全局变量声明
     pub fn emit_type_id_declarations(&self) -> String {
         let mut result = String::new();
-        for (class_name, info) in &self.type_id_map {
+        // 按标识符值排序，使输出的 IR 稳定
+        let mut entries: Vec<_> = self.type_id_map.iter().collect();
+        entries.sort_by_key(|(_, info)| info.type_id_value);
+        for (class_name, info) in entries {
             let type_id_name = format!("@__type_id_{}", class_name);
             // 使用整数标识符作为类型标识符的值
             result.push_str(&format!(
@@ -454,64 +762,6 @@ impl IRGenerator {
         result
     }
 
-    /// 计算类的实例布局
-    /// 
-    /// 对象内存布局: [type_id: i32][padding: i32][field1][field2]...
-    /// 返回对象总大小（字节）
-    pub fn compute_class_layout(&mut self, class_name: &str, fields: &[crate::ast::FieldDecl]) -> usize {
-        // 对象头大小：type_id (4 bytes) + padding (4 bytes) = 8 bytes
-        let header_size = 8usize;
-        let mut current_offset = header_size;
-        let mut field_map = HashMap::new();
-
-        for field in fields {
-            // 跳过静态字段
-            if field.modifiers.contains(&crate::ast::Modifier::Static) {
-                continue;
-            }
-
-            let llvm_type = self.type_to_llvm(&field.field_type);
-            let size = field.field_type.size_in_bytes();
-            
-            // 对齐处理
-            let align = self.get_type_align(&llvm_type) as usize;
-            current_offset = (current_offset + align - 1) & !(align - 1);
-
-            let field_info = InstanceFieldInfo {
-                name: field.name.clone(),
-                llvm_type: llvm_type.clone(),
-                field_type: field.field_type.clone(),
-                offset: current_offset,
-                size,
-            };
-
-            field_map.insert(field.name.clone(), field_info);
-            current_offset += size;
-        }
-
-        // 最终对齐到 8 字节边界
-        let total_size = (current_offset + 7) & !7;
-
-        let layout = ClassLayoutInfo {
-            class_name: class_name.to_string(),
-            total_size,
-            fields: field_map,
-        };
-
-        self.class_layouts.insert(class_name.to_string(), layout);
-        total_size
-    }
-
-    /// 获取类布局信息
-    pub fn get_class_layout(&self, class_name: &str) -> Option<&ClassLayoutInfo> {
-        self.class_layouts.get(class_name)
-    }
-
-    /// 获取实例字段信息
-    pub fn get_instance_field(&self, class_name: &str, field_name: &str) -> Option<&InstanceFieldInfo> {
-        self.class_layouts.get(class_name)?.fields.get(field_name)
-    }
-
     /// 设置平台配置
     pub fn set_platform_config(&mut self, config: &crate::CompilerOptions) {
         let platform_config = PlatformConfig {
@@ -521,8 +771,16 @@ impl IRGenerator {
             defines: config.defines.clone(),
             undefines: config.undefines.clone(),
             obfuscate: config.obfuscate,
+            external_runtime: config.external_runtime,
+            stack_guard_depth: config.stack_guard_depth,
+            max_steps: config.max_steps,
+            max_heap_bytes: config.max_heap_bytes,
+            no_main: config.no_main,
+            verify_ir: config.verify_ir,
         };
         self.platform_config = Some(platform_config);
+        self.jobs = config.jobs.max(1);
+        self.data_layout = DataLayout::for_triple(self.get_target_triple());
     }
 
     /// 获取平台配置
@@ -547,4 +805,25 @@ impl IRGenerator {
             String::new()
         }
     }
-}
\ No newline at end of file
+}
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+/// 按 LLVM IR `c"..."` 字符串的规则转义并写入 `out`
+///
+/// `\`、`"` 和控制字符使用 `\XX` 十六进制转义；其余字节（包括 UTF-8 多字节序列）原样保留。
+pub(crate) fn write_escaped_ir_string(out: &mut String, s: &str) {
+    let bytes = s.as_bytes();
+    let mut run_start = 0;
+    for (i, &b) in bytes.iter().enumerate() {
+        if b == b'\\' || b == b'"' || b < 0x20 || b == 0x7F {
+            // 需要转义的字节都是 ASCII，切分点一定落在字符边界上
+            out.push_str(&s[run_start..i]);
+            out.push('\\');
+            out.push(HEX_DIGITS[(b >> 4) as usize] as char);
+            out.push(HEX_DIGITS[(b & 0xF) as usize] as char);
+            run_start = i + 1;
+        }
+    }
+    out.push_str(&s[run_start..]);
+}
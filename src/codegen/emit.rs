@@ -0,0 +1,85 @@
+//! 按指令类型发射 IR 的辅助函数
+//!
+//! 常用指令（load/store/br/switch/call/alloca）通过这些函数生成，统一处理空格和对齐，
+//! 避免手写 format! 字符串时漏掉空格或写错 align 导致生成的 IR 无效。
+//! 函数体中这些指令以结构化的 [`Instruction`] 加入当前基本块（见 `codegen::ir`）。
+//! 其他指令和特殊情况仍然使用 `emit_line`（函数体内）或 `emit_raw`（模块级）。
+
+use crate::codegen::context::IRGenerator;
+use crate::codegen::ir::Instruction;
+
+impl IRGenerator {
+    /// 发射 `store`，对齐由类型决定
+    pub fn emit_store(&mut self, ty: &str, val: &str, ptr: &str) {
+        let align = self.get_type_align(ty);
+        self.emit_instruction(Instruction::Store { ty: ty.to_string(), value: val.to_string(), ptr: ptr.to_string(), align });
+    }
+
+    /// 发射 `load`，结果存入新的临时变量并返回
+    pub fn emit_load(&mut self, ty: &str, ptr: &str) -> String {
+        let temp = self.new_temp();
+        self.emit_load_into(&temp, ty, ptr);
+        temp
+    }
+
+    /// 发射 `load`，结果存入指定的寄存器名
+    pub fn emit_load_into(&mut self, dest: &str, ty: &str, ptr: &str) {
+        let align = self.get_type_align(ty);
+        self.emit_instruction(Instruction::Load { dest: dest.to_string(), ty: ty.to_string(), ptr: ptr.to_string(), align });
+    }
+
+    /// 发射 `alloca`，`name` 为不带 `%` 的局部变量名
+    pub fn emit_alloca(&mut self, name: &str, ty: &str) {
+        let align = self.get_type_align(ty);
+        self.emit_instruction(Instruction::Alloca { dest: format!("%{}", name), ty: ty.to_string(), align });
+    }
+
+    /// 发射无条件跳转
+    pub fn emit_br(&mut self, label: &str) {
+        self.emit_instruction(Instruction::Br { target: label.to_string() });
+    }
+
+    /// 发射条件跳转，`cond` 为 i1 值
+    pub fn emit_cond_br(&mut self, cond: &str, then_label: &str, else_label: &str) {
+        self.emit_instruction(Instruction::CondBr {
+            cond: cond.to_string(),
+            then_label: then_label.to_string(),
+            else_label: else_label.to_string(),
+        });
+    }
+
+    /// 发射 `switch`，`cases` 为 (常量值, 目标标签)
+    pub fn emit_switch(&mut self, ty: &str, value: &str, default_label: &str, cases: &[(String, String)]) {
+        self.emit_instruction(Instruction::Switch {
+            ty: ty.to_string(),
+            value: value.to_string(),
+            default: default_label.to_string(),
+            cases: cases.to_vec(),
+        });
+    }
+
+    /// 发射基本块标签
+    pub fn emit_label(&mut self, label: &str) {
+        self.emit_line(&format!("{}:", label));
+    }
+
+    /// 发射函数调用
+    ///
+    /// # Arguments
+    /// * `ret_type` - 返回值的 LLVM 类型
+    /// * `callee` - 被调用者（`@name` 或函数指针寄存器）
+    /// * `args` - 带类型的参数，如 `i32 %t1`
+    ///
+    /// # Returns
+    /// 返回值所在的临时变量；`void` 调用返回 `None`
+    pub fn emit_call(&mut self, ret_type: &str, callee: &str, args: &[String]) -> Option<String> {
+        let dest = (ret_type != "void").then(|| self.new_temp());
+        self.emit_instruction(Instruction::Call {
+            dest: dest.clone(),
+            ret_ty: ret_type.to_string(),
+            callee: callee.to_string(),
+            args: args.to_vec(),
+        });
+        dest
+    }
+}
@@ -0,0 +1,408 @@
+//! 逃逸分析
+//!
+//! 找出不会逃逸出所在方法的 `new` 表达式，代码生成时把它们分配在栈上（函数入口处的 alloca），
+//! 而不是调用 calloc，避免循环中的临时对象反复进行堆分配。
+//!
+//! 分析是保守的，只处理 `Foo x = new Foo();` 形式的局部变量，并要求该变量只用于：
+//! - 读写字段: `x.f`、`x.f = v`、`x?.f`
+//! - 调用不会泄漏 `this` 的方法: `x.m(...)`
+//! - 与其他引用比较 (`x == null`) 和 `instanceof`
+//!
+//! 其他任何用法（作为参数或返回值、赋给其他变量/字段/数组元素、被 lambda 捕获等）都视为逃逸。
+//! 方法是否泄漏 `this` 按同样的规则判断：在方法体中 `this` 只能用于上述位置，
+//! 隐式调用本类的实例方法也要求被调用方法不泄漏 `this`。方法按名称匹配（覆盖所有类中的同名方法，
+//! 以兼顾继承和重载），通过不动点迭代求出所有不泄漏 `this` 的方法。
+
+use std::collections::{HashMap, HashSet};
+use crate::ast::*;
+use crate::types::ParameterInfo;
+
+/// 分析整个程序，返回可以在栈上分配的 `new` 表达式的节点 ID
+pub fn analyze(program: &Program) -> HashSet<NodeId> {
+    let methods = MethodTable::new(program);
+    let safe_methods = methods.non_leaking_methods();
+
+    let mut stack_allocated = HashSet::new();
+    for class in &program.classes {
+        for member in &class.members {
+            let (body, params): (Option<&Block>, &[ParameterInfo]) = match member {
+                ClassMember::Method(method) => (method.body.as_ref(), &method.params),
+                ClassMember::Constructor(ctor) => (Some(&ctor.body), &ctor.params),
+                ClassMember::Destructor(dtor) => (Some(&dtor.body), &[]),
+                ClassMember::StaticInitializer(block) | ClassMember::InstanceInitializer(block) => (Some(block), &[]),
+                ClassMember::Field(_) => (None, &[]),
+            };
+            if let Some(body) = body {
+                collect_stack_allocations(body, params, &methods, &safe_methods, &mut stack_allocated);
+            }
+        }
+    }
+    for func in &program.top_level_functions {
+        collect_stack_allocations(&func.body, &func.params, &methods, &safe_methods, &mut stack_allocated);
+    }
+    stack_allocated
+}
+
+/// 在一个函数体中找出不逃逸的 `Foo x = new Foo();`
+fn collect_stack_allocations(
+    body: &Block,
+    params: &[ParameterInfo],
+    methods: &MethodTable,
+    safe_methods: &HashSet<String>,
+    out: &mut HashSet<NodeId>,
+) {
+    let mut candidates = Vec::new();
+    let mut declared: HashMap<String, usize> = HashMap::new();
+    for param in params {
+        *declared.entry(param.name.clone()).or_default() += 1;
+    }
+    collect_declarations(&body.statements, &mut candidates, &mut declared);
+
+    for (name, new_expr) in candidates {
+        // 同名变量声明多次时无法按名称区分，保守处理
+        if declared.get(name).copied().unwrap_or(0) != 1 {
+            continue;
+        }
+        if !methods.constructors_are_safe(&new_expr.class_name, safe_methods) {
+            continue;
+        }
+        let mut checker = EscapeChecker::new(name, safe_methods, None);
+        checker.check_block(body);
+        if !checker.escapes {
+            out.insert(new_expr.id);
+        }
+    }
+}
+
+/// 收集变量声明（用于检测重名）以及以 `new` 初始化的候选变量
+///
+/// 不进入 lambda 体：lambda 生成为独立的函数，其中的对象不在这里处理。
+fn collect_declarations<'a>(
+    stmts: &'a [Stmt],
+    candidates: &mut Vec<(&'a str, &'a NewExpr)>,
+    declared: &mut HashMap<String, usize>,
+) {
+    for stmt in stmts {
+        match stmt {
+            Stmt::VarDecl(var) => {
+                *declared.entry(var.name.clone()).or_default() += 1;
+                if let Some(Expr::New(new_expr)) = &var.initializer {
+                    candidates.push((&var.name, new_expr));
+                }
+            }
+            Stmt::If(if_stmt) => {
+                collect_declarations(std::slice::from_ref(&*if_stmt.then_branch), candidates, declared);
+                if let Some(else_branch) = &if_stmt.else_branch {
+                    collect_declarations(std::slice::from_ref(&**else_branch), candidates, declared);
+                }
+            }
+            Stmt::While(while_stmt) => {
+                collect_declarations(std::slice::from_ref(&*while_stmt.body), candidates, declared);
+            }
+            Stmt::DoWhile(do_while) => {
+                collect_declarations(std::slice::from_ref(&*do_while.body), candidates, declared);
+            }
+            Stmt::For(for_stmt) => {
+                if let Some(init) = &for_stmt.init {
+                    collect_declarations(std::slice::from_ref(&**init), candidates, declared);
+                }
+                collect_declarations(std::slice::from_ref(&*for_stmt.body), candidates, declared);
+            }
+            Stmt::ForEach(for_each) => {
+                *declared.entry(for_each.var_name.clone()).or_default() += 1;
+                collect_declarations(std::slice::from_ref(&*for_each.body), candidates, declared);
+            }
+            Stmt::Switch(switch) => {
+                for case in &switch.cases {
+                    collect_declarations(&case.body, candidates, declared);
+                }
+                if let Some(default) = &switch.default {
+                    collect_declarations(default, candidates, declared);
+                }
+            }
+            Stmt::Block(block) => collect_declarations(&block.statements, candidates, declared),
+            Stmt::Labeled(labeled) => {
+                collect_declarations(std::slice::from_ref(&*labeled.body), candidates, declared);
+            }
+            Stmt::Expr(_) | Stmt::Return(_) | Stmt::Break(_) | Stmt::Continue(_) => {}
+        }
+    }
+}
+
+/// 方法体（`None` 表示无法分析，例如 native 方法）
+type MethodBody<'a> = Option<&'a Block>;
+
+/// 按名称索引的实例方法和构造函数
+struct MethodTable<'a> {
+    /// 方法名 -> 所有类中同名实例方法的方法体
+    instance_methods: HashMap<&'a str, Vec<MethodBody<'a>>>,
+    /// 类名 -> 构造函数体（带 this()/super() 调用的构造函数记为 `None`，不做分析）
+    constructors: HashMap<&'a str, Vec<MethodBody<'a>>>,
+    /// 类名 -> 父类名（构造时隐式执行父类的无参构造函数）
+    parents: HashMap<&'a str, &'a str>,
+}
+
+impl<'a> MethodTable<'a> {
+    fn new(program: &'a Program) -> Self {
+        let mut instance_methods: HashMap<&str, Vec<MethodBody>> = HashMap::new();
+        let mut constructors: HashMap<&str, Vec<MethodBody>> = HashMap::new();
+        let mut parents = HashMap::new();
+
+        for class in &program.classes {
+            if let Some(parent) = &class.parent {
+                parents.insert(class.name.as_str(), parent.as_str());
+            }
+            for member in &class.members {
+                match member {
+                    ClassMember::Method(method) if !method.modifiers.contains(&Modifier::Static) => {
+                        let bodies = instance_methods.entry(method.name.as_str()).or_default();
+                        if method.modifiers.contains(&Modifier::Native) {
+                            bodies.push(None);
+                        } else if let Some(body) = &method.body {
+                            bodies.push(Some(body));
+                        }
+                        // 抽象方法没有方法体，实际执行的是子类中的同名方法
+                    }
+                    ClassMember::Constructor(ctor) => {
+                        let body = ctor.constructor_call.is_none().then_some(&ctor.body);
+                        constructors.entry(class.name.as_str()).or_default().push(body);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Self { instance_methods, constructors, parents }
+    }
+
+    /// 不动点迭代：求出所有同名实例方法都不泄漏 `this` 的方法名
+    fn non_leaking_methods(&self) -> HashSet<String> {
+        let mut safe: HashSet<String> = HashSet::new();
+        loop {
+            let mut changed = false;
+            for (name, bodies) in &self.instance_methods {
+                if safe.contains(*name) {
+                    continue;
+                }
+                let leaks = bodies.iter().any(|body| match body {
+                    Some(body) => self.this_escapes(body, &safe),
+                    None => true,
+                });
+                if !leaks {
+                    safe.insert(name.to_string());
+                    changed = true;
+                }
+            }
+            if !changed {
+                return safe;
+            }
+        }
+    }
+
+    fn this_escapes(&self, body: &Block, safe: &HashSet<String>) -> bool {
+        let mut checker = EscapeChecker::new("this", safe, Some(&self.instance_methods));
+        checker.check_block(body);
+        checker.escapes
+    }
+
+    /// 类及其所有父类的构造函数都不泄漏 `this`
+    fn constructors_are_safe(&self, class_name: &str, safe: &HashSet<String>) -> bool {
+        let mut current = Some(class_name);
+        let mut visited = HashSet::new();
+        while let Some(class_name) = current.filter(|name| visited.insert(*name)) {
+            let safe_here = self.constructors.get(class_name)
+                .is_none_or(|ctors| ctors.iter().all(|body| body.is_some_and(|body| !self.this_escapes(body, safe))));
+            if !safe_here {
+                return false;
+            }
+            current = self.parents.get(class_name).copied();
+        }
+        true
+    }
+}
+
+/// 检查变量引用的对象是否可能逃逸
+struct EscapeChecker<'a> {
+    /// 被跟踪的变量名（局部变量或 `this`）
+    var: &'a str,
+    /// 不泄漏 `this` 的方法
+    safe_methods: &'a HashSet<String>,
+    /// 跟踪 `this` 时，用于识别隐式传递 `this` 的无接收者调用
+    implicit_this_methods: Option<&'a HashMap<&'a str, Vec<MethodBody<'a>>>>,
+    /// 在 lambda 体中：任何引用都视为被捕获
+    in_lambda: bool,
+    escapes: bool,
+}
+
+impl<'a> EscapeChecker<'a> {
+    fn new(
+        var: &'a str,
+        safe_methods: &'a HashSet<String>,
+        implicit_this_methods: Option<&'a HashMap<&'a str, Vec<MethodBody<'a>>>>,
+    ) -> Self {
+        Self { var, safe_methods, implicit_this_methods, in_lambda: false, escapes: false }
+    }
+
+    /// 表达式是否是对被跟踪变量的直接引用（lambda 中的引用不算，按捕获处理）
+    fn is_tracked(&self, expr: &Expr) -> bool {
+        !self.in_lambda && matches!(expr, Expr::Identifier(name) if name == self.var)
+    }
+
+    fn check_block(&mut self, block: &Block) {
+        for stmt in &block.statements {
+            self.check_stmt(stmt);
+        }
+    }
+
+    fn check_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Expr(expr) => self.check_expr(expr),
+            Stmt::VarDecl(var) => {
+                if let Some(init) = &var.initializer {
+                    self.check_expr(init);
+                }
+            }
+            Stmt::Return(ret) => {
+                if let Some(expr) = &ret.value {
+                    self.check_expr(expr);
+                }
+            }
+            Stmt::If(if_stmt) => {
+                self.check_expr(&if_stmt.condition);
+                self.check_stmt(&if_stmt.then_branch);
+                if let Some(else_branch) = &if_stmt.else_branch {
+                    self.check_stmt(else_branch);
+                }
+            }
+            Stmt::While(while_stmt) => {
+                self.check_expr(&while_stmt.condition);
+                self.check_stmt(&while_stmt.body);
+            }
+            Stmt::DoWhile(do_while) => {
+                self.check_stmt(&do_while.body);
+                self.check_expr(&do_while.condition);
+            }
+            Stmt::For(for_stmt) => {
+                if let Some(init) = &for_stmt.init {
+                    self.check_stmt(init);
+                }
+                if let Some(condition) = &for_stmt.condition {
+                    self.check_expr(condition);
+                }
+                if let Some(update) = &for_stmt.update {
+                    self.check_expr(update);
+                }
+                self.check_stmt(&for_stmt.body);
+            }
+            Stmt::ForEach(for_each) => {
+                self.check_expr(&for_each.iterable);
+                self.check_stmt(&for_each.body);
+            }
+            Stmt::Switch(switch) => {
+                self.check_expr(&switch.expr);
+                for case in &switch.cases {
+                    case.body.iter().for_each(|s| self.check_stmt(s));
+                }
+                if let Some(default) = &switch.default {
+                    default.iter().for_each(|s| self.check_stmt(s));
+                }
+            }
+            Stmt::Block(block) => self.check_block(block),
+            Stmt::Labeled(labeled) => self.check_stmt(&labeled.body),
+            Stmt::Break(_) | Stmt::Continue(_) => {}
+        }
+    }
+
+    /// 检查表达式；被跟踪变量出现在未列出的位置即视为逃逸
+    fn check_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Literal(_) => {}
+            Expr::Identifier(name) => {
+                if name == self.var {
+                    self.escapes = true;
+                }
+            }
+            // 读取字段
+            Expr::MemberAccess(member) => {
+                if !self.is_tracked(&member.object) {
+                    self.check_expr(&member.object);
+                }
+            }
+            Expr::Call(call) => {
+                match call.callee.as_ref() {
+                    Expr::MemberAccess(member) if self.is_tracked(&member.object) => {
+                        if !self.safe_methods.contains(&member.member) {
+                            self.escapes = true;
+                        }
+                    }
+                    Expr::Identifier(name) => {
+                        // 实例方法中无接收者的调用会隐式传递 this
+                        if let Some(methods) = self.implicit_this_methods
+                            && methods.contains_key(name.as_str())
+                            && !self.safe_methods.contains(name)
+                        {
+                            self.escapes = true;
+                        }
+                    }
+                    callee => self.check_expr(callee),
+                }
+                call.args.iter().for_each(|arg| self.check_expr(arg));
+            }
+            Expr::Assignment(assign) => {
+                match assign.target.as_ref() {
+                    // 写字段，或让变量指向其他对象，都不会泄漏当前对象
+                    Expr::MemberAccess(member) if self.is_tracked(&member.object) => {}
+                    target if self.is_tracked(target) => {}
+                    target => self.check_expr(target),
+                }
+                self.check_expr(&assign.value);
+            }
+            Expr::Binary(bin) => {
+                let is_ref_comparison = matches!(bin.op, BinaryOp::Eq | BinaryOp::Ne);
+                for operand in [&bin.left, &bin.right] {
+                    if !(is_ref_comparison && self.is_tracked(operand)) {
+                        self.check_expr(operand);
+                    }
+                }
+            }
+            Expr::InstanceOf(instanceof) => {
+                if !self.is_tracked(&instanceof.expr) {
+                    self.check_expr(&instanceof.expr);
+                }
+            }
+            Expr::Unary(unary) => self.check_expr(&unary.operand),
+            Expr::Cast(cast) => self.check_expr(&cast.expr),
+            Expr::New(new_expr) => new_expr.args.iter().for_each(|arg| self.check_expr(arg)),
+            Expr::ArrayCreation(arr) => arr.sizes.iter().for_each(|size| self.check_expr(size)),
+            Expr::ArrayAccess(arr) => {
+                self.check_expr(&arr.array);
+                self.check_expr(&arr.index);
+            }
+            Expr::ArrayInit(init) => init.elements.iter().for_each(|e| self.check_expr(e)),
+            Expr::MethodRef(method_ref) => {
+                if let Some(object) = &method_ref.object {
+                    self.check_expr(object);
+                }
+            }
+            Expr::Lambda(lambda) => {
+                let was_in_lambda = std::mem::replace(&mut self.in_lambda, true);
+                match &lambda.body {
+                    LambdaBody::Expr(body) => self.check_expr(body),
+                    LambdaBody::Block(block) => self.check_block(block),
+                }
+                self.in_lambda = was_in_lambda;
+            }
+            Expr::Ternary(ternary) => {
+                self.check_expr(&ternary.condition);
+                self.check_expr(&ternary.true_branch);
+                self.check_expr(&ternary.false_branch);
+            }
+            Expr::NullCoalesce(coalesce) => {
+                self.check_expr(&coalesce.value);
+                self.check_expr(&coalesce.default);
+            }
+            // 内联 IR 可能任意使用实参，被跟踪的对象作为实参即视为逃逸
+            Expr::InlineIr(inline) => inline.args.iter().for_each(|arg| self.check_expr(arg)),
+        }
+    }
+}
@@ -3,6 +3,7 @@
 //! 处理数组创建、数组访问、数组初始化和多维数组。
 
 use crate::codegen::context::IRGenerator;
+use crate::codegen::value::LLVMValue;
 use crate::ast::*;
 use crate::types::Type;
 use crate::error::{cayResult, codegen_error};
@@ -12,7 +13,7 @@ impl IRGenerator {
     ///
     /// # Arguments
     /// * `arr` - 数组创建表达式
-    pub fn generate_array_creation(&mut self, arr: &ArrayCreationExpr) -> cayResult<String> {
+    pub fn generate_array_creation(&mut self, arr: &ArrayCreationExpr) -> cayResult<LLVMValue> {
         if arr.sizes.len() == 1 {
             // 一维数组
             self.generate_1d_array_creation(&arr.element_type, &arr.sizes[0])
@@ -29,10 +30,10 @@ impl IRGenerator {
     /// # Arguments
     /// * `element_type` - 元素类型
     /// * `size_expr` - 大小表达式
-    fn generate_1d_array_creation(&mut self, element_type: &Type, size_expr: &Expr) -> cayResult<String> {
+    fn generate_1d_array_creation(&mut self, element_type: &Type, size_expr: &Expr) -> cayResult<LLVMValue> {
         // 生成数组大小表达式
         let size_val_expr = self.generate_expression(size_expr)?;
-        let (size_type, size_val) = self.parse_typed_value(&size_val_expr);
+        let (size_type, size_val) = (&size_val_expr.ty, &size_val_expr.repr);
         
         // 确保大小是整数类型
         if !size_type.starts_with("i") {
@@ -61,18 +62,7 @@ impl IRGenerator {
         let elem_type = self.type_to_llvm(element_type);
         
         // 计算元素大小
-        let elem_size = match element_type {
-            Type::Int32 => 4,
-            Type::Int64 => 8,
-            Type::Float32 => 4,
-            Type::Float64 => 8,
-            Type::Bool => 1,
-            Type::Char => 1,
-            Type::String => 8, // 指针大小
-            Type::Object(_) => 8, // 指针大小
-            Type::Array(_) => 8, // 指针大小
-            _ => 8, // 默认
-        };
+        let elem_size = element_type.size_of(&self.data_layout);
         
         // 计算数据字节数 = 大小 * 元素大小
         let data_bytes_temp = self.new_temp();
@@ -89,7 +79,7 @@ impl IRGenerator {
         // 存储长度（前4字节）- calloc 已零初始化，只需设置长度
         let len_ptr = self.new_temp();
         self.emit_line(&format!("  {} = bitcast i8* {} to i32*", len_ptr, calloc_temp));
-        self.emit_line(&format!("  store i32 {}, i32* {}, align 4", size_i32, len_ptr));
+        self.emit_store("i32", &size_i32, &len_ptr);
         
         // 计算数据起始地址（跳过8字节长度头）
         let data_ptr = self.new_temp();
@@ -100,7 +90,7 @@ impl IRGenerator {
         self.emit_line(&format!("  {} = bitcast i8* {} to {}*", cast_temp, data_ptr, elem_type));
         
         // 返回数组指针（指向数据，长度在指针前8字节）
-        Ok(format!("{}* {}", elem_type, cast_temp))
+        Ok(LLVMValue::new(format!("{}*", elem_type), cast_temp, Type::Array(Box::new(element_type.clone()))))
     }
 
     /// 生成多维数组创建: new Type[size1][size2]...[sizeN]
@@ -108,7 +98,7 @@ impl IRGenerator {
     /// # Arguments
     /// * `element_type` - 元素类型
     /// * `sizes` - 各维度大小表达式列表
-    fn generate_md_array_creation(&mut self, element_type: &Type, sizes: &[Expr]) -> cayResult<String> {
+    fn generate_md_array_creation(&mut self, element_type: &Type, sizes: &[Expr]) -> cayResult<LLVMValue> {
         // 多维数组实现：分配一个指针数组，每个指针指向子数组
         // 例如 new int[3][4][5]:
         // 1. 分配 3 个指针的数组 (int**)
@@ -124,7 +114,7 @@ impl IRGenerator {
 
         // 生成第一维大小
         let first_size_expr = self.generate_expression(&sizes[0])?;
-        let (first_size_type, first_size_val) = self.parse_typed_value(&first_size_expr);
+        let (first_size_type, first_size_val) = (&first_size_expr.ty, &first_size_expr.repr);
 
         let first_size_i64 = if first_size_type != "i64" {
             let temp = self.new_temp();
@@ -134,29 +124,38 @@ impl IRGenerator {
             first_size_val.to_string()
         };
 
-        // 获取元素类型的 LLVM 表示
-        let elem_llvm_type = self.type_to_llvm(element_type);
-
-        // 确定子数组的 LLVM 类型
-        // 如果还有多个维度，子数组是指向更低维度的指针
-        // 如果只剩一个维度，子数组是元素指针
-        let sub_array_llvm_type = if sub_sizes.len() == 1 {
-            format!("{}*", elem_llvm_type)
-        } else {
-            // 递归获取子数组类型
-            format!("{}*", self.get_md_array_type(element_type, sub_sizes.len()))
+        // element_type 是子数组的类型（new int[2][3][4] 中为 int[][]），子数组的元素类型再去掉一维
+        let Type::Array(sub_element_type) = element_type else {
+            return Err(codegen_error(format!("Multidimensional array element type must be an array, got {}", element_type)));
         };
+        let sub_array_llvm_type = self.type_to_llvm(element_type);
 
         // 分配指针数组 (elem_type** 用于存储子数组指针)
+        // 与一维数组相同的布局: [长度:i32][填充:i32][子数组0]...，使每一维都能读取 length
         let ptr_array_bytes = self.new_temp();
         self.emit_line(&format!("  {} = mul i64 {}, 8", ptr_array_bytes, first_size_i64));
+        let total_bytes = self.new_temp();
+        self.emit_line(&format!("  {} = add i64 {}, 8", total_bytes, ptr_array_bytes));
 
         let calloc_ptr_array = self.new_temp();
-        self.emit_line(&format!("  {} = call i8* @calloc(i64 1, i64 {})", calloc_ptr_array, ptr_array_bytes));
+        self.emit_line(&format!("  {} = call i8* @calloc(i64 1, i64 {})", calloc_ptr_array, total_bytes));
+
+        let first_size_i32 = if first_size_type != "i32" {
+            let temp = self.new_temp();
+            self.emit_line(&format!("  {} = trunc {} {} to i32", temp, first_size_type, first_size_val));
+            temp
+        } else {
+            first_size_val.to_string()
+        };
+        let len_ptr = self.new_temp();
+        self.emit_line(&format!("  {} = bitcast i8* {} to i32*", len_ptr, calloc_ptr_array));
+        self.emit_store("i32", &first_size_i32, &len_ptr);
 
-        // 转换为正确的指针类型
+        // 转换为正确的指针类型（跳过长度头）
+        let data_ptr = self.new_temp();
+        self.emit_line(&format!("  {} = getelementptr i8, i8* {}, i64 8", data_ptr, calloc_ptr_array));
         let ptr_array = self.new_temp();
-        self.emit_line(&format!("  {} = bitcast i8* {} to {}*", ptr_array, calloc_ptr_array, sub_array_llvm_type));
+        self.emit_line(&format!("  {} = bitcast i8* {} to {}*", ptr_array, data_ptr, sub_array_llvm_type));
 
         // 生成循环来分配每个子数组
         let loop_label = self.new_label("md_loop");
@@ -166,18 +165,17 @@ impl IRGenerator {
         // 循环变量 - 使用临时变量名避免冲突
         let loop_var = self.new_temp();
         self.emit_line(&format!("  {} = alloca i64", loop_var));
-        self.emit_line(&format!("  store i64 0, i64* {}", loop_var));
+        self.emit_store("i64", "0", &loop_var);
 
         // 跳转到循环条件
-        self.emit_line(&format!("  br label %{}", loop_label));
+        self.emit_br(&loop_label);
 
         // 循环条件
         self.emit_line(&format!("\n{}:", loop_label));
-        let current_idx = self.new_temp();
-        self.emit_line(&format!("  {} = load i64, i64* {}", current_idx, loop_var));
+        let current_idx = self.emit_load("i64", &loop_var);
         let cond = self.new_temp();
         self.emit_line(&format!("  {} = icmp slt i64 {}, {}", cond, current_idx, first_size_i64));
-        self.emit_line(&format!("  br i1 {}, label %{}, label %{}", cond, body_label, end_label));
+        self.emit_cond_br(&cond, &body_label, &end_label);
 
         // 循环体
         self.emit_line(&format!("\n{}:", body_label));
@@ -185,43 +183,32 @@ impl IRGenerator {
         // 分配子数组
         let sub_array = if sub_sizes.len() == 1 {
             // 最后一维，创建一维数组
-            self.generate_1d_array_creation(element_type, &sub_sizes[0])?
+            self.generate_1d_array_creation(sub_element_type, &sub_sizes[0])?
         } else {
             // 还有多个维度，递归创建多维数组
-            self.generate_md_array_creation(element_type, sub_sizes)?
+            self.generate_md_array_creation(sub_element_type, sub_sizes)?
         };
-        let (_, sub_array_val) = self.parse_typed_value(&sub_array);
 
         // 将子数组指针存入指针数组
         let elem_ptr = self.new_temp();
         self.emit_line(&format!("  {} = getelementptr {}, {}* {}, i64 {}",
             elem_ptr, sub_array_llvm_type, sub_array_llvm_type, ptr_array, current_idx));
 
-        self.emit_line(&format!("  store {} {}, {}* {}", sub_array_llvm_type, sub_array_val, sub_array_llvm_type, elem_ptr));
+        self.emit_store(&sub_array_llvm_type, &sub_array.repr, &elem_ptr);
 
         // 增加循环变量
         let next_idx = self.new_temp();
         self.emit_line(&format!("  {} = add i64 {}, 1", next_idx, current_idx));
-        self.emit_line(&format!("  store i64 {}, i64* {}", next_idx, loop_var));
+        self.emit_store("i64", &next_idx, &loop_var);
 
         // 跳回循环条件
-        self.emit_line(&format!("  br label %{}", loop_label));
+        self.emit_br(&loop_label);
 
         // 循环结束
         self.emit_line(&format!("\n{}:", end_label));
 
         // 返回指针数组
-        Ok(format!("{}* {}", sub_array_llvm_type, ptr_array))
-    }
-
-    /// 获取多维数组类型的 LLVM 表示
-    ///
-    /// # Arguments
-    /// * `element_type` - 元素类型
-    /// * `dimensions` - 维度数
-    fn get_md_array_type(&self, element_type: &Type, dimensions: usize) -> String {
-        let base = self.type_to_llvm(element_type);
-        format!("{}{}", base, "*".repeat(dimensions))
+        Ok(LLVMValue::new(format!("{}*", sub_array_llvm_type), ptr_array, Type::Array(Box::new(element_type.clone()))))
     }
 
     /// 获取数组元素指针（用于赋值操作）
@@ -233,12 +220,10 @@ impl IRGenerator {
     /// (元素类型, 元素指针, 索引值)
     pub fn get_array_element_ptr(&mut self, arr: &ArrayAccessExpr) -> cayResult<(String, String, String)> {
         // 生成数组表达式
-        let array_expr = self.generate_expression(&arr.array)?;
-        let (array_type, array_val) = self.parse_typed_value(&array_expr);
+        let LLVMValue { ty: array_type, repr: array_val, .. } = self.generate_expression(&arr.array)?;
 
         // 生成索引表达式
-        let index_expr = self.generate_expression(&arr.index)?;
-        let (index_type, index_val) = self.parse_typed_value(&index_expr);
+        let LLVMValue { ty: index_type, repr: index_val, .. } = self.generate_expression(&arr.index)?;
 
         // 确保索引是整数类型
         if !index_type.starts_with("i") {
@@ -254,17 +239,29 @@ impl IRGenerator {
             index_val.to_string()
         };
 
-        // 获取数组元素类型（去掉末尾的一个 *）
-        // 例如: i32* -> i32, i32** -> i32*, i64* -> i64
-        let elem_type = if array_type.ends_with("*") {
-            // 找到最后一个 * 的位置，去掉它
-            let len = array_type.len();
-            array_type[..len-1].to_string()
-        } else {
-            // 如果不是指针类型，假设是 i64*（向后兼容）
-            "i64".to_string()
+        // 元素类型以语义分析记录的静态类型为准；数组值的 LLVM 类型不同（如 i8*）时先转换指针
+        let (elem_type, array_type, array_val) = match self.array_element_types.get(&arr.id) {
+            Some(element_type) => {
+                let elem_type = self.type_to_llvm(element_type);
+                let expected = format!("{}*", elem_type);
+                if array_type == expected {
+                    (elem_type, array_type, array_val)
+                } else {
+                    let cast = self.new_temp();
+                    self.emit_line(&format!("  {} = bitcast {} {} to {}", cast, array_type, array_val, expected));
+                    (elem_type, expected, cast)
+                }
+            }
+            // 代码生成内部构造的访问（如 for-each 展开）没有语义信息，去掉末尾的一个 *
+            // 例如: i32* -> i32, i32** -> i32*, i64* -> i64
+            None => match array_type.strip_suffix('*') {
+                Some(elem_type) => (elem_type.to_string(), array_type.clone(), array_val),
+                None => return Err(codegen_error(format!("Cannot index non-array value of type {}", array_type))),
+            },
         };
 
+        self.emit_array_bounds_check(&array_type, &array_val, &index_i64);
+
         // 计算元素地址
         let elem_ptr_temp = self.new_temp();
         self.emit_line(&format!("  {} = getelementptr {}, {}* {}, i64 {}",
@@ -273,19 +270,53 @@ impl IRGenerator {
         Ok((elem_type, elem_ptr_temp, index_i64))
     }
     
+    /// 读取数组指针前 8 字节中的长度，返回 i32 值
+    ///
+    /// 一维数组和多维数组的每一层都带有长度头，因此对任意维度都适用。
+    pub fn emit_array_length(&mut self, array_type: &str, array_val: &str) -> String {
+        let array_i8 = self.new_temp();
+        self.emit_line(&format!("  {} = bitcast {} {} to i8*", array_i8, array_type, array_val));
+        let len_ptr_i8 = self.new_temp();
+        self.emit_line(&format!("  {} = getelementptr i8, i8* {}, i64 -8", len_ptr_i8, array_i8));
+        let len_ptr = self.new_temp();
+        self.emit_line(&format!("  {} = bitcast i8* {} to i32*", len_ptr, len_ptr_i8));
+        self.emit_load("i32", &len_ptr)
+    }
+
+    /// 生成数组下标越界检查：下标不在 [0, length) 内时打印错误并退出
+    fn emit_array_bounds_check(&mut self, array_type: &str, array_val: &str, index_i64: &str) {
+        let len = self.emit_array_length(array_type, array_val);
+        let len_i64 = self.new_temp();
+        self.emit_line(&format!("  {} = sext i32 {} to i64", len_i64, len));
+
+        // 无符号比较同时排除负数下标
+        let out_of_bounds = self.new_temp();
+        self.emit_line(&format!("  {} = icmp uge i64 {}, {}", out_of_bounds, index_i64, len_i64));
+        let error_label = self.new_label("bounds.error");
+        let ok_label = self.new_label("bounds.ok");
+        self.emit_cond_br(&out_of_bounds, &error_label, &ok_label);
+
+        self.emit_label(&error_label);
+        let i64_format = self.get_i64_format_specifier();
+        let message = format!("Error: Array index out of bounds: index {}, length {}\n", i64_format, i64_format);
+        let message_ptr = self.get_string_constant_ptr(&message);
+        self.emit_runtime_error(&message_ptr, &[index_i64, &len_i64]);
+
+        self.emit_label(&ok_label);
+    }
+
     /// 生成数组访问表达式代码: arr[index]
     ///
     /// # Arguments
     /// * `arr` - 数组访问表达式
-    pub fn generate_array_access(&mut self, arr: &ArrayAccessExpr) -> cayResult<String> {
+    pub fn generate_array_access(&mut self, arr: &ArrayAccessExpr) -> cayResult<LLVMValue> {
         let (elem_type, elem_ptr_temp, _) = self.get_array_element_ptr(arr)?;
         
         // 加载元素值
         let elem_temp = self.new_temp();
-        let align = self.get_type_align(&elem_type);
-        self.emit_line(&format!("  {} = load {}, {}* {}, align {}", elem_temp, elem_type, elem_type, elem_ptr_temp, align));
+        self.emit_load_into(&elem_temp, &elem_type, &elem_ptr_temp);
         
-        Ok(format!("{} {}", elem_type, elem_temp))
+        Ok(LLVMValue::from_llvm(elem_type, elem_temp))
     }
 
     /// 生成数组初始化表达式代码: {1, 2, 3}
@@ -293,25 +324,17 @@ impl IRGenerator {
     ///
     /// # Arguments
     /// * `init` - 数组初始化表达式
-    pub fn generate_array_init(&mut self, init: &ArrayInitExpr) -> cayResult<String> {
+    pub fn generate_array_init(&mut self, init: &ArrayInitExpr) -> cayResult<LLVMValue> {
         if init.elements.is_empty() {
             return Err(codegen_error("Cannot generate code for empty array initializer".to_string()));
         }
         
         // 推断元素类型（从第一个元素）
         let first_elem = self.generate_expression(&init.elements[0])?;
-        let (elem_llvm_type, _) = self.parse_typed_value(&first_elem);
+        let elem_llvm_type = first_elem.ty.clone();
         
         // 获取元素大小
-        let elem_size = match elem_llvm_type.as_str() {
-            "i1" => 1,
-            "i8" => 1,
-            "i32" => 4,
-            "i64" => 8,
-            "float" => 4,
-            "double" => 8,
-            _ => 8, // 指针类型
-        };
+        let elem_size = self.get_type_size(&elem_llvm_type) as i64;
         
         let num_elements = init.elements.len() as i64;
         
@@ -327,7 +350,7 @@ impl IRGenerator {
         // 存储长度（前4字节）- calloc 已零初始化，只需设置长度
         let len_ptr = self.new_temp();
         self.emit_line(&format!("  {} = bitcast i8* {} to i32*", len_ptr, calloc_temp));
-        self.emit_line(&format!("  store i32 {}, i32* {}, align 4", num_elements, len_ptr));
+        self.emit_store("i32", &num_elements.to_string(), &len_ptr);
         
         // 计算数据起始地址（跳过8字节长度头）
         let data_ptr = self.new_temp();
@@ -339,8 +362,7 @@ impl IRGenerator {
         
         // 存储每个元素
         for (i, elem) in init.elements.iter().enumerate() {
-            let elem_val = self.generate_expression(elem)?;
-            let (_, val) = self.parse_typed_value(&elem_val);
+            let val = self.generate_expression(elem)?.repr;
             
             // 获取元素地址
             let elem_ptr = self.new_temp();
@@ -348,11 +370,11 @@ impl IRGenerator {
                 elem_ptr, elem_llvm_type, elem_llvm_type, cast_temp, i));
             
             // 存储元素
-            self.emit_line(&format!("  store {} {}, {}* {}", elem_llvm_type, val, elem_llvm_type, elem_ptr));
+            self.emit_store(&elem_llvm_type, &val, &elem_ptr);
         }
         
         // 返回数组指针（指向数据，长度在指针前8字节）
-        Ok(format!("{}* {}", elem_llvm_type, cast_temp))
+        Ok(LLVMValue::new(format!("{}*", elem_llvm_type), cast_temp, Type::Array(Box::new(first_elem.lang_ty))))
     }
 
     /// 生成数组初始化表达式代码，使用指定的目标类型: {1, 2, 3}
@@ -361,30 +383,20 @@ impl IRGenerator {
     /// # Arguments
     /// * `init` - 数组初始化表达式
     /// * `target_type` - 目标数组类型
-    pub fn generate_array_init_with_type(&mut self, init: &ArrayInitExpr, target_type: &Type) -> cayResult<String> {
-        if init.elements.is_empty() {
+    pub fn generate_array_init_with_type(&mut self, init: &ArrayInitExpr, target_type: &Type) -> cayResult<LLVMValue> {
+        // 目标类型确定时空的初始化器生成长度为 0 的数组
+        if init.elements.is_empty() && !matches!(target_type, Type::Array(_)) {
             return Err(codegen_error("Cannot generate code for empty array initializer".to_string()));
         }
 
-        // 从目标类型获取元素类型
-        let elem_llvm_type = if let Type::Array(elem_type) = target_type {
-            self.type_to_llvm(elem_type)
+        // 从目标类型获取元素类型和大小
+        let (elem_llvm_type, elem_size, array_type) = if let Type::Array(elem_type) = target_type {
+            (elem_type.llvm_of(), elem_type.size_of(&self.data_layout) as i64, target_type.clone())
         } else {
             // 如果目标类型不是数组，使用第一个元素的类型
             let first_elem = self.generate_expression(&init.elements[0])?;
-            let (elem_type, _) = self.parse_typed_value(&first_elem);
-            elem_type
-        };
-
-        // 获取元素大小
-        let elem_size = match elem_llvm_type.as_str() {
-            "i1" => 1,
-            "i8" => 1,
-            "i32" => 4,
-            "i64" => 8,
-            "float" => 4,
-            "double" => 8,
-            _ => 8, // 指针类型
+            let elem_size = self.get_type_size(&first_elem.ty) as i64;
+            (first_elem.ty, elem_size, Type::Array(Box::new(first_elem.lang_ty)))
         };
 
         let num_elements = init.elements.len() as i64;
@@ -401,7 +413,7 @@ impl IRGenerator {
         // 存储长度（前4字节）- calloc 已零初始化，只需设置长度
         let len_ptr = self.new_temp();
         self.emit_line(&format!("  {} = bitcast i8* {} to i32*", len_ptr, calloc_temp));
-        self.emit_line(&format!("  store i32 {}, i32* {}, align 4", num_elements, len_ptr));
+        self.emit_store("i32", &num_elements.to_string(), &len_ptr);
 
         // 计算数据起始地址（跳过8字节长度头）
         let data_ptr = self.new_temp();
@@ -411,13 +423,16 @@ impl IRGenerator {
         let cast_temp = self.new_temp();
         self.emit_line(&format!("  {} = bitcast i8* {} to {}*", cast_temp, data_ptr, elem_llvm_type));
 
-        // 存储每个元素
+        // 存储每个元素；嵌套的初始化器按元素类型生成，使 {{}, {1}} 中的空数组也有确定的类型
         for (i, elem) in init.elements.iter().enumerate() {
-            let elem_val = self.generate_expression(elem)?;
-            let (elem_value_type, val) = self.parse_typed_value(&elem_val);
+            let elem_val = match (elem, target_type) {
+                (Expr::ArrayInit(inner), Type::Array(elem_type)) => self.generate_array_init_with_type(inner, elem_type)?,
+                _ => self.generate_expression(elem)?,
+            };
+            let (elem_value_type, val) = (&elem_val.ty, &elem_val.repr);
 
             // 如果需要，进行类型转换
-            let final_val = if elem_value_type != elem_llvm_type {
+            let final_val = if *elem_value_type != elem_llvm_type {
                 let temp = self.new_temp();
                 // 整数到浮点数转换
                 if elem_value_type.starts_with("i") && (elem_llvm_type == "float" || elem_llvm_type == "double") {
@@ -463,10 +478,10 @@ impl IRGenerator {
                 elem_ptr, elem_llvm_type, elem_llvm_type, cast_temp, i));
 
             // 存储元素
-            self.emit_line(&format!("  store {} {}, {}* {}", elem_llvm_type, final_val, elem_llvm_type, elem_ptr));
+            self.emit_store(&elem_llvm_type, &final_val, &elem_ptr);
         }
 
         // 返回数组指针（指向数据，长度在指针前8字节）
-        Ok(format!("{}* {}", elem_llvm_type, cast_temp))
+        Ok(LLVMValue::new(format!("{}*", elem_llvm_type), cast_temp, array_type))
     }
 }
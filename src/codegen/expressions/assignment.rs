@@ -1,8 +1,9 @@
 //! 赋值表达式代码生成
 //!
-//! 处理变量赋值、数组元素赋值和静态字段赋值。
+//! 处理变量赋值、数组元素赋值、静态字段赋值和实例字段赋值。
 
-use crate::codegen::context::IRGenerator;
+use crate::codegen::context::{IRGenerator, InstanceFieldInfo};
+use crate::codegen::value::LLVMValue;
 use crate::ast::*;
 use crate::error::{cayResult, codegen_error};
 
@@ -11,33 +12,31 @@ impl IRGenerator {
     ///
     /// # Arguments
     /// * `assign` - 赋值表达式
-    pub fn generate_assignment(&mut self, assign: &AssignmentExpr) -> cayResult<String> {
+    pub fn generate_assignment(&mut self, assign: &AssignmentExpr) -> cayResult<LLVMValue> {
         let value = self.generate_expression(&assign.value)?;
-        let (value_type, val) = self.parse_typed_value(&value);
         
         match assign.target.as_ref() {
             Expr::MemberAccess(member) => {
-                self.generate_member_assignment(member, &value_type, &val, &value)
+                self.generate_member_assignment(member, &value)
             }
             Expr::Identifier(name) => {
-                self.generate_variable_assignment(name, &value_type, &val, &value)
+                self.generate_variable_assignment(name, &value)
             }
             Expr::ArrayAccess(arr_access) => {
-                self.generate_array_assignment(arr_access, &value_type, &val, &value)
+                self.generate_array_assignment(arr_access, &value)
             }
             _ => Err(codegen_error("Invalid assignment target".to_string()))
         }
     }
 
     /// 生成成员赋值（静态字段或实例字段赋值）
-    fn generate_member_assignment(&mut self, member: &MemberAccessExpr, value_type: &str, val: &str, value: &str) -> cayResult<String> {
+    fn generate_member_assignment(&mut self, member: &MemberAccessExpr, value: &LLVMValue) -> cayResult<LLVMValue> {
+        let (value_type, val) = (value.ty.as_str(), value.repr.as_str());
         // 检查是否是静态字段赋值: ClassName.fieldName = value
         if let Expr::Identifier(class_name) = &*member.object {
             let static_key = format!("{}.{}", class_name, member.member);
             if let Some(field_info) = self.static_field_map.get(&static_key).cloned() {
                 // 静态字段赋值
-                let align = self.get_type_align(&field_info.llvm_type);
-                
                 // 如果值类型与字段类型不匹配，需要转换
                 if value_type != field_info.llvm_type {
                     let temp = self.new_temp();
@@ -52,104 +51,39 @@ impl IRGenerator {
                             self.emit_line(&format!("  {} = trunc {} {} to {}",
                                 temp, value_type, val, field_info.llvm_type));
                         }
-                        self.emit_line(&format!("  store {} {}, {}* {}, align {}", 
-                            field_info.llvm_type, temp, field_info.llvm_type, field_info.name, align));
-                        return Ok(format!("{} {}", field_info.llvm_type, temp));
+                        self.emit_store(&field_info.llvm_type, &temp, &field_info.name);
+                        return Ok(LLVMValue::from_llvm(field_info.llvm_type, temp));
                     }
                 }
                 
                 // 类型匹配，直接存储
-                self.emit_line(&format!("  store {} {}, {}* {}, align {}", 
-                    value_type, val, field_info.llvm_type, field_info.name, align));
-                return Ok(value.to_string());
+                self.emit_store(value_type, val, &field_info.name);
+                return Ok(value.clone());
             }
         }
         
-        // 处理实例字段赋值: this.fieldName = value 或 obj.fieldName = value
-        
-        // 确定对象所属的类
-        let class_name_opt: Option<String> = if let Expr::Identifier(name) = &*member.object {
-            if name == "this" {
-                Some(self.current_class.clone())
-            } else {
-                self.var_class_map.get(name).cloned()
-            }
-        } else {
-            None
-        };
-        
-        if let Some(class_name) = class_name_opt {
-            if let Some(field_info) = self.get_instance_field(&class_name, &member.member).cloned() {
-                // 实例字段赋值
-                let align = self.get_type_align(&field_info.llvm_type);
-                
-                // 获取对象指针
-                // 对于 this，从作用域管理器获取 this_ptr 的 LLVM 名称；对于其他变量，加载其值
-                let obj_ptr = if let Expr::Identifier(name) = &*member.object {
-                    if name == "this" {
-                        // 从作用域管理器获取 this_ptr 的 LLVM 名称，然后加载其值
-                        let this_llvm_name = self.scope_manager.get_llvm_name("this_ptr")
-                            .unwrap_or_else(|| "this_ptr_s1".to_string());
-                        let temp = self.new_temp();
-                        self.emit_line(&format!("  {} = load i8*, i8** %{}, align 8", 
-                            temp, this_llvm_name));
-                        temp
-                    } else {
-                        // 其他变量：生成表达式并提取值
-                        let obj = self.generate_expression(&member.object)?;
-                        let (_, obj_val) = self.parse_typed_value(&obj);
-                        obj_val
-                    }
-                } else {
-                    let obj = self.generate_expression(&member.object)?;
-                    let (_, obj_val) = self.parse_typed_value(&obj);
-                    obj_val
-                };
-                
-                // 计算字段地址: obj_ptr + offset
-                let field_ptr_i8 = self.new_temp();
-                self.emit_line(&format!("  {} = getelementptr i8, i8* {}, i64 {}", 
-                    field_ptr_i8, obj_ptr, field_info.offset));
-                
-                // 将字段指针转换为正确类型的指针
-                let field_ptr = self.new_temp();
-                self.emit_line(&format!("  {} = bitcast i8* {} to {}*", 
-                    field_ptr, field_ptr_i8, field_info.llvm_type));
-                
-                // 如果值类型与字段类型不匹配，需要转换
-                let final_val = if value_type != field_info.llvm_type {
-                    let temp = self.new_temp();
-                    if value_type.starts_with("i") && field_info.llvm_type.starts_with("i") {
-                        let from_bits: u32 = value_type.trim_start_matches('i').parse().unwrap_or(64);
-                        let to_bits: u32 = field_info.llvm_type.trim_start_matches('i').parse().unwrap_or(64);
-                        if to_bits > from_bits {
-                            self.emit_line(&format!("  {} = sext {} {} to {}",
-                                temp, value_type, val, field_info.llvm_type));
-                        } else {
-                            self.emit_line(&format!("  {} = trunc {} {} to {}",
-                                temp, value_type, val, field_info.llvm_type));
-                        }
-                    } else {
-                        // 其他类型转换，直接使用原值
-                        self.emit_line(&format!("  {} = {} {}", temp, value_type, val));
-                    }
-                    temp
-                } else {
-                    val.to_string()
-                };
-                
-                // 存储值到字段
-                self.emit_line(&format!("  store {} {}, {}* {}, align {}", 
-                    field_info.llvm_type, final_val, field_info.llvm_type, field_ptr, align));
-                return Ok(value.to_string());
-            }
+        // 处理实例字段赋值: this.fieldName = value、obj.fieldName = value，对象也可以是字段链、数组元素或方法调用结果
+        if let Some(class_name) = self.field_access_class(member)
+            && let Some(field_info) = self.get_instance_field(&class_name, &member.member).cloned()
+        {
+            let obj_ptr = self.generate_object_ptr(&member.object)?;
+            let field_ptr = self.emit_field_ptr(&class_name, &field_info, &obj_ptr);
+            self.store_instance_field(&field_info, &field_ptr, value_type, val);
+            return Ok(value.clone());
         }
         
         Err(codegen_error("Invalid member access assignment target".to_string()))
     }
 
+    /// 将值存入实例字段，类型不一致时先转换为字段类型
+    fn store_instance_field(&mut self, field_info: &InstanceFieldInfo, field_ptr: &str, value_type: &str, val: &str) {
+        let final_val = self.convert_value_to(value_type, val, &field_info.llvm_type);
+        self.emit_store(&field_info.llvm_type, &final_val, field_ptr);
+    }
+
     /// 生成变量赋值
-    fn generate_variable_assignment(&mut self, name: &str, value_type: &str, val: &str, value: &str) -> cayResult<String> {
+    fn generate_variable_assignment(&mut self, name: &str, value: &LLVMValue) -> cayResult<LLVMValue> {
+        let (value_type, val) = (value.ty.as_str(), value.repr.as_str());
         // 优先使用作用域管理器获取变量类型和 LLVM 名称
         let (var_type, llvm_name) = if let Some(scope_type) = self.scope_manager.get_var_type(name) {
             let llvm_name = self.scope_manager.get_llvm_name(name).unwrap_or_else(|| name.to_string());
@@ -159,10 +93,17 @@ impl IRGenerator {
             if !self.current_class.is_empty() {
                 let static_key = format!("{}.{}", self.current_class, name);
                 if let Some(field_info) = self.static_field_map.get(&static_key).cloned() {
-                    let align = self.get_type_align(&field_info.llvm_type);
-                    self.emit_line(&format!("  store {} {}, {}* {}, align {}",
-                        field_info.llvm_type, val, field_info.llvm_type, field_info.name, align));
-                    return Ok(value.to_string());
+                    self.emit_store(&field_info.llvm_type, val, &field_info.name);
+                    return Ok(value.clone());
+                }
+
+                // 当前类的实例字段（隐式 this）
+                if let Some(field_info) = self.get_instance_field(&self.current_class, name).cloned() {
+                    let class_name = self.current_class.clone();
+                    let this_ptr = self.load_this_ptr();
+                    let field_ptr = self.emit_field_ptr(&class_name, &field_info, &this_ptr);
+                    self.store_instance_field(&field_info, &field_ptr, value_type, val);
+                    return Ok(value.clone());
                 }
             }
             // 回退到旧系统
@@ -178,52 +119,48 @@ impl IRGenerator {
         }
 
         // 类型匹配，直接存储
-        let align = self.get_type_align(&var_type);
-        self.emit_line(&format!("  store {} {}, {}* %{}, align {}", var_type, val, var_type, llvm_name, align));
-        Ok(value.to_string())
+        self.emit_store(&var_type, val, &format!("%{}", llvm_name));
+        Ok(value.clone())
     }
 
     /// 生成数组元素赋值
-    fn generate_array_assignment(&mut self, arr_access: &ArrayAccessExpr, value_type: &str, val: &str, value: &str) -> cayResult<String> {
+    fn generate_array_assignment(&mut self, arr_access: &ArrayAccessExpr, value: &LLVMValue) -> cayResult<LLVMValue> {
+        let (value_type, val) = (value.ty.as_str(), value.repr.as_str());
         // 获取数组元素指针
         let (elem_type, elem_ptr, _) = self.get_array_element_ptr(arr_access)?;
 
         // 如果值类型与元素类型不匹配，需要转换
         if value_type != elem_type {
-            return self.generate_array_assignment_with_conversion(&elem_type, &elem_ptr, value_type, val, value);
+            return self.generate_array_assignment_with_conversion(&elem_type, &elem_ptr, value);
         }
 
         // 类型匹配，直接存储到数组元素
-        let align = self.get_type_align(&elem_type);
-        self.emit_line(&format!("  store {} {}, {}* {}, align {}", elem_type, val, elem_type, elem_ptr, align));
-        Ok(value.to_string())
+        self.emit_store(&elem_type, val, &elem_ptr);
+        Ok(value.clone())
     }
 
     /// 生成带类型转换的变量赋值
-    fn generate_assignment_with_conversion(&mut self, var_type: &str, llvm_name: &str, value_type: &str, val: &str) -> cayResult<String> {
+    fn generate_assignment_with_conversion(&mut self, var_type: &str, llvm_name: &str, value_type: &str, val: &str) -> cayResult<LLVMValue> {
         let temp = self.new_temp();
 
         // 浮点类型转换
         if value_type == "double" && var_type == "float" {
             // double -> float 转换
             self.emit_line(&format!("  {} = fptrunc double {} to float", temp, val));
-            let align = self.get_type_align("float");
-            self.emit_line(&format!("  store float {}, float* %{}, align {}", temp, llvm_name, align));
-            return Ok(format!("float {}", temp));
+            self.emit_store("float", &temp, &format!("%{}", llvm_name));
+            return Ok(LLVMValue::from_llvm("float", temp));
         } else if value_type == "float" && var_type == "double" {
             // float -> double 转换
             self.emit_line(&format!("  {} = fpext float {} to double", temp, val));
-            let align = self.get_type_align("double");
-            self.emit_line(&format!("  store double {}, double* %{}, align {}", temp, llvm_name, align));
-            return Ok(format!("double {}", temp));
+            self.emit_store("double", &temp, &format!("%{}", llvm_name));
+            return Ok(LLVMValue::from_llvm("double", temp));
         }
         // 整数到浮点数转换
         else if value_type.starts_with("i") && (var_type == "float" || var_type == "double") {
             // 整数 -> 浮点数转换
             self.emit_line(&format!("  {} = sitofp {} {} to {}", temp, value_type, val, var_type));
-            let align = self.get_type_align(var_type);
-            self.emit_line(&format!("  store {} {}, {}* %{}, align {}", var_type, temp, var_type, llvm_name, align));
-            return Ok(format!("{} {}", var_type, temp));
+            self.emit_store(var_type, &temp, &format!("%{}", llvm_name));
+            return Ok(LLVMValue::from_llvm(var_type, temp));
         }
         // 整数类型转换
         else if value_type.starts_with("i") && var_type.starts_with("i") {
@@ -239,42 +176,38 @@ impl IRGenerator {
                 self.emit_line(&format!("  {} = trunc {} {} to {}",
                     temp, value_type, val, var_type));
             }
-            let align = self.get_type_align(var_type);
-            self.emit_line(&format!("  store {} {}, {}* %{}, align {}", var_type, temp, var_type, llvm_name, align));
-            return Ok(format!("{} {}", var_type, temp));
+            self.emit_store(var_type, &temp, &format!("%{}", llvm_name));
+            return Ok(LLVMValue::from_llvm(var_type, temp));
         }
 
         // 默认情况：直接存储
-        let align = self.get_type_align(var_type);
-        self.emit_line(&format!("  store {} {}, {}* %{}, align {}", var_type, val, var_type, llvm_name, align));
-        Ok(format!("{} {}", var_type, val))
+        self.emit_store(var_type, val, &format!("%{}", llvm_name));
+        Ok(LLVMValue::from_llvm(var_type, val))
     }
 
     /// 生成带类型转换的数组元素赋值
-    fn generate_array_assignment_with_conversion(&mut self, elem_type: &str, elem_ptr: &str, value_type: &str, val: &str, value: &str) -> cayResult<String> {
+    fn generate_array_assignment_with_conversion(&mut self, elem_type: &str, elem_ptr: &str, value: &LLVMValue) -> cayResult<LLVMValue> {
+        let (value_type, val) = (value.ty.as_str(), value.repr.as_str());
         let temp = self.new_temp();
 
         // 浮点类型转换
         if value_type == "double" && elem_type == "float" {
             // double -> float 转换
             self.emit_line(&format!("  {} = fptrunc double {} to float", temp, val));
-            let align = self.get_type_align(elem_type);
-            self.emit_line(&format!("  store float {}, {}* {}, align {}", temp, elem_type, elem_ptr, align));
-            return Ok(format!("float {}", temp));
+            self.emit_store(elem_type, &temp, elem_ptr);
+            return Ok(LLVMValue::from_llvm("float", temp));
         } else if value_type == "float" && elem_type == "double" {
             // float -> double 转换
             self.emit_line(&format!("  {} = fpext float {} to double", temp, val));
-            let align = self.get_type_align(elem_type);
-            self.emit_line(&format!("  store double {}, {}* {}, align {}", temp, elem_type, elem_ptr, align));
-            return Ok(format!("double {}", temp));
+            self.emit_store(elem_type, &temp, elem_ptr);
+            return Ok(LLVMValue::from_llvm("double", temp));
         }
         // 整数到浮点数转换
         else if value_type.starts_with("i") && (elem_type == "float" || elem_type == "double") {
             // 整数 -> 浮点数转换
             self.emit_line(&format!("  {} = sitofp {} {} to {}", temp, value_type, val, elem_type));
-            let align = self.get_type_align(elem_type);
-            self.emit_line(&format!("  store {} {}, {}* {}, align {}", elem_type, temp, elem_type, elem_ptr, align));
-            return Ok(format!("{} {}", elem_type, temp));
+            self.emit_store(elem_type, &temp, elem_ptr);
+            return Ok(LLVMValue::from_llvm(elem_type, temp));
         }
         // 整数类型转换
         else if value_type.starts_with("i") && elem_type.starts_with("i") {
@@ -290,14 +223,12 @@ impl IRGenerator {
                 self.emit_line(&format!("  {} = trunc {} {} to {}",
                     temp, value_type, val, elem_type));
             }
-            let align = self.get_type_align(elem_type);
-            self.emit_line(&format!("  store {} {}, {}* {}, align {}", elem_type, temp, elem_type, elem_ptr, align));
-            return Ok(format!("{} {}", elem_type, temp));
+            self.emit_store(elem_type, &temp, elem_ptr);
+            return Ok(LLVMValue::from_llvm(elem_type, temp));
         }
 
         // 默认情况：直接存储
-        let align = self.get_type_align(elem_type);
-        self.emit_line(&format!("  store {} {}, {}* {}, align {}", elem_type, val, elem_type, elem_ptr, align));
-        Ok(value.to_string())
+        self.emit_store(elem_type, val, elem_ptr);
+        Ok(value.clone())
     }
 }
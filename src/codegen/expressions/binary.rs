@@ -3,6 +3,7 @@
 //! 处理算术运算、比较运算、位运算和逻辑运算。
 
 use crate::codegen::context::IRGenerator;
+use crate::codegen::value::LLVMValue;
 use crate::ast::*;
 use crate::error::{cayResult, codegen_error};
 
@@ -11,63 +12,76 @@ impl IRGenerator {
     ///
     /// # Arguments
     /// * `bin` - 二元表达式
-    pub fn generate_binary_expression(&mut self, bin: &BinaryExpr) -> cayResult<String> {
+    pub fn generate_binary_expression(&mut self, bin: &BinaryExpr) -> cayResult<LLVMValue> {
+        if matches!(bin.op, BinaryOp::And | BinaryOp::Or) {
+            return self.generate_short_circuit(bin);
+        }
+
         let left = self.generate_expression(&bin.left)?;
         let right = self.generate_expression(&bin.right)?;
-        
-        // 解析类型和值
-        let (left_type, left_val) = self.parse_typed_value(&left);
-        let (right_type, right_val) = self.parse_typed_value(&right);
-        
+        let (left_type, left_val) = (left.ty.as_str(), left.repr.as_str());
+        let (right_type, right_val) = (right.ty.as_str(), right.repr.as_str());
+
         let temp = self.new_temp();
+
+        // 字符串 ==/!= 比较内容而不是指针
+        if self.string_comparisons.contains(&bin.id) {
+            return self.generate_string_equality(bin.op == BinaryOp::Ne, left_val, right_val, &temp);
+        }
+
+        // 字符串与非字符串拼接：按静态类型转换为字符串
+        if let Some(types) = self.string_conversions.get(&bin.id).cloned() {
+            return self.generate_string_conversion_concat(&left, &right, &types, &temp);
+        }
         
         match bin.op {
-            BinaryOp::Add => self.generate_add(&left_type, &left_val, &right_type, &right_val, &temp),
-            BinaryOp::Sub => self.generate_sub(&left_type, &left_val, &right_type, &right_val, &temp),
-            BinaryOp::Mul => self.generate_mul(&left_type, &left_val, &right_type, &right_val, &temp),
-            BinaryOp::Div => self.generate_div(&left_type, &left_val, &right_type, &right_val, &temp),
-            BinaryOp::Mod => self.generate_mod(&left_type, &left_val, &right_type, &right_val, &temp),
-            BinaryOp::Eq => self.generate_eq(&left_type, &left_val, &right_type, &right_val, &temp),
-            BinaryOp::Ne => self.generate_ne(&left_type, &left_val, &right_type, &right_val, &temp),
-            BinaryOp::Lt => self.generate_lt(&left_type, &left_val, &right_type, &right_val, &temp),
-            BinaryOp::Le => self.generate_le(&left_type, &left_val, &right_type, &right_val, &temp),
-            BinaryOp::Gt => self.generate_gt(&left_type, &left_val, &right_type, &right_val, &temp),
-            BinaryOp::Ge => self.generate_ge(&left_type, &left_val, &right_type, &right_val, &temp),
-            BinaryOp::And => self.generate_and(&left_type, &left_val, &right_type, &right_val, &temp),
-            BinaryOp::Or => self.generate_or(&left_type, &left_val, &right_type, &right_val, &temp),
-            BinaryOp::BitAnd => self.generate_bitand(&left_type, &left_val, &right_type, &right_val, &temp),
-            BinaryOp::BitOr => self.generate_bitor(&left_type, &left_val, &right_type, &right_val, &temp),
-            BinaryOp::BitXor => self.generate_bitxor(&left_type, &left_val, &right_type, &right_val, &temp),
-            BinaryOp::Shl => self.generate_shl(&left_type, &left_val, &right_type, &right_val, &temp),
-            BinaryOp::Shr => self.generate_shr(&left_type, &left_val, &right_type, &right_val, &temp),
-            BinaryOp::UnsignedShr => self.generate_ushr(&left_type, &left_val, &right_type, &right_val, &temp),
+            BinaryOp::Add => self.generate_add(left_type, left_val, right_type, right_val, &temp),
+            BinaryOp::Sub => self.generate_sub(left_type, left_val, right_type, right_val, &temp),
+            BinaryOp::Mul => self.generate_mul(left_type, left_val, right_type, right_val, &temp),
+            BinaryOp::Div => self.generate_div(left_type, left_val, right_type, right_val, &temp),
+            BinaryOp::Mod => self.generate_mod(left_type, left_val, right_type, right_val, &temp),
+            BinaryOp::Eq => self.generate_eq(left_type, left_val, right_type, right_val, &temp),
+            BinaryOp::Ne => self.generate_ne(left_type, left_val, right_type, right_val, &temp),
+            BinaryOp::Lt => self.generate_lt(left_type, left_val, right_type, right_val, &temp),
+            BinaryOp::Le => self.generate_le(left_type, left_val, right_type, right_val, &temp),
+            BinaryOp::Gt => self.generate_gt(left_type, left_val, right_type, right_val, &temp),
+            BinaryOp::Ge => self.generate_ge(left_type, left_val, right_type, right_val, &temp),
+            BinaryOp::And | BinaryOp::Or => unreachable!("logical operators are generated by generate_short_circuit"),
+            BinaryOp::BitAnd => self.generate_bitand(left_type, left_val, right_type, right_val, &temp),
+            BinaryOp::BitOr => self.generate_bitor(left_type, left_val, right_type, right_val, &temp),
+            BinaryOp::BitXor => self.generate_bitxor(left_type, left_val, right_type, right_val, &temp),
+            BinaryOp::Shl => self.generate_shl(left_type, left_val, right_type, right_val, &temp),
+            BinaryOp::Shr => self.generate_shr(left_type, left_val, right_type, right_val, &temp),
+            BinaryOp::UnsignedShr => self.generate_ushr(left_type, left_val, right_type, right_val, &temp),
         }
     }
 
     /// 生成加法表达式
-    fn generate_add(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<String> {
+    fn generate_add(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<LLVMValue> {
         // 字符串拼接处理
         if left_type == "i8*" && right_type == "i8*" {
             // 调用内建的字符串拼接函数
             self.emit_line(&format!("  {} = call i8* @__cay_string_concat(i8* {}, i8* {})",
                 temp, left_val, right_val));
-            return Ok(format!("i8* {}", temp));
-        } else if left_type == "i8*" && right_type == "i8" {
-            // 字符串 + char：先将char转换为字符串，然后拼接
-            let char_as_string = self.new_temp();
-            self.emit_line(&format!("  {} = call i8* @__cay_char_to_string(i8 {})",
-                char_as_string, right_val));
-            self.emit_line(&format!("  {} = call i8* @__cay_string_concat(i8* {}, i8* {})",
-                temp, left_val, char_as_string));
-            return Ok(format!("i8* {}", temp));
-        } else if left_type == "i8" && right_type == "i8*" {
-            // char + 字符串：先将char转换为字符串，然后拼接
-            let char_as_string = self.new_temp();
-            self.emit_line(&format!("  {} = call i8* @__cay_char_to_string(i8 {})",
-                char_as_string, left_val));
+            return Ok(LLVMValue::from_llvm("i8*", temp));
+        } else if (left_type == "i8*" && (right_type == "float" || right_type == "double"))
+            || ((left_type == "float" || left_type == "double") && right_type == "i8*") {
+            // 字符串与浮点数拼接：先将浮点数转换为字符串
+            let (float_type, float_val) = if left_type == "i8*" { (right_type, right_val) } else { (left_type, left_val) };
+            let double_val = if float_type == "float" {
+                let extended = self.new_temp();
+                self.emit_line(&format!("  {} = fpext float {} to double", extended, float_val));
+                extended
+            } else {
+                float_val.to_string()
+            };
+            let float_as_string = self.new_temp();
+            self.emit_line(&format!("  {} = call i8* @__cay_double_to_string(double {}, i1 {})",
+                float_as_string, double_val, if float_type == "float" { 1 } else { 0 }));
+            let (lhs, rhs) = if left_type == "i8*" { (left_val, float_as_string.as_str()) } else { (float_as_string.as_str(), right_val) };
             self.emit_line(&format!("  {} = call i8* @__cay_string_concat(i8* {}, i8* {})",
-                temp, char_as_string, right_val));
-            return Ok(format!("i8* {}", temp));
+                temp, lhs, rhs));
+            return Ok(LLVMValue::from_llvm("i8*", temp));
         } else if left_type == "i8*" && right_type.starts_with("i") {
             // 字符串 + 整数：先将整数转换为字符串，然后拼接
             let int_as_string = self.new_temp();
@@ -83,7 +97,7 @@ impl IRGenerator {
                 int_as_string, int_val));
             self.emit_line(&format!("  {} = call i8* @__cay_string_concat(i8* {}, i8* {})",
                 temp, left_val, int_as_string));
-            return Ok(format!("i8* {}", temp));
+            return Ok(LLVMValue::from_llvm("i8*", temp));
         } else if left_type.starts_with("i") && right_type == "i8*" {
             // 整数 + 字符串：先将整数转换为字符串，然后拼接
             let int_as_string = self.new_temp();
@@ -99,19 +113,19 @@ impl IRGenerator {
                 int_as_string, int_val));
             self.emit_line(&format!("  {} = call i8* @__cay_string_concat(i8* {}, i8* {})",
                 temp, int_as_string, right_val));
-            return Ok(format!("i8* {}", temp));
+            return Ok(LLVMValue::from_llvm("i8*", temp));
         } else if left_type.starts_with("i") && right_type.starts_with("i") {
             // 整数加法，需要类型提升
             let (promoted_type, promoted_left, promoted_right) = self.promote_integer_operands(left_type, left_val, right_type, right_val);
             self.emit_line(&format!("  {} = add {} {}, {}",
                 temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("{} {}", promoted_type, temp));
+            return Ok(LLVMValue::from_llvm(promoted_type, temp));
         } else if (left_type == "float" || left_type == "double") && (right_type == "float" || right_type == "double") {
             // 浮点数加法，需要类型提升
             let (promoted_type, promoted_left, promoted_right) = self.promote_float_operands(left_type, left_val, right_type, right_val);
             self.emit_line(&format!("  {} = fadd {} {}, {}",
                 temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("{} {}", promoted_type, temp));
+            return Ok(LLVMValue::from_llvm(promoted_type, temp));
         } else if left_type.starts_with("i") && (right_type == "float" || right_type == "double") {
             // 整数 + 浮点数：将整数转换为浮点数
             let (promoted_type, promoted_right) = if right_type == "double" { ("double", right_val.to_string()) } else { ("float", right_val.to_string()) };
@@ -123,7 +137,7 @@ impl IRGenerator {
             }
             self.emit_line(&format!("  {} = fadd {} {}, {}",
                 temp, promoted_type, converted_left, promoted_right));
-            return Ok(format!("{} {}", promoted_type, temp));
+            return Ok(LLVMValue::from_llvm(promoted_type, temp));
         } else if (left_type == "float" || left_type == "double") && right_type.starts_with("i") {
             // 浮点数 + 整数：将整数转换为浮点数
             let (promoted_type, promoted_left) = if left_type == "double" { ("double", left_val.to_string()) } else { ("float", left_val.to_string()) };
@@ -135,186 +149,245 @@ impl IRGenerator {
             }
             self.emit_line(&format!("  {} = fadd {} {}, {}",
                 temp, promoted_type, promoted_left, converted_right));
-            return Ok(format!("{} {}", promoted_type, temp));
+            return Ok(LLVMValue::from_llvm(promoted_type, temp));
         } else {
             return Err(codegen_error(format!("Unsupported addition types: {} and {}", left_type, right_type)));
         }
     }
 
     /// 生成减法表达式
-    fn generate_sub(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<String> {
+    fn generate_sub(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<LLVMValue> {
         if left_type.starts_with("i") && right_type.starts_with("i") {
             // 整数减法，需要类型提升
             let (promoted_type, promoted_left, promoted_right) = self.promote_integer_operands(left_type, left_val, right_type, right_val);
             self.emit_line(&format!("  {} = sub {} {}, {}",
                 temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("{} {}", promoted_type, temp));
+            return Ok(LLVMValue::from_llvm(promoted_type, temp));
         } else if (left_type == "float" || left_type == "double") && (right_type == "float" || right_type == "double") {
             // 浮点数减法，需要类型提升
             let (promoted_type, promoted_left, promoted_right) = self.promote_float_operands(left_type, left_val, right_type, right_val);
             self.emit_line(&format!("  {} = fsub {} {}, {}",
                 temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("{} {}", promoted_type, temp));
+            return Ok(LLVMValue::from_llvm(promoted_type, temp));
         } else if let Some((promoted_type, promoted_left, promoted_right)) = self.promote_mixed_operands(left_type, left_val, right_type, right_val) {
             // 混合类型：整数和浮点数
             self.emit_line(&format!("  {} = fsub {} {}, {}",
                 temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("{} {}", promoted_type, temp));
+            return Ok(LLVMValue::from_llvm(promoted_type, temp));
         } else {
             return Err(codegen_error(format!("Unsupported subtraction types: {} and {}", left_type, right_type)));
         }
     }
 
     /// 生成乘法表达式
-    fn generate_mul(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<String> {
+    fn generate_mul(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<LLVMValue> {
         if left_type.starts_with("i") && right_type.starts_with("i") {
             // 整数乘法，需要类型提升
             let (promoted_type, promoted_left, promoted_right) = self.promote_integer_operands(left_type, left_val, right_type, right_val);
             self.emit_line(&format!("  {} = mul {} {}, {}",
                 temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("{} {}", promoted_type, temp));
+            return Ok(LLVMValue::from_llvm(promoted_type, temp));
         } else if (left_type == "float" || left_type == "double") && (right_type == "float" || right_type == "double") {
             // 浮点数乘法，需要类型提升
             let (promoted_type, promoted_left, promoted_right) = self.promote_float_operands(left_type, left_val, right_type, right_val);
             self.emit_line(&format!("  {} = fmul {} {}, {}",
                 temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("{} {}", promoted_type, temp));
+            return Ok(LLVMValue::from_llvm(promoted_type, temp));
         } else if let Some((promoted_type, promoted_left, promoted_right)) = self.promote_mixed_operands(left_type, left_val, right_type, right_val) {
             // 混合类型：整数和浮点数
             self.emit_line(&format!("  {} = fmul {} {}, {}",
                 temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("{} {}", promoted_type, temp));
+            return Ok(LLVMValue::from_llvm(promoted_type, temp));
         } else {
             return Err(codegen_error(format!("Unsupported multiplication types: {} and {}", left_type, right_type)));
         }
     }
 
     /// 生成除法表达式
-    fn generate_div(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<String> {
+    fn generate_div(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<LLVMValue> {
         if left_type.starts_with("i") && right_type.starts_with("i") {
-            // 整数除法，需要类型提升
-            let (promoted_type, promoted_left, promoted_right) = self.promote_integer_operands(left_type, left_val, right_type, right_val);
-            // 运行时除零检查
-            self.generate_division_by_zero_check(&promoted_type, &promoted_right)?;
-            self.emit_line(&format!("  {} = sdiv {} {}, {}",
-                temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("{} {}", promoted_type, temp));
+            return self.generate_int_div_rem(false, left_type, left_val, right_type, right_val, temp);
         } else if (left_type == "float" || left_type == "double") && (right_type == "float" || right_type == "double") {
             // 浮点数除法，需要类型提升
             let (promoted_type, promoted_left, promoted_right) = self.promote_float_operands(left_type, left_val, right_type, right_val);
             self.emit_line(&format!("  {} = fdiv {} {}, {}",
                 temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("{} {}", promoted_type, temp));
+            return Ok(LLVMValue::from_llvm(promoted_type, temp));
         } else if let Some((promoted_type, promoted_left, promoted_right)) = self.promote_mixed_operands(left_type, left_val, right_type, right_val) {
             // 混合类型：整数和浮点数
             self.emit_line(&format!("  {} = fdiv {} {}, {}",
                 temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("{} {}", promoted_type, temp));
+            return Ok(LLVMValue::from_llvm(promoted_type, temp));
         } else {
             return Err(codegen_error(format!("Unsupported division types: {} and {}", left_type, right_type)));
         }
     }
 
     /// 生成取模表达式
-    fn generate_mod(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<String> {
+    fn generate_mod(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<LLVMValue> {
         if left_type.starts_with("i") && right_type.starts_with("i") {
-            // 整数取模，需要类型提升
-            let (promoted_type, promoted_left, promoted_right) = self.promote_integer_operands(left_type, left_val, right_type, right_val);
-            // 运行时除零检查（取模也需要检查）
-            self.generate_division_by_zero_check(&promoted_type, &promoted_right)?;
-            self.emit_line(&format!("  {} = srem {} {}, {}",
-                temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("{} {}", promoted_type, temp));
+            return self.generate_int_div_rem(true, left_type, left_val, right_type, right_val, temp);
         } else {
             return Err(codegen_error(format!("Unsupported modulo types: {} and {}", left_type, right_type)));
         }
     }
 
+    /// 生成整数除法（`is_rem` 为 false）或取模（`is_rem` 为 true）
+    ///
+    /// 语义与 Java 相同：商向零截断，余数与被除数同号（`-7 / 2 == -3`，`-7 % 2 == -1`），
+    /// `MIN / -1` 回绕为 `MIN`，`MIN % -1` 为 0。生成的 IR 不经过 LLVM 优化，
+    /// 因此除数为 2 的幂常量时直接生成移位和掩码，其他常量除数省去运行时检查。
+    fn generate_int_div_rem(&mut self, is_rem: bool, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<LLVMValue> {
+        let (promoted_type, promoted_left, promoted_right) = self.promote_integer_operands(left_type, left_val, right_type, right_val);
+        let bits: u32 = promoted_type[1..].parse()
+            .map_err(|_| codegen_error(format!("Unsupported integer type for division: {}", promoted_type)))?;
+        // 类型提升可能把常量扩展到临时变量中，常量除数从原始操作数中识别
+        let divisor = right_val.parse::<i64>().ok();
+
+        if let Some(shift) = divisor.and_then(|d| power_of_two_shift(d, bits)) {
+            // x / 2^k：负数先加上 2^k - 1 再算术右移，使结果向零截断
+            let sign = self.new_temp();
+            let bias = self.new_temp();
+            let biased = self.new_temp();
+            self.emit_line(&format!("  {} = ashr {} {}, {}", sign, promoted_type, promoted_left, bits - 1));
+            self.emit_line(&format!("  {} = lshr {} {}, {}", bias, promoted_type, sign, bits - shift));
+            self.emit_line(&format!("  {} = add {} {}, {}", biased, promoted_type, promoted_left, bias));
+            if is_rem {
+                // x % 2^k == x - (x / 2^k) * 2^k
+                let multiple = self.new_temp();
+                self.emit_line(&format!("  {} = and {} {}, {}", multiple, promoted_type, biased, -(1i64 << shift)));
+                self.emit_line(&format!("  {} = sub {} {}, {}", temp, promoted_type, promoted_left, multiple));
+            } else {
+                self.emit_line(&format!("  {} = ashr {} {}, {}", temp, promoted_type, biased, shift));
+            }
+            return Ok(LLVMValue::from_llvm(promoted_type, temp));
+        }
+
+        let op = if is_rem { "srem" } else { "sdiv" };
+        if matches!(divisor, Some(d) if d != 0 && d != -1) {
+            // 常量除数既不为零也不为 -1，不会溢出
+            self.emit_line(&format!("  {} = {} {} {}, {}", temp, op, promoted_type, promoted_left, promoted_right));
+            return Ok(LLVMValue::from_llvm(promoted_type, temp));
+        }
+
+        // 运行时除零检查
+        self.generate_division_by_zero_check(&promoted_type, &promoted_right)?;
+        // MIN / -1 在 LLVM 中是未定义行为：除数为 -1 时改为除以 1，商再取负（回绕）
+        let is_minus_one = self.new_temp();
+        let safe_divisor = self.new_temp();
+        self.emit_line(&format!("  {} = icmp eq {} {}, -1", is_minus_one, promoted_type, promoted_right));
+        self.emit_line(&format!("  {} = select i1 {}, {} 1, {} {}",
+            safe_divisor, is_minus_one, promoted_type, promoted_type, promoted_right));
+        if is_rem {
+            self.emit_line(&format!("  {} = srem {} {}, {}", temp, promoted_type, promoted_left, safe_divisor));
+        } else {
+            let quotient = self.new_temp();
+            let negated = self.new_temp();
+            self.emit_line(&format!("  {} = sdiv {} {}, {}", quotient, promoted_type, promoted_left, safe_divisor));
+            self.emit_line(&format!("  {} = sub {} 0, {}", negated, promoted_type, promoted_left));
+            self.emit_line(&format!("  {} = select i1 {}, {} {}, {} {}",
+                temp, is_minus_one, promoted_type, negated, promoted_type, quotient));
+        }
+        Ok(LLVMValue::from_llvm(promoted_type, temp))
+    }
+
     /// 生成等于比较表达式
-    fn generate_eq(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<String> {
+    fn generate_eq(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<LLVMValue> {
         if left_type == "i8*" && right_type == "i8*" {
             // 字符串比较
             self.emit_line(&format!("  {} = icmp eq i8* {}, {}", temp, left_val, right_val));
-            return Ok(format!("i1 {}", temp));
+            return Ok(LLVMValue::from_llvm("i1", temp));
         } else if left_type.starts_with("i") && right_type.starts_with("i") {
             let (promoted_type, promoted_left, promoted_right) = self.promote_integer_operands(left_type, left_val, right_type, right_val);
             self.emit_line(&format!("  {} = icmp eq {} {}, {}", temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("i1 {}", temp));
+            return Ok(LLVMValue::from_llvm("i1", temp));
         } else if (left_type == "float" || left_type == "double") && (right_type == "float" || right_type == "double") {
             let (promoted_type, promoted_left, promoted_right) = self.promote_float_operands(left_type, left_val, right_type, right_val);
             self.emit_line(&format!("  {} = fcmp oeq {} {}, {}", temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("i1 {}", temp));
+            return Ok(LLVMValue::from_llvm("i1", temp));
         } else if let Some((promoted_type, promoted_left, promoted_right)) = self.promote_mixed_operands(left_type, left_val, right_type, right_val) {
             // 混合类型：整数和浮点数
             self.emit_line(&format!("  {} = fcmp oeq {} {}, {}", temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("i1 {}", temp));
+            return Ok(LLVMValue::from_llvm("i1", temp));
         } else {
             return Err(codegen_error(format!("Unsupported equality comparison types: {} and {}", left_type, right_type)));
         }
     }
 
+    /// 生成字符串内容比较，`negate` 为 true 时生成 `!=`
+    fn generate_string_equality(&mut self, negate: bool, left_val: &str, right_val: &str, temp: &str) -> cayResult<LLVMValue> {
+        if !negate {
+            self.emit_line(&format!("  {} = call i1 @__cay_string_equals(i8* {}, i8* {})", temp, left_val, right_val));
+            return Ok(LLVMValue::from_llvm("i1", temp));
+        }
+        let equal = self.new_temp();
+        self.emit_line(&format!("  {} = call i1 @__cay_string_equals(i8* {}, i8* {})", equal, left_val, right_val));
+        self.emit_line(&format!("  {} = xor i1 {}, true", temp, equal));
+        Ok(LLVMValue::from_llvm("i1", temp))
+    }
+
     /// 生成不等于比较表达式
-    fn generate_ne(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<String> {
+    fn generate_ne(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<LLVMValue> {
         if left_type == "i8*" && right_type == "i8*" {
             self.emit_line(&format!("  {} = icmp ne i8* {}, {}", temp, left_val, right_val));
-            return Ok(format!("i1 {}", temp));
+            return Ok(LLVMValue::from_llvm("i1", temp));
         } else if left_type.starts_with("i") && right_type.starts_with("i") {
             let (promoted_type, promoted_left, promoted_right) = self.promote_integer_operands(left_type, left_val, right_type, right_val);
             self.emit_line(&format!("  {} = icmp ne {} {}, {}", temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("i1 {}", temp));
+            return Ok(LLVMValue::from_llvm("i1", temp));
         } else if (left_type == "float" || left_type == "double") && (right_type == "float" || right_type == "double") {
             let (promoted_type, promoted_left, promoted_right) = self.promote_float_operands(left_type, left_val, right_type, right_val);
             self.emit_line(&format!("  {} = fcmp one {} {}, {}", temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("i1 {}", temp));
+            return Ok(LLVMValue::from_llvm("i1", temp));
         } else if let Some((promoted_type, promoted_left, promoted_right)) = self.promote_mixed_operands(left_type, left_val, right_type, right_val) {
             // 混合类型：整数和浮点数
             self.emit_line(&format!("  {} = fcmp one {} {}, {}", temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("i1 {}", temp));
+            return Ok(LLVMValue::from_llvm("i1", temp));
         } else {
             return Err(codegen_error(format!("Unsupported inequality comparison types: {} and {}", left_type, right_type)));
         }
     }
 
     /// 生成小于比较表达式
-    fn generate_lt(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<String> {
+    fn generate_lt(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<LLVMValue> {
         if left_type.starts_with("i") && right_type.starts_with("i") {
             let (promoted_type, promoted_left, promoted_right) = self.promote_integer_operands(left_type, left_val, right_type, right_val);
             self.emit_line(&format!("  {} = icmp slt {} {}, {}", temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("i1 {}", temp));
+            return Ok(LLVMValue::from_llvm("i1", temp));
         } else if (left_type == "float" || left_type == "double") && (right_type == "float" || right_type == "double") {
             let (promoted_type, promoted_left, promoted_right) = self.promote_float_operands(left_type, left_val, right_type, right_val);
             self.emit_line(&format!("  {} = fcmp olt {} {}, {}", temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("i1 {}", temp));
+            return Ok(LLVMValue::from_llvm("i1", temp));
         } else if let Some((promoted_type, promoted_left, promoted_right)) = self.promote_mixed_operands(left_type, left_val, right_type, right_val) {
             // 混合类型：整数和浮点数
             self.emit_line(&format!("  {} = fcmp olt {} {}, {}", temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("i1 {}", temp));
+            return Ok(LLVMValue::from_llvm("i1", temp));
         } else {
             return Err(codegen_error(format!("Unsupported less-than comparison types: {} and {}", left_type, right_type)));
         }
     }
 
     /// 生成小于等于比较表达式
-    fn generate_le(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<String> {
+    fn generate_le(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<LLVMValue> {
         if left_type.starts_with("i") && right_type.starts_with("i") {
             let (promoted_type, promoted_left, promoted_right) = self.promote_integer_operands(left_type, left_val, right_type, right_val);
             self.emit_line(&format!("  {} = icmp sle {} {}, {}", temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("i1 {}", temp));
+            return Ok(LLVMValue::from_llvm("i1", temp));
         } else if (left_type == "float" || left_type == "double") && (right_type == "float" || right_type == "double") {
             let (promoted_type, promoted_left, promoted_right) = self.promote_float_operands(left_type, left_val, right_type, right_val);
             self.emit_line(&format!("  {} = fcmp ole {} {}, {}", temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("i1 {}", temp));
+            return Ok(LLVMValue::from_llvm("i1", temp));
         } else if let Some((promoted_type, promoted_left, promoted_right)) = self.promote_mixed_operands(left_type, left_val, right_type, right_val) {
             // 混合类型：整数和浮点数
             self.emit_line(&format!("  {} = fcmp ole {} {}, {}", temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("i1 {}", temp));
+            return Ok(LLVMValue::from_llvm("i1", temp));
         } else {
             return Err(codegen_error(format!("Unsupported less-or-equal comparison types: {} and {}", left_type, right_type)));
         }
     }
 
     /// 生成大于比较表达式
-    fn generate_gt(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<String> {
+    fn generate_gt(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<LLVMValue> {
         if left_type.starts_with("i") && right_type.starts_with("i") {
             // 整数大于比较，需要类型提升
             let (promoted_type, promoted_left, promoted_right) = self.promote_integer_operands(left_type, left_val, right_type, right_val);
@@ -331,11 +404,11 @@ impl IRGenerator {
         } else {
             return Err(codegen_error(format!("Unsupported greater-than comparison types: {} and {}", left_type, right_type)));
         }
-        Ok(format!("i1 {}", temp))
+        Ok(LLVMValue::from_llvm("i1", temp))
     }
 
     /// 生成大于等于比较表达式
-    fn generate_ge(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<String> {
+    fn generate_ge(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<LLVMValue> {
         if left_type.starts_with("i") && right_type.starts_with("i") {
             // 整数大于等于比较，需要类型提升
             let (promoted_type, promoted_left, promoted_right) = self.promote_integer_operands(left_type, left_val, right_type, right_val);
@@ -352,98 +425,140 @@ impl IRGenerator {
         } else {
             return Err(codegen_error(format!("Unsupported greater-than-or-equal comparison types: {} and {}", left_type, right_type)));
         }
-        Ok(format!("i1 {}", temp))
+        Ok(LLVMValue::from_llvm("i1", temp))
     }
 
-    /// 生成逻辑与表达式
-    fn generate_and(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<String> {
-        self.emit_line(&format!("  {} = and {} {}, {}", 
-            temp, left_type, left_val, right_val));
-        Ok(format!("i1 {}", temp))
+    /// 生成短路求值的逻辑与/或表达式
+    ///
+    /// 左侧已能决定结果时（`&&` 为 false、`||` 为 true）不求值右侧，
+    /// 使 `i >= 0 && arr[i] > 0` 这类写法不会越界。右侧可能含有嵌套的控制流，
+    /// 因此求值后跳入单独的出口块，`phi` 以出口块作为前驱。
+    fn generate_short_circuit(&mut self, bin: &BinaryExpr) -> cayResult<LLVMValue> {
+        let is_and = bin.op == BinaryOp::And;
+        let prefix = if is_and { "and" } else { "or" };
+        let left_exit_label = self.new_label(&format!("{}.lhs", prefix));
+        let rhs_label = self.new_label(&format!("{}.rhs", prefix));
+        let rhs_exit_label = self.new_label(&format!("{}.rhs.exit", prefix));
+        let end_label = self.new_label(&format!("{}.end", prefix));
+
+        let left = self.generate_expression(&bin.left)?;
+        let left_val = self.emit_logical_operand(&left);
+        self.emit_br(&left_exit_label);
+        self.emit_line(&format!("\n{}:", left_exit_label));
+        if is_and {
+            self.emit_cond_br(&left_val, &rhs_label, &end_label);
+        } else {
+            self.emit_cond_br(&left_val, &end_label, &rhs_label);
+        }
+
+        self.emit_line(&format!("\n{}:", rhs_label));
+        let right = self.generate_expression(&bin.right)?;
+        let right_val = self.emit_logical_operand(&right);
+        self.emit_br(&rhs_exit_label);
+        self.emit_line(&format!("\n{}:", rhs_exit_label));
+        self.emit_br(&end_label);
+
+        self.emit_line(&format!("\n{}:", end_label));
+        let result_temp = self.new_temp();
+        self.emit_line(&format!("  {} = phi i1 [ {}, %{} ], [ {}, %{} ]",
+            result_temp, if is_and { 0 } else { 1 }, left_exit_label, right_val, rhs_exit_label));
+        Ok(LLVMValue::from_llvm("i1", result_temp))
     }
 
-    /// 生成逻辑或表达式
-    fn generate_or(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<String> {
-        self.emit_line(&format!("  {} = or {} {}, {}",
-            temp, left_type, left_val, right_val));
-        Ok(format!("i1 {}", temp))
+    /// 将逻辑运算的操作数转换为 i1，非 i1 的值与 0 比较
+    fn emit_logical_operand(&mut self, value: &LLVMValue) -> String {
+        if value.ty == "i1" {
+            return value.repr.clone();
+        }
+        let temp = self.new_temp();
+        self.emit_line(&format!("  {} = icmp ne {}, 0", temp, value));
+        temp
     }
 
     /// 生成位与表达式
-    fn generate_bitand(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<String> {
+    fn generate_bitand(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<LLVMValue> {
         if left_type.starts_with("i") && right_type.starts_with("i") {
             // 位与，需要类型提升
             let (promoted_type, promoted_left, promoted_right) = self.promote_integer_operands(left_type, left_val, right_type, right_val);
             self.emit_line(&format!("  {} = and {} {}, {}",
                 temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("{} {}", promoted_type, temp));
+            return Ok(LLVMValue::from_llvm(promoted_type, temp));
         } else {
             return Err(codegen_error(format!("Bitwise AND requires integer operands, got {} and {}", left_type, right_type)));
         }
     }
 
     /// 生成位或表达式
-    fn generate_bitor(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<String> {
+    fn generate_bitor(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<LLVMValue> {
         if left_type.starts_with("i") && right_type.starts_with("i") {
             // 位或，需要类型提升
             let (promoted_type, promoted_left, promoted_right) = self.promote_integer_operands(left_type, left_val, right_type, right_val);
             self.emit_line(&format!("  {} = or {} {}, {}",
                 temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("{} {}", promoted_type, temp));
+            return Ok(LLVMValue::from_llvm(promoted_type, temp));
         } else {
             return Err(codegen_error(format!("Bitwise OR requires integer operands, got {} and {}", left_type, right_type)));
         }
     }
 
     /// 生成位异或表达式
-    fn generate_bitxor(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<String> {
+    fn generate_bitxor(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<LLVMValue> {
         if left_type.starts_with("i") && right_type.starts_with("i") {
             // 位异或，需要类型提升
             let (promoted_type, promoted_left, promoted_right) = self.promote_integer_operands(left_type, left_val, right_type, right_val);
             self.emit_line(&format!("  {} = xor {} {}, {}",
                 temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("{} {}", promoted_type, temp));
+            return Ok(LLVMValue::from_llvm(promoted_type, temp));
         } else {
             return Err(codegen_error(format!("Bitwise XOR requires integer operands, got {} and {}", left_type, right_type)));
         }
     }
 
     /// 生成左移表达式
-    fn generate_shl(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<String> {
+    fn generate_shl(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<LLVMValue> {
         if left_type.starts_with("i") && right_type.starts_with("i") {
             // 左移，需要类型提升
             let (promoted_type, promoted_left, promoted_right) = self.promote_integer_operands(left_type, left_val, right_type, right_val);
             self.emit_line(&format!("  {} = shl {} {}, {}",
                 temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("{} {}", promoted_type, temp));
+            return Ok(LLVMValue::from_llvm(promoted_type, temp));
         } else {
             return Err(codegen_error(format!("Shift left requires integer operands, got {} and {}", left_type, right_type)));
         }
     }
 
     /// 生成算术右移表达式
-    fn generate_shr(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<String> {
+    fn generate_shr(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<LLVMValue> {
         if left_type.starts_with("i") && right_type.starts_with("i") {
             // 算术右移，需要类型提升
             let (promoted_type, promoted_left, promoted_right) = self.promote_integer_operands(left_type, left_val, right_type, right_val);
             self.emit_line(&format!("  {} = ashr {} {}, {}",
                 temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("{} {}", promoted_type, temp));
+            return Ok(LLVMValue::from_llvm(promoted_type, temp));
         } else {
             return Err(codegen_error(format!("Arithmetic shift right requires integer operands, got {} and {}", left_type, right_type)));
         }
     }
 
     /// 生成逻辑右移表达式
-    fn generate_ushr(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<String> {
+    fn generate_ushr(&mut self, left_type: &str, left_val: &str, right_type: &str, right_val: &str, temp: &str) -> cayResult<LLVMValue> {
         if left_type.starts_with("i") && right_type.starts_with("i") {
             // 逻辑右移，需要类型提升
             let (promoted_type, promoted_left, promoted_right) = self.promote_integer_operands(left_type, left_val, right_type, right_val);
             self.emit_line(&format!("  {} = lshr {} {}, {}",
                 temp, promoted_type, promoted_left, promoted_right));
-            return Ok(format!("{} {}", promoted_type, temp));
+            return Ok(LLVMValue::from_llvm(promoted_type, temp));
         } else {
             return Err(codegen_error(format!("Unsigned shift right requires integer operands, got {} and {}", left_type, right_type)));
         }
     }
 }
+
+/// 除数是 `2^k`（`1 <= k < bits - 1`）时返回 k
+fn power_of_two_shift(divisor: i64, bits: u32) -> Option<u32> {
+    if divisor <= 1 || divisor.count_ones() != 1 {
+        return None;
+    }
+    let shift = divisor.trailing_zeros();
+    (shift < bits - 1).then_some(shift)
+}
@@ -1,66 +1,109 @@
 //! 内置函数调用代码生成
 //!
-//! 处理 print/println/readInt/readFloat/readLine 等内置函数。
+//! 处理 print/println/setPrintPrecision/readInt/readLine/exit 等内置函数，以及内置 Math、Bits、File 类的静态方法和常量。
 
 use crate::codegen::context::IRGenerator;
+use crate::codegen::value::LLVMValue;
 use crate::ast::*;
+use crate::types::Type;
 use crate::error::{cayResult, codegen_error};
 
 impl IRGenerator {
     /// 生成 print/println 调用代码
     ///
     /// # Arguments
-    /// * `args` - 参数列表
+    /// * `call` - print/println 调用表达式
     /// * `newline` - 是否打印换行符
-    pub fn generate_print_call(&mut self, args: &[Expr], newline: bool) -> cayResult<String> {
+    pub fn generate_print_call(&mut self, call: &CallExpr, newline: bool) -> cayResult<LLVMValue> {
+        let args = &call.args;
         if args.is_empty() {
             // 无参数，仅打印换行符（如果是 println）或什么都不做（如果是 print）
             if newline {
                 // 打印一个空字符串加上换行符
                 let fmt_str = "\n";
-                let fmt_name = self.get_or_create_string_constant(fmt_str);
-                let fmt_len = fmt_str.len() + 1;
-                let fmt_ptr = self.new_temp();
-                self.emit_line(&format!("  {} = getelementptr [{} x i8], [{} x i8]* {}, i64 0, i64 0",
-                    fmt_ptr, fmt_len, fmt_len, fmt_name));
+                let fmt_ptr = self.get_string_constant_ptr(fmt_str);
                 self.emit_line(&format!("  call i32 (i8*, ...) @printf(i8* {})", fmt_ptr));
             }
             // 对于 print 无参数，什么都不做
-            return Ok("void".to_string());
+            return Ok(LLVMValue::void());
         }
         
         let first_arg = &args[0];
+
+        // println(value, digits)：浮点数按给定的小数位数输出，不影响 setPrintPrecision 的设置
+        if let [value, digits] = args.as_slice() {
+            let value = self.generate_expression(value)?;
+            let wide = self.convert_value_to(&value.ty, &value.repr, "double");
+            let digits = self.generate_expression(digits)?;
+            let digits = self.convert_value_to(&digits.ty, &digits.repr, "i32");
+            let text = self.emit_call("i8*", "@__cay_double_to_fixed", &[format!("double {}", wide), format!("i32 {}", digits)]).unwrap_or_default();
+            let fmt_ptr = self.get_string_constant_ptr(if newline { "%s\n" } else { "%s" });
+            self.emit_line(&format!("  call i32 (i8*, ...) @printf(i8* {}, i8* {})", fmt_ptr, text));
+            return Ok(LLVMValue::void());
+        }
+
+        // char[] 按字符串打印（码点按 UTF-8 编码），char 打印字符本身而不是码点的数值
+        match self.print_arg_types.get(&call.id) {
+            Some(Type::Array(elem)) if elem.as_ref() == &Type::Char => {
+                let value = self.generate_expression(first_arg)?;
+                let text = self.emit_call("i8*", "@__cay_char_array_to_string", &[value.to_string()]).unwrap_or_default();
+                let fmt_ptr = self.get_string_constant_ptr(if newline { "%s\n" } else { "%s" });
+                self.emit_line(&format!("  call i32 (i8*, ...) @printf(i8* {}, i8* {})", fmt_ptr, text));
+                return Ok(LLVMValue::void());
+            }
+            Some(Type::Char) => {
+                let value = self.generate_expression(first_arg)?;
+                let text = self.generate_to_string(&Type::Char, &value)?;
+                let fmt_ptr = self.get_string_constant_ptr(if newline { "%s\n" } else { "%s" });
+                self.emit_line(&format!("  call i32 (i8*, ...) @printf(i8* {}, i8* {})", fmt_ptr, text));
+                return Ok(LLVMValue::void());
+            }
+            _ => {}
+        }
+
+        // boolean[] 的元素只占 1 字节，交给按元素类型处理的运行时函数打印
+        if let Some(Type::Array(elem)) = self.print_arg_types.get(&call.id)
+            && elem.as_ref() == &Type::Bool
+        {
+            let value = self.generate_expression(first_arg)?;
+            self.emit_call("void", "@__cay_print_bool_array", &[value.to_string()]);
+            if newline {
+                let fmt_ptr = self.get_string_constant_ptr("\n");
+                self.emit_line(&format!("  call i32 (i8*, ...) @printf(i8* {})", fmt_ptr));
+            }
+            return Ok(LLVMValue::void());
+        }
+
+        // 其他数组逐个元素打印
+        if let Some(Type::Array(elem)) = self.print_arg_types.get(&call.id).cloned() {
+            let value = self.generate_expression(first_arg)?;
+            let text = self.generate_array_to_string(&elem, &value);
+            let fmt_ptr = self.get_string_constant_ptr(if newline { "%s\n" } else { "%s" });
+            self.emit_line(&format!("  call i32 (i8*, ...) @printf(i8* {}, i8* {})", fmt_ptr, text));
+            return Ok(LLVMValue::void());
+        }
+
+        // 对象按 toString() 打印
+        if let Some(Type::Object(class_name)) = self.print_arg_types.get(&call.id).cloned() {
+            self.generate_print_object(&class_name, first_arg, newline)?;
+            return Ok(LLVMValue::void());
+        }
         
         match first_arg {
             Expr::Literal(LiteralValue::String(s)) => {
-                let global_name = self.get_or_create_string_constant(s);
                 let fmt_str = if newline { "%s\n" } else { "%s" };
-                let fmt_name = self.get_or_create_string_constant(fmt_str);
-                let len = s.len() + 1;
-                let fmt_len = fmt_str.len() + 1; // 加上null终止符
-                
-                let str_ptr = self.new_temp();
-                let fmt_ptr = self.new_temp();
-                
-                self.emit_line(&format!("  {} = getelementptr [{} x i8], [{} x i8]* {}, i64 0, i64 0",
-                    str_ptr, len, len, global_name));
-                self.emit_line(&format!("  {} = getelementptr [{} x i8], [{} x i8]* {}, i64 0, i64 0",
-                    fmt_ptr, fmt_len, fmt_len, fmt_name));
-                
+                let str_ptr = self.get_string_constant_ptr(s);
+                let fmt_ptr = self.get_string_constant_ptr(fmt_str);
+
                 self.emit_line(&format!("  call i32 (i8*, ...) @printf(i8* {}, i8* {})",
                     fmt_ptr, str_ptr));
             }
             Expr::Literal(LiteralValue::Int32(_)) | Expr::Literal(LiteralValue::Int64(_)) => {
                 let value = self.generate_expression(first_arg)?;
-                let (type_str, val) = self.parse_typed_value(&value);
+                let (type_str, val) = (&value.ty, &value.repr);
                 let i64_fmt = self.get_i64_format_specifier();
                 let fmt_str = if newline { format!("{}\n", i64_fmt) } else { i64_fmt.to_string() };
-                let fmt_name = self.get_or_create_string_constant(&fmt_str);
-                let fmt_len = fmt_str.len() + 1;
-
-                let fmt_ptr = self.new_temp();
-                self.emit_line(&format!("  {} = getelementptr [{} x i8], [{} x i8]* {}, i64 0, i64 0",
-                    fmt_ptr, fmt_len, fmt_len, fmt_name));
+                let fmt_ptr = self.get_string_constant_ptr(&fmt_str);
 
                 // 如果类型不是 i64，需要扩展
                 let final_val = if type_str != "i64" {
@@ -77,28 +120,25 @@ impl IRGenerator {
             _ => {
                 // 根据类型决定格式字符串
                 let value = self.generate_expression(first_arg)?;
-                let (type_str, val) = self.parse_typed_value(&value);
+                let (type_str, val) = (&value.ty, &value.repr);
                 
                 if type_str == "i8*" {
                     // 字符串指针类型
                     let fmt_str = if newline { "%s\n" } else { "%s" };
-                    let fmt_name = self.get_or_create_string_constant(fmt_str);
-                    let fmt_len = fmt_str.len() + 1;
-                    let fmt_ptr = self.new_temp();
-                    self.emit_line(&format!("  {} = getelementptr [{} x i8], [{} x i8]* {}, i64 0, i64 0",
-                        fmt_ptr, fmt_len, fmt_len, fmt_name));
+                    let fmt_ptr = self.get_string_constant_ptr(fmt_str);
                     self.emit_line(&format!("  call i32 (i8*, ...) @printf(i8* {}, i8* {})",
                         fmt_ptr, val));
+                } else if type_str == "i1" {
+                    // 布尔值打印为 "true" / "false"，而不是按整数打印
+                    let text = self.generate_to_string(&Type::Bool, &value)?;
+                    let fmt_ptr = self.get_string_constant_ptr(if newline { "%s\n" } else { "%s" });
+                    self.emit_line(&format!("  call i32 (i8*, ...) @printf(i8* {}, i8* {})", fmt_ptr, text));
                 } else if type_str.starts_with("i") && type_str != "i8*" {
                     // 整数类型（排除i8*）
                     // 需要将整数扩展为 i64 以匹配格式
                     let i64_fmt = self.get_i64_format_specifier();
                     let fmt_str = if newline { format!("{}\n", i64_fmt) } else { i64_fmt.to_string() };
-                    let fmt_name = self.get_or_create_string_constant(&fmt_str);
-                    let fmt_len = fmt_str.len() + 1;
-                    let fmt_ptr = self.new_temp();
-                    self.emit_line(&format!("  {} = getelementptr [{} x i8], [{} x i8]* {}, i64 0, i64 0",
-                        fmt_ptr, fmt_len, fmt_len, fmt_name));
+                    let fmt_ptr = self.get_string_constant_ptr(&fmt_str);
 
                     // 如果类型不是 i64，需要扩展
                     let final_val = if type_str != "i64" {
@@ -112,149 +152,396 @@ impl IRGenerator {
                     self.emit_line(&format!("  call i32 (i8*, ...) @printf(i8* {}, i64 {})",
                         fmt_ptr, final_val));
                 } else if type_str == "double" || type_str == "float" {
-                    // 浮点数类型
-                    let fmt_str = if newline { "%f\n" } else { "%f" };
-                    let fmt_name = self.get_or_create_string_constant(fmt_str);
-                    let fmt_len = fmt_str.len() + 1;
-                    let fmt_ptr = self.new_temp();
-                    self.emit_line(&format!("  {} = getelementptr [{} x i8], [{} x i8]* {}, i64 0, i64 0",
-                        fmt_ptr, fmt_len, fmt_len, fmt_name));
-                    
-                    // 如果类型是float，需要转换为double
-                    let final_val = if type_str == "float" {
-                        let ext_temp = self.new_temp();
-                        self.emit_line(&format!("  {} = fpext float {} to double", ext_temp, val));
-                        ext_temp
-                    } else {
-                        val.to_string()
-                    };
-                    
-                    self.emit_line(&format!("  call i32 (i8*, ...) @printf(i8* {}, double {})",
-                        fmt_ptr, final_val));
+                    // 浮点数类型：经运行时格式化函数转换为最短的可还原形式（或 setPrintPrecision 设置的小数位数），与 locale 无关
+                    let ty = if type_str == "float" { Type::Float32 } else { Type::Float64 };
+                    let text = self.generate_to_string(&ty, &value)?;
+                    let fmt_str = if newline { "%s\n" } else { "%s" };
+                    let fmt_ptr = self.get_string_constant_ptr(fmt_str);
+                    self.emit_line(&format!("  call i32 (i8*, ...) @printf(i8* {}, i8* {})",
+                        fmt_ptr, text));
                 } else {
                     // 默认作为字符串处理
                     let fmt_str = if newline { "%s\n" } else { "%s" };
-                    let fmt_name = self.get_or_create_string_constant(fmt_str);
-                    let fmt_len = fmt_str.len() + 1;
-                    let fmt_ptr = self.new_temp();
-                    self.emit_line(&format!("  {} = getelementptr [{} x i8], [{} x i8]* {}, i64 0, i64 0",
-                        fmt_ptr, fmt_len, fmt_len, fmt_name));
+                    let fmt_ptr = self.get_string_constant_ptr(fmt_str);
                     self.emit_line(&format!("  call i32 (i8*, ...) @printf(i8* {}, {})",
                         fmt_ptr, value));
                 }
             }
         }
         
-        Ok("i64 0".to_string())
+        Ok(LLVMValue::void())
     }
 
-    /// 生成 readInt 调用代码
+    /// 生成 setPrintPrecision 调用代码
+    ///
+    /// 设置之后打印浮点数和浮点数转字符串使用的小数位数，超出 [0, 20] 的参数会被截断。
     ///
     /// # Arguments
-    /// * `args` - 参数列表（应该为空）
-    pub fn generate_read_int_call(&mut self, args: &[Expr]) -> cayResult<String> {
-        // readInt 应该没有参数
-        if !args.is_empty() {
-            return Err(codegen_error("readInt() takes no arguments".to_string()));
+    /// * `args` - 参数列表（一个 int）
+    pub fn generate_set_print_precision_call(&mut self, args: &[Expr]) -> cayResult<LLVMValue> {
+        if args.len() != 1 {
+            return Err(codegen_error("setPrintPrecision() takes exactly one int argument".to_string()));
+        }
+
+        let digits = self.generate_expression(&args[0])?;
+        let (digits_type, digits_val) = (&digits.ty, &digits.repr);
+        let digits_i32 = match digits_type.as_str() {
+            "i32" => digits_val.clone(),
+            "i64" => {
+                let truncated = self.new_temp();
+                self.emit_line(&format!("  {} = trunc i64 {} to i32", truncated, digits_val));
+                truncated
+            }
+            "i8" | "i16" => {
+                let extended = self.new_temp();
+                self.emit_line(&format!("  {} = sext {} {} to i32", extended, digits_type, digits_val));
+                extended
+            }
+            _ => return Err(codegen_error(format!("setPrintPrecision() expects an int argument, got {}", digits_type))),
+        };
+        self.emit_line(&format!("  call void @__cay_set_print_precision(i32 {})", digits_i32));
+        Ok(LLVMValue::void())
+    }
+
+    /// 生成 exit 调用代码
+    ///
+    /// 调用 C 的 `exit`（先刷新标准输出等缓冲区，再以给定的退出码终止进程），
+    /// 之后以 `unreachable` 结束当前基本块，后面的指令放入不可达块。
+    ///
+    /// # Arguments
+    /// * `args` - 参数列表（一个 int 退出码）
+    pub fn generate_exit_call(&mut self, args: &[Expr]) -> cayResult<LLVMValue> {
+        let [code] = args else {
+            return Err(codegen_error("exit() takes exactly one int argument".to_string()));
+        };
+        let code = self.generate_expression(code)?;
+        let code_i32 = self.convert_value_to(&code.ty, &code.repr, "i32");
+        self.emit_line(&format!("  call void @exit(i32 {})", code_i32));
+        self.emit_line("  unreachable");
+        Ok(LLVMValue::void())
+    }
+
+    /// 生成带范围检查的窄化转换 toIntExact / toByteExact
+    ///
+    /// 与 `(int)` 等截断转换不同，值超出目标类型的范围时打印错误并以退出码 1 终止程序：
+    /// - `toIntExact(long)` 检查值在 int 范围内并截断为 int
+    /// - `toByteExact(int)` 检查值在 [-128, 127] 内，结果仍为 int
+    ///
+    /// # Arguments
+    /// * `name` - 函数名
+    /// * `args` - 参数列表（一个整数）
+    pub fn generate_exact_narrowing_call(&mut self, name: &str, args: &[Expr]) -> cayResult<LLVMValue> {
+        let (source_type, target_name, min, max): (&str, &str, i64, i64) = match name {
+            "toIntExact" => ("i64", "int", i32::MIN as i64, i32::MAX as i64),
+            "toByteExact" => ("i32", "byte", i8::MIN as i64, i8::MAX as i64),
+            _ => return Err(codegen_error(format!("Unknown narrowing builtin {}()", name))),
+        };
+        if args.len() != 1 {
+            return Err(codegen_error(format!("{}() takes exactly one integer argument", name)));
+        }
+
+        let value = self.generate_expression(&args[0])?;
+        let (value_type, value_val) = (&value.ty, &value.repr);
+        let value = match value_type.as_str() {
+            t if t == source_type => value_val.clone(),
+            "i8" | "i16" | "i32" => {
+                let extended = self.new_temp();
+                self.emit_line(&format!("  {} = sext {} {} to {}", extended, value_type, value_val, source_type));
+                extended
+            }
+            _ => return Err(codegen_error(format!("{}() expects an integer argument, got {}", name, value_type))),
+        };
+
+        let too_small = self.new_temp();
+        let too_large = self.new_temp();
+        let out_of_range = self.new_temp();
+        self.emit_line(&format!("  {} = icmp slt {} {}, {}", too_small, source_type, value, min));
+        self.emit_line(&format!("  {} = icmp sgt {} {}, {}", too_large, source_type, value, max));
+        self.emit_line(&format!("  {} = or i1 {}, {}", out_of_range, too_small, too_large));
+        let error_label = self.new_label("narrow.error");
+        let ok_label = self.new_label("narrow.ok");
+        self.emit_cond_br(&out_of_range, &error_label, &ok_label);
+
+        // 错误处理块：打印越界的值并退出
+        self.emit_label(&error_label);
+        let wide = if source_type == "i64" {
+            value.clone()
+        } else {
+            let extended = self.new_temp();
+            self.emit_line(&format!("  {} = sext {} {} to i64", extended, source_type, value));
+            extended
+        };
+        let message = format!("Error: {}() value {} out of {} range\n", name, self.get_i64_format_specifier(), target_name);
+        let message_ptr = self.get_string_constant_ptr(&message);
+        self.emit_runtime_error(&message_ptr, &[&wide]);
+
+        self.emit_label(&ok_label);
+        if source_type == "i64" {
+            let truncated = self.new_temp();
+            self.emit_line(&format!("  {} = trunc i64 {} to i32", truncated, value));
+            Ok(LLVMValue::from_llvm("i32", truncated))
+        } else {
+            Ok(LLVMValue::from_llvm("i32", value))
         }
-        
-        // 为输入缓冲区分配空间
-        let buffer_size = 32; // 足够存储整数
-        let buffer_temp = self.new_temp();
-        self.emit_line(&format!("  {} = alloca [{} x i8], align 1", buffer_temp, buffer_size));
-        
-        // 获取缓冲区指针
-        let buffer_ptr = self.new_temp();
-        self.emit_line(&format!("  {} = getelementptr [{} x i8], [{} x i8]* {}, i64 0, i64 0",
-            buffer_ptr, buffer_size, buffer_size, buffer_temp));
-        
-        // 调用 scanf 读取整数
-        let fmt_str = self.get_i64_format_specifier();
-        let fmt_name = self.get_or_create_string_constant(fmt_str);
-        let fmt_len = fmt_str.len() + 1;
-        let fmt_ptr = self.new_temp();
-        self.emit_line(&format!("  {} = getelementptr [{} x i8], [{} x i8]* {}, i64 0, i64 0",
-            fmt_ptr, fmt_len, fmt_len, fmt_name));
-        
-        // 为整数结果分配空间
-        let int_temp = self.new_temp();
-        self.emit_line(&format!("  {} = alloca i64, align 8", int_temp));
-        
-        // 调用 scanf
-        self.emit_line(&format!("  call i32 (i8*, ...) @scanf(i8* {}, i64* {})",
-            fmt_ptr, int_temp));
-        
-        // 加载读取的整数值
-        let result_temp = self.new_temp();
-        self.emit_line(&format!("  {} = load i64, i64* {}, align 8", result_temp, int_temp));
-        
-        Ok(format!("i64 {}", result_temp))
     }
 
-    /// 生成 readFloat 调用代码
+    /// 生成 readChars 调用代码
+    ///
+    /// 从标准输入读取一行到 char[] 中，返回读取的字符数（EOF 时为 -1）。
     ///
     /// # Arguments
+    /// * `args` - 参数列表（一个 char[]）
+    pub fn generate_read_chars_call(&mut self, args: &[Expr]) -> cayResult<LLVMValue> {
+        if args.len() != 1 {
+            return Err(codegen_error("readChars() takes exactly one char[] argument".to_string()));
+        }
+
+        let buffer = self.generate_expression(&args[0])?;
+        let (buffer_type, _) = (&buffer.ty, &buffer.repr);
+        if buffer_type != "i32*" {
+            return Err(codegen_error(format!("readChars() expects a char[] argument, got {}", buffer_type)));
+        }
+        let count = self.new_temp();
+        self.emit_line(&format!("  {} = call i32 @__cay_read_chars({})", count, buffer));
+        Ok(LLVMValue::from_llvm("i32", count))
+    }
+
+    /// 生成 readInt/readLong/readFloat/readDouble/readChar/readBool 调用代码
+    ///
+    /// 结果的类型与语义分析登记的返回类型一致（int、long、float、double、char、boolean）。
+    /// 用 scanf 读取下一个以空白分隔的值，读取失败或 EOF 时结果为 0（readBool 为 false）。
+    ///
+    /// # Arguments
+    /// * `name` - 内置函数名
     /// * `args` - 参数列表（应该为空）
-    pub fn generate_read_float_call(&mut self, args: &[Expr]) -> cayResult<String> {
-        // readFloat 应该没有参数
+    pub fn generate_read_value_call(&mut self, name: &str, args: &[Expr]) -> cayResult<LLVMValue> {
         if !args.is_empty() {
-            return Err(codegen_error("readFloat() takes no arguments".to_string()));
+            return Err(codegen_error(format!("{}() takes no arguments", name)));
         }
-        
-        // 为浮点数结果分配空间
-        let float_temp = self.new_temp();
-        self.emit_line(&format!("  {} = alloca double, align 8", float_temp));
-        
-        // 调用 scanf 读取浮点数
-        let fmt_str = "%lf";
-        let fmt_name = self.get_or_create_string_constant(fmt_str);
-        let fmt_len = fmt_str.len() + 1;
-        let fmt_ptr = self.new_temp();
-        self.emit_line(&format!("  {} = getelementptr [{} x i8], [{} x i8]* {}, i64 0, i64 0",
-            fmt_ptr, fmt_len, fmt_len, fmt_name));
-        
-        // 调用 scanf
-        self.emit_line(&format!("  call i32 (i8*, ...) @scanf(i8* {}, double* {})",
-            fmt_ptr, float_temp));
-        
-        // 加载读取的浮点数值
-        let result_temp = self.new_temp();
-        self.emit_line(&format!("  {} = load double, double* {}, align 8", result_temp, float_temp));
-        
-        Ok(format!("double {}", result_temp))
+        // readBool 读取一个单词并与 "true" 比较，readChar 读取一个 UTF-8 字符，由运行时函数完成
+        if name == "readBool" {
+            let value = self.emit_call("i1", "@__cay_read_bool", &[]).unwrap_or_default();
+            return Ok(LLVMValue::from_llvm("i1", value));
+        }
+        if name == "readChar" {
+            let value = self.emit_call("i32", "@__cay_read_char", &[]).unwrap_or_default();
+            return Ok(LLVMValue::new("i32", value, Type::Char));
+        }
+
+        let (llvm_type, zero, format) = match name {
+            "readInt" => ("i32", "0", "%d"),
+            "readLong" => ("i64", "0", self.get_i64_format_specifier()),
+            "readFloat" => ("float", "0.0", "%f"),
+            "readDouble" => ("double", "0.0", "%lf"),
+            _ => return Err(codegen_error(format!("Unknown input function '{}'", name))),
+        };
+
+        // 结果槽放在入口块，循环中读取不会增长栈；先清零，读取失败时保持 0
+        let slot = self.new_temp();
+        self.emit_entry_alloca(&format!("  {} = alloca {}, align {}", slot, llvm_type, self.get_type_align(llvm_type)));
+        self.emit_store(llvm_type, zero, &slot);
+        let fmt_ptr = self.get_string_constant_ptr(format);
+        self.emit_line(&format!("  call i32 (i8*, ...) @scanf(i8* {}, {}* {})", fmt_ptr, llvm_type, slot));
+        let value = self.emit_load(llvm_type, &slot);
+        Ok(LLVMValue::from_llvm(llvm_type, value))
     }
 
     /// 生成 readLine 调用代码
     ///
     /// # Arguments
     /// * `args` - 参数列表（应该为空）
-    pub fn generate_read_line_call(&mut self, args: &[Expr]) -> cayResult<String> {
+    pub fn generate_read_line_call(&mut self, args: &[Expr]) -> cayResult<LLVMValue> {
         // readLine 应该没有参数
         if !args.is_empty() {
             return Err(codegen_error("readLine() takes no arguments".to_string()));
         }
         
-        // 为输入缓冲区分配空间（假设最大256字符）
-        let buffer_size = 256;
-        let buffer_temp = self.new_temp();
-        self.emit_line(&format!("  {} = alloca [{} x i8], align 1", buffer_temp, buffer_size));
-        
-        // 获取缓冲区指针
-        let buffer_ptr = self.new_temp();
-        self.emit_line(&format!("  {} = getelementptr [{} x i8], [{} x i8]* {}, i64 0, i64 0",
-            buffer_ptr, buffer_size, buffer_size, buffer_temp));
-        
-        // 调用 fgets 读取一行
-        let stdin_name = self.get_or_create_string_constant("stdin");
-        let stdin_ptr = self.new_temp();
-        self.emit_line(&format!("  {} = load i8*, i8** {}, align 8", stdin_ptr, stdin_name));
-        
-        self.emit_line(&format!("  call i8* @fgets(i8* {}, i32 {}, i8* {})",
-            buffer_ptr, buffer_size, stdin_ptr));
-        
-        // 移除换行符（如果需要）
-        // 这里我们直接返回缓冲区指针
-        Ok(format!("i8* {}", buffer_ptr))
+        // 运行时函数在堆上分配并按需扩容，返回的字符串不含换行符
+        let line = self.emit_call("i8*", "@__cay_read_line", &[]).unwrap_or_default();
+        Ok(LLVMValue::from_llvm("i8*", line))
+    }
+
+    /// 生成内置类静态方法调用代码
+    ///
+    /// 按实参类型在类型注册表中解析重载，实参先转换为形参类型，
+    /// 再展开为 LLVM 内建函数、libm 调用或运行时函数。
+    pub fn generate_builtin_class_call(&mut self, class_name: &str, method_name: &str, call: &CallExpr) -> cayResult<LLVMValue> {
+        let args = &call.args;
+        // Arrays.toString(arr)：按语义分析记录的数组类型交给反射格式化函数
+        if class_name == "Arrays" {
+            let Some(Type::Array(elem)) = self.call_overloads.get(&call.id).and_then(|params| params.first()).map(|p| p.param_type.clone()) else {
+                return Err(codegen_error(format!("Unsupported builtin method 'Arrays.{}'", method_name)));
+            };
+            let value = self.generate_expression(&args[0])?;
+            let text = self.generate_array_to_string(&elem, &value);
+            return Ok(LLVMValue::new("i8*", text, Type::String));
+        }
+
+        let mut arg_values = Vec::new();
+        let mut arg_types = Vec::new();
+        for arg in args {
+            let LLVMValue { ty: llvm_type, repr: value, .. } = self.generate_expression(arg)?;
+            arg_types.push(llvm_type_to_value_type(&llvm_type));
+            arg_values.push((llvm_type, value));
+        }
+
+        let method = self.type_registry.as_ref()
+            .and_then(|r| r.get_class(class_name))
+            .and_then(|c| c.find_method(method_name, &arg_types))
+            .cloned()
+            .ok_or_else(|| codegen_error(format!("Unknown builtin method '{}.{}'", class_name, method_name)))?;
+
+        let mut operands = Vec::new();
+        for ((llvm_type, value), param) in arg_values.iter().zip(&method.params) {
+            let param_type = param.param_type.llvm_of();
+            operands.push(self.convert_value_to(llvm_type, value, &param_type));
+        }
+        let ret = method.return_type.llvm_of();
+        if class_name == "File" {
+            return self.generate_file_operation(method_name, &ret, &operands);
+        }
+        if class_name == "Bits" {
+            let operand_type = method.params.first().map(|p| p.param_type.llvm_of()).unwrap_or_default();
+            return self.generate_bits_operation(method_name, &ret, &operand_type, &operands);
+        }
+        self.generate_math_operation(method_name, &ret, &operands)
+    }
+
+    /// 展开 File 方法：实参都是 `i8*` 字符串，直接调用对应的运行时函数
+    fn generate_file_operation(&mut self, method_name: &str, ty: &str, operands: &[String]) -> cayResult<LLVMValue> {
+        let function = match method_name {
+            "readAll" => "@__cay_file_read_all",
+            "writeAll" => "@__cay_file_write_all",
+            "appendLine" => "@__cay_file_append_line",
+            "exists" => "@__cay_file_exists",
+            "delete" => "@__cay_file_delete",
+            _ => return Err(codegen_error(format!("Unsupported builtin method 'File.{}'", method_name))),
+        };
+        let args: Vec<String> = operands.iter().map(|operand| format!("i8* {}", operand)).collect();
+        let result = self.emit_call(ty, function, &args).unwrap_or_default();
+        Ok(LLVMValue::from_llvm(ty, result))
+    }
+
+    /// 展开 Bits 方法，`ty` 为返回类型，`operand_type` 为第一个实参（被操作的整数）的类型
+    fn generate_bits_operation(&mut self, method_name: &str, ty: &str, operand_type: &str, operands: &[String]) -> cayResult<LLVMValue> {
+        let result = match (method_name, operands) {
+            ("popcount" | "leadingZeros" | "trailingZeros", [x]) => {
+                // ctlz/cttz 的第二个参数为 false：参数为 0 时结果为位宽，与 Java 一致
+                let intrinsic = match method_name {
+                    "popcount" => "ctpop",
+                    "leadingZeros" => "ctlz",
+                    _ => "cttz",
+                };
+                let mut args = vec![format!("{} {}", operand_type, x)];
+                if intrinsic != "ctpop" {
+                    args.push("i1 false".to_string());
+                }
+                let count = self.emit_call(operand_type, &format!("@llvm.{}.{}", intrinsic, operand_type), &args).unwrap_or_default();
+                if operand_type == ty {
+                    Some(count)
+                } else {
+                    let narrowed = self.new_temp();
+                    self.emit_line(&format!("  {} = trunc {} {} to {}", narrowed, operand_type, count, ty));
+                    Some(narrowed)
+                }
+            }
+            // 循环移位即两个操作数相同的漏斗移位，移位距离按位宽取模
+            ("rotateLeft" | "rotateRight", [x, distance]) => {
+                let amount = if ty == "i32" {
+                    distance.clone()
+                } else {
+                    let widened = self.new_temp();
+                    self.emit_line(&format!("  {} = sext i32 {} to {}", widened, distance, ty));
+                    widened
+                };
+                let intrinsic = if method_name == "rotateLeft" { "fshl" } else { "fshr" };
+                self.emit_call(ty, &format!("@llvm.{}.{}", intrinsic, ty),
+                    &[format!("{} {}", ty, x), format!("{} {}", ty, x), format!("{} {}", ty, amount)])
+            }
+            _ => None,
+        };
+        match result {
+            Some(value) => Ok(LLVMValue::from_llvm(ty, value)),
+            None => Err(codegen_error(format!("Unsupported builtin method 'Bits.{}'", method_name))),
+        }
     }
 
+    /// 展开 Math 方法，`ty` 为返回类型（abs/min/max 同时也是实参类型）
+    fn generate_math_operation(&mut self, method_name: &str, ty: &str, operands: &[String]) -> cayResult<LLVMValue> {
+        let is_float = ty == "float" || ty == "double";
+        let suffix = if ty == "float" { "f32" } else { "f64" };
+        let result = match (method_name, operands) {
+            ("sqrt" | "floor" | "ceil" | "sin" | "cos" | "exp" | "log", [x]) => {
+                let intrinsic = format!("@llvm.{}.f64", method_name);
+                self.emit_call("double", &intrinsic, &[format!("double {}", x)])
+            }
+            ("tan", [x]) => self.emit_call("double", "@tan", &[format!("double {}", x)]),
+            ("pow", [x, y]) => self.emit_call("double", "@llvm.pow.f64", &[format!("double {}", x), format!("double {}", y)]),
+            // Java 语义：round(x) = floor(x + 0.5)
+            ("round", [x]) => {
+                let shifted = self.new_temp();
+                self.emit_line(&format!("  {} = fadd double {}, 0.5", shifted, x));
+                let floored = self.emit_call("double", "@llvm.floor.f64", &[format!("double {}", shifted)]);
+                let rounded = self.new_temp();
+                self.emit_line(&format!("  {} = fptosi double {} to i64", rounded, floored.unwrap_or_default()));
+                Some(rounded)
+            }
+            ("random", []) => self.emit_call("double", "@__cay_math_random", &[]),
+            ("abs", [x]) if is_float => {
+                let intrinsic = format!("@llvm.fabs.{}", suffix);
+                self.emit_call(ty, &intrinsic, &[format!("{} {}", ty, x)])
+            }
+            ("abs", [x]) => {
+                let negated = self.new_temp();
+                self.emit_line(&format!("  {} = sub {} 0, {}", negated, ty, x));
+                let is_negative = self.new_temp();
+                self.emit_line(&format!("  {} = icmp slt {} {}, 0", is_negative, ty, x));
+                let result = self.new_temp();
+                self.emit_line(&format!("  {} = select i1 {}, {} {}, {} {}", result, is_negative, ty, negated, ty, x));
+                Some(result)
+            }
+            ("min" | "max", [x, y]) if is_float => {
+                let op = if method_name == "min" { "minnum" } else { "maxnum" };
+                let intrinsic = format!("@llvm.{}.{}", op, suffix);
+                self.emit_call(ty, &intrinsic, &[format!("{} {}", ty, x), format!("{} {}", ty, y)])
+            }
+            ("min" | "max", [x, y]) => {
+                let cond = if method_name == "min" { "slt" } else { "sgt" };
+                let pick_x = self.new_temp();
+                self.emit_line(&format!("  {} = icmp {} {} {}, {}", pick_x, cond, ty, x, y));
+                let result = self.new_temp();
+                self.emit_line(&format!("  {} = select i1 {}, {} {}, {} {}", result, pick_x, ty, x, ty, y));
+                Some(result)
+            }
+            _ => None,
+        };
+        match result {
+            Some(value) => Ok(LLVMValue::from_llvm(ty, value)),
+            None => Err(codegen_error(format!("Unsupported builtin method 'Math.{}'", method_name))),
+        }
+    }
+
+    /// 生成内置类常量（Math.PI、Math.E）
+    pub fn generate_builtin_class_constant(&mut self, class_name: &str, name: &str) -> cayResult<LLVMValue> {
+        match (class_name, name) {
+            ("Math", "PI") => Ok(LLVMValue::new("double", format!("0x{:016X}", std::f64::consts::PI.to_bits()), Type::Float64)),
+            ("Math", "E") => Ok(LLVMValue::new("double", format!("0x{:016X}", std::f64::consts::E.to_bits()), Type::Float64)),
+            _ => Err(codegen_error(format!("Unknown builtin constant '{}.{}'", class_name, name))),
+        }
+    }
+}
+
+/// 由 LLVM 类型还原值的静态类型，用于内置方法的重载解析
+fn llvm_type_to_value_type(llvm_type: &str) -> Type {
+    match llvm_type {
+        "i1" => Type::Bool,
+        "i8" => Type::Int8,
+        "i16" => Type::Int16,
+        "i32" => Type::Int32,
+        "i64" => Type::Int64,
+        "float" => Type::Float32,
+        "double" => Type::Float64,
+        "i8*" => Type::String,
+        _ => Type::Object(llvm_type.to_string()),
+    }
 }
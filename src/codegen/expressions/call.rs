@@ -2,28 +2,58 @@
 //!
 //! 处理函数调用、内置函数（print/read）、String 方法调用和可变参数。
 
-use crate::codegen::context::IRGenerator;
+use crate::codegen::context::{IRGenerator, THIS_PTR_VAR};
+use crate::codegen::value::LLVMValue;
+use crate::codegen::mangle;
 use crate::ast::*;
 use crate::error::{cayResult, codegen_error};
+use crate::types::{MethodInfo, ParameterInfo, Type};
 
 impl IRGenerator {
     /// 生成函数调用表达式代码
     ///
     /// # Arguments
     /// * `call` - 函数调用表达式
-    pub fn generate_call_expression(&mut self, call: &CallExpr) -> cayResult<String> {
-        // 处理 print 和 println 函数
-        if let Expr::Identifier(name) = call.callee.as_ref() {
+    pub fn generate_call_expression(&mut self, call: &CallExpr) -> cayResult<LLVMValue> {
+        // 通过函数类型变量调用闭包（语义分析以调用节点记录了闭包的函数类型）
+        if let Expr::Identifier(name) = call.callee.as_ref()
+            && let Some(function_type) = self.function_types.get(&call.id).cloned()
+        {
+            return self.generate_closure_call(name, &call.args, &function_type);
+        }
+
+        // 处理 print 和 println 等内置函数；当前类或其父类声明的同名方法优先
+        if let Expr::Identifier(name) = call.callee.as_ref()
+            && !self.shadows_builtin(name)
+        {
             match name.as_str() {
-                "print" => return self.generate_print_call(&call.args, false),
-                "println" => return self.generate_print_call(&call.args, true),
-                "readInt" => return self.generate_read_int_call(&call.args),
-                "readFloat" => return self.generate_read_float_call(&call.args),
+                "print" | "println" if self.format_calls.contains_key(&call.id) => {
+                    return self.generate_formatted_print(call, name == "println");
+                }
+                "print" => return self.generate_print_call(call, false),
+                "println" => return self.generate_print_call(call, true),
+                "format" => return self.generate_format_call(call),
+                "setPrintPrecision" => return self.generate_set_print_precision_call(&call.args),
+                "toIntExact" | "toByteExact" => return self.generate_exact_narrowing_call(name, &call.args),
+                "exit" => return self.generate_exit_call(&call.args),
+                "debugPrint" => return self.generate_debug_print_call(call),
+                "readInt" | "readLong" | "readFloat" | "readDouble" | "readChar" | "readBool" => {
+                    return self.generate_read_value_call(name, &call.args);
+                }
                 "readLine" => return self.generate_read_line_call(&call.args),
+                "readChars" => return self.generate_read_chars_call(&call.args),
                 _ => {}
             }
         }
 
+        // 内置类的静态方法调用: Math.sqrt(x)
+        if let Expr::MemberAccess(member) = call.callee.as_ref()
+            && let Expr::Identifier(class_name) = member.object.as_ref()
+            && self.is_builtin_class_reference(member.object.as_ref())
+        {
+            return self.generate_builtin_class_call(class_name, &member.member, call);
+        }
+
         // 处理 String 方法调用: str.method(args)
         if let Expr::MemberAccess(member) = call.callee.as_ref() {
             // 检查是否是 String 方法调用
@@ -45,9 +75,20 @@ impl IRGenerator {
             }
             Expr::MemberAccess(member) => {
                 if let Expr::Identifier(obj_name) = member.object.as_ref() {
-                    let class_name = self.var_class_map.get(obj_name)
-                        .cloned()
-                        .unwrap_or_else(|| obj_name.clone());
+                    let class_name = if obj_name == "this" && !self.current_class.is_empty() {
+                        self.current_class.clone()
+                    } else if obj_name == "super" {
+                        self.parent_class(&self.current_class).ok_or_else(|| codegen_error(format!(
+                            "Class '{}' has no superclass; 'super' cannot be used", self.current_class
+                        )))?
+                    } else {
+                        self.var_class_map.get(obj_name)
+                            .cloned()
+                            .unwrap_or_else(|| obj_name.clone())
+                    };
+                    (class_name, member.member.clone(), Some(member.object.clone()))
+                } else if let Some(class_name) = self.field_access_class(member) {
+                    // 接收者是调用结果、字段等表达式：`sb.append(a).append(b)`
                     (class_name, member.member.clone(), Some(member.object.clone()))
                 } else {
                     return Err(codegen_error("Invalid method call".to_string()));
@@ -55,6 +96,18 @@ impl IRGenerator {
             }
             _ => return Err(codegen_error("Invalid function call".to_string())),
         };
+        // 通过接口类型调用：查 itable 间接调用
+        if let Some(obj) = &obj_expr
+            && self.is_interface(&class_name)
+        {
+            return self.generate_interface_call(&class_name, &method_name, obj, &call.args);
+        }
+
+        // super.method(args) 总是调用父类中的实现，不经过虚表
+        let is_super_call = matches!(obj_expr.as_deref(), Some(Expr::Identifier(name)) if name == "super");
+
+        // 继承的方法在声明它的父类中生成
+        let class_name = self.resolve_method_owner(&class_name, &method_name);
 
         // 检查是否是可变参数方法（根据方法名推断）
         let is_varargs_method = self.is_varargs_method(&class_name, &method_name);
@@ -65,22 +118,32 @@ impl IRGenerator {
             arg_results.push(self.generate_expression(arg)?);
         }
 
-        // 处理可变参数：将多余参数打包成数组
+        // 处理可变参数：将多余参数打包成数组（没有可变参数时传空数组）
         let (processed_args, has_varargs_array) = if is_varargs_method {
             let packed = self.pack_varargs_args(&class_name, &method_name, &arg_results)?;
-            // 如果原始参数多于固定参数数量，说明创建了数组
-            let fixed_count = match method_name.as_str() {
-                "sum" => 0,
-                "printAll" => 1,
-                "multiplyAndAdd" => 1,
-                _ => 0,
-            };
-            let has_array = arg_results.len() > fixed_count;
+            let fixed_count = self.varargs_signature(&class_name, &method_name)
+                .map_or(0, |(count, _)| count);
+            let has_array = packed.len() == fixed_count + 1;
             (packed, has_array)
         } else {
             (arg_results, false)
         };
 
+        // 语义分析选中的重载：实参转换为声明的参数类型（如 int 实参传给 long 参数）
+        let overload = self.call_overloads.get(&call.id).cloned();
+        let processed_args = match &overload {
+            Some(params) if !has_varargs_array && params.len() == processed_args.len() => {
+                let mut converted_args = Vec::with_capacity(params.len());
+                for (arg, param) in processed_args.iter().zip(params) {
+                    let param_type = self.type_to_llvm(&param.param_type);
+                    let converted = self.convert_value_to(&arg.ty, &arg.repr, &param_type);
+                    converted_args.push(LLVMValue::new(param_type, converted, param.param_type.clone()));
+                }
+                converted_args
+            }
+            _ => processed_args,
+        };
+
         // 检查是否是实例方法（需要传递 this）
         let is_instance_method = self.is_instance_method(&class_name, &method_name);
         
@@ -90,64 +153,83 @@ impl IRGenerator {
         if is_instance_method {
             // 获取 this 指针
             if let Some(obj) = obj_expr {
-                // 通过对象表达式获取 this 指针（如 obj1.getId()）
-                let obj_result = self.generate_expression(&obj)?;
-                let (_, obj_val) = self.parse_typed_value(&obj_result);
-                final_args.push(format!("i8* {}", obj_val));
-            } else if let Some(this_llvm_name) = self.scope_manager.get_llvm_name("this_ptr") {
+                if self.is_class_reference(&obj) {
+                    return Err(codegen_error(format!(
+                        "Instance method '{}.{}' cannot be called through the class name", class_name, method_name
+                    )));
+                }
+                // 通过对象表达式获取 this 指针（如 obj1.getId()、super.getId()）
+                let obj_val = self.generate_object_ptr(&obj)?;
+                final_args.push(LLVMValue::new("i8*", obj_val, Type::Object(class_name.clone())));
+            } else if let Some(this_llvm_name) = self.scope_manager.get_llvm_name(THIS_PTR_VAR) {
                 // 通过当前方法的 this_ptr 获取（如在实例方法中调用其他实例方法）
-                let this_temp = self.new_temp();
-                self.emit_line(&format!("  {} = load i8*, i8** %{}, align 8", 
-                    this_temp, this_llvm_name));
-                final_args.push(format!("i8* {}", this_temp));
+                let this_temp = self.emit_load("i8*", &format!("%{}", this_llvm_name));
+                final_args.push(LLVMValue::new("i8*", this_temp, Type::Object(class_name.clone())));
             } else {
-                // 在静态方法中调用实例方法且没有对象表达式，使用 null 作为 this
-                final_args.push("i8* null".to_string());
+                return Err(codegen_error(format!(
+                    "Instance method '{}.{}' called without an object in a static context", class_name, method_name
+                )));
             }
         }
         
         // 添加其他参数
-        for arg_str in &processed_args {
-            final_args.push(arg_str.clone());
-        }
+        final_args.extend(processed_args.iter().cloned());
 
         // 生成函数名 - 使用类型注册表获取方法定义的参数类型
         // 注意：函数名不包含 this 参数，this 只在 IR 调用时传递
-        let fn_name = self.generate_function_name(&class_name, &method_name, &processed_args, has_varargs_array);
+        let fn_name = self.generate_function_name(&class_name, &method_name, &processed_args, has_varargs_array, overload.as_deref());
 
         // 获取方法的返回类型
-        let ret_type = self.get_method_return_type(&class_name, &method_name, &processed_args, has_varargs_array);
+        let ret_type = self.get_method_return_type(&class_name, &method_name, &processed_args, has_varargs_array, overload.as_deref());
         let llvm_ret_type = self.type_to_llvm(&ret_type);
-        
-        if llvm_ret_type == "void" {
-            // void 方法调用不需要命名结果
-            self.emit_line(&format!("  call void @{}({})",
-                fn_name, final_args.join(", ")));
-            Ok("void %dummy".to_string())
-        } else {
-            let temp = self.new_temp();
-            self.emit_line(&format!("  {} = call {} @{}({})",
-                temp, llvm_ret_type, fn_name, final_args.join(", ")));
-            Ok(format!("{} {}", llvm_ret_type, temp))
+
+        // 被子类覆盖的实例方法通过对象的虚表间接调用
+        let callee = match self.find_vtable_slot(&class_name, &fn_name) {
+            Some((slot_index, slot)) if is_instance_method && !is_super_call && self.needs_virtual_dispatch(&class_name, slot_index) => {
+                let fn_type = slot.fn_type.clone();
+                self.emit_virtual_method_ptr(&class_name, slot_index, &fn_type, &final_args[0].repr)
+            }
+            _ => format!("@{}", fn_name),
+        };
+
+        // void 方法调用不需要命名结果
+        let final_args: Vec<String> = final_args.iter().map(LLVMValue::to_string).collect();
+        match self.emit_call(&llvm_ret_type, &callee, &final_args) {
+            Some(temp) => Ok(LLVMValue::new(llvm_ret_type, temp, ret_type)),
+            None => Ok(LLVMValue::void()),
+        }
+    }
+
+    /// 生成接口方法调用：根据对象的 type_id 找到实现类的 itable，按槽位间接调用
+    fn generate_interface_call(&mut self, interface_name: &str, method_name: &str, obj: &Expr, args: &[Expr]) -> cayResult<LLVMValue> {
+        let (slot_index, method) = self.find_interface_slot(interface_name, method_name)
+            .ok_or_else(|| codegen_error(format!("Interface '{}' has no method '{}'", interface_name, method_name)))?;
+
+        let obj_val = self.generate_expression(obj)?.repr;
+
+        // 参数转换为接口声明的参数类型
+        let mut final_args = vec![format!("i8* {}", obj_val)];
+        for (arg, param) in args.iter().zip(&method.params) {
+            let arg_result = self.generate_expression(arg)?;
+            let param_type = self.type_to_llvm(&param.param_type);
+            let converted = self.convert_value_to(&arg_result.ty, &arg_result.repr, &param_type);
+            final_args.push(format!("{} {}", param_type, converted));
+        }
+
+        let fn_type = self.interface_method_fn_type(&method);
+        let callee = self.emit_interface_method_ptr(interface_name, slot_index, &fn_type, &obj_val);
+        let llvm_ret_type = self.type_to_llvm(&method.return_type);
+        match self.emit_call(&llvm_ret_type, &callee, &final_args) {
+            Some(temp) => Ok(LLVMValue::new(llvm_ret_type, temp, method.return_type)),
+            None => Ok(LLVMValue::void()),
         }
     }
 
     /// 生成函数名 - 优先使用类型注册表中方法定义的参数类型，支持继承
-    fn generate_function_name(&self, class_name: &str, method_name: &str, processed_args: &[String], has_varargs_array: bool) -> String {
-        // 获取实际参数的类型签名
-        let arg_types: Vec<String> = processed_args.iter()
-            .enumerate()
-            .map(|(idx, r)| {
-                let (ty, _) = self.parse_typed_value(r);
-                let is_varargs_array = has_varargs_array && idx == processed_args.len() - 1;
-                let llvm_type = self.llvm_type_to_signature(&ty);
-                if is_varargs_array {
-                    "ai".to_string()
-                } else {
-                    llvm_type
-                }
-            })
-            .collect();
+    ///
+    /// `overload` 是语义分析选中的重载的参数，给出时按它的签名匹配（byte 与 char 的实参在 IR 中无法区分）。
+    fn generate_function_name(&self, class_name: &str, method_name: &str, processed_args: &[LLVMValue], has_varargs_array: bool, overload: Option<&[ParameterInfo]>) -> String {
+        let arg_types = self.overload_signature(class_name, method_name, processed_args, has_varargs_array, overload);
         
         // 尝试从类型注册表获取方法信息（支持继承查找）
         if let Some(ref registry) = self.type_registry {
@@ -168,18 +250,18 @@ impl IRGenerator {
                                 let fixed_count = param_count.saturating_sub(1);
                                 if arg_count >= fixed_count {
                                     // 检查固定参数类型是否匹配
-                                    let method_sig = self.build_function_name_from_method(&current_class_name, method_name, &method.params, has_varargs_array);
-                                    let expected_sig = format!("{}.__{}_{}", current_class_name, method_name, arg_types.join("_"));
+                                    let method_sig = self.build_function_name_from_method(&current_class_name, method_name, &method.params);
+                                    let expected_sig = mangle::mangle(&current_class_name, method_name, &arg_types);
                                     if method_sig == expected_sig {
-                                        return method_sig;
+                                        return self.method_symbol(&current_class_name, method);
                                     }
                                 }
                             } else if param_count == arg_count {
                                 // 非可变参数方法：检查参数类型是否匹配
-                                let method_sig = self.build_function_name_from_method(&current_class_name, method_name, &method.params, has_varargs_array);
-                                let expected_sig = format!("{}.__{}_{}", current_class_name, method_name, arg_types.join("_"));
+                                let method_sig = self.build_function_name_from_method(&current_class_name, method_name, &method.params);
+                                let expected_sig = mangle::mangle(&current_class_name, method_name, &arg_types);
                                 if method_sig == expected_sig {
-                                    return method_sig;
+                                    return self.method_symbol(&current_class_name, method);
                                 }
                             }
                         }
@@ -192,10 +274,10 @@ impl IRGenerator {
                             if is_varargs {
                                 let fixed_count = param_count.saturating_sub(1);
                                 if arg_count >= fixed_count {
-                                    return self.build_function_name_from_method(&current_class_name, method_name, &method.params, has_varargs_array);
+                                    return self.method_symbol(&current_class_name, method);
                                 }
                             } else if param_count == arg_count {
-                                return self.build_function_name_from_method(&current_class_name, method_name, &method.params, has_varargs_array);
+                                return self.method_symbol(&current_class_name, method);
                             }
                         }
                     }
@@ -211,66 +293,49 @@ impl IRGenerator {
         }
 
         // 回退到使用实际参数类型生成函数名
-        if arg_types.is_empty() {
-            format!("{}.{}", class_name, method_name)
-        } else {
-            format!("{}.__{}_{}", class_name, method_name, arg_types.join("_"))
-        }
+        mangle::mangle(class_name, method_name, &arg_types)
     }
 
-    /// 根据方法定义的参数类型构建函数名
-    fn build_function_name_from_method(&self, class_name: &str, method_name: &str, params: &[crate::types::ParameterInfo], has_varargs_array: bool) -> String {
-        if params.is_empty() {
-            return format!("{}.{}", class_name, method_name);
+    /// 调用的参数签名：有语义分析选中的重载时取其参数类型，否则取实际参数的类型
+    fn overload_signature(&self, class_name: &str, method_name: &str, processed_args: &[LLVMValue], has_varargs_array: bool, overload: Option<&[ParameterInfo]>) -> Vec<Type> {
+        match overload {
+            Some(params) => params.iter().map(|p| p.param_type.clone()).collect(),
+            None => self.call_signature(class_name, method_name, processed_args, has_varargs_array),
         }
+    }
 
-        let param_types: Vec<String> = params.iter()
+    /// 实际参数的类型
+    ///
+    /// 打包后的可变参数数组按方法声明的元素类型取数组类型（如 `double...` 为 `double[]`），
+    /// 与方法定义处的参数类型一致。
+    fn call_signature(&self, class_name: &str, method_name: &str, processed_args: &[LLVMValue], has_varargs_array: bool) -> Vec<Type> {
+        let varargs_elem = self.varargs_signature(class_name, method_name).map(|(_, elem)| elem);
+        processed_args.iter()
             .enumerate()
-            .map(|(idx, p)| {
-                let is_last_varargs = has_varargs_array && idx == params.len() - 1 && p.is_varargs;
-                self.param_type_to_signature(&p.param_type, is_last_varargs)
+            .map(|(idx, r)| match &varargs_elem {
+                Some(elem) if has_varargs_array && idx == processed_args.len() - 1 => Type::Array(Box::new(elem.clone())),
+                _ => r.lang_ty.clone(),
             })
-            .collect();
-
-        format!("{}.__{}_{}", class_name, method_name, param_types.join("_"))
+            .collect()
     }
 
-    /// 将参数类型转换为签名
-    fn param_type_to_signature(&self, ty: &crate::types::Type, is_varargs_array: bool) -> String {
-        if is_varargs_array {
-            return "ai".to_string(); // 可变参数数组签名
+    /// 调用方法时使用的函数名：绑定到 C 函数的 native 方法为 C 函数名，其他方法为修饰后的符号名
+    pub(crate) fn method_symbol(&self, class_name: &str, method: &MethodInfo) -> String {
+        match &method.link_name {
+            Some(link_name) => link_name.clone(),
+            None => self.build_function_name_from_method(class_name, &method.name, &method.params),
         }
+    }
 
-        match ty {
-            crate::types::Type::Int32 => "i".to_string(),
-            crate::types::Type::Int64 => "l".to_string(),
-            crate::types::Type::Float32 => "f".to_string(),
-            crate::types::Type::Float64 => "d".to_string(),
-            crate::types::Type::Bool => "b".to_string(),
-            crate::types::Type::String => "s".to_string(),
-            crate::types::Type::Char => "c".to_string(),
-            crate::types::Type::Object(name) => format!("o{}", name),
-            crate::types::Type::Array(inner) => format!("a{}", self.param_type_to_signature(inner, false)),
-            _ => "x".to_string(),
-        }
+    /// 根据方法定义的参数类型构建函数名
+    pub(crate) fn build_function_name_from_method(&self, class_name: &str, method_name: &str, params: &[ParameterInfo]) -> String {
+        let param_types: Vec<Type> = params.iter().map(|p| p.param_type.clone()).collect();
+        mangle::mangle(class_name, method_name, &param_types)
     }
 
     /// 获取方法的返回类型
-    fn get_method_return_type(&self, class_name: &str, method_name: &str, processed_args: &[String], has_varargs_array: bool) -> crate::types::Type {
-        // 获取实际参数的类型签名
-        let arg_types: Vec<String> = processed_args.iter()
-            .enumerate()
-            .map(|(idx, r)| {
-                let (ty, _) = self.parse_typed_value(r);
-                let is_varargs_array = has_varargs_array && idx == processed_args.len() - 1;
-                let llvm_type = self.llvm_type_to_signature(&ty);
-                if is_varargs_array {
-                    "ai".to_string()
-                } else {
-                    llvm_type
-                }
-            })
-            .collect();
+    fn get_method_return_type(&self, class_name: &str, method_name: &str, processed_args: &[LLVMValue], has_varargs_array: bool, overload: Option<&[ParameterInfo]>) -> crate::types::Type {
+        let arg_types = self.overload_signature(class_name, method_name, processed_args, has_varargs_array, overload);
         
         if let Some(ref registry) = self.type_registry {
             if let Some(class_info) = registry.get_class(class_name) {
@@ -285,15 +350,15 @@ impl IRGenerator {
                         if is_varargs {
                             let fixed_count = param_count.saturating_sub(1);
                             if arg_count >= fixed_count {
-                                let method_sig = self.build_function_name_from_method(class_name, method_name, &method.params, has_varargs_array);
-                                let expected_sig = format!("{}.__{}_{}", class_name, method_name, arg_types.join("_"));
+                                let method_sig = self.build_function_name_from_method(class_name, method_name, &method.params);
+                                let expected_sig = mangle::mangle(class_name, method_name, &arg_types);
                                 if method_sig == expected_sig {
                                     return method.return_type.clone();
                                 }
                             }
                         } else if param_count == arg_count {
-                            let method_sig = self.build_function_name_from_method(class_name, method_name, &method.params, has_varargs_array);
-                            let expected_sig = format!("{}.__{}_{}", class_name, method_name, arg_types.join("_"));
+                            let method_sig = self.build_function_name_from_method(class_name, method_name, &method.params);
+                            let expected_sig = mangle::mangle(class_name, method_name, &arg_types);
                             if method_sig == expected_sig {
                                 return method.return_type.clone();
                             }
@@ -343,6 +408,27 @@ impl IRGenerator {
     }
 
     /// 检查方法是否是实例方法（非静态方法）
+    /// 沿继承链查找声明了该方法的类，找不到时返回原类名
+    /// 不带对象的调用 `name(...)` 是否指向当前类（含父类）中的用户方法而不是同名内置函数
+    fn shadows_builtin(&self, name: &str) -> bool {
+        !self.current_class.is_empty()
+            && self.type_registry.as_ref()
+                .is_some_and(|registry| registry.get_method(&self.current_class, name).is_some())
+    }
+
+    fn resolve_method_owner(&self, class_name: &str, method_name: &str) -> String {
+        if let Some(ref registry) = self.type_registry {
+            let mut current = registry.get_class(class_name);
+            while let Some(class_info) = current {
+                if class_info.methods.contains_key(method_name) {
+                    return class_info.name.clone();
+                }
+                current = class_info.parent.as_deref().and_then(|p| registry.get_class(p));
+            }
+        }
+        class_name.to_string()
+    }
+
     fn is_instance_method(&self, class_name: &str, method_name: &str) -> bool {
         // 查询类型注册表
         if let Some(ref registry) = self.type_registry {
@@ -361,62 +447,87 @@ impl IRGenerator {
         false
     }
 
-    /// 将可变参数打包成数组
-    /// fixed_param_count: 固定参数的数量
-    fn pack_varargs_args(&mut self, _class_name: &str, method_name: &str, arg_results: &[String]) -> cayResult<Vec<String>> {
-        // 确定固定参数数量（这里需要根据实际方法定义来确定）
-        let fixed_param_count = match method_name {
-            "sum" => 0,  // sum(int... numbers) 没有固定参数
-            "printAll" => 1,  // printAll(string prefix, int... numbers) 有1个固定参数
-            "multiplyAndAdd" => 1,  // multiplyAndAdd(int multiplier, int... numbers) 有1个固定参数
-            _ => 0,
+    /// 接收者表达式是否是内置类的类名
+    pub(crate) fn is_builtin_class_reference(&self, obj: &Expr) -> bool {
+        matches!(obj, Expr::Identifier(name)
+            if self.type_registry.as_ref().is_some_and(|r| r.is_builtin_class(name)))
+            && self.is_class_reference(obj)
+    }
+
+    /// 接收者表达式是否是类名（而不是同名的局部变量或字段）
+    fn is_class_reference(&self, obj: &Expr) -> bool {
+        let Expr::Identifier(name) = obj else { return false };
+        let Some(registry) = &self.type_registry else { return false };
+        self.scope_manager.get_llvm_name(name).is_none()
+            && registry.find_field(&self.current_class, name).is_none()
+            && registry.class_exists(name)
+    }
+
+    /// 查找可变参数方法的固定参数数量和可变参数的元素类型
+    fn varargs_signature(&self, class_name: &str, method_name: &str) -> Option<(usize, Type)> {
+        let methods = self.type_registry.as_ref()?.get_class(class_name)?.methods.get(method_name)?;
+        methods.iter().find_map(|method| {
+            let last = method.params.last().filter(|p| p.is_varargs)?;
+            let Type::Array(elem_type) = &last.param_type else {
+                return None;
+            };
+            Some((method.params.len() - 1, (**elem_type).clone()))
+        })
+    }
+
+    /// 将可变参数打包成数组，元素按方法声明的可变参数类型转换和存放
+    ///
+    /// 打包的数组与 `new T[n]` 布局相同（数据前 8 字节存放长度），方法体内可以使用 `.length`；
+    /// 没有可变参数时传入长度为 0 的数组，直接传入一个同类型数组时原样传递。
+    fn pack_varargs_args(&mut self, class_name: &str, method_name: &str, arg_results: &[LLVMValue]) -> cayResult<Vec<LLVMValue>> {
+        let Some((fixed_param_count, elem_type)) = self.varargs_signature(class_name, method_name) else {
+            return Ok(arg_results.to_vec());
         };
 
-        if arg_results.len() <= fixed_param_count {
-            // 参数数量不足或刚好，不需要打包
+        if arg_results.len() < fixed_param_count {
+            // 参数数量不足，由语义分析报告错误
             return Ok(arg_results.to_vec());
         }
 
+        let elem_llvm_type = elem_type.llvm_of();
+        let array_llvm_type = format!("{}*", elem_llvm_type);
+
         // 分割固定参数和可变参数
         let fixed_args = &arg_results[..fixed_param_count];
         let varargs = &arg_results[fixed_param_count..];
 
-        // 创建数组来存储可变参数
-        let array_size = varargs.len();
-        let array_type = "i32";  // 假设可变参数是 int 类型
-        let array_ptr = self.new_temp();
-
-        // 分配数组内存
-        let elem_size = 4;  // i32 占 4 字节
-        let total_size = array_size * elem_size;
-        self.emit_line(&format!("  {} = call i8* @calloc(i64 1, i64 {})", array_ptr, total_size));
-
-        // 将可变参数存入数组
-        for (i, arg_str) in varargs.iter().enumerate() {
-            let (arg_type, arg_val) = self.parse_typed_value(arg_str);
-            let elem_ptr_i8 = self.new_temp();
-            let elem_ptr_i32 = self.new_temp();
-            let offset = i * elem_size;
-
-            // 计算元素地址 (i8*)
-            self.emit_line(&format!("  {} = getelementptr i8, i8* {}, i64 {}", elem_ptr_i8, array_ptr, offset));
-
-            // 将 i8* 转换为 i32*
-            self.emit_line(&format!("  {} = bitcast i8* {} to i32*", elem_ptr_i32, elem_ptr_i8));
-
-            // 将值转换为 i32 并存储
-            if arg_type == "i64" {
-                let truncated = self.new_temp();
-                self.emit_line(&format!("  {} = trunc i64 {} to i32", truncated, arg_val));
-                self.emit_line(&format!("  store i32 {}, i32* {}, align 4", truncated, elem_ptr_i32));
-            } else if arg_type == "i32" {
-                self.emit_line(&format!("  store i32 {}, i32* {}, align 4", arg_val, elem_ptr_i32));
-            }
+        // 直接传入数组：f(arr)
+        if let [single] = varargs
+            && single.ty == array_llvm_type
+        {
+            return Ok(arg_results.to_vec());
+        }
+
+        // 分配数组内存：8 字节长度头 + 数据
+        let elem_size = elem_type.size_of(&self.data_layout);
+        let total_size = 8 + varargs.len() * elem_size;
+        let raw_ptr = self.new_temp();
+        self.emit_line(&format!("  {} = call i8* @calloc(i64 1, i64 {})", raw_ptr, total_size));
+        let len_ptr = self.new_temp();
+        self.emit_line(&format!("  {} = bitcast i8* {} to i32*", len_ptr, raw_ptr));
+        self.emit_store("i32", &varargs.len().to_string(), &len_ptr);
+        let data_ptr = self.new_temp();
+        self.emit_line(&format!("  {} = getelementptr i8, i8* {}, i64 8", data_ptr, raw_ptr));
+        let typed_ptr = self.new_temp();
+        self.emit_line(&format!("  {} = bitcast i8* {} to {}", typed_ptr, data_ptr, array_llvm_type));
+
+        // 将可变参数转换为元素类型后存入数组
+        for (i, arg) in varargs.iter().enumerate() {
+            let elem_ptr = self.new_temp();
+            self.emit_line(&format!("  {} = getelementptr inbounds {}, {} {}, i64 {}",
+                elem_ptr, elem_llvm_type, array_llvm_type, typed_ptr, i));
+            let converted = self.convert_value_to(&arg.ty, &arg.repr, &elem_llvm_type);
+            self.emit_store(&elem_llvm_type, &converted, &elem_ptr);
         }
 
         // 构建结果：固定参数 + 数组指针
         let mut result = fixed_args.to_vec();
-        result.push(format!("i8* {}", array_ptr));
+        result.push(LLVMValue::new(array_llvm_type, typed_ptr, Type::Array(Box::new(elem_type))));
 
         Ok(result)
     }
@@ -3,7 +3,9 @@
 //! 处理整数、浮点数、指针之间的类型转换，以及到字符串的转换。
 
 use crate::codegen::context::IRGenerator;
+use crate::codegen::value::LLVMValue;
 use crate::ast::*;
+use crate::types::Type;
 use crate::error::{cayResult, codegen_error};
 
 impl IRGenerator {
@@ -11,23 +13,31 @@ impl IRGenerator {
     ///
     /// # Arguments
     /// * `cast` - 类型转换表达式
-    pub fn generate_cast_expression(&mut self, cast: &CastExpr) -> cayResult<String> {
+    pub fn generate_cast_expression(&mut self, cast: &CastExpr) -> cayResult<LLVMValue> {
         let expr_value = self.generate_expression(&cast.expr)?;
-        let (from_type, val) = self.parse_typed_value(&expr_value);
+        let (from_type, val) = (&expr_value.ty, &expr_value.repr);
         let to_type = self.type_to_llvm(&cast.target_type);
+
+        // 转换为 String 按操作数的静态类型转换（char 编码为字符，boolean 为 true/false）
+        if cast.target_type == Type::String
+            && let Some(operand_type) = self.cast_types.get(&cast.id).cloned()
+        {
+            let text = self.generate_to_string(&operand_type, &expr_value)?;
+            return Ok(LLVMValue::from_llvm("i8*", text));
+        }
         
         let temp = self.new_temp();
         
         // 相同类型无需转换
-        if from_type == to_type {
-            return Ok(format!("{} {}", to_type, val));
+        if *from_type == to_type {
+            return Ok(LLVMValue::from_llvm(to_type, val));
         }
         
         // 指针类型转换 (bitcast)
         if from_type.ends_with("*") && to_type.ends_with("*") {
             self.emit_line(&format!("  {} = bitcast {} {} to {}",
                 temp, from_type, val, to_type));
-            return Ok(format!("{} {}", to_type, temp));
+            return Ok(LLVMValue::from_llvm(to_type, temp));
         }
         
         // 整数到整数
@@ -44,7 +54,7 @@ impl IRGenerator {
                 self.emit_line(&format!("  {} = trunc {} {} to {}",
                     temp, from_type, val, to_type));
             }
-            return Ok(format!("{} {}", to_type, temp));
+            return Ok(LLVMValue::from_llvm(to_type, temp));
         }
         
         // 整数到浮点
@@ -52,7 +62,7 @@ impl IRGenerator {
            (to_type == "float" || to_type == "double") {
             self.emit_line(&format!("  {} = sitofp {} {} to {}",
                 temp, from_type, val, to_type));
-            return Ok(format!("{} {}", to_type, temp));
+            return Ok(LLVMValue::from_llvm(to_type, temp));
         }
         
         // 浮点到整数
@@ -60,7 +70,7 @@ impl IRGenerator {
            to_type.starts_with("i") && !to_type.ends_with("*") {
             self.emit_line(&format!("  {} = fptosi {} {} to {}",
                 temp, from_type, val, to_type));
-            return Ok(format!("{} {}", to_type, temp));
+            return Ok(LLVMValue::from_llvm(to_type, temp));
         }
         
         // 浮点到浮点
@@ -73,7 +83,7 @@ impl IRGenerator {
                 self.emit_line(&format!("  {} = fptrunc {} {} to {}",
                     temp, from_type, val, to_type));
             }
-            return Ok(format!("{} {}", to_type, temp));
+            return Ok(LLVMValue::from_llvm(to_type, temp));
         }
         
         // 浮点到字符串（float/double -> String）
@@ -90,18 +100,10 @@ impl IRGenerator {
 
             // 调用专门的运行时函数来避免调用约定问题
             let result = self.new_temp();
-            self.emit_line(&format!("  {} = call i8* @__cay_float_to_string(double {})",
-                result, arg_val));
+            self.emit_line(&format!("  {} = call i8* @__cay_double_to_string(double {}, i1 {})",
+                result, arg_val, if from_type == "float" { 1 } else { 0 }));
 
-            return Ok(format!("{} {}", to_type, result));
-        }
-        
-        // 字符到字符串（char -> String）- 必须在整数转字符串之前处理
-        if from_type == "i8" && to_type == "i8*" {
-            let result = self.new_temp();
-            self.emit_line(&format!("  {} = call i8* @__cay_char_to_string(i8 {})",
-                result, val));
-            return Ok(format!("{} {}", to_type, result));
+            return Ok(LLVMValue::from_llvm(to_type, result));
         }
         
         // 布尔到字符串（bool -> String）
@@ -118,7 +120,7 @@ impl IRGenerator {
             };
             self.emit_line(&format!("  {} = call i8* @__cay_bool_to_string(i1 {})",
                 result, bool_val));
-            return Ok(format!("{} {}", to_type, result));
+            return Ok(LLVMValue::from_llvm(to_type, result));
         }
         
         // 整数到字符串（int -> String）- 放在字符和布尔之后
@@ -134,7 +136,7 @@ impl IRGenerator {
             };
             self.emit_line(&format!("  {} = call i8* @__cay_int_to_string(i64 {})",
                 result, i64_val));
-            return Ok(format!("{} {}", to_type, result));
+            return Ok(LLVMValue::from_llvm(to_type, result));
         }
         
         Err(codegen_error(format!("Unsupported cast from {} to {}", from_type, to_type)))
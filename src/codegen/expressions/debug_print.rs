@@ -0,0 +1,184 @@
+//! debugPrint 代码生成
+//!
+//! `debugPrint(expr)` 输出 `[line N] 表达式: 类型 = 值`。表达式文本和静态类型由语义分析记录，
+//! 值按静态类型格式化：字符串和字符带引号，数组逐个元素输出为 `[a, b]`，
+//! 定义了 `toString()` 的对象调用它，其余对象按布局信息输出字段 `Point{x=1, y=2}`。
+
+use crate::codegen::context::IRGenerator;
+use crate::codegen::value::LLVMValue;
+use crate::ast::*;
+use crate::types::Type;
+use crate::error::{cayResult, codegen_error};
+
+/// 展开对象字段和数组元素的最大嵌套层数，更深的对象按 `toString()` 或 `类名@地址` 输出
+const MAX_DEBUG_DEPTH: usize = 3;
+
+impl IRGenerator {
+    /// 生成 debugPrint 调用代码
+    pub fn generate_debug_print_call(&mut self, call: &CallExpr) -> cayResult<LLVMValue> {
+        let (text, ty) = self.debug_prints.get(&call.id).cloned()
+            .ok_or_else(|| codegen_error("debugPrint() argument has no recorded type".to_string()))?;
+        let value = self.generate_expression(&call.args[0])?;
+        let rendered = self.generate_debug_string(&ty, &value, 0)?;
+
+        // 表达式文本可能含有 '%'，作为参数传给 printf 而不是拼进格式字符串
+        let header = format!("[line {}] {}: {} = ", call.loc.line, text, ty.source_name());
+        let header_ptr = self.get_string_constant_ptr(&header);
+        let fmt_ptr = self.get_string_constant_ptr("%s%s\n");
+        self.emit_line(&format!("  call i32 (i8*, ...) @printf(i8* {}, i8* {}, i8* {})",
+            fmt_ptr, header_ptr, rendered));
+        Ok(LLVMValue::void())
+    }
+
+    /// 把值按静态类型 `ty` 格式化为调试文本，返回 `i8*` 寄存器
+    fn generate_debug_string(&mut self, ty: &Type, value: &LLVMValue, depth: usize) -> cayResult<String> {
+        let val = value.repr.clone();
+        match ty {
+            Type::String => self.emit_unless_null(value, |this| {
+                let quoted = this.emit_concat_constant("\"", &val, true);
+                Ok(this.emit_concat_constant("\"", &quoted, false))
+            }),
+            Type::Char => {
+                let text = self.generate_to_string(ty, value)?;
+                let quoted = self.emit_concat_constant("'", &text, true);
+                Ok(self.emit_concat_constant("'", &quoted, false))
+            }
+            Type::Array(elem) => {
+                let elem = elem.as_ref().clone();
+                self.emit_unless_null(value, |this| this.generate_debug_array(&elem, &val, depth))
+            }
+            Type::Object(class_name) if depth < MAX_DEBUG_DEPTH
+                && !self.has_to_string(class_name)
+                && !self.is_interface(class_name)
+                && self.get_class_layout(class_name).is_some() =>
+            {
+                let class_name = class_name.clone();
+                self.emit_unless_null(value, |this| this.generate_debug_fields(&class_name, &val, depth))
+            }
+            Type::Function(_) => Ok(self.get_string_constant_ptr("<function>")),
+            _ => self.generate_to_string(ty, value),
+        }
+    }
+
+    /// 逐个元素格式化非 null 数组：`[a, b, c]`
+    ///
+    /// 元素的格式化可能含有分支，因此循环变量和累积的文本都放在栈槽中而不用 phi。
+    fn generate_debug_array(&mut self, elem: &Type, arr: &str, depth: usize) -> cayResult<String> {
+        let elem_llvm = self.type_to_llvm(elem);
+        let index_slot = format!("{}.idx", self.new_temp());
+        let text_slot = format!("{}.text", self.new_temp());
+        self.emit_entry_alloca(&format!("  {} = alloca i32, align 4", index_slot));
+        self.emit_entry_alloca(&format!("  {} = alloca i8*, align 8", text_slot));
+        let open = self.get_string_constant_ptr("[");
+        self.emit_store("i8*", &open, &text_slot);
+        self.emit_store("i32", "0", &index_slot);
+
+        // 数组长度存储在数据指针前面的 8 字节中
+        let arr_i8 = self.new_temp();
+        self.emit_line(&format!("  {} = bitcast {}* {} to i8*", arr_i8, elem_llvm, arr));
+        let len_ptr_i8 = self.new_temp();
+        self.emit_line(&format!("  {} = getelementptr i8, i8* {}, i64 -8", len_ptr_i8, arr_i8));
+        let len_ptr = self.new_temp();
+        self.emit_line(&format!("  {} = bitcast i8* {} to i32*", len_ptr, len_ptr_i8));
+        let len = self.emit_load("i32", &len_ptr);
+
+        let cond_label = self.new_label("debug.array.cond");
+        let body_label = self.new_label("debug.array.body");
+        let end_label = self.new_label("debug.array.end");
+        self.emit_br(&cond_label);
+
+        self.emit_label(&cond_label);
+        let index = self.emit_load("i32", &index_slot);
+        let at_end = self.new_temp();
+        self.emit_line(&format!("  {} = icmp sge i32 {}, {}", at_end, index, len));
+        self.emit_cond_br(&at_end, &end_label, &body_label);
+
+        self.emit_label(&body_label);
+        let wide_index = self.new_temp();
+        self.emit_line(&format!("  {} = sext i32 {} to i64", wide_index, index));
+        let elem_ptr = self.new_temp();
+        self.emit_line(&format!("  {} = getelementptr inbounds {}, {}* {}, i64 {}",
+            elem_ptr, elem_llvm, elem_llvm, arr, wide_index));
+        let elem_val = self.emit_load(&elem_llvm, &elem_ptr);
+        let is_first = self.new_temp();
+        self.emit_line(&format!("  {} = icmp eq i32 {}, 0", is_first, index));
+        let empty = self.get_string_constant_ptr("");
+        let comma = self.get_string_constant_ptr(", ");
+        let separator = self.new_temp();
+        self.emit_line(&format!("  {} = select i1 {}, i8* {}, i8* {}", separator, is_first, empty, comma));
+        let text = self.emit_load("i8*", &text_slot);
+        let text = self.emit_concat(&text, &separator);
+        self.emit_store("i8*", &text, &text_slot);
+        let elem_text = self.generate_debug_string(elem, &LLVMValue::new(elem_llvm, elem_val, elem.clone()), depth + 1)?;
+        let text = self.emit_load("i8*", &text_slot);
+        let text = self.emit_concat(&text, &elem_text);
+        self.emit_store("i8*", &text, &text_slot);
+        let index = self.emit_load("i32", &index_slot);
+        let next = self.new_temp();
+        self.emit_line(&format!("  {} = add i32 {}, 1", next, index));
+        self.emit_store("i32", &next, &index_slot);
+        self.emit_br(&cond_label);
+
+        self.emit_label(&end_label);
+        let text = self.emit_load("i8*", &text_slot);
+        Ok(self.emit_concat_constant("]", &text, false))
+    }
+
+    /// 按布局信息格式化非 null 对象的字段（包括继承的字段，按布局顺序）：`Point{x=1, y=2}`
+    ///
+    /// 使用静态类型的布局，子类对象只输出静态类型可见的字段。
+    fn generate_debug_fields(&mut self, class_name: &str, obj: &str, depth: usize) -> cayResult<String> {
+        let mut fields: Vec<_> = self.get_class_layout(class_name)
+            .map(|layout| layout.fields.values().cloned().collect())
+            .unwrap_or_default();
+        fields.sort_by_key(|field| field.index);
+
+        let mut text = self.get_string_constant_ptr(&format!("{}{{", class_name));
+        for (i, field) in fields.iter().enumerate() {
+            let label = format!("{}{}=", if i == 0 { "" } else { ", " }, field.name);
+            text = self.emit_concat_constant(&label, &text, false);
+            let field_ptr = self.emit_field_ptr(class_name, field, obj);
+            let field_val = self.emit_load(&field.llvm_type, &field_ptr);
+            let field_value = LLVMValue::new(field.llvm_type.clone(), field_val, field.field_type.clone());
+            let field_text = self.generate_debug_string(&field.field_type, &field_value, depth + 1)?;
+            text = self.emit_concat(&text, &field_text);
+        }
+        Ok(self.emit_concat_constant("}", &text, false))
+    }
+
+    /// 指针为 null 时结果为 "null"，否则为 `format` 生成的文本；结果通过栈槽在分支间传递
+    fn emit_unless_null(&mut self, value: &LLVMValue, format: impl FnOnce(&mut Self) -> cayResult<String>) -> cayResult<String> {
+        let slot = format!("{}.debug", self.new_temp());
+        self.emit_entry_alloca(&format!("  {} = alloca i8*, align 8", slot));
+        self.emit_store("i8*", "getelementptr ([5 x i8], [5 x i8]* @.str.null_str, i64 0, i64 0)", &slot);
+        let is_null = self.new_temp();
+        let format_label = self.new_label("debug.format");
+        let end_label = self.new_label("debug.end");
+        self.emit_line(&format!("  {} = icmp eq {}, null", is_null, value));
+        self.emit_cond_br(&is_null, &end_label, &format_label);
+
+        self.emit_label(&format_label);
+        let text = format(self)?;
+        self.emit_store("i8*", &text, &slot);
+        self.emit_br(&end_label);
+
+        self.emit_label(&end_label);
+        Ok(self.emit_load("i8*", &slot))
+    }
+
+    /// 拼接两个 `i8*` 字符串
+    fn emit_concat(&mut self, left: &str, right: &str) -> String {
+        self.emit_call("i8*", "@__cay_string_concat", &[format!("i8* {}", left), format!("i8* {}", right)])
+            .unwrap_or_default()
+    }
+
+    /// 把常量字符串拼接到 `text` 之前（`prepend`）或之后
+    fn emit_concat_constant(&mut self, constant: &str, text: &str, prepend: bool) -> String {
+        let constant_ptr = self.get_string_constant_ptr(constant);
+        if prepend {
+            self.emit_concat(&constant_ptr, text)
+        } else {
+            self.emit_concat(text, &constant_ptr)
+        }
+    }
+}
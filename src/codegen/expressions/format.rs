@@ -0,0 +1,116 @@
+//! 格式化字符串代码生成
+//!
+//! `format(fmt, args...)` 展开为两次 `snprintf` 调用：第一次以空缓冲区求出结果的字节数，
+//! 分配后再写入。格式字符串由语义分析解析，说明符按参数的静态类型改写为 C 的说明符：
+//! 整数扩展为 i64 后加长度修饰（`%x`/`%o` 按类型的位宽零扩展，`(byte) -1` 输出 `ff`），
+//! 浮点数扩展为 double，`%s`/`%b` 的参数按字符串拼接的规则转换为字符串，null 字符串输出 "null"。
+//! `print(fmt, args...)`/`println(fmt, args...)` 打印格式化的结果。
+
+use crate::codegen::context::IRGenerator;
+use crate::codegen::value::LLVMValue;
+use crate::ast::*;
+use crate::semantic::{FormatPiece, FormatSpec};
+use crate::types::Type;
+use crate::error::{cayResult, codegen_error};
+
+impl IRGenerator {
+    /// 生成 format 调用，返回 `i8*` 字符串
+    pub fn generate_format_call(&mut self, call: &CallExpr) -> cayResult<LLVMValue> {
+        let (pieces, arg_types) = self.format_calls.get(&call.id).cloned()
+            .ok_or_else(|| codegen_error("format() call has no recorded format string".to_string()))?;
+
+        let mut c_format = String::new();
+        let mut c_args = Vec::with_capacity(arg_types.len());
+        let mut args = call.args[1..].iter().zip(&arg_types);
+        for piece in &pieces {
+            match piece {
+                FormatPiece::Text(text) => c_format.push_str(&text.replace('%', "%%")),
+                FormatPiece::Arg(spec) => {
+                    let (arg, ty) = args.next()
+                        .ok_or_else(|| codegen_error("format() has fewer arguments than specifiers".to_string()))?;
+                    let value = self.generate_expression(arg)?;
+                    let (conversion, c_arg) = self.format_argument(spec, ty, &value)?;
+                    c_format.push_str(&format!("%{}", spec.flags));
+                    if let Some(width) = spec.width {
+                        c_format.push_str(&width.to_string());
+                    }
+                    if let Some(precision) = spec.precision {
+                        c_format.push_str(&format!(".{}", precision));
+                    }
+                    c_format.push_str(&conversion);
+                    c_args.push(c_arg);
+                }
+            }
+        }
+
+        let fmt_ptr = self.get_string_constant_ptr(&c_format);
+        let extra: String = c_args.iter().map(|arg| format!(", {}", arg)).collect();
+        let len = self.new_temp();
+        self.emit_line(&format!("  {} = call i32 (i8*, i64, i8*, ...) @snprintf(i8* null, i64 0, i8* {}{})",
+            len, fmt_ptr, extra));
+        let len_wide = self.new_temp();
+        self.emit_line(&format!("  {} = sext i32 {} to i64", len_wide, len));
+        let size = self.new_temp();
+        self.emit_line(&format!("  {} = add i64 {}, 1", size, len_wide));
+        let buf = self.emit_call("i8*", "@calloc", &["i64 1".to_string(), format!("i64 {}", size)]).unwrap_or_default();
+        self.emit_line(&format!("  call i32 (i8*, i64, i8*, ...) @snprintf(i8* {}, i64 {}, i8* {}{})",
+            buf, size, fmt_ptr, extra));
+        Ok(LLVMValue::new("i8*", buf, Type::String))
+    }
+
+    /// 生成 print(fmt, args...)/println(fmt, args...) 调用
+    pub(super) fn generate_formatted_print(&mut self, call: &CallExpr, newline: bool) -> cayResult<LLVMValue> {
+        let text = self.generate_format_call(call)?;
+        let fmt_ptr = self.get_string_constant_ptr(if newline { "%s\n" } else { "%s" });
+        self.emit_line(&format!("  call i32 (i8*, ...) @printf(i8* {}, {})", fmt_ptr, text));
+        Ok(LLVMValue::void())
+    }
+
+    /// 把一个参数转换为 snprintf 的实参
+    ///
+    /// # Returns
+    /// (C 说明符的长度修饰和转换字符, 带类型的实参)
+    fn format_argument(&mut self, spec: &FormatSpec, ty: &Type, value: &LLVMValue) -> cayResult<(String, String)> {
+        let (llvm_type, val) = (value.ty.clone(), value.repr.clone());
+        match spec.conversion {
+            'd' | 'x' | 'X' | 'o' => {
+                // "%ld" / "%lld" 去掉 % 和 d 即为 i64 的长度修饰
+                let length = self.get_i64_format_specifier().trim_start_matches('%').trim_end_matches('d');
+                let conversion = format!("{}{}", length, spec.conversion);
+                let wide = if llvm_type == "i64" {
+                    val
+                } else {
+                    let ext = if spec.conversion == 'd' { "sext" } else { "zext" };
+                    let temp = self.new_temp();
+                    self.emit_line(&format!("  {} = {} {} {} to i64", temp, ext, llvm_type, val));
+                    temp
+                };
+                Ok((conversion, format!("i64 {}", wide)))
+            }
+            'f' | 'e' | 'E' => {
+                let wide = self.convert_value_to(&llvm_type, &val, "double");
+                Ok((spec.conversion.to_string(), format!("double {}", wide)))
+            }
+            'c' => {
+                // C 的 %c 只输出一个字节，码点先按 UTF-8 编码为字符串
+                let text = self.generate_to_string(&Type::Char, value)?;
+                Ok(("s".to_string(), format!("i8* {}", text)))
+            }
+            _ => {
+                let text = self.generate_to_string(ty, value)?;
+                let text = if *ty == Type::String {
+                    let is_null = self.new_temp();
+                    self.emit_line(&format!("  {} = icmp eq i8* {}, null", is_null, text));
+                    let selected = self.new_temp();
+                    self.emit_line(&format!(
+                        "  {} = select i1 {}, i8* getelementptr ([5 x i8], [5 x i8]* @.str.null_str, i64 0, i64 0), i8* {}",
+                        selected, is_null, text));
+                    selected
+                } else {
+                    text
+                };
+                Ok(("s".to_string(), format!("i8* {}", text)))
+            }
+        }
+    }
+}
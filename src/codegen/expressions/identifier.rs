@@ -1,8 +1,10 @@
 //! 标识符表达式代码生成
 //!
-//! 处理变量访问、静态字段访问和隐式 this 访问。
+//! 处理变量访问、静态字段访问、this/super 和隐式 this 访问。
 
 use crate::codegen::context::IRGenerator;
+use crate::codegen::value::LLVMValue;
+use crate::types::Type;
 use crate::error::cayResult;
 
 impl IRGenerator {
@@ -10,13 +12,24 @@ impl IRGenerator {
     ///
     /// # Arguments
     /// * `name` - 标识符名称
-    pub fn generate_identifier(&mut self, name: &str) -> cayResult<String> {
+    pub fn generate_identifier(&mut self, name: &str) -> cayResult<LLVMValue> {
+        // this 是方法的隐藏首参数，保存在 this_ptr 中；super 是以父类类型看待的当前对象
+        if name == "this" || name == "super" {
+            let class_name = match name {
+                "super" => self.class_layouts.get(&self.current_class)
+                    .and_then(|layout| layout.parent.clone())
+                    .unwrap_or_else(|| "Object".to_string()),
+                _ => self.current_class.clone(),
+            };
+            return Ok(LLVMValue::new("i8*", self.load_this_ptr(), Type::Object(class_name)));
+        }
+
         // 检查是否是类名（静态成员访问的上下文）
         if let Some(ref registry) = self.type_registry {
             if registry.class_exists(name) {
                 // 类名不应该单独作为表达式使用
                 // 返回一个占位符，实际使用应该在 MemberAccess 中处理
-                return Ok(format!("i64 0"));
+                return Ok(LLVMValue::new("i64", "0", Type::Object(name.to_string())));
             }
         }
 
@@ -25,10 +38,8 @@ impl IRGenerator {
             let static_key = format!("{}.{}", self.current_class, name);
             if let Some(field_info) = self.static_field_map.get(&static_key).cloned() {
                 let temp = self.new_temp();
-                let align = self.get_type_align(&field_info.llvm_type);
-                self.emit_line(&format!("  {} = load {}, {}* {}, align {}",
-                    temp, field_info.llvm_type, field_info.llvm_type, field_info.name, align));
-                return Ok(format!("{} {}", field_info.llvm_type, temp));
+                self.emit_load_into(&temp, &field_info.llvm_type, &field_info.name);
+                return Ok(LLVMValue::new(field_info.llvm_type, temp, field_info.field_type));
             }
         }
 
@@ -47,48 +58,31 @@ impl IRGenerator {
                 let var_type = self.var_types.get(name).cloned().unwrap_or_else(|| "i64".to_string());
                 (var_type, name.to_string())
             };
-            let align = self.get_type_align(&var_type);  // 获取正确的对齐
-            self.emit_line(&format!("  {} = load {}, {}* %{}, align {}",
-                temp, var_type, var_type, llvm_name, align));
-            return Ok(format!("{} {}", var_type, temp));
+            self.emit_load_into(&temp, &var_type, &format!("%{}", llvm_name));
+            return Ok(match self.scope_manager.get_var_lang_type(name) {
+                Some(lang_type) => LLVMValue::new(var_type, temp, lang_type),
+                None => LLVMValue::from_llvm(var_type, temp),
+            });
         }
 
         // 尝试作为实例字段访问（隐式 this）
         if !self.current_class.is_empty() {
             if let Some(field_info) = self.get_instance_field(&self.current_class, name).cloned() {
-                // 获取 this 指针
-                let this_llvm_name = self.scope_manager.get_llvm_name("this_ptr")
-                    .unwrap_or_else(|| "this_ptr_s1".to_string());
-                let this_temp = self.new_temp();
-                self.emit_line(&format!("  {} = load i8*, i8** %{}, align 8", 
-                    this_temp, this_llvm_name));
-                
-                // 计算字段地址: this_ptr + offset
-                let field_ptr_i8 = self.new_temp();
-                self.emit_line(&format!("  {} = getelementptr i8, i8* {}, i64 {}", 
-                    field_ptr_i8, this_temp, field_info.offset));
-                
-                // 将字段指针转换为正确类型的指针
-                let field_ptr = self.new_temp();
-                self.emit_line(&format!("  {} = bitcast i8* {} to {}*", 
-                    field_ptr, field_ptr_i8, field_info.llvm_type));
+                let class_name = self.current_class.clone();
+                let this_ptr = self.load_this_ptr();
+                let field_ptr = self.emit_field_ptr(&class_name, &field_info, &this_ptr);
                 
                 // 加载字段值
-                let field_val = self.new_temp();
-                self.emit_line(&format!("  {} = load {}, {}* {}, align {}", 
-                    field_val, field_info.llvm_type, field_info.llvm_type, field_ptr,
-                    self.get_type_align(&field_info.llvm_type)));
+                let field_val = self.emit_load(&field_info.llvm_type, &field_ptr);
                 
-                return Ok(format!("{} {}", field_info.llvm_type, field_val));
+                return Ok(LLVMValue::new(field_info.llvm_type, field_val, field_info.field_type));
             }
         }
 
         // 未定义的变量，回退到旧行为（可能会报错）
         let temp = self.new_temp();
         let var_type = self.var_types.get(name).cloned().unwrap_or_else(|| "i64".to_string());
-        let align = self.get_type_align(&var_type);
-        self.emit_line(&format!("  {} = load {}, {}* %{}, align {}",
-            temp, var_type, var_type, name, align));
-        Ok(format!("{} {}", var_type, temp))
+        self.emit_load_into(&temp, &var_type, &format!("%{}", name));
+        Ok(LLVMValue::from_llvm(var_type, temp))
     }
 }
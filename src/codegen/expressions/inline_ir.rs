@@ -0,0 +1,67 @@
+//! 内联 IR 表达式代码生成
+//!
+//! `__llvm__<T>("template", args...)` 先按顺序求值实参，再把模板中的占位符替换为实参的值和类型、
+//! 结果寄存器和结果类型，逐行插入当前基本块。模板中自己命名的局部值（`%sum`）每次展开都加上唯一的后缀，
+//! 同一模板在一个函数中展开多次也不会重名。
+
+use crate::codegen::context::IRGenerator;
+use crate::codegen::value::LLVMValue;
+use crate::ast::*;
+use crate::types::Type;
+use crate::error::{cayResult, codegen_error};
+
+/// 给文本中的局部值名加上后缀：`%sum` → `%sum.inline.3`（类的结构体类型 `%class.Point` 保持不变）
+fn rename_locals(text: &str, suffix: &str) -> String {
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '.' || c == '_';
+    let mut renamed = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('%') {
+        let name_len = rest[at + 1..].find(|c: char| !is_name_char(c)).unwrap_or(rest.len() - at - 1);
+        renamed.push_str(&rest[..at + 1 + name_len]);
+        if name_len > 0 && !rest[at + 1..].starts_with("class.") {
+            renamed.push('.');
+            renamed.push_str(suffix);
+        }
+        rest = &rest[at + 1 + name_len..];
+    }
+    renamed.push_str(rest);
+    renamed
+}
+
+impl IRGenerator {
+    /// 生成内联 IR 表达式代码
+    ///
+    /// # Arguments
+    /// * `inline` - 内联 IR 表达式
+    pub fn generate_inline_ir(&mut self, inline: &InlineIrExpr) -> cayResult<LLVMValue> {
+        let pieces = inline.pieces().map_err(codegen_error)?;
+        let args = inline.args.iter()
+            .map(|arg| self.generate_expression(arg))
+            .collect::<cayResult<Vec<_>>>()?;
+
+        let has_result = inline.result_type != Type::Void;
+        let result_type = self.type_to_llvm(&inline.result_type);
+        let result = if has_result { self.new_temp() } else { String::new() };
+        let suffix = self.new_label("inline");
+
+        let mut code = String::new();
+        for piece in &pieces {
+            match piece {
+                IrTemplatePiece::Text(text) => code.push_str(&rename_locals(text, &suffix)),
+                IrTemplatePiece::Arg(index) => code.push_str(&args[*index].repr),
+                IrTemplatePiece::ArgType(index) => code.push_str(&args[*index].ty),
+                IrTemplatePiece::Out => code.push_str(&result),
+                IrTemplatePiece::OutType => code.push_str(&result_type),
+            }
+        }
+        for line in code.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with(';')) {
+            self.emit_line(&format!("  {}", line));
+        }
+
+        if has_result {
+            Ok(LLVMValue::new(result_type, result, inline.result_type.clone()))
+        } else {
+            Ok(LLVMValue::void())
+        }
+    }
+}
@@ -3,6 +3,7 @@
 //! 处理类型检查表达式。
 
 use crate::codegen::context::IRGenerator;
+use crate::codegen::value::LLVMValue;
 use crate::ast::*;
 use crate::error::{cayResult, codegen_error};
 
@@ -11,9 +12,9 @@ impl IRGenerator {
     ///
     /// # Arguments
     /// * `instanceof` - instanceof 表达式
-    pub fn generate_instanceof_expression(&mut self, instanceof: &InstanceOfExpr) -> cayResult<String> {
+    pub fn generate_instanceof_expression(&mut self, instanceof: &InstanceOfExpr) -> cayResult<LLVMValue> {
         let expr_result = self.generate_expression(&instanceof.expr)?;
-        let (expr_type, expr_val) = self.parse_typed_value(&expr_result);
+        let (expr_type, expr_val) = (&expr_result.ty, &expr_result.repr);
 
         let null_label = self.new_label("instanceof.null");
         let check_label = self.new_label("instanceof.check");
@@ -28,18 +29,17 @@ impl IRGenerator {
             self.emit_line(&format!("  {} = icmp eq i1 0, 1", is_null));
         }
 
-        self.emit_line(&format!("  br i1 {}, label %{}, label %{}", is_null, null_label, check_label));
+        self.emit_cond_br(&is_null, &null_label, &check_label);
 
         self.emit_line(&format!("\n{}:", null_label));
-        self.emit_line(&format!("  br label %{}", false_label));
+        self.emit_br(&false_label);
 
         self.emit_line(&format!("\n{}:", check_label));
 
         let type_id_ptr = self.new_temp();
         self.emit_line(&format!("  {} = bitcast {} {} to i32*", type_id_ptr, expr_type, expr_val));
 
-        let actual_type_id = self.new_temp();
-        self.emit_line(&format!("  {} = load i32, i32* {}", actual_type_id, type_id_ptr));
+        let actual_type_id = self.emit_load("i32", &type_id_ptr);
 
         let target_type = &instanceof.target_type;
         let target_class = match target_type {
@@ -58,17 +58,17 @@ impl IRGenerator {
         }
 
         self.emit_line(&format!("\n{}:", true_label));
-        self.emit_line(&format!("  br label %{}", end_label));
+        self.emit_br(&end_label);
 
         self.emit_line(&format!("\n{}:", false_label));
-        self.emit_line(&format!("  br label %{}", end_label));
+        self.emit_br(&end_label);
 
         self.emit_line(&format!("\n{}:", end_label));
         let result_temp = self.new_temp();
         self.emit_line(&format!("  {} = phi i1 [ 1, %{} ], [ 0, %{} ]",
             result_temp, true_label, false_label));
 
-        Ok(format!("i1 {}", result_temp))
+        Ok(LLVMValue::from_llvm("i1", result_temp))
     }
 
     /// 生成类型检查代码（用于类继承）
@@ -109,8 +109,7 @@ impl IRGenerator {
                 false_label.to_string()
             };
 
-            self.emit_line(&format!("  br i1 {}, label %{}, label %{}",
-                is_match, true_label, next_check_label));
+            self.emit_cond_br(&is_match, true_label, &next_check_label);
 
             if i < all_matching_type_ids.len() - 1 {
                 self.emit_line(&format!("\n{}:", next_check_label));
@@ -148,7 +147,7 @@ impl IRGenerator {
         };
 
         if implementing_type_ids.is_empty() {
-            self.emit_line(&format!("  br label %{}", false_label));
+            self.emit_br(false_label);
             return Ok(());
         }
 
@@ -163,8 +162,7 @@ impl IRGenerator {
                 false_label.to_string()
             };
 
-            self.emit_line(&format!("  br i1 {}, label %{}, label %{}",
-                is_match, true_label, next_check_label));
+            self.emit_cond_br(&is_match, true_label, &next_check_label);
 
             if i < implementing_type_ids.len() - 1 {
                 self.emit_line(&format!("\n{}:", next_check_label));
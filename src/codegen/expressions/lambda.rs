@@ -1,137 +1,317 @@
 //! Lambda 表达式代码生成
 //!
-//! 处理 Lambda 表达式和方法引用。
+//! 处理 Lambda 表达式、方法引用和通过函数类型变量的闭包调用。
+//!
+//! 函数类型的值是闭包对象：指向 `{ i8* fn, i8* env }` 的 `i8*`。
+//! `fn` 指向的函数第一个参数是环境指针 `i8* %env`，其余参数和返回类型与函数类型一致，
+//! 因此调用方只需要知道函数类型就能间接调用，不关心闭包来自 lambda 还是方法引用。
 
-use crate::codegen::context::IRGenerator;
+use crate::codegen::context::{IRGenerator, THIS_PTR_VAR};
+use crate::codegen::value::LLVMValue;
+use crate::codegen::ir::FunctionBody;
 use crate::ast::*;
-use crate::error::cayResult;
+use crate::error::{cayResult, codegen_error};
+use crate::types::{Type, FunctionType};
+
+/// 生成闭包函数体前保存的外层函数状态
+struct OuterFunctionState {
+    code: String,
+    temp_counter: usize,
+    function_body: Option<FunctionBody>,
+    global_ptr_cache: std::collections::HashMap<(String, usize), String>,
+    stack_guard_active: bool,
+    return_type: String,
+}
 
 impl IRGenerator {
+    /// 开始生成一个独立的闭包函数：保存外层函数的代码缓冲区和状态
+    fn begin_closure_function(&mut self, return_type: &str) -> OuterFunctionState {
+        let state = OuterFunctionState {
+            code: std::mem::take(&mut self.code),
+            temp_counter: self.temp_counter,
+            function_body: self.function_body.take(),
+            global_ptr_cache: std::mem::take(&mut self.global_ptr_cache),
+            // 闭包函数不计入调用深度，其中的 return 不能减少外层方法的计数
+            stack_guard_active: std::mem::replace(&mut self.stack_guard_active, false),
+            // 闭包函数中的 return 按函数类型的返回类型生成
+            return_type: std::mem::replace(&mut self.current_return_type, return_type.to_string()),
+        };
+        self.temp_counter = 0;
+        state
+    }
+
+    /// 结束闭包函数：把生成的函数加入全局函数列表并恢复外层函数的状态
+    fn end_closure_function(&mut self, outer: OuterFunctionState) -> cayResult<()> {
+        self.finish_function_body()?;
+        self.emit_line("}\n");
+        let function_code = std::mem::replace(&mut self.code, outer.code);
+        self.lambda_functions.push(function_code);
+        self.temp_counter = outer.temp_counter;
+        self.function_body = outer.function_body;
+        self.global_ptr_cache = outer.global_ptr_cache;
+        self.stack_guard_active = outer.stack_guard_active;
+        self.current_return_type = outer.return_type;
+        Ok(())
+    }
+
+    /// 函数类型对应的闭包函数 LLVM 类型（不含 `*`），如 `i32 (i8*, i32)`
+    fn closure_fn_type(&self, function_type: &FunctionType) -> String {
+        let params: Vec<String> = std::iter::once("i8*".to_string())
+            .chain(function_type.params.iter().map(|p| self.type_to_llvm(p)))
+            .collect();
+        format!("{} ({})", self.type_to_llvm(&function_type.return_type), params.join(", "))
+    }
+
+    /// 闭包函数的函数头参数列表：`i8* %env` 之后是 `%param0`、`%param1` ...
+    fn closure_fn_params(&self, function_type: &FunctionType) -> Vec<String> {
+        std::iter::once("i8* %env".to_string())
+            .chain(function_type.params.iter().enumerate()
+                .map(|(i, p)| format!("{} %param{}", self.type_to_llvm(p), i)))
+            .collect()
+    }
+
+    /// 在闭包函数中返回一个值，按函数类型的返回类型转换
+    fn emit_closure_return(&mut self, value: &LLVMValue) {
+        let return_type = self.current_return_type.clone();
+        if return_type == "void" {
+            self.emit_line("  ret void");
+            return;
+        }
+        let converted = self.convert_value_to(&value.ty, &value.repr, &return_type);
+        self.emit_line(&format!("  ret {} {}", return_type, converted));
+    }
+
+    /// 在堆上构造闭包对象 `{ fn, env }`，返回闭包指针
+    fn emit_closure_object(&mut self, fn_name: &str, function_type: &FunctionType, env: &str) -> LLVMValue {
+        let fn_ptr = self.new_temp();
+        self.emit_line(&format!("  {} = bitcast {}* @{} to i8*", fn_ptr, self.closure_fn_type(function_type), fn_name));
+        let closure = self.new_temp();
+        self.emit_line(&format!("  {} = call i8* @calloc(i64 1, i64 16)", closure));
+        let fn_slot = self.new_temp();
+        self.emit_line(&format!("  {} = bitcast i8* {} to i8**", fn_slot, closure));
+        self.emit_store("i8*", &fn_ptr, &fn_slot);
+        let env_slot = self.new_temp();
+        self.emit_line(&format!("  {} = getelementptr i8*, i8** {}, i64 1", env_slot, fn_slot));
+        self.emit_store("i8*", env, &env_slot);
+        LLVMValue::new("i8*", closure, Type::Function(Box::new(function_type.clone())))
+    }
+
+    /// 语义分析记录的函数类型，缺失时按未标注参数为 int、返回 void 处理
+    fn recorded_function_type(&self, id: NodeId, params: &[LambdaParam]) -> FunctionType {
+        self.function_types.get(&id).cloned().unwrap_or_else(|| FunctionType {
+            params: params.iter().map(|p| p.param_type.clone().unwrap_or(Type::Int32)).collect(),
+            return_type: Box::new(Type::Void),
+            is_static: false,
+        })
+    }
+
     /// 生成 Lambda 表达式代码
     /// Lambda: (params) -> { body }
     ///
+    /// Lambda 转换为独立的函数，第一个参数是隐藏的环境指针 `i8* %env`。
+    /// 被捕获的变量在创建点按值复制到堆上的环境结构体中，函数体开头再把它们
+    /// 读回局部变量，因此函数体中的变量访问与普通方法相同。
+    /// 表达式的值是闭包对象，没有捕获时 env 为 null。
+    ///
     /// # Arguments
     /// * `lambda` - Lambda 表达式
-    pub fn generate_lambda(&mut self, lambda: &LambdaExpr) -> cayResult<String> {
-        // Lambda 表达式需要生成一个匿名函数
-        // 由于 LLVM IR 的复杂性，这里采用简化实现
-
-        // 生成唯一的 Lambda 函数名
-        let current_class = self.current_class.clone();
-        let temp = self.new_temp().replace("%", "");
-        let lambda_name = format!("__lambda_{}_{}", current_class, temp);
+    pub fn generate_lambda(&mut self, lambda: &LambdaExpr) -> cayResult<LLVMValue> {
+        // 生成唯一的 Lambda 函数名（NodeId 在整个程序中唯一，不会与其他方法中的 lambda 重名）
+        let lambda_name = format!("__lambda_{}_{}", self.current_class, lambda.id.0);
+        let function_type = self.recorded_function_type(lambda.id, &lambda.params);
 
-        // 保存当前代码缓冲区
-        let saved_code = std::mem::take(&mut self.code);
-        let saved_temp_counter = self.temp_counter;
-
-        // 重置临时变量计数器
-        self.temp_counter = 0;
-
-        // 生成 Lambda 参数类型
-        let mut param_types = Vec::new();
-        let mut param_names = Vec::new();
-
-        for (i, param) in lambda.params.iter().enumerate() {
-            let param_type = param.param_type.as_ref()
-                .map(|t| self.type_to_llvm(t))
-                .unwrap_or_else(|| "i64".to_string());
-            param_types.push(format!("{} %param{}", param_type, i));
-            param_names.push((param.name.clone(), param_type, format!("%param{}", i)));
+        // 在创建点读取被捕获变量的当前值
+        let captures = self.lambda_captures.get(&lambda.id).cloned().unwrap_or_default();
+        let mut captured_values = Vec::new();
+        for name in &captures {
+            let value = if name == "this" {
+                LLVMValue::new("i8*", self.load_this_ptr(), Type::Object(self.current_class.clone()))
+            } else {
+                self.generate_identifier(name)?
+            };
+            captured_values.push(value);
         }
+        let env_type = format!("{{ {} }}", captured_values.iter()
+            .map(|value| value.ty.as_str())
+            .collect::<Vec<_>>()
+            .join(", "));
 
-        // 确定返回类型（简化处理，假设返回 i64）
-        let return_type = "i64";
+        let return_type = self.type_to_llvm(&function_type.return_type);
+        let outer = self.begin_closure_function(&return_type);
 
         // 生成 Lambda 函数头
-        self.emit_line(&format!("\ndefine {} @{}({}) {{", return_type, lambda_name, param_types.join(", ")));
-        self.emit_line("entry:");
+        self.emit_line(&format!("\ndefine {} @{}({}) {{",
+            return_type, lambda_name, self.closure_fn_params(&function_type).join(", ")));
+        self.emit_entry_label();
 
         // 创建新的作用域
         self.scope_manager.enter_scope();
 
+        // 从环境结构体中取出被捕获的变量
+        if !captures.is_empty() {
+            let env_ptr = self.new_temp();
+            self.emit_line(&format!("  {} = bitcast i8* %env to {}*", env_ptr, env_type));
+            for (i, (name, captured)) in captures.iter().zip(&captured_values).enumerate() {
+                let ty = &captured.ty;
+                let slot = self.new_temp();
+                self.emit_line(&format!("  {} = getelementptr inbounds {}, {}* {}, i32 0, i32 {}",
+                    slot, env_type, env_type, env_ptr, i));
+                let value = self.emit_load(ty, &slot);
+                let scope_name = if name == "this" { THIS_PTR_VAR } else { name.as_str() };
+                let llvm_name = self.scope_manager.declare_var(scope_name, ty, &captured.lang_ty);
+                self.emit_alloca(&llvm_name, ty);
+                self.emit_store(ty, &value, &format!("%{}", llvm_name));
+            }
+        }
+
         // 添加参数到作用域
-        for (name, ty, llvm_name) in &param_names {
-            let local_temp = self.new_temp();
-            self.emit_line(&format!("  {} = alloca {}, align {}", local_temp, ty, self.get_type_align(ty)));
-            self.emit_line(&format!("  store {} {}, {}* {}, align {}", ty, llvm_name, ty, local_temp, self.get_type_align(ty)));
-            self.scope_manager.declare_var(name, ty);
+        for (i, (param, param_type)) in lambda.params.iter().zip(&function_type.params).enumerate() {
+            let ty = self.type_to_llvm(param_type);
+            let llvm_name = self.scope_manager.declare_var(&param.name, &ty, param_type);
+            self.emit_alloca(&llvm_name, &ty);
+            self.emit_store(&ty, &format!("%param{}", i), &format!("%{}", llvm_name));
         }
 
         // 生成 Lambda 体
-        let _result: Result<(), crate::error::cayError> = match &lambda.body {
-            LambdaBody::Expr(expr) => {
-                let val = self.generate_expression(expr)?;
-                let (_, val_str) = self.parse_typed_value(&val);
-                // 确保返回 i64
-                if val.starts_with("i32") {
-                    let temp = self.new_temp();
-                    self.emit_line(&format!("  {} = sext i32 {} to i64", temp, val_str));
-                    self.emit_line(&format!("  ret i64 {}", temp));
-                } else {
-                    self.emit_line(&format!("  ret i64 {}", val_str));
-                }
-                Ok(())
-            }
+        let body_result = match &lambda.body {
+            LambdaBody::Expr(expr) => self.generate_expression(expr)
+                .map(|value| self.emit_closure_return(&value)),
             LambdaBody::Block(block) => {
-                // 生成块中的语句
-                for stmt in &block.statements {
-                    self.generate_statement(stmt)?;
+                let result = block.statements.iter().try_for_each(|stmt| self.generate_statement(stmt));
+                // 与普通方法相同：void 函数体末尾补上 return，其他返回类型由语义分析保证有 return
+                if return_type == "void" {
+                    self.emit_line("  ret void");
                 }
-                // 如果没有显式 return，返回 0
-                self.emit_line("  ret i64 0");
-                Ok(())
+                result
             }
         };
 
         // 退出作用域
         self.scope_manager.exit_scope();
+        self.end_closure_function(outer)?;
+        body_result?;
 
-        self.emit_line("}\n");
-
-        // 获取 Lambda 函数代码
-        let lambda_code = std::mem::take(&mut self.code);
-
-        // 恢复之前的代码缓冲区
-        self.code = saved_code;
-        self.temp_counter = saved_temp_counter;
-
-        // 将 Lambda 函数代码存储到全局函数列表
-        self.lambda_functions.push(lambda_code);
-
-        // 返回函数指针
-        let temp = self.new_temp();
-        self.emit_line(&format!("  {} = bitcast void (i64)* @{} to i8*", temp, lambda_name));
+        // 在堆上分配环境结构体并写入捕获的值
+        let env = if captures.is_empty() {
+            "null".to_string()
+        } else {
+            let size_ptr = self.new_temp();
+            self.emit_line(&format!("  {} = getelementptr {}, {}* null, i32 1", size_ptr, env_type, env_type));
+            let size = self.new_temp();
+            self.emit_line(&format!("  {} = ptrtoint {}* {} to i64", size, env_type, size_ptr));
+            let env_raw = self.new_temp();
+            self.emit_line(&format!("  {} = call i8* @calloc(i64 1, i64 {})", env_raw, size));
+            let env_ptr = self.new_temp();
+            self.emit_line(&format!("  {} = bitcast i8* {} to {}*", env_ptr, env_raw, env_type));
+            for (i, value) in captured_values.iter().enumerate() {
+                let slot = self.new_temp();
+                self.emit_line(&format!("  {} = getelementptr inbounds {}, {}* {}, i32 0, i32 {}",
+                    slot, env_type, env_type, env_ptr, i));
+                self.emit_store(&value.ty, &value.repr, &slot);
+            }
+            env_raw
+        };
 
-        Ok(format!("i8* {}", temp))
+        Ok(self.emit_closure_object(&lambda_name, &function_type, &env))
     }
 
     /// 生成方法引用表达式代码
     /// 方法引用: ClassName::methodName 或 obj::methodName
     ///
+    /// 为每个方法引用生成一个转发函数：参数按目标方法的参数类型转换后调用目标方法，
+    /// 返回值再转换为函数类型的返回类型。静态方法引用的环境为 null，
+    /// 实例方法引用在创建点求值接收者对象并把它作为环境，调用时按虚表分派。
+    ///
     /// # Arguments
     /// * `method_ref` - 方法引用表达式
-    pub fn generate_method_ref(&mut self, method_ref: &MethodRefExpr) -> cayResult<String> {
-        // 方法引用在 cay 中暂时作为函数指针处理
-        // 返回函数指针（i8* 作为占位符）
-        let temp = self.new_temp();
-
-        if let Some(ref class_name) = method_ref.class_name {
-            // 静态方法引用: ClassName::methodName
-            // 生成函数名
-            let fn_name = format!("{}.{}", class_name, method_ref.method_name);
-
-            // 使用 bitcast 获取函数指针
-            self.emit_line(&format!("  {} = bitcast void (i64)* @{} to i8*", temp, fn_name));
-        } else if let Some(_object) = &method_ref.object {
-            // 实例方法引用: obj::methodName
-            // 暂时不支持，返回空指针
-            self.emit_line(&format!("  {} = inttoptr i64 0 to i8*", temp));
+    pub fn generate_method_ref(&mut self, method_ref: &MethodRefExpr) -> cayResult<LLVMValue> {
+        let method = self.method_refs.get(&method_ref.id).cloned().ok_or_else(|| codegen_error(format!(
+            "Unresolved method reference '::{}'", method_ref.method_name
+        )))?;
+        let function_type = self.recorded_function_type(method_ref.id, &[]);
+        let thunk_name = format!("__methodref_{}_{}", self.current_class, method_ref.id.0);
+
+        // 在创建点求值接收者：`obj::m` 中的 obj 被解析为类名或对象表达式
+        let env = if method.is_static {
+            "null".to_string()
         } else {
-            // 未知类型，返回空指针
-            self.emit_line(&format!("  {} = inttoptr i64 0 to i8*", temp));
+            let receiver = match (&method_ref.class_name, &method_ref.object) {
+                (Some(name), _) => self.generate_identifier(name)?,
+                (None, Some(object)) => self.generate_expression(object)?,
+                (None, None) => return Err(codegen_error("Invalid method reference".to_string())),
+            };
+            receiver.repr
+        };
+
+        let fn_name = self.method_symbol(&method.class_name, &method);
+        let target_return = self.type_to_llvm(&method.return_type);
+        let return_type = self.type_to_llvm(&function_type.return_type);
+        let outer = self.begin_closure_function(&return_type);
+
+        self.emit_line(&format!("\ndefine {} @{}({}) {{",
+            return_type, thunk_name, self.closure_fn_params(&function_type).join(", ")));
+        self.emit_entry_label();
+
+        let mut args = Vec::new();
+        if !method.is_static {
+            args.push("i8* %env".to_string());
+        }
+        for (i, (param, target)) in function_type.params.iter().zip(&method.params).enumerate() {
+            let from = self.type_to_llvm(param);
+            let to = self.type_to_llvm(&target.param_type);
+            let value = self.convert_value_to(&from, &format!("%param{}", i), &to);
+            args.push(format!("{} {}", to, value));
+        }
+
+        // 被子类覆盖的实例方法通过接收者的虚表间接调用
+        let callee = match self.find_vtable_slot(&method.class_name, &fn_name) {
+            Some((slot_index, slot)) if !method.is_static && self.needs_virtual_dispatch(&method.class_name, slot_index) => {
+                let fn_type = slot.fn_type.clone();
+                self.emit_virtual_method_ptr(&method.class_name, slot_index, &fn_type, "%env")
+            }
+            _ => format!("@{}", fn_name),
+        };
+        match self.emit_call(&target_return, &callee, &args) {
+            Some(result) => self.emit_closure_return(&LLVMValue::new(target_return, result, method.return_type.clone())),
+            None => self.emit_line("  ret void"),
         }
+        self.end_closure_function(outer)?;
+
+        Ok(self.emit_closure_object(&thunk_name, &function_type, &env))
+    }
 
-        Ok(format!("i8* {}", temp))
+    /// 生成闭包调用 `f(args)`：从闭包对象中取出函数指针和环境，间接调用
+    ///
+    /// # Arguments
+    /// * `name` - 函数类型变量名
+    /// * `args` - 实参
+    /// * `function_type` - 被调用闭包的函数类型
+    pub(crate) fn generate_closure_call(&mut self, name: &str, args: &[Expr], function_type: &FunctionType) -> cayResult<LLVMValue> {
+        let closure = self.generate_identifier(name)?.repr;
+
+        // 实参在读取闭包之后、调用之前按声明顺序求值
+        let mut call_args = Vec::new();
+        for (arg, param) in args.iter().zip(&function_type.params) {
+            let value = self.generate_expression(arg)?;
+            let param_type = self.type_to_llvm(param);
+            let converted = self.convert_value_to(&value.ty, &value.repr, &param_type);
+            call_args.push(format!("{} {}", param_type, converted));
+        }
+
+        let slots = self.new_temp();
+        self.emit_line(&format!("  {} = bitcast i8* {} to i8**", slots, closure));
+        let fn_raw = self.emit_load("i8*", &slots);
+        let env_slot = self.new_temp();
+        self.emit_line(&format!("  {} = getelementptr i8*, i8** {}, i64 1", env_slot, slots));
+        let env = self.emit_load("i8*", &env_slot);
+        let fn_ptr = self.new_temp();
+        self.emit_line(&format!("  {} = bitcast i8* {} to {}*", fn_ptr, fn_raw, self.closure_fn_type(function_type)));
+        call_args.insert(0, format!("i8* {}", env));
+
+        let return_type = self.type_to_llvm(&function_type.return_type);
+        match self.emit_call(&return_type, &fn_ptr, &call_args) {
+            Some(result) => Ok(LLVMValue::new(return_type, result, (*function_type.return_type).clone())),
+            None => Ok(LLVMValue::void()),
+        }
     }
 }
@@ -3,7 +3,9 @@
 //! 处理整数、浮点数、布尔、字符串、字符和 null 字面量。
 
 use crate::codegen::context::IRGenerator;
+use crate::codegen::value::LLVMValue;
 use crate::ast::*;
+use crate::types::Type;
 use crate::error::cayResult;
 
 impl IRGenerator {
@@ -13,43 +15,50 @@ impl IRGenerator {
     /// * `lit` - 字面量值
     ///
     /// # Returns
-    /// 格式为 "type value" 的字符串
-    pub fn generate_literal(&mut self, lit: &LiteralValue) -> cayResult<String> {
+    /// 字面量的常量值
+    pub fn generate_literal(&mut self, lit: &LiteralValue) -> cayResult<LLVMValue> {
         match lit {
-            LiteralValue::Int32(val) => Ok(format!("i32 {}", val)),
-            LiteralValue::Int64(val) => Ok(format!("i64 {}", val)),
-            LiteralValue::Float32(val) => {
-                // 对于float字面量，生成double常量
-                // 类型转换逻辑会将其转换为float
-                // 确保浮点数常量有小数点
-                let formatted = if val.fract() == 0.0 {
-                    format!("double {}.0", val)
-                } else {
-                    format!("double {}", val)
-                };
-                Ok(formatted)
-            }
+            LiteralValue::Int32(val) => Ok(LLVMValue::new("i32", val.to_string(), Type::Int32)),
+            LiteralValue::Int64(val) => Ok(LLVMValue::new("i64", val.to_string(), Type::Int64)),
+            LiteralValue::Float32(val) => Ok(LLVMValue::new("float", Self::format_float_constant(*val), Type::Float32)),
             LiteralValue::Float64(val) => {
                 // 对于double，使用十进制表示
                 // 确保浮点数常量有小数点
                 let formatted = if val.fract() == 0.0 {
-                    format!("double {}.0", val)
+                    format!("{}.0", val)
                 } else {
-                    format!("double {}", val)
+                    val.to_string()
                 };
-                Ok(formatted)
+                Ok(LLVMValue::new("double", formatted, Type::Float64))
             }
-            LiteralValue::Bool(val) => Ok(format!("i1 {}", if *val { 1 } else { 0 })),
+            LiteralValue::Bool(val) => Ok(LLVMValue::new("i1", if *val { "1" } else { "0" }, Type::Bool)),
             LiteralValue::String(s) => {
-                let global_name = self.get_or_create_string_constant(s);
-                let temp = self.new_temp();
-                let len = s.len() + 1;
-                self.emit_line(&format!("  {} = getelementptr [{} x i8], [{} x i8]* {}, i64 0, i64 0",
-                    temp, len, len, global_name));
-                Ok(format!("i8* {}", temp))
+                let ptr = self.get_string_constant_ptr(s);
+                Ok(LLVMValue::new("i8*", ptr, Type::String))
+            }
+            LiteralValue::Char(c) => Ok(LLVMValue::new("i32", (*c as u32).to_string(), Type::Char)),
+            LiteralValue::Null => Ok(LLVMValue::new("i8*", "null", Type::Object("Object".to_string()))),
+        }
+    }
+
+    /// 生成 float 常量的 IR 表示
+    ///
+    /// LLVM 要求 float 常量能被单精度精确表示，因此先扩展为 double 再输出最短的十进制表示
+    /// （如 `1.1f` 输出为 `1.100000023841858`）；NaN 和无穷大使用十六进制形式。
+    pub fn format_float_constant(val: f32) -> String {
+        let wide = val as f64;
+        if !wide.is_finite() {
+            return format!("0x{:016X}", wide.to_bits());
+        }
+        let repr = format!("{:?}", wide);
+        if repr.contains('.') {
+            repr
+        } else {
+            // 科学计数法（如 1e20）也必须带小数点
+            match repr.split_once('e') {
+                Some((mantissa, exp)) => format!("{}.0e{}", mantissa, exp),
+                None => format!("{}.0", repr),
             }
-            LiteralValue::Char(c) => Ok(format!("i8 {}", *c as u8)),
-            LiteralValue::Null => Ok("i64 0".to_string()),
         }
     }
 }
@@ -3,7 +3,9 @@
 //! 这是表达式代码生成的统一入口点，根据表达式类型分发到具体的处理函数。
 
 use crate::codegen::context::IRGenerator;
+use crate::codegen::value::LLVMValue;
 use crate::ast::*;
+use crate::types::Type;
 use crate::error::cayResult;
 
 impl IRGenerator {
@@ -15,8 +17,30 @@ impl IRGenerator {
     /// * `expr` - AST 表达式节点
     ///
     /// # Returns
-    /// 格式为 "type value" 的 LLVM IR 值字符串
-    pub fn generate_expression(&mut self, expr: &Expr) -> cayResult<String> {
+    /// 生成的值；带 NodeId 的表达式的语言类型取语义分析记录的静态类型
+    pub fn generate_expression(&mut self, expr: &Expr) -> cayResult<LLVMValue> {
+        if let Some(loc) = expr.location() {
+            crate::ice::set_location(loc);
+        }
+        let value = self.generate_expression_value(expr)?;
+        // void 调用没有值；LLVM 类型与语义类型不一致的值（如类名占位符）保留推定的类型
+        match expr.node_id().and_then(|id| self.expr_types.get(&id)) {
+            Some(lang_ty) if !value.is_void()
+                && !matches!(lang_ty, Type::Generic(..) | Type::Auto)
+                && lang_ty.llvm_of() == value.ty => {
+                let lang_ty = lang_ty.clone();
+                Ok(value.with_lang_ty(lang_ty))
+            }
+            _ => Ok(value),
+        }
+    }
+
+    fn generate_expression_value(&mut self, expr: &Expr) -> cayResult<LLVMValue> {
+        // 安全导航 obj?.member / obj?.method(...)
+        if let Some(member) = expr.null_safe_member() {
+            return self.generate_null_safe_access(expr, member);
+        }
+
         match expr {
             // 字面量
             Expr::Literal(lit) => self.generate_literal(lit),
@@ -65,6 +89,12 @@ impl IRGenerator {
             
             // instanceof
             Expr::InstanceOf(instanceof) => self.generate_instanceof_expression(instanceof),
+
+            // 空值合并
+            Expr::NullCoalesce(coalesce) => self.generate_null_coalesce(coalesce),
+
+            // 内联 IR
+            Expr::InlineIr(inline) => self.generate_inline_ir(inline),
         }
     }
 }
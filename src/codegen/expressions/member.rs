@@ -3,7 +3,9 @@
 //! 处理静态字段访问、对象成员访问和数组 length 属性。
 
 use crate::codegen::context::IRGenerator;
+use crate::codegen::value::LLVMValue;
 use crate::ast::*;
+use crate::types::Type;
 use crate::error::cayResult;
 
 impl IRGenerator {
@@ -11,113 +13,51 @@ impl IRGenerator {
     ///
     /// # Arguments
     /// * `member` - 成员访问表达式
-    pub fn generate_member_access(&mut self, member: &MemberAccessExpr) -> cayResult<String> {
+    pub fn generate_member_access(&mut self, member: &MemberAccessExpr) -> cayResult<LLVMValue> {
+        // 内置类的常量: Math.PI
+        if let Expr::Identifier(class_name) = &*member.object
+            && self.is_builtin_class_reference(&member.object)
+        {
+            return self.generate_builtin_class_constant(class_name, &member.member);
+        }
+
         // 检查是否是静态字段访问: ClassName.fieldName
         if let Expr::Identifier(class_name) = &*member.object {
             let static_key = format!("{}.{}", class_name, member.member);
             if let Some(field_info) = self.static_field_map.get(&static_key).cloned() {
                 // 静态字段访问 - 返回全局变量的指针
-                let temp = self.new_temp();
-                self.emit_line(&format!("  {} = load {}, {}* {}, align {}", 
-                    temp, field_info.llvm_type, field_info.llvm_type, field_info.name, 
-                    self.get_type_align(&field_info.llvm_type)));
-                return Ok(format!("{} {}", field_info.llvm_type, temp));
+                let temp = self.emit_load(&field_info.llvm_type, &field_info.name);
+                return Ok(LLVMValue::new(field_info.llvm_type, temp, field_info.field_type));
             }
         }
         
         // 特殊处理数组的 .length 属性
         if member.member == "length" {
             let obj = self.generate_expression(&member.object)?;
-            let (obj_type, obj_val) = self.parse_typed_value(&obj);
             
-            // 检查是否是数组类型（以 * 结尾）
-            if obj_type.ends_with("*") {
-                // 首先将数组指针转换为 i8*
-                let obj_i8 = self.new_temp();
-                self.emit_line(&format!("  {} = bitcast {} {} to i8*", obj_i8, obj_type, obj_val));
-                
-                // 数组长度存储在数组指针前面的 8 字节中
-                // 计算长度地址：array_ptr - 8
-                let len_ptr_i8 = self.new_temp();
-                self.emit_line(&format!("  {} = getelementptr i8, i8* {}, i64 -8", len_ptr_i8, obj_i8));
-                
-                // 将长度指针转换为 i32*
-                let len_ptr = self.new_temp();
-                self.emit_line(&format!("  {} = bitcast i8* {} to i32*", len_ptr, len_ptr_i8));
-                
-                // 加载长度（作为 i32）
-                let len_val = self.new_temp();
-                self.emit_line(&format!("  {} = load i32, i32* {}, align 4", len_val, len_ptr));
-                
-                return Ok(format!("i32 {}", len_val));
+            // 检查是否是数组类型（以 * 结尾）；多维数组的每一层都有长度头
+            if obj.ty.ends_with("*") {
+                let len_val = self.emit_array_length(&obj.ty, &obj.repr);
+                return Ok(LLVMValue::new("i32", len_val, Type::Int32));
             }
         }
         
-        // 处理实例字段访问: this.fieldName 或 obj.fieldName
-        
-        // 确定对象所属的类
-        let class_name_opt: Option<String> = if let Expr::Identifier(name) = &*member.object {
-            if name == "this" {
-                Some(self.current_class.clone())
-            } else {
-                // 尝试从变量类型推断类名
-                self.var_class_map.get(name).cloned()
-            }
-        } else {
-            None
-        };
-        
-        if let Some(class_name) = class_name_opt {
-            if let Some(field_info) = self.get_instance_field(&class_name, &member.member).cloned() {
-                // 实例字段访问
-                
-                // 获取对象指针
-                // 对于 this，从作用域管理器获取 this_ptr 的 LLVM 名称；对于其他变量，加载其值
-                let obj_ptr = if let Expr::Identifier(name) = &*member.object {
-                    if name == "this" {
-                        // 从作用域管理器获取 this_ptr 的 LLVM 名称，然后加载其值
-                        let this_llvm_name = self.scope_manager.get_llvm_name("this_ptr")
-                            .unwrap_or_else(|| "this_ptr_s1".to_string());
-                        let temp = self.new_temp();
-                        self.emit_line(&format!("  {} = load i8*, i8** %{}, align 8", 
-                            temp, this_llvm_name));
-                        temp
-                    } else {
-                        // 其他变量：生成表达式并提取值
-                        let obj = self.generate_expression(&member.object)?;
-                        let (_, obj_val) = self.parse_typed_value(&obj);
-                        obj_val
-                    }
-                } else {
-                    let obj = self.generate_expression(&member.object)?;
-                    let (_, obj_val) = self.parse_typed_value(&obj);
-                    obj_val
-                };
-                
-                // 计算字段地址: obj_ptr + offset
-                let field_ptr_i8 = self.new_temp();
-                self.emit_line(&format!("  {} = getelementptr i8, i8* {}, i64 {}", 
-                    field_ptr_i8, obj_ptr, field_info.offset));
-                
-                // 将字段指针转换为正确类型的指针
-                let field_ptr = self.new_temp();
-                self.emit_line(&format!("  {} = bitcast i8* {} to {}*", 
-                    field_ptr, field_ptr_i8, field_info.llvm_type));
-                
-                // 加载字段值
-                let field_val = self.new_temp();
-                self.emit_line(&format!("  {} = load {}, {}* {}, align {}", 
-                    field_val, field_info.llvm_type, field_info.llvm_type, field_ptr,
-                    self.get_type_align(&field_info.llvm_type)));
-                
-                return Ok(format!("{} {}", field_info.llvm_type, field_val));
-            }
+        // 处理实例字段访问: this.fieldName、obj.fieldName、a.b.fieldName、arr[i].fieldName 或 f().fieldName
+        if let Some(class_name) = self.field_access_class(member)
+            && let Some(field_info) = self.get_instance_field(&class_name, &member.member).cloned()
+        {
+            let obj_ptr = self.generate_object_ptr(&member.object)?;
+            let field_ptr = self.emit_field_ptr(&class_name, &field_info, &obj_ptr);
+
+            // 加载字段值
+            let field_val = self.emit_load(&field_info.llvm_type, &field_ptr);
+
+            return Ok(LLVMValue::new(field_info.llvm_type, field_val, field_info.field_type));
         }
-        
+
         // 目前仅支持将成员访问视为对象指针的占位符
         // 生成对象表达式并返回其指针值
         let obj = self.generate_expression(&member.object)?;
-        let (_, obj_val) = self.parse_typed_value(&obj);
-        Ok(format!("i8* {}", obj_val))
+        Ok(LLVMValue::new("i8*", obj.repr, obj.lang_ty))
     }
 }
@@ -13,6 +13,9 @@
 //! - `call`: 函数/方法调用
 //! - `builtin`: 内置函数（print/read 等）
 //! - `string_methods`: String 方法调用
+//! - `stringify`: 字符串拼接和打印时的自动字符串转换（含 `toString()`）
+//! - `debug_print`: debugPrint 的值格式化（数组逐个元素、对象字段）
+//! - `format`: 格式化字符串 format()/print(fmt, ...)
 //! - `array`: 数组创建、访问、初始化
 //! - `cast`: 类型转换
 //! - `member`: 成员访问
@@ -21,6 +24,8 @@
 //! - `lambda`: Lambda 表达式和方法引用
 //! - `ternary`: 三元运算符
 //! - `instanceof`: instanceof 表达式
+//! - `null_safe`: 安全导航 `?.` 和空值合并 `??`
+//! - `inline_ir`: 内联 IR `__llvm__`
 
 // 工具模块（需要最先加载）
 mod utils;
@@ -38,6 +43,9 @@ mod unary;
 mod call;
 mod builtin;
 mod string_methods;
+mod stringify;
+mod debug_print;
+mod format;
 
 // 数组
 mod array;
@@ -52,3 +60,5 @@ mod new;
 mod lambda;
 mod ternary;
 mod instanceof;
+mod null_safe;
+mod inline_ir;
@@ -1,8 +1,10 @@
 //! new 表达式代码生成
 //!
-//! 处理对象创建和数组创建。
+//! 处理对象创建和数组创建：分配对象、写入对象头后调用匹配的构造函数。
+//! 逃逸分析（`codegen::escape`）确定不会逃逸的对象分配在栈上，其余对象用 calloc 分配在堆上。
 
 use crate::codegen::context::IRGenerator;
+use crate::codegen::value::LLVMValue;
 use crate::ast::*;
 use crate::error::cayResult;
 
@@ -11,24 +13,44 @@ impl IRGenerator {
     ///
     /// # Arguments
     /// * `new_expr` - new 表达式
-    pub fn generate_new_expression(&mut self, new_expr: &NewExpr) -> cayResult<String> {
+    pub fn generate_new_expression(&mut self, new_expr: &NewExpr) -> cayResult<LLVMValue> {
         let class_name = &new_expr.class_name;
         let type_id_value = self.get_type_id_value(class_name).unwrap_or(0);
 
-        // 获取类布局信息，确定对象大小
-        let obj_size = self.get_class_layout(class_name)
-            .map(|layout| layout.total_size as i64)
-            .unwrap_or(8i64); // 默认最小大小
+        // 获取类布局信息，确定对象大小和对齐
+        let (obj_size, obj_align) = self.get_class_layout(class_name)
+            .map(|layout| (layout.total_size as i64, layout.align))
+            .unwrap_or((8i64, 8)); // 默认最小大小
 
-        let calloc_temp = self.new_temp();
-        self.emit_line(&format!("  {} = call i8* @calloc(i64 1, i64 {})", calloc_temp, obj_size));
+        let obj_ptr = if self.stack_allocated_objects.contains(&new_expr.id) {
+            // 逃逸分析确定对象不会逃逸出当前方法：在入口块分配栈空间，每次执行 new 时清零
+            let slot = format!("%obj.{}", new_expr.id.0);
+            self.emit_entry_alloca(&format!("  {} = alloca [{} x i8], align {}", slot, obj_size, obj_align));
+            self.emit_line(&format!("  store [{} x i8] zeroinitializer, [{} x i8]* {}, align {}", obj_size, obj_size, slot, obj_align));
+            let ptr_temp = self.new_temp();
+            self.emit_line(&format!("  {} = bitcast [{} x i8]* {} to i8*", ptr_temp, obj_size, slot));
+            ptr_temp
+        } else {
+            let calloc_temp = self.new_temp();
+            self.emit_line(&format!("  {} = call i8* @calloc(i64 1, i64 {})", calloc_temp, obj_size));
+            calloc_temp
+        };
 
         let type_id_ptr = self.new_temp();
-        self.emit_line(&format!("  {} = bitcast i8* {} to i32*", type_id_ptr, calloc_temp));
-        self.emit_line(&format!("  store i32 {}, i32* {}", type_id_value, type_id_ptr));
+        self.emit_line(&format!("  {} = bitcast i8* {} to i32*", type_id_ptr, obj_ptr));
+        self.emit_store("i32", &type_id_value.to_string(), &type_id_ptr);
+
+        // 对象头中保存本类的虚表指针，用于虚方法分派
+        if let Some(vtable) = self.vtable_ptr_constant(class_name) {
+            let vtable_field = self.emit_vtable_field_ptr(class_name, &obj_ptr);
+            self.emit_store("i8**", &vtable, &vtable_field);
+        }
 
         let cast_temp = self.new_temp();
-        self.emit_line(&format!("  {} = bitcast i8* {} to i8*", cast_temp, calloc_temp));
-        Ok(format!("i8* {}", cast_temp))
+        self.emit_line(&format!("  {} = bitcast i8* {} to i8*", cast_temp, obj_ptr));
+
+        // 对象头初始化之后执行构造函数（包括父类的构造链）
+        self.emit_constructor_call(class_name, &new_expr.args, &cast_temp)?;
+        Ok(LLVMValue::from_llvm("i8*", cast_temp))
     }
 }
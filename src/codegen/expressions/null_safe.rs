@@ -0,0 +1,153 @@
+//! 空值安全运算符代码生成
+//!
+//! 处理安全导航 `obj?.member` / `obj?.method(...)` 和空值合并 `value ?? default`。
+//! 两者都生成 null 检查分支，最后在合并块中用 phi 汇总结果。
+
+use crate::codegen::context::IRGenerator;
+use crate::codegen::value::LLVMValue;
+use crate::ast::*;
+use crate::types::Type;
+use crate::error::cayResult;
+
+impl IRGenerator {
+    /// 生成安全导航表达式代码
+    ///
+    /// 对象为 null 时结果为 null（void 方法调用则直接跳过）。
+    ///
+    /// # Arguments
+    /// * `expr` - 安全导航表达式（成员访问或方法调用）
+    /// * `member` - 其中带 `?.` 的成员访问节点
+    pub fn generate_null_safe_access(&mut self, expr: &Expr, member: &MemberAccessExpr) -> cayResult<LLVMValue> {
+        let value_label = self.new_label("nullsafe.value");
+        let end_label = self.new_label("nullsafe.end");
+
+        let (value, null_label) = self.emit_null_safe_access(expr, member)?;
+
+        self.emit_br(&value_label);
+        self.emit_line(&format!("\n{}:", value_label));
+        self.emit_br(&end_label);
+        self.emit_line(&format!("\n{}:", null_label));
+        self.emit_br(&end_label);
+        self.emit_line(&format!("\n{}:", end_label));
+
+        if value.is_void() {
+            return Ok(value);
+        }
+
+        let result_temp = self.new_temp();
+        self.emit_line(&format!("  {} = phi {} [ {}, %{} ], [ null, %{} ]",
+            result_temp, value.ty, value.repr, value_label, null_label));
+        Ok(LLVMValue::new(value.ty, result_temp, value.lang_ty))
+    }
+
+    /// 生成空值合并表达式代码: value ?? default
+    ///
+    /// 只有左侧为 null 时才求值右侧；左侧为安全导航时，对象为 null 也取右侧。
+    ///
+    /// # Arguments
+    /// * `coalesce` - 空值合并表达式
+    pub fn generate_null_coalesce(&mut self, coalesce: &NullCoalesceExpr) -> cayResult<LLVMValue> {
+        let value_label = self.new_label("coalesce.value");
+        let default_label = self.new_label("coalesce.default");
+        let default_exit_label = self.new_label("coalesce.default.exit");
+        let end_label = self.new_label("coalesce.end");
+
+        // 左侧
+        let (value, null_label) = match coalesce.value.null_safe_member() {
+            Some(member) => {
+                let (value, null_label) = self.emit_null_safe_access(&coalesce.value, member)?;
+                (value, Some(null_label))
+            }
+            None => (self.generate_expression(&coalesce.value)?, None),
+        };
+        let (result_type, result_lang_type) = match self.null_safe_types.get(&coalesce.id).cloned() {
+            Some(ty) => (self.type_to_llvm(&ty), ty),
+            None => (value.ty.clone(), value.lang_ty.clone()),
+        };
+        let value_val = self.convert_value_to(&value.ty, &value.repr, &result_type);
+
+        // 引用类型的结果本身也可能为 null
+        if result_type.ends_with('*') {
+            let is_null = self.new_temp();
+            self.emit_line(&format!("  {} = icmp eq {} {}, null", is_null, result_type, value_val));
+            self.emit_cond_br(&is_null, &default_label, &value_label);
+        } else {
+            self.emit_br(&value_label);
+        }
+        self.emit_line(&format!("\n{}:", value_label));
+        self.emit_br(&end_label);
+
+        if let Some(null_label) = null_label {
+            self.emit_line(&format!("\n{}:", null_label));
+            self.emit_br(&default_label);
+        }
+
+        // 右侧（只在需要时求值）
+        self.emit_line(&format!("\n{}:", default_label));
+        let default = self.generate_expression(&coalesce.default)?;
+        let default_val = self.convert_value_to(&default.ty, &default.repr, &result_type);
+        self.emit_br(&default_exit_label);
+        self.emit_line(&format!("\n{}:", default_exit_label));
+        self.emit_br(&end_label);
+
+        // 合并点
+        self.emit_line(&format!("\n{}:", end_label));
+        let result_temp = self.new_temp();
+        self.emit_line(&format!("  {} = phi {} [ {}, %{} ], [ {}, %{} ]",
+            result_temp, result_type, value_val, value_label, default_val, default_exit_label));
+        Ok(LLVMValue::new(result_type, result_temp, result_lang_type))
+    }
+
+    /// 求值对象并检查 null，在非 null 分支中生成普通的成员访问或方法调用
+    ///
+    /// 对象只求值一次：结果存入隐藏的局部变量，再以普通的 `.` 访问它。
+    /// 返回后当前位于非 null 分支中，调用者负责跳转并生成 null 分支块。
+    ///
+    /// # Returns
+    /// (非 null 分支的结果值, null 分支的标签)
+    fn emit_null_safe_access(&mut self, expr: &Expr, member: &MemberAccessExpr) -> cayResult<(LLVMValue, String)> {
+        let null_label = self.new_label("nullsafe.null");
+        let notnull_label = self.new_label("nullsafe.notnull");
+
+        let obj = self.generate_expression(&member.object)?;
+        let (obj_type, obj_val) = (&obj.ty, &obj.repr);
+
+        // 存入隐藏变量（名称含 '.'，不会与用户标识符冲突）
+        let temp_name = format!("nullsafe.obj.{}", member.id.0);
+        let llvm_name = self.scope_manager.declare_var(&temp_name, obj_type, &obj.lang_ty);
+        self.emit_alloca(&llvm_name, obj_type);
+        self.emit_store(obj_type, obj_val, &format!("%{}", llvm_name));
+        self.var_types.insert(temp_name.clone(), obj_type.clone());
+        if let Some(Type::Object(class_name)) = self.null_safe_types.get(&member.id) {
+            self.var_class_map.insert(temp_name.clone(), class_name.clone());
+        } else if let Expr::Identifier(name) = member.object.as_ref()
+            && let Some(class_name) = self.var_class_map.get(name).cloned()
+        {
+            self.var_class_map.insert(temp_name.clone(), class_name);
+        }
+
+        let is_null = self.new_temp();
+        self.emit_line(&format!("  {} = icmp eq {} {}, null", is_null, obj_type, obj_val));
+        self.emit_cond_br(&is_null, &null_label, &notnull_label);
+
+        // 非 null 分支：以普通成员访问处理
+        self.emit_line(&format!("\n{}:", notnull_label));
+        let access = MemberAccessExpr {
+            object: Box::new(Expr::Identifier(temp_name)),
+            member: member.member.clone(),
+            null_safe: false,
+            loc: member.loc.clone(),
+            id: member.id,
+        };
+        let value = match expr {
+            Expr::Call(call) => self.generate_call_expression(&CallExpr {
+                callee: Box::new(Expr::MemberAccess(access)),
+                args: call.args.clone(),
+                loc: call.loc.clone(),
+                id: call.id,
+            })?,
+            _ => self.generate_member_access(&access)?,
+        };
+        Ok((value, null_label))
+    }
+}
@@ -1,9 +1,12 @@
 //! String 方法调用代码生成
 //!
-//! 处理 String 类型的方法调用（length, substring, indexOf, charAt, replace）。
+//! 处理 String 类型的方法调用（length, substring, indexOf, charAt, replace,
+//! toUpperCase, toLowerCase, trim, startsWith, endsWith, contains, split）。
 
 use crate::codegen::context::IRGenerator;
+use crate::codegen::value::LLVMValue;
 use crate::ast::*;
+use crate::types::Type;
 use crate::error::{cayResult, codegen_error};
 
 impl IRGenerator {
@@ -13,10 +16,21 @@ impl IRGenerator {
     /// # Arguments
     /// * `member` - 成员访问表达式
     /// * `args` - 参数列表
-    pub fn try_generate_string_method_call(&mut self, member: &MemberAccessExpr, args: &[Expr]) -> cayResult<Option<String>> {
+    pub fn try_generate_string_method_call(&mut self, member: &MemberAccessExpr, args: &[Expr]) -> cayResult<Option<LLVMValue>> {
+        // 已知类型的对象变量、this 和 super 调用的是其类中的方法，即使方法名与 String 方法相同
+        if let Expr::Identifier(name) = member.object.as_ref()
+            && (self.var_class_map.contains_key(name) || name == "this" || name == "super")
+        {
+            return Ok(None);
+        }
+        // 语义分析记录了接收者类名的是实例方法调用；不能在这里先求值一次接收者（如 `sb.append(a).append(b)`）
+        if self.field_access_classes.contains_key(&member.id) {
+            return Ok(None);
+        }
+
         // 生成对象表达式（字符串）
         let obj_result = self.generate_expression(&member.object)?;
-        let (obj_type, obj_val) = self.parse_typed_value(&obj_result);
+        let (obj_type, obj_val) = (&obj_result.ty, &obj_result.repr);
 
         // 检查对象是否是字符串类型 (i8*)
         if obj_type != "i8*" {
@@ -34,7 +48,7 @@ impl IRGenerator {
                 }
                 self.emit_line(&format!("  {} = call i32 @__cay_string_length(i8* {})",
                     temp, obj_val));
-                Ok(Some(format!("i32 {}", temp)))
+                Ok(Some(LLVMValue::new("i32", temp, Type::Int32)))
             }
             "substring" => {
                 // substring(beginIndex) 或 substring(beginIndex, endIndex)
@@ -44,7 +58,7 @@ impl IRGenerator {
 
                 // 生成 beginIndex 参数
                 let begin_result = self.generate_expression(&args[0])?;
-                let (begin_type, begin_val) = self.parse_typed_value(&begin_result);
+                let (begin_type, begin_val) = (&begin_result.ty, &begin_result.repr);
                 let begin_i32 = if begin_type == "i32" {
                     begin_val.to_string()
                 } else {
@@ -56,7 +70,7 @@ impl IRGenerator {
                 // 生成 endIndex 参数
                 let end_i32 = if args.len() == 2 {
                     let end_result = self.generate_expression(&args[1])?;
-                    let (end_type, end_val) = self.parse_typed_value(&end_result);
+                    let (end_type, end_val) = (&end_result.ty, &end_result.repr);
                     if end_type == "i32" {
                         end_val.to_string()
                     } else {
@@ -74,7 +88,7 @@ impl IRGenerator {
 
                 self.emit_line(&format!("  {} = call i8* @__cay_string_substring(i8* {}, i32 {}, i32 {})",
                     temp, obj_val, begin_i32, end_i32));
-                Ok(Some(format!("i8* {}", temp)))
+                Ok(Some(LLVMValue::new("i8*", temp, Type::String)))
             }
             "indexOf" => {
                 // indexOf(substr) - 返回子串首次出现的位置
@@ -83,7 +97,7 @@ impl IRGenerator {
                 }
 
                 let substr_result = self.generate_expression(&args[0])?;
-                let (substr_type, substr_val) = self.parse_typed_value(&substr_result);
+                let (substr_type, substr_val) = (&substr_result.ty, &substr_result.repr);
 
                 if substr_type != "i8*" {
                     return Err(codegen_error("String.indexOf() argument must be a string".to_string()));
@@ -91,7 +105,7 @@ impl IRGenerator {
 
                 self.emit_line(&format!("  {} = call i32 @__cay_string_indexof(i8* {}, i8* {})",
                     temp, obj_val, substr_val));
-                Ok(Some(format!("i32 {}", temp)))
+                Ok(Some(LLVMValue::new("i32", temp, Type::Int32)))
             }
             "charAt" => {
                 // charAt(index) - 返回指定位置的字符
@@ -100,7 +114,7 @@ impl IRGenerator {
                 }
 
                 let index_result = self.generate_expression(&args[0])?;
-                let (index_type, index_val) = self.parse_typed_value(&index_result);
+                let (index_type, index_val) = (&index_result.ty, &index_result.repr);
                 let index_i32 = if index_type == "i32" {
                     index_val.to_string()
                 } else {
@@ -109,9 +123,9 @@ impl IRGenerator {
                     t
                 };
 
-                self.emit_line(&format!("  {} = call i8 @__cay_string_charat(i8* {}, i32 {})",
+                self.emit_line(&format!("  {} = call i32 @__cay_string_charat(i8* {}, i32 {})",
                     temp, obj_val, index_i32));
-                Ok(Some(format!("i8 {}", temp)))
+                Ok(Some(LLVMValue::new("i32", temp, Type::Char)))
             }
             "replace" => {
                 // replace(oldStr, newStr) - 替换所有出现的子串
@@ -120,9 +134,9 @@ impl IRGenerator {
                 }
 
                 let old_result = self.generate_expression(&args[0])?;
-                let (old_type, old_val) = self.parse_typed_value(&old_result);
+                let (old_type, old_val) = (&old_result.ty, &old_result.repr);
                 let new_result = self.generate_expression(&args[1])?;
-                let (new_type, new_val) = self.parse_typed_value(&new_result);
+                let (new_type, new_val) = (&new_result.ty, &new_result.repr);
 
                 if old_type != "i8*" || new_type != "i8*" {
                     return Err(codegen_error("String.replace() arguments must be strings".to_string()));
@@ -130,7 +144,44 @@ impl IRGenerator {
 
                 self.emit_line(&format!("  {} = call i8* @__cay_string_replace(i8* {}, i8* {}, i8* {})",
                     temp, obj_val, old_val, new_val));
-                Ok(Some(format!("i8* {}", temp)))
+                Ok(Some(LLVMValue::new("i8*", temp, Type::String)))
+            }
+            "toUpperCase" | "toLowerCase" | "trim" => {
+                // 无参数，返回新字符串
+                if !args.is_empty() {
+                    return Err(codegen_error(format!("String.{}() takes no arguments", method_name)));
+                }
+                let runtime_fn = match method_name {
+                    "toUpperCase" => "__cay_string_to_upper",
+                    "toLowerCase" => "__cay_string_to_lower",
+                    _ => "__cay_string_trim",
+                };
+                self.emit_line(&format!("  {} = call i8* @{}(i8* {})",
+                    temp, runtime_fn, obj_val));
+                Ok(Some(LLVMValue::new("i8*", temp, Type::String)))
+            }
+            "startsWith" | "endsWith" | "contains" | "split" => {
+                // 单个字符串参数
+                if args.len() != 1 {
+                    return Err(codegen_error(format!("String.{}() takes 1 argument", method_name)));
+                }
+
+                let arg_result = self.generate_expression(&args[0])?;
+                let (arg_type, arg_val) = (&arg_result.ty, &arg_result.repr);
+
+                if arg_type != "i8*" {
+                    return Err(codegen_error(format!("String.{}() argument must be a string", method_name)));
+                }
+
+                let (ret_type, runtime_fn) = match method_name {
+                    "startsWith" => ("i1", "__cay_string_starts_with"),
+                    "endsWith" => ("i1", "__cay_string_ends_with"),
+                    "contains" => ("i1", "__cay_string_contains"),
+                    _ => ("i8**", "__cay_string_split"),
+                };
+                self.emit_line(&format!("  {} = call {} @{}(i8* {}, i8* {})",
+                    temp, ret_type, runtime_fn, obj_val, arg_val));
+                Ok(Some(LLVMValue::from_llvm(ret_type, temp)))
             }
             _ => Ok(None), // 不是已知的 String 方法
         }
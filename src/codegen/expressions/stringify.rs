@@ -0,0 +1,147 @@
+//! 值到字符串的自动转换
+//!
+//! 字符串拼接 `"x = " + value` 和打印对象时，按语义分析记录的静态类型把值转换为字符串：
+//! 基本类型调用对应的运行时转换函数，对象调用类中定义的 `toString()`（被子类覆盖时通过虚表调用），
+//! 没有定义 `toString()` 的类和数组交给反射格式化函数，按运行时类型输出字段 `Point{x=1, y=2}`
+//! 和元素 `[1, 2, 3]`，null 转换为 "null"。
+
+use crate::codegen::context::IRGenerator;
+use crate::codegen::value::LLVMValue;
+use crate::ast::*;
+use crate::types::Type;
+use crate::error::{cayResult, codegen_error};
+
+impl IRGenerator {
+    /// 生成需要自动转换的字符串拼接：两侧都转换为字符串后拼接
+    pub(super) fn generate_string_conversion_concat(&mut self, left: &LLVMValue, right: &LLVMValue, types: &(Type, Type), temp: &str) -> cayResult<LLVMValue> {
+        let (left_type, right_type) = types;
+        let left_str = self.generate_to_string(left_type, left)?;
+        let right_str = self.generate_to_string(right_type, right)?;
+        self.emit_line(&format!("  {} = call i8* @__cay_string_concat(i8* {}, i8* {})",
+            temp, left_str, right_str));
+        Ok(LLVMValue::new("i8*", temp, Type::String))
+    }
+
+    /// 把值按静态类型 `ty` 转换为字符串，返回 `i8*` 寄存器
+    pub fn generate_to_string(&mut self, ty: &Type, value: &LLVMValue) -> cayResult<String> {
+        let (llvm_type, val) = (value.ty.clone(), value.repr.clone());
+        match ty {
+            Type::String => Ok(val),
+            Type::Int8 | Type::Int16 | Type::Int32 | Type::Int64 => {
+                let wide = if llvm_type == "i64" {
+                    val
+                } else {
+                    let extended = self.new_temp();
+                    self.emit_line(&format!("  {} = sext {} {} to i64", extended, llvm_type, val));
+                    extended
+                };
+                Ok(self.emit_call("i8*", "@__cay_int_to_string", &[format!("i64 {}", wide)]).unwrap_or_default())
+            }
+            Type::Float32 | Type::Float64 => {
+                let wide = if llvm_type == "float" {
+                    let extended = self.new_temp();
+                    self.emit_line(&format!("  {} = fpext float {} to double", extended, val));
+                    extended
+                } else {
+                    val
+                };
+                // float 按单精度判断最短的可还原形式
+                let single = if *ty == Type::Float32 { 1 } else { 0 };
+                Ok(self.emit_call("i8*", "@__cay_double_to_string", &[format!("double {}", wide), format!("i1 {}", single)]).unwrap_or_default())
+            }
+            Type::Bool => {
+                let flag = if llvm_type == "i1" {
+                    val
+                } else {
+                    let flag = self.new_temp();
+                    self.emit_line(&format!("  {} = icmp ne {} {}, 0", flag, llvm_type, val));
+                    flag
+                };
+                Ok(self.emit_call("i8*", "@__cay_bool_to_string", &[format!("i1 {}", flag)]).unwrap_or_default())
+            }
+            Type::Char => {
+                let code = self.convert_value_to(&llvm_type, &val, "i32");
+                Ok(self.emit_call("i8*", "@__cay_char_to_string", &[format!("i32 {}", code)]).unwrap_or_default())
+            }
+            Type::Object(class_name) => Ok(self.generate_object_to_string(class_name, &val)),
+            Type::Array(elem) => Ok(self.generate_array_to_string(elem, value)),
+            _ => Err(codegen_error(format!("Cannot convert a value of type {} to a string", ty))),
+        }
+    }
+
+    /// 定义 `String toString()` 的类（自身或最近的父类），没有时返回 `None`
+    pub(crate) fn find_to_string_owner(&self, class_name: &str) -> Option<String> {
+        let registry = self.type_registry.as_ref()?;
+        let mut current = registry.get_class(class_name);
+        while let Some(class_info) = current {
+            let defines_to_string = class_info.methods.get("toString").is_some_and(|methods| {
+                methods.iter().any(|m| !m.is_static && m.params.is_empty() && m.return_type == Type::String)
+            });
+            if defines_to_string {
+                return Some(class_info.name.clone());
+            }
+            current = class_info.parent.as_deref().and_then(|p| registry.get_class(p));
+        }
+        None
+    }
+
+    /// 静态类型为 `class_name`（类或接口）的对象是否有 `String toString()` 可调用
+    pub(super) fn has_to_string(&self, class_name: &str) -> bool {
+        if self.is_interface(class_name) {
+            return self.find_interface_slot(class_name, "toString")
+                .is_some_and(|(_, m)| m.params.is_empty() && m.return_type == Type::String);
+        }
+        self.find_to_string_owner(class_name).is_some()
+    }
+
+    /// 把对象转换为字符串：null 为 "null"，否则调用 `toString()`；
+    /// 静态类型没有 `toString()` 时按运行时类型反射格式化（子类定义的 `toString()` 仍会被调用）
+    fn generate_object_to_string(&mut self, class_name: &str, obj: &str) -> String {
+        if !self.has_to_string(class_name) {
+            return self.generate_reflect_object_to_string(obj);
+        }
+
+        // null 对象不能调用 toString()，结果通过栈槽在两个分支间传递
+        let slot = format!("{}.str", self.new_temp());
+        self.emit_entry_alloca(&format!("  {} = alloca i8*, align 8", slot));
+        self.emit_store("i8*", "getelementptr ([5 x i8], [5 x i8]* @.str.null_str, i64 0, i64 0)", &slot);
+        let is_null = self.new_temp();
+        let call_label = self.new_label("tostring.call");
+        let end_label = self.new_label("tostring.end");
+        self.emit_line(&format!("  {} = icmp eq i8* {}, null", is_null, obj));
+        self.emit_cond_br(&is_null, &end_label, &call_label);
+
+        self.emit_label(&call_label);
+        let callee = if let Some((slot_index, method)) = self.find_interface_slot(class_name, "toString") {
+            // 接口类型：通过实现类的 itable 调用
+            let fn_type = self.interface_method_fn_type(&method);
+            self.emit_interface_method_ptr(class_name, slot_index, &fn_type, obj)
+        } else {
+            let owner = self.find_to_string_owner(class_name).unwrap_or_else(|| class_name.to_string());
+            let fn_name = self.build_function_name_from_method(&owner, "toString", &[]);
+            let slot_name = self.build_function_name_from_method(class_name, "toString", &[]);
+            match self.find_vtable_slot(class_name, &slot_name) {
+                Some((slot_index, vtable_slot)) if self.needs_virtual_dispatch(class_name, slot_index) => {
+                    let fn_type = vtable_slot.fn_type.clone();
+                    self.emit_virtual_method_ptr(class_name, slot_index, &fn_type, obj)
+                }
+                _ => format!("@{}", fn_name),
+            }
+        };
+        let result = self.emit_call("i8*", &callee, &[format!("i8* {}", obj)]).unwrap_or_default();
+        self.emit_store("i8*", &result, &slot);
+        self.emit_br(&end_label);
+
+        self.emit_label(&end_label);
+        self.emit_load("i8*", &slot)
+    }
+
+    /// print/println 的对象参数：转换为字符串后按 `%s` 打印
+    pub(super) fn generate_print_object(&mut self, class_name: &str, arg: &Expr, newline: bool) -> cayResult<()> {
+        let value = self.generate_expression(arg)?;
+        let text = self.generate_object_to_string(class_name, &value.repr);
+        let fmt_ptr = self.get_string_constant_ptr(if newline { "%s\n" } else { "%s" });
+        self.emit_line(&format!("  call i32 (i8*, ...) @printf(i8* {}, i8* {})", fmt_ptr, text));
+        Ok(())
+    }
+}
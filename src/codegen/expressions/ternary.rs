@@ -1,60 +1,75 @@
 //! 三元运算符表达式代码生成
 //!
-//! 处理条件表达式 ? :
+//! 处理条件表达式 ? :。只求值选中的分支：条件跳转到 then/else 块，两个分支的值在合并块中由
+//! `phi` 汇合。分支中可能含有嵌套的控制流（三元、`&&`、`??`），求值结束时所在的块不一定是
+//! 分支的起始块，因此每个分支最后跳入各自的出口块，`phi` 以出口块作为前驱。
 
 use crate::codegen::context::IRGenerator;
+use crate::codegen::value::LLVMValue;
 use crate::ast::*;
+use crate::types::Type;
 use crate::error::cayResult;
 
 impl IRGenerator {
     /// 生成三元运算符表达式代码
     ///
+    /// 结果类型为语义分析统一两个分支后的类型（数值类型按二元运算的规则提升），
+    /// 每个分支的值在自己的块中转换为该类型。
+    ///
     /// # Arguments
     /// * `ternary` - 三元表达式
-    pub fn generate_ternary_expression(&mut self, ternary: &TernaryExpr) -> cayResult<String> {
+    pub fn generate_ternary_expression(&mut self, ternary: &TernaryExpr) -> cayResult<LLVMValue> {
         // 创建标签
         let then_label = self.new_label("ternary.then");
+        let then_exit_label = self.new_label("ternary.then.exit");
         let else_label = self.new_label("ternary.else");
+        let else_exit_label = self.new_label("ternary.else.exit");
         let end_label = self.new_label("ternary.end");
 
-        // 生成条件表达式
+        // 生成条件表达式，非 i1 的条件与 0 比较
         let cond_result = self.generate_expression(&ternary.condition)?;
-        let (cond_type, cond_val) = self.parse_typed_value(&cond_result);
-        let cond_reg = self.new_temp();
-
-        // 将条件转换为 i1 类型
-        if cond_type == "i1" {
-            self.emit_line(&format!("  {} = icmp ne i1 {}, 0", cond_reg, cond_val));
+        let cond_reg = if cond_result.ty == "i1" {
+            cond_result.repr
         } else {
-            // 对于整数类型，先与 0 比较
-            self.emit_line(&format!("  {} = icmp ne {} {}, 0", cond_reg, cond_type, cond_val));
-        }
+            let temp = self.new_temp();
+            self.emit_line(&format!("  {} = icmp ne {}, 0", temp, cond_result));
+            temp
+        };
+        self.emit_cond_br(&cond_reg, &then_label, &else_label);
 
-        // 条件分支
-        self.emit_line(&format!("  br i1 {}, label %{}, label %{}", cond_reg, then_label, else_label));
+        let result_type = self.ternary_types.get(&ternary.id).cloned();
 
         // then 分支
         self.emit_line(&format!("\n{}:", then_label));
-        let then_result = self.generate_expression(&ternary.true_branch)?;
-        let (then_type, then_val) = self.parse_typed_value(&then_result);
-        let then_temp = self.new_temp();
-        self.emit_line(&format!("  {} = add {} {}, 0", then_temp, then_type, then_val));
-        self.emit_line(&format!("  br label %{}", end_label));
+        let then_value = self.generate_ternary_branch(&ternary.true_branch, result_type.as_ref())?;
+        self.emit_br(&then_exit_label);
+        self.emit_line(&format!("\n{}:", then_exit_label));
+        self.emit_br(&end_label);
 
-        // else 分支
+        // else 分支；没有语义分析结果时以 then 分支的类型为准
         self.emit_line(&format!("\n{}:", else_label));
-        let else_result = self.generate_expression(&ternary.false_branch)?;
-        let (else_type, else_val) = self.parse_typed_value(&else_result);
-        let else_temp = self.new_temp();
-        self.emit_line(&format!("  {} = add {} {}, 0", else_temp, else_type, else_val));
-        self.emit_line(&format!("  br label %{}", end_label));
+        let else_value = self.generate_ternary_branch(&ternary.false_branch, Some(result_type.as_ref().unwrap_or(&then_value.lang_ty)))?;
+        self.emit_br(&else_exit_label);
+        self.emit_line(&format!("\n{}:", else_exit_label));
+        self.emit_br(&end_label);
 
         // 合并点
         self.emit_line(&format!("\n{}:", end_label));
         let result_temp = self.new_temp();
         self.emit_line(&format!("  {} = phi {} [ {}, %{} ], [ {}, %{} ]",
-            result_temp, then_type, then_temp, then_label, else_temp, else_label));
+            result_temp, else_value.ty, then_value.repr, then_exit_label, else_value.repr, else_exit_label));
+
+        Ok(LLVMValue::new(else_value.ty, result_temp, else_value.lang_ty))
+    }
 
-        Ok(format!("{} {}", then_type, result_temp))
+    /// 在当前块中求值一个分支，并转换为结果类型（`None` 时保持原类型）
+    fn generate_ternary_branch(&mut self, branch: &Expr, result_type: Option<&Type>) -> cayResult<LLVMValue> {
+        let value = self.generate_expression(branch)?;
+        let Some(result_type) = result_type else {
+            return Ok(value);
+        };
+        let target_type = self.type_to_llvm(result_type);
+        let converted = self.convert_value_to(&value.ty, &value.repr, &target_type);
+        Ok(LLVMValue::new(target_type, converted, result_type.clone()))
     }
 }
@@ -3,6 +3,7 @@
 //! 处理取负、逻辑非、位取反和自增/自减操作。
 
 use crate::codegen::context::IRGenerator;
+use crate::codegen::value::LLVMValue;
 use crate::ast::*;
 use crate::error::{cayResult, codegen_error};
 
@@ -11,9 +12,14 @@ impl IRGenerator {
     ///
     /// # Arguments
     /// * `unary` - 一元表达式
-    pub fn generate_unary_expression(&mut self, unary: &UnaryExpr) -> cayResult<String> {
+    pub fn generate_unary_expression(&mut self, unary: &UnaryExpr) -> cayResult<LLVMValue> {
+        // 自增/自减直接对左值的地址读改写，操作数不能先按右值求值一次
+        if matches!(unary.op, UnaryOp::PreInc | UnaryOp::PostInc | UnaryOp::PreDec | UnaryOp::PostDec) {
+            return self.generate_inc_dec(unary);
+        }
+
         let operand = self.generate_expression(&unary.operand)?;
-        let (op_type, op_val) = self.parse_typed_value(&operand);
+        let (op_type, op_val) = (&operand.ty, &operand.repr);
         let temp = self.new_temp();
         
         match unary.op {
@@ -29,7 +35,7 @@ impl IRGenerator {
             UnaryOp::Not => {
                 self.emit_line(&format!("  {} = xor {} {}, 1",
                     temp, op_type, op_val));
-                return Ok(format!("i1 {}", temp));
+                return Ok(LLVMValue::from_llvm("i1", temp));
             }
             UnaryOp::BitNot => {
                 // 位取反：xor 操作数与 -1
@@ -41,21 +47,19 @@ impl IRGenerator {
                     return Err(codegen_error("Bitwise NOT not supported for floating point".to_string()));
                 }
             }
-            UnaryOp::PreInc | UnaryOp::PostInc | UnaryOp::PreDec | UnaryOp::PostDec => {
-                return self.generate_inc_dec(unary, op_type, op_val);
-            }
+            UnaryOp::PreInc | UnaryOp::PostInc | UnaryOp::PreDec | UnaryOp::PostDec => unreachable!("increment and decrement are generated by generate_inc_dec"),
         }
         
-        Ok(format!("{} {}", op_type, temp))
+        Ok(LLVMValue::from_llvm(op_type, temp))
     }
 
     /// 生成自增/自减表达式代码
     ///
+    /// 操作数可以是变量、字段或数组元素：先求出左值的地址，再加载、计算并存回。
+    ///
     /// # Arguments
     /// * `unary` - 一元表达式（必须是自增/自减操作）
-    /// * `op_type` - 操作数类型
-    /// * `op_val` - 操作数值
-    fn generate_inc_dec(&mut self, unary: &UnaryExpr, _op_type: String, _op_val: String) -> cayResult<String> {
+    fn generate_inc_dec(&mut self, unary: &UnaryExpr) -> cayResult<LLVMValue> {
         // 自增/自减操作：需要先获取变量地址，加载值，计算，存储
         let is_inc = unary.op == UnaryOp::PreInc || unary.op == UnaryOp::PostInc;
         let is_pre = unary.op == UnaryOp::PreInc || unary.op == UnaryOp::PreDec;
@@ -64,9 +68,7 @@ impl IRGenerator {
         let (llvm_type, llvm_ptr) = self.get_lvalue_info(&unary.operand)?;
         
         // 加载当前值
-        let load_temp = self.new_temp();
-        self.emit_line(&format!("  {} = load {}, {}* {}, align {}",
-            load_temp, llvm_type, llvm_type, llvm_ptr, self.get_type_align(&llvm_type)));
+        let load_temp = self.emit_load(&llvm_type, &llvm_ptr);
         
         // 计算新值
         let new_temp = self.new_temp();
@@ -90,14 +92,13 @@ impl IRGenerator {
         }
         
         // 存储新值
-        self.emit_line(&format!("  store {} {}, {}* {}, align {}",
-            llvm_type, new_temp, llvm_type, llvm_ptr, self.get_type_align(&llvm_type)));
+        self.emit_store(&llvm_type, &new_temp, &llvm_ptr);
         
         // 前置返回新值，后缀返回旧值
         if is_pre {
-            Ok(format!("{} {}", llvm_type, new_temp))
+            Ok(LLVMValue::from_llvm(llvm_type, new_temp))
         } else {
-            Ok(format!("{} {}", llvm_type, load_temp))
+            Ok(LLVMValue::from_llvm(llvm_type, load_temp))
         }
     }
 }
@@ -27,7 +27,7 @@ impl IRGenerator {
             return (left_type.to_string(), left_val.to_string(), right_val.to_string());
         }
         
-        // char (i8) 类型在算术运算中需要提升到 i32
+        // byte (i8) 类型在算术运算中需要提升到 i32
         let target_type = if left_type == "i8" || right_type == "i8" {
             "i32"
         } else if left_type == right_type {
@@ -143,8 +143,39 @@ impl IRGenerator {
         }
     }
 
+    /// 将值转换为目标类型（整数扩展/截断、整数转浮点、浮点扩展/截断、指针 bitcast）
+    ///
+    /// # Returns
+    /// 转换后的值（类型相同时原样返回）
+    pub fn convert_value_to(&mut self, from_type: &str, val: &str, to_type: &str) -> String {
+        if from_type == to_type {
+            return val.to_string();
+        }
+        let from_is_float = from_type == "float" || from_type == "double";
+        let to_is_float = to_type == "float" || to_type == "double";
+        let instr = if from_type.ends_with('*') || to_type.ends_with('*') {
+            "bitcast"
+        } else if from_is_float && to_is_float {
+            if to_type == "double" { "fpext" } else { "fptrunc" }
+        } else if to_is_float {
+            "sitofp"
+        } else if from_is_float {
+            "fptosi"
+        } else {
+            let from_bits: u32 = from_type.trim_start_matches('i').parse().unwrap_or(64);
+            let to_bits: u32 = to_type.trim_start_matches('i').parse().unwrap_or(64);
+            if from_bits < to_bits { "sext" } else { "trunc" }
+        };
+        let temp = self.new_temp();
+        self.emit_line(&format!("  {} = {} {} {} to {}", temp, instr, from_type, val, to_type));
+        temp
+    }
+
     /// 获取左值的类型和 LLVM 指针表示
     ///
+    /// 左值可以是局部变量、静态字段（`total` 或 `Counter.total`）、实例字段（隐式 this 的 `count`
+    /// 或 `obj.count`）和数组元素（`arr[i]`、`m[i][j]`）。对象和下标表达式只求值一次。
+    ///
     /// # Arguments
     /// * `expr` - 表达式
     ///
@@ -164,6 +195,14 @@ impl IRGenerator {
                         if let Some(field_info) = self.static_field_map.get(&static_key).cloned() {
                             return Ok((field_info.llvm_type, field_info.name));
                         }
+
+                        // 当前类的实例字段（隐式 this）
+                        if let Some(field_info) = self.get_instance_field(&self.current_class, name).cloned() {
+                            let class_name = self.current_class.clone();
+                            let this_ptr = self.load_this_ptr();
+                            let field_ptr = self.emit_field_ptr(&class_name, &field_info, &this_ptr);
+                            return Ok((field_info.llvm_type, field_ptr));
+                        }
                     }
                     // 回退到旧系统
                     let var_type = self.var_types.get(name)
@@ -177,6 +216,24 @@ impl IRGenerator {
                 let (elem_type, elem_ptr, _) = self.get_array_element_ptr(arr)?;
                 Ok((elem_type, elem_ptr))
             }
+            Expr::MemberAccess(member) => {
+                // 静态字段: ClassName.fieldName
+                if let Expr::Identifier(class_name) = member.object.as_ref() {
+                    let static_key = format!("{}.{}", class_name, member.member);
+                    if let Some(field_info) = self.static_field_map.get(&static_key).cloned() {
+                        return Ok((field_info.llvm_type, field_info.name));
+                    }
+                }
+                // 实例字段: this.fieldName、obj.fieldName、arr[i].fieldName ...
+                if let Some(class_name) = self.field_access_class(member)
+                    && let Some(field_info) = self.get_instance_field(&class_name, &member.member).cloned()
+                {
+                    let obj_ptr = self.generate_object_ptr(&member.object)?;
+                    let field_ptr = self.emit_field_ptr(&class_name, &field_info, &obj_ptr);
+                    return Ok((field_info.llvm_type, field_ptr));
+                }
+                Err(codegen_error(format!("Invalid lvalue expression: field '{}' not found", member.member)))
+            }
             _ => Err(codegen_error("Invalid lvalue expression".to_string()))
         }
     }
@@ -194,46 +251,18 @@ impl IRGenerator {
         // 检查除数是否为零
         let is_zero = self.new_temp();
         self.emit_line(&format!("  {} = icmp eq {} {}, 0", is_zero, val_type, val));
-        self.emit_line(&format!("  br i1 {}, label %{}, label %{}", is_zero, error_label, continue_label));
+        self.emit_cond_br(&is_zero, &error_label, &continue_label);
 
         // 错误处理块
-        self.emit_line(&format!("{}:", error_label));
-        // 输出错误信息到 stderr
-        let error_msg = self.get_or_create_string_constant("Error: Division by zero\n");
-        self.emit_line(&format!("  call i32 (i8*, ...) @printf(i8* {})", error_msg));
-        // 调用 exit 退出程序
-        self.emit_line("  call void @exit(i32 1)");
-        self.emit_line("  unreachable");
+        self.emit_label(&error_label);
+        // 输出错误信息并退出程序
+        let error_msg = self.get_string_constant_ptr("Error: Division by zero\n");
+        self.emit_runtime_error(&error_msg, &[]);
 
         // 正常继续块
-        self.emit_line(&format!("{}:", continue_label));
+        self.emit_label(&continue_label);
 
         Ok(())
     }
 
-    /// 将 LLVM 类型转换为方法签名
-    pub fn llvm_type_to_signature(&self, llvm_type: &str) -> String {
-        match llvm_type {
-            "i32" => "i".to_string(),
-            "i64" => "l".to_string(),
-            "float" => "f".to_string(),
-            "double" => "d".to_string(),
-            "i1" => "b".to_string(),
-            "i8*" => "s".to_string(),
-            "i8" => "c".to_string(),
-            t if t.ends_with("*") => "o".to_string(), // 对象/数组指针
-            _ => "x".to_string(), // 未知类型
-        }
-    }
-
-    /// 将 LLVM 类型转换为方法签名（支持可变参数数组类型）
-    pub fn llvm_type_to_signature_with_varargs(&self, llvm_type: &str, is_varargs_array: bool) -> String {
-        if is_varargs_array {
-            // 可变参数数组使用 ai 签名（array of int）
-            "ai".to_string()
-        } else {
-            self.llvm_type_to_signature(llvm_type)
-        }
-    }
-
 }
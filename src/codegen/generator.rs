@@ -1,4 +1,5 @@
-use crate::codegen::context::IRGenerator;
+use crate::codegen::context::{IRGenerator, THIS_PTR_VAR};
+use crate::codegen::mangle;
 use crate::ast::*;
 use crate::types::Type;
 use crate::error::cayResult;
@@ -79,9 +80,47 @@ impl PlatformAbstraction {
 }
 
 impl IRGenerator {
+    /// 在 C 入口函数中调用用户的 main 并返回退出码
+    ///
+    /// `void main()` 以 0 退出，`int main()` 以返回值作为退出码。
+    /// 带 `String[] args` 参数的 main 收到命令行参数（不含程序名 `argv[0]`），
+    /// 数组元素直接指向 `argv` 中的字符串，它们在整个程序运行期间有效。
+    fn emit_user_main_call(&mut self, main_fn_name: &str, return_type: &Type, takes_args: bool) {
+        let args = if takes_args {
+            // String[] 布局：8 字节头（前 4 字节为长度）后接元素指针
+            self.output.push_str("  %args.has_program = icmp sgt i32 %argc, 0\n");
+            self.output.push_str("  %args.count = sub i32 %argc, 1\n");
+            self.output.push_str("  %args.len = select i1 %args.has_program, i32 %args.count, i32 0\n");
+            self.output.push_str("  %args.len64 = sext i32 %args.len to i64\n");
+            self.output.push_str("  %args.bytes = mul i64 %args.len64, 8\n");
+            self.output.push_str("  %args.size = add i64 %args.bytes, 8\n");
+            self.output.push_str("  %args.raw = call i8* @calloc(i64 1, i64 %args.size)\n");
+            self.output.push_str("  %args.header = bitcast i8* %args.raw to i32*\n");
+            self.output.push_str("  store i32 %args.len, i32* %args.header, align 4\n");
+            self.output.push_str("  %args.data = getelementptr i8, i8* %args.raw, i64 8\n");
+            self.output.push_str("  %args.src = getelementptr i8*, i8** %argv, i64 1\n");
+            self.output.push_str("  %args.src.raw = bitcast i8** %args.src to i8*\n");
+            self.output.push_str("  call void @llvm.memcpy.p0i8.p0i8.i64(i8* %args.data, i8* %args.src.raw, i64 %args.bytes, i1 false)\n");
+            self.output.push_str("  %args = bitcast i8* %args.data to i8**\n");
+            "i8** %args"
+        } else {
+            ""
+        };
+        if *return_type == Type::Void {
+            self.output.push_str(&format!("  call void @{}({})\n", main_fn_name, args));
+            self.output.push_str("  ret i32 0\n");
+        } else {
+            self.output.push_str(&format!("  %ret = call i32 @{}({})\n", main_fn_name, args));
+            self.output.push_str("  ret i32 %ret\n");
+        }
+    }
+
     pub fn generate(&mut self, program: &Program) -> cayResult<String> {
         self.emit_header();
 
+        // 逃逸分析：不逃逸出方法的对象在栈上分配
+        self.stack_allocated_objects = crate::codegen::escape::analyze(program);
+
         let mut main_class = None;
         let mut main_method = None;
         let mut fallback_main_class = None;
@@ -96,16 +135,9 @@ impl IRGenerator {
             }
         }
 
-        // 首先计算所有类的实例布局
-        for class in &program.classes {
-            let instance_fields: Vec<_> = class.members.iter()
-                .filter_map(|m| match m {
-                    ClassMember::Field(f) => Some(f.clone()),
-                    _ => None,
-                })
-                .collect();
-            self.compute_class_layout(&class.name, &instance_fields);
-        }
+        // 首先计算所有类的实例布局和虚表
+        self.compute_class_layouts(program);
+        self.compute_vtables(program);
 
         for class in &program.classes {
             self.collect_static_fields(class)?;
@@ -137,25 +169,32 @@ impl IRGenerator {
 
         self.emit_static_field_declarations();
         self.register_type_identifiers(program);
+        self.emit_imported_class_declarations();
 
         // 生成顶层函数
         for func in &program.top_level_functions {
             self.generate_top_level_function(func)?;
         }
 
-        for class in &program.classes {
-            self.generate_class(class)?;
+        if self.jobs > 1 && program.classes.len() > 1 {
+            self.generate_classes_parallel(&program.classes)?;
+        } else {
+            for class in &program.classes {
+                self.generate_class(class)?;
+            }
         }
 
         self.output.push_str(&self.code);
 
-        // 生成跨平台 C entry point
-        if use_top_level_main {
+        // 生成跨平台 C entry point；库模式下没有入口，静态数组在全局构造函数中初始化
+        let library = self.platform_config.as_ref().is_some_and(|config| config.no_main);
+        if library {
+            self.emit_library_static_initializer();
+        } else if use_top_level_main {
             // 使用顶层 main 函数
             let func = top_level_main.unwrap();
             self.output.push_str("; Cross-platform C entry point\n");
-            self.output.push_str(&format!("define i32 @main() {{
-"));
+            self.output.push_str("define i32 @main(i32 %argc, i8** %argv) {\n");
             self.output.push_str("entry:\n");
             
             // 使用平台配置生成初始化代码
@@ -166,18 +205,12 @@ impl IRGenerator {
             
             self.generate_static_array_initialization();
             let main_fn_name = self.generate_top_level_function_name(&func.name);
-            if func.return_type == Type::Void {
-                self.output.push_str(&format!("  call void @{}()\n", main_fn_name));
-                self.output.push_str("  ret i32 0\n");
-            } else {
-                self.output.push_str(&format!("  %ret = call i32 @{}()\n", main_fn_name));
-                self.output.push_str("  ret i32 %ret\n");
-            }
+            self.emit_user_main_call(&main_fn_name, &func.return_type, !func.params.is_empty());
             self.output.push_str("}\n");
             self.output.push_str("\n");
         } else if let (Some(class_name), Some(main_method)) = (main_class, main_method) {
             self.output.push_str("; C entry point\n");
-            self.output.push_str(&format!("define i32 @main() {{\n"));
+            self.output.push_str("define i32 @main(i32 %argc, i8** %argv) {\n");
             self.output.push_str("entry:\n");
             // 只在 Windows 目标平台上设置控制台代码页
             if self.is_windows_target() {
@@ -185,8 +218,7 @@ impl IRGenerator {
             }
             self.generate_static_array_initialization();
             let main_fn_name = self.generate_method_name(&class_name, &main_method);
-            self.output.push_str(&format!("  call void @{}()\n", main_fn_name));
-            self.output.push_str("  ret i32 0\n");
+            self.emit_user_main_call(&main_fn_name, &main_method.return_type, !main_method.params.is_empty());
             self.output.push_str("}\n");
             self.output.push_str("\n");
         }
@@ -195,27 +227,29 @@ impl IRGenerator {
             self.output.push_str(lambda_code);
         }
 
-        let string_decls = self.get_string_declarations();
         let type_id_decls = self.emit_type_id_declarations();
-
-        let mut output = self.output.clone();
-        let insert_pos = output.find("define i8* @__cay_string_concat")
+        let class_type_decls = self.emit_class_type_declarations();
+        let vtable_decls = self.emit_vtable_declarations();
+        let itable_decls = self.emit_itable_declarations();
+        let reflection_decls = self.emit_reflection_declarations();
+
+        // 模块级声明插入到运行时区段之前：一次性拼接，避免移动整个模块
+        let output = std::mem::take(&mut self.output);
+        let native_decls = self.emit_native_declarations(&output)?;
+        let insert_pos = output.find(crate::codegen::runtime::RUNTIME_SECTION_MARKER)
             .unwrap_or(output.len());
-
-        let mut decls = String::new();
-        if !type_id_decls.is_empty() {
-            decls.push_str(&type_id_decls);
-            decls.push_str("\n");
-        }
-        if !string_decls.is_empty() {
-            decls.push_str(&string_decls);
-        }
-
-        if !decls.is_empty() {
-            output.insert_str(insert_pos, &decls);
+        let mut module = String::with_capacity(output.len() + self.global_strings.keys().map(|s| s.len() + 64).sum::<usize>());
+        module.push_str(&output[..insert_pos]);
+        for decls in [&class_type_decls, &type_id_decls, &vtable_decls, &itable_decls, &reflection_decls, &native_decls] {
+            if !decls.is_empty() {
+                module.push_str(decls);
+                module.push('\n');
+            }
         }
+        self.write_string_declarations(&mut module);
+        module.push_str(&output[insert_pos..]);
 
-        self.output = output;
+        self.output = self.apply_heap_limit(module);
 
         Ok(self.output.clone())
     }
@@ -232,9 +266,9 @@ impl IRGenerator {
     }
 
     fn register_static_field(&mut self, class_name: &str, field: &FieldDecl) -> cayResult<()> {
-        let full_name = format!("@{}.{}_s", class_name, field.name);
+        let full_name = self.static_field_symbol(class_name, &field.name);
         let llvm_type = self.type_to_llvm(&field.field_type);
-        let size = field.field_type.size_in_bytes();
+        let size = field.field_type.size_of(&self.data_layout);
 
         let field_info = crate::codegen::context::StaticFieldInfo {
             name: full_name.clone(),
@@ -299,19 +333,9 @@ impl IRGenerator {
         match expr {
             Expr::Literal(crate::ast::LiteralValue::Int32(n)) => Some(n.to_string()),
             Expr::Literal(crate::ast::LiteralValue::Int64(n)) => Some(n.to_string()),
-            Expr::Literal(crate::ast::LiteralValue::Float32(f)) => {
-                if f.is_nan() {
-                    Some("0x7FC00000".to_string())
-                } else if f.is_infinite() {
-                    if *f > 0.0 {
-                        Some("0x7F800000".to_string())
-                    } else {
-                        Some("0xFF800000".to_string())
-                    }
-                } else {
-                    Some(format!("{:.6e}", f))
-                }
-            }
+            Expr::Literal(crate::ast::LiteralValue::Float32(f)) => Some(Self::format_float_constant(*f)),
+            // float 字段用 double 字面量初始化时，按单精度取值
+            Expr::Literal(crate::ast::LiteralValue::Float64(f)) if llvm_type == "float" => Some(Self::format_float_constant(*f as f32)),
             Expr::Literal(crate::ast::LiteralValue::Float64(f)) => {
                 if f.is_nan() {
                     Some("0x7FF8000000000000".to_string())
@@ -342,6 +366,24 @@ impl IRGenerator {
         }
     }
 
+    /// 库模式：把静态数组的初始化放入 `@llvm.global_ctors` 注册的内部函数，在使用方的 main 之前执行
+    fn emit_library_static_initializer(&mut self) {
+        let body_start = self.output.len();
+        self.generate_static_array_initialization();
+        if self.output.len() == body_start {
+            return;
+        }
+        let body = self.output.split_off(body_start);
+        self.output.push_str("; 库模块的静态初始化\n");
+        self.output.push_str("define internal void @__cay_static_init() {\n");
+        self.output.push_str("entry:\n");
+        self.output.push_str(&body);
+        self.output.push_str("  ret void\n");
+        self.output.push_str("}\n");
+        self.output.push_str("@llvm.global_ctors = appending global [1 x { i32, void ()*, i8* }] \
+                              [{ i32, void ()*, i8* } { i32 65535, void ()* @__cay_static_init, i8* null }]\n\n");
+    }
+
     fn generate_static_array_initialization(&mut self) {
         let fields: Vec<_> = self.static_fields.clone();
         for field in fields {
@@ -351,8 +393,8 @@ impl IRGenerator {
                         if !array_creation.sizes.is_empty() {
                             if let Some(size_val) = self.evaluate_const_int(&array_creation.sizes[0]) {
                                 let elem_llvm_type = self.type_to_llvm(elem_type);
-                                let elem_size = self.get_type_size(&elem_llvm_type);
-                                let total_size = size_val as i64 * elem_size;
+                                let elem_size = elem_type.size_of(&self.data_layout);
+                                let total_size = size_val as i64 * elem_size as i64;
 
                                 let calloc_temp = self.new_temp();
                                 self.output.push_str(&format!(
@@ -367,8 +409,9 @@ impl IRGenerator {
                                 ));
 
                                 self.output.push_str(&format!(
-                                    "  store {}* {}, {}** {}, align 8\n",
-                                    elem_llvm_type, cast_temp, elem_llvm_type, field.name
+                                    "  store {}* {}, {}** {}, align {}\n",
+                                    elem_llvm_type, cast_temp, elem_llvm_type, field.name,
+                                    self.get_type_align("i8*")
                                 ));
                             }
                         }
@@ -397,18 +440,6 @@ impl IRGenerator {
         }
     }
 
-    fn get_type_size(&self, llvm_type: &str) -> i64 {
-        match llvm_type {
-            "i1" => 1,
-            "i8" => 1,
-            "i32" => 4,
-            "i64" => 8,
-            "float" => 4,
-            "double" => 8,
-            _ => 8,
-        }
-    }
-
     fn generate_class_declarations(&mut self, class: &ClassDecl) -> cayResult<()> {
         for member in &class.members {
             if let ClassMember::Method(method) = member {
@@ -439,12 +470,60 @@ impl IRGenerator {
         Ok(())
     }
 
-    fn generate_class(&mut self, class: &ClassDecl) -> cayResult<()> {
+    /// 为从 .cavi 接口文件导入的类生成外部函数声明
+    ///
+    /// 这些类的方法和构造函数在其他编译单元中定义，链接时解析。
+    fn emit_imported_class_declarations(&mut self) {
+        let Some(registry) = self.type_registry.clone() else {
+            return;
+        };
+        let mut imported: Vec<&String> = registry.imported_classes.iter().collect();
+        imported.sort();
+
+        for class_name in imported {
+            let Some(class_info) = registry.get_class(class_name) else {
+                continue;
+            };
+            let mut method_names: Vec<&String> = class_info.methods.keys().collect();
+            method_names.sort();
+            for method_name in method_names {
+                for method in &class_info.methods[method_name] {
+                    if method.is_native {
+                        continue;
+                    }
+                    let param_types: Vec<Type> = method.params.iter().map(|p| p.param_type.clone()).collect();
+                    let fn_name = mangle::mangle(class_name, &method.name, &param_types);
+                    let mut params: Vec<String> = Vec::new();
+                    if !method.is_static {
+                        params.push("i8*".to_string());
+                    }
+                    params.extend(method.params.iter().map(|p| self.type_to_llvm(&p.param_type)));
+                    let ret_type = self.type_to_llvm(&method.return_type);
+                    self.emit_raw(&format!("declare {} @{}({})", ret_type, fn_name, params.join(", ")));
+                }
+            }
+            for ctor in &class_info.constructors {
+                let param_types: Vec<Type> = ctor.params.iter().map(|p| p.param_type.clone()).collect();
+                let fn_name = mangle::mangle_constructor(class_name, &param_types);
+                let mut params = vec!["i8*".to_string()];
+                params.extend(ctor.params.iter().map(|p| self.type_to_llvm(&p.param_type)));
+                self.emit_raw(&format!("declare void @{}({})", fn_name, params.join(", ")));
+            }
+        }
+    }
+
+    pub(super) fn generate_class(&mut self, class: &ClassDecl) -> cayResult<()> {
+        let native_class = crate::prelude::native_class(class);
         for member in &class.members {
             match member {
                 ClassMember::Method(method) => {
-                    if !method.modifiers.contains(&Modifier::Native) {
+                    // 抽象方法没有函数体，由子类实现（虚表中对应槽位为空）
+                    if !method.modifiers.contains(&Modifier::Native) && method.body.is_some() {
                         self.generate_method(&class.name, method)?;
+                    } else if let Some(native) = &native_class
+                        && method.modifiers.contains(&Modifier::Native)
+                    {
+                        self.generate_native_method(&class.name, method, native)?;
                     }
                 }
                 ClassMember::Field(field) => {
@@ -468,6 +547,7 @@ impl IRGenerator {
     }
 
     fn generate_method(&mut self, class_name: &str, method: &MethodDecl) -> cayResult<()> {
+        crate::ice::set_location(&method.loc);
         let fn_name = self.generate_method_name(class_name, method);
         self.current_function = fn_name.clone();
         self.current_class = class_name.to_string();
@@ -496,24 +576,28 @@ impl IRGenerator {
             ret_type, fn_name, params.join(", ")));
         self.indent += 1;
 
-        self.emit_line("entry:");
+        self.emit_entry_label();
+        self.emit_stack_guard_enter(&format!("{}.{}", class_name, method.name));
+        self.emit_step_tick(&format!("{}.{}", class_name, method.name));
         
         // 实例方法声明 this 变量
         if !is_static {
             // 使用 this_ptr 作为变量名，避免与参数 %this 冲突
-            let this_llvm_name = self.scope_manager.declare_var("this_ptr", "i8*");
-            self.emit_line(&format!("  %{} = alloca i8*", this_llvm_name));
-            self.emit_line(&format!("  store i8* %this, i8** %{}", this_llvm_name));
+            let this_llvm_name = self.scope_manager.declare_var(THIS_PTR_VAR, "i8*", &Type::Object(class_name.to_string()));
+            self.emit_alloca(&this_llvm_name, "i8*");
+            self.emit_store("i8*", "%this", &format!("%{}", this_llvm_name));
             self.var_types.insert("this".to_string(), "i8*".to_string());
         }
 
         for param in &method.params {
             let param_type = self.type_to_llvm(&param.param_type);
-            let llvm_name = self.scope_manager.declare_var(&param.name, &param_type);
-            self.emit_line(&format!("  %{} = alloca {}", llvm_name, param_type));
-            self.emit_line(&format!("  store {} %{}.{}, {}* %{}",
-                param_type, class_name, param.name, param_type, llvm_name));
+            let llvm_name = self.scope_manager.declare_var(&param.name, &param_type, &param.param_type);
+            self.emit_alloca(&llvm_name, &param_type);
+            self.emit_store(&param_type, &format!("%{}.{}", class_name, param.name), &format!("%{}", llvm_name));
             self.var_types.insert(param.name.clone(), param_type);
+            if let Type::Object(param_class) = &param.param_type {
+                self.var_class_map.insert(param.name.clone(), param_class.clone());
+            }
         }
 
         if let Some(body) = method.body.as_ref() {
@@ -521,10 +605,13 @@ impl IRGenerator {
         }
 
         if method.return_type == Type::Void {
+            self.emit_stack_guard_exit();
             self.emit_line("  ret void");
         }
+        self.stack_guard_active = false;
 
         self.indent -= 1;
+        self.finish_function_body()?;
         self.emit_line("}");
         self.emit_line("");
 
@@ -553,49 +640,35 @@ impl IRGenerator {
             fn_name, all_params.join(", ")));
         self.indent += 1;
 
-        self.emit_line("entry:");
+        self.emit_entry_label();
 
-        let this_llvm_name = self.scope_manager.declare_var("this", "i8*");
-        self.emit_line(&format!("  %{} = alloca i8*", this_llvm_name));
-        self.emit_line(&format!("  store i8* %this, i8** %{}", this_llvm_name));
+        let this_llvm_name = self.scope_manager.declare_var(THIS_PTR_VAR, "i8*", &Type::Object(class_name.to_string()));
+        self.emit_alloca(&this_llvm_name, "i8*");
+        self.emit_store("i8*", "%this", &format!("%{}", this_llvm_name));
         self.var_types.insert("this".to_string(), "i8*".to_string());
 
         for param in &ctor.params {
             let param_type = self.type_to_llvm(&param.param_type);
-            let llvm_name = self.scope_manager.declare_var(&param.name, &param_type);
-            self.emit_line(&format!("  %{} = alloca {}", llvm_name, param_type));
-            self.emit_line(&format!("  store {} %{}.{}_param, {}* %{}",
-                param_type, class_name, param.name, param_type, llvm_name));
+            let llvm_name = self.scope_manager.declare_var(&param.name, &param_type, &param.param_type);
+            self.emit_alloca(&llvm_name, &param_type);
+            self.emit_store(&param_type, &format!("%{}.{}_param", class_name, param.name), &format!("%{}", llvm_name));
             self.var_types.insert(param.name.clone(), param_type);
+            if let Type::Object(param_class) = &param.param_type {
+                self.var_class_map.insert(param.name.clone(), param_class.clone());
+            }
         }
 
-        if let Some(ref call) = ctor.constructor_call {
-            match call {
-                crate::ast::ConstructorCall::This(args) => {
-                    let target_ctor_name = self.generate_constructor_call_name(class_name, args.len());
-                    let mut arg_strs = vec!["i8* %this".to_string()];
-                    for arg in args {
-                        let arg_val = self.generate_expression(arg)?;
-                        arg_strs.push(arg_val);
-                    }
-                    self.emit_line(&format!("  call void @{}({})",
-                        target_ctor_name, arg_strs.join(", ")));
+        // 构造链调用；没有显式调用时隐式调用父类的无参构造函数
+        match &ctor.constructor_call {
+            Some(crate::ast::ConstructorCall::This(args)) => self.emit_constructor_call(class_name, args, "%this")?,
+            Some(crate::ast::ConstructorCall::Super(args)) => {
+                if let Some(parent) = self.parent_class(class_name) {
+                    self.emit_constructor_call(&parent, args, "%this")?;
                 }
-                crate::ast::ConstructorCall::Super(args) => {
-                    if let Some(ref registry) = self.type_registry {
-                        if let Some(class_info) = registry.get_class(class_name) {
-                            if let Some(ref parent_name) = class_info.parent {
-                                let parent_ctor_name = format!("{}.__ctor", parent_name);
-                                let mut arg_strs = vec!["i8* %this".to_string()];
-                                for arg in args {
-                                    let arg_val = self.generate_expression(arg)?;
-                                    arg_strs.push(arg_val);
-                                }
-                                self.emit_line(&format!("  call void @{}({})",
-                                    parent_ctor_name, arg_strs.join(", ")));
-                            }
-                        }
-                    }
+            }
+            None => {
+                if let Some(parent) = self.parent_class(class_name) {
+                    self.emit_constructor_call(&parent, &[], "%this")?;
                 }
             }
         }
@@ -605,6 +678,7 @@ impl IRGenerator {
         self.emit_line("  ret void");
 
         self.indent -= 1;
+        self.finish_function_body()?;
         self.emit_line("}");
         self.emit_line("");
 
@@ -612,7 +686,7 @@ impl IRGenerator {
     }
 
     fn generate_destructor(&mut self, class_name: &str, dtor: &crate::ast::DestructorDecl) -> cayResult<()> {
-        let fn_name = format!("{}.__dtor", class_name);
+        let fn_name = mangle::destructor_symbol(class_name);
         self.current_function = fn_name.clone();
         self.current_class = class_name.to_string();
         self.current_return_type = "void".to_string();
@@ -625,11 +699,11 @@ impl IRGenerator {
         self.emit_line(&format!("define void @{}(i8* %this) {{", fn_name));
         self.indent += 1;
 
-        self.emit_line("entry:");
+        self.emit_entry_label();
 
-        let this_llvm_name = self.scope_manager.declare_var("this", "i8*");
-        self.emit_line(&format!("  %{} = alloca i8*", this_llvm_name));
-        self.emit_line(&format!("  store i8* %this, i8** %{}", this_llvm_name));
+        let this_llvm_name = self.scope_manager.declare_var(THIS_PTR_VAR, "i8*", &Type::Object(class_name.to_string()));
+        self.emit_alloca(&this_llvm_name, "i8*");
+        self.emit_store("i8*", "%this", &format!("%{}", this_llvm_name));
         self.var_types.insert("this".to_string(), "i8*".to_string());
 
         self.generate_block(&dtor.body)?;
@@ -637,6 +711,7 @@ impl IRGenerator {
         self.emit_line("  ret void");
 
         self.indent -= 1;
+        self.finish_function_body()?;
         self.emit_line("}");
         self.emit_line("");
 
@@ -644,7 +719,7 @@ impl IRGenerator {
     }
 
     fn generate_static_initializer(&mut self, class_name: &str, block: &crate::ast::Block) -> cayResult<()> {
-        let fn_name = format!("{}.__static_init", class_name);
+        let fn_name = mangle::static_init_symbol(class_name);
         self.current_function = fn_name.clone();
         self.current_class = class_name.to_string();
         self.current_return_type = "void".to_string();
@@ -657,13 +732,14 @@ impl IRGenerator {
         self.emit_line(&format!("define void @{}() {{", fn_name));
         self.indent += 1;
 
-        self.emit_line("entry:");
+        self.emit_entry_label();
 
         self.generate_block(block)?;
 
         self.emit_line("  ret void");
 
         self.indent -= 1;
+        self.finish_function_body()?;
         self.emit_line("}");
         self.emit_line("");
 
@@ -671,23 +747,54 @@ impl IRGenerator {
     }
 
     fn generate_constructor_name(&self, class_name: &str, ctor: &crate::ast::ConstructorDecl) -> String {
-        if ctor.params.is_empty() {
-            format!("{}.__ctor", class_name)
-        } else {
-            let param_types: Vec<String> = ctor.params.iter()
-                .map(|p| self.type_to_signature(&p.param_type))
-                .collect();
-            format!("{}.__ctor_{}", class_name, param_types.join("_"))
-        }
+        let param_types: Vec<Type> = ctor.params.iter().map(|p| p.param_type.clone()).collect();
+        mangle::mangle_constructor(class_name, &param_types)
     }
 
     fn generate_constructor_call_name(&self, class_name: &str, arg_count: usize) -> String {
-        if arg_count == 0 {
-            format!("{}.__ctor", class_name)
-        } else {
-            let param_types: Vec<String> = (0..arg_count).map(|_| "i".to_string()).collect();
-            format!("{}.__ctor_{}", class_name, param_types.join("_"))
+        // 优先按参数个数匹配类中声明的构造函数，使用其参数类型生成签名
+        let declared = self.type_registry.as_ref()
+            .and_then(|registry| registry.get_class(class_name))
+            .and_then(|class_info| class_info.constructors.iter().find(|c| c.params.len() == arg_count));
+        let param_types: Vec<Type> = match declared {
+            Some(ctor) => ctor.params.iter().map(|p| p.param_type.clone()).collect(),
+            None => vec![Type::Int32; arg_count],
+        };
+        mangle::mangle_constructor(class_name, &param_types)
+    }
+
+    /// 以 `arg_count` 个实参构造 `class_name` 时执行的构造函数名和形参类型
+    ///
+    /// 没有声明构造函数的类只有默认构造函数，它只调用父类的无参构造函数，因此直接沿继承链查找。
+    fn resolve_constructor(&self, class_name: &str, arg_count: usize) -> Option<(String, Vec<Type>)> {
+        let class_info = self.type_registry.as_ref()?.get_class(class_name)?;
+        if class_info.constructors.is_empty() {
+            return match &class_info.parent {
+                Some(parent) if arg_count == 0 => self.resolve_constructor(parent, 0),
+                _ => None,
+            };
         }
+        let ctor = class_info.constructors.iter().find(|c| c.params.len() == arg_count)?;
+        let param_types = ctor.params.iter().map(|p| p.param_type.clone()).collect();
+        Some((self.generate_constructor_call_name(class_name, arg_count), param_types))
+    }
+
+    /// 以 `this_ptr` 为接收者调用 `class_name` 的构造函数，实参转换为形参类型
+    ///
+    /// 构造过程中没有需要执行的构造函数时不生成调用。
+    pub(crate) fn emit_constructor_call(&mut self, class_name: &str, args: &[Expr], this_ptr: &str) -> cayResult<()> {
+        let Some((fn_name, param_types)) = self.resolve_constructor(class_name, args.len()) else {
+            return Ok(());
+        };
+        let mut arg_strs = vec![format!("i8* {}", this_ptr)];
+        for (arg, param_type) in args.iter().zip(&param_types) {
+            let value = self.generate_expression(arg)?;
+            let llvm_type = self.type_to_llvm(param_type);
+            let converted = self.convert_value_to(&value.ty, &value.repr, &llvm_type);
+            arg_strs.push(format!("{} {}", llvm_type, converted));
+        }
+        self.emit_line(&format!("  call void @{}({})", fn_name, arg_strs.join(", ")));
+        Ok(())
     }
 
     /// 生成顶层函数
@@ -711,24 +818,31 @@ impl IRGenerator {
             ret_type, fn_name, params.join(", ")));
         self.indent += 1;
 
-        self.emit_line("entry:");
+        self.emit_entry_label();
+        self.emit_stack_guard_enter(&func.name);
+        self.emit_step_tick(&func.name);
 
         for param in &func.params {
             let param_type = self.type_to_llvm(&param.param_type);
-            let llvm_name = self.scope_manager.declare_var(&param.name, &param_type);
-            self.emit_line(&format!("  %{} = alloca {}", llvm_name, param_type));
-            self.emit_line(&format!("  store {} %{}.param, {}* %{}",
-                param_type, param.name, param_type, llvm_name));
+            let llvm_name = self.scope_manager.declare_var(&param.name, &param_type, &param.param_type);
+            self.emit_alloca(&llvm_name, &param_type);
+            self.emit_store(&param_type, &format!("%{}.param", param.name), &format!("%{}", llvm_name));
             self.var_types.insert(param.name.clone(), param_type);
+            if let Type::Object(param_class) = &param.param_type {
+                self.var_class_map.insert(param.name.clone(), param_class.clone());
+            }
         }
 
         self.generate_block(&func.body)?;
 
         if func.return_type == Type::Void {
+            self.emit_stack_guard_exit();
             self.emit_line("  ret void");
         }
+        self.stack_guard_active = false;
 
         self.indent -= 1;
+        self.finish_function_body()?;
         self.emit_line("}");
         self.emit_line("");
 
@@ -0,0 +1,254 @@
+//! 结构化的函数体 IR
+//!
+//! `IRGenerator` 生成函数体时不直接拼接文本，而是把指令追加到 [`FunctionBody`] 的当前基本块，
+//! 函数结束时校验并一次性序列化为文本。常用指令（alloca/load/store/br/switch/call）由
+//! `emit_*` 辅助函数构造为结构化的 [`Instruction`]，其余指令暂以 [`Instruction::Raw`] 保存文本，
+//! 但仍能识别终止指令和跳转目标。
+//!
+//! 基本块的结构由构建过程保证：
+//! - 新标签开始时上一块还没有终止，补一条跳转到新块的 `br`（顺序执行）；
+//! - 终止指令之后继续生成的指令（如 `return` 之后的语句）放入新的不可达块，而不是跟在终止指令后面；
+//! - 函数结束时仍未终止的块补 `unreachable`（例如非 void 方法以死循环结尾）。
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::error::SourceLocation;
+
+/// 函数体中的一条指令
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    /// `%name = alloca ty, align n`
+    Alloca { dest: String, ty: String, align: u32 },
+    /// `%dest = load ty, ty* ptr, align n`
+    Load { dest: String, ty: String, ptr: String, align: u32 },
+    /// `store ty value, ty* ptr, align n`
+    Store { ty: String, value: String, ptr: String, align: u32 },
+    /// `br label %target`
+    Br { target: String },
+    /// `br i1 cond, label %then, label %else`
+    CondBr { cond: String, then_label: String, else_label: String },
+    /// `switch ty value, label %default [ ty v, label %l ... ]`
+    Switch { ty: String, value: String, default: String, cases: Vec<(String, String)> },
+    /// `%dest = call ret callee(args)`，`void` 调用没有 `dest`
+    Call { dest: Option<String>, ret_ty: String, callee: String, args: Vec<String> },
+    /// `unreachable`
+    Unreachable,
+    /// 注释行，不属于控制流
+    Comment(String),
+    /// 尚未结构化的指令文本（不含缩进）
+    Raw(String),
+}
+
+impl Instruction {
+    /// 把一行指令文本解析为指令（标签行由调用方处理）
+    pub fn parse(text: &str) -> Instruction {
+        let text = text.trim();
+        if text.starts_with(';') {
+            return Instruction::Comment(text.to_string());
+        }
+        match text {
+            "unreachable" => Instruction::Unreachable,
+            _ => Instruction::Raw(text.to_string()),
+        }
+    }
+
+    /// 操作码（`%x = add ...` 的操作码为 `add`）
+    fn opcode(text: &str) -> &str {
+        let rest = match text.split_once(" = ") {
+            Some((dest, rest)) if dest.starts_with('%') && !dest.contains(' ') => rest,
+            _ => text,
+        };
+        rest.split_whitespace().next().unwrap_or("")
+    }
+
+    /// 是否为基本块的终止指令
+    pub fn is_terminator(&self) -> bool {
+        match self {
+            Instruction::Br { .. } | Instruction::CondBr { .. } | Instruction::Switch { .. } | Instruction::Unreachable => true,
+            Instruction::Raw(text) => matches!(Self::opcode(text), "ret" | "br" | "switch" | "indirectbr" | "resume" | "unreachable"),
+            _ => false,
+        }
+    }
+
+    /// 指令跳转到的基本块标签
+    pub fn successors(&self) -> Vec<&str> {
+        match self {
+            Instruction::Br { target } => vec![target],
+            Instruction::CondBr { then_label, else_label, .. } => vec![then_label, else_label],
+            Instruction::Switch { default, cases, .. } => {
+                std::iter::once(default.as_str()).chain(cases.iter().map(|(_, label)| label.as_str())).collect()
+            }
+            Instruction::Raw(text) if self.is_terminator() => text
+                .split("label %")
+                .skip(1)
+                .map(|rest| rest.split([',', ' ', ']']).next().unwrap_or(""))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instruction::Alloca { dest, ty, align } => write!(f, "{} = alloca {}, align {}", dest, ty, align),
+            Instruction::Load { dest, ty, ptr, align } => write!(f, "{} = load {}, {}* {}, align {}", dest, ty, ty, ptr, align),
+            Instruction::Store { ty, value, ptr, align } => write!(f, "store {} {}, {}* {}, align {}", ty, value, ty, ptr, align),
+            Instruction::Br { target } => write!(f, "br label %{}", target),
+            Instruction::CondBr { cond, then_label, else_label } => {
+                write!(f, "br i1 {}, label %{}, label %{}", cond, then_label, else_label)
+            }
+            Instruction::Switch { ty, value, default, cases } => {
+                write!(f, "switch {} {}, label %{} [", ty, value, default)?;
+                for (case_value, label) in cases {
+                    write!(f, "\n    {} {}, label %{}", ty, case_value, label)?;
+                }
+                write!(f, "\n  ]")
+            }
+            Instruction::Call { dest, ret_ty, callee, args } => {
+                if let Some(dest) = dest {
+                    write!(f, "{} = ", dest)?;
+                }
+                write!(f, "call {} {}({})", ret_ty, callee, args.join(", "))
+            }
+            Instruction::Unreachable => write!(f, "unreachable"),
+            Instruction::Comment(text) | Instruction::Raw(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+/// 基本块
+#[derive(Debug, Clone)]
+pub struct BasicBlock {
+    pub label: String,
+    pub instructions: Vec<Instruction>,
+    /// 与 `instructions` 一一对应：生成该指令时正在处理的源码位置
+    pub locations: Vec<Option<SourceLocation>>,
+}
+
+impl BasicBlock {
+    fn new(label: &str) -> Self {
+        BasicBlock { label: label.to_string(), instructions: Vec::new(), locations: Vec::new() }
+    }
+
+    fn push(&mut self, inst: Instruction, location: Option<SourceLocation>) {
+        self.instructions.push(inst);
+        self.locations.push(location);
+    }
+
+    /// 块末尾的终止指令（忽略其后的注释）
+    pub fn terminator(&self) -> Option<&Instruction> {
+        self.instructions.iter().rev()
+            .find(|inst| !matches!(inst, Instruction::Comment(_)))
+            .filter(|inst| inst.is_terminator())
+    }
+}
+
+/// 正在生成的函数体
+#[derive(Debug, Clone)]
+pub struct FunctionBody {
+    /// 放在入口块开头的指令（alloca 和字符串常量地址）
+    pub entry_prologue: Vec<Instruction>,
+    pub blocks: Vec<BasicBlock>,
+    /// 之后追加的指令对应的源码位置
+    pub location: Option<SourceLocation>,
+    /// 为终止指令之后的不可达代码生成的块计数
+    dead_blocks: usize,
+}
+
+impl Default for FunctionBody {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FunctionBody {
+    /// 以 `entry` 块开始的空函数体
+    pub fn new() -> Self {
+        FunctionBody { entry_prologue: Vec::new(), blocks: vec![BasicBlock::new("entry")], location: None, dead_blocks: 0 }
+    }
+
+    fn current(&mut self) -> &mut BasicBlock {
+        self.blocks.last_mut().expect("函数体至少有入口块")
+    }
+
+    /// 当前块是否已经终止（之后的指令不可达）
+    pub fn is_terminated(&self) -> bool {
+        self.blocks.last().is_some_and(|block| block.terminator().is_some())
+    }
+
+    /// 开始新的基本块，上一块未终止时顺序执行到新块
+    pub fn start_block(&mut self, label: &str) {
+        if !self.is_terminated() {
+            let location = self.location.clone();
+            self.current().push(Instruction::Br { target: label.to_string() }, location);
+        }
+        self.blocks.push(BasicBlock::new(label));
+    }
+
+    /// 追加指令；当前块已终止时先开始一个不可达块
+    pub fn push(&mut self, inst: Instruction) {
+        if self.is_terminated() && !matches!(inst, Instruction::Comment(_)) {
+            let label = format!("dead.{}", self.dead_blocks);
+            self.dead_blocks += 1;
+            self.blocks.push(BasicBlock::new(&label));
+        }
+        let location = self.location.clone();
+        self.current().push(inst, location);
+    }
+
+    /// 结束函数体：未终止的块补 `unreachable`
+    pub fn finish(&mut self) {
+        for block in &mut self.blocks {
+            if block.terminator().is_none() {
+                block.push(Instruction::Unreachable, None);
+            }
+        }
+    }
+
+    /// 校验基本块结构：标签唯一、每块恰好以一条终止指令结束、跳转目标存在
+    pub fn verify(&self) -> Result<(), String> {
+        let mut labels = HashSet::new();
+        for block in &self.blocks {
+            if !labels.insert(block.label.as_str()) {
+                return Err(format!("duplicate basic block label '{}'", block.label));
+            }
+        }
+        for block in &self.blocks {
+            let code: Vec<&Instruction> = block.instructions.iter()
+                .filter(|inst| !matches!(inst, Instruction::Comment(_)))
+                .collect();
+            match code.split_last() {
+                Some((last, rest)) if last.is_terminator() => {
+                    if let Some(inst) = rest.iter().find(|inst| inst.is_terminator()) {
+                        return Err(format!("terminator '{}' in the middle of block '{}'", inst, block.label));
+                    }
+                    for target in last.successors() {
+                        if !labels.contains(target) {
+                            return Err(format!("block '{}' branches to undefined label '{}'", block.label, target));
+                        }
+                    }
+                }
+                _ => return Err(format!("block '{}' does not end with a terminator", block.label)),
+            }
+        }
+        Ok(())
+    }
+
+    /// 序列化为文本（不含 `define` 行和右花括号）
+    pub fn write_to(&self, out: &mut String) {
+        for (index, block) in self.blocks.iter().enumerate() {
+            out.push_str(&block.label);
+            out.push_str(":\n");
+            if index == 0 {
+                for inst in &self.entry_prologue {
+                    out.push_str(&format!("  {}\n", inst));
+                }
+            }
+            for inst in &block.instructions {
+                out.push_str(&format!("  {}\n", inst));
+            }
+        }
+    }
+}
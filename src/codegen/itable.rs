@@ -0,0 +1,143 @@
+//! 接口方法表
+//!
+//! 每个（类, 接口）组合生成一个接口方法表（itable），槽位按接口方法名排序：
+//!
+//! ```text
+//! @Circle.itable.Shape = internal constant [2 x i8*] [
+//!   i8* bitcast (double (i8*)* @Circle.area to i8*),
+//!   i8* bitcast (i8* (i8*)* @Circle.name to i8*)
+//! ]
+//! ```
+//!
+//! 每个接口再生成一张按 type_id 索引的表，实现了该接口的类对应其 itable，其他位置为 null：
+//!
+//! ```text
+//! @Shape.itables = internal constant [4 x i8**] [i8** null, i8** getelementptr (...), ...]
+//! ```
+//!
+//! 通过接口类型调用方法时，从对象头读取 type_id，查表得到 itable，再按槽位间接调用。
+
+use crate::codegen::context::IRGenerator;
+use crate::codegen::mangle;
+use crate::types::{MethodInfo, Type};
+
+impl IRGenerator {
+    /// 类型名是否是接口
+    pub fn is_interface(&self, name: &str) -> bool {
+        self.type_registry.as_ref().is_some_and(|r| r.interface_exists(name))
+    }
+
+    /// 查找接口方法的槽位下标和声明
+    pub fn find_interface_slot(&self, interface_name: &str, method_name: &str) -> Option<(usize, MethodInfo)> {
+        let interface = self.type_registry.as_ref()?.get_interface(interface_name)?;
+        interface.sorted_methods()
+            .into_iter()
+            .enumerate()
+            .find(|(_, m)| m.name == method_name)
+            .map(|(index, m)| (index, m.clone()))
+    }
+
+    /// 接口方法的函数指针类型（第一个参数为 this）
+    pub fn interface_method_fn_type(&self, method: &MethodInfo) -> String {
+        let mut params = vec!["i8*".to_string()];
+        params.extend(method.params.iter().map(|p| self.type_to_llvm(&p.param_type)));
+        format!("{} ({})*", self.type_to_llvm(&method.return_type), params.join(", "))
+    }
+
+    /// 沿继承链查找类中实现接口方法的函数名（参数类型完全一致的实例方法）
+    fn resolve_interface_impl(&self, class_name: &str, method: &MethodInfo) -> Option<String> {
+        let registry = self.type_registry.as_ref()?;
+        let mut current = registry.get_class(class_name);
+        while let Some(class_info) = current {
+            let found = class_info.methods.get(&method.name).and_then(|methods| {
+                methods.iter().find(|m| {
+                    !m.is_static && m.params.iter().map(|p| &p.param_type)
+                        .eq(method.params.iter().map(|p| &p.param_type))
+                })
+            });
+            if let Some(found) = found {
+                let param_types: Vec<Type> = found.params.iter().map(|p| p.param_type.clone()).collect();
+                let fn_name = mangle::mangle(&class_info.name, &found.name, &param_types);
+                // 抽象方法没有函数体（虚表槽位为空），不能放入 itable
+                let is_abstract = self.find_vtable_slot(&class_info.name, &fn_name)
+                    .is_some_and(|(_, slot)| slot.fn_name.is_none());
+                return (!is_abstract).then_some(fn_name);
+            }
+            current = class_info.parent.as_deref().and_then(|p| registry.get_class(p));
+        }
+        None
+    }
+
+    /// 接口按 type_id 索引的 itable 表: @InterfaceName.itables
+    fn itables_symbol(&self, interface_name: &str) -> String {
+        format!("@{}.itables", interface_name)
+    }
+
+    /// 生成所有 itable 以及各接口按 type_id 索引的表（按名称排序，保证输出稳定）
+    pub fn emit_itable_declarations(&self) -> String {
+        let Some(registry) = self.type_registry.as_ref() else {
+            return String::new();
+        };
+        let mut interface_names: Vec<&String> = registry.interfaces.keys().collect();
+        interface_names.sort();
+        let mut class_names: Vec<&String> = self.vtables.keys().collect();
+        class_names.sort();
+
+        let mut result = String::new();
+        for interface_name in interface_names {
+            let methods = registry.interfaces[interface_name].sorted_methods();
+            let mut table = vec!["i8** null".to_string(); self.type_id_counter];
+
+            for class_name in &class_names {
+                if !self.is_subtype(class_name, interface_name) {
+                    continue;
+                }
+                let Some(type_id) = self.get_type_id_value(class_name) else {
+                    continue;
+                };
+                let entries: Vec<String> = methods.iter()
+                    .map(|m| match self.resolve_interface_impl(class_name, m) {
+                        Some(fn_name) => format!("i8* bitcast ({} @{} to i8*)", self.interface_method_fn_type(m), fn_name),
+                        None => "i8* null".to_string(),
+                    })
+                    .collect();
+                let itable = format!("@{}.itable.{}", class_name, interface_name);
+                result.push_str(&format!("{} = internal constant [{} x i8*] [{}]\n",
+                    itable, entries.len(), entries.join(", ")));
+                table[type_id as usize] = format!("i8** getelementptr inbounds ([{} x i8*], [{} x i8*]* {}, i64 0, i64 0)",
+                    entries.len(), entries.len(), itable);
+            }
+
+            result.push_str(&format!("{} = internal constant [{} x i8**] [{}]\n",
+                self.itables_symbol(interface_name), table.len(),
+                table.iter().map(|e| e.as_str()).collect::<Vec<_>>().join(", ")));
+        }
+        result
+    }
+
+    /// 根据对象的 type_id 查找接口的 itable，取出槽位中的函数指针并转换为方法的函数类型
+    ///
+    /// # Arguments
+    /// * `interface_name` - 接口名
+    /// * `slot_index` - 槽位下标
+    /// * `fn_type` - 方法的函数指针类型
+    /// * `obj_ptr` - 对象指针 (i8*)
+    pub fn emit_interface_method_ptr(&mut self, interface_name: &str, slot_index: usize, fn_type: &str, obj_ptr: &str) -> String {
+        let table_len = self.type_id_counter;
+        let table = self.itables_symbol(interface_name);
+
+        let type_id_ptr = self.new_temp();
+        self.emit_line(&format!("  {} = bitcast i8* {} to i32*", type_id_ptr, obj_ptr));
+        let type_id = self.emit_load("i32", &type_id_ptr);
+        let itable_ptr = self.new_temp();
+        self.emit_line(&format!("  {} = getelementptr inbounds [{} x i8**], [{} x i8**]* {}, i64 0, i32 {}",
+            itable_ptr, table_len, table_len, table, type_id));
+        let itable = self.emit_load("i8**", &itable_ptr);
+        let slot_ptr = self.new_temp();
+        self.emit_line(&format!("  {} = getelementptr inbounds i8*, i8** {}, i64 {}", slot_ptr, itable, slot_index));
+        let raw_fn = self.emit_load("i8*", &slot_ptr);
+        let fn_ptr = self.new_temp();
+        self.emit_line(&format!("  {} = bitcast i8* {} to {}", fn_ptr, raw_fn, fn_type));
+        fn_ptr
+    }
+}
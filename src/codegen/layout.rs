@@ -0,0 +1,387 @@
+//! 类实例布局
+//!
+//! 为每个用户类计算实例布局并生成 LLVM 结构体类型：
+//!
+//! ```text
+//! %class.Point = type { i32, i8**, i64, i64 }   ; type_id, vtable, x, y
+//! ```
+//!
+//! 子类结构体以父类的全部成员开头，因此父类方法可以直接访问子类对象中继承的字段。
+//! 字段读写通过 `getelementptr %class.X, %class.X* obj, i32 0, i32 <index>` 完成，
+//! 字段偏移与 LLVM 对结构体的自然对齐规则一致，对象大小用于 calloc/alloca。
+//!
+//! 结构体定义按依赖顺序输出：父类先于子类，字段引用的类先于引用它的类。对象类型的字段
+//! 以 `i8*`（不透明指针）保存，互相引用的类（如 `Node.next`、`Tree`/`Forest`）因此不需要
+//! 递归的结构体类型，依赖成环时按类名顺序打断。字段引用了没有布局的类型（接口）时，
+//! 先输出 `type opaque` 前向声明。
+//!
+//! 从 .cavi 导入的类按接口文件中的字段列表以同样的规则计算布局（`import_class_layouts`），
+//! 依赖方分配的对象与定义方的构造函数和方法使用相同的字段偏移。
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use crate::ast::*;
+use crate::codegen::context::{ClassLayoutInfo, InstanceFieldInfo, IRGenerator, THIS_PTR_VAR};
+use crate::error::{cayResult, codegen_error};
+use crate::interface::{ClassLayoutSummary, FieldLayoutSummary};
+use crate::types::Type;
+
+/// 对象头：type_id (i32) 和虚表指针 (i8**)
+const HEADER_TYPES: [&str; 2] = ["i32", "i8**"];
+/// 虚表指针在对象结构体中的成员下标
+const VTABLE_FIELD_INDEX: usize = 1;
+
+impl IRGenerator {
+    /// 计算程序中所有类的实例布局（父类先于子类）
+    pub fn compute_class_layouts(&mut self, program: &Program) {
+        let classes: HashMap<&str, &ClassDecl> = program.classes.iter()
+            .map(|c| (c.name.as_str(), c))
+            .collect();
+        for class in &program.classes {
+            self.compute_class_layout_recursive(class, &classes, &mut Vec::new());
+        }
+    }
+
+    fn compute_class_layout_recursive<'a>(
+        &mut self,
+        class: &'a ClassDecl,
+        classes: &HashMap<&str, &'a ClassDecl>,
+        visiting: &mut Vec<&'a str>,
+    ) {
+        if self.class_layouts.contains_key(&class.name) || visiting.contains(&class.name.as_str()) {
+            // 已计算，或继承关系成环（由语义分析报告），不再递归
+            return;
+        }
+        visiting.push(&class.name);
+
+        // 父类在程序中定义时先计算其布局，否则使用从 .cavi 导入的布局
+        if let Some(parent) = class.parent.as_deref().and_then(|parent| classes.get(parent).copied()) {
+            self.compute_class_layout_recursive(parent, classes, visiting);
+        }
+        let parent_layout = class.parent.as_deref()
+            .and_then(|parent| self.class_layouts.get(parent).cloned());
+
+        let fields: Vec<(&str, &Type)> = class.members.iter()
+            .filter_map(|m| match m {
+                ClassMember::Field(f) if !f.modifiers.contains(&Modifier::Static) => Some((f.name.as_str(), &f.field_type)),
+                _ => None,
+            })
+            .collect();
+        self.compute_class_layout(&class.name, parent_layout.as_ref(), &fields);
+
+        visiting.pop();
+    }
+
+    /// 计算单个类的实例布局
+    ///
+    /// 对象内存布局: [type_id: i32][vtable: i8**][父类字段...][本类字段...]，字段按自然对齐放置。
+    /// 返回对象总大小（字节）
+    pub fn compute_class_layout(&mut self, class_name: &str, parent: Option<&ClassLayoutInfo>, fields: &[(&str, &Type)]) -> usize {
+        let header_size = self.object_header_size();
+        let (mut members, mut field_map, mut current_offset) = match parent {
+            Some(parent) => (parent.members.clone(), parent.fields.clone(), parent.fields.values()
+                .map(|f| f.offset + f.size)
+                .max()
+                .unwrap_or(header_size)),
+            None => (HEADER_TYPES.iter().map(|t| t.to_string()).collect(), HashMap::new(), header_size),
+        };
+
+        for &(name, field_type) in fields {
+            let llvm_type = self.type_to_llvm(field_type);
+            let size = field_type.size_of(&self.data_layout);
+
+            // 对齐处理
+            let align = field_type.align_of(&self.data_layout);
+            current_offset = current_offset.next_multiple_of(align);
+
+            let field_info = InstanceFieldInfo {
+                name: name.to_string(),
+                llvm_type: llvm_type.clone(),
+                field_type: field_type.clone(),
+                index: members.len(),
+                offset: current_offset,
+                size,
+            };
+
+            members.push(llvm_type);
+            field_map.insert(name.to_string(), field_info);
+            current_offset += size;
+        }
+
+        // 结构体大小对齐到成员的最大对齐（与 LLVM 结构体的分配大小一致）
+        let struct_align = members.iter()
+            .map(|m| self.get_type_align(m) as usize)
+            .max()
+            .unwrap_or(1);
+        let total_size = current_offset.next_multiple_of(struct_align);
+
+        let layout = ClassLayoutInfo {
+            class_name: class_name.to_string(),
+            struct_type: format!("%class.{}", class_name),
+            parent: parent.map(|p| p.class_name.clone()),
+            members,
+            total_size,
+            align: struct_align,
+            fields: field_map,
+        };
+
+        self.class_layouts.insert(class_name.to_string(), layout);
+        total_size
+    }
+
+    /// 导出类的实例布局，写入 .cavi 接口文件
+    ///
+    /// 只保存本类声明的字段（按结构体成员顺序），继承的字段由父类的布局给出；
+    /// 被子类同名字段遮蔽的父类字段仍然占据父类布局中的位置。
+    pub fn export_class_layout(&self, class_name: &str) -> Option<ClassLayoutSummary> {
+        let layout = self.class_layouts.get(class_name)?;
+        let inherited = layout.parent.as_deref()
+            .and_then(|parent| self.class_layouts.get(parent))
+            .map_or(HEADER_TYPES.len(), |parent| parent.members.len());
+        let mut own: Vec<&InstanceFieldInfo> = layout.fields.values()
+            .filter(|field| field.index >= inherited)
+            .collect();
+        own.sort_by_key(|field| field.index);
+        Some(ClassLayoutSummary {
+            class_name: class_name.to_string(),
+            parent: layout.parent.clone(),
+            vtable_index: VTABLE_FIELD_INDEX,
+            fields: own.into_iter()
+                .map(|field| FieldLayoutSummary { name: field.name.clone(), field_type: field.field_type.clone() })
+                .collect(),
+        })
+    }
+
+    /// 按 .cavi 接口文件中的布局计算导入的类的实例布局（父类先于子类）
+    ///
+    /// 必须在 `generate` 之前调用；程序中定义的子类可以继承导入的类。
+    pub fn import_class_layouts(&mut self, layouts: &[ClassLayoutSummary]) -> cayResult<()> {
+        let by_name: HashMap<&str, &ClassLayoutSummary> = layouts.iter()
+            .map(|layout| (layout.class_name.as_str(), layout))
+            .collect();
+        for layout in layouts {
+            self.import_class_layout(layout, &by_name, &mut Vec::new())?;
+        }
+        Ok(())
+    }
+
+    fn import_class_layout<'a>(
+        &mut self,
+        layout: &'a ClassLayoutSummary,
+        layouts: &HashMap<&str, &'a ClassLayoutSummary>,
+        visiting: &mut Vec<&'a str>,
+    ) -> cayResult<()> {
+        if self.class_layouts.contains_key(&layout.class_name) || visiting.contains(&layout.class_name.as_str()) {
+            return Ok(());
+        }
+        if layout.vtable_index != VTABLE_FIELD_INDEX {
+            return Err(codegen_error(format!(
+                "The interface file for class '{}' uses an incompatible object header (vtable at member {}, expected {})",
+                layout.class_name, layout.vtable_index, VTABLE_FIELD_INDEX)));
+        }
+        visiting.push(&layout.class_name);
+
+        let parent_layout = match layout.parent.as_deref() {
+            Some(parent) => {
+                if let Some(parent_summary) = layouts.get(parent).copied() {
+                    self.import_class_layout(parent_summary, layouts, visiting)?;
+                }
+                let parent_layout = self.class_layouts.get(parent).cloned().ok_or_else(|| codegen_error(format!(
+                    "Imported class '{}' extends '{}', but no imported interface file provides the layout of '{}'",
+                    layout.class_name, parent, parent)))?;
+                Some(parent_layout)
+            }
+            None => None,
+        };
+        let fields: Vec<(&str, &Type)> = layout.fields.iter()
+            .map(|field| (field.name.as_str(), &field.field_type))
+            .collect();
+        self.compute_class_layout(&layout.class_name, parent_layout.as_ref(), &fields);
+
+        visiting.pop();
+        Ok(())
+    }
+
+    /// 对象头大小：type_id 之后按指针对齐放置虚表指针
+    fn object_header_size(&self) -> usize {
+        HEADER_TYPES.iter().fold(0, |offset, ty| {
+            offset.next_multiple_of(self.get_type_align(ty) as usize) + self.get_type_size(ty) as usize
+        })
+    }
+
+    /// 获取类布局信息
+    pub fn get_class_layout(&self, class_name: &str) -> Option<&ClassLayoutInfo> {
+        self.class_layouts.get(class_name)
+    }
+
+    /// 获取实例字段信息
+    pub fn get_instance_field(&self, class_name: &str, field_name: &str) -> Option<&InstanceFieldInfo> {
+        self.class_layouts.get(class_name)?.fields.get(field_name)
+    }
+
+    /// 生成所有类的结构体类型定义：先是没有布局的被引用类型的前向声明，再按依赖顺序输出各个类
+    pub fn emit_class_type_declarations(&self) -> String {
+        let mut result = String::new();
+        for name in self.opaque_class_references() {
+            result.push_str(&format!("%class.{} = type opaque\n", name));
+        }
+        for layout in self.class_emission_order() {
+            result.push_str(&format!("{} = type {{ {} }}\n", layout.struct_type, layout.members.join(", ")));
+        }
+        result
+    }
+
+    /// 结构体定义的输出顺序（拓扑序）：父类先于子类，字段引用的类先于引用它的类
+    ///
+    /// 从按类名排序的类依次深度优先访问依赖，结果与 HashMap 的遍历顺序无关；
+    /// 字段引用成环时，先访问到的类先输出。
+    pub fn class_emission_order(&self) -> Vec<&ClassLayoutInfo> {
+        let mut names: Vec<&str> = self.class_layouts.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        let mut order = Vec::with_capacity(names.len());
+        let mut visited = HashSet::new();
+        for name in names {
+            self.visit_class_dependencies(name, &mut visited, &mut order);
+        }
+        order
+    }
+
+    fn visit_class_dependencies<'a>(&'a self, name: &'a str, visited: &mut HashSet<&'a str>, order: &mut Vec<&'a ClassLayoutInfo>) {
+        let Some(layout) = self.class_layouts.get(name) else {
+            return;
+        };
+        // 先标记再访问依赖：成环时不会重复进入
+        if !visited.insert(name) {
+            return;
+        }
+        for dependency in layout.parent.as_deref().into_iter().chain(field_classes(layout)) {
+            self.visit_class_dependencies(dependency, visited, order);
+        }
+        order.push(layout);
+    }
+
+    /// 字段引用、但程序中没有布局的类型（按名称排序）
+    fn opaque_class_references(&self) -> BTreeSet<&str> {
+        self.class_layouts.values()
+            .flat_map(field_classes)
+            .filter(|name| !self.class_layouts.contains_key(*name))
+            .collect()
+    }
+
+    /// 计算实例字段的地址，返回指向字段类型的指针
+    ///
+    /// # Arguments
+    /// * `class_name` - 对象的静态类型
+    /// * `field_info` - 字段信息
+    /// * `obj_ptr` - 对象指针 (i8*)
+    pub fn emit_field_ptr(&mut self, class_name: &str, field_info: &InstanceFieldInfo, obj_ptr: &str) -> String {
+        let struct_type = match self.get_class_layout(class_name) {
+            Some(layout) => layout.struct_type.clone(),
+            None => format!("%class.{}", class_name),
+        };
+
+        let typed_obj = self.new_temp();
+        self.emit_line(&format!("  {} = bitcast i8* {} to {}*", typed_obj, obj_ptr, struct_type));
+        let field_ptr = self.new_temp();
+        self.emit_line(&format!("  {} = getelementptr inbounds {}, {}* {}, i32 0, i32 {}",
+            field_ptr, struct_type, struct_type, typed_obj, field_info.index));
+        field_ptr
+    }
+
+    /// 计算对象头中虚表指针的地址，返回 i8***
+    pub fn emit_vtable_field_ptr(&mut self, class_name: &str, obj_ptr: &str) -> String {
+        let struct_type = match self.get_class_layout(class_name) {
+            Some(layout) => layout.struct_type.clone(),
+            None => format!("%class.{}", class_name),
+        };
+
+        let typed_obj = self.new_temp();
+        self.emit_line(&format!("  {} = bitcast i8* {} to {}*", typed_obj, obj_ptr, struct_type));
+        let field_ptr = self.new_temp();
+        self.emit_line(&format!("  {} = getelementptr inbounds {}, {}* {}, i32 0, i32 {}",
+            field_ptr, struct_type, struct_type, typed_obj, VTABLE_FIELD_INDEX));
+        field_ptr
+    }
+
+    /// 类的直接父类
+    pub fn parent_class(&self, class_name: &str) -> Option<String> {
+        self.type_registry.as_ref()?.get_class(class_name)?.parent.clone()
+    }
+
+    /// 确定对象表达式的静态类名，用于字段访问
+    ///
+    /// 支持 `this`、`super`、局部变量、隐式 this 字段、字段链 (`a.b.c`)、`new` 和类型转换。
+    pub fn object_class_of(&self, expr: &Expr) -> Option<String> {
+        match expr {
+            Expr::Identifier(name) if name == "this" => {
+                (!self.current_class.is_empty()).then(|| self.current_class.clone())
+            }
+            Expr::Identifier(name) if name == "super" => self.parent_class(&self.current_class),
+            Expr::Identifier(name) => {
+                if let Some(class_name) = self.var_class_map.get(name) {
+                    return Some(class_name.clone());
+                }
+                if self.scope_manager.get_llvm_name(name).is_some() {
+                    return None;
+                }
+                match self.get_instance_field(&self.current_class, name).map(|f| &f.field_type) {
+                    Some(Type::Object(class_name)) => Some(class_name.clone()),
+                    _ => None,
+                }
+            }
+            Expr::MemberAccess(member) if !member.null_safe => {
+                let owner = self.object_class_of(&member.object)?;
+                match self.get_instance_field(&owner, &member.member).map(|f| &f.field_type) {
+                    Some(Type::Object(class_name)) => Some(class_name.clone()),
+                    _ => None,
+                }
+            }
+            Expr::New(new_expr) => Some(new_expr.class_name.clone()),
+            Expr::Cast(cast) => match &cast.target_type {
+                Type::Object(class_name) => Some(class_name.clone()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// 实例字段访问 `obj.field` 的对象类名：优先使用语义分析的记录，否则按对象表达式的形式推断
+    pub fn field_access_class(&self, member: &MemberAccessExpr) -> Option<String> {
+        self.field_access_classes.get(&member.id).cloned()
+            .or_else(|| self.object_class_of(&member.object))
+    }
+
+    /// 从当前方法的 this_ptr 加载 this 指针
+    pub fn load_this_ptr(&mut self) -> String {
+        let this_llvm_name = self.scope_manager.get_llvm_name(THIS_PTR_VAR)
+            .unwrap_or_else(|| format!("{}_s1", THIS_PTR_VAR));
+        self.emit_load("i8*", &format!("%{}", this_llvm_name))
+    }
+
+    /// 生成对象表达式的指针值 (i8*)
+    ///
+    /// `this` 和 `super` 直接从当前方法的 this_ptr 加载，其他表达式正常求值。
+    pub fn generate_object_ptr(&mut self, expr: &Expr) -> crate::error::cayResult<String> {
+        if let Expr::Identifier(name) = expr
+            && (name == "this" || name == "super")
+        {
+            return Ok(self.load_this_ptr());
+        }
+        Ok(self.generate_expression(expr)?.repr)
+    }
+}
+
+/// 布局中本类及继承的对象类型字段引用的类（按名称排序，包括数组元素的类）
+fn field_classes(layout: &ClassLayoutInfo) -> BTreeSet<&str> {
+    layout.fields.values()
+        .filter_map(|field| {
+            let mut ty = &field.field_type;
+            while let Type::Array(elem) = ty {
+                ty = elem;
+            }
+            match ty {
+                Type::Object(name) => Some(name.as_str()),
+                _ => None,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,102 @@
+//! 执行限制
+//!
+//! 运行不受信任或可能有缺陷的代码片段（REPL、在线演示、测试评测等）时，
+//! 在生成的程序中插入运行时检查，避免死循环或无限分配拖垮宿主进程：
+//! - 步数上限：每次进入函数、每轮循环计一步；
+//! - 堆上限：所有 `calloc` 调用改为经过计数的 `__cay_limited_calloc`，按累计分配字节数计算。
+//!
+//! 递归深度上限由 `--stack-guard` 提供（见 `emit_stack_guard_enter`）。
+//! 超出任一上限时打印 "Execution limit exceeded" 诊断并以退出码 1 结束。未启用时不生成任何代码。
+
+use crate::codegen::context::IRGenerator;
+
+/// 受限分配函数名
+const LIMITED_CALLOC: &str = "@__cay_limited_calloc";
+
+impl IRGenerator {
+    /// 步数上限（未启用时为 `None`）
+    pub fn max_steps(&self) -> Option<u64> {
+        self.platform_config.as_ref().and_then(|c| c.max_steps)
+    }
+
+    /// 累计堆分配上限，单位字节（未启用时为 `None`）
+    pub fn max_heap_bytes(&self) -> Option<u64> {
+        self.platform_config.as_ref().and_then(|c| c.max_heap_bytes)
+    }
+
+    /// 发射执行限制使用的计数器
+    pub(crate) fn emit_execution_limit_globals(&mut self) {
+        if self.max_steps().is_some() {
+            self.emit_raw("@.cay_steps = internal global i64 0, align 8");
+        }
+        if let Some(max_heap) = self.max_heap_bytes() {
+            self.emit_raw("@.cay_heap_used = internal global i64 0, align 8");
+            // 提前登记诊断字符串，使其随其他字符串常量一起输出
+            self.get_or_create_string_constant(&Self::heap_limit_message(max_heap));
+        }
+    }
+
+    /// 步数加一，超过上限时报告并退出；`site` 描述计数位置（方法名或循环所在行）
+    pub fn emit_step_tick(&mut self, site: &str) {
+        let Some(max_steps) = self.max_steps() else {
+            return;
+        };
+        let steps = self.new_temp();
+        let next_steps = self.new_temp();
+        let exceeded = self.new_temp();
+        let exceeded_label = self.new_label("limit.steps");
+        let ok_label = self.new_label("steps.ok");
+        self.emit_load_into(&steps, "i64", "@.cay_steps");
+        self.emit_line(&format!("  {} = add i64 {}, 1", next_steps, steps));
+        self.emit_store("i64", &next_steps, "@.cay_steps");
+        self.emit_line(&format!("  {} = icmp ugt i64 {}, {}", exceeded, next_steps, max_steps));
+        self.emit_cond_br(&exceeded, &exceeded_label, &ok_label);
+
+        self.emit_label(&exceeded_label);
+        let message = format!("Execution limit exceeded: more than {} steps (in {})\n", max_steps, site);
+        let message_ptr = self.get_string_constant_ptr(&message);
+        self.emit_runtime_error(&message_ptr, &[]);
+
+        self.emit_label(&ok_label);
+    }
+
+    fn heap_limit_message(max_heap: u64) -> String {
+        format!("Execution limit exceeded: heap allocation exceeds {} bytes\n", max_heap)
+    }
+
+    /// 把模块中所有 `calloc` 调用改为受限分配，并追加受限分配函数的定义
+    ///
+    /// 运行时函数和用户代码都通过 `call i8* @calloc(` 分配内存，因此在整个模块生成完后统一改写，
+    /// 再追加受限分配函数本身（其中对 `calloc` 的调用不参与改写）。
+    /// 使用 `--external-runtime` 时，独立运行时模块中的分配不计入。
+    pub(crate) fn apply_heap_limit(&mut self, module: String) -> String {
+        let Some(max_heap) = self.max_heap_bytes() else {
+            return module;
+        };
+        let message = Self::heap_limit_message(max_heap);
+        let message_global = self.get_or_create_string_constant(&message);
+        let len = message.len() + 1;
+
+        let mut module = module.replace("call i8* @calloc(", &format!("call i8* {}(", LIMITED_CALLOC));
+        module.push_str(&format!("\
+define internal i8* {limited}(i64 %count, i64 %size) {{
+entry:
+  %bytes = mul i64 %count, %size
+  %used = load i64, i64* @.cay_heap_used, align 8
+  %next = add i64 %used, %bytes
+  %exceeded = icmp ugt i64 %next, {max_heap}
+  br i1 %exceeded, label %limit.heap, label %heap.ok
+limit.heap:
+  %message = getelementptr [{len} x i8], [{len} x i8]* {message_global}, i64 0, i64 0
+  call void @__cay_runtime_error(i8* %message, i64 0, i64 0)
+  unreachable
+heap.ok:
+  store i64 %next, i64* @.cay_heap_used, align 8
+  %ptr = call i8* @calloc(i64 %count, i64 %size)
+  ret i8* %ptr
+}}
+
+", limited = LIMITED_CALLOC, max_heap = max_heap, len = len, message_global = message_global));
+        module
+    }
+}
@@ -0,0 +1,243 @@
+//! 函数符号名的修饰与还原
+//!
+//! 方法、构造函数、析构函数和静态初始化块在 IR 中的函数名都由本模块生成，
+//! 定义、调用、虚表、接口表和分别编译的声明使用同一套规则，`demangle` 可以把符号名还原为类、成员和参数类型。
+//!
+//! 符号名的格式（函数名中只有类名与成员名之间的一个 `.`）：
+//!
+//! ```text
+//! symbol  := class "." member                      无参数的方法
+//!          | class ".__" member ("$" type)+        有参数的方法（支持重载）
+//!          | class ".__ctor" | class ".__dtor" | class ".__static_init"
+//! type    := "y" | "h" | "i" | "l" | "f" | "d"     byte short int long float double
+//!          | "b" | "s" | "c" | "v"                 boolean String char void
+//!          | "a" type                              数组
+//!          | "o" <名字的字节数> <类名>               对象，如 Point → o5Point
+//!          | "fn" <参数个数> type* type             函数类型：参数类型后跟返回类型，如 (int) -> int → fn1ii
+//! ```
+//!
+//! 类名和成员名可能含有 `$`（泛型实例 `Box$int`、泛型方法实例 `identity$int`），
+//! 还原时成员名取第一个使其后部分能完整解析为参数类型的 `$` 之前的部分。
+
+use std::fmt;
+
+use crate::types::{FunctionType, Type};
+
+/// 参数类型之间的分隔符
+pub const SEPARATOR: char = '$';
+/// 有参数的成员名前缀
+const OVERLOAD_PREFIX: &str = "__";
+/// 构造函数的成员名
+pub const CONSTRUCTOR: &str = "ctor";
+/// 析构函数的成员名
+pub const DESTRUCTOR: &str = "dtor";
+/// 静态初始化块的成员名
+pub const STATIC_INIT: &str = "static_init";
+
+/// 符号对应的成员种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Method,
+    Constructor,
+    Destructor,
+    StaticInit,
+}
+
+/// 还原后的符号
+#[derive(Debug, Clone, PartialEq)]
+pub struct Demangled {
+    pub class: String,
+    /// 成员名；构造函数、析构函数和静态初始化块为 `ctor`、`dtor`、`static_init`
+    pub member: String,
+    pub kind: SymbolKind,
+    pub params: Vec<Type>,
+}
+
+/// 以源码中的写法输出，如 `Point.move(int, int)`、`Point.<init>(int)`
+impl fmt::Display for Demangled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            SymbolKind::Method => write!(f, "{}.{}(", self.class, self.member)?,
+            SymbolKind::Constructor => write!(f, "{}.<init>(", self.class)?,
+            SymbolKind::Destructor => write!(f, "{}.<dtor>(", self.class)?,
+            SymbolKind::StaticInit => write!(f, "{}.<clinit>(", self.class)?,
+        }
+        for (i, param) in self.params.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", param)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// 方法的符号名：无参数时为 `Class.method`，有参数时为 `Class.__method$<type>...`
+pub fn mangle(class: &str, method: &str, params: &[Type]) -> String {
+    if params.is_empty() {
+        format!("{}.{}", class, method)
+    } else {
+        mangle_overloaded(class, method, params)
+    }
+}
+
+/// 构造函数的符号名：`Class.__ctor` 或 `Class.__ctor$<type>...`
+pub fn mangle_constructor(class: &str, params: &[Type]) -> String {
+    mangle_overloaded(class, CONSTRUCTOR, params)
+}
+
+/// 析构函数的符号名：`Class.__dtor`
+pub fn destructor_symbol(class: &str) -> String {
+    mangle_overloaded(class, DESTRUCTOR, &[])
+}
+
+/// 静态初始化块的符号名：`Class.__static_init`
+pub fn static_init_symbol(class: &str) -> String {
+    mangle_overloaded(class, STATIC_INIT, &[])
+}
+
+fn mangle_overloaded(class: &str, member: &str, params: &[Type]) -> String {
+    let mut symbol = format!("{}.{}{}", class, OVERLOAD_PREFIX, member);
+    for param in params {
+        symbol.push(SEPARATOR);
+        write_type_code(&mut symbol, param);
+    }
+    symbol
+}
+
+/// 类型在符号名中的编码
+pub fn type_code(ty: &Type) -> String {
+    let mut code = String::new();
+    write_type_code(&mut code, ty);
+    code
+}
+
+fn write_type_code(out: &mut String, ty: &Type) {
+    match ty {
+        Type::Void | Type::Never => out.push('v'),
+        Type::Int8 => out.push('y'),
+        Type::Int16 => out.push('h'),
+        Type::Int32 => out.push('i'),
+        Type::Int64 => out.push('l'),
+        Type::Float32 => out.push('f'),
+        Type::Float64 => out.push('d'),
+        Type::Bool => out.push('b'),
+        Type::String => out.push('s'),
+        Type::Char => out.push('c'),
+        Type::Object(name) => {
+            out.push('o');
+            out.push_str(&name.len().to_string());
+            out.push_str(name);
+        }
+        Type::Array(inner) => {
+            out.push('a');
+            write_type_code(out, inner);
+        }
+        Type::Function(func) => {
+            out.push_str("fn");
+            out.push_str(&func.params.len().to_string());
+            for param in &func.params {
+                write_type_code(out, param);
+            }
+            write_type_code(out, &func.return_type);
+        }
+        Type::Generic(..) => panic!("Type::Generic should have been monomorphized before code generation"),
+        Type::Auto => panic!("Type::Auto should have been resolved before code generation"),
+    }
+}
+
+/// 把 `mangle` 系列函数生成的符号名还原；不是这种格式时返回 `None`
+pub fn demangle(symbol: &str) -> Option<Demangled> {
+    let symbol = symbol.strip_prefix('@').unwrap_or(symbol);
+    let (class, member) = symbol.split_once('.')?;
+    if class.is_empty() || member.is_empty() || member.contains('.') {
+        return None;
+    }
+    let demangled = |member: &str, kind, params| Demangled { class: class.to_string(), member: member.to_string(), kind, params };
+    let Some(rest) = member.strip_prefix(OVERLOAD_PREFIX) else {
+        return Some(demangled(member, SymbolKind::Method, Vec::new()));
+    };
+    match rest {
+        DESTRUCTOR => return Some(demangled(rest, SymbolKind::Destructor, Vec::new())),
+        STATIC_INIT => return Some(demangled(rest, SymbolKind::StaticInit, Vec::new())),
+        CONSTRUCTOR => return Some(demangled(rest, SymbolKind::Constructor, Vec::new())),
+        _ => {}
+    }
+    // 成员名本身可能含有 `$`：取第一个之后能完整解析为参数类型的分隔符
+    for (index, _) in rest.match_indices(SEPARATOR) {
+        let (name, codes) = (&rest[..index], &rest[index + 1..]);
+        if name.is_empty() {
+            continue;
+        }
+        if let Some(params) = parse_params(codes) {
+            let kind = if name == CONSTRUCTOR { SymbolKind::Constructor } else { SymbolKind::Method };
+            return Some(demangled(name, kind, params));
+        }
+    }
+    // 以 `__` 开头但没有参数签名的普通方法名
+    Some(demangled(member, SymbolKind::Method, Vec::new()))
+}
+
+/// 解析 `$` 分隔的参数类型编码，必须恰好用完整个字符串
+fn parse_params(codes: &str) -> Option<Vec<Type>> {
+    let mut params = Vec::new();
+    let mut rest = codes;
+    loop {
+        let (ty, tail) = parse_type(rest)?;
+        params.push(ty);
+        if tail.is_empty() {
+            return Some(params);
+        }
+        rest = tail.strip_prefix(SEPARATOR)?;
+    }
+}
+
+/// 从开头解析一个类型编码，返回类型和剩余部分
+fn parse_type(code: &str) -> Option<(Type, &str)> {
+    if let Some(rest) = code.strip_prefix("fn") {
+        let (count, mut rest) = parse_count(rest)?;
+        let mut params = Vec::with_capacity(count);
+        for _ in 0..count {
+            let (param, tail) = parse_type(rest)?;
+            params.push(param);
+            rest = tail;
+        }
+        let (return_type, rest) = parse_type(rest)?;
+        let func = FunctionType { params, return_type: Box::new(return_type), is_static: false };
+        return Some((Type::Function(Box::new(func)), rest));
+    }
+    let mut chars = code.chars();
+    let ty = match chars.next()? {
+        'v' => Type::Void,
+        'y' => Type::Int8,
+        'h' => Type::Int16,
+        'i' => Type::Int32,
+        'l' => Type::Int64,
+        'f' => Type::Float32,
+        'd' => Type::Float64,
+        'b' => Type::Bool,
+        's' => Type::String,
+        'c' => Type::Char,
+        'a' => {
+            let (inner, rest) = parse_type(chars.as_str())?;
+            return Some((Type::Array(Box::new(inner)), rest));
+        }
+        'o' => {
+            let (len, rest) = parse_count(chars.as_str())?;
+            let name = rest.get(..len)?;
+            if name.is_empty() {
+                return None;
+            }
+            return Some((Type::Object(name.to_string()), &rest[len..]));
+        }
+        _ => return None,
+    };
+    Some((ty, chars.as_str()))
+}
+
+/// 解析开头的十进制数
+fn parse_count(text: &str) -> Option<(usize, &str)> {
+    let digits = text.bytes().take_while(|b| b.is_ascii_digit()).count();
+    let count = text[..digits].parse().ok()?;
+    Some((count, &text[digits..]))
+}
@@ -4,12 +4,26 @@
 //! 已重构为多个子模块以提高可维护性。
 
 pub mod context;
+pub mod value;
 mod types;
 mod expressions;
 mod statements;
 pub mod runtime;
 mod generator;
+mod emit;
+pub mod ir;
+pub mod mangle;
+pub mod verify;
+mod layout;
+mod vtable;
+mod itable;
+mod prelude;
+mod native;
+mod reflect;
+pub mod escape;
 mod platform;
+mod limits;
+mod parallel;
 pub mod obfuscator;
 
 // 公开 IRGenerator 作为代码生成器的入口
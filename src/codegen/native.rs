@@ -0,0 +1,129 @@
+//! native 方法绑定的 C 函数
+//!
+//! 普通类中的 `static native` 方法没有函数体，调用直接指向同名的 C 函数（或 `@Symbol("name")` 指定的函数），
+//! 由链接时的 C 库或其他目标文件提供。本模块为这些函数生成声明：
+//!
+//! ```text
+//! // @Symbol("labs") public static native long abs(long x);
+//! declare i64 @labs(i64)
+//! // public static native boolean isatty(int fd);
+//! declare zeroext i1 @isatty(i32)
+//! ```
+//!
+//! 参数和返回值按 C 的类型传递：整数和浮点数对应同宽度的 C 类型，`boolean` 为 `bool`，`char` 为 32 位整数，
+//! `String` 为 `char*`，数组为指向首元素的指针，对象为不透明指针。
+//! 模块中已经声明的 C 函数（运行时用到的 `strlen`、`malloc` 等）不再重复声明，签名不一致时报告错误。
+
+use std::collections::BTreeMap;
+
+use crate::codegen::context::IRGenerator;
+use crate::error::{cayResult, codegen_error};
+use crate::types::Type;
+
+/// C 函数的声明：返回类型和参数类型（不含参数属性）
+#[derive(PartialEq)]
+struct CSignature {
+    ret: String,
+    params: Vec<String>,
+}
+
+impl CSignature {
+    fn describe(&self) -> String {
+        format!("{} ({})", self.ret, self.params.join(", "))
+    }
+}
+
+/// 按 C 调用约定需要扩展的窄整数类型的参数属性
+fn extension_attribute(ty: &Type) -> Option<&'static str> {
+    match ty {
+        Type::Bool => Some("zeroext"),
+        Type::Int8 | Type::Int16 => Some("signext"),
+        _ => None,
+    }
+}
+
+/// 带参数属性的类型，如 `zeroext i1`
+fn with_attribute(ty: &Type, llvm_type: String) -> String {
+    match extension_attribute(ty) {
+        Some(attribute) => format!("{} {}", attribute, llvm_type),
+        None => llvm_type,
+    }
+}
+
+/// 模块中 `@symbol` 已有的声明或定义的签名
+fn existing_signature(module: &str, symbol: &str) -> Option<CSignature> {
+    let marker = format!(" @{}(", symbol);
+    module.lines()
+        .filter(|line| line.starts_with("declare ") || line.starts_with("define "))
+        .find_map(|line| {
+            let at = line.find(&marker)?;
+            let ret = line[..at].split_whitespace().last()?.to_string();
+            let rest = &line[at + marker.len()..];
+            let params = rest[..rest.find(')')?].split(',')
+                .map(str::trim)
+                .filter(|param| !param.is_empty())
+                .map(|param| param.split_whitespace().next().unwrap_or("").to_string())
+                .collect();
+            Some(CSignature { ret, params })
+        })
+}
+
+impl IRGenerator {
+    /// 为绑定到 C 函数的 native 方法生成声明（`module` 为已生成的模块，用于跳过已经声明的函数）
+    pub(super) fn emit_native_declarations(&self, module: &str) -> cayResult<String> {
+        let Some(registry) = &self.type_registry else {
+            return Ok(String::new());
+        };
+        let mut class_names: Vec<&String> = registry.classes.keys().collect();
+        class_names.sort();
+
+        // C 函数名 → (签名, 带属性的声明, 方法)
+        let mut bindings: BTreeMap<&str, (CSignature, String, String)> = BTreeMap::new();
+        for class_name in class_names {
+            let class_info = &registry.classes[class_name];
+            let mut method_names: Vec<&String> = class_info.methods.keys().collect();
+            method_names.sort();
+            for method in method_names.into_iter().flat_map(|name| &class_info.methods[name]) {
+                let Some(symbol) = method.link_name.as_deref() else {
+                    continue;
+                };
+                let owner = format!("{}.{}", class_name, method.name);
+                let signature = CSignature {
+                    ret: self.type_to_llvm(&method.return_type),
+                    params: method.params.iter().map(|p| self.type_to_llvm(&p.param_type)).collect(),
+                };
+                if let Some((bound, _, bound_owner)) = bindings.get(symbol) {
+                    if *bound != signature {
+                        return Err(codegen_error(format!(
+                            "Native methods '{}' and '{}' bind to C function '{}' with different signatures: {} and {}",
+                            bound_owner, owner, symbol, bound.describe(), signature.describe())));
+                    }
+                    continue;
+                }
+                let params: Vec<String> = method.params.iter()
+                    .map(|p| with_attribute(&p.param_type, self.type_to_llvm(&p.param_type)))
+                    .collect();
+                let ret = with_attribute(&method.return_type, signature.ret.clone());
+                let declaration = format!("declare {} @{}({})", ret, symbol, params.join(", "));
+                bindings.insert(symbol, (signature, declaration, owner));
+            }
+        }
+
+        let mut declarations = String::new();
+        for (symbol, (signature, declaration, owner)) in bindings {
+            match existing_signature(module, symbol) {
+                Some(existing) if existing == signature => {}
+                Some(existing) => {
+                    return Err(codegen_error(format!(
+                        "Native method '{}' binds to C function '{}' as {}, but the module already declares it as {}",
+                        owner, symbol, signature.describe(), existing.describe())));
+                }
+                None => {
+                    declarations.push_str(&declaration);
+                    declarations.push('\n');
+                }
+            }
+        }
+        Ok(declarations)
+    }
+}
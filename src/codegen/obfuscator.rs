@@ -59,7 +59,7 @@ impl IRObfuscator {
                     let actual_pos = last_pos + pos;
                     let remaining = &processed_line[actual_pos+1..];
                     
-                    if let Some(end_pos) = remaining.find(|c: char| !c.is_alphanumeric() && c != '_' && c != '.') {
+                    if let Some(end_pos) = remaining.find(|c: char| !c.is_alphanumeric() && c != '_' && c != '.' && c != '$') {
                         let symbol = &remaining[..end_pos];
                         if !symbol.starts_with("llvm.") && !symbol.starts_with("__obf_") && !symbol.is_empty() {
                             let obfuscated = self.obfuscate_symbol(symbol);
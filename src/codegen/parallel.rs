@@ -0,0 +1,53 @@
+//! 并行代码生成
+//!
+//! 类的实例布局、虚表、类型标识和静态字段都在生成方法体之前计算好，
+//! 生成一个类只会追加函数代码、lambda 函数和字符串常量，因此不同的类可以独立生成。
+//! `--jobs N` 大于 1 时，每个类在生成器的一个副本上生成，最后按类在程序中的顺序拼接，
+//! 输出与线程调度无关。
+//!
+//! 副本新建的字符串常量使用各自的命名空间（`@.str.<类序号>.<n>`），合并时不会重名；
+//! 不同副本中内容相同的常量各自保留一份声明。
+
+use crate::ast::ClassDecl;
+use crate::error::cayResult;
+use super::context::IRGenerator;
+
+impl IRGenerator {
+    /// 在 `self.jobs` 个线程中生成所有类，并按顺序合并到当前生成器
+    pub(super) fn generate_classes_parallel(&mut self, classes: &[ClassDecl]) -> cayResult<()> {
+        // 副本从空的输出缓冲区开始，不复制已生成的模块头和运行时
+        let output = std::mem::take(&mut self.output);
+        let code = std::mem::take(&mut self.code);
+        let lambda_functions = std::mem::take(&mut self.lambda_functions);
+        let base = self.clone();
+        self.output = output;
+        self.code = code;
+        self.lambda_functions = lambda_functions;
+
+        let results = crate::parallel::map_ordered(self.jobs, classes, |index, class| {
+            let mut worker = base.clone();
+            worker.string_namespace = Some(index);
+            worker.global_counter = 0;
+            worker.generate_class(class).map(|()| worker)
+        });
+
+        // 与顺序生成一致：按类的顺序合并，遇到第一个失败的类时返回它的错误
+        for result in results {
+            let worker = result?;
+            self.output.push_str(&worker.output);
+            self.code.push_str(&worker.code);
+            self.lambda_functions.extend(worker.lambda_functions);
+            self.uses_reflection |= worker.uses_reflection;
+            for (s, name) in worker.global_strings {
+                match self.global_strings.get(&s) {
+                    None => {
+                        self.global_strings.insert(s, name);
+                    }
+                    Some(existing) if *existing != name => self.duplicate_strings.push((s, name)),
+                    Some(_) => {}
+                }
+            }
+        }
+        Ok(())
+    }
+}
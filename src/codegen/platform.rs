@@ -7,6 +7,18 @@ pub struct PlatformConfig {
     pub defines: Vec<String>,
     pub undefines: Vec<String>,
     pub obfuscate: bool,
+    /// 运行时函数由外部运行时模块提供，当前模块只生成声明
+    pub external_runtime: bool,
+    /// 递归深度保护的最大调用深度，`None` 表示不插入保护代码
+    pub stack_guard_depth: Option<u32>,
+    /// 步数上限，`None` 表示不计数
+    pub max_steps: Option<u64>,
+    /// 累计堆分配上限（字节），`None` 表示不限制
+    pub max_heap_bytes: Option<u64>,
+    /// 库模式：不生成 C 入口 `@main`，静态数组改在全局构造函数中初始化
+    pub no_main: bool,
+    /// 每个函数生成后检查值的定义、使用和类型（`--verify-ir`）
+    pub verify_ir: bool,
 }
 
 impl PlatformConfig {
@@ -18,6 +30,12 @@ impl PlatformConfig {
             defines: Vec::new(),
             undefines: Vec::new(),
             obfuscate: false,
+            external_runtime: false,
+            stack_guard_depth: None,
+            max_steps: None,
+            max_heap_bytes: None,
+            no_main: false,
+            verify_ir: false,
         }
     }
     
@@ -0,0 +1,163 @@
+//! 内置类的 native 方法
+//!
+//! `Map<K, V>`（`src/prelude/map.cay`）和 `StringBuilder`（`src/prelude/string_builder.cay`）
+//! 的主要方法声明为 native，这里为它们生成函数体：取出对象 `handle` 字段中的运行时数据
+//! （哈希表或字符缓冲区，第一次使用时创建），再调用 `__cay_map_*`/`__cay_sb_*` 函数
+//! （见 `runtime/map.rs`、`runtime/string_builder.rs`）。其余方法用 Cavvy 编写，和普通方法一样生成。
+
+use crate::ast::*;
+use crate::codegen::context::IRGenerator;
+use crate::codegen::value::LLVMValue;
+use crate::error::{cayResult, codegen_error};
+use crate::prelude::{NativeClass, HANDLE_FIELD};
+use crate::types::Type;
+
+impl IRGenerator {
+    /// 生成内置类的 native 方法
+    pub(super) fn generate_native_method(&mut self, class_name: &str, method: &MethodDecl, native: &NativeClass) -> cayResult<()> {
+        let fn_name = self.generate_method_name(class_name, method);
+        self.current_function = fn_name.clone();
+        self.current_class = class_name.to_string();
+        self.current_return_type = self.type_to_llvm(&method.return_type);
+        self.temp_counter = 0;
+
+        let mut params = vec!["i8* %this".to_string()];
+        for param in &method.params {
+            params.push(format!("{} %{}", self.type_to_llvm(&param.param_type), param.name));
+        }
+        self.emit_line(&format!("define {} @{}({}) {{", self.current_return_type, fn_name, params.join(", ")));
+        self.indent += 1;
+        self.emit_entry_label();
+
+        let offset = self.get_class_layout(class_name)
+            .and_then(|layout| layout.get_field_gep_offset(HANDLE_FIELD))
+            .ok_or_else(|| codegen_error(format!("Class '{}' has no '{}' field", class_name, HANDLE_FIELD)))?;
+        let field_ptr = self.new_temp();
+        self.emit_line(&format!("  {} = getelementptr i8, i8* %this, i64 {}", field_ptr, offset));
+        let slot = self.new_temp();
+        self.emit_line(&format!("  {} = bitcast i8* {} to i64*", slot, field_ptr));
+
+        let result = match native {
+            NativeClass::Map(key_type, value_type) => self.generate_map_method_body(class_name, method, &slot, key_type, value_type)?,
+            NativeClass::StringBuilder => self.generate_string_builder_method_body(class_name, method, &slot)?,
+        };
+        match result {
+            Some(value) => self.emit_line(&format!("  ret {} {}", self.current_return_type, value)),
+            None => self.emit_line("  ret void"),
+        }
+
+        self.indent -= 1;
+        self.finish_function_body()?;
+        self.emit_line("}");
+        self.emit_line("");
+        Ok(())
+    }
+
+    /// Map 的 native 方法体，返回结果值（void 方法为 `None`）
+    fn generate_map_method_body(&mut self, class_name: &str, method: &MethodDecl, slot: &str, key_type: &Type, value_type: &Type) -> cayResult<Option<String>> {
+        let string_keys = if *key_type == Type::String { "true" } else { "false" };
+        let map = self.emit_call("i8*", "@__cay_map_of", &[format!("i64* {}", slot), format!("i1 {}", string_keys)])
+            .unwrap_or_default();
+        let map_arg = format!("i8* {}", map);
+
+        let key_arg = |generator: &mut Self| -> cayResult<String> {
+            let param = method.params.first()
+                .ok_or_else(|| codegen_error(format!("'{}.{}' expects a key", class_name, method.name)))?;
+            Ok(format!("i64 {}", generator.map_word(key_type, &format!("%{}", param.name))))
+        };
+        let result = match method.name.as_str() {
+            "put" => {
+                let key = key_arg(self)?;
+                let value_name = format!("%{}", method.params[1].name);
+                let value = self.map_word(value_type, &value_name);
+                self.emit_call("void", "@__cay_map_put", &[map_arg, key, format!("i64 {}", value)]);
+                None
+            }
+            "get" => {
+                let key = key_arg(self)?;
+                let word = self.emit_call("i64", "@__cay_map_get", &[map_arg, key, "i64 0".to_string()])
+                    .unwrap_or_default();
+                Some(self.map_word_to(value_type, &word))
+            }
+            "containsKey" => {
+                let key = key_arg(self)?;
+                self.emit_call("i1", "@__cay_map_contains", &[map_arg, key])
+            }
+            "remove" => {
+                let key = key_arg(self)?;
+                self.emit_call("i1", "@__cay_map_remove", &[map_arg, key])
+            }
+            "size" => self.emit_call("i32", "@__cay_map_size", &[map_arg]),
+            "keys" => {
+                let data = self.emit_call("i8*", "@__cay_map_keys", &[map_arg]).unwrap_or_default();
+                let keys = self.new_temp();
+                self.emit_line(&format!("  {} = bitcast i8* {} to {}", keys, data, self.current_return_type));
+                Some(keys)
+            }
+            other => return Err(codegen_error(format!("Unknown native method '{}.{}'", class_name, other))),
+        };
+        Ok(result)
+    }
+
+    /// StringBuilder 的 native 方法体：append 把参数按字符串拼接的规则转换为文本后追加，返回 this
+    fn generate_string_builder_method_body(&mut self, class_name: &str, method: &MethodDecl, slot: &str) -> cayResult<Option<String>> {
+        let sb = self.emit_call("i8*", "@__cay_sb_of", &[format!("i64* {}", slot)]).unwrap_or_default();
+        let sb_arg = format!("i8* {}", sb);
+        Ok(match method.name.as_str() {
+            "append" => {
+                let param = method.params.first()
+                    .ok_or_else(|| codegen_error(format!("'{}.append' expects a value", class_name)))?;
+                let value = LLVMValue::new(self.type_to_llvm(&param.param_type), format!("%{}", param.name), param.param_type.clone());
+                let text = self.generate_to_string(&param.param_type, &value)?;
+                self.emit_call("void", "@__cay_sb_append", &[sb_arg, format!("i8* {}", text)]);
+                Some("%this".to_string())
+            }
+            "toString" => self.emit_call("i8*", "@__cay_sb_to_string", &[sb_arg]),
+            "length" => self.emit_call("i32", "@__cay_sb_length", &[sb_arg]),
+            "clear" => {
+                self.emit_call("void", "@__cay_sb_clear", &[sb_arg]);
+                None
+            }
+            other => return Err(codegen_error(format!("Unknown native method '{}.{}'", class_name, other))),
+        })
+    }
+
+    /// 把 `ty` 类型的值转换为哈希表中保存的 i64
+    fn map_word(&mut self, ty: &Type, value: &str) -> String {
+        let llvm_type = self.type_to_llvm(ty);
+        let instruction = match ty {
+            Type::Int64 => return value.to_string(),
+            Type::Int8 | Type::Int16 | Type::Int32 => format!("sext {} {} to i64", llvm_type, value),
+            Type::Bool | Type::Char => format!("zext {} {} to i64", llvm_type, value),
+            Type::Float64 => format!("bitcast double {} to i64", value),
+            Type::Float32 => {
+                let bits = self.new_temp();
+                self.emit_line(&format!("  {} = bitcast float {} to i32", bits, value));
+                format!("zext i32 {} to i64", bits)
+            }
+            _ => format!("ptrtoint {} {} to i64", llvm_type, value),
+        };
+        let word = self.new_temp();
+        self.emit_line(&format!("  {} = {}", word, instruction));
+        word
+    }
+
+    /// 把哈希表中保存的 i64 转换回 `ty` 类型的值（0 转换为该类型的默认值）
+    fn map_word_to(&mut self, ty: &Type, word: &str) -> String {
+        let llvm_type = self.type_to_llvm(ty);
+        let instruction = match ty {
+            Type::Int64 => return word.to_string(),
+            Type::Int8 | Type::Int16 | Type::Int32 | Type::Bool | Type::Char => format!("trunc i64 {} to {}", word, llvm_type),
+            Type::Float64 => format!("bitcast i64 {} to double", word),
+            Type::Float32 => {
+                let bits = self.new_temp();
+                self.emit_line(&format!("  {} = trunc i64 {} to i32", bits, word));
+                format!("bitcast i32 {} to float", bits)
+            }
+            _ => format!("inttoptr i64 {} to {}", word, llvm_type),
+        };
+        let value = self.new_temp();
+        self.emit_line(&format!("  {} = {}", value, instruction));
+        value
+    }
+}
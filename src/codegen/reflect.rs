@@ -0,0 +1,130 @@
+//! 反射元数据
+//!
+//! 打印或拼接没有定义 `toString()` 的对象以及数组时，调用运行时的反射格式化函数
+//! （见 `runtime::reflect`），由它们按元数据读取字段和元素。本模块负责：
+//! - 按模块输出每个类的元数据（类名、`toString()` 函数、字段名、偏移和类型签名）；
+//! - 输出按类型 ID 索引的类表 `@__cay_class_table`，接口对应的槽位为 null；
+//! - 生成调用反射格式化函数的代码。
+//!
+//! 元数据只在模块中实际用到反射格式化时输出。
+
+use crate::codegen::context::IRGenerator;
+use crate::codegen::value::LLVMValue;
+use crate::codegen::runtime::reflect::{CLASS_INFO_TYPE, FIELD_INFO_TYPE};
+use crate::types::Type;
+
+/// 类表的全局变量名
+const CLASS_TABLE: &str = "@__cay_class_table";
+
+/// 类型在反射元数据中的签名，与运行时函数的约定一致
+pub(crate) fn type_signature(ty: &Type) -> String {
+    match ty {
+        Type::Int8 => "B".to_string(),
+        Type::Int16 => "H".to_string(),
+        Type::Int32 => "I".to_string(),
+        Type::Int64 => "J".to_string(),
+        Type::Float32 => "F".to_string(),
+        Type::Float64 => "D".to_string(),
+        Type::Bool => "Z".to_string(),
+        Type::Char => "C".to_string(),
+        Type::String => "S".to_string(),
+        Type::Object(_) | Type::Generic(_, _) => "L".to_string(),
+        Type::Array(elem) => format!("[{}", type_signature(elem)),
+        _ => "?".to_string(),
+    }
+}
+
+impl IRGenerator {
+    /// 反射格式化函数共用的类表参数：`i8** 类表, i32 类数`
+    fn class_table_args(&mut self) -> [String; 2] {
+        self.uses_reflection = true;
+        let count = self.type_id_counter;
+        [
+            format!("i8** getelementptr ([{0} x i8*], [{0} x i8*]* {1}, i64 0, i64 0)", count, CLASS_TABLE),
+            format!("i32 {}", count),
+        ]
+    }
+
+    /// 按运行时类型把对象（`i8*`，可以为 null）转换为字符串
+    pub(crate) fn generate_reflect_object_to_string(&mut self, obj: &str) -> String {
+        let [classes, count] = self.class_table_args();
+        self.emit_call("i8*", "@__cay_reflect_object_to_string",
+            &[format!("i8* {}", obj), classes, count, "i32 0".to_string()])
+            .unwrap_or_default()
+    }
+
+    /// 把元素类型为 `elem` 的数组（带类型的值，可以为 null）转换为字符串 `[a, b, c]`
+    pub(crate) fn generate_array_to_string(&mut self, elem: &Type, value: &LLVMValue) -> String {
+        let arr_i8 = if value.ty == "i8*" {
+            value.repr.clone()
+        } else {
+            let cast = self.new_temp();
+            self.emit_line(&format!("  {} = bitcast {} to i8*", cast, value));
+            cast
+        };
+        let sig = self.get_string_constant_ptr(&type_signature(elem));
+        let [classes, count] = self.class_table_args();
+        self.emit_call("i8*", "@__cay_reflect_array_to_string",
+            &[format!("i8* {}", arr_i8), format!("i8* {}", sig), classes, count, "i32 0".to_string()])
+            .unwrap_or_default()
+    }
+
+    /// 生成类元数据和类表的声明；模块中没有用到反射格式化时返回空字符串
+    pub fn emit_reflection_declarations(&mut self) -> String {
+        if !self.uses_reflection {
+            return String::new();
+        }
+
+        let mut ids: Vec<(i32, String)> = self.type_id_map.values()
+            .map(|info| (info.type_id_value, info.class_name.clone()))
+            .collect();
+        ids.sort();
+
+        let mut result = String::new();
+        let mut table = vec!["i8* null".to_string(); self.type_id_counter];
+        for (id, class_name) in ids {
+            if self.is_interface(&class_name) {
+                continue;
+            }
+            let Some(layout) = self.get_class_layout(&class_name).cloned() else {
+                continue;
+            };
+            let mut fields: Vec<_> = layout.fields.values().cloned().collect();
+            fields.sort_by_key(|field| field.index);
+
+            let field_entries: Vec<String> = fields.iter().map(|field| {
+                let name = self.constant_string_ref(&field.name);
+                let sig = self.constant_string_ref(&type_signature(&field.field_type));
+                // 偏移用常量表达式计算，与 LLVM 的结构体布局保持一致
+                let offset = format!("ptrtoint ({0}* getelementptr ({1}, {1}* null, i32 0, i32 {2}) to i64)",
+                    field.llvm_type, layout.struct_type, field.index);
+                format!("{} {{ i8* {}, i64 {}, i8* {} }}", FIELD_INFO_TYPE, name, offset, sig)
+            }).collect();
+            let fields_global = format!("@__cay_fields.{}", id);
+            result.push_str(&format!("{} = private constant [{} x {}] [{}]\n",
+                fields_global, field_entries.len(), FIELD_INFO_TYPE, field_entries.join(", ")));
+
+            let to_string = match self.find_to_string_owner(&class_name) {
+                Some(owner) => format!("bitcast (i8* (i8*)* @{} to i8*)",
+                    self.build_function_name_from_method(&owner, "toString", &[])),
+                None => "null".to_string(),
+            };
+            let name = self.constant_string_ref(&class_name);
+            let info_global = format!("@__cay_class_info.{}", id);
+            result.push_str(&format!(
+                "{0} = private constant {1} {{ i8* {2}, i8* {3}, i64 {4}, i8* bitcast ([{4} x {5}]* {6} to i8*) }}\n",
+                info_global, CLASS_INFO_TYPE, name, to_string, fields.len(), FIELD_INFO_TYPE, fields_global));
+            table[id as usize] = format!("i8* bitcast ({}* {} to i8*)", CLASS_INFO_TYPE, info_global);
+        }
+        result.push_str(&format!("{} = private constant [{} x i8*] [{}]\n",
+            CLASS_TABLE, table.len(), table.join(", ")));
+        result
+    }
+
+    /// 字符串常量首字符指针的常量表达式，用于全局变量的初始值
+    fn constant_string_ref(&mut self, s: &str) -> String {
+        let global = self.get_or_create_string_constant(s);
+        let len = s.len() + 1;
+        format!("getelementptr ([{0} x i8], [{0} x i8]* {1}, i64 0, i64 0)", len, global)
+    }
+}
@@ -0,0 +1,172 @@
+//! char[]/boolean[] 数组输入输出运行时函数
+//!
+//! char[] 的元素是 Unicode 码点，输出前按 UTF-8 编码，读入时从 UTF-8 解码；
+//! boolean 数组的元素只占 1 字节。这里的函数都从数组头部（数据指针前 8 字节）读取长度，按元素类型逐个访问。
+
+use crate::codegen::context::IRGenerator;
+
+impl IRGenerator {
+    /// 生成数组输入输出运行时函数
+    pub(super) fn emit_array_io_runtime(&mut self) {
+        self.emit_char_array_to_string_runtime();
+        self.emit_print_bool_array_runtime();
+        self.emit_read_chars_runtime();
+    }
+
+    /// 发射读取数组长度的指令（结果为 `%len`）
+    fn emit_array_length_load(&mut self, arr: &str) {
+        self.emit_raw(&format!("  %len_ptr_i8 = getelementptr i8, i8* {}, i64 -8", arr));
+        self.emit_raw("  %len_ptr = bitcast i8* %len_ptr_i8 to i32*");
+        self.emit_raw("  %len = load i32, i32* %len_ptr, align 4");
+    }
+
+    /// 把 char[] 转换为字符串：码点按 UTF-8 编码，到数组末尾或第一个 '\0' 为止，null 数组为 "null"
+    fn emit_char_array_to_string_runtime(&mut self) {
+        self.emit_raw("define i8* @__cay_char_array_to_string(i32* %arr) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %is_null = icmp eq i32* %arr, null");
+        self.emit_raw("  br i1 %is_null, label %null_arr, label %init");
+        self.emit_raw("");
+        self.emit_raw("null_arr:");
+        self.emit_raw("  ret i8* getelementptr ([5 x i8], [5 x i8]* @.str.null_str, i64 0, i64 0)");
+        self.emit_raw("");
+        self.emit_raw("init:");
+        self.emit_raw("  %arr_i8 = bitcast i32* %arr to i8*");
+        self.emit_array_length_load("%arr_i8");
+        self.emit_raw("  ; 每个码点最多 4 个字节，另加终止符");
+        self.emit_raw("  %len64 = sext i32 %len to i64");
+        self.emit_raw("  %max_bytes = mul i64 %len64, 4");
+        self.emit_raw("  %size = add i64 %max_bytes, 1");
+        self.emit_raw("  %buf = call i8* @calloc(i64 1, i64 %size)");
+        self.emit_raw("  br label %loop");
+        self.emit_raw("");
+        self.emit_raw("loop:");
+        self.emit_raw("  %i = phi i32 [ 0, %init ], [ %next, %body ]");
+        self.emit_raw("  %pos = phi i64 [ 0, %init ], [ %pos_next, %body ]");
+        self.emit_raw("  %at_end = icmp sge i32 %i, %len");
+        self.emit_raw("  br i1 %at_end, label %done, label %load_char");
+        self.emit_raw("");
+        self.emit_raw("load_char:");
+        self.emit_raw("  %idx = sext i32 %i to i64");
+        self.emit_raw("  %ch_ptr = getelementptr inbounds i32, i32* %arr, i64 %idx");
+        self.emit_raw("  %ch = load i32, i32* %ch_ptr, align 4");
+        self.emit_raw("  %is_nul = icmp eq i32 %ch, 0");
+        self.emit_raw("  br i1 %is_nul, label %done, label %body");
+        self.emit_raw("");
+        self.emit_raw("body:");
+        self.emit_raw("  %dst = getelementptr i8, i8* %buf, i64 %pos");
+        self.emit_raw("  %written = call i64 @__cay_utf8_encode(i32 %ch, i8* %dst)");
+        self.emit_raw("  %pos_next = add i64 %pos, %written");
+        self.emit_raw("  %next = add i32 %i, 1");
+        self.emit_raw("  br label %loop");
+        self.emit_raw("");
+        self.emit_raw("done:");
+        self.emit_raw("  ret i8* %buf");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// 打印 boolean[]，格式为 `[true, false]`，null 数组打印 "null"
+    fn emit_print_bool_array_runtime(&mut self) {
+        self.emit_raw("define void @__cay_print_bool_array(i1* %arr) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %is_null = icmp eq i1* %arr, null");
+        self.emit_raw("  br i1 %is_null, label %print_null, label %init");
+        self.emit_raw("");
+        self.emit_raw("print_null:");
+        self.emit_raw("  call i32 (i8*, ...) @printf(i8* getelementptr ([5 x i8], [5 x i8]* @.str.null_str, i64 0, i64 0))");
+        self.emit_raw("  ret void");
+        self.emit_raw("");
+        self.emit_raw("init:");
+        self.emit_raw("  %arr_i8 = bitcast i1* %arr to i8*");
+        self.emit_array_length_load("%arr_i8");
+        self.emit_raw("  call i32 @putchar(i32 91)");
+        self.emit_raw("  br label %loop");
+        self.emit_raw("");
+        self.emit_raw("loop:");
+        self.emit_raw("  %i = phi i32 [ 0, %init ], [ %next, %print_elem ]");
+        self.emit_raw("  %at_end = icmp sge i32 %i, %len");
+        self.emit_raw("  br i1 %at_end, label %done, label %separator");
+        self.emit_raw("");
+        self.emit_raw("separator:");
+        self.emit_raw("  %first = icmp eq i32 %i, 0");
+        self.emit_raw("  br i1 %first, label %print_elem, label %print_sep");
+        self.emit_raw("");
+        self.emit_raw("print_sep:");
+        self.emit_raw("  call i32 @putchar(i32 44)");
+        self.emit_raw("  call i32 @putchar(i32 32)");
+        self.emit_raw("  br label %print_elem");
+        self.emit_raw("");
+        self.emit_raw("print_elem:");
+        self.emit_raw("  %idx = sext i32 %i to i64");
+        self.emit_raw("  %elem_ptr = getelementptr inbounds i1, i1* %arr, i64 %idx");
+        self.emit_raw("  %elem = load i1, i1* %elem_ptr, align 1");
+        self.emit_raw("  %true_ptr = getelementptr [5 x i8], [5 x i8]* @.str.true_str, i64 0, i64 0");
+        self.emit_raw("  %false_ptr = getelementptr [6 x i8], [6 x i8]* @.str.false_str, i64 0, i64 0");
+        self.emit_raw("  %text = select i1 %elem, i8* %true_ptr, i8* %false_ptr");
+        self.emit_raw("  call i32 (i8*, ...) @printf(i8* %text)");
+        self.emit_raw("  %next = add i32 %i, 1");
+        self.emit_raw("  br label %loop");
+        self.emit_raw("");
+        self.emit_raw("done:");
+        self.emit_raw("  call i32 @putchar(i32 93)");
+        self.emit_raw("  ret void");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// 从标准输入读取一行到 char[]，最多读取数组长度个字符，返回读取的字符数
+    ///
+    /// 多字节的 UTF-8 字符解码为一个码点。换行符不写入数组；数组还有空间时在末尾写入 '\0'，遇到 EOF 且未读到字符时返回 -1。
+    fn emit_read_chars_runtime(&mut self) {
+        self.emit_raw("define i32 @__cay_read_chars(i32* %buf) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %is_null = icmp eq i32* %buf, null");
+        self.emit_raw("  br i1 %is_null, label %null_buf, label %init");
+        self.emit_raw("");
+        self.emit_raw("null_buf:");
+        self.emit_raw("  ret i32 -1");
+        self.emit_raw("");
+        self.emit_raw("init:");
+        self.emit_raw("  %buf_i8 = bitcast i32* %buf to i8*");
+        self.emit_array_length_load("%buf_i8");
+        self.emit_raw("  br label %loop");
+        self.emit_raw("");
+        self.emit_raw("loop:");
+        self.emit_raw("  %i = phi i32 [ 0, %init ], [ %next, %store ]");
+        self.emit_raw("  %full = icmp sge i32 %i, %len");
+        self.emit_raw("  br i1 %full, label %done, label %read");
+        self.emit_raw("");
+        self.emit_raw("read:");
+        self.emit_raw("  %c = call i32 @getchar()");
+        self.emit_raw("  %is_eof = icmp slt i32 %c, 0");
+        self.emit_raw("  br i1 %is_eof, label %eof, label %check_newline");
+        self.emit_raw("");
+        self.emit_raw("check_newline:");
+        self.emit_raw("  %is_newline = icmp eq i32 %c, 10");
+        self.emit_raw("  br i1 %is_newline, label %terminate, label %store");
+        self.emit_raw("");
+        self.emit_raw("store:");
+        self.emit_raw("  %idx = sext i32 %i to i64");
+        self.emit_raw("  %ch_ptr = getelementptr inbounds i32, i32* %buf, i64 %idx");
+        self.emit_raw("  %ch = call i32 @__cay_read_utf8_tail(i32 %c)");
+        self.emit_raw("  store i32 %ch, i32* %ch_ptr, align 4");
+        self.emit_raw("  %next = add i32 %i, 1");
+        self.emit_raw("  br label %loop");
+        self.emit_raw("");
+        self.emit_raw("eof:");
+        self.emit_raw("  %nothing_read = icmp eq i32 %i, 0");
+        self.emit_raw("  br i1 %nothing_read, label %null_buf, label %terminate");
+        self.emit_raw("");
+        self.emit_raw("terminate:");
+        self.emit_raw("  %end_idx = sext i32 %i to i64");
+        self.emit_raw("  %end_ptr = getelementptr inbounds i32, i32* %buf, i64 %end_idx");
+        self.emit_raw("  store i32 0, i32* %end_ptr, align 4");
+        self.emit_raw("  ret i32 %i");
+        self.emit_raw("");
+        self.emit_raw("done:");
+        self.emit_raw("  ret i32 %i");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+}
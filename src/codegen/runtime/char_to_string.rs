@@ -1,21 +1,89 @@
 //! 字符转字符串运行时函数
+//!
+//! char 保存 Unicode 码点（i32），转换为字符串时按 UTF-8 编码为 1 ~ 4 个字节；
+//! 不是合法码点的值（负数、代理项、超过 U+10FFFF）编码为替换字符 U+FFFD。
 
 use crate::codegen::context::IRGenerator;
 
 impl IRGenerator {
     /// 生成字符到字符串运行时函数
     pub(super) fn emit_char_to_string_runtime(&mut self) {
-        self.emit_raw("define i8* @__cay_char_to_string(i8 %value) {");
+        self.emit_utf8_encode_runtime();
+        self.emit_raw("define i8* @__cay_char_to_string(i32 %value) {");
         self.emit_raw("entry:");
-        self.emit_raw("  ; 分配堆内存缓冲区（2字节：字符 + 终止符）");
-        self.emit_raw("  %buf = call i8* @calloc(i64 1, i64 2)");
-        self.emit_raw("  ; 存储字符");
-        self.emit_raw("  store i8 %value, i8* %buf");
-        self.emit_raw("  ; 存储终止符");
-        self.emit_raw("  %end_ptr = getelementptr i8, i8* %buf, i64 1");
-        self.emit_raw("  store i8 0, i8* %end_ptr");
+        self.emit_raw("  ; 最多 4 个字节加终止符，calloc 已清零");
+        self.emit_raw("  %buf = call i8* @calloc(i64 1, i64 5)");
+        self.emit_raw("  %len = call i64 @__cay_utf8_encode(i32 %value, i8* %buf)");
         self.emit_raw("  ret i8* %buf");
         self.emit_raw("}");
         self.emit_raw("");
     }
+
+    /// 把码点按 UTF-8 写入 `out`（至少 4 字节），返回写入的字节数
+    fn emit_utf8_encode_runtime(&mut self) {
+        self.emit_raw("define i64 @__cay_utf8_encode(i32 %value, i8* %out) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %too_large = icmp ugt i32 %value, 1114111");
+        self.emit_raw("  %high_bits = and i32 %value, -2048");
+        self.emit_raw("  %surrogate = icmp eq i32 %high_bits, 55296");
+        self.emit_raw("  %invalid = or i1 %too_large, %surrogate");
+        self.emit_raw("  %code = select i1 %invalid, i32 65533, i32 %value");
+        self.emit_raw("  %out1 = getelementptr i8, i8* %out, i64 1");
+        self.emit_raw("  %out2 = getelementptr i8, i8* %out, i64 2");
+        self.emit_raw("  %out3 = getelementptr i8, i8* %out, i64 3");
+        self.emit_raw("  ; 低 6 位依次作为最后、倒数第二、倒数第三个续字节");
+        self.emit_raw("  %low0 = and i32 %code, 63");
+        self.emit_raw("  %cont0 = or i32 %low0, 128");
+        self.emit_raw("  %byte_cont0 = trunc i32 %cont0 to i8");
+        self.emit_raw("  %shift6 = lshr i32 %code, 6");
+        self.emit_raw("  %low1 = and i32 %shift6, 63");
+        self.emit_raw("  %cont1 = or i32 %low1, 128");
+        self.emit_raw("  %byte_cont1 = trunc i32 %cont1 to i8");
+        self.emit_raw("  %shift12 = lshr i32 %code, 12");
+        self.emit_raw("  %low2 = and i32 %shift12, 63");
+        self.emit_raw("  %cont2 = or i32 %low2, 128");
+        self.emit_raw("  %byte_cont2 = trunc i32 %cont2 to i8");
+        self.emit_raw("  %is_one = icmp ult i32 %code, 128");
+        self.emit_raw("  br i1 %is_one, label %one, label %check_two");
+        self.emit_raw("");
+        self.emit_raw("one:");
+        self.emit_raw("  %byte_ascii = trunc i32 %code to i8");
+        self.emit_raw("  store i8 %byte_ascii, i8* %out");
+        self.emit_raw("  ret i64 1");
+        self.emit_raw("");
+        self.emit_raw("check_two:");
+        self.emit_raw("  %is_two = icmp ult i32 %code, 2048");
+        self.emit_raw("  br i1 %is_two, label %two, label %check_three");
+        self.emit_raw("");
+        self.emit_raw("two:");
+        self.emit_raw("  %lead2 = or i32 %shift6, 192");
+        self.emit_raw("  %byte_lead2 = trunc i32 %lead2 to i8");
+        self.emit_raw("  store i8 %byte_lead2, i8* %out");
+        self.emit_raw("  store i8 %byte_cont0, i8* %out1");
+        self.emit_raw("  ret i64 2");
+        self.emit_raw("");
+        self.emit_raw("check_three:");
+        self.emit_raw("  %is_three = icmp ult i32 %code, 65536");
+        self.emit_raw("  br i1 %is_three, label %three, label %four");
+        self.emit_raw("");
+        self.emit_raw("three:");
+        self.emit_raw("  %lead3 = or i32 %shift12, 224");
+        self.emit_raw("  %byte_lead3 = trunc i32 %lead3 to i8");
+        self.emit_raw("  store i8 %byte_lead3, i8* %out");
+        self.emit_raw("  store i8 %byte_cont1, i8* %out1");
+        self.emit_raw("  store i8 %byte_cont0, i8* %out2");
+        self.emit_raw("  ret i64 3");
+        self.emit_raw("");
+        self.emit_raw("four:");
+        self.emit_raw("  %shift18 = lshr i32 %code, 18");
+        self.emit_raw("  %lead4 = or i32 %shift18, 240");
+        self.emit_raw("  %byte_lead4 = trunc i32 %lead4 to i8");
+        self.emit_raw("  store i8 %byte_lead4, i8* %out");
+        self.emit_raw("  store i8 %byte_cont2, i8* %out1");
+        self.emit_raw("  store i8 %byte_cont1, i8* %out2");
+        self.emit_raw("  store i8 %byte_cont0, i8* %out3");
+        self.emit_raw("  ret i64 4");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
 }
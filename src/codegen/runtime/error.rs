@@ -0,0 +1,30 @@
+//! 运行时错误报告函数
+//!
+//! 数组越界、除零、窄化越界、递归深度和执行限制等运行时检查失败时，
+//! 生成的代码调用 `__cay_runtime_error` 打印诊断并以退出码 1 结束程序。
+
+use crate::codegen::context::IRGenerator;
+
+impl IRGenerator {
+    /// 生成运行时错误报告函数
+    ///
+    /// `format` 是 printf 格式串，最多引用两个 i64 参数，未使用的参数传 0。
+    pub(super) fn emit_runtime_error_runtime(&mut self) {
+        self.emit_raw("define void @__cay_runtime_error(i8* %format, i64 %a, i64 %b) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  call i32 (i8*, ...) @printf(i8* %format, i64 %a, i64 %b)");
+        self.emit_raw("  call void @exit(i32 1)");
+        self.emit_raw("  unreachable");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// 在当前基本块中报告运行时错误并终止程序
+    ///
+    /// `args` 是格式串引用的 i64 参数（最多两个），之后当前基本块以 `unreachable` 结束。
+    pub(crate) fn emit_runtime_error(&mut self, message_ptr: &str, args: &[&str]) {
+        let arg = |i: usize| args.get(i).copied().unwrap_or("0");
+        self.emit_line(&format!("  call void @__cay_runtime_error(i8* {}, i64 {}, i64 {})", message_ptr, arg(0), arg(1)));
+        self.emit_line("  unreachable");
+    }
+}
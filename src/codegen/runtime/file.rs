@@ -0,0 +1,146 @@
+//! 文件读写运行时函数
+//!
+//! 内置 File 类的静态方法展开为这里的函数，只使用 C 标准库的 fopen/fread/fputs/remove，
+//! 各平台行为一致。文件以二进制模式打开，内容按字节原样读写，不转换行尾。
+//! 路径为 null 或文件无法打开时不终止程序：readAll 返回 null，其余函数返回 false。
+
+use crate::codegen::context::IRGenerator;
+
+impl IRGenerator {
+    /// 生成文件读写运行时函数
+    pub(super) fn emit_file_runtime(&mut self) {
+        self.emit_file_read_all_runtime();
+        self.emit_file_write_runtime("@__cay_file_write_all", "@.str.file_mode_write", false);
+        self.emit_file_write_runtime("@__cay_file_append_line", "@.str.file_mode_append", true);
+        self.emit_file_exists_runtime();
+        self.emit_file_delete_runtime();
+    }
+
+    /// File.readAll：读取整个文件，缓冲区满时容量翻倍，不依赖 ftell 求文件大小
+    fn emit_file_read_all_runtime(&mut self) {
+        self.emit_raw("define i8* @__cay_file_read_all(i8* %path) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %no_path = icmp eq i8* %path, null");
+        self.emit_raw("  br i1 %no_path, label %fail, label %open");
+        self.emit_raw("");
+        self.emit_raw("open:");
+        self.emit_raw("  %f = call i8* @fopen(i8* %path, i8* getelementptr ([3 x i8], [3 x i8]* @.str.file_mode_read, i64 0, i64 0))");
+        self.emit_raw("  %open_failed = icmp eq i8* %f, null");
+        self.emit_raw("  br i1 %open_failed, label %fail, label %start");
+        self.emit_raw("");
+        self.emit_raw("start:");
+        self.emit_raw("  %initial = call i8* @calloc(i64 1, i64 4096)");
+        self.emit_raw("  br label %loop");
+        self.emit_raw("");
+        self.emit_raw("loop:");
+        self.emit_raw("  %buf = phi i8* [ %initial, %start ], [ %buf_next, %next ]");
+        self.emit_raw("  %cap = phi i64 [ 4096, %start ], [ %cap_next, %next ]");
+        self.emit_raw("  %len = phi i64 [ 0, %start ], [ %len_read, %next ]");
+        self.emit_raw("  ; 为终止符留出一个字节");
+        self.emit_raw("  %free = sub i64 %cap, %len");
+        self.emit_raw("  %space = sub i64 %free, 1");
+        self.emit_raw("  %dst = getelementptr i8, i8* %buf, i64 %len");
+        self.emit_raw("  %n = call i64 @fread(i8* %dst, i64 1, i64 %space, i8* %f)");
+        self.emit_raw("  %len_read = add i64 %len, %n");
+        self.emit_raw("  %at_end = icmp eq i64 %n, 0");
+        self.emit_raw("  br i1 %at_end, label %done, label %check_capacity");
+        self.emit_raw("");
+        self.emit_raw("check_capacity:");
+        self.emit_raw("  %needed = add i64 %len_read, 1");
+        self.emit_raw("  %full = icmp uge i64 %needed, %cap");
+        self.emit_raw("  br i1 %full, label %grow, label %next");
+        self.emit_raw("");
+        self.emit_raw("grow:");
+        self.emit_raw("  %cap_grown = mul i64 %cap, 2");
+        self.emit_raw("  %buf_grown = call i8* @calloc(i64 1, i64 %cap_grown)");
+        self.emit_raw("  call void @llvm.memcpy.p0i8.p0i8.i64(i8* %buf_grown, i8* %buf, i64 %len_read, i1 false)");
+        self.emit_raw("  br label %next");
+        self.emit_raw("");
+        self.emit_raw("next:");
+        self.emit_raw("  %buf_next = phi i8* [ %buf, %check_capacity ], [ %buf_grown, %grow ]");
+        self.emit_raw("  %cap_next = phi i64 [ %cap, %check_capacity ], [ %cap_grown, %grow ]");
+        self.emit_raw("  br label %loop");
+        self.emit_raw("");
+        self.emit_raw("done:");
+        self.emit_raw("  call i32 @fclose(i8* %f)");
+        self.emit_raw("  %end_ptr = getelementptr i8, i8* %buf, i64 %len_read");
+        self.emit_raw("  store i8 0, i8* %end_ptr");
+        self.emit_raw("  ret i8* %buf");
+        self.emit_raw("");
+        self.emit_raw("fail:");
+        self.emit_raw("  ret i8* null");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// File.writeAll/File.appendLine：以 `mode` 打开文件写入文本，null 文本写入 "null"
+    ///
+    /// `newline` 为 true 时在文本后追加换行符。关闭文件成功（数据已写出）时返回 true。
+    fn emit_file_write_runtime(&mut self, name: &str, mode: &str, newline: bool) {
+        self.emit_raw(&format!("define i1 {}(i8* %path, i8* %text) {{", name));
+        self.emit_raw("entry:");
+        self.emit_raw("  %no_path = icmp eq i8* %path, null");
+        self.emit_raw("  br i1 %no_path, label %fail, label %open");
+        self.emit_raw("");
+        self.emit_raw("open:");
+        self.emit_raw(&format!("  %f = call i8* @fopen(i8* %path, i8* getelementptr ([3 x i8], [3 x i8]* {}, i64 0, i64 0))", mode));
+        self.emit_raw("  %open_failed = icmp eq i8* %f, null");
+        self.emit_raw("  br i1 %open_failed, label %fail, label %write");
+        self.emit_raw("");
+        self.emit_raw("write:");
+        self.emit_raw("  %no_text = icmp eq i8* %text, null");
+        self.emit_raw("  %content = select i1 %no_text, i8* getelementptr ([5 x i8], [5 x i8]* @.str.null_str, i64 0, i64 0), i8* %text");
+        self.emit_raw("  call i32 @fputs(i8* %content, i8* %f)");
+        if newline {
+            self.emit_raw("  call i32 @fputc(i32 10, i8* %f)");
+        }
+        self.emit_raw("  %closed = call i32 @fclose(i8* %f)");
+        self.emit_raw("  %ok = icmp eq i32 %closed, 0");
+        self.emit_raw("  ret i1 %ok");
+        self.emit_raw("");
+        self.emit_raw("fail:");
+        self.emit_raw("  ret i1 false");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// File.exists：文件能以只读方式打开时返回 true
+    fn emit_file_exists_runtime(&mut self) {
+        self.emit_raw("define i1 @__cay_file_exists(i8* %path) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %no_path = icmp eq i8* %path, null");
+        self.emit_raw("  br i1 %no_path, label %missing, label %open");
+        self.emit_raw("");
+        self.emit_raw("open:");
+        self.emit_raw("  %f = call i8* @fopen(i8* %path, i8* getelementptr ([3 x i8], [3 x i8]* @.str.file_mode_read, i64 0, i64 0))");
+        self.emit_raw("  %open_failed = icmp eq i8* %f, null");
+        self.emit_raw("  br i1 %open_failed, label %missing, label %found");
+        self.emit_raw("");
+        self.emit_raw("found:");
+        self.emit_raw("  call i32 @fclose(i8* %f)");
+        self.emit_raw("  ret i1 true");
+        self.emit_raw("");
+        self.emit_raw("missing:");
+        self.emit_raw("  ret i1 false");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// File.delete：删除文件，成功时返回 true
+    fn emit_file_delete_runtime(&mut self) {
+        self.emit_raw("define i1 @__cay_file_delete(i8* %path) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %no_path = icmp eq i8* %path, null");
+        self.emit_raw("  br i1 %no_path, label %fail, label %remove");
+        self.emit_raw("");
+        self.emit_raw("remove:");
+        self.emit_raw("  %result = call i32 @remove(i8* %path)");
+        self.emit_raw("  %ok = icmp eq i32 %result, 0");
+        self.emit_raw("  ret i1 %ok");
+        self.emit_raw("");
+        self.emit_raw("fail:");
+        self.emit_raw("  ret i1 false");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+}
@@ -1,21 +1,149 @@
 //! 浮点数转字符串运行时函数
+//!
+//! 打印浮点数和字符串拼接都经过 `__cay_double_to_string`：默认输出能精确还原该值的最短十进制形式
+//! （float 按单精度判断，最多 9 位有效数字；double 最多 17 位），整数值保留一位小数（`2.0`），
+//! 指数小于 -4 或不小于最大有效位数时使用科学计数法（`1e+20`），与 C 的 `%g` 规则一致。
+//! 调用 `setPrintPrecision(n)` 之后改为固定 n 位小数，`println(value, n)` 只对这一次输出使用 n 位小数。
+//! 区域设置可能产生的 ',' 小数分隔符统一替换为 '.'，使程序输出不依赖运行环境的 locale。
 
 use crate::codegen::context::IRGenerator;
 
+/// 默认的精度设置：负数表示使用最短的可还原形式
+const DEFAULT_PRINT_PRECISION: i32 = -1;
+
+/// `setPrintPrecision` 允许的最大小数位数，超出范围的参数会被截断到 [0, 最大值]
+const MAX_PRINT_PRECISION: i32 = 20;
+
 impl IRGenerator {
     /// 生成浮点数转字符串运行时函数
     pub(super) fn emit_float_to_string_runtime(&mut self) {
-        // 使用一个包装函数来确保正确的调用约定
-        // 注意：使用 calloc 分配堆内存（自动零初始化），而不是 alloca 分配栈内存
-        self.emit_raw("define i8* @__cay_float_to_string(double %value) {");
+        // 精度使用 linkonce_odr 全局变量，分别编译的多个模块共享同一份设置
+        self.emit_raw(&format!("@.cay_print_precision = linkonce_odr global i32 {}, align 4", DEFAULT_PRINT_PRECISION));
+        self.emit_raw("");
+
+        // single 为 true 时 value 是 float 扩展而来，按单精度判断能否还原
+        self.emit_raw("define i8* @__cay_double_to_string(double %value, i1 %single) {");
         self.emit_raw("entry:");
-        self.emit_raw("  ; 分配堆内存缓冲区（64字节，8字节对齐，使用 calloc 自动零初始化）");
+        self.emit_raw("  %precision = load i32, i32* @.cay_print_precision, align 4");
+        self.emit_raw("  %use_fixed = icmp sge i32 %precision, 0");
+        self.emit_raw("  br i1 %use_fixed, label %fixed, label %shortest");
+        self.emit_raw("");
+        self.emit_raw("fixed:");
+        self.emit_raw("  %fixed_str = call i8* @__cay_double_to_fixed(double %value, i32 %precision)");
+        self.emit_raw("  ret i8* %fixed_str");
+        self.emit_raw("");
+        self.emit_raw("shortest:");
+        self.emit_raw("  ; 定点形式的整数部分不超过 17 位，小数部分不超过 20 位");
         self.emit_raw("  %buf = call i8* @calloc(i64 1, i64 64)");
-        self.emit_raw("  %fmt_ptr = getelementptr [3 x i8], [3 x i8]* @.str.float_fmt, i64 0, i64 0");
-        self.emit_raw("  ; 调用 snprintf（指定缓冲区大小）");
-        self.emit_raw("  call i32 (i8*, i64, i8*, ...) @snprintf(i8* %buf, i64 64, i8* %fmt_ptr, double %value)");
+        self.emit_raw("  %fmt_ptr = getelementptr [5 x i8], [5 x i8]* @.str.float_fmt, i64 0, i64 0");
+        self.emit_raw("  %exp_fmt_ptr = getelementptr [5 x i8], [5 x i8]* @.str.float_exp_fmt, i64 0, i64 0");
+        self.emit_raw("  %max_digits = select i1 %single, i32 9, i32 17");
+        self.emit_raw("  ; NaN 和无穷大减去自身得到 NaN");
+        self.emit_raw("  %diff = fsub double %value, %value");
+        self.emit_raw("  %finite = fcmp ord double %diff, %diff");
+        self.emit_raw("  br i1 %finite, label %try_digits, label %non_finite");
+        self.emit_raw("");
+        self.emit_raw("non_finite:");
+        self.emit_raw("  call i32 (i8*, i64, i8*, ...) @snprintf(i8* %buf, i64 64, i8* %fmt_ptr, i32 0, double %value)");
+        self.emit_raw("  ret i8* %buf");
+        self.emit_raw("");
+        self.emit_raw("try_digits:");
+        self.emit_raw("  ; 从 1 位有效数字开始增加，直到解析回来的值与原值相等");
+        self.emit_raw("  %digits = phi i32 [1, %shortest], [%next_digits, %retry]");
+        self.emit_raw("  %decimals = sub i32 %digits, 1");
+        self.emit_raw("  call i32 (i8*, i64, i8*, ...) @snprintf(i8* %buf, i64 64, i8* %exp_fmt_ptr, i32 %decimals, double %value)");
+        self.emit_raw("  %parsed = call double @strtod(i8* %buf, i8** null)");
+        self.emit_raw("  %narrowed = fptrunc double %parsed to float");
+        self.emit_raw("  %widened = fpext float %narrowed to double");
+        self.emit_raw("  %round_trip = select i1 %single, double %widened, double %parsed");
+        self.emit_raw("  %same = fcmp oeq double %round_trip, %value");
+        self.emit_raw("  %last = icmp sge i32 %digits, %max_digits");
+        self.emit_raw("  %stop = or i1 %same, %last");
+        self.emit_raw("  br i1 %stop, label %found, label %retry");
+        self.emit_raw("");
+        self.emit_raw("retry:");
+        self.emit_raw("  %next_digits = add i32 %digits, 1");
+        self.emit_raw("  br label %try_digits");
+        self.emit_raw("");
+        self.emit_raw("found:");
+        self.emit_raw("  ; 读出科学计数法的指数，决定使用定点形式还是保留科学计数法");
+        self.emit_raw("  %e_ptr = call i8* @strchr(i8* %buf, i32 101)");
+        self.emit_raw("  %exp_ptr = getelementptr i8, i8* %e_ptr, i64 1");
+        self.emit_raw("  %exponent = call i32 @atoi(i8* %exp_ptr)");
+        self.emit_raw("  %too_small = icmp slt i32 %exponent, -4");
+        self.emit_raw("  %too_large = icmp sge i32 %exponent, %max_digits");
+        self.emit_raw("  %scientific = or i1 %too_small, %too_large");
+        self.emit_raw("  br i1 %scientific, label %done, label %positional");
+        self.emit_raw("");
+        self.emit_raw("positional:");
+        self.emit_raw("  ; 小数位数使有效数字个数不变，至少保留一位小数");
+        self.emit_raw("  %frac = sub i32 %decimals, %exponent");
+        self.emit_raw("  %few = icmp slt i32 %frac, 1");
+        self.emit_raw("  %frac_digits = select i1 %few, i32 1, i32 %frac");
+        self.emit_raw("  call i32 (i8*, i64, i8*, ...) @snprintf(i8* %buf, i64 64, i8* %fmt_ptr, i32 %frac_digits, double %value)");
+        self.emit_raw("  br label %done");
+        self.emit_raw("");
+        self.emit_raw("done:");
+        self.emit_raw("  call void @__cay_fix_decimal_point(i8* %buf)");
         self.emit_raw("  ret i8* %buf");
         self.emit_raw("}");
         self.emit_raw("");
+
+        // 固定小数位数：digits 截断到 [0, MAX_PRINT_PRECISION]
+        // 缓冲区需要容纳 %f 格式下最大的 double（309 位整数部分）加上小数部分
+        self.emit_raw("define i8* @__cay_double_to_fixed(double %value, i32 %digits) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %negative = icmp slt i32 %digits, 0");
+        self.emit_raw("  %low = select i1 %negative, i32 0, i32 %digits");
+        self.emit_raw(&format!("  %too_many = icmp sgt i32 %low, {}", MAX_PRINT_PRECISION));
+        self.emit_raw(&format!("  %clamped = select i1 %too_many, i32 {}, i32 %low", MAX_PRINT_PRECISION));
+        self.emit_raw("  %buf = call i8* @calloc(i64 1, i64 352)");
+        self.emit_raw("  %fmt_ptr = getelementptr [5 x i8], [5 x i8]* @.str.float_fmt, i64 0, i64 0");
+        self.emit_raw("  call i32 (i8*, i64, i8*, ...) @snprintf(i8* %buf, i64 352, i8* %fmt_ptr, i32 %clamped, double %value)");
+        self.emit_raw("  call void @__cay_fix_decimal_point(i8* %buf)");
+        self.emit_raw("  ret i8* %buf");
+        self.emit_raw("}");
+        self.emit_raw("");
+
+        // 区域设置的小数分隔符可能是 ','，统一替换为 '.'
+        self.emit_raw("define void @__cay_fix_decimal_point(i8* %buf) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  br label %scan");
+        self.emit_raw("");
+        self.emit_raw("scan:");
+        self.emit_raw("  %i = phi i64 [0, %entry], [%next, %advance]");
+        self.emit_raw("  %ptr = getelementptr i8, i8* %buf, i64 %i");
+        self.emit_raw("  %c = load i8, i8* %ptr");
+        self.emit_raw("  %at_end = icmp eq i8 %c, 0");
+        self.emit_raw("  br i1 %at_end, label %done, label %check");
+        self.emit_raw("");
+        self.emit_raw("check:");
+        self.emit_raw("  %is_comma = icmp eq i8 %c, 44");
+        self.emit_raw("  br i1 %is_comma, label %replace, label %advance");
+        self.emit_raw("");
+        self.emit_raw("replace:");
+        self.emit_raw("  store i8 46, i8* %ptr");
+        self.emit_raw("  br label %advance");
+        self.emit_raw("");
+        self.emit_raw("advance:");
+        self.emit_raw("  %next = add i64 %i, 1");
+        self.emit_raw("  br label %scan");
+        self.emit_raw("");
+        self.emit_raw("done:");
+        self.emit_raw("  ret void");
+        self.emit_raw("}");
+        self.emit_raw("");
+
+        // setPrintPrecision(n)：把 n 截断到 [0, MAX_PRINT_PRECISION] 后保存
+        self.emit_raw("define void @__cay_set_print_precision(i32 %digits) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %negative = icmp slt i32 %digits, 0");
+        self.emit_raw("  %low = select i1 %negative, i32 0, i32 %digits");
+        self.emit_raw(&format!("  %too_many = icmp sgt i32 %low, {}", MAX_PRINT_PRECISION));
+        self.emit_raw(&format!("  %clamped = select i1 %too_many, i32 {}, i32 %low", MAX_PRINT_PRECISION));
+        self.emit_raw("  store i32 %clamped, i32* @.cay_print_precision, align 4");
+        self.emit_raw("  ret void");
+        self.emit_raw("}");
+        self.emit_raw("");
     }
 }
@@ -0,0 +1,173 @@
+//! 读取标准输入的运行时函数
+
+use crate::codegen::context::IRGenerator;
+
+impl IRGenerator {
+    /// 生成输入运行时函数
+    pub(super) fn emit_input_runtime(&mut self) {
+        self.emit_read_line_runtime();
+        self.emit_read_bool_runtime();
+        self.emit_read_char_runtime();
+        self.emit_read_utf8_tail_runtime();
+    }
+
+    /// 生成 readLine 运行时函数
+    ///
+    /// 逐个字符读取到换行或 EOF，缓冲区满时容量翻倍（重新分配后复制），行的长度不受限制。
+    /// 返回堆上的字符串，不含换行符（Windows 的 "\r\n" 一并去掉）；EOF 时返回空字符串。
+    fn emit_read_line_runtime(&mut self) {
+        self.emit_raw("define i8* @__cay_read_line() {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %initial = call i8* @calloc(i64 1, i64 64)");
+        self.emit_raw("  br label %loop");
+        self.emit_raw("");
+        self.emit_raw("loop:");
+        self.emit_raw("  %buf = phi i8* [ %initial, %entry ], [ %buf_next, %append ]");
+        self.emit_raw("  %cap = phi i64 [ 64, %entry ], [ %cap_next, %append ]");
+        self.emit_raw("  %len = phi i64 [ 0, %entry ], [ %len_next, %append ]");
+        self.emit_raw("  %c = call i32 @getchar()");
+        self.emit_raw("  %is_eof = icmp eq i32 %c, -1");
+        self.emit_raw("  %is_newline = icmp eq i32 %c, 10");
+        self.emit_raw("  %line_end = or i1 %is_eof, %is_newline");
+        self.emit_raw("  br i1 %line_end, label %done, label %check_capacity");
+        self.emit_raw("");
+        self.emit_raw("check_capacity:");
+        self.emit_raw("  ; 为新字符和终止符留出空间");
+        self.emit_raw("  %needed = add i64 %len, 2");
+        self.emit_raw("  %full = icmp ugt i64 %needed, %cap");
+        self.emit_raw("  br i1 %full, label %grow, label %append");
+        self.emit_raw("");
+        self.emit_raw("grow:");
+        self.emit_raw("  %cap_grown = mul i64 %cap, 2");
+        self.emit_raw("  %buf_grown = call i8* @calloc(i64 1, i64 %cap_grown)");
+        self.emit_raw("  call void @llvm.memcpy.p0i8.p0i8.i64(i8* %buf_grown, i8* %buf, i64 %len, i1 false)");
+        self.emit_raw("  br label %append");
+        self.emit_raw("");
+        self.emit_raw("append:");
+        self.emit_raw("  %buf_next = phi i8* [ %buf, %check_capacity ], [ %buf_grown, %grow ]");
+        self.emit_raw("  %cap_next = phi i64 [ %cap, %check_capacity ], [ %cap_grown, %grow ]");
+        self.emit_raw("  %ch = trunc i32 %c to i8");
+        self.emit_raw("  %slot = getelementptr i8, i8* %buf_next, i64 %len");
+        self.emit_raw("  store i8 %ch, i8* %slot");
+        self.emit_raw("  %len_next = add i64 %len, 1");
+        self.emit_raw("  br label %loop");
+        self.emit_raw("");
+        self.emit_raw("done:");
+        self.emit_raw("  %has_chars = icmp ugt i64 %len, 0");
+        self.emit_raw("  br i1 %has_chars, label %check_cr, label %terminate");
+        self.emit_raw("");
+        self.emit_raw("check_cr:");
+        self.emit_raw("  %last_index = sub i64 %len, 1");
+        self.emit_raw("  %last_ptr = getelementptr i8, i8* %buf, i64 %last_index");
+        self.emit_raw("  %last = load i8, i8* %last_ptr");
+        self.emit_raw("  %is_cr = icmp eq i8 %last, 13");
+        self.emit_raw("  %trimmed = select i1 %is_cr, i64 %last_index, i64 %len");
+        self.emit_raw("  br label %terminate");
+        self.emit_raw("");
+        self.emit_raw("terminate:");
+        self.emit_raw("  %end = phi i64 [ %len, %done ], [ %trimmed, %check_cr ]");
+        self.emit_raw("  %end_ptr = getelementptr i8, i8* %buf, i64 %end");
+        self.emit_raw("  store i8 0, i8* %end_ptr");
+        self.emit_raw("  ret i8* %buf");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// 生成 readBool 运行时函数：读取下一个单词（最多 7 个字符），等于 "true" 时返回 true
+    fn emit_read_bool_runtime(&mut self) {
+        self.emit_raw("define i1 @__cay_read_bool() {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %word = call i8* @calloc(i64 1, i64 8)");
+        self.emit_raw("  call i32 (i8*, ...) @scanf(i8* getelementptr ([4 x i8], [4 x i8]* @.str.word_scan_fmt, i64 0, i64 0), i8* %word)");
+        self.emit_raw("  %cmp = call i32 @strcmp(i8* %word, i8* getelementptr ([5 x i8], [5 x i8]* @.str.true_str, i64 0, i64 0))");
+        self.emit_raw("  %is_true = icmp eq i32 %cmp, 0");
+        self.emit_raw("  ret i1 %is_true");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// 生成 readChar 运行时函数：跳过空白后读取一个 UTF-8 字符，返回其码点；EOF 时返回 0
+    fn emit_read_char_runtime(&mut self) {
+        self.emit_raw("define i32 @__cay_read_char() {");
+        self.emit_raw("entry:");
+        self.emit_raw("  br label %loop");
+        self.emit_raw("");
+        self.emit_raw("loop:");
+        self.emit_raw("  %c = call i32 @getchar()");
+        self.emit_raw("  %is_eof = icmp slt i32 %c, 0");
+        self.emit_raw("  br i1 %is_eof, label %eof, label %check_space");
+        self.emit_raw("");
+        self.emit_raw("check_space:");
+        self.emit_raw("  ; 空格以及 \\t \\n \\v \\f \\r（9 ~ 13）");
+        self.emit_raw("  %is_space = icmp eq i32 %c, 32");
+        self.emit_raw("  %control = sub i32 %c, 9");
+        self.emit_raw("  %is_control_space = icmp ult i32 %control, 5");
+        self.emit_raw("  %skip = or i1 %is_space, %is_control_space");
+        self.emit_raw("  br i1 %skip, label %loop, label %decode");
+        self.emit_raw("");
+        self.emit_raw("decode:");
+        self.emit_raw("  %code = call i32 @__cay_read_utf8_tail(i32 %c)");
+        self.emit_raw("  ret i32 %code");
+        self.emit_raw("");
+        self.emit_raw("eof:");
+        self.emit_raw("  ret i32 0");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// 已读入 UTF-8 序列的首字节 `lead`，从标准输入读取其余的续字节并返回码点
+    ///
+    /// 首字节是续字节或无效字节（0xF8 及以上）、续字节缺失（EOF 或不是 10xxxxxx）时返回替换字符 U+FFFD，
+    /// 与 `charAt` 的解码一致。
+    fn emit_read_utf8_tail_runtime(&mut self) {
+        self.emit_raw("define i32 @__cay_read_utf8_tail(i32 %lead) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %is_single = icmp ult i32 %lead, 128");
+        self.emit_raw("  br i1 %is_single, label %single, label %check_lead");
+        self.emit_raw("");
+        self.emit_raw("single:");
+        self.emit_raw("  ret i32 %lead");
+        self.emit_raw("");
+        self.emit_raw("check_lead:");
+        self.emit_raw("  %lead_offset = sub i32 %lead, 192");
+        self.emit_raw("  %is_lead = icmp ult i32 %lead_offset, 56");
+        self.emit_raw("  br i1 %is_lead, label %multi, label %malformed");
+        self.emit_raw("");
+        self.emit_raw("multi:");
+        self.emit_raw("  %is_two = icmp ult i32 %lead, 224");
+        self.emit_raw("  %is_three = icmp ult i32 %lead, 240");
+        self.emit_raw("  %count_34 = select i1 %is_three, i32 2, i32 3");
+        self.emit_raw("  %count = select i1 %is_two, i32 1, i32 %count_34");
+        self.emit_raw("  %mask_34 = select i1 %is_three, i32 15, i32 7");
+        self.emit_raw("  %mask = select i1 %is_two, i32 31, i32 %mask_34");
+        self.emit_raw("  %initial = and i32 %lead, %mask");
+        self.emit_raw("  br label %loop");
+        self.emit_raw("");
+        self.emit_raw("loop:");
+        self.emit_raw("  %i = phi i32 [ 0, %multi ], [ %next, %append ]");
+        self.emit_raw("  %code = phi i32 [ %initial, %multi ], [ %code_next, %append ]");
+        self.emit_raw("  %finished = icmp sge i32 %i, %count");
+        self.emit_raw("  br i1 %finished, label %done, label %read");
+        self.emit_raw("");
+        self.emit_raw("read:");
+        self.emit_raw("  %byte = call i32 @getchar()");
+        self.emit_raw("  %tag = and i32 %byte, -64");
+        self.emit_raw("  %is_cont = icmp eq i32 %tag, 128");
+        self.emit_raw("  br i1 %is_cont, label %append, label %malformed");
+        self.emit_raw("");
+        self.emit_raw("append:");
+        self.emit_raw("  %shifted = shl i32 %code, 6");
+        self.emit_raw("  %bits = and i32 %byte, 63");
+        self.emit_raw("  %code_next = or i32 %shifted, %bits");
+        self.emit_raw("  %next = add i32 %i, 1");
+        self.emit_raw("  br label %loop");
+        self.emit_raw("");
+        self.emit_raw("malformed:");
+        self.emit_raw("  ret i32 65533");
+        self.emit_raw("");
+        self.emit_raw("done:");
+        self.emit_raw("  ret i32 %code");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+}
@@ -0,0 +1,502 @@
+//! 内置 Map 的哈希表运行时函数
+//!
+//! `Map<K, V>` 的 native 方法展开为这里的函数（见 `codegen::prelude`）。键和值都以 i64 传递：
+//! int 键符号扩展，String 键和引用类型的值为指针值，浮点数按位转换。
+//!
+//! 哈希表采用开放寻址（线性探测）加按插入顺序排列的条目数组，表头为 8 个 i64：
+//!
+//! ```text
+//! [0] 键是否为字符串  [1] 存活的条目数  [2] 已使用的条目槽数  [3] 条目容量
+//! [4] 索引数组（2 * 容量个 i64：0 为空槽，否则为条目下标 + 1）
+//! [5] 键数组  [6] 值数组  [7] 存活标记数组（i8）
+//! ```
+//!
+//! 删除只清除存活标记，索引槽保留（探测时跳过），因此探测链不会断开；
+//! 条目槽用完时按存活条目数重建（容量至少为存活条目数的两倍），同时清除已删除的条目。
+//! String 键用 FNV-1a 按内容求哈希并用 strcmp 比较，int 键使用乘法哈希。
+
+use crate::codegen::context::IRGenerator;
+
+impl IRGenerator {
+    /// 生成 Map 运行时函数
+    pub(super) fn emit_map_runtime(&mut self) {
+        self.emit_map_new_runtime();
+        self.emit_map_reserve_runtime();
+        self.emit_map_of_runtime();
+        self.emit_map_hash_runtime();
+        self.emit_map_key_eq_runtime();
+        self.emit_map_find_runtime();
+        self.emit_map_entry_runtime();
+        self.emit_map_insert_entry_runtime();
+        self.emit_map_rebuild_runtime();
+        self.emit_map_put_runtime();
+        self.emit_map_get_runtime();
+        self.emit_map_contains_runtime();
+        self.emit_map_remove_runtime();
+        self.emit_map_size_runtime();
+        self.emit_map_keys_runtime();
+    }
+
+    /// 表头字段指针：`%{name} = getelementptr i64, i64* %table, i64 {index}`
+    fn emit_map_header_field(&mut self, name: &str, index: usize) {
+        self.emit_raw(&format!("  %{} = getelementptr i64, i64* %table, i64 {}", name, index));
+    }
+
+    /// 读取表头中的数组指针，`name` 为结果寄存器名
+    fn emit_map_header_array(&mut self, name: &str, index: usize, elem: &str) {
+        self.emit_map_header_field(&format!("{}_field", name), index);
+        self.emit_raw(&format!("  %{0}_word = load i64, i64* %{0}_field", name));
+        self.emit_raw(&format!("  %{0} = inttoptr i64 %{0}_word to {1}*", name, elem));
+    }
+
+    /// 创建空的哈希表
+    fn emit_map_new_runtime(&mut self) {
+        self.emit_raw("define i8* @__cay_map_new(i1 %string_keys) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %raw = call i8* @calloc(i64 8, i64 8)");
+        self.emit_raw("  %table = bitcast i8* %raw to i64*");
+        self.emit_raw("  %flag = zext i1 %string_keys to i64");
+        self.emit_raw("  store i64 %flag, i64* %table");
+        self.emit_raw("  call void @__cay_map_reserve(i64* %table, i64 8)");
+        self.emit_raw("  ret i8* %raw");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// 分配容量为 `cap` 的空索引和条目数组（不修改存活条目数）
+    fn emit_map_reserve_runtime(&mut self) {
+        self.emit_raw("define void @__cay_map_reserve(i64* %table, i64 %cap) {");
+        self.emit_raw("entry:");
+        self.emit_map_header_field("used_field", 2);
+        self.emit_raw("  store i64 0, i64* %used_field");
+        self.emit_map_header_field("cap_field", 3);
+        self.emit_raw("  store i64 %cap, i64* %cap_field");
+        self.emit_raw("  %index_size = shl i64 %cap, 1");
+        for (name, index, count, elem_size) in [
+            ("index", 4, "%index_size", 8),
+            ("keys", 5, "%cap", 8),
+            ("values", 6, "%cap", 8),
+            ("alive", 7, "%cap", 1),
+        ] {
+            self.emit_raw(&format!("  %{} = call i8* @calloc(i64 {}, i64 {})", name, count, elem_size));
+            self.emit_raw(&format!("  %{0}_word = ptrtoint i8* %{0} to i64", name));
+            self.emit_map_header_field(&format!("{}_field", name), index);
+            self.emit_raw(&format!("  store i64 %{0}_word, i64* %{0}_field", name));
+        }
+        self.emit_raw("  ret void");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// 取出对象字段 `slot` 中的哈希表，字段为 0 时创建新表并写回
+    fn emit_map_of_runtime(&mut self) {
+        self.emit_raw("define i8* @__cay_map_of(i64* %slot, i1 %string_keys) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %handle = load i64, i64* %slot");
+        self.emit_raw("  %missing = icmp eq i64 %handle, 0");
+        self.emit_raw("  br i1 %missing, label %create, label %existing");
+        self.emit_raw("");
+        self.emit_raw("existing:");
+        self.emit_raw("  %map = inttoptr i64 %handle to i8*");
+        self.emit_raw("  ret i8* %map");
+        self.emit_raw("");
+        self.emit_raw("create:");
+        self.emit_raw("  %created = call i8* @__cay_map_new(i1 %string_keys)");
+        self.emit_raw("  %created_handle = ptrtoint i8* %created to i64");
+        self.emit_raw("  store i64 %created_handle, i64* %slot");
+        self.emit_raw("  ret i8* %created");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// 键的哈希值：字符串为 FNV-1a（null 为 0），整数为乘法哈希（高位折叠到低位）
+    fn emit_map_hash_runtime(&mut self) {
+        self.emit_raw("define i64 @__cay_map_hash(i64* %table, i64 %key) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %flag = load i64, i64* %table");
+        self.emit_raw("  %is_string = icmp ne i64 %flag, 0");
+        self.emit_raw("  br i1 %is_string, label %string, label %int");
+        self.emit_raw("");
+        self.emit_raw("int:");
+        self.emit_raw("  ; 0x9E3779B97F4A7C15");
+        self.emit_raw("  %mixed = mul i64 %key, -7046029254386353131");
+        self.emit_raw("  %high = lshr i64 %mixed, 32");
+        self.emit_raw("  %int_hash = xor i64 %mixed, %high");
+        self.emit_raw("  ret i64 %int_hash");
+        self.emit_raw("");
+        self.emit_raw("string:");
+        self.emit_raw("  %str = inttoptr i64 %key to i8*");
+        self.emit_raw("  %is_null = icmp eq i8* %str, null");
+        self.emit_raw("  br i1 %is_null, label %null, label %loop");
+        self.emit_raw("");
+        self.emit_raw("null:");
+        self.emit_raw("  ret i64 0");
+        self.emit_raw("");
+        self.emit_raw("loop:");
+        self.emit_raw("  %i = phi i64 [ 0, %string ], [ %i_next, %body ]");
+        self.emit_raw("  ; FNV-1a 初值 0xCBF29CE484222325");
+        self.emit_raw("  %hash = phi i64 [ -3750763034362895579, %string ], [ %hash_next, %body ]");
+        self.emit_raw("  %char_ptr = getelementptr i8, i8* %str, i64 %i");
+        self.emit_raw("  %char = load i8, i8* %char_ptr");
+        self.emit_raw("  %at_end = icmp eq i8 %char, 0");
+        self.emit_raw("  br i1 %at_end, label %done, label %body");
+        self.emit_raw("");
+        self.emit_raw("body:");
+        self.emit_raw("  %byte = zext i8 %char to i64");
+        self.emit_raw("  %mixed_byte = xor i64 %hash, %byte");
+        self.emit_raw("  %hash_next = mul i64 %mixed_byte, 1099511628211");
+        self.emit_raw("  %i_next = add i64 %i, 1");
+        self.emit_raw("  br label %loop");
+        self.emit_raw("");
+        self.emit_raw("done:");
+        self.emit_raw("  ret i64 %hash");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// 键是否相等：整数键比较数值，字符串键比较内容（null 只等于 null）
+    fn emit_map_key_eq_runtime(&mut self) {
+        self.emit_raw("define i1 @__cay_map_key_eq(i64* %table, i64 %a, i64 %b) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %same = icmp eq i64 %a, %b");
+        self.emit_raw("  br i1 %same, label %equal, label %check_string");
+        self.emit_raw("");
+        self.emit_raw("check_string:");
+        self.emit_raw("  %flag = load i64, i64* %table");
+        self.emit_raw("  %is_string = icmp ne i64 %flag, 0");
+        self.emit_raw("  br i1 %is_string, label %check_null, label %different");
+        self.emit_raw("");
+        self.emit_raw("check_null:");
+        self.emit_raw("  %str_a = inttoptr i64 %a to i8*");
+        self.emit_raw("  %str_b = inttoptr i64 %b to i8*");
+        self.emit_raw("  %a_null = icmp eq i8* %str_a, null");
+        self.emit_raw("  %b_null = icmp eq i8* %str_b, null");
+        self.emit_raw("  %any_null = or i1 %a_null, %b_null");
+        self.emit_raw("  br i1 %any_null, label %different, label %compare");
+        self.emit_raw("");
+        self.emit_raw("compare:");
+        self.emit_raw("  %cmp = call i32 @strcmp(i8* %str_a, i8* %str_b)");
+        self.emit_raw("  %eq = icmp eq i32 %cmp, 0");
+        self.emit_raw("  ret i1 %eq");
+        self.emit_raw("");
+        self.emit_raw("equal:");
+        self.emit_raw("  ret i1 true");
+        self.emit_raw("");
+        self.emit_raw("different:");
+        self.emit_raw("  ret i1 false");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// 探测键所在的索引槽：返回存放该键的槽，键不存在时返回探测到的第一个空槽
+    fn emit_map_find_runtime(&mut self) {
+        self.emit_raw("define i64 @__cay_map_find(i64* %table, i64 %key) {");
+        self.emit_raw("entry:");
+        self.emit_map_header_field("cap_field", 3);
+        self.emit_raw("  %cap = load i64, i64* %cap_field");
+        self.emit_raw("  %index_size = shl i64 %cap, 1");
+        self.emit_raw("  %mask = sub i64 %index_size, 1");
+        self.emit_map_header_array("index", 4, "i64");
+        self.emit_map_header_array("keys", 5, "i64");
+        self.emit_map_header_array("alive", 7, "i8");
+        self.emit_raw("  %hash = call i64 @__cay_map_hash(i64* %table, i64 %key)");
+        self.emit_raw("  %start = and i64 %hash, %mask");
+        self.emit_raw("  br label %probe");
+        self.emit_raw("");
+        self.emit_raw("probe:");
+        self.emit_raw("  %pos = phi i64 [ %start, %entry ], [ %pos_next, %next ]");
+        self.emit_raw("  %slot_ptr = getelementptr i64, i64* %index, i64 %pos");
+        self.emit_raw("  %slot = load i64, i64* %slot_ptr");
+        self.emit_raw("  %empty = icmp eq i64 %slot, 0");
+        self.emit_raw("  br i1 %empty, label %found, label %check_alive");
+        self.emit_raw("");
+        self.emit_raw("check_alive:");
+        self.emit_raw("  %entry_index = sub i64 %slot, 1");
+        self.emit_raw("  %alive_ptr = getelementptr i8, i8* %alive, i64 %entry_index");
+        self.emit_raw("  %alive_flag = load i8, i8* %alive_ptr");
+        self.emit_raw("  %is_alive = icmp ne i8 %alive_flag, 0");
+        self.emit_raw("  br i1 %is_alive, label %compare, label %next");
+        self.emit_raw("");
+        self.emit_raw("compare:");
+        self.emit_raw("  %key_ptr = getelementptr i64, i64* %keys, i64 %entry_index");
+        self.emit_raw("  %stored = load i64, i64* %key_ptr");
+        self.emit_raw("  %eq = call i1 @__cay_map_key_eq(i64* %table, i64 %stored, i64 %key)");
+        self.emit_raw("  br i1 %eq, label %found, label %next");
+        self.emit_raw("");
+        self.emit_raw("next:");
+        self.emit_raw("  %pos_inc = add i64 %pos, 1");
+        self.emit_raw("  %pos_next = and i64 %pos_inc, %mask");
+        self.emit_raw("  br label %probe");
+        self.emit_raw("");
+        self.emit_raw("found:");
+        self.emit_raw("  ret i64 %pos");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// 键所在的条目下标，键不存在时返回 -1
+    fn emit_map_entry_runtime(&mut self) {
+        self.emit_raw("define i64 @__cay_map_entry(i64* %table, i64 %key) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %pos = call i64 @__cay_map_find(i64* %table, i64 %key)");
+        self.emit_map_header_array("index", 4, "i64");
+        self.emit_raw("  %slot_ptr = getelementptr i64, i64* %index, i64 %pos");
+        self.emit_raw("  %slot = load i64, i64* %slot_ptr");
+        self.emit_raw("  ; 空槽为 0，结果为 -1");
+        self.emit_raw("  %entry_index = sub i64 %slot, 1");
+        self.emit_raw("  ret i64 %entry_index");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// 在下一个条目槽写入键值对，并登记到索引槽 `pos`（调用方保证还有空闲条目槽）
+    fn emit_map_insert_entry_runtime(&mut self) {
+        self.emit_raw("define void @__cay_map_insert_entry(i64* %table, i64 %pos, i64 %key, i64 %value) {");
+        self.emit_raw("entry:");
+        self.emit_map_header_field("used_field", 2);
+        self.emit_raw("  %used = load i64, i64* %used_field");
+        self.emit_map_header_array("index", 4, "i64");
+        self.emit_map_header_array("keys", 5, "i64");
+        self.emit_map_header_array("values", 6, "i64");
+        self.emit_map_header_array("alive", 7, "i8");
+        self.emit_raw("  %key_ptr = getelementptr i64, i64* %keys, i64 %used");
+        self.emit_raw("  store i64 %key, i64* %key_ptr");
+        self.emit_raw("  %value_ptr = getelementptr i64, i64* %values, i64 %used");
+        self.emit_raw("  store i64 %value, i64* %value_ptr");
+        self.emit_raw("  %alive_ptr = getelementptr i8, i8* %alive, i64 %used");
+        self.emit_raw("  store i8 1, i8* %alive_ptr");
+        self.emit_raw("  %used_next = add i64 %used, 1");
+        self.emit_raw("  %slot_ptr = getelementptr i64, i64* %index, i64 %pos");
+        self.emit_raw("  store i64 %used_next, i64* %slot_ptr");
+        self.emit_raw("  store i64 %used_next, i64* %used_field");
+        self.emit_raw("  ret void");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// 重建哈希表：容量取存活条目数的两倍（至少 8），按原顺序重新插入存活的条目
+    fn emit_map_rebuild_runtime(&mut self) {
+        self.emit_raw("define void @__cay_map_rebuild(i64* %table) {");
+        self.emit_raw("entry:");
+        self.emit_map_header_field("used_field", 2);
+        self.emit_raw("  %old_used = load i64, i64* %used_field");
+        self.emit_map_header_array("old_keys", 5, "i64");
+        self.emit_map_header_array("old_values", 6, "i64");
+        self.emit_map_header_array("old_alive", 7, "i8");
+        self.emit_map_header_field("count_field", 1);
+        self.emit_raw("  %count = load i64, i64* %count_field");
+        self.emit_raw("  %doubled = shl i64 %count, 1");
+        self.emit_raw("  %small = icmp ult i64 %doubled, 8");
+        self.emit_raw("  %new_cap = select i1 %small, i64 8, i64 %doubled");
+        self.emit_raw("  call void @__cay_map_reserve(i64* %table, i64 %new_cap)");
+        self.emit_raw("  br label %loop");
+        self.emit_raw("");
+        self.emit_raw("loop:");
+        self.emit_raw("  %i = phi i64 [ 0, %entry ], [ %i_next, %next ]");
+        self.emit_raw("  %done = icmp eq i64 %i, %old_used");
+        self.emit_raw("  br i1 %done, label %end, label %check");
+        self.emit_raw("");
+        self.emit_raw("check:");
+        self.emit_raw("  %alive_ptr = getelementptr i8, i8* %old_alive, i64 %i");
+        self.emit_raw("  %alive_flag = load i8, i8* %alive_ptr");
+        self.emit_raw("  %is_alive = icmp ne i8 %alive_flag, 0");
+        self.emit_raw("  br i1 %is_alive, label %copy, label %next");
+        self.emit_raw("");
+        self.emit_raw("copy:");
+        self.emit_raw("  %key_ptr = getelementptr i64, i64* %old_keys, i64 %i");
+        self.emit_raw("  %key = load i64, i64* %key_ptr");
+        self.emit_raw("  %value_ptr = getelementptr i64, i64* %old_values, i64 %i");
+        self.emit_raw("  %value = load i64, i64* %value_ptr");
+        self.emit_raw("  %pos = call i64 @__cay_map_find(i64* %table, i64 %key)");
+        self.emit_raw("  call void @__cay_map_insert_entry(i64* %table, i64 %pos, i64 %key, i64 %value)");
+        self.emit_raw("  br label %next");
+        self.emit_raw("");
+        self.emit_raw("next:");
+        self.emit_raw("  %i_next = add i64 %i, 1");
+        self.emit_raw("  br label %loop");
+        self.emit_raw("");
+        self.emit_raw("end:");
+        self.emit_raw("  ret void");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// Map.put：键已存在时替换值，否则追加条目（条目槽用完时先重建）
+    fn emit_map_put_runtime(&mut self) {
+        self.emit_raw("define void @__cay_map_put(i8* %map, i64 %key, i64 %value) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %table = bitcast i8* %map to i64*");
+        self.emit_raw("  %entry_index = call i64 @__cay_map_entry(i64* %table, i64 %key)");
+        self.emit_raw("  %present = icmp sge i64 %entry_index, 0");
+        self.emit_raw("  br i1 %present, label %update, label %check_space");
+        self.emit_raw("");
+        self.emit_raw("update:");
+        self.emit_map_header_array("values", 6, "i64");
+        self.emit_raw("  %value_ptr = getelementptr i64, i64* %values, i64 %entry_index");
+        self.emit_raw("  store i64 %value, i64* %value_ptr");
+        self.emit_raw("  ret void");
+        self.emit_raw("");
+        self.emit_raw("check_space:");
+        self.emit_map_header_field("used_field", 2);
+        self.emit_raw("  %used = load i64, i64* %used_field");
+        self.emit_map_header_field("cap_field", 3);
+        self.emit_raw("  %cap = load i64, i64* %cap_field");
+        self.emit_raw("  %full = icmp eq i64 %used, %cap");
+        self.emit_raw("  br i1 %full, label %grow, label %insert");
+        self.emit_raw("");
+        self.emit_raw("grow:");
+        self.emit_raw("  call void @__cay_map_rebuild(i64* %table)");
+        self.emit_raw("  br label %insert");
+        self.emit_raw("");
+        self.emit_raw("insert:");
+        self.emit_raw("  %pos = call i64 @__cay_map_find(i64* %table, i64 %key)");
+        self.emit_raw("  call void @__cay_map_insert_entry(i64* %table, i64 %pos, i64 %key, i64 %value)");
+        self.emit_map_header_field("count_field", 1);
+        self.emit_raw("  %count = load i64, i64* %count_field");
+        self.emit_raw("  %count_next = add i64 %count, 1");
+        self.emit_raw("  store i64 %count_next, i64* %count_field");
+        self.emit_raw("  ret void");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// Map.get：键不存在时返回 `default`
+    fn emit_map_get_runtime(&mut self) {
+        self.emit_raw("define i64 @__cay_map_get(i8* %map, i64 %key, i64 %default) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %table = bitcast i8* %map to i64*");
+        self.emit_raw("  %entry_index = call i64 @__cay_map_entry(i64* %table, i64 %key)");
+        self.emit_raw("  %missing = icmp slt i64 %entry_index, 0");
+        self.emit_raw("  br i1 %missing, label %absent, label %present");
+        self.emit_raw("");
+        self.emit_raw("absent:");
+        self.emit_raw("  ret i64 %default");
+        self.emit_raw("");
+        self.emit_raw("present:");
+        self.emit_map_header_array("values", 6, "i64");
+        self.emit_raw("  %value_ptr = getelementptr i64, i64* %values, i64 %entry_index");
+        self.emit_raw("  %value = load i64, i64* %value_ptr");
+        self.emit_raw("  ret i64 %value");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// Map.containsKey
+    fn emit_map_contains_runtime(&mut self) {
+        self.emit_raw("define i1 @__cay_map_contains(i8* %map, i64 %key) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %table = bitcast i8* %map to i64*");
+        self.emit_raw("  %entry_index = call i64 @__cay_map_entry(i64* %table, i64 %key)");
+        self.emit_raw("  %present = icmp sge i64 %entry_index, 0");
+        self.emit_raw("  ret i1 %present");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// Map.remove：清除条目的存活标记，键存在时返回 true
+    fn emit_map_remove_runtime(&mut self) {
+        self.emit_raw("define i1 @__cay_map_remove(i8* %map, i64 %key) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %table = bitcast i8* %map to i64*");
+        self.emit_raw("  %entry_index = call i64 @__cay_map_entry(i64* %table, i64 %key)");
+        self.emit_raw("  %missing = icmp slt i64 %entry_index, 0");
+        self.emit_raw("  br i1 %missing, label %absent, label %present");
+        self.emit_raw("");
+        self.emit_raw("absent:");
+        self.emit_raw("  ret i1 false");
+        self.emit_raw("");
+        self.emit_raw("present:");
+        self.emit_map_header_array("alive", 7, "i8");
+        self.emit_raw("  %alive_ptr = getelementptr i8, i8* %alive, i64 %entry_index");
+        self.emit_raw("  store i8 0, i8* %alive_ptr");
+        self.emit_map_header_field("count_field", 1);
+        self.emit_raw("  %count = load i64, i64* %count_field");
+        self.emit_raw("  %count_next = sub i64 %count, 1");
+        self.emit_raw("  store i64 %count_next, i64* %count_field");
+        self.emit_raw("  ret i1 true");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// Map.size
+    fn emit_map_size_runtime(&mut self) {
+        self.emit_raw("define i32 @__cay_map_size(i8* %map) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %table = bitcast i8* %map to i64*");
+        self.emit_map_header_field("count_field", 1);
+        self.emit_raw("  %count = load i64, i64* %count_field");
+        self.emit_raw("  %size = trunc i64 %count to i32");
+        self.emit_raw("  ret i32 %size");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// Map.keys：按插入顺序把存活的键复制到新数组（int 键为 i32 元素，String 键为指针元素），
+    /// 返回指向元素 0 的指针
+    fn emit_map_keys_runtime(&mut self) {
+        self.emit_raw("define i8* @__cay_map_keys(i8* %map) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %table = bitcast i8* %map to i64*");
+        self.emit_raw("  %flag = load i64, i64* %table");
+        self.emit_raw("  %is_string = icmp ne i64 %flag, 0");
+        self.emit_map_header_field("count_field", 1);
+        self.emit_raw("  %count = load i64, i64* %count_field");
+        self.emit_map_header_field("used_field", 2);
+        self.emit_raw("  %used = load i64, i64* %used_field");
+        self.emit_map_header_array("keys", 5, "i64");
+        self.emit_map_header_array("alive", 7, "i8");
+        self.emit_raw("  %elem_size = select i1 %is_string, i64 8, i64 4");
+        self.emit_raw("  %data_bytes = mul i64 %count, %elem_size");
+        self.emit_raw("  %total_bytes = add i64 %data_bytes, 8");
+        self.emit_raw("  %raw = call i8* @calloc(i64 1, i64 %total_bytes)");
+        self.emit_raw("  %len_ptr = bitcast i8* %raw to i32*");
+        self.emit_raw("  %len = trunc i64 %count to i32");
+        self.emit_raw("  store i32 %len, i32* %len_ptr");
+        self.emit_raw("  %data = getelementptr i8, i8* %raw, i64 8");
+        self.emit_raw("  %wide_data = bitcast i8* %data to i64*");
+        self.emit_raw("  %int_data = bitcast i8* %data to i32*");
+        self.emit_raw("  br label %loop");
+        self.emit_raw("");
+        self.emit_raw("loop:");
+        self.emit_raw("  %i = phi i64 [ 0, %entry ], [ %i_next, %next ]");
+        self.emit_raw("  %n = phi i64 [ 0, %entry ], [ %n_next, %next ]");
+        self.emit_raw("  %done = icmp eq i64 %i, %used");
+        self.emit_raw("  br i1 %done, label %end, label %check");
+        self.emit_raw("");
+        self.emit_raw("check:");
+        self.emit_raw("  %alive_ptr = getelementptr i8, i8* %alive, i64 %i");
+        self.emit_raw("  %alive_flag = load i8, i8* %alive_ptr");
+        self.emit_raw("  %is_alive = icmp ne i8 %alive_flag, 0");
+        self.emit_raw("  br i1 %is_alive, label %copy, label %next");
+        self.emit_raw("");
+        self.emit_raw("copy:");
+        self.emit_raw("  %key_ptr = getelementptr i64, i64* %keys, i64 %i");
+        self.emit_raw("  %key = load i64, i64* %key_ptr");
+        self.emit_raw("  br i1 %is_string, label %store_wide, label %store_int");
+        self.emit_raw("");
+        self.emit_raw("store_wide:");
+        self.emit_raw("  %wide_ptr = getelementptr i64, i64* %wide_data, i64 %n");
+        self.emit_raw("  store i64 %key, i64* %wide_ptr");
+        self.emit_raw("  br label %copied");
+        self.emit_raw("");
+        self.emit_raw("store_int:");
+        self.emit_raw("  %int_ptr = getelementptr i32, i32* %int_data, i64 %n");
+        self.emit_raw("  %int_key = trunc i64 %key to i32");
+        self.emit_raw("  store i32 %int_key, i32* %int_ptr");
+        self.emit_raw("  br label %copied");
+        self.emit_raw("");
+        self.emit_raw("copied:");
+        self.emit_raw("  %n_inc = add i64 %n, 1");
+        self.emit_raw("  br label %next");
+        self.emit_raw("");
+        self.emit_raw("next:");
+        self.emit_raw("  %n_next = phi i64 [ %n, %check ], [ %n_inc, %copied ]");
+        self.emit_raw("  %i_next = add i64 %i, 1");
+        self.emit_raw("  br label %loop");
+        self.emit_raw("");
+        self.emit_raw("end:");
+        self.emit_raw("  ret i8* %data");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+}
@@ -0,0 +1,42 @@
+//! Math 运行时函数
+
+use crate::codegen::context::IRGenerator;
+
+impl IRGenerator {
+    /// 生成 Math.random 运行时函数
+    ///
+    /// 使用 xorshift64* 生成器，状态首次使用时以当前时间播种，返回 [0, 1) 内的 double。
+    pub(super) fn emit_math_runtime(&mut self) {
+        self.emit_raw("@.cay_random_state = internal global i64 0, align 8");
+        self.emit_raw("");
+        self.emit_raw("define double @__cay_math_random() {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %state = load i64, i64* @.cay_random_state, align 8");
+        self.emit_raw("  %unseeded = icmp eq i64 %state, 0");
+        self.emit_raw("  br i1 %unseeded, label %seed, label %next");
+        self.emit_raw("");
+        self.emit_raw("seed:");
+        self.emit_raw("  %now = call i64 @time(i64* null)");
+        self.emit_raw("  ; 与黄金分割常数异或，保证种子非零");
+        self.emit_raw("  %seed_value = xor i64 %now, -7046029254386353131");
+        self.emit_raw("  br label %next");
+        self.emit_raw("");
+        self.emit_raw("next:");
+        self.emit_raw("  %x0 = phi i64 [ %state, %entry ], [ %seed_value, %seed ]");
+        self.emit_raw("  %s1 = lshr i64 %x0, 12");
+        self.emit_raw("  %x1 = xor i64 %x0, %s1");
+        self.emit_raw("  %s2 = shl i64 %x1, 25");
+        self.emit_raw("  %x2 = xor i64 %x1, %s2");
+        self.emit_raw("  %s3 = lshr i64 %x2, 27");
+        self.emit_raw("  %x3 = xor i64 %x2, %s3");
+        self.emit_raw("  store i64 %x3, i64* @.cay_random_state, align 8");
+        self.emit_raw("  %scrambled = mul i64 %x3, 2685821657736338717");
+        self.emit_raw("  ; 取高 53 位转换为 [0, 1) 内的 double");
+        self.emit_raw("  %bits = lshr i64 %scrambled, 11");
+        self.emit_raw("  %as_double = uitofp i64 %bits to double");
+        self.emit_raw("  %result = fmul double %as_double, 0x3CA0000000000000");
+        self.emit_raw("  ret double %result");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+}
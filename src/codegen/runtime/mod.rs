@@ -2,6 +2,14 @@
 //!
 //! 本模块包含所有 cay 运行时支持函数的 LLVM IR 生成。
 //! 每个运行时函数都有独立的子模块。
+//!
+//! 运行时随编译器二进制一起发布，默认内联到每个生成的模块中；
+//! 也可以通过 `external_runtime` 选项只生成声明，并单独生成运行时模块
+//! （`generate_runtime_module`）在链接时一起交给 clang。
+//! 两种方式使用同一份运行时代码，不会与编译器版本脱节。
+//!
+//! 字符串操作、输入、文件读写和错误报告另有原生实现（仓库中的 `runtime/` 静态库，
+//! 由 ir2exe 自动链接）。独立运行时模块不再包含这些函数的定义，见 [`NATIVE_RUNTIME_FUNCTIONS`]。
 
 use crate::codegen::context::IRGenerator;
 
@@ -11,34 +19,78 @@ mod float_to_string;
 mod int_to_string;
 mod bool_to_string;
 mod char_to_string;
+mod object_to_string;
+pub(crate) mod reflect;
 mod string_length;
 mod string_substring;
 mod string_indexof;
 mod string_charat;
 mod string_replace;
+mod string_equals;
+mod string_case;
+mod string_trim;
+mod string_affix;
+mod string_split;
+mod string_builder;
+mod array_io;
+mod input;
+mod file;
+mod map;
+mod math;
+mod error;
+
+/// 运行时区段标记，字符串常量等模块级声明插入在该标记之前
+pub(crate) const RUNTIME_SECTION_MARKER: &str = "; ---- cay runtime ----";
+
+/// 运行时 ABI 版本
+///
+/// 运行时函数的签名或语义发生不兼容变化时递增。
+pub const RUNTIME_ABI_VERSION: u32 = 4;
+
+/// 由原生运行时库（`runtime/`，`libcavvy_runtime.a`）提供的运行时函数
+///
+/// 独立运行时模块只保留这些函数的声明，定义在链接时由静态库提供；内联模式不受影响。
+pub const NATIVE_RUNTIME_FUNCTIONS: &[&str] = &[
+    "__cay_runtime_error",
+    "__cay_string_concat",
+    "__cay_string_length",
+    "__cay_string_equals",
+    "__cay_string_substring",
+    "__cay_string_indexof",
+    "__cay_string_contains",
+    "__cay_string_starts_with",
+    "__cay_string_ends_with",
+    "__cay_string_charat",
+    "__cay_string_replace",
+    "__cay_string_trim",
+    "__cay_string_to_upper",
+    "__cay_string_to_lower",
+    "__cay_int_to_string",
+    "__cay_bool_to_string",
+    "__cay_char_to_string",
+    "__cay_utf8_encode",
+    "__cay_utf8_decode",
+    "__cay_read_line",
+    "__cay_read_bool",
+    "__cay_read_char",
+    "__cay_read_utf8_tail",
+    "__cay_file_read_all",
+    "__cay_file_write_all",
+    "__cay_file_append_line",
+    "__cay_file_exists",
+    "__cay_file_delete",
+];
 
 impl IRGenerator {
     /// 发射IR头部（外部声明和运行时函数）
     pub fn emit_header(&mut self) {
         self.emit_raw("; cay (Ethernos Object Language) Generated LLVM IR");
+        self.emit_raw(&format!("; runtime ABI version: {}", RUNTIME_ABI_VERSION));
         
         // 根据目标平台设置目标三元组
-        let target_triple = if let Some(config) = &self.platform_config {
-            match config.target_os.as_str() {
-                "windows" => "x86_64-w64-mingw32",
-                "linux" => "x86_64-unknown-linux-gnu",
-                "macos" => "x86_64-apple-darwin",
-                _ => "x86_64-unknown-linux-gnu"
-            }
-        } else if cfg!(target_os = "windows") {
-            "x86_64-w64-mingw32"
-        } else if cfg!(target_os = "linux") {
-            "x86_64-unknown-linux-gnu"
-        } else if cfg!(target_os = "macos") {
-            "x86_64-apple-darwin"
-        } else {
-            "x86_64-unknown-linux-gnu"
-        };
+        let target_triple = self.get_target_triple();
+        let datalayout = self.data_layout.as_str().to_string();
+        self.emit_raw(&format!("target datalayout = \"{}\"", datalayout));
         self.emit_raw(&format!("target triple = \"{}\"", target_triple));
         self.emit_raw("");
 
@@ -101,31 +153,227 @@ impl IRGenerator {
             self.emit_raw(&platform_declarations);
         }
         
+        self.emit_runtime_support_declarations();
+
+        // 递归深度保护的调用深度计数器
+        if self.stack_guard_depth().is_some() {
+            self.emit_raw("@.cay_call_depth = internal global i32 0, align 4");
+            self.emit_raw("");
+        }
+        self.emit_execution_limit_globals();
+
+        // 生成运行时函数（或仅生成声明，由外部运行时模块提供定义）
+        self.emit_raw(RUNTIME_SECTION_MARKER);
+        if self.uses_external_runtime() {
+            self.emit_runtime_function_declarations();
+        } else {
+            self.emit_inline_runtime_functions();
+        }
+    }
+
+    /// 发射内联到当前模块的运行时函数
+    ///
+    /// 使用 linkonce_odr 链接属性，多个分别编译的模块各自内联运行时后仍可链接在一起，
+    /// 未被使用的运行时函数也可以被优化器删除。
+    fn emit_inline_runtime_functions(&mut self) {
+        let saved = std::mem::take(&mut self.output);
+        self.emit_runtime_functions();
+        let definitions = std::mem::replace(&mut self.output, saved);
+
+        for line in definitions.lines() {
+            match line.strip_prefix("define ") {
+                Some(rest) => self.emit_raw(&format!("define linkonce_odr {}", rest)),
+                None => self.emit_raw(line),
+            }
+        }
+    }
+
+    /// 发射运行时函数依赖的 C 库声明和公共常量
+    fn emit_runtime_support_declarations(&mut self) {
         self.emit_raw("declare i64 @strlen(i8*)");
+        self.emit_raw("declare i32 @strcmp(i8*, i8*)");
         self.emit_raw("declare i8* @calloc(i64, i64)");
         self.emit_raw("declare void @exit(i32)");
         self.emit_raw("declare void @llvm.memcpy.p0i8.p0i8.i64(i8* noalias nocapture writeonly, i8* noalias nocapture readonly, i64, i1 immarg)");
         self.emit_raw("declare i32 @snprintf(i8*, i64, i8*, ...)");
-        self.emit_raw("@.str.float_fmt = private unnamed_addr constant [3 x i8] c\"%f\\00\", align 1");
+        self.emit_raw("declare i32 @putchar(i32)");
+        self.emit_raw("declare i32 @getchar()");
+        self.emit_raw("declare i64 @time(i64*)");
+        self.emit_raw("declare double @tan(double)");
+        self.emit_raw("declare i8* @fopen(i8*, i8*)");
+        self.emit_raw("declare i32 @fclose(i8*)");
+        self.emit_raw("declare i64 @fread(i8*, i64, i64, i8*)");
+        self.emit_raw("declare i32 @fputs(i8*, i8*)");
+        self.emit_raw("declare i32 @fputc(i32, i8*)");
+        self.emit_raw("declare i32 @remove(i8*)");
+        self.emit_raw("declare double @strtod(i8*, i8**)");
+        self.emit_raw("declare i8* @strchr(i8*, i32)");
+        self.emit_raw("declare i32 @atoi(i8*)");
+        for name in ["sqrt", "sin", "cos", "exp", "log", "floor", "ceil", "fabs"] {
+            self.emit_raw(&format!("declare double @llvm.{}.f64(double)", name));
+        }
+        self.emit_raw("declare double @llvm.pow.f64(double, double)");
+        self.emit_raw("declare float @llvm.fabs.f32(float)");
+        for name in ["minnum", "maxnum"] {
+            self.emit_raw(&format!("declare float @llvm.{}.f32(float, float)", name));
+            self.emit_raw(&format!("declare double @llvm.{}.f64(double, double)", name));
+        }
+        for ty in ["i32", "i64"] {
+            self.emit_raw(&format!("declare {0} @llvm.ctpop.{0}({0})", ty));
+            self.emit_raw(&format!("declare {0} @llvm.ctlz.{0}({0}, i1 immarg)", ty));
+            self.emit_raw(&format!("declare {0} @llvm.cttz.{0}({0}, i1 immarg)", ty));
+            self.emit_raw(&format!("declare {0} @llvm.fshl.{0}({0}, {0}, {0})", ty));
+            self.emit_raw(&format!("declare {0} @llvm.fshr.{0}({0}, {0}, {0})", ty));
+        }
+        self.emit_raw("@.str.float_fmt = private unnamed_addr constant [5 x i8] c\"%.*f\\00\", align 1");
+        self.emit_raw("@.str.float_exp_fmt = private unnamed_addr constant [5 x i8] c\"%.*e\\00\", align 1");
         self.emit_raw("@.str.int_fmt = private unnamed_addr constant [5 x i8] c\"%lld\\00\", align 1");
         self.emit_raw("@.str.true_str = private unnamed_addr constant [5 x i8] c\"true\\00\", align 1");
         self.emit_raw("@.str.false_str = private unnamed_addr constant [6 x i8] c\"false\\00\", align 1");
+        self.emit_raw("@.str.word_scan_fmt = private unnamed_addr constant [4 x i8] c\"%7s\\00\", align 1");
+        for (name, mode) in [("read", "rb"), ("write", "wb"), ("append", "ab")] {
+            self.emit_raw(&format!("@.str.file_mode_{} = private unnamed_addr constant [3 x i8] c\"{}\\00\", align 1", name, mode));
+        }
+        self.emit_raw("@.str.null_str = private unnamed_addr constant [5 x i8] c\"null\\00\", align 1");
+        self.emit_raw("@.str.object_fmt = private unnamed_addr constant [8 x i8] c\"%s@%llx\\00\", align 1");
+        self.emit_raw("@.str.object_name = private unnamed_addr constant [7 x i8] c\"Object\\00\", align 1");
+        self.emit_raw("@.str.function_str = private unnamed_addr constant [11 x i8] c\"<function>\\00\", align 1");
+        self.emit_raw("@.str.open_brace = private unnamed_addr constant [2 x i8] c\"{\\00\", align 1");
+        self.emit_raw("@.str.close_brace = private unnamed_addr constant [2 x i8] c\"}\\00\", align 1");
+        self.emit_raw("@.str.open_bracket = private unnamed_addr constant [2 x i8] c\"[\\00\", align 1");
+        self.emit_raw("@.str.close_bracket = private unnamed_addr constant [2 x i8] c\"]\\00\", align 1");
+        self.emit_raw("@.str.equals_sign = private unnamed_addr constant [2 x i8] c\"=\\00\", align 1");
+        self.emit_raw("@.str.list_sep = private unnamed_addr constant [3 x i8] c\", \\00\", align 1");
         self.emit_raw("");
 
         // 空字符串常量（用于 null 安全）
         self.emit_raw("@.cay_empty_str = private unnamed_addr constant [1 x i8] c\"\\00\", align 1");
         self.emit_raw("");
+    }
 
-        // 生成运行时函数
+    /// 发射所有运行时函数的定义
+    fn emit_runtime_functions(&mut self) {
+        self.emit_runtime_error_runtime();
         self.emit_string_concat_runtime();
         self.emit_float_to_string_runtime();
         self.emit_int_to_string_runtime();
         self.emit_bool_to_string_runtime();
         self.emit_char_to_string_runtime();
+        self.emit_object_to_string_runtime();
+        self.emit_reflect_runtime();
         self.emit_string_length_runtime();
         self.emit_string_substring_runtime();
         self.emit_string_indexof_runtime();
         self.emit_string_charat_runtime();
         self.emit_string_replace_runtime();
+        self.emit_string_equals_runtime();
+        self.emit_string_case_runtime();
+        self.emit_string_trim_runtime();
+        self.emit_string_affix_runtime();
+        self.emit_string_split_runtime();
+        self.emit_string_builder_runtime();
+        self.emit_array_io_runtime();
+        self.emit_input_runtime();
+        self.emit_file_runtime();
+        self.emit_map_runtime();
+        self.emit_math_runtime();
+    }
+
+    /// 根据目标平台获取目标三元组
+    pub(crate) fn get_target_triple(&self) -> &'static str {
+        if let Some(config) = &self.platform_config {
+            match config.target_os.as_str() {
+                "windows" => "x86_64-w64-mingw32",
+                "linux" => "x86_64-unknown-linux-gnu",
+                "macos" => "x86_64-apple-darwin",
+                _ => "x86_64-unknown-linux-gnu"
+            }
+        } else if cfg!(target_os = "windows") {
+            "x86_64-w64-mingw32"
+        } else if cfg!(target_os = "linux") {
+            "x86_64-unknown-linux-gnu"
+        } else if cfg!(target_os = "macos") {
+            "x86_64-apple-darwin"
+        } else {
+            "x86_64-unknown-linux-gnu"
+        }
+    }
+
+    /// 是否使用外部运行时模块
+    fn uses_external_runtime(&self) -> bool {
+        self.platform_config.as_ref().is_some_and(|c| c.external_runtime)
     }
-}
\ No newline at end of file
+
+    /// 发射所有运行时函数的声明
+    ///
+    /// 声明由运行时函数定义自动推导，保证与 `generate_runtime_module` 的输出一致。
+    fn emit_runtime_function_declarations(&mut self) {
+        let saved = std::mem::take(&mut self.output);
+        self.emit_runtime_functions();
+        let definitions = std::mem::replace(&mut self.output, saved);
+
+        for line in definitions.lines() {
+            if let Some(decl) = definition_to_declaration(line) {
+                self.emit_raw(&decl);
+            }
+        }
+        self.emit_raw("");
+    }
+
+    /// 生成独立的运行时模块
+    ///
+    /// 与 `external_runtime` 选项配合使用，生成的模块在链接时与程序模块、原生运行时库一起交给 clang。
+    /// 原生运行时库提供的函数（[`NATIVE_RUNTIME_FUNCTIONS`]）只生成声明。
+    pub fn generate_runtime_module(&mut self) -> String {
+        self.output.clear();
+        self.emit_raw("; cay (Ethernos Object Language) Runtime Module");
+        self.emit_raw(&format!("; runtime ABI version: {}", RUNTIME_ABI_VERSION));
+        let target_triple = self.get_target_triple();
+        let datalayout = self.data_layout.as_str().to_string();
+        self.emit_raw(&format!("target datalayout = \"{}\"", datalayout));
+        self.emit_raw(&format!("target triple = \"{}\"", target_triple));
+        self.emit_raw("");
+        self.emit_raw("declare i32 @printf(i8*, ...)");
+        self.emit_raw("declare i32 @scanf(i8*, ...)");
+        self.emit_runtime_support_declarations();
+
+        // 递归深度保护的调用深度计数器
+        if self.stack_guard_depth().is_some() {
+            self.emit_raw("@.cay_call_depth = internal global i32 0, align 4");
+            self.emit_raw("");
+        }
+
+        let saved = std::mem::take(&mut self.output);
+        self.emit_runtime_functions();
+        let definitions = std::mem::replace(&mut self.output, saved);
+
+        let mut skipping = false;
+        for line in definitions.lines() {
+            if skipping {
+                skipping = line != "}";
+                continue;
+            }
+            match definition_to_declaration(line) {
+                Some(decl) if NATIVE_RUNTIME_FUNCTIONS.iter().any(|name| decl.contains(&format!("@{}(", name))) => {
+                    self.emit_raw(&decl);
+                    skipping = true;
+                }
+                _ => self.emit_raw(line),
+            }
+        }
+        self.output.clone()
+    }
+}
+
+/// 将运行时函数的定义行（`define <ret> @name(<params>) {`）转换为声明
+fn definition_to_declaration(line: &str) -> Option<String> {
+    let signature = line.strip_prefix("define ")?.trim_end().strip_suffix('{')?.trim_end();
+    let open = signature.find('(')?;
+    let close = signature.rfind(')')?;
+    let params: Vec<&str> = signature[open + 1..close]
+        .split(',')
+        .map(|p| p.split_whitespace().next().unwrap_or(""))
+        .filter(|p| !p.is_empty())
+        .collect();
+    Some(format!("declare {}({})", &signature[..open], params.join(", ")))
+}
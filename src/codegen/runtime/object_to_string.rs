@@ -0,0 +1,29 @@
+//! 对象转字符串运行时函数
+
+use crate::codegen::context::IRGenerator;
+
+impl IRGenerator {
+    /// 生成对象默认字符串表示的运行时函数
+    ///
+    /// 没有定义 `toString()` 的类按 `类名@地址` 转换，null 转换为 "null"。
+    pub(super) fn emit_object_to_string_runtime(&mut self) {
+        self.emit_raw("define i8* @__cay_object_to_string(i8* %obj, i8* %class_name) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %is_null = icmp eq i8* %obj, null");
+        self.emit_raw("  br i1 %is_null, label %null_case, label %format");
+        self.emit_raw("");
+        self.emit_raw("null_case:");
+        self.emit_raw("  ret i8* getelementptr ([5 x i8], [5 x i8]* @.str.null_str, i64 0, i64 0)");
+        self.emit_raw("");
+        self.emit_raw("format:");
+        self.emit_raw("  ; 缓冲区：类名 + '@' + 16 位十六进制地址 + 终止符");
+        self.emit_raw("  %name_len = call i64 @strlen(i8* %class_name)");
+        self.emit_raw("  %buf_size = add i64 %name_len, 18");
+        self.emit_raw("  %buf = call i8* @calloc(i64 1, i64 %buf_size)");
+        self.emit_raw("  %address = ptrtoint i8* %obj to i64");
+        self.emit_raw("  call i32 (i8*, i64, i8*, ...) @snprintf(i8* %buf, i64 %buf_size, i8* getelementptr ([8 x i8], [8 x i8]* @.str.object_fmt, i64 0, i64 0), i8* %class_name, i64 %address)");
+        self.emit_raw("  ret i8* %buf");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+}
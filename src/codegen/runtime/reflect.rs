@@ -0,0 +1,271 @@
+//! 基于反射元数据的对象和数组转字符串运行时函数
+//!
+//! 元数据由代码生成按模块输出（见 `codegen::reflect`），以参数形式传入，运行时本身不引用任何类：
+//! - 类元数据 `{ i8* 类名, i8* toString 函数, i64 字段数, i8* 字段数组 }`；
+//! - 字段元数据 `{ i8* 字段名, i64 偏移, i8* 类型签名 }`；
+//! - 类表 `i8**` 按类型 ID 索引类元数据（接口为 null），对象按运行时类型格式化。
+//!
+//! 类型签名：`I` int、`J` long、`F` float、`D` double、`Z` boolean、`C` char、`S` String、
+//! `L` 对象、`[` 后接元素签名表示数组，其他签名（如函数）输出 `<function>`。
+
+use crate::codegen::context::IRGenerator;
+
+/// 展开对象字段的最大嵌套层数，更深的对象（包括循环引用）按 `类名@地址` 输出
+const MAX_REFLECT_DEPTH: u32 = 4;
+
+/// 类元数据的 LLVM 类型
+pub(crate) const CLASS_INFO_TYPE: &str = "{ i8*, i8*, i64, i8* }";
+/// 字段元数据的 LLVM 类型
+pub(crate) const FIELD_INFO_TYPE: &str = "{ i8*, i64, i8* }";
+
+impl IRGenerator {
+    /// 生成反射格式化的运行时函数
+    pub(super) fn emit_reflect_runtime(&mut self) {
+        self.emit_reflect_value_runtime();
+        self.emit_reflect_object_runtime();
+        self.emit_reflect_array_runtime();
+    }
+
+    /// `__cay_reflect_value_to_string(ptr, sig, classes, class_count, depth)`：按签名读取 `ptr` 处的值并转换为字符串
+    fn emit_reflect_value_runtime(&mut self) {
+        self.emit_raw("define i8* @__cay_reflect_value_to_string(i8* %ptr, i8* %sig, i8** %classes, i32 %class_count, i32 %depth) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %kind = load i8, i8* %sig, align 1");
+        self.emit_raw("  switch i8 %kind, label %other [");
+        for (code, label) in [(b'B', "byte"), (b'H', "short"), (b'I', "int"), (b'J', "long"), (b'F', "float"), (b'D', "double"), (b'Z', "bool"),
+            (b'C', "char"), (b'S', "string"), (b'L', "object"), (b'[', "array")]
+        {
+            self.emit_raw(&format!("    i8 {}, label %{}", code, label));
+        }
+        self.emit_raw("  ]");
+        self.emit_raw("");
+        self.emit_raw("byte:");
+        self.emit_raw("  %byte_val = load i8, i8* %ptr, align 1");
+        self.emit_raw("  %byte_wide = sext i8 %byte_val to i64");
+        self.emit_raw("  %byte_str = call i8* @__cay_int_to_string(i64 %byte_wide)");
+        self.emit_raw("  ret i8* %byte_str");
+        self.emit_raw("");
+        self.emit_raw("short:");
+        self.emit_raw("  %short_ptr = bitcast i8* %ptr to i16*");
+        self.emit_raw("  %short_val = load i16, i16* %short_ptr, align 2");
+        self.emit_raw("  %short_wide = sext i16 %short_val to i64");
+        self.emit_raw("  %short_str = call i8* @__cay_int_to_string(i64 %short_wide)");
+        self.emit_raw("  ret i8* %short_str");
+        self.emit_raw("");
+        self.emit_raw("int:");
+        self.emit_raw("  %int_ptr = bitcast i8* %ptr to i32*");
+        self.emit_raw("  %int_val = load i32, i32* %int_ptr, align 4");
+        self.emit_raw("  %int_wide = sext i32 %int_val to i64");
+        self.emit_raw("  %int_str = call i8* @__cay_int_to_string(i64 %int_wide)");
+        self.emit_raw("  ret i8* %int_str");
+        self.emit_raw("");
+        self.emit_raw("long:");
+        self.emit_raw("  %long_ptr = bitcast i8* %ptr to i64*");
+        self.emit_raw("  %long_val = load i64, i64* %long_ptr, align 8");
+        self.emit_raw("  %long_str = call i8* @__cay_int_to_string(i64 %long_val)");
+        self.emit_raw("  ret i8* %long_str");
+        self.emit_raw("");
+        self.emit_raw("float:");
+        self.emit_raw("  %float_ptr = bitcast i8* %ptr to float*");
+        self.emit_raw("  %float_val = load float, float* %float_ptr, align 4");
+        self.emit_raw("  %float_wide = fpext float %float_val to double");
+        self.emit_raw("  %float_str = call i8* @__cay_double_to_string(double %float_wide, i1 1)");
+        self.emit_raw("  ret i8* %float_str");
+        self.emit_raw("");
+        self.emit_raw("double:");
+        self.emit_raw("  %double_ptr = bitcast i8* %ptr to double*");
+        self.emit_raw("  %double_val = load double, double* %double_ptr, align 8");
+        self.emit_raw("  %double_str = call i8* @__cay_double_to_string(double %double_val, i1 0)");
+        self.emit_raw("  ret i8* %double_str");
+        self.emit_raw("");
+        self.emit_raw("bool:");
+        self.emit_raw("  %bool_ptr = bitcast i8* %ptr to i1*");
+        self.emit_raw("  %bool_val = load i1, i1* %bool_ptr, align 1");
+        self.emit_raw("  %bool_str = call i8* @__cay_bool_to_string(i1 %bool_val)");
+        self.emit_raw("  ret i8* %bool_str");
+        self.emit_raw("");
+        self.emit_raw("char:");
+        self.emit_raw("  %char_ptr = bitcast i8* %ptr to i32*");
+        self.emit_raw("  %char_val = load i32, i32* %char_ptr, align 4");
+        self.emit_raw("  %char_str = call i8* @__cay_char_to_string(i32 %char_val)");
+        self.emit_raw("  ret i8* %char_str");
+        self.emit_raw("");
+        self.emit_raw("string:");
+        self.emit_raw("  %string_ptr = bitcast i8* %ptr to i8**");
+        self.emit_raw("  %string_val = load i8*, i8** %string_ptr, align 8");
+        self.emit_raw("  %string_null = icmp eq i8* %string_val, null");
+        self.emit_raw("  %string_str = select i1 %string_null, i8* getelementptr ([5 x i8], [5 x i8]* @.str.null_str, i64 0, i64 0), i8* %string_val");
+        self.emit_raw("  ret i8* %string_str");
+        self.emit_raw("");
+        self.emit_raw("object:");
+        self.emit_raw("  %object_ptr = bitcast i8* %ptr to i8**");
+        self.emit_raw("  %object_val = load i8*, i8** %object_ptr, align 8");
+        self.emit_raw("  %object_str = call i8* @__cay_reflect_object_to_string(i8* %object_val, i8** %classes, i32 %class_count, i32 %depth)");
+        self.emit_raw("  ret i8* %object_str");
+        self.emit_raw("");
+        self.emit_raw("array:");
+        self.emit_raw("  %array_ptr = bitcast i8* %ptr to i8**");
+        self.emit_raw("  %array_val = load i8*, i8** %array_ptr, align 8");
+        self.emit_raw("  %elem_sig = getelementptr i8, i8* %sig, i64 1");
+        self.emit_raw("  %array_str = call i8* @__cay_reflect_array_to_string(i8* %array_val, i8* %elem_sig, i8** %classes, i32 %class_count, i32 %depth)");
+        self.emit_raw("  ret i8* %array_str");
+        self.emit_raw("");
+        self.emit_raw("other:");
+        self.emit_raw("  ret i8* getelementptr ([11 x i8], [11 x i8]* @.str.function_str, i64 0, i64 0)");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// `__cay_reflect_object_to_string(obj, classes, class_count, depth)`：按运行时类型格式化对象
+    ///
+    /// 类定义了 `toString()` 时调用它，否则输出 `Point{x=1, y=2}`；
+    /// 类型 ID 不在类表中（如其他模块中的类）时按 `Object@地址` 输出。
+    fn emit_reflect_object_runtime(&mut self) {
+        let class_info = CLASS_INFO_TYPE;
+        let field_info = FIELD_INFO_TYPE;
+        self.emit_raw("define i8* @__cay_reflect_object_to_string(i8* %obj, i8** %classes, i32 %class_count, i32 %depth) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %is_null = icmp eq i8* %obj, null");
+        self.emit_raw("  br i1 %is_null, label %null_case, label %lookup");
+        self.emit_raw("");
+        self.emit_raw("null_case:");
+        self.emit_raw("  ret i8* getelementptr ([5 x i8], [5 x i8]* @.str.null_str, i64 0, i64 0)");
+        self.emit_raw("");
+        self.emit_raw("lookup:");
+        self.emit_raw("  ; 对象头的第一个成员是类型 ID");
+        self.emit_raw("  %type_id_ptr = bitcast i8* %obj to i32*");
+        self.emit_raw("  %type_id = load i32, i32* %type_id_ptr, align 4");
+        self.emit_raw("  %known = icmp ult i32 %type_id, %class_count");
+        self.emit_raw("  br i1 %known, label %load_info, label %unknown");
+        self.emit_raw("");
+        self.emit_raw("load_info:");
+        self.emit_raw("  %type_index = zext i32 %type_id to i64");
+        self.emit_raw("  %info_slot = getelementptr i8*, i8** %classes, i64 %type_index");
+        self.emit_raw("  %info_raw = load i8*, i8** %info_slot, align 8");
+        self.emit_raw("  %has_info = icmp ne i8* %info_raw, null");
+        self.emit_raw("  br i1 %has_info, label %described, label %unknown");
+        self.emit_raw("");
+        self.emit_raw("unknown:");
+        self.emit_raw("  %fallback = call i8* @__cay_object_to_string(i8* %obj, i8* getelementptr ([7 x i8], [7 x i8]* @.str.object_name, i64 0, i64 0))");
+        self.emit_raw("  ret i8* %fallback");
+        self.emit_raw("");
+        self.emit_raw("described:");
+        self.emit_raw(&format!("  %info = bitcast i8* %info_raw to {}*", class_info));
+        self.emit_raw(&format!("  %name_ptr = getelementptr {0}, {0}* %info, i32 0, i32 0", class_info));
+        self.emit_raw("  %name = load i8*, i8** %name_ptr, align 8");
+        self.emit_raw(&format!("  %to_string_ptr = getelementptr {0}, {0}* %info, i32 0, i32 1", class_info));
+        self.emit_raw("  %to_string_raw = load i8*, i8** %to_string_ptr, align 8");
+        self.emit_raw("  %has_to_string = icmp ne i8* %to_string_raw, null");
+        self.emit_raw("  br i1 %has_to_string, label %call_to_string, label %check_depth");
+        self.emit_raw("");
+        self.emit_raw("call_to_string:");
+        self.emit_raw("  %to_string = bitcast i8* %to_string_raw to i8* (i8*)*");
+        self.emit_raw("  %custom = call i8* %to_string(i8* %obj)");
+        self.emit_raw("  ret i8* %custom");
+        self.emit_raw("");
+        self.emit_raw("check_depth:");
+        self.emit_raw(&format!("  %too_deep = icmp uge i32 %depth, {}", MAX_REFLECT_DEPTH));
+        self.emit_raw("  br i1 %too_deep, label %shallow, label %fields");
+        self.emit_raw("");
+        self.emit_raw("shallow:");
+        self.emit_raw("  %short = call i8* @__cay_object_to_string(i8* %obj, i8* %name)");
+        self.emit_raw("  ret i8* %short");
+        self.emit_raw("");
+        self.emit_raw("fields:");
+        self.emit_raw(&format!("  %count_ptr = getelementptr {0}, {0}* %info, i32 0, i32 2", class_info));
+        self.emit_raw("  %field_count = load i64, i64* %count_ptr, align 8");
+        self.emit_raw(&format!("  %fields_ptr = getelementptr {0}, {0}* %info, i32 0, i32 3", class_info));
+        self.emit_raw("  %fields_raw = load i8*, i8** %fields_ptr, align 8");
+        self.emit_raw(&format!("  %field_table = bitcast i8* %fields_raw to {}*", field_info));
+        self.emit_raw("  %open = call i8* @__cay_string_concat(i8* %name, i8* getelementptr ([2 x i8], [2 x i8]* @.str.open_brace, i64 0, i64 0))");
+        self.emit_raw("  %next_depth = add i32 %depth, 1");
+        self.emit_raw("  br label %loop_cond");
+        self.emit_raw("");
+        self.emit_raw("loop_cond:");
+        self.emit_raw("  %i = phi i64 [ 0, %fields ], [ %i_next, %loop_body ]");
+        self.emit_raw("  %text = phi i8* [ %open, %fields ], [ %text_next, %loop_body ]");
+        self.emit_raw("  %done = icmp uge i64 %i, %field_count");
+        self.emit_raw("  br i1 %done, label %finish, label %loop_body");
+        self.emit_raw("");
+        self.emit_raw("loop_body:");
+        self.emit_raw("  %is_first = icmp eq i64 %i, 0");
+        self.emit_raw("  %separator = select i1 %is_first, i8* getelementptr ([1 x i8], [1 x i8]* @.cay_empty_str, i64 0, i64 0), i8* getelementptr ([3 x i8], [3 x i8]* @.str.list_sep, i64 0, i64 0)");
+        self.emit_raw("  %with_sep = call i8* @__cay_string_concat(i8* %text, i8* %separator)");
+        self.emit_raw(&format!("  %field_name_ptr = getelementptr {0}, {0}* %field_table, i64 %i, i32 0", field_info));
+        self.emit_raw("  %field_name = load i8*, i8** %field_name_ptr, align 8");
+        self.emit_raw("  %with_name = call i8* @__cay_string_concat(i8* %with_sep, i8* %field_name)");
+        self.emit_raw("  %with_eq = call i8* @__cay_string_concat(i8* %with_name, i8* getelementptr ([2 x i8], [2 x i8]* @.str.equals_sign, i64 0, i64 0))");
+        self.emit_raw(&format!("  %offset_ptr = getelementptr {0}, {0}* %field_table, i64 %i, i32 1", field_info));
+        self.emit_raw("  %offset = load i64, i64* %offset_ptr, align 8");
+        self.emit_raw(&format!("  %field_sig_ptr = getelementptr {0}, {0}* %field_table, i64 %i, i32 2", field_info));
+        self.emit_raw("  %field_sig = load i8*, i8** %field_sig_ptr, align 8");
+        self.emit_raw("  %field_addr = getelementptr i8, i8* %obj, i64 %offset");
+        self.emit_raw("  %value = call i8* @__cay_reflect_value_to_string(i8* %field_addr, i8* %field_sig, i8** %classes, i32 %class_count, i32 %next_depth)");
+        self.emit_raw("  %text_next = call i8* @__cay_string_concat(i8* %with_eq, i8* %value)");
+        self.emit_raw("  %i_next = add i64 %i, 1");
+        self.emit_raw("  br label %loop_cond");
+        self.emit_raw("");
+        self.emit_raw("finish:");
+        self.emit_raw("  %closed = call i8* @__cay_string_concat(i8* %text, i8* getelementptr ([2 x i8], [2 x i8]* @.str.close_brace, i64 0, i64 0))");
+        self.emit_raw("  ret i8* %closed");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// `__cay_reflect_array_to_string(arr, elem_sig, classes, class_count, depth)`：逐个元素格式化数组 `[a, b, c]`
+    ///
+    /// 元素大小由元素签名决定：boolean/byte 1 字节，short 2 字节，int/float/char 4 字节，其余 8 字节。
+    fn emit_reflect_array_runtime(&mut self) {
+        self.emit_raw("define i8* @__cay_reflect_array_to_string(i8* %arr, i8* %sig, i8** %classes, i32 %class_count, i32 %depth) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %is_null = icmp eq i8* %arr, null");
+        self.emit_raw("  br i1 %is_null, label %null_case, label %format");
+        self.emit_raw("");
+        self.emit_raw("null_case:");
+        self.emit_raw("  ret i8* getelementptr ([5 x i8], [5 x i8]* @.str.null_str, i64 0, i64 0)");
+        self.emit_raw("");
+        self.emit_raw("format:");
+        self.emit_raw("  ; 数组长度存储在数据指针前面的 8 字节中");
+        self.emit_raw("  %len_ptr_raw = getelementptr i8, i8* %arr, i64 -8");
+        self.emit_raw("  %len_ptr = bitcast i8* %len_ptr_raw to i32*");
+        self.emit_raw("  %len32 = load i32, i32* %len_ptr, align 4");
+        self.emit_raw("  %len = sext i32 %len32 to i64");
+        self.emit_raw("  %kind = load i8, i8* %sig, align 1");
+        self.emit_raw(&format!("  %is_bool = icmp eq i8 %kind, {}", b'Z'));
+        self.emit_raw(&format!("  %is_int8 = icmp eq i8 %kind, {}", b'B'));
+        self.emit_raw("  %is_byte = or i1 %is_bool, %is_int8");
+        self.emit_raw(&format!("  %is_short = icmp eq i8 %kind, {}", b'H'));
+        self.emit_raw(&format!("  %is_int = icmp eq i8 %kind, {}", b'I'));
+        self.emit_raw(&format!("  %is_float = icmp eq i8 %kind, {}", b'F'));
+        self.emit_raw(&format!("  %is_char = icmp eq i8 %kind, {}", b'C'));
+        self.emit_raw("  %is_int_or_float = or i1 %is_int, %is_float");
+        self.emit_raw("  %is_word = or i1 %is_int_or_float, %is_char");
+        self.emit_raw("  %short_or_wide = select i1 %is_short, i64 2, i64 8");
+        self.emit_raw("  %byte_or_wide = select i1 %is_byte, i64 1, i64 %short_or_wide");
+        self.emit_raw("  %elem_size = select i1 %is_word, i64 4, i64 %byte_or_wide");
+        self.emit_raw("  br label %loop_cond");
+        self.emit_raw("");
+        self.emit_raw("loop_cond:");
+        self.emit_raw("  %i = phi i64 [ 0, %format ], [ %i_next, %loop_body ]");
+        self.emit_raw("  %text = phi i8* [ getelementptr ([2 x i8], [2 x i8]* @.str.open_bracket, i64 0, i64 0), %format ], [ %text_next, %loop_body ]");
+        self.emit_raw("  %done = icmp sge i64 %i, %len");
+        self.emit_raw("  br i1 %done, label %finish, label %loop_body");
+        self.emit_raw("");
+        self.emit_raw("loop_body:");
+        self.emit_raw("  %is_first = icmp eq i64 %i, 0");
+        self.emit_raw("  %separator = select i1 %is_first, i8* getelementptr ([1 x i8], [1 x i8]* @.cay_empty_str, i64 0, i64 0), i8* getelementptr ([3 x i8], [3 x i8]* @.str.list_sep, i64 0, i64 0)");
+        self.emit_raw("  %with_sep = call i8* @__cay_string_concat(i8* %text, i8* %separator)");
+        self.emit_raw("  %elem_offset = mul i64 %i, %elem_size");
+        self.emit_raw("  %elem_addr = getelementptr i8, i8* %arr, i64 %elem_offset");
+        self.emit_raw("  %value = call i8* @__cay_reflect_value_to_string(i8* %elem_addr, i8* %sig, i8** %classes, i32 %class_count, i32 %depth)");
+        self.emit_raw("  %text_next = call i8* @__cay_string_concat(i8* %with_sep, i8* %value)");
+        self.emit_raw("  %i_next = add i64 %i, 1");
+        self.emit_raw("  br label %loop_cond");
+        self.emit_raw("");
+        self.emit_raw("finish:");
+        self.emit_raw("  %closed = call i8* @__cay_string_concat(i8* %text, i8* getelementptr ([2 x i8], [2 x i8]* @.str.close_bracket, i64 0, i64 0))");
+        self.emit_raw("  ret i8* %closed");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+}
@@ -0,0 +1,60 @@
+//! 字符串前缀/后缀/包含判断运行时函数
+
+use crate::codegen::context::IRGenerator;
+
+impl IRGenerator {
+    /// 生成 startsWith / endsWith / contains 运行时函数
+    ///
+    /// 任一参数为 null 时返回 false；空串是任何字符串的前缀、后缀和子串。
+    pub(super) fn emit_string_affix_runtime(&mut self) {
+        // startsWith 与 endsWith 只在比较起点上不同
+        for (name, at_end) in [("__cay_string_starts_with", false), ("__cay_string_ends_with", true)] {
+            self.emit_raw(&format!("define i1 @{}(i8* %str, i8* %affix) {{", name));
+            self.emit_raw("entry:");
+            self.emit_raw("  ; 空指针安全检查");
+            self.emit_raw("  %str_null = icmp eq i8* %str, null");
+            self.emit_raw("  %affix_null = icmp eq i8* %affix, null");
+            self.emit_raw("  %either_null = or i1 %str_null, %affix_null");
+            self.emit_raw("  br i1 %either_null, label %no_match, label %check_len");
+            self.emit_raw("");
+            self.emit_raw("no_match:");
+            self.emit_raw("  ret i1 false");
+            self.emit_raw("");
+            self.emit_raw("check_len:");
+            self.emit_raw("  %str_len = call i64 @strlen(i8* %str)");
+            self.emit_raw("  %affix_len = call i64 @strlen(i8* %affix)");
+            self.emit_raw("  %too_long = icmp ugt i64 %affix_len, %str_len");
+            self.emit_raw("  br i1 %too_long, label %no_match, label %compare");
+            self.emit_raw("");
+            self.emit_raw("compare:");
+            if at_end {
+                self.emit_raw("  %offset = sub i64 %str_len, %affix_len");
+            } else {
+                self.emit_raw("  %offset = add i64 0, 0");
+            }
+            self.emit_raw("  %start_ptr = getelementptr i8, i8* %str, i64 %offset");
+            self.emit_raw("  %cmp = call i32 @strncmp(i8* %start_ptr, i8* %affix, i64 %affix_len)");
+            self.emit_raw("  %matched = icmp eq i32 %cmp, 0");
+            self.emit_raw("  ret i1 %matched");
+            self.emit_raw("}");
+            self.emit_raw("");
+        }
+
+        self.emit_raw("define i1 @__cay_string_contains(i8* %str, i8* %substr) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %str_null = icmp eq i8* %str, null");
+        self.emit_raw("  %substr_null = icmp eq i8* %substr, null");
+        self.emit_raw("  %either_null = or i1 %str_null, %substr_null");
+        self.emit_raw("  br i1 %either_null, label %no_match, label %search");
+        self.emit_raw("");
+        self.emit_raw("no_match:");
+        self.emit_raw("  ret i1 false");
+        self.emit_raw("");
+        self.emit_raw("search:");
+        self.emit_raw("  %index = call i32 @__cay_string_indexof(i8* %str, i8* %substr)");
+        self.emit_raw("  %found = icmp sge i32 %index, 0");
+        self.emit_raw("  ret i1 %found");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+}
@@ -0,0 +1,145 @@
+//! 内置 StringBuilder 的缓冲区运行时函数
+//!
+//! `StringBuilder` 的 native 方法展开为这里的函数（见 `codegen::prelude`）。
+//! 缓冲区头为 3 个 i64：`[0]` 数据指针、`[1]` 当前长度、`[2]` 已分配的字节数。
+//! 数据始终以 NUL 结尾；容量不足时翻倍（至少容纳新内容），追加的总开销与最终长度成正比。
+
+use crate::codegen::context::IRGenerator;
+
+impl IRGenerator {
+    /// 生成 StringBuilder 运行时函数
+    pub(super) fn emit_string_builder_runtime(&mut self) {
+        self.emit_sb_new_runtime();
+        self.emit_sb_of_runtime();
+        self.emit_sb_append_runtime();
+        self.emit_sb_to_string_runtime();
+        self.emit_sb_length_runtime();
+        self.emit_sb_clear_runtime();
+    }
+
+    /// 创建初始容量为 16 字节的空缓冲区
+    fn emit_sb_new_runtime(&mut self) {
+        self.emit_raw("define i8* @__cay_sb_new() {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %raw = call i8* @calloc(i64 3, i64 8)");
+        self.emit_raw("  %buf = bitcast i8* %raw to i64*");
+        self.emit_raw("  %data = call i8* @calloc(i64 1, i64 16)");
+        self.emit_raw("  %data_word = ptrtoint i8* %data to i64");
+        self.emit_raw("  store i64 %data_word, i64* %buf");
+        self.emit_raw("  %cap_field = getelementptr i64, i64* %buf, i64 2");
+        self.emit_raw("  store i64 16, i64* %cap_field");
+        self.emit_raw("  ret i8* %raw");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// 取出对象字段 `slot` 中的缓冲区，字段为 0 时创建新缓冲区并写回
+    fn emit_sb_of_runtime(&mut self) {
+        self.emit_raw("define i8* @__cay_sb_of(i64* %slot) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %handle = load i64, i64* %slot");
+        self.emit_raw("  %missing = icmp eq i64 %handle, 0");
+        self.emit_raw("  br i1 %missing, label %create, label %existing");
+        self.emit_raw("");
+        self.emit_raw("existing:");
+        self.emit_raw("  %sb = inttoptr i64 %handle to i8*");
+        self.emit_raw("  ret i8* %sb");
+        self.emit_raw("");
+        self.emit_raw("create:");
+        self.emit_raw("  %created = call i8* @__cay_sb_new()");
+        self.emit_raw("  %created_handle = ptrtoint i8* %created to i64");
+        self.emit_raw("  store i64 %created_handle, i64* %slot");
+        self.emit_raw("  ret i8* %created");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// StringBuilder.append：把文本（null 为 "null"）复制到缓冲区末尾
+    fn emit_sb_append_runtime(&mut self) {
+        self.emit_raw("define void @__cay_sb_append(i8* %sb, i8* %text) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %buf = bitcast i8* %sb to i64*");
+        self.emit_raw("  %is_null = icmp eq i8* %text, null");
+        self.emit_raw("  %src = select i1 %is_null, i8* getelementptr ([5 x i8], [5 x i8]* @.str.null_str, i64 0, i64 0), i8* %text");
+        self.emit_raw("  %n = call i64 @strlen(i8* %src)");
+        self.emit_raw("  %len_field = getelementptr i64, i64* %buf, i64 1");
+        self.emit_raw("  %len = load i64, i64* %len_field");
+        self.emit_raw("  %cap_field = getelementptr i64, i64* %buf, i64 2");
+        self.emit_raw("  %cap = load i64, i64* %cap_field");
+        self.emit_raw("  %len_new = add i64 %len, %n");
+        self.emit_raw("  ; 为终止符留出一个字节");
+        self.emit_raw("  %needed = add i64 %len_new, 1");
+        self.emit_raw("  %full = icmp ugt i64 %needed, %cap");
+        self.emit_raw("  br i1 %full, label %grow, label %copy");
+        self.emit_raw("");
+        self.emit_raw("grow:");
+        self.emit_raw("  %doubled = shl i64 %cap, 1");
+        self.emit_raw("  %enough = icmp uge i64 %doubled, %needed");
+        self.emit_raw("  %cap_grown = select i1 %enough, i64 %doubled, i64 %needed");
+        self.emit_raw("  %old_word = load i64, i64* %buf");
+        self.emit_raw("  %old_data = inttoptr i64 %old_word to i8*");
+        self.emit_raw("  %new_data = call i8* @calloc(i64 1, i64 %cap_grown)");
+        self.emit_raw("  call void @llvm.memcpy.p0i8.p0i8.i64(i8* %new_data, i8* %old_data, i64 %len, i1 false)");
+        self.emit_raw("  %new_word = ptrtoint i8* %new_data to i64");
+        self.emit_raw("  store i64 %new_word, i64* %buf");
+        self.emit_raw("  store i64 %cap_grown, i64* %cap_field");
+        self.emit_raw("  br label %copy");
+        self.emit_raw("");
+        self.emit_raw("copy:");
+        self.emit_raw("  %data_word = load i64, i64* %buf");
+        self.emit_raw("  %data = inttoptr i64 %data_word to i8*");
+        self.emit_raw("  %dst = getelementptr i8, i8* %data, i64 %len");
+        self.emit_raw("  call void @llvm.memcpy.p0i8.p0i8.i64(i8* %dst, i8* %src, i64 %n, i1 false)");
+        self.emit_raw("  %end = getelementptr i8, i8* %data, i64 %len_new");
+        self.emit_raw("  store i8 0, i8* %end");
+        self.emit_raw("  store i64 %len_new, i64* %len_field");
+        self.emit_raw("  ret void");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// StringBuilder.toString：返回当前内容的副本
+    fn emit_sb_to_string_runtime(&mut self) {
+        self.emit_raw("define i8* @__cay_sb_to_string(i8* %sb) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %buf = bitcast i8* %sb to i64*");
+        self.emit_raw("  %len_field = getelementptr i64, i64* %buf, i64 1");
+        self.emit_raw("  %len = load i64, i64* %len_field");
+        self.emit_raw("  %size = add i64 %len, 1");
+        self.emit_raw("  %copy = call i8* @calloc(i64 1, i64 %size)");
+        self.emit_raw("  %data_word = load i64, i64* %buf");
+        self.emit_raw("  %data = inttoptr i64 %data_word to i8*");
+        self.emit_raw("  call void @llvm.memcpy.p0i8.p0i8.i64(i8* %copy, i8* %data, i64 %len, i1 false)");
+        self.emit_raw("  ret i8* %copy");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// StringBuilder.length：当前内容的字节数
+    fn emit_sb_length_runtime(&mut self) {
+        self.emit_raw("define i32 @__cay_sb_length(i8* %sb) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %buf = bitcast i8* %sb to i64*");
+        self.emit_raw("  %len_field = getelementptr i64, i64* %buf, i64 1");
+        self.emit_raw("  %len = load i64, i64* %len_field");
+        self.emit_raw("  %result = trunc i64 %len to i32");
+        self.emit_raw("  ret i32 %result");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// StringBuilder.clear：清空内容，保留已分配的容量
+    fn emit_sb_clear_runtime(&mut self) {
+        self.emit_raw("define void @__cay_sb_clear(i8* %sb) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %buf = bitcast i8* %sb to i64*");
+        self.emit_raw("  %len_field = getelementptr i64, i64* %buf, i64 1");
+        self.emit_raw("  store i64 0, i64* %len_field");
+        self.emit_raw("  %data_word = load i64, i64* %buf");
+        self.emit_raw("  %data = inttoptr i64 %data_word to i8*");
+        self.emit_raw("  store i8 0, i8* %data");
+        self.emit_raw("  ret void");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+}
@@ -0,0 +1,54 @@
+//! 字符串大小写转换运行时函数
+
+use crate::codegen::context::IRGenerator;
+
+impl IRGenerator {
+    /// 生成 toUpperCase / toLowerCase 运行时函数
+    ///
+    /// 仅转换 ASCII 字母，其余字节（包括 UTF-8 多字节序列）原样复制。
+    pub(super) fn emit_string_case_runtime(&mut self) {
+        // (函数名, 待转换区间下界, 上界, 偏移量)
+        for (name, lo, hi, delta) in [
+            ("__cay_string_to_upper", 97, 122, -32),
+            ("__cay_string_to_lower", 65, 90, 32),
+        ] {
+            self.emit_raw(&format!("define i8* @{}(i8* %str) {{", name));
+            self.emit_raw("entry:");
+            self.emit_raw("  ; 空指针安全检查");
+            self.emit_raw("  %is_null = icmp eq i8* %str, null");
+            self.emit_raw("  br i1 %is_null, label %null_case, label %alloc");
+            self.emit_raw("");
+            self.emit_raw("null_case:");
+            self.emit_raw("  ret i8* getelementptr ([1 x i8], [1 x i8]* @.cay_empty_str, i64 0, i64 0)");
+            self.emit_raw("");
+            self.emit_raw("alloc:");
+            self.emit_raw("  %len = call i64 @strlen(i8* %str)");
+            self.emit_raw("  %buf_size = add i64 %len, 1");
+            self.emit_raw("  %result = call i8* @calloc(i64 1, i64 %buf_size)");
+            self.emit_raw("  br label %loop_check");
+            self.emit_raw("");
+            self.emit_raw("loop_check:");
+            self.emit_raw("  %i = phi i64 [0, %alloc], [%i_next, %loop_body]");
+            self.emit_raw("  %done = icmp uge i64 %i, %len");
+            self.emit_raw("  br i1 %done, label %exit, label %loop_body");
+            self.emit_raw("");
+            self.emit_raw("loop_body:");
+            self.emit_raw("  %src_ptr = getelementptr i8, i8* %str, i64 %i");
+            self.emit_raw("  %c = load i8, i8* %src_ptr");
+            self.emit_raw(&format!("  %ge_lo = icmp uge i8 %c, {}", lo));
+            self.emit_raw(&format!("  %le_hi = icmp ule i8 %c, {}", hi));
+            self.emit_raw("  %in_range = and i1 %ge_lo, %le_hi");
+            self.emit_raw(&format!("  %converted = add i8 %c, {}", delta));
+            self.emit_raw("  %out = select i1 %in_range, i8 %converted, i8 %c");
+            self.emit_raw("  %dst_ptr = getelementptr i8, i8* %result, i64 %i");
+            self.emit_raw("  store i8 %out, i8* %dst_ptr");
+            self.emit_raw("  %i_next = add i64 %i, 1");
+            self.emit_raw("  br label %loop_check");
+            self.emit_raw("");
+            self.emit_raw("exit:");
+            self.emit_raw("  ret i8* %result");
+            self.emit_raw("}");
+            self.emit_raw("");
+        }
+    }
+}
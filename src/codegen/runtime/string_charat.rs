@@ -1,11 +1,16 @@
 //! 字符串字符获取运行时函数
+//!
+//! 字符串按 UTF-8 字节保存，下标是字节下标；`charAt` 解码从该字节开始的 UTF-8 序列，
+//! 返回其码点。下标落在多字节字符中间（续字节）、首字节无效或序列不完整时返回替换字符 U+FFFD，
+//! 与 readChar 的解码一致。
 
 use crate::codegen::context::IRGenerator;
 
 impl IRGenerator {
     /// 生成字符串字符获取运行时函数
     pub(super) fn emit_string_charat_runtime(&mut self) {
-        self.emit_raw("define i8 @__cay_string_charat(i8* %str, i32 %index) {");
+        self.emit_utf8_decode_runtime();
+        self.emit_raw("define i32 @__cay_string_charat(i8* %str, i32 %index) {");
         self.emit_raw("entry:");
         self.emit_raw("  ; 空指针安全检查");
         self.emit_raw("  %is_null = icmp eq i8* %str, null");
@@ -20,13 +25,74 @@ impl IRGenerator {
         self.emit_raw("  br i1 %out_of_range, label %out_of_bounds, label %get_char");
         self.emit_raw("");
         self.emit_raw("out_of_bounds:");
-        self.emit_raw("  ret i8 0");
+        self.emit_raw("  ret i32 0");
         self.emit_raw("");
         self.emit_raw("get_char:");
         self.emit_raw("  %idx_i64 = sext i32 %index to i64");
         self.emit_raw("  %char_ptr = getelementptr i8, i8* %str, i64 %idx_i64");
-        self.emit_raw("  %char_val = load i8, i8* %char_ptr");
-        self.emit_raw("  ret i8 %char_val");
+        self.emit_raw("  %char_val = call i32 @__cay_utf8_decode(i8* %char_ptr)");
+        self.emit_raw("  ret i32 %char_val");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+
+    /// 解码从 `ptr` 开始的一个 UTF-8 序列
+    ///
+    /// 首字节是续字节（10xxxxxx）或无效字节（0xF8 及以上），或者续字节缺失（遇到终止符或其他字节）时返回 U+FFFD。
+    fn emit_utf8_decode_runtime(&mut self) {
+        self.emit_raw("define i32 @__cay_utf8_decode(i8* %ptr) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %lead_byte = load i8, i8* %ptr");
+        self.emit_raw("  %lead = zext i8 %lead_byte to i32");
+        self.emit_raw("  %is_single = icmp ult i32 %lead, 128");
+        self.emit_raw("  br i1 %is_single, label %single, label %check_lead");
+        self.emit_raw("");
+        self.emit_raw("single:");
+        self.emit_raw("  ret i32 %lead");
+        self.emit_raw("");
+        self.emit_raw("check_lead:");
+        self.emit_raw("  ; 合法的首字节为 0xC0 ~ 0xF7");
+        self.emit_raw("  %lead_offset = sub i32 %lead, 192");
+        self.emit_raw("  %is_lead = icmp ult i32 %lead_offset, 56");
+        self.emit_raw("  br i1 %is_lead, label %multi, label %malformed");
+        self.emit_raw("");
+        self.emit_raw("multi:");
+        self.emit_raw("  ; 110xxxxx 后跟 1 个续字节，1110xxxx 后跟 2 个，11110xxx 后跟 3 个");
+        self.emit_raw("  %is_two = icmp ult i32 %lead, 224");
+        self.emit_raw("  %is_three = icmp ult i32 %lead, 240");
+        self.emit_raw("  %count_34 = select i1 %is_three, i64 2, i64 3");
+        self.emit_raw("  %count = select i1 %is_two, i64 1, i64 %count_34");
+        self.emit_raw("  %mask_34 = select i1 %is_three, i32 15, i32 7");
+        self.emit_raw("  %mask = select i1 %is_two, i32 31, i32 %mask_34");
+        self.emit_raw("  %initial = and i32 %lead, %mask");
+        self.emit_raw("  br label %loop");
+        self.emit_raw("");
+        self.emit_raw("loop:");
+        self.emit_raw("  %i = phi i64 [ 1, %multi ], [ %next, %append ]");
+        self.emit_raw("  %code = phi i32 [ %initial, %multi ], [ %code_next, %append ]");
+        self.emit_raw("  %finished = icmp ugt i64 %i, %count");
+        self.emit_raw("  br i1 %finished, label %done, label %load");
+        self.emit_raw("");
+        self.emit_raw("load:");
+        self.emit_raw("  %byte_ptr = getelementptr i8, i8* %ptr, i64 %i");
+        self.emit_raw("  %byte = load i8, i8* %byte_ptr");
+        self.emit_raw("  %byte32 = zext i8 %byte to i32");
+        self.emit_raw("  %tag = and i32 %byte32, 192");
+        self.emit_raw("  %is_cont = icmp eq i32 %tag, 128");
+        self.emit_raw("  br i1 %is_cont, label %append, label %malformed");
+        self.emit_raw("");
+        self.emit_raw("append:");
+        self.emit_raw("  %shifted = shl i32 %code, 6");
+        self.emit_raw("  %bits = and i32 %byte32, 63");
+        self.emit_raw("  %code_next = or i32 %shifted, %bits");
+        self.emit_raw("  %next = add i64 %i, 1");
+        self.emit_raw("  br label %loop");
+        self.emit_raw("");
+        self.emit_raw("malformed:");
+        self.emit_raw("  ret i32 65533");
+        self.emit_raw("");
+        self.emit_raw("done:");
+        self.emit_raw("  ret i32 %code");
         self.emit_raw("}");
         self.emit_raw("");
     }
@@ -0,0 +1,34 @@
+//! 字符串相等比较运行时函数
+
+use crate::codegen::context::IRGenerator;
+
+impl IRGenerator {
+    /// 生成字符串内容比较运行时函数
+    ///
+    /// 同一指针（包括两个 null）视为相等，只有一方为 null 时不相等，否则按内容比较。
+    pub(super) fn emit_string_equals_runtime(&mut self) {
+        self.emit_raw("define i1 @__cay_string_equals(i8* %a, i8* %b) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  %same = icmp eq i8* %a, %b");
+        self.emit_raw("  br i1 %same, label %equal, label %check_null");
+        self.emit_raw("");
+        self.emit_raw("check_null:");
+        self.emit_raw("  %a_null = icmp eq i8* %a, null");
+        self.emit_raw("  %b_null = icmp eq i8* %b, null");
+        self.emit_raw("  %any_null = or i1 %a_null, %b_null");
+        self.emit_raw("  br i1 %any_null, label %not_equal, label %compare");
+        self.emit_raw("");
+        self.emit_raw("compare:");
+        self.emit_raw("  %cmp = call i32 @strcmp(i8* %a, i8* %b)");
+        self.emit_raw("  %is_equal = icmp eq i32 %cmp, 0");
+        self.emit_raw("  ret i1 %is_equal");
+        self.emit_raw("");
+        self.emit_raw("equal:");
+        self.emit_raw("  ret i1 1");
+        self.emit_raw("");
+        self.emit_raw("not_equal:");
+        self.emit_raw("  ret i1 0");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+}
@@ -0,0 +1,149 @@
+//! 字符串分割运行时函数
+
+use crate::codegen::context::IRGenerator;
+
+impl IRGenerator {
+    /// 生成 split 运行时函数
+    ///
+    /// 分隔符按字面量匹配（不是正则表达式），返回 `String[]`，内存布局与
+    /// `new String[n]` 相同：8 字节长度头之后紧跟元素数据，返回指向数据的指针。
+    /// 与 Java 一致：找不到分隔符时返回只含原串的数组，末尾的空串会被去掉，
+    /// 空分隔符按字节拆分。
+    pub(super) fn emit_string_split_runtime(&mut self) {
+        self.emit_raw("define i8** @__cay_string_split(i8* %str, i8* %sep) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  ; null 按空串处理");
+        self.emit_raw("  %str_null = icmp eq i8* %str, null");
+        self.emit_raw("  %s = select i1 %str_null, i8* getelementptr ([1 x i8], [1 x i8]* @.cay_empty_str, i64 0, i64 0), i8* %str");
+        self.emit_raw("  %sep_null = icmp eq i8* %sep, null");
+        self.emit_raw("  %p = select i1 %sep_null, i8* getelementptr ([1 x i8], [1 x i8]* @.cay_empty_str, i64 0, i64 0), i8* %sep");
+        self.emit_raw("  %str_len = call i64 @strlen(i8* %s)");
+        self.emit_raw("  %sep_len = call i64 @strlen(i8* %p)");
+        self.emit_raw("  %s_addr = ptrtoint i8* %s to i64");
+        self.emit_raw("  %sep_empty = icmp eq i64 %sep_len, 0");
+        self.emit_raw("  br i1 %sep_empty, label %chars, label %count_check");
+        self.emit_raw("");
+        self.emit_raw("; ---- 空分隔符：逐字节拆分 ----");
+        self.emit_raw("chars:");
+        self.emit_raw("  %str_empty = icmp eq i64 %str_len, 0");
+        self.emit_raw("  br i1 %str_empty, label %whole, label %chars_alloc");
+        self.emit_raw("");
+        self.emit_raw("chars_alloc:");
+        self.emit_raw("  %chars_bytes = mul i64 %str_len, 8");
+        self.emit_raw("  %chars_size = add i64 %chars_bytes, 8");
+        self.emit_raw("  %chars_base = call i8* @calloc(i64 1, i64 %chars_size)");
+        self.emit_raw("  %chars_len_ptr = bitcast i8* %chars_base to i32*");
+        self.emit_raw("  %chars_len32 = trunc i64 %str_len to i32");
+        self.emit_raw("  store i32 %chars_len32, i32* %chars_len_ptr");
+        self.emit_raw("  %chars_raw = getelementptr i8, i8* %chars_base, i64 8");
+        self.emit_raw("  %chars_data = bitcast i8* %chars_raw to i8**");
+        self.emit_raw("  br label %chars_check");
+        self.emit_raw("");
+        self.emit_raw("chars_check:");
+        self.emit_raw("  %ci = phi i64 [0, %chars_alloc], [%ci_next, %chars_body]");
+        self.emit_raw("  %chars_done = icmp uge i64 %ci, %str_len");
+        self.emit_raw("  br i1 %chars_done, label %chars_exit, label %chars_body");
+        self.emit_raw("");
+        self.emit_raw("chars_body:");
+        self.emit_raw("  %ci32 = trunc i64 %ci to i32");
+        self.emit_raw("  %ci_end = add i32 %ci32, 1");
+        self.emit_raw("  %char_str = call i8* @__cay_string_substring(i8* %s, i32 %ci32, i32 %ci_end)");
+        self.emit_raw("  %chars_slot = getelementptr i8*, i8** %chars_data, i64 %ci");
+        self.emit_raw("  store i8* %char_str, i8** %chars_slot");
+        self.emit_raw("  %ci_next = add i64 %ci, 1");
+        self.emit_raw("  br label %chars_check");
+        self.emit_raw("");
+        self.emit_raw("chars_exit:");
+        self.emit_raw("  ret i8** %chars_data");
+        self.emit_raw("");
+        self.emit_raw("; ---- 没有可分割的位置：返回只含原串的数组 ----");
+        self.emit_raw("whole:");
+        self.emit_raw("  %whole_base = call i8* @calloc(i64 1, i64 16)");
+        self.emit_raw("  %whole_len_ptr = bitcast i8* %whole_base to i32*");
+        self.emit_raw("  store i32 1, i32* %whole_len_ptr");
+        self.emit_raw("  %whole_raw = getelementptr i8, i8* %whole_base, i64 8");
+        self.emit_raw("  %whole_data = bitcast i8* %whole_raw to i8**");
+        self.emit_raw("  store i8* %s, i8** %whole_data");
+        self.emit_raw("  ret i8** %whole_data");
+        self.emit_raw("");
+        self.emit_raw("; ---- 第一遍：统计分段数 ----");
+        self.emit_raw("count_check:");
+        self.emit_raw("  %cnt = phi i64 [1, %entry], [%cnt_next, %count_found]");
+        self.emit_raw("  %cpos = phi i8* [%s, %entry], [%cpos_next, %count_found]");
+        self.emit_raw("  %cq = call i8* @strstr(i8* %cpos, i8* %p)");
+        self.emit_raw("  %cq_null = icmp eq i8* %cq, null");
+        self.emit_raw("  br i1 %cq_null, label %count_done, label %count_found");
+        self.emit_raw("");
+        self.emit_raw("count_found:");
+        self.emit_raw("  %cnt_next = add i64 %cnt, 1");
+        self.emit_raw("  %cpos_next = getelementptr i8, i8* %cq, i64 %sep_len");
+        self.emit_raw("  br label %count_check");
+        self.emit_raw("");
+        self.emit_raw("count_done:");
+        self.emit_raw("  %no_sep = icmp eq i64 %cnt, 1");
+        self.emit_raw("  br i1 %no_sep, label %whole, label %fill_alloc");
+        self.emit_raw("");
+        self.emit_raw("; ---- 第二遍：复制各分段 ----");
+        self.emit_raw("fill_alloc:");
+        self.emit_raw("  %fill_bytes = mul i64 %cnt, 8");
+        self.emit_raw("  %fill_size = add i64 %fill_bytes, 8");
+        self.emit_raw("  %fill_base = call i8* @calloc(i64 1, i64 %fill_size)");
+        self.emit_raw("  %fill_len_ptr = bitcast i8* %fill_base to i32*");
+        self.emit_raw("  %fill_raw = getelementptr i8, i8* %fill_base, i64 8");
+        self.emit_raw("  %fill_data = bitcast i8* %fill_raw to i8**");
+        self.emit_raw("  br label %fill_check");
+        self.emit_raw("");
+        self.emit_raw("fill_check:");
+        self.emit_raw("  %fi = phi i64 [0, %fill_alloc], [%fi_next, %fill_found]");
+        self.emit_raw("  %fpos = phi i8* [%s, %fill_alloc], [%fpos_next, %fill_found]");
+        self.emit_raw("  %fpos_addr = ptrtoint i8* %fpos to i64");
+        self.emit_raw("  %part_begin = sub i64 %fpos_addr, %s_addr");
+        self.emit_raw("  %part_begin32 = trunc i64 %part_begin to i32");
+        self.emit_raw("  %fslot = getelementptr i8*, i8** %fill_data, i64 %fi");
+        self.emit_raw("  %fq = call i8* @strstr(i8* %fpos, i8* %p)");
+        self.emit_raw("  %fq_null = icmp eq i8* %fq, null");
+        self.emit_raw("  br i1 %fq_null, label %fill_last, label %fill_found");
+        self.emit_raw("");
+        self.emit_raw("fill_found:");
+        self.emit_raw("  %fq_addr = ptrtoint i8* %fq to i64");
+        self.emit_raw("  %part_end = sub i64 %fq_addr, %s_addr");
+        self.emit_raw("  %part_end32 = trunc i64 %part_end to i32");
+        self.emit_raw("  %part = call i8* @__cay_string_substring(i8* %s, i32 %part_begin32, i32 %part_end32)");
+        self.emit_raw("  store i8* %part, i8** %fslot");
+        self.emit_raw("  %fi_next = add i64 %fi, 1");
+        self.emit_raw("  %fpos_next = getelementptr i8, i8* %fq, i64 %sep_len");
+        self.emit_raw("  br label %fill_check");
+        self.emit_raw("");
+        self.emit_raw("fill_last:");
+        self.emit_raw("  %str_len32 = trunc i64 %str_len to i32");
+        self.emit_raw("  %last = call i8* @__cay_string_substring(i8* %s, i32 %part_begin32, i32 %str_len32)");
+        self.emit_raw("  store i8* %last, i8** %fslot");
+        self.emit_raw("  br label %trim_check");
+        self.emit_raw("");
+        self.emit_raw("; ---- 去掉末尾的空串 ----");
+        self.emit_raw("trim_check:");
+        self.emit_raw("  %n = phi i64 [%cnt, %fill_last], [%n_prev, %trim_pop]");
+        self.emit_raw("  %n_zero = icmp eq i64 %n, 0");
+        self.emit_raw("  br i1 %n_zero, label %fill_exit, label %trim_test");
+        self.emit_raw("");
+        self.emit_raw("trim_test:");
+        self.emit_raw("  %n_prev = sub i64 %n, 1");
+        self.emit_raw("  %tail_slot = getelementptr i8*, i8** %fill_data, i64 %n_prev");
+        self.emit_raw("  %tail = load i8*, i8** %tail_slot");
+        self.emit_raw("  %tail_first = load i8, i8* %tail");
+        self.emit_raw("  %tail_empty = icmp eq i8 %tail_first, 0");
+        self.emit_raw("  br i1 %tail_empty, label %trim_pop, label %fill_exit");
+        self.emit_raw("");
+        self.emit_raw("trim_pop:");
+        self.emit_raw("  br label %trim_check");
+        self.emit_raw("");
+        self.emit_raw("fill_exit:");
+        self.emit_raw("  %final_len = trunc i64 %n to i32");
+        self.emit_raw("  store i32 %final_len, i32* %fill_len_ptr");
+        self.emit_raw("  ret i8** %fill_data");
+        self.emit_raw("}");
+        self.emit_raw("");
+        self.emit_raw("declare i8* @strstr(i8*, i8*)");
+        self.emit_raw("");
+    }
+}
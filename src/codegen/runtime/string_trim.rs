@@ -0,0 +1,63 @@
+//! 字符串去空白运行时函数
+
+use crate::codegen::context::IRGenerator;
+
+impl IRGenerator {
+    /// 生成 trim 运行时函数
+    ///
+    /// 与 Java 一致，去除首尾所有码值不大于空格（0x20）的字节。
+    pub(super) fn emit_string_trim_runtime(&mut self) {
+        self.emit_raw("define i8* @__cay_string_trim(i8* %str) {");
+        self.emit_raw("entry:");
+        self.emit_raw("  ; 空指针安全检查");
+        self.emit_raw("  %is_null = icmp eq i8* %str, null");
+        self.emit_raw("  br i1 %is_null, label %null_case, label %scan_start");
+        self.emit_raw("");
+        self.emit_raw("null_case:");
+        self.emit_raw("  ret i8* getelementptr ([1 x i8], [1 x i8]* @.cay_empty_str, i64 0, i64 0)");
+        self.emit_raw("");
+        self.emit_raw("scan_start:");
+        self.emit_raw("  %len = call i64 @strlen(i8* %str)");
+        self.emit_raw("  br label %front_check");
+        self.emit_raw("");
+        self.emit_raw("front_check:");
+        self.emit_raw("  ; 从前向后跳过空白");
+        self.emit_raw("  %begin = phi i64 [0, %scan_start], [%begin_next, %front_skip]");
+        self.emit_raw("  %front_done = icmp uge i64 %begin, %len");
+        self.emit_raw("  br i1 %front_done, label %back_check, label %front_test");
+        self.emit_raw("");
+        self.emit_raw("front_test:");
+        self.emit_raw("  %front_ptr = getelementptr i8, i8* %str, i64 %begin");
+        self.emit_raw("  %front_c = load i8, i8* %front_ptr");
+        self.emit_raw("  %front_ws = icmp ule i8 %front_c, 32");
+        self.emit_raw("  br i1 %front_ws, label %front_skip, label %back_check");
+        self.emit_raw("");
+        self.emit_raw("front_skip:");
+        self.emit_raw("  %begin_next = add i64 %begin, 1");
+        self.emit_raw("  br label %front_check");
+        self.emit_raw("");
+        self.emit_raw("back_check:");
+        self.emit_raw("  ; 从后向前跳过空白，不越过 begin");
+        self.emit_raw("  %end = phi i64 [%len, %front_check], [%len, %front_test], [%end_prev, %back_skip]");
+        self.emit_raw("  %back_done = icmp ule i64 %end, %begin");
+        self.emit_raw("  br i1 %back_done, label %copy, label %back_test");
+        self.emit_raw("");
+        self.emit_raw("back_test:");
+        self.emit_raw("  %end_prev = sub i64 %end, 1");
+        self.emit_raw("  %back_ptr = getelementptr i8, i8* %str, i64 %end_prev");
+        self.emit_raw("  %back_c = load i8, i8* %back_ptr");
+        self.emit_raw("  %back_ws = icmp ule i8 %back_c, 32");
+        self.emit_raw("  br i1 %back_ws, label %back_skip, label %copy");
+        self.emit_raw("");
+        self.emit_raw("back_skip:");
+        self.emit_raw("  br label %back_check");
+        self.emit_raw("");
+        self.emit_raw("copy:");
+        self.emit_raw("  %begin_i32 = trunc i64 %begin to i32");
+        self.emit_raw("  %end_i32 = trunc i64 %end to i32");
+        self.emit_raw("  %result = call i8* @__cay_string_substring(i8* %str, i32 %begin_i32, i32 %end_i32)");
+        self.emit_raw("  ret i8* %result");
+        self.emit_raw("}");
+        self.emit_raw("");
+    }
+}
@@ -3,92 +3,51 @@
 //! 处理if-else语句的代码生成。
 
 use crate::codegen::context::IRGenerator;
+use crate::codegen::value::LLVMValue;
 use crate::ast::*;
 use crate::error::cayResult;
 
 impl IRGenerator {
     /// 生成 if 语句代码
     pub fn generate_if_statement(&mut self, if_stmt: &IfStmt) -> cayResult<()> {
+        // 条件为常量时只生成会执行的分支
+        if let Some(value) = Self::constant_condition(&if_stmt.condition) {
+            return match (value, &if_stmt.else_branch) {
+                (true, _) => self.generate_statement(&if_stmt.then_branch),
+                (false, Some(else_branch)) => self.generate_statement(else_branch),
+                (false, None) => Ok(()),
+            };
+        }
+
         let then_label = self.new_label("then");
         let else_label = self.new_label("else");
         let merge_label = self.new_label("ifmerge");
 
-        let cond = self.generate_expression(&if_stmt.condition)?;
-        let (cond_type, cond_val) = self.parse_typed_value(&cond);
-        let cond_reg = self.new_temp();
-        
-        // 将条件转换为 i1 类型
-        if cond_type == "i1" {
-            self.emit_line(&format!("  {} = icmp ne i1 {}, 0", cond_reg, cond_val));
-        } else {
-            // 对于整数类型，先与 0 比较
-            self.emit_line(&format!("  {} = icmp ne {} {}, 0", cond_reg, cond_type, cond_val));
-        }
-
         let has_else = if_stmt.else_branch.is_some();
+        let false_label = if has_else { &else_label } else { &merge_label };
+        self.generate_condition(&if_stmt.condition, &then_label, false_label)?;
 
-        if has_else {
-            self.emit_line(&format!("  br i1 {}, label %{}, label %{}",
-                cond_reg, then_label, else_label));
-        } else {
-            self.emit_line(&format!("  br i1 {}, label %{}, label %{}",
-                cond_reg, then_label, merge_label));
-        }
-
-        // then块
-        self.emit_line(&format!("{}:", then_label));
-        let then_code_before = self.code.len();
+        // then块：未以终止指令结束时跳到 merge
+        self.emit_label(&then_label);
         self.generate_statement(&if_stmt.then_branch)?;
-        let then_code_after = self.code.len();
-
-        // 检查 then 块是否以终止指令结束
-        let mut then_terminates = false;
-        if then_code_after > then_code_before {
-            let then_code = &self.code[then_code_before..then_code_after];
-            let then_lines: Vec<&str> = then_code.trim().lines().collect();
-            if let Some(last_line) = then_lines.last() {
-                let trimmed = last_line.trim();
-                if trimmed.starts_with("ret") || trimmed.starts_with("br") || trimmed.starts_with("switch") || trimmed.starts_with("unreachable") {
-                    then_terminates = true;
-                } else {
-                    self.emit_line(&format!("  br label %{}", merge_label));
-                }
-            } else {
-                self.emit_line(&format!("  br label %{}", merge_label));
-            }
-        } else {
-            self.emit_line(&format!("  br label %{}", merge_label));
+        let then_terminates = self.current_block_terminated();
+        if !then_terminates {
+            self.emit_br(&merge_label);
         }
 
         // else块
         let mut else_terminates = false;
         if let Some(else_branch) = if_stmt.else_branch.as_ref() {
-            self.emit_line(&format!("{}:", else_label));
-            let else_code_before = self.code.len();
+            self.emit_label(&else_label);
             self.generate_statement(else_branch)?;
-            let else_code_after = self.code.len();
-
-            // 检查 else 块是否以终止指令结束
-            if else_code_after > else_code_before {
-                let else_code = &self.code[else_code_before..else_code_after];
-                let else_lines: Vec<&str> = else_code.trim().lines().collect();
-                if let Some(last_line) = else_lines.last() {
-                    let trimmed = last_line.trim();
-                    if trimmed.starts_with("ret") || trimmed.starts_with("br") || trimmed.starts_with("switch") || trimmed.starts_with("unreachable") {
-                        else_terminates = true;
-                    } else {
-                        self.emit_line(&format!("  br label %{}", merge_label));
-                    }
-                } else {
-                    self.emit_line(&format!("  br label %{}", merge_label));
-                }
-            } else {
-                self.emit_line(&format!("  br label %{}", merge_label));
+            else_terminates = self.current_block_terminated();
+            if !else_terminates {
+                self.emit_br(&merge_label);
             }
         }
 
         // merge块
-        self.emit_line(&format!("{}:", merge_label));
+        self.emit_label(&merge_label);
 
         // 只有当两个分支都以终止指令结束时，merge 才不可达
         // 特殊情况：如果没有 else，false 分支直接 fall-through 到 merge，所以 merge 一定可达
@@ -105,4 +64,32 @@ impl IRGenerator {
 
         Ok(())
     }
+
+    /// 生成条件跳转：条件为真时跳到 `true_label`，否则跳到 `false_label`
+    ///
+    /// 常量条件直接跳到会执行的一侧，不生成比较。
+    pub(super) fn generate_condition(&mut self, condition: &Expr, true_label: &str, false_label: &str) -> cayResult<()> {
+        if let Some(value) = Self::constant_condition(condition) {
+            self.emit_br(if value { true_label } else { false_label });
+            return Ok(());
+        }
+        let LLVMValue { ty: cond_type, repr: cond_val, .. } = self.generate_expression(condition)?;
+        let cond_reg = self.new_temp();
+        // 将条件转换为 i1 类型
+        if cond_type == "i1" {
+            self.emit_line(&format!("  {} = icmp ne i1 {}, 0", cond_reg, cond_val));
+        } else {
+            self.emit_line(&format!("  {} = icmp ne {} {}, 0", cond_reg, cond_type, cond_val));
+        }
+        self.emit_cond_br(&cond_reg, true_label, false_label);
+        Ok(())
+    }
+
+    /// 字面量 true/false 条件的值（常量传播之后，常量表达式也已折叠为字面量）
+    pub(super) fn constant_condition(condition: &Expr) -> Option<bool> {
+        match condition {
+            Expr::Literal(LiteralValue::Bool(value)) => Some(*value),
+            _ => None,
+        }
+    }
 }
@@ -3,13 +3,14 @@
 //! 处理break和continue语句的代码生成。
 
 use crate::codegen::context::IRGenerator;
+use crate::ast::JumpStmt;
 use crate::error::{cayResult, codegen_error};
 
 impl IRGenerator {
     /// 生成 break 语句代码
-    pub fn generate_break_statement(&mut self) -> cayResult<()> {
-        if let Some(loop_ctx) = self.current_loop() {
-            self.emit_line(&format!("  br label %{}", loop_ctx.end_label));
+    pub fn generate_break_statement(&mut self, jump: &JumpStmt) -> cayResult<()> {
+        if let Some(label) = self.jump_target(jump.label.as_deref(), false).map(|ctx| ctx.end_label.clone()) {
+            self.emit_br(&label);
         } else {
             return Err(codegen_error("break statement outside of loop".to_string()));
         }
@@ -17,9 +18,9 @@ impl IRGenerator {
     }
 
     /// 生成 continue 语句代码
-    pub fn generate_continue_statement(&mut self) -> cayResult<()> {
-        if let Some(loop_ctx) = self.current_loop() {
-            self.emit_line(&format!("  br label %{}", loop_ctx.cond_label));
+    pub fn generate_continue_statement(&mut self, jump: &JumpStmt) -> cayResult<()> {
+        if let Some(label) = self.jump_target(jump.label.as_deref(), true).and_then(|ctx| ctx.cond_label.clone()) {
+            self.emit_br(&label);
         } else {
             return Err(codegen_error("continue statement outside of loop".to_string()));
         }
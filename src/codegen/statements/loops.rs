@@ -1,10 +1,11 @@
 //! 循环语句代码生成
 //!
-//! 处理while、for、do-while循环的代码生成。
+//! 处理while、for、for-each、do-while循环的代码生成。
 
 use crate::codegen::context::IRGenerator;
 use crate::ast::*;
-use crate::error::cayResult;
+use crate::types::Type;
+use crate::error::{cayResult, codegen_error};
 
 impl IRGenerator {
     /// 生成 while 语句代码
@@ -14,30 +15,27 @@ impl IRGenerator {
         let end_label = self.new_label("while.end");
 
         // 进入循环上下文
-        self.enter_loop(cond_label.clone(), end_label.clone());
+        let label = self.pending_label.take();
+        // 条件恒为假时循环体不会执行
+        if Self::constant_condition(&while_stmt.condition) == Some(false) {
+            return Ok(());
+        }
+        self.enter_loop(cond_label.clone(), end_label.clone(), label);
 
-        self.emit_line(&format!("  br label %{}", cond_label));
+        self.emit_br(&cond_label);
 
         // 条件块
-        self.emit_line(&format!("{}:", cond_label));
-        let cond = self.generate_expression(&while_stmt.condition)?;
-        let (cond_type, cond_val) = self.parse_typed_value(&cond);
-        let cond_reg = self.new_temp();
-        if cond_type == "i1" {
-            self.emit_line(&format!("  {} = icmp ne i1 {}, 0", cond_reg, cond_val));
-        } else {
-            self.emit_line(&format!("  {} = icmp ne {} {}, 0", cond_reg, cond_type, cond_val));
-        }
-        self.emit_line(&format!("  br i1 {}, label %{}, label %{}",
-            cond_reg, body_label, end_label));
+        self.emit_label(&cond_label);
+        self.emit_step_tick(&format!("while loop at line {}", while_stmt.loc.line));
+        self.generate_condition(&while_stmt.condition, &body_label, &end_label)?;
 
         // 循环体
-        self.emit_line(&format!("{}:", body_label));
+        self.emit_label(&body_label);
         self.generate_statement(&while_stmt.body)?;
-        self.emit_line(&format!("  br label %{}", cond_label));
+        self.emit_br(&cond_label);
 
         // 结束块
-        self.emit_line(&format!("{}:", end_label));
+        self.emit_label(&end_label);
 
         // 退出循环上下文
         self.exit_loop();
@@ -51,49 +49,45 @@ impl IRGenerator {
         let body_label = self.new_label("for.body");
         let update_label = self.new_label("for.update");
         let end_label = self.new_label("for.end");
+        let label = self.pending_label.take();
 
         // 初始化部分
         if let Some(init) = for_stmt.init.as_ref() {
             self.generate_statement(init)?;
         }
+        if for_stmt.condition.as_ref().and_then(Self::constant_condition) == Some(false) {
+            return Ok(());
+        }
 
         // 进入循环上下文（continue 跳转到 update 标签）
-        self.enter_loop(update_label.clone(), end_label.clone());
+        self.enter_loop(update_label.clone(), end_label.clone(), label);
 
-        self.emit_line(&format!("  br label %{}", cond_label));
+        self.emit_br(&cond_label);
 
         // 条件块
-        self.emit_line(&format!("{}:", cond_label));
+        self.emit_label(&cond_label);
+        self.emit_step_tick(&format!("for loop at line {}", for_stmt.loc.line));
         if let Some(condition) = for_stmt.condition.as_ref() {
-            let cond = self.generate_expression(condition)?;
-            let (cond_type, cond_val) = self.parse_typed_value(&cond);
-            let cond_reg = self.new_temp();
-            if cond_type == "i1" {
-                self.emit_line(&format!("  {} = icmp ne i1 {}, 0", cond_reg, cond_val));
-            } else {
-                self.emit_line(&format!("  {} = icmp ne {} {}, 0", cond_reg, cond_type, cond_val));
-            }
-            self.emit_line(&format!("  br i1 {}, label %{}, label %{}",
-                cond_reg, body_label, end_label));
+            self.generate_condition(condition, &body_label, &end_label)?;
         } else {
             // 无条件时默认跳转到循环体（无限循环）
-            self.emit_line(&format!("  br label %{}", body_label));
+            self.emit_br(&body_label);
         }
 
         // 循环体
-        self.emit_line(&format!("{}:", body_label));
+        self.emit_label(&body_label);
         self.generate_statement(&for_stmt.body)?;
-        self.emit_line(&format!("  br label %{}", update_label));
+        self.emit_br(&update_label);
 
         // 更新块
-        self.emit_line(&format!("{}:", update_label));
+        self.emit_label(&update_label);
         if let Some(update) = for_stmt.update.as_ref() {
             self.generate_expression(update)?;
         }
-        self.emit_line(&format!("  br label %{}", cond_label));
+        self.emit_br(&cond_label);
 
         // 结束块
-        self.emit_line(&format!("{}:", end_label));
+        self.emit_label(&end_label);
 
         // 退出循环上下文
         self.exit_loop();
@@ -108,33 +102,116 @@ impl IRGenerator {
         let end_label = self.new_label("dowhile.end");
 
         // 进入循环上下文
-        self.enter_loop(cond_label.clone(), end_label.clone());
+        let label = self.pending_label.take();
+        self.enter_loop(cond_label.clone(), end_label.clone(), label);
 
         // 先执行循环体
-        self.emit_line(&format!("  br label %{}", body_label));
-        self.emit_line(&format!("{}:", body_label));
+        self.emit_br(&body_label);
+        self.emit_label(&body_label);
+        self.emit_step_tick(&format!("do-while loop at line {}", do_while_stmt.loc.line));
         self.generate_statement(&do_while_stmt.body)?;
-        self.emit_line(&format!("  br label %{}", cond_label));
+        self.emit_br(&cond_label);
 
         // 条件检查
-        self.emit_line(&format!("{}:", cond_label));
-        let cond = self.generate_expression(&do_while_stmt.condition)?;
-        let (cond_type, cond_val) = self.parse_typed_value(&cond);
-        let cond_reg = self.new_temp();
-        if cond_type == "i1" {
-            self.emit_line(&format!("  {} = icmp ne i1 {}, 0", cond_reg, cond_val));
-        } else {
-            self.emit_line(&format!("  {} = icmp ne {} {}, 0", cond_reg, cond_type, cond_val));
-        }
-        self.emit_line(&format!("  br i1 {}, label %{}, label %{}",
-            cond_reg, body_label, end_label));
+        self.emit_label(&cond_label);
+        self.generate_condition(&do_while_stmt.condition, &body_label, &end_label)?;
 
         // 结束块
-        self.emit_line(&format!("{}:", end_label));
+        self.emit_label(&end_label);
 
         // 退出循环上下文
         self.exit_loop();
 
         Ok(())
     }
+
+    /// 生成 for-each 语句代码
+    ///
+    /// 改写为按下标遍历的 for 循环后生成，遍历对象只求值一次。
+    pub fn generate_for_each_statement(&mut self, for_each: &ForEachStmt) -> cayResult<()> {
+        let (iterable_type, var_type) = self.for_each_types.get(&for_each.id).cloned()
+            .ok_or_else(|| codegen_error("for-each loop has no recorded element type".to_string()))?;
+        self.generate_statement(&lower_for_each(for_each, iterable_type, var_type))
+    }
+}
+
+/// 把 for-each 改写为等价的语句：
+///
+/// ```text
+/// { Iter items = expr; for (int i = 0; i < items.length; i = i + 1) { T x = items[i]; body } }
+/// ```
+///
+/// 对象用 `items.size()` 和 `items.get(i)` 代替 `length` 和下标访问。
+/// 引入的变量名带 `$`，不会与源码中的变量冲突。
+fn lower_for_each(for_each: &ForEachStmt, iterable_type: Type, var_type: Type) -> Stmt {
+    let loc = &for_each.loc;
+    let items_name = format!("foreach${}$items", for_each.id.0);
+    let index_name = format!("foreach${}$index", for_each.id.0);
+    let items = || Box::new(Expr::Identifier(items_name.clone()));
+    let index = || Box::new(Expr::Identifier(index_name.clone()));
+    let member = |name: &str| Box::new(Expr::MemberAccess(MemberAccessExpr {
+        object: items(),
+        member: name.to_string(),
+        null_safe: false,
+        loc: loc.clone(),
+        id: NodeId::DUMMY,
+    }));
+    let call = |name: &str, args: Vec<Expr>| Expr::Call(CallExpr {
+        callee: member(name),
+        args,
+        loc: loc.clone(),
+        id: NodeId::DUMMY,
+    });
+    let binary = |left: Box<Expr>, op: BinaryOp, right: Expr| Expr::Binary(BinaryExpr {
+        left,
+        op,
+        right: Box::new(right),
+        loc: loc.clone(),
+        id: NodeId::DUMMY,
+    });
+    let declare = |name: &str, var_type: Type, init: Expr, is_final: bool| Stmt::VarDecl(VarDecl {
+        name: name.to_string(),
+        var_type,
+        initializer: Some(init),
+        is_final,
+        loc: loc.clone(),
+        id: NodeId::DUMMY,
+    });
+
+    let (length, element) = match iterable_type {
+        Type::Array(_) => (*member("length"), Expr::ArrayAccess(ArrayAccessExpr {
+            array: items(),
+            index: index(),
+            loc: loc.clone(),
+            id: NodeId::DUMMY,
+        })),
+        _ => (call("size", Vec::new()), call("get", vec![*index()])),
+    };
+    let body = Stmt::Block(Block {
+        statements: vec![
+            declare(&for_each.var_name, var_type, element, for_each.is_final),
+            (*for_each.body).clone(),
+        ],
+        loc: loc.clone(),
+        id: NodeId::DUMMY,
+    });
+    let for_stmt = Stmt::For(ForStmt {
+        init: Some(Box::new(declare(&index_name, Type::Int32, Expr::Literal(LiteralValue::Int32(0)), false))),
+        condition: Some(binary(index(), BinaryOp::Lt, length)),
+        update: Some(Expr::Assignment(AssignmentExpr {
+            target: index(),
+            value: Box::new(binary(index(), BinaryOp::Add, Expr::Literal(LiteralValue::Int32(1)))),
+            op: AssignOp::Assign,
+            loc: loc.clone(),
+            id: NodeId::DUMMY,
+        })),
+        body: Box::new(body),
+        loc: loc.clone(),
+        id: NodeId::DUMMY,
+    });
+    Stmt::Block(Block {
+        statements: vec![declare(&items_name, iterable_type, for_each.iterable.clone(), false), for_stmt],
+        loc: loc.clone(),
+        id: NodeId::DUMMY,
+    })
 }
@@ -11,8 +11,9 @@ impl IRGenerator {
     pub fn generate_return_statement(&mut self, expr: &Option<Expr>) -> cayResult<()> {
         if let Some(e) = expr.as_ref() {
             let value = self.generate_expression(e)?;
-            let (value_type, val) = self.parse_typed_value(&value);
+            let (value_type, val) = (value.ty.as_str(), value.repr.as_str());
             let ret_type = self.current_return_type.clone();
+            self.emit_stack_guard_exit();
 
             // 如果返回类型是 void，但表达式非空，这是错误（但由语义分析处理）
             if ret_type == "void" {
@@ -70,6 +71,7 @@ impl IRGenerator {
                 self.emit_line(&format!("  ret {}", value));
             }
         } else {
+            self.emit_stack_guard_exit();
             self.emit_line("  ret void");
         }
 
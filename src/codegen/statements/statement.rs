@@ -16,8 +16,8 @@ impl IRGenerator {
             Stmt::VarDecl(var) => {
                 self.generate_var_decl(var)?;
             }
-            Stmt::Return(expr) => {
-                self.generate_return_statement(expr)?;
+            Stmt::Return(ret) => {
+                self.generate_return_statement(&ret.value)?;
             }
             Stmt::Block(block) => {
                 self.generate_block(block)?;
@@ -31,19 +31,43 @@ impl IRGenerator {
             Stmt::For(for_stmt) => {
                 self.generate_for_statement(for_stmt)?;
             }
+            Stmt::ForEach(for_each) => {
+                self.generate_for_each_statement(for_each)?;
+            }
             Stmt::DoWhile(do_while_stmt) => {
                 self.generate_do_while_statement(do_while_stmt)?;
             }
             Stmt::Switch(switch_stmt) => {
                 self.generate_switch_statement(switch_stmt)?;
             }
-            Stmt::Break => {
-                self.generate_break_statement()?;
+            Stmt::Labeled(labeled) => {
+                self.generate_labeled_statement(labeled)?;
+            }
+            Stmt::Break(jump) => {
+                self.generate_break_statement(jump)?;
             }
-            Stmt::Continue => {
-                self.generate_continue_statement()?;
+            Stmt::Continue(jump) => {
+                self.generate_continue_statement(jump)?;
             }
         }
         Ok(())
     }
+
+    /// 生成标签语句代码
+    ///
+    /// 标记循环或 switch 时，标签交给该语句的跳转上下文；
+    /// 标记其他语句时，`break label;` 跳到语句之后的新基本块。
+    fn generate_labeled_statement(&mut self, labeled: &LabeledStmt) -> cayResult<()> {
+        if matches!(*labeled.body, Stmt::While(_) | Stmt::DoWhile(_) | Stmt::For(_) | Stmt::ForEach(_) | Stmt::Switch(_)) {
+            self.pending_label = Some(labeled.label.clone());
+            return self.generate_statement(&labeled.body);
+        }
+        let end_label = self.new_label("labeled.end");
+        self.enter_labeled(labeled.label.clone(), end_label.clone());
+        self.generate_statement(&labeled.body)?;
+        self.exit_loop();
+        self.emit_br(&end_label);
+        self.emit_label(&end_label);
+        Ok(())
+    }
 }
@@ -3,12 +3,14 @@
 //! 处理switch-case语句的代码生成。
 
 use crate::codegen::context::IRGenerator;
+use crate::codegen::value::LLVMValue;
 use crate::ast::*;
 use crate::error::cayResult;
 
 impl IRGenerator {
     /// 生成 switch 语句代码
     pub fn generate_switch_statement(&mut self, switch_stmt: &SwitchStmt) -> cayResult<()> {
+        let label = self.pending_label.take();
         let end_label = self.new_label("switch.end");
         let default_label = if switch_stmt.default.is_some() {
             self.new_label("switch.default")
@@ -17,8 +19,7 @@ impl IRGenerator {
         };
 
         // 生成条件表达式
-        let expr = self.generate_expression(&switch_stmt.expr)?;
-        let (expr_type, expr_val) = self.parse_typed_value(&expr);
+        let LLVMValue { ty: expr_type, repr: expr_val, .. } = self.generate_expression(&switch_stmt.expr)?;
 
         // 创建 case 标签
         let mut case_labels: Vec<(i64, String, usize)> = Vec::new();
@@ -37,80 +38,34 @@ impl IRGenerator {
         };
 
         // 生成 switch 指令
-        self.emit_line(&format!("  switch i64 {}, label %{} [", switch_val, default_label));
-        for (value, label, _) in &case_labels {
-            self.emit_line(&format!("    i64 {}, label %{}", value, label));
-        }
-        self.emit_line("  ]");
-
-        // 生成 case 块
-        let mut fallthrough = false;
-        for i in 0..case_labels.len() {
-            let (value, label, case_idx) = &case_labels[i];
-            let case = &switch_stmt.cases[*case_idx];
-            self.emit_line(&format!("{}:", label));
-
-            // 执行 case 体
-            if case.body.is_empty() {
-                // 空的 case 体，直接穿透到下一个 case
-                fallthrough = true;
-            } else {
-                for (j, stmt) in case.body.iter().enumerate() {
-                    match stmt {
-                        Stmt::Break => {
-                            // 遇到 break，跳转到 switch 结束
-                            self.emit_line(&format!("  br label %{}", end_label));
-                            fallthrough = false;
-                            break;
-                        }
-                        _ => {
-                            self.generate_statement(stmt)?;
-                            // 如果不是最后一条，继续执行
-                            if j == case.body.len() - 1 {
-                                // 最后一条语句，检查是否需要穿透
-                                fallthrough = true;
-                            }
-                        }
-                    }
-                }
-            }
+        let cases: Vec<(String, String)> = case_labels.iter()
+            .map(|(value, label, _)| (value.to_string(), label.clone()))
+            .collect();
+        self.emit_switch("i64", &switch_val, &default_label, &cases);
 
-            // 如果不是 break，穿透到下一个 case
-            if fallthrough && i < case_labels.len() - 1 {
-                let (_, next_label, _) = &case_labels[i + 1];
-                self.emit_line(&format!("  br label %{}", next_label));
-                fallthrough = false;
-            } else if fallthrough {
-                // 最后一个 case 没有 break，穿透到 default 或结束
-                if switch_stmt.default.is_some() {
-                    self.emit_line(&format!("  br label %{}", default_label));
-                } else {
-                    self.emit_line(&format!("  br label %{}", end_label));
-                }
-                fallthrough = false;
-            }
-        }
+        // break 跳到 switch 结束；嵌套在 if、块或循环中的 break 同样通过跳转上下文找到这里
+        self.enter_switch(end_label.clone(), label);
 
-        // 生成 default 块
+        // 按源代码顺序生成各分支，default 在最后；分支末尾没有跳出时穿透到下一个分支
+        let mut blocks: Vec<(String, &Vec<Stmt>)> = case_labels.iter()
+            .map(|(_, label, idx)| (label.clone(), &switch_stmt.cases[*idx].body))
+            .collect();
         if let Some(default_body) = switch_stmt.default.as_ref() {
-            self.emit_line(&format!("{}:", default_label));
-            for stmt in default_body {
-                match stmt {
-                    Stmt::Break => {
-                        self.emit_line(&format!("  br label %{}", end_label));
-                        break;
-                    }
-                    _ => {
-                        self.generate_statement(stmt)?;
-                    }
-                }
+            blocks.push((default_label.clone(), default_body));
+        }
+        for (i, (label, body)) in blocks.iter().enumerate() {
+            self.emit_label(label);
+            for stmt in body.iter() {
+                self.generate_statement(stmt)?;
             }
-            // 确保 default 最后跳转到结束
-            self.emit_line(&format!("  br label %{}", end_label));
+            let next = blocks.get(i + 1).map_or(&end_label, |(next, _)| next);
+            self.emit_br(next);
         }
 
+        self.exit_loop();
+
         // 结束块
-        self.emit_line(&format!("{}:", end_label));
+        self.emit_label(&end_label);
 
         Ok(())
     }
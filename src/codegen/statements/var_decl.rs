@@ -51,7 +51,8 @@ impl IRGenerator {
             "float" => Some(Type::Float32),
             "double" => Some(Type::Float64),
             "i1" => Some(Type::Bool),
-            "i8" => Some(Type::Char),
+            "i8" => Some(Type::Int8),
+            "i16" => Some(Type::Int16),
             "i8*" => Some(Type::String),
             _ => {
                 // 检查是否是对象指针类型
@@ -67,10 +68,12 @@ impl IRGenerator {
 
     /// 生成变量声明代码
     pub fn generate_var_decl(&mut self, var: &VarDecl) -> cayResult<()> {
-        // 处理 auto 类型推断
+        // 处理 auto/var/let 类型推断：优先使用语义分析记录的类型
         let actual_type = if var.var_type == Type::Auto {
-            // 从初始化器推断类型
-            if let Some(init) = &var.initializer {
+            if let Some(inferred) = self.inferred_var_types.get(&var.id) {
+                inferred.clone()
+            } else if let Some(init) = &var.initializer {
+                // 没有语义分析结果时（例如单独使用代码生成器），从初始化器粗略推断
                 self.infer_type_from_expr(init).unwrap_or(Type::Int32)
             } else {
                 return Err(crate::error::semantic_error(
@@ -83,12 +86,11 @@ impl IRGenerator {
         };
 
         let var_type = self.type_to_llvm(&actual_type);
-        let align = self.get_type_align(&var_type);  // 获取对齐
 
         // 使用作用域管理器生成唯一的 LLVM 变量名
-        let llvm_name = self.scope_manager.declare_var(&var.name, &var_type);
+        let llvm_name = self.scope_manager.declare_var(&var.name, &var_type, &actual_type);
 
-        self.emit_line(&format!("  %{} = alloca {}, align {}", llvm_name, var_type, align));
+        self.emit_alloca(&llvm_name, &var_type);
         // 同时存储到旧系统以保持兼容性
         self.var_types.insert(var.name.clone(), var_type.clone());
         // 如果变量类型是对象，记录其类名以便后续方法调用解析
@@ -100,11 +102,10 @@ impl IRGenerator {
             // 特殊处理数组初始化，传递目标类型信息
             if let Expr::ArrayInit(array_init) = init {
                 let value = self.generate_array_init_with_type(array_init, &actual_type)?;
-                self.emit_line(&format!("  store {}, {}* %{}",
-                    value, var_type, llvm_name));
+                self.emit_store(&var_type, &value.repr, &format!("%{}", llvm_name));
             } else {
                 let value = self.generate_expression(init)?;
-                let (value_type, val) = self.parse_typed_value(&value);
+                let (value_type, val) = (value.ty.as_str(), value.repr.as_str());
 
                 // 如果值类型与变量类型不匹配，需要转换
                 if value_type != var_type {
@@ -114,19 +115,17 @@ impl IRGenerator {
                     if value_type == "double" && var_type == "float" {
                         // double -> float 转换
                         self.emit_line(&format!("  {} = fptrunc double {} to float", temp, val));
-                        let align = self.get_type_align("float");
-                        self.emit_line(&format!("  store float {}, float* %{}, align {}", temp, llvm_name, align));
+                        self.emit_store("float", &temp, &format!("%{}", llvm_name));
                     } else if value_type == "float" && var_type == "double" {
                         // float -> double 转换
                         self.emit_line(&format!("  {} = fpext float {} to double", temp, val));
-                        let align = self.get_type_align("double");
-                        self.emit_line(&format!("  store double {}, double* %{}, align {}", temp, llvm_name, align));
+                        self.emit_store("double", &temp, &format!("%{}", llvm_name));
                     }
                     // 指针类型转换 (bitcast)
                     else if value_type.ends_with("*") && var_type.ends_with("*") {
                         self.emit_line(&format!("  {} = bitcast {} {} to {}",
                             temp, value_type, val, var_type));
-                        self.emit_line(&format!("  store {} {}, {}* %{}, align {}", var_type, temp, var_type, llvm_name, align));
+                        self.emit_store(&var_type, &temp, &format!("%{}", llvm_name));
                     }
                     // 整数类型转换
                     else if value_type.starts_with("i") && var_type.starts_with("i") && !value_type.ends_with("*") && !var_type.ends_with("*") {
@@ -142,29 +141,27 @@ impl IRGenerator {
                             self.emit_line(&format!("  {} = trunc {} {} to {}",
                                 temp, value_type, val, var_type));
                         }
-                        self.emit_line(&format!("  store {} {}, {}* %{}, align {}", var_type, temp, var_type, llvm_name, align));
+                        self.emit_store(&var_type, &temp, &format!("%{}", llvm_name));
                     }
                     // 整数到浮点数转换
                     else if value_type.starts_with("i") && (var_type == "float" || var_type == "double") {
                         self.emit_line(&format!("  {} = sitofp {} {} to {}",
                             temp, value_type, val, var_type));
-                        self.emit_line(&format!("  store {} {}, {}* %{}, align {}", var_type, temp, var_type, llvm_name, align));
+                        self.emit_store(&var_type, &temp, &format!("%{}", llvm_name));
                     }
                     // 浮点数到整数转换
                     else if (value_type == "float" || value_type == "double") && var_type.starts_with("i") {
                         self.emit_line(&format!("  {} = fptosi {} {} to {}",
                             temp, value_type, val, var_type));
-                        self.emit_line(&format!("  store {} {}, {}* %{}, align {}", var_type, temp, var_type, llvm_name, align));
+                        self.emit_store(&var_type, &temp, &format!("%{}", llvm_name));
                     }
                     else {
                         // 类型不兼容，直接存储（可能会出错）
-                        self.emit_line(&format!("  store {}, {}* %{}",
-                            value, var_type, llvm_name));
+                        self.emit_store(value_type, val, &format!("%{}", llvm_name));
                     }
                 } else {
                     // 类型匹配，直接存储
-                    self.emit_line(&format!("  store {}, {}* %{}",
-                        value, var_type, llvm_name));
+                    self.emit_store(&var_type, val, &format!("%{}", llvm_name));
                 }
             }
         }
@@ -0,0 +1,80 @@
+//! 代码生成中的类型化值
+//!
+//! 表达式生成的结果是 `LLVMValue`，同时携带 LLVM 类型、值在 IR 中的写法和源语言中的静态类型，
+//! 而不是 "i32 %t1" 这样需要重新拆分的字符串。LLVM 类型相同的值（char 与 int、String 与对象）
+//! 由 `lang_ty` 区分。
+
+use std::fmt;
+use crate::types::Type;
+
+/// LLVM 类型的文本写法，如 `i32`、`double`、`i8*`、`i32**`
+pub type LLVMType = String;
+
+/// 生成表达式得到的值
+#[derive(Debug, Clone, PartialEq)]
+pub struct LLVMValue {
+    pub ty: LLVMType,   // LLVM 类型
+    pub repr: String,   // 值在 IR 中的写法：寄存器 `%t1`、常量 `42`、`null` 或常量表达式
+    pub lang_ty: Type,  // 源语言中的静态类型
+}
+
+impl LLVMValue {
+    pub fn new(ty: impl Into<LLVMType>, repr: impl Into<String>, lang_ty: Type) -> Self {
+        Self { ty: ty.into(), repr: repr.into(), lang_ty }
+    }
+
+    /// 只知道 LLVM 类型的值（运行时函数的结果、内部的临时值），语言类型按 LLVM 类型推定
+    ///
+    /// `i8*` 推定为 String；对象和 byte 数组需要用 `new` 或 `with_lang_ty` 给出准确的类型。
+    /// 带 NodeId 的表达式在 `generate_expression` 中改用语义分析记录的类型。
+    pub fn from_llvm(ty: impl Into<LLVMType>, repr: impl Into<String>) -> Self {
+        let ty = ty.into();
+        let lang_ty = lang_type_of(&ty);
+        Self { ty, repr: repr.into(), lang_ty }
+    }
+
+    /// 无返回值的调用的结果
+    pub fn void() -> Self {
+        Self::new("void", "", Type::Void)
+    }
+
+    pub fn is_void(&self) -> bool {
+        self.ty == "void"
+    }
+
+    /// 替换语言类型，LLVM 类型和写法不变
+    pub fn with_lang_ty(mut self, lang_ty: Type) -> Self {
+        self.lang_ty = lang_ty;
+        self
+    }
+}
+
+/// 以 IR 操作数的形式输出（`i32 %t1`），可以直接用于 call 的实参和 ret
+impl fmt::Display for LLVMValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.repr.is_empty() {
+            write!(f, "{}", self.ty)
+        } else {
+            write!(f, "{} {}", self.ty, self.repr)
+        }
+    }
+}
+
+/// 由 LLVM 类型推定语言类型（`Type::llvm_of` 的逆映射，有歧义时取最常见的类型）
+fn lang_type_of(ty: &str) -> Type {
+    match ty {
+        "void" => Type::Void,
+        "i1" => Type::Bool,
+        "i8" => Type::Int8,
+        "i16" => Type::Int16,
+        "i32" => Type::Int32,
+        "i64" => Type::Int64,
+        "float" => Type::Float32,
+        "double" => Type::Float64,
+        "i8*" => Type::String,
+        _ => match ty.strip_suffix('*') {
+            Some(elem) => Type::Array(Box::new(lang_type_of(elem))),
+            None => Type::Object("Object".to_string()),
+        },
+    }
+}
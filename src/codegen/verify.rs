@@ -0,0 +1,443 @@
+//! 生成的 IR 的自检（`--verify-ir`）
+//!
+//! [`FunctionBody::verify`] 总会检查基本块结构（标签唯一、终止指令、跳转目标）。
+//! 打开 `--verify-ir` 后，每个函数结束时再按指令文本检查值的定义和使用：
+//! - 每个 `%` 值只定义一次，用到的值都有定义（不检查支配关系）；
+//! - 能从指令文本确定类型的值，在 load/store/运算/比较/转换/select/phi/call 实参等处的类型与定义一致；
+//! - `ret` 的类型与函数的返回类型一致，条件跳转的条件是 `i1`。
+//!
+//! 只识别代码生成器实际输出的指令形式，确定不了类型的值跳过类型检查。
+//! 发现的问题带有生成该指令时正在处理的 Cavvy 源码位置，由 `IRGenerator` 报告为代码生成错误。
+
+use std::collections::{HashMap, HashSet};
+
+use crate::error::SourceLocation;
+use super::ir::FunctionBody;
+
+/// 自检发现的问题
+#[derive(Debug, Clone)]
+pub struct IrIssue {
+    pub message: String,
+    /// 生成出错指令时正在处理的源码位置
+    pub location: Option<SourceLocation>,
+}
+
+/// 函数签名：返回类型和形参（类型、不带 `%` 的名字）
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Signature {
+    pub ret_ty: String,
+    pub params: Vec<(String, String)>,
+}
+
+/// `define` 与返回类型之间可能出现的链接属性
+const LINKAGE_WORDS: &[&str] = &["internal", "private", "linkonce_odr", "weak_odr", "dso_local", "external"];
+
+impl Signature {
+    /// 解析函数头 `define <ret> @<name>(<params>) {`
+    pub fn parse_define(line: &str) -> Option<Signature> {
+        let rest = line.trim().strip_prefix("define ")?;
+        let at = rest.find(" @")?;
+        let mut ret_ty = rest[..at].trim();
+        while let Some((word, tail)) = ret_ty.split_once(' ') {
+            if !LINKAGE_WORDS.contains(&word) {
+                break;
+            }
+            ret_ty = tail.trim_start();
+        }
+        let after = &rest[at + 2..];
+        let open = after.find('(')?;
+        let close = matching_paren(after, open)?;
+        let params = split_top_level(&after[open + 1..close])
+            .into_iter()
+            .filter(|param| !param.is_empty() && *param != "...")
+            .filter_map(|param| {
+                let (ty, value) = split_operand(param)?;
+                Some((ty.to_string(), local_name(value)?.to_string()))
+            })
+            .collect();
+        Some(Signature { ret_ty: ret_ty.to_string(), params })
+    }
+}
+
+/// 检查函数体中值的定义、使用和类型（签名的返回类型为空时不检查 `ret`）
+pub fn verify_values(signature: &Signature, body: &FunctionBody) -> Result<(), IrIssue> {
+    let mut lines: Vec<(String, Option<&SourceLocation>)> = body.entry_prologue.iter()
+        .map(|inst| (inst.to_string(), None))
+        .collect();
+    for block in &body.blocks {
+        for (inst, location) in block.instructions.iter().zip(&block.locations) {
+            lines.push((inst.to_string(), location.as_ref()));
+        }
+    }
+    let lines: Vec<(String, Option<&SourceLocation>)> = lines.into_iter()
+        .filter(|(text, _)| !text.starts_with(';'))
+        .collect();
+    let labels: HashSet<&str> = body.blocks.iter().map(|block| block.label.as_str()).collect();
+    let issue = |message: String, location: Option<&SourceLocation>| IrIssue { message, location: location.cloned() };
+
+    // 第一遍：收集定义及其类型
+    let mut defs: HashMap<String, Option<String>> = HashMap::new();
+    for (ty, name) in &signature.params {
+        if defs.insert(name.clone(), Some(ty.clone())).is_some() {
+            return Err(issue(format!("parameter '%{}' is declared more than once", name), None));
+        }
+    }
+    let parsed: Vec<ParsedInstruction> = lines.iter().map(|(text, _)| ParsedInstruction::parse(text)).collect();
+    for (inst, (text, location)) in parsed.iter().zip(&lines) {
+        if let Some(dest) = &inst.dest
+            && defs.insert(dest.clone(), inst.result_ty.clone()).is_some()
+        {
+            return Err(issue(format!("value '%{}' is defined more than once (`{}`)", dest, text), *location));
+        }
+    }
+
+    // 第二遍：检查使用
+    for (inst, (text, location)) in parsed.iter().zip(&lines) {
+        for name in used_locals(text, inst.dest.as_deref()) {
+            if !defs.contains_key(name) && !labels.contains(name) {
+                return Err(issue(format!("use of undefined value '%{}' (`{}`)", name, text), *location));
+            }
+        }
+        for (expected, value) in &inst.typed_uses {
+            let Some(name) = local_name(value) else { continue };
+            if let Some(Some(actual)) = defs.get(name)
+                && actual != expected
+            {
+                return Err(issue(
+                    format!("'%{}' has type {} but is used as {} (`{}`)", name, actual, expected, text),
+                    *location,
+                ));
+            }
+        }
+        if let Some(message) = &inst.mismatch {
+            return Err(issue(format!("{} (`{}`)", message, text), *location));
+        }
+        if let Some(ret_ty) = &inst.ret_ty
+            && !signature.ret_ty.is_empty()
+            && *ret_ty != signature.ret_ty
+        {
+            return Err(issue(
+                format!("returns {} from a function returning {} (`{}`)", ret_ty, signature.ret_ty, text),
+                *location,
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 从一行指令文本中提取的信息
+#[derive(Debug, Default)]
+struct ParsedInstruction {
+    /// 定义的值（不带 `%`）
+    dest: Option<String>,
+    /// 定义的值的类型，无法确定时为 `None`
+    result_ty: Option<String>,
+    /// 带显式类型的操作数：(期望类型, 操作数写法)
+    typed_uses: Vec<(String, String)>,
+    /// 指令内部的类型不一致（如 `load i32, i64* %p`）
+    mismatch: Option<String>,
+    /// `ret` 指令返回的类型
+    ret_ty: Option<String>,
+}
+
+const BINARY_OPS: &[&str] = &[
+    "add", "sub", "mul", "udiv", "sdiv", "urem", "srem", "shl", "lshr", "ashr", "and", "or", "xor",
+    "fadd", "fsub", "fmul", "fdiv", "frem",
+];
+const CAST_OPS: &[&str] = &[
+    "trunc", "zext", "sext", "fptrunc", "fpext", "fptoui", "fptosi", "uitofp", "sitofp",
+    "ptrtoint", "inttoptr", "bitcast", "addrspacecast",
+];
+const FLAGS: &[&str] = &["nsw", "nuw", "exact", "fast", "nnan", "ninf", "nsz", "arcp", "contract", "afn", "reassoc", "inbounds"];
+
+impl ParsedInstruction {
+    fn parse(text: &str) -> ParsedInstruction {
+        let mut inst = ParsedInstruction::default();
+        let rest = match text.split_once(" = ") {
+            Some((dest, rest)) if dest.starts_with('%') && !dest.contains(' ') => {
+                inst.dest = Some(dest[1..].to_string());
+                rest
+            }
+            _ => text,
+        };
+        let (mut op, mut body) = rest.split_once(' ').unwrap_or((rest, ""));
+        if op == "tail" {
+            (op, body) = body.split_once(' ').unwrap_or((body, ""));
+        }
+        // 去掉操作码后的修饰词（nsw、inbounds 等）
+        while let Some((word, tail)) = body.split_once(' ') {
+            if !FLAGS.contains(&word) {
+                break;
+            }
+            body = tail;
+        }
+        let parts = split_top_level(body);
+
+        match op {
+            "alloca" => {
+                inst.result_ty = parts.first().map(|ty| format!("{}*", ty));
+            }
+            "load" => {
+                if let [ty, ptr, ..] = parts.as_slice() {
+                    inst.result_ty = Some(ty.to_string());
+                    inst.use_pointer(ty, ptr);
+                }
+            }
+            "store" => {
+                if let [value, ptr, ..] = parts.as_slice()
+                    && let Some((ty, value)) = split_operand(value)
+                {
+                    inst.typed_uses.push((ty.to_string(), value.to_string()));
+                    inst.use_pointer(ty, ptr);
+                }
+            }
+            "getelementptr" => {
+                if let [ty, ptr, indices @ ..] = parts.as_slice() {
+                    inst.use_pointer(ty, ptr);
+                    inst.result_ty = match indices.len() {
+                        1 => Some(format!("{}*", ty)),
+                        2 => array_element(ty).map(|elem| format!("{}*", elem)),
+                        _ => None,
+                    };
+                }
+            }
+            "icmp" | "fcmp" => {
+                let body = body.split_once(' ').map_or("", |(_, operands)| operands);
+                let parts = split_top_level(body);
+                inst.use_binary(&parts);
+                inst.result_ty = Some("i1".to_string());
+            }
+            "select" => {
+                if let [cond, a, b] = parts.as_slice() {
+                    for operand in [cond, a, b] {
+                        if let Some((ty, value)) = split_operand(operand) {
+                            inst.typed_uses.push((ty.to_string(), value.to_string()));
+                        }
+                    }
+                    inst.result_ty = split_operand(a).map(|(ty, _)| ty.to_string());
+                }
+            }
+            "phi" => {
+                if let Some(open) = body.find(" [") {
+                    let ty = &body[..open];
+                    for incoming in split_top_level(&body[open..]) {
+                        let inner = incoming.trim().trim_start_matches('[').trim_end_matches(']');
+                        if let Some(value) = split_top_level(inner).first() {
+                            inst.typed_uses.push((ty.to_string(), value.to_string()));
+                        }
+                    }
+                    inst.result_ty = Some(ty.to_string());
+                }
+            }
+            "call" => inst.parse_call(body),
+            "ret" => {
+                if body == "void" {
+                    inst.ret_ty = Some("void".to_string());
+                } else if let Some((ty, value)) = split_operand(body) {
+                    inst.ret_ty = Some(ty.to_string());
+                    inst.typed_uses.push((ty.to_string(), value.to_string()));
+                }
+            }
+            "br" => {
+                if let Some(cond) = parts.first().filter(|cond| cond.starts_with("i1 "))
+                    && let Some((ty, value)) = split_operand(cond)
+                {
+                    inst.typed_uses.push((ty.to_string(), value.to_string()));
+                }
+            }
+            "switch" => {
+                if let Some((ty, value)) = parts.first().and_then(|operand| split_operand(operand)) {
+                    inst.typed_uses.push((ty.to_string(), value.to_string()));
+                }
+            }
+            op if BINARY_OPS.contains(&op) => {
+                inst.use_binary(&parts);
+                inst.result_ty = parts.first().and_then(|operand| split_operand(operand)).map(|(ty, _)| ty.to_string());
+            }
+            "fneg" => {
+                if let Some((ty, value)) = split_operand(body) {
+                    inst.typed_uses.push((ty.to_string(), value.to_string()));
+                    inst.result_ty = Some(ty.to_string());
+                }
+            }
+            op if CAST_OPS.contains(&op) => {
+                if let Some(to) = body.rfind(" to ") {
+                    if let Some((ty, value)) = split_operand(&body[..to]) {
+                        inst.typed_uses.push((ty.to_string(), value.to_string()));
+                    }
+                    inst.result_ty = Some(body[to + 4..].trim().to_string());
+                }
+            }
+            _ => {}
+        }
+        inst
+    }
+
+    /// `ty` 类型的访问通过指针操作数 `ptr`（`T* %p`）
+    fn use_pointer(&mut self, ty: &str, ptr: &str) {
+        let Some((ptr_ty, ptr_value)) = split_operand(ptr) else { return };
+        let expected = format!("{}*", ty);
+        if ptr_ty != expected {
+            self.mismatch = Some(format!("pointer operand has type {} but the accessed type is {}", ptr_ty, ty));
+        }
+        self.typed_uses.push((ptr_ty.to_string(), ptr_value.to_string()));
+    }
+
+    /// `T a, b` 形式的两个操作数
+    fn use_binary(&mut self, parts: &[&str]) {
+        if let [first, second] = parts
+            && let Some((ty, a)) = split_operand(first)
+        {
+            self.typed_uses.push((ty.to_string(), a.to_string()));
+            self.typed_uses.push((ty.to_string(), second.trim().to_string()));
+        }
+    }
+
+    /// `call <ret> [(<sig>)] <callee>(<args>)`
+    fn parse_call(&mut self, body: &str) {
+        let body = body.trim();
+        let Some(close) = body.rfind(')') else { return };
+        let Some(open) = matching_open(body, close) else { return };
+        for arg in split_top_level(&body[open + 1..close]) {
+            if let Some((ty, value)) = split_operand(arg) {
+                self.typed_uses.push((ty.to_string(), value.to_string()));
+            }
+        }
+        let head = body[..open].trim_end();
+        let Some((ret, _callee)) = head.rsplit_once(' ') else { return };
+        // 变参函数的调用带有函数类型：`call i32 (i8*, ...) @printf(...)`
+        let ret = match ret.find(" (") {
+            Some(sig) if ret.ends_with(')') => &ret[..sig],
+            _ => ret,
+        };
+        if self.dest.is_some() && !ret.contains('(') {
+            self.result_ty = Some(ret.trim().to_string());
+        }
+    }
+}
+
+/// `[N x T]` 的元素类型
+fn array_element(ty: &str) -> Option<&str> {
+    let inner = ty.strip_prefix('[')?.strip_suffix(']')?;
+    inner.split_once(" x ").map(|(_, elem)| elem.trim())
+}
+
+/// 指令文本中用到的局部值名（不含定义的值、跳转标签和 `%class.*` 类型名）
+fn used_locals<'a>(text: &'a str, dest: Option<&str>) -> Vec<&'a str> {
+    let mut names = Vec::new();
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let start = i + 1;
+            let mut end = start;
+            while end < bytes.len() && is_name_byte(bytes[end]) {
+                end += 1;
+            }
+            let name = &text[start..end];
+            let is_label = text[..i].ends_with("label ");
+            if !name.is_empty() && !is_label && !name.starts_with("class.") && Some(name) != dest {
+                names.push(name);
+            }
+            i = end;
+        } else {
+            i += 1;
+        }
+    }
+    names
+}
+
+fn is_name_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || matches!(b, b'_' | b'.' | b'$' | b'-')
+}
+
+/// 操作数写法对应的局部值名（`%t1` → `t1`），常量和全局值返回 `None`
+fn local_name(value: &str) -> Option<&str> {
+    let name = value.trim().strip_prefix('%')?;
+    (!name.is_empty() && !name.starts_with("class.") && name.bytes().all(is_name_byte)).then_some(name)
+}
+
+/// 常量表达式的开头，如 `i8* getelementptr inbounds (...)` 中的值从这些词开始
+const CONSTANT_EXPRESSIONS: &[&str] = &["getelementptr ", "bitcast ", "ptrtoint ", "inttoptr ", "addrspacecast "];
+
+/// 把 `T v` 拆成类型和值：值是常量表达式或最后一个不在括号内的词
+fn split_operand(operand: &str) -> Option<(&str, &str)> {
+    let operand = operand.trim();
+    let mut depth = 0i32;
+    let mut split = None;
+    for (index, c) in operand.char_indices() {
+        match c {
+            '(' | '[' | '{' | '<' => depth += 1,
+            ')' | ']' | '}' | '>' => depth -= 1,
+            ' ' if depth == 0 => {
+                split = Some(index);
+                if CONSTANT_EXPRESSIONS.iter().any(|kw| operand[index + 1..].starts_with(kw)) {
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    let index = split?;
+    let (ty, value) = (operand[..index].trim(), operand[index + 1..].trim());
+    (!ty.is_empty() && !value.is_empty()).then_some((ty, value))
+}
+
+/// 按不在括号内的逗号拆分
+fn split_top_level(text: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut start = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '(' | '[' | '{' | '<' => depth += 1,
+            ')' | ']' | '}' | '>' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(text[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    let last = text[start..].trim();
+    if !last.is_empty() {
+        parts.push(last);
+    }
+    parts
+}
+
+/// 与 `open` 处的 `(` 配对的 `)` 的位置
+fn matching_paren(text: &str, open: usize) -> Option<usize> {
+    let mut depth = 0i32;
+    for (index, c) in text[open..].char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + index);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// 与 `close` 处的 `)` 配对的 `(` 的位置
+fn matching_open(text: &str, close: usize) -> Option<usize> {
+    let mut depth = 0i32;
+    for (index, c) in text[..=close].char_indices().rev() {
+        match c {
+            ')' => depth += 1,
+            '(' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
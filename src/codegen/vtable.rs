@@ -0,0 +1,178 @@
+//! 虚方法表
+//!
+//! 每个类生成一个函数指针数组作为虚表，对象头中保存指向本类虚表的指针：
+//!
+//! ```text
+//! @Dog.vtable = internal constant [2 x i8*] [
+//!   i8* bitcast (i8* (i8*)* @Dog.speak to i8*),       ; 覆盖 Animal.speak
+//!   i8* bitcast (i32 (i8*)* @Animal.legCount to i8*)  ; 继承
+//! ]
+//! ```
+//!
+//! 子类虚表以父类的全部槽位开头，覆盖的方法替换对应槽位，新增的方法追加在末尾，
+//! 因此同一方法在整个继承链中槽位下标相同，调用方只需按静态类型查到下标即可间接调用。
+//! 静态、final、private 方法不会被覆盖，不占用新槽位，仍然直接调用。
+//! 如果静态类型的所有子类都没有覆盖某个方法，调用也直接生成（类层次分析去虚化）。
+
+use std::collections::HashMap;
+use crate::ast::*;
+use crate::codegen::context::{IRGenerator, VtableSlot};
+
+impl IRGenerator {
+    /// 计算程序中所有类的虚表（父类先于子类）
+    pub fn compute_vtables(&mut self, program: &Program) {
+        let classes: HashMap<&str, &ClassDecl> = program.classes.iter()
+            .map(|c| (c.name.as_str(), c))
+            .collect();
+        for class in &program.classes {
+            self.compute_vtable_recursive(class, &classes, &mut Vec::new());
+        }
+    }
+
+    fn compute_vtable_recursive<'a>(
+        &mut self,
+        class: &'a ClassDecl,
+        classes: &HashMap<&str, &'a ClassDecl>,
+        visiting: &mut Vec<&'a str>,
+    ) {
+        if self.vtables.contains_key(&class.name) || visiting.contains(&class.name.as_str()) {
+            return;
+        }
+        visiting.push(&class.name);
+
+        let mut slots = class.parent.as_deref()
+            .and_then(|parent| classes.get(parent).copied())
+            .and_then(|parent| {
+                self.compute_vtable_recursive(parent, classes, visiting);
+                self.vtables.get(&parent.name).cloned()
+            })
+            .unwrap_or_default();
+
+        for member in &class.members {
+            let ClassMember::Method(method) = member else {
+                continue;
+            };
+            if method.modifiers.contains(&Modifier::Static) || method.modifiers.contains(&Modifier::Native) {
+                continue;
+            }
+            let fn_name = self.generate_method_name(&class.name, method);
+            let key = Self::vtable_key(&class.name, &fn_name);
+            // 抽象方法没有函数体，槽位留空
+            let target = method.body.is_some().then_some(fn_name);
+
+            if let Some(slot) = slots.iter_mut().find(|s| s.key == key) {
+                slot.fn_name = target;
+            } else if !method.modifiers.contains(&Modifier::Final) && !method.modifiers.contains(&Modifier::Private) {
+                let mut params = vec!["i8*".to_string()];
+                params.extend(method.params.iter().map(|p| self.type_to_llvm(&p.param_type)));
+                slots.push(VtableSlot {
+                    key,
+                    fn_name: target,
+                    fn_type: format!("{} ({})*", self.type_to_llvm(&method.return_type), params.join(", ")),
+                });
+            }
+        }
+
+        self.vtables.insert(class.name.clone(), slots);
+        visiting.pop();
+    }
+
+    /// 槽位键：去掉类名前缀的函数名（方法名 + 参数签名）
+    fn vtable_key(class_name: &str, fn_name: &str) -> String {
+        fn_name.strip_prefix(class_name)
+            .and_then(|rest| rest.strip_prefix('.'))
+            .unwrap_or(fn_name)
+            .to_string()
+    }
+
+    /// 虚表全局变量名: @ClassName.vtable
+    pub fn vtable_symbol(&self, class_name: &str) -> String {
+        format!("@{}.vtable", class_name)
+    }
+
+    /// 指向类虚表首元素的常量表达式（类型为 i8**）
+    pub fn vtable_ptr_constant(&self, class_name: &str) -> Option<String> {
+        let slots = self.vtables.get(class_name)?;
+        Some(format!("getelementptr inbounds ([{} x i8*], [{} x i8*]* {}, i64 0, i64 0)",
+            slots.len(), slots.len(), self.vtable_symbol(class_name)))
+    }
+
+    /// 查找方法在类虚表中的槽位
+    ///
+    /// `fn_name` 为静态解析得到的函数名，方法不可覆盖（不在虚表中）时返回 `None`。
+    pub fn find_vtable_slot(&self, class_name: &str, fn_name: &str) -> Option<(usize, &VtableSlot)> {
+        let key = Self::vtable_key(class_name, fn_name);
+        self.vtables.get(class_name)?
+            .iter()
+            .enumerate()
+            .find(|(_, slot)| slot.key == key)
+    }
+
+    /// 是否需要通过虚表调用：静态类型的某个子类覆盖了该槽位，或槽位是抽象方法
+    pub fn needs_virtual_dispatch(&self, class_name: &str, slot_index: usize) -> bool {
+        let Some(target) = self.vtables.get(class_name).and_then(|slots| slots.get(slot_index)) else {
+            return false;
+        };
+        if target.fn_name.is_none() {
+            return true;
+        }
+        self.vtables.iter().any(|(other, slots)| {
+            other != class_name
+                && self.is_subclass_of(other, class_name)
+                && slots.get(slot_index).is_some_and(|slot| slot.fn_name != target.fn_name)
+        })
+    }
+
+    /// `class_name` 是否（间接）继承自 `ancestor`
+    fn is_subclass_of(&self, class_name: &str, ancestor: &str) -> bool {
+        let Some(registry) = self.type_registry.as_ref() else {
+            return false;
+        };
+        let mut current = registry.get_class(class_name).and_then(|c| c.parent.as_deref());
+        while let Some(parent) = current {
+            if parent == ancestor {
+                return true;
+            }
+            current = registry.get_class(parent).and_then(|c| c.parent.as_deref());
+        }
+        false
+    }
+
+    /// 生成所有类的虚表定义（按类名排序，保证输出稳定）
+    pub fn emit_vtable_declarations(&self) -> String {
+        let mut class_names: Vec<&String> = self.vtables.keys().collect();
+        class_names.sort();
+
+        let mut result = String::new();
+        for class_name in class_names {
+            let slots = &self.vtables[class_name];
+            let entries: Vec<String> = slots.iter()
+                .map(|slot| match &slot.fn_name {
+                    Some(fn_name) => format!("i8* bitcast ({} @{} to i8*)", slot.fn_type, fn_name),
+                    None => "i8* null".to_string(),
+                })
+                .collect();
+            result.push_str(&format!("{} = internal constant [{} x i8*] [{}]\n",
+                self.vtable_symbol(class_name), slots.len(), entries.join(", ")));
+        }
+        result
+    }
+
+    /// 从对象头加载虚表，取出槽位中的函数指针并转换为方法的函数类型
+    ///
+    /// # Arguments
+    /// * `class_name` - 对象的静态类型（槽位所在的类）
+    /// * `slot_index` - 槽位下标
+    /// * `fn_type` - 方法的函数指针类型
+    /// * `obj_ptr` - 对象指针 (i8*)
+    pub fn emit_virtual_method_ptr(&mut self, class_name: &str, slot_index: usize, fn_type: &str, obj_ptr: &str) -> String {
+        let vtable_field = self.emit_vtable_field_ptr(class_name, obj_ptr);
+        let vtable = self.emit_load("i8**", &vtable_field);
+        let slot_ptr = self.new_temp();
+        self.emit_line(&format!("  {} = getelementptr inbounds i8*, i8** {}, i64 {}", slot_ptr, vtable, slot_index));
+        let raw_fn = self.emit_load("i8*", &slot_ptr);
+        let fn_ptr = self.new_temp();
+        self.emit_line(&format!("  {} = bitcast i8* {} to {}", fn_ptr, raw_fn, fn_type));
+        fn_ptr
+    }
+}
@@ -0,0 +1,305 @@
+//! 诊断信息
+//!
+//! 把编译错误（[`cayError`]）转换为带稳定错误码的诊断，并渲染出错的源代码行和下划线：
+//!
+//! ```text
+//! error[E0011]: Undefined variable: x
+//!  --> main.cay:3:17
+//!   |
+//! 3 |         int y = x + 1;
+//!   |                 ^
+//!   = 提示: 变量未定义。请在使用前声明变量
+//! ```
+//!
+//! 一次编译收集到的多个错误（[`cayError::Multiple`]）逐个渲染为独立的诊断，
+//! 被导入模块中的错误（[`cayError::Module`]）使用该模块的文件名和源码。
+
+use std::collections::HashMap;
+use std::fmt;
+use crate::error::{cayError, SourceLocation};
+
+/// 稳定的错误码，显示为 `E0001` 形式
+///
+/// 编号一经分配不再改变或复用，新的错误种类追加在 [`ERROR_CODES`] 末尾。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ErrorCode(pub u16);
+
+impl ErrorCode {
+    /// 错误码对应的错误种类说明
+    pub fn description(self) -> &'static str {
+        ERROR_CODES.iter()
+            .find(|entry| entry.code == self.0)
+            .map_or("未知错误", |entry| entry.description)
+    }
+
+    /// 按错误的种类和消息确定错误码；多个错误取第一个，被导入模块中的错误取其内部错误
+    pub fn of(error: &cayError) -> Self {
+        let (category, message) = match error {
+            cayError::Io(_) => return ErrorCode(1),
+            cayError::Preprocessor { .. } => return ErrorCode(2),
+            cayError::CodeGen { .. } => return ErrorCode(19),
+            cayError::Llvm(_) => return ErrorCode(20),
+            cayError::TypeMismatch { .. } => return ErrorCode(10),
+            cayError::UndefinedIdentifier { .. } => return ErrorCode(11),
+            cayError::DuplicateDefinition { .. } => return ErrorCode(13),
+            cayError::Module { error, .. } => return Self::of(error),
+            cayError::Multiple(errors) => return errors.first().map_or(ErrorCode(9), Self::of),
+            cayError::Lexer { message, .. } => (Category::Lexer, message),
+            cayError::Parser { message, .. } => (Category::Parser, message),
+            cayError::Semantic { message, .. } => (Category::Semantic, message),
+        };
+        ERROR_CODES.iter()
+            .find(|entry| entry.category == Some(category) && entry.patterns.iter().any(|p| message.contains(p)))
+            .map_or(category.fallback(), |entry| ErrorCode(entry.code))
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "E{:04}", self.0)
+    }
+}
+
+/// 按消息细分错误码的错误类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Category {
+    Lexer,
+    Parser,
+    Semantic,
+}
+
+impl Category {
+    /// 没有更具体的错误码时使用的通用错误码
+    fn fallback(self) -> ErrorCode {
+        match self {
+            Category::Lexer => ErrorCode(3),
+            Category::Parser => ErrorCode(6),
+            Category::Semantic => ErrorCode(9),
+        }
+    }
+}
+
+/// 错误码表中的一项
+struct ErrorCodeEntry {
+    code: u16,
+    description: &'static str,
+    /// 按消息细分的错误码所属的类别；`None` 表示直接由错误种类确定
+    category: Option<Category>,
+    /// 消息包含其中任一片段时使用该错误码（同一类别内按表中顺序匹配）
+    patterns: &'static [&'static str],
+}
+
+const fn entry(code: u16, description: &'static str, category: Option<Category>, patterns: &'static [&'static str]) -> ErrorCodeEntry {
+    ErrorCodeEntry { code, description, category, patterns }
+}
+
+/// 全部错误码
+const ERROR_CODES: &[ErrorCodeEntry] = &[
+    entry(1, "文件读写错误", None, &[]),
+    entry(2, "预处理指令错误", None, &[]),
+    entry(3, "词法错误", None, &[]),
+    entry(4, "未闭合的字符串或字符字面量", Some(Category::Lexer), &["Unterminated"]),
+    entry(5, "无效的转义序列", Some(Category::Lexer), &["Invalid escape", "escape"]),
+    entry(6, "语法错误", None, &[]),
+    entry(7, "缺少分号", Some(Category::Parser), &["Expected ';'"]),
+    entry(8, "import 声明位置错误", Some(Category::Parser), &["Import declarations must come before"]),
+    entry(9, "语义错误", None, &[]),
+    entry(14, "给 final 变量赋值", Some(Category::Semantic), &["final variable", "final field", "final parameter"]),
+    entry(13, "重复定义", Some(Category::Semantic), &["already defined", "Duplicate", "already declared"]),
+    entry(10, "类型不匹配", Some(Category::Semantic), &["Type mismatch", "type mismatch", "Incompatible", "Cannot assign", "Bad return type", "must be boolean"]),
+    entry(11, "未定义的变量", Some(Category::Semantic), &["Undefined variable"]),
+    entry(12, "未定义的方法", Some(Category::Semantic), &["Undefined function", "Undefined method", "Unknown method", "Cannot find method"]),
+    entry(15, "主类或 main 方法错误", Some(Category::Semantic), &["main 方法", "@main", "main method"]),
+    entry(16, "找不到模块", Some(Category::Semantic), &["Cannot find module"]),
+    entry(17, "未定义的类型", Some(Category::Semantic), &["Unknown class", "Unknown type", "Undefined class"]),
+    entry(18, "参数个数或类型不匹配", Some(Category::Semantic), &["Argument", "argument"]),
+    entry(19, "代码生成错误", None, &[]),
+    entry(20, "LLVM 错误", None, &[]),
+    entry(21, "使用可能未初始化的变量", Some(Category::Semantic), &["may not have been initialized"]),
+    entry(22, "不可达的语句", Some(Category::Semantic), &["Unreachable statement"]),
+];
+
+/// 一条诊断：单个错误及其所在的文件和位置
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub code: ErrorCode,
+    /// 出错的编译阶段，名称与 [`cayError::phase`] 一致
+    pub phase: &'static str,
+    pub message: String,
+    /// 修改建议
+    pub help: Option<String>,
+    pub file: String,
+    /// 没有位置信息的错误（如代码生成错误）为 `None`
+    pub location: Option<SourceLocation>,
+}
+
+impl Diagnostic {
+    /// 把编译错误展开为诊断列表，`file` 为错误所在的源文件
+    pub fn from_error(error: &cayError, file: &str) -> Vec<Diagnostic> {
+        match error {
+            cayError::Multiple(errors) => errors.iter().flat_map(|e| Self::from_error(e, file)).collect(),
+            cayError::Module { path, error } => Self::from_error(error, path),
+            _ => {
+                let (message, help) = match error {
+                    cayError::Lexer { message, suggestion, .. }
+                    | cayError::Parser { message, suggestion, .. }
+                    | cayError::Semantic { message, suggestion, .. }
+                    | cayError::Preprocessor { message, suggestion, .. }
+                    | cayError::TypeMismatch { message, suggestion, .. }
+                    | cayError::CodeGen { message, suggestion } => (message.clone(), Some(suggestion.clone())),
+                    cayError::UndefinedIdentifier { name, suggestion, .. } => (format!("未定义标识符 '{}'", name), Some(suggestion.clone())),
+                    cayError::DuplicateDefinition { name, suggestion, .. } => (format!("重复定义 '{}'", name), Some(suggestion.clone())),
+                    _ => (error.to_string(), None),
+                };
+                vec![Diagnostic {
+                    code: ErrorCode::of(error),
+                    phase: error.phase(),
+                    message,
+                    help,
+                    file: file.to_string(),
+                    location: error.location().map(|(line, column)| SourceLocation { line, column }),
+                }]
+            }
+        }
+    }
+
+    /// 渲染诊断；`source` 为所在文件的源码，用于显示出错的行
+    pub fn render(&self, source: Option<&str>) -> String {
+        let mut out = format!("error[{}]: {}\n", self.code, self.message);
+        let Some(loc) = &self.location else {
+            out.push_str(&format!(" --> {}\n", self.file));
+            self.render_help(&mut out, 1);
+            return out;
+        };
+        let line_text = source.and_then(|s| crate::source::SourceFile::new(&self.file, s).line_text(loc.line).map(str::to_string));
+        let column = line_text.as_deref().map_or(loc.column, |text| self.highlight(text).0);
+
+        let gutter = loc.line.to_string().len();
+        out.push_str(&format!("{:gutter$}--> {}:{}:{}\n", "", self.file, loc.line, column));
+        if let Some(text) = line_text {
+            out.push_str(&format!("{:gutter$} |\n", ""));
+            out.push_str(&format!("{:>gutter$} | {}\n", loc.line, text));
+            out.push_str(&format!("{:gutter$} | {}\n", "", underline(&text, column)));
+        }
+        self.render_help(&mut out, gutter);
+        out
+    }
+
+    /// 诊断在出错行 `line_text` 中标出的范围：起始列（从 1 开始）和字符数
+    ///
+    /// 与 [`render`](Self::render) 的下划线一致，供编辑器集成（cay-lsp）标出同样的范围。
+    pub fn highlight(&self, line_text: &str) -> (usize, usize) {
+        let column = self.location.as_ref().map_or(1, |loc| self.refine_column(line_text, loc.column));
+        let chars: Vec<char> = line_text.chars().collect();
+        let start = column.saturating_sub(1).min(chars.len());
+        (column, underline_width(&chars[start..]))
+    }
+
+    /// 让下划线指向消息中提到的符号
+    ///
+    /// 标识符表达式不带位置，引用它的错误位于外层表达式（如二元运算符）或所在语句的开头；
+    /// 如果消息提到的符号（`Undefined variable: x` 或第一个 `'...'` 中的名称）在该列之后
+    /// 作为完整的单词出现，改为指向它，否则指向该列之前最近的一次出现。
+    fn refine_column(&self, text: &str, column: usize) -> usize {
+        let symbol = match self.message.strip_prefix("Undefined variable: ") {
+            Some(name) => name,
+            None => match self.message.split('\'').nth(1) {
+                Some(name) => name,
+                None => return column,
+            },
+        };
+        if symbol.is_empty() || !symbol.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return column;
+        }
+        let chars: Vec<char> = text.chars().collect();
+        let symbol: Vec<char> = symbol.chars().collect();
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        let matches_at = |&i: &usize| {
+            chars[i..].starts_with(&symbol)
+                && (i == 0 || !is_word(chars[i - 1]))
+                && chars.get(i + symbol.len()).is_none_or(|&c| !is_word(c))
+        };
+        let start = column.saturating_sub(1).min(chars.len());
+        (start..chars.len()).find(matches_at)
+            .or_else(|| (0..start).rev().find(matches_at))
+            .map_or(column, |i| i + 1)
+    }
+
+    fn render_help(&self, out: &mut String, gutter: usize) {
+        if let Some(help) = &self.help {
+            out.push_str(&format!("{:gutter$} = 提示: {}\n", "", help));
+        }
+    }
+}
+
+/// 生成指向第 `column` 列（从 1 开始）的下划线
+///
+/// 列处是标识符或数字时下划线覆盖整个单词，是字符串字面量时覆盖到结尾引号，其余情况只标出一个字符。
+/// 制表符原样保留，使下划线与源代码对齐。
+fn underline(text: &str, column: usize) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let start = column.saturating_sub(1).min(chars.len());
+    let mut marker: String = chars[..start].iter().map(|&c| if c == '\t' { '\t' } else { ' ' }).collect();
+
+    marker.push_str(&"^".repeat(underline_width(&chars[start..])));
+    marker
+}
+
+/// 从 `rest` 开头起下划线覆盖的字符数
+fn underline_width(rest: &[char]) -> usize {
+    let is_word = |c: &char| c.is_alphanumeric() || *c == '_';
+    match rest.first() {
+        Some(c) if is_word(c) => rest.iter().take_while(|c| is_word(c)).count(),
+        Some('"') => rest[1..].iter().position(|&c| c == '"').map_or(rest.len(), |end| end + 2),
+        _ => 1,
+    }
+}
+
+/// 诊断引擎：收集一次运行中的所有诊断，并按报告顺序渲染
+#[derive(Default)]
+pub struct DiagnosticEngine {
+    diagnostics: Vec<Diagnostic>,
+    /// 已知的源码（文件名 -> 内容）；未登记的文件（如被导入的模块）在渲染时从磁盘读取
+    sources: HashMap<String, String>,
+}
+
+impl DiagnosticEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记文件的源码，渲染该文件中的诊断时使用
+    pub fn add_source(&mut self, file: &str, source: &str) {
+        self.sources.insert(file.to_string(), source.to_string());
+    }
+
+    /// 报告一个编译错误（多个错误会展开为多条诊断）
+    pub fn report(&mut self, error: &cayError, file: &str) {
+        self.diagnostics.extend(Diagnostic::from_error(error, file));
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.diagnostics.len()
+    }
+
+    /// 渲染所有诊断，多于一个时末尾附上错误总数
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for diagnostic in &self.diagnostics {
+            let source = match self.sources.get(&diagnostic.file) {
+                Some(source) => Some(source.clone()),
+                None => crate::source::read_source_file(&diagnostic.file).ok(),
+            };
+            out.push_str(&diagnostic.render(source.as_deref()));
+            out.push('\n');
+        }
+        if self.diagnostics.len() > 1 {
+            out.push_str(&format!("共 {} 个错误\n", self.diagnostics.len()));
+        }
+        out
+    }
+}
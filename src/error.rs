@@ -1,5 +1,6 @@
 use thiserror::Error;
 use std::fmt;
+use serde::Serialize;
 
 #[derive(Error, Debug, Clone)]
 pub enum cayError {
@@ -73,11 +74,139 @@ pub enum cayError {
         message: String,
         suggestion: String,
     },
+
+    /// 被导入模块中的错误，位置相对于该模块的源文件 `path`
+    #[error("模块 {path}:\n{error}")]
+    Module {
+        path: String,
+        error: Box<cayError>,
+    },
+
+    /// 同一阶段收集到的多个错误，每个错误保留自己的位置
+    #[error("{}", join_errors(.0))]
+    Multiple(Vec<cayError>),
+}
+
+/// 命令行工具的进程退出码
+///
+/// 所有命令行工具共用这组退出码，数值保持稳定，构建脚本和测试可以据此区分失败的阶段。
+pub mod exit_codes {
+    /// 成功
+    pub const SUCCESS: i32 = 0;
+    /// 文件读写错误
+    pub const IO: i32 = 1;
+    /// 预处理、词法或语法错误
+    pub const SYNTAX: i32 = 2;
+    /// 语义错误
+    pub const SEMANTIC: i32 = 3;
+    /// 代码生成错误或编译器内部错误
+    pub const CODEGEN: i32 = 4;
+    /// 外部工具（clang、ir2exe 等）找不到、超时或执行失败
+    pub const TOOL: i32 = 5;
+    /// 命令行用法错误（未知选项、缺少参数等），与 sysexits.h 的 EX_USAGE 一致
+    pub const USAGE: i32 = 64;
+}
+
+fn join_errors(errors: &[cayError]) -> String {
+    errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("\n")
+}
+
+impl cayError {
+    /// 获取出错的编译阶段名称
+    pub fn phase(&self) -> &'static str {
+        match self {
+            cayError::Preprocessor { .. } => "预处理",
+            cayError::Lexer { .. } => "词法分析",
+            cayError::Parser { .. } => "语法分析",
+            cayError::Semantic { .. }
+            | cayError::TypeMismatch { .. }
+            | cayError::UndefinedIdentifier { .. }
+            | cayError::DuplicateDefinition { .. } => "语义分析",
+            cayError::CodeGen { .. } | cayError::Llvm(_) => "代码生成",
+            cayError::Io(_) => "文件读写",
+            cayError::Module { error, .. } => error.phase(),
+            cayError::Multiple(errors) => errors.first().map_or("语义分析", |e| e.phase()),
+        }
+    }
+
+    /// 获取该错误对应的进程退出码，取值见 [`exit_codes`]
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            cayError::Io(_) => exit_codes::IO,
+            cayError::Preprocessor { .. }
+            | cayError::Lexer { .. }
+            | cayError::Parser { .. } => exit_codes::SYNTAX,
+            cayError::Semantic { .. }
+            | cayError::TypeMismatch { .. }
+            | cayError::UndefinedIdentifier { .. }
+            | cayError::DuplicateDefinition { .. } => exit_codes::SEMANTIC,
+            cayError::CodeGen { .. } | cayError::Llvm(_) => exit_codes::CODEGEN,
+            cayError::Module { error, .. } => error.exit_code(),
+            cayError::Multiple(errors) => errors.first().map_or(exit_codes::SEMANTIC, |e| e.exit_code()),
+        }
+    }
+
+    /// 稳定的错误码（如 `E0011`），多个错误时取第一个错误的错误码
+    pub fn code(&self) -> crate::diagnostics::ErrorCode {
+        crate::diagnostics::ErrorCode::of(self)
+    }
+
+    /// 错误的行列位置，没有位置信息时返回 `None`
+    ///
+    /// 被导入模块中的错误（[`cayError::Module`]）不属于当前文件，同样返回 `None`。
+    pub fn location(&self) -> Option<(usize, usize)> {
+        let (line, column) = match self {
+            cayError::Lexer { line, column, .. }
+            | cayError::Parser { line, column, .. }
+            | cayError::Semantic { line, column, .. }
+            | cayError::TypeMismatch { line, column, .. }
+            | cayError::UndefinedIdentifier { line, column, .. }
+            | cayError::DuplicateDefinition { line, column, .. }
+            | cayError::Preprocessor { line, column, .. } => (*line, *column),
+            _ => return None,
+        };
+        (line > 0).then_some((line, column))
+    }
+
+    /// 为缺少位置信息的错误补充位置（例如由不带位置的标识符引发的错误）
+    pub fn with_fallback_location(mut self, fallback_line: usize, fallback_column: usize) -> Self {
+        if fallback_line == 0 || self.location().is_some() {
+            return self;
+        }
+        match &mut self {
+            cayError::Lexer { line, column, .. }
+            | cayError::Parser { line, column, .. }
+            | cayError::Semantic { line, column, .. }
+            | cayError::TypeMismatch { line, column, .. }
+            | cayError::UndefinedIdentifier { line, column, .. }
+            | cayError::DuplicateDefinition { line, column, .. }
+            | cayError::Preprocessor { line, column, .. } => {
+                *line = fallback_line;
+                *column = fallback_column;
+            }
+            cayError::Multiple(errors) => {
+                for error in errors.iter_mut() {
+                    *error = error.clone().with_fallback_location(fallback_line, fallback_column);
+                }
+            }
+            _ => {}
+        }
+        self
+    }
+
+    /// 把收集到的多个错误合并为一个结果；只有一个错误时直接返回它
+    pub fn from_errors(mut errors: Vec<cayError>) -> Option<Self> {
+        match errors.len() {
+            0 => None,
+            1 => errors.pop(),
+            _ => Some(cayError::Multiple(errors)),
+        }
+    }
 }
 
 pub type cayResult<T> = Result<T, cayError>;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SourceLocation {
     pub line: usize,
     pub column: usize,
@@ -237,6 +366,20 @@ fn get_semantic_suggestion(message: &str) -> String {
         "赋值错误。请确保左侧是可赋值的变量".to_string()
     } else if message.contains("Operator") {
         "运算符不支持这些类型的操作数".to_string()
+    } else if message.contains("type inference") || message.contains("Cannot infer type") {
+        "请为变量提供初始化表达式，或显式声明类型，例如 var x: int = 0;".to_string()
+    } else if message.contains("may not have been initialized") {
+        "变量在某些执行路径上没有赋值。请在声明时初始化，或确保每个分支都为它赋值".to_string()
+    } else if message.contains("may already have been assigned") {
+        "没有初始值的 final 变量在每条执行路径上只能赋值一次".to_string()
+    } else if message.contains("Unreachable statement") {
+        "exit() 会结束程序，之后的语句永远不会执行。请删除这些语句，或把 exit() 移到条件分支中".to_string()
+    } else if message.contains("must be boolean, got") {
+        "条件必须是 boolean 表达式，整数不会隐式转换为 boolean。例如把 while (1) 写成 while (true)，把 if (n) 写成 if (n != 0)".to_string()
+    } else if message.contains("Undefined label") || message.contains("is not a loop") {
+        "break/continue 的标签必须标记包含该语句的外层语句，continue 的标签必须标记循环".to_string()
+    } else if message.contains("Safe navigation") || message.contains("'??'") {
+        "?. 和 ?? 只能用于可能为 null 的引用类型（String、对象、数组），基本类型结果请写成 obj?.member ?? 默认值".to_string()
     } else {
         "请检查语义正确性".to_string()
     }
@@ -254,42 +397,11 @@ fn get_codegen_suggestion(message: &str) -> String {
 }
 
 // 打印带有上下文的错误信息
+//
+// 每个错误渲染为一条带错误码、出错源代码行和下划线的诊断，见 `crate::diagnostics`。
 pub fn print_error_with_context(error: &cayError, source: &str, filename: &str) {
-    eprintln!("\n[编译错误]");
-    eprintln!("文件: {}", filename);
-    
-    // 获取错误位置
-    let (line, column) = match error {
-        cayError::Lexer { line, column, .. } => (*line, *column),
-        cayError::Parser { line, column, .. } => (*line, *column),
-        cayError::Semantic { line, column, .. } => (*line, *column),
-        cayError::TypeMismatch { line, column, .. } => (*line, *column),
-        cayError::UndefinedIdentifier { line, column, .. } => (*line, *column),
-        cayError::DuplicateDefinition { line, column, .. } => (*line, *column),
-        _ => (0, 0),
-    };
-    
-    if line > 0 {
-        eprintln!("位置: 第 {} 行, 第 {} 列", line, column);
-        
-        // 打印源代码上下文
-        let lines: Vec<&str> = source.lines().collect();
-        let start = line.saturating_sub(3).max(1);
-        let end = (line + 1).min(lines.len());
-        
-        eprintln!("\n源代码上下文:");
-        for i in start..=end {
-            if i <= lines.len() {
-                eprintln!("{:4} | {}", i, lines[i - 1]);
-                if i == line {
-                    // 打印错误指示器
-                    let spaces = " ".repeat(column.saturating_sub(1) + 6);
-                    eprintln!("{}^ 错误发生在这里", spaces);
-                }
-            }
-        }
-    }
-    
-    eprintln!("\n{}", error);
-    eprintln!();
+    let mut engine = crate::diagnostics::DiagnosticEngine::new();
+    engine.add_source(filename, source);
+    engine.report(error, filename);
+    eprint!("\n{}", engine.render());
 }
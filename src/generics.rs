@@ -0,0 +1,649 @@
+//! 泛型单态化
+//!
+//! 在语义分析之前把泛型类 `class Box<T>` 和泛型方法 `<T> T identity(T x)`
+//! 展开为具体类型的副本，后续阶段只会看到普通的类和方法：
+//!
+//! - 每个用到的 `Box<int>` 生成一个名为 `Box$int` 的类，类体中的 `T` 全部替换为 `int`；
+//! - 泛型方法按调用处实参推导出的类型实例化为 `identity$int`，并改写调用处的方法名。
+//!
+//! 实例化出的节点会重新分配 `NodeId`，保证各旁路表的键在整个程序中唯一。
+//! 内置的泛型类（如 `List<T>`，见 [`crate::prelude`]）在这里作为模板加入程序，
+//! 未被使用的泛型模板不会进入语义分析。
+
+use std::collections::{HashMap, HashSet};
+use crate::ast::*;
+use crate::error::{cayResult, semantic_error, SourceLocation};
+use crate::types::Type;
+use crate::visit::{self, VisitMut};
+
+/// 单个程序允许的泛型实例数量上限（防止 `class A<T> { A<A<T>> x; }` 这类无限展开）
+const MAX_INSTANTIATIONS: usize = 1024;
+
+/// 对程序进行泛型单态化
+pub fn monomorphize(mut program: Program) -> cayResult<Program> {
+    let mut max_id = MaxNodeId(0);
+    visit::walk_program(&mut max_id, &mut program)?;
+    let next_id = crate::prelude::add_to(&mut program, max_id.0 + 1)?;
+
+    let (templates, classes): (Vec<ClassDecl>, Vec<ClassDecl>) = program.classes
+        .into_iter()
+        .partition(|c| !c.type_params.is_empty());
+    program.classes = classes;
+
+    let mut mono = Monomorphizer {
+        class_templates: templates.into_iter().map(|c| (c.name.clone(), c)).collect(),
+        method_templates: HashMap::new(),
+        instantiated: HashSet::new(),
+        queue: Vec::new(),
+        shapes: HashMap::new(),
+        next_id,
+    };
+
+    // 1. 解析所有泛型类型的使用并实例化泛型类
+    visit::walk_program(&mut mono, &mut program)?;
+    mono.drain_class_queue(&mut program.classes)?;
+
+    // 2. 抽出泛型方法模板，按调用处实例化
+    for class in &mut program.classes {
+        mono.extract_generic_methods(class);
+        mono.shapes.insert(class.name.clone(), ClassShape::of(class));
+    }
+    let mut class_index = 0;
+    while class_index < program.classes.len() {
+        let class_name = program.classes[class_index].name.clone();
+        let mut member_index = 0;
+        while member_index < program.classes[class_index].members.len() {
+            let placeholder = ClassMember::InstanceInitializer(Block {
+                statements: Vec::new(),
+                loc: SourceLocation { line: 0, column: 0 },
+                id: NodeId::DUMMY,
+            });
+            let mut member = std::mem::replace(&mut program.classes[class_index].members[member_index], placeholder);
+            let mut rewriter = CallRewriter {
+                mono: &mut mono,
+                classes: &mut program.classes,
+                current_class: class_name.clone(),
+                env: HashMap::new(),
+            };
+            let result = rewriter.visit_member(&mut member);
+            program.classes[class_index].members[member_index] = member;
+            result?;
+            member_index += 1;
+        }
+        class_index += 1;
+    }
+
+    for func in &mut program.top_level_functions {
+        let mut rewriter = CallRewriter {
+            mono: &mut mono,
+            classes: &mut program.classes,
+            current_class: String::new(),
+            env: func.params.iter().map(|p| (p.name.clone(), p.param_type.clone())).collect(),
+        };
+        visit::walk_block(&mut rewriter, &mut func.body)?;
+    }
+
+    Ok(program)
+}
+
+/// 泛型实例的类名：`Box<int>` -> `Box$int`，`Pair<String, int[]>` -> `Pair$String$int_array`
+///
+/// 每个模板的类型形参个数固定，因此按 `$` 平铺拼接不会产生歧义。
+pub fn instance_name(base: &str, args: &[Type]) -> String {
+    let mut name = base.to_string();
+    for arg in args {
+        name.push('$');
+        name.push_str(&type_arg_name(arg));
+    }
+    name
+}
+
+fn type_arg_name(ty: &Type) -> String {
+    match ty {
+        Type::Int8 => "byte".to_string(),
+        Type::Int16 => "short".to_string(),
+        Type::Int32 => "int".to_string(),
+        Type::Int64 => "long".to_string(),
+        Type::Float32 => "float".to_string(),
+        Type::Float64 => "double".to_string(),
+        Type::Bool => "boolean".to_string(),
+        Type::Char => "char".to_string(),
+        Type::String => "String".to_string(),
+        Type::Object(name) => name.clone(),
+        Type::Array(inner) => format!("{}_array", type_arg_name(inner)),
+        Type::Generic(name, args) => instance_name(name, args),
+        other => other.to_string(),
+    }
+}
+
+fn error_at(loc: &SourceLocation, message: impl Into<String>) -> crate::error::cayError {
+    semantic_error(loc.line, loc.column, message)
+}
+
+/// 求程序中最大的节点 ID
+struct MaxNodeId(u32);
+
+impl VisitMut for MaxNodeId {
+    fn visit_id(&mut self, id: &mut NodeId) {
+        if *id != NodeId::DUMMY && id.0 > self.0 {
+            self.0 = id.0;
+        }
+    }
+}
+
+/// 为实例化出的节点重新分配 ID
+struct Renumber<'a> {
+    next_id: &'a mut u32,
+}
+
+impl VisitMut for Renumber<'_> {
+    fn visit_id(&mut self, id: &mut NodeId) {
+        *id = NodeId(*self.next_id);
+        *self.next_id += 1;
+    }
+}
+
+/// 把类型形参替换为具体类型
+struct Substitute {
+    bindings: HashMap<String, Type>,
+}
+
+impl VisitMut for Substitute {
+    fn visit_type(&mut self, ty: &mut Type, loc: &SourceLocation) -> cayResult<()> {
+        if let Type::Object(name) = ty
+            && let Some(concrete) = self.bindings.get(name)
+        {
+            *ty = concrete.clone();
+            return Ok(());
+        }
+        visit::walk_type(self, ty, loc)
+    }
+
+    fn visit_member(&mut self, member: &mut ClassMember) -> cayResult<()> {
+        // 泛型方法自己的类型形参会遮蔽同名的类类型形参
+        if let ClassMember::Method(method) = member
+            && method.type_params.iter().any(|p| self.bindings.contains_key(p))
+        {
+            let saved = self.bindings.clone();
+            for param in &method.type_params {
+                self.bindings.remove(param);
+            }
+            let result = visit::walk_member(self, member);
+            self.bindings = saved;
+            return result;
+        }
+        visit::walk_member(self, member)
+    }
+
+    fn visit_expr(&mut self, expr: &mut Expr) -> cayResult<()> {
+        // new T() 只有在 T 被替换为类类型时才有意义
+        if let Expr::New(new_expr) = expr
+            && let Some(Type::Object(concrete)) = self.bindings.get(&new_expr.class_name)
+        {
+            new_expr.class_name = concrete.clone();
+        }
+        visit::walk_expr(self, expr)
+    }
+}
+
+/// 泛型方法之外的类成员概要，用于推导调用实参的类型
+#[derive(Default)]
+struct ClassShape {
+    parent: Option<String>,
+    fields: HashMap<String, Type>,
+    /// (方法名, 参数个数, 返回类型)
+    methods: Vec<(String, usize, Type)>,
+}
+
+impl ClassShape {
+    fn of(class: &ClassDecl) -> Self {
+        let mut shape = ClassShape { parent: class.parent.clone(), ..Default::default() };
+        for member in &class.members {
+            match member {
+                ClassMember::Field(field) => {
+                    shape.fields.insert(field.name.clone(), field.field_type.clone());
+                }
+                ClassMember::Method(method) if method.type_params.is_empty() => {
+                    shape.methods.push((method.name.clone(), method.params.len(), method.return_type.clone()));
+                }
+                _ => {}
+            }
+        }
+        shape
+    }
+}
+
+struct Monomorphizer {
+    class_templates: HashMap<String, ClassDecl>,
+    /// 类名 -> 该类中声明的泛型方法
+    method_templates: HashMap<String, Vec<MethodDecl>>,
+    /// 已实例化的类名和 "类名.方法名"
+    instantiated: HashSet<String>,
+    /// 待实例化的泛型类：(模板名, 类型实参, 实例类名)
+    queue: Vec<(String, Vec<Type>, String)>,
+    shapes: HashMap<String, ClassShape>,
+    next_id: u32,
+}
+
+impl Monomorphizer {
+    /// 解析泛型类型 `Box<int>`，登记实例并返回实例类名
+    fn resolve_generic(&mut self, name: &str, args: &mut [Type], loc: &SourceLocation) -> cayResult<String> {
+        for arg in args.iter_mut() {
+            self.visit_type(arg, loc)?;
+        }
+        let template = match self.class_templates.get(name) {
+            Some(template) => template,
+            None => return Err(error_at(loc, format!("Type '{}' is not a generic class", name))),
+        };
+        if args.is_empty() {
+            return Err(error_at(loc, format!(
+                "Cannot infer type arguments for '{}<>'; declare the variable or field with explicit type arguments",
+                name
+            )));
+        }
+        if args.len() != template.type_params.len() {
+            return Err(error_at(loc, format!(
+                "Generic class '{}' expects {} type argument(s), got {}",
+                name, template.type_params.len(), args.len()
+            )));
+        }
+        if let Some(void) = args.iter().find(|a| **a == Type::Void) {
+            return Err(error_at(loc, format!("'{}' cannot be used as a type argument", void)));
+        }
+        // 内置 Map 的运行时哈希表只支持按内容比较的 String 键和 int 键
+        if matches!(crate::prelude::native_class(template), Some(crate::prelude::NativeClass::Map(..))) && !matches!(args[0], Type::String | Type::Int32) {
+            return Err(error_at(loc, format!("Map keys must be String or int, got {}", args[0])));
+        }
+        let mangled = instance_name(name, args);
+        if self.instantiated.insert(mangled.clone()) {
+            if self.instantiated.len() > MAX_INSTANTIATIONS {
+                return Err(error_at(loc, format!(
+                    "Too many generic instantiations while expanding '{}' (recursive generic type?)",
+                    mangled
+                )));
+            }
+            self.queue.push((name.to_string(), args.to_vec(), mangled.clone()));
+        }
+        Ok(mangled)
+    }
+
+    /// 实例化队列中的所有泛型类（实例化过程中可能产生新的实例）
+    fn drain_class_queue(&mut self, classes: &mut Vec<ClassDecl>) -> cayResult<()> {
+        while let Some((template_name, args, mangled)) = self.queue.pop() {
+            let mut class = self.class_templates[&template_name].clone();
+            let mut subst = Substitute {
+                bindings: class.type_params.iter().cloned().zip(args).collect(),
+            };
+            subst.visit_class(&mut class)?;
+            class.name = mangled;
+            class.type_params.clear();
+            self.renumber(|r| r.visit_class(&mut class))?;
+            self.visit_class(&mut class)?;
+            self.extract_generic_methods(&mut class);
+            self.shapes.insert(class.name.clone(), ClassShape::of(&class));
+            classes.push(class);
+        }
+        Ok(())
+    }
+
+    fn renumber(&mut self, f: impl FnOnce(&mut Renumber) -> cayResult<()>) -> cayResult<()> {
+        let mut renumber = Renumber { next_id: &mut self.next_id };
+        f(&mut renumber)
+    }
+
+    /// 把类中的泛型方法移入模板表
+    fn extract_generic_methods(&mut self, class: &mut ClassDecl) {
+        let mut generic = Vec::new();
+        class.members.retain(|member| match member {
+            ClassMember::Method(method) if !method.type_params.is_empty() => {
+                generic.push(method.clone());
+                false
+            }
+            _ => true,
+        });
+        if !generic.is_empty() {
+            self.method_templates.entry(class.name.clone()).or_default().extend(generic);
+        }
+    }
+
+    /// 沿继承链查找泛型方法模板，返回声明它的类名
+    fn find_method_template(&self, class_name: &str, method: &str, argc: usize) -> Option<(String, MethodDecl)> {
+        let mut current = Some(class_name.to_string());
+        while let Some(name) = current {
+            if let Some(template) = self.method_templates.get(&name)
+                .and_then(|methods| methods.iter().find(|m| m.name == method && m.params.len() == argc))
+            {
+                return Some((name, template.clone()));
+            }
+            current = self.shapes.get(&name).and_then(|s| s.parent.clone());
+        }
+        None
+    }
+
+    fn field_type(&self, class_name: &str, field: &str) -> Option<Type> {
+        let mut current = Some(class_name.to_string());
+        while let Some(name) = current {
+            let shape = self.shapes.get(&name)?;
+            if let Some(ty) = shape.fields.get(field) {
+                return Some(ty.clone());
+            }
+            current = shape.parent.clone();
+        }
+        None
+    }
+
+    fn method_return_type(&self, class_name: &str, method: &str, argc: usize) -> Option<Type> {
+        let mut current = Some(class_name.to_string());
+        while let Some(name) = current {
+            let shape = self.shapes.get(&name)?;
+            if let Some((_, _, ret)) = shape.methods.iter().find(|(n, c, _)| n == method && *c == argc) {
+                return Some(ret.clone());
+            }
+            current = shape.parent.clone();
+        }
+        None
+    }
+}
+
+/// 第一步：把所有 `Type::Generic` 和 `new Box<int>()` 改写为实例类
+impl VisitMut for Monomorphizer {
+    fn visit_type(&mut self, ty: &mut Type, loc: &SourceLocation) -> cayResult<()> {
+        match ty {
+            Type::Generic(name, args) => {
+                let mangled = self.resolve_generic(&name.clone(), args, loc)?;
+                *ty = Type::Object(mangled);
+                Ok(())
+            }
+            Type::Object(name) if self.class_templates.contains_key(name.as_str()) => {
+                Err(error_at(loc, format!("Generic class '{}' requires type arguments", name)))
+            }
+            _ => visit::walk_type(self, ty, loc),
+        }
+    }
+
+    fn visit_member(&mut self, member: &mut ClassMember) -> cayResult<()> {
+        match member {
+            // 泛型方法中的类型形参在方法实例化时才确定
+            ClassMember::Method(method) if !method.type_params.is_empty() => Ok(()),
+            ClassMember::Field(field) => {
+                if let Some(init) = &mut field.initializer {
+                    infer_diamond(&field.field_type, init);
+                }
+                visit::walk_member(self, member)
+            }
+            _ => visit::walk_member(self, member),
+        }
+    }
+
+    fn visit_stmt(&mut self, stmt: &mut Stmt) -> cayResult<()> {
+        if let Stmt::VarDecl(var) = stmt
+            && let Some(init) = &mut var.initializer
+        {
+            infer_diamond(&var.var_type, init);
+        }
+        visit::walk_stmt(self, stmt)
+    }
+
+    fn visit_expr(&mut self, expr: &mut Expr) -> cayResult<()> {
+        if let Expr::New(new_expr) = expr {
+            if let Some(mut args) = new_expr.type_args.take() {
+                new_expr.class_name = self.resolve_generic(&new_expr.class_name.clone(), &mut args, &new_expr.loc)?;
+            } else if self.class_templates.contains_key(&new_expr.class_name) {
+                return Err(error_at(&new_expr.loc, format!(
+                    "Generic class '{}' requires type arguments", new_expr.class_name
+                )));
+            }
+        }
+        visit::walk_expr(self, expr)
+    }
+}
+
+/// 菱形写法 `Box<int> b = new Box<>();` 从声明类型补全类型实参
+fn infer_diamond(declared: &Type, init: &mut Expr) {
+    if let Type::Generic(name, args) = declared
+        && let Expr::New(new_expr) = init
+        && new_expr.class_name == *name
+        && new_expr.type_args.as_ref().is_some_and(|a| a.is_empty())
+    {
+        new_expr.type_args = Some(args.clone());
+    }
+}
+
+/// 第二步：推导泛型方法调用的类型实参，实例化方法并改写调用处
+struct CallRewriter<'a> {
+    mono: &'a mut Monomorphizer,
+    classes: &'a mut Vec<ClassDecl>,
+    current_class: String,
+    /// 当前方法中参数和局部变量的声明类型（按出现顺序覆盖，不区分作用域）
+    env: HashMap<String, Type>,
+}
+
+impl CallRewriter<'_> {
+    fn infer(&self, expr: &Expr) -> Option<Type> {
+        match expr {
+            Expr::Literal(lit) => match lit {
+                LiteralValue::Int32(_) => Some(Type::Int32),
+                LiteralValue::Int64(_) => Some(Type::Int64),
+                LiteralValue::Float32(_) => Some(Type::Float32),
+                LiteralValue::Float64(_) => Some(Type::Float64),
+                LiteralValue::String(_) => Some(Type::String),
+                LiteralValue::Bool(_) => Some(Type::Bool),
+                LiteralValue::Char(_) => Some(Type::Char),
+                LiteralValue::Null => None,
+            },
+            Expr::Identifier(name) => self.env.get(name).cloned()
+                .or_else(|| self.mono.field_type(&self.current_class, name)),
+            Expr::New(new_expr) => Some(Type::Object(new_expr.class_name.clone())),
+            Expr::Cast(cast) => Some(cast.target_type.clone()),
+            Expr::ArrayCreation(creation) => {
+                let mut ty = creation.element_type.clone();
+                for _ in &creation.sizes {
+                    ty = Type::Array(Box::new(ty));
+                }
+                Some(ty)
+            }
+            Expr::ArrayAccess(access) => match self.infer(&access.array)? {
+                Type::Array(inner) => Some(*inner),
+                _ => None,
+            },
+            Expr::MemberAccess(member) => {
+                if let Some(class_name) = self.static_class(&member.object) {
+                    return self.mono.field_type(&class_name, &member.member);
+                }
+                match self.infer(&member.object)? {
+                    Type::Array(_) if member.member == "length" => Some(Type::Int32),
+                    Type::Object(class_name) => self.mono.field_type(&class_name, &member.member),
+                    _ => None,
+                }
+            }
+            Expr::Call(call) => match call.callee.as_ref() {
+                Expr::Identifier(name) => self.mono.method_return_type(&self.current_class, name, call.args.len()),
+                Expr::MemberAccess(member) => {
+                    let class_name = match self.static_class(&member.object) {
+                        Some(class_name) => class_name,
+                        None => match self.infer(&member.object)? {
+                            Type::Object(class_name) => class_name,
+                            _ => return None,
+                        },
+                    };
+                    self.mono.method_return_type(&class_name, &member.member, call.args.len())
+                }
+                _ => None,
+            },
+            Expr::Binary(binary) => {
+                if binary.op.is_comparison() || matches!(binary.op, BinaryOp::And | BinaryOp::Or) {
+                    return Some(Type::Bool);
+                }
+                let left = self.infer(&binary.left)?;
+                let right = self.infer(&binary.right)?;
+                if binary.op == BinaryOp::Add && (left == Type::String || right == Type::String) {
+                    return Some(Type::String);
+                }
+                [Type::Float64, Type::Float32, Type::Int64]
+                    .into_iter()
+                    .find(|t| left == *t || right == *t)
+                    .or(Some(Type::Int32))
+            }
+            Expr::Unary(unary) => match unary.op {
+                UnaryOp::Not => Some(Type::Bool),
+                _ => self.infer(&unary.operand),
+            },
+            Expr::Ternary(ternary) => self.infer(&ternary.true_branch)
+                .or_else(|| self.infer(&ternary.false_branch)),
+            Expr::Assignment(assign) => self.infer(&assign.target),
+            _ => None,
+        }
+    }
+
+    /// `ClassName.member` 中作为类名使用的标识符
+    fn static_class(&self, object: &Expr) -> Option<String> {
+        match object {
+            Expr::Identifier(name) if !self.env.contains_key(name) && self.mono.shapes.contains_key(name) => Some(name.clone()),
+            _ => None,
+        }
+    }
+
+    /// 确定调用的目标类：返回 (接收者所在类, 方法名)
+    fn call_target(&self, call: &CallExpr) -> Option<(String, String)> {
+        match call.callee.as_ref() {
+            Expr::Identifier(name) => Some((self.current_class.clone(), name.clone())),
+            Expr::MemberAccess(member) => {
+                let class_name = match self.static_class(&member.object) {
+                    Some(class_name) => class_name,
+                    None => match self.infer(&member.object)? {
+                        Type::Object(class_name) => class_name,
+                        _ => return None,
+                    },
+                };
+                Some((class_name, member.member.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    fn rewrite_call(&mut self, call: &mut CallExpr) -> cayResult<()> {
+        let Some((receiver, method_name)) = self.call_target(call) else { return Ok(()) };
+        let Some((owner, template)) = self.mono.find_method_template(&receiver, &method_name, call.args.len()) else {
+            return Ok(());
+        };
+
+        let mut bindings = HashMap::new();
+        for (param, arg) in template.params.iter().zip(&call.args) {
+            if let Some(arg_type) = self.infer(arg) {
+                unify(&param.param_type, &arg_type, &template.type_params, &mut bindings)
+                    .map_err(|msg| error_at(&call.loc, format!("{} in call to generic method '{}'", msg, method_name)))?;
+            }
+        }
+        let mut args = Vec::new();
+        for param in &template.type_params {
+            match bindings.get(param) {
+                Some(ty) => args.push(Type::clone(ty)),
+                None => return Err(error_at(&call.loc, format!(
+                    "Cannot infer type argument '{}' for generic method '{}'; pass an argument of the parameter type",
+                    param, method_name
+                ))),
+            }
+        }
+
+        let mangled = instance_name(&method_name, &args);
+        if self.mono.instantiated.insert(format!("{}.{}", owner, mangled)) {
+            self.instantiate_method(&owner, template, bindings, mangled.clone())?;
+        }
+
+        match call.callee.as_mut() {
+            Expr::Identifier(name) => *name = mangled,
+            Expr::MemberAccess(member) => member.member = mangled,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn instantiate_method(&mut self, owner: &str, mut method: MethodDecl, bindings: HashMap<String, Type>, mangled: String) -> cayResult<()> {
+        method.type_params.clear();
+        let mut subst = Substitute { bindings };
+        visit::walk_method(&mut subst, &mut method)?;
+        method.name = mangled;
+        self.mono.renumber(|r| visit::walk_method(r, &mut method))?;
+        visit::walk_method(self.mono, &mut method)?;
+        self.mono.drain_class_queue(self.classes)?;
+
+        // 方法体中可能还有泛型方法调用；先登记签名，递归调用自身时才能推导返回类型
+        if let Some(shape) = self.mono.shapes.get_mut(owner) {
+            shape.methods.push((method.name.clone(), method.params.len(), method.return_type.clone()));
+        }
+        let mut member = ClassMember::Method(method);
+        let mut rewriter = CallRewriter {
+            mono: &mut *self.mono,
+            classes: &mut *self.classes,
+            current_class: owner.to_string(),
+            env: HashMap::new(),
+        };
+        rewriter.visit_member(&mut member)?;
+
+        if let Some(class) = self.classes.iter_mut().find(|c| c.name == owner) {
+            class.members.push(member);
+        }
+        Ok(())
+    }
+}
+
+/// 用实参类型匹配形参类型，绑定其中出现的类型形参
+fn unify(param: &Type, arg: &Type, type_params: &[String], bindings: &mut HashMap<String, Type>) -> Result<(), String> {
+    match (param, arg) {
+        (Type::Object(name), _) if type_params.contains(name) => {
+            match bindings.get(name) {
+                Some(bound) if bound != arg => Err(format!(
+                    "Conflicting types '{}' and '{}' inferred for type parameter '{}'", bound, arg, name
+                )),
+                Some(_) => Ok(()),
+                None => {
+                    bindings.insert(name.clone(), arg.clone());
+                    Ok(())
+                }
+            }
+        }
+        (Type::Array(p), Type::Array(a)) => unify(p, a, type_params, bindings),
+        _ => Ok(()),
+    }
+}
+
+impl VisitMut for CallRewriter<'_> {
+    fn visit_member(&mut self, member: &mut ClassMember) -> cayResult<()> {
+        self.env = match member {
+            ClassMember::Method(method) => method.params.iter().map(|p| (p.name.clone(), p.param_type.clone())).collect(),
+            ClassMember::Constructor(ctor) => ctor.params.iter().map(|p| (p.name.clone(), p.param_type.clone())).collect(),
+            _ => HashMap::new(),
+        };
+        visit::walk_member(self, member)
+    }
+
+    fn visit_stmt(&mut self, stmt: &mut Stmt) -> cayResult<()> {
+        visit::walk_stmt(self, stmt)?;
+        if let Stmt::VarDecl(var) = stmt {
+            let ty = if var.var_type == Type::Auto {
+                var.initializer.as_ref().and_then(|init| self.infer(init))
+            } else {
+                Some(var.var_type.clone())
+            };
+            if let Some(ty) = ty {
+                self.env.insert(var.name.clone(), ty);
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_expr(&mut self, expr: &mut Expr) -> cayResult<()> {
+        if let Expr::Lambda(lambda) = expr {
+            for param in &lambda.params {
+                if let Some(ty) = &param.param_type {
+                    self.env.insert(param.name.clone(), ty.clone());
+                }
+            }
+        }
+        visit::walk_expr(self, expr)?;
+        if let Expr::Call(call) = expr {
+            self.rewrite_call(call)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,541 @@
+//! 语法规范与语法测试用例生成
+//!
+//! 仓库根目录的 `cavvy.ebnf` 是 Cavvy 语法的规范版本。本模块解析这份 EBNF，
+//! 并从 `program` 出发按语法随机推导测试用例：
+//! - 正例：推导出的 token 序列，手写的递归下降解析器必须接受；
+//! - 反例：从正例中删去一个括号（`(`、`)`、`[`、`]`、`{`、`}`）。解析器总是成对地消耗括号，
+//!   括号不配对的程序一定会被拒绝。
+//!
+//! 推导只到 token 一级：标识符和字面量等词法规则直接取固定的样例，预处理器指令不参与推导。
+//! 推导使用固定种子的伪随机数，同一种子总是得到同一个程序，失败的用例可以直接复现。
+//! 新增语法时同时修改解析器和 `cavvy.ebnf`，测试保证两者不会相互偏离。
+
+use std::collections::{HashMap, HashSet};
+
+/// 规范语法的 EBNF 源文本
+pub const GRAMMAR_SOURCE: &str = include_str!("../cavvy.ebnf");
+
+/// 推导的起始规则
+pub const START_RULE: &str = "program";
+
+/// 词法规则的样例：推导到这些规则时直接输出其中一个样例 token
+///
+/// 标识符样例之后再加上递增的编号，保证每次推导出的名字都不相同（类型形参等处不允许重名）。
+pub const TOKEN_SAMPLES: &[(&str, &[&str])] = &[
+    ("identifier", &["a", "b", "value", "Node", "_tmp", "x_"]),
+    ("integer_literal", &["0", "42", "0x1F", "0b101", "7L", "1_000"]),
+    ("floating_point_literal", &["1.5", "2.0f", ".5", "3.25e2"]),
+    ("string_literal", &["\"text\"", "\"\""]),
+    ("character_literal", &["'c'", "'\\n'"]),
+];
+
+/// 推导路径上规则递归（同一规则再次展开）的次数上限：
+/// 越接近上限越倾向于选择最短的推导，达到后只选择最短的推导
+const MAX_RECURSION: usize = 6;
+
+/// 递归达到上限后，可以选择比最短推导高出这么多层的分支
+const MIN_HEIGHT_SLACK: usize = 2;
+
+/// 默认的 token 数预算，输出超过预算后只选择最短的推导
+const DEFAULT_TOKEN_BUDGET: usize = 300;
+
+/// EBNF 表达式
+#[derive(Debug, Clone, PartialEq)]
+pub enum GrammarExpr {
+    /// 终结符 `"..."` 或 `'...'`
+    Terminal(String),
+    /// 规则引用
+    Rule(String),
+    /// 特殊序列 `? ... ?`（用自然语言描述的字符集合）
+    Special(String),
+    /// 字符范围 `"a" .. "z"`
+    Range(String, String),
+    /// 连接 `a, b`
+    Sequence(Vec<GrammarExpr>),
+    /// 选择 `a | b`
+    Choice(Vec<GrammarExpr>),
+    /// 可选 `[ a ]`
+    Optional(Box<GrammarExpr>),
+    /// 重复 0 或多次 `{ a }`
+    Repeat(Box<GrammarExpr>),
+    /// 重复 1 或多次 `{ a }-`
+    RepeatOnce(Box<GrammarExpr>),
+}
+
+impl GrammarExpr {
+    /// 收集表达式中引用的规则名
+    fn collect_rules<'a>(&'a self, out: &mut Vec<&'a str>) {
+        match self {
+            GrammarExpr::Rule(name) => out.push(name),
+            GrammarExpr::Sequence(items) | GrammarExpr::Choice(items) => {
+                for item in items {
+                    item.collect_rules(out);
+                }
+            }
+            GrammarExpr::Optional(inner) | GrammarExpr::Repeat(inner) | GrammarExpr::RepeatOnce(inner) => {
+                inner.collect_rules(out)
+            }
+            GrammarExpr::Terminal(_) | GrammarExpr::Special(_) | GrammarExpr::Range(..) => {}
+        }
+    }
+}
+
+/// 解析后的 EBNF 语法
+#[derive(Debug, Clone)]
+pub struct Grammar {
+    /// 规则，保持源文件中的顺序
+    rules: Vec<(String, GrammarExpr)>,
+    index: HashMap<String, usize>,
+}
+
+impl Grammar {
+    /// 解析 EBNF 源文本
+    pub fn parse(source: &str) -> Result<Grammar, String> {
+        let tokens = tokenize(source)?;
+        let mut parser = EbnfParser { tokens, pos: 0 };
+        let mut grammar = Grammar { rules: Vec::new(), index: HashMap::new() };
+        while parser.peek().is_some() {
+            let (name, expr) = parser.parse_rule()?;
+            if grammar.index.insert(name.clone(), grammar.rules.len()).is_some() {
+                return Err(format!("rule '{}' is defined more than once", name));
+            }
+            grammar.rules.push((name, expr));
+        }
+        Ok(grammar)
+    }
+
+    /// 仓库中的规范语法（`cavvy.ebnf`）
+    pub fn canonical() -> Result<Grammar, String> {
+        Self::parse(GRAMMAR_SOURCE)
+    }
+
+    /// 规则的定义
+    pub fn rule(&self, name: &str) -> Option<&GrammarExpr> {
+        self.index.get(name).map(|&i| &self.rules[i].1)
+    }
+
+    /// 所有规则名（按定义顺序）
+    pub fn rule_names(&self) -> impl Iterator<Item = &str> {
+        self.rules.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// 被引用但没有定义的规则名
+    pub fn undefined_rules(&self) -> Vec<&str> {
+        let mut undefined = Vec::new();
+        for (_, expr) in &self.rules {
+            let mut referenced = Vec::new();
+            expr.collect_rules(&mut referenced);
+            for name in referenced {
+                if !self.index.contains_key(name) && !undefined.contains(&name) {
+                    undefined.push(name);
+                }
+            }
+        }
+        undefined
+    }
+
+    /// 从 `start` 出发推导时会展开的规则（词法样例规则本身计入，但不再深入）
+    pub fn reachable_rules(&self, start: &str) -> HashSet<String> {
+        let mut reachable = HashSet::new();
+        let mut pending = vec![start];
+        while let Some(name) = pending.pop() {
+            if !reachable.insert(name.to_string()) || token_samples(name).is_some() {
+                continue;
+            }
+            if let Some(expr) = self.rule(name) {
+                expr.collect_rules(&mut pending);
+            }
+        }
+        reachable
+    }
+}
+
+/// 词法规则的样例
+fn token_samples(name: &str) -> Option<&'static [&'static str]> {
+    TOKEN_SAMPLES.iter().find(|(rule, _)| *rule == name).map(|(_, samples)| *samples)
+}
+
+/// EBNF 的 token
+#[derive(Debug, Clone, PartialEq)]
+enum EbnfToken {
+    Name(String),
+    Terminal(String),
+    Special(String),
+    Symbol(&'static str),
+}
+
+/// 带行号的 EBNF token
+type Located = (EbnfToken, usize);
+
+/// 把 EBNF 源文本切分为 token，跳过注释（注释可以嵌套）
+fn tokenize(source: &str) -> Result<Vec<Located>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\n' {
+            line += 1;
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if c == '(' && chars.get(i + 1) == Some(&'*') {
+            let start_line = line;
+            let mut depth = 0;
+            loop {
+                match (chars.get(i), chars.get(i + 1)) {
+                    (Some('('), Some('*')) => { depth += 1; i += 2; }
+                    (Some('*'), Some(')')) => {
+                        depth -= 1;
+                        i += 2;
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    (Some(ch), _) => {
+                        if *ch == '\n' {
+                            line += 1;
+                        }
+                        i += 1;
+                    }
+                    (None, _) => return Err(format!("line {}: unterminated comment", start_line)),
+                }
+            }
+        } else if c == '"' || c == '\'' || c == '?' {
+            let end = chars[i + 1..].iter().position(|&ch| ch == c || ch == '\n')
+                .map(|offset| i + 1 + offset)
+                .filter(|&end| chars[end] == c)
+                .ok_or_else(|| format!("line {}: unterminated {} literal", line, c))?;
+            let text: String = chars[i + 1..end].iter().collect();
+            let token = if c == '?' { EbnfToken::Special(text.trim().to_string()) } else { EbnfToken::Terminal(text) };
+            tokens.push((token, line));
+            i = end + 1;
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push((EbnfToken::Name(chars[start..i].iter().collect()), line));
+        } else {
+            let symbol = match (c, chars.get(i + 1)) {
+                ('.', Some('.')) => "..",
+                ('}', Some('-')) => "}-",
+                ('=', _) => "=",
+                (',', _) => ",",
+                ('|', _) => "|",
+                (';', _) => ";",
+                ('(', _) => "(",
+                (')', _) => ")",
+                ('[', _) => "[",
+                (']', _) => "]",
+                ('{', _) => "{",
+                ('}', _) => "}",
+                _ => return Err(format!("line {}: unexpected character '{}'", line, c)),
+            };
+            tokens.push((EbnfToken::Symbol(symbol), line));
+            i += symbol.len();
+        }
+    }
+    Ok(tokens)
+}
+
+/// EBNF 规则解析器
+struct EbnfParser {
+    tokens: Vec<Located>,
+    pos: usize,
+}
+
+impl EbnfParser {
+    fn peek(&self) -> Option<&EbnfToken> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens.get(self.pos).or(self.tokens.last()).map_or(1, |(_, line)| *line)
+    }
+
+    fn eat(&mut self, symbol: &str) -> bool {
+        if matches!(self.peek(), Some(EbnfToken::Symbol(s)) if *s == symbol) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), String> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            Err(format!("line {}: expected '{}'", self.line(), symbol))
+        }
+    }
+
+    /// rule = name, "=", choice, ";"
+    fn parse_rule(&mut self) -> Result<(String, GrammarExpr), String> {
+        let name = match self.peek() {
+            Some(EbnfToken::Name(name)) => name.clone(),
+            _ => return Err(format!("line {}: expected rule name", self.line())),
+        };
+        self.pos += 1;
+        self.expect("=")?;
+        let expr = self.parse_choice()?;
+        self.expect(";")?;
+        Ok((name, expr))
+    }
+
+    fn parse_choice(&mut self) -> Result<GrammarExpr, String> {
+        let mut alternatives = vec![self.parse_sequence()?];
+        while self.eat("|") {
+            alternatives.push(self.parse_sequence()?);
+        }
+        Ok(if alternatives.len() == 1 { alternatives.remove(0) } else { GrammarExpr::Choice(alternatives) })
+    }
+
+    fn parse_sequence(&mut self) -> Result<GrammarExpr, String> {
+        let mut items = vec![self.parse_term()?];
+        while self.eat(",") {
+            items.push(self.parse_term()?);
+        }
+        Ok(if items.len() == 1 { items.remove(0) } else { GrammarExpr::Sequence(items) })
+    }
+
+    fn parse_term(&mut self) -> Result<GrammarExpr, String> {
+        let token = self.peek().cloned();
+        self.pos += 1;
+        match token {
+            Some(EbnfToken::Terminal(text)) => {
+                if self.eat("..") {
+                    match self.peek().cloned() {
+                        Some(EbnfToken::Terminal(end)) => {
+                            self.pos += 1;
+                            Ok(GrammarExpr::Range(text, end))
+                        }
+                        _ => Err(format!("line {}: expected terminal after '..'", self.line())),
+                    }
+                } else {
+                    Ok(GrammarExpr::Terminal(text))
+                }
+            }
+            Some(EbnfToken::Name(name)) => Ok(GrammarExpr::Rule(name)),
+            Some(EbnfToken::Special(text)) => Ok(GrammarExpr::Special(text)),
+            Some(EbnfToken::Symbol("(")) => {
+                let inner = self.parse_choice()?;
+                self.expect(")")?;
+                Ok(inner)
+            }
+            Some(EbnfToken::Symbol("[")) => {
+                let inner = self.parse_choice()?;
+                self.expect("]")?;
+                Ok(GrammarExpr::Optional(Box::new(inner)))
+            }
+            Some(EbnfToken::Symbol("{")) => {
+                let inner = Box::new(self.parse_choice()?);
+                if self.eat("}-") {
+                    Ok(GrammarExpr::RepeatOnce(inner))
+                } else {
+                    self.expect("}")?;
+                    Ok(GrammarExpr::Repeat(inner))
+                }
+            }
+            _ => {
+                self.pos -= 1;
+                Err(format!("line {}: expected terminal, rule name or group", self.line()))
+            }
+        }
+    }
+}
+
+/// 按语法推导测试用例的生成器
+pub struct CaseGenerator<'a> {
+    grammar: &'a Grammar,
+    /// 每条规则最短推导的嵌套高度，没有有限推导的规则不在表中
+    heights: HashMap<String, usize>,
+    state: u64,
+    token_budget: usize,
+    /// 推导过程中展开过的规则
+    used_rules: HashSet<String>,
+    /// 已生成的标识符个数
+    identifiers: usize,
+    /// 当前推导路径上正在展开的规则
+    path: Vec<String>,
+}
+
+impl<'a> CaseGenerator<'a> {
+    /// 以 `seed` 为种子创建生成器
+    pub fn new(grammar: &'a Grammar, seed: u64) -> Self {
+        CaseGenerator {
+            grammar,
+            heights: min_heights(grammar),
+            // xorshift 的状态不能为 0
+            state: seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1,
+            token_budget: DEFAULT_TOKEN_BUDGET,
+            used_rules: HashSet::new(),
+            identifiers: 0,
+            path: Vec::new(),
+        }
+    }
+
+    /// 设置 token 数预算，超过后只选择最短的推导
+    pub fn with_token_budget(mut self, budget: usize) -> Self {
+        self.token_budget = budget;
+        self
+    }
+
+    /// 推导过程中展开过的规则
+    pub fn used_rules(&self) -> &HashSet<String> {
+        &self.used_rules
+    }
+
+    /// 从起始规则推导一个合法程序的 token 序列
+    pub fn positive(&mut self) -> Vec<String> {
+        let mut out = Vec::new();
+        self.expand(&GrammarExpr::Rule(START_RULE.to_string()), &mut out, 0);
+        out
+    }
+
+    /// 从合法程序中随机删去一个括号，程序中没有括号时返回 `None`
+    pub fn negative(&mut self, tokens: &[String]) -> Option<Vec<String>> {
+        let brackets: Vec<usize> = tokens.iter().enumerate()
+            .filter(|(_, token)| matches!(token.as_str(), "(" | ")" | "[" | "]" | "{" | "}"))
+            .map(|(i, _)| i)
+            .collect();
+        if brackets.is_empty() {
+            return None;
+        }
+        let removed = brackets[self.next_below(brackets.len())];
+        Some(tokens.iter().enumerate().filter(|(i, _)| *i != removed).map(|(_, t)| t.clone()).collect())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    fn next_below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// 展开表达式，`depth` 为推导路径上规则递归的次数
+    fn expand(&mut self, expr: &GrammarExpr, out: &mut Vec<String>, depth: usize) {
+        // 超出预算后每一步都选择最短推导，保证推导终止；未超出时递归越深越倾向于最短推导
+        let minimal = out.len() >= self.token_budget || depth >= MAX_RECURSION;
+        match expr {
+            GrammarExpr::Terminal(text) => out.push(text.clone()),
+            // 词法规则以样例代替，推导到字符级说明语法把词法规则用在了 token 位置
+            GrammarExpr::Special(text) => out.push(format!("?{}?", text)),
+            GrammarExpr::Range(start, _) => out.push(start.clone()),
+            GrammarExpr::Rule(name) => {
+                self.used_rules.insert(name.clone());
+                if let Some(samples) = token_samples(name) {
+                    let sample = samples[self.next_below(samples.len())];
+                    if name == "identifier" {
+                        self.identifiers += 1;
+                        out.push(format!("{}{}", sample, self.identifiers));
+                    } else {
+                        out.push(sample.to_string());
+                    }
+                } else if let Some(rule) = self.grammar.rule(name) {
+                    let nested = depth + usize::from(self.path.contains(name));
+                    self.path.push(name.clone());
+                    self.expand(rule, out, nested);
+                    self.path.pop();
+                } else {
+                    out.push(format!("<undefined {}>", name));
+                }
+            }
+            GrammarExpr::Sequence(items) => {
+                for item in items {
+                    self.expand(item, out, depth);
+                }
+            }
+            GrammarExpr::Choice(alternatives) => {
+                let fresh: Vec<&GrammarExpr> = alternatives.iter().filter(|alt| self.mentions_unused(alt)).collect();
+                let chosen = if minimal || self.next_below(MAX_RECURSION) < depth {
+                    // 超出预算时严格选择最短推导；否则允许稍长的分支（如字面量而不总是 this）
+                    let slack = if out.len() >= self.token_budget { 0 } else { MIN_HEIGHT_SLACK };
+                    let min = alternatives.iter().filter_map(|alt| self.height(alt)).min().unwrap_or(0);
+                    let short: Vec<&GrammarExpr> = alternatives.iter()
+                        .filter(|alt| self.height(alt).is_some_and(|h| h <= min + slack))
+                        .collect();
+                    short[self.next_below(short.len())]
+                } else if !fresh.is_empty() && self.next_below(2) == 0 {
+                    fresh[self.next_below(fresh.len())]
+                } else {
+                    &alternatives[self.next_below(alternatives.len())]
+                };
+                self.expand(chosen, out, depth);
+            }
+            GrammarExpr::Optional(inner) => {
+                if !minimal && (self.mentions_unused(inner) || self.keep_going(depth)) {
+                    self.expand(inner, out, depth);
+                }
+            }
+            GrammarExpr::Repeat(inner) | GrammarExpr::RepeatOnce(inner) => {
+                if matches!(expr, GrammarExpr::RepeatOnce(_)) {
+                    self.expand(inner, out, depth);
+                }
+                let mut count = 0;
+                while count < 3 && !minimal && out.len() < self.token_budget
+                    && (self.mentions_unused(inner) || self.keep_going(depth + count))
+                {
+                    self.expand(inner, out, depth + 1);
+                    count += 1;
+                }
+            }
+        }
+    }
+
+    /// 是否展开可选项或再重复一次：没有递归时概率为 1/2，随递归次数线性降到 0
+    fn keep_going(&mut self, depth: usize) -> bool {
+        depth < MAX_RECURSION && self.next_below(2 * MAX_RECURSION) < MAX_RECURSION - depth
+    }
+
+    /// 表达式是否直接引用了还没有展开过的规则（优先选择，使推导尽快覆盖整个语法）
+    fn mentions_unused(&self, expr: &GrammarExpr) -> bool {
+        let mut referenced = Vec::new();
+        expr.collect_rules(&mut referenced);
+        referenced.iter().any(|name| !self.used_rules.contains(*name))
+    }
+
+    fn height(&self, expr: &GrammarExpr) -> Option<usize> {
+        expr_height(expr, &self.heights)
+    }
+}
+
+/// 表达式最短推导的嵌套高度（每展开一层规则加 1），无法终止时为 `None`
+fn expr_height(expr: &GrammarExpr, heights: &HashMap<String, usize>) -> Option<usize> {
+    match expr {
+        GrammarExpr::Terminal(_) | GrammarExpr::Special(_) | GrammarExpr::Range(..) => Some(0),
+        GrammarExpr::Rule(name) if token_samples(name).is_some() => Some(1),
+        GrammarExpr::Rule(name) => heights.get(name).map(|h| h + 1),
+        GrammarExpr::Sequence(items) => items.iter()
+            .map(|item| expr_height(item, heights))
+            .try_fold(0, |max, h| h.map(|h| max.max(h))),
+        GrammarExpr::Choice(alternatives) => alternatives.iter().filter_map(|alt| expr_height(alt, heights)).min(),
+        GrammarExpr::Optional(_) | GrammarExpr::Repeat(_) => Some(0),
+        GrammarExpr::RepeatOnce(inner) => expr_height(inner, heights),
+    }
+}
+
+/// 计算每条规则最短推导的高度（不动点迭代）
+fn min_heights(grammar: &Grammar) -> HashMap<String, usize> {
+    let mut heights = HashMap::new();
+    loop {
+        let mut changed = false;
+        for (name, expr) in &grammar.rules {
+            if let Some(h) = expr_height(expr, &heights)
+                && heights.get(name).is_none_or(|&old| h < old)
+            {
+                heights.insert(name.clone(), h);
+                changed = true;
+            }
+        }
+        if !changed {
+            return heights;
+        }
+    }
+}
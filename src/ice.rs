@@ -0,0 +1,137 @@
+//! 编译器内部错误（ICE）报告
+//!
+//! 编译流水线在线程局部变量中记录当前阶段和正在处理的源码位置。
+//! 驱动程序通过 [`run_guarded`] 运行编译：编译器内部 panic 时打印
+//! "internal compiler error" 横幅（阶段、源码位置、编译器版本和 panic 信息），
+//! 并用 [`crate::reduce`] 生成最小复现程序，提示用户附在问题报告中。
+
+use std::cell::RefCell;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+
+use crate::error::SourceLocation;
+
+/// 问题反馈地址
+pub const ISSUE_URL: &str = "https://github.com/Ethernos-Studio/Cavvy/issues";
+
+/// panic 发生时编译器所处的位置
+#[derive(Debug, Clone, Default)]
+pub struct IceContext {
+    /// 编译阶段，名称与 `cayError::phase` 一致
+    pub phase: Option<&'static str>,
+    /// 正在处理的源码位置
+    pub location: Option<SourceLocation>,
+}
+
+thread_local! {
+    static CONTEXT: RefCell<IceContext> = RefCell::new(IceContext::default());
+}
+
+/// 进入新的编译阶段，同时清除上一阶段的源码位置
+pub fn enter_phase(phase: &'static str) {
+    CONTEXT.with(|ctx| *ctx.borrow_mut() = IceContext { phase: Some(phase), location: None });
+}
+
+/// 记录正在处理的源码位置（行号为 0 表示没有位置信息，忽略）
+pub fn set_location(loc: &SourceLocation) {
+    if loc.line > 0 {
+        CONTEXT.with(|ctx| ctx.borrow_mut().location = Some(loc.clone()));
+    }
+}
+
+/// 当前线程的编译器位置
+pub fn current_context() -> IceContext {
+    CONTEXT.with(|ctx| ctx.borrow().clone())
+}
+
+/// 被捕获的编译器内部错误
+#[derive(Debug, Clone)]
+pub struct InternalError {
+    pub message: String,
+    pub context: IceContext,
+    /// 生成的最小复现程序路径（无法复现时为 `None`）
+    pub reproducer: Option<String>,
+}
+
+/// 从 panic 负载中取出信息
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload.downcast_ref::<&str>().map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "未知 panic".to_string())
+}
+
+/// 生成 ICE 横幅
+pub fn format_banner(tool: &str, version: &str, source_path: &str, message: &str, context: &IceContext) -> String {
+    let mut banner = String::new();
+    banner.push_str("error: internal compiler error (编译器内部错误)\n");
+    banner.push_str(&format!("  阶段:       {}\n", context.phase.unwrap_or("未知")));
+    match &context.location {
+        Some(loc) => banner.push_str(&format!("  源码位置:   {}:{}\n", source_path, loc)),
+        None => banner.push_str(&format!("  源码位置:   {}\n", source_path)),
+    }
+    banner.push_str(&format!("  编译器版本: {} v{}\n", tool, version));
+    banner.push_str(&format!("  panic 信息: {}\n", message));
+    banner
+}
+
+/// 运行编译，把编译器内部 panic 转换为 [`InternalError`]
+///
+/// panic 时立即在 stderr 打印横幅，然后尝试缩减 `source_path` 得到最小复现程序，
+/// 写入与源文件同目录的 `<文件名>.ice.cay`。
+pub fn run_guarded<T>(tool: &'static str, version: &'static str, source_path: &str, f: impl FnOnce() -> T) -> Result<T, InternalError> {
+    let previous_hook = panic::take_hook();
+    let banner_path = source_path.to_string();
+    panic::set_hook(Box::new(move |info| {
+        let message = match info.location() {
+            Some(at) => format!("{} ({}:{})", panic_message(info.payload()), at.file(), at.line()),
+            None => panic_message(info.payload()),
+        };
+        eprint!("{}", format_banner(tool, version, &banner_path, &message, &current_context()));
+    }));
+
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    panic::set_hook(previous_hook);
+
+    result.map_err(|payload| {
+        let message = panic_message(payload.as_ref());
+        let context = current_context();
+        let reproducer = write_reproducer(source_path, &message);
+        InternalError { message, context, reproducer }
+    })
+}
+
+/// 缩减触发 panic 的源文件并写入 `<文件名>.ice.cay`，无法复现时返回 `None`
+fn write_reproducer(source_path: &str, message: &str) -> Option<String> {
+    let source = crate::source::read_source_file(source_path).ok()?;
+    let signature = crate::reduce::error_signature(&format!("internal compiler error: {}", message));
+
+    // 先确认能用默认选项复现，检查期间屏蔽 panic 输出
+    let previous_hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let reproducible = crate::reduce::pipeline_error(&source, source_path).is_some_and(|e| e.contains(&signature));
+    panic::set_hook(previous_hook);
+    if !reproducible {
+        return None;
+    }
+    let (reduced, _) = crate::reduce::reduce_file(&source, source_path, &signature);
+    let path = Path::new(source_path).with_extension("ice.cay").to_string_lossy().to_string();
+    fs::write(&path, reduced).ok()?;
+    Some(path)
+}
+
+impl InternalError {
+    /// 提示用户提交问题报告
+    pub fn report_instructions(&self, source_path: &str) -> String {
+        let mut text = String::from("这是编译器的缺陷，而不是你的程序的错误。\n");
+        text.push_str(&format!("请在 {} 提交问题，并附上:\n", ISSUE_URL));
+        text.push_str("  - 以上完整的错误信息\n");
+        match &self.reproducer {
+            Some(path) => text.push_str(&format!("  - 自动生成的最小复现程序: {}\n", path)),
+            None => text.push_str(&format!(
+                "  - 源文件 {}（无法自动缩减，可尝试 cay-reduce {} --error-contains \"internal compiler error\"）\n",
+                source_path, source_path)),
+        }
+        text
+    }
+}
@@ -0,0 +1,137 @@
+//! .cavi 接口文件
+//!
+//! 接口文件保存一个编译单元对外可见的类、接口、方法和字段签名，
+//! 依赖它的编译单元在语义分析时直接加载摘要，而不必重新解析源代码，
+//! 从而支持分别编译。
+//!
+//! 导入的类的对象在依赖方分配、读写字段，因此摘要中还保存各个类的实例布局：本类声明的全部实例字段
+//! （包括 private 字段）的名称、类型和顺序，以及虚表指针在对象头中的位置。依赖方按相同的规则
+//! 重新计算 `%class.X` 结构体和字段偏移，与定义方的构造函数和方法一致。
+//!
+//! 文件格式: 4 字节魔数 `CAVI` + 4 字节小端格式版本号 + 紧凑 JSON 负载（serde 序列化）。
+
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::error::{cayError, cayResult};
+use crate::types::{ClassInfo, InterfaceInfo, Type, TypeRegistry};
+
+/// 接口文件魔数
+const CAVI_MAGIC: &[u8; 4] = b"CAVI";
+
+/// 接口文件格式版本，格式不兼容变化时递增
+pub const CAVI_FORMAT_VERSION: u32 = 2;
+
+/// 一个编译单元的接口摘要
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterfaceSummary {
+    /// 生成该摘要的编译器版本
+    pub compiler_version: String,
+    /// 对外可见的类（已去除 private 成员）
+    pub classes: Vec<ClassInfo>,
+    /// 接口定义
+    pub interfaces: Vec<InterfaceInfo>,
+    /// 导出的类的实例布局
+    pub layouts: Vec<ClassLayoutSummary>,
+}
+
+/// 类的实例布局：对象头之后依次是父类的字段和本类的字段
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClassLayoutSummary {
+    pub class_name: String,
+    /// 父类（其布局在同一个或其他接口文件中）
+    pub parent: Option<String>,
+    /// 虚表指针在对象结构体中的成员下标
+    pub vtable_index: usize,
+    /// 本类声明的实例字段（包括 private 字段），按结构体中的顺序排列
+    pub fields: Vec<FieldLayoutSummary>,
+}
+
+/// 布局中的一个实例字段
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldLayoutSummary {
+    pub name: String,
+    pub field_type: Type,
+}
+
+impl InterfaceSummary {
+    /// 从类型注册表生成接口摘要
+    ///
+    /// 只包含在当前编译单元中定义的类和接口，不会重复导出从其他 .cavi 导入的类和接口。
+    /// 实例布局在代码生成时确定，由调用方在代码生成之后填入 `layouts`。
+    pub fn from_registry(registry: &TypeRegistry) -> Self {
+        let mut classes: Vec<ClassInfo> = registry.classes.values()
+            .filter(|c| !registry.imported_classes.contains(&c.name) && !registry.is_builtin_class(&c.name))
+            .map(public_view)
+            .collect();
+        classes.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut interfaces: Vec<InterfaceInfo> = registry.interfaces.values()
+            .filter(|i| !registry.imported_interfaces.contains(&i.name))
+            .cloned()
+            .collect();
+        interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Self {
+            compiler_version: crate::version::cavvy_version().to_string(),
+            classes,
+            interfaces,
+            layouts: Vec::new(),
+        }
+    }
+
+    /// 序列化为 .cavi 字节流
+    pub fn to_bytes(&self) -> cayResult<Vec<u8>> {
+        let payload = serde_json::to_vec(self)
+            .map_err(|e| cayError::Io(format!("无法序列化接口摘要: {}", e)))?;
+        let mut bytes = Vec::with_capacity(payload.len() + 8);
+        bytes.extend_from_slice(CAVI_MAGIC);
+        bytes.extend_from_slice(&CAVI_FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&payload);
+        Ok(bytes)
+    }
+
+    /// 从 .cavi 字节流反序列化
+    pub fn from_bytes(bytes: &[u8]) -> cayResult<Self> {
+        if bytes.len() < 8 || &bytes[..4] != CAVI_MAGIC {
+            return Err(cayError::Io("不是有效的 .cavi 接口文件".to_string()));
+        }
+        let version = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+        if version != CAVI_FORMAT_VERSION {
+            return Err(cayError::Io(format!(
+                "接口文件格式版本不兼容: 文件版本 {}, 编译器支持版本 {}，请重新生成该接口文件",
+                version, CAVI_FORMAT_VERSION
+            )));
+        }
+        serde_json::from_slice(&bytes[8..])
+            .map_err(|e| cayError::Io(format!("接口文件已损坏: {}", e)))
+    }
+
+    /// 写入 .cavi 文件
+    pub fn write(&self, path: &str) -> cayResult<()> {
+        let bytes = self.to_bytes()?;
+        std::fs::write(path, bytes)
+            .map_err(|e| cayError::Io(format!("无法写入接口文件 '{}': {}", path, e)))
+    }
+
+    /// 读取 .cavi 文件
+    pub fn read(path: &str) -> cayResult<Self> {
+        let bytes = std::fs::read(path)
+            .map_err(|e| cayError::Io(format!("无法读取接口文件 '{}': {}", path, e)))?;
+        Self::from_bytes(&bytes)
+            .map_err(|e| cayError::Io(format!("{} ({})", e, path)))
+    }
+}
+
+/// 去除类中 private 成员后的对外视图
+fn public_view(class: &ClassInfo) -> ClassInfo {
+    let mut view = class.clone();
+    view.fields.retain(|_, f| !f.is_private);
+    view.methods = class.methods.iter()
+        .filter_map(|(name, overloads)| {
+            let visible: Vec<_> = overloads.iter().filter(|m| !m.is_private).cloned().collect();
+            (!visible.is_empty()).then(|| (name.clone(), visible))
+        })
+        .collect::<HashMap<_, _>>();
+    view.constructors.retain(|c| !c.is_private);
+    view
+}
@@ -0,0 +1,495 @@
+//! 内置函数、内置类（Math、Bits、Arrays）、Map/StringBuilder 的 native 方法和字符串方法
+//!
+//! 行为与代码生成展开的版本一致：打印格式相同，窄化转换越界时报告同样的错误。
+
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+use crate::ast::CallExpr;
+use crate::prelude::NativeClass;
+use crate::semantic::{FormatPiece, FormatSpec};
+use crate::types::Type;
+use super::eval::{runtime_error, Exec, Interpreter, Unwind};
+use super::value::{c_exponent, format_fixed, NativeData, Value};
+
+/// `setPrintPrecision` 允许的最大小数位数，与编译后程序一致
+const MAX_PRINT_PRECISION: i64 = 20;
+
+impl<W: Write> Interpreter<W> {
+    /// 调用内置全局函数；不是内置函数时返回 `None`
+    pub(crate) fn call_builtin(&mut self, name: &str, args: &[Value]) -> Exec<Option<Value>> {
+        let value = match (name, args) {
+            ("println", [value @ (Value::Float(_) | Value::Double(_)), digits]) => {
+                // println(value, digits)：只对这一次输出使用固定的小数位数
+                let digits = digits.as_i64().unwrap_or_default().clamp(0, MAX_PRINT_PRECISION) as usize;
+                let text = format_fixed(value.as_f64().unwrap_or_default(), digits);
+                self.write_output(&format!("{}\n", text))?;
+                Value::Void
+            }
+            ("print" | "println", _) => {
+                let mut text = match args.first() {
+                    Some(value) => self.format_printed(value)?,
+                    None => String::new(),
+                };
+                if name == "println" {
+                    text.push('\n');
+                }
+                self.write_output(&text)?;
+                Value::Void
+            }
+            ("setPrintPrecision", [n]) => {
+                self.precision = Some(n.as_i64().unwrap_or_default().clamp(0, MAX_PRINT_PRECISION) as usize);
+                Value::Void
+            }
+            ("toIntExact", [v]) => {
+                let v = v.as_i64().unwrap_or_default();
+                match i32::try_from(v) {
+                    Ok(v) => Value::Int(v),
+                    Err(_) => return runtime_error(format!("toIntExact() value {} out of int range", v)),
+                }
+            }
+            ("toByteExact", [v]) => {
+                let v = v.as_i64().unwrap_or_default();
+                match i8::try_from(v) {
+                    Ok(v) => Value::Int(v as i32),
+                    Err(_) => return runtime_error(format!("toByteExact() value {} out of byte range", v)),
+                }
+            }
+            ("exit", [code]) => return Err(Unwind::Exit(code.as_i64().unwrap_or_default() as i32)),
+            ("readLine", []) => Value::str(read_line()?),
+            ("readInt", []) => Value::Int(read_line()?.trim().parse().unwrap_or(0)),
+            ("readFloat", []) => Value::Float(read_line()?.trim().parse().unwrap_or(0.0)),
+            ("readLong", []) => Value::Long(read_line()?.trim().parse().unwrap_or(0)),
+            ("readDouble", []) => Value::Double(read_line()?.trim().parse().unwrap_or(0.0)),
+            ("readChar", []) => Value::Char(read_line()?.trim().chars().next().unwrap_or('\0')),
+            ("readBool", []) => Value::Bool(read_line()?.trim() == "true"),
+            ("readChars", [Value::Array(arr)]) => {
+                let line = read_line()?;
+                let mut arr = arr.borrow_mut();
+                let count = line.chars().zip(arr.values.iter_mut()).map(|(c, slot)| *slot = Value::Char(c)).count();
+                Value::Int(count as i32)
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(value))
+    }
+
+    /// print/println 的参数格式：char[] 按字符串打印，其他值与字符串拼接相同
+    fn format_printed(&mut self, value: &Value) -> Exec<String> {
+        if let Value::Array(arr) = value
+            && arr.borrow().elem == Type::Char
+        {
+            return Ok(arr.borrow().values.iter().filter_map(|v| match v {
+                Value::Char(c) => Some(*c),
+                _ => None,
+            }).collect());
+        }
+        self.format(value)
+    }
+
+    /// debugPrint(expr)：输出 `[line N] 表达式: 类型 = 值`
+    pub(crate) fn debug_print(&mut self, call: &CallExpr) -> Exec<Value> {
+        let Some(arg) = call.args.first() else {
+            return runtime_error("debugPrint() expects 1 argument(s), got 0");
+        };
+        let value = self.eval(arg)?;
+        let (text, ty) = match self.debug_prints.get(&call.id) {
+            Some((text, ty)) => (text.clone(), ty.source_name()),
+            None => (arg.to_string(), self.describe(&value)),
+        };
+        let line = call.loc.line.saturating_sub(self.line_offset);
+        let formatted = self.format(&value)?;
+        self.write_output(&format!("[line {}] {}: {} = {}\n", line, text, ty, formatted))?;
+        Ok(Value::Void)
+    }
+
+    /// format(fmt, args...) 和 print/println(fmt, args...)：按语义分析解析出的格式字符串输出
+    pub(crate) fn formatted_call(&mut self, name: &str, call: &CallExpr) -> Exec<Value> {
+        let Some((pieces, arg_types)) = self.format_calls.get(&call.id).cloned() else {
+            return runtime_error(format!("{}() call has no recorded format string", name));
+        };
+        let args = self.eval_args(&call.args[1..])?;
+        let mut text = String::new();
+        let mut args = args.iter().zip(&arg_types);
+        for piece in &pieces {
+            match piece {
+                FormatPiece::Text(part) => text.push_str(part),
+                FormatPiece::Arg(spec) => {
+                    let Some((value, ty)) = args.next() else {
+                        return runtime_error(format!("{}() has fewer arguments than specifiers", name));
+                    };
+                    let formatted = self.format_argument(spec, ty, value)?;
+                    text.push_str(&formatted);
+                }
+            }
+        }
+        match name {
+            "format" => Ok(Value::str(text)),
+            _ => {
+                if name == "println" {
+                    text.push('\n');
+                }
+                self.write_output(&text)?;
+                Ok(Value::Void)
+            }
+        }
+    }
+
+    /// 按说明符格式化一个参数，与 C 的 snprintf 输出一致
+    fn format_argument(&mut self, spec: &FormatSpec, ty: &Type, value: &Value) -> Exec<String> {
+        let (sign, body) = match spec.conversion {
+            'd' => {
+                let v = value.as_i64().unwrap_or_default();
+                (sign_prefix(spec, v < 0), v.unsigned_abs().to_string())
+            }
+            'x' | 'X' | 'o' => {
+                // 按静态类型的位宽解释为无符号数，(byte) -1 输出 ff
+                let bits = match ty {
+                    Type::Int8 => 8,
+                    Type::Int16 => 16,
+                    Type::Int64 => 64,
+                    _ => 32,
+                };
+                let v = value.as_i64().unwrap_or_default() as u64 & (u64::MAX >> (64 - bits));
+                let body = match spec.conversion {
+                    'x' => format!("{:x}", v),
+                    'X' => format!("{:X}", v),
+                    _ => format!("{:o}", v),
+                };
+                let prefix = match spec.conversion {
+                    _ if !spec.has_flag('#') => "",
+                    'o' => if body.starts_with('0') { "" } else { "0" },
+                    _ if v == 0 => "",
+                    'x' => "0x",
+                    _ => "0X",
+                };
+                (prefix.to_string(), body)
+            }
+            'f' | 'e' | 'E' => {
+                let v = value.as_f64().unwrap_or_default();
+                let precision = spec.precision.unwrap_or(6);
+                let body = if v.is_nan() {
+                    "nan".to_string()
+                } else if v.is_infinite() {
+                    "inf".to_string()
+                } else if spec.conversion == 'f' {
+                    format!("{:.*}", precision, v.abs())
+                } else {
+                    c_exponent(&format!("{:.*e}", precision, v.abs()))
+                };
+                let body = if spec.conversion == 'E' { body.to_uppercase() } else { body };
+                if !v.is_finite() {
+                    return Ok(pad(spec, &sign_prefix(spec, v.is_sign_negative()), &body, false));
+                }
+                (sign_prefix(spec, v.is_sign_negative()), body)
+            }
+            'c' => {
+                let c = match value {
+                    Value::Char(c) => *c,
+                    other => other.as_i64().and_then(|v| char::from_u32(v as u32)).unwrap_or_default(),
+                };
+                return Ok(pad(spec, "", &c.to_string(), false));
+            }
+            _ => {
+                let text = self.format(value)?;
+                let text = match spec.precision {
+                    Some(precision) => text.chars().take(precision).collect(),
+                    None => text,
+                };
+                return Ok(pad(spec, "", &text, false));
+            }
+        };
+        Ok(pad(spec, &sign, &body, true))
+    }
+
+    /// 内置类的常量（`Math.PI`、`Math.E`）
+    pub(crate) fn builtin_constant(&self, class: &str, name: &str) -> Option<Value> {
+        match (class, name) {
+            ("Math", "PI") => Some(Value::Double(std::f64::consts::PI)),
+            ("Math", "E") => Some(Value::Double(std::f64::consts::E)),
+            _ => None,
+        }
+    }
+
+    /// 内置类的 native 方法（见 [`crate::prelude::NativeClass`]）
+    pub(crate) fn native_method(&mut self, this: &Value, native: &NativeClass, name: &str, args: Vec<Value>) -> Exec<Value> {
+        let Value::Object(obj) = this else {
+            return runtime_error(format!("Cannot call method '{}' on {}", name, self.describe(this)));
+        };
+        match native {
+            NativeClass::Map(key_type, value_type) => {
+                if !matches!(obj.borrow().native, NativeData::Map(_)) {
+                    obj.borrow_mut().native = NativeData::Map(Vec::new());
+                }
+                let NativeData::Map(entries) = &mut obj.borrow_mut().native else {
+                    unreachable!("map data was just created");
+                };
+                map_method(entries, name, key_type, value_type, args)
+            }
+            NativeClass::StringBuilder => {
+                let mut args = args.into_iter();
+                // 追加的值按字符串拼接的规则转换为文本
+                let appended = match args.next() {
+                    Some(value) => Some(self.format(&value)?),
+                    None => None,
+                };
+                if !matches!(obj.borrow().native, NativeData::Text(_)) {
+                    obj.borrow_mut().native = NativeData::Text(String::new());
+                }
+                let NativeData::Text(text) = &mut obj.borrow_mut().native else {
+                    unreachable!("text data was just created");
+                };
+                Ok(match (name, appended) {
+                    ("append", Some(appended)) => {
+                        text.push_str(&appended);
+                        this.clone()
+                    }
+                    ("toString", None) => Value::str(text.as_str()),
+                    ("length", None) => Value::Int(text.len() as i32),
+                    ("clear", None) => {
+                        text.clear();
+                        Value::Void
+                    }
+                    _ => return runtime_error(format!("Unknown native method 'StringBuilder.{}'", name)),
+                })
+            }
+        }
+    }
+
+    /// 调用内置类的静态方法；不是内置方法时返回 `None`
+    pub(crate) fn call_builtin_static(&mut self, class: &str, name: &str, args: &[Value]) -> Exec<Option<Value>> {
+        let value = match (class, name, args) {
+            // 数组与字符串拼接的格式相同，null 转换为 "null"
+            ("Arrays", "toString", [arr]) => Value::str(self.format(arr)?),
+            ("Math", "sqrt" | "floor" | "ceil" | "sin" | "cos" | "tan" | "exp" | "log", [x]) => {
+                let x = x.as_f64().unwrap_or_default();
+                Value::Double(match name {
+                    "sqrt" => x.sqrt(),
+                    "floor" => x.floor(),
+                    "ceil" => x.ceil(),
+                    "sin" => x.sin(),
+                    "cos" => x.cos(),
+                    "tan" => x.tan(),
+                    "exp" => x.exp(),
+                    _ => x.ln(),
+                })
+            }
+            ("Math", "pow", [x, y]) => Value::Double(x.as_f64().unwrap_or_default().powf(y.as_f64().unwrap_or_default())),
+            // Java 语义：round(x) = floor(x + 0.5)
+            ("Math", "round", [x]) => Value::Long((x.as_f64().unwrap_or_default() + 0.5).floor() as i64),
+            ("Math", "random", []) => Value::Double(self.next_random()),
+            ("Math", "abs", [x]) => match x {
+                Value::Int(v) => Value::Int(v.wrapping_abs()),
+                Value::Long(v) => Value::Long(v.wrapping_abs()),
+                Value::Float(v) => Value::Float(v.abs()),
+                Value::Double(v) => Value::Double(v.abs()),
+                other => Value::Int(other.as_i64().unwrap_or_default().wrapping_abs() as i32),
+            },
+            ("Math", "min" | "max", [a, b]) => {
+                let ty = wider_numeric_type(a, b);
+                let (a, b) = (a.clone().convert_to(&ty), b.clone().convert_to(&ty));
+                let a_smaller = a.as_f64().unwrap_or_default() < b.as_f64().unwrap_or_default();
+                if a_smaller == (name == "min") { a } else { b }
+            }
+            ("Bits", "popcount" | "leadingZeros" | "trailingZeros", [v]) => Value::Int(match v {
+                Value::Long(v) => match name {
+                    "popcount" => v.count_ones(),
+                    "leadingZeros" => v.leading_zeros(),
+                    _ => v.trailing_zeros(),
+                },
+                other => {
+                    let v = other.as_i64().unwrap_or_default() as i32;
+                    match name {
+                        "popcount" => v.count_ones(),
+                        "leadingZeros" => v.leading_zeros(),
+                        _ => v.trailing_zeros(),
+                    }
+                }
+            } as i32),
+            ("Bits", "rotateLeft" | "rotateRight", [v, n]) => {
+                // 负数距离表示反方向移位
+                let n = n.as_i64().unwrap_or_default();
+                let left = (name == "rotateLeft") == (n >= 0);
+                let n = n.unsigned_abs() as u32;
+                match v {
+                    Value::Long(v) => Value::Long(if left { v.rotate_left(n % 64) } else { v.rotate_right(n % 64) }),
+                    other => {
+                        let v = other.as_i64().unwrap_or_default() as i32;
+                        Value::Int(if left { v.rotate_left(n % 32) } else { v.rotate_right(n % 32) })
+                    }
+                }
+            }
+            // 与编译后的运行时一致：文件无法打开时 readAll 返回 null，其余方法返回 false
+            ("File", "readAll", [Value::Str(path)]) => std::fs::read(path.as_ref())
+                .map(|bytes| Value::str(String::from_utf8_lossy(&bytes).as_ref()))
+                .unwrap_or(Value::Null),
+            ("File", "writeAll", [Value::Str(path), text]) => Value::Bool(std::fs::write(path.as_ref(), file_text(text)).is_ok()),
+            ("File", "appendLine", [Value::Str(path), text]) => {
+                let appended = std::fs::OpenOptions::new().append(true).create(true).open(path.as_ref())
+                    .and_then(|mut file| file.write_all(format!("{}\n", file_text(text)).as_bytes()));
+                Value::Bool(appended.is_ok())
+            }
+            ("File", "exists", [Value::Str(path)]) => Value::Bool(std::fs::File::open(path.as_ref()).is_ok()),
+            ("File", "delete", [Value::Str(path)]) => Value::Bool(std::fs::remove_file(path.as_ref()).is_ok()),
+            // 路径为 null
+            ("File", "readAll", [_]) => Value::Null,
+            ("File", _, _) => Value::Bool(false),
+            _ => return Ok(None),
+        };
+        Ok(Some(value))
+    }
+
+    /// xorshift 伪随机数，结果在 [0, 1) 内
+    fn next_random(&mut self) -> f64 {
+        let mut x = self.rng;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.rng = x;
+        (x >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// 字符串的内置方法
+    pub(crate) fn string_method(&mut self, s: &Rc<str>, name: &str, args: &[Value]) -> Exec<Value> {
+        let chars: Vec<char> = s.chars().collect();
+        let index = |v: &Value| v.as_i64().unwrap_or_default();
+        let text = |v: &Value| match v {
+            Value::Str(s) => Some(s.to_string()),
+            _ => None,
+        };
+        let value = match (name, args) {
+            ("length", []) => Value::Int(chars.len() as i32),
+            ("charAt", [i]) => {
+                let i = index(i);
+                match usize::try_from(i).ok().and_then(|i| chars.get(i)) {
+                    Some(c) => Value::Char(*c),
+                    None => return runtime_error(format!("String index out of bounds: index {}, length {}", i, chars.len())),
+                }
+            }
+            ("substring", [begin, rest @ ..]) => {
+                let begin = index(begin);
+                let end = rest.first().map_or(chars.len() as i64, index);
+                if begin < 0 || end < begin || end > chars.len() as i64 {
+                    return runtime_error(format!("substring({}, {}) out of range for length {}", begin, end, chars.len()));
+                }
+                Value::str(chars[begin as usize..end as usize].iter().collect::<String>())
+            }
+            ("indexOf", [needle]) => {
+                let needle = text(needle).unwrap_or_default();
+                Value::Int(s.find(needle.as_str()).map_or(-1, |byte| s[..byte].chars().count() as i32))
+            }
+            ("replace", [from, to]) => {
+                let (from, to) = (text(from).unwrap_or_default(), text(to).unwrap_or_default());
+                if from.is_empty() { Value::Str(s.clone()) } else { Value::str(s.replace(&from, &to)) }
+            }
+            ("toUpperCase", []) => Value::str(s.to_ascii_uppercase()),
+            ("toLowerCase", []) => Value::str(s.to_ascii_lowercase()),
+            ("trim", []) => Value::str(s.trim_matches(|c: char| c <= ' ')),
+            ("startsWith", [prefix]) => Value::Bool(s.starts_with(text(prefix).unwrap_or_default().as_str())),
+            ("endsWith", [suffix]) => Value::Bool(s.ends_with(text(suffix).unwrap_or_default().as_str())),
+            ("contains", [part]) => Value::Bool(s.contains(text(part).unwrap_or_default().as_str())),
+            ("split", [sep]) => {
+                let sep = text(sep).unwrap_or_default();
+                let parts: Vec<Value> = if sep.is_empty() {
+                    chars.iter().map(|c| Value::str(c.to_string())).collect()
+                } else {
+                    s.split(sep.as_str()).map(Value::str).collect()
+                };
+                Value::new_array(Type::String, parts)
+            }
+            _ => return runtime_error(format!("Unknown String method '{}'", name)),
+        };
+        Ok(value)
+    }
+}
+
+/// min/max 的结果类型：两个参数中较宽的数值类型
+fn wider_numeric_type(a: &Value, b: &Value) -> Type {
+    let rank = |v: &Value| match v {
+        Value::Long(_) => 1,
+        Value::Float(_) => 2,
+        Value::Double(_) => 3,
+        _ => 0,
+    };
+    match rank(a).max(rank(b)) {
+        0 => Type::Int32,
+        1 => Type::Int64,
+        2 => Type::Float32,
+        _ => Type::Float64,
+    }
+}
+
+/// 数值的符号前缀：负数为 `-`，否则按 `+`/空格标志
+fn sign_prefix(spec: &FormatSpec, negative: bool) -> String {
+    if negative {
+        "-".to_string()
+    } else if spec.has_flag('+') {
+        "+".to_string()
+    } else if spec.has_flag(' ') {
+        " ".to_string()
+    } else {
+        String::new()
+    }
+}
+
+/// 按宽度填充：`-` 标志左对齐，`0` 标志在前缀之后补零（仅数值），否则右对齐
+fn pad(spec: &FormatSpec, prefix: &str, body: &str, numeric: bool) -> String {
+    let len = prefix.chars().count() + body.chars().count();
+    let fill = spec.width.unwrap_or(0).saturating_sub(len);
+    if spec.has_flag('-') {
+        format!("{}{}{}", prefix, body, " ".repeat(fill))
+    } else if spec.has_flag('0') && numeric {
+        format!("{}{}{}", prefix, "0".repeat(fill), body)
+    } else {
+        format!("{}{}{}", " ".repeat(fill), prefix, body)
+    }
+}
+
+/// 从标准输入读一行（不含换行符），输入结束时返回空字符串
+/// 写入文件的文本，null 写入 "null"
+/// Map 的 native 方法：条目按插入顺序保存，keys() 的顺序与编译后的程序一致
+fn map_method(entries: &mut Vec<(Value, Value)>, name: &str, key_type: &Type, value_type: &Type, args: Vec<Value>) -> Exec<Value> {
+    let mut args = args.into_iter();
+    let key = args.next().map(|k| k.convert_to(key_type));
+    let position = key.as_ref().and_then(|key| entries.iter().position(|(k, _)| map_key_eq(k, key)));
+    Ok(match (name, key, args.next()) {
+        ("put", Some(key), Some(value)) => {
+            let value = value.convert_to(value_type);
+            match position {
+                Some(index) => entries[index].1 = value,
+                None => entries.push((key, value)),
+            }
+            Value::Void
+        }
+        ("get", Some(_), None) => position.map_or_else(|| Value::default_for(value_type), |index| entries[index].1.clone()),
+        ("containsKey", Some(_), None) => Value::Bool(position.is_some()),
+        ("remove", Some(_), None) => Value::Bool(position.map(|index| entries.remove(index)).is_some()),
+        ("size", None, None) => Value::Int(entries.len() as i32),
+        ("keys", None, None) => Value::new_array(key_type.clone(), entries.iter().map(|(k, _)| k.clone()).collect()),
+        _ => return runtime_error(format!("Unknown native method 'Map.{}'", name)),
+    })
+}
+
+/// Map 键的比较：int 键比较数值，String 键比较内容
+fn map_key_eq(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Int(a), Value::Int(b)) => a == b,
+        (Value::Str(a), Value::Str(b)) => a == b,
+        (Value::Null, Value::Null) => true,
+        _ => false,
+    }
+}
+
+fn file_text(value: &Value) -> String {
+    match value {
+        Value::Str(s) => s.to_string(),
+        _ => "null".to_string(),
+    }
+}
+
+fn read_line() -> Exec<String> {
+    let mut line = String::new();
+    if let Err(e) = io::stdin().lock().read_line(&mut line) {
+        return runtime_error(format!("Failed to read input: {}", e));
+    }
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
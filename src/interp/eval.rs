@@ -0,0 +1,1441 @@
+//! 语句和表达式的求值
+//!
+//! 直接遍历经过语义分析的 AST：语义分析已经保证了类型正确，这里只处理运行时才能
+//! 发现的错误（除以零、数组越界、访问 null 等）。方法重载按实参的运行时类型选择，
+//! 实例方法按对象的实际类动态分派。
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::rc::Rc;
+use crate::ast::*;
+use crate::semantic::FormatPiece;
+use crate::types::{ParameterInfo, Type};
+use super::value::{Array, Bindings, Formatter, Function, NativeData, Object, Value};
+
+/// 调用深度上限：超过时报告栈溢出，避免耗尽解释器自身的栈（`max_depth` 设置得更大时也以此为准）
+const MAX_CALL_DEPTH: usize = 2000;
+
+/// 对象头（类型 ID 和虚表指针）的字节数，与编译后的对象布局一致
+const OBJECT_HEADER_BYTES: u64 = 16;
+/// 数组长度前缀的字节数
+const ARRAY_HEADER_BYTES: u64 = 8;
+
+/// 非正常结束的求值：控制流跳转或运行时错误
+pub(crate) enum Unwind {
+    /// break/continue 及其目标标签
+    Break(Option<String>),
+    Continue(Option<String>),
+    Return(Value),
+    Error(String),
+    /// exit(code)：结束整个程序
+    Exit(i32),
+}
+
+pub(crate) type Exec<T> = Result<T, Unwind>;
+
+pub(crate) fn runtime_error<T>(message: impl Into<String>) -> Exec<T> {
+    Err(Unwind::Error(message.into()))
+}
+
+/// 类的定义：方法和构造函数单独保存，调用时不必克隆方法体
+struct ClassDef {
+    decl: Rc<ClassDecl>,
+    methods: Vec<Rc<MethodDecl>>,
+    constructors: Vec<Rc<ConstructorDecl>>,
+}
+
+/// 一次调用的上下文
+struct Frame {
+    scopes: Vec<Bindings>,
+    this: Option<Value>,
+    class: Option<String>,
+}
+
+/// 可以赋值的位置
+enum Place {
+    Local(String),
+    Field(Rc<RefCell<Object>>, String),
+    Static(String, String),
+    Element(Rc<RefCell<Array>>, usize),
+}
+
+/// AST 解释器
+pub struct Interpreter<W: Write> {
+    pub(crate) out: W,
+    classes: HashMap<String, ClassDef>,
+    interfaces: HashSet<String>,
+    /// 静态字段：(类名, 字段名) -> (类型, 值)
+    statics: HashMap<(String, String), (Type, Value)>,
+    frames: Vec<Frame>,
+    /// 浮点数的小数位数（`setPrintPrecision`），`None` 表示最短的可还原形式
+    pub(crate) precision: Option<usize>,
+    pub(crate) max_steps: Option<u64>,
+    steps: u64,
+    /// 累计堆分配上限（字节），按编译后程序的内存布局估算对象、数组和拼接出的字符串
+    pub(crate) max_heap_bytes: Option<u64>,
+    heap_used: u64,
+    /// 调用深度上限
+    pub(crate) max_depth: Option<usize>,
+    /// 语义分析推断出的 auto/var/let 变量类型
+    pub(crate) inferred: NodeMap<Type>,
+    /// 语义分析记录的 debugPrint 参数文本和类型
+    pub(crate) debug_prints: NodeMap<(String, Type)>,
+    /// 语义分析解析出的格式字符串和参数类型（format 和格式化的 print/println）
+    pub(crate) format_calls: NodeMap<(Vec<FormatPiece>, Vec<Type>)>,
+    /// 会话程序中当前输入之前的行数，debugPrint 据此报告输入中的行号
+    pub(crate) line_offset: usize,
+    pub(crate) rng: u64,
+}
+
+impl<W: Write> Interpreter<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            classes: HashMap::new(),
+            interfaces: HashSet::new(),
+            statics: HashMap::new(),
+            frames: Vec::new(),
+            precision: None,
+            max_steps: None,
+            steps: 0,
+            max_heap_bytes: None,
+            heap_used: 0,
+            max_depth: None,
+            inferred: NodeMap::new(),
+            debug_prints: NodeMap::new(),
+            format_calls: NodeMap::new(),
+            line_offset: 0,
+            rng: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+
+    /// 载入程序中的类和接口，替换之前的定义；静态字段保持不变
+    pub(crate) fn load_program(&mut self, program: &Program) {
+        self.classes = program.classes.iter().map(|class| {
+            let decl = Rc::new(class.clone());
+            let mut methods = Vec::new();
+            let mut constructors = Vec::new();
+            for member in &class.members {
+                match member {
+                    ClassMember::Method(method) => methods.push(Rc::new(method.clone())),
+                    ClassMember::Constructor(ctor) => constructors.push(Rc::new(ctor.clone())),
+                    _ => {}
+                }
+            }
+            (class.name.clone(), ClassDef { decl, methods, constructors })
+        }).collect();
+        self.interfaces = program.interfaces.iter().map(|i| i.name.clone()).collect();
+    }
+
+    /// 删除类的静态字段（类被重新定义时）
+    pub(crate) fn reset_statics(&mut self, class: &str) {
+        self.statics.retain(|(owner, _), _| owner != class);
+    }
+
+    /// 初始化类的静态字段并执行静态初始化块
+    pub(crate) fn init_statics(&mut self, class: &str) -> Exec<()> {
+        let Some(decl) = self.classes.get(class).map(|def| def.decl.clone()) else {
+            return Ok(());
+        };
+        let static_fields = decl.members.iter().filter_map(|member| match member {
+            ClassMember::Field(field) if field.modifiers.contains(&Modifier::Static) => Some(field),
+            _ => None,
+        });
+        for field in static_fields.clone() {
+            self.statics.insert((class.to_string(), field.name.clone()),
+                (field.field_type.clone(), Value::default_for(&field.field_type)));
+        }
+
+        self.frames.push(Frame { scopes: vec![Bindings::new()], this: None, class: Some(class.to_string()) });
+        let result = (|| {
+            for member in &decl.members {
+                match member {
+                    ClassMember::Field(field) if field.modifiers.contains(&Modifier::Static) => {
+                        if let Some(init) = &field.initializer {
+                            let value = self.eval_initializer(init, &field.field_type)?;
+                            self.statics.insert((class.to_string(), field.name.clone()), (field.field_type.clone(), value));
+                        }
+                    }
+                    ClassMember::StaticInitializer(block) => self.exec_body(block).map(|_| ())?,
+                    _ => {}
+                }
+            }
+            Ok(())
+        })();
+        self.frames.pop();
+        result
+    }
+
+    /// 在会话的顶层执行语句：`globals` 是最外层作用域，顶层声明的变量留在其中
+    ///
+    /// 返回最后一条语句的值（最后一条语句是表达式语句时）。
+    pub(crate) fn run(&mut self, statements: &[Stmt], globals: &mut Bindings, class: &str) -> Exec<Value> {
+        self.steps = 0;
+        self.heap_used = 0;
+        self.frames.push(Frame {
+            scopes: vec![std::mem::take(globals)],
+            this: None,
+            class: Some(class.to_string()),
+        });
+        let mut result = Ok(Value::Void);
+        for stmt in statements {
+            result = match stmt {
+                Stmt::Expr(expr) => self.eval(expr),
+                _ => self.exec(stmt).map(|_| Value::Void),
+            };
+            if result.is_err() {
+                break;
+            }
+        }
+        // 出错时保留调用栈底部的会话帧，其余帧随错误展开丢弃
+        self.frames.truncate(1);
+        if let Some(frame) = self.frames.pop() {
+            *globals = frame.scopes.into_iter().next().unwrap_or_default();
+        }
+        match result {
+            Err(Unwind::Return(_)) => Ok(Value::Void),
+            other => other,
+        }
+    }
+
+    // ---------- 语句 ----------
+
+    fn frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().expect("interpreter frame")
+    }
+
+    fn frame_ref(&self) -> &Frame {
+        self.frames.last().expect("interpreter frame")
+    }
+
+    /// 在新的块作用域中执行，作用域在正常结束和跳转时都会弹出
+    fn scoped<T>(&mut self, f: impl FnOnce(&mut Self) -> Exec<T>) -> Exec<T> {
+        self.frame().scopes.push(Bindings::new());
+        let result = f(self);
+        self.frame().scopes.pop();
+        result
+    }
+
+    fn exec_block(&mut self, statements: &[Stmt]) -> Exec<()> {
+        self.scoped(|this| statements.iter().try_for_each(|stmt| this.exec(stmt)))
+    }
+
+    /// 执行方法体，返回 return 语句的值
+    fn exec_body(&mut self, body: &Block) -> Exec<Value> {
+        match self.exec_block(&body.statements) {
+            Ok(()) | Err(Unwind::Break(_)) | Err(Unwind::Continue(_)) => Ok(Value::Void),
+            Err(Unwind::Return(value)) => Ok(value),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// 每次进入函数、每轮循环计一步，与编译选项 `--max-steps` 一致
+    pub(crate) fn step(&mut self) -> Exec<()> {
+        self.steps += 1;
+        match self.max_steps {
+            Some(max) if self.steps > max => runtime_error(format!("Execution limit exceeded ({} steps)", max)),
+            _ => Ok(()),
+        }
+    }
+
+    /// 登记一次堆分配，超过 `--max-heap` 上限时报错；在真正分配之前调用
+    fn allocate(&mut self, bytes: u64) -> Exec<()> {
+        self.heap_used = self.heap_used.saturating_add(bytes);
+        match self.max_heap_bytes {
+            Some(max) if self.heap_used > max => runtime_error(format!("Execution limit exceeded ({} bytes of heap)", max)),
+            _ => Ok(()),
+        }
+    }
+
+    /// 登记数组的分配：多维数组逐层计算
+    fn allocate_array(&mut self, elem: &Type, sizes: &[usize]) -> Exec<()> {
+        let mut count: u64 = 1;
+        for (depth, &len) in sizes.iter().enumerate() {
+            // 最内层是元素本身，外层的元素是指向下一层数组的指针
+            let elem_bytes = if depth + 1 == sizes.len() { value_bytes(elem) } else { 8 };
+            self.allocate(count.saturating_mul(ARRAY_HEADER_BYTES.saturating_add((len as u64).saturating_mul(elem_bytes))))?;
+            count = count.saturating_mul(len as u64);
+        }
+        Ok(())
+    }
+
+    fn exec(&mut self, stmt: &Stmt) -> Exec<()> {
+        match stmt {
+            Stmt::Expr(expr) => self.eval(expr).map(|_| ()),
+            Stmt::VarDecl(decl) => self.exec_var_decl(decl),
+            Stmt::Return(ret) => {
+                let value = match &ret.value {
+                    Some(expr) => self.eval(expr)?,
+                    None => Value::Void,
+                };
+                Err(Unwind::Return(value))
+            }
+            Stmt::If(stmt) => {
+                if self.eval_condition(&stmt.condition)? {
+                    self.scoped(|this| this.exec(&stmt.then_branch))
+                } else if let Some(else_branch) = &stmt.else_branch {
+                    self.scoped(|this| this.exec(else_branch))
+                } else {
+                    Ok(())
+                }
+            }
+            Stmt::While(_) | Stmt::DoWhile(_) | Stmt::For(_) | Stmt::ForEach(_) | Stmt::Switch(_) => self.exec_target(stmt, None),
+            Stmt::Labeled(labeled) => match *labeled.body {
+                Stmt::While(_) | Stmt::DoWhile(_) | Stmt::For(_) | Stmt::ForEach(_) | Stmt::Switch(_) => {
+                    self.exec_target(&labeled.body, Some(&labeled.label))
+                }
+                _ => match self.exec(&labeled.body) {
+                    Err(Unwind::Break(Some(target))) if target == labeled.label => Ok(()),
+                    other => other,
+                },
+            },
+            Stmt::Block(block) => self.exec_block(&block.statements),
+            Stmt::Break(jump) => Err(Unwind::Break(jump.label.clone())),
+            Stmt::Continue(jump) => Err(Unwind::Continue(jump.label.clone())),
+        }
+    }
+
+    /// 执行循环或 switch，`label` 为标记它的标签
+    fn exec_target(&mut self, stmt: &Stmt, label: Option<&str>) -> Exec<()> {
+        match stmt {
+            Stmt::While(stmt) => {
+                while self.eval_condition(&stmt.condition)? {
+                    self.step()?;
+                    if !self.exec_loop_body(&stmt.body, label)? {
+                        break;
+                    }
+                }
+                Ok(())
+            }
+            Stmt::DoWhile(stmt) => {
+                loop {
+                    self.step()?;
+                    if !self.exec_loop_body(&stmt.body, label)? || !self.eval_condition(&stmt.condition)? {
+                        break;
+                    }
+                }
+                Ok(())
+            }
+            Stmt::For(stmt) => self.scoped(|this| {
+                if let Some(init) = &stmt.init {
+                    this.exec(init)?;
+                }
+                loop {
+                    if let Some(condition) = &stmt.condition
+                        && !this.eval_condition(condition)?
+                    {
+                        break;
+                    }
+                    this.step()?;
+                    if !this.exec_loop_body(&stmt.body, label)? {
+                        break;
+                    }
+                    if let Some(update) = &stmt.update {
+                        this.eval(update)?;
+                    }
+                }
+                Ok(())
+            }),
+            Stmt::ForEach(stmt) => self.exec_for_each(stmt, label),
+            Stmt::Switch(stmt) => self.exec_switch(stmt, label),
+            _ => self.exec(stmt),
+        }
+    }
+
+    /// 执行 for-each 循环：数组按下标取元素，对象每轮调用 `size()` 和 `get(i)`
+    fn exec_for_each(&mut self, stmt: &ForEachStmt, label: Option<&str>) -> Exec<()> {
+        let iterable = self.eval(&stmt.iterable)?;
+        let mut index = 0;
+        loop {
+            let element = match &iterable {
+                Value::Array(array) => match array.borrow().values.get(index) {
+                    Some(value) => value.clone(),
+                    None => break,
+                },
+                Value::Object(_) => {
+                    let size = self.call_on_value(iterable.clone(), "size", Vec::new())?;
+                    if size.as_i64().is_none_or(|size| index as i64 >= size) {
+                        break;
+                    }
+                    self.call_on_value(iterable.clone(), "get", vec![Value::Int(index as i32)])?
+                }
+                Value::Null => return runtime_error("Null pointer access: cannot iterate over null".to_string()),
+                other => return runtime_error(format!("Cannot iterate over {}", self.describe(other))),
+            };
+            let ty = match &stmt.var_type {
+                Type::Auto => element.runtime_type().unwrap_or(Type::Auto),
+                ty => ty.clone(),
+            };
+            self.step()?;
+            let proceed = self.scoped(|this| {
+                if let Some(scope) = this.frame().scopes.last_mut() {
+                    scope.insert(stmt.var_name.clone(), (ty.clone(), element.convert_to(&ty)));
+                }
+                this.exec_loop_body(&stmt.body, label)
+            })?;
+            if !proceed {
+                break;
+            }
+            index += 1;
+        }
+        Ok(())
+    }
+
+    /// 执行一轮循环体，返回是否继续循环
+    fn exec_loop_body(&mut self, body: &Stmt, label: Option<&str>) -> Exec<bool> {
+        match self.scoped(|this| this.exec(body)) {
+            Ok(()) => Ok(true),
+            Err(Unwind::Continue(target)) if targets(&target, label) => Ok(true),
+            Err(Unwind::Break(target)) if targets(&target, label) => Ok(false),
+            Err(other) => Err(other),
+        }
+    }
+
+    fn exec_switch(&mut self, stmt: &SwitchStmt, label: Option<&str>) -> Exec<()> {
+        let value = self.eval(&stmt.expr)?;
+        let Some(key) = value.as_i64() else {
+            return runtime_error(format!("Cannot switch on {}", self.describe(&value)));
+        };
+        // 从匹配的分支开始顺序执行（贯穿），直到 break；没有匹配时执行 default
+        let start = stmt.cases.iter().position(|case| case.value == key);
+        let mut bodies: Vec<&[Stmt]> = Vec::new();
+        match start {
+            Some(start) => {
+                bodies.extend(stmt.cases[start..].iter().map(|case| case.body.as_slice()));
+                bodies.extend(stmt.default.as_deref());
+            }
+            None => bodies.extend(stmt.default.as_deref()),
+        }
+        let result = self.scoped(|this| {
+            bodies.iter().flat_map(|body| body.iter()).try_for_each(|stmt| this.exec(stmt))
+        });
+        match result {
+            Err(Unwind::Break(target)) if targets(&target, label) => Ok(()),
+            other => other,
+        }
+    }
+
+    fn exec_var_decl(&mut self, decl: &VarDecl) -> Exec<()> {
+        let declared = match &decl.var_type {
+            Type::Auto => self.inferred.get(&decl.id).cloned(),
+            ty => Some(ty.clone()),
+        };
+        let value = match (&decl.initializer, &declared) {
+            (Some(init), Some(ty)) => self.eval_initializer(init, ty)?,
+            (Some(init), None) => self.eval(init)?,
+            (None, Some(ty)) => Value::default_for(ty),
+            (None, None) => Value::Null,
+        };
+        let ty = declared.or_else(|| value.runtime_type()).unwrap_or(Type::Auto);
+        let value = value.convert_to(&ty);
+        if let Some(scope) = self.frame().scopes.last_mut() {
+            scope.insert(decl.name.clone(), (ty, value));
+        }
+        Ok(())
+    }
+
+    fn eval_condition(&mut self, expr: &Expr) -> Exec<bool> {
+        match self.eval(expr)? {
+            Value::Bool(b) => Ok(b),
+            other => runtime_error(format!("Condition must be bool, got {}", self.describe(&other))),
+        }
+    }
+
+    /// 求值带目标类型的初始值：数组初始化 `{...}` 按目标类型的元素类型创建数组
+    fn eval_initializer(&mut self, expr: &Expr, ty: &Type) -> Exec<Value> {
+        match (expr, ty) {
+            (Expr::ArrayInit(init), Type::Array(elem)) => {
+                let values = init.elements.iter()
+                    .map(|e| self.eval_initializer(e, elem))
+                    .collect::<Exec<Vec<_>>>()?;
+                self.allocate_array(elem, &[values.len()])?;
+                Ok(Value::new_array((**elem).clone(), values))
+            }
+            _ => Ok(self.eval(expr)?.convert_to(ty)),
+        }
+    }
+
+    // ---------- 表达式 ----------
+
+    pub(crate) fn eval(&mut self, expr: &Expr) -> Exec<Value> {
+        match expr {
+            Expr::Literal(literal) => Ok(match literal {
+                LiteralValue::Int32(v) => Value::Int(*v),
+                LiteralValue::Int64(v) => Value::Long(*v),
+                LiteralValue::Float32(v) => Value::Float(*v),
+                LiteralValue::Float64(v) => Value::Double(*v),
+                LiteralValue::String(s) => Value::str(s.as_str()),
+                LiteralValue::Bool(b) => Value::Bool(*b),
+                LiteralValue::Char(c) => Value::Char(*c),
+                LiteralValue::Null => Value::Null,
+            }),
+            Expr::Identifier(name) => {
+                let place = self.resolve_name(name)?;
+                self.load(&place)
+            }
+            Expr::Binary(bin) => self.eval_binary(bin),
+            Expr::Unary(unary) => self.eval_unary(unary),
+            Expr::Call(call) => self.eval_call(call),
+            Expr::MemberAccess(member) => self.eval_member(member),
+            Expr::New(new_expr) => {
+                let args = self.eval_args(&new_expr.args)?;
+                self.instantiate(&new_expr.class_name, args)
+            }
+            Expr::Assignment(assign) => self.eval_assignment(assign),
+            Expr::Cast(cast) => {
+                let value = self.eval(&cast.expr)?;
+                // 转换为 String 得到值的字符串形式，与字符串拼接一致
+                if cast.target_type == Type::String && !matches!(value, Value::Str(_) | Value::Null) {
+                    return Ok(Value::str(self.format(&value)?));
+                }
+                Ok(value.convert_to(&cast.target_type))
+            }
+            Expr::ArrayCreation(creation) => {
+                let sizes = creation.sizes.iter().map(|size| {
+                    let value = self.eval(size)?;
+                    match value.as_i64() {
+                        Some(n) if n >= 0 => Ok(n as usize),
+                        _ => runtime_error(format!("Invalid array size: {}", self.describe(&value))),
+                    }
+                }).collect::<Exec<Vec<_>>>()?;
+                let dims = creation.sizes.len();
+                let elem = (1..dims).fold(creation.element_type.clone(), |inner, _| match inner {
+                    Type::Array(inner) => *inner,
+                    other => other,
+                });
+                self.allocate_array(&elem, &sizes)?;
+                Ok(create_array(&creation.element_type, &sizes))
+            }
+            Expr::ArrayAccess(access) => {
+                let place = self.element_place(access)?;
+                self.load(&place)
+            }
+            Expr::ArrayInit(init) => {
+                let values = self.eval_args(&init.elements)?;
+                let elem = values.iter().find_map(Value::runtime_type).unwrap_or(Type::Object("Object".to_string()));
+                self.allocate_array(&elem, &[values.len()])?;
+                Ok(Value::new_array(elem, values))
+            }
+            Expr::MethodRef(method_ref) => {
+                let (class, receiver) = match (&method_ref.class_name, &method_ref.object) {
+                    (_, Some(object)) => {
+                        let receiver = self.eval(object)?;
+                        (self.class_of(&receiver).unwrap_or_default(), Some(receiver))
+                    }
+                    (Some(class), None) => (class.clone(), None),
+                    (None, None) => (self.frame_ref().class.clone().unwrap_or_default(), None),
+                };
+                Ok(Value::Function(Rc::new(Function::Method { class, name: method_ref.method_name.clone(), receiver })))
+            }
+            Expr::Lambda(lambda) => {
+                let frame = self.frame_ref();
+                let mut visible: Bindings = Bindings::new();
+                for scope in &frame.scopes {
+                    visible.extend(scope.iter().map(|(k, v)| (k.clone(), v.clone())));
+                }
+                let captured = visible.into_iter().map(|(name, (ty, value))| (name, ty, value)).collect();
+                Ok(Value::Function(Rc::new(Function::Lambda {
+                    params: lambda.params.clone(),
+                    body: lambda.body.clone(),
+                    captured,
+                    this: frame.this.clone(),
+                    class: frame.class.clone(),
+                })))
+            }
+            Expr::Ternary(ternary) => {
+                if self.eval_condition(&ternary.condition)? {
+                    self.eval(&ternary.true_branch)
+                } else {
+                    self.eval(&ternary.false_branch)
+                }
+            }
+            Expr::InstanceOf(instance_of) => {
+                let value = self.eval(&instance_of.expr)?;
+                Ok(Value::Bool(self.is_instance(&value, &instance_of.target_type)))
+            }
+            Expr::NullCoalesce(coalesce) => {
+                let value = self.eval(&coalesce.value)?;
+                if value.is_null() {
+                    self.eval(&coalesce.default)
+                } else {
+                    Ok(value)
+                }
+            }
+            Expr::InlineIr(_) => runtime_error("Inline IR (__llvm__) is not available in the interpreter"),
+        }
+    }
+
+    pub(crate) fn eval_args(&mut self, args: &[Expr]) -> Exec<Vec<Value>> {
+        args.iter().map(|arg| self.eval(arg)).collect()
+    }
+
+    // ---------- 名字和赋值 ----------
+
+    fn lookup_local(&self, name: &str) -> Option<&(Type, Value)> {
+        self.frame_ref().scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    /// 按局部变量、当前对象的字段、当前类（及父类）的静态字段的顺序解析名字
+    fn resolve_name(&self, name: &str) -> Exec<Place> {
+        if self.lookup_local(name).is_some() {
+            return Ok(Place::Local(name.to_string()));
+        }
+        let frame = self.frame_ref();
+        // super 作为值时就是当前对象，只有方法调用按父类静态分派
+        if name == "this" || name == "super" {
+            return match &frame.this {
+                Some(_) => Ok(Place::Local("this".to_string())),
+                None => runtime_error("'this' is not available in a static context"),
+            };
+        }
+        if let Some(Value::Object(obj)) = &frame.this
+            && obj.borrow().field(name).is_some()
+        {
+            return Ok(Place::Field(obj.clone(), name.to_string()));
+        }
+        if let Some(class) = &frame.class
+            && let Some(owner) = self.static_owner(class, name)
+        {
+            return Ok(Place::Static(owner, name.to_string()));
+        }
+        runtime_error(format!("Undefined variable '{}'", name))
+    }
+
+    /// 名字是否指向变量（而不是类名）
+    fn is_variable(&self, name: &str) -> bool {
+        self.resolve_name(name).is_ok()
+    }
+
+    /// 沿继承链查找声明了静态字段 `name` 的类
+    fn static_owner(&self, class: &str, name: &str) -> Option<String> {
+        self.class_chain(class).into_iter()
+            .find(|owner| self.statics.contains_key(&(owner.clone(), name.to_string())))
+    }
+
+    /// 类及其所有父类的名字，从子类到父类
+    fn class_chain(&self, class: &str) -> Vec<String> {
+        let mut chain = Vec::new();
+        let mut current = Some(class.to_string());
+        while let Some(name) = current {
+            if chain.contains(&name) {
+                break;
+            }
+            current = self.classes.get(&name).and_then(|def| def.decl.parent.clone());
+            chain.push(name);
+        }
+        chain
+    }
+
+    fn load(&self, place: &Place) -> Exec<Value> {
+        match place {
+            Place::Local(name) if name == "this" && self.lookup_local(name).is_none() => {
+                Ok(self.frame_ref().this.clone().unwrap_or(Value::Null))
+            }
+            Place::Local(name) => match self.lookup_local(name) {
+                Some((_, value)) => Ok(value.clone()),
+                None => runtime_error(format!("Undefined variable '{}'", name)),
+            },
+            Place::Field(obj, name) => Ok(obj.borrow().field(name).cloned().unwrap_or(Value::Null)),
+            Place::Static(class, name) => Ok(self.statics.get(&(class.clone(), name.clone()))
+                .map(|(_, value)| value.clone())
+                .unwrap_or(Value::Null)),
+            Place::Element(arr, index) => Ok(arr.borrow().values[*index].clone()),
+        }
+    }
+
+    /// 写入位置，值按位置的类型转换
+    fn store(&mut self, place: &Place, value: Value) -> Exec<Value> {
+        match place {
+            Place::Local(name) => {
+                let Some((ty, slot)) = self.frame().scopes.iter_mut().rev().find_map(|scope| scope.get_mut(name)) else {
+                    return runtime_error(format!("Undefined variable '{}'", name));
+                };
+                *slot = value.convert_to(ty);
+                Ok(slot.clone())
+            }
+            Place::Field(obj, name) => {
+                let class = obj.borrow().class.clone();
+                let value = match self.field_type(&class, name) {
+                    Some(ty) => value.convert_to(&ty),
+                    None => value,
+                };
+                if let Some(slot) = obj.borrow_mut().field_mut(name) {
+                    *slot = value.clone();
+                }
+                Ok(value)
+            }
+            Place::Static(class, name) => {
+                let Some((ty, slot)) = self.statics.get_mut(&(class.clone(), name.clone())) else {
+                    return runtime_error(format!("Undefined static field '{}.{}'", class, name));
+                };
+                *slot = value.convert_to(ty);
+                Ok(slot.clone())
+            }
+            Place::Element(arr, index) => {
+                let mut arr = arr.borrow_mut();
+                let value = value.convert_to(&arr.elem);
+                arr.values[*index] = value.clone();
+                Ok(value)
+            }
+        }
+    }
+
+    /// 实例字段的声明类型
+    fn field_type(&self, class: &str, name: &str) -> Option<Type> {
+        self.class_chain(class).iter().find_map(|owner| {
+            self.classes.get(owner)?.decl.members.iter().find_map(|member| match member {
+                ClassMember::Field(field) if field.name == name => Some(field.field_type.clone()),
+                _ => None,
+            })
+        })
+    }
+
+    fn place(&mut self, expr: &Expr) -> Exec<Place> {
+        match expr {
+            Expr::Identifier(name) => self.resolve_name(name),
+            Expr::ArrayAccess(access) => self.element_place(access),
+            Expr::MemberAccess(member) => {
+                if let Expr::Identifier(class) = member.object.as_ref()
+                    && !self.is_variable(class)
+                    && let Some(owner) = self.static_owner(class, &member.member)
+                {
+                    return Ok(Place::Static(owner, member.member.clone()));
+                }
+                match self.eval(&member.object)? {
+                    Value::Object(obj) => {
+                        if obj.borrow().field(&member.member).is_some() {
+                            return Ok(Place::Field(obj, member.member.clone()));
+                        }
+                        let class = obj.borrow().class.clone();
+                        match self.static_owner(&class, &member.member) {
+                            Some(owner) => Ok(Place::Static(owner, member.member.clone())),
+                            None => runtime_error(format!("No field '{}' in class {}", member.member, class)),
+                        }
+                    }
+                    Value::Null => runtime_error(format!("Null pointer access: cannot write field '{}'", member.member)),
+                    other => runtime_error(format!("Cannot assign to '{}' of {}", member.member, self.describe(&other))),
+                }
+            }
+            _ => runtime_error(format!("Cannot assign to '{}'", expr)),
+        }
+    }
+
+    fn element_place(&mut self, access: &ArrayAccessExpr) -> Exec<Place> {
+        let array = self.eval(&access.array)?;
+        let index = self.eval(&access.index)?;
+        let Value::Array(arr) = array else {
+            return match array {
+                Value::Null => runtime_error("Null pointer access: cannot index a null array"),
+                other => runtime_error(format!("Cannot index {}", self.describe(&other))),
+            };
+        };
+        let len = arr.borrow().values.len();
+        match index.as_i64() {
+            Some(i) if i >= 0 && (i as usize) < len => Ok(Place::Element(arr, i as usize)),
+            Some(i) => runtime_error(format!("Array index out of bounds: index {}, length {}", i, len)),
+            None => runtime_error(format!("Invalid array index: {}", self.describe(&index))),
+        }
+    }
+
+    fn eval_assignment(&mut self, assign: &AssignmentExpr) -> Exec<Value> {
+        let place = self.place(&assign.target)?;
+        let value = match assign.op {
+            AssignOp::Assign => {
+                let ty = self.place_type(&place);
+                match ty {
+                    Some(ty) => self.eval_initializer(&assign.value, &ty)?,
+                    None => self.eval(&assign.value)?,
+                }
+            }
+            op => {
+                let current = self.load(&place)?;
+                let rhs = self.eval(&assign.value)?;
+                let bin_op = match op {
+                    AssignOp::AddAssign => BinaryOp::Add,
+                    AssignOp::SubAssign => BinaryOp::Sub,
+                    AssignOp::MulAssign => BinaryOp::Mul,
+                    AssignOp::DivAssign => BinaryOp::Div,
+                    _ => BinaryOp::Mod,
+                };
+                self.binary(bin_op, current, rhs)?
+            }
+        };
+        self.store(&place, value)
+    }
+
+    fn place_type(&self, place: &Place) -> Option<Type> {
+        match place {
+            Place::Local(name) => self.lookup_local(name).map(|(ty, _)| ty.clone()),
+            Place::Field(obj, name) => self.field_type(&obj.borrow().class, name),
+            Place::Static(class, name) => self.statics.get(&(class.clone(), name.clone())).map(|(ty, _)| ty.clone()),
+            Place::Element(arr, _) => Some(arr.borrow().elem.clone()),
+        }
+    }
+
+    // ---------- 运算符 ----------
+
+    fn eval_binary(&mut self, bin: &BinaryExpr) -> Exec<Value> {
+        // 短路求值
+        if matches!(bin.op, BinaryOp::And | BinaryOp::Or) {
+            let left = self.eval_condition(&bin.left)?;
+            if left == (bin.op == BinaryOp::Or) {
+                return Ok(Value::Bool(left));
+            }
+            return Ok(Value::Bool(self.eval_condition(&bin.right)?));
+        }
+        let left = self.eval(&bin.left)?;
+        let right = self.eval(&bin.right)?;
+        self.binary(bin.op, left, right)
+    }
+
+    fn binary(&mut self, op: BinaryOp, left: Value, right: Value) -> Exec<Value> {
+        // 字符串拼接
+        if op == BinaryOp::Add && (matches!(left, Value::Str(_)) || matches!(right, Value::Str(_))) {
+            let text = format!("{}{}", self.format(&left)?, self.format(&right)?);
+            self.allocate(text.len() as u64 + 1)?;
+            return Ok(Value::str(text));
+        }
+        if matches!(op, BinaryOp::Eq | BinaryOp::Ne) && (numeric_rank(&left).is_none() || numeric_rank(&right).is_none()) {
+            let equal = values_equal(&left, &right);
+            return Ok(Value::Bool(equal == (op == BinaryOp::Eq)));
+        }
+        if let (Value::Bool(a), Value::Bool(b)) = (&left, &right) {
+            return match op {
+                BinaryOp::BitAnd => Ok(Value::Bool(a & b)),
+                BinaryOp::BitOr => Ok(Value::Bool(a | b)),
+                BinaryOp::BitXor => Ok(Value::Bool(a ^ b)),
+                _ => runtime_error(format!("Operator '{}' cannot be applied to bool", op.symbol())),
+            };
+        }
+
+        let (Some(lr), Some(rr)) = (numeric_rank(&left), numeric_rank(&right)) else {
+            return runtime_error(format!("Operator '{}' cannot be applied to {} and {}",
+                op.symbol(), self.describe(&left), self.describe(&right)));
+        };
+        // 移位的结果类型只取决于左操作数
+        if matches!(op, BinaryOp::Shl | BinaryOp::Shr | BinaryOp::UnsignedShr) {
+            let amount = right.as_i64().unwrap_or_default() as u32;
+            return match left {
+                Value::Long(v) => Ok(Value::Long(match op {
+                    BinaryOp::Shl => v.wrapping_shl(amount),
+                    BinaryOp::Shr => v.wrapping_shr(amount),
+                    _ => (v as u64).wrapping_shr(amount) as i64,
+                })),
+                _ if lr == 0 => {
+                    let v = left.as_i64().unwrap_or_default() as i32;
+                    Ok(Value::Int(match op {
+                        BinaryOp::Shl => v.wrapping_shl(amount),
+                        BinaryOp::Shr => v.wrapping_shr(amount),
+                        _ => (v as u32).wrapping_shr(amount) as i32,
+                    }))
+                }
+                _ => runtime_error(format!("Operator '{}' cannot be applied to floating-point values", op.symbol())),
+            };
+        }
+
+        match lr.max(rr) {
+            0 => int_op(op, left.as_i64().unwrap_or_default() as i32, right.as_i64().unwrap_or_default() as i32),
+            1 => long_op(op, left.as_i64().unwrap_or_default(), right.as_i64().unwrap_or_default()),
+            rank => {
+                let (a, b) = (left.as_f64().unwrap_or_default(), right.as_f64().unwrap_or_default());
+                let result = match op {
+                    BinaryOp::Add => a + b,
+                    BinaryOp::Sub => a - b,
+                    BinaryOp::Mul => a * b,
+                    BinaryOp::Div => a / b,
+                    BinaryOp::Mod => a % b,
+                    _ => return compare(op, a.partial_cmp(&b))
+                        .map(Value::Bool)
+                        .map_or_else(|| runtime_error(format!("Operator '{}' cannot be applied to floating-point values", op.symbol())), Ok),
+                };
+                // float 运算按单精度舍入
+                Ok(if rank == 2 { Value::Float(result as f32) } else { Value::Double(result) })
+            }
+        }
+    }
+
+    fn eval_unary(&mut self, unary: &UnaryExpr) -> Exec<Value> {
+        match unary.op {
+            UnaryOp::PreInc | UnaryOp::PreDec | UnaryOp::PostInc | UnaryOp::PostDec => {
+                let place = self.place(&unary.operand)?;
+                let old = self.load(&place)?;
+                let delta = if matches!(unary.op, UnaryOp::PreInc | UnaryOp::PostInc) { BinaryOp::Add } else { BinaryOp::Sub };
+                let new = self.binary(delta, old.clone(), Value::Int(1))?;
+                let new = self.store(&place, new)?;
+                Ok(if matches!(unary.op, UnaryOp::PreInc | UnaryOp::PreDec) { new } else { old })
+            }
+            op => {
+                let value = self.eval(&unary.operand)?;
+                match (op, value) {
+                    (UnaryOp::Not, Value::Bool(b)) => Ok(Value::Bool(!b)),
+                    (UnaryOp::Neg, Value::Int(v)) => Ok(Value::Int(v.wrapping_neg())),
+                    (UnaryOp::Neg, Value::Char(c)) => Ok(Value::Int(-(c as i32))),
+                    (UnaryOp::Neg, Value::Long(v)) => Ok(Value::Long(v.wrapping_neg())),
+                    (UnaryOp::Neg, Value::Float(v)) => Ok(Value::Float(-v)),
+                    (UnaryOp::Neg, Value::Double(v)) => Ok(Value::Double(-v)),
+                    (UnaryOp::BitNot, Value::Int(v)) => Ok(Value::Int(!v)),
+                    (UnaryOp::BitNot, Value::Char(c)) => Ok(Value::Int(!(c as i32))),
+                    (UnaryOp::BitNot, Value::Long(v)) => Ok(Value::Long(!v)),
+                    (op, value) => runtime_error(format!("Unary operator {:?} cannot be applied to {}", op, self.describe(&value))),
+                }
+            }
+        }
+    }
+
+    fn is_instance(&self, value: &Value, target: &Type) -> bool {
+        match (value, target) {
+            (Value::Null, _) => false,
+            (Value::Object(obj), Type::Object(name)) => {
+                let class = obj.borrow().class.clone();
+                name == "Object" || self.class_chain(&class).iter().any(|owner| {
+                    owner == name || self.classes.get(owner)
+                        .is_some_and(|def| def.decl.interfaces.iter().any(|i| i == name))
+                })
+            }
+            (Value::Str(_), Type::String) => true,
+            (Value::Str(_) | Value::Array(_), Type::Object(name)) => name == "Object",
+            (Value::Array(arr), Type::Array(elem)) => &arr.borrow().elem == elem.as_ref(),
+            _ => false,
+        }
+    }
+
+    // ---------- 成员访问和调用 ----------
+
+    fn eval_member(&mut self, member: &MemberAccessExpr) -> Exec<Value> {
+        if let Expr::Identifier(name) = member.object.as_ref()
+            && !self.is_variable(name)
+        {
+            if let Some(owner) = self.static_owner(name, &member.member) {
+                return self.load(&Place::Static(owner, member.member.clone()));
+            }
+            if let Some(value) = self.builtin_constant(name, &member.member) {
+                return Ok(value);
+            }
+        }
+        let object = self.eval(&member.object)?;
+        match &object {
+            Value::Null if member.null_safe => Ok(Value::Null),
+            Value::Null => runtime_error(format!("Null pointer access: cannot read field '{}'", member.member)),
+            Value::Array(arr) if member.member == "length" => Ok(Value::Int(arr.borrow().values.len() as i32)),
+            Value::Str(s) if member.member == "length" => Ok(Value::Int(s.chars().count() as i32)),
+            Value::Object(obj) => {
+                if let Some(value) = obj.borrow().field(&member.member) {
+                    return Ok(value.clone());
+                }
+                let class = obj.borrow().class.clone();
+                match self.static_owner(&class, &member.member) {
+                    Some(owner) => self.load(&Place::Static(owner, member.member.clone())),
+                    None => runtime_error(format!("No field '{}' in class {}", member.member, class)),
+                }
+            }
+            other => runtime_error(format!("Cannot read '{}' of {}", member.member, self.describe(other))),
+        }
+    }
+
+    fn eval_call(&mut self, call: &CallExpr) -> Exec<Value> {
+        match call.callee.as_ref() {
+            Expr::Identifier(name) => {
+                // 函数类型的变量优先于同名方法
+                if let Ok(place) = self.resolve_name(name)
+                    && let Value::Function(function) = self.load(&place)?
+                {
+                    let args = self.eval_args(&call.args)?;
+                    return self.call_function(&function, args);
+                }
+                let class = self.frame_ref().class.clone().unwrap_or_default();
+                if self.has_method(&class, name) {
+                    let args = self.eval_args(&call.args)?;
+                    // 实例上下文中按当前对象的实际类分派
+                    let this = self.frame_ref().this.clone();
+                    let dispatch = this.as_ref().and_then(|this| self.class_of(this)).unwrap_or(class);
+                    return self.invoke(&dispatch, name, this, args);
+                }
+                if name == "debugPrint" {
+                    return self.debug_print(call);
+                }
+                if self.format_calls.contains_key(&call.id) {
+                    return self.formatted_call(name, call);
+                }
+                let args = self.eval_args(&call.args)?;
+                match self.call_builtin(name, &args)? {
+                    Some(value) => Ok(value),
+                    None => runtime_error(format!("Undefined function '{}'", name)),
+                }
+            }
+            Expr::MemberAccess(member) => {
+                // super.method(...) 从父类开始查找实现，不按对象的实际类分派
+                if let Expr::Identifier(name) = member.object.as_ref()
+                    && name == "super"
+                {
+                    let class = self.frame_ref().class.clone().unwrap_or_default();
+                    let Some(parent) = self.classes.get(&class).and_then(|def| def.decl.parent.clone()) else {
+                        return runtime_error(format!("Class '{}' has no superclass", class));
+                    };
+                    let this = self.frame_ref().this.clone();
+                    let args = self.eval_args(&call.args)?;
+                    return self.invoke(&parent, &member.member, this, args);
+                }
+                // 静态方法调用 Class.method(...)
+                if let Expr::Identifier(class) = member.object.as_ref()
+                    && !self.is_variable(class)
+                {
+                    let args = self.eval_args(&call.args)?;
+                    if self.classes.contains_key(class) {
+                        return self.invoke(class, &member.member, None, args);
+                    }
+                    return match self.call_builtin_static(class, &member.member, &args)? {
+                        Some(value) => Ok(value),
+                        None => runtime_error(format!("Undefined method '{}.{}'", class, member.member)),
+                    };
+                }
+                let receiver = self.eval(&member.object)?;
+                if receiver.is_null() && member.null_safe {
+                    return Ok(Value::Null);
+                }
+                let args = self.eval_args(&call.args)?;
+                self.call_on_value(receiver, &member.member, args)
+            }
+            callee => match self.eval(callee)? {
+                Value::Function(function) => {
+                    let args = self.eval_args(&call.args)?;
+                    self.call_function(&function, args)
+                }
+                other => runtime_error(format!("{} is not callable", self.describe(&other))),
+            },
+        }
+    }
+
+    /// 调用值的方法：对象按实际类分派，字符串调用内置方法
+    fn call_on_value(&mut self, receiver: Value, name: &str, args: Vec<Value>) -> Exec<Value> {
+        match &receiver {
+            Value::Null => runtime_error(format!("Null pointer access: cannot call method '{}'", name)),
+            Value::Str(s) => self.string_method(s, name, &args),
+            Value::Object(obj) => {
+                let class = obj.borrow().class.clone();
+                self.invoke(&class, name, Some(receiver.clone()), args)
+            }
+            other => runtime_error(format!("Cannot call method '{}' on {}", name, self.describe(other))),
+        }
+    }
+
+    fn has_method(&self, class: &str, name: &str) -> bool {
+        self.class_chain(class).iter()
+            .any(|owner| self.classes.get(owner).is_some_and(|def| def.methods.iter().any(|m| m.name == name)))
+    }
+
+    /// 从 `class` 开始沿继承链查找并调用方法；静态方法忽略 `this`
+    fn invoke(&mut self, class: &str, name: &str, this: Option<Value>, args: Vec<Value>) -> Exec<Value> {
+        let mut found = None;
+        for owner in self.class_chain(class) {
+            let Some(def) = self.classes.get(&owner) else {
+                continue;
+            };
+            let candidates: Vec<&Rc<MethodDecl>> = def.methods.iter().filter(|m| m.name == name).collect();
+            let params: Vec<&[ParameterInfo]> = candidates.iter().map(|m| m.params.as_slice()).collect();
+            if let Some((index, bound)) = select_overload(&params, &args) {
+                found = Some((candidates[index].clone(), owner, bound));
+                break;
+            }
+        }
+        let Some((method, owner, args)) = found else {
+            let types: Vec<String> = args.iter().map(|a| self.describe(a)).collect();
+            return runtime_error(format!("No method '{}' in class {} accepts ({})", name, class, types.join(", ")));
+        };
+        let is_static = method.modifiers.contains(&Modifier::Static);
+        if !is_static && this.is_none() {
+            return runtime_error(format!("Cannot call instance method '{}' without an object", name));
+        }
+        if method.modifiers.contains(&Modifier::Native)
+            && let Some(this) = &this
+            && let Some(native) = self.classes.get(&owner).and_then(|def| crate::prelude::native_class(&def.decl))
+        {
+            return self.native_method(this, &native, name, args);
+        }
+        if method.modifiers.contains(&Modifier::Native) {
+            return runtime_error(format!("Native method '{}.{}' calls a C function, which is not available in the interpreter", owner, name));
+        }
+        let Some(body) = &method.body else {
+            return runtime_error(format!("Method '{}.{}' has no body", owner, name));
+        };
+
+        let scope = method.params.iter().zip(args)
+            .map(|(param, value)| (param.name.clone(), (param.param_type.clone(), value)))
+            .collect();
+        let frame = Frame { scopes: vec![scope], this: if is_static { None } else { this }, class: Some(owner) };
+        let result = self.call_frame(frame, |interp| interp.exec_body(body))?;
+        Ok(result.convert_to(&method.return_type))
+    }
+
+    /// 在新的调用帧中执行，检查调用深度和执行步数
+    fn call_frame(&mut self, frame: Frame, f: impl FnOnce(&mut Self) -> Exec<Value>) -> Exec<Value> {
+        if let Some(max) = self.max_depth
+            && self.frames.len() > max
+        {
+            return runtime_error(format!("Execution limit exceeded (call depth {})", max));
+        }
+        if self.frames.len() > MAX_CALL_DEPTH {
+            return runtime_error(format!("Stack overflow: call depth exceeded {}", MAX_CALL_DEPTH));
+        }
+        self.step()?;
+        self.frames.push(frame);
+        let result = f(self);
+        self.frames.pop();
+        result
+    }
+
+    pub(crate) fn call_function(&mut self, function: &Rc<Function>, args: Vec<Value>) -> Exec<Value> {
+        match function.as_ref() {
+            Function::Lambda { params, body, captured, this, class } => {
+                if params.len() != args.len() {
+                    return runtime_error(format!("Function expects {} argument(s), got {}", params.len(), args.len()));
+                }
+                let captured_scope = captured.iter()
+                    .map(|(name, ty, value)| (name.clone(), (ty.clone(), value.clone())))
+                    .collect();
+                let param_scope = params.iter().zip(args).map(|(param, value)| {
+                    let ty = param.param_type.clone().or_else(|| value.runtime_type()).unwrap_or(Type::Auto);
+                    (param.name.clone(), (ty.clone(), value.convert_to(&ty)))
+                }).collect();
+                let frame = Frame { scopes: vec![captured_scope, param_scope], this: this.clone(), class: class.clone() };
+                self.call_frame(frame, |interp| match body {
+                    LambdaBody::Expr(expr) => interp.eval(expr),
+                    LambdaBody::Block(block) => interp.exec_body(block),
+                })
+            }
+            Function::Method { class, name, receiver } => match receiver {
+                Some(receiver) => self.call_on_value(receiver.clone(), name, args),
+                None => self.invoke(class, name, None, args),
+            },
+        }
+    }
+
+    /// 对象的实际类名
+    fn class_of(&self, value: &Value) -> Option<String> {
+        match value {
+            Value::Object(obj) => Some(obj.borrow().class.clone()),
+            _ => None,
+        }
+    }
+
+    // ---------- 对象创建 ----------
+
+    fn instantiate(&mut self, class: &str, args: Vec<Value>) -> Exec<Value> {
+        if !self.classes.contains_key(class) {
+            return runtime_error(format!("Unknown class '{}'", class));
+        }
+        // 父类字段在前，先全部设为默认值，再由构造过程按声明顺序初始化
+        let mut fields = Vec::new();
+        for owner in self.class_chain(class).iter().rev() {
+            let Some(def) = self.classes.get(owner) else {
+                continue;
+            };
+            for member in &def.decl.members {
+                if let ClassMember::Field(field) = member
+                    && !field.modifiers.contains(&Modifier::Static)
+                {
+                    fields.push((field.name.clone(), Value::default_for(&field.field_type)));
+                }
+            }
+        }
+        let field_bytes: u64 = fields.iter().map(|(_, value)| value.runtime_type().map_or(8, |ty| value_bytes(&ty))).sum();
+        self.allocate(OBJECT_HEADER_BYTES + field_bytes)?;
+        let object = Value::Object(Rc::new(RefCell::new(Object { class: class.to_string(), fields, native: NativeData::None })));
+        self.construct(class, &object, args)?;
+        Ok(object)
+    }
+
+    /// 执行 `class` 的构造过程：this(...) 委托或父类构造、字段初始化和初始化块、构造函数体
+    fn construct(&mut self, class: &str, this: &Value, args: Vec<Value>) -> Exec<()> {
+        let Some(def) = self.classes.get(class) else {
+            return runtime_error(format!("Unknown class '{}'", class));
+        };
+        let decl = def.decl.clone();
+        let constructors = def.constructors.clone();
+        let params: Vec<&[ParameterInfo]> = constructors.iter().map(|c| c.params.as_slice()).collect();
+        let (ctor, args) = match select_overload(&params, &args) {
+            Some((index, bound)) => (Some(constructors[index].clone()), bound),
+            None if constructors.is_empty() && args.is_empty() => (None, args),
+            None => {
+                let types: Vec<String> = args.iter().map(|a| self.describe(a)).collect();
+                return runtime_error(format!("No constructor of {} accepts ({})", class, types.join(", ")));
+            }
+        };
+
+        let scope = match &ctor {
+            Some(ctor) => ctor.params.iter().zip(args)
+                .map(|(param, value)| (param.name.clone(), (param.param_type.clone(), value)))
+                .collect(),
+            None => Bindings::new(),
+        };
+        let frame = Frame { scopes: vec![scope], this: Some(this.clone()), class: Some(class.to_string()) };
+        self.call_frame(frame, |interp| {
+            let explicit = ctor.as_ref().and_then(|c| c.constructor_call.as_ref());
+            if let Some(ConstructorCall::This(args)) = explicit {
+                let args = interp.eval_args(args)?;
+                interp.construct(&decl.name, this, args)?;
+            } else {
+                if let Some(parent) = &decl.parent {
+                    let args = match explicit {
+                        Some(ConstructorCall::Super(args)) => interp.eval_args(args)?,
+                        _ => Vec::new(),
+                    };
+                    interp.construct(parent, this, args)?;
+                }
+                interp.init_instance_members(&decl, this)?;
+            }
+            match &ctor {
+                Some(ctor) => interp.exec_body(&ctor.body),
+                None => Ok(Value::Void),
+            }
+        })?;
+        Ok(())
+    }
+
+    /// 按声明顺序执行实例字段的初始值和实例初始化块
+    fn init_instance_members(&mut self, decl: &ClassDecl, this: &Value) -> Exec<()> {
+        let Value::Object(obj) = this else {
+            return Ok(());
+        };
+        for member in &decl.members {
+            match member {
+                ClassMember::Field(field) if !field.modifiers.contains(&Modifier::Static) => {
+                    if let Some(init) = &field.initializer {
+                        let value = self.eval_initializer(init, &field.field_type)?;
+                        if let Some(slot) = obj.borrow_mut().field_mut(&field.name) {
+                            *slot = value;
+                        }
+                    }
+                }
+                ClassMember::InstanceInitializer(block) => {
+                    self.exec_body(block)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // ---------- 字符串转换 ----------
+
+    /// 值的字符串形式，对象优先使用 `toString()`
+    pub(crate) fn format(&mut self, value: &Value) -> Exec<String> {
+        let precision = self.precision;
+        let mut failure = None;
+        let mut to_string = |value: &Value| match self.call_to_string(value) {
+            Ok(text) => text,
+            Err(error) => {
+                failure.get_or_insert(error);
+                Some(String::new())
+            }
+        };
+        let text = Formatter { precision, to_string: &mut to_string }.format(value);
+        match failure {
+            Some(error) => Err(error),
+            None => Ok(text),
+        }
+    }
+
+    /// 调用对象的 `toString()`；类（及父类）没有定义时返回 `None`
+    fn call_to_string(&mut self, value: &Value) -> Exec<Option<String>> {
+        let Some(class) = self.class_of(value) else {
+            return Ok(None);
+        };
+        let defined = self.class_chain(&class).iter().any(|owner| {
+            self.classes.get(owner).is_some_and(|def| def.methods.iter().any(|m| m.name == "toString" && m.params.is_empty()))
+        });
+        if !defined {
+            return Ok(None);
+        }
+        match self.invoke(&class, "toString", Some(value.clone()), Vec::new())? {
+            Value::Str(s) => Ok(Some(s.to_string())),
+            other => Ok(Some(self.format(&other)?)),
+        }
+    }
+
+    /// 错误消息中值的描述：类型名和值
+    pub(crate) fn describe(&self, value: &Value) -> String {
+        match value.runtime_type() {
+            Some(ty) => ty.source_name(),
+            None if value.is_null() => "null".to_string(),
+            None => "function".to_string(),
+        }
+    }
+
+    pub(crate) fn write_output(&mut self, text: &str) -> Exec<()> {
+        self.out.write_all(text.as_bytes())
+            .and_then(|_| self.out.flush())
+            .or_else(|e| runtime_error(format!("Failed to write output: {}", e)))
+    }
+}
+
+/// 按元素类型和各维长度创建数组，多维数组逐层创建
+/// 跳转是否以该语句为目标：不带标签的跳转以最内层的循环或 switch 为目标
+fn targets(jump: &Option<String>, label: Option<&str>) -> bool {
+    jump.is_none() || jump.as_deref() == label
+}
+
+fn create_array(elem: &Type, sizes: &[usize]) -> Value {
+    let Some((&len, rest)) = sizes.split_first() else {
+        return Value::Null;
+    };
+    let values = match (elem, rest.is_empty()) {
+        (Type::Array(inner), false) => (0..len).map(|_| create_array(inner, rest)).collect(),
+        _ => vec![Value::default_for(elem); len],
+    };
+    Value::new_array(elem.clone(), values)
+}
+
+/// 值在编译后程序中占用的字节数（作为数组元素或字段），用于估算堆分配
+fn value_bytes(ty: &Type) -> u64 {
+    match ty {
+        Type::Int8 | Type::Bool => 1,
+        Type::Int16 => 2,
+        Type::Int32 | Type::Float32 | Type::Char => 4,
+        _ => 8,
+    }
+}
+
+/// 数值提升的等级：int/char、long、float、double；非数值返回 `None`
+fn numeric_rank(value: &Value) -> Option<u8> {
+    match value {
+        Value::Int(_) | Value::Char(_) => Some(0),
+        Value::Long(_) => Some(1),
+        Value::Float(_) => Some(2),
+        Value::Double(_) => Some(3),
+        _ => None,
+    }
+}
+
+/// 非数值的相等比较：字符串比较内容，数组、对象和函数比较引用
+fn values_equal(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Null, Value::Null) => true,
+        (Value::Bool(a), Value::Bool(b)) => a == b,
+        (Value::Str(a), Value::Str(b)) => a == b,
+        (Value::Array(a), Value::Array(b)) => Rc::ptr_eq(a, b),
+        (Value::Object(a), Value::Object(b)) => Rc::ptr_eq(a, b),
+        (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+        _ => false,
+    }
+}
+
+fn compare(op: BinaryOp, ordering: Option<std::cmp::Ordering>) -> Option<bool> {
+    use std::cmp::Ordering::*;
+    // NaN 参与的比较除 != 外都为 false
+    let Some(ordering) = ordering else {
+        return op.is_comparison().then_some(op == BinaryOp::Ne);
+    };
+    Some(match op {
+        BinaryOp::Eq => ordering == Equal,
+        BinaryOp::Ne => ordering != Equal,
+        BinaryOp::Lt => ordering == Less,
+        BinaryOp::Le => ordering != Greater,
+        BinaryOp::Gt => ordering == Greater,
+        BinaryOp::Ge => ordering != Less,
+        _ => return None,
+    })
+}
+
+fn int_op(op: BinaryOp, a: i32, b: i32) -> Exec<Value> {
+    Ok(Value::Int(match op {
+        BinaryOp::Add => a.wrapping_add(b),
+        BinaryOp::Sub => a.wrapping_sub(b),
+        BinaryOp::Mul => a.wrapping_mul(b),
+        BinaryOp::Div | BinaryOp::Mod if b == 0 => return runtime_error("Division by zero"),
+        BinaryOp::Div => a.wrapping_div(b),
+        BinaryOp::Mod => a.wrapping_rem(b),
+        BinaryOp::BitAnd => a & b,
+        BinaryOp::BitOr => a | b,
+        BinaryOp::BitXor => a ^ b,
+        _ => return compare(op, Some(a.cmp(&b))).map(Value::Bool)
+            .map_or_else(|| runtime_error(format!("Operator '{}' cannot be applied to int", op.symbol())), Ok),
+    }))
+}
+
+fn long_op(op: BinaryOp, a: i64, b: i64) -> Exec<Value> {
+    Ok(Value::Long(match op {
+        BinaryOp::Add => a.wrapping_add(b),
+        BinaryOp::Sub => a.wrapping_sub(b),
+        BinaryOp::Mul => a.wrapping_mul(b),
+        BinaryOp::Div | BinaryOp::Mod if b == 0 => return runtime_error("Division by zero"),
+        BinaryOp::Div => a.wrapping_div(b),
+        BinaryOp::Mod => a.wrapping_rem(b),
+        BinaryOp::BitAnd => a & b,
+        BinaryOp::BitOr => a | b,
+        BinaryOp::BitXor => a ^ b,
+        _ => return compare(op, Some(a.cmp(&b))).map(Value::Bool)
+            .map_or_else(|| runtime_error(format!("Operator '{}' cannot be applied to long", op.symbol())), Ok),
+    }))
+}
+
+/// 按实参的运行时类型在重载中选择最匹配的一个，返回下标和整理后的实参（可变参数打包为数组）
+fn select_overload(candidates: &[&[ParameterInfo]], args: &[Value]) -> Option<(usize, Vec<Value>)> {
+    let mut best: Option<(u32, usize, Vec<Value>)> = None;
+    for (index, params) in candidates.iter().enumerate() {
+        if let Some((score, bound)) = bind_args(params, args)
+            && best.as_ref().is_none_or(|(best_score, _, _)| score < *best_score)
+        {
+            best = Some((score, index, bound));
+        }
+    }
+    best.map(|(_, index, bound)| (index, bound))
+}
+
+/// 实参能否传给这组形参：返回匹配代价（越小越好）和转换后的实参
+fn bind_args(params: &[ParameterInfo], args: &[Value]) -> Option<(u32, Vec<Value>)> {
+    let varargs = params.last().is_some_and(|p| p.is_varargs);
+    // 直接传入数组也可以匹配可变参数
+    let direct = (args.len() == params.len())
+        .then(|| params.iter().zip(args).try_fold(0, |score, (p, a)| Some(score + fit_score(&p.param_type, a)?)))
+        .flatten();
+    if let Some(score) = direct {
+        let bound = params.iter().zip(args).map(|(p, a)| a.clone().convert_to(&p.param_type)).collect();
+        return Some((score, bound));
+    }
+    if !varargs || args.len() + 1 < params.len() {
+        return None;
+    }
+    let fixed = params.len() - 1;
+    let elem = match &params[fixed].param_type {
+        Type::Array(elem) => (**elem).clone(),
+        other => other.clone(),
+    };
+    let mut score = 1;
+    let mut bound = Vec::with_capacity(params.len());
+    for (param, arg) in params[..fixed].iter().zip(args) {
+        score += fit_score(&param.param_type, arg)?;
+        bound.push(arg.clone().convert_to(&param.param_type));
+    }
+    let mut rest = Vec::new();
+    for arg in &args[fixed..] {
+        score += fit_score(&elem, arg)?;
+        rest.push(arg.clone().convert_to(&elem));
+    }
+    bound.push(Value::new_array(elem, rest));
+    Some((score, bound))
+}
+
+/// 实参传给形参类型的匹配代价：类型相同为 0，隐式拓宽为 1，对象向上转型为 2
+fn fit_score(param: &Type, arg: &Value) -> Option<u32> {
+    let Some(arg_type) = arg.runtime_type() else {
+        return match arg {
+            Value::Function(_) if matches!(param, Type::Function(_) | Type::Auto) => Some(0),
+            Value::Null if param.is_reference_type() || matches!(param, Type::Generic(..)) => Some(1),
+            _ => None,
+        };
+    };
+    if &arg_type == param || *param == Type::Auto {
+        return Some(0);
+    }
+    match (param, &arg_type) {
+        (Type::Int8 | Type::Int16, Type::Int32)
+        | (Type::Int32, Type::Char)
+        | (Type::Int64, Type::Int32 | Type::Char)
+        | (Type::Float32, Type::Int32 | Type::Int64 | Type::Char)
+        | (Type::Float64, Type::Int32 | Type::Int64 | Type::Float32 | Type::Char) => Some(1),
+        (Type::Object(_) | Type::Generic(..), _) if !arg_type.is_primitive() => Some(2),
+        (Type::Array(_), Type::Array(_)) => Some(2),
+        _ => None,
+    }
+}
@@ -0,0 +1,417 @@
+//! 交互式解释器（cay-repl）
+//!
+//! 逐条执行 Cavvy 代码，适合试验和教学：
+//! - 语句和表达式：与之前定义的会话变量一起检查后执行，表达式语句输出它的值
+//! - 类和接口声明：加入会话，之后的输入都可以使用，再次声明同名的类会替换它
+//! - 方法声明（如 `int square(int x) { ... }`）：作为会话函数，可以直接调用，同名函数会被替换
+//!
+//! 每次输入都与会话中已有的声明合成一个完整的程序，经过解析、单态化和语义分析，
+//! 报告的错误与编译器相同（位置相对于本次输入）；通过检查后由树遍历解释器在持久的环境中
+//! 执行，不生成代码，也不需要 LLVM 工具链。
+
+pub mod value;
+mod eval;
+mod builtins;
+
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use crate::ast::{AssignmentExpr, Expr, Program, Stmt, UnaryOp};
+use crate::diagnostics::Diagnostic;
+use crate::error::{cayError, cayResult, SourceLocation};
+use crate::lexer::{self, Token};
+use crate::types::Type;
+use crate::visit::{self, VisitMut};
+use crate::{generics, modules, parser, semantic, CompilerOptions};
+use eval::Unwind;
+use value::Bindings;
+
+pub use eval::Interpreter;
+
+/// 会话类：会话函数是它的静态方法，输入的语句放在它的 main 方法中（用户不能再声明同名的类）
+const SESSION_CLASS: &str = "ReplSession";
+/// 错误消息中输入的文件名
+const INPUT_FILE: &str = "<repl>";
+
+/// 一次输入的执行结果
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    /// 空输入
+    Empty,
+    /// 定义了类、接口或会话函数
+    Defined(Vec<String>),
+    /// 执行了语句；输入是有值的表达式时给出值的打印形式
+    Executed(Option<String>),
+}
+
+/// 输入执行失败的原因
+#[derive(Debug)]
+pub enum EvalError {
+    /// 解析或语义错误，位置相对于本次输入
+    Compile(Vec<Diagnostic>),
+    /// 运行时错误，错误发生前的输出和变量修改保留
+    Runtime(String),
+    /// 程序调用了 exit(code)
+    Exit(i32),
+}
+
+impl EvalError {
+    /// 渲染错误，编译错误带出错的输入行
+    pub fn render(&self, input: &str) -> String {
+        match self {
+            EvalError::Compile(diagnostics) => diagnostics.iter().map(|d| d.render(Some(input))).collect(),
+            EvalError::Runtime(message) => format!("运行时错误: {}\n", message),
+            EvalError::Exit(code) => format!("程序以退出码 {} 结束\n", code),
+        }
+    }
+}
+
+/// 输入的种类
+#[derive(Clone, Copy, PartialEq)]
+enum InputKind {
+    Declaration,
+    Function,
+    Statements,
+}
+
+/// 合成的会话程序
+struct SessionProgram {
+    source: String,
+    /// 输入之前的行数
+    offset: usize,
+    /// main 方法开头声明会话变量的语句数
+    prelude_len: usize,
+}
+
+/// 交互式会话：保存已定义的类、函数和变量
+pub struct Session<W: Write> {
+    interp: Interpreter<W>,
+    /// 类和接口声明：(声明的名称, 源码)，按输入顺序
+    declarations: Vec<(Vec<String>, String)>,
+    /// 会话函数：(名称, 源码)
+    functions: Vec<(String, String)>,
+    /// 会话变量的类型和值
+    globals: Bindings,
+    finals: HashSet<String>,
+    /// 用到过的泛型实例：实例类名（`List$int`）-> 源码写法（`List<int>`），用于重新声明会话变量
+    generic_names: HashMap<String, String>,
+}
+
+impl<W: Write> Session<W> {
+    /// 创建会话，程序的输出写入 `out`
+    pub fn new(out: W) -> Self {
+        Self {
+            interp: Interpreter::new(out),
+            declarations: Vec::new(),
+            functions: Vec::new(),
+            globals: Bindings::new(),
+            finals: HashSet::new(),
+            generic_names: HashMap::new(),
+        }
+    }
+
+    /// 设置每次输入的执行限制，与编译后的程序使用同样的选项：
+    /// `max_steps`（每次进入函数、每轮循环计一步）、`max_heap_bytes`（累计堆分配字节数）
+    /// 和 `stack_guard_depth`（调用深度），超出时报告 "Execution limit exceeded" 运行时错误
+    pub fn set_limits(&mut self, options: &CompilerOptions) {
+        self.interp.max_steps = options.max_steps;
+        self.interp.max_heap_bytes = options.max_heap_bytes;
+        self.interp.max_depth = options.stack_guard_depth.map(|depth| depth as usize);
+    }
+
+    pub fn output(&self) -> &W {
+        &self.interp.out
+    }
+
+    /// 会话变量，按名称排序：`类型 名称 = 值`
+    pub fn variables(&mut self) -> Vec<String> {
+        let mut names: Vec<String> = self.globals.keys().cloned().collect();
+        names.sort();
+        names.into_iter().map(|name| {
+            let (ty, value) = self.globals[&name].clone();
+            let text = self.interp.format(&value).unwrap_or_else(|_| "?".to_string());
+            let text = if matches!(value, value::Value::Str(_)) { format!("\"{}\"", text) } else { text };
+            let modifier = if self.finals.contains(&name) { "final " } else { "" };
+            format!("{}{} {} = {}", modifier, self.type_name(&ty), name, text)
+        }).collect()
+    }
+
+    /// 执行一次输入
+    pub fn eval(&mut self, input: &str) -> Result<Outcome, EvalError> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Ok(Outcome::Empty);
+        }
+        let tokens = lexer::lex(input).map_err(|e| compile_error(&e, 0))?;
+        let looks_like_declaration = matches!(tokens.first().map(|t| &t.token),
+            Some(Token::Class | Token::Interface | Token::Public | Token::Private | Token::Protected | Token::AtMain));
+
+        let primary = if looks_like_declaration {
+            parser::parse_module(tokens, 0)
+                .map(|(program, _)| program.classes.iter().map(|c| c.name.clone())
+                    .chain(program.interfaces.iter().map(|i| i.name.clone()))
+                    .collect::<Vec<_>>())
+                .map(|names| (InputKind::Declaration, input.to_string(), names))
+                .map_err(|e| compile_error(&e, 0))
+        } else {
+            let statements = if input.ends_with(';') || input.ends_with('}') {
+                input.to_string()
+            } else {
+                format!("{};", input)
+            };
+            self.declared_variables(&statements)
+                .map(|names| (InputKind::Statements, statements, names))
+        };
+        let (kind, text, names) = match primary {
+            Ok(parsed) => parsed,
+            // 不是语句或类声明时再尝试作为会话函数
+            Err(error) => match self.parse_function(input) {
+                Some(parsed) => parsed,
+                None => return Err(error),
+            },
+        };
+
+        let program = self.compose(kind, &text, &names);
+        let (program_ast, analyzer) = check(&program.source, &mut self.generic_names).map_err(|e| compile_error(&e, program.offset))?;
+        self.interp.load_program(&program_ast);
+        self.interp.inferred = analyzer.inferred_var_types().clone();
+        self.interp.debug_prints = analyzer.debug_prints().clone();
+        self.interp.format_calls = analyzer.format_calls().clone();
+        self.interp.line_offset = program.offset;
+
+        match kind {
+            InputKind::Declaration => {
+                self.declarations.retain(|(declared, _)| !declared.iter().any(|n| names.contains(n)));
+                self.declarations.push((names.clone(), text));
+                for name in &names {
+                    self.interp.reset_statics(name);
+                    self.interp.init_statics(name).map_err(runtime_error)?;
+                }
+                Ok(Outcome::Defined(names))
+            }
+            InputKind::Function => {
+                self.functions.retain(|(name, _)| !names.contains(name));
+                self.functions.push((names[0].clone(), text));
+                Ok(Outcome::Defined(names))
+            }
+            InputKind::Statements => {
+                let statements = main_statements(&program_ast)
+                    .map(|body| body[program.prelude_len.min(body.len())..].to_vec())
+                    .unwrap_or_default();
+                for stmt in &statements {
+                    if let Stmt::VarDecl(decl) = stmt {
+                        if decl.is_final {
+                            self.finals.insert(decl.name.clone());
+                        } else {
+                            self.finals.remove(&decl.name);
+                        }
+                    }
+                }
+                let value = self.interp.run(&statements, &mut self.globals, SESSION_CLASS).map_err(runtime_error)?;
+                let shown = match statements.as_slice() {
+                    [Stmt::Expr(expr)] if shows_value(expr) && !matches!(value, value::Value::Void) => {
+                        Some(self.interp.format(&value).map_err(runtime_error)?)
+                    }
+                    _ => None,
+                };
+                Ok(Outcome::Executed(shown))
+            }
+        }
+    }
+
+    /// 语句输入在顶层声明的变量名（它们替换同名的会话变量）；同时检查语法
+    fn declared_variables(&self, statements: &str) -> Result<Vec<String>, EvalError> {
+        let program = self.compose(InputKind::Statements, statements, &[]);
+        let (ast, _) = lexer::lex(&program.source)
+            .and_then(|tokens| parser::parse_module(tokens, 0))
+            .map_err(|e| compile_error(&e, program.offset))?;
+        Ok(main_statements(&ast).unwrap_or_default().iter()
+            .skip(program.prelude_len)
+            .filter_map(|stmt| match stmt {
+                Stmt::VarDecl(decl) => Some(decl.name.clone()),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// 尝试把输入作为会话函数解析，返回函数名
+    fn parse_function(&self, input: &str) -> Option<(InputKind, String, Vec<String>)> {
+        let text = if input.split_whitespace().take_while(|w| matches!(*w, "public" | "private" | "static" | "final")).any(|w| w == "static") {
+            input.to_string()
+        } else {
+            format!("static {}", input)
+        };
+        let program = self.compose(InputKind::Function, &text, &[]);
+        let tokens = lexer::lex(&program.source).ok()?;
+        let (ast, _) = parser::parse_module(tokens, 0).ok()?;
+        let session = ast.classes.iter().find(|c| c.name == SESSION_CLASS)?;
+        let name = session.members.iter().find_map(|member| match member {
+            crate::ast::ClassMember::Method(method) if method.loc.line > program.offset => Some(method.name.clone()),
+            _ => None,
+        })?;
+        Some((InputKind::Function, text, vec![name]))
+    }
+
+    /// 合成会话程序：已有的声明、会话类（会话函数和 main 方法）以及本次输入
+    ///
+    /// `replaced` 是本次输入重新定义的名称，合成时跳过会话中的同名声明。
+    fn compose(&self, kind: InputKind, input: &str, replaced: &[String]) -> SessionProgram {
+        let mut source = String::new();
+        let mut offset = 0;
+        let mut push = |source: &mut String, text: &str, is_input: bool| {
+            if is_input {
+                offset = source.matches('\n').count();
+            }
+            source.push_str(text);
+            source.push('\n');
+        };
+
+        for (names, text) in &self.declarations {
+            if kind != InputKind::Declaration || !names.iter().any(|n| replaced.contains(n)) {
+                push(&mut source, text, false);
+            }
+        }
+        if kind == InputKind::Declaration {
+            push(&mut source, input, true);
+        }
+        push(&mut source, &format!("public class {} {{", SESSION_CLASS), false);
+        for (name, text) in &self.functions {
+            if kind != InputKind::Function || !replaced.contains(name) {
+                push(&mut source, text, false);
+            }
+        }
+        if kind == InputKind::Function {
+            push(&mut source, input, true);
+        }
+        push(&mut source, "public static void main() {", false);
+
+        // 会话变量按类型的默认值重新声明，只用于语义检查，执行时使用保存的值
+        let mut names: Vec<&String> = self.globals.keys()
+            .filter(|name| kind != InputKind::Statements || !replaced.contains(name))
+            .collect();
+        names.sort();
+        let mut prelude_len = 0;
+        for name in names {
+            let ty = &self.globals[name].0;
+            if *ty == Type::Auto {
+                continue;
+            }
+            let modifier = if self.finals.contains(name) { "final " } else { "" };
+            push(&mut source, &format!("{}{} {} = {};", modifier, self.type_name(ty), name, default_literal(ty)), false);
+            prelude_len += 1;
+        }
+        if kind == InputKind::Statements {
+            push(&mut source, input, true);
+        }
+        push(&mut source, "}\n}", false);
+        SessionProgram { source, offset, prelude_len }
+    }
+
+    /// 类型的源码写法，泛型实例写回 `List<int>` 的形式
+    fn type_name(&self, ty: &Type) -> String {
+        match ty {
+            Type::Object(name) => self.generic_names.get(name).cloned().unwrap_or_else(|| name.clone()),
+            Type::Array(inner) => format!("{}[]", self.type_name(inner)),
+            _ => ty.source_name(),
+        }
+    }
+}
+
+/// 输入是否完整：括号都已闭合时才执行，否则继续读入下一行
+///
+/// 无法分词的输入（如未闭合的字符串）视为完整，直接报告错误。
+pub fn is_complete(input: &str) -> bool {
+    let Ok(tokens) = lexer::lex(input) else {
+        return true;
+    };
+    let depth: i64 = tokens.iter().map(|t| match t.token {
+        Token::LParen | Token::LBrace | Token::LBracket => 1,
+        Token::RParen | Token::RBrace | Token::RBracket => -1,
+        _ => 0,
+    }).sum();
+    depth <= 0
+}
+
+/// 解析、单态化并做语义分析
+///
+/// 单态化之前记录源码中用到的泛型类型的写法。
+fn check(source: &str, generic_names: &mut HashMap<String, String>) -> cayResult<(Program, semantic::SemanticAnalyzer)> {
+    let tokens = lexer::lex(source)?;
+    let mut program = modules::ModuleLoader::new(None).parse_program(tokens)?;
+    visit::walk_program(&mut GenericNames(generic_names), &mut program)?;
+    let program = generics::monomorphize(program)?;
+    let mut analyzer = semantic::SemanticAnalyzer::new();
+    analyzer.analyze(&program)?;
+    Ok((program, analyzer))
+}
+
+/// 会话类 main 方法中的语句
+fn main_statements(program: &Program) -> Option<&[Stmt]> {
+    let session = program.classes.iter().find(|c| c.name == SESSION_CLASS)?;
+    session.members.iter().find_map(|member| match member {
+        crate::ast::ClassMember::Method(method) if method.name == "main" => method.body.as_ref().map(|b| b.statements.as_slice()),
+        _ => None,
+    })
+}
+
+/// 表达式语句是否输出它的值：赋值和自增自减只有副作用，不输出
+fn shows_value(expr: &Expr) -> bool {
+    match expr {
+        Expr::Assignment(AssignmentExpr { .. }) => false,
+        Expr::Unary(unary) => !matches!(unary.op, UnaryOp::PreInc | UnaryOp::PreDec | UnaryOp::PostInc | UnaryOp::PostDec),
+        _ => true,
+    }
+}
+
+/// 类型的默认值在源码中的写法
+/// 收集泛型类型的实例类名和源码写法（包括 `new List<int>()` 中的类型）
+struct GenericNames<'a>(&'a mut HashMap<String, String>);
+
+impl VisitMut for GenericNames<'_> {
+    fn visit_type(&mut self, ty: &mut Type, loc: &SourceLocation) -> cayResult<()> {
+        if let Type::Generic(name, args) = ty {
+            self.0.insert(generics::instance_name(name, args), ty.source_name());
+        }
+        visit::walk_type(self, ty, loc)
+    }
+
+    fn visit_expr(&mut self, expr: &mut Expr) -> cayResult<()> {
+        if let Expr::New(new_expr) = expr
+            && let Some(args) = new_expr.type_args.as_ref().filter(|args| !args.is_empty())
+        {
+            let mut ty = Type::Generic(new_expr.class_name.clone(), args.clone());
+            self.visit_type(&mut ty, &new_expr.loc)?;
+        }
+        visit::walk_expr(self, expr)
+    }
+}
+
+fn default_literal(ty: &Type) -> &'static str {
+    match ty {
+        Type::Int8 | Type::Int16 | Type::Int32 | Type::Int64 | Type::Float32 | Type::Float64 => "0",
+        Type::Bool => "false",
+        Type::Char => "(char) 0",
+        _ => "null",
+    }
+}
+
+fn compile_error(error: &cayError, offset: usize) -> EvalError {
+    let mut diagnostics = Diagnostic::from_error(error, INPUT_FILE);
+    for d in &mut diagnostics {
+        // 落在会话已有声明中的位置对本次输入没有意义
+        d.location = d.location.take()
+            .filter(|loc| loc.line > offset)
+            .map(|mut loc| {
+                loc.line -= offset;
+                loc
+            });
+    }
+    EvalError::Compile(diagnostics)
+}
+
+fn runtime_error(unwind: Unwind) -> EvalError {
+    match unwind {
+        Unwind::Error(message) => EvalError::Runtime(message),
+        Unwind::Exit(code) => EvalError::Exit(code),
+        Unwind::Break(_) | Unwind::Continue(_) | Unwind::Return(_) => EvalError::Runtime("Unexpected control flow at top level".to_string()),
+    }
+}
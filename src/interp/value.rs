@@ -0,0 +1,295 @@
+//! 解释器的运行时值
+//!
+//! 基本类型按值保存，字符串、数组、对象和函数值按引用共享（`Rc`），
+//! 与编译后程序的引用语义一致：数组和对象赋值后指向同一份数据。
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use crate::ast::{LambdaBody, LambdaParam};
+use crate::types::Type;
+
+/// 运行时值
+#[derive(Debug, Clone)]
+pub enum Value {
+    /// void 方法调用的结果
+    Void,
+    Null,
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    Bool(bool),
+    Char(char),
+    Str(Rc<str>),
+    Array(Rc<RefCell<Array>>),
+    Object(Rc<RefCell<Object>>),
+    Function(Rc<Function>),
+}
+
+/// 数组：元素类型决定写入元素时的转换和默认值
+#[derive(Debug)]
+pub struct Array {
+    pub elem: Type,
+    pub values: Vec<Value>,
+}
+
+/// 对象：实例字段按声明顺序保存，父类字段在前
+#[derive(Debug)]
+pub struct Object {
+    pub class: String,
+    pub fields: Vec<(String, Value)>,
+    /// 内置类由解释器实现的数据（见 [`crate::prelude::NativeClass`]）
+    pub native: NativeData,
+}
+
+/// 内置类的数据，第一次调用 native 方法时创建
+#[derive(Debug, Default)]
+pub enum NativeData {
+    #[default]
+    None,
+    /// Map 的条目，按插入顺序保存
+    Map(Vec<(Value, Value)>),
+    /// StringBuilder 的内容
+    Text(String),
+}
+
+impl Object {
+    pub fn field(&self, name: &str) -> Option<&Value> {
+        self.fields.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+    }
+
+    pub fn field_mut(&mut self, name: &str) -> Option<&mut Value> {
+        self.fields.iter_mut().find(|(n, _)| n == name).map(|(_, v)| v)
+    }
+}
+
+/// 函数值：Lambda 或方法引用
+#[derive(Debug)]
+pub enum Function {
+    /// Lambda 表达式，创建时按值捕获可见的局部变量
+    Lambda {
+        params: Vec<LambdaParam>,
+        body: LambdaBody,
+        captured: Vec<(String, Type, Value)>,
+        this: Option<Value>,
+        class: Option<String>,
+    },
+    /// 方法引用 `Class::method` 或 `obj::method`
+    Method {
+        class: String,
+        name: String,
+        receiver: Option<Value>,
+    },
+}
+
+impl Value {
+    pub fn str(s: impl Into<Rc<str>>) -> Value {
+        Value::Str(s.into())
+    }
+
+    pub fn new_array(elem: Type, values: Vec<Value>) -> Value {
+        Value::Array(Rc::new(RefCell::new(Array { elem, values })))
+    }
+
+    /// 类型的默认值（字段、数组元素和未初始化变量使用）
+    pub fn default_for(ty: &Type) -> Value {
+        match ty {
+            Type::Int8 | Type::Int16 | Type::Int32 => Value::Int(0),
+            Type::Int64 => Value::Long(0),
+            Type::Float32 => Value::Float(0.0),
+            Type::Float64 => Value::Double(0.0),
+            Type::Bool => Value::Bool(false),
+            Type::Char => Value::Char('\0'),
+            _ => Value::Null,
+        }
+    }
+
+    /// 值的运行时类型；null 和 void 没有确定的类型
+    pub fn runtime_type(&self) -> Option<Type> {
+        Some(match self {
+            Value::Void | Value::Null | Value::Function(_) => return None,
+            Value::Int(_) => Type::Int32,
+            Value::Long(_) => Type::Int64,
+            Value::Float(_) => Type::Float32,
+            Value::Double(_) => Type::Float64,
+            Value::Bool(_) => Type::Bool,
+            Value::Char(_) => Type::Char,
+            Value::Str(_) => Type::String,
+            Value::Array(arr) => Type::Array(Box::new(arr.borrow().elem.clone())),
+            Value::Object(obj) => Type::Object(obj.borrow().class.clone()),
+        })
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    /// 整数值（int、long、char），用作下标、数组长度和 switch 的分支值
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Int(v) => Some(*v as i64),
+            Value::Long(v) => Some(*v),
+            Value::Char(c) => Some(*c as i64),
+            _ => None,
+        }
+    }
+
+    /// 数值转换为 double，非数值返回 `None`
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Float(v) => Some(*v as f64),
+            Value::Double(v) => Some(*v),
+            _ => self.as_i64().map(|v| v as f64),
+        }
+    }
+
+    /// 按目标类型转换（赋值、传参、返回值和强制类型转换）
+    ///
+    /// 数值之间按 C 的规则截断或扩展，其他类型原样返回。byte/short 用 `Value::Int` 表示，
+    /// 值截断到 8/16 位后按符号扩展。
+    pub fn convert_to(self, ty: &Type) -> Value {
+        match (ty, &self) {
+            (Type::Int8 | Type::Int16, Value::Int(_) | Value::Long(_) | Value::Char(_) | Value::Float(_) | Value::Double(_)) => {
+                let int = match self.convert_to(&Type::Int32) {
+                    Value::Int(v) => v,
+                    _ => 0,
+                };
+                Value::Int(if *ty == Type::Int8 { int as i8 as i32 } else { int as i16 as i32 })
+            }
+            (Type::Int32, Value::Long(v)) => Value::Int(*v as i32),
+            (Type::Int32, Value::Char(c)) => Value::Int(*c as i32),
+            (Type::Int32, Value::Float(v)) => Value::Int(*v as i32),
+            (Type::Int32, Value::Double(v)) => Value::Int(*v as i32),
+            (Type::Int64, Value::Int(_) | Value::Char(_)) => Value::Long(self.as_i64().unwrap_or_default()),
+            (Type::Int64, Value::Float(v)) => Value::Long(*v as i64),
+            (Type::Int64, Value::Double(v)) => Value::Long(*v as i64),
+            (Type::Float32, Value::Int(_) | Value::Long(_) | Value::Char(_) | Value::Double(_)) => {
+                Value::Float(self.as_f64().unwrap_or_default() as f32)
+            }
+            (Type::Float64, Value::Int(_) | Value::Long(_) | Value::Char(_) | Value::Float(_)) => {
+                Value::Double(self.as_f64().unwrap_or_default())
+            }
+            // char 占 1 字节，与编译后程序一致
+            (Type::Char, Value::Int(_) | Value::Long(_)) => {
+                // 不是合法码点的值（负数、代理项、超过 U+10FFFF）转换为替换字符
+                let code = u32::try_from(self.as_i64().unwrap_or_default()).ok().and_then(char::from_u32);
+                Value::Char(code.unwrap_or(char::REPLACEMENT_CHARACTER))
+            }
+            _ => self,
+        }
+    }
+}
+
+/// 格式化对象时的递归深度上限，与编译后程序的反射打印一致
+const MAX_DEPTH: usize = 4;
+
+/// 值的字符串形式：打印和字符串拼接共用
+///
+/// 定义了 `toString()` 的对象由调用方提供的 `to_string` 转换（返回 `None` 时按字段格式化）。
+pub struct Formatter<'a> {
+    /// 浮点数的小数位数（`setPrintPrecision`），`None` 表示最短的可还原形式
+    pub precision: Option<usize>,
+    pub to_string: &'a mut dyn FnMut(&Value) -> Option<String>,
+}
+
+impl Formatter<'_> {
+    pub fn format(&mut self, value: &Value) -> String {
+        self.format_depth(value, 0)
+    }
+
+    fn format_depth(&mut self, value: &Value, depth: usize) -> String {
+        match value {
+            Value::Void => String::new(),
+            Value::Null => "null".to_string(),
+            Value::Int(v) => v.to_string(),
+            Value::Long(v) => v.to_string(),
+            Value::Float(v) => match self.precision {
+                Some(digits) => format_fixed(*v as f64, digits),
+                None => format_shortest(*v as f64, true),
+            },
+            Value::Double(v) => match self.precision {
+                Some(digits) => format_fixed(*v, digits),
+                None => format_shortest(*v, false),
+            },
+            Value::Bool(b) => b.to_string(),
+            Value::Char(c) => c.to_string(),
+            Value::Str(s) => s.to_string(),
+            Value::Function(_) => "<function>".to_string(),
+            Value::Array(arr) => {
+                let values = arr.borrow().values.clone();
+                let items: Vec<String> = values.iter().map(|v| self.format_depth(v, depth + 1)).collect();
+                format!("[{}]", items.join(", "))
+            }
+            Value::Object(obj) => {
+                if let Some(text) = (self.to_string)(value) {
+                    return text;
+                }
+                let (class, fields) = {
+                    let obj = obj.borrow();
+                    (obj.class.clone(), obj.fields.clone())
+                };
+                if depth >= MAX_DEPTH {
+                    return format!("{}@{:p}", class, Rc::as_ptr(obj));
+                }
+                let items: Vec<String> = fields.iter()
+                    .map(|(name, v)| format!("{}={}", name, self.format_depth(v, depth + 1)))
+                    .collect();
+                format!("{}{{{}}}", class, items.join(", "))
+            }
+        }
+    }
+}
+
+/// 按固定小数位数格式化浮点数，与 C 的 `%.*f` 一致
+pub(crate) fn format_fixed(v: f64, precision: usize) -> String {
+    if v.is_nan() {
+        "nan".to_string()
+    } else {
+        format!("{:.*}", precision, v)
+    }
+}
+
+/// 能精确还原该值的最短十进制形式，与编译后程序的 `__cay_double_to_string` 一致
+///
+/// `single` 为 true 时按单精度判断能否还原（最多 9 位有效数字，double 最多 17 位）；
+/// 指数在 [-4, 最大有效位数) 之内时使用定点形式并至少保留一位小数，否则使用 C 风格的科学计数法。
+fn format_shortest(v: f64, single: bool) -> String {
+    if !v.is_finite() {
+        return format_fixed(v, 0);
+    }
+    let max_digits = if single { 9 } else { 17 };
+    let mut text = String::new();
+    for digits in 1..=max_digits {
+        text = format!("{:.*e}", digits - 1, v);
+        let parsed: f64 = text.parse().unwrap_or_default();
+        let round_trip = if single { parsed as f32 as f64 } else { parsed };
+        if round_trip == v {
+            break;
+        }
+    }
+    let Some((mantissa, exponent)) = text.split_once('e') else {
+        return text;
+    };
+    let exponent: i32 = exponent.parse().unwrap_or_default();
+    if exponent < -4 || exponent >= max_digits as i32 {
+        return c_exponent(&text);
+    }
+    let decimals = mantissa.split_once('.').map_or(0, |(_, frac)| frac.len() as i32);
+    format!("{:.*}", (decimals - exponent).max(1) as usize, v)
+}
+
+/// Rust 的指数写法（`1.5e4`）改写为 C 的写法（`1.5e+04`）
+pub(crate) fn c_exponent(text: &str) -> String {
+    let Some((mantissa, exponent)) = text.split_once('e') else {
+        return text.to_string();
+    };
+    let (sign, digits) = match exponent.strip_prefix('-') {
+        Some(digits) => ('-', digits),
+        None => ('+', exponent),
+    };
+    format!("{}e{}{:0>2}", mantissa, sign, digits)
+}
+
+/// 按名称保存的变量及其声明类型
+pub type Bindings = HashMap<String, (Type, Value)>;
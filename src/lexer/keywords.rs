@@ -0,0 +1,49 @@
+//! 关键字表
+//!
+//! Cavvy 的关键字分为两类：
+//! - 保留关键字（`class`、`if`、`int` 等）由词法分析器识别为专门的 token，任何位置都不能用作标识符；
+//! - 上下文关键字在词法上是普通标识符，只在特定的语法位置由解析器识别，其他位置仍可作为
+//!   变量名、方法名、字段名或类名使用。
+//!
+//! 新增语言特性需要新的关键字时，优先加入上下文关键字，这样已有程序中同名的标识符不受影响。
+
+/// 上下文关键字
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextualKeyword {
+    /// `var x = ...;` / `var x: T = ...;`，只在语句开头且后面紧跟变量名时是关键字
+    Var,
+    /// `let x = ...;`，规则同 `var`
+    Let,
+    /// `auto x = ...;`，规则同 `var`
+    Auto,
+    /// 函数类型 `fn(int) -> int`，只在类型位置且配对的 `)` 后紧跟 `->` 时是关键字
+    Fn,
+    /// `import a.b;` / `import "a/b.cay";`，只在文件开头、所有类型声明之前是关键字
+    Import,
+}
+
+impl ContextualKeyword {
+    /// 所有上下文关键字
+    pub const ALL: [ContextualKeyword; 5] = [Self::Var, Self::Let, Self::Auto, Self::Fn, Self::Import];
+
+    /// 关键字的源码拼写
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Var => "var",
+            Self::Let => "let",
+            Self::Auto => "auto",
+            Self::Fn => "fn",
+            Self::Import => "import",
+        }
+    }
+
+    /// 标识符对应的上下文关键字
+    pub fn from_identifier(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|keyword| keyword.as_str() == name)
+    }
+
+    /// 是否是声明局部变量的关键字（`var`、`let`、`auto`）
+    pub fn declares_variable(self) -> bool {
+        matches!(self, Self::Var | Self::Let | Self::Auto)
+    }
+}
@@ -1,6 +1,9 @@
+pub mod keywords;
+
 use logos::Logos;
 use crate::error::{cayResult, lexer_error};
 use crate::error::SourceLocation;
+use crate::source::{normalize_source, LineIndex};
 
 #[derive(Logos, Debug, Clone, PartialEq)]
 #[logos(skip r"[ \t\f]+")]
@@ -22,15 +25,21 @@ pub enum Token {
     Abstract,
     #[token("native")]
     Native,
-    // 注解 - 注意：@main 和 @Override 是完整的令牌，不是 @ + 标识符
+    // 注解 - 注意：@main、@Override 和 @Symbol 是完整的令牌，不是 @ + 标识符
     #[token("@main")]
     AtMain,
     #[token("@Override")]
     AtOverride,
+    #[token("@Symbol")]
+    AtSymbol,
     #[token("class")]
     Class,
     #[token("void")]
     Void,
+    #[token("byte")]
+    Byte,
+    #[token("short")]
+    Short,
     #[token("int")]
     Int,
     #[token("long")]
@@ -89,12 +98,9 @@ pub enum Token {
     Interface,
     #[token("instanceof")]
     InstanceOf,
-    #[token("var")]
-    Var,
-    #[token("let")]
-    Let,
-    #[token("auto")]
-    Auto,
+    #[token("__llvm__")]
+    InlineIr,
+    // var/let/auto/fn/import 是上下文关键字（见 keywords 模块），词法上作为标识符
 
     // 标识符
     #[regex(r"[a-zA-Z_][a-zA-Z0-9_]*", |lex| lex.slice().to_string())]
@@ -148,17 +154,13 @@ pub enum Token {
     })]
     FloatLiteral(Option<(f64, Option<char>)>),
     
-    #[regex(r#""([^"\\]|\\.)*""#, |lex| {
-        let s = lex.slice();
-        let content = &s[1..s.len()-1];
-        Some(process_escape_sequences(content))
-    })]
+    #[token("\"", lex_string_literal)]
     StringLiteral(Option<String>),
     
-    #[regex(r"'([^'\\]|\\.)'", |lex| {
+    #[regex(r"'([^'\\]|\\u[^'\n]*|\\.)'", |lex| {
         let s = lex.slice();
         let content = &s[1..s.len()-1];
-        process_char_escape(content)
+        process_char_escape(content).ok()
     })]
     CharLiteral(Option<char>),
     
@@ -255,6 +257,12 @@ pub enum Token {
     Arrow,
     #[token("?")]
     Question,
+    #[token("?.")]
+    QuestionDot,     // 安全导航: obj?.member
+    #[token("??")]
+    QuestionQuestion, // 空值合并: a ?? b
+    #[token("?:")]
+    Elvis,           // 空值合并的另一种写法: a ?: b
 
     // 换行（用于跟踪行号）- 支持 Windows \r\n 和 Unix \n
     #[regex(r"\r?\n")]
@@ -270,8 +278,8 @@ pub struct TokenWithLocation {
 pub struct Lexer<'a> {
     source: &'a str,
     inner: logos::Lexer<'a, Token>,
-    line: usize,
-    column: usize,
+    line_index: LineIndex,
+    last_location: (usize, SourceLocation),  // 上一个 token 的 (字节偏移, 位置)，用于增量计算列号
 }
 
 impl<'a> Lexer<'a> {
@@ -279,40 +287,73 @@ impl<'a> Lexer<'a> {
         Self {
             source,
             inner: Token::lexer(source),
-            line: 1,
-            column: 1,
+            line_index: LineIndex::new(source),
+            last_location: (0, SourceLocation { line: 1, column: 1 }),
         }
     }
 
+    /// 字节偏移对应的位置
+    ///
+    /// token 按偏移递增产生，同一行内从上一个 token 的位置向后数字符，
+    /// 避免超长行（如巨大的字符串字面量或表达式）上每个 token 都从行首重新计数。
+    fn location(&mut self, offset: usize) -> SourceLocation {
+        let (last_offset, last) = &self.last_location;
+        let loc = match self.source.get(*last_offset..offset) {
+            Some(between) if !between.contains('\n') => SourceLocation {
+                line: last.line,
+                column: last.column + between.chars().count(),
+            },
+            _ => self.line_index.location(self.source, offset),
+        };
+        self.last_location = (offset, loc.clone());
+        loc
+    }
+
     pub fn tokenize(&mut self) -> cayResult<Vec<TokenWithLocation>> {
         let mut tokens = Vec::new();
         
         while let Some(token_result) = self.inner.next() {
             match token_result {
                 Ok(token) => {
-                    let span = self.inner.span();
-                    let loc = SourceLocation {
-                        line: self.line,
-                        column: self.column,
-                    };
-                    
-                    // 更新行号和列号
                     if token == Token::Newline {
-                        self.line += 1;
-                        self.column = 1;
                         continue; // 不保留换行token
-                    } else {
-                        self.column += span.end - span.start;
                     }
-                    
+
+                    // `cond ?.5 : x` 是三元运算符后接浮点数 .5，而不是安全导航
+                    if token == Token::QuestionDot
+                        && self.inner.remainder().starts_with(|c: char| c.is_ascii_digit())
+                    {
+                        let start = self.inner.span().start;
+                        let loc = self.location(start);
+                        tokens.push(TokenWithLocation { token: Token::Question, loc });
+                        self.inner = Token::lexer(self.source);
+                        self.inner.bump(start + 1);
+                        continue;
+                    }
+
+                    // 行列号由行起始偏移表换算，跳过的空白和注释不会影响列号
+                    let loc = self.location(self.inner.span().start);
+
+                    // 含有非法转义序列的字面量：重新处理字面量内容得到具体的错误信息
+                    if matches!(token, Token::StringLiteral(None) | Token::CharLiteral(None)) {
+                        let slice = self.inner.slice();
+                        let content = &slice[1..slice.len() - 1];
+                        let message = match token {
+                            Token::StringLiteral(_) => process_escape_sequences(content).err(),
+                            _ => process_char_escape(content).err(),
+                        };
+                        return Err(lexer_error(loc.line, loc.column, message.unwrap_or_default()));
+                    }
+
                     tokens.push(TokenWithLocation { token, loc });
                 }
                 Err(_) => {
                     let span = self.inner.span();
                     let error_char = &self.source[span.clone()];
+                    let loc = self.location(span.start);
                     return Err(lexer_error(
-                        self.line,
-                        self.column,
+                        loc.line,
+                        loc.column,
                         format!("Unexpected character: '{}'", error_char)
                     ));
                 }
@@ -322,10 +363,7 @@ impl<'a> Lexer<'a> {
         // 添加EOF标记 - 使用Identifier作为哨兵值
         tokens.push(TokenWithLocation {
             token: Token::Identifier(String::new()), // 用作EOF标记
-            loc: SourceLocation {
-                line: self.line,
-                column: self.column,
-            },
+            loc: self.location(self.source.len()),
         });
         
         Ok(tokens)
@@ -333,54 +371,120 @@ impl<'a> Lexer<'a> {
 }
 
 pub fn lex(source: &str) -> cayResult<Vec<TokenWithLocation>> {
-    let mut lexer = Lexer::new(source);
+    // 去除 BOM、统一换行符，避免 BOM 被识别为非法字符以及单独的 \r 打乱行号
+    let source = normalize_source(source);
+    let mut lexer = Lexer::new(&source);
     lexer.tokenize()
 }
 
+/// 读取字符串字面量的剩余部分（起始引号已匹配）
+///
+/// 直接在源码上查找结束引号，而不是使用正则：字面量可能长达数 MB，
+/// 正则匹配在调试构建中会按字符递归，导致栈溢出。未闭合时报告起始引号处的非法字符错误，
+/// 含有非法转义序列时值为 `None`。
+fn lex_string_literal(lex: &mut logos::Lexer<Token>) -> Result<Option<String>, ()> {
+    let bytes = lex.remainder().as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => {
+                let content = &lex.remainder()[..i];
+                let value = process_escape_sequences(content).ok();
+                lex.bump(i + 1);
+                return Ok(value);
+            }
+            // 反斜杠后的字符（包括引号）属于转义序列
+            b'\\' => i += 2,
+            _ => i += 1,
+        }
+    }
+    Err(())
+}
+
 /// 处理字符串中的转义序列
-fn process_escape_sequences(s: &str) -> String {
+///
+/// 转义序列非法时返回错误信息，由 `Lexer::tokenize` 报告。
+fn process_escape_sequences(s: &str) -> Result<String, String> {
     let mut result = String::with_capacity(s.len());
     let mut chars = s.chars().peekable();
-    
+
     while let Some(c) = chars.next() {
         if c == '\\' {
-            match chars.next() {
-                Some('n') => result.push('\n'),
-                Some('t') => result.push('\t'),
-                Some('r') => result.push('\r'),
-                Some('\\') => result.push('\\'),
-                Some('"') => result.push('"'),
-                Some('\'') => result.push('\''),
-                Some('0') => result.push('\0'),
-                Some(other) => {
-                    // 对于不认识的转义序列，保留原样
-                    result.push('\\');
-                    result.push(other);
-                }
-                None => result.push('\\'),
-            }
+            let c = read_escape(&mut chars)
+                .map_err(|seq| format!("Invalid escape sequence '{}' in string literal", seq))?;
+            result.push(c);
         } else {
             result.push(c);
         }
     }
-    
-    result
+
+    Ok(result)
 }
 
 /// 处理字符字面量的转义序列
-fn process_char_escape(s: &str) -> Option<char> {
-    if s.starts_with("\\") {
-        match s.chars().nth(1) {
-            Some('n') => Some('\n'),
-            Some('t') => Some('\t'),
-            Some('r') => Some('\r'),
-            Some('\\') => Some('\\'),
-            Some('\'') => Some('\''),
-            Some('"') => Some('"'),
-            Some('0') => Some('\0'),
-            _ => s.chars().nth(1),
+///
+/// char 保存一个 Unicode 码点，`\u` 转义可以用代理对表示 BMP 之外的字符。非法时返回错误信息。
+fn process_char_escape(s: &str) -> Result<char, String> {
+    let mut chars = s.chars().peekable();
+    let c = match chars.next() {
+        Some('\\') => read_escape(&mut chars)
+            .map_err(|seq| format!("Invalid escape sequence '{}' in character literal", seq))?,
+        Some(c) => c,
+        None => return Err("Empty character literal".to_string()),
+    };
+    if chars.next().is_some() {
+        return Err(format!("Character literal '{}' contains more than one character", s));
+    }
+    Ok(c)
+}
+
+/// 读取反斜杠之后的一个转义序列
+///
+/// 支持 `\n \t \r \b \f \0 \\ \' \"` 和 `\uXXXX`（恰好 4 位十六进制）。
+/// `\uD83D\uDE00` 形式的代理对合并为一个字符，单独的代理项是非法的。
+fn read_escape(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<char, String> {
+    let c = match chars.next() {
+        Some('n') => '\n',
+        Some('t') => '\t',
+        Some('r') => '\r',
+        Some('b') => '\u{8}',
+        Some('f') => '\u{c}',
+        Some('0') => '\0',
+        Some('\\') => '\\',
+        Some('"') => '"',
+        Some('\'') => '\'',
+        Some('u') => {
+            let high = read_unicode_digits(chars)?;
+            if !(0xD800..0xDC00).contains(&high) {
+                return char::from_u32(high).ok_or_else(|| format!("\\u{:04x}", high));
+            }
+            // 高代理项之后必须紧跟 \uDC00 ~ \uDFFF 的低代理项
+            let mut rest = chars.clone();
+            if rest.next() != Some('\\') || rest.next() != Some('u') {
+                return Err(format!("\\u{:04x}", high));
+            }
+            let low = read_unicode_digits(&mut rest)?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(format!("\\u{:04x}\\u{:04x}", high, low));
+            }
+            *chars = rest;
+            let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+            return char::from_u32(code).ok_or_else(|| format!("\\u{:04x}\\u{:04x}", high, low));
+        }
+        Some(other) => return Err(format!("\\{}", other)),
+        None => return Err("\\".to_string()),
+    };
+    Ok(c)
+}
+
+/// 读取 `\u` 之后的 4 位十六进制数字
+fn read_unicode_digits(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<u32, String> {
+    let mut digits = String::with_capacity(4);
+    while digits.len() < 4 {
+        match chars.peek() {
+            Some(c) if c.is_ascii_hexdigit() => digits.push(chars.next().unwrap_or_default()),
+            _ => return Err(format!("\\u{}", digits)),
         }
-    } else {
-        s.chars().next()
     }
+    u32::from_str_radix(&digits, 16).map_err(|_| format!("\\u{}", digits))
 }
@@ -1,15 +1,43 @@
 pub mod error;
+pub mod diagnostics;
 pub mod types;
 pub mod ast;
+pub mod source;
 pub mod preprocessor;
 pub mod lexer;
 pub mod parser;
 pub mod semantic;
 pub mod codegen;
+pub mod toolchain;
+pub mod version;
+pub mod interface;
+pub mod reduce;
+pub mod visit;
+pub mod generics;
+pub mod prelude;
+pub mod stack_usage;
+pub mod target;
+pub mod cli;
+pub mod ice;
+pub mod opt;
+pub mod grammar;
+pub mod modules;
+pub mod parallel;
+pub mod cache;
+pub mod lsp;
+pub mod watch;
+pub mod interp;
+pub mod ast_dump;
+pub mod refactor;
 
 use std::path::{Path, PathBuf};
 use error::cayResult;
 
+pub use version::cavvy_version;
+
+/// `--stack-guard` 未指定深度时的默认最大调用深度
+pub const DEFAULT_STACK_GUARD_DEPTH: u32 = 10000;
+
 /// 编译器配置选项
 #[derive(Debug, Clone)]
 pub struct CompilerOptions {
@@ -19,6 +47,28 @@ pub struct CompilerOptions {
     pub defines: Vec<String>,
     pub undefines: Vec<String>,
     pub obfuscate: bool,
+    /// 不在生成的模块中内联运行时，改为链接 `compile_runtime` 生成的运行时模块
+    pub external_runtime: bool,
+    /// 语义分析前导入的 .cavi 接口文件
+    pub interface_imports: Vec<String>,
+    /// 编译成功后输出本编译单元的 .cavi 接口文件
+    pub emit_interface: Option<String>,
+    /// 在方法入口插入调用深度计数，超过该深度时报告 StackOverflow 并退出
+    pub stack_guard_depth: Option<u32>,
+    /// 执行限制：每次进入函数、每轮循环计一步，超过该步数时报告 "Execution limit exceeded" 并退出
+    pub max_steps: Option<u64>,
+    /// 执行限制：累计堆分配超过该字节数时报告 "Execution limit exceeded" 并退出
+    pub max_heap_bytes: Option<u64>,
+    /// 语义分析后运行源码级优化（常量折叠、常量传播、死分支消除），对应 `-O1` 及以上
+    pub optimize: bool,
+    /// 类型检查和代码生成使用的线程数，大于 1 时各个类并行处理（`--jobs N`）
+    pub jobs: usize,
+    /// 编译缓存目录：源文件及其导入的模块内容未变时直接复用上次生成的 IR（`--cache-dir DIR`）
+    pub cache_dir: Option<String>,
+    /// 库模式（`--no-main`）：不要求也不生成程序入口，`main` 作为普通静态方法编译
+    pub no_main: bool,
+    /// 每个函数生成后自检 IR 的值和类型，出错时报告对应的源码位置（`--verify-ir`）
+    pub verify_ir: bool,
 }
 
 impl Default for CompilerOptions {
@@ -30,6 +80,17 @@ impl Default for CompilerOptions {
             defines: Vec::new(),
             undefines: Vec::new(),
             obfuscate: false,
+            external_runtime: false,
+            interface_imports: Vec::new(),
+            emit_interface: None,
+            stack_guard_depth: None,
+            max_steps: None,
+            max_heap_bytes: None,
+            optimize: false,
+            jobs: 1,
+            cache_dir: None,
+            no_main: false,
+            verify_ir: false,
         }
     }
 }
@@ -58,7 +119,29 @@ impl Compiler {
     /// # Returns
     /// 编译成功返回 Ok(())
     pub fn compile(&self, source: &str, output_path: &str) -> cayResult<()> {
+        let ir = self.compile_to_ir(source)?;
+
+        // 输出到文件（仅在全部阶段成功后原子写入）
+        write_output_atomically(output_path, &ir)
+    }
+
+    /// 编译源代码，返回 LLVM IR 文本而不写入文件
+    /// 
+    /// # Arguments
+    /// * `source` - 原始源代码（已预处理）
+    pub fn compile_to_ir(&self, source: &str) -> cayResult<String> {
+        self.compile_module_to_ir(source, None)
+    }
+
+    /// 编译入口模块的源代码（已预处理），`entry_path` 为其文件路径，import 声明相对该文件所在目录解析
+    pub fn compile_module_to_ir(&self, source: &str, entry_path: Option<&str>) -> cayResult<String> {
+        self.compile_with_loader(source, &mut modules::ModuleLoader::new(entry_path))
+    }
+
+    /// 用给定的模块加载器编译入口模块，编译后可从加载器取得被导入模块的列表
+    fn compile_with_loader(&self, source: &str, loader: &mut modules::ModuleLoader) -> cayResult<String> {
         // 1. 词法分析
+        ice::enter_phase("词法分析");
         let tokens = lexer::lex(source)?;
         
         // 调试：打印所有token
@@ -71,19 +154,58 @@ impl Compiler {
             println!();
         }
         
-        // 2. 语法分析
-        let ast = parser::parse(tokens)?;
+        // 2. 语法分析（同时加载 import 声明引用的模块）
+        ice::enter_phase("语法分析");
+        let ast = loader.parse_program(tokens)?;
+
+        // 泛型单态化：展开泛型类和泛型方法
+        ice::enter_phase("泛型单态化");
+        let mut ast = generics::monomorphize(ast)?;
         
         // 3. 语义分析
+        ice::enter_phase("语义分析");
         let mut analyzer = semantic::SemanticAnalyzer::new();
+        analyzer.set_jobs(self.options.jobs);
+        analyzer.set_entry_point(if self.options.no_main { semantic::EntryPoint::Library } else { semantic::EntryPoint::Required });
+        let mut imported_layouts = Vec::new();
+        for path in &self.options.interface_imports {
+            let summary = interface::InterfaceSummary::read(path)?;
+            analyzer.import_interface(&summary)?;
+            imported_layouts.extend(summary.layouts);
+        }
         analyzer.analyze(&ast)?;
 
+        // 源码级优化
+        if self.options.optimize {
+            ice::enter_phase("优化");
+            opt::optimize(&mut ast)?;
+        }
+
         // 4. 代码生成 - 生成LLVM IR（字符串常量已在生成器内处理）
+        ice::enter_phase("代码生成");
         let mut ir_gen = codegen::IRGenerator::new();
         // 传递多平台配置
         ir_gen.set_platform_config(&self.options);
         // 传递类型注册表以支持正确的方法名生成
         ir_gen.set_type_registry(analyzer.get_type_registry().clone());
+        ir_gen.set_inferred_var_types(analyzer.inferred_var_types().clone());
+        ir_gen.set_null_safe_types(analyzer.null_safe_types().clone());
+        ir_gen.set_ternary_types(analyzer.ternary_types().clone());
+        ir_gen.set_field_access_classes(analyzer.field_access_classes().clone());
+        ir_gen.set_string_comparisons(analyzer.string_comparisons().clone());
+        ir_gen.set_print_arg_types(analyzer.print_arg_types().clone());
+        ir_gen.set_string_conversions(analyzer.string_conversions().clone());
+        ir_gen.set_lambda_captures(analyzer.lambda_captures().clone());
+        ir_gen.set_function_types(analyzer.function_types().clone());
+        ir_gen.set_method_refs(analyzer.method_refs().clone());
+        ir_gen.set_debug_prints(analyzer.debug_prints().clone());
+        ir_gen.set_format_calls(analyzer.format_calls().clone());
+        ir_gen.set_for_each_types(analyzer.for_each_types().clone());
+        ir_gen.set_call_overloads(analyzer.call_overloads().clone());
+        ir_gen.set_cast_types(analyzer.cast_types().clone());
+        ir_gen.set_array_element_types(analyzer.array_element_types().clone());
+        ir_gen.set_expr_types(analyzer.expr_types().clone());
+        ir_gen.import_class_layouts(&imported_layouts)?;
         let mut ir = ir_gen.generate(&ast)?;
         
         // 5. 如果启用了混淆，应用IR混淆
@@ -93,11 +215,43 @@ impl Compiler {
             ir = obfuscator.obfuscate_ir(&ir);
         }
         
-        // 输出到文件
-        std::fs::write(output_path, ir)
-            .map_err(|e| error::cayError::Io(e.to_string()))?;
-        
-        Ok(())
+        // 6. 输出接口文件
+        if let Some(ref cavi_path) = self.options.emit_interface {
+            let mut summary = interface::InterfaceSummary::from_registry(analyzer.get_type_registry());
+            summary.layouts = summary.classes.iter()
+                .filter_map(|class| ir_gen.export_class_layout(&class.name))
+                .collect();
+            summary.write(cavi_path)?;
+        }
+
+        Ok(ir)
+    }
+
+    /// 生成独立的运行时模块
+    /// 
+    /// 运行时代码内置于编译器中，与 `external_runtime` 选项配合使用。
+    /// 
+    /// # Arguments
+    /// * `output_path` - 输出 LLVM IR 文件路径
+    pub fn compile_runtime(&self, output_path: &str) -> cayResult<()> {
+        let mut ir_gen = codegen::IRGenerator::new();
+        ir_gen.set_platform_config(&self.options);
+        let ir = ir_gen.generate_runtime_module();
+        write_output_atomically(output_path, &ir)
+    }
+
+    /// 预处理并解析源文件（同时加载 import 的模块），不做语义分析
+    ///
+    /// 用于 `--emit=ast`：语义错误不影响输出，便于检查解析结果。
+    pub fn parse_file(&self, input_path: &str) -> cayResult<ast::Program> {
+        let source = source::read_source_file(input_path)?;
+        let base_dir = Path::new(input_path)
+            .parent()
+            .map(|p| p.to_path_buf())
+            .unwrap_or_else(|| PathBuf::from("."));
+        let preprocessed = preprocessor::preprocess(&source, input_path, base_dir)?;
+        let tokens = lexer::lex(&preprocessed)?;
+        modules::ModuleLoader::new(Some(input_path)).parse_program(tokens)
     }
 
     /// 从文件编译，自动执行预处理
@@ -110,10 +264,7 @@ impl Compiler {
     /// 编译成功返回 Ok(())
     pub fn compile_file(&self, input_path: &str, output_path: &str) -> cayResult<()> {
         // 读取源文件
-        let source = std::fs::read_to_string(input_path)
-            .map_err(|e| error::cayError::Io(
-                format!("无法读取源文件 '{}': {}", input_path, e)
-            ))?;
+        let source = source::read_source_file(input_path)?;
         
         // 获取基础目录（用于解析相对路径的 #include）
         let base_dir = Path::new(input_path)
@@ -122,11 +273,55 @@ impl Compiler {
             .unwrap_or_else(|| PathBuf::from("."));
         
         // 预处理
+        ice::enter_phase("预处理");
         let preprocessed = preprocessor::preprocess(&source, input_path, base_dir)?;
         
+        // 输出接口文件是编译的副作用，无法从缓存重现，此时不使用缓存
+        let cache = self.options.cache_dir.as_deref()
+            .filter(|_| self.options.emit_interface.is_none())
+            .map(cache::CompilationCache::new);
+        let Some(cache) = cache else {
+            let ir = self.compile_module_to_ir(&preprocessed, Some(input_path))?;
+            return write_output_atomically(output_path, &ir);
+        };
+
+        let key = cache::CompilationCache::key(&preprocessed, &self.options)?;
+        if let Some(ir) = cache.lookup(&key) {
+            return write_output_atomically(output_path, &ir);
+        }
+
         // 编译预处理后的代码
-        self.compile(&preprocessed, output_path)
+        let mut loader = modules::ModuleLoader::new(Some(input_path));
+        let ir = self.compile_with_loader(&preprocessed, &mut loader)?;
+        // 缓存写入失败不影响本次编译
+        let _ = cache.store(&key, &ir, loader.dependencies());
+        write_output_atomically(output_path, &ir)
+    }
+}
+
+/// 原子地写入输出文件
+///
+/// 先写入同目录下的临时文件，成功后再重命名为目标文件，
+/// 避免代码生成中途失败或写入中断时留下被截断的 .ll 文件。
+/// 任何失败都会清理临时文件。
+fn write_output_atomically(output_path: &str, contents: &str) -> cayResult<()> {
+    let temp_path = format!("{}.partial", output_path);
+
+    if let Err(e) = std::fs::write(&temp_path, contents) {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(error::cayError::Io(
+            format!("无法写入输出文件 '{}': {}", temp_path, e)
+        ));
+    }
+
+    if let Err(e) = std::fs::rename(&temp_path, output_path) {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(error::cayError::Io(
+            format!("无法创建输出文件 '{}': {}", output_path, e)
+        ));
     }
+
+    Ok(())
 }
 
 impl Default for Compiler {
@@ -179,6 +374,26 @@ public class Test {
         assert!(preprocessed.contains("int x = 1;"));
     }
 
+    #[test]
+    fn test_preprocessor_include_statement() {
+        let dir = std::env::temp_dir().join(format!("cay_include_stmt_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("common.cay"), "class Common { }").unwrap();
+        std::fs::write(dir.join("loop.cay"), "include \"loop.cay\";").unwrap();
+        let main = dir.join("main.cay");
+        let main_path = main.to_string_lossy().to_string();
+
+        // 重复包含只展开一次；名为 include 的变量不是包含语句
+        let source = "include \"common.cay\";\ninclude \"common.cay\"\n#include \"common.cay\"\nint include = 1;";
+        let preprocessed = preprocessor::preprocess(source, &main_path, &dir).unwrap();
+        assert_eq!(preprocessed.matches("class Common").count(), 1, "{}", preprocessed);
+        assert!(preprocessed.contains("int include = 1;"));
+
+        let err = preprocessor::preprocess("include \"loop.cay\";", &main_path, &dir).unwrap_err();
+        assert!(err.to_string().contains("循环包含"), "{}", err);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn test_preprocessor_ifdef() {
         let source = r#"
@@ -191,4 +406,2398 @@ public class DebugClass {
         let preprocessed = preprocessor::preprocess(source, "test.cay", ".").unwrap();
         assert!(preprocessed.contains("DebugClass"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_node_ids_deterministic() {
+        let source = r#"public class Test {
+    public static void main() {
+        int x = 1 + 2 * 3;
+        if (x > 3) {
+            println(x);
+        }
+    }
+}"#;
+        let first = parser::parse(lexer::lex(source).unwrap()).unwrap();
+        let second = parser::parse(lexer::lex(source).unwrap()).unwrap();
+        assert_eq!(format!("{:?}", first), format!("{:?}", second));
+
+        // 同一次解析中的 ID 互不相同
+        let method_id = match &first.classes[0].members[0] {
+            ast::ClassMember::Method(m) => m.id,
+            _ => panic!("expected method"),
+        };
+        assert_ne!(method_id, first.classes[0].id);
+        assert_ne!(method_id, ast::NodeId::DUMMY);
+    }
+
+    #[test]
+    fn test_interface_roundtrip() {
+        let source = r#"public interface Shape {
+    int area();
+}
+public class MathUtil {
+    public static int add(int a, int b) {
+        return a + b;
+    }
+    private static int hidden() {
+        return 1;
+    }
+}"#;
+        let ast = parser::parse(lexer::lex(source).unwrap()).unwrap();
+        let mut analyzer = semantic::SemanticAnalyzer::new();
+        analyzer.analyze(&ast).unwrap();
+
+        let summary = interface::InterfaceSummary::from_registry(analyzer.get_type_registry());
+        let bytes = summary.to_bytes().unwrap();
+        let loaded = interface::InterfaceSummary::from_bytes(&bytes).unwrap();
+
+        let class = &loaded.classes[0];
+        assert_eq!(class.name, "MathUtil");
+        assert!(class.methods.contains_key("add"));
+        assert!(!class.methods.contains_key("hidden"));
+        assert_eq!(loaded.interfaces.iter().map(|i| i.name.as_str()).collect::<Vec<_>>(), ["Shape"]);
+
+        // 导入后可以在另一个编译单元中使用
+        let mut dependent = semantic::SemanticAnalyzer::new();
+        dependent.import_interface(&loaded).unwrap();
+        let app = r#"public interface Named {
+    String name();
+}
+public class App {
+    public static void main() {
+        int x = MathUtil.add(1, 2);
+    }
+}"#;
+        let app_ast = parser::parse(lexer::lex(app).unwrap()).unwrap();
+        dependent.analyze(&app_ast).unwrap();
+
+        // 导入的类和接口不会从依赖方的接口文件中再次导出
+        let reexported = interface::InterfaceSummary::from_registry(dependent.get_type_registry());
+        assert_eq!(reexported.classes.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), ["App"]);
+        assert_eq!(reexported.interfaces.iter().map(|i| i.name.as_str()).collect::<Vec<_>>(), ["Named"]);
+    }
+
+    #[test]
+    fn test_interface_layouts() {
+        let dir = std::env::temp_dir().join(format!("cay_cavi_layout_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cavi = dir.join("util.cavi").to_string_lossy().into_owned();
+        let library = "public class Base { public int a; } \
+                       public class Util extends Base { private long secret; public String label; public Util() { } }";
+        let lib_ir = Compiler::with_options(CompilerOptions { no_main: true, emit_interface: Some(cavi.clone()), ..Default::default() })
+            .compile_to_ir(library).unwrap();
+        assert!(lib_ir.contains("%class.Util = type { i32, i8**, i32, i64, i8* }"), "{}", lib_ir);
+
+        // private 字段不出现在类签名中，但仍然占据布局中的位置
+        let summary = interface::InterfaceSummary::read(&cavi).unwrap();
+        let util = summary.layouts.iter().find(|layout| layout.class_name == "Util").unwrap();
+        assert_eq!(util.parent.as_deref(), Some("Base"));
+        let names: Vec<&str> = util.fields.iter().map(|field| field.name.as_str()).collect();
+        assert_eq!(names, ["secret", "label"]);
+        assert!(!summary.classes.iter().any(|class| class.fields.contains_key("secret")));
+
+        // 依赖方使用相同的结构体类型和对象大小，可以直接读写导入的类的字段
+        let app = "public class App { public static void main() { Util u = new Util(); u.a = 5; println(u.a + 1); println(u.label); } }";
+        let app_ir = Compiler::with_options(CompilerOptions { interface_imports: vec![cavi.clone()], ..Default::default() })
+            .compile_to_ir(app).unwrap();
+        assert!(app_ir.contains("%class.Util = type { i32, i8**, i32, i64, i8* }"), "{}", app_ir);
+        assert!(app_ir.contains("call i8* @calloc(i64 1, i64 32)") || app_ir.contains("alloca i8, i64 32"), "{}", app_ir);
+        assert!(app_ir.contains("getelementptr inbounds %class.Util, %class.Util* ") && app_ir.contains(", i32 0, i32 4"), "{}", app_ir);
+
+        // 父类的布局不在导入的接口文件中时无法确定对象布局
+        let mut orphan = summary.clone();
+        orphan.layouts.retain(|layout| layout.class_name != "Base");
+        let mut generator = codegen::IRGenerator::new();
+        let err = generator.import_class_layouts(&orphan.layouts).unwrap_err();
+        assert!(err.to_string().contains("Imported class 'Util' extends 'Base'"), "{}", err);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_reduce_keeps_failing_statement() {
+        let source = r#"public class Main {
+    public static void helper() {
+        int a = 1;
+    }
+    public static void main() {
+        int x = 1;
+        int y = 2;
+        String s = 3;
+        int z = x + y;
+    }
+}"#;
+        let (reduced, stats) = reduce::reduce_source(source, |candidate| {
+            reduce::pipeline_error(candidate, "test.cay").is_some_and(|e| e.contains("Cannot assign int to string"))
+        });
+        assert!(reduced.contains("String s = 3;"));
+        assert!(!reduced.contains("helper"));
+        assert!(!reduced.contains("int y = 2;"));
+        assert!(stats.reduced_lines < stats.original_lines);
+        assert_eq!(
+            reduce::error_signature("语义错误 [0:0]: Cannot assign int to string at line 8"),
+            "Cannot assign int to string"
+        );
+    }
+
+    #[test]
+    fn test_source_bom_and_line_endings() {
+        let decoded = source::decode_source(b"\xEF\xBB\xBFint a;\r\nint b;\rint c;", "t.cay").unwrap();
+        assert_eq!(decoded, "int a;\nint b;\nint c;");
+
+        let utf16: Vec<u8> = [0xFF, 0xFE].into_iter()
+            .chain("int x;".encode_utf16().flat_map(|u| u.to_le_bytes()))
+            .collect();
+        assert_eq!(source::decode_source(&utf16, "t.cay").unwrap(), "int x;");
+
+        let err = source::decode_source(b"int a;\nint \xFF;", "t.cay").unwrap_err();
+        assert!(matches!(err, error::cayError::Lexer { line: 2, column: 5, .. }));
+
+        // 列号不受跳过的空白和 CRLF 影响
+        let tokens = lexer::lex("\u{FEFF}class A {\r\n    int x;\r\n}").unwrap();
+        assert_eq!((tokens[0].loc.line, tokens[0].loc.column), (1, 1));
+        assert_eq!((tokens[3].loc.line, tokens[3].loc.column), (2, 5));
+
+        let file = source::SourceFile::new("t.cay", "a\r\nbc\r\n");
+        assert_eq!(file.line_text(2), Some("bc"));
+        assert_eq!(file.location(3).line, 2);
+    }
+
+    #[test]
+    fn test_semantic_reports_multiple_errors_with_locations() {
+        let source = r#"public class Main {
+    public static void main() {
+        int a = "x";
+        int b = missing + 1;
+        String s = 5;
+    }
+}"#;
+        let ast = parser::parse(lexer::lex(source).unwrap()).unwrap();
+        let mut analyzer = semantic::SemanticAnalyzer::new();
+        let err = analyzer.analyze(&ast).unwrap_err();
+        let error::cayError::Multiple(errors) = err else {
+            panic!("expected multiple errors, got: {}", err);
+        };
+        let lines: Vec<usize> = errors.iter().map(|e| e.location().unwrap().0).collect();
+        assert_eq!(lines, vec![3, 4, 5]);
+        assert_eq!(analyzer.diagnostics().len(), 3);
+    }
+
+    #[test]
+    fn test_semantic_errors_locate_unlocated_expressions() {
+        // 字面量和标识符没有位置：返回值错误落在 return 语句上，
+        // 未定义变量和 super 错误落在最内层带位置的外层表达式上
+        let source = r#"public class Main {
+    public static int name() {
+        return "x";
+    }
+    public static void main() {
+        int total = 0;
+        total +=
+            1 + missing;
+    }
+    public void show() {
+        int h = 1 +
+            super.hashCode();
+    }
+}"#;
+        let ast = parser::parse(lexer::lex(source).unwrap()).unwrap();
+        let err = semantic::SemanticAnalyzer::new().analyze(&ast).unwrap_err();
+        let error::cayError::Multiple(errors) = err else {
+            panic!("expected multiple errors, got: {}", err);
+        };
+        let located: Vec<(usize, usize)> = errors.iter().map(|e| e.location().unwrap()).collect();
+        // `return` 关键字、`1 + missing` 的运算符、`super.hashCode()` 的调用括号
+        assert_eq!(located, vec![(3, 9), (8, 15), (12, 27)], "{:?}", errors);
+        assert!(errors[1].to_string().contains("Undefined variable: missing"), "{}", errors[1]);
+        assert!(errors[2].to_string().contains("has no superclass"), "{}", errors[2]);
+    }
+
+    #[test]
+    fn test_var_type_inference_recorded() {
+        let source = r#"public class Main {
+    public static String name() { return "x"; }
+    public static void main() {
+        var s = name();
+        let d = {1, 2.5};
+    }
+}"#;
+        let ast = parser::parse(lexer::lex(source).unwrap()).unwrap();
+        let mut analyzer = semantic::SemanticAnalyzer::new();
+        analyzer.analyze(&ast).unwrap();
+        let mut inferred: Vec<types::Type> = analyzer.inferred_var_types().values().cloned().collect();
+        inferred.sort_by_key(|t| t.to_string());
+        assert_eq!(inferred, vec![
+            types::Type::Array(Box::new(types::Type::Float64)),
+            types::Type::String,
+        ]);
+    }
+
+    #[test]
+    fn test_null_safety_operators() {
+        // `?.5` 后接数字时仍是三元运算符加浮点字面量
+        let tokens = lexer::lex("b ?.5 : 1.0").unwrap();
+        assert_eq!(tokens[1].token, lexer::Token::Question);
+        assert_eq!(tokens[2].token, lexer::Token::FloatLiteral(Some((0.5, None))));
+        let tokens = lexer::lex("a?.b ?? c ?: d").unwrap();
+        assert_eq!(tokens[1].token, lexer::Token::QuestionDot);
+        assert_eq!(tokens[3].token, lexer::Token::QuestionQuestion);
+        assert_eq!(tokens[5].token, lexer::Token::Elvis);
+
+        let analyze = |body: &str| {
+            let source = format!("public class Main {{ public static void main() {{ {} }} }}", body);
+            let ast = parser::parse(lexer::lex(&source).unwrap()).unwrap();
+            semantic::SemanticAnalyzer::new().analyze(&ast).map_err(|e| e.to_string())
+        };
+        assert!(analyze("String s = null; String t = s ?? \"x\"; int n = s?.length() ?? 0;").is_ok());
+        assert!(analyze("int x = 1; int y = x ?? 0;").unwrap_err().contains("non-nullable"));
+        assert!(analyze("String s = null; int n = s?.length();").unwrap_err().contains("cannot be null"));
+    }
+
+    /// 把表达式 AST 打印为带括号的前缀形式，便于比较结合方式
+    fn sexpr(expr: &ast::Expr) -> String {
+        use ast::*;
+        match expr {
+            Expr::Literal(LiteralValue::Int32(v)) => v.to_string(),
+            Expr::Literal(LiteralValue::Bool(v)) => v.to_string(),
+            Expr::Literal(LiteralValue::Null) => "null".to_string(),
+            Expr::Literal(lit) => format!("{:?}", lit),
+            Expr::Identifier(name) => name.clone(),
+            Expr::Binary(b) => format!("({} {} {})", b.op.symbol(), sexpr(&b.left), sexpr(&b.right)),
+            Expr::Unary(u) => {
+                let op = match u.op {
+                    UnaryOp::Neg => "neg",
+                    UnaryOp::Not => "!",
+                    UnaryOp::BitNot => "~",
+                    UnaryOp::PreInc => "++pre",
+                    UnaryOp::PreDec => "--pre",
+                    UnaryOp::PostInc => "post++",
+                    UnaryOp::PostDec => "post--",
+                };
+                format!("({} {})", op, sexpr(&u.operand))
+            }
+            Expr::Assignment(a) => {
+                let op = match a.op {
+                    AssignOp::Assign => "=",
+                    AssignOp::AddAssign => "+=",
+                    AssignOp::SubAssign => "-=",
+                    AssignOp::MulAssign => "*=",
+                    AssignOp::DivAssign => "/=",
+                    AssignOp::ModAssign => "%=",
+                };
+                format!("({} {} {})", op, sexpr(&a.target), sexpr(&a.value))
+            }
+            Expr::Ternary(t) => format!("(? {} {} {})", sexpr(&t.condition), sexpr(&t.true_branch), sexpr(&t.false_branch)),
+            Expr::NullCoalesce(n) => format!("(?? {} {})", sexpr(&n.value), sexpr(&n.default)),
+            Expr::MemberAccess(m) => format!("({} {} {})", if m.null_safe { "?." } else { "." }, sexpr(&m.object), m.member),
+            Expr::Call(c) => {
+                let args: Vec<String> = c.args.iter().map(sexpr).collect();
+                format!("(call {}{})", sexpr(&c.callee), args.iter().map(|a| format!(" {}", a)).collect::<String>())
+            }
+            Expr::ArrayAccess(a) => format!("([] {} {})", sexpr(&a.array), sexpr(&a.index)),
+            Expr::Cast(c) => format!("(cast {} {})", c.target_type, sexpr(&c.expr)),
+            Expr::InstanceOf(i) => format!("(instanceof {} {})", sexpr(&i.expr), i.target_type),
+            other => format!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_operator_precedence() {
+        let cases = [
+            // 算术
+            ("a + b * c", "(+ a (* b c))"),
+            ("a - b - c", "(- (- a b) c)"),
+            ("a / b % c", "(% (/ a b) c)"),
+            ("(a + b) * c", "(* (+ a b) c)"),
+            ("-a * b", "(* (neg a) b)"),
+            // 移位与比较、算术
+            ("1 + 2 << 3", "(<< (+ 1 2) 3)"),
+            ("a << 1 < b", "(< (<< a 1) b)"),
+            ("a < b << 1", "(< a (<< b 1))"),
+            ("a >>> 2 + 1", "(>>> a (+ 2 1))"),
+            ("a >> b >> c", "(>> (>> a b) c)"),
+            // 比较和相等
+            ("a < b < c", "(< (< a b) c)"),
+            ("a == b < c", "(== a (< b c))"),
+            ("a != b == c", "(== (!= a b) c)"),
+            ("a instanceof Foo == b", "(== (instanceof a Foo) b)"),
+            // 位运算与相等、逻辑运算
+            ("a & b == c", "(& a (== b c))"),
+            ("a | b ^ c & d", "(| a (^ b (& c d)))"),
+            ("a || b && c", "(|| a (&& b c))"),
+            ("!a && b", "(&& (! a) b)"),
+            ("a && b | c", "(&& a (| b c))"),
+            // 三元、空值合并和赋值
+            ("c ? x : y ? p : q", "(? c x (? y p q))"),
+            ("a || b ? x : y", "(? (|| a b) x y)"),
+            ("a = b ? c : d", "(= a (? b c d))"),
+            ("a = b = c", "(= a (= b c))"),
+            ("a += b * 2", "(+= a (* b 2))"),
+            ("a ?? b ?? c", "(?? a (?? b c))"),
+            ("a ?? b || c", "(?? a (|| b c))"),
+            ("c ? a ?? b : d", "(? c (?? a b) d)"),
+            ("a ?? b ? x : y", "(? (?? a b) x y)"),
+            // 后缀和一元
+            ("a++ + ++b", "(+ (post++ a) (++pre b))"),
+            ("-a[i]", "(neg ([] a i))"),
+            ("a.b.c(1)[2]", "([] (call (. (. a b) c) 1) 2)"),
+            ("a?.b.c", "(. (?. a b) c)"),
+            ("(int) a + b", "(+ (cast int a) b)"),
+            // 括号与类型转换：括号内是类型且后面是操作数时才是转换
+            ("((a))", "a"),
+            ("(a) + b", "(+ a b)"),
+            ("(Foo) a", "(cast Foo a)"),
+            ("(int) -a", "(cast int (neg a))"),
+            ("(int[]) (a)", "(cast int[] a)"),
+            ("((a) * (b))", "(* a b)"),
+            ("~a & b", "(& (~ a) b)"),
+        ];
+
+        for (source, expected) in cases {
+            let expr = parser::parse_expression(lexer::lex(source).unwrap())
+                .unwrap_or_else(|e| panic!("failed to parse '{}': {}", source, e));
+            assert_eq!(sexpr(&expr), expected, "precedence mismatch for '{}'", source);
+        }
+
+        assert!(parser::parse_expression(lexer::lex("a + b c").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_chained_comparison_diagnostic() {
+        let analyze = |body: &str| {
+            let source = format!("public class Main {{ public static void main() {{ int a = 1; int b = 2; int c = 3; boolean f = true; {} }} }}", body);
+            let ast = parser::parse(lexer::lex(&source).unwrap()).unwrap();
+            semantic::SemanticAnalyzer::new().analyze(&ast).map_err(|e| e.to_string())
+        };
+
+        let err = analyze("boolean r = a < b < c;").unwrap_err();
+        assert!(err.contains("Chained comparison") && err.contains("a < b && b < c"), "got: {}", err);
+        let err = analyze("boolean r = a == b == c;").unwrap_err();
+        assert!(err.contains("Chained comparison"), "got: {}", err);
+        let err = analyze("boolean r = f == 1;").unwrap_err();
+        assert!(err.contains("cannot be applied to bool and int"), "got: {}", err);
+        let err = analyze("boolean r = f < f;").unwrap_err();
+        assert!(err.contains("cannot be applied"), "got: {}", err);
+
+        assert!(analyze("boolean r = a < b && b < c; boolean s = (a < b) == f; boolean t = f != true;").is_ok());
+    }
+    #[test]
+    fn test_escape_analysis() {
+        let stack_allocated = |body: &str| {
+            let source = format!(
+                "public class P {{ public int x; public P next; \
+                   public int get() {{ return this.x; }} \
+                   public P self() {{ return this; }} \
+                   public int indirect() {{ return get(); }} }} \
+                 public class Main {{ public static int use(P p) {{ return 0; }} \
+                   public static P test() {{ {} return null; }} }}",
+                body
+            );
+            let ast = parser::parse(lexer::lex(&source).unwrap()).unwrap();
+            codegen::escape::analyze(&ast).len()
+        };
+
+        // 只读写字段、调用不泄漏 this 的方法、与 null 比较：不逃逸
+        assert_eq!(stack_allocated("P p = new P(); p.x = 1; int a = p.x + p.get() + p.indirect(); boolean b = p == null;"), 1);
+        assert_eq!(stack_allocated("for (int i = 0; i < 3; i++) { P p = new P(); p.x = i; }"), 1);
+        // 返回、传参、赋给其他变量或字段、调用泄漏 this 的方法、被 lambda 捕获：逃逸
+        assert_eq!(stack_allocated("P p = new P(); return p;"), 0);
+        assert_eq!(stack_allocated("P p = new P(); use(p);"), 0);
+        assert_eq!(stack_allocated("P p = new P(); P q = p;"), 0);
+        assert_eq!(stack_allocated("P p = new P(); P q = new P(); q.next = p;"), 1);
+        assert_eq!(stack_allocated("P p = new P(); P q = p.self();"), 0);
+        assert_eq!(stack_allocated("P p = new P(); var f = () -> { return p.x; };"), 0);
+        // 同名变量重复声明时保守处理
+        assert_eq!(stack_allocated("{ P p = new P(); } { P p = new P(); use(p); }"), 0);
+    }
+    #[test]
+    fn test_stack_guard_option() {
+        let source = "public class Main { public static int f(int n) { return f(n + 1); } public static void main() { f(0); } }";
+        let guarded = Compiler::with_options(CompilerOptions {
+            stack_guard_depth: Some(500),
+            ..Default::default()
+        }).compile_to_ir(source).unwrap();
+        assert!(guarded.contains("@.cay_call_depth = internal global i32 0"));
+        assert!(guarded.contains("icmp sgt i32") && guarded.contains(", 500"));
+        assert!(guarded.contains("StackOverflow in method Main.f (call depth exceeded 500)"));
+
+        let plain = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(!plain.contains("cay_call_depth"));
+
+        assert_eq!(toolchain::parse_stack_size("8M"), Ok(8 * 1024 * 1024));
+        assert_eq!(toolchain::parse_stack_size("4096"), Ok(4096));
+        assert!(toolchain::parse_stack_size("0").is_err());
+        assert!(toolchain::parse_stack_size("big").is_err());
+        assert_eq!(toolchain::stack_size_link_flag("x86_64-w64-mingw32", 1024), "-Wl,--stack,1024");
+    }
+    #[test]
+    fn test_stack_usage_estimate() {
+        let source = "public class Main { \
+                      public static long fact(long n) { if (n <= 1) { return 1; } return n * fact(n - 1); } \
+                      public static int leaf(int a) { long x = 5; return a + 1; } \
+                      public static int mid(int a) { return leaf(a) * 2; } \
+                      public static void main() { println(mid(3)); println(fact(5)); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        let report = stack_usage::analyze_ir(&ir);
+
+        // 递归环被单独报告
+        assert!(report.recursive_cycles.iter().any(|c| c.iter().any(|f| f.contains("fact"))), "{:?}", report.recursive_cycles);
+        let main = report.function("Main.main").unwrap();
+        assert!(main.reaches_recursion);
+
+        // 非递归路径：main -> mid -> leaf，最坏深度为路径上栈帧之和
+        let mid = report.functions.iter().find(|f| f.name.contains("mid")).unwrap();
+        let leaf = report.functions.iter().find(|f| f.name.contains("leaf")).unwrap();
+        assert!(!mid.reaches_recursion && leaf.frame_size > 0);
+        assert_eq!(mid.worst_case, mid.frame_size + leaf.worst_case);
+        assert_eq!(mid.worst_path.last(), Some(&leaf.name));
+        assert!(main.worst_case >= mid.worst_case + main.frame_size);
+    }
+    #[test]
+    fn test_format_string_gep_reuse() {
+        let source = "public class Main { public static void main() { \
+                      int i = 0; while (i < 3) { println(\"x\"); println(i); i = i + 1; } \
+                      if (i > 1) { println(\"x\"); } println(i); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        let body: Vec<&str> = ir.lines()
+            .skip_while(|l| !l.starts_with("define void @Main.main"))
+            .take_while(|l| *l != "}")
+            .collect();
+        let geps: Vec<&str> = body.iter().copied().filter(|l| l.contains("getelementptr [")).collect();
+        // "x"、"%s\n"、整数格式各计算一次，且都位于入口块
+        assert_eq!(geps.len(), 3, "{}", body.join("\n"));
+        let entry = body.iter().position(|l| l.trim() == "entry:").unwrap();
+        assert!(body[entry + 1..entry + 4].iter().all(|l| l.contains("getelementptr [")));
+    }
+    #[test]
+    fn test_interface_itable_dispatch() {
+        let source = "public interface Shape { double area(); String name(); } \
+                      public class Square implements Shape { public double side; \
+                        public double area() { return side * side; } public String name() { return \"sq\"; } } \
+                      public class Main { public static void main() { Shape s = new Square(); println(s.area()); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        // itable 槽位按方法名排序，并按 type_id 登记到接口的表中
+        assert!(ir.contains("@Square.itable.Shape = internal constant [2 x i8*] [i8* bitcast (double (i8*)* @Square.area to i8*), i8* bitcast (i8* (i8*)* @Square.name to i8*)]"), "{}", ir);
+        assert!(ir.contains("@Shape.itables = internal constant [3 x i8**]"), "{}", ir);
+        assert!(ir.contains("getelementptr inbounds [3 x i8**], [3 x i8**]* @Shape.itables, i64 0, i32 %t"));
+
+        let missing = "public interface Shape { double area(); } \
+                       public class Square implements Shape { } \
+                       public class Main { public static void main() { } }";
+        let err = Compiler::new().compile_to_ir(missing).unwrap_err().to_string();
+        assert!(err.contains("Class 'Square' must implement method 'double area()' from interface 'Shape'"), "{}", err);
+
+        let abstract_new = "public abstract class A { public abstract int f(); } \
+                            public class Main { public static void main() { A a = new A(); } }";
+        let err = Compiler::new().compile_to_ir(abstract_new).unwrap_err().to_string();
+        assert!(err.contains("Cannot instantiate abstract class 'A'"), "{}", err);
+    }
+    #[test]
+    fn test_emit_helpers_alignment() {
+        let source = "public class Main { public static void main() { \
+                        long a = 5; double d = 1.5; a = a + 1; d = d * 2.0; if (a > 3) { println(a); } } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        // load/store/alloca 统一带上按类型计算的对齐
+        assert!(ir.contains("alloca i64, align 8"), "{}", ir);
+        assert!(ir.contains("store double 1.5, double* %d_s1, align 8"), "{}", ir);
+        assert!(ir.contains("= load i64, i64* %a_s1, align 8"), "{}", ir);
+        assert!(ir.contains("br i1 %t"), "{}", ir);
+    }
+    #[test]
+    fn test_target_datalayout() {
+        use target::DataLayout;
+        let x86_64 = DataLayout::for_triple("x86_64-unknown-linux-gnu");
+        assert_eq!((x86_64.pointer_size(), x86_64.align_of("i8*"), x86_64.align_of("i64")), (8, 8, 8));
+        assert_eq!(x86_64.align_of("i1"), 1);
+        assert_eq!(x86_64.size_of("[3 x i32]"), 12);
+
+        // 32 位 ARM：指针 4 字节，i64/double 仍按 8 字节对齐
+        let arm = DataLayout::for_triple("armv7-unknown-linux-gnueabihf");
+        assert_eq!((arm.pointer_size(), arm.align_of("i8**"), arm.align_of("i64"), arm.align_of("double")), (4, 4, 8, 8));
+        // i386：i64 和 double 只按 4 字节对齐
+        let i386 = DataLayout::for_triple("i686-unknown-linux-gnu");
+        assert_eq!((i386.align_of("i64"), i386.align_of("double"), i386.size_of("i64")), (4, 4, 8));
+
+        let ir = Compiler::new().compile_to_ir("public class Main { public static void main() { } }").unwrap();
+        assert!(ir.contains("target datalayout = \""), "{}", ir);
+    }
+    #[test]
+    fn test_type_size_and_llvm_mapping() {
+        use target::DataLayout;
+        use types::Type;
+        let x86_64 = DataLayout::for_triple("x86_64-w64-mingw32");
+        let arm = DataLayout::for_triple("armv7-unknown-linux-gnueabihf");
+        assert_eq!(Type::Char.llvm_of(), "i32");
+        assert_eq!(Type::Char.size_of(&x86_64), 4);
+        assert_eq!(Type::Array(Box::new(Type::Float64)).llvm_of(), "double*");
+        assert_eq!(Type::String.size_of(&x86_64), 8);
+        assert_eq!(Type::String.size_of(&arm), 4);
+        assert_eq!(Type::Int64.align_of(&arm), 8);
+
+        // 可变参数按声明的元素类型打包（8 字节长度头 + 元素）
+        let source = "public class Main { public static int sum(int... xs) { return xs[0]; } \
+                      public static void main() { println(sum(1, 2, 3)); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(ir.contains("@calloc(i64 1, i64 20)"), "{}", ir);
+        assert!(ir.contains("getelementptr inbounds i32, i32* %t"), "{}", ir);
+    }
+    #[test]
+    fn test_float32_literals() {
+        let source = "public class Main { public static void main() { float x = 1.5f + 2.5f; float y = x * 1.1f; } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        let main_start = ir.find("define void @Main.main").unwrap();
+        let main_body = &ir[main_start..main_start + ir[main_start..].find("\n}").unwrap()];
+        // float 字面量直接以 float 常量参与运算，不经过 double
+        assert!(main_body.contains("fadd float 1.5, 2.5"), "{}", main_body);
+        assert!(main_body.contains("fmul float %t1, 1.100000023841858"), "{}", main_body);
+        assert!(!main_body.contains("double") && !main_body.contains("fptrunc"), "{}", main_body);
+    }
+    #[test]
+    fn test_string_equality_by_content() {
+        let source = "public class Main { public static void main() { \
+                        String a = \"abc\"; String b = a + \"\"; Main m = null; \
+                        if (a == b) { println(a != b); } if (m == null) { } } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        // 字符串比较调用运行时按内容比较，对象与 null 比较仍然比较指针
+        assert_eq!(ir.matches("call i1 @__cay_string_equals").count(), 2, "{}", ir);
+        assert!(ir.contains("xor i1 %t"), "{}", ir);
+        assert!(ir.contains("icmp eq i8* %t"), "{}", ir);
+    }
+    #[test]
+    fn test_char_bool_array_printing() {
+        let source = "public class Main { public static void main() { \
+                        char[] s = {'h', 'i'}; boolean[] b = new boolean[2]; String t = \"x\"; \
+                        println(s); print(b); println(t); int n = readChars(s); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        // char[] 的码点按 UTF-8 编码为字符串后打印
+        assert!(ir.contains("call i8* @__cay_char_array_to_string(i32* %t"), "{}", ir);
+        assert!(ir.contains("call void @__cay_print_bool_array(i1* %t"), "{}", ir);
+        assert_eq!(ir.matches("call void @__cay_print_").count(), 1, "{}", ir);
+        assert!(ir.contains("call i32 @__cay_read_chars(i32* %t"), "{}", ir);
+        // boolean[] 元素按 1 字节访问
+        assert!(ir.contains("getelementptr inbounds i1, i1* %arr"), "{}", ir);
+    }
+    #[test]
+    fn test_switch_long_case_labels() {
+        let source = "public class Main { public static void main() { \
+                        long v = 1L; int i = 2; \
+                        switch (v) { case 9223372036854775807L: break; case -9223372036854775808L: break; case - 1: break; } \
+                        switch (i) { case -2147483648: break; case 10L: break; } } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(ir.contains("i64 9223372036854775807, label"), "{}", ir);
+        assert!(ir.contains("i64 -9223372036854775808, label"), "{}", ir);
+        assert!(ir.contains("i64 -1, label"), "{}", ir);
+        // int 表达式符号扩展后与 i64 case 值比较
+        assert!(ir.contains("sext i32 %t"), "{}", ir);
+        assert!(ir.contains("i64 -2147483648, label") && ir.contains("i64 10, label"), "{}", ir);
+
+        let out_of_range = "public class Main { public static void main() { int i = 0; switch (i) { case 2147483648L: break; } } }";
+        assert!(Compiler::new().compile_to_ir(out_of_range).is_err());
+        let duplicate = "public class Main { public static void main() { long v = 0L; switch (v) { case 1: break; case 1L: break; } } }";
+        assert!(Compiler::new().compile_to_ir(duplicate).is_err());
+    }
+    #[test]
+    fn test_final_and_varargs_parameters() {
+        let source = "public class Box { public Box(final int base, int... extras) { } \
+                        public double avg(final String tag, double... xs) { return 0.0; } } \
+                      public class Main { public static void main() { Box b = new Box(1); b.avg(\"t\", 1.0, 2.5f); b.avg(\"t\"); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        // 可变参数数组按元素类型命名和传递，带长度头
+        assert!(ir.contains("define void @Box.__ctor$i$ai(i8* %this, i32 %Box.base_param, i32* %Box.extras_param)"), "{}", ir);
+        assert!(ir.contains("define double @Box.__avg$s$ad(i8* %this, i8* %Box.tag, double* %Box.xs)"), "{}", ir);
+        assert_eq!(ir.matches("call double @Box.__avg$s$ad(i8* %t").count(), 2, "{}", ir);
+        assert!(ir.contains("store i32 2, i32* %t"), "{}", ir);
+        assert!(ir.contains("store i32 0, i32* %t"), "{}", ir);
+
+        // 直接传入同类型数组作为可变参数
+        let pass_array = "public class Main { static long sum(long... xs) { return 0L; } \
+                          public static void main() { long[] v = {1L}; long r = sum(v); } }";
+        let ir = Compiler::new().compile_to_ir(pass_array).unwrap();
+        assert!(ir.contains("call i64 @Main.__sum$al(i64* %t"), "{}", ir);
+
+        let final_param = "public class Main { static void f(final int x) { x = 1; } public static void main() { f(0); } }";
+        assert!(Compiler::new().compile_to_ir(final_param).is_err());
+        let final_inc = "public class Main { static void f(final int x) { x++; } public static void main() { f(0); } }";
+        assert!(Compiler::new().compile_to_ir(final_inc).is_err());
+    }
+    #[test]
+    fn test_generic_monomorphization() {
+        let source = "class Box<T> { private T value; public void set(T v) { value = v; } public T get() { return value; } } \
+                      class Util { public static <T> T identity(T x) { return x; } } \
+                      public class Main { public static void main() { \
+                        Box<int> a = new Box<int>(); Box<Box<long>> b = new Box<>(); a.set(1); \
+                        int x = Util.identity(2); String s = Util.identity(\"s\"); \
+                        if (x < 3 && x >> 1 > 0) { println(s); } } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        // 每组类型实参生成一个实例类，嵌套实参中的 >> 被拆成两个 >
+        assert!(ir.contains("%class.Box$int = type"), "{}", ir);
+        assert!(ir.contains("%class.Box$long = type"), "{}", ir);
+        assert!(ir.contains("%class.Box$Box$long = type"), "{}", ir);
+        assert!(ir.contains("define void @Box$int.__set$i(i8* %this, i32 %Box$int.v)"), "{}", ir);
+        // 泛型方法按推导出的类型实参实例化
+        assert!(ir.contains("define i32 @Util.__identity$int$i(i32 %Util.x)"), "{}", ir);
+        assert!(ir.contains("define i8* @Util.__identity$String$s(i8* %Util.x)"), "{}", ir);
+
+        let raw = "class Box<T> { } public class Main { public static void main() { Box b = null; } }";
+        assert!(Compiler::new().compile_to_ir(raw).is_err());
+        let uninferred = "class U { static <T> T f() { return null; } } public class Main { public static void main() { U.f(); } }";
+        assert!(Compiler::new().compile_to_ir(uninferred).is_err());
+    }
+    #[test]
+    fn test_static_and_instance_call_forms() {
+        let source = "public class Counter { private int count; public static int twice(int x) { return x * 2; } \
+                        public int inc() { count = count + 1; return count; } } \
+                      public class Main { public static void main() { Counter c = new Counter(); \
+                        int a = c.twice(1); int b = Counter.twice(2); int d = c.inc(); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        // 通过对象调用静态方法时不传递 this
+        assert_eq!(ir.matches("call i32 @Counter.__twice$i(i32 ").count(), 2, "{}", ir);
+        assert!(ir.contains("call i32 @Counter.inc(i8* %"), "{}", ir);
+
+        let via_class = "public class Counter { public int inc() { return 1; } } \
+                         public class Main { public static void main() { int n = Counter.inc(); } }";
+        let err = Compiler::new().compile_to_ir(via_class).unwrap_err().to_string();
+        assert!(err.contains("non-static method inc() in class 'Counter'"), "{}", err);
+        let unqualified = "public class Main { public int helper() { return 1; } \
+                           public static void main() { println(helper()); } }";
+        let err = Compiler::new().compile_to_ir(unqualified).unwrap_err().to_string();
+        assert!(err.contains("non-static method helper()"), "{}", err);
+        // 局部变量与类同名时按变量处理
+        let shadowed = "public class Counter { public int inc() { return 1; } } \
+                        public class Main { public static void main() { Counter Counter = new Counter(); int n = Counter.inc(); } }";
+        assert!(Compiler::new().compile_to_ir(shadowed).is_ok());
+    }
+    #[test]
+    fn test_math_builtins() {
+        let source = "public class Main { public static void main() { double r = Math.sqrt(2); \
+                        int a = Math.abs(-3); long m = Math.min(1L, 2L); float f = Math.max(1.0f, 2.0f); double p = Math.PI; } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        // int 实参转换为 double 后调用内建函数
+        assert!(ir.contains("sitofp i32 "), "{}", ir);
+        assert!(ir.contains("call double @llvm.sqrt.f64(double %t"), "{}", ir);
+        // 整数 abs/min 使用比较加 select，不调用库函数
+        assert!(ir.contains("icmp slt i32 "), "{}", ir);
+        assert!(ir.contains("icmp slt i64 "), "{}", ir);
+        assert!(ir.contains("call float @llvm.maxnum.f32("), "{}", ir);
+        assert!(ir.contains("0x400921FB54442D18"), "{}", ir);
+
+        let user_math = "public class Math { public static int sqrt(int x) { return x + 1; } } \
+                         public class Main { public static void main() { int r = Math.sqrt(3); } }";
+        let ir = Compiler::new().compile_to_ir(user_math).unwrap();
+        assert!(ir.contains("call i32 @Math.__sqrt$i(i32 "), "{}", ir);
+        let err = Compiler::new().compile_to_ir("public class Main { public static void main() { Math m = new Math(); } }")
+            .unwrap_err().to_string();
+        assert!(err.contains("Cannot instantiate builtin class 'Math'"), "{}", err);
+    }
+    #[test]
+    fn test_large_string_literals() {
+        // 数 MB 的字面量和一行内的大量字面量都应在线性时间内完成编译
+        let big = "x".repeat(2_000_000);
+        let concat: Vec<String> = (0..50).map(|i| format!("\"part{}\\n\"", i)).collect();
+        let source = format!(
+            "public class Main {{ public static void main() {{ String s = \"{}\\\"q\\\\\"; String t = {}; }} }}",
+            big, concat.join(" + ")
+        );
+        let ir = Compiler::new().compile_to_ir(&source).unwrap();
+        assert!(ir.contains("constant [2000004 x i8] c\"xxxx"), "large literal should be emitted once");
+        assert!(ir.contains("x\\22q\\5C\\00\", align 1"), "special bytes should use hex escapes");
+        assert!(ir.contains("c\"part49\\0A\\00\""));
+
+        // 同一长行中靠后的 token 列号正确
+        let tokens = crate::lexer::lex(&source).unwrap();
+        let last = &tokens[tokens.len() - 4];
+        assert_eq!(last.loc.line, 1);
+        assert_eq!(last.loc.column, source.chars().count() - 4);
+    }
+    #[test]
+    fn test_parser_lookahead() {
+        // 括号中的 Lambda 由配对的 ')' 后是否为 '->' 判断，不再推测解析后回退
+        let source = "public class Main { public static void main() { var f = ((int x) -> x + 1); \
+                        var g = ((y) -> ((z) -> z)); int a = 1; int b = ((((a)))) + (a); } }";
+        assert!(parser::parse(lexer::lex(source).unwrap()).is_ok());
+
+        // 嵌套的括号和转换：每层只做常数次前瞻
+        let depth = 16;
+        let nested = format!("{}a{}", "(".repeat(depth), ")".repeat(depth));
+        let casts = "(long) (int) ".repeat(depth / 2);
+        let source = format!("public class Main {{ public static void main() {{ int a = 1; int x = {}; long y = {}a; }} }}", nested, casts);
+        assert!(parser::parse(lexer::lex(&source).unwrap()).is_ok());
+    }
+    #[test]
+    fn test_builtin_signatures() {
+        let check = |body: &str| {
+            let source = format!("public class Main {{ public static void main() {{ {} }} }}", body);
+            Compiler::new().compile_to_ir(&source).map(|_| ()).map_err(|e| e.to_string())
+        };
+        assert!(check("println(); print(1L); println('c'); long n = readInt(); char[] b = new char[4]; int k = readChars(b);").is_ok());
+        // 参数个数和类型错误都在语义分析阶段报告，并带有调用位置
+        let err = check("print(1, 2, 3);").unwrap_err();
+        assert!(err.contains("[1:") && err.contains("print() expects a String format string, got int"), "{}", err);
+        assert!(check("print();").unwrap_err().contains("print() expects at least 1 argument, got 0"));
+        assert!(check("float f = readFloat(1.0);").unwrap_err().contains("readFloat() takes no arguments, got 1"));
+        assert!(check("int[] a = new int[1]; print(a);").is_ok());
+        assert!(check("print(() -> 1);").unwrap_err().contains("print() cannot be applied"));
+        assert!(check("int[] a = new int[1]; int n = readChars(a);").unwrap_err().contains("readChars() expects a char[] argument, got int[]"));
+    }
+    #[test]
+    fn test_string_method_set() {
+        let source = "public class Main { public static void main() { \
+                        String s = \" a,b \"; String[] p = s.trim().toUpperCase().split(\",\"); \
+                        boolean x = s.startsWith(\"a\") || s.endsWith(\"b\") || s.contains(\",\"); \
+                        println(p.length); println(s.toLowerCase()); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        for call in ["@__cay_string_trim(", "@__cay_string_to_upper(", "@__cay_string_to_lower(",
+                     "i8** @__cay_string_split(", "i1 @__cay_string_starts_with(",
+                     "i1 @__cay_string_ends_with(", "i1 @__cay_string_contains("] {
+            assert!(ir.contains(&format!("call i8* {}", call)) || ir.contains(&format!("call {}", call)), "missing {}", call);
+        }
+        // 对象变量上的同名方法仍然调用用户类中的实现
+        let source = "public class Bag { public boolean contains(String x) { return true; } \
+                        public static void main() { Bag b = new Bag(); boolean y = b.contains(\"q\"); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(ir.contains("call i1 @Bag.__contains$s("), "{}", ir);
+        let err = Compiler::new().compile_to_ir("public class Main { public static void main() { String s = \"x\"; String t = s.trim(1); } }")
+            .unwrap_err().to_string();
+        assert!(err.contains("String.trim() takes no arguments"), "{}", err);
+    }
+    #[test]
+    fn test_lambda_closure_conversion() {
+        let source = "public class Main { public int f; \
+                        public void run(int k) { auto g = (x) -> x + k + f; } \
+                        public static void main() { int n = 1; double d = 2.0; auto h = (x) -> x + n; auto p = (int y) -> { double e = d; return y; }; auto c = (x) -> x; } }";
+        let mut analyzer = semantic::SemanticAnalyzer::new();
+        let program = generics::monomorphize(parser::parse(lexer::lex(source).unwrap()).unwrap()).unwrap();
+        analyzer.analyze(&program).unwrap();
+        // 捕获集合按首次引用的顺序记录，访问实例字段时捕获 this
+        let mut captures: Vec<Vec<String>> = analyzer.lambda_captures().values().cloned().collect();
+        captures.sort();
+        assert_eq!(captures, vec![vec![], vec!["d".to_string()], vec!["k".to_string(), "this".to_string()], vec!["n".to_string()]]);
+
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        // lambda 函数以环境指针作为隐藏的第一个参数
+        assert!(ir.contains("(i8* %env, i32 %param0)"), "{}", ir);
+        assert!(ir.contains("bitcast i8* %env to { i32, i8* }*"), "{}", ir);
+        assert!(ir.contains("bitcast i8* %env to { double }*"), "{}", ir);
+        // 环境在创建点分配，闭包对象保存函数指针和环境指针
+        assert!(ir.contains("getelementptr { i32 }, { i32 }* null, i32 1"), "{}", ir);
+        assert!(ir.contains("store i8* null, i8** "), "{}", ir);
+
+        let err = Compiler::new().compile_to_ir("public class Main { public static void main() { int n = 0; auto f = (x) -> x + n; n = 2; } }")
+            .unwrap_err().to_string();
+        assert!(err.contains("Local variable 'n' referenced from a lambda expression must be final or effectively final"), "{}", err);
+    }
+    #[test]
+    fn test_main_signatures() {
+        let ir = Compiler::new().compile_to_ir("public class Main { public static int main() { return 7; } }").unwrap();
+        assert!(ir.contains("%ret = call i32 @Main.main()") && ir.contains("ret i32 %ret"), "{}", ir);
+        let ir = Compiler::new().compile_to_ir("public class Main { public static void main() { } }").unwrap();
+        assert!(ir.contains("call void @Main.main()") && ir.contains("ret i32 0"), "{}", ir);
+        // C 入口接收 argc/argv，String[] 参数由 argv[1..] 构造
+        assert!(ir.contains("define i32 @main(i32 %argc, i8** %argv)"), "{}", ir);
+        let ir = Compiler::new().compile_to_ir("public class Main { public static void main(String[] args) { } }").unwrap();
+        assert!(ir.contains("%args.count = sub i32 %argc, 1") && ir.contains("%args.src = getelementptr i8*, i8** %argv, i64 1"), "{}", ir);
+        assert!(ir.contains("store i32 %args.len, i32* %args.header") && ir.contains("(i8** %args)"), "{}", ir);
+        let err = Compiler::new().compile_to_ir("public class Main { public static void main(int n) { } }").unwrap_err().to_string();
+        assert!(err.contains("Unsupported main signature 'public static void main(int)'"), "{}", err);
+        // 非 public static 的 main 只是普通方法
+        assert!(Compiler::new().compile_to_ir("public class Main { public static void main() { } public long main(int x) { return 0L; } }").is_ok());
+    }
+    #[test]
+    fn test_builtin_shadowing() {
+        // 类中声明的 print 遮蔽内置函数，子类继承同样的遮蔽；其他类仍调用内置函数
+        let source = "public class Log { public void print(String s) { } public void run() { print(\"a\"); } } \
+                      public class Sub extends Log { public void go() { print(\"b\"); } } \
+                      public class Main { public static void main() { print(\"c\"); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        let body = |name: &str| {
+            let start = ir.find(&format!("@{}(", name)).unwrap();
+            ir[start..].split("\n}\n").next().unwrap().to_string()
+        };
+        assert!(body("Log.run").contains("@Log.__print$s(") && !body("Log.run").contains("@printf("), "{}", ir);
+        assert!(body("Sub.go").contains("@Log.__print$s("), "{}", ir);
+        assert!(body("Main.main").contains("@printf("), "{}", ir);
+    }
+    #[test]
+    fn test_function_types() {
+        // fn(...) -> R 是类型；没有 -> 的 fn(x) 仍然是普通调用
+        let source = "public class Main { public static int fn(int x) { return x; } \
+                        public static int apply(fn(int) -> int f, int x) { return f(x); } \
+                        public static void main() { fn(long) -> long g = (x) -> x * 2; long r = g(3); int y = fn(1) + apply((x) -> x, 2); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        // lambda 按目标类型生成签名，闭包调用通过函数指针间接调用
+        assert!(ir.contains("define i64 @__lambda_Main_") && ir.contains("(i8* %env, i64 %param0)"), "{}", ir);
+        assert!(ir.contains("to i64 (i8*, i64)*") && ir.contains("to i32 (i8*, i32)*"), "{}", ir);
+        assert!(ir.contains("call i32 @Main.__fn$i("), "{}", ir);
+        assert!(ir.contains("define i32 @Main.__apply$fn1ii$i(") && ir.contains("call i32 @Main.__apply$fn1ii$i("), "{}", ir);
+
+        let err = Compiler::new().compile_to_ir("public class Main { public static void main() { \
+                      fn(int) -> int f = (x) -> x; int y = f(1, 2); } }").unwrap_err().to_string();
+        assert!(err.contains("Function 'f' of type fn(int) -> int expects 1 argument(s), got 2"), "{}", err);
+        let err = Compiler::new().compile_to_ir("public class Main { public static int over(int x) { return x; } \
+                      public static int over(long x) { return 1; } public static void main() { auto f = Main::over; } }")
+            .unwrap_err().to_string();
+        assert!(err.contains("Ambiguous method reference 'Main::over'"), "{}", err);
+    }
+    #[test]
+    fn test_contextual_keywords() {
+        // var/let/auto/fn 在词法上是标识符
+        let tokens = lexer::lex("int var = let;").unwrap();
+        assert!(tokens.iter().any(|t| t.token == lexer::Token::Identifier("var".to_string())));
+        assert!(tokens.iter().any(|t| t.token == lexer::Token::Identifier("let".to_string())));
+
+        // 声明位置仍是关键字，其他位置是普通名字
+        let source = "public class Rec { public int let; public int auto(int fn) { return fn + let; } } \
+                      public class Main { public static int yield(int record) { return record; } \
+                        public static void main() { int var = 1; var = var + 2; var x = var; let y: long = 3; \
+                          auto z = x; Rec r = new Rec(); r.let = yield(z); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(ir.contains("%var_s1 = alloca i32") && ir.contains("%x_s1 = alloca i32"), "{}", ir);
+        assert!(ir.contains("define i32 @Rec.__auto$i(") && ir.contains("call i32 @Main.__yield$i("), "{}", ir);
+        assert!(ir.contains("alloca i64"), "{}", ir);
+    }
+    #[test]
+    fn test_var_rejected_in_member_type_positions() {
+        // 字段、返回类型、参数类型没有初始化器可推断，var/let/auto 在这些位置报错并带位置
+        let cases = [
+            ("public class D1 {\n    static var counter = 5;\n}", 2, 12, "'var' cannot be used as a field type"),
+            ("public class D1 {\n    static let get() { return 3; }\n}", 2, 12, "'let' cannot be used as a method return type"),
+            ("public class D1 {\n    static int id(auto x) { return x; }\n}", 2, 19, "'auto' cannot be used as a parameter type"),
+        ];
+        for (source, line, column, message) in cases {
+            let err = parser::parse(lexer::lex(source).unwrap()).unwrap_err();
+            assert!(matches!(&err, error::cayError::Parser { line: l, column: c, message: m, .. }
+                             if *l == line && *c == column && m.starts_with(message)), "{:?}", err);
+        }
+    }
+    #[test]
+    fn test_cayc_command_line() {
+        let matches = cli::cayc_command()
+            .try_get_matches_from(["cayc", "-O3", "-march=native", "-fPIC", "-fprofile-use=app.prof", "-L./libs", "-lm",
+                                   "--lto=thin", "--stack-guard", "app.cay", "app"])
+            .unwrap();
+        assert_eq!(matches.get_one::<String>("opt-level").map(String::as_str), Some("3"));
+        assert_eq!(matches.get_many::<String>("machine").unwrap().collect::<Vec<_>>(), ["arch=native"]);
+        assert_eq!(matches.get_many::<String>("codegen-flag").unwrap().collect::<Vec<_>>(), ["PIC", "profile-use=app.prof"]);
+        assert_eq!(matches.get_one::<String>("lib").map(String::as_str), Some("m"));
+        assert!(matches.contains_id("stack-guard") && matches.get_one::<String>("stack-guard").is_none());
+        assert_eq!(matches.get_one::<String>("output").map(String::as_str), Some("app"));
+
+        // -o 与位置参数 OUTPUT 等价，二者不能同时给出
+        let matches = cli::cayc_command().try_get_matches_from(["cayc", "--emit=obj", "-o", "app.o", "app.cay"]).unwrap();
+        assert_eq!(matches.get_one::<String>("output-file").map(String::as_str), Some("app.o"));
+        assert_eq!(matches.get_one::<String>("emit").map(String::as_str), Some("obj"));
+        assert!(cli::cayc_command().try_get_matches_from(["cayc", "-o", "a", "app.cay", "b"]).is_err());
+        assert!(cli::cayc_command().try_get_matches_from(["cayc", "--emit=asm", "app.cay"]).is_err());
+
+        // 未知的 -f/-m 取值和缺少输入文件都是用法错误
+        assert!(cli::cayc_command().try_get_matches_from(["cayc", "-fbogus", "a.cay"]).is_err());
+        assert!(cli::cayc_command().try_get_matches_from(["cayc", "-march", "a.cay"]).is_err());
+        assert!(cli::cayc_command().try_get_matches_from(["cayc", "-O3"]).is_err());
+        assert!(cli::cayc_command().try_get_matches_from(["cayc", "--completions", "fish"]).is_ok());
+
+        // 补全脚本由同一份定义生成
+        for shell in [clap_complete::Shell::Bash, clap_complete::Shell::Zsh, clap_complete::Shell::Fish] {
+            let mut script = Vec::new();
+            cli::write_completions(shell, &mut script);
+            let script = String::from_utf8(script).unwrap();
+            assert!(script.contains("opt-ir") && script.contains("stack-guard") && script.contains("completions"), "{}", script);
+            if shell != clap_complete::Shell::Bash {
+                assert!(script.contains("x86_64-unknown-linux-gnu") && script.contains("unroll-loops"), "{}", script);
+            }
+        }
+    }
+    #[test]
+    fn test_internal_compiler_error_report() {
+        // 编译流水线记录当前阶段和正在处理的源码位置
+        Compiler::new().compile_to_ir("public class Main { public static void main() {\n int x = 1 + 2; } }").unwrap();
+        let context = ice::current_context();
+        assert_eq!(context.phase, Some("代码生成"));
+        assert_eq!(context.location.map(|loc| loc.line), Some(2));
+
+        // 内部 panic 被转换为 InternalError，保留 panic 时的阶段
+        let error = ice::run_guarded("Test", "0.0.0", "missing.cay", || {
+            ice::enter_phase("语义分析");
+            panic!("unexpected node")
+        }).unwrap_err();
+        assert_eq!(error.message, "unexpected node");
+        assert_eq!(error.context.phase, Some("语义分析"));
+        assert!(error.reproducer.is_none());
+        assert!(error.report_instructions("missing.cay").contains(ice::ISSUE_URL));
+
+        let banner = ice::format_banner("Test", "0.0.0", "a.cay", "boom", &error.context);
+        assert!(banner.contains("internal compiler error") && banner.contains("语义分析") && banner.contains("Test v0.0.0"), "{}", banner);
+    }
+    #[test]
+    fn test_constant_folding() {
+        use ast::{BinaryOp, LiteralValue};
+        use types::Type;
+
+        let compile = |source: &str| Compiler::with_options(CompilerOptions { optimize: true, ..Default::default() })
+            .compile_to_ir(source).unwrap();
+        let main_body = |ir: &str| ir[ir.find("@Main.main").unwrap()..].to_string();
+
+        // 折叠与传播：x 是常量，y 依赖 x，r 被重新赋值不传播
+        let source = "public class Main { static final int K = 1 << 4; \
+                      public static void main() { int x = 2 * 3 + 4; long y = x * 3L + K; int r = 1; r = r + 1; \
+                      if (false) { println(\"dead\"); } if (x > 5) { println(y); } else { println(\"never\"); } \
+                      while (false) { println(\"loop\"); } println(r); } }";
+        let ir = compile(source);
+        let body = main_body(&ir);
+        assert!(!body.contains("mul i32") && !body.contains("shl i32"), "{}", body);
+        assert!(body.contains("store i32 10") && body.contains("store i64 46"), "{}", body);
+        assert!(body.contains("add i32"), "重新赋值的变量不应传播: {}", body);
+        assert!(!ir.contains("c\"dead\\00\"") && !ir.contains("c\"never\\00\"") && !ir.contains("c\"loop\\00\""), "{}", ir);
+
+        // 默认不启用（字面量 false 的分支由代码生成省略，x > 5 的分支不折叠）
+        let plain = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(main_body(&plain).contains("mul i32") && plain.contains("c\"never\\00\""));
+
+        // 折叠规则与代码生成的类型提升和运行时语义一致
+        use opt::{convert, fold_binary, fold_unary};
+        assert!(matches!(fold_binary(BinaryOp::Add, &LiteralValue::Int32(i32::MAX), &LiteralValue::Int32(1)), Some(LiteralValue::Int32(i32::MIN))));
+        assert!(matches!(fold_binary(BinaryOp::Mul, &LiteralValue::Int32(3), &LiteralValue::Int64(4)), Some(LiteralValue::Int64(12))));
+        assert!(matches!(fold_binary(BinaryOp::Add, &LiteralValue::Int32(1), &LiteralValue::Float32(0.5)), Some(LiteralValue::Float32(v)) if v == 1.5));
+        assert!(matches!(fold_binary(BinaryOp::UnsignedShr, &LiteralValue::Int32(-8), &LiteralValue::Int32(28)), Some(LiteralValue::Int32(15))));
+        assert!(matches!(fold_binary(BinaryOp::Ne, &LiteralValue::Float64(f64::NAN), &LiteralValue::Float64(1.0)), Some(LiteralValue::Bool(false))));
+        assert!(fold_binary(BinaryOp::Div, &LiteralValue::Int32(1), &LiteralValue::Int32(0)).is_none());
+        assert!(fold_binary(BinaryOp::Div, &LiteralValue::Float64(1.0), &LiteralValue::Float64(0.0)).is_none());
+        assert!(fold_binary(BinaryOp::Shl, &LiteralValue::Int32(1), &LiteralValue::Int32(33)).is_none());
+        assert!(fold_binary(BinaryOp::Mod, &LiteralValue::Float64(1.0), &LiteralValue::Float64(2.0)).is_none());
+        assert!(matches!(fold_unary(ast::UnaryOp::Neg, &LiteralValue::Int32(i32::MIN)), Some(LiteralValue::Int32(i32::MIN))));
+        assert!(matches!(convert(&LiteralValue::Float64(-2.9), &Type::Int32), Some(LiteralValue::Int32(-2))));
+        assert!(convert(&LiteralValue::Float64(1e20), &Type::Int32).is_none());
+    }
+    #[test]
+    fn test_execution_limits() {
+        let source = "public class Main { public static void main() { int n = 0; while (true) { int[] a = new int[8]; n = n + a.length; } } }";
+        let limited = Compiler::with_options(CompilerOptions {
+            max_steps: Some(1000),
+            max_heap_bytes: Some(4096),
+            ..Default::default()
+        }).compile_to_ir(source).unwrap();
+        // 步数计数：方法入口和每轮循环
+        assert!(limited.contains("@.cay_steps = internal global i64 0"));
+        assert!(limited.contains("icmp ugt i64") && limited.contains(", 1000"));
+        assert!(limited.contains("Execution limit exceeded: more than 1000 steps (in Main.main)"));
+        assert!(limited.contains("Execution limit exceeded: more than 1000 steps (in while loop at line 1)"));
+        // 堆上限：所有分配经过受限分配函数，只有它本身直接调用 calloc
+        assert!(limited.contains("define internal i8* @__cay_limited_calloc(i64 %count, i64 %size)"));
+        assert!(limited.contains("call i8* @__cay_limited_calloc(i64 1"));
+        assert_eq!(limited.matches("call i8* @calloc(").count(), 1, "{}", limited);
+        assert!(limited.contains("Execution limit exceeded: heap allocation exceeds 4096 bytes"));
+
+        let plain = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(!plain.contains("@.cay_steps") && !plain.contains("__cay_limited_calloc"));
+
+        assert_eq!(toolchain::parse_max_steps("5000"), Ok(5000));
+        assert!(toolchain::parse_max_steps("0").is_err());
+        assert_eq!(toolchain::parse_max_heap("64M"), Ok(64 * 1024 * 1024));
+        assert!(toolchain::parse_max_heap("lots").is_err());
+    }
+    #[test]
+    fn test_structured_ir_builder() {
+        use crate::codegen::ir::{FunctionBody, Instruction};
+
+        // return / break 之后的语句放入不可达块，每个基本块只有一条终止指令
+        let source = "public class Main { static int f(int x) { return x; println(\"after\"); } \
+                      public static void main() { while (true) { break; println(\"x\"); } println(f(3)); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(ir.contains("dead.0:"), "{}", ir);
+        let lines: Vec<&str> = ir.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with(';')).collect();
+        for pair in lines.windows(2) {
+            if pair[0].starts_with("ret ") || pair[0].starts_with("br ") {
+                assert!(pair[1].ends_with(':') || pair[1] == "}", "terminator followed by '{}'\n{}", pair[1], ir);
+            }
+        }
+
+        // 顺序执行到新标签时补跳转，函数结束时补 unreachable
+        let mut body = FunctionBody::new();
+        body.push(Instruction::parse("%t0 = add i32 1, 2"));
+        body.start_block("next");
+        body.push(Instruction::parse("ret i32 %t0"));
+        body.push(Instruction::parse("%t1 = add i32 %t0, 1"));
+        body.finish();
+        assert_eq!(body.verify(), Ok(()));
+        let mut text = String::new();
+        body.write_to(&mut text);
+        assert_eq!(text, "entry:\n  %t0 = add i32 1, 2\n  br label %next\nnext:\n  ret i32 %t0\ndead.0:\n  %t1 = add i32 %t0, 1\n  unreachable\n");
+
+        // 校验报告未定义的跳转目标和重复标签
+        let mut body = FunctionBody::new();
+        body.push(Instruction::Br { target: "missing".to_string() });
+        assert!(body.verify().unwrap_err().contains("undefined label 'missing'"));
+        let mut body = FunctionBody::new();
+        body.start_block("entry");
+        body.finish();
+        assert!(body.verify().unwrap_err().contains("duplicate basic block label 'entry'"));
+    }
+    #[test]
+    fn test_string_conversion() {
+        let source = "public class Point { public int x; public String toString() { return \"P\" + x; } } \
+                      public class Main { public static void main() { Point p = new Point(); \
+                        String s = \"b=\" + true + p + 1.5 + 'c'; println(p); println(s); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(ir.contains("call i8* @__cay_bool_to_string(i1 1)"), "{}", ir);
+        assert!(ir.contains("call i8* @Point.toString(i8* "), "{}", ir);
+        assert!(ir.contains("call i8* @__cay_double_to_string(double"), "{}", ir);
+        assert!(ir.contains("call i8* @__cay_char_to_string(i32 99)"), "{}", ir);
+
+        // 没有 toString() 的类使用默认表示
+        let source = "public class Plain { public int v; } \
+                      public class Main { public static void main() { Plain p = new Plain(); println(\"p=\" + p); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(ir.contains("call i8* @__cay_object_to_string(i8* "), "{}", ir);
+
+        // 数组与字符串拼接时按元素格式化
+        let source = "public class Main { public static void main() { int[] a = new int[2]; String s = \"a\" + a; } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(ir.contains("@__cay_reflect_array_to_string("), "{}", ir);
+    }
+    #[test]
+    fn test_grammar_spec_matches_parser() {
+        let grammar = grammar::Grammar::canonical().unwrap();
+        assert!(grammar.undefined_rules().is_empty(), "undefined rules: {:?}", grammar.undefined_rules());
+        for (name, _) in grammar::TOKEN_SAMPLES {
+            assert!(grammar.rule(name).is_some(), "token rule '{}' missing from grammar", name);
+        }
+
+        let parse = |tokens: &[String]| lexer::lex(&tokens.join(" ")).and_then(parser::parse);
+        // 同一个生成器连续生成用例，已覆盖的规则会引导后续推导选择新的分支
+        let mut generator = grammar::CaseGenerator::new(&grammar, 1);
+        for case in 0..400 {
+            let program = generator.positive();
+            if let Err(err) = parse(&program) {
+                panic!("case {}: derived program rejected by the parser: {}\n{}", case, err, program.join(" "));
+            }
+            if let Some(broken) = generator.negative(&program) {
+                assert!(parse(&broken).is_err(), "case {}: program with an unbalanced bracket accepted:\n{}", case, broken.join(" "));
+            }
+        }
+
+        // 语法中的每条规则都要被推导覆盖到
+        let mut unused: Vec<String> = grammar.reachable_rules(grammar::START_RULE).difference(generator.used_rules()).cloned().collect();
+        unused.sort();
+        assert!(unused.is_empty(), "rules never derived: {:?}", unused);
+    }
+
+    #[test]
+    fn test_print_precision() {
+        let source = "public class Main { public static void main() { double d = 1.5; println(d); \
+                      setPrintPrecision(2); print(d); String s = \"d=\" + d; } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        // 浮点数打印不再直接使用 %f，而是经过运行时格式化函数
+        assert!(!ir.contains("c\"%f\\0A\\00\""), "{}", ir);
+        assert!(ir.matches("call i8* @__cay_double_to_string(double").count() >= 3, "{}", ir);
+        assert!(ir.contains("call void @__cay_set_print_precision(i32 2)"), "{}", ir);
+        // 默认使用最短的可还原形式，float 按单精度判断
+        assert!(ir.contains("@.cay_print_precision = linkonce_odr global i32 -1"), "{}", ir);
+        let ir = Compiler::new().compile_to_ir("public class Main { public static void main() { float f = 0.1f; println(f); println(f, 3); } }").unwrap();
+        assert!(ir.contains("call i8* @__cay_double_to_string(double %t") && ir.contains(", i1 1)"), "{}", ir);
+        assert!(ir.contains("call i8* @__cay_double_to_fixed(double %t") && ir.contains(", i32 3)"), "{}", ir);
+
+        let check = |body: &str| Compiler::new().compile_to_ir(&format!("public class Main {{ public static void main() {{ {} }} }}", body));
+        assert!(check("setPrintPrecision(\"3\");").is_err());
+        assert!(check("setPrintPrecision();").is_err());
+        // 第一个参数不是浮点数时仍按格式字符串检查
+        assert!(check("println(1, 2);").is_err());
+
+        let mut session = interp::Session::new(Vec::new());
+        session.eval("float f = 0.1f; println(f); println(1.0 / 3); println(100.0); println(1.0e20); println(1.0 / 3, 2);").unwrap();
+        assert_eq!(String::from_utf8_lossy(session.output()), "0.1\n0.3333333333333333\n100.0\n1e+20\n0.33\n");
+    }
+
+    #[test]
+    fn test_import_modules() {
+        let dir = std::env::temp_dir().join(format!("cay_import_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("util")).unwrap();
+        std::fs::write(dir.join("util").join("math.cay"), "import \"../shared.cay\";\npublic class MathUtil { public static int twice(int x) { return Shared.add(x, x); } }").unwrap();
+        std::fs::write(dir.join("shared.cay"), "import util.math;\npublic class Shared { public static int add(int a, int b) { return a + b; } }").unwrap();
+        std::fs::write(dir.join("broken.cay"), "public class Broken { int f( }").unwrap();
+        let main_path = dir.join("main.cay").to_string_lossy().to_string();
+        let compile = |source: &str| Compiler::new().compile_module_to_ir(source, Some(&main_path));
+
+        // 点分隔的模块名和文件路径都可以导入；循环导入的模块只加载一次
+        let ir = compile("import util.math;\nimport \"shared.cay\";\n\
+                          public class Main { public static void main() { println(MathUtil.twice(21) + Shared.add(1, 2)); } }").unwrap();
+        assert_eq!(ir.matches("define i32 @Shared.__add$i$i").count(), 1, "{}", ir);
+        assert!(ir.contains("define i32 @MathUtil.__twice$i"), "{}", ir);
+
+        let err = compile("import util.missing;\npublic class Main { public static void main() { } }").unwrap_err();
+        assert!(err.to_string().contains("Cannot find module util.missing"), "{}", err);
+        assert_eq!(err.location(), Some((1, 1)));
+
+        // 被导入模块中的错误带有该模块的路径
+        let err = compile("import \"broken.cay\";\npublic class Main { public static void main() { } }").unwrap_err();
+        assert!(matches!(&err, error::cayError::Module { path, .. } if path.ends_with("broken.cay")), "{:?}", err);
+        assert_eq!(err.phase(), "语法分析");
+
+        let err = compile("public class Main { public static void main() { } }\nimport util.math;").unwrap_err();
+        assert!(err.to_string().contains("Import declarations must come before"), "{}", err);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_bits_builtins() {
+        let source = "public class Main { public static void main() { int a = Bits.popcount(7); int b = Bits.leadingZeros(5L); \
+                      int c = Bits.trailingZeros(8); long d = Bits.rotateLeft(3L, 2); int e = Bits.rotateRight(3, 1); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(ir.contains("call i32 @llvm.ctpop.i32(i32 7)"), "{}", ir);
+        // long 的计数结果截断为 int
+        assert!(ir.contains("call i64 @llvm.ctlz.i64(i64 5, i1 false)"), "{}", ir);
+        assert!(ir.contains("trunc i64 "), "{}", ir);
+        assert!(ir.contains("call i32 @llvm.cttz.i32(i32 8, i1 false)"), "{}", ir);
+        // 循环移位的距离扩展为 long
+        assert!(ir.contains("sext i32 2 to i64"), "{}", ir);
+        assert!(ir.contains("call i64 @llvm.fshl.i64(i64 3, i64 3, i64 "), "{}", ir);
+        assert!(ir.contains("call i32 @llvm.fshr.i32(i32 3, i32 3, i32 1)"), "{}", ir);
+
+        let err = Compiler::new().compile_to_ir("public class Main { public static void main() { int n = Bits.popcount(\"1\"); } }")
+            .unwrap_err().to_string();
+        assert!(err.contains("Method 'popcount' in class 'Bits' cannot be applied"), "{}", err);
+    }
+
+    #[test]
+    fn test_parallel_compilation() {
+        let source = "public class A { public static String name() { return \"shared\"; } }\n\
+                      public class B { public static String name() { return \"shared\" + \"b\"; } public static int twice(int x) { return x * 2; } }\n\
+                      public class Main { public static void main() { println(A.name() + B.name()); var n = B.twice(21); println(n); } }";
+        let sequential = Compiler::new().compile_to_ir(source).unwrap();
+        let parallel = Compiler::with_options(CompilerOptions { jobs: 4, ..Default::default() })
+            .compile_to_ir(source).unwrap();
+        for function in ["define i8* @A.name()", "define i8* @B.name()", "define i32 @B.__twice$i(i32", "define void @Main.main()"] {
+            assert!(sequential.contains(function) && parallel.contains(function), "{}", parallel);
+        }
+        // 各类新建的字符串常量使用各自的命名空间，内容相同时各自声明一份
+        assert!(parallel.contains("@.str.0.0 = private unnamed_addr constant [7 x i8] c\"shared\\00\""), "{}", parallel);
+        assert!(parallel.contains("@.str.1.0 = private unnamed_addr constant [7 x i8] c\"shared\\00\""), "{}", parallel);
+
+        // 与顺序编译一样，报告所有类中的错误
+        let err = Compiler::with_options(CompilerOptions { jobs: 4, ..Default::default() })
+            .compile_to_ir("public class A { public static void f() { int x = \"a\"; } }\n\
+                            public class Main { public static void main() { int y = true; } }").unwrap_err();
+        assert!(matches!(err, error::cayError::Multiple(ref errors) if errors.len() == 2), "{:?}", err);
+    }
+
+    #[test]
+    fn test_compilation_cache() {
+        let dir = std::env::temp_dir().join(format!("cay_cache_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cache_dir = dir.join("cache").to_string_lossy().to_string();
+        let main_path = dir.join("main.cay").to_string_lossy().to_string();
+        let out_path = dir.join("main.ll").to_string_lossy().to_string();
+        std::fs::write(&main_path, "import util;\npublic class Main { public static void main() { println(Util.value()); } }").unwrap();
+        std::fs::write(dir.join("util.cay"), "public class Util { public static int value() { return 1; } }").unwrap();
+        let compiler = Compiler::with_options(CompilerOptions { cache_dir: Some(cache_dir.clone()), ..Default::default() });
+
+        compiler.compile_file(&main_path, &out_path).unwrap();
+        let entries = || std::fs::read_dir(&cache_dir).unwrap().count();
+        assert_eq!(entries(), 2);
+
+        // 命中时直接复用缓存的 IR
+        let cached = std::fs::read_dir(&cache_dir).unwrap().map(|e| e.unwrap().path())
+            .find(|p| p.extension().is_some_and(|ext| ext == "ll")).unwrap();
+        std::fs::write(&cached, "; cached\n").unwrap();
+        compiler.compile_file(&main_path, &out_path).unwrap();
+        assert_eq!(std::fs::read_to_string(&out_path).unwrap(), "; cached\n");
+
+        // 被导入的模块变化后重新编译
+        std::fs::write(dir.join("util.cay"), "public class Util { public static int value() { return 2; } }").unwrap();
+        compiler.compile_file(&main_path, &out_path).unwrap();
+        assert!(std::fs::read_to_string(&out_path).unwrap().contains("ret i32 2"));
+
+        // 编译选项不同时使用不同的缓存项
+        let optimized = Compiler::with_options(CompilerOptions { cache_dir: Some(cache_dir.clone()), optimize: true, ..Default::default() });
+        optimized.compile_file(&main_path, &out_path).unwrap();
+        assert_eq!(entries(), 4);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_exact_narrowing() {
+        let source = "public class Main { public static void main() { long big = 5L; int a = toIntExact(big); int b = toByteExact(a); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(ir.contains("icmp slt i64 ") && ir.contains(", -2147483648"), "{}", ir);
+        assert!(ir.contains("icmp sgt i64 ") && ir.contains(", 2147483647"), "{}", ir);
+        assert!(ir.contains("icmp sgt i32 ") && ir.contains(", 127"), "{}", ir);
+        assert!(ir.contains("trunc i64 "), "{}", ir);
+        assert!(ir.contains("Error: toIntExact() value"), "{}", ir);
+
+        let check = |body: &str| Compiler::new().compile_to_ir(&format!("public class Main {{ public static void main() {{ {} }} }}", body));
+        assert!(check("int n = toByteExact(5L);").is_err());
+        assert!(check("int n = toIntExact(\"1\");").is_err());
+        assert!(check("int n = toIntExact();").is_err());
+    }
+
+    #[test]
+    fn test_debug_print() {
+        let source = "public class P { public int x; public String s; }\n\
+            public class Main { public static void main() { int a = 2; int[] xs = {1}; P p = new P();\n\
+            debugPrint(a * (a + 1) - -a); debugPrint(xs); debugPrint(p); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(ir.contains("[line 3] a * (a + 1) - -a: int = "), "{}", ir);
+        assert!(ir.contains("[line 3] xs: int[] = "), "{}", ir);
+        assert!(ir.contains("c\"P{\\00\"") && ir.contains("c\", s=\\00\""), "{}", ir);
+        assert!(ir.contains("debug.array.cond"), "{}", ir);
+
+        let check = |body: &str| Compiler::new().compile_to_ir(&format!("public class Main {{ public static void main() {{ {} }} }}", body));
+        assert!(check("debugPrint();").is_err());
+        assert!(check("debugPrint(1, 2);").is_err());
+        assert!(check("debugPrint(println(1));").is_err());
+    }
+
+    #[test]
+    fn test_reflection_printing() {
+        let source = "public class P { public int x; public String s; }\n\
+            public class Main { public static void main() { int[] xs = {1}; P p = new P(); println(p); println(xs); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        // 类元数据：字段名和类型签名，类表按类型 ID 索引
+        assert!(ir.contains("@__cay_class_table = private constant [2 x i8*]"), "{}", ir);
+        assert!(ir.contains("c\"x\\00\"") && ir.contains("c\"I\\00\"") && ir.contains("c\"S\\00\""), "{}", ir);
+        assert!(ir.contains("call i8* @__cay_reflect_object_to_string("), "{}", ir);
+        assert!(ir.contains("call i8* @__cay_reflect_array_to_string("), "{}", ir);
+
+        // 没有用到反射格式化时不生成元数据
+        let plain = Compiler::new().compile_to_ir("public class Main { public static void main() { println(1); } }").unwrap();
+        assert!(!plain.contains("@__cay_class_table"));
+    }
+
+    #[test]
+    fn test_watch_analysis() {
+        let dir = std::env::temp_dir().join(format!("cay_watch_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let main_path = dir.join("main.cay").to_string_lossy().to_string();
+        let util_path = dir.join("util.cay");
+        std::fs::write(&main_path, "import util;\npublic class Main { public static void main() { int a = x; int b = y; } }").unwrap();
+        std::fs::write(&util_path, "public class Util { public static int value() { return 1; } }").unwrap();
+
+        let first = watch::analyze(&main_path);
+        assert_eq!(first.diagnostics.len(), 2);
+        assert_eq!(first.files.len(), 2, "{:?}", first.files);
+
+        // 被导入的模块变化时指纹变化，内容不变时指纹不变
+        let fingerprint = watch::fingerprint(&first.files);
+        assert_eq!(watch::fingerprint(&first.files), fingerprint);
+        std::fs::write(&util_path, "public class Util { public static int value() { return 2; } }").unwrap();
+        assert_ne!(watch::fingerprint(&first.files), fingerprint);
+
+        // 修复一个错误、引入一个新错误
+        std::fs::write(&main_path, "import util;\npublic class Main { public static void main() {\n int b = y; int c = z; } }").unwrap();
+        let second = watch::analyze(&main_path);
+        let delta = watch::DiagnosticDelta::between(&first.diagnostics, &second.diagnostics);
+        assert_eq!((delta.new.len(), delta.remaining.len(), delta.fixed), (1, 1, 1));
+        let rendered = delta.render();
+        assert!(rendered.contains("Undefined variable: z") && rendered.contains("仍存在: error[E0011]"), "{}", rendered);
+        assert!(rendered.contains("main.cay:3: Undefined variable: y") && rendered.contains("已修复 1 个错误"), "{}", rendered);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_repl_session() {
+        use interp::{EvalError, Outcome, Session};
+        let mut session = Session::new(Vec::new());
+        let shown = |session: &mut Session<Vec<u8>>, input: &str| match session.eval(input) {
+            Ok(Outcome::Executed(value)) => value,
+            other => panic!("{}: {:?}", input, other.err().map(|e| e.render(input))),
+        };
+
+        // 表达式语句输出值，变量在输入之间保留
+        assert_eq!(shown(&mut session, "1 + 2").as_deref(), Some("3"));
+        assert_eq!(shown(&mut session, "int x = 5;"), None);
+        assert_eq!(shown(&mut session, "x += 3;"), None);
+        assert_eq!(shown(&mut session, "x * 2").as_deref(), Some("16"));
+        assert_eq!(shown(&mut session, "-7 % 3").as_deref(), Some("-1"));
+
+        // 类声明和会话函数
+        let class = "class Point { public int x; public int y; Point(int x, int y) { this.x = x; this.y = y; } public int sum() { return x + y; } }";
+        assert!(matches!(session.eval(class), Ok(Outcome::Defined(names)) if names == ["Point"]));
+        assert!(matches!(session.eval("int square(int n) { return n * n; }"), Ok(Outcome::Defined(names)) if names == ["square"]));
+        shown(&mut session, "Point p = new Point(3, 4);");
+        assert_eq!(shown(&mut session, "square(p.sum())").as_deref(), Some("49"));
+        assert_eq!(shown(&mut session, "p").as_deref(), Some("Point{x=3, y=4}"));
+        shown(&mut session, "for (int i = 0; i < 3; i++) { print(i); }");
+        assert_eq!(String::from_utf8_lossy(session.output()), "012");
+        assert!(session.variables().contains(&"int x = 8".to_string()), "{:?}", session.variables());
+
+        // 编译错误的行号相对于本次输入，运行时错误不影响已有变量
+        match session.eval("int a = 1;\nint b = missing;") {
+            Err(EvalError::Compile(diagnostics)) => assert_eq!(diagnostics[0].location.as_ref().map(|l| l.line), Some(2)),
+            other => panic!("{:?}", other.ok()),
+        }
+        let error = session.eval("int[] arr = new int[2];\narr[5] = 1;").err().unwrap().render("");
+        assert!(error.contains("Array index out of bounds"), "{}", error);
+        assert_eq!(shown(&mut session, "x").as_deref(), Some("8"));
+
+        // 死循环在步数上限处终止
+        session.set_limits(&CompilerOptions { max_steps: Some(100), ..Default::default() });
+        assert!(matches!(session.eval("while (true) { x++; }"), Err(EvalError::Runtime(_))));
+        assert!(interp::is_complete("if (x > 0) { x--; }") && !interp::is_complete("if (x > 0) {"));
+    }
+
+    #[test]
+    fn test_repl_execution_limits() {
+        use interp::{EvalError, Session};
+        let limit_error = |session: &mut Session<Vec<u8>>, input: &str| match session.eval(input) {
+            Err(EvalError::Runtime(message)) if message.contains("Execution limit exceeded") => message,
+            other => panic!("{}: {:?}", input, other.map_err(|e| e.render(input))),
+        };
+
+        // 堆上限：数组、对象和拼接出的字符串都计入，每次输入重新计数
+        let mut session = Session::new(Vec::new());
+        session.set_limits(&CompilerOptions { max_heap_bytes: Some(4096), ..Default::default() });
+        assert!(session.eval("int[] small = new int[100];").is_ok());
+        assert!(session.eval("int[] again = new int[100];").is_ok());
+        assert!(limit_error(&mut session, "long[][] grid = new long[64][64];").contains("4096 bytes of heap"));
+        assert!(session.eval("class Node { public long value; public Node next; }").is_ok());
+        limit_error(&mut session, "Node head = null; while (true) { Node n = new Node(); n.next = head; head = n; }");
+        limit_error(&mut session, "String s = \"\"; while (true) { s = s + \"abcdefgh\"; }");
+
+        // 递归深度上限
+        let mut session = Session::new(Vec::new());
+        session.set_limits(&CompilerOptions { stack_guard_depth: Some(50), ..Default::default() });
+        assert!(session.eval("int down(int n) { return n == 0 ? 0 : down(n - 1); }").is_ok());
+        assert!(session.eval("down(40)").is_ok());
+        assert!(limit_error(&mut session, "down(100)").contains("call depth 50"));
+    }
+
+    #[test]
+    fn test_ast_dump() {
+        let source = "public class Main {\n    public static void main() {\n        int x = a - b * c;\n        if (x > 0) { x++; } else return;\n    }\n}";
+        let program = parser::parse(lexer::lex(source).unwrap()).unwrap();
+        let text = ast_dump::to_text(&program);
+        let expected = "\
+        VarDecl int x @3:9 #2
+          init: Binary - @3:19 #1
+            Identifier a
+            Binary * @3:23 #0
+              Identifier b
+              Identifier c
+        If @4:9 #";
+        assert!(text.contains(expected), "{}", text);
+        assert!(text.contains("Method main(): void [public, static] @2:5"), "{}", text);
+        assert!(text.contains("then: Block") && text.contains("Unary ++ (postfix)") && text.contains("else: Return"), "{}", text);
+
+        let json: serde_json::Value = serde_json::from_str(&ast_dump::to_json(&program).unwrap()).unwrap();
+        assert_eq!(json["classes"][0]["name"], "Main");
+        let init = &json["classes"][0]["members"][0]["Method"]["body"]["statements"][0]["VarDecl"]["initializer"];
+        assert_eq!(init["Binary"]["op"], "Sub");
+        assert_eq!(init["Binary"]["right"]["Binary"]["op"], "Mul");
+    }
+
+    #[test]
+    fn test_rename_symbol() {
+        use refactor::{apply_edits, rename_in_source};
+        let source = "public class Shape {\n    public int size;\n    public int area() { return size * size; }\n    public int area(int k) { return area() * k; }\n}\n\
+                      public class Square extends Shape {\n    public int area() { return size * 2; }\n}\n\
+                      public class Main {\n    public static void main() {\n        Shape s = new Square();\n        int total = s.area();\n        int other = total;\n    }\n}\n";
+        let rename = |pos, name: &str| rename_in_source(source, None, pos, name);
+
+        // 重载和重写的方法一起改名
+        let edits = rename((3, 16), "surface").unwrap();
+        assert_eq!(edits.len(), 5, "{:?}", edits);
+        let renamed = apply_edits(source, &edits);
+        assert!(renamed.contains("public int surface(int k) { return surface() * k; }") && renamed.contains("s.surface()"), "{}", renamed);
+        assert!(Compiler::new().compile_to_ir(&renamed).is_ok());
+
+        // 类名连同类型引用一起改名
+        let renamed = apply_edits(source, &rename((1, 14), "Figure").unwrap());
+        assert!(renamed.contains("class Square extends Figure") && renamed.contains("Figure s = new Square();"), "{}", renamed);
+
+        // 局部变量只改它可见范围内的引用
+        let edits = rename((12, 13), "sum").unwrap();
+        assert_eq!(edits.iter().map(|e| e.start).collect::<Vec<_>>(), vec![(12, 13), (13, 21)]);
+
+        // 与可见的声明冲突、不是标识符或光标处没有符号时拒绝
+        let conflict = rename((12, 13), "other").unwrap_err().to_string();
+        assert!(conflict.contains("would refer to a different declaration"), "{}", conflict);
+        assert!(rename((2, 16), "area").is_err());
+        assert!(rename((12, 13), "while").unwrap_err().to_string().contains("not a valid identifier"));
+        assert!(rename((12, 13), "__tmp").is_err());
+        assert!(rename((12, 5), "x").unwrap_err().to_string().contains("No renamable symbol"));
+    }
+
+    #[test]
+    fn test_diagnostics_rendering() {
+        use diagnostics::{DiagnosticEngine, ErrorCode};
+        let source = "public class Main {\n    public static void main() {\n        int y = missing + 1;\n        String s = 5;\n    }\n}\n";
+        let err = Compiler::new().compile_to_ir(source).unwrap_err();
+        assert_eq!(err.code(), ErrorCode(11));
+        assert_eq!(err.code().to_string(), "E0011");
+        assert_eq!(err.code().description(), "未定义的变量");
+
+        // 多个错误渲染为独立的诊断，下划线指向出错的符号
+        let mut engine = DiagnosticEngine::new();
+        engine.add_source("main.cay", source);
+        engine.report(&err, "main.cay");
+        assert_eq!(engine.error_count(), 2);
+        let rendered = engine.render();
+        assert!(rendered.contains("error[E0011]: Undefined variable: missing\n --> main.cay:3:17\n  |\n\
+                                   3 |         int y = missing + 1;\n  |                 ^^^^^^^\n"), "{}", rendered);
+        assert!(rendered.contains("error[E0010]: Cannot assign int to string\n --> main.cay:4:9\n"), "{}", rendered);
+        assert!(rendered.ends_with("共 2 个错误\n"), "{}", rendered);
+
+        // 重复定义的类报告在第二个定义处
+        let err = Compiler::new().compile_to_ir("public class A { }\npublic class A { }").unwrap_err();
+        assert_eq!(err.code().to_string(), "E0013");
+        assert_eq!(err.location(), Some((2, 1)));
+        assert_eq!(Compiler::new().compile_to_ir("public class Main { int f( }").unwrap_err().code(), ErrorCode(6));
+    }
+
+    #[test]
+    fn test_lsp_server() {
+        use serde_json::json;
+        let uri = "untitled:main.cay";
+        let source = "public class Main {\n    public static int twice(int n) { return n * 2; }\n    public static void main() {\n        var total = twice(3);\n        int y = total;\n    }\n}\n";
+        let mut server = lsp::Server::new();
+        let init = server.handle(&json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }));
+        assert_eq!(init[0]["result"]["capabilities"]["textDocumentSync"]["change"], 2);
+
+        let open = server.handle(&json!({ "jsonrpc": "2.0", "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": uri, "version": 1, "text": source } } }));
+        assert_eq!(open[0]["params"]["diagnostics"], json!([]));
+
+        let at = |line: u32, character: u32| json!({ "textDocument": { "uri": uri }, "position": { "line": line, "character": character } });
+        let hover = server.handle(&json!({ "jsonrpc": "2.0", "id": 2, "method": "textDocument/hover", "params": at(4, 17) }));
+        assert!(hover[0]["result"]["contents"]["value"].as_str().unwrap().contains("int total"), "{}", hover[0]);
+        let definition = server.handle(&json!({ "jsonrpc": "2.0", "id": 3, "method": "textDocument/definition", "params": at(3, 21) }));
+        assert_eq!(definition[0]["result"]["range"]["start"], json!({ "line": 1, "character": 22 }));
+        let mut rename = at(3, 13);
+        rename["newName"] = json!("sum");
+        let rename = server.handle(&json!({ "jsonrpc": "2.0", "id": 5, "method": "textDocument/rename", "params": rename }));
+        let edits = rename[0]["result"]["changes"][uri].as_array().unwrap();
+        assert_eq!(edits.len(), 2, "{}", rename[0]);
+        assert_eq!(edits[1]["range"]["start"], json!({ "line": 4, "character": 16 }));
+        assert_eq!(edits[1]["newText"], "sum");
+
+        // 增量修改：把 `total;` 改成 `totl;`，发布未定义变量的诊断
+        let change = server.handle(&json!({ "jsonrpc": "2.0", "method": "textDocument/didChange", "params": {
+            "textDocument": { "uri": uri, "version": 2 },
+            "contentChanges": [{ "range": { "start": { "line": 4, "character": 16 }, "end": { "line": 4, "character": 21 } }, "text": "totl" }] } }));
+        let diagnostics = &change[0]["params"]["diagnostics"];
+        assert_eq!(diagnostics[0]["code"], "E0011", "{}", change[0]);
+        assert_eq!(diagnostics[0]["range"]["start"], json!({ "line": 4, "character": 16 }));
+        assert_eq!(diagnostics[0]["range"]["end"], json!({ "line": 4, "character": 20 }));
+
+        let shutdown = server.handle(&json!({ "jsonrpc": "2.0", "id": 4, "method": "shutdown" }));
+        assert_eq!(shutdown[0]["result"], serde_json::Value::Null);
+        server.handle(&json!({ "jsonrpc": "2.0", "method": "exit" }));
+        assert_eq!(server.exit_code(), Some(0));
+    }
+
+    #[test]
+    fn test_class_struct_layout() {
+        let source = "public class Base { public int id; public double weight; } \
+                      public class Derived extends Base { public char tag; public Derived next; } \
+                      public class Main { public static void main() { \
+                        Derived d = new Derived(); d.next = new Derived(); d.next.id = 3; d.weight = 1.5; } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        // 子类结构体以父类成员开头
+        assert!(ir.contains("%class.Base = type { i32, i8**, i32, double }"), "{}", ir);
+        assert!(ir.contains("%class.Derived = type { i32, i8**, i32, double, i32, i8* }"), "{}", ir);
+        // 链式字段访问通过结构体 GEP 完成
+        assert!(ir.contains("getelementptr inbounds %class.Derived, %class.Derived* %t"));
+        assert!(ir.contains(", i32 0, i32 5"));
+        assert!(ir.contains(", i32 0, i32 2"));
+        assert!(ir.contains("@calloc(i64 1, i64 48)") || ir.contains("alloca [48 x i8]"));
+    }
+    #[test]
+    fn test_class_struct_emission_order() {
+        let source = "public class Zone extends Area { public Area[] parts; } \
+                      public class Tree { public int value; public Forest children; public Tree parent; } \
+                      public class Forest { public Tree first; public Forest rest; public Shape shape; } \
+                      public class Area { public Tree owner; } \
+                      public interface Shape { int sides(); } \
+                      public class Main { public static void main() { Tree t = new Tree(); t.children = new Forest(); t.children.first = t; } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        let order: Vec<&str> = ir.lines()
+            .filter_map(|line| line.strip_prefix("%class.")?.split(' ').next())
+            .collect();
+        // 互相引用的 Tree/Forest 按先访问到的顺序打断环，父类和被引用的类先输出
+        assert_eq!(order, ["Shape", "Forest", "Tree", "Area", "Main", "Zone"], "{}", ir);
+        // 接口没有布局，以不透明类型前向声明；对象字段保存为 i8*，不形成递归类型
+        assert!(ir.contains("%class.Shape = type opaque"));
+        assert!(ir.contains("%class.Tree = type { i32, i8**, i32, i8*, i8* }"), "{}", ir);
+        assert_eq!(ir, Compiler::new().compile_to_ir(source).unwrap());
+    }
+
+    #[test]
+    fn test_library_mode() {
+        let library = "public class Shapes { public static int[] table = new int[4]; \
+                       public static int square(int x) { table[0] = table[0] + 1; return x * x; } }";
+        // 编译可执行程序时缺少入口是错误
+        let err = Compiler::new().compile_to_ir(library).unwrap_err();
+        assert!(err.to_string().contains("no entry point"), "{}", err);
+
+        let compiler = Compiler::with_options(CompilerOptions { no_main: true, ..Default::default() });
+        let ir = compiler.compile_to_ir(library).unwrap();
+        assert!(!ir.contains("define i32 @main("), "{}", ir);
+        assert!(ir.contains("define internal void @__cay_static_init()"), "{}", ir);
+        assert!(ir.contains("@llvm.global_ctors = appending global"), "{}", ir);
+
+        // 库模式下 main 是普通方法：多个 main、不支持的签名都不报错，也不生成入口
+        let mains = "public class A { public static void main() { } } \
+                     public class B { public static String main(int x) { return \"b\"; } }";
+        assert!(Compiler::new().compile_to_ir(mains).is_err());
+        let ir = compiler.compile_to_ir(mains).unwrap();
+        assert!(!ir.contains("define i32 @main(") && !ir.contains("global_ctors"), "{}", ir);
+    }
+
+    #[test]
+    fn test_ternary_codegen() {
+        let source = "public class Main { public static int calls = 0; \
+                        public static int bump() { calls = calls + 1; return calls; } \
+                        public static double pick(int a) { return a > 0 ? a : 2.5; } \
+                        public static int lazy(int a) { return a > 0 ? 1 : bump(); } \
+                        public static String name(int a) { return a > 0 ? \"big\" : null; } \
+                        public static void main() { println(pick(1)); println(lazy(1)); println(name(0)); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        // 分支的值在各自的出口块汇合，int 与 double 统一为 double
+        assert!(ir.contains("ternary.then.exit") && ir.contains("ternary.else.exit"), "{}", ir);
+        assert!(ir.contains("= phi double ["), "{}", ir);
+        assert!(ir.contains("sitofp i32"), "{}", ir);
+        // 字符串与 null 统一为字符串类型
+        assert!(ir.contains("= phi i8* ["), "{}", ir);
+        // 只有 else 分支调用 bump
+        let lazy = ir.split("lazy$i(i32 %Main.a) {").nth(1).unwrap().split("\n}\n").next().unwrap();
+        let (before_else, else_block) = lazy.split_once("\nternary.else.").unwrap();
+        assert!(!before_else.contains("call i32 @Main.bump("), "{}", lazy);
+        assert!(else_block.contains("call i32 @Main.bump("), "{}", lazy);
+
+        let err = Compiler::new().compile_to_ir("public class Main { public static void main() { int a = 1; int x = a > 0 ? 1 : \"s\"; } }").unwrap_err();
+        assert!(err.to_string().contains("Ternary operator branches must have compatible types"), "{}", err);
+    }
+
+    #[test]
+    fn test_super_calls() {
+        let source = "public class Animal { public String name; public Animal(String n) { name = n; } public Animal() { name = \"?\"; } \
+                        public String speak() { return \"...\"; } } \
+                      public class Dog extends Animal { public Dog(String n) { super(n); } public Dog(int x) { } \
+                        public String speak() { return \"Woof \" + super.speak() + super.name; } } \
+                      public class Main { public static void main() { Animal a = new Dog(\"rex\"); println(a.speak()); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        // super(args) 和隐式的父类无参构造函数都以当前 this 调用
+        assert!(ir.contains("call void @Animal.__ctor$s(i8* %this, i8* "), "{}", ir);
+        assert!(ir.contains("call void @Animal.__ctor(i8* %this)"), "{}", ir);
+        // new 调用匹配的构造函数
+        assert!(ir.contains("call void @Dog.__ctor$s(i8* "), "{}", ir);
+        // super.speak() 静态调用父类实现
+        let dog_speak = ir.split("define i8* @Dog.speak(i8* %this) {").nth(1).unwrap().split("\n}\n").next().unwrap();
+        assert!(dog_speak.contains("call i8* @Animal.speak(i8* "), "{}", dog_speak);
+
+        let check = |source: &str| Compiler::new().compile_to_ir(source).unwrap_err().to_string();
+        let err = check("public class A { public A(int x) { } } public class B extends A { public B() { super(\"s\"); } } \
+                         public class Main { public static void main() { } }");
+        assert!(err.contains("No constructor of A accepts (string)"), "{}", err);
+        let err = check("public class A { public void f() { super.f(); } } public class Main { public static void main() { } }");
+        assert!(err.contains("has no superclass"), "{}", err);
+        let err = check("public class A { public int f() { return 1; } } public class B extends A { public static int g() { return super.f(); } } \
+                         public class Main { public static void main() { } }");
+        assert!(err.contains("non-static variable super"), "{}", err);
+        let err = check("public class A { } public class B extends A { public B() { int x = 1; super(); } } \
+                         public class Main { public static void main() { } }");
+        assert!(err.contains("must be the first statement"), "{}", err);
+    }
+
+    #[test]
+    fn test_this_expression() {
+        let source = "public class Point { public int x; public Point(int x) { this.x = x; } \
+                        public int get() { return this.x; } public int twice() { return this.get() * 2; } \
+                        public Point self() { return this; } } \
+                      public class Main { public static void main() { Point p = new Point(3); Point q = p.self(); println(q.x + p.twice()); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        // this 是隐藏的首参数，this.method() 以当前对象调用本类方法
+        assert!(ir.contains("define i32 @Point.twice(i8* %this)"), "{}", ir);
+        assert!(ir.contains("call i32 @Point.get(i8* "), "{}", ir);
+        assert!(!ir.contains("@this."), "{}", ir);
+        // return this 返回从 this_ptr 加载的指针
+        let body = ir.split("define i8* @Point.self(i8* %this) {").nth(1).unwrap().split("\n}\n").next().unwrap();
+        assert!(body.contains("= load i8*, i8** %this.ptr") && !body.contains("i8** %this,"), "{}", body);
+
+        let err = Compiler::new().compile_to_ir("public class Main { public static void main() { println(this); } }").unwrap_err();
+        assert!(err.to_string().contains("non-static variable this"), "{}", err);
+    }
+
+    #[test]
+    fn test_instance_field_access() {
+        let source = "public class Node { public int value; public double weight; public Node next; public Node self() { return this; } } \
+                      public class Main { public static void main() { Node[] arr = new Node[1]; arr[0] = new Node(); \
+                        arr[0].value = 7; arr[0].weight = 1; Node m = arr[0]; Node n = m.self(); n.next = n; Node k = n.next; println(k.self().value); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        // 数组元素和方法调用结果的字段按类布局 getelementptr 后读写，int 值写入 double 字段前先转换
+        assert!(ir.contains("getelementptr inbounds %class.Node, %class.Node* "), "{}", ir);
+        assert!(ir.contains("store i32 7, i32* "), "{}", ir);
+        assert!(ir.contains("sitofp i32 1 to double"), "{}", ir);
+        assert!(ir.contains("= load i32, i32* "), "{}", ir);
+        assert!(!ir.contains("Invalid member access"), "{}", ir);
+    }
+
+    #[test]
+    fn test_byte_short_types() {
+        let source = "public class Main { public static void main() { byte b = 100; short s = -3000; byte[] bs = {1, 2, 3}; \
+                        bs[0] = b; int sum = b + s; println(sum); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        // byte/short 分配 i8/i16，常量截断后存入，运算前符号扩展为 int
+        assert!(ir.contains("alloca i8, align 1"), "{}", ir);
+        assert!(ir.contains("trunc i32 -3000 to i16"), "{}", ir);
+        assert!(ir.contains("sext i8 ") && ir.contains("sext i16 "), "{}", ir);
+        // byte 数组元素占 1 字节：8 字节长度头 + 3 个元素
+        assert!(ir.contains("@calloc(i64 1, i64 11)"), "{}", ir);
+
+        // 超出范围的常量和 int 表达式不能隐式收窄
+        for bad in ["byte b = 200;", "short s = 40000;", "byte a = 1; byte b = a + 1;", "byte[] bs = {1, 300};"] {
+            let source = format!("public class Main {{ public static void main() {{ {} }} }}", bad);
+            assert!(Compiler::new().compile_to_ir(&source).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        use lexer::Token;
+        let tokens = lexer::lex(r#""a\tb\\ \u00e9\u4e2d\uD83D\uDE00" '\u0041' '\n' '\u4e2d' '\uD83D\uDE00'"#).unwrap();
+        assert_eq!(tokens[0].token, Token::StringLiteral(Some("a\tb\\ \u{e9}\u{4e2d}\u{1F600}".to_string())));
+        assert_eq!(tokens[1].token, Token::CharLiteral(Some('A')));
+        assert_eq!(tokens[2].token, Token::CharLiteral(Some('\n')));
+        // char 是 Unicode 码点，BMP 之外的字符用代理对转义
+        assert_eq!(tokens[3].token, Token::CharLiteral(Some('\u{4e2d}')));
+        assert_eq!(tokens[4].token, Token::CharLiteral(Some('\u{1F600}')));
+
+        // 常量长度按 UTF-8 字节数计算，非 ASCII 字节原样输出
+        let source = "public class Main { public static void main() { println(\"caf\\u00e9\\t\"); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(ir.contains("constant [7 x i8] c\"caf\u{e9}\\09\\00\""), "{}", ir);
+
+        for (bad, message) in [
+            (r#""\q""#, "Invalid escape sequence '\\q' in string literal"),
+            (r#""\u12""#, "Invalid escape sequence '\\u12' in string literal"),
+            (r#""\uD83D""#, "Invalid escape sequence '\\ud83d' in string literal"),
+            (r"'\uDE00'", "Invalid escape sequence '\\ude00' in character literal"),
+        ] {
+            let error = lexer::lex(bad).unwrap_err().to_string();
+            assert!(error.contains(message), "{}: {}", bad, error);
+        }
+    }
+
+    #[test]
+    fn test_format_builtin() {
+        let source = "public class Main { public static void main() { byte b = (byte) -1; \
+                      String s = format(\"%s=%5d|%x\", \"n\", 42, b); println(\"%.2f%%%n\", 1.5); println(s); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        // 先以空缓冲区求长度，分配后再写入；byte 零扩展后按无符号输出
+        assert!(ir.contains("@snprintf(i8* null, i64 0, i8* "), "{}", ir);
+        assert!(ir.contains("zext i8 ") && ir.contains("call i8* @calloc(i64 1, i64 "), "{}", ir);
+        assert!(ir.contains("%.2f%%\\0A"), "{}", ir);
+
+        for (body, message) in [
+            ("println(format(\"%d\", 1.5));", "Format specifier '%d' expects an integer argument, but argument 1 is double"),
+            ("println(\"%d %d\", 1);", "Format string expects 2 argument(s), got 1"),
+            ("println(\"%q\", 1);", "Unknown format conversion '%q'"),
+            ("println(\"%-d\", 1);", "Format specifier '%-d' requires a width"),
+            ("println(\"%+s\", \"a\");", "Flag '+' is not allowed in format specifier '%+s'"),
+            ("String f = \"%d\"; println(format(f, 1));", "The format string of format() must be a string literal"),
+            ("print(1, 2);", "print() expects a String format string, got int"),
+        ] {
+            let source = format!("public class Main {{ public static void main() {{ {} }} }}", body);
+            let error = Compiler::new().compile_to_ir(&source).unwrap_err().to_string();
+            assert!(error.contains(message), "{}: {}", body, error);
+        }
+
+        // 解释器的输出与 snprintf 一致
+        let mut session = interp::Session::new(Vec::new());
+        session.eval("println(\"[%-4d|%04d|%+.1e|%6.3s|%o|%c|%b]\", 7, -7, 12345.0, \"abcdef\", 8, 'z', false);").unwrap();
+        assert_eq!(String::from_utf8_lossy(session.output()), "[7   |-007|+1.2e+04|   abc|10|z|false]\n");
+    }
+
+    #[test]
+    fn test_empty_array_initializer() {
+        // 有声明类型时 {} 生成长度为 0 的数组，嵌套的空初始化器按元素类型生成
+        let source = "public class Main { public static void main() { int[] a = {}; var b: String[] = {}; \
+                      int[][] m = {{}, {1, 2}}; println(a.length + b.length + m[0].length); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(ir.contains("call i8* @calloc(i64 1, i64 8)"), "{}", ir);
+
+        // var/let/auto 没有元素可供推断时报告变量名
+        let source = "public class Main { public static void main() { var e = {}; } }";
+        let error = Compiler::new().compile_to_ir(source).unwrap_err().to_string();
+        assert!(error.contains("Cannot infer type of 'e' from an empty array initializer"), "{}", error);
+    }
+
+    #[test]
+    fn test_definite_assignment() {
+        let check = |body: &str| {
+            let source = format!("public class Main {{ public static int f() {{ return 1; }} public static void main() {{ {} }} }}", body);
+            Compiler::new().compile_to_ir(&source).map(|_| ()).map_err(|e| e.to_string())
+        };
+        for ok in [
+            "int x; if (f() > 0) { x = 1; } else { x = 2; } println(x);",
+            "int x; if (f() > 0) { x = 1; } else { return; } println(x);",
+            "int x; while (true) { x = 1; break; } println(x);",
+            "int x; do { x = f(); } while (x < 0); println(x);",
+            "int x; switch (f()) { case 1: x = 1; break; default: x = 2; } println(x);",
+            "int x; int y = f() > 0 ? (x = 1) : (x = 2); println(x + y);",
+            "final int x; if (f() > 0) { x = 1; } else { x = 2; } println(x);",
+        ] {
+            assert!(check(ok).is_ok(), "{}: {:?}", ok, check(ok));
+        }
+        for (bad, message) in [
+            ("int x; println(x);", "Variable 'x' may not have been initialized"),
+            ("int x; if (f() > 0) { x = 1; } println(x);", "Variable 'x' may not have been initialized"),
+            ("int x; for (int i = 0; i < 3; i++) { x = i; } println(x);", "Variable 'x' may not have been initialized"),
+            ("int x; switch (f()) { case 1: x = 1; break; } println(x);", "Variable 'x' may not have been initialized"),
+            ("int x; boolean b = f() > 0 && (x = 1) > 0; println(x);", "Variable 'x' may not have been initialized"),
+            ("int x; x++;", "Variable 'x' may not have been initialized"),
+            ("int x; var g = () -> x;", "Variable 'x' may not have been initialized"),
+            ("final int x; x = 1; x = 2;", "final variable 'x': it may already have been assigned"),
+            ("final int x; while (f() > 0) { x = 1; }", "final variable 'x': it may already have been assigned"),
+            ("final int x = 1; x = 2;", "Cannot assign a value to final variable 'x'"),
+        ] {
+            let error = check(bad).unwrap_err();
+            assert!(error.contains(message), "{}: {}", bad, error);
+        }
+        let error = check("int x; println(x);").unwrap_err();
+        assert_eq!(error.matches("may not have been initialized").count(), 1, "{}", error);
+    }
+
+    #[test]
+    fn test_labeled_break_continue() {
+        let source = "public class Main { public static void main() { \
+                      outer: for (int i = 0; i < 3; i++) { for (int j = 0; j < 3; j++) { if (j == 2) continue outer; if (i == 2) break outer; } } \
+                      switch (1) { case 1: if (true) { break; } println(1); default: println(2); } } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        // if 中的 break 跳到 switch 的结束块
+        assert!(ir.contains("br label %switch.end"), "{}", ir);
+
+        for (body, message) in [
+            ("break;", "break statement outside of loop or switch"),
+            ("switch (1) { case 1: continue; }", "continue statement outside of loop"),
+            ("while (true) { break missing; }", "Undefined label 'missing' in break statement"),
+            ("while (true) { continue missing; }", "Undefined label 'missing' in continue statement"),
+            ("block: { while (true) { continue block; } }", "continue target 'block' is not a loop"),
+            ("a: while (true) { a: while (true) { break a; } }", "Label 'a' is already defined in an enclosing statement"),
+            ("a: while (true) { var g = () -> { break a; }; }", "Undefined label 'a' in break statement"),
+        ] {
+            let source = format!("public class Main {{ public static void main() {{ {} }} }}", body);
+            let error = Compiler::new().compile_to_ir(&source).unwrap_err().to_string();
+            assert!(error.contains(message), "{}: {}", body, error);
+        }
+
+        // 标签跳转参与明确赋值分析
+        let source = "public class Main { public static void main() { int k; \
+                      found: { for (int i = 0; i < 10; i++) { if (i > 3) { k = i; break found; } } k = -1; } println(k); } }";
+        assert!(Compiler::new().compile_to_ir(source).is_ok());
+
+        // 解释器：switch 中嵌套的 break 只跳出 switch，带标签的跳转作用于外层语句
+        let mut session = interp::Session::new(Vec::new());
+        session.eval("int n = 0; outer: for (int i = 0; i < 3; i++) { for (int j = 0; j < 3; j++) { \
+                      if (j == 2) continue outer; if (i == 2) break outer; n = n + 1; } }").unwrap();
+        session.eval("switch (n) { case 4: if (n == 4) { print(\"four \"); break; } print(\"bad \"); default: print(\"bad \"); }").unwrap();
+        session.eval("done: { if (n == 4) break done; print(\"bad \"); } println(n);").unwrap();
+        assert_eq!(String::from_utf8_lossy(session.output()), "four 4\n");
+    }
+
+    #[test]
+    fn test_boolean_conditions() {
+        for (body, message) in [
+            ("int n = 1; while (n) { n--; }", "Condition of while loop must be boolean, got int"),
+            ("if (1) { println(1); }", "Condition of if statement must be boolean, got int"),
+            ("for (int i = 0; i; i++) { }", "Condition of for loop must be boolean, got int"),
+            ("do { } while (\"yes\");", "Condition of do-while loop must be boolean, got String"),
+        ] {
+            let source = format!("public class Main {{ public static void main() {{ {} }} }}", body);
+            let error = Compiler::new().compile_to_ir(&source).unwrap_err();
+            assert!(error.to_string().contains(message), "{}: {}", body, error);
+            assert_eq!(error.code().to_string(), "E0010", "{}", body);
+        }
+
+        // 字面量条件只生成会执行的分支，不生成比较
+        let source = "public class Main { public static void main() { int i = 0; \
+                      while (true) { i++; if (i > 3) break; } \
+                      while (false) { println(\"never\"); } \
+                      if (false) { println(\"dead\"); } else { println(\"alive\"); } \
+                      do { println(i); } while (false); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        let body = &ir[ir.find("@Main.main").unwrap()..];
+        assert!(!ir.contains("c\"never\\00\"") && !ir.contains("c\"dead\\00\""), "{}", ir);
+        assert!(ir.contains("c\"alive\\00\""), "{}", ir);
+        // 只有 if (i > 3) 需要比较
+        assert_eq!(body.matches("icmp ne i1").count(), 1, "{}", body);
+    }
+
+    #[test]
+    fn test_runtime_helpers_are_provided() {
+        // 生成的代码引用的每个 __cay_* 运行时函数都由编译器提供，不需要用户另外实现
+        let source = "public class Point { int x; } public class Main { public static void main() { \
+                      String s = \"a\" + 1.5 + 'c' + true + 2L; println(s.length() + s.indexOf(\"c\")); \
+                      println(s.substring(1).replace(\"c\", \"d\").toUpperCase().trim()); \
+                      println(new Point()); char[] cs = {'h', 'i'}; println(cs); println(Math.random() < 1.0); } }";
+        let helpers = |ir: &str, prefix: &str| -> std::collections::BTreeSet<String> {
+            ir.lines()
+                .filter(|line| line.starts_with(prefix))
+                .filter_map(|line| line.split('@').nth(1))
+                .map(|rest| rest.split('(').next().unwrap_or_default().to_string())
+                .filter(|name| name.starts_with("__cay_"))
+                .collect()
+        };
+        let referenced = |ir: &str| -> std::collections::BTreeSet<String> {
+            ir.split("@__cay_").skip(1)
+                .map(|rest| format!("__cay_{}", rest.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.')).next().unwrap_or_default()))
+                .collect()
+        };
+
+        // 默认内联：引用的运行时函数都在模块中定义
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        let defined = helpers(&ir, "define ");
+        let missing: Vec<_> = referenced(&ir).into_iter()
+            .filter(|name| !defined.contains(name) && !ir.contains(&format!("@{} = ", name)))
+            .collect();
+        assert!(missing.is_empty(), "{:?}", missing);
+
+        // 外部运行时：模块只有声明，compile_runtime 生成的运行时模块和原生运行时库一起提供全部定义
+        let options = CompilerOptions { external_runtime: true, ..Default::default() };
+        let ir = Compiler::with_options(options.clone()).compile_to_ir(source).unwrap();
+        let declared = helpers(&ir, "declare ");
+        assert!(helpers(&ir, "define ").is_empty());
+        let path = std::env::temp_dir().join(format!("cay_runtime_{}.ll", std::process::id()));
+        Compiler::with_options(options).compile_runtime(&path.to_string_lossy()).unwrap();
+        let runtime = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert!(runtime.contains(&format!("; runtime ABI version: {}", codegen::runtime::RUNTIME_ABI_VERSION)));
+        let native: std::collections::BTreeSet<String> = codegen::runtime::NATIVE_RUNTIME_FUNCTIONS.iter().map(|name| name.to_string()).collect();
+        let runtime_defined = helpers(&runtime, "define ");
+        assert!(runtime_defined.is_disjoint(&native), "{:?}", runtime_defined.intersection(&native).collect::<Vec<_>>());
+        assert!(helpers(&runtime, "declare ").is_superset(&native));
+        assert_eq!(runtime_defined.union(&native).cloned().collect::<std::collections::BTreeSet<_>>(), declared);
+        assert!(declared.is_superset(&referenced(&ir).into_iter().filter(|name| !ir.contains(&format!("@{} = ", name))).collect()));
+    }
+
+    #[test]
+    fn test_native_runtime_exports_match() {
+        // runtime/ 静态库导出的函数与 NATIVE_RUNTIME_FUNCTIONS 一致，签名与内联运行时的定义一致
+        let runtime_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("runtime/src");
+        let mut exported = std::collections::BTreeMap::new();
+        for entry in std::fs::read_dir(&runtime_dir).unwrap() {
+            let source = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+            for item in source.split("#[unsafe(no_mangle)]").skip(1) {
+                let signature = item.split('{').next().unwrap_or_default();
+                let name = signature.split("fn ").nth(1).and_then(|rest| rest.split('(').next()).unwrap_or_default();
+                let params = signature.split('(').nth(1).and_then(|rest| rest.rsplit(')').nth(1)).unwrap_or_default();
+                let arity = params.split(',').filter(|p| !p.trim().is_empty()).count();
+                exported.insert(name.trim().to_string(), arity);
+            }
+        }
+        let expected: std::collections::BTreeSet<String> = codegen::runtime::NATIVE_RUNTIME_FUNCTIONS.iter().map(|name| name.to_string()).collect();
+        assert_eq!(exported.keys().cloned().collect::<std::collections::BTreeSet<_>>(), expected);
+
+        let ir = Compiler::with_options(CompilerOptions { external_runtime: true, ..Default::default() })
+            .compile_to_ir("public class Main { public static void main() { } }").unwrap();
+        for (name, arity) in &exported {
+            let decl = ir.lines().find(|line| line.starts_with("declare ") && line.contains(&format!("@{}(", name)))
+                .unwrap_or_else(|| panic!("{} is not declared", name));
+            let params = decl.split('(').nth(1).unwrap_or_default().trim_end_matches(')');
+            assert_eq!(params.split(',').filter(|p| !p.trim().is_empty()).count(), *arity, "{}", decl);
+        }
+    }
+
+    #[test]
+    fn test_read_line_runtime() {
+        // readLine 调用运行时函数，结果在堆上，不再使用栈上的固定缓冲区
+        let source = "public class Main { public static void main() { String line = readLine(); println(line.length()); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        let main_body = &ir[ir.find("@Main.main").unwrap()..];
+        assert!(main_body.contains("call i8* @__cay_read_line()"), "{}", main_body);
+        assert!(!ir.contains("@fgets") && !ir.contains("alloca [256 x i8]"), "{}", ir);
+        assert!(ir.contains("define linkonce_odr i8* @__cay_read_line()"), "{}", ir);
+    }
+
+    #[test]
+    fn test_typed_read_builtins() {
+        // 每个读取函数返回语义分析声明的类型，不再经过 i64/double 再截断
+        let source = "public class Main { public static void main() { \
+                      int a = readInt(); long b = readLong(); float c = readFloat(); double d = readDouble(); \
+                      char e = readChar(); boolean f = readBool(); long g = readInt(); \
+                      println(a); println(b); println(c); println(d); println(e); println(f); println(g); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        let start = ir.find("define void @Main.main").unwrap();
+        let main_body = &ir[start..start + ir[start..].find("\n}").unwrap()];
+        assert!(main_body.contains("i32* %t") && main_body.contains("float* %t") && main_body.contains("double* %t"), "{}", main_body);
+        assert!(main_body.contains("call i1 @__cay_read_bool()"), "{}", main_body);
+        assert!(!main_body.contains("trunc i64"), "{}", main_body);
+        assert!(!main_body.contains("fptrunc double"), "{}", main_body);
+        // int 赋给 long 时做拓宽转换
+        assert!(main_body.contains("sext i32"), "{}", main_body);
+        assert!(ir.contains("define linkonce_odr i1 @__cay_read_bool()"), "{}", ir);
+    }
+
+    #[test]
+    fn test_file_builtin_class() {
+        let source = "public class Main { public static void main() { String path = \"out.txt\"; \
+                      if (File.writeAll(path, \"a\") && File.appendLine(path, \"b\")) { String text = File.readAll(path); println(text); } \
+                      File.delete(path); println(File.exists(path)); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        for call in ["call i1 @__cay_file_write_all(i8* ", "call i1 @__cay_file_append_line(i8* ", "call i8* @__cay_file_read_all(i8* ",
+                     "call i1 @__cay_file_delete(i8* ", "call i1 @__cay_file_exists(i8* "] {
+            assert!(ir.contains(call), "missing {}", call);
+        }
+        assert!(ir.contains("define linkonce_odr i8* @__cay_file_read_all(i8* %path)"), "{}", ir);
+
+        // 参数类型在语义分析阶段检查
+        let err = Compiler::new().compile_to_ir("public class Main { public static void main() { File.readAll(1); } }").unwrap_err();
+        assert!(err.to_string().contains("readAll"), "{}", err);
+
+        // 用户定义的同名类覆盖内置类
+        let source = "public class File { public static int exists(int x) { return x; } } \
+                      public class Main { public static void main() { println(File.exists(3)); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(ir.contains("call i32 @File.__exists") && !ir.contains("call i1 @__cay_file_exists"), "{}", ir);
+    }
+
+    #[test]
+    fn test_exit_builtin() {
+        // exit 之后的位置不可达：另一分支赋值的变量在之后已赋值，调用后以 unreachable 结束基本块
+        let source = "public class Main { static int half(int n) { if (n % 2 != 0) { exit(3); } return n / 2; } \
+                      public static void main() { int x; if (half(4) == 2) { x = 1; } else { exit(2); } println(x); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(ir.contains("call void @exit(i32 3)\n  unreachable") && ir.contains("call void @exit(i32 2)\n  unreachable"), "{}", ir);
+
+        let err = Compiler::new().compile_to_ir("public class Main { public static void main() { exit(0); println(1); } }").unwrap_err();
+        assert!(err.to_string().contains("Unreachable statement"), "{}", err);
+        assert_eq!(err.code().to_string(), "E0022");
+        let err = Compiler::new().compile_to_ir("public class Main { public static void main() { int code = exit(0); } }").unwrap_err();
+        assert!(err.to_string().contains("Cannot assign never to int"), "{}", err);
+        // 用户方法遮蔽内置的 exit
+        assert!(Compiler::new().compile_to_ir("public class Main { static void exit(int c) { } public static void main() { exit(0); println(1); } }").is_ok());
+
+        let mut session = interp::Session::new(Vec::new());
+        assert!(matches!(session.eval("exit(4);"), Err(interp::EvalError::Exit(4))));
+    }
+
+    #[test]
+    fn test_list_and_for_each() {
+        // 内置的 List<T> 只为用到的实例生成代码；for-each 遍历数组按下标，遍历对象调用 size()/get(int)
+        let source = "public class Main { public static void main() { List<String> names = new List<String>(); names.add(\"a\"); \
+                      int[] nums = {1, 2}; long total = 0; for (long n : nums) { total = total + n; } \
+                      for (var name : names) { println(name); } println(names.contains(\"a\")); } }";
+        for optimize in [false, true] {
+            let ir = Compiler::with_options(CompilerOptions { optimize, ..Default::default() }).compile_to_ir(source).unwrap();
+            assert!(ir.contains("define void @List$String.__add$s(") && !ir.contains("@List$int"), "{}", ir);
+            assert!(ir.contains("call i32 @List$String.size(") && ir.contains("call i8* @List$String.__get$i("), "{}", ir);
+        }
+
+        // 程序自己定义的 List 替换内置的类
+        let source = "public class List { public int size() { return 0; } } \
+                      public class Main { public static void main() { List l = new List(); println(l.size()); } }";
+        assert!(Compiler::new().compile_to_ir(source).is_ok());
+
+        for (body, message) in [
+            ("int n = 3; for (int x : n) { }", "Cannot iterate over int"),
+            ("List<String> l = new List<String>(); for (int s : l) { }", "Cannot assign string to int"),
+            ("int[] a = {1}; for (final int x : a) { x = 2; }", "final variable 'x'"),
+        ] {
+            let source = format!("public class Main {{ public static void main() {{ {} }} }}", body);
+            let error = Compiler::new().compile_to_ir(&source).unwrap_err().to_string();
+            assert!(error.contains(message), "{}: {}", body, error);
+        }
+
+        // `var i: int = 0` 中的 ':' 是类型注解，不是 for-each
+        let source = "public class Main { public static void main() { for (var i: int = 0; i < 2; i = i + 1) { println(i); } } }";
+        assert!(Compiler::new().compile_to_ir(source).is_ok());
+
+        let mut session = interp::Session::new(Vec::new());
+        session.eval("List<int> xs = new List<int>();").unwrap();
+        session.eval("xs.add(3); xs.add(4); xs.set(0, 5);").unwrap();
+        session.eval("outer: for (var x : xs) { int[] pair = {x, x * 10}; for (int y : pair) { if (y == 4) { continue outer; } print(y); print(\" \"); } }").unwrap();
+        session.eval("println(xs.remove(0) + xs.size());").unwrap();
+        assert_eq!(String::from_utf8_lossy(session.output()), "5 50 6\n");
+    }
+
+    #[test]
+    fn test_map() {
+        // 内置 Map 的 native 方法展开为运行时的 __cay_map_* 函数，值以 i64 保存
+        let source = "public class Main { public static void main() { Map<String, double> m = new Map<>(); m.put(\"a\", 1.5); \
+                      println(m.get(\"a\")); println(m.containsKey(\"b\")); String[] ks = m.keys(); println(m); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(ir.contains("define void @Map$String$double.__put$s$d(i8* %this, i8* %key, double %value)"), "{}", ir);
+        assert!(ir.contains("call i8* @__cay_map_of(i64* ") && ir.contains(", i1 true)"), "{}", ir);
+        assert!(ir.contains("bitcast double %value to i64") && ir.contains("call void @__cay_map_put(i8* "), "{}", ir);
+        assert!(ir.contains("define i8** @Map$String$double.keys(i8* %this)"), "{}", ir);
+
+        let source = "public class Main { public static void main() { Map<int, boolean> m = new Map<int, boolean>(); m.put(1, true); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(ir.contains("call i8* @__cay_map_of(i64* ") && ir.contains(", i1 false)"), "{}", ir);
+
+        for (body, message) in [
+            ("Map<double, int> m = new Map<double, int>();", "Map keys must be String or int, got double"),
+            ("Map<String, int> m = new Map<>(); m.put(1, 2);", "put"),
+            ("Map<String, int> m = new Map<>(); String s = m.get(\"a\");", "Cannot assign"),
+        ] {
+            let source = format!("public class Main {{ public static void main() {{ {} }} }}", body);
+            let error = Compiler::new().compile_to_ir(&source).unwrap_err().to_string();
+            assert!(error.contains(message), "{}: {}", body, error);
+        }
+
+        let mut session = interp::Session::new(Vec::new());
+        session.eval("Map<String, int> counts = new Map<>();").unwrap();
+        session.eval("for (String w : \"b a b c b\".split(\" \")) { counts.put(w, counts.get(w) + 1); }").unwrap();
+        session.eval("counts.remove(\"a\"); counts.put(\"a\", 9);").unwrap();
+        session.eval("println(counts + \" \" + counts.size() + \" \" + counts.getOrDefault(\"z\", -1));").unwrap();
+        assert_eq!(String::from_utf8_lossy(session.output()), "{b=3, c=1, a=9} 3 -1\n");
+    }
+
+    #[test]
+    fn test_string_builder() {
+        // append 把参数转换为字符串后追加到运行时缓冲区，并返回 this 以便链式调用
+        let source = "public class Main { public static void main() { StringBuilder sb = new StringBuilder(); \
+                      sb.append(\"n=\").append((byte) 3).append('c'); println(sb.toString()); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(ir.contains("define i8* @StringBuilder.__append$s(i8* %this, i8* %text)"), "{}", ir);
+        assert!(ir.contains("call void @__cay_sb_append(i8* "), "{}", ir);
+        // 按语义分析选中的重载调用
+        assert!(ir.contains("call i8* @StringBuilder.__append$y(i8* "), "{}", ir);
+        assert!(ir.contains("call i8* @StringBuilder.__append$c(i8* "), "{}", ir);
+
+        // 未使用的内置类不会生成
+        let ir = Compiler::new().compile_to_ir("public class Main { public static void main() { println(1); } }").unwrap();
+        assert!(!ir.contains("@StringBuilder."), "{}", ir);
+
+        // 重载选中的参数类型决定实参的转换
+        let source = "public class Main { static long twice(long x) { return x * 2; } \
+                      public static void main() { println(twice(21)); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(ir.contains("sext i32 21 to i64") && ir.contains("call i64 @Main.__twice$l(i64 "), "{}", ir);
+
+        let mut session = interp::Session::new(Vec::new());
+        session.eval("StringBuilder sb = new StringBuilder(\"[\");").unwrap();
+        session.eval("for (int i = 0; i < 3; i++) { sb.append(i).append(i < 2 ? \",\" : \"]\"); }").unwrap();
+        session.eval("println(sb.toString() + \" \" + sb.length()); sb.clear(); println(sb.length());").unwrap();
+        assert_eq!(String::from_utf8_lossy(session.output()), "[0,1,2] 7\n0\n");
+    }
+
+    #[test]
+    fn test_char_code_points() {
+        // char 保存 Unicode 码点（i32），charAt 解码 UTF-8，转换为字符串时重新编码
+        let source = "public class Main { public static void main() { char c = '中'; String s = \"héllo\"; \
+                      char e = s.charAt(1); println(c + \"\" + e); println((char) (c + 1)); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(ir.contains("i32 20013"), "{}", ir);
+        assert!(ir.contains("call i32 @__cay_string_charat(i8* "), "{}", ir);
+        assert!(ir.contains("call i8* @__cay_char_to_string(i32 "), "{}", ir);
+
+        let mut session = interp::Session::new(Vec::new());
+        session.eval("char c = 'a'; println((char) (c + 1)); println((String) '中'); println('a' + 'b');").unwrap();
+        session.eval("switch (c) { case 'a': println(\"A\"); break; default: println(\"?\"); }").unwrap();
+        assert_eq!(String::from_utf8_lossy(session.output()), "b\n中\n195\nA\n");
+    }
+
+    #[test]
+    fn test_bool_printing() {
+        // boolean 打印为 true/false，而不是按整数打印为 -1/0
+        let source = "public class Main { public static void main() { boolean flag = 3 > 5; println(flag); print(true); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(ir.contains("call i8* @__cay_bool_to_string(i1 %t"), "{}", ir);
+        assert!(ir.contains("call i8* @__cay_bool_to_string(i1 1)"), "{}", ir);
+        assert!(!ir.contains("sext i1 %t1 to i64"), "{}", ir);
+    }
+
+    #[test]
+    fn test_arrays_to_string() {
+        // Arrays.toString 和数组的字符串拼接都交给反射格式化函数，按元素类型签名格式化
+        let source = "public class Main { public static void main() { int[][] grid = {{1}, {2, 3}}; \
+                      String s = Arrays.toString(grid); String t = \"d=\" + new double[2]; } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(ir.matches("call i8* @__cay_reflect_array_to_string(i8* ").count() >= 2, "{}", ir);
+
+        let check = |body: &str| Compiler::new().compile_to_ir(&format!("public class Main {{ public static void main() {{ {} }} }}", body));
+        let err = check("String s = Arrays.toString(1);").unwrap_err();
+        assert!(err.to_string().contains("Arrays.toString() expects an array argument, got int"), "{}", err);
+        assert!(check("String s = Arrays.toString();").is_err());
+        // 用户定义的同名类遮蔽内置类
+        assert!(Compiler::new().compile_to_ir("public class Arrays { public static String toString(int x) { return \"u\"; } } \
+            public class Main { public static void main() { String s = Arrays.toString(1); } }").is_ok());
+
+        let mut session = interp::Session::new(Vec::new());
+        session.eval("int[][] grid = {{1}, {2, 3}}; println(Arrays.toString(grid)); println(\"flags \" + new boolean[2]);").unwrap();
+        assert_eq!(String::from_utf8_lossy(session.output()), "[[1], [2, 3]]\nflags [false, false]\n");
+    }
+
+    #[test]
+    fn test_array_bounds_and_md_length() {
+        let source = "public class Main { public static void main() { int[][] m = new int[2][3]; \
+                        int i = 0; if (i < 2 && m[i].length == 3) { println(m[i][1]); } } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        // 外层指针数组同样带有 8 字节长度头
+        assert!(ir.matches("getelementptr i8, i8* %").count() >= 2, "{}", ir);
+        assert!(ir.contains("Array index out of bounds: index %"), "{}", ir);
+        assert!(ir.contains("icmp uge i64"), "{}", ir);
+        // && 短路：右侧在单独的块中求值
+        assert!(ir.contains("and.rhs"), "{}", ir);
+        assert!(ir.contains("= phi i1 [ 0, %and.lhs"), "{}", ir);
+    }
+    #[test]
+    fn test_array_covariance() {
+        // 基本类型数组不变：int 与 long 元素宽度不同
+        for source in [
+            "public class Main { public static void main() { int[] a = {1}; long[] b = a; } }",
+            "public class Main { static long f(long[] xs) { return xs[0]; } public static void main() { int[] a = {1}; long r = f(a); } }",
+            "public class Main { public static void main() { long[][] g = new int[2][2]; } }",
+            "public class Main { public static void main() { char[] c = {'a'}; int[] i = c; } }",
+        ] {
+            assert!(Compiler::new().compile_to_ir(source).is_err(), "{}", source);
+        }
+
+        // 引用类型数组协变；元素读写按语义类型选择 LLVM 类型
+        let source = "public class A { } public class B extends A { } \
+                      public class Main { public static void main() { B[] bs = new B[1]; A[] as = bs; \
+                        byte[] d = {(byte)1}; byte x = d[0]; short[] s = new short[1]; s[0] = x; } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(ir.contains("getelementptr i8, i8* %"), "{}", ir);
+        assert!(ir.contains("getelementptr i16, i16* %"), "{}", ir);
+    }
+
+    #[test]
+    fn test_typed_codegen_values() {
+        use codegen::value::LLVMValue;
+        use types::Type;
+
+        // 语义分析为带 NodeId 的表达式记录静态类型，LLVM 类型相同的 char 与 int 仍可区分
+        let source = r#"public class Main {
+    public static void main() {
+        String s = "abc";
+        int n = s.charAt(0) + 1;
+    }
+}"#;
+        let ast = parser::parse(lexer::lex(source).unwrap()).unwrap();
+        let mut analyzer = semantic::SemanticAnalyzer::new();
+        analyzer.analyze(&ast).unwrap();
+        let types: Vec<&Type> = analyzer.expr_types().values().collect();
+        assert!(types.contains(&&Type::Char));
+        assert!(types.contains(&&Type::Int32));
+
+        let value = LLVMValue::new("i32", "%t1", Type::Char);
+        assert_eq!(value.to_string(), "i32 %t1");
+        assert_eq!(LLVMValue::from_llvm("i32*", "%t2").lang_ty, Type::Array(Box::new(Type::Int32)));
+        assert_eq!(LLVMValue::void().to_string(), "void");
+    }
+
+    #[test]
+    fn test_inc_dec_lvalues() {
+        let source = "public class C { public int n; public static int total; } \
+                      public class Main { static int next() { return 0; } \
+                      public static void main() { int[] a = new int[1]; a[next()]++; \
+                      C c = new C(); ++c.n; C.total--; } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        // 下标表达式只求值一次，读改写通过同一个元素指针
+        assert_eq!(ir.matches("call i32 @Main.next").count(), 1, "{}", ir);
+        assert!(ir.contains("store i32 %"), "{}", ir);
+        assert!(ir.contains("@C.total"), "{}", ir);
+    }
+
+    #[test]
+    fn test_power_of_two_division() {
+        // 2 的幂常量除数生成移位和掩码，不生成 sdiv/srem，也不需要除零检查
+        let source = "public class Main { public static void main() { int x = -37; \
+                      println(x / 8); println(x % 16); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        let main = &ir[ir.find("define void @Main.main").unwrap()..];
+        let main = &main[..main.find("\n}").unwrap()];
+        assert!(main.contains("ashr i32"), "{}", main);
+        assert!(main.contains("and i32"), "{}", main);
+        assert!(!main.contains("sdiv") && !main.contains("srem"), "{}", main);
+        assert!(!main.contains("div.error"), "{}", main);
+
+        // 变量除数需要除零检查，并单独处理 -1 以避免 MIN / -1 溢出
+        let source = "public class Main { public static void main() { int b = readInt(); println(100 / b); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(ir.contains("div.error"), "{}", ir);
+        assert!(ir.contains(", -1\n"), "{}", ir);
+        assert!(ir.contains("sdiv i32 100"), "{}", ir);
+    }
+
+    #[test]
+    fn test_ir_verification() {
+        use toolchain::ToolError;
+
+        let stderr = "Instruction does not dominate all uses!\n  %y = add i32 %a, 1\n\nopt: bad.ll: error: input module is broken!\n";
+        assert_eq!(toolchain::summarize_ir_errors(stderr),
+            "  Instruction does not dominate all uses!\n    %y = add i32 %a, 1\n  bad.ll: error: input module is broken!");
+        let long: String = (0..30).map(|i| format!("line {}\n", i)).collect();
+        assert!(toolchain::summarize_ir_errors(&long).ends_with("  ... (还有 10 行)"));
+
+        let invalid = ToolError::InvalidIr { file: "a.ll".to_string(), details: "  bad".to_string() };
+        assert_eq!(invalid.exit_code(), error::exit_codes::CODEGEN);
+        assert!(invalid.to_string().starts_with("LLVM IR 校验失败: a.ll\n  bad\n"));
+
+        // 多维数组每一层的指针类型与子数组一致，LLVM 校验器才能接受
+        let source = "public class Main { public static void main() { int[][][] c = new int[2][3][4]; c[1][2][3] = 7; println(c[1][2][3]); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(ir.contains("store i32** %"), "{}", ir);
+        assert!(!ir.contains("i32*****"), "{}", ir);
+        if let Ok(opt) = toolchain::find_opt(None) {
+            let path = std::env::temp_dir().join(format!("cay_verify_{}.ll", std::process::id()));
+            std::fs::write(&path, &ir).unwrap();
+            let result = toolchain::verify_ir(&opt, path.to_str().unwrap(), std::time::Duration::from_secs(60));
+            let _ = std::fs::remove_file(&path);
+            assert!(result.is_ok(), "{}", result.unwrap_err());
+        }
+    }
+
+    #[test]
+    fn test_verify_ir_values() {
+        use codegen::ir::{FunctionBody, Instruction};
+        use codegen::verify::{Signature, verify_values};
+
+        let signature = Signature::parse_define("define internal i32 @Main.__f$i(i32 %n) {").unwrap();
+        assert_eq!(signature.ret_ty, "i32");
+        assert_eq!(signature.params, vec![("i32".to_string(), "n".to_string())]);
+
+        let body_of = |lines: &[&str]| {
+            let mut body = FunctionBody::new();
+            body.location = Some(error::SourceLocation { line: 3, column: 9 });
+            for line in lines {
+                body.push(Instruction::parse(line));
+            }
+            body.finish();
+            body
+        };
+        let ok = body_of(&["%t1 = add i32 %n, 1", "%t2 = getelementptr inbounds [3 x i8], [3 x i8]* @.str.0, i64 0, i64 0", "ret i32 %t1"]);
+        assert!(verify_values(&signature, &ok).is_ok());
+
+        let undefined = verify_values(&signature, &body_of(&["%t1 = add i32 %m, 1", "ret i32 %t1"])).unwrap_err();
+        assert!(undefined.message.contains("'%m'"), "{}", undefined.message);
+        assert_eq!(undefined.location.map(|loc| (loc.line, loc.column)), Some((3, 9)));
+        let mismatch = verify_values(&signature, &body_of(&["%t1 = sext i32 %n to i64", "%t2 = add i32 %t1, 1", "ret i32 %t2"])).unwrap_err();
+        assert!(mismatch.message.contains("i64"), "{}", mismatch.message);
+        let wrong_ret = verify_values(&signature, &body_of(&["%t1 = sext i32 %n to i64", "ret i64 %t1"])).unwrap_err();
+        assert!(wrong_ret.message.contains("returns i64"), "{}", wrong_ret.message);
+
+        // 打开 --verify-ir 后正常程序照常编译
+        let source = "public class Main { static String f(int n, double d) { return \"\" + n + d; } \
+                      public static void main() { int[] a = new int[3]; a[1] = 2; long s = 0; \
+                      for (int i = 0; i < 3; i++) { s += a[i]; } println(f((int) s, 1.5)); } }";
+        let compiler = Compiler::with_options(CompilerOptions { verify_ir: true, ..Default::default() });
+        assert!(compiler.compile_to_ir(source).is_ok());
+    }
+
+    #[test]
+    fn test_mangle_demangle() {
+        use codegen::mangle::{self, SymbolKind};
+        use types::{FunctionType, Type};
+
+        let callback = Type::Function(Box::new(FunctionType { params: vec![Type::Int32], return_type: Box::new(Type::Int32), is_static: false }));
+        let params = vec![Type::Object("Box$int".to_string()), Type::Array(Box::new(Type::Array(Box::new(Type::Float64)))), callback, Type::String];
+        let symbol = mangle::mangle("Util", "identity$int", &params);
+        assert_eq!(symbol, "Util.__identity$int$o7Box$int$aad$fn1ii$s");
+        let demangled = mangle::demangle(&symbol).unwrap();
+        assert_eq!((demangled.class.as_str(), demangled.member.as_str(), demangled.kind), ("Util", "identity$int", SymbolKind::Method));
+        assert_eq!(demangled.params.iter().map(mangle::type_code).collect::<Vec<_>>(), ["o7Box$int", "aad", "fn1ii", "s"]);
+
+        assert_eq!(mangle::mangle("Point", "norm", &[]), "Point.norm");
+        assert_eq!(mangle::demangle("@Point.__move$i$i").unwrap().to_string(), "Point.move(int, int)");
+        assert_eq!(mangle::mangle_constructor("Point", &[]), "Point.__ctor");
+        assert_eq!(mangle::demangle(&mangle::mangle_constructor("Point", &[Type::Float64])).unwrap().to_string(), "Point.<init>(double)");
+        assert_eq!(mangle::demangle(&mangle::destructor_symbol("Point")).unwrap().kind, SymbolKind::Destructor);
+        assert_eq!(mangle::demangle(&mangle::static_init_symbol("Point")).unwrap().kind, SymbolKind::StaticInit);
+        assert!(mangle::demangle("printf").is_none());
+
+        // 定义、调用和接口表使用同一个符号名，类类型的参数按类名长度编码
+        let source = "public interface Shape { double area(Point p); } \
+                      public class Point { public double x; public Point(double x) { this.x = x; } } \
+                      public class Sq implements Shape { public double area(Point p) { return p.x * p.x; } } \
+                      public class Main { public static void main() { Shape s = new Sq(); println(s.area(new Point(2.0))); \
+                      Sq q = new Sq(); println(q.area(new Point(3.0))); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(ir.contains("define double @Sq.__area$o5Point(i8* %this, i8* "), "{}", ir);
+        assert!(ir.contains("call double @Sq.__area$o5Point(i8* ") && ir.contains("@Sq.__area$o5Point to i8*"), "{}", ir);
+        assert!(ir.contains("call void @Point.__ctor$d(i8* "), "{}", ir);
+    }
+
+    #[test]
+    fn test_native_methods() {
+        let compile = |members: &str| Compiler::new().compile_to_ir(&format!(
+            "public class Main {{ {} public static void main() {{ }} }}", members));
+
+        // 调用直接指向 C 函数；窄整数和 boolean 按 C 调用约定扩展；运行时已经声明的 strlen 不重复声明
+        let source = "public class Main { @Symbol(\"labs\") public static native long absLong(long x); \
+                      public static native boolean flag(byte b, short s); public static native long strlen(String s); \
+                      public static void main() { long a = absLong(-5L); boolean f = flag((byte) 1, (short) 2); println(strlen(\"ab\") + a); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        assert!(ir.contains("declare i64 @labs(i64)") && ir.contains("call i64 @labs(i64 "), "{}", ir);
+        assert!(ir.contains("declare zeroext i1 @flag(signext i8, signext i16)") && ir.contains("call i1 @flag("), "{}", ir);
+        assert_eq!(ir.matches("declare i64 @strlen(").count(), 1, "{}", ir);
+        assert!(!ir.contains("Main.absLong") && !ir.contains("Main.__absLong"), "{}", ir);
+
+        let err = compile("public static native int strlen(String s);").unwrap_err();
+        assert!(err.to_string().contains("already declares it as i64 (i8*)"), "{}", err);
+        let err = Compiler::new().compile_to_ir("public class A { public static native int f(int x); } \
+            public class B { @Symbol(\"f\") public static native long g(long x); } \
+            public class Main { public static void main() { } }").unwrap_err();
+        assert!(err.to_string().contains("'A.f' and 'B.g' bind to C function 'f' with different signatures"), "{}", err);
+
+        for (members, message) in [
+            ("public native int f(int x);", "Native method 'Main.f' must be static"),
+            ("@Symbol(\"g\") public static int f(int x) { return x; }", "@Symbol can only be used on native methods"),
+            ("@Symbol(\"bad name\") public static native int f(int x);", "Invalid C function name 'bad name'"),
+            ("public static native int f(int... xs);", "cannot have a varargs parameter"),
+            ("public static native int f(fn(int) -> int g);", "cannot take or return function type"),
+        ] {
+            let err = compile(members).unwrap_err();
+            assert!(err.to_string().contains(message), "{}: {}", members, err);
+        }
+        assert!(compile("@Symbol(labs) public static native long f(long x);").unwrap_err().to_string().contains("Expected a string literal"));
+    }
+
+    #[test]
+    fn test_inline_ir() {
+        let compile = |body: &str| Compiler::new().compile_to_ir(&format!(
+            "public class Main {{ public static void main() {{ int x = 3; {} }} }}", body));
+
+        // 占位符替换为实参、类型和结果寄存器，模板中的局部值每次展开都改名
+        let ir = compile("int y = __llvm__<int>(\"%sq = mul $T0 $0, $0\\n$out = add $T0 %sq, $1\", x, 1); \
+                          int z = __llvm__<int>(\"%sq = mul i32 $0, $0\\n$out = sub i32 %sq, 1\", y); println(z);").unwrap();
+        assert!(ir.contains("%sq.inline.") && ir.contains("= add i32 %sq.inline."), "{}", ir);
+        assert!(ir.contains("%sq.inline.0 = mul i32 %t0, %t0") && ir.contains("%sq.inline.1 = mul i32"), "{}", ir);
+        assert!(compile("__llvm__(\"; $$ $0\", x);").is_ok());
+
+        for (body, message) in [
+            ("int y = __llvm__<int>(\"$out = add i32 $0, $1\", x);", "refers to $1, but only 1 argument(s) were given"),
+            ("int y = __llvm__<int>(\"$out = add i32 $0, 1\", x, x);", "Argument 1 of __llvm__ is not used"),
+            ("int y = __llvm__<int>(\"%r = add i32 $0, 1\", x);", "must assign $out"),
+            ("__llvm__(\"$out = add i32 $0, 1\", x);", "without a result type cannot use $out"),
+            ("int y = __llvm__<int>(\"$out = add i32 $x, 1\");", "Unknown placeholder '$x'"),
+            ("__llvm__(\"br label %next $0\", x);", "cannot contain labels or terminators"),
+            ("__llvm__(\"$0\", println(x));", "cannot be a void expression"),
+        ] {
+            let err = compile(body).unwrap_err();
+            assert!(err.to_string().contains(message), "{}: {}", body, err);
+        }
+        assert!(compile("int y = __llvm__<int>(x);").unwrap_err().to_string().contains("Expected a string literal"));
+    }
+
+    #[test]
+    fn test_tool_not_found_hint() {
+        use std::process::Command;
+        use std::time::Duration;
+        // 找不到工具时的提示与工具名对应，而不是一律提示安装 clang
+        let missing = |tool: &str| {
+            let mut cmd = Command::new("cay-definitely-missing-tool");
+            toolchain::run_with_timeout(&mut cmd, tool, Duration::from_secs(5)).unwrap_err().to_string()
+        };
+        assert!(missing("opt").contains("opt 随 LLVM 一起发布"), "{}", missing("opt"));
+        assert!(missing("ir2exe").contains("ir2exe 随 Cavvy 编译器一起发布"), "{}", missing("ir2exe"));
+        assert!(missing("clang").contains("clang 在 PATH 中"), "{}", missing("clang"));
+        assert!(!missing("ld").contains("clang"), "{}", missing("ld"));
+    }
+
+    #[test]
+    fn test_virtual_dispatch() {
+        let source = "public class Animal { public String speak() { return \"...\"; } public int legs() { return 4; } } \
+                      public class Dog extends Animal { public String speak() { return \"Woof\"; } } \
+                      public class Main { public static void show(Animal a) { println(a.speak()); println(a.legs()); } \
+                        public static void main() { show(new Animal()); show(new Dog()); } }";
+        let ir = Compiler::new().compile_to_ir(source).unwrap();
+        // 子类虚表覆盖父类槽位，继承的方法沿用父类实现
+        assert!(ir.contains("@Animal.vtable = internal constant [2 x i8*] [i8* bitcast (i8* (i8*)* @Animal.speak to i8*), i8* bitcast (i32 (i8*)* @Animal.legs to i8*)]"), "{}", ir);
+        assert!(ir.contains("@Dog.vtable = internal constant [2 x i8*] [i8* bitcast (i8* (i8*)* @Dog.speak to i8*), i8* bitcast (i32 (i8*)* @Animal.legs to i8*)]"), "{}", ir);
+        assert!(ir.contains("store i8** getelementptr inbounds ([2 x i8*], [2 x i8*]* @Dog.vtable, i64 0, i64 0)"));
+        // 被覆盖的方法间接调用，未被覆盖的方法直接调用
+        assert!(ir.contains("bitcast i8* %t") && ir.contains(" to i8* (i8*)*"));
+        assert!(ir.contains("call i32 @Animal.legs(i8* "));
+        assert!(!ir.contains("call i8* @Animal.speak("));
+    }
+}
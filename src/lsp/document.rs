@@ -0,0 +1,119 @@
+//! 打开的文档及位置换算
+//!
+//! 协议中的位置是 `(行, 列)`，均从 0 开始，列按 UTF-16 代码单元计数；
+//! 编译器的位置从 1 开始，列按字符计数。这里的函数负责两者之间的换算和增量修改的应用。
+
+use serde_json::{json, Value};
+use super::index::{Pos, SymbolIndex};
+
+/// 编辑器中打开的一个文档
+pub struct Document {
+    pub text: String,
+    pub version: i64,
+    /// 文件路径（`file://` URI 以外的文档为 `None`），用于解析 import 和 `#include`
+    pub path: Option<String>,
+    /// 最近一次成功解析得到的符号索引；源码暂时无法解析时保留上一次的结果
+    pub index: Option<SymbolIndex>,
+}
+
+impl Document {
+    pub fn new(text: String, version: i64, path: Option<String>) -> Self {
+        Self { text, version, path, index: None }
+    }
+
+    /// 应用一个 `TextDocumentContentChangeEvent`：带 `range` 时替换该范围，否则替换全文
+    pub fn apply_change(&mut self, change: &Value) {
+        let text = change["text"].as_str().unwrap_or_default();
+        match change.get("range") {
+            Some(range) if !range.is_null() => {
+                let start = self.offset_at(&range["start"]);
+                let end = self.offset_at(&range["end"]).max(start);
+                self.text.replace_range(start..end, text);
+            }
+            _ => self.text = text.to_string(),
+        }
+    }
+
+    /// 协议位置对应的字节偏移，超出文档的位置截断到行尾或文档末尾
+    pub fn offset_at(&self, position: &Value) -> usize {
+        let line = position["line"].as_u64().unwrap_or(0) as usize;
+        let character = position["character"].as_u64().unwrap_or(0) as usize;
+        let mut offset = 0;
+        for (i, line_text) in self.text.split_inclusive('\n').enumerate() {
+            if i < line {
+                offset += line_text.len();
+                continue;
+            }
+            let mut units = 0;
+            for (byte, c) in line_text.char_indices() {
+                if units >= character || c == '\n' || c == '\r' {
+                    return offset + byte;
+                }
+                units += c.len_utf16();
+            }
+            return offset + line_text.len();
+        }
+        self.text.len()
+    }
+
+    /// 协议位置对应的编译器位置
+    pub fn pos_at(&self, position: &Value) -> Pos {
+        let line = position["line"].as_u64().unwrap_or(0) as usize;
+        let character = position["character"].as_u64().unwrap_or(0) as usize;
+        let mut units = 0;
+        let mut column = 1;
+        for c in self.line_text(line + 1).chars() {
+            if units >= character {
+                break;
+            }
+            units += c.len_utf16();
+            column += 1;
+        }
+        (line + 1, column)
+    }
+
+    /// 编译器位置对应的协议位置
+    pub fn position(&self, pos: Pos) -> Value {
+        let (line, column) = pos;
+        let character: usize = self.line_text(line).chars()
+            .take(column.saturating_sub(1))
+            .map(char::len_utf16)
+            .sum();
+        json!({ "line": line.saturating_sub(1), "character": character })
+    }
+
+    /// 从 `pos` 开始、长 `len` 个字符的协议范围
+    pub fn range(&self, pos: Pos, len: usize) -> Value {
+        json!({ "start": self.position(pos), "end": self.position((pos.0, pos.1 + len)) })
+    }
+
+    /// 第 `line` 行（从 1 开始）的文本，不含换行符
+    pub fn line_text(&self, line: usize) -> &str {
+        self.text.lines().nth(line.saturating_sub(1)).unwrap_or_default()
+    }
+}
+
+/// `file://` URI 对应的文件路径，其他 URI 返回 `None`
+pub fn uri_to_path(uri: &str) -> Option<String> {
+    let path = uri.strip_prefix("file://")?;
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = path.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            decoded.push(byte);
+            i += 3;
+            continue;
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    let path = String::from_utf8_lossy(&decoded).to_string();
+    // Windows 路径 file:///C:/dir 去掉盘符前的斜杠
+    match path.as_bytes() {
+        [b'/', drive, b':', ..] if drive.is_ascii_alphabetic() => Some(path[1..].to_string()),
+        _ => Some(path),
+    }
+}
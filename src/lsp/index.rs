@@ -0,0 +1,471 @@
+//! 符号索引
+//!
+//! 从入口模块的语法树和令牌流收集声明（类、接口、方法、字段、构造函数、参数、局部变量）
+//! 及局部变量的可见范围，用于跳转到定义和悬停提示。标识符表达式不带位置，
+//! 因此按光标处的令牌解析名称：`a.b` 中的 `b` 在 `a` 的类型中查找成员，
+//! 其余名称依次在可见的局部变量和参数、所在类（及父类）的成员、类型和顶层函数中查找。
+
+use crate::ast::*;
+use crate::lexer::{Token, TokenWithLocation};
+use crate::types::{ParameterInfo, Type};
+
+/// 源码位置 `(行, 列)`，均从 1 开始，列按字符计数
+pub type Pos = (usize, usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Class,
+    Interface,
+    Method,
+    Field,
+    Constructor,
+    Parameter,
+    Local,
+    Function,
+}
+
+impl SymbolKind {
+    /// 悬停提示中显示的种类名称
+    pub fn description(self) -> &'static str {
+        match self {
+            SymbolKind::Class => "类",
+            SymbolKind::Interface => "接口",
+            SymbolKind::Method => "方法",
+            SymbolKind::Field => "字段",
+            SymbolKind::Constructor => "构造函数",
+            SymbolKind::Parameter => "参数",
+            SymbolKind::Local => "局部变量",
+            SymbolKind::Function => "函数",
+        }
+    }
+}
+
+/// 一个声明
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// 变量、参数和字段的类型，方法和函数的返回类型
+    pub ty: Option<Type>,
+    /// 成员所在的类或接口
+    pub container: Option<String>,
+    /// 名称在源码中的位置
+    pub def: Pos,
+    /// 局部变量和参数的可见范围（从声明到所在代码块结束）
+    pub scope: Option<(Pos, Pos)>,
+    /// auto/var/let 局部变量的声明节点，语义分析推断出类型后据此填入 `ty`
+    decl_id: Option<NodeId>,
+    modifiers: Vec<Modifier>,
+    params: Vec<ParameterInfo>,
+    supertypes: (Option<String>, Vec<String>),
+}
+
+impl Symbol {
+    /// 悬停提示中显示的声明
+    pub fn signature(&self) -> String {
+        let ty = |t: &Option<Type>| t.as_ref().map_or("var".to_string(), |t| t.source_name());
+        let mut prefix = String::new();
+        for (modifier, text) in [(Modifier::Static, "static "), (Modifier::Final, "final ")] {
+            if self.modifiers.contains(&modifier) {
+                prefix.push_str(text);
+            }
+        }
+        let params = self.params.iter()
+            .map(|p| format!("{}{} {}", p.param_type.source_name(), if p.is_varargs { "..." } else { "" }, p.name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let owner = self.container.as_ref().map_or(String::new(), |c| format!("{}.", c));
+        match self.kind {
+            SymbolKind::Class | SymbolKind::Interface => {
+                let mut text = format!("{} {}", if self.kind == SymbolKind::Class { "class" } else { "interface" }, self.name);
+                let (parent, interfaces) = &self.supertypes;
+                if let Some(parent) = parent {
+                    text.push_str(&format!(" extends {}", parent));
+                }
+                if !interfaces.is_empty() {
+                    text.push_str(&format!(" implements {}", interfaces.join(", ")));
+                }
+                text
+            }
+            SymbolKind::Method | SymbolKind::Function => {
+                format!("{}{} {}{}({})", prefix, ty(&self.ty), owner, self.name, params)
+            }
+            SymbolKind::Constructor => format!("{}({})", self.name, params),
+            SymbolKind::Field => format!("{}{} {}{}", prefix, ty(&self.ty), owner, self.name),
+            SymbolKind::Parameter | SymbolKind::Local => format!("{}{} {}", prefix, ty(&self.ty), self.name),
+        }
+    }
+}
+
+/// 类或接口声明体的范围及其父类
+struct TypeRange {
+    name: String,
+    parent: Option<String>,
+    start: Pos,
+    end: Pos,
+}
+
+/// 一个文档的符号索引
+pub struct SymbolIndex {
+    symbols: Vec<Symbol>,
+    tokens: Vec<TokenWithLocation>,
+    types: Vec<TypeRange>,
+}
+
+fn pos_of(token: &TokenWithLocation) -> Pos {
+    (token.loc.line, token.loc.column)
+}
+
+impl SymbolIndex {
+    /// 为入口模块 `program` 建立索引，`tokens` 为解析它的令牌流
+    pub fn build(program: &Program, tokens: Vec<TokenWithLocation>) -> Self {
+        let mut index = Self { symbols: Vec::new(), tokens, types: Vec::new() };
+        for class in &program.classes {
+            index.add_class(class);
+        }
+        for interface in &program.interfaces {
+            let def = index.find_name(&interface.name, (interface.loc.line, interface.loc.column));
+            let end = index.matching_brace(def);
+            index.types.push(TypeRange { name: interface.name.clone(), parent: None, start: def, end });
+            index.push(Symbol::new(&interface.name, SymbolKind::Interface, None, None, def));
+            for method in &interface.methods {
+                index.add_method(&interface.name, method);
+            }
+        }
+        for function in &program.top_level_functions {
+            let def = index.find_name(&function.name, (function.loc.line, function.loc.column));
+            let mut symbol = Symbol::new(&function.name, SymbolKind::Function, Some(function.return_type.clone()), None, def);
+            symbol.params = function.params.clone();
+            symbol.modifiers = function.modifiers.clone();
+            index.push(symbol);
+            let end = index.matching_brace((function.body.loc.line, function.body.loc.column));
+            index.add_params(&function.params, def, end);
+            index.add_block(&function.body);
+        }
+        index
+    }
+
+    /// 填入语义分析推断出的 auto/var/let 局部变量类型
+    pub fn apply_inferred_types(&mut self, inferred: &NodeMap<Type>) {
+        for symbol in &mut self.symbols {
+            if let Some(id) = symbol.decl_id
+                && let Some(ty) = inferred.get(&id)
+            {
+                symbol.ty = Some(ty.clone());
+            }
+        }
+    }
+
+    /// 光标位置处的标识符所指的声明及该标识符的位置
+    pub fn symbol_at(&self, pos: Pos) -> Option<(&Symbol, Pos)> {
+        let index = self.tokens.partition_point(|t| pos_of(t) <= pos).checked_sub(1)?;
+        let token = &self.tokens[index];
+        let Token::Identifier(name) = &token.token else {
+            return None;
+        };
+        let start = pos_of(token);
+        if start.0 != pos.0 || pos.1 >= start.1 + name.chars().count().max(1) {
+            return None;
+        }
+        self.resolve_token(index).map(|s| (s, start))
+    }
+
+    /// 文档中的每个标识符：位置、名称及其所指的声明（无法解析时为 `None`）
+    pub fn identifiers(&self) -> Vec<(Pos, &str, Option<&Symbol>)> {
+        self.tokens.iter().enumerate()
+            .filter_map(|(i, token)| match &token.token {
+                Token::Identifier(name) if !name.is_empty() => Some((pos_of(token), name.as_str(), self.resolve_token(i))),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    /// 与该类型处于同一继承层次的类型（经由 extends/implements 相连，包括它自己）
+    pub fn related_types(&self, name: &str) -> Vec<String> {
+        let edges: Vec<(&str, &str)> = self.symbols.iter()
+            .filter(|s| matches!(s.kind, SymbolKind::Class | SymbolKind::Interface))
+            .flat_map(|s| {
+                let (parent, interfaces) = &s.supertypes;
+                parent.iter().chain(interfaces).map(move |t| (s.name.as_str(), t.as_str()))
+            })
+            .collect();
+        let mut related = vec![name.to_string()];
+        let mut i = 0;
+        while i < related.len() {
+            let current = related[i].clone();
+            for &(sub, sup) in &edges {
+                let next = if sub == current { sup } else if sup == current { sub } else { continue };
+                if !related.iter().any(|r| r == next) {
+                    related.push(next.to_string());
+                }
+            }
+            i += 1;
+        }
+        related
+    }
+
+    /// 第 `index` 个令牌（标识符）所指的声明
+    fn resolve_token(&self, index: usize) -> Option<&Symbol> {
+        let token = &self.tokens[index];
+        let Token::Identifier(name) = &token.token else {
+            return None;
+        };
+        let start = pos_of(token);
+
+        // 声明的名称
+        if let Some(symbol) = self.symbols.iter().find(|s| s.def == start && &s.name == name) {
+            return Some(symbol);
+        }
+
+        if index >= 2 && self.tokens[index - 1].token == Token::Dot {
+            self.resolve_member(index - 2, name)
+        } else {
+            self.resolve_name(name, start)
+        }
+    }
+
+    /// 解析 `receiver.name` 中的 `name`，`receiver` 为点号之前的令牌
+    fn resolve_member(&self, receiver: usize, name: &str) -> Option<&Symbol> {
+        let receiver_pos = pos_of(&self.tokens[receiver]);
+        let class_name = match &self.tokens[receiver].token {
+            Token::This => self.type_at(receiver_pos).map(|t| t.name.clone()),
+            Token::Super => self.type_at(receiver_pos).and_then(|t| t.parent.clone()),
+            Token::Identifier(receiver_name) => match self.resolve_name(receiver_name, receiver_pos) {
+                Some(symbol) if matches!(symbol.kind, SymbolKind::Class | SymbolKind::Interface) => Some(symbol.name.clone()),
+                Some(symbol) => match &symbol.ty {
+                    Some(Type::Object(class_name)) | Some(Type::Generic(class_name, _)) => Some(class_name.clone()),
+                    _ => None,
+                },
+                None => None,
+            },
+            _ => None,
+        };
+        match class_name {
+            Some(class_name) => self.lookup_member(&class_name, name),
+            // 无法确定接收者的类型（如调用结果）：按成员名在所有类型中查找
+            None => self.symbols.iter().find(|s| s.name == name && matches!(s.kind, SymbolKind::Field | SymbolKind::Method)),
+        }
+    }
+
+    /// 解析不带接收者的名称
+    fn resolve_name(&self, name: &str, pos: Pos) -> Option<&Symbol> {
+        let local = self.symbols.iter()
+            .filter(|s| s.name == name && s.scope.is_some_and(|(start, end)| start <= pos && pos <= end))
+            .max_by_key(|s| s.def);
+        if local.is_some() {
+            return local;
+        }
+        if let Some(current) = self.type_at(pos)
+            && let Some(member) = self.lookup_member(&current.name, name)
+        {
+            return Some(member);
+        }
+        self.symbols.iter().find(|s| {
+            s.name == name && matches!(s.kind, SymbolKind::Class | SymbolKind::Interface | SymbolKind::Function)
+        })
+    }
+
+    /// 在类及其父类中查找字段或方法
+    fn lookup_member(&self, class_name: &str, name: &str) -> Option<&Symbol> {
+        let mut current = Some(class_name.to_string());
+        let mut visited = 0;
+        while let Some(class_name) = current {
+            let member = self.symbols.iter().find(|s| {
+                s.name == name
+                    && s.container.as_deref() == Some(class_name.as_str())
+                    && matches!(s.kind, SymbolKind::Field | SymbolKind::Method)
+            });
+            if member.is_some() {
+                return member;
+            }
+            // 继承关系有环时语义分析会报错，这里只需避免死循环
+            visited += 1;
+            if visited > self.types.len() {
+                return None;
+            }
+            current = self.types.iter().find(|t| t.name == class_name).and_then(|t| t.parent.clone());
+        }
+        None
+    }
+
+    /// 包含该位置的类或接口
+    fn type_at(&self, pos: Pos) -> Option<&TypeRange> {
+        self.types.iter().find(|t| t.start <= pos && pos <= t.end)
+    }
+
+    fn push(&mut self, symbol: Symbol) {
+        self.symbols.push(symbol);
+    }
+
+    fn add_class(&mut self, class: &ClassDecl) {
+        let def = self.find_name(&class.name, (class.loc.line, class.loc.column));
+        let end = self.matching_brace(def);
+        self.types.push(TypeRange { name: class.name.clone(), parent: class.parent.clone(), start: def, end });
+        let mut symbol = Symbol::new(&class.name, SymbolKind::Class, None, None, def);
+        symbol.modifiers = class.modifiers.clone();
+        symbol.supertypes = (class.parent.clone(), class.interfaces.clone());
+        self.push(symbol);
+
+        for member in &class.members {
+            match member {
+                ClassMember::Field(field) => {
+                    let def = self.find_name(&field.name, (field.loc.line, field.loc.column));
+                    let mut symbol = Symbol::new(&field.name, SymbolKind::Field, Some(field.field_type.clone()), Some(&class.name), def);
+                    symbol.modifiers = field.modifiers.clone();
+                    self.push(symbol);
+                }
+                ClassMember::Method(method) => self.add_method(&class.name, method),
+                ClassMember::Constructor(constructor) => {
+                    let def = self.find_name(&class.name, (constructor.loc.line, constructor.loc.column));
+                    let mut symbol = Symbol::new(&class.name, SymbolKind::Constructor, None, Some(&class.name), def);
+                    symbol.params = constructor.params.clone();
+                    self.push(symbol);
+                    let end = self.matching_brace((constructor.body.loc.line, constructor.body.loc.column));
+                    self.add_params(&constructor.params, def, end);
+                    self.add_block(&constructor.body);
+                }
+                ClassMember::Destructor(destructor) => self.add_block(&destructor.body),
+                ClassMember::InstanceInitializer(block) | ClassMember::StaticInitializer(block) => self.add_block(block),
+            }
+        }
+    }
+
+    fn add_method(&mut self, owner: &str, method: &MethodDecl) {
+        let def = self.find_name(&method.name, (method.loc.line, method.loc.column));
+        let mut symbol = Symbol::new(&method.name, SymbolKind::Method, Some(method.return_type.clone()), Some(owner), def);
+        symbol.modifiers = method.modifiers.clone();
+        symbol.params = method.params.clone();
+        self.push(symbol);
+        if let Some(body) = &method.body {
+            let end = self.matching_brace((body.loc.line, body.loc.column));
+            self.add_params(&method.params, def, end);
+            self.add_block(body);
+        }
+    }
+
+    /// 参数在从声明名称到函数体结束的范围内可见
+    fn add_params(&mut self, params: &[ParameterInfo], after: Pos, end: Pos) {
+        let mut from = after;
+        for param in params {
+            let def = self.find_name(&param.name, from);
+            from = def;
+            let mut symbol = Symbol::new(&param.name, SymbolKind::Parameter, Some(param.param_type.clone()), None, def);
+            symbol.scope = Some((def, end));
+            if param.is_final {
+                symbol.modifiers.push(Modifier::Final);
+            }
+            self.push(symbol);
+        }
+    }
+
+    fn add_block(&mut self, block: &Block) {
+        let end = self.matching_brace((block.loc.line, block.loc.column));
+        for stmt in &block.statements {
+            self.add_stmt(stmt, end);
+        }
+    }
+
+    /// 收集语句中声明的局部变量，`end` 为所在代码块的结束位置
+    fn add_stmt(&mut self, stmt: &Stmt, end: Pos) {
+        match stmt {
+            Stmt::VarDecl(decl) => {
+                let def = self.find_name(&decl.name, (decl.loc.line, decl.loc.column));
+                let ty = (decl.var_type != Type::Auto).then(|| decl.var_type.clone());
+                let mut symbol = Symbol::new(&decl.name, SymbolKind::Local, ty, None, def);
+                symbol.scope = Some((def, end));
+                symbol.decl_id = Some(decl.id);
+                if decl.is_final {
+                    symbol.modifiers.push(Modifier::Final);
+                }
+                self.push(symbol);
+            }
+            Stmt::If(if_stmt) => {
+                self.add_stmt(&if_stmt.then_branch, end);
+                if let Some(else_branch) = &if_stmt.else_branch {
+                    self.add_stmt(else_branch, end);
+                }
+            }
+            Stmt::While(while_stmt) => self.add_stmt(&while_stmt.body, end),
+            Stmt::DoWhile(do_while) => self.add_stmt(&do_while.body, end),
+            Stmt::For(for_stmt) => {
+                // for 的初始化变量只在循环体内可见
+                let body_end = match for_stmt.body.as_ref() {
+                    Stmt::Block(body) => self.matching_brace((body.loc.line, body.loc.column)),
+                    _ => end,
+                };
+                if let Some(init) = &for_stmt.init {
+                    self.add_stmt(init, body_end);
+                }
+                self.add_stmt(&for_stmt.body, end);
+            }
+            Stmt::ForEach(for_each) => {
+                // 循环变量只在循环体内可见
+                let body_end = match for_each.body.as_ref() {
+                    Stmt::Block(body) => self.matching_brace((body.loc.line, body.loc.column)),
+                    _ => end,
+                };
+                let def = self.find_name(&for_each.var_name, (for_each.loc.line, for_each.loc.column));
+                let ty = (for_each.var_type != Type::Auto).then(|| for_each.var_type.clone());
+                let mut symbol = Symbol::new(&for_each.var_name, SymbolKind::Local, ty, None, def);
+                symbol.scope = Some((def, body_end));
+                if for_each.is_final {
+                    symbol.modifiers.push(Modifier::Final);
+                }
+                self.push(symbol);
+                self.add_stmt(&for_each.body, end);
+            }
+            Stmt::Switch(switch) => {
+                for stmt in switch.cases.iter().flat_map(|c| &c.body).chain(switch.default.iter().flatten()) {
+                    self.add_stmt(stmt, end);
+                }
+            }
+            Stmt::Block(block) => self.add_block(block),
+            Stmt::Labeled(labeled) => self.add_stmt(&labeled.body, end),
+            Stmt::Expr(_) | Stmt::Return(_) | Stmt::Break(_) | Stmt::Continue(_) => {}
+        }
+    }
+
+    /// 从 `from` 开始第一个名为 `name` 的标识符令牌的位置，找不到时返回 `from`
+    fn find_name(&self, name: &str, from: Pos) -> Pos {
+        let start = self.tokens.partition_point(|t| pos_of(t) < from);
+        self.tokens[start..].iter()
+            .find(|t| matches!(&t.token, Token::Identifier(n) if n == name))
+            .map_or(from, pos_of)
+    }
+
+    /// 从 `from` 开始的第一个 `{` 对应的 `}` 的位置，没有配对时返回文档末尾
+    fn matching_brace(&self, from: Pos) -> Pos {
+        let start = self.tokens.partition_point(|t| pos_of(t) < from);
+        let mut depth = 0usize;
+        for token in &self.tokens[start..] {
+            match token.token {
+                Token::LBrace => depth += 1,
+                Token::RBrace if depth == 1 => return pos_of(token),
+                Token::RBrace => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+        self.tokens.last().map_or(from, pos_of)
+    }
+}
+
+impl Symbol {
+    fn new(name: &str, kind: SymbolKind, ty: Option<Type>, container: Option<&str>, def: Pos) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+            ty,
+            container: container.map(str::to_string),
+            def,
+            scope: None,
+            decl_id: None,
+            modifiers: Vec::new(),
+            params: Vec::new(),
+            supertypes: (None, Vec::new()),
+        }
+    }
+}
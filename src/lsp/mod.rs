@@ -0,0 +1,256 @@
+//! 语言服务器（cay-lsp）
+//!
+//! 通过标准输入输出实现语言服务器协议（LSP），供 VS Code 等编辑器集成：
+//! - `textDocument/didOpen`、`didChange`（增量同步）、`didClose`
+//! - 每次修改后重新分析文档（预处理、解析、语义分析，不生成代码），发布 `textDocument/publishDiagnostics`
+//! - `textDocument/definition`：按符号索引跳转到声明
+//! - `textDocument/hover`：显示声明及其类型，auto/var/let 变量显示语义分析推断出的类型
+//! - `textDocument/rename`：重命名符号及其所有引用（见 [`crate::refactor`]）
+//!
+//! 只处理入口文档中的声明；被导入模块中的错误显示在文档开头。
+
+pub mod protocol;
+pub mod document;
+pub mod index;
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use serde_json::{json, Value};
+use crate::diagnostics::Diagnostic;
+use crate::error::cayResult;
+use crate::{generics, lexer, modules, parser, preprocessor, semantic};
+use document::{uri_to_path, Document};
+use index::SymbolIndex;
+use protocol::{error_response, notification, response, INVALID_PARAMS, METHOD_NOT_FOUND, REQUEST_FAILED};
+
+/// 文本同步方式：增量
+const SYNC_INCREMENTAL: i64 = 2;
+/// 诊断严重级别：错误
+const SEVERITY_ERROR: i64 = 1;
+
+/// 请求失败时的错误码和消息
+type RequestError = (i64, String);
+/// 光标处找到的符号：所在文档、声明和光标处标识符的位置
+type Found<'a> = (&'a Document, &'a index::Symbol, index::Pos);
+
+/// 语言服务器状态
+pub struct Server {
+    documents: HashMap<String, Document>,
+    shutdown_requested: bool,
+    exit_code: Option<i32>,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self { documents: HashMap::new(), shutdown_requested: false, exit_code: None }
+    }
+
+    /// 收到 `exit` 通知后的进程退出码（之前收到过 `shutdown` 时为 0）
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    /// 处理一条客户端消息，返回要发给客户端的响应和通知
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let Some(id) = message.get("id") else {
+            return self.handle_notification(method, params);
+        };
+        if method.is_empty() {
+            // 客户端对服务器请求的响应，服务器不发请求，忽略
+            return Vec::new();
+        }
+
+        let result = match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": { "openClose": true, "change": SYNC_INCREMENTAL },
+                    "definitionProvider": true,
+                    "hoverProvider": true,
+                    "renameProvider": true,
+                },
+                "serverInfo": { "name": "cay-lsp", "version": crate::cavvy_version() },
+            })),
+            "shutdown" => {
+                self.shutdown_requested = true;
+                Ok(Value::Null)
+            }
+            "textDocument/definition" => self.definition(params),
+            "textDocument/hover" => self.hover(params),
+            "textDocument/rename" => self.rename(params),
+            _ => Err((METHOD_NOT_FOUND, format!("Unsupported method: {}", method))),
+        };
+        vec![match result {
+            Ok(result) => response(id, result),
+            Err((code, message)) => error_response(id, code, &message),
+        }]
+    }
+
+    fn handle_notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_string();
+        match method {
+            "exit" => {
+                self.exit_code = Some(if self.shutdown_requested { 0 } else { 1 });
+                Vec::new()
+            }
+            "textDocument/didOpen" => {
+                let document = &params["textDocument"];
+                let text = document["text"].as_str().unwrap_or_default().to_string();
+                let version = document["version"].as_i64().unwrap_or(0);
+                self.documents.insert(uri.clone(), Document::new(text, version, uri_to_path(&uri)));
+                self.analyze(&uri)
+            }
+            "textDocument/didChange" => {
+                let Some(document) = self.documents.get_mut(&uri) else {
+                    return Vec::new();
+                };
+                for change in params["contentChanges"].as_array().into_iter().flatten() {
+                    document.apply_change(change);
+                }
+                document.version = params["textDocument"]["version"].as_i64().unwrap_or(document.version);
+                self.analyze(&uri)
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                vec![notification("textDocument/publishDiagnostics", json!({ "uri": uri, "diagnostics": [] }))]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// 重新分析文档，更新符号索引并返回诊断通知
+    fn analyze(&mut self, uri: &str) -> Vec<Value> {
+        let Some(document) = self.documents.get_mut(uri) else {
+            return Vec::new();
+        };
+        let file = document.path.clone().unwrap_or_else(|| uri.to_string());
+        let mut index = None;
+        let diagnostics = match check_document(&document.text, document.path.as_deref(), &mut index) {
+            Ok(()) => Vec::new(),
+            Err(error) => Diagnostic::from_error(&error, &file),
+        };
+        if index.is_some() {
+            document.index = index;
+        }
+
+        let diagnostics: Vec<Value> = diagnostics.iter().map(|d| {
+            let range = match &d.location {
+                Some(loc) if d.file == file => {
+                    let (column, width) = d.highlight(document.line_text(loc.line));
+                    document.range((loc.line, column), width)
+                }
+                _ => document.range((1, 1), 0),
+            };
+            let message = match (d.file == file, &d.help) {
+                (true, Some(help)) => format!("{}\n提示: {}", d.message, help),
+                (true, None) => d.message.clone(),
+                (false, _) => format!("{}: {}", d.file, d.message),
+            };
+            json!({
+                "range": range,
+                "severity": SEVERITY_ERROR,
+                "code": d.code.to_string(),
+                "source": "cavvy",
+                "message": message,
+            })
+        }).collect();
+        vec![notification("textDocument/publishDiagnostics", json!({
+            "uri": uri,
+            "version": document.version,
+            "diagnostics": diagnostics,
+        }))]
+    }
+
+    /// 光标处的文档和符号所在的位置
+    fn lookup(&self, params: &Value) -> Result<Option<Found<'_>>, RequestError> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let document = self.documents.get(uri)
+            .ok_or_else(|| (INVALID_PARAMS, format!("Document is not open: {}", uri)))?;
+        let pos = document.pos_at(&params["position"]);
+        Ok(document.index.as_ref()
+            .and_then(|index| index.symbol_at(pos))
+            .map(|(symbol, at)| (document, symbol, at)))
+    }
+
+    fn definition(&self, params: &Value) -> Result<Value, RequestError> {
+        let uri = params["textDocument"]["uri"].clone();
+        Ok(match self.lookup(params)? {
+            Some((document, symbol, _)) => json!({
+                "uri": uri,
+                "range": document.range(symbol.def, symbol.name.chars().count()),
+            }),
+            None => Value::Null,
+        })
+    }
+
+    fn hover(&self, params: &Value) -> Result<Value, RequestError> {
+        Ok(match self.lookup(params)? {
+            Some((document, symbol, at)) => json!({
+                "contents": {
+                    "kind": "markdown",
+                    "value": format!("```cavvy\n{}\n```\n{}", symbol.signature(), symbol.kind.description()),
+                },
+                "range": document.range(at, symbol.name.chars().count()),
+            }),
+            None => Value::Null,
+        })
+    }
+
+    fn rename(&self, params: &Value) -> Result<Value, RequestError> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let document = self.documents.get(uri)
+            .ok_or_else(|| (INVALID_PARAMS, format!("Document is not open: {}", uri)))?;
+        let new_name = params["newName"].as_str().unwrap_or_default();
+        let pos = document.pos_at(&params["position"]);
+        let edits = crate::refactor::rename_in_source(&document.text, document.path.as_deref(), pos, new_name)
+            .map_err(|e| (REQUEST_FAILED, Diagnostic::from_error(&e, uri).first().map_or_else(|| e.to_string(), |d| d.message.clone())))?;
+        let edits: Vec<Value> = edits.iter()
+            .map(|edit| json!({
+                "range": { "start": document.position(edit.start), "end": document.position(edit.end) },
+                "newText": edit.new_text,
+            }))
+            .collect();
+        Ok(json!({ "changes": { uri: edits } }))
+    }
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 分析文档（预处理、解析、语义分析），解析成功时把入口模块的符号索引写入 `index`
+pub(crate) fn check_document(text: &str, path: Option<&str>, index: &mut Option<SymbolIndex>) -> cayResult<()> {
+    let file = path.unwrap_or("<untitled>");
+    let dir = path.and_then(|p| Path::new(p).parent()).map(Path::to_path_buf).unwrap_or_else(|| ".".into());
+    let source = preprocessor::preprocess(text, file, dir)?;
+    let tokens = lexer::lex(&source)?;
+
+    // 入口模块单独解析一次用于索引，节点 ID 与模块加载器解析入口时相同
+    let (entry, _) = parser::parse_module(tokens.clone(), 0)?;
+    let built = index.insert(SymbolIndex::build(&entry, tokens.clone()));
+
+    let program = modules::ModuleLoader::new(path).parse_program(tokens)?;
+    let program = generics::monomorphize(program)?;
+    let mut analyzer = semantic::SemanticAnalyzer::new();
+    let result = analyzer.analyze(&program);
+    built.apply_inferred_types(analyzer.inferred_var_types());
+    result
+}
+
+/// 运行语言服务器，直到收到 `exit` 通知或输入结束，返回进程退出码
+pub fn run(mut input: impl BufRead, mut output: impl Write) -> io::Result<i32> {
+    let mut server = Server::new();
+    while let Some(message) = protocol::read_message(&mut input)? {
+        for reply in server.handle(&message) {
+            protocol::write_message(&mut output, &reply)?;
+        }
+        if let Some(code) = server.exit_code() {
+            return Ok(code);
+        }
+    }
+    Ok(1)
+}
@@ -0,0 +1,66 @@
+//! JSON-RPC 消息的读写
+//!
+//! 语言服务器协议的每条消息由头部 `Content-Length: N` 和空行分隔的 N 字节 JSON 正文组成。
+
+use std::io::{self, BufRead, Write};
+use serde_json::{json, Value};
+
+/// 请求的方法不存在
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// 请求的参数无效
+pub const INVALID_PARAMS: i64 = -32602;
+/// 请求的语法正确但无法完成（如重命名会改变程序的含义）
+pub const REQUEST_FAILED: i64 = -32803;
+
+/// 读取一条消息；输入结束时返回 `None`
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            if content_length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("Content-Length")
+        {
+            let length = value.trim().parse::<usize>()
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("invalid Content-Length: {}", value.trim())))?;
+            content_length = Some(length);
+        }
+    }
+
+    let mut body = vec![0; content_length.unwrap_or(0)];
+    reader.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// 写出一条消息
+pub fn write_message(writer: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+/// 成功的响应
+pub fn response(id: &Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+/// 失败的响应
+pub fn error_response(id: &Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// 服务器发给客户端的通知
+pub fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
@@ -1,7 +1,7 @@
 use std::env;
-use std::fs;
 use std::process;
 use cavvy::Compiler;
+use cavvy::error::exit_codes;
 
 fn print_usage() {
     println!("Usage: cayc <source_file.cay> [output_file.ll]");
@@ -15,7 +15,7 @@ fn main() {
     
     if args.len() < 2 {
         print_usage();
-        process::exit(1);
+        process::exit(exit_codes::USAGE);
     }
     
     let source_path = &args[1];
@@ -31,11 +31,11 @@ fn main() {
     };
     
     // 读取源文件
-    let source = match fs::read_to_string(source_path) {
+    let source = match cavvy::source::read_source_file(source_path) {
         Ok(content) => content,
         Err(e) => {
-            eprintln!("Error reading source file '{}': {}", source_path, e);
-            process::exit(1);
+            eprintln!("Error: {}", e);
+            process::exit(e.exit_code());
         }
     };
     
@@ -54,7 +54,7 @@ fn main() {
         }
         Err(e) => {
             eprintln!("Compilation error: {}", e);
-            process::exit(1);
+            process::exit(e.exit_code());
         }
     }
 }
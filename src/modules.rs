@@ -0,0 +1,135 @@
+//! 多文件编译：模块加载器
+//!
+//! 源文件开头的 `import` 声明引用其他源文件：
+//! - `import "util/math.cay";` 按相对导入者所在目录的路径查找文件
+//! - `import util.math;` 按点分隔的模块名查找 `util/math.cay`，找不到时再查找 `util/math.cavvy`
+//!
+//! 加载器依次预处理、词法分析、解析每个被导入的模块（每个文件只加载一次，循环导入是允许的），
+//! 并把它们的类、接口和顶层函数合并到入口程序中。合并后的程序作为一个整体进行语义分析和代码生成，
+//! 因此一个模块可以直接使用其他模块中定义的类，最终生成一个 LLVM 模块。
+//! 各模块的节点 ID 连续分配，在合并后的程序中保持唯一。
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use crate::ast::{ImportDecl, ImportTarget, Program};
+use crate::error::{cayError, cayResult, semantic_error};
+use crate::lexer::TokenWithLocation;
+use crate::{cache, lexer, parser, preprocessor, source};
+
+/// 按模块名导入时依次尝试的文件扩展名
+pub const MODULE_EXTENSIONS: [&str; 2] = ["cay", "cavvy"];
+
+/// 模块加载器
+pub struct ModuleLoader {
+    /// 入口文件所在目录；从字符串编译时为当前目录
+    base_dir: PathBuf,
+    /// 已加载的模块（规范化后的绝对路径），包括入口文件
+    loaded: HashSet<PathBuf>,
+    /// 下一个模块解析时使用的起始节点 ID
+    next_id: u32,
+    /// 已加载的被导入模块（按加载顺序）及其预处理后源码的内容哈希，供编译缓存校验
+    dependencies: Vec<(String, u64)>,
+}
+
+impl ModuleLoader {
+    /// 创建加载器，`entry_path` 为入口源文件路径（从字符串编译时为 `None`）
+    pub fn new(entry_path: Option<&str>) -> Self {
+        let mut loaded = HashSet::new();
+        let base_dir = match entry_path {
+            Some(path) => {
+                if let Ok(canonical) = Path::new(path).canonicalize() {
+                    loaded.insert(canonical);
+                }
+                Path::new(path).parent().map(|p| p.to_path_buf()).unwrap_or_else(|| PathBuf::from("."))
+            }
+            None => PathBuf::from("."),
+        };
+        Self { base_dir, loaded, next_id: 0, dependencies: Vec::new() }
+    }
+
+    /// 解析入口模块的令牌流，并加载它直接或间接导入的全部模块，返回合并后的程序
+    pub fn parse_program(&mut self, tokens: Vec<TokenWithLocation>) -> cayResult<Program> {
+        let (mut program, next_id) = parser::parse_module(tokens, self.next_id)?;
+        self.next_id = next_id;
+        let base_dir = self.base_dir.clone();
+        let imports = std::mem::take(&mut program.imports);
+        self.load_imports(&imports, &base_dir, None, &mut program)?;
+        program.imports = imports;
+        Ok(program)
+    }
+
+    /// 已加载的被导入模块路径及其预处理后源码的内容哈希（不含入口文件）
+    pub fn dependencies(&self) -> &[(String, u64)] {
+        &self.dependencies
+    }
+
+    /// 加载 `imports` 引用的模块（相对 `dir` 解析），合并到 `program`
+    ///
+    /// `importer` 为发出导入的模块路径（入口文件为 `None`），找不到模块的错误位于该模块中。
+    fn load_imports(&mut self, imports: &[ImportDecl], dir: &Path, importer: Option<&str>, program: &mut Program) -> cayResult<()> {
+        for import in imports {
+            let path = resolve_import(&import.target, dir).ok_or_else(|| {
+                let error = semantic_error(
+                    import.loc.line,
+                    import.loc.column,
+                    format!("Cannot find module {} (searched in '{}')", import.target, dir.display()),
+                );
+                in_module(importer, error)
+            })?;
+            let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+            if !self.loaded.insert(canonical) {
+                continue;
+            }
+
+            let path_str = path.to_string_lossy().to_string();
+            let module = self.parse_file(&path_str).map_err(|error| in_module(Some(&path_str), error))?;
+            let module_dir = path.parent().map(|p| p.to_path_buf()).unwrap_or_else(|| PathBuf::from("."));
+            self.load_imports(&module.imports, &module_dir, Some(&path_str), program)?;
+            program.classes.extend(module.classes);
+            program.interfaces.extend(module.interfaces);
+            program.top_level_functions.extend(module.top_level_functions);
+        }
+        Ok(())
+    }
+
+    /// 预处理、词法分析并解析一个被导入的模块文件
+    fn parse_file(&mut self, path: &str) -> cayResult<Program> {
+        let preprocessed = preprocess_module(path)?;
+        self.dependencies.push((path.to_string(), cache::content_hash(preprocessed.as_bytes())));
+        let tokens = lexer::lex(&preprocessed)?;
+        let (program, next_id) = parser::parse_module(tokens, self.next_id)?;
+        self.next_id = next_id;
+        Ok(program)
+    }
+}
+
+/// 读取并预处理一个模块文件，`#include` 相对该文件所在目录解析
+pub fn preprocess_module(path: &str) -> cayResult<String> {
+    let source = source::read_source_file(path)?;
+    let dir = Path::new(path).parent().map(|p| p.to_path_buf()).unwrap_or_else(|| PathBuf::from("."));
+    preprocessor::preprocess(&source, path, dir)
+}
+
+/// 把被导入模块 `path` 中发生的错误标记为属于该模块（入口文件中的错误保持不变）
+fn in_module(path: Option<&str>, error: cayError) -> cayError {
+    match path {
+        Some(path) => cayError::Module { path: path.to_string(), error: Box::new(error) },
+        None => error,
+    }
+}
+
+/// 把 import 目标解析为存在的文件路径
+fn resolve_import(target: &ImportTarget, dir: &Path) -> Option<PathBuf> {
+    match target {
+        ImportTarget::File(path) => {
+            let path = dir.join(path);
+            path.is_file().then_some(path)
+        }
+        ImportTarget::Module(parts) => {
+            let stem: PathBuf = parts.iter().collect();
+            MODULE_EXTENSIONS.iter()
+                .map(|ext| dir.join(&stem).with_extension(ext))
+                .find(|path| path.is_file())
+        }
+    }
+}
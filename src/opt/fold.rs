@@ -0,0 +1,187 @@
+//! 常量折叠
+//!
+//! 对操作数都是字面量的运算求值。结果类型与代码生成的类型提升规则一致：
+//! int 与 long 运算得到 long，整数与浮点数运算得到浮点操作数的类型，float 与 double 运算得到 double。
+//! 运行时行为依赖具体值的运算（整数除以零、越界移位、溢出的浮点转整数）不折叠，留给生成的代码处理。
+
+use crate::ast::{BinaryOp, LiteralValue, UnaryOp};
+use crate::types::Type;
+
+/// 提升后的数值操作数
+enum Numeric {
+    Int(i32, i32),
+    Long(i64, i64),
+    Float(f32, f32),
+    Double(f64, f64),
+}
+
+fn promote(left: &LiteralValue, right: &LiteralValue) -> Option<Numeric> {
+    use LiteralValue::*;
+    Some(match (left, right) {
+        (Int32(a), Int32(b)) => Numeric::Int(*a, *b),
+        (Int32(a), Int64(b)) => Numeric::Long(*a as i64, *b),
+        (Int64(a), Int32(b)) => Numeric::Long(*a, *b as i64),
+        (Int64(a), Int64(b)) => Numeric::Long(*a, *b),
+        (Float32(a), Float32(b)) => Numeric::Float(*a, *b),
+        (Float64(a), Float64(b)) => Numeric::Double(*a, *b),
+        (Float32(a), Float64(b)) => Numeric::Double(*a as f64, *b),
+        (Float64(a), Float32(b)) => Numeric::Double(*a, *b as f64),
+        (Int32(a), Float32(b)) => Numeric::Float(*a as f32, *b),
+        (Int64(a), Float32(b)) => Numeric::Float(*a as f32, *b),
+        (Float32(a), Int32(b)) => Numeric::Float(*a, *b as f32),
+        (Float32(a), Int64(b)) => Numeric::Float(*a, *b as f32),
+        (Int32(a), Float64(b)) => Numeric::Double(*a as f64, *b),
+        (Int64(a), Float64(b)) => Numeric::Double(*a as f64, *b),
+        (Float64(a), Int32(b)) => Numeric::Double(*a, *b as f64),
+        (Float64(a), Int64(b)) => Numeric::Double(*a, *b as f64),
+        _ => return None,
+    })
+}
+
+/// 折叠二元运算，无法在编译期确定结果时返回 `None`
+pub fn fold_binary(op: BinaryOp, left: &LiteralValue, right: &LiteralValue) -> Option<LiteralValue> {
+    use LiteralValue::{Bool, String as Str};
+
+    match (left, right) {
+        (Bool(a), Bool(b)) => {
+            return Some(Bool(match op {
+                BinaryOp::And | BinaryOp::BitAnd => *a && *b,
+                BinaryOp::Or | BinaryOp::BitOr => *a || *b,
+                BinaryOp::BitXor | BinaryOp::Ne => a != b,
+                BinaryOp::Eq => a == b,
+                _ => return None,
+            }));
+        }
+        (Str(a), Str(b)) if op == BinaryOp::Add => return Some(Str(format!("{}{}", a, b))),
+        _ => {}
+    }
+
+    match promote(left, right)? {
+        Numeric::Int(a, b) => fold_int(op, a, b),
+        Numeric::Long(a, b) => fold_long(op, a, b),
+        // 代码生成只能输出有限的浮点字面量，NaN 和无穷大留到运行时计算
+        Numeric::Float(a, b) => fold_float(op, a as f64, b as f64, |v| {
+            let v = v as f32;
+            v.is_finite().then_some(LiteralValue::Float32(v))
+        }),
+        Numeric::Double(a, b) => fold_float(op, a, b, |v| v.is_finite().then_some(LiteralValue::Float64(v))),
+    }
+}
+
+macro_rules! fold_integer {
+    ($name:ident, $ty:ty, $unsigned:ty, $variant:ident) => {
+        fn $name(op: BinaryOp, a: $ty, b: $ty) -> Option<LiteralValue> {
+            let bits = <$ty>::BITS as $ty;
+            let shift_ok = (0..bits).contains(&b);
+            let value = match op {
+                BinaryOp::Add => a.wrapping_add(b),
+                BinaryOp::Sub => a.wrapping_sub(b),
+                BinaryOp::Mul => a.wrapping_mul(b),
+                BinaryOp::Div => a.checked_div(b)?,
+                BinaryOp::Mod => a.checked_rem(b)?,
+                BinaryOp::BitAnd => a & b,
+                BinaryOp::BitOr => a | b,
+                BinaryOp::BitXor => a ^ b,
+                BinaryOp::Shl if shift_ok => a << b,
+                BinaryOp::Shr if shift_ok => a >> b,
+                BinaryOp::UnsignedShr if shift_ok => ((a as $unsigned) >> b) as $ty,
+                BinaryOp::Eq => return Some(LiteralValue::Bool(a == b)),
+                BinaryOp::Ne => return Some(LiteralValue::Bool(a != b)),
+                BinaryOp::Lt => return Some(LiteralValue::Bool(a < b)),
+                BinaryOp::Le => return Some(LiteralValue::Bool(a <= b)),
+                BinaryOp::Gt => return Some(LiteralValue::Bool(a > b)),
+                BinaryOp::Ge => return Some(LiteralValue::Bool(a >= b)),
+                _ => return None,
+            };
+            Some(LiteralValue::$variant(value))
+        }
+    };
+}
+
+fold_integer!(fold_int, i32, u32, Int32);
+fold_integer!(fold_long, i64, u64, Int64);
+
+/// 浮点运算在 f64 中进行，`make` 负责按结果类型舍入；比较使用有序比较（与 fcmp o* 一致）
+fn fold_float(op: BinaryOp, a: f64, b: f64, make: impl Fn(f64) -> Option<LiteralValue>) -> Option<LiteralValue> {
+    let value = match op {
+        BinaryOp::Add => a + b,
+        BinaryOp::Sub => a - b,
+        BinaryOp::Mul => a * b,
+        BinaryOp::Div => a / b,
+        BinaryOp::Eq => return Some(LiteralValue::Bool(a == b)),
+        BinaryOp::Ne => return Some(LiteralValue::Bool(a.partial_cmp(&b).is_some_and(|o| o.is_ne()))),
+        BinaryOp::Lt => return Some(LiteralValue::Bool(a < b)),
+        BinaryOp::Le => return Some(LiteralValue::Bool(a <= b)),
+        BinaryOp::Gt => return Some(LiteralValue::Bool(a > b)),
+        BinaryOp::Ge => return Some(LiteralValue::Bool(a >= b)),
+        _ => return None,
+    };
+    make(value)
+}
+
+/// 折叠一元运算（自增自减不是常量运算）
+pub fn fold_unary(op: UnaryOp, operand: &LiteralValue) -> Option<LiteralValue> {
+    use LiteralValue::*;
+    Some(match (op, operand) {
+        (UnaryOp::Neg, Int32(v)) => Int32(v.wrapping_neg()),
+        (UnaryOp::Neg, Int64(v)) => Int64(v.wrapping_neg()),
+        (UnaryOp::Neg, Float32(v)) => Float32(-v),
+        (UnaryOp::Neg, Float64(v)) => Float64(-v),
+        (UnaryOp::Not, Bool(v)) => Bool(!v),
+        (UnaryOp::BitNot, Int32(v)) => Int32(!v),
+        (UnaryOp::BitNot, Int64(v)) => Int64(!v),
+        _ => return None,
+    })
+}
+
+/// 把字面量转换为数值类型 `target`
+///
+/// 用于类型转换表达式和带声明类型的常量。浮点数转整数只在目标类型的范围内进行，
+/// 整数只有是合法的 Unicode 码点时才转换为 char。
+pub fn convert(value: &LiteralValue, target: &Type) -> Option<LiteralValue> {
+    use LiteralValue::*;
+    let as_f64 = match value {
+        Int32(v) => *v as f64,
+        Int64(v) => *v as f64,
+        Float32(v) => *v as f64,
+        Float64(v) => *v,
+        Char(c) => *c as u32 as f64,
+        Bool(_) if *target == Type::Bool => return Some(value.clone()),
+        _ => return None,
+    };
+    let is_float = matches!(value, Float32(_) | Float64(_));
+    let in_range = |bits: i32| as_f64.is_finite() && as_f64.trunc().abs() < 2f64.powi(bits - 1);
+    Some(match target {
+        Type::Int32 if is_float && !in_range(32) => return None,
+        Type::Int64 if is_float && !in_range(64) => return None,
+        Type::Int32 => Int32(match value {
+            Int64(v) => *v as i32,
+            _ => as_f64 as i32,
+        }),
+        Type::Int64 => Int64(match value {
+            Int64(v) => *v,
+            _ => as_f64 as i64,
+        }),
+        Type::Float32 => {
+            let v = match value {
+                Int64(v) => *v as f32,
+                Float32(v) => *v,
+                _ => as_f64 as f32,
+            };
+            if !v.is_finite() {
+                return None;
+            }
+            Float32(v)
+        }
+        Type::Float64 => Float64(match value {
+            Int64(v) => *v as f64,
+            _ => as_f64,
+        }),
+        Type::Char => match value {
+            Char(_) => value.clone(),
+            Int32(v) => Char(char::from_u32(u32::try_from(*v).ok()?)?),
+            _ => return None,
+        },
+        _ => return None,
+    })
+}
@@ -0,0 +1,30 @@
+//! 源码级优化
+//!
+//! 在语义分析之后、代码生成之前就地改写 AST：
+//! - 常量折叠：操作数都是字面量的算术、位运算、比较、逻辑运算和数值类型转换直接求值；
+//! - 常量传播：从未被重新赋值的局部变量和本类的 `static final` 常量替换为其值；
+//! - 死分支消除：条件为常量的 `if` 只保留会执行的分支，`while (false)` 整个删除。
+//!
+//! 改写只产生语义分析已经接受过的字面量，因此不需要重新分析。
+//! 由 `CompilerOptions::optimize` 控制，cayc 在 `-O1` 及以上级别启用。
+
+mod fold;
+mod propagate;
+
+pub use fold::{convert, fold_binary, fold_unary};
+pub use propagate::ConstantPropagator;
+
+use crate::ast::Program;
+use crate::error::cayResult;
+
+/// 对整个程序运行源码级优化
+pub fn optimize(program: &mut Program) -> cayResult<()> {
+    let mut propagator = ConstantPropagator::new();
+    for class in &mut program.classes {
+        propagator.optimize_class(class)?;
+    }
+    for func in &mut program.top_level_functions {
+        propagator.optimize_function(func)?;
+    }
+    Ok(())
+}
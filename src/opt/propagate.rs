@@ -0,0 +1,288 @@
+//! 常量传播与死分支消除
+//!
+//! 只传播整个方法体内从未被重新赋值（赋值、复合赋值、自增自减）的局部变量，
+//! 以及本类中初始化表达式为常量的 `static final` 字段。字符串不传播，
+//! 以免把同一个字符串对象复制成多个字面量。
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::*;
+use crate::error::{cayResult, SourceLocation};
+use crate::types::{ParameterInfo, Type};
+use crate::visit::{self, VisitMut};
+
+use super::fold::{convert, fold_binary, fold_unary};
+
+/// 收集被重新赋值的变量名
+#[derive(Default)]
+struct AssignedNames {
+    names: HashSet<String>,
+}
+
+impl VisitMut for AssignedNames {
+    fn visit_expr(&mut self, expr: &mut Expr) -> cayResult<()> {
+        match expr {
+            Expr::Assignment(assign) => {
+                if let Expr::Identifier(name) = assign.target.as_ref() {
+                    self.names.insert(name.clone());
+                }
+            }
+            Expr::Unary(unary) if matches!(unary.op, UnaryOp::PreInc | UnaryOp::PreDec | UnaryOp::PostInc | UnaryOp::PostDec) => {
+                if let Expr::Identifier(name) = unary.operand.as_ref() {
+                    self.names.insert(name.clone());
+                }
+            }
+            _ => {}
+        }
+        visit::walk_expr(self, expr)
+    }
+}
+
+/// 可以传播的字面量：数值、布尔和字符
+fn is_propagatable(lit: &LiteralValue) -> bool {
+    !matches!(lit, LiteralValue::String(_) | LiteralValue::Null)
+}
+
+/// 替换被消除语句的空语句块，沿用原语句的位置和节点 ID
+fn empty_block(loc: &SourceLocation, id: NodeId) -> Stmt {
+    Stmt::Block(Block { statements: Vec::new(), loc: loc.clone(), id })
+}
+
+#[derive(Default)]
+pub struct ConstantPropagator {
+    /// 局部作用域栈，`None` 表示该名字不是常量（同时遮蔽外层同名常量）
+    scopes: Vec<HashMap<String, Option<LiteralValue>>>,
+    /// 当前类的 `static final` 常量
+    class_constants: HashMap<String, LiteralValue>,
+    /// 当前方法体内被重新赋值的名字
+    assigned: HashSet<String>,
+}
+
+impl ConstantPropagator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 开始处理一个方法体：收集被赋值的名字，参数不是常量
+    fn enter_body(&mut self, params: &[ParameterInfo], body: &mut Block) -> cayResult<()> {
+        let mut collector = AssignedNames::default();
+        visit::walk_block(&mut collector, body)?;
+        self.assigned = collector.names;
+        self.scopes = vec![params.iter().map(|p| (p.name.clone(), None)).collect()];
+        Ok(())
+    }
+
+    fn lookup(&self, name: &str) -> Option<LiteralValue> {
+        for scope in self.scopes.iter().rev() {
+            if let Some(value) = scope.get(name) {
+                return value.clone();
+            }
+        }
+        self.class_constants.get(name).cloned()
+    }
+
+    fn declare(&mut self, name: &str, value: Option<LiteralValue>) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), value);
+        }
+    }
+
+    /// 常量按声明类型转换后的值（`var`/`auto` 保持字面量本身的类型）
+    fn constant_value(declared: &Type, init: Option<&Expr>) -> Option<LiteralValue> {
+        let Some(Expr::Literal(lit)) = init else {
+            return None;
+        };
+        if !is_propagatable(lit) {
+            return None;
+        }
+        match declared {
+            Type::Auto => Some(lit.clone()),
+            _ => convert(lit, declared),
+        }
+    }
+
+    fn with_scope(&mut self, f: impl FnOnce(&mut Self) -> cayResult<()>) -> cayResult<()> {
+        self.scopes.push(HashMap::new());
+        let result = f(self);
+        self.scopes.pop();
+        result
+    }
+
+    /// 处理一个类：先按声明顺序求出 `static final` 常量，再处理方法体
+    pub fn optimize_class(&mut self, class: &mut ClassDecl) -> cayResult<()> {
+        self.class_constants.clear();
+        for member in &mut class.members {
+            if let ClassMember::Field(field) = member {
+                self.scopes.clear();
+                self.assigned.clear();
+                if let Some(init) = &mut field.initializer {
+                    self.visit_expr(init)?;
+                }
+                let is_constant = field.modifiers.contains(&Modifier::Static) && field.modifiers.contains(&Modifier::Final);
+                if is_constant
+                    && let Some(value) = Self::constant_value(&field.field_type, field.initializer.as_ref()) {
+                    self.class_constants.insert(field.name.clone(), value);
+                }
+            }
+        }
+        for member in &mut class.members {
+            match member {
+                ClassMember::Field(_) => {}
+                ClassMember::Method(method) => {
+                    if let Some(body) = &mut method.body {
+                        self.enter_body(&method.params, body)?;
+                        visit::walk_block(self, body)?;
+                    }
+                }
+                ClassMember::Constructor(ctor) => {
+                    self.enter_body(&ctor.params, &mut ctor.body)?;
+                    if let Some(ConstructorCall::This(args) | ConstructorCall::Super(args)) = &mut ctor.constructor_call {
+                        for arg in args {
+                            self.visit_expr(arg)?;
+                        }
+                    }
+                    visit::walk_block(self, &mut ctor.body)?;
+                }
+                ClassMember::Destructor(dtor) => {
+                    self.enter_body(&[], &mut dtor.body)?;
+                    visit::walk_block(self, &mut dtor.body)?;
+                }
+                ClassMember::InstanceInitializer(block) | ClassMember::StaticInitializer(block) => {
+                    self.enter_body(&[], block)?;
+                    visit::walk_block(self, block)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 处理顶层函数（不属于任何类，没有类常量）
+    pub fn optimize_function(&mut self, func: &mut TopLevelFunction) -> cayResult<()> {
+        self.class_constants.clear();
+        self.enter_body(&func.params, &mut func.body)?;
+        visit::walk_block(self, &mut func.body)
+    }
+
+    /// 折叠子表达式已经处理过的表达式
+    fn fold(expr: &mut Expr) {
+        let folded = match expr {
+            Expr::Binary(bin) => match (bin.left.as_ref(), bin.op, bin.right.as_ref()) {
+                (Expr::Literal(l), op, Expr::Literal(r)) => fold_binary(op, l, r).map(Expr::Literal),
+                // 短路运算只需要左操作数是常量
+                (Expr::Literal(LiteralValue::Bool(false)), BinaryOp::And, _) => Some(Expr::Literal(LiteralValue::Bool(false))),
+                (Expr::Literal(LiteralValue::Bool(true)), BinaryOp::Or, _) => Some(Expr::Literal(LiteralValue::Bool(true))),
+                (Expr::Literal(LiteralValue::Bool(true)), BinaryOp::And, right)
+                | (Expr::Literal(LiteralValue::Bool(false)), BinaryOp::Or, right) => Some(right.clone()),
+                _ => None,
+            },
+            Expr::Unary(unary) => match unary.operand.as_ref() {
+                Expr::Literal(lit) => fold_unary(unary.op, lit).map(Expr::Literal),
+                _ => None,
+            },
+            Expr::Cast(cast) => match cast.expr.as_ref() {
+                Expr::Literal(lit) => convert(lit, &cast.target_type).map(Expr::Literal),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(folded) = folded {
+            *expr = folded;
+        }
+    }
+}
+
+impl VisitMut for ConstantPropagator {
+    fn visit_stmt(&mut self, stmt: &mut Stmt) -> cayResult<()> {
+        match stmt {
+            Stmt::Block(_) | Stmt::For(_) | Stmt::Switch(_) => {
+                return self.with_scope(|this| visit::walk_stmt(this, stmt));
+            }
+            Stmt::ForEach(for_each) => {
+                // 循环变量每轮取不同的元素，遮蔽外层的同名常量
+                return self.with_scope(|this| {
+                    this.visit_expr(&mut for_each.iterable)?;
+                    this.declare(&for_each.var_name, None);
+                    this.visit_stmt(&mut for_each.body)
+                });
+            }
+            Stmt::VarDecl(_) => {
+                visit::walk_stmt(self, stmt)?;
+                if let Stmt::VarDecl(var) = stmt {
+                    let value = if self.assigned.contains(&var.name) {
+                        None
+                    } else {
+                        Self::constant_value(&var.var_type, var.initializer.as_ref())
+                    };
+                    self.declare(&var.name, value);
+                }
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        visit::walk_stmt(self, stmt)?;
+
+        // 死分支消除
+        let replacement = match stmt {
+            Stmt::If(if_stmt) => match if_stmt.condition {
+                Expr::Literal(LiteralValue::Bool(true)) => {
+                    let empty = empty_block(&if_stmt.loc, if_stmt.id);
+                    Some(std::mem::replace(&mut *if_stmt.then_branch, empty))
+                }
+                Expr::Literal(LiteralValue::Bool(false)) => Some(match if_stmt.else_branch.take() {
+                    Some(else_branch) => *else_branch,
+                    None => empty_block(&if_stmt.loc, if_stmt.id),
+                }),
+                _ => None,
+            },
+            Stmt::While(while_stmt) => match while_stmt.condition {
+                Expr::Literal(LiteralValue::Bool(false)) => Some(empty_block(&while_stmt.loc, while_stmt.id)),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(replacement) = replacement {
+            *stmt = replacement;
+        }
+        Ok(())
+    }
+
+    fn visit_expr(&mut self, expr: &mut Expr) -> cayResult<()> {
+        match expr {
+            Expr::Identifier(name) => {
+                if let Some(value) = self.lookup(name) {
+                    *expr = Expr::Literal(value);
+                }
+                return Ok(());
+            }
+            // 方法名不是变量
+            Expr::Call(call) if matches!(call.callee.as_ref(), Expr::Identifier(_)) => {
+                for arg in &mut call.args {
+                    self.visit_expr(arg)?;
+                }
+                return Ok(());
+            }
+            // 赋值目标不替换
+            Expr::Assignment(assign) if matches!(assign.target.as_ref(), Expr::Identifier(_)) => {
+                return self.visit_expr(&mut assign.value);
+            }
+            Expr::Unary(unary) if matches!(unary.op, UnaryOp::PreInc | UnaryOp::PreDec | UnaryOp::PostInc | UnaryOp::PostDec) => {
+                return match unary.operand.as_mut() {
+                    Expr::Identifier(_) => Ok(()),
+                    operand => self.visit_expr(operand),
+                };
+            }
+            Expr::Lambda(lambda) => {
+                let params: HashMap<_, _> = lambda.params.iter().map(|p| (p.name.clone(), None)).collect();
+                self.scopes.push(params);
+                let result = visit::walk_expr(self, expr);
+                self.scopes.pop();
+                return result;
+            }
+            _ => {}
+        }
+        visit::walk_expr(self, expr)?;
+        Self::fold(expr);
+        Ok(())
+    }
+}
@@ -0,0 +1,20 @@
+//! 并行编译辅助
+//!
+//! `--jobs N` 大于 1 时，类型检查和代码生成按类拆分到 N 个线程的线程池中执行。
+//! 结果按输入顺序返回，调用方据此按类在程序中的顺序合并。
+
+use rayon::prelude::*;
+
+/// 在 `jobs` 个线程中对每个元素调用 `f(下标, 元素)`，按输入顺序返回结果
+pub fn map_ordered<T, R, F>(jobs: usize, items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(usize, &T) -> R + Sync,
+{
+    match rayon::ThreadPoolBuilder::new().num_threads(jobs).build() {
+        Ok(pool) => pool.install(|| items.par_iter().enumerate().map(|(i, item)| f(i, item)).collect()),
+        // 无法创建线程池时退回到顺序执行
+        Err(_) => items.iter().enumerate().map(|(i, item)| f(i, item)).collect(),
+    }
+}
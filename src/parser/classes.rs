@@ -6,9 +6,30 @@ use crate::error::cayResult;
 use crate::lexer::Token;
 use crate::error::SourceLocation;
 use super::Parser;
-use super::types::{parse_type, is_type_token};
+use super::types::{parse_type, parse_declared_type, parse_type_params, is_type_token, is_function_type_start};
 use super::expressions::parse_expression;
-use super::statements::parse_block;
+use super::statements::{parse_block, parse_statement};
+
+/// 解析 import 声明：`import "path/file.cay";` 或 `import pkg.module;`
+pub fn parse_import(parser: &mut Parser) -> cayResult<ImportDecl> {
+    let loc = parser.current_loc();
+    parser.advance(); // 'import'
+
+    let target = if let Token::StringLiteral(path) = parser.current_token() {
+        let path = path.clone().unwrap_or_default();
+        parser.advance();
+        ImportTarget::File(path)
+    } else {
+        let mut parts = vec![parser.consume_identifier("Expected module name or file path after 'import'")?];
+        while parser.match_token(&Token::Dot) {
+            parts.push(parser.consume_identifier("Expected module name after '.'")?);
+        }
+        ImportTarget::Module(parts)
+    };
+
+    parser.consume(&Token::Semicolon, "Expected ';' after import declaration")?;
+    Ok(ImportDecl { target, loc })
+}
 
 /// 解析类声明
 pub fn parse_class(parser: &mut Parser) -> cayResult<ClassDecl> {
@@ -21,6 +42,9 @@ pub fn parse_class(parser: &mut Parser) -> cayResult<ClassDecl> {
 
     let name = parser.consume_identifier("Expected class name")?;
 
+    // 泛型类型形参 class Box<T>
+    let type_params = parse_type_params(parser)?;
+
     // 支持 extends 关键字或 : 符号作为继承语法
     let parent = if parser.match_token(&Token::Extends) {
         Some(parser.consume_identifier("Expected parent class name after 'extends'")?)
@@ -55,10 +79,12 @@ pub fn parse_class(parser: &mut Parser) -> cayResult<ClassDecl> {
     Ok(ClassDecl {
         name,
         modifiers,
+        type_params,
         parent,
         interfaces,
         members,
         loc,
+        id: parser.next_node_id(),
     })
 }
 
@@ -88,6 +114,7 @@ pub fn parse_interface(parser: &mut Parser) -> cayResult<InterfaceDecl> {
         modifiers,
         methods,
         loc,
+        id: parser.next_node_id(),
     })
 }
 
@@ -100,7 +127,7 @@ fn parse_interface_method(parser: &mut Parser) -> cayResult<MethodDecl> {
         parser.advance();
         Type::Void
     } else {
-        parse_type(parser)?
+        parse_declared_type(parser, "a method return type")?
     };
 
     let name = parser.consume_identifier("Expected method name")?;
@@ -115,10 +142,12 @@ fn parse_interface_method(parser: &mut Parser) -> cayResult<MethodDecl> {
     Ok(MethodDecl {
         name,
         modifiers,
+        type_params: Vec::new(),
         return_type,
         params,
         body: None,  // 接口方法没有方法体
         loc,
+        id: parser.next_node_id(),
     })
 }
 
@@ -146,17 +175,29 @@ pub fn parse_class_member(parser: &mut Parser) -> cayResult<ClassMember> {
         return Ok(ClassMember::Destructor(parse_destructor(parser)?));
     }
     
-    // 如果是void，一定是方法返回类型
-    if parser.check(&Token::Void) {
+    // 如果是void或泛型类型形参 <T>，一定是方法
+    if parser.check(&Token::Void) || parser.check(&Token::Lt) {
         parser.pos = checkpoint;
         return Ok(ClassMember::Method(parse_method(parser)?));
     }
     
+    // 类名(...) 是构造函数（标识符也可以是类型，需要先于字段和方法判断）
+    if matches!(parser.current_token(), Token::Identifier(_)) && !is_function_type_start(parser) {
+        let current_pos = parser.pos;
+        parser.advance(); // 跳过标识符
+        let is_constructor = parser.check(&Token::LParen);
+        parser.pos = current_pos;
+        if is_constructor {
+            parser.pos = checkpoint;
+            return Ok(ClassMember::Constructor(parse_constructor(parser)?));
+        }
+    }
+
     // 如果是类型关键字，可能是字段或方法
     if is_type_token(parser) {
         // 读取类型
-        let member_type = parse_type(parser)?;
-        let member_name = parser.consume_identifier("Expected member name")?;
+        let _member_type = parse_type(parser)?;
+        let _member_name = parser.consume_identifier("Expected member name")?;
         
         if parser.check(&Token::LParen) {
             // 是方法
@@ -167,45 +208,6 @@ pub fn parse_class_member(parser: &mut Parser) -> cayResult<ClassMember> {
             parser.pos = checkpoint;
             Ok(ClassMember::Field(parse_field(parser)?))
         }
-    } else if matches!(parser.current_token(), Token::Identifier(_)) {
-        // 可能是构造函数：类名(...)
-        // 向前看：检查下一个token是否是 '('
-        let current_pos = parser.pos;
-        parser.advance(); // 跳过标识符
-        
-        if parser.check(&Token::LParen) {
-            // 是构造函数 - 回溯到checkpoint并解析
-            parser.pos = checkpoint;
-            
-            // 直接解析构造函数
-            let loc = parser.current_loc();
-            let ctor_modifiers = parse_modifiers(parser)?;
-            let _ctor_name = parser.consume_identifier("Expected constructor name")?;
-            
-            parser.consume(&Token::LParen, "Expected '(' after constructor name")?;
-            let ctor_params = parse_parameters(parser)?;
-            parser.consume(&Token::RParen, "Expected ')' after constructor parameters")?;
-            
-            // 解析构造链调用 this() 或 super()
-            let constructor_call = parse_constructor_call(parser)?;
-            
-            // 解析构造函数体
-            let ctor_body = parse_block(parser)?;
-            
-            return Ok(ClassMember::Constructor(ConstructorDecl {
-                modifiers: ctor_modifiers,
-                params: ctor_params,
-                body: ctor_body,
-                constructor_call,
-                loc,
-            }));
-        } else {
-            // 不是构造函数，回退位置
-            parser.pos = current_pos;
-            // 是字段（没有类型声明的字段，错误）
-            parser.pos = checkpoint;
-            Err(parser.error("Expected field or method declaration"))
-        }
     } else {
         Err(parser.error("Expected field, method, constructor, or destructor declaration"))
     }
@@ -215,7 +217,7 @@ pub fn parse_class_member(parser: &mut Parser) -> cayResult<ClassMember> {
 pub fn parse_field(parser: &mut Parser) -> cayResult<FieldDecl> {
     let loc = parser.current_loc();
     let modifiers = parse_modifiers(parser)?;
-    let field_type = parse_type(parser)?;
+    let field_type = parse_declared_type(parser, "a field type")?;
     let name = parser.consume_identifier("Expected field name")?;
     
     let initializer = if parser.match_token(&Token::Assign) {
@@ -232,6 +234,7 @@ pub fn parse_field(parser: &mut Parser) -> cayResult<FieldDecl> {
         modifiers,
         initializer,
         loc,
+        id: parser.next_node_id(),
     })
 }
 
@@ -239,12 +242,13 @@ pub fn parse_field(parser: &mut Parser) -> cayResult<FieldDecl> {
 pub fn parse_method(parser: &mut Parser) -> cayResult<MethodDecl> {
     let loc = parser.current_loc();
     let modifiers = parse_modifiers(parser)?;
+    let type_params = parse_type_params(parser)?;
     
     let return_type = if parser.check(&Token::Void) {
         parser.advance();
         Type::Void
     } else {
-        parse_type(parser)?
+        parse_declared_type(parser, "a method return type")?
     };
     
     let name = parser.consume_identifier("Expected method name")?;
@@ -259,6 +263,10 @@ pub fn parse_method(parser: &mut Parser) -> cayResult<MethodDecl> {
     let body = if is_native {
         parser.consume(&Token::Semicolon, "Expected ';' after native method declaration")?;
         None
+    } else if modifiers.contains(&Modifier::Abstract) {
+        // 抽象方法只有声明，由子类实现
+        parser.consume(&Token::Semicolon, "Expected ';' after abstract method declaration")?;
+        None
     } else {
         Some(parse_block(parser)?)
     };
@@ -266,10 +274,12 @@ pub fn parse_method(parser: &mut Parser) -> cayResult<MethodDecl> {
     Ok(MethodDecl {
         name,
         modifiers,
+        type_params,
         return_type,
         params,
         body,
         loc,
+        id: parser.next_node_id(),
     })
 }
 
@@ -292,7 +302,10 @@ pub fn parse_constructor(parser: &mut Parser) -> cayResult<ConstructorDecl> {
     let constructor_call = parse_constructor_call(parser)?;
     
     // 解析构造函数体
-    let body = parse_block(parser)?;
+    let (constructor_call, body) = match constructor_call {
+        Some(call) => (Some(call), parse_block(parser)?),
+        None => parse_constructor_body(parser)?,
+    };
     
     Ok(ConstructorDecl {
         modifiers,
@@ -300,6 +313,7 @@ pub fn parse_constructor(parser: &mut Parser) -> cayResult<ConstructorDecl> {
         body,
         constructor_call,
         loc,
+        id: parser.next_node_id(),
     })
 }
 
@@ -323,11 +337,38 @@ fn parse_constructor_call(parser: &mut Parser) -> cayResult<Option<ConstructorCa
         }
     }
     
-    // Java风格: this(args) 或 super(args) 作为构造函数体的第一条语句
-    // 这部分在语义分析中处理
+    // Java风格: this(args) 或 super(args) 作为构造函数体的第一条语句，由 parse_constructor_body 处理
     Ok(None)
 }
 
+/// 解析构造函数体，第一条语句可以是 Java 风格的构造链调用 `this(args);` 或 `super(args);`
+fn parse_constructor_body(parser: &mut Parser) -> cayResult<(Option<ConstructorCall>, Block)> {
+    let loc = parser.current_loc();
+    parser.consume(&Token::LBrace, "Expected '{' to start block")?;
+
+    let is_call = matches!(parser.current_token(), Token::This | Token::Super)
+        && parser.tokens.get(parser.pos + 1).is_some_and(|t| t.token == Token::LParen);
+    let constructor_call = if is_call {
+        let is_super = *parser.current_token() == Token::Super;
+        parser.advance();
+        parser.consume(&Token::LParen, "Expected '('")?;
+        let args = parse_constructor_call_args(parser)?;
+        parser.consume(&Token::RParen, "Expected ')' after constructor call arguments")?;
+        parser.consume(&Token::Semicolon, "Expected ';' after constructor call")?;
+        Some(if is_super { ConstructorCall::Super(args) } else { ConstructorCall::This(args) })
+    } else {
+        None
+    };
+
+    let mut statements = Vec::new();
+    while !parser.check(&Token::RBrace) && !parser.is_at_end() {
+        statements.push(parse_statement(parser)?);
+    }
+    parser.consume(&Token::RBrace, "Expected '}' to end block")?;
+
+    Ok((constructor_call, Block { statements, loc, id: parser.next_node_id() }))
+}
+
 /// 解析构造函数调用参数
 fn parse_constructor_call_args(parser: &mut Parser) -> cayResult<Vec<Expr>> {
     let mut args = Vec::new();
@@ -366,6 +407,7 @@ pub fn parse_destructor(parser: &mut Parser) -> cayResult<DestructorDecl> {
         modifiers,
         body,
         loc,
+        id: parser.next_node_id(),
     })
 }
 
@@ -424,6 +466,16 @@ pub fn parse_modifiers(parser: &mut Parser) -> cayResult<Vec<Modifier>> {
                 modifiers.push(Modifier::Main);
                 parser.advance();
             }
+            Token::AtSymbol => {
+                parser.advance();
+                parser.consume(&Token::LParen, "Expected '(' after '@Symbol'")?;
+                let Token::StringLiteral(Some(name)) = parser.current_token().clone() else {
+                    return Err(parser.error("Expected a string literal with the C function name in '@Symbol(...)'"));
+                };
+                parser.advance();
+                parser.consume(&Token::RParen, "Expected ')' after '@Symbol' name")?;
+                modifiers.push(Modifier::Symbol(name));
+            }
             _ => break,
         }
     }
@@ -437,8 +489,11 @@ pub fn parse_parameters(parser: &mut Parser) -> cayResult<Vec<ParameterInfo>> {
 
     if !parser.check(&Token::RParen) {
         loop {
+            // 参数可以用 final 修饰
+            let is_final = parser.match_token(&Token::Final);
+
             // 检查是否是可变参数类型（type...）
-            let param_type = parse_type(parser)?;
+            let param_type = parse_declared_type(parser, "a parameter type")?;
 
             // 检查是否有 ... 标记
             let is_varargs = parser.match_token(&Token::DotDotDot);
@@ -446,14 +501,14 @@ pub fn parse_parameters(parser: &mut Parser) -> cayResult<Vec<ParameterInfo>> {
             let name = parser.consume_identifier("Expected parameter name")?;
 
             if is_varargs {
-                params.push(ParameterInfo::new_varargs(name, param_type));
+                params.push(ParameterInfo::new_varargs(name, param_type).with_final(is_final));
                 // 可变参数必须是最后一个参数
                 if parser.match_token(&Token::Comma) {
                     return Err(parser.error("Varargs parameter must be the last parameter"));
                 }
                 break;
             } else {
-                params.push(ParameterInfo::new(name, param_type));
+                params.push(ParameterInfo::new(name, param_type).with_final(is_final));
             }
 
             if !parser.match_token(&Token::Comma) {
@@ -25,6 +25,7 @@ pub fn parse_assignment(parser: &mut Parser) -> cayResult<Expr> {
             value: Box::new(value),
             op,
             loc,
+            id: parser.next_node_id(),
         }));
     }
 
@@ -34,11 +35,11 @@ pub fn parse_assignment(parser: &mut Parser) -> cayResult<Expr> {
 /// 解析三元运算符表达式: condition ? true_expr : false_expr
 fn parse_ternary(parser: &mut Parser) -> cayResult<Expr> {
     let loc = parser.current_loc();
-    let condition = parse_or(parser)?;
+    let condition = parse_null_coalesce(parser)?;
 
     // 检查是否有 ? 标记
     if parser.match_token(&crate::lexer::Token::Question) {
-        let true_branch = Box::new(parse_or(parser)?);
+        let true_branch = Box::new(parse_null_coalesce(parser)?);
         parser.consume(&crate::lexer::Token::Colon, "Expected ':' after '?' in ternary expression")?;
         let false_branch = Box::new(parse_ternary(parser)?); // 右结合
 
@@ -47,12 +48,35 @@ fn parse_ternary(parser: &mut Parser) -> cayResult<Expr> {
             true_branch,
             false_branch,
             loc,
+            id: parser.next_node_id(),
         }));
     }
 
     Ok(condition)
 }
 
+/// 解析空值合并表达式: value ?? default 或 value ?: default
+///
+/// 优先级低于 `||`、高于三元运算符，右结合：`a ?? b ?? c` 即 `a ?? (b ?? c)`。
+fn parse_null_coalesce(parser: &mut Parser) -> cayResult<Expr> {
+    let loc = parser.current_loc();
+    let value = parse_or(parser)?;
+
+    if parser.match_token(&crate::lexer::Token::QuestionQuestion)
+        || parser.match_token(&crate::lexer::Token::Elvis)
+    {
+        let default = parse_null_coalesce(parser)?;
+        return Ok(Expr::NullCoalesce(NullCoalesceExpr {
+            value: Box::new(value),
+            default: Box::new(default),
+            loc,
+            id: parser.next_node_id(),
+        }));
+    }
+
+    Ok(value)
+}
+
 /// 匹配赋值操作符
 pub fn match_assignment_op(parser: &mut Parser) -> Option<AssignOp> {
     if parser.check(&crate::lexer::Token::Assign) {
@@ -19,6 +19,7 @@ pub fn parse_or(parser: &mut Parser) -> cayResult<Expr> {
             op: BinaryOp::Or,
             right: Box::new(right),
             loc,
+            id: parser.next_node_id(),
         });
     }
 
@@ -37,6 +38,7 @@ pub fn parse_and(parser: &mut Parser) -> cayResult<Expr> {
             op: BinaryOp::And,
             right: Box::new(right),
             loc,
+            id: parser.next_node_id(),
         });
     }
 
@@ -55,6 +57,7 @@ pub fn parse_bitwise_or(parser: &mut Parser) -> cayResult<Expr> {
             op: BinaryOp::BitOr,
             right: Box::new(right),
             loc,
+            id: parser.next_node_id(),
         });
     }
 
@@ -73,6 +76,7 @@ pub fn parse_bitwise_xor(parser: &mut Parser) -> cayResult<Expr> {
             op: BinaryOp::BitXor,
             right: Box::new(right),
             loc,
+            id: parser.next_node_id(),
         });
     }
 
@@ -91,6 +95,7 @@ pub fn parse_bitwise_and(parser: &mut Parser) -> cayResult<Expr> {
             op: BinaryOp::BitAnd,
             right: Box::new(right),
             loc,
+            id: parser.next_node_id(),
         });
     }
 
@@ -110,6 +115,7 @@ pub fn parse_equality(parser: &mut Parser) -> cayResult<Expr> {
                 op: BinaryOp::Eq,
                 right: Box::new(right),
                 loc,
+                id: parser.next_node_id(),
             });
         } else if parser.match_token(&crate::lexer::Token::NotEq) {
             let right = parse_comparison(parser)?;
@@ -118,6 +124,7 @@ pub fn parse_equality(parser: &mut Parser) -> cayResult<Expr> {
                 op: BinaryOp::Ne,
                 right: Box::new(right),
                 loc,
+                id: parser.next_node_id(),
             });
         } else {
             break;
@@ -140,6 +147,7 @@ pub fn parse_comparison(parser: &mut Parser) -> cayResult<Expr> {
                 op: BinaryOp::Lt,
                 right: Box::new(right),
                 loc,
+                id: parser.next_node_id(),
             });
         } else if parser.match_token(&crate::lexer::Token::Le) {
             let right = parse_shift(parser)?;
@@ -148,6 +156,7 @@ pub fn parse_comparison(parser: &mut Parser) -> cayResult<Expr> {
                 op: BinaryOp::Le,
                 right: Box::new(right),
                 loc,
+                id: parser.next_node_id(),
             });
         } else if parser.match_token(&crate::lexer::Token::Gt) {
             let right = parse_shift(parser)?;
@@ -156,6 +165,7 @@ pub fn parse_comparison(parser: &mut Parser) -> cayResult<Expr> {
                 op: BinaryOp::Gt,
                 right: Box::new(right),
                 loc,
+                id: parser.next_node_id(),
             });
         } else if parser.match_token(&crate::lexer::Token::Ge) {
             let right = parse_shift(parser)?;
@@ -164,6 +174,7 @@ pub fn parse_comparison(parser: &mut Parser) -> cayResult<Expr> {
                 op: BinaryOp::Ge,
                 right: Box::new(right),
                 loc,
+                id: parser.next_node_id(),
             });
         } else if parser.match_token(&crate::lexer::Token::InstanceOf) {
             // 解析 instanceof 表达式
@@ -172,6 +183,7 @@ pub fn parse_comparison(parser: &mut Parser) -> cayResult<Expr> {
                 expr: Box::new(left),
                 target_type,
                 loc,
+                id: parser.next_node_id(),
             });
         } else {
             break;
@@ -194,6 +206,7 @@ pub fn parse_shift(parser: &mut Parser) -> cayResult<Expr> {
                 op: BinaryOp::Shl,
                 right: Box::new(right),
                 loc,
+                id: parser.next_node_id(),
             });
         } else if parser.match_token(&crate::lexer::Token::Shr) {
             let right = parse_term(parser)?;
@@ -202,6 +215,7 @@ pub fn parse_shift(parser: &mut Parser) -> cayResult<Expr> {
                 op: BinaryOp::Shr,
                 right: Box::new(right),
                 loc,
+                id: parser.next_node_id(),
             });
         } else if parser.match_token(&crate::lexer::Token::UnsignedShr) {
             let right = parse_term(parser)?;
@@ -210,6 +224,7 @@ pub fn parse_shift(parser: &mut Parser) -> cayResult<Expr> {
                 op: BinaryOp::UnsignedShr,
                 right: Box::new(right),
                 loc,
+                id: parser.next_node_id(),
             });
         } else {
             break;
@@ -232,6 +247,7 @@ pub fn parse_term(parser: &mut Parser) -> cayResult<Expr> {
                 op: BinaryOp::Add,
                 right: Box::new(right),
                 loc,
+                id: parser.next_node_id(),
             });
         } else if parser.match_token(&crate::lexer::Token::Minus) {
             let right = parse_factor(parser)?;
@@ -240,6 +256,7 @@ pub fn parse_term(parser: &mut Parser) -> cayResult<Expr> {
                 op: BinaryOp::Sub,
                 right: Box::new(right),
                 loc,
+                id: parser.next_node_id(),
             });
         } else {
             break;
@@ -262,6 +279,7 @@ pub fn parse_factor(parser: &mut Parser) -> cayResult<Expr> {
                 op: BinaryOp::Mul,
                 right: Box::new(right),
                 loc,
+                id: parser.next_node_id(),
             });
         } else if parser.match_token(&crate::lexer::Token::Slash) {
             let right = parse_unary(parser)?;
@@ -270,6 +288,7 @@ pub fn parse_factor(parser: &mut Parser) -> cayResult<Expr> {
                 op: BinaryOp::Div,
                 right: Box::new(right),
                 loc,
+                id: parser.next_node_id(),
             });
         } else if parser.match_token(&crate::lexer::Token::Percent) {
             let right = parse_unary(parser)?;
@@ -278,6 +297,7 @@ pub fn parse_factor(parser: &mut Parser) -> cayResult<Expr> {
                 op: BinaryOp::Mod,
                 right: Box::new(right),
                 loc,
+                id: parser.next_node_id(),
             });
         } else {
             break;
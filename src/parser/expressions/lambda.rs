@@ -5,7 +5,8 @@
 use crate::ast::*;
 use crate::error::cayResult;
 use super::super::Parser;
-use super::super::types::{parse_type, is_type_token};
+use super::super::types::parse_type;
+use super::super::lookahead::is_typed_name;
 use super::super::statements::parse_statement;
 use super::assignment::parse_expression;
 
@@ -55,36 +56,20 @@ pub fn try_parse_lambda(parser: &mut Parser, loc: crate::error::SourceLocation)
         params,
         body,
         loc,
+        id: parser.next_node_id(),
     }))
 }
 
 /// 解析 Lambda 参数
 fn parse_lambda_param(parser: &mut Parser) -> cayResult<LambdaParam> {
-    // 检查是否有类型注解（可选）
-    let checkpoint = parser.pos;
-
-    // 尝试解析类型
-    let type_result = if is_type_token(parser) {
+    // 带类型注解的参数：类型后面紧跟参数名
+    if is_typed_name(parser) {
         let ty = parse_type(parser)?;
-        // 类型后面必须跟着标识符
-        if let crate::lexer::Token::Identifier(name) = parser.current_token() {
-            let name = name.clone();
-            parser.advance();
-            Ok(LambdaParam {
-                name,
-                param_type: Some(ty),
-            })
-        } else {
-            // 类型后面没有标识符，回退
-            parser.pos = checkpoint;
-            Err(parser.error("Expected parameter name after type"))
-        }
-    } else {
-        Err(parser.error("Expected type or parameter name"))
-    };
-
-    if let Ok(param) = type_result {
-        return Ok(param);
+        let name = parser.consume_identifier("Expected parameter name after type")?;
+        return Ok(LambdaParam {
+            name,
+            param_type: Some(ty),
+        });
     }
 
     // 没有类型注解，只有参数名
@@ -114,5 +99,6 @@ fn parse_lambda_block(parser: &mut Parser) -> cayResult<Block> {
     Ok(Block {
         statements,
         loc: crate::error::SourceLocation { line: 0, column: 0 },
+        id: crate::ast::NodeId::DUMMY,
     })
 }
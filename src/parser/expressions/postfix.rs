@@ -22,6 +22,7 @@ pub fn parse_postfix(parser: &mut Parser) -> cayResult<Expr> {
                 callee: Box::new(expr),
                 args,
                 loc,
+                id: parser.next_node_id(),
             });
         } else if parser.match_token(&crate::lexer::Token::Dot) {
             // 成员访问
@@ -29,7 +30,19 @@ pub fn parse_postfix(parser: &mut Parser) -> cayResult<Expr> {
             expr = Expr::MemberAccess(MemberAccessExpr {
                 object: Box::new(expr),
                 member,
+                null_safe: false,
                 loc,
+                id: parser.next_node_id(),
+            });
+        } else if parser.match_token(&crate::lexer::Token::QuestionDot) {
+            // 安全导航: obj?.member 或 obj?.method(...)
+            let member = parser.consume_identifier("Expected member name after '?.'")?;
+            expr = Expr::MemberAccess(MemberAccessExpr {
+                object: Box::new(expr),
+                member,
+                null_safe: true,
+                loc,
+                id: parser.next_node_id(),
             });
         } else if parser.match_token(&crate::lexer::Token::LBracket) {
             // 数组索引访问: arr[index]
@@ -39,6 +52,7 @@ pub fn parse_postfix(parser: &mut Parser) -> cayResult<Expr> {
                 array: Box::new(expr),
                 index: Box::new(index),
                 loc,
+                id: parser.next_node_id(),
             });
         } else if parser.match_token(&crate::lexer::Token::Inc) {
             // 后缀自增: i++
@@ -46,6 +60,7 @@ pub fn parse_postfix(parser: &mut Parser) -> cayResult<Expr> {
                 op: UnaryOp::PostInc,
                 operand: Box::new(expr),
                 loc,
+                id: parser.next_node_id(),
             });
         } else if parser.match_token(&crate::lexer::Token::Dec) {
             // 后缀自减: i--
@@ -53,6 +68,7 @@ pub fn parse_postfix(parser: &mut Parser) -> cayResult<Expr> {
                 op: UnaryOp::PostDec,
                 operand: Box::new(expr),
                 loc,
+                id: parser.next_node_id(),
             });
         } else {
             break;
@@ -6,7 +6,8 @@ use crate::ast::*;
 use crate::types::Type;
 use crate::error::cayResult;
 use super::super::Parser;
-use super::super::types::is_type_token;
+use super::super::types::{is_type_token, parse_type, parse_type_args};
+use super::super::lookahead::is_lambda_start;
 use super::lambda::try_parse_lambda;
 use super::assignment::parse_expression;
 
@@ -65,6 +66,17 @@ pub fn parse_primary(parser: &mut Parser) -> cayResult<Expr> {
             parser.advance();
             Ok(Expr::Identifier("this".to_string()))
         }
+        crate::lexer::Token::Super => {
+            parser.advance();
+            // super 只能用于访问父类成员 super.member；super(args) 只能是构造函数的第一条语句
+            if parser.check(&crate::lexer::Token::LParen) {
+                return Err(parser.error("Call to 'super' must be the first statement in a constructor"));
+            }
+            if !parser.check(&crate::lexer::Token::Dot) {
+                return Err(parser.error("Expected '.' after 'super'"));
+            }
+            Ok(Expr::Identifier("super".to_string()))
+        }
         crate::lexer::Token::Identifier(name) => {
             let name = name.clone();
             parser.advance();
@@ -77,6 +89,7 @@ pub fn parse_primary(parser: &mut Parser) -> cayResult<Expr> {
                     object: None,
                     method_name,
                     loc,
+                    id: parser.next_node_id(),
                 }));
             }
 
@@ -86,19 +99,18 @@ pub fn parse_primary(parser: &mut Parser) -> cayResult<Expr> {
             parser.advance();
             parse_new_expression(parser, loc)
         }
+        crate::lexer::Token::InlineIr => {
+            parser.advance();
+            parse_inline_ir(parser, loc)
+        }
         crate::lexer::Token::LParen => {
-            // 检查是否是 Lambda 表达式: (params) -> { body }
-            // 需要向前看，检查是否有 -> 箭头
-            let checkpoint = parser.pos;
-            parser.advance(); // 跳过 '('
-
-            // 尝试解析 Lambda 参数列表
-            if let Ok(lambda_expr) = try_parse_lambda(parser, loc.clone()) {
-                return Ok(lambda_expr);
+            // 配对的 ')' 后紧跟 -> 箭头则是 Lambda 表达式: (params) -> { body }
+            if is_lambda_start(parser) {
+                parser.advance(); // 跳过 '('
+                return try_parse_lambda(parser, loc);
             }
 
-            // 不是 Lambda，回退并解析普通括号表达式
-            parser.pos = checkpoint;
+            // 普通括号表达式
             parser.advance(); // 跳过 '('
             let expr = parse_expression(parser)?;
             parser.consume(&crate::lexer::Token::RParen, "Expected ')' after expression")?;
@@ -108,6 +120,30 @@ pub fn parse_primary(parser: &mut Parser) -> cayResult<Expr> {
     }
 }
 
+/// 解析内联 IR 表达式: __llvm__<T>("template", args...)，省略 `<T>` 时结果类型为 void
+fn parse_inline_ir(parser: &mut Parser, loc: crate::error::SourceLocation) -> cayResult<Expr> {
+    let result_type = if parser.match_token(&crate::lexer::Token::Lt) {
+        let ty = parse_type(parser)?;
+        parser.consume(&crate::lexer::Token::Gt, "Expected '>' after the result type of '__llvm__'")?;
+        ty
+    } else {
+        Type::Void
+    };
+    parser.consume(&crate::lexer::Token::LParen, "Expected '(' after '__llvm__'")?;
+    let crate::lexer::Token::StringLiteral(Some(template)) = parser.current_token().clone() else {
+        return Err(parser.error("Expected a string literal with the IR template in '__llvm__(...)'"));
+    };
+    parser.advance();
+
+    let mut args = Vec::new();
+    while parser.match_token(&crate::lexer::Token::Comma) {
+        args.push(parse_expression(parser)?);
+    }
+    parser.consume(&crate::lexer::Token::RParen, "Expected ')' after '__llvm__' arguments")?;
+
+    Ok(Expr::InlineIr(InlineIrExpr { result_type, template, args, loc, id: parser.next_node_id() }))
+}
+
 /// 解析 new 表达式（支持类创建和多维数组创建）
 pub fn parse_new_expression(parser: &mut Parser, loc: crate::error::SourceLocation) -> cayResult<Expr> {
     // 首先尝试解析类型
@@ -133,14 +169,19 @@ pub fn parse_new_expression(parser: &mut Parser, loc: crate::error::SourceLocati
             }
 
             // 检查是否有 () 零初始化后缀
-            let zero_init = parser.match_token(&crate::lexer::Token::LParen)
-                && parser.match_token(&crate::lexer::Token::RParen);
+            let zero_init = parser.check(&crate::lexer::Token::LParen)
+                && parser.check_next(&crate::lexer::Token::RParen);
+            if zero_init {
+                parser.advance();
+                parser.advance();
+            }
 
             return Ok(Expr::ArrayCreation(ArrayCreationExpr {
                 element_type,
                 sizes,
                 zero_init,
                 loc,
+                id: parser.next_node_id(),
             }));
         }
 
@@ -151,7 +192,12 @@ pub fn parse_new_expression(parser: &mut Parser, loc: crate::error::SourceLocati
                 crate::types::Type::Object(name) => {
                     let args = parse_arguments(parser)?;
                     parser.consume(&crate::lexer::Token::RParen, "Expected ')' after arguments")?;
-                    return Ok(Expr::New(NewExpr { class_name: name, args, loc }));
+                    return Ok(Expr::New(NewExpr { class_name: name, type_args: None, args, loc, id: parser.next_node_id() }));
+                }
+                crate::types::Type::Generic(name, type_args) => {
+                    let args = parse_arguments(parser)?;
+                    parser.consume(&crate::lexer::Token::RParen, "Expected ')' after arguments")?;
+                    return Ok(Expr::New(NewExpr { class_name: name, type_args: Some(type_args), args, loc, id: parser.next_node_id() }));
                 }
                 _ => {
                     return Err(parser.error("Only object types can be constructed with 'new Type()'"));
@@ -170,14 +216,21 @@ pub fn parse_new_expression(parser: &mut Parser, loc: crate::error::SourceLocati
     parser.consume(&crate::lexer::Token::RParen, "Expected ')' after arguments")?;
     Ok(Expr::New(NewExpr {
         class_name,
+        type_args: None,
         args,
         loc,
+        id: parser.next_node_id(),
     }))
 }
 
 /// 解析基本类型（不包含数组维度）
+///
+/// 类名后可以带泛型实参 `Box<int>`；菱形写法 `Box<>` 解析为实参为空的泛型类型，
+/// 由泛型单态化阶段根据声明类型补全。
 pub fn parse_base_type(parser: &mut Parser) -> cayResult<Type> {
     match parser.current_token() {
+        crate::lexer::Token::Byte => { parser.advance(); Ok(Type::Int8) }
+        crate::lexer::Token::Short => { parser.advance(); Ok(Type::Int16) }
         crate::lexer::Token::Int => { parser.advance(); Ok(Type::Int32) }
         crate::lexer::Token::Long => { parser.advance(); Ok(Type::Int64) }
         crate::lexer::Token::Float => { parser.advance(); Ok(Type::Float32) }
@@ -188,6 +241,14 @@ pub fn parse_base_type(parser: &mut Parser) -> cayResult<Type> {
         crate::lexer::Token::Identifier(name) => {
             let name = name.clone();
             parser.advance();
+            if parser.check(&crate::lexer::Token::Lt) {
+                let mut pending_gt = 0;
+                let args = parse_type_args(parser, &mut pending_gt)?;
+                if pending_gt > 0 {
+                    return Err(parser.error("Unexpected '>' after type arguments"));
+                }
+                return Ok(Type::Generic(name, args));
+            }
             Ok(Type::Object(name))
         }
         _ => Err(parser.error("Expected type")),
@@ -5,7 +5,8 @@
 use crate::ast::*;
 use crate::error::cayResult;
 use super::super::Parser;
-use super::super::types::{parse_type, is_type_token};
+use super::super::types::parse_type;
+use super::super::lookahead::cast_close_paren;
 use super::postfix::parse_postfix;
 
 /// 解析一元表达式（包括类型转换）
@@ -18,6 +19,7 @@ pub fn parse_unary(parser: &mut Parser) -> cayResult<Expr> {
             op: UnaryOp::Neg,
             operand: Box::new(operand),
             loc,
+            id: parser.next_node_id(),
         }));
     }
 
@@ -27,6 +29,7 @@ pub fn parse_unary(parser: &mut Parser) -> cayResult<Expr> {
             op: UnaryOp::Not,
             operand: Box::new(operand),
             loc,
+            id: parser.next_node_id(),
         }));
     }
 
@@ -36,6 +39,7 @@ pub fn parse_unary(parser: &mut Parser) -> cayResult<Expr> {
             op: UnaryOp::BitNot,
             operand: Box::new(operand),
             loc,
+            id: parser.next_node_id(),
         }));
     }
 
@@ -46,6 +50,7 @@ pub fn parse_unary(parser: &mut Parser) -> cayResult<Expr> {
             op: UnaryOp::PreInc,
             operand: Box::new(operand),
             loc,
+            id: parser.next_node_id(),
         }));
     }
 
@@ -56,46 +61,23 @@ pub fn parse_unary(parser: &mut Parser) -> cayResult<Expr> {
             op: UnaryOp::PreDec,
             operand: Box::new(operand),
             loc,
+            id: parser.next_node_id(),
         }));
     }
 
-    // 尝试解析类型转换 (type) expr
-    if parser.check(&crate::lexer::Token::LParen) {
-        let checkpoint = parser.pos;
+    // 类型转换 (type) expr：由前瞻判断，不是转换时按括号表达式解析
+    if parser.check(&crate::lexer::Token::LParen) && cast_close_paren(parser).is_some() {
         let loc = parser.current_loc();
-
-        // 尝试解析 ( type )
         parser.advance(); // 跳过 LParen
-
-        // 检查是否是类型关键字
-        if is_type_token(parser) {
-            // 解析类型
-            match parse_type(parser) {
-                Ok(target_type) => {
-                    // 期望 RParen
-                    if parser.check(&crate::lexer::Token::RParen) {
-                        parser.advance();
-                        // 成功解析类型转换，解析后面的表达式
-                        let expr = parse_unary(parser)?;
-                        return Ok(Expr::Cast(CastExpr {
-                            expr: Box::new(expr),
-                            target_type,
-                            loc,
-                        }));
-                    } else {
-                        // 没有 RParen，回退
-                        parser.pos = checkpoint;
-                    }
-                }
-                Err(_) => {
-                    // 解析类型失败，回退
-                    parser.pos = checkpoint;
-                }
-            }
-        } else {
-            // 不是类型，回退
-            parser.pos = checkpoint;
-        }
+        let target_type = parse_type(parser)?;
+        parser.consume(&crate::lexer::Token::RParen, "Expected ')' after cast type")?;
+        let expr = parse_unary(parser)?;
+        return Ok(Expr::Cast(CastExpr {
+            expr: Box::new(expr),
+            target_type,
+            loc,
+            id: parser.next_node_id(),
+        }));
     }
 
     parse_postfix(parser)
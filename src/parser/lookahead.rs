@@ -0,0 +1,119 @@
+//! 预测性前瞻
+//!
+//! 语法中有几处需要先判断"这里是不是类型"才能决定如何解析：变量声明、类型转换 `(T) x`
+//! 和 Lambda 参数。这些判断都通过查表完成，不再推测解析后回退：
+//! - 括号配对表在创建解析器时一次性计算，`(` 之后是否是 Lambda 只需看配对的 `)` 后是否为 `->`；
+//! - for 的括号内是否是 for-each 循环，看变量名之后是否为 `:`；
+//! - 某位置起的类型解析结果按位置缓存，同一位置无论被询问多少次都只解析一次。
+//!
+//! 因此嵌套括号等输入的解析时间与 token 数成线性关系。
+
+use crate::lexer::{Token, TokenWithLocation};
+use crate::lexer::keywords::ContextualKeyword;
+use super::Parser;
+use super::types::parse_type;
+
+/// 计算每个 `(` 对应的 `)` 位置
+pub fn compute_matching_parens(tokens: &[TokenWithLocation]) -> Vec<Option<usize>> {
+    let mut matches = vec![None; tokens.len()];
+    let mut open = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        match token.token {
+            Token::LParen => open.push(i),
+            Token::RParen => {
+                if let Some(start) = open.pop() {
+                    matches[start] = Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    matches
+}
+
+/// 从 `pos` 开始解析类型，返回类型之后的位置（不是类型时返回 `None`）
+///
+/// 结果按起始位置缓存，不移动解析位置。
+pub fn type_end_at(parser: &mut Parser, pos: usize) -> Option<usize> {
+    if let Some(end) = parser.type_ends.get(&pos) {
+        return *end;
+    }
+    let saved = parser.pos;
+    parser.pos = pos;
+    let end = match parser.current_token() {
+        Token::Byte | Token::Short | Token::Int | Token::Long | Token::Float | Token::Double | Token::Bool
+        | Token::String | Token::Char | Token::Identifier(_) => {
+            parse_type(parser).ok().map(|_| parser.pos)
+        }
+        _ => None,
+    };
+    parser.pos = saved;
+    parser.type_ends.insert(pos, end);
+    end
+}
+
+/// 当前位置起是否是"类型 + 标识符"（变量声明或带类型的参数）
+pub fn is_typed_name(parser: &mut Parser) -> bool {
+    match type_end_at(parser, parser.pos) {
+        // 空标识符是 EOF 标记
+        Some(end) => matches!(&parser.tokens[end].token, Token::Identifier(name) if !name.is_empty()),
+        None => false,
+    }
+}
+
+/// 当前 `(` 是否开始一个 Lambda 表达式：配对的 `)` 后紧跟 `->`
+pub fn is_lambda_start(parser: &Parser) -> bool {
+    parser.matching_parens[parser.pos]
+        .is_some_and(|close| matches!(parser.tokens.get(close + 1).map(|t| &t.token), Some(Token::Arrow)))
+}
+
+/// 当前 `(` 是否开始一个类型转换 `(T) expr`，是则返回 `)` 的位置
+///
+/// 与 Java 相同：括号内必须恰好是一个类型；基本类型转换后可以跟任何一元表达式，
+/// 引用类型（含数组）转换后不能跟 `+`/`-`，否则 `(a) + b` 会被误判为转换。
+pub fn cast_close_paren(parser: &mut Parser) -> Option<usize> {
+    let close = parser.matching_parens[parser.pos]?;
+    let type_start = parser.pos + 1;
+    if type_end_at(parser, type_start)? != close {
+        return None;
+    }
+    let primitive = close == type_start + 1 && matches!(
+        parser.tokens[type_start].token,
+        Token::Byte | Token::Short | Token::Int | Token::Long | Token::Float | Token::Double | Token::Bool | Token::Char
+    );
+    let next = &parser.tokens.get(close + 1)?.token;
+    let starts_operand = match next {
+        Token::Identifier(name) => !name.is_empty(),
+        // 负数字面量（`(a)-1` 中的 `-1`）等同于前面有减号
+        Token::IntegerLiteral(Some((value, _))) if *value < 0 => primitive,
+        Token::FloatLiteral(Some((value, _))) if value.is_sign_negative() => primitive,
+        Token::IntegerLiteral(_) | Token::FloatLiteral(_) | Token::StringLiteral(_) | Token::CharLiteral(_)
+        | Token::True | Token::False | Token::Null | Token::This | Token::Super | Token::New | Token::InlineIr
+        | Token::LParen | Token::Bang | Token::Tilde => true,
+        Token::Plus | Token::Minus | Token::Inc | Token::Dec => primitive,
+        _ => false,
+    };
+    starts_operand.then_some(close)
+}
+
+/// 当前位置起是否是 for-each 循环的头部 `[final] 类型 名称 :` 或 `[final] var 名称 :`
+///
+/// `for (var i: int = 0; ...)` 中名称后的 `:` 是类型注解：注解的类型之后紧跟 `=` 或 `;`。
+pub fn is_for_each_header(parser: &mut Parser) -> bool {
+    let start = if parser.check(&Token::Final) { parser.pos + 1 } else { parser.pos };
+    let inferred = parser.contextual_keyword_at(start).is_some_and(ContextualKeyword::declares_variable);
+    let name = if inferred {
+        start + 1
+    } else {
+        match type_end_at(parser, start) {
+            Some(end) => end,
+            None => return false,
+        }
+    };
+    let token_at = |pos: usize| parser.tokens.get(pos).map(|t| &t.token);
+    if !matches!(token_at(name), Some(Token::Identifier(n)) if !n.is_empty()) || !matches!(token_at(name + 1), Some(Token::Colon)) {
+        return false;
+    }
+    !(inferred && type_end_at(parser, name + 2)
+        .is_some_and(|end| matches!(parser.tokens.get(end).map(|t| &t.token), Some(Token::Assign | Token::Semicolon))))
+}
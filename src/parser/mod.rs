@@ -8,6 +8,7 @@ mod types;
 mod statements;
 mod expressions;
 mod utils;
+mod lookahead;
 
 use crate::lexer::TokenWithLocation;
 use crate::ast::Program;
@@ -19,22 +20,45 @@ pub struct Parser {
     pub tokens: Vec<TokenWithLocation>,
     /// 当前解析位置
     pub pos: usize,
+    /// 下一个待分配的 AST 节点 ID
+    pub next_id: u32,
+    /// 每个 `(` 对应的 `)` 位置
+    matching_parens: Vec<Option<usize>>,
+    /// 类型前瞻缓存：起始位置 -> 类型之后的位置（不是类型时为 `None`）
+    type_ends: std::collections::HashMap<usize, Option<usize>>,
 }
 
 impl Parser {
     /// 创建新的语法分析器
     pub fn new(tokens: Vec<TokenWithLocation>) -> Self {
-        Self { tokens, pos: 0 }
+        let matching_parens = lookahead::compute_matching_parens(&tokens);
+        Self { tokens, pos: 0, next_id: 0, matching_parens, type_ends: std::collections::HashMap::new() }
+    }
+
+    /// 分配一个新的 AST 节点 ID
+    pub fn next_node_id(&mut self) -> crate::ast::NodeId {
+        let id = crate::ast::NodeId(self.next_id);
+        self.next_id += 1;
+        id
     }
 
     /// 解析整个程序
     pub fn parse(&mut self) -> cayResult<Program> {
+        let mut imports = Vec::new();
         let mut classes = Vec::new();
         let mut interfaces = Vec::new();
         let mut top_level_functions = Vec::new();
 
+        // import 声明只能出现在文件开头
+        let is_import = |parser: &Self| parser.contextual_keyword() == Some(crate::lexer::keywords::ContextualKeyword::Import);
+        while is_import(self) {
+            imports.push(classes::parse_import(self)?);
+        }
+
         while !self.is_at_end() {
-            if self.check(&crate::lexer::Token::Interface)
+            if is_import(self) {
+                return Err(self.error("Import declarations must come before all type declarations"));
+            } else if self.check(&crate::lexer::Token::Interface)
                 || (self.check(&crate::lexer::Token::Public) && self.check_next(&crate::lexer::Token::Interface))
             {
                 interfaces.push(self.parse_interface()?);
@@ -57,7 +81,7 @@ impl Parser {
             }
         }
 
-        Ok(Program { classes, interfaces, top_level_functions })
+        Ok(Program { imports, classes, interfaces, top_level_functions })
     }
 
     // 类解析方法
@@ -241,6 +265,14 @@ impl Parser {
         utils::check_next(self, token)
     }
 
+    fn contextual_keyword(&self) -> Option<crate::lexer::keywords::ContextualKeyword> {
+        utils::contextual_keyword_at(self, self.pos)
+    }
+
+    fn contextual_keyword_at(&self, pos: usize) -> Option<crate::lexer::keywords::ContextualKeyword> {
+        utils::contextual_keyword_at(self, pos)
+    }
+
     fn match_token(&mut self, token: &crate::lexer::Token) -> bool {
         utils::match_token(self, token)
     }
@@ -319,6 +351,7 @@ impl Parser {
             params,
             body,
             loc,
+            id: self.next_node_id(),
         })
     }
 }
@@ -328,3 +361,28 @@ pub fn parse(tokens: Vec<TokenWithLocation>) -> cayResult<Program> {
     let mut parser = Parser::new(tokens);
     parser.parse()
 }
+
+/// 从节点 ID `first_id` 开始解析一个模块，返回程序和下一个可用的节点 ID
+///
+/// 多文件编译时各模块依次解析，节点 ID 在合并后的程序中保持唯一。
+pub fn parse_module(tokens: Vec<TokenWithLocation>, first_id: u32) -> cayResult<(Program, u32)> {
+    let mut parser = Parser::new(tokens);
+    parser.next_id = first_id;
+    let program = parser.parse()?;
+    Ok((program, parser.next_id))
+}
+
+/// 解析单个表达式（要求消耗全部令牌），用于测试和工具
+pub fn parse_expression(tokens: Vec<TokenWithLocation>) -> cayResult<crate::ast::Expr> {
+    let mut parser = Parser::new(tokens);
+    let expr = expressions::parse_expression(&mut parser)?;
+    if !parser.is_at_end() {
+        let loc = parser.current_loc();
+        return Err(crate::error::parser_error(
+            loc.line,
+            loc.column,
+            format!("Unexpected token after expression: {:?}", parser.current_token()),
+        ));
+    }
+    Ok(expr)
+}
@@ -2,6 +2,7 @@
 
 use crate::ast::*;
 use crate::error::cayResult;
+use crate::lexer::keywords::ContextualKeyword;
 use super::Parser;
 use super::types::{parse_type, is_primitive_type_token};
 use super::expressions::parse_expression;
@@ -18,11 +19,12 @@ pub fn parse_block(parser: &mut Parser) -> cayResult<Block> {
     
     parser.consume(&crate::lexer::Token::RBrace, "Expected '}' to end block")?;
     
-    Ok(Block { statements, loc })
+    Ok(Block { statements, loc, id: parser.next_node_id() })
 }
 
 /// 解析语句
 pub fn parse_statement(parser: &mut Parser) -> cayResult<Stmt> {
+    crate::ice::set_location(&parser.current_loc());
     match parser.current_token() {
         crate::lexer::Token::LBrace => Ok(Stmt::Block(parse_block(parser)?)),
         crate::lexer::Token::If => parse_if_statement(parser),
@@ -32,18 +34,17 @@ pub fn parse_statement(parser: &mut Parser) -> cayResult<Stmt> {
         crate::lexer::Token::Switch => parse_switch_statement(parser),
         crate::lexer::Token::Return => parse_return_statement(parser),
         crate::lexer::Token::Break => {
-            let _loc = parser.current_loc();
-            parser.advance();
+            let jump = parse_jump(parser)?;
             parser.consume(&crate::lexer::Token::Semicolon, "Expected ';' after break")?;
-            Ok(Stmt::Break)
+            Ok(Stmt::Break(jump))
         }
         crate::lexer::Token::Continue => {
-            let _loc = parser.current_loc();
-            parser.advance();
+            let jump = parse_jump(parser)?;
             parser.consume(&crate::lexer::Token::Semicolon, "Expected ';' after continue")?;
-            Ok(Stmt::Continue)
+            Ok(Stmt::Continue(jump))
         }
-        crate::lexer::Token::Var | crate::lexer::Token::Let | crate::lexer::Token::Auto => {
+        crate::lexer::Token::Identifier(_) if is_label_at(parser, parser.pos) => parse_labeled_statement(parser),
+        crate::lexer::Token::Identifier(_) if is_modern_var_decl_at(parser, parser.pos) => {
             // 后置类型声明或自动类型推断
             parse_modern_var_decl(parser)
         }
@@ -52,26 +53,15 @@ pub fn parse_statement(parser: &mut Parser) -> cayResult<Stmt> {
             // 但要确保接下来的 token 是变量名（Identifier），以避免将函数调用等标识误判为类型。
             if parser.check(&crate::lexer::Token::Final) {
                 // 检查是否是 final var/let/auto 语法
-                if parser.check_next(&crate::lexer::Token::Var) ||
-                   parser.check_next(&crate::lexer::Token::Let) ||
-                   parser.check_next(&crate::lexer::Token::Auto) {
+                if is_modern_var_decl_at(parser, parser.pos + 1) {
                     return parse_modern_var_decl(parser);
                 }
                 return parse_var_decl(parser);
             }
 
-            if super::types::is_type_token(parser) {
-                // 尝试解析类型（不消耗最终位置）以判断是否紧跟变量名。
-                let checkpoint = parser.pos;
-                if super::types::parse_type(parser).is_ok() {
-                    // 如果解析类型后当前token是标识符，则认为是变量声明
-                    if let crate::lexer::Token::Identifier(_) = parser.current_token() {
-                        parser.pos = checkpoint; // 回退到类型前位置
-                        return parse_var_decl(parser);
-                    }
-                }
-                // 回退到初始位置，继续解析为表达式语句
-                parser.pos = checkpoint;
+            // 类型之后紧跟变量名则是变量声明，否则解析为表达式语句
+            if super::lookahead::is_typed_name(parser) {
+                return parse_var_decl(parser);
             }
 
             parse_expression_statement(parser)
@@ -79,6 +69,51 @@ pub fn parse_statement(parser: &mut Parser) -> cayResult<Stmt> {
     }
 }
 
+/// 解析 break/continue 关键字及可选的目标标签（不含分号）
+fn parse_jump(parser: &mut Parser) -> cayResult<JumpStmt> {
+    let loc = parser.current_loc();
+    parser.advance();
+    let label = match parser.current_token() {
+        crate::lexer::Token::Identifier(name) if !name.is_empty() => {
+            let name = name.clone();
+            parser.advance();
+            Some(name)
+        }
+        _ => None,
+    };
+    Ok(JumpStmt { label, loc })
+}
+
+/// `pos` 处是否是语句标签 `name:`
+fn is_label_at(parser: &Parser, pos: usize) -> bool {
+    matches!(parser.tokens.get(pos).map(|t| &t.token), Some(crate::lexer::Token::Identifier(name)) if !name.is_empty())
+        && matches!(parser.tokens.get(pos + 1).map(|t| &t.token), Some(crate::lexer::Token::Colon))
+}
+
+/// 解析带标签的语句 `outer: while (...) { ... }`
+fn parse_labeled_statement(parser: &mut Parser) -> cayResult<Stmt> {
+    let loc = parser.current_loc();
+    let label = parser.consume_identifier("Expected label name")?;
+    parser.consume(&crate::lexer::Token::Colon, "Expected ':' after label")?;
+    let body = parse_statement(parser)?;
+    Ok(Stmt::Labeled(LabeledStmt {
+        label,
+        body: Box::new(body),
+        loc,
+        id: parser.next_node_id(),
+    }))
+}
+
+/// `pos` 处是否开始一个 var/let/auto 变量声明
+///
+/// `var`、`let`、`auto` 是上下文关键字：只有后面紧跟变量名时才是声明，
+/// 否则（如 `var = 1;`、`auto.run();`）按普通标识符解析。
+fn is_modern_var_decl_at(parser: &Parser, pos: usize) -> bool {
+    parser.contextual_keyword_at(pos).is_some_and(ContextualKeyword::declares_variable)
+        // 空标识符是 EOF 标记
+        && matches!(parser.tokens.get(pos + 1).map(|t| &t.token), Some(crate::lexer::Token::Identifier(name)) if !name.is_empty())
+}
+
 /// 解析传统变量声明（类型前置）
 pub fn parse_var_decl(parser: &mut Parser) -> cayResult<Stmt> {
     let loc = parser.current_loc();
@@ -107,6 +142,7 @@ pub fn parse_var_decl(parser: &mut Parser) -> cayResult<Stmt> {
         initializer,
         is_final,
         loc,
+        id: parser.next_node_id(),
     }))
 }
 
@@ -122,8 +158,6 @@ pub fn parse_modern_var_decl(parser: &mut Parser) -> cayResult<Stmt> {
     // 检查是否有 final 修饰符（final var x: int = 10）
     let is_final = parser.match_token(&crate::lexer::Token::Final);
     
-    // 获取声明关键字（var/let/auto）
-    let keyword = parser.current_token().clone();
     parser.advance(); // consume var/let/auto
     
     let name = parser.consume_identifier("Expected variable name after var/let/auto")?;
@@ -133,15 +167,8 @@ pub fn parse_modern_var_decl(parser: &mut Parser) -> cayResult<Stmt> {
         // 有类型注解：var x: int
         parse_type(parser)?
     } else {
-        // 无类型注解，必须是 auto 或必须有初始化器
-        match keyword {
-            crate::lexer::Token::Auto => crate::types::Type::Auto,
-            crate::lexer::Token::Var | crate::lexer::Token::Let => {
-                // var/let 必须有类型注解（暂时如此，之后可以实现类型推断）
-                return Err(parser.error("var/let declaration requires type annotation (: Type) or use 'auto' for type inference"));
-            }
-            _ => unreachable!()
-        }
+        // 无类型注解，由语义分析根据初始化器推断类型
+        crate::types::Type::Auto
     };
     
     // 解析初始化器
@@ -164,6 +191,7 @@ pub fn parse_modern_var_decl(parser: &mut Parser) -> cayResult<Stmt> {
         initializer,
         is_final,
         loc,
+        id: parser.next_node_id(),
     }))
 }
 
@@ -192,7 +220,7 @@ fn parse_array_initializer(parser: &mut Parser) -> cayResult<Expr> {
     
     parser.consume(&crate::lexer::Token::RBrace, "Expected '}' to end array initializer")?;
     
-    Ok(Expr::ArrayInit(ArrayInitExpr { elements, loc }))
+    Ok(Expr::ArrayInit(ArrayInitExpr { elements, loc, id: parser.next_node_id() }))
 }
 
 /// 解析 if 语句
@@ -216,6 +244,7 @@ pub fn parse_if_statement(parser: &mut Parser) -> cayResult<Stmt> {
         then_branch,
         else_branch,
         loc,
+        id: parser.next_node_id(),
     }))
 }
 
@@ -234,6 +263,7 @@ pub fn parse_while_statement(parser: &mut Parser) -> cayResult<Stmt> {
         condition,
         body,
         loc,
+        id: parser.next_node_id(),
     }))
 }
 
@@ -243,8 +273,12 @@ pub fn parse_for_statement(parser: &mut Parser) -> cayResult<Stmt> {
     parser.advance(); // consume 'for'
     
     parser.consume(&crate::lexer::Token::LParen, "Expected '(' after 'for'")?;
+    if super::lookahead::is_for_each_header(parser) {
+        return parse_for_each_rest(parser, loc);
+    }
     
-    let init = if parser.check(&crate::lexer::Token::Semicolon) {
+    // 省略初始化部分时直接消耗 ';'，否则 ';' 由初始化语句自身消耗
+    let init = if parser.match_token(&crate::lexer::Token::Semicolon) {
         None
     } else {
         Some(Box::new(parse_statement(parser)?))
@@ -273,6 +307,34 @@ pub fn parse_for_statement(parser: &mut Parser) -> cayResult<Stmt> {
         update,
         body,
         loc,
+        id: parser.next_node_id(),
+    }))
+}
+
+/// 解析 for-each 循环 `for (Type x : expr)` / `for (var x : expr)`，'(' 已被消耗
+fn parse_for_each_rest(parser: &mut Parser, loc: crate::error::SourceLocation) -> cayResult<Stmt> {
+    let is_final = parser.match_token(&crate::lexer::Token::Final);
+    let var_type = if is_modern_var_decl_at(parser, parser.pos) {
+        parser.advance(); // consume var/let/auto
+        crate::types::Type::Auto
+    } else {
+        parse_type(parser)?
+    };
+    let var_name = parser.consume_identifier("Expected variable name in for-each loop")?;
+    parser.consume(&crate::lexer::Token::Colon, "Expected ':' after for-each variable")?;
+    let iterable = parse_expression(parser)?;
+    parser.consume(&crate::lexer::Token::RParen, "Expected ')' after for-each expression")?;
+
+    let body = Box::new(parse_statement(parser)?);
+
+    Ok(Stmt::ForEach(ForEachStmt {
+        var_name,
+        var_type,
+        is_final,
+        iterable,
+        body,
+        loc,
+        id: parser.next_node_id(),
     }))
 }
 
@@ -293,6 +355,7 @@ pub fn parse_do_while_statement(parser: &mut Parser) -> cayResult<Stmt> {
         condition,
         body,
         loc,
+        id: parser.next_node_id(),
     }))
 }
 
@@ -312,15 +375,7 @@ pub fn parse_switch_statement(parser: &mut Parser) -> cayResult<Stmt> {
     
     while !parser.check(&crate::lexer::Token::RBrace) && !parser.is_at_end() {
         if parser.match_token(&crate::lexer::Token::Case) {
-            // 解析 case 值
-            let value = match *parser.current_token() {
-                crate::lexer::Token::IntegerLiteral(Some((v, _))) => {
-                    let val = v;  // v 是 i64
-                    parser.advance();
-                    val
-                }
-                _ => return Err(parser.error("Expected integer literal in case")),
-            };
+            let value = parse_case_value(parser)?;
             parser.consume(&crate::lexer::Token::Colon, "Expected ':' after case value")?;
             
             // 解析 case 体（直到遇到另一个 case、default 或 }）
@@ -354,12 +409,36 @@ pub fn parse_switch_statement(parser: &mut Parser) -> cayResult<Stmt> {
         cases,
         default,
         loc,
+        id: parser.next_node_id(),
     }))
 }
 
+/// 解析 case 标签的值
+///
+/// 支持 `L` 后缀的长整型字面量、负数（`case -1:` 与 `case - 1:` 均可）和字符字面量（取其码点），
+/// 值统一保存为 i64。
+fn parse_case_value(parser: &mut Parser) -> cayResult<i64> {
+    let negative = parser.match_token(&crate::lexer::Token::Minus);
+    let value = match *parser.current_token() {
+        crate::lexer::Token::IntegerLiteral(Some((v, _))) => {
+            parser.advance();
+            v
+        }
+        crate::lexer::Token::CharLiteral(Some(c)) if !negative => {
+            parser.advance();
+            c as i64
+        }
+        _ => return Err(parser.error("Expected integer literal in case")),
+    };
+    if !negative {
+        return Ok(value);
+    }
+    value.checked_neg().ok_or_else(|| parser.error("Case value out of range"))
+}
+
 /// 解析 return 语句
 pub fn parse_return_statement(parser: &mut Parser) -> cayResult<Stmt> {
-    let _loc = parser.current_loc();
+    let loc = parser.current_loc();
     parser.advance(); // consume 'return'
     
     let value = if !parser.check(&crate::lexer::Token::Semicolon) {
@@ -370,7 +449,7 @@ pub fn parse_return_statement(parser: &mut Parser) -> cayResult<Stmt> {
     
     parser.consume(&crate::lexer::Token::Semicolon, "Expected ';' after return")?;
     
-    Ok(Stmt::Return(value))
+    Ok(Stmt::Return(ReturnStmt { value, loc }))
 }
 
 /// 解析表达式语句
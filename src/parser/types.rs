@@ -1,12 +1,41 @@
 //! 类型解析
 
-use crate::types::Type;
+use crate::types::{Type, FunctionType};
 use crate::error::cayResult;
+use crate::lexer::keywords::ContextualKeyword;
 use super::Parser;
 
-/// 解析类型（支持多维数组）
+/// 解析类型（支持多维数组和泛型实参）
 pub fn parse_type(parser: &mut Parser) -> cayResult<Type> {
+    let mut pending_gt = 0;
+    let ty = parse_type_inner(parser, &mut pending_gt)?;
+    if pending_gt > 0 {
+        return Err(parser.error("Unexpected '>' after type"));
+    }
+    Ok(ty)
+}
+
+/// 解析声明中显式写出的类型（字段类型、方法返回类型、参数类型）
+///
+/// `var`/`let`/`auto` 只能用于局部变量的类型推断，这些位置没有初始化器可供推断，
+/// 直接报错，避免把它们当作名为 `var` 的类。`what` 描述所在位置，用于错误信息。
+pub fn parse_declared_type(parser: &mut Parser, what: &str) -> cayResult<Type> {
+    if let Some(keyword) = parser.contextual_keyword().filter(|k| k.declares_variable()) {
+        return Err(parser.error(&format!(
+            "'{}' cannot be used as {}; write the type explicitly", keyword.as_str(), what)));
+    }
+    parse_type(parser)
+}
+
+/// 解析类型的内部实现
+///
+/// `pending_gt` 记录已经被内层类型从 `>>`/`>>>` 中多消耗掉的 `>` 个数，
+/// 例如 `Box<Box<int>>` 中内层类型一次消耗了两个 `>`，外层类型直接使用。
+/// 计数只保存在本次解析调用链中，推测解析失败回退时不会污染 token 流。
+fn parse_type_inner(parser: &mut Parser, pending_gt: &mut usize) -> cayResult<Type> {
     let base_type = match parser.current_token() {
+        crate::lexer::Token::Byte => { parser.advance(); Type::Int8 }
+        crate::lexer::Token::Short => { parser.advance(); Type::Int16 }
         crate::lexer::Token::Int => { parser.advance(); Type::Int32 }
         crate::lexer::Token::Long => { parser.advance(); Type::Int64 }
         crate::lexer::Token::Float => { parser.advance(); Type::Float32 }
@@ -14,14 +43,31 @@ pub fn parse_type(parser: &mut Parser) -> cayResult<Type> {
         crate::lexer::Token::Bool => { parser.advance(); Type::Bool }
         crate::lexer::Token::String => { parser.advance(); Type::String }
         crate::lexer::Token::Char => { parser.advance(); Type::Char }
+        crate::lexer::Token::Identifier(_) if is_function_type_start(parser) => {
+            // 返回类型之后的数组维度属于返回类型
+            return parse_function_type(parser, pending_gt);
+        }
         crate::lexer::Token::Identifier(name) => {
             let name = name.clone();
             parser.advance();
-            Type::Object(name)
+            if parser.check(&crate::lexer::Token::Lt) {
+                let args = parse_type_args(parser, pending_gt)?;
+                if args.is_empty() {
+                    return Err(parser.error("Expected type arguments between '<' and '>'"));
+                }
+                Type::Generic(name, args)
+            } else {
+                Type::Object(name)
+            }
         }
         _ => return Err(parser.error("Expected type")),
     };
-    
+
+    // 内层泛型已经吃掉了外层的 '>'，数组维度属于外层类型
+    if *pending_gt > 0 {
+        return Ok(base_type);
+    }
+
     // 检查多维数组类型 Type[][]...
     let mut result_type = base_type;
     while parser.match_token(&crate::lexer::Token::LBracket) {
@@ -32,9 +78,101 @@ pub fn parse_type(parser: &mut Parser) -> cayResult<Type> {
     Ok(result_type)
 }
 
+/// 当前位置是否开始一个函数类型：上下文关键字 `fn` 后是括号，配对的 `)` 后紧跟 `->`
+///
+/// 普通的调用 `fn(x)` 后面没有 `->`，仍然按标识符解析。
+pub fn is_function_type_start(parser: &Parser) -> bool {
+    parser.contextual_keyword() == Some(ContextualKeyword::Fn)
+        && parser.matching_parens.get(parser.pos + 1).copied().flatten()
+            .is_some_and(|close| matches!(parser.tokens.get(close + 1).map(|t| &t.token), Some(crate::lexer::Token::Arrow)))
+}
+
+/// 解析函数类型 `fn(T1, T2) -> R`，返回类型可以是 `void`
+fn parse_function_type(parser: &mut Parser, pending_gt: &mut usize) -> cayResult<Type> {
+    parser.advance();
+    parser.consume(&crate::lexer::Token::LParen, "Expected '(' after 'fn'")?;
+    let mut params = Vec::new();
+    if !parser.check(&crate::lexer::Token::RParen) {
+        loop {
+            params.push(parse_type(parser)?);
+            if !parser.match_token(&crate::lexer::Token::Comma) {
+                break;
+            }
+        }
+    }
+    parser.consume(&crate::lexer::Token::RParen, "Expected ')' after function parameter types")?;
+    parser.consume(&crate::lexer::Token::Arrow, "Expected '->' after function parameter types")?;
+    let return_type = if parser.match_token(&crate::lexer::Token::Void) {
+        Type::Void
+    } else {
+        parse_type_inner(parser, pending_gt)?
+    };
+    Ok(Type::Function(Box::new(FunctionType {
+        params,
+        return_type: Box::new(return_type),
+        is_static: false,
+    })))
+}
+
+/// 解析泛型实参列表 `<T1, T2, ...>`（当前 token 为 `<`），`<>` 返回空列表
+pub fn parse_type_args(parser: &mut Parser, pending_gt: &mut usize) -> cayResult<Vec<Type>> {
+    parser.consume(&crate::lexer::Token::Lt, "Expected '<'")?;
+    let mut args = Vec::new();
+    if !consume_closing_gt(parser, pending_gt) {
+        loop {
+            args.push(parse_type_inner(parser, pending_gt)?);
+            if *pending_gt > 0 || !parser.match_token(&crate::lexer::Token::Comma) {
+                break;
+            }
+        }
+        if !consume_closing_gt(parser, pending_gt) {
+            return Err(parser.error("Expected '>' after type arguments"));
+        }
+    }
+    Ok(args)
+}
+
+/// 消耗一个闭合的 `>`，`>>` 和 `>>>` 中多出的部分记入 `pending_gt`
+fn consume_closing_gt(parser: &mut Parser, pending_gt: &mut usize) -> bool {
+    if *pending_gt > 0 {
+        *pending_gt -= 1;
+        return true;
+    }
+    let extra = match parser.current_token() {
+        crate::lexer::Token::Gt => 0,
+        crate::lexer::Token::Shr => 1,
+        crate::lexer::Token::UnsignedShr => 2,
+        _ => return false,
+    };
+    parser.advance();
+    *pending_gt = extra;
+    true
+}
+
+/// 解析泛型类型形参列表 `<T, U>`，当前 token 不是 `<` 时返回空列表
+pub fn parse_type_params(parser: &mut Parser) -> cayResult<Vec<String>> {
+    let mut params: Vec<String> = Vec::new();
+    if !parser.match_token(&crate::lexer::Token::Lt) {
+        return Ok(params);
+    }
+    loop {
+        let name = parser.consume_identifier("Expected type parameter name")?;
+        if params.contains(&name) {
+            return Err(parser.error(&format!("Duplicate type parameter '{}'", name)));
+        }
+        params.push(name);
+        if !parser.match_token(&crate::lexer::Token::Comma) {
+            break;
+        }
+    }
+    parser.consume(&crate::lexer::Token::Gt, "Expected '>' after type parameters")?;
+    Ok(params)
+}
+
 /// 检查当前token是否是类型token
 pub fn is_type_token(parser: &Parser) -> bool {
     matches!(parser.current_token(),
+        crate::lexer::Token::Byte | crate::lexer::Token::Short |
         crate::lexer::Token::Int | crate::lexer::Token::Long | crate::lexer::Token::Float |
         crate::lexer::Token::Double | crate::lexer::Token::Bool | crate::lexer::Token::String |
         crate::lexer::Token::Char | crate::lexer::Token::Identifier(_)
@@ -44,6 +182,7 @@ pub fn is_type_token(parser: &Parser) -> bool {
 /// 检查当前token是否是原始类型token
 pub fn is_primitive_type_token(parser: &Parser) -> bool {
     matches!(parser.current_token(),
+        crate::lexer::Token::Byte | crate::lexer::Token::Short |
         crate::lexer::Token::Int | crate::lexer::Token::Long | crate::lexer::Token::Float |
         crate::lexer::Token::Double | crate::lexer::Token::Bool | crate::lexer::Token::String |
         crate::lexer::Token::Char
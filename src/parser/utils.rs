@@ -1,6 +1,7 @@
 //! 解析器辅助方法
 
 use crate::lexer::{Token, TokenWithLocation};
+use crate::lexer::keywords::ContextualKeyword;
 use crate::error::{cayResult, cayError, parser_error, SourceLocation};
 use super::Parser;
 
@@ -94,4 +95,12 @@ pub fn check_next(parser: &Parser, token: &Token) -> bool {
     } else {
         &parser.tokens[parser.pos + 1].token == token
     }
-}
\ No newline at end of file
+}
+
+/// 指定位置的标识符是否拼写为上下文关键字（是否按关键字处理由调用处的语法位置决定）
+pub fn contextual_keyword_at(parser: &Parser, pos: usize) -> Option<ContextualKeyword> {
+    match &parser.tokens.get(pos)?.token {
+        Token::Identifier(name) => ContextualKeyword::from_identifier(name),
+        _ => None,
+    }
+}
@@ -0,0 +1,109 @@
+//! 内置的标准库类
+//!
+//! 这些类用 Cavvy 源码编写（`src/prelude/*.cay`），随编译器一起分发，在单态化开始时加入程序：
+//! 泛型类作为模板，和用户编写的泛型类一样只为用到的实例生成代码；
+//! 非泛型的类只在程序用到它的名字时加入。程序自己定义了同名的类或接口时使用程序中的定义。
+//!
+//! - `List<T>`：可变长度的列表（add/get/set/remove/size/contains 等），可以用 for-each 遍历
+//! - `Map<K, V>`：键为 String 或 int 的哈希表
+//! - `StringBuilder`：可增长的字符缓冲区，循环中拼接字符串不必每次复制已有内容
+//!
+//! `Map` 和 `StringBuilder` 的主要方法声明为 native（见 [`native_class`]），
+//! 由代码生成展开为运行时的 `__cay_map_*`/`__cay_sb_*` 函数，解释器中另有实现。
+
+use std::collections::HashSet;
+use crate::ast::{ClassDecl, ClassMember, Expr, Modifier, Program};
+use crate::error::{cayResult, SourceLocation};
+use crate::types::Type;
+use crate::visit::{self, VisitMut};
+use crate::{lexer, parser};
+
+/// 内置 Map 的类名（模板名）
+pub const MAP_CLASS: &str = "Map";
+
+/// 内置 StringBuilder 的类名
+pub const STRING_BUILDER_CLASS: &str = "StringBuilder";
+
+/// 内置类中保存运行时数据指针的字段（第一次调用 native 方法时创建）
+pub const HANDLE_FIELD: &str = "handle";
+
+/// native 方法由运行时实现的内置类
+#[derive(Debug, Clone, PartialEq)]
+pub enum NativeClass {
+    /// `Map<K, V>` 的模板或实例，带键和值的类型
+    Map(Type, Type),
+    StringBuilder,
+}
+
+/// 内置类的源码
+const SOURCES: &[&str] = &[
+    include_str!("prelude/list.cay"),
+    include_str!("prelude/map.cay"),
+    include_str!("prelude/string_builder.cay"),
+];
+
+/// 把程序中没有同名定义的内置类加入程序
+///
+/// 节点 ID 从 `first_id` 开始分配，返回下一个可用的节点 ID。
+pub fn add_to(program: &mut Program, first_id: u32) -> cayResult<u32> {
+    let mut used = UsedClasses::default();
+    visit::walk_program(&mut used, program)?;
+
+    let mut next_id = first_id;
+    for source in SOURCES {
+        let (prelude, end_id) = parser::parse_module(lexer::lex(source)?, next_id)?;
+        next_id = end_id;
+        for class in prelude.classes {
+            let defined = program.classes.iter().any(|c| c.name == class.name)
+                || program.interfaces.iter().any(|i| i.name == class.name);
+            // 非泛型的类不会被单态化裁剪，没有用到时不加入
+            let unused = class.type_params.is_empty() && !used.0.contains(&class.name);
+            if !defined && !unused {
+                program.classes.push(class);
+            }
+        }
+    }
+    Ok(next_id)
+}
+
+/// 内置类 `class` 的 native 方法由哪种运行时实现；其他类返回 `None`
+///
+/// 按类名和 native 方法识别（程序自己定义的同名类会替换内置类）：
+/// Map 的模板或实例（`Map$String$int`）有 native 的 `put(K, V)`，StringBuilder 有 native 的 `append`。
+pub fn native_class(class: &ClassDecl) -> Option<NativeClass> {
+    let is_map_name = class.name == MAP_CLASS
+        || class.name.strip_prefix(MAP_CLASS).is_some_and(|rest| rest.starts_with('$'));
+    let natives = class.members.iter().filter_map(|member| match member {
+        ClassMember::Method(method) if method.modifiers.contains(&Modifier::Native) => Some(method),
+        _ => None,
+    });
+    for method in natives {
+        if is_map_name && method.name == "put" && method.params.len() == 2 {
+            return Some(NativeClass::Map(method.params[0].param_type.clone(), method.params[1].param_type.clone()));
+        }
+        if class.name == STRING_BUILDER_CLASS && method.name == "append" {
+            return Some(NativeClass::StringBuilder);
+        }
+    }
+    None
+}
+
+/// 程序中作为类型或在 new 表达式中出现的类名
+#[derive(Default)]
+struct UsedClasses(HashSet<String>);
+
+impl VisitMut for UsedClasses {
+    fn visit_type(&mut self, ty: &mut Type, loc: &SourceLocation) -> cayResult<()> {
+        if let Type::Object(name) = ty {
+            self.0.insert(name.clone());
+        }
+        visit::walk_type(self, ty, loc)
+    }
+
+    fn visit_expr(&mut self, expr: &mut Expr) -> cayResult<()> {
+        if let Expr::New(new_expr) = expr {
+            self.0.insert(new_expr.class_name.clone());
+        }
+        visit::walk_expr(self, expr)
+    }
+}
@@ -2,6 +2,7 @@
 //! 
 //! 实现 0.3.5.0 版本的预处理指令系统：
 //! - #include "path"  - 文件包含（隐式 #pragma once）
+//! - include "path";  - 同 #include，便于在类之外共享辅助类（分号可省略）
 //! - #define NAME value  - 常量定义（无参数宏）
 //! - #ifdef / #ifndef / #endif  - 条件编译
 //! - #error "message"  - 编译期错误
@@ -128,6 +129,7 @@ impl Preprocessor {
 
     /// 内部处理函数
     fn process_internal(&mut self, source: &str, file_path: &str) -> cayResult<String> {
+        let source = crate::source::normalize_source(source);
         let lines: Vec<&str> = source.lines().collect();
         let mut output_lines = Vec::new();
         
@@ -146,6 +148,9 @@ impl Preprocessor {
                     }
                     Err(e) => return Err(e),
                 }
+            } else if let Some(args) = Self::include_statement_args(trimmed) {
+                let path = self.parse_string_literal(args, line_number)?;
+                self.process_directive(Directive::Include(path), &mut output_lines, file_path)?;
             } else if self.skipping {
                 // 处于条件编译跳过状态，不输出代码行
                 // 但仍需跟踪行号以保持行号映射（用于调试信息）
@@ -241,6 +246,19 @@ impl Preprocessor {
         }
     }
 
+    /// `include "path";` 形式的包含语句，返回路径字符串部分
+    ///
+    /// 只识别行首的 `include` 后紧跟字符串字面量的情况，名为 `include` 的变量或方法不受影响。
+    fn include_statement_args(line: &str) -> Option<&str> {
+        let rest = line.strip_prefix("include")?;
+        if !rest.starts_with(char::is_whitespace) {
+            return None;
+        }
+        let args = rest.trim();
+        let args = args.strip_suffix(';').unwrap_or(args).trim_end();
+        args.starts_with('"').then_some(args)
+    }
+
     /// 解析字符串字面量（用于 #include, #error, #warning）
     fn parse_string_literal(&self, args: &str, line_num: usize) -> cayResult<String> {
         let trimmed = args.trim();
@@ -422,10 +440,11 @@ impl Preprocessor {
         }
         
         // 读取文件内容
-        let content = std::fs::read_to_string(&canonical_path)
+        let bytes = std::fs::read(&canonical_path)
             .map_err(|e| cayError::Io(
                 format!("无法读取包含文件 '{}': {}", path, e)
             ))?;
+        let content = crate::source::decode_source(&bytes, path)?;
         
         // 标记为已包含
         self.included_files.insert(path_key.clone());
@@ -0,0 +1,251 @@
+//! 最小复现程序生成（delta debugging）
+//!
+//! 给定一个会触发编译错误（或编译器内部 panic）的源文件，
+//! 反复删除源码片段并重新运行编译流水线，只要错误信息仍包含指定文本就保留删除，
+//! 最终得到一个尽可能小、但仍能复现同一问题的程序。
+//!
+//! 按花括号结构分层缩减：先以类为单位，再深入到方法、语句块和单条语句。
+//! 花括号的配对以行为单位统计，字符串和注释中的花括号会被忽略。
+
+use std::ops::Range;
+use std::panic;
+use std::path::Path;
+use crate::Compiler;
+
+/// 缩减结果统计
+#[derive(Debug, Clone)]
+pub struct ReduceStats {
+    /// 原始行数
+    pub original_lines: usize,
+    /// 缩减后行数
+    pub reduced_lines: usize,
+    /// 运行编译流水线的次数
+    pub tests_run: usize,
+}
+
+/// 运行完整的编译流水线（预处理、词法、语法、语义、代码生成），返回错误信息
+///
+/// 编译成功返回 `None`；编译器内部 panic 也会被捕获并作为错误信息返回。
+pub fn pipeline_error(source: &str, file_path: &str) -> Option<String> {
+    let base_dir = Path::new(file_path)
+        .parent()
+        .map(|p| p.to_path_buf())
+        .unwrap_or_else(|| ".".into());
+
+    let result = panic::catch_unwind(|| {
+        let preprocessed = crate::preprocessor::preprocess(source, file_path, base_dir.clone())?;
+        Compiler::new().compile_to_ir(&preprocessed)
+    });
+
+    match result {
+        Ok(Ok(_)) => None,
+        Ok(Err(e)) => Some(e.to_string()),
+        Err(payload) => {
+            let message = payload.downcast_ref::<&str>().map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "未知 panic".to_string());
+            Some(format!("internal compiler error: {}", message))
+        }
+    }
+}
+
+/// 从错误信息中提取不随源码位置变化的部分，作为默认的匹配文本
+///
+/// 删除代码会改变行号，因此去掉 `[行:列]: ` 前缀和 `at line N` 后缀，只保留第一行。
+pub fn error_signature(message: &str) -> String {
+    let first_line = message.lines().next().unwrap_or_default();
+    let without_prefix = match first_line.find("]: ") {
+        Some(pos) if first_line[..pos].contains('[') => &first_line[pos + 3..],
+        _ => first_line,
+    };
+    let without_line = match without_prefix.rfind(" at line ") {
+        Some(pos) if without_prefix[pos + 9..].chars().all(|c| c.is_ascii_digit()) => &without_prefix[..pos],
+        _ => without_prefix,
+    };
+    without_line.trim().to_string()
+}
+
+/// 缩减源文件，使 `is_interesting` 对结果仍返回 true
+///
+/// 原始源码本身必须满足 `is_interesting`，否则原样返回。
+pub fn reduce_source<F>(source: &str, mut is_interesting: F) -> (String, ReduceStats)
+where
+    F: FnMut(&str) -> bool,
+{
+    let lines: Vec<&str> = source.lines().collect();
+    let mut reducer = Reducer {
+        lines: &lines,
+        keep: vec![true; lines.len()],
+        tests_run: 0,
+        test: &mut is_interesting,
+    };
+
+    let original_interesting = reducer.test_mask(&reducer.keep.clone());
+    if original_interesting {
+        reducer.reduce_range(0..lines.len());
+    }
+
+    let reduced = reducer.render(&reducer.keep);
+    let stats = ReduceStats {
+        original_lines: lines.len(),
+        reduced_lines: reducer.keep.iter().filter(|k| **k).count(),
+        tests_run: reducer.tests_run,
+    };
+    (reduced, stats)
+}
+
+/// 在编译流水线的错误信息中查找指定文本，缩减源文件
+///
+/// 缩减期间会暂时屏蔽 panic 输出。
+pub fn reduce_file(source: &str, file_path: &str, error_contains: &str) -> (String, ReduceStats) {
+    let previous_hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+
+    let result = reduce_source(source, |candidate| {
+        pipeline_error(candidate, file_path)
+            .is_some_and(|message| message.contains(error_contains))
+    });
+
+    panic::set_hook(previous_hook);
+    result
+}
+
+struct Reducer<'a, F: FnMut(&str) -> bool> {
+    lines: &'a [&'a str],
+    keep: Vec<bool>,
+    tests_run: usize,
+    test: &'a mut F,
+}
+
+impl<F: FnMut(&str) -> bool> Reducer<'_, F> {
+    fn render(&self, mask: &[bool]) -> String {
+        let mut out = String::new();
+        for (line, keep) in self.lines.iter().zip(mask) {
+            if *keep {
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    fn test_mask(&mut self, mask: &[bool]) -> bool {
+        self.tests_run += 1;
+        let candidate = self.render(mask);
+        (self.test)(&candidate)
+    }
+
+    /// 分层缩减指定行范围
+    fn reduce_range(&mut self, range: Range<usize>) {
+        let units = self.units(range);
+        let kept = self.ddmin(units);
+
+        // 深入到保留下来的多行块内部（不含首尾的花括号行）
+        for unit in kept {
+            if unit.len() > 2 {
+                self.reduce_range(unit.start + 1..unit.end - 1);
+            }
+        }
+    }
+
+    /// 将行范围划分为花括号平衡的单元（单条语句或完整的块）
+    fn units(&self, range: Range<usize>) -> Vec<Range<usize>> {
+        let mut units = Vec::new();
+        let mut start = None;
+        let mut depth: i64 = 0;
+        let end = range.end;
+
+        for i in range {
+            if !self.keep[i] {
+                continue;
+            }
+            let unit_start = *start.get_or_insert(i);
+            depth += brace_delta(self.lines[i]);
+            if depth <= 0 {
+                units.push(unit_start..i + 1);
+                start = None;
+                depth = 0;
+            }
+        }
+        if let Some(s) = start {
+            // 花括号不平衡，剩余部分作为一个整体
+            units.push(s..end);
+        }
+        units
+    }
+
+    /// 经典 ddmin：返回仍保留的单元
+    fn ddmin(&mut self, mut units: Vec<Range<usize>>) -> Vec<Range<usize>> {
+        let mut granularity = 2;
+
+        while units.len() >= 2 {
+            let chunk_size = units.len().div_ceil(granularity);
+            let mut reduced = false;
+
+            for chunk_start in (0..units.len()).step_by(chunk_size) {
+                let chunk_end = (chunk_start + chunk_size).min(units.len());
+                let mut mask = self.keep.clone();
+                for unit in &units[chunk_start..chunk_end] {
+                    for line in unit.clone() {
+                        mask[line] = false;
+                    }
+                }
+
+                if self.test_mask(&mask) {
+                    self.keep = mask;
+                    units.drain(chunk_start..chunk_end);
+                    granularity = (granularity - 1).max(2);
+                    reduced = true;
+                    break;
+                }
+            }
+
+            if !reduced {
+                if granularity >= units.len() {
+                    break;
+                }
+                granularity = (granularity * 2).min(units.len());
+            }
+        }
+
+        // 只剩一个单元时也尝试整体删除
+        if units.len() == 1 {
+            let mut mask = self.keep.clone();
+            for line in units[0].clone() {
+                mask[line] = false;
+            }
+            if self.test_mask(&mask) {
+                self.keep = mask;
+                units.clear();
+            }
+        }
+        units
+    }
+}
+
+/// 统计一行中花括号的净增量，忽略字符串、字符字面量和行注释中的花括号
+fn brace_delta(line: &str) -> i64 {
+    let mut delta = 0;
+    let mut chars = line.chars().peekable();
+    let mut in_string: Option<char> = None;
+
+    while let Some(c) = chars.next() {
+        match in_string {
+            Some(quote) => {
+                if c == '\\' {
+                    chars.next();
+                } else if c == quote {
+                    in_string = None;
+                }
+            }
+            None => match c {
+                '"' | '\'' => in_string = Some(c),
+                '/' if chars.peek() == Some(&'/') => break,
+                '{' => delta += 1,
+                '}' => delta -= 1,
+                _ => {}
+            },
+        }
+    }
+    delta
+}
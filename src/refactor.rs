@@ -0,0 +1,171 @@
+//! 重构：重命名符号
+//!
+//! 重命名基于语言服务器的符号索引（见 [`crate::lsp::index`]）：光标处的标识符解析到声明后，
+//! 文档中所有解析到同一声明的标识符一起改名。为了不改变程序的含义：
+//! - 方法连同同一继承层次中的同名方法（重载和重写）一起改名，否则调用可能解析到别的方法
+//! - 类连同它的构造函数一起改名
+//! - 改名后重新解析，每个标识符所指的声明必须与改名前相同（新名称不能与可见的声明冲突或遮蔽它们）；
+//!   改名前能通过语义分析的程序，改名后也必须能通过
+//!
+//! 与符号索引一样只处理入口文件中的声明和引用。供 `cay-refactor rename` 和 cay-lsp 的
+//! `textDocument/rename` 使用。
+
+use std::collections::HashMap;
+use crate::error::{cayResult, semantic_error, cayError};
+use crate::lexer::{self, Token};
+use crate::lsp::check_document;
+use crate::lsp::index::{Pos, Symbol, SymbolIndex, SymbolKind};
+
+/// 一处文本修改：把 `start` 到 `end`（同一行，不含 `end`）之间的文本替换为 `new_text`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub start: Pos,
+    pub end: Pos,
+    pub new_text: String,
+}
+
+/// 重命名文件中 `position`（行、列从 1 开始）处的符号，返回需要的修改
+pub fn rename_symbol(file: &str, position: Pos, new_name: &str) -> cayResult<Vec<TextEdit>> {
+    let text = crate::source::read_source_file(file)?;
+    rename_in_source(&text, Some(file), position, new_name)
+}
+
+/// 重命名源码 `text` 中 `position` 处的符号；`path` 为其文件路径，用于解析 import 和 `#include`
+pub fn rename_in_source(text: &str, path: Option<&str>, position: Pos, new_name: &str) -> cayResult<Vec<TextEdit>> {
+    let (line, column) = position;
+    check_identifier(new_name, position)?;
+
+    let mut index = None;
+    let analyzed = check_document(text, path, &mut index);
+    let Some(index) = index else {
+        // 无法解析时没有索引，报告解析错误
+        return Err(analyzed.err().unwrap_or_else(|| semantic_error(line, column, "Cannot parse the document")));
+    };
+    let Some((symbol, _)) = index.symbol_at(position) else {
+        return Err(semantic_error(line, column, "No renamable symbol at this position"));
+    };
+    if symbol.name == new_name {
+        return Ok(Vec::new());
+    }
+
+    let targets = rename_targets(&index, symbol);
+    let old_len = symbol.name.chars().count();
+    let edits: Vec<TextEdit> = index.identifiers().into_iter()
+        .filter(|(_, _, resolved)| resolved.is_some_and(|r| targets.iter().any(|t| std::ptr::eq(*t, r))))
+        .map(|(start, _, _)| TextEdit { start, end: (start.0, start.1 + old_len), new_text: new_name.to_string() })
+        .collect();
+
+    // 位置来自预处理后的源码，#include 和宏展开会使它与原文不一致
+    let lines: Vec<&str> = text.lines().collect();
+    for edit in &edits {
+        let found: String = lines.get(edit.start.0 - 1).map_or(String::new(), |l| l.chars().skip(edit.start.1 - 1).take(old_len).collect());
+        if found != symbol.name {
+            return Err(semantic_error(edit.start.0, edit.start.1,
+                format!("Cannot locate '{}' in the source text (the reference may come from #include or #define)", symbol.name)));
+        }
+    }
+
+    let renamed = apply_edits(text, &edits);
+    let mut renamed_index = None;
+    let renamed_result = check_document(&renamed, path, &mut renamed_index);
+    let broken = |reason: String| semantic_error(line, column,
+        format!("Renaming '{}' to '{}' would change the program: {}", symbol.name, new_name, reason));
+    let Some(renamed_index) = renamed_index else {
+        return Err(broken("the renamed source cannot be parsed".to_string()));
+    };
+    if analyzed.is_ok()
+        && let Err(error) = renamed_result
+    {
+        return Err(broken(first_message(&error)));
+    }
+
+    let before = resolutions(&index);
+    let after = resolutions(&renamed_index);
+    if before.len() != after.len() {
+        return Err(broken("the renamed source has a different structure".to_string()));
+    }
+    let identifiers = index.identifiers();
+    if let Some(i) = (0..before.len()).find(|&i| before[i] != after[i]) {
+        let (pos, name, _) = identifiers[i];
+        return Err(broken(format!("'{}' at {}:{} would refer to a different declaration", name, pos.0, pos.1)));
+    }
+    Ok(edits)
+}
+
+/// 把修改应用到源码上，修改之间不能重叠
+pub fn apply_edits(text: &str, edits: &[TextEdit]) -> String {
+    let mut by_line: HashMap<usize, Vec<&TextEdit>> = HashMap::new();
+    for edit in edits {
+        by_line.entry(edit.start.0).or_default().push(edit);
+    }
+    let mut result = String::with_capacity(text.len());
+    for (i, line) in text.split_inclusive('\n').enumerate() {
+        let Some(line_edits) = by_line.get_mut(&(i + 1)) else {
+            result.push_str(line);
+            continue;
+        };
+        line_edits.sort_by_key(|e| e.start.1);
+        let chars: Vec<char> = line.chars().collect();
+        let mut column = 1;
+        for edit in line_edits.iter() {
+            result.extend(&chars[(column - 1).min(chars.len())..(edit.start.1 - 1).min(chars.len())]);
+            result.push_str(&edit.new_text);
+            column = edit.end.1;
+        }
+        result.extend(&chars[(column - 1).min(chars.len())..]);
+    }
+    result
+}
+
+/// 新名称必须是一个标识符，且不使用编译器保留的 `__` 前缀
+fn check_identifier(name: &str, (line, column): Pos) -> cayResult<()> {
+    let tokens = lexer::lex(name).unwrap_or_default();
+    let valid = matches!(tokens.as_slice(), [first, eof]
+        if first.token == Token::Identifier(name.to_string()) && eof.token == Token::Identifier(String::new()));
+    if !valid {
+        return Err(semantic_error(line, column, format!("'{}' is not a valid identifier", name)));
+    }
+    if name.starts_with("__") {
+        return Err(semantic_error(line, column, format!("'{}' starts with '__', which is reserved for compiler-generated symbols", name)));
+    }
+    Ok(())
+}
+
+/// 需要一起改名的声明
+fn rename_targets<'a>(index: &'a SymbolIndex, symbol: &'a Symbol) -> Vec<&'a Symbol> {
+    match symbol.kind {
+        SymbolKind::Method => {
+            let family = symbol.container.as_deref().map(|c| index.related_types(c)).unwrap_or_default();
+            index.symbols().iter()
+                .filter(|s| s.kind == SymbolKind::Method && s.name == symbol.name)
+                .filter(|s| s.container.as_ref().is_some_and(|c| family.contains(c)))
+                .collect()
+        }
+        SymbolKind::Class | SymbolKind::Interface | SymbolKind::Constructor => {
+            let class = symbol.container.as_deref().unwrap_or(&symbol.name);
+            index.symbols().iter()
+                .filter(|s| match s.kind {
+                    SymbolKind::Class | SymbolKind::Interface => s.name == class,
+                    SymbolKind::Constructor => s.container.as_deref() == Some(class),
+                    _ => false,
+                })
+                .collect()
+        }
+        _ => vec![symbol],
+    }
+}
+
+/// 每个标识符所指声明的名称令牌序号；改名不改变标识符的个数和顺序，因此可以直接比较改名前后
+fn resolutions(index: &SymbolIndex) -> Vec<Option<usize>> {
+    let identifiers = index.identifiers();
+    identifiers.iter()
+        .map(|(_, _, resolved)| resolved.and_then(|symbol| identifiers.binary_search_by_key(&symbol.def, |(pos, _, _)| *pos).ok()))
+        .collect()
+}
+
+/// 错误的第一条消息（不含位置和提示）
+fn first_message(error: &cayError) -> String {
+    crate::diagnostics::Diagnostic::from_error(error, "")
+        .first()
+        .map_or_else(|| error.to_string(), |d| d.message.clone())
+}
@@ -1,11 +1,17 @@
 //! 语义分析器核心实现
 
+use std::collections::HashSet;
 use crate::ast::*;
-use crate::types::{Type, ParameterInfo, ClassInfo, MethodInfo, FieldInfo, TypeRegistry};
-use crate::error::{cayResult, semantic_error};
+use crate::types::{Type, FunctionType, ParameterInfo, ClassInfo, MethodInfo, FieldInfo, TypeRegistry};
+use crate::error::{cayError, cayResult, SourceLocation};
+use crate::interface::InterfaceSummary;
 use super::symbol_table::{SemanticSymbolTable, SemanticSymbolInfo};
+use super::class_analysis::EntryPoint;
+use super::format::FormatPiece;
+use super::type_check::JumpTarget;
 
 /// 语义分析器
+#[derive(Clone)]
 pub struct SemanticAnalyzer {
     pub(super) type_registry: TypeRegistry,
     pub(super) symbol_table: SemanticSymbolTable,
@@ -13,7 +19,30 @@ pub struct SemanticAnalyzer {
     pub(super) current_method: Option<String>,
     pub(super) current_method_is_static: bool,  // 当前方法是否是静态方法
     pub(super) current_method_is_constructor: bool,  // 当前是否是构造函数
-    pub(super) errors: Vec<String>,
+    pub(super) errors: Vec<cayError>,  // 收集的错误（各自保留位置信息）
+    pub(super) inferred_var_types: NodeMap<Type>,  // auto/var/let 变量推断出的类型
+    pub(super) null_safe_types: NodeMap<Type>,  // ?. 的对象类型和 ?? 的结果类型
+    pub(super) ternary_types: NodeMap<Type>,  // 三元运算符两个分支统一后的结果类型
+    pub(super) field_access_classes: NodeMap<String>,  // 实例字段访问和实例方法调用的对象静态类名
+    pub(super) string_comparisons: HashSet<NodeId>,  // 两侧都是 String 的 ==/!= 表达式
+    pub(super) never_returns: HashSet<NodeId>,  // 作为语句的不返回调用（exit），之后的代码不可达
+    pub(super) print_arg_types: NodeMap<Type>,  // print/println 调用参数的静态类型
+    pub(super) string_conversions: NodeMap<(Type, Type)>,  // 字符串拼接两侧操作数的静态类型
+    pub(super) lambda_captures: NodeMap<Vec<String>>,  // lambda 捕获的外层变量
+    pub(super) function_types: NodeMap<FunctionType>,  // lambda/方法引用的函数类型和通过函数变量的调用
+    pub(super) method_refs: NodeMap<MethodInfo>,  // 方法引用选中的目标方法
+    pub(super) debug_prints: NodeMap<(String, Type)>,  // debugPrint 参数的源代码文本和静态类型
+    pub(super) format_calls: NodeMap<(Vec<FormatPiece>, Vec<Type>)>,  // 格式化调用解析后的格式字符串和参数的静态类型
+    pub(super) for_each_types: NodeMap<(Type, Type)>,  // for-each 循环遍历对象的静态类型和循环变量的类型
+    pub(super) call_overloads: NodeMap<Vec<ParameterInfo>>,  // 方法调用选中的重载的参数
+    pub(super) cast_types: NodeMap<Type>,  // 类型转换表达式操作数的静态类型
+    pub(super) array_element_types: NodeMap<Type>,  // 数组下标访问的元素静态类型
+    pub(super) expr_types: NodeMap<Type>,  // 每个带 NodeId 的表达式的静态类型
+    pub(super) lambda_returns: Option<Vec<Type>>,  // 正在检查的块体 lambda 中 return 语句的类型
+    pub(super) jump_targets: Vec<JumpTarget>,  // 当前语句外层的循环、switch 和标签语句
+    pub(super) enclosing_expr_loc: Option<SourceLocation>,  // 正在推断的最内层带位置表达式的位置，供标识符等没有位置的表达式报错
+    pub(super) jobs: usize,  // 类型检查使用的线程数，大于 1 时各个类并行检查
+    pub(super) entry_point: EntryPoint,  // 程序入口 main 的检查方式
 }
 
 impl SemanticAnalyzer {
@@ -26,6 +55,29 @@ impl SemanticAnalyzer {
             current_method_is_static: false,
             current_method_is_constructor: false,
             errors: Vec::new(),
+            inferred_var_types: NodeMap::new(),
+            null_safe_types: NodeMap::new(),
+            ternary_types: NodeMap::new(),
+            field_access_classes: NodeMap::new(),
+            string_comparisons: HashSet::new(),
+            never_returns: HashSet::new(),
+            print_arg_types: NodeMap::new(),
+            string_conversions: NodeMap::new(),
+            lambda_captures: NodeMap::new(),
+            function_types: NodeMap::new(),
+            method_refs: NodeMap::new(),
+            debug_prints: NodeMap::new(),
+            format_calls: NodeMap::new(),
+            for_each_types: NodeMap::new(),
+            call_overloads: NodeMap::new(),
+            cast_types: NodeMap::new(),
+            array_element_types: NodeMap::new(),
+            expr_types: NodeMap::new(),
+            lambda_returns: None,
+            jump_targets: Vec::new(),
+            enclosing_expr_loc: None,
+            jobs: 1,
+            entry_point: EntryPoint::Optional,
         };
         
         // 注册内置函数
@@ -35,30 +87,196 @@ impl SemanticAnalyzer {
     }
 
     fn register_builtin_functions(&mut self) {
-        // 注册 print 函数 - 作为特殊处理
-        // print 可以接受任意类型参数
+        for signature in builtin_function_signatures() {
+            self.type_registry.register_builtin_function(signature);
+        }
+        self.type_registry.register_builtin_class(math_class_info());
+        self.type_registry.register_builtin_class(bits_class_info());
+        self.type_registry.register_builtin_class(file_class_info());
+        self.type_registry.register_builtin_class(arrays_class_info());
     }
 
+    /// 分析程序
+    ///
+    /// 类型检查阶段会在语句级别从错误中恢复并继续检查，
+    /// 发现多个错误时返回 `cayError::Multiple`，其中每个错误保留自己的位置。
     pub fn analyze(&mut self, program: &Program) -> cayResult<()> {
+        if let Err(e) = self.run_passes(program) {
+            self.errors.push(e);
+        }
+
+        match cayError::from_errors(self.errors.clone()) {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// 设置类型检查使用的线程数（默认 1，即按类的顺序依次检查）
+    pub fn set_jobs(&mut self, jobs: usize) {
+        self.jobs = jobs.max(1);
+    }
+
+    /// 设置程序入口的检查方式（默认 [`EntryPoint::Optional`]）
+    pub fn set_entry_point(&mut self, entry_point: EntryPoint) {
+        self.entry_point = entry_point;
+    }
+
+    /// auto/var/let 变量声明推断出的类型（以声明的 NodeId 为键），供代码生成使用
+    pub fn inferred_var_types(&self) -> &NodeMap<Type> {
+        &self.inferred_var_types
+    }
+
+    /// 空值安全运算符的类型信息，供代码生成使用
+    ///
+    /// 安全导航 `obj?.member` 以成员访问节点的 NodeId 记录对象的类型，
+    /// 空值合并 `a ?? b` 以其节点的 NodeId 记录结果类型。
+    pub fn null_safe_types(&self) -> &NodeMap<Type> {
+        &self.null_safe_types
+    }
+
+    /// 三元运算符的结果类型（以三元表达式的 NodeId 为键），代码生成把两个分支的值转换为该类型
+    pub fn ternary_types(&self) -> &NodeMap<Type> {
+        &self.ternary_types
+    }
+
+    /// 实例字段访问 `obj.field` 的对象静态类名（以成员访问节点的 NodeId 为键）
+    ///
+    /// 代码生成据此找到字段所在的类布局，对象可以是任意表达式（数组元素、方法调用结果等）。
+    pub fn field_access_classes(&self) -> &NodeMap<String> {
+        &self.field_access_classes
+    }
+
+    /// 两侧都是 String 的 `==`/`!=` 表达式（以二元表达式的 NodeId 为键），代码生成按内容比较
+    pub fn string_comparisons(&self) -> &HashSet<NodeId> {
+        &self.string_comparisons
+    }
+
+    /// print/println 参数的静态类型（以调用节点的 NodeId 为键）
+    ///
+    /// `char[]` 与 String 在 IR 中都是 `i8*`，代码生成需要据此区分打印方式。
+    pub fn print_arg_types(&self) -> &NodeMap<Type> {
+        &self.print_arg_types
+    }
+
+    /// 字符串拼接 `+` 两侧操作数的静态类型（以二元表达式的 NodeId 为键）
+    ///
+    /// 只记录至少一侧不是 String 的拼接，代码生成据此把另一侧转换为字符串；
+    /// 对象按其 `toString()` 转换，boolean 与 int 在 IR 中无法区分，也依赖这里的类型。
+    pub fn string_conversions(&self) -> &NodeMap<(Type, Type)> {
+        &self.string_conversions
+    }
+
+    /// lambda 捕获的外层变量名（以 lambda 节点的 NodeId 为键，按首次引用的顺序）
+    ///
+    /// 在实例上下文中访问字段或调用本类方法的 lambda 会捕获 `this`。
+    pub fn lambda_captures(&self) -> &NodeMap<Vec<String>> {
+        &self.lambda_captures
+    }
+
+    /// 函数类型信息（供代码生成使用）
+    ///
+    /// lambda 和方法引用以其节点的 NodeId 记录闭包的函数类型，
+    /// 通过函数类型变量的调用 `f(x)` 以调用节点的 NodeId 记录被调用闭包的类型。
+    pub fn function_types(&self) -> &NodeMap<FunctionType> {
+        &self.function_types
+    }
+
+    /// 方法引用选中的目标方法（以方法引用节点的 NodeId 为键），代码生成据此生成转发函数
+    pub fn method_refs(&self) -> &NodeMap<MethodInfo> {
+        &self.method_refs
+    }
+
+    /// debugPrint 参数的源代码文本和静态类型（以调用节点的 NodeId 为键）
+    pub fn debug_prints(&self) -> &NodeMap<(String, Type)> {
+        &self.debug_prints
+    }
+
+    /// format()/print(fmt, ...)/println(fmt, ...) 解析后的格式字符串和参数的静态类型（以调用节点的 NodeId 为键）
+    pub fn format_calls(&self) -> &NodeMap<(Vec<FormatPiece>, Vec<Type>)> {
+        &self.format_calls
+    }
+
+    /// for-each 循环遍历对象的静态类型和循环变量的类型（以循环节点的 NodeId 为键）
+    pub fn for_each_types(&self) -> &NodeMap<(Type, Type)> {
+        &self.for_each_types
+    }
+
+    /// 方法调用选中的重载的参数（以调用节点的 NodeId 为键）
+    ///
+    /// byte 与 char 在 IR 中都是 `i8`，代码生成按这里的参数类型选择函数名并转换实参。
+    pub fn call_overloads(&self) -> &NodeMap<Vec<ParameterInfo>> {
+        &self.call_overloads
+    }
+
+    /// 类型转换表达式操作数的静态类型（以转换表达式的 NodeId 为键）
+    ///
+    /// char 与 int 在 IR 中都是 `i32`，`(String) c` 需要据此把码点编码为字符。
+    pub fn cast_types(&self) -> &NodeMap<Type> {
+        &self.cast_types
+    }
+
+    /// 数组下标访问的元素静态类型（以访问表达式的 NodeId 为键）
+    ///
+    /// 数组值的 LLVM 类型不一定反映元素类型（如经过 `i8*` 传递的数组），元素的读写以这里为准。
+    pub fn array_element_types(&self) -> &NodeMap<Type> {
+        &self.array_element_types
+    }
+
+    /// 表达式的静态类型（以表达式的 NodeId 为键），代码生成据此为生成的值附上语言类型
+    pub fn expr_types(&self) -> &NodeMap<Type> {
+        &self.expr_types
+    }
+
+    /// 分析过程中收集到的所有错误
+    pub fn diagnostics(&self) -> &[cayError] {
+        &self.errors
+    }
+
+    fn run_passes(&mut self, program: &Program) -> cayResult<()> {
         // 第一遍：收集所有类定义
         self.collect_classes(program)?;
 
-        // 检查主类冲突（在收集类之后，类型检查之前）
-        self.check_main_class_conflicts(program)?;
+        // 检查保留标识符（与编译器生成的符号冲突）
+        self.check_reserved_identifiers(program);
+
+        // 检查主类冲突（在收集类之后，类型检查之前）；库模式下 main 只是普通方法
+        if self.entry_point != EntryPoint::Library {
+            self.check_main_class_conflicts(program)?;
+            self.check_main_signatures(program);
+        }
 
         // 第二遍：分析方法定义
         self.analyze_methods(program)?;
 
-        // 第三遍：检查继承关系（包括 @Override 验证）
+        // 第三遍：检查继承关系（包括 @Override 验证）和接口实现
         self.check_inheritance(program)?;
+        self.check_interface_implementations(program);
 
         // 第四遍：类型检查
         self.type_check_program(program)?;
 
-        if !self.errors.is_empty() {
-            return Err(semantic_error(0, 0, self.errors.join("\n")));
-        }
+        // 局部变量读取前必须已赋值，没有初始值的 final 变量只能赋值一次
+        self.check_definite_assignment(program);
 
+        // 被 lambda 捕获的变量必须是事实上的 final
+        self.check_captured_variables(program);
+        Ok(())
+    }
+
+    /// 导入 .cavi 接口摘要中的类和接口
+    ///
+    /// 必须在 `analyze` 之前调用，导入的类在语义分析中与本地定义的类一样可见。
+    pub fn import_interface(&mut self, summary: &InterfaceSummary) -> cayResult<()> {
+        for interface in &summary.interfaces {
+            if !self.type_registry.interface_exists(&interface.name) {
+                self.type_registry.register_interface(interface.clone())?;
+                self.type_registry.imported_interfaces.insert(interface.name.clone());
+            }
+        }
+        for class in &summary.classes {
+            self.type_registry.register_class(class.clone())?;
+            self.type_registry.imported_classes.insert(class.name.clone());
+        }
         Ok(())
     }
 
@@ -67,3 +285,187 @@ impl SemanticAnalyzer {
         &self.type_registry
     }
 }
+
+/// 构造内置函数或内置类静态方法的签名（全局函数的 `class_name` 为空）
+fn builtin_signature(class_name: &str, name: &str, params: &[Type], return_type: Type) -> MethodInfo {
+    MethodInfo {
+        name: name.to_string(),
+        class_name: class_name.to_string(),
+        params: params.iter().enumerate()
+            .map(|(i, ty)| ParameterInfo::new(format!("arg{}", i), ty.clone()))
+            .collect(),
+        return_type,
+        is_public: true,
+        is_private: false,
+        is_protected: false,
+        is_static: true,
+        is_native: false,
+        is_override: false,
+        is_final: true,
+        link_name: None,
+    }
+}
+
+/// 内置全局函数的签名
+///
+/// print 按可打印的类型重载（char[] 按字符串打印，对象按 `toString()` 打印，
+/// 其他数组不在签名中，由 `infer_builtin_call_type` 直接接受并按元素打印），
+/// println 另有无参数的重载只输出换行，`println(double, int)` 按给定的小数位数输出浮点数；
+/// setPrintPrecision 设置之后打印浮点数使用的小数位数；
+/// toIntExact/toByteExact 是值超出范围时终止程序的窄化转换；exit 以给定的退出码结束程序，不会返回。
+fn builtin_function_signatures() -> Vec<MethodInfo> {
+    let printable = [
+        Type::Int8, Type::Int16, Type::Int32, Type::Int64, Type::Float32, Type::Float64, Type::Bool, Type::Char, Type::String,
+        Type::Array(Box::new(Type::Char)), Type::Array(Box::new(Type::Bool)),
+        Type::Object("Object".to_string()),
+    ];
+    let mut signatures = vec![builtin_signature("", "println", &[], Type::Void)];
+    for name in ["print", "println"] {
+        for ty in &printable {
+            signatures.push(builtin_signature("", name, std::slice::from_ref(ty), Type::Void));
+        }
+    }
+    signatures.push(builtin_signature("", "println", &[Type::Float64, Type::Int32], Type::Void));
+    signatures.push(builtin_signature("", "setPrintPrecision", &[Type::Int32], Type::Void));
+    signatures.push(builtin_signature("", "toIntExact", &[Type::Int64], Type::Int32));
+    signatures.push(builtin_signature("", "toByteExact", &[Type::Int32], Type::Int32));
+    signatures.push(builtin_signature("", "exit", &[Type::Int32], Type::Never));
+    signatures.push(builtin_signature("", "readInt", &[], Type::Int32));
+    signatures.push(builtin_signature("", "readLong", &[], Type::Int64));
+    signatures.push(builtin_signature("", "readFloat", &[], Type::Float32));
+    signatures.push(builtin_signature("", "readDouble", &[], Type::Float64));
+    signatures.push(builtin_signature("", "readChar", &[], Type::Char));
+    signatures.push(builtin_signature("", "readBool", &[], Type::Bool));
+    signatures.push(builtin_signature("", "readLine", &[], Type::String));
+    signatures.push(builtin_signature("", "readChars", &[Type::Array(Box::new(Type::Char))], Type::Int32));
+    signatures
+}
+
+/// 内置 Math 类：只包含静态方法和常量，由代码生成展开为 LLVM 内建函数或 libm 调用
+///
+/// abs/min/max 按 int、long、float、double 重载，重载解析按声明顺序优先选择较窄的类型。
+fn math_class_info() -> ClassInfo {
+    let mut methods: std::collections::HashMap<String, Vec<MethodInfo>> = std::collections::HashMap::new();
+    let mut add = |name: &str, params: &[Type], return_type: Type| {
+        methods.entry(name.to_string()).or_default().push(builtin_signature("Math", name, params, return_type));
+    };
+
+    for name in ["sqrt", "floor", "ceil", "sin", "cos", "tan", "exp", "log"] {
+        add(name, &[Type::Float64], Type::Float64);
+    }
+    add("pow", &[Type::Float64, Type::Float64], Type::Float64);
+    add("round", &[Type::Float64], Type::Int64);
+    add("random", &[], Type::Float64);
+    for ty in [Type::Int32, Type::Int64, Type::Float32, Type::Float64] {
+        add("abs", std::slice::from_ref(&ty), ty.clone());
+        add("min", &[ty.clone(), ty.clone()], ty.clone());
+        add("max", &[ty.clone(), ty.clone()], ty);
+    }
+
+    let mut fields = std::collections::HashMap::new();
+    for name in ["PI", "E"] {
+        fields.insert(name.to_string(), FieldInfo {
+            name: name.to_string(),
+            field_type: Type::Float64,
+            is_public: true,
+            is_private: false,
+            is_protected: false,
+            is_static: true,
+            is_final: true,
+            is_const_expr: true,
+        });
+    }
+
+    ClassInfo {
+        name: "Math".to_string(),
+        methods,
+        fields,
+        constructors: Vec::new(),
+        has_destructor: false,
+        parent: None,
+        interfaces: Vec::new(),
+        is_abstract: true,
+        is_final: true,
+    }
+}
+
+/// 内置 Bits 类：位运算静态方法，由代码生成展开为 LLVM 位运算内建函数
+///
+/// 与 Java 的 `Integer.bitCount` 等一致，计数类方法对 int 和 long 都返回 int；
+/// 循环移位的距离按位宽取模，负数距离表示反方向移位。
+fn bits_class_info() -> ClassInfo {
+    let mut methods: std::collections::HashMap<String, Vec<MethodInfo>> = std::collections::HashMap::new();
+    let mut add = |name: &str, params: &[Type], return_type: Type| {
+        methods.entry(name.to_string()).or_default().push(builtin_signature("Bits", name, params, return_type));
+    };
+
+    for ty in [Type::Int32, Type::Int64] {
+        for name in ["popcount", "leadingZeros", "trailingZeros"] {
+            add(name, std::slice::from_ref(&ty), Type::Int32);
+        }
+        for name in ["rotateLeft", "rotateRight"] {
+            add(name, &[ty.clone(), Type::Int32], ty.clone());
+        }
+    }
+
+    ClassInfo {
+        name: "Bits".to_string(),
+        methods,
+        fields: std::collections::HashMap::new(),
+        constructors: Vec::new(),
+        has_destructor: false,
+        parent: None,
+        interfaces: Vec::new(),
+        is_abstract: true,
+        is_final: true,
+    }
+}
+
+/// 内置 Arrays 类：`toString(arr)` 把任意元素类型的数组（包括多维数组）格式化为 `[1, 2, 3]`
+///
+/// 登记的签名只用于名称查找，实参由 `infer_arrays_to_string_type` 按数组类型单独检查。
+fn arrays_class_info() -> ClassInfo {
+    let mut methods: std::collections::HashMap<String, Vec<MethodInfo>> = std::collections::HashMap::new();
+    let array = Type::Array(Box::new(Type::Object("Object".to_string())));
+    methods.insert("toString".to_string(), vec![builtin_signature("Arrays", "toString", &[array], Type::String)]);
+
+    ClassInfo {
+        name: "Arrays".to_string(),
+        methods,
+        fields: std::collections::HashMap::new(),
+        constructors: Vec::new(),
+        has_destructor: false,
+        parent: None,
+        interfaces: Vec::new(),
+        is_abstract: true,
+        is_final: true,
+    }
+}
+
+/// 内置 File 类：按路径读写整个文本文件的静态方法，由代码生成展开为运行时函数
+///
+/// 文件无法打开时不终止程序：readAll 返回 null，writeAll/appendLine/delete 返回 false。
+fn file_class_info() -> ClassInfo {
+    let mut methods: std::collections::HashMap<String, Vec<MethodInfo>> = std::collections::HashMap::new();
+    let mut add = |name: &str, params: &[Type], return_type: Type| {
+        methods.entry(name.to_string()).or_default().push(builtin_signature("File", name, params, return_type));
+    };
+
+    add("readAll", &[Type::String], Type::String);
+    add("writeAll", &[Type::String, Type::String], Type::Bool);
+    add("appendLine", &[Type::String, Type::String], Type::Bool);
+    add("exists", &[Type::String], Type::Bool);
+    add("delete", &[Type::String], Type::Bool);
+
+    ClassInfo {
+        name: "File".to_string(),
+        methods,
+        fields: std::collections::HashMap::new(),
+        constructors: Vec::new(),
+        has_destructor: false,
+        parent: None,
+        interfaces: Vec::new(),
+        is_abstract: true,
+        is_final: true,
+    }
+}
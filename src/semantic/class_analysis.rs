@@ -1,10 +1,73 @@
-//! 类定义、继承关系分析和主类冲突分析
+//! 类定义、继承关系分析、主类冲突分析和入口签名检查
 
 use crate::ast::{Program, ClassMember, Modifier, MethodDecl};
 use crate::types::{ClassInfo, FieldInfo, MethodInfo, ParameterInfo, Type};
-use crate::error::{cayResult, semantic_error};
+use crate::error::{cayResult, semantic_error, SourceLocation};
 use super::analyzer::SemanticAnalyzer;
 
+/// static native 方法绑定的 C 函数名：`@Symbol("name")` 指定的名字，默认为方法名
+fn native_link_name(method: &MethodDecl) -> Option<String> {
+    if !method.modifiers.contains(&Modifier::Native) || !method.modifiers.contains(&Modifier::Static) {
+        return None;
+    }
+    let symbol = method.modifiers.iter().find_map(|m| match m {
+        Modifier::Symbol(name) => Some(name.clone()),
+        _ => None,
+    });
+    Some(symbol.unwrap_or_else(|| method.name.clone()))
+}
+
+/// 检查 native 方法能否绑定到 C 函数
+///
+/// 内置类（Map、StringBuilder）的 native 实例方法由运行时实现；其他类的 native 方法必须是 static，
+/// 参数和返回值只能是基本类型、String、数组和对象（按指针传递）。
+fn check_native_method(class_name: &str, method: &MethodDecl, builtin_native: bool) -> cayResult<()> {
+    let error = |message: String| Err(semantic_error(method.loc.line, method.loc.column, message));
+    let is_native = method.modifiers.contains(&Modifier::Native);
+    let symbol = method.modifiers.iter().find_map(|m| match m {
+        Modifier::Symbol(name) => Some(name),
+        _ => None,
+    });
+    if let Some(symbol) = symbol {
+        if !is_native {
+            return error(format!("@Symbol can only be used on native methods, but '{}.{}' is not native", class_name, method.name));
+        }
+        let valid = symbol.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && symbol.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return error(format!("Invalid C function name '{}' in @Symbol", symbol));
+        }
+    }
+    if !is_native || builtin_native {
+        return Ok(());
+    }
+    if !method.modifiers.contains(&Modifier::Static) {
+        return error(format!("Native method '{}.{}' must be static: only static native methods can bind to C functions", class_name, method.name));
+    }
+    if method.params.iter().any(|p| p.is_varargs) {
+        return error(format!("Native method '{}.{}' cannot have a varargs parameter", class_name, method.name));
+    }
+    let types = method.params.iter().map(|p| &p.param_type).chain(std::iter::once(&method.return_type));
+    if let Some(ty) = types.into_iter().find(|ty| matches!(ty, Type::Function(_))) {
+        return error(format!("Native method '{}.{}' cannot take or return function type {}", class_name, method.name, ty));
+    }
+    Ok(())
+}
+
+/// 编译器保留的标识符前缀
+pub const RESERVED_IDENTIFIER_PREFIX: &str = "__";
+
+/// 程序入口 main 的检查方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryPoint {
+    /// 有 main 时检查签名和主类冲突，没有 main 不报错（cay-check、语言服务器分析单个模块）
+    Optional,
+    /// 编译可执行程序，必须有 main
+    Required,
+    /// 库模式（`--no-main`）：main 作为普通静态方法，不检查也不生成入口
+    Library,
+}
+
 impl SemanticAnalyzer {
     /// 检查主类冲突
     /// 规则：
@@ -16,6 +79,7 @@ impl SemanticAnalyzer {
     pub fn check_main_class_conflicts(&mut self, program: &Program) -> cayResult<()> {
         // 收集所有有 main 方法的类
         let mut main_classes: Vec<(String, bool)> = Vec::new(); // (类名, 是否有@main标记)
+        let mut conflict_loc = None;  // 第二个包含 main 方法的类，冲突报告在这里
 
         for class in &program.classes {
             let has_main = class.members.iter().any(|m| {
@@ -30,14 +94,26 @@ impl SemanticAnalyzer {
 
             if has_main {
                 let has_main_marker = class.modifiers.contains(&crate::ast::Modifier::Main);
+                if main_classes.len() == 1 {
+                    conflict_loc = Some(class.loc.clone());
+                }
                 main_classes.push((class.name.clone(), has_main_marker));
             }
         }
 
         // 分析冲突
-        match main_classes.len() {
+        let result = match main_classes.len() {
+            0 if self.entry_point == EntryPoint::Required
+                && !program.top_level_functions.iter().any(|f| f.name == "main") =>
+            {
+                Err(semantic_error(
+                    0, 0,
+                    "Program has no entry point: no class declares a 'public static void main()' main method and \
+                     there is no top-level 'main' function; compile with --no-main to build a library".to_string()
+                ))
+            }
             0 => {
-                // 没有主类，这是允许的（可能是库文件）
+                // 没有主类：顶层 main 函数作为入口，或者只做检查的库文件
                 Ok(())
             }
             1 => {
@@ -84,6 +160,111 @@ impl SemanticAnalyzer {
                     }
                 }
             }
+        };
+        result.map_err(|e| match conflict_loc {
+            Some(loc) => e.with_fallback_location(loc.line, loc.column),
+            None => e,
+        })
+    }
+
+    /// 检查程序入口 main 的签名
+    ///
+    /// 入口（类中的 `public static main` 或顶层 `main` 函数）返回 `void` 或 `int`，
+    /// 不带参数或只带一个 `String[]` 参数：返回 `void` 时进程退出码为 0，
+    /// 返回 `int` 时以返回值作为退出码。
+    pub fn check_main_signatures(&mut self, program: &Program) {
+        for func in &program.top_level_functions {
+            if func.name == "main" {
+                self.check_main_signature(&func.return_type, &func.params, "", &func.loc);
+            }
+        }
+
+        for class in &program.classes {
+            for member in &class.members {
+                if let ClassMember::Method(method) = member
+                    && method.name == "main"
+                    && method.modifiers.contains(&Modifier::Public)
+                    && method.modifiers.contains(&Modifier::Static)
+                {
+                    self.check_main_signature(&method.return_type, &method.params, "public static ", &method.loc);
+                }
+            }
+        }
+    }
+
+    fn check_main_signature(&mut self, return_type: &Type, params: &[ParameterInfo], prefix: &str, loc: &SourceLocation) {
+        let params_ok = match params {
+            [] => true,
+            [param] => param.param_type == Type::Array(Box::new(Type::String)),
+            _ => false,
+        };
+        if params_ok && matches!(return_type, Type::Void | Type::Int32) {
+            return;
+        }
+        let param_list: Vec<String> = params.iter().map(|p| p.param_type.to_string()).collect();
+        self.errors.push(semantic_error(
+            loc.line, loc.column,
+            format!(
+                "Unsupported main signature '{prefix}{} main({})'; accepted forms are '{prefix}void main()', \
+                 '{prefix}int main()', '{prefix}void main(String[] args)' and '{prefix}int main(String[] args)'",
+                return_type, param_list.join(", ")
+            )
+        ));
+    }
+
+    /// 检查声明中是否使用了编译器保留的标识符
+    ///
+    /// 以 `__` 开头的名称保留给编译器生成的符号（运行时函数 `__cay_*`、类型标识 `__type_id_*`、
+    /// Lambda 函数、构造函数和重载方法的修饰名），用户声明使用这类名称时报告错误。
+    pub fn check_reserved_identifiers(&mut self, program: &Program) {
+        for interface in &program.interfaces {
+            self.check_identifier_not_reserved(&interface.name, "接口", &interface.loc);
+            for method in &interface.methods {
+                self.check_method_identifiers(method);
+            }
+        }
+
+        for func in &program.top_level_functions {
+            self.check_identifier_not_reserved(&func.name, "函数", &func.loc);
+            for param in &func.params {
+                self.check_identifier_not_reserved(&param.name, "参数", &func.loc);
+            }
+        }
+
+        for class in &program.classes {
+            self.check_identifier_not_reserved(&class.name, "类", &class.loc);
+            for member in &class.members {
+                match member {
+                    ClassMember::Field(field) => {
+                        self.check_identifier_not_reserved(&field.name, "字段", &field.loc);
+                    }
+                    ClassMember::Method(method) => self.check_method_identifiers(method),
+                    ClassMember::Constructor(ctor) => {
+                        for param in &ctor.params {
+                            self.check_identifier_not_reserved(&param.name, "参数", &ctor.loc);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn check_method_identifiers(&mut self, method: &MethodDecl) {
+        self.check_identifier_not_reserved(&method.name, "方法", &method.loc);
+        for param in &method.params {
+            self.check_identifier_not_reserved(&param.name, "参数", &method.loc);
+        }
+    }
+
+    /// 标识符以保留前缀开头时记录错误
+    pub(crate) fn check_identifier_not_reserved(&mut self, name: &str, kind: &str, loc: &SourceLocation) {
+        if name.starts_with(RESERVED_IDENTIFIER_PREFIX) {
+            self.errors.push(semantic_error(
+                loc.line,
+                loc.column,
+                format!("{}名 '{}' 以 '{}' 开头，该前缀保留给编译器生成的符号", kind, name, RESERVED_IDENTIFIER_PREFIX),
+            ));
         }
     }
 
@@ -107,11 +288,13 @@ impl SemanticAnalyzer {
                     is_native: false,
                     is_override: false,
                     is_final: false,  // 接口方法不是final
+                    link_name: None,
                 };
                 interface_info.add_method(method_info);
             }
 
-            self.type_registry.register_interface(interface_info)?;
+            self.type_registry.register_interface(interface_info)
+                .map_err(|e| e.with_fallback_location(interface.loc.line, interface.loc.column))?;
         }
 
         // 然后收集类定义
@@ -168,7 +351,8 @@ impl SemanticAnalyzer {
                 }
             }
 
-            self.type_registry.register_class(class_info)?;
+            self.type_registry.register_class(class_info)
+                .map_err(|e| e.with_fallback_location(class.loc.line, class.loc.column))?;
         }
         Ok(())
     }
@@ -178,8 +362,10 @@ impl SemanticAnalyzer {
         for class in &program.classes {
             self.current_class = Some(class.name.clone());
 
+            let builtin_native = crate::prelude::native_class(class).is_some();
             for member in &class.members {
                 if let ClassMember::Method(method) = member {
+                    check_native_method(&class.name, method, builtin_native)?;
                     let method_info = MethodInfo {
                         name: method.name.clone(),
                         class_name: class.name.clone(),
@@ -192,6 +378,7 @@ impl SemanticAnalyzer {
                         is_native: method.modifiers.contains(&Modifier::Native),
                         is_override: method.modifiers.contains(&Modifier::Override),
                         is_final: method.modifiers.contains(&Modifier::Final),
+                        link_name: native_link_name(method),
                     };
 
                     if let Some(class_info) = self.type_registry.classes.get_mut(&class.name) {
@@ -240,7 +427,8 @@ impl SemanticAnalyzer {
 
         // 第三遍：检测循环继承
         for class in &program.classes {
-            self.check_circular_inheritance(&class.name, &class.name, &mut Vec::new())?;
+            self.check_circular_inheritance(&class.name, &class.name, &mut Vec::new())
+                .map_err(|e| e.with_fallback_location(class.loc.line, class.loc.column))?;
         }
 
         // 第四遍：验证 @Override 注解 和 final 方法检查
@@ -252,6 +440,72 @@ impl SemanticAnalyzer {
         Ok(())
     }
 
+    /// 检查类实现了所声明接口（包括父类声明的接口）的全部方法
+    ///
+    /// 实现方法必须是实例方法，参数类型和返回类型与接口声明完全一致；抽象类可以不实现。
+    pub fn check_interface_implementations(&mut self, program: &Program) {
+        for class in &program.classes {
+            for interface_name in &class.interfaces {
+                if !self.type_registry.interface_exists(interface_name) {
+                    self.errors.push(semantic_error(
+                        class.loc.line,
+                        class.loc.column,
+                        format!("Class '{}' implements undefined interface '{}'", class.name, interface_name)
+                    ));
+                }
+            }
+            if class.modifiers.contains(&Modifier::Abstract) {
+                continue;
+            }
+
+            for interface_name in self.collect_class_interfaces(&class.name) {
+                let Some(interface) = self.type_registry.get_interface(&interface_name) else {
+                    continue;
+                };
+                for required in interface.sorted_methods() {
+                    let param_types: Vec<Type> = required.params.iter().map(|p| p.param_type.clone()).collect();
+                    let implemented = self.find_interface_implementation(&class.name, &required.name, &param_types)
+                        .is_some_and(|m| m.return_type == required.return_type);
+                    if !implemented {
+                        let params: Vec<String> = param_types.iter().map(|t| t.to_string()).collect();
+                        self.errors.push(semantic_error(
+                            class.loc.line,
+                            class.loc.column,
+                            format!("Class '{}' must implement method '{} {}({})' from interface '{}'",
+                                class.name, required.return_type, required.name, params.join(", "), interface_name)
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    /// 类及其父类声明实现的所有接口（去重，保持声明顺序）
+    fn collect_class_interfaces(&self, class_name: &str) -> Vec<String> {
+        let mut interfaces = Vec::new();
+        let mut current = self.type_registry.get_class(class_name);
+        while let Some(class_info) = current {
+            for interface in &class_info.interfaces {
+                if !interfaces.contains(interface) {
+                    interfaces.push(interface.clone());
+                }
+            }
+            current = class_info.parent.as_deref().and_then(|p| self.type_registry.get_class(p));
+        }
+        interfaces
+    }
+
+    /// 沿继承链查找参数类型完全一致的实例方法
+    fn find_interface_implementation(&self, class_name: &str, method_name: &str, param_types: &[Type]) -> Option<&MethodInfo> {
+        let class_info = self.type_registry.get_class(class_name)?;
+        let found = class_info.methods.get(method_name).and_then(|methods| {
+            methods.iter().find(|m| {
+                !m.is_static && m.params.iter().map(|p| &p.param_type).eq(param_types.iter())
+            })
+        });
+        found.or_else(|| self.find_interface_implementation(class_info.parent.as_deref()?, method_name, param_types))
+    }
+
     /// 递归检查循环继承
     fn check_circular_inheritance(&self, original: &str, current: &str, visited: &mut Vec<String>) -> cayResult<()> {
         if visited.contains(&current.to_string()) {
@@ -0,0 +1,319 @@
+//! Lambda 闭包分析
+//!
+//! 计算 lambda 体引用的外层局部变量（捕获集合），供代码生成构造环境结构体。
+//! 捕获按值进行：创建 lambda 时把变量的当前值复制到环境中，
+//! 因此与 Java 一样要求被捕获的局部变量是 final 或事实上的 final（effectively final）。
+
+use std::collections::{HashMap, HashSet};
+use crate::ast::*;
+use crate::error::{cayError, semantic_error};
+use super::analyzer::SemanticAnalyzer;
+
+/// 遍历时访问到的节点
+#[derive(Clone, Copy)]
+enum Node<'a> {
+    Stmt(&'a Stmt),
+    Expr(&'a Expr),
+}
+
+/// 按先序遍历语句块中的所有语句和表达式，包括嵌套 lambda 的函数体
+///
+/// 不带对象的调用 `f(x)` 中的 `f` 是方法名，不作为标识符表达式访问。
+fn walk_block<'a>(block: &'a Block, f: &mut dyn FnMut(Node<'a>)) {
+    for stmt in &block.statements {
+        walk_stmt(stmt, f);
+    }
+}
+
+fn walk_stmt<'a>(stmt: &'a Stmt, f: &mut dyn FnMut(Node<'a>)) {
+    f(Node::Stmt(stmt));
+    match stmt {
+        Stmt::Expr(expr) => walk_expr(expr, f),
+        Stmt::VarDecl(var) => {
+            if let Some(init) = &var.initializer {
+                walk_expr(init, f);
+            }
+        }
+        Stmt::Return(ret) => {
+            if let Some(expr) = &ret.value {
+                walk_expr(expr, f);
+            }
+        }
+        Stmt::If(if_stmt) => {
+            walk_expr(&if_stmt.condition, f);
+            walk_stmt(&if_stmt.then_branch, f);
+            if let Some(else_branch) = &if_stmt.else_branch {
+                walk_stmt(else_branch, f);
+            }
+        }
+        Stmt::While(while_stmt) => {
+            walk_expr(&while_stmt.condition, f);
+            walk_stmt(&while_stmt.body, f);
+        }
+        Stmt::DoWhile(do_while) => {
+            walk_stmt(&do_while.body, f);
+            walk_expr(&do_while.condition, f);
+        }
+        Stmt::For(for_stmt) => {
+            if let Some(init) = &for_stmt.init {
+                walk_stmt(init, f);
+            }
+            if let Some(condition) = &for_stmt.condition {
+                walk_expr(condition, f);
+            }
+            if let Some(update) = &for_stmt.update {
+                walk_expr(update, f);
+            }
+            walk_stmt(&for_stmt.body, f);
+        }
+        Stmt::ForEach(for_each) => {
+            walk_expr(&for_each.iterable, f);
+            walk_stmt(&for_each.body, f);
+        }
+        Stmt::Switch(switch) => {
+            walk_expr(&switch.expr, f);
+            for body in switch.cases.iter().map(|c| &c.body).chain(switch.default.as_ref()) {
+                for stmt in body {
+                    walk_stmt(stmt, f);
+                }
+            }
+        }
+        Stmt::Block(block) => walk_block(block, f),
+        Stmt::Labeled(labeled) => walk_stmt(&labeled.body, f),
+        Stmt::Break(_) | Stmt::Continue(_) => {}
+    }
+}
+
+fn walk_expr<'a>(expr: &'a Expr, f: &mut dyn FnMut(Node<'a>)) {
+    f(Node::Expr(expr));
+    match expr {
+        Expr::Literal(_) | Expr::Identifier(_) => {}
+        Expr::Binary(bin) => {
+            walk_expr(&bin.left, f);
+            walk_expr(&bin.right, f);
+        }
+        Expr::Unary(unary) => walk_expr(&unary.operand, f),
+        Expr::Call(call) => {
+            if !matches!(call.callee.as_ref(), Expr::Identifier(_)) {
+                walk_expr(&call.callee, f);
+            }
+            for arg in &call.args {
+                walk_expr(arg, f);
+            }
+        }
+        Expr::MemberAccess(member) => walk_expr(&member.object, f),
+        Expr::New(new_expr) => {
+            for arg in &new_expr.args {
+                walk_expr(arg, f);
+            }
+        }
+        Expr::Assignment(assign) => {
+            walk_expr(&assign.target, f);
+            walk_expr(&assign.value, f);
+        }
+        Expr::Cast(cast) => walk_expr(&cast.expr, f),
+        Expr::ArrayCreation(creation) => {
+            for size in &creation.sizes {
+                walk_expr(size, f);
+            }
+        }
+        Expr::ArrayAccess(access) => {
+            walk_expr(&access.array, f);
+            walk_expr(&access.index, f);
+        }
+        Expr::ArrayInit(init) => {
+            for element in &init.elements {
+                walk_expr(element, f);
+            }
+        }
+        Expr::MethodRef(method_ref) => {
+            if let Some(object) = &method_ref.object {
+                walk_expr(object, f);
+            }
+        }
+        Expr::Lambda(lambda) => walk_lambda_body(&lambda.body, f),
+        Expr::Ternary(ternary) => {
+            walk_expr(&ternary.condition, f);
+            walk_expr(&ternary.true_branch, f);
+            walk_expr(&ternary.false_branch, f);
+        }
+        Expr::InstanceOf(instance_of) => walk_expr(&instance_of.expr, f),
+        Expr::NullCoalesce(coalesce) => {
+            walk_expr(&coalesce.value, f);
+            walk_expr(&coalesce.default, f);
+        }
+        Expr::InlineIr(inline) => inline.args.iter().for_each(|arg| walk_expr(arg, f)),
+    }
+}
+
+fn walk_lambda_body<'a>(body: &'a LambdaBody, f: &mut dyn FnMut(Node<'a>)) {
+    match body {
+        LambdaBody::Expr(expr) => walk_expr(expr, f),
+        LambdaBody::Block(block) => walk_block(block, f),
+    }
+}
+
+/// lambda 体中引用但未在其中声明的名字
+#[derive(Debug, Default)]
+pub(super) struct FreeNames {
+    /// 作为标识符引用的名字（按首次出现的顺序）
+    pub variables: Vec<String>,
+    /// 不带对象的调用名，如 `helper(x)` 中的 `helper`
+    pub calls: Vec<String>,
+}
+
+/// 计算 lambda 体中的自由名字
+///
+/// lambda 参数、体内声明的局部变量以及嵌套 lambda 的参数都不是自由的。
+/// 与 Java 一样，lambda 参数和体内变量不能遮蔽外层局部变量，因此不区分作用域。
+pub(super) fn free_names(lambda: &LambdaExpr) -> FreeNames {
+    let mut declared: HashSet<&str> = lambda.params.iter().map(|p| p.name.as_str()).collect();
+    let mut variables: Vec<&str> = Vec::new();
+    let mut calls: Vec<&str> = Vec::new();
+
+    walk_lambda_body(&lambda.body, &mut |node| match node {
+        Node::Stmt(Stmt::VarDecl(var)) => {
+            declared.insert(&var.name);
+        }
+        Node::Expr(Expr::Identifier(name)) if !variables.contains(&name.as_str()) => {
+            variables.push(name);
+        }
+        // `obj::method` 中的名字被解析为类名，是局部变量时同样需要捕获
+        Node::Expr(Expr::MethodRef(MethodRefExpr { class_name: Some(name), .. })) if !variables.contains(&name.as_str()) => {
+            variables.push(name);
+        }
+        Node::Expr(Expr::Call(call)) => {
+            if let Expr::Identifier(name) = call.callee.as_ref()
+                && !calls.contains(&name.as_str())
+            {
+                calls.push(name);
+            }
+        }
+        Node::Expr(Expr::Lambda(inner)) => {
+            declared.extend(inner.params.iter().map(|p| p.name.as_str()));
+        }
+        _ => {}
+    });
+
+    let is_free = |name: &&str| !declared.contains(name);
+    FreeNames {
+        variables: variables.into_iter().filter(is_free).map(str::to_string).collect(),
+        calls: calls.into_iter().filter(is_free).map(str::to_string).collect(),
+    }
+}
+
+impl SemanticAnalyzer {
+    /// 计算 lambda 的捕获集合
+    ///
+    /// 捕获当前作用域中可见的局部变量和参数；在实例上下文中访问字段或调用本类方法时
+    /// 捕获 `this`。当前类的静态字段直接访问，不需要捕获。
+    pub(super) fn lambda_capture_set(&self, lambda: &LambdaExpr) -> Vec<String> {
+        let names = free_names(lambda);
+        let mut captures: Vec<String> = Vec::new();
+        let mut needs_this = false;
+
+        let current_class = self.current_class.as_deref().unwrap_or("");
+        for name in &names.variables {
+            let field = self.type_registry.find_field(current_class, name).map(|(_, f)| f.is_static);
+            if field == Some(true) {
+                continue;
+            }
+            if self.symbol_table.lookup(name).is_some() {
+                captures.push(name.clone());
+            } else if field == Some(false) {
+                needs_this = true;
+            }
+        }
+        for name in &names.calls {
+            if self.symbol_table.lookup(name).is_some() {
+                if !captures.contains(name) {
+                    captures.push(name.clone());
+                }
+            } else if self.shadows_builtin(name) || self.type_registry.builtin_function(name).is_none() {
+                needs_this = true;
+            }
+        }
+
+        let this = "this".to_string();
+        if needs_this && !captures.contains(&this) && self.symbol_table.lookup(&this).is_some() {
+            captures.push(this);
+        }
+        captures
+    }
+
+    /// 检查被 lambda 捕获的局部变量是否为 final 或事实上的 final
+    ///
+    /// 有初始值的变量和参数不能再被赋值；没有初始值的变量只能被赋值一次。
+    /// 复合赋值和自增自减总是视为重新赋值。
+    pub(super) fn check_captured_variables(&mut self, program: &Program) {
+        let mut errors = Vec::new();
+        for class in &program.classes {
+            for member in &class.members {
+                let (body, params) = match member {
+                    ClassMember::Method(method) => match &method.body {
+                        Some(body) => (body, method.params.as_slice()),
+                        None => continue,
+                    },
+                    ClassMember::Constructor(ctor) => (&ctor.body, ctor.params.as_slice()),
+                    ClassMember::Destructor(dtor) => (&dtor.body, [].as_slice()),
+                    ClassMember::InstanceInitializer(block) | ClassMember::StaticInitializer(block) => (block, [].as_slice()),
+                    ClassMember::Field(_) => continue,
+                };
+                errors.extend(self.check_body_captures(body, params));
+            }
+        }
+        self.errors.extend(errors);
+    }
+
+    fn check_body_captures(&self, body: &Block, params: &[crate::types::ParameterInfo]) -> Vec<cayError> {
+        let mut assignments: HashMap<&str, usize> = HashMap::new();
+        let mut finals: HashSet<&str> = params.iter().filter(|p| p.is_final).map(|p| p.name.as_str()).collect();
+        let mut uninitialized: HashSet<&str> = HashSet::new();
+        let mut lambdas: Vec<&LambdaExpr> = Vec::new();
+
+        walk_block(body, &mut |node| match node {
+            Node::Stmt(Stmt::VarDecl(var)) => {
+                if var.is_final {
+                    finals.insert(&var.name);
+                }
+                if var.initializer.is_none() {
+                    uninitialized.insert(&var.name);
+                }
+            }
+            Node::Expr(Expr::Assignment(assign)) => {
+                if let Expr::Identifier(name) = assign.target.as_ref() {
+                    let weight = if assign.op == AssignOp::Assign { 1 } else { 2 };
+                    *assignments.entry(name).or_default() += weight;
+                }
+            }
+            Node::Expr(Expr::Unary(unary)) => {
+                if matches!(unary.op, UnaryOp::PreInc | UnaryOp::PreDec | UnaryOp::PostInc | UnaryOp::PostDec)
+                    && let Expr::Identifier(name) = unary.operand.as_ref()
+                {
+                    *assignments.entry(name).or_default() += 2;
+                }
+            }
+            Node::Expr(Expr::Lambda(lambda)) => lambdas.push(lambda),
+            _ => {}
+        });
+
+        let mut errors = Vec::new();
+        for lambda in lambdas {
+            let Some(captures) = self.lambda_captures.get(&lambda.id) else { continue };
+            for name in captures {
+                if name == "this" || finals.contains(name.as_str()) {
+                    continue;
+                }
+                let count = assignments.get(name.as_str()).copied().unwrap_or(0);
+                let allowed = usize::from(uninitialized.contains(name.as_str()));
+                if count > allowed {
+                    errors.push(semantic_error(
+                        lambda.loc.line, lambda.loc.column,
+                        format!("Local variable '{}' referenced from a lambda expression must be final or effectively final", name)
+                    ));
+                }
+            }
+        }
+        errors
+    }
+}
@@ -0,0 +1,518 @@
+//! 明确赋值分析
+//!
+//! 按语句的控制流检查局部变量在读取之前一定已被赋值，以及没有初始值的 final 变量最多被赋值一次。
+//! 规则与 Java 的 definite assignment 一致但有所简化：
+//! - if、三元运算符、`&&`/`||`/`??` 的右侧等分支汇合时，取各分支一定已赋值变量的交集；
+//! - return、break、continue 和不返回的调用 `exit(...)` 之后的位置不可达，汇合时不参与求交集；
+//!   紧跟在 `exit(...)` 之后的语句报告为不可达；
+//! - 条件为字面量 `true` 的循环只能经由 break 退出；
+//! - 循环体按两遍检查：第一遍求出回边上可能已赋值的变量，第二遍据此检查 final 变量在循环中的重复赋值；
+//! - lambda 体在创建时检查，读取的外层变量必须已赋值，体内的赋值不影响外层。
+//!
+//! 参数和带初始值的变量总是已赋值；字段不在分析范围内。
+
+use std::collections::{HashMap, HashSet};
+use crate::ast::*;
+use crate::error::{cayError, semantic_error, SourceLocation};
+use super::analyzer::SemanticAnalyzer;
+use super::type_check::stmt_location;
+
+/// 某个程序位置上的赋值状态
+#[derive(Clone, Default)]
+struct Flow {
+    /// 一定已赋值的变量；`None` 表示该位置不可达，任何变量都视为已赋值
+    assigned: Option<HashSet<String>>,
+    /// 可能已赋值的变量，用于检查 final 变量的单次赋值
+    maybe: HashSet<String>,
+}
+
+impl Flow {
+    fn reachable() -> Self {
+        Self { assigned: Some(HashSet::new()), maybe: HashSet::new() }
+    }
+
+    /// 控制流汇合：一定已赋值取交集，可能已赋值取并集
+    fn merge(self, other: Flow) -> Flow {
+        let assigned = match (self.assigned, other.assigned) {
+            (None, assigned) | (assigned, None) => assigned,
+            (Some(a), Some(b)) => Some(a.intersection(&b).cloned().collect()),
+        };
+        let mut maybe = self.maybe;
+        maybe.extend(other.maybe);
+        Flow { assigned, maybe }
+    }
+
+    fn is_assigned(&self, name: &str) -> bool {
+        self.assigned.as_ref().is_none_or(|assigned| assigned.contains(name))
+    }
+
+    fn assign(&mut self, name: &str) {
+        if let Some(assigned) = &mut self.assigned {
+            assigned.insert(name.to_string());
+        }
+        self.maybe.insert(name.to_string());
+    }
+
+    fn forget(&mut self, name: &str) {
+        if let Some(assigned) = &mut self.assigned {
+            assigned.remove(name);
+        }
+        self.maybe.remove(name);
+    }
+}
+
+/// 循环、switch 或标签语句中 break/continue 跳出时的状态
+struct Jumps {
+    breaks: Vec<Flow>,
+    /// switch 和不是循环的标签语句不接受 continue，为 `None`
+    continues: Option<Vec<Flow>>,
+    /// 标记该语句的标签
+    label: Option<String>,
+    /// 是否为不带标签的 break/continue 的目标（标签语句本身不是）
+    unlabeled: bool,
+}
+
+struct Checker<'a> {
+    flow: Flow,
+    /// 可见的局部变量 -> 是否为没有初始值的 final 变量
+    locals: HashMap<String, bool>,
+    /// 每个作用域中声明的变量及被遮蔽的外层声明
+    scopes: Vec<Vec<(String, Option<bool>)>>,
+    jumps: Vec<Jumps>,
+    /// 标签语句标记的循环或 switch 尚未取走的标签
+    pending_label: Option<String>,
+    /// 最近访问到的带位置的节点，标识符本身没有位置
+    loc: SourceLocation,
+    /// 循环体的第一遍不报告错误
+    silent: bool,
+    /// 已报告过未初始化读取的变量，每个声明只报告一次
+    reported: HashSet<String>,
+    /// 作为语句的不返回调用
+    never_returns: &'a HashSet<NodeId>,
+    errors: &'a mut Vec<cayError>,
+}
+
+impl Checker<'_> {
+    fn error(&mut self, message: String) {
+        if !self.silent {
+            self.errors.push(semantic_error(self.loc.line, self.loc.column, message));
+        }
+    }
+
+    fn enter_scope(&mut self) {
+        self.scopes.push(Vec::new());
+    }
+
+    fn exit_scope(&mut self) {
+        for (name, shadowed) in self.scopes.pop().unwrap_or_default().into_iter().rev() {
+            match shadowed {
+                Some(blank_final) => {
+                    self.locals.insert(name, blank_final);
+                }
+                None => {
+                    self.locals.remove(&name);
+                    self.flow.forget(&name);
+                }
+            }
+        }
+    }
+
+    fn declare(&mut self, name: &str, initialized: bool, blank_final: bool) {
+        let shadowed = self.locals.insert(name.to_string(), blank_final);
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push((name.to_string(), shadowed));
+        }
+        self.flow.forget(name);
+        self.reported.remove(name);
+        if initialized {
+            self.flow.assign(name);
+        }
+    }
+
+    fn read(&mut self, name: &str) {
+        if self.locals.contains_key(name) && !self.flow.is_assigned(name) && !self.reported.contains(name) {
+            if !self.silent {
+                self.reported.insert(name.to_string());
+            }
+            self.error(format!("Variable '{}' may not have been initialized", name));
+        }
+    }
+
+    /// 简单赋值 `name = ...`；没有初始值的 final 变量只能在一定未赋值时赋值
+    fn write(&mut self, name: &str) {
+        let Some(&blank_final) = self.locals.get(name) else { return };
+        if blank_final && self.flow.maybe.contains(name) {
+            self.error(format!("Cannot assign a value to final variable '{}': it may already have been assigned", name));
+        }
+        self.flow.assign(name);
+    }
+
+    fn block(&mut self, statements: &[Stmt]) {
+        self.enter_scope();
+        self.statements(statements);
+        self.exit_scope();
+    }
+
+    /// 依次检查语句列表，紧跟在不返回的调用之后的语句报告为不可达
+    fn statements(&mut self, statements: &[Stmt]) {
+        for pair in statements.windows(2) {
+            if self.is_never_return(&pair[0]) {
+                if let Some(loc) = stmt_location(&pair[1]) {
+                    self.loc = loc.clone();
+                }
+                self.error("Unreachable statement: the preceding call never returns".to_string());
+            }
+        }
+        for stmt in statements {
+            self.stmt(stmt);
+        }
+    }
+
+    fn is_never_return(&self, stmt: &Stmt) -> bool {
+        matches!(stmt, Stmt::Expr(Expr::Call(call)) if self.never_returns.contains(&call.id))
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Expr(expr) => {
+                self.expr(expr);
+                if self.is_never_return(stmt) {
+                    self.flow = Flow::default();
+                }
+            }
+            Stmt::VarDecl(var) => {
+                self.loc = var.loc.clone();
+                if let Some(init) = &var.initializer {
+                    self.expr(init);
+                }
+                self.declare(&var.name, var.initializer.is_some(), var.is_final && var.initializer.is_none());
+            }
+            Stmt::Return(ret) => {
+                if let Some(expr) = &ret.value {
+                    self.expr(expr);
+                }
+                self.flow = Flow::default();
+            }
+            Stmt::If(if_stmt) => {
+                self.loc = if_stmt.loc.clone();
+                self.expr(&if_stmt.condition);
+                let before = self.flow.clone();
+                self.block(std::slice::from_ref(if_stmt.then_branch.as_ref()));
+                let then_flow = std::mem::replace(&mut self.flow, before);
+                if let Some(else_branch) = &if_stmt.else_branch {
+                    self.block(std::slice::from_ref(else_branch.as_ref()));
+                }
+                self.flow = then_flow.merge(std::mem::take(&mut self.flow));
+            }
+            Stmt::While(while_stmt) => {
+                self.loc = while_stmt.loc.clone();
+                let label = self.pending_label.take();
+                self.in_loop(|checker| {
+                    checker.expr(&while_stmt.condition);
+                    let exit = checker.exit_after(Some(&while_stmt.condition));
+                    let (end, jumps) = checker.loop_body(&while_stmt.body, &label);
+                    (end, exit, jumps)
+                });
+            }
+            Stmt::DoWhile(do_while) => {
+                self.loc = do_while.loc.clone();
+                let label = self.pending_label.take();
+                self.in_loop(|checker| {
+                    let (end, jumps) = checker.loop_body(&do_while.body, &label);
+                    checker.flow = end;
+                    checker.expr(&do_while.condition);
+                    let exit = checker.exit_after(Some(&do_while.condition));
+                    (checker.flow.clone(), exit, jumps)
+                });
+            }
+            Stmt::For(for_stmt) => {
+                self.loc = for_stmt.loc.clone();
+                let label = self.pending_label.take();
+                self.enter_scope();
+                if let Some(init) = &for_stmt.init {
+                    self.stmt(init);
+                }
+                self.in_loop(|checker| {
+                    if let Some(condition) = &for_stmt.condition {
+                        checker.expr(condition);
+                    }
+                    let exit = checker.exit_after(for_stmt.condition.as_ref());
+                    let (end, jumps) = checker.loop_body(&for_stmt.body, &label);
+                    checker.flow = end;
+                    if let Some(update) = &for_stmt.update {
+                        checker.expr(update);
+                    }
+                    (checker.flow.clone(), exit, jumps)
+                });
+                self.exit_scope();
+            }
+            Stmt::ForEach(for_each) => {
+                self.loc = for_each.loc.clone();
+                let label = self.pending_label.take();
+                self.expr(&for_each.iterable);
+                self.enter_scope();
+                self.declare(&for_each.var_name, true, false);
+                // 循环体可能一次也不执行
+                self.in_loop(|checker| {
+                    let exit = checker.flow.clone();
+                    let (end, jumps) = checker.loop_body(&for_each.body, &label);
+                    (end, exit, jumps)
+                });
+                self.exit_scope();
+            }
+            Stmt::Switch(switch) => {
+                self.loc = switch.loc.clone();
+                let label = self.pending_label.take();
+                self.expr(&switch.expr);
+                let entry = self.flow.clone();
+                // 没有匹配的分支且没有 default 时直接跳过整个 switch
+                let mut exit = if switch.default.is_some() { Flow::default() } else { entry.clone() };
+                self.jumps.push(Jumps { breaks: Vec::new(), continues: None, label, unlabeled: true });
+                self.enter_scope();
+                // 每个分支可以从 switch 跳入，也可以从上一个分支穿透进入
+                let mut previous = Flow::default();
+                for body in switch.cases.iter().map(|c| &c.body).chain(switch.default.as_ref()) {
+                    self.flow = entry.clone().merge(previous);
+                    self.statements(body);
+                    previous = std::mem::take(&mut self.flow);
+                }
+                self.exit_scope();
+                let jumps = self.jumps.pop().map(|j| j.breaks).unwrap_or_default();
+                exit = exit.merge(previous);
+                self.flow = jumps.into_iter().fold(exit, Flow::merge);
+            }
+            Stmt::Block(block) => {
+                self.loc = block.loc.clone();
+                self.block(&block.statements);
+            }
+            Stmt::Labeled(labeled) => {
+                self.loc = labeled.loc.clone();
+                if matches!(*labeled.body, Stmt::While(_) | Stmt::DoWhile(_) | Stmt::For(_) | Stmt::ForEach(_) | Stmt::Switch(_)) {
+                    self.pending_label = Some(labeled.label.clone());
+                    self.stmt(&labeled.body);
+                } else {
+                    // `break label;` 跳到标签语句之后
+                    self.jumps.push(Jumps { breaks: Vec::new(), continues: None, label: Some(labeled.label.clone()), unlabeled: false });
+                    self.stmt(&labeled.body);
+                    let jumps = self.jumps.pop().map(|j| j.breaks).unwrap_or_default();
+                    let end = std::mem::take(&mut self.flow);
+                    self.flow = jumps.into_iter().fold(end, Flow::merge);
+                }
+            }
+            Stmt::Break(jump) => {
+                let flow = std::mem::take(&mut self.flow);
+                if let Some(jumps) = self.jump_target(jump) {
+                    jumps.breaks.push(flow);
+                }
+            }
+            Stmt::Continue(jump) => {
+                let flow = std::mem::take(&mut self.flow);
+                let continues = self.jumps.iter_mut().rev()
+                    .filter(|j| j.continues.is_some())
+                    .find(|j| match &jump.label {
+                        Some(label) => j.label.as_ref() == Some(label),
+                        None => j.unlabeled,
+                    })
+                    .and_then(|j| j.continues.as_mut());
+                if let Some(continues) = continues {
+                    continues.push(flow);
+                }
+            }
+        }
+    }
+
+    /// 条件为假时离开循环的状态；没有条件或条件为字面量 true 时只能经由 break 离开
+    fn exit_after(&self, condition: Option<&Expr>) -> Flow {
+        match condition {
+            None | Some(Expr::Literal(LiteralValue::Bool(true))) => Flow::default(),
+            Some(_) => self.flow.clone(),
+        }
+    }
+
+    /// break 跳出的语句：带标签时是同名的标签语句，否则是最内层的循环或 switch
+    fn jump_target(&mut self, jump: &JumpStmt) -> Option<&mut Jumps> {
+        self.jumps.iter_mut().rev().find(|j| match &jump.label {
+            Some(label) => j.label.as_ref() == Some(label),
+            None => j.unlabeled,
+        })
+    }
+
+    /// 检查循环体，返回回到循环开头时的状态（包括 continue）和跳出的 break 状态
+    fn loop_body(&mut self, body: &Stmt, label: &Option<String>) -> (Flow, Vec<Flow>) {
+        self.jumps.push(Jumps { breaks: Vec::new(), continues: Some(Vec::new()), label: label.clone(), unlabeled: true });
+        self.block(std::slice::from_ref(body));
+        let jumps = self.jumps.pop().expect("loop jumps");
+        let end = jumps.continues.unwrap_or_default().into_iter()
+            .fold(std::mem::take(&mut self.flow), Flow::merge);
+        (end, jumps.breaks)
+    }
+
+    /// 检查一个循环：`pass` 从循环开头的状态出发，返回 (回边状态, 条件为假时的退出状态, break 状态)
+    ///
+    /// 第一遍不报错，只求回边上可能已赋值的变量；并入循环开头的状态后再检查一遍。
+    /// 回边上一定已赋值的变量包含循环开头的，因此开头一定已赋值的集合不变。
+    fn in_loop<F>(&mut self, mut pass: F)
+    where
+        F: FnMut(&mut Self) -> (Flow, Flow, Vec<Flow>),
+    {
+        let entry = self.flow.clone();
+        let silent = std::mem::replace(&mut self.silent, true);
+        let (back, _, _) = pass(self);
+        self.silent = silent;
+
+        self.flow = entry;
+        self.flow.maybe.extend(back.maybe);
+        let (_, exit, breaks) = pass(self);
+        self.flow = breaks.into_iter().fold(exit, Flow::merge);
+    }
+
+    /// 检查只在部分路径上求值的子表达式，之后的一定已赋值集合保持不变
+    fn conditional(&mut self, expr: &Expr) {
+        let before = self.flow.assigned.clone();
+        self.expr(expr);
+        self.flow.assigned = before;
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        if let Some(loc) = expr.location() {
+            self.loc = loc.clone();
+        }
+        match expr {
+            Expr::Literal(_) => {}
+            Expr::Identifier(name) => self.read(name),
+            Expr::Binary(bin) => {
+                self.expr(&bin.left);
+                if matches!(bin.op, BinaryOp::And | BinaryOp::Or) {
+                    self.conditional(&bin.right);
+                } else {
+                    self.expr(&bin.right);
+                }
+            }
+            Expr::Unary(unary) => {
+                self.expr(&unary.operand);
+                if matches!(unary.op, UnaryOp::PreInc | UnaryOp::PreDec | UnaryOp::PostInc | UnaryOp::PostDec)
+                    && let Expr::Identifier(name) = unary.operand.as_ref()
+                {
+                    self.flow.assign(name);
+                }
+            }
+            Expr::Call(call) => {
+                // 不带对象的调用 `f(x)` 中的 f 是局部变量时读取它（函数类型的变量）
+                self.expr(&call.callee);
+                for arg in &call.args {
+                    self.expr(arg);
+                }
+            }
+            Expr::MemberAccess(member) => self.expr(&member.object),
+            Expr::New(new_expr) => {
+                for arg in &new_expr.args {
+                    self.expr(arg);
+                }
+            }
+            Expr::Assignment(assign) => match assign.target.as_ref() {
+                Expr::Identifier(name) if assign.op == AssignOp::Assign => {
+                    self.expr(&assign.value);
+                    self.write(name);
+                }
+                // 复合赋值先读取再赋值，对 final 变量的复合赋值由类型检查报告
+                Expr::Identifier(name) => {
+                    self.read(name);
+                    self.expr(&assign.value);
+                    self.flow.assign(name);
+                }
+                target => {
+                    self.expr(target);
+                    self.expr(&assign.value);
+                }
+            },
+            Expr::Cast(cast) => self.expr(&cast.expr),
+            Expr::ArrayCreation(creation) => {
+                for size in &creation.sizes {
+                    self.expr(size);
+                }
+            }
+            Expr::ArrayAccess(access) => {
+                self.expr(&access.array);
+                self.expr(&access.index);
+            }
+            Expr::ArrayInit(init) => {
+                for element in &init.elements {
+                    self.expr(element);
+                }
+            }
+            Expr::MethodRef(method_ref) => {
+                if let Some(object) = &method_ref.object {
+                    self.expr(object);
+                }
+                // `obj::method` 中的 obj 被解析为类名，是局部变量时同样读取它
+                if let Some(name) = &method_ref.class_name {
+                    self.read(name);
+                }
+            }
+            Expr::Lambda(lambda) => {
+                let flow = self.flow.clone();
+                let jumps = std::mem::take(&mut self.jumps);
+                self.enter_scope();
+                for param in &lambda.params {
+                    self.declare(&param.name, true, false);
+                }
+                match &lambda.body {
+                    LambdaBody::Expr(body) => self.expr(body),
+                    LambdaBody::Block(body) => self.block(&body.statements),
+                }
+                self.exit_scope();
+                self.jumps = jumps;
+                self.flow = flow;
+            }
+            Expr::Ternary(ternary) => {
+                self.expr(&ternary.condition);
+                let before = self.flow.clone();
+                self.expr(&ternary.true_branch);
+                let true_flow = std::mem::replace(&mut self.flow, before);
+                self.expr(&ternary.false_branch);
+                self.flow = true_flow.merge(std::mem::take(&mut self.flow));
+            }
+            Expr::InstanceOf(instance_of) => self.expr(&instance_of.expr),
+            Expr::NullCoalesce(coalesce) => {
+                self.expr(&coalesce.value);
+                self.conditional(&coalesce.default);
+            }
+            Expr::InlineIr(inline) => inline.args.iter().for_each(|arg| self.expr(arg)),
+        }
+    }
+}
+
+impl SemanticAnalyzer {
+    /// 检查所有方法体、构造函数、析构函数和初始化块中局部变量的明确赋值
+    pub(super) fn check_definite_assignment(&mut self, program: &Program) {
+        let mut errors = Vec::new();
+        for class in &program.classes {
+            for member in &class.members {
+                let body = match member {
+                    ClassMember::Method(method) => match &method.body {
+                        Some(body) => body,
+                        None => continue,
+                    },
+                    ClassMember::Constructor(ctor) => &ctor.body,
+                    ClassMember::Destructor(dtor) => &dtor.body,
+                    ClassMember::InstanceInitializer(block) | ClassMember::StaticInitializer(block) => block,
+                    ClassMember::Field(_) => continue,
+                };
+                let mut checker = Checker {
+                    flow: Flow::reachable(),
+                    locals: HashMap::new(),
+                    scopes: Vec::new(),
+                    jumps: Vec::new(),
+                    pending_label: None,
+                    loc: body.loc.clone(),
+                    silent: false,
+                    reported: HashSet::new(),
+                    never_returns: &self.never_returns,
+                    errors: &mut errors,
+                };
+                checker.block(&body.statements);
+            }
+        }
+        self.errors.extend(errors);
+    }
+}
@@ -1,14 +1,48 @@
 //! 表达式类型推断
 
 use crate::ast::*;
-use crate::types::Type;
+use crate::types::{Type, FunctionType, ClassInfo, MethodInfo, ParameterInfo};
 use crate::error::{cayResult, semantic_error};
 use super::analyzer::SemanticAnalyzer;
 use super::symbol_table::SemanticSymbolInfo;
+use super::format::{parse_format, check_format_args};
+
+/// 在静态上下文中引用实例成员的错误信息后缀
+const STATIC_CONTEXT: &str = "cannot be referenced from a static context";
 
 impl SemanticAnalyzer {
-    /// 推断表达式类型
+    /// 推断表达式类型，并记录到 `expr_types` 旁路表供代码生成使用
     pub fn infer_expr_type(&mut self, expr: &Expr) -> cayResult<Type> {
+        let ty = self.infer_expr_type_uncached(expr)?;
+        self.record_expr_type(expr, &ty);
+        Ok(ty)
+    }
+
+    /// 记录表达式的静态类型（字面量和标识符没有 NodeId，代码生成直接从字面量和变量得到类型）
+    pub(super) fn record_expr_type(&mut self, expr: &Expr, ty: &Type) {
+        if let Some(id) = expr.node_id() {
+            self.expr_types.insert(id, ty.clone());
+        }
+    }
+
+    fn infer_expr_type_uncached(&mut self, expr: &Expr) -> cayResult<Type> {
+        let Some(loc) = expr.location() else {
+            return self.infer_expr_type_at(expr);
+        };
+        crate::ice::set_location(loc);
+        let outer = self.enclosing_expr_loc.replace(loc.clone());
+        let result = self.infer_expr_type_at(expr);
+        self.enclosing_expr_loc = outer;
+        result
+    }
+
+    /// 没有位置的表达式报错时使用的位置：最内层带位置的外层表达式，
+    /// 没有时为 (0, 0)，由语句级别的错误恢复补上语句的位置
+    fn enclosing_line_column(&self) -> (usize, usize) {
+        self.enclosing_expr_loc.as_ref().map_or((0, 0), |loc| (loc.line, loc.column))
+    }
+
+    fn infer_expr_type_at(&mut self, expr: &Expr) -> cayResult<Type> {
         match expr {
             Expr::Literal(lit) => match lit {
                 LiteralValue::Int32(_) => Ok(Type::Int32),
@@ -20,31 +54,32 @@ impl SemanticAnalyzer {
                 LiteralValue::Char(_) => Ok(Type::Char),
                 LiteralValue::Null => Ok(Type::Object("Object".to_string())),
             }
+            Expr::Identifier(name) if name == "super" => self.infer_super_type(),
             Expr::Identifier(name) => {
                 // 检查是否在静态上下文中访问 this
                 if self.current_method_is_static && name == "this" {
+                    let (line, column) = self.enclosing_line_column();
                     return Err(semantic_error(
-                        0, 0,
-                        format!("non-static variable this cannot be referenced from a static context")
+                        line, column,
+                        format!("non-static variable this {}", STATIC_CONTEXT)
                     ));
                 }
                 
                 // 首先检查是否是当前类的字段（包括静态和非静态）
                 if let Some(current_class_name) = &self.current_class {
-                    if let Some(class_info) = self.type_registry.get_class(current_class_name) {
-                        if let Some(field_info) = class_info.fields.get(name) {
-                            if field_info.is_static {
-                                return Ok(field_info.field_type.clone());
-                            } else if self.current_method_is_static {
-                                // 静态方法中不能访问非静态字段
-                                return Err(semantic_error(
-                                    0, 0,
-                                    format!("non-static variable {} cannot be referenced from a static context", name)
-                                ));
-                            }
-                            // 非静态方法中返回字段类型
+                    if let Some((_, field_info)) = self.type_registry.find_field(current_class_name, name) {
+                        if field_info.is_static {
                             return Ok(field_info.field_type.clone());
+                        } else if self.current_method_is_static {
+                            // 静态方法中不能访问非静态字段
+                            let (line, column) = self.enclosing_line_column();
+                            return Err(semantic_error(
+                                line, column,
+                                format!("non-static variable {} {}", name, STATIC_CONTEXT)
+                            ));
                         }
+                        // 非静态方法中返回字段类型
+                        return Ok(field_info.field_type.clone());
                     }
                 }
                 
@@ -54,26 +89,115 @@ impl SemanticAnalyzer {
                     // 标识符是类名，返回类类型（用于静态成员访问）
                     Ok(Type::Object(name.clone()))
                 } else {
-                    Err(semantic_error(0, 0, format!("Undefined variable: {}", name)))
+                    let (line, column) = self.enclosing_line_column();
+                    Err(semantic_error(line, column, format!("Undefined variable: {}", name)))
                 }
             }
             Expr::Binary(bin) => self.infer_binary_type(bin),
             Expr::Unary(unary) => self.infer_unary_type(unary),
-            Expr::Call(call) => self.infer_call_type(call),
-            Expr::MemberAccess(member) => self.infer_member_access_type(member),
+            Expr::Call(call) => {
+                let ty = self.infer_call_type(call)?;
+                self.check_null_safe_result(expr, ty)
+            }
+            Expr::MemberAccess(member) => {
+                let ty = self.infer_member_access_type(member)?;
+                self.check_null_safe_result(expr, ty)
+            }
             Expr::New(new_expr) => self.infer_new_type(new_expr),
             Expr::Assignment(assign) => self.infer_assignment_type(assign),
             Expr::Cast(cast) => self.infer_cast_type(cast),
             Expr::ArrayCreation(arr) => self.infer_array_creation_type(arr),
             Expr::ArrayInit(init) => self.infer_array_init_type(init),
             Expr::ArrayAccess(arr) => self.infer_array_access_type(arr),
-            Expr::MethodRef(method_ref) => self.infer_method_ref_type(method_ref),
-            Expr::Lambda(lambda) => self.infer_lambda_type(lambda),
+            Expr::MethodRef(method_ref) => self.infer_method_ref_type(method_ref, None),
+            Expr::Lambda(lambda) => self.infer_lambda_type(lambda, None),
             Expr::Ternary(ternary) => self.infer_ternary_type(ternary),
             Expr::InstanceOf(instanceof) => self.infer_instanceof_type(instanceof),
+            Expr::NullCoalesce(coalesce) => self.infer_null_coalesce_type(coalesce),
+            Expr::InlineIr(inline) => self.infer_inline_ir_type(inline),
         }
     }
 
+
+    /// 在已知目标类型的位置推断表达式类型
+    ///
+    /// 目标类型是函数类型时，lambda 的未标注参数和返回值、方法引用的重载都按目标类型确定；
+    /// 其他表达式与 `infer_expr_type` 相同。
+    pub(super) fn infer_expr_type_expecting(&mut self, expr: &Expr, expected: &Type) -> cayResult<Type> {
+        let ty = match (expr, expected) {
+            (Expr::Lambda(lambda), Type::Function(target)) => self.infer_lambda_type(lambda, Some(target))?,
+            (Expr::MethodRef(method_ref), Type::Function(target)) => self.infer_method_ref_type(method_ref, Some(target))?,
+            _ => return self.infer_expr_type(expr),
+        };
+        self.record_expr_type(expr, &ty);
+        Ok(ty)
+    }
+
+    /// 推断赋值上下文（变量初始化、赋值、return）中值的类型
+    ///
+    /// 在 `infer_expr_type_expecting` 的基础上允许 Java 的常量收窄：范围内的 int 常量可以直接赋给
+    /// byte/short，数组初始化器的元素逐个按元素类型检查（`byte[] b = {1, 2, 3};`）。
+    /// 方法参数不属于赋值上下文，传给 byte/short 参数的常量仍需显式转换。
+    pub(super) fn infer_assigned_type(&mut self, expr: &Expr, target: &Type) -> cayResult<Type> {
+        match (expr, target) {
+            (_, Type::Int8 | Type::Int16) if Self::int_constant(expr).is_some_and(|v| Self::constant_fits(v, target)) => {
+                self.infer_expr_type(expr)?;
+                Ok(target.clone())
+            }
+            // 有目标类型时空的初始化器 `{}` 不存在歧义，得到长度为 0 的数组
+            (Expr::ArrayInit(init), Type::Array(elem)) => {
+                for element in &init.elements {
+                    let ty = self.infer_assigned_type(element, elem)?;
+                    if !self.types_compatible(&ty, elem) {
+                        return Err(semantic_error(
+                            init.loc.line,
+                            init.loc.column,
+                            format!("Cannot assign {} to {} in array initializer", ty, elem)
+                        ));
+                    }
+                }
+                Ok(target.clone())
+            }
+            _ => self.infer_expr_type_expecting(expr, target),
+        }
+    }
+
+    /// int 字面量（可带负号）的值
+    fn int_constant(expr: &Expr) -> Option<i64> {
+        match expr {
+            Expr::Literal(LiteralValue::Int32(v)) => Some(*v as i64),
+            Expr::Unary(unary) if unary.op == UnaryOp::Neg => Self::int_constant(&unary.operand).map(|v| -v),
+            _ => None,
+        }
+    }
+
+    /// Arrays.toString(arr)：接受任意元素类型的数组，选中的数组类型记录为调用的重载参数
+    fn infer_arrays_to_string_type(&mut self, call: &CallExpr, arg_types: &[Type]) -> cayResult<Type> {
+        match arg_types {
+            [array @ Type::Array(_)] => {
+                self.call_overloads.insert(call.id, vec![ParameterInfo::new("arr".to_string(), array.clone())]);
+                Ok(Type::String)
+            }
+            [other] => Err(semantic_error(call.loc.line, call.loc.column,
+                format!("Arrays.toString() expects an array argument, got {}", other))),
+            _ => Err(semantic_error(call.loc.line, call.loc.column,
+                format!("Arrays.toString() expects 1 argument(s), got {}", arg_types.len()))),
+        }
+    }
+
+    /// 常量是否在 byte/short 的取值范围内
+    fn constant_fits(value: i64, target: &Type) -> bool {
+        match target {
+            Type::Int8 => i8::try_from(value).is_ok(),
+            Type::Int16 => i16::try_from(value).is_ok(),
+            _ => false,
+        }
+    }
+    /// 能在字符串拼接中自动转换为字符串的类型（数组按元素格式化为 `[1, 2, 3]`）
+    fn is_string_convertible(ty: &Type) -> bool {
+        ty.is_primitive() || matches!(ty, Type::Object(_) | Type::Array(_))
+    }
+
     /// 推断二元表达式类型
     fn infer_binary_type(&mut self, bin: &BinaryExpr) -> cayResult<Type> {
         let left_type = self.infer_expr_type(&bin.left)?;
@@ -81,16 +205,14 @@ impl SemanticAnalyzer {
         
         match bin.op {
             BinaryOp::Add => {
-                // 字符串连接：两个操作数都必须是字符串
+                // 字符串连接：两个操作数都是字符串
                 if left_type == Type::String && right_type == Type::String {
                     Ok(Type::String)
                 }
-                // 字符串 + char：允许，结果为字符串
-                else if left_type == Type::String && right_type == Type::Char {
-                    Ok(Type::String)
-                }
-                // char + 字符串：允许，结果为字符串
-                else if left_type == Type::Char && right_type == Type::String {
+                // 字符串与基本类型或对象拼接：另一侧自动转换为字符串（对象调用 toString()）
+                else if (left_type == Type::String && Self::is_string_convertible(&right_type))
+                    || (right_type == Type::String && Self::is_string_convertible(&left_type)) {
+                    self.string_conversions.insert(bin.id, (left_type, right_type));
                     Ok(Type::String)
                 }
                 // 数值加法：两个操作数都必须是基本数值类型
@@ -101,7 +223,7 @@ impl SemanticAnalyzer {
                     Err(semantic_error(
                         bin.loc.line,
                         bin.loc.column,
-                        format!("Cannot add {} and {}: addition requires both operands to be numeric or both to be strings", left_type, right_type)
+                        format!("Cannot add {} and {}: addition requires both operands to be numeric, or one of them to be a string", left_type, right_type)
                     ))
                 }
             }
@@ -135,6 +257,10 @@ impl SemanticAnalyzer {
                 }
             }
             BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+                self.check_comparison_operands(bin, &left_type, &right_type)?;
+                if matches!(bin.op, BinaryOp::Eq | BinaryOp::Ne) && left_type == Type::String && right_type == Type::String {
+                    self.string_comparisons.insert(bin.id);
+                }
                 Ok(Type::Bool)
             }
             BinaryOp::And | BinaryOp::Or => {
@@ -177,8 +303,53 @@ impl SemanticAnalyzer {
         }
     }
 
+    /// 检查比较运算的操作数：bool 不能与数值比较，也不能比较大小
+    ///
+    /// `a < b < c` 会被解析为 `(a < b) < c`，左侧是 bool，在这里给出专门的提示。
+    fn check_comparison_operands(&self, bin: &BinaryExpr, left_type: &Type, right_type: &Type) -> cayResult<()> {
+        let is_ordering = !matches!(bin.op, BinaryOp::Eq | BinaryOp::Ne);
+        let left_bool = *left_type == Type::Bool;
+        let right_bool = *right_type == Type::Bool;
+        let mismatched = (left_bool && Self::is_numeric_type_helper(right_type))
+            || (right_bool && Self::is_numeric_type_helper(left_type));
+
+        if !mismatched && !(is_ordering && (left_bool || right_bool)) {
+            return Ok(());
+        }
+
+        if let Expr::Binary(inner) = bin.left.as_ref()
+            && inner.op.is_comparison()
+        {
+            return Err(semantic_error(
+                bin.loc.line,
+                bin.loc.column,
+                format!(
+                    "Chained comparison 'a {} b {} c' is not supported: it compares the bool result of 'a {} b' with c, use 'a {} b && b {} c' instead",
+                    inner.op.symbol(), bin.op.symbol(), inner.op.symbol(), inner.op.symbol(), bin.op.symbol()
+                )
+            ));
+        }
+
+        Err(semantic_error(
+            bin.loc.line,
+            bin.loc.column,
+            format!("Operator '{}' cannot be applied to {} and {}", bin.op.symbol(), left_type, right_type)
+        ))
+    }
+
     /// 推断一元表达式类型
     fn infer_unary_type(&mut self, unary: &UnaryExpr) -> cayResult<Type> {
+        // ++/-- 会修改操作数，不能用于 final 变量和参数
+        if matches!(unary.op, UnaryOp::PreInc | UnaryOp::PreDec | UnaryOp::PostInc | UnaryOp::PostDec)
+            && let Expr::Identifier(name) = unary.operand.as_ref()
+            && self.symbol_table.lookup(name).is_some_and(|info| info.is_final)
+        {
+            return Err(semantic_error(
+                unary.loc.line,
+                unary.loc.column,
+                format!("Cannot assign a value to final variable '{}'", name)
+            ));
+        }
         let operand_type = self.infer_expr_type(&unary.operand)?;
         match unary.op {
             UnaryOp::Neg => Ok(operand_type),
@@ -198,39 +369,214 @@ impl SemanticAnalyzer {
         }
     }
 
+    /// 记录表达式中两侧都是 String 的 `==`/`!=`
+    ///
+    /// print/println 的参数不做完整的类型检查（允许拼接任意类型），
+    /// 但其中的字符串比较仍需记录，以便代码生成按内容比较。
+    fn record_string_comparisons(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Binary(bin) => {
+                if matches!(bin.op, BinaryOp::Eq | BinaryOp::Ne)
+                    && self.infer_expr_type(&bin.left).is_ok_and(|t| t == Type::String)
+                    && self.infer_expr_type(&bin.right).is_ok_and(|t| t == Type::String)
+                {
+                    self.string_comparisons.insert(bin.id);
+                }
+                self.record_string_comparisons(&bin.left);
+                self.record_string_comparisons(&bin.right);
+            }
+            Expr::Unary(unary) => self.record_string_comparisons(&unary.operand),
+            Expr::Ternary(ternary) => {
+                self.record_string_comparisons(&ternary.condition);
+                self.record_string_comparisons(&ternary.true_branch);
+                self.record_string_comparisons(&ternary.false_branch);
+            }
+            Expr::Call(call) => {
+                for arg in &call.args {
+                    self.record_string_comparisons(arg);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// 推断内置函数调用的类型
+    ///
+    /// 参数个数不匹配任何签名、或参数类型不匹配任何重载时报错。
+    /// print/println 的参数类型推断失败（例如字符串与数值拼接）时只检查个数、按 IR 类型打印，
+    /// 但在静态上下文中引用实例成员和 format 的格式错误不能放过。
+    fn infer_builtin_call_type(&mut self, name: &str, call: &CallExpr, signatures: &[MethodInfo]) -> cayResult<Type> {
+        let (line, column) = (call.loc.line, call.loc.column);
+        let mut arities: Vec<usize> = signatures.iter().map(|s| s.params.len()).collect();
+        arities.sort_unstable();
+        arities.dedup();
+        if !arities.contains(&call.args.len()) {
+            let message = if arities == [0] {
+                format!("{}() takes no arguments, got {}", name, call.args.len())
+            } else if name == "print" || name == "println" {
+                // 多于一个参数时是格式化输出，参数个数没有上限
+                format!("{}() expects at least 1 argument, got {}", name, call.args.len())
+            } else {
+                let expected = arities.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(" or ");
+                format!("{}() expects {} argument(s), got {}", name, expected, call.args.len())
+            };
+            return Err(semantic_error(line, column, message));
+        }
+
+        let is_print = name == "print" || name == "println";
+        let mut arg_types = Vec::new();
+        for arg in &call.args {
+            if is_print {
+                self.record_string_comparisons(arg);
+                match self.infer_expr_type(arg) {
+                    Ok(ty) => arg_types.push(ty),
+                    Err(e) if e.to_string().contains(STATIC_CONTEXT) => {
+                        return Err(e.with_fallback_location(line, column));
+                    }
+                    // format(...) 的格式错误也不能放过，否则代码生成找不到解析结果
+                    Err(e) if matches!(arg, Expr::Call(inner) if matches!(inner.callee.as_ref(), Expr::Identifier(n) if n == "format")) => {
+                        return Err(e.with_fallback_location(line, column));
+                    }
+                    Err(_) => return Ok(Type::Void),
+                }
+            } else {
+                arg_types.push(self.infer_expr_type(arg)?);
+            }
+        }
+
+        let candidates: Vec<MethodInfo> = signatures.iter()
+            .filter(|s| s.params.len() == call.args.len())
+            .cloned()
+            .collect();
+        if is_print && let Some(ty) = arg_types.first() {
+            self.print_arg_types.insert(call.id, ty.clone());
+            // 任意元素类型的数组都可以打印
+            if matches!(ty, Type::Array(_)) {
+                return Ok(Type::Void);
+            }
+        }
+        let Some(signature) = ClassInfo::resolve_overload(&candidates, &arg_types) else {
+            let got = arg_types.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(", ");
+            let message = match candidates.as_slice() {
+                [only] if only.params.len() == 1 => {
+                    format!("{}() expects a {} argument, got {}", name, only.params[0].param_type, got)
+                }
+                _ => format!("{}() cannot be applied to argument type(s) ({})", name, got),
+            };
+            return Err(semantic_error(line, column, message));
+        };
+
+        Ok(signature.return_type.clone())
+    }
+
+    /// 推断 debugPrint 调用的类型
+    ///
+    /// 参数可以是任意非 void 类型；记录参数的源代码文本和静态类型，代码生成据此输出
+    /// `[line N] 表达式: 类型 = 值`。文本在常量折叠之前取得，因此保留原始写法。
+    fn infer_debug_print_type(&mut self, call: &CallExpr) -> cayResult<Type> {
+        let (line, column) = (call.loc.line, call.loc.column);
+        let [arg] = call.args.as_slice() else {
+            return Err(semantic_error(line, column,
+                format!("debugPrint() expects 1 argument(s), got {}", call.args.len())));
+        };
+        let ty = self.infer_expr_type(arg)?;
+        if matches!(ty, Type::Void | Type::Never) {
+            return Err(semantic_error(line, column, "debugPrint() cannot print a void expression".to_string()));
+        }
+        self.debug_prints.insert(call.id, (arg.to_string(), ty));
+        Ok(Type::Void)
+    }
+
+    /// 推断格式化调用 `format(fmt, args...)`、`print(fmt, args...)`、`println(fmt, args...)` 的类型
+    ///
+    /// 格式字符串必须是字符串字面量，解析后逐个对照说明符检查实参类型；
+    /// 解析结果和实参的静态类型记录下来供代码生成和解释器使用。format 返回 String，print/println 返回 void。
+    fn infer_format_call_type(&mut self, name: &str, call: &CallExpr) -> cayResult<Type> {
+        let (line, column) = (call.loc.line, call.loc.column);
+        let Some((fmt_arg, args)) = call.args.split_first() else {
+            return Err(semantic_error(line, column, format!("{}() expects a format string argument", name)));
+        };
+        let Expr::Literal(LiteralValue::String(fmt)) = fmt_arg else {
+            let ty = self.infer_expr_type(fmt_arg)?;
+            return Err(semantic_error(line, column, match ty {
+                Type::String => format!("The format string of {}() must be a string literal", name),
+                _ => format!("{}() expects a String format string, got {}", name, ty.source_name()),
+            }));
+        };
+        let pieces = parse_format(fmt).map_err(|message| semantic_error(line, column, message))?;
+        let mut arg_types = Vec::with_capacity(args.len());
+        for arg in args {
+            self.record_string_comparisons(arg);
+            arg_types.push(self.infer_expr_type(arg)?);
+        }
+        check_format_args(&pieces, &arg_types).map_err(|message| semantic_error(line, column, message))?;
+        self.format_calls.insert(call.id, (pieces, arg_types));
+        Ok(if name == "format" { Type::String } else { Type::Void })
+    }
+
+    /// 不带对象的调用 `name(...)` 是否指向用户方法而不是同名内置函数
+    ///
+    /// 当前类或其父类声明了该名称的方法（任意重载）时，用户方法优先。
+    pub(super) fn shadows_builtin(&self, name: &str) -> bool {
+        self.current_class.as_ref()
+            .is_some_and(|class_name| self.type_registry.get_method(class_name, name).is_some())
+    }
+
     /// 推断函数调用类型
     fn infer_call_type(&mut self, call: &CallExpr) -> cayResult<Type> {
+        // 通过函数类型的变量调用闭包: f(x)
+        if let Expr::Identifier(name) = call.callee.as_ref()
+            && let Some(function_type) = self.function_variable_type(name)
+        {
+            return self.infer_closure_call_type(name, call, function_type);
+        }
+
         // 特殊处理内置函数
         if let Expr::Identifier(name) = call.callee.as_ref() {
-            // 内置输入函数的类型推断
-            match name.as_str() {
-                "print" | "println" => return Ok(Type::Void),
-                "readInt" => return Ok(Type::Int32),
-                "readLong" => return Ok(Type::Int64),
-                "readFloat" => return Ok(Type::Float32),
-                "readDouble" => return Ok(Type::Float64),
-                "readLine" => return Ok(Type::String),
-                "readChar" => return Ok(Type::Char),
-                "readBool" => return Ok(Type::Bool),
-                _ => {}
+            // debugPrint 接受任意类型的参数，不能用固定签名描述
+            if name == "debugPrint" && !self.shadows_builtin(name) {
+                return self.infer_debug_print_type(call);
+            }
+
+            // format(fmt, ...) 和多于一个参数的 print/println 按格式字符串检查参数，
+            // 第一个参数是浮点数的 println(value, digits) 除外
+            let is_print = name == "print" || name == "println";
+            let is_fixed_println = name == "println" && call.args.len() == 2
+                && matches!(self.infer_expr_type(&call.args[0]), Ok(Type::Float32 | Type::Float64));
+            if (name == "format" || (is_print && call.args.len() > 1 && !is_fixed_println)) && !self.shadows_builtin(name) {
+                return self.infer_format_call_type(name, call);
+            }
+
+            // 内置函数：按注册的签名检查参数个数和类型。
+            // 当前类或其父类声明了同名方法时，用户方法遮蔽内置函数，只在这些方法中做重载解析
+            if !self.shadows_builtin(name)
+                && let Some(signatures) = self.type_registry.builtin_function(name)
+            {
+                let signatures = signatures.to_vec();
+                return self.infer_builtin_call_type(name, call, &signatures);
             }
 
             // 尝试查找当前类的方法（无对象调用）- 支持方法重载
             if let Some(ref current_class) = self.current_class.clone() {
                 // 先推断所有参数类型
-                let mut arg_types = Vec::new();
-                for arg in &call.args {
-                    arg_types.push(self.infer_expr_type(arg)?);
-                }
+                let arg_types = self.infer_call_arg_types(current_class, name, &call.args)?;
 
                 // 使用参数类型查找匹配的方法
                 if let Some(method_info) = self.type_registry.find_method(current_class, name, &arg_types) {
+                    if !method_info.is_static && self.current_method_is_static {
+                        return Err(semantic_error(
+                            call.loc.line,
+                            call.loc.column,
+                            format!("non-static method {}() {}", name, STATIC_CONTEXT)
+                        ));
+                    }
                     let return_type = method_info.return_type.clone();
                     let params = method_info.params.clone();
                     // 检查参数类型兼容性（支持可变参数）
                     if let Err(msg) = self.check_arguments_compatible(&call.args, &params, call.loc.line, call.loc.column) {
                         return Err(semantic_error(call.loc.line, call.loc.column, msg));
                     }
+                    self.call_overloads.insert(call.id, params);
 
                     return Ok(return_type);
                 }
@@ -239,6 +585,10 @@ impl SemanticAnalyzer {
 
         // 支持成员调用: obj.method(...) 或 ClassName.method()（静态方法）
         if let Expr::MemberAccess(member) = call.callee.as_ref() {
+            if member.null_safe {
+                self.check_null_safe_object(member)?;
+            }
+
             // 推断对象类型
             let obj_type = self.infer_expr_type(&member.object)?;
 
@@ -251,14 +601,26 @@ impl SemanticAnalyzer {
             if let Expr::Identifier(class_name) = &*member.object {
                 let class_name = class_name.clone();
                 // 先推断所有参数类型
-                let mut arg_types = Vec::new();
-                for arg in &call.args {
-                    arg_types.push(self.infer_expr_type(arg)?);
+                let arg_types = self.infer_call_arg_types(&class_name, &member.member, &call.args)?;
+
+                if class_name == "Arrays" && member.member == "toString" && self.type_registry.is_builtin_class(&class_name) {
+                    return self.infer_arrays_to_string_type(call, &arg_types);
                 }
 
                 if let Some(class_info) = self.type_registry.get_class(&class_name) {
                     // 使用参数类型查找匹配的静态方法
                     if let Some(method_info) = class_info.find_method(&member.member, &arg_types) {
+                        // 类名.实例方法()：没有接收者对象
+                        if !method_info.is_static && self.is_class_reference(&class_name) {
+                            return Err(semantic_error(
+                                call.loc.line,
+                                call.loc.column,
+                                format!(
+                                    "non-static method {}() in class '{}' {}",
+                                    member.member, class_name, STATIC_CONTEXT
+                                )
+                            ));
+                        }
                         if method_info.is_static {
                             let return_type = method_info.return_type.clone();
                             let params = method_info.params.clone();
@@ -266,9 +628,21 @@ impl SemanticAnalyzer {
                             if let Err(msg) = self.check_arguments_compatible(&call.args, &params, call.loc.line, call.loc.column) {
                                 return Err(semantic_error(call.loc.line, call.loc.column, msg));
                             }
+                            self.call_overloads.insert(call.id, params);
 
                             return Ok(return_type);
                         }
+                    } else if self.type_registry.is_builtin_class(&class_name)
+                        && class_info.methods.contains_key(&member.member) {
+                        // 内置类没有实例，重载不匹配时直接报告实参错误
+                        return Err(semantic_error(
+                            call.loc.line,
+                            call.loc.column,
+                            format!(
+                                "Method '{}' in class '{}' cannot be applied to given types: argument mismatch",
+                                member.member, class_name
+                            )
+                        ));
                     }
                 }
             }
@@ -276,10 +650,7 @@ impl SemanticAnalyzer {
             // 处理类实例方法调用 - 支持方法重载
             if let Type::Object(class_name) = obj_type {
                 // 先推断所有参数类型
-                let mut arg_types = Vec::new();
-                for arg in &call.args {
-                    arg_types.push(self.infer_expr_type(arg)?);
-                }
+                let arg_types = self.infer_call_arg_types(&class_name, &member.member, &call.args)?;
 
                 // 使用参数类型查找匹配的方法
                 if let Some(method_info) = self.type_registry.find_method(&class_name, &member.member, &arg_types) {
@@ -289,6 +660,9 @@ impl SemanticAnalyzer {
                     if let Err(msg) = self.check_arguments_compatible(&call.args, &params, call.loc.line, call.loc.column) {
                         return Err(semantic_error(call.loc.line, call.loc.column, msg));
                     }
+                    // 接收者是任意表达式（如链式调用 `sb.append(a).append(b)`）时，代码生成按这里记录的类名调用
+                    self.field_access_classes.insert(member.id, class_name);
+                    self.call_overloads.insert(call.id, params);
 
                     return Ok(return_type);
                 } else {
@@ -305,15 +679,13 @@ impl SemanticAnalyzer {
         // 尝试提供更详细的错误信息
         if let Expr::Identifier(name) = call.callee.as_ref() {
             if let Some(ref current_class) = self.current_class {
-                // 检查是否存在同名方法（参数不匹配）
-                if let Some(class_info) = self.type_registry.get_class(current_class) {
-                    if class_info.methods.contains_key(name) {
-                        return Err(semantic_error(
-                            call.loc.line,
-                            call.loc.column,
-                            format!("Method '{}' in class '{}' cannot be applied to given types: argument mismatch", name, current_class)
-                        ));
-                    }
+                // 检查是否存在同名方法（参数不匹配），包括继承的方法
+                if self.type_registry.get_method(current_class, name).is_some() {
+                    return Err(semantic_error(
+                        call.loc.line,
+                        call.loc.column,
+                        format!("Method '{}' in class '{}' cannot be applied to given types: argument mismatch", name, current_class)
+                    ));
                 }
             }
             return Err(semantic_error(
@@ -347,8 +719,82 @@ impl SemanticAnalyzer {
         ))
     }
 
+    /// 推断调用实参的类型
+    ///
+    /// lambda 和方法引用实参的类型取决于目标参数的函数类型：同名且参数个数相同的重载
+    /// 在该位置声明了唯一的函数类型时按它推断，否则按无目标类型推断。
+    fn infer_call_arg_types(&mut self, class_name: &str, method_name: &str, args: &[Expr]) -> cayResult<Vec<Type>> {
+        let overloads = self.type_registry.get_overloads(class_name, method_name).to_vec();
+        let mut arg_types = Vec::with_capacity(args.len());
+        for (i, arg) in args.iter().enumerate() {
+            let mut targets: Vec<&Type> = overloads.iter()
+                .filter(|m| m.params.len() == args.len())
+                .map(|m| &m.params[i].param_type)
+                .filter(|t| matches!(t, Type::Function(_)))
+                .collect();
+            targets.dedup();
+            let arg_type = match targets.as_slice() {
+                [target] => self.infer_expr_type_expecting(arg, target)?,
+                _ => self.infer_expr_type(arg)?,
+            };
+            arg_types.push(arg_type);
+        }
+        Ok(arg_types)
+    }
+
+    /// 名字是否指向函数类型的变量（局部变量、参数或当前类的字段），返回其函数类型
+    ///
+    /// 局部变量和参数优先于同名方法，因此 `f(x)` 可以调用作为参数传入的闭包。
+    pub(super) fn function_variable_type(&self, name: &str) -> Option<FunctionType> {
+        let ty = match self.symbol_table.lookup(name) {
+            Some(info) => info.symbol_type.clone(),
+            None => self.type_registry.find_field(self.current_class.as_deref()?, name)?.1.field_type.clone(),
+        };
+        match ty {
+            Type::Function(function_type) => Some(*function_type),
+            _ => None,
+        }
+    }
+
+    /// 推断闭包调用 `f(args)` 的类型：实参按函数类型的参数检查，结果是函数类型的返回类型
+    fn infer_closure_call_type(&mut self, name: &str, call: &CallExpr, function_type: FunctionType) -> cayResult<Type> {
+        if call.args.len() != function_type.params.len() {
+            return Err(semantic_error(
+                call.loc.line,
+                call.loc.column,
+                format!("Function '{}' of type {} expects {} argument(s), got {}",
+                    name, Type::Function(Box::new(function_type.clone())), function_type.params.len(), call.args.len())
+            ));
+        }
+        for (i, (arg, param)) in call.args.iter().zip(&function_type.params).enumerate() {
+            let arg_type = self.infer_expr_type_expecting(arg, param)?;
+            if !self.types_compatible(&arg_type, param) {
+                return Err(semantic_error(
+                    call.loc.line,
+                    call.loc.column,
+                    format!("Argument {} of function '{}' type mismatch: expected {}, got {}", i + 1, name, param, arg_type)
+                ));
+            }
+        }
+        let return_type = (*function_type.return_type).clone();
+        self.function_types.insert(call.id, function_type);
+        Ok(return_type)
+    }
+
+    /// 标识符是否作为类名使用（没有被同名的局部变量、参数或字段遮蔽）
+    fn is_class_reference(&self, name: &str) -> bool {
+        self.symbol_table.lookup(name).is_none()
+            && self.current_class.as_ref()
+                .is_none_or(|current| self.type_registry.find_field(current, name).is_none())
+            && self.type_registry.class_exists(name)
+    }
+
     /// 推断成员访问类型
     fn infer_member_access_type(&mut self, member: &MemberAccessExpr) -> cayResult<Type> {
+        if member.null_safe {
+            self.check_null_safe_object(member)?;
+        }
+
         // 检查是否是静态字段访问: ClassName.fieldName
         if let Expr::Identifier(class_name) = &*member.object {
             if let Some(class_info) = self.type_registry.get_class(class_name) {
@@ -404,7 +850,7 @@ impl SemanticAnalyzer {
                     return Err(semantic_error(
                         member.loc.line,
                         member.loc.column,
-                        format!("non-static variable {} cannot be referenced from a static context", member.member)
+                        format!("non-static variable {} {}", member.member, STATIC_CONTEXT)
                     ));
                 }
             }
@@ -412,42 +858,34 @@ impl SemanticAnalyzer {
 
         // 类成员访问
         if let Type::Object(class_name) = obj_type {
-            if let Some(class_info) = self.type_registry.get_class(&class_name) {
-                if let Some(field_info) = class_info.fields.get(&member.member) {
-                    // 检查静态方法中是否访问非静态字段
-                    if self.current_method_is_static && !field_info.is_static {
-                        // 检查是否是当前类的实例字段
-                        if let Some(current_class) = &self.current_class {
-                            if current_class == &class_name {
-                                return Err(semantic_error(
-                                    member.loc.line,
-                                    member.loc.column,
-                                    format!("non-static variable {} cannot be referenced from a static context", member.member)
-                                ));
-                            }
-                        }
-                    }
-                    
-                    // 检查私有字段访问权限
-                    if !field_info.is_public {
-                        if let Some(current_class) = &self.current_class {
-                            if current_class != &class_name {
-                                return Err(semantic_error(
-                                    member.loc.line,
-                                    member.loc.column,
-                                    format!("{} has private access in {}", member.member, class_name)
-                                ));
-                            }
-                        } else {
+            if let Some((owner, field_info)) = self.type_registry.find_field(&class_name, &member.member) {
+                // 检查静态方法中是否访问非静态字段
+                if self.current_method_is_static && !field_info.is_static {
+                    // 检查是否是当前类的实例字段
+                    if let Some(current_class) = &self.current_class {
+                        if current_class == &class_name {
                             return Err(semantic_error(
                                 member.loc.line,
                                 member.loc.column,
-                                format!("{} has private access in {}", member.member, class_name)
+                                format!("non-static variable {} {}", member.member, STATIC_CONTEXT)
                             ));
                         }
                     }
-                    return Ok(field_info.field_type.clone());
                 }
+                
+                // 检查私有字段访问权限
+                if !field_info.is_public && self.current_class.as_deref() != Some(owner) {
+                    return Err(semantic_error(
+                        member.loc.line,
+                        member.loc.column,
+                        format!("{} has private access in {}", member.member, owner)
+                    ));
+                }
+                let field_type = field_info.field_type.clone();
+                if !field_info.is_static {
+                    self.field_access_classes.insert(member.id, class_name);
+                }
+                return Ok(field_type);
             }
             return Err(semantic_error(
                 member.loc.line,
@@ -463,10 +901,45 @@ impl SemanticAnalyzer {
         ))
     }
 
+    /// 推断 `super` 的类型：实例上下文中为当前类的父类
+    fn infer_super_type(&self) -> cayResult<Type> {
+        let (line, column) = self.enclosing_line_column();
+        if self.current_method_is_static {
+            return Err(semantic_error(line, column, format!("non-static variable super {}", STATIC_CONTEXT)));
+        }
+        let Some(current_class) = &self.current_class else {
+            return Err(semantic_error(line, column, "'super' can only be used inside a class".to_string()));
+        };
+        match self.type_registry.get_class(current_class).and_then(|c| c.parent.clone()) {
+            Some(parent) => Ok(Type::Object(parent)),
+            None => Err(semantic_error(line, column, format!("Class '{}' has no superclass; 'super' cannot be used", current_class))),
+        }
+    }
+
     /// 推断 new 表达式类型
     fn infer_new_type(&mut self, new_expr: &NewExpr) -> cayResult<Type> {
-        if self.type_registry.class_exists(&new_expr.class_name) {
+        if let Some(class_info) = self.type_registry.get_class(&new_expr.class_name) {
+            if self.type_registry.is_builtin_class(&new_expr.class_name) {
+                return Err(semantic_error(
+                    new_expr.loc.line,
+                    new_expr.loc.column,
+                    format!("Cannot instantiate builtin class '{}'", new_expr.class_name)
+                ));
+            }
+            if class_info.is_abstract {
+                return Err(semantic_error(
+                    new_expr.loc.line,
+                    new_expr.loc.column,
+                    format!("Cannot instantiate abstract class '{}'", new_expr.class_name)
+                ));
+            }
             Ok(Type::Object(new_expr.class_name.clone()))
+        } else if self.type_registry.interface_exists(&new_expr.class_name) {
+            Err(semantic_error(
+                new_expr.loc.line,
+                new_expr.loc.column,
+                format!("Cannot instantiate interface '{}'", new_expr.class_name)
+            ))
         } else {
             Err(semantic_error(
                 new_expr.loc.line,
@@ -478,10 +951,10 @@ impl SemanticAnalyzer {
 
     /// 推断赋值表达式类型
     fn infer_assignment_type(&mut self, assign: &AssignmentExpr) -> cayResult<Type> {
-        // 检查是否是 final 变量重新赋值
+        // 检查是否是 final 变量重新赋值；没有初始值的 final 变量的首次赋值由明确赋值分析检查
         if let Expr::Identifier(name) = &assign.target.as_ref() {
             if let Some(info) = self.symbol_table.lookup(name) {
-                if info.is_final {
+                if info.is_final && (info.is_initialized || assign.op != AssignOp::Assign) {
                     return Err(semantic_error(
                         assign.loc.line,
                         assign.loc.column,
@@ -492,7 +965,16 @@ impl SemanticAnalyzer {
         }
 
         let target_type = self.infer_expr_type(&assign.target)?;
-        let value_type = self.infer_expr_type(&assign.value)?;
+        let value_type = if assign.op == AssignOp::Assign {
+            self.infer_assigned_type(&assign.value, &target_type)?
+        } else {
+            self.infer_expr_type_expecting(&assign.value, &target_type)?
+        };
+
+        // 复合赋值隐含一次到目标类型的转换（`b += 1` 对 byte 合法）
+        if assign.op != AssignOp::Assign && Self::is_numeric_type_helper(&target_type) && Self::is_numeric_type_helper(&value_type) {
+            return Ok(target_type);
+        }
 
         if self.types_compatible(&value_type, &target_type) {
             Ok(target_type)
@@ -508,6 +990,8 @@ impl SemanticAnalyzer {
     /// 推断类型转换表达式类型
     fn infer_cast_type(&mut self, cast: &CastExpr) -> cayResult<Type> {
         // TODO: 检查转换是否合法
+        let operand_type = self.infer_expr_type(&cast.expr)?;
+        self.cast_types.insert(cast.id, operand_type);
         Ok(cast.target_type.clone())
     }
 
@@ -559,8 +1043,23 @@ impl SemanticAnalyzer {
                 "Cannot infer type of empty array initializer".to_string()
             ));
         }
-        // 推断第一个元素的类型作为数组元素类型
-        let elem_type = self.infer_expr_type(&init.elements[0])?;
+        // 元素类型取所有元素的公共类型，数值类型按提升规则合并（如 {1, 2.5} 推断为 double[]）
+        let mut elem_type = self.infer_expr_type(&init.elements[0])?;
+        for element in &init.elements[1..] {
+            let ty = self.infer_expr_type(element)?;
+            if ty == elem_type {
+                continue;
+            }
+            if Self::is_numeric_type_helper(&ty) && Self::is_numeric_type_helper(&elem_type) {
+                elem_type = self.promote_types(&elem_type, &ty);
+            } else if !self.types_compatible(&ty, &elem_type) {
+                return Err(semantic_error(
+                    init.loc.line,
+                    init.loc.column,
+                    format!("Array initializer elements must have compatible types, got {} and {}", elem_type, ty)
+                ));
+            }
+        }
         Ok(Type::Array(Box::new(elem_type)))
     }
 
@@ -579,7 +1078,10 @@ impl SemanticAnalyzer {
         }
 
         match array_type {
-            Type::Array(element_type) => Ok(*element_type),
+            Type::Array(element_type) => {
+                self.array_element_types.insert(arr.id, (*element_type).clone());
+                Ok(*element_type)
+            }
             _ => Err(semantic_error(
                 arr.loc.line,
                 arr.loc.column,
@@ -589,74 +1091,209 @@ impl SemanticAnalyzer {
     }
 
     /// 推断方法引用表达式类型
-    fn infer_method_ref_type(&mut self, method_ref: &MethodRefExpr) -> cayResult<Type> {
-        // 方法引用: ClassName::methodName 或 obj::methodName
-        // 返回函数类型（这里简化为 Object 类型，实际应该返回函数类型）
-        // TODO: 实现完整的函数类型系统
-        if let Some(ref class_name) = method_ref.class_name {
-            // 检查类是否存在
-            if !self.type_registry.class_exists(class_name) {
-                return Err(semantic_error(
-                    method_ref.loc.line,
-                    method_ref.loc.column,
-                    format!("Unknown class: {}", class_name)
-                ));
+    ///
+    /// `ClassName::method` 引用静态方法，`obj::method` 引用绑定到该对象的实例方法。
+    /// 有目标函数类型时按其参数类型选择重载，否则方法不能有重载。
+    fn infer_method_ref_type(&mut self, method_ref: &MethodRefExpr, target: Option<&FunctionType>) -> cayResult<Type> {
+        // 没有目标类型时沿用之前推断出的类型（同一实参在重载解析中会被多次推断）
+        let target = target.cloned().or_else(|| self.function_types.get(&method_ref.id).cloned());
+        let (line, column) = (method_ref.loc.line, method_ref.loc.column);
+        let method_name = &method_ref.method_name;
+
+        // 解析器把 `name::method` 中的名字都记为类名，名字是变量时按对象处理
+        let (class_name, bound) = match (&method_ref.class_name, &method_ref.object) {
+            (Some(name), _) if self.is_class_reference(name) => (name.clone(), false),
+            (Some(name), _) if self.symbol_table.lookup(name).is_none()
+                && self.current_class.as_ref().is_none_or(|c| self.type_registry.find_field(c, name).is_none()) => {
+                return Err(semantic_error(line, column, format!("Unknown class: {}", name)));
             }
-            // 检查方法是否存在
-            if let Some(class_info) = self.type_registry.get_class(class_name) {
-                if !class_info.methods.contains_key(&method_ref.method_name) {
+            (Some(name), _) => (self.method_ref_receiver_class(&Expr::Identifier(name.clone()), method_name, line, column)?, true),
+            (None, Some(object)) => (self.method_ref_receiver_class(object, method_name, line, column)?, true),
+            (None, None) => return Err(semantic_error(line, column, "Invalid method reference")),
+        };
+
+        let overloads = self.type_registry.get_overloads(&class_name, method_name).to_vec();
+        if overloads.is_empty() {
+            return Err(semantic_error(line, column, format!("Unknown method '{}' for class {}", method_name, class_name)));
+        }
+        let method = match &target {
+            Some(target) => ClassInfo::resolve_overload(&overloads, &target.params).cloned().ok_or_else(|| semantic_error(
+                line, column,
+                format!("Incompatible method reference '{}::{}': no overload accepts {}",
+                    class_name, method_name, Type::Function(Box::new(target.clone())))
+            ))?,
+            None if overloads.len() == 1 => overloads[0].clone(),
+            None => return Err(semantic_error(
+                line, column,
+                format!("Ambiguous method reference '{}::{}': the method is overloaded, declare the target function type", class_name, method_name)
+            )),
+        };
+
+        if method.params.iter().any(|p| p.is_varargs) {
+            return Err(semantic_error(line, column, format!("Method reference to varargs method '{}::{}' is not supported", class_name, method_name)));
+        }
+        if !method.is_static && !bound {
+            return Err(semantic_error(
+                line, column,
+                format!("Instance method '{}' in class '{}' must be referenced through an object, e.g. obj::{}", method_name, class_name, method_name)
+            ));
+        }
+
+        let function_type = match target {
+            Some(target) => {
+                if *target.return_type != Type::Void && !self.types_compatible(&method.return_type, &target.return_type) {
                     return Err(semantic_error(
-                        method_ref.loc.line,
-                        method_ref.loc.column,
-                        format!("Unknown method '{}' for class {}", method_ref.method_name, class_name)
+                        line, column,
+                        format!("Bad return type in method reference: {} cannot be converted to {}", method.return_type, target.return_type)
                     ));
                 }
+                target
             }
+            None => FunctionType {
+                params: method.params.iter().map(|p| p.param_type.clone()).collect(),
+                return_type: Box::new(method.return_type.clone()),
+                is_static: false,
+            },
+        };
+        self.function_types.insert(method_ref.id, function_type.clone());
+        self.method_refs.insert(method_ref.id, method);
+        Ok(Type::Function(Box::new(function_type)))
+    }
+
+    /// 绑定方法引用 `obj::method` 的接收者类型，必须是类类型的对象
+    fn method_ref_receiver_class(&mut self, object: &Expr, method_name: &str, line: usize, column: usize) -> cayResult<String> {
+        match self.infer_expr_type(object)? {
+            Type::Object(class_name) => Ok(class_name),
+            other => Err(semantic_error(
+                line, column,
+                format!("Cannot reference method '{}' on a value of type {}", method_name, other)
+            )),
         }
-        // 方法引用返回 Object 类型（简化处理）
-        Ok(Type::Object("Function".to_string()))
     }
 
     /// 推断 Lambda 表达式类型
-    fn infer_lambda_type(&mut self, lambda: &LambdaExpr) -> cayResult<Type> {
+    ///
+    /// 有目标函数类型时，未标注类型的参数取目标的参数类型，lambda 的类型就是目标类型；
+    /// 否则未标注类型的参数按 int 处理，返回类型由函数体推断。
+    fn infer_lambda_type(&mut self, lambda: &LambdaExpr, target: Option<&FunctionType>) -> cayResult<Type> {
         // Lambda 表达式: (params) -> { body }
+        // 没有目标类型时沿用之前推断出的类型（同一实参在重载解析中会被多次推断）
+        let target = target.cloned().or_else(|| self.function_types.get(&lambda.id).cloned());
+        let (line, column) = (lambda.loc.line, lambda.loc.column);
+
+        let mut params = Vec::with_capacity(lambda.params.len());
+        if let Some(target) = &target
+            && target.params.len() != lambda.params.len()
+        {
+            return Err(semantic_error(
+                line, column,
+                format!("Incompatible lambda expression: {} expects {} parameter(s), got {}",
+                    Type::Function(Box::new(target.clone())), target.params.len(), lambda.params.len())
+            ));
+        }
+        for (i, param) in lambda.params.iter().enumerate() {
+            let expected = target.as_ref().map(|t| &t.params[i]);
+            let param_type = match (&param.param_type, expected) {
+                (Some(declared), Some(expected)) if declared != expected => {
+                    return Err(semantic_error(
+                        line, column,
+                        format!("Incompatible parameter types in lambda expression: expected {} for '{}', got {}", expected, param.name, declared)
+                    ));
+                }
+                (Some(declared), _) => declared.clone(),
+                (None, Some(expected)) => expected.clone(),
+                (None, None) => Type::Int32,
+            };
+            params.push(param_type);
+        }
+
+        // 在进入 lambda 作用域之前计算捕获集合，此时符号表中只有外层变量
+        let captures = self.lambda_capture_set(lambda);
+        self.lambda_captures.insert(lambda.id, captures);
+
         // 创建新的作用域
         self.symbol_table.enter_scope();
 
         // 添加 Lambda 参数到符号表
-        for param in &lambda.params {
-            let param_type = param.param_type.clone().unwrap_or(Type::Int32);
+        for (param, param_type) in lambda.params.iter().zip(&params) {
+            self.check_identifier_not_reserved(&param.name, "参数", &lambda.loc);
             self.symbol_table.declare(
                 param.name.clone(),
                 SemanticSymbolInfo {
                     name: param.name.clone(),
-                    symbol_type: param_type,
+                    symbol_type: param_type.clone(),
                     is_final: false,
                     is_initialized: true,
                 }
             );
         }
 
-        // 推断 Lambda 体类型
+        // 推断 Lambda 体类型；块体中 return 语句的类型收集在 lambda_returns 中
+        let expected_return = target.as_ref().map(|t| (*t.return_type).clone());
+        let saved_returns = self.lambda_returns.replace(Vec::new());
+        // lambda 体内的 break/continue 不能跳出 lambda
+        let saved_targets = std::mem::take(&mut self.jump_targets);
         let body_type = match &lambda.body {
-            LambdaBody::Expr(expr) => self.infer_expr_type(expr)?,
+            LambdaBody::Expr(expr) => match &expected_return {
+                Some(expected) => self.infer_expr_type_expecting(expr, expected),
+                None => self.infer_expr_type(expr),
+            },
             LambdaBody::Block(block) => {
-                // 分析块中的语句
-                let mut last_type = Type::Void;
+                // 检查块中的语句，体内的局部变量在后续语句中可见
                 for stmt in &block.statements {
-                    // 查找 return 语句来确定返回类型
-                    if let Stmt::Return(Some(ret_expr)) = stmt {
-                        last_type = self.infer_expr_type(ret_expr)?;
+                    if let Err(e) = self.type_check_statement(stmt, expected_return.as_ref()) {
+                        self.errors.push(e.with_fallback_location(line, column));
                     }
                 }
-                last_type
+                self.lambda_block_return_type(self.lambda_returns.clone().unwrap_or_default(), expected_return.as_ref(), line, column)
             }
         };
+        self.lambda_returns = saved_returns;
+        self.jump_targets = saved_targets;
 
         self.symbol_table.exit_scope();
+        let body_type = body_type?;
+
+        let return_type = match expected_return {
+            Some(expected) => {
+                // 表达式体的值在目标返回 void 时丢弃
+                if matches!(lambda.body, LambdaBody::Expr(_))
+                    && expected != Type::Void
+                    && !self.types_compatible(&body_type, &expected)
+                {
+                    return Err(semantic_error(
+                        line, column,
+                        format!("Bad return type in lambda expression: {} cannot be converted to {}", body_type, expected)
+                    ));
+                }
+                expected
+            }
+            None => body_type,
+        };
+
+        let function_type = FunctionType { params, return_type: Box::new(return_type), is_static: false };
+        self.function_types.insert(lambda.id, function_type.clone());
+        Ok(Type::Function(Box::new(function_type)))
+    }
 
-        // Lambda 表达式返回 Object 类型（简化处理）
-        Ok(Type::Object("Function".to_string()))
+    /// 块体 lambda 的返回类型：第一个带值的 return 语句的类型，没有时为 void
+    ///
+    /// 有目标返回类型时各 return 已经按目标检查过，只需检查是否缺少 return。
+    fn lambda_block_return_type(&self, returns: Vec<Type>, expected: Option<&Type>, line: usize, column: usize) -> cayResult<Type> {
+        if let Some(expected) = expected {
+            if *expected != Type::Void && returns.is_empty() {
+                return Err(semantic_error(line, column, format!("Missing return statement in lambda expression returning {}", expected)));
+            }
+            return Ok(expected.clone());
+        }
+        let return_type = returns.iter().find(|t| **t != Type::Void).cloned().unwrap_or(Type::Void);
+        if let Some(other) = returns.iter().find(|t| !self.types_compatible(t, &return_type)) {
+            return Err(semantic_error(
+                line, column,
+                format!("Bad return type in lambda expression: {} cannot be converted to {}", other, return_type)
+            ));
+        }
+        Ok(return_type)
     }
 
     /// 推断三元运算符表达式类型
@@ -677,19 +1314,27 @@ impl SemanticAnalyzer {
         let true_type = self.infer_expr_type(&ternary.true_branch)?;
         let false_type = self.infer_expr_type(&ternary.false_branch)?;
 
-        // 两个分支类型必须兼容
-        if true_type == false_type {
-            Ok(true_type)
+        // 两个分支类型必须兼容：数值类型按二元运算的规则提升，null 取另一分支的引用类型
+        let is_null = |expr: &Expr| matches!(expr, Expr::Literal(LiteralValue::Null));
+        let result_type = if true_type == false_type {
+            true_type
         } else if Self::is_numeric_type_helper(&true_type) && Self::is_numeric_type_helper(&false_type) {
-            // 数值类型进行类型提升
-            Ok(self.promote_types(&true_type, &false_type))
+            self.promote_types(&true_type, &false_type)
+        } else if is_null(&ternary.true_branch) && false_type.is_reference_type() {
+            false_type
+        } else if true_type.is_reference_type()
+            && (is_null(&ternary.false_branch) || self.types_compatible(&false_type, &true_type)) {
+            true_type
         } else {
-            Err(semantic_error(
+            return Err(semantic_error(
                 ternary.loc.line,
                 ternary.loc.column,
                 format!("Ternary operator branches must have compatible types, got {} and {}", true_type, false_type)
-            ))
-        }
+            ));
+        };
+
+        self.ternary_types.insert(ternary.id, result_type.clone());
+        Ok(result_type)
     }
 
     /// 推断 instanceof 表达式类型
@@ -722,8 +1367,159 @@ impl SemanticAnalyzer {
         Ok(Type::Bool)
     }
 
+    /// 推断空值合并表达式类型: value ?? default
+    fn infer_null_coalesce_type(&mut self, coalesce: &NullCoalesceExpr) -> cayResult<Type> {
+        let loc = &coalesce.loc;
+        // 左侧为安全导航时允许基本类型结果（对象为 null 时取默认值）
+        let is_null_safe = coalesce.value.null_safe_member().is_some();
+        let value_type = match coalesce.value.as_ref() {
+            Expr::MemberAccess(member) if is_null_safe => self.infer_member_access_type(member)?,
+            Expr::Call(call) if is_null_safe => self.infer_call_type(call)?,
+            value => self.infer_expr_type(value)?,
+        };
+
+        if matches!(value_type, Type::Void | Type::Never) {
+            return Err(semantic_error(
+                loc.line,
+                loc.column,
+                "Left operand of '??' cannot be a void expression".to_string()
+            ));
+        }
+        if !is_null_safe && value_type.is_primitive() {
+            return Err(semantic_error(
+                loc.line,
+                loc.column,
+                format!("Left operand of '??' has non-nullable type {}", value_type)
+            ));
+        }
+
+        let default_type = self.infer_expr_type(&coalesce.default)?;
+
+        let result_type = if matches!(coalesce.value.as_ref(), Expr::Literal(LiteralValue::Null)) {
+            default_type
+        } else if value_type == default_type {
+            value_type
+        } else if Self::is_numeric_type_helper(&value_type) && Self::is_numeric_type_helper(&default_type) {
+            self.promote_types(&value_type, &default_type)
+        } else if value_type.is_reference_type() && self.types_compatible(&default_type, &value_type) {
+            value_type
+        } else {
+            return Err(semantic_error(
+                loc.line,
+                loc.column,
+                format!("Operands of '??' must have compatible types, got {} and {}", value_type, default_type)
+            ));
+        };
+
+        self.null_safe_types.insert(coalesce.id, result_type.clone());
+        Ok(result_type)
+    }
+
+    /// 推断内联 IR 表达式类型: __llvm__<T>("template", args...)
+    ///
+    /// 检查模板中的占位符与实参一一对应、有结果类型时恰好给 `$out` 赋值；
+    /// 模板只能是一段直线代码，不能包含标签和终结指令。
+    fn infer_inline_ir_type(&mut self, inline: &InlineIrExpr) -> cayResult<Type> {
+        let loc = &inline.loc;
+        let error = |message: String| Err(semantic_error(loc.line, loc.column, message));
+
+        for (index, arg) in inline.args.iter().enumerate() {
+            if matches!(self.infer_expr_type(arg)?, Type::Void | Type::Never) {
+                return error(format!("Argument {} of __llvm__ cannot be a void expression", index));
+            }
+        }
+        if let Type::Object(name) = &inline.result_type
+            && !self.type_registry.class_exists(name)
+        {
+            return error(format!("Unknown type in __llvm__: {}", name));
+        }
+
+        let pieces = match inline.pieces() {
+            Ok(pieces) => pieces,
+            Err(message) => return error(message),
+        };
+        let mut used = vec![false; inline.args.len()];
+        let mut assigns_out = false;
+        for piece in &pieces {
+            match piece {
+                IrTemplatePiece::Arg(index) | IrTemplatePiece::ArgType(index) => match used.get_mut(*index) {
+                    Some(flag) => *flag = true,
+                    None => return error(format!(
+                        "Inline IR template refers to ${}, but only {} argument(s) were given",
+                        index, inline.args.len())),
+                },
+                IrTemplatePiece::Out | IrTemplatePiece::OutType if inline.result_type == Type::Void => {
+                    return error("Inline IR without a result type cannot use $out or $T; write __llvm__<T>(...)".to_string());
+                }
+                IrTemplatePiece::Out => assigns_out = true,
+                IrTemplatePiece::OutType | IrTemplatePiece::Text(_) => {}
+            }
+        }
+        if let Some(index) = used.iter().position(|used| !used) {
+            return error(format!("Argument {} of __llvm__ is not used in the template", index));
+        }
+        if inline.result_type != Type::Void && !assigns_out {
+            return error(format!("Inline IR with result type {} must assign $out", inline.result_type));
+        }
+
+        for line in inline.template.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with(';')) {
+            let opcode = line.split_once('=').filter(|(lhs, _)| lhs.trim_start().starts_with('%'))
+                .map_or(line, |(_, rhs)| rhs)
+                .split_whitespace().next().unwrap_or("");
+            if line.ends_with(':') || matches!(opcode, "br" | "ret" | "switch" | "indirectbr" | "unreachable" | "resume") {
+                return error(format!("Inline IR cannot contain labels or terminators: '{}'", line));
+            }
+        }
+
+        Ok(inline.result_type.clone())
+    }
+
+    /// 检查安全导航的对象是否可能为 null，并记录对象类型供代码生成使用
+    fn check_null_safe_object(&mut self, member: &MemberAccessExpr) -> cayResult<()> {
+        if let Expr::Identifier(name) = member.object.as_ref()
+            && self.symbol_table.lookup(name).is_none()
+            && self.type_registry.class_exists(name)
+        {
+            return Err(semantic_error(
+                member.loc.line,
+                member.loc.column,
+                format!("Safe navigation '?.' cannot be applied to class name {}", name)
+            ));
+        }
+
+        let obj_type = self.infer_expr_type(&member.object)?;
+        if !obj_type.is_reference_type() {
+            return Err(semantic_error(
+                member.loc.line,
+                member.loc.column,
+                format!("Safe navigation '?.' requires a nullable reference type, got {}", obj_type)
+            ));
+        }
+        self.null_safe_types.insert(member.id, obj_type);
+        Ok(())
+    }
+
+    /// 安全导航的结果在对象为 null 时也是 null，因此不能是基本类型
+    ///
+    /// 作为 `??` 左侧时由 `infer_null_coalesce_type` 直接推断，不经过此检查。
+    fn check_null_safe_result(&self, expr: &Expr, ty: Type) -> cayResult<Type> {
+        if let Some(member) = expr.null_safe_member()
+            && ty.is_primitive()
+        {
+            return Err(semantic_error(
+                member.loc.line,
+                member.loc.column,
+                format!(
+                    "Safe navigation '?.{}' yields {} which cannot be null, use '??' to provide a default value",
+                    member.member, ty
+                )
+            ));
+        }
+        Ok(ty)
+    }
+
     /// 辅助方法：检查类型是否为数值类型
     fn is_numeric_type_helper(ty: &Type) -> bool {
-        matches!(ty, Type::Int32 | Type::Int64 | Type::Float32 | Type::Float64 | Type::Char)
+        matches!(ty, Type::Int8 | Type::Int16 | Type::Int32 | Type::Int64 | Type::Float32 | Type::Float64 | Type::Char)
     }
 }
@@ -0,0 +1,170 @@
+//! 格式化字符串的解析与检查
+//!
+//! `format(fmt, args...)` 和 `print/println(fmt, args...)` 的格式字符串在语义分析时解析，
+//! 说明符与实参的类型逐个对照检查，代码生成和解释器都使用这里解析出的片段。
+//!
+//! 说明符的写法与 C 的 printf 相同：`%[标志][宽度][.精度]转换`。
+//! 支持的转换：`d`/`x`/`X`/`o`（整数）、`f`/`e`/`E`（浮点数）、`c`（char）、`b`（boolean）、
+//! `s`（任意值，按字符串拼接的规则转换）；`%%` 输出百分号，`%n` 输出换行。
+
+use std::fmt;
+use crate::types::Type;
+
+/// 格式字符串的一段：原样输出的文本，或一个参数的说明符
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormatPiece {
+    Text(String),
+    Arg(FormatSpec),
+}
+
+/// 参数说明符 `%[标志][宽度][.精度]转换`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatSpec {
+    /// `-`（左对齐）、`+`、空格、`0`（补零）、`#`（进制前缀）
+    pub flags: String,
+    pub width: Option<usize>,
+    pub precision: Option<usize>,
+    pub conversion: char,
+}
+
+impl FormatSpec {
+    pub fn has_flag(&self, flag: char) -> bool {
+        self.flags.contains(flag)
+    }
+
+    /// 转换是否接受该类型的参数
+    pub fn accepts(&self, ty: &Type) -> bool {
+        match self.conversion {
+            'd' | 'x' | 'X' | 'o' => ty.is_integer(),
+            'f' | 'e' | 'E' => matches!(ty, Type::Float32 | Type::Float64),
+            'c' => *ty == Type::Char,
+            'b' => *ty == Type::Bool,
+            _ => !matches!(ty, Type::Void | Type::Never | Type::Function(_)),
+        }
+    }
+
+    /// 转换期望的参数种类，用于错误信息
+    fn expected(&self) -> &'static str {
+        match self.conversion {
+            'd' | 'x' | 'X' | 'o' => "an integer",
+            'f' | 'e' | 'E' => "a floating-point",
+            'c' => "a char",
+            'b' => "a boolean",
+            _ => "a printable",
+        }
+    }
+
+    /// 检查标志、宽度和精度的组合
+    fn validate(&self) -> Result<(), String> {
+        let numeric = matches!(self.conversion, 'd' | 'x' | 'X' | 'o' | 'f' | 'e' | 'E');
+        let signed = matches!(self.conversion, 'd' | 'f' | 'e' | 'E');
+        for flag in self.flags.chars() {
+            let allowed = match flag {
+                '-' => true,
+                '+' | ' ' => signed,
+                '0' => numeric,
+                _ => matches!(self.conversion, 'x' | 'X' | 'o'),
+            };
+            if !allowed {
+                return Err(format!("Flag '{}' is not allowed in format specifier '{}'", flag, self));
+            }
+        }
+        if (self.has_flag('-') || self.has_flag('0')) && self.width.is_none() {
+            return Err(format!("Format specifier '{}' requires a width", self));
+        }
+        if self.has_flag('-') && self.has_flag('0') {
+            return Err(format!("Flags '-' and '0' cannot be combined in format specifier '{}'", self));
+        }
+        if self.precision.is_some() && !matches!(self.conversion, 'f' | 'e' | 'E' | 's') {
+            return Err(format!("Format specifier '{}' does not accept a precision", self));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for FormatSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "%{}", self.flags)?;
+        if let Some(width) = self.width {
+            write!(f, "{}", width)?;
+        }
+        if let Some(precision) = self.precision {
+            write!(f, ".{}", precision)?;
+        }
+        write!(f, "{}", self.conversion)
+    }
+}
+
+/// 解析格式字符串，相邻的文本合并为一段
+pub fn parse_format(fmt: &str) -> Result<Vec<FormatPiece>, String> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut chars = fmt.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            text.push(c);
+            continue;
+        }
+        let mut spec = FormatSpec { flags: String::new(), width: None, precision: None, conversion: ' ' };
+        while let Some(&flag) = chars.peek().filter(|c| "-+ 0#".contains(**c)) {
+            spec.flags.push(flag);
+            chars.next();
+        }
+        spec.width = read_number(&mut chars);
+        if chars.next_if_eq(&'.').is_some() {
+            spec.precision = Some(read_number(&mut chars)
+                .ok_or_else(|| format!("Missing precision after '.' in format specifier '{}.'", spec))?);
+        }
+        spec.conversion = chars.next()
+            .ok_or_else(|| format!("Incomplete format specifier '{}' at end of format string", spec.to_string().trim_end()))?;
+        match spec.conversion {
+            '%' | 'n' => {
+                if !spec.flags.is_empty() || spec.width.is_some() || spec.precision.is_some() {
+                    return Err(format!("Format specifier '{}' does not accept flags, width or precision", spec));
+                }
+                text.push(if spec.conversion == '%' { '%' } else { '\n' });
+            }
+            'd' | 'x' | 'X' | 'o' | 'f' | 'e' | 'E' | 'c' | 'b' | 's' => {
+                spec.validate()?;
+                if !text.is_empty() {
+                    pieces.push(FormatPiece::Text(std::mem::take(&mut text)));
+                }
+                pieces.push(FormatPiece::Arg(spec));
+            }
+            _ => return Err(format!("Unknown format conversion '{}'", spec)),
+        }
+    }
+    if !text.is_empty() {
+        pieces.push(FormatPiece::Text(text));
+    }
+    Ok(pieces)
+}
+
+/// 检查实参的个数和类型是否与说明符一一对应
+pub fn check_format_args(pieces: &[FormatPiece], arg_types: &[Type]) -> Result<(), String> {
+    let specs: Vec<&FormatSpec> = pieces.iter()
+        .filter_map(|piece| match piece {
+            FormatPiece::Arg(spec) => Some(spec),
+            FormatPiece::Text(_) => None,
+        })
+        .collect();
+    if specs.len() != arg_types.len() {
+        return Err(format!("Format string expects {} argument(s), got {}", specs.len(), arg_types.len()));
+    }
+    for (i, (spec, ty)) in specs.iter().zip(arg_types).enumerate() {
+        if !spec.accepts(ty) {
+            return Err(format!("Format specifier '{}' expects {} argument, but argument {} is {}",
+                spec, spec.expected(), i + 1, ty.source_name()));
+        }
+    }
+    Ok(())
+}
+
+fn read_number(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<usize> {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+        digits.push(c);
+    }
+    digits.parse().ok()
+}
@@ -10,7 +10,12 @@ mod class_analysis;
 mod type_check;
 mod expr_inference;
 mod type_utils;
+mod closure;
+mod definite_assignment;
+mod format;
 
 // 公开导出
 pub use symbol_table::{SemanticSymbolTable, SemanticSymbolInfo};
 pub use analyzer::SemanticAnalyzer;
+pub use class_analysis::EntryPoint;
+pub use format::{FormatPiece, FormatSpec};
@@ -4,6 +4,7 @@ use std::collections::HashMap;
 use crate::types::Type;
 
 /// 语义分析符号表
+#[derive(Clone)]
 pub struct SemanticSymbolTable {
     scopes: Vec<HashMap<String, SemanticSymbolInfo>>,
 }
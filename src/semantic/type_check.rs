@@ -1,15 +1,89 @@
 //! 类型检查实现
 
 use crate::ast::*;
-use crate::types::{Type, ParameterInfo};
-use crate::error::{cayResult, semantic_error};
+use crate::types::{Type, ParameterInfo, MethodInfo};
+use crate::error::{cayResult, semantic_error, SourceLocation};
 use super::analyzer::SemanticAnalyzer;
 use super::symbol_table::SemanticSymbolInfo;
 
 impl SemanticAnalyzer {
     /// 类型检查程序
     pub fn type_check_program(&mut self, program: &Program) -> cayResult<()> {
+        if self.jobs > 1 && program.classes.len() > 1 {
+            return self.type_check_classes_parallel(program);
+        }
         for class in &program.classes {
+            self.type_check_class(class)?;
+        }
+        Ok(())
+    }
+
+    /// 在 `self.jobs` 个线程中并行检查各个类
+    ///
+    /// 每个类在分析器的一个副本上检查，之后按类的顺序合并收集到的错误和旁路表；
+    /// 遇到第一个中止检查的错误时返回它，与顺序检查的结果一致。
+    fn type_check_classes_parallel(&mut self, program: &Program) -> cayResult<()> {
+        let base = self.fork();
+        let results = crate::parallel::map_ordered(self.jobs, &program.classes, |_, class| {
+            let mut worker = base.clone();
+            let result = worker.type_check_class(class);
+            (worker, result)
+        });
+        for (worker, result) in results {
+            self.errors.extend(worker.errors);
+            self.inferred_var_types.extend(worker.inferred_var_types);
+            self.null_safe_types.extend(worker.null_safe_types);
+            self.ternary_types.extend(worker.ternary_types);
+            self.field_access_classes.extend(worker.field_access_classes);
+            self.string_comparisons.extend(worker.string_comparisons);
+            self.never_returns.extend(worker.never_returns);
+            self.print_arg_types.extend(worker.print_arg_types);
+            self.string_conversions.extend(worker.string_conversions);
+            self.lambda_captures.extend(worker.lambda_captures);
+            self.function_types.extend(worker.function_types);
+            self.method_refs.extend(worker.method_refs);
+            self.debug_prints.extend(worker.debug_prints);
+            self.format_calls.extend(worker.format_calls);
+            self.for_each_types.extend(worker.for_each_types);
+            self.call_overloads.extend(worker.call_overloads);
+            self.cast_types.extend(worker.cast_types);
+            self.array_element_types.extend(worker.array_element_types);
+            self.expr_types.extend(worker.expr_types);
+            result?;
+        }
+        Ok(())
+    }
+
+    /// 复制分析器用于检查一个类：保留类型注册表和符号表，清空错误和旁路表
+    fn fork(&self) -> Self {
+        let mut fork = self.clone();
+        fork.errors.clear();
+        fork.inferred_var_types.clear();
+        fork.null_safe_types.clear();
+        fork.ternary_types.clear();
+        fork.field_access_classes.clear();
+        fork.string_comparisons.clear();
+        fork.never_returns.clear();
+        fork.print_arg_types.clear();
+        fork.string_conversions.clear();
+        fork.lambda_captures.clear();
+        fork.function_types.clear();
+        fork.method_refs.clear();
+        fork.debug_prints.clear();
+        fork.format_calls.clear();
+        fork.for_each_types.clear();
+        fork.call_overloads.clear();
+        fork.cast_types.clear();
+        fork.array_element_types.clear();
+        fork.expr_types.clear();
+        fork
+    }
+
+    /// 类型检查一个类的所有成员
+    ///
+    /// 只读取类型注册表，结果写入错误列表和以 NodeId 为键的旁路表，因此不同的类可以并行检查。
+    pub(super) fn type_check_class(&mut self, class: &ClassDecl) -> cayResult<()> {
+        {
             self.current_class = Some(class.name.clone());
             
             for member in &class.members {
@@ -35,6 +109,8 @@ impl SemanticAnalyzer {
                             }
                         }
                         
+                        self.check_parameter_list(&method.params, &method.loc)?;
+
                         // 添加参数到符号表
                         for param in &method.params {
                             self.symbol_table.declare(
@@ -42,7 +118,7 @@ impl SemanticAnalyzer {
                                 SemanticSymbolInfo {
                                     name: param.name.clone(),
                                     symbol_type: param.param_type.clone(),
-                                    is_final: false,
+                                    is_final: param.is_final,
                                     is_initialized: true,
                                 }
                             );
@@ -77,6 +153,8 @@ impl SemanticAnalyzer {
                             }
                         );
                         
+                        self.check_parameter_list(&ctor.params, &ctor.loc)?;
+
                         // 添加参数到符号表
                         for param in &ctor.params {
                             self.symbol_table.declare(
@@ -84,12 +162,16 @@ impl SemanticAnalyzer {
                                 SemanticSymbolInfo {
                                     name: param.name.clone(),
                                     symbol_type: param.param_type.clone(),
-                                    is_final: false,
+                                    is_final: param.is_final,
                                     is_initialized: true,
                                 }
                             );
                         }
                         
+                        if let Some(call) = &ctor.constructor_call {
+                            self.check_constructor_call(&class.name, call, &ctor.loc)?;
+                        }
+
                         // 类型检查构造函数体
                         self.type_check_statement(&Stmt::Block(ctor.body.clone()), Some(&Type::Void))?;
                         
@@ -143,38 +225,123 @@ impl SemanticAnalyzer {
         Ok(())
     }
 
+    /// 检查构造链调用 this(args) / super(args)：实参必须匹配目标类的某个构造函数，
+    /// 没有声明构造函数的类只有无参的默认构造函数
+    fn check_constructor_call(&mut self, class_name: &str, call: &ConstructorCall, loc: &SourceLocation) -> cayResult<()> {
+        let (target, args) = match call {
+            ConstructorCall::This(args) => (class_name.to_string(), args),
+            ConstructorCall::Super(args) => match self.type_registry.get_class(class_name).and_then(|c| c.parent.clone()) {
+                Some(parent) => (parent, args),
+                None => return Err(semantic_error(
+                    loc.line,
+                    loc.column,
+                    format!("Class '{}' has no superclass; 'super(...)' cannot be called", class_name)
+                )),
+            },
+        };
+
+        let mut arg_types = Vec::with_capacity(args.len());
+        for arg in args {
+            arg_types.push(self.infer_expr_type(arg)?);
+        }
+        let constructors = self.type_registry.get_class(&target)
+            .map(|c| c.constructors.clone())
+            .unwrap_or_default();
+        let accepted = if constructors.is_empty() {
+            arg_types.is_empty()
+        } else {
+            constructors.iter().any(|ctor| ctor.params.len() == arg_types.len()
+                && ctor.params.iter().zip(&arg_types).all(|(param, arg)| self.types_compatible(arg, &param.param_type)))
+        };
+        if !accepted {
+            let types: Vec<String> = arg_types.iter().map(|t| t.to_string()).collect();
+            return Err(semantic_error(
+                loc.line,
+                loc.column,
+                format!("No constructor of {} accepts ({})", target, types.join(", "))
+            ));
+        }
+        Ok(())
+    }
+
     /// 类型检查语句
     pub fn type_check_statement(&mut self, stmt: &Stmt, expected_return: Option<&Type>) -> cayResult<()> {
         match stmt {
             Stmt::Expr(expr) => {
-                self.infer_expr_type(expr)?;
+                if self.infer_expr_type(expr)? == Type::Never
+                    && let Expr::Call(call) = expr
+                {
+                    self.never_returns.insert(call.id);
+                }
             }
             Stmt::VarDecl(var) => {
+                self.check_identifier_not_reserved(&var.name, "变量", &var.loc);
                 let mut var_type = var.var_type.clone();
                 
-                // 处理 auto 类型推断
-                if var_type == Type::Auto {
-                    if let Some(init) = &var.initializer {
-                        var_type = self.infer_expr_type(init)?;
-                    } else {
-                        self.errors.push(format!(
-                            "'auto' variable declaration requires an initializer at line {}",
-                            var.loc.line
+                // 推断初始化表达式类型；出错时记录错误但仍声明变量，避免后续语句连带报错
+                let init_type = match &var.initializer {
+                    // 空的初始化器没有元素可供推断，var/let/auto 必须显式声明类型
+                    Some(Expr::ArrayInit(init)) if var_type == Type::Auto && init.elements.is_empty() => {
+                        self.errors.push(semantic_error(
+                            var.loc.line, var.loc.column,
+                            format!("Cannot infer type of '{}' from an empty array initializer, please declare the type explicitly", var.name)
                         ));
-                        var_type = Type::Int32; // 默认回退类型
+                        None
                     }
-                }
+                    Some(init) => match self.infer_assigned_type(init, &var_type) {
+                        Ok(ty) => Some(ty),
+                        Err(e) => {
+                            self.errors.push(e.with_fallback_location(var.loc.line, var.loc.column));
+                            None
+                        }
+                    },
+                    None => None,
+                };
                 
-                if let Some(init) = &var.initializer {
-                    let init_type = self.infer_expr_type(init)?;
-                    if !self.types_compatible(&init_type, &var_type) {
-                        self.errors.push(format!(
-                            "Cannot assign {} to {} at line {}",
-                            init_type, var_type, var.loc.line
-                        ));
+                // 处理 auto/var/let 类型推断
+                if var_type == Type::Auto {
+                    match &init_type {
+                        Some(Type::Void) => {
+                            self.errors.push(semantic_error(
+                                var.loc.line, var.loc.column,
+                                format!("Cannot infer type of '{}' from a void expression", var.name)
+                            ));
+                            var_type = Type::Int32; // 默认回退类型
+                        }
+                        Some(_) if matches!(var.initializer, Some(Expr::Literal(LiteralValue::Null))) => {
+                            self.errors.push(semantic_error(
+                                var.loc.line, var.loc.column,
+                                format!("Cannot infer type of '{}' from null, please declare the type explicitly", var.name)
+                            ));
+                            var_type = Type::Int32;
+                        }
+                        Some(ty) => {
+                            var_type = ty.clone();
+                            self.inferred_var_types.insert(var.id, var_type.clone());
+                        }
+                        None => {
+                            if var.initializer.is_none() {
+                                self.errors.push(semantic_error(
+                                    var.loc.line, var.loc.column,
+                                    format!("Variable '{}' has no type annotation and requires an initializer for type inference", var.name)
+                                ));
+                            }
+                            var_type = Type::Int32; // 默认回退类型
+                        }
                     }
                 }
                 
+                // 推断出的类型与初始化器一致，只需检查显式声明的类型
+                if var.var_type != Type::Auto
+                    && let Some(init_type) = &init_type
+                    && !self.types_compatible(init_type, &var_type)
+                {
+                    self.errors.push(semantic_error(
+                        var.loc.line, var.loc.column,
+                        format!("Cannot assign {} to {}", init_type, var_type)
+                    ));
+                }
+                
                 self.symbol_table.declare(
                     var.name.clone(),
                     SemanticSymbolInfo {
@@ -185,32 +352,285 @@ impl SemanticAnalyzer {
                     }
                 );
             }
-            Stmt::Return(expr) => {
-                let return_type = if let Some(e) = expr {
-                    self.infer_expr_type(e)?
-                } else {
-                    Type::Void
+            Stmt::Return(ret) => {
+                let return_type = match (&ret.value, expected_return) {
+                    (Some(e), Some(expected)) => self.infer_assigned_type(e, expected)?,
+                    (Some(e), None) => self.infer_expr_type(e)?,
+                    (None, _) => Type::Void,
                 };
-                
-                if let Some(expected) = expected_return {
-                    if !self.types_compatible(&return_type, expected) {
-                        self.errors.push(format!(
-                            "Return type mismatch: expected {}, got {}",
-                            expected, return_type
-                        ));
-                    }
+                if let Some(returns) = &mut self.lambda_returns {
+                    returns.push(return_type.clone());
+                }
+
+                if let Some(expected) = expected_return
+                    && !self.types_compatible(&return_type, expected)
+                {
+                    // 字面量和标识符没有位置，退回到 return 语句本身
+                    let loc = ret.value.as_ref().and_then(|e| e.location()).unwrap_or(&ret.loc);
+                    let (line, column) = (loc.line, loc.column);
+                    self.errors.push(semantic_error(
+                        line, column,
+                        format!("Return type mismatch: expected {}, got {}", expected, return_type)
+                    ));
                 }
             }
             Stmt::Block(block) => {
                 self.symbol_table.enter_scope();
                 for stmt in &block.statements {
-                    self.type_check_statement(stmt, expected_return)?;
+                    // 在语句级别恢复：记录错误后继续检查后续语句
+                    if let Err(e) = self.type_check_statement(stmt, expected_return) {
+                        let (line, column) = stmt_location(stmt).map_or((0, 0), |loc| (loc.line, loc.column));
+                        self.errors.push(e.with_fallback_location(line, column));
+                    }
+                }
+                self.symbol_table.exit_scope();
+            }
+            Stmt::If(if_stmt) => {
+                self.check_condition(&if_stmt.condition, "if statement", &if_stmt.loc)?;
+                self.type_check_nested(&if_stmt.then_branch, expected_return);
+                if let Some(else_branch) = &if_stmt.else_branch {
+                    self.type_check_nested(else_branch, expected_return);
                 }
+            }
+            Stmt::While(while_stmt) => {
+                self.check_condition(&while_stmt.condition, "while loop", &while_stmt.loc)?;
+                self.jump_targets.push(JumpTarget::Loop);
+                self.type_check_nested(&while_stmt.body, expected_return);
+                self.jump_targets.pop();
+            }
+            Stmt::DoWhile(do_while) => {
+                self.jump_targets.push(JumpTarget::Loop);
+                self.type_check_nested(&do_while.body, expected_return);
+                self.jump_targets.pop();
+                self.check_condition(&do_while.condition, "do-while loop", &do_while.loc)?;
+            }
+            Stmt::For(for_stmt) => {
+                // for 的初始化变量只在循环内可见
+                self.symbol_table.enter_scope();
+                self.jump_targets.push(JumpTarget::Loop);
+                let result = self.type_check_for(for_stmt, expected_return);
+                self.jump_targets.pop();
                 self.symbol_table.exit_scope();
+                result?;
             }
-            _ => {}
+            Stmt::ForEach(for_each) => {
+                // 循环变量只在循环体内可见
+                self.symbol_table.enter_scope();
+                self.jump_targets.push(JumpTarget::Loop);
+                let result = self.type_check_for_each(for_each, expected_return);
+                self.jump_targets.pop();
+                self.symbol_table.exit_scope();
+                result?;
+            }
+            Stmt::Switch(switch) => {
+                let scrutinee_type = self.infer_expr_type(&switch.expr)?;
+                self.check_switch_cases(switch, &scrutinee_type)?;
+                self.jump_targets.push(JumpTarget::Switch);
+                let bodies = switch.cases.iter().map(|c| &c.body).chain(switch.default.as_ref());
+                for body in bodies {
+                    self.symbol_table.enter_scope();
+                    for stmt in body {
+                        self.type_check_nested(stmt, expected_return);
+                    }
+                    self.symbol_table.exit_scope();
+                }
+                self.jump_targets.pop();
+            }
+            Stmt::Labeled(labeled) => {
+                let shadowed = self.jump_targets.iter()
+                    .any(|target| matches!(target, JumpTarget::Label { name, .. } if *name == labeled.label));
+                if shadowed {
+                    return Err(semantic_error(
+                        labeled.loc.line, labeled.loc.column,
+                        format!("Label '{}' is already defined in an enclosing statement", labeled.label)
+                    ));
+                }
+                let is_loop = matches!(*labeled.body, Stmt::While(_) | Stmt::DoWhile(_) | Stmt::For(_) | Stmt::ForEach(_));
+                self.jump_targets.push(JumpTarget::Label { name: labeled.label.clone(), is_loop });
+                self.type_check_nested(&labeled.body, expected_return);
+                self.jump_targets.pop();
+            }
+            Stmt::Break(jump) => self.check_jump_target(jump, false)?,
+            Stmt::Continue(jump) => self.check_jump_target(jump, true)?,
         }
         
         Ok(())
     }
+
+    /// 类型检查控制流语句的子语句，错误记录后继续检查
+    fn type_check_nested(&mut self, stmt: &Stmt, expected_return: Option<&Type>) {
+        if let Err(e) = self.type_check_statement(stmt, expected_return) {
+            let (line, column) = stmt_location(stmt).map_or((0, 0), |loc| (loc.line, loc.column));
+            self.errors.push(e.with_fallback_location(line, column));
+        }
+    }
+
+    /// 检查参数列表：可变参数只能有一个且必须是最后一个参数
+    fn check_parameter_list(&self, params: &[ParameterInfo], loc: &SourceLocation) -> cayResult<()> {
+        let last = params.len().saturating_sub(1);
+        match params.iter().enumerate().find(|(idx, p)| p.is_varargs && *idx != last) {
+            Some((_, param)) => Err(semantic_error(
+                loc.line, loc.column,
+                format!("Varargs parameter '{}' must be the last parameter", param.name)
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// 检查 case 标签：值必须能用 switch 表达式的类型表示，且不能重复
+    fn check_switch_cases(&self, switch: &SwitchStmt, scrutinee_type: &Type) -> cayResult<()> {
+        let mut seen = std::collections::HashSet::new();
+        for case in &switch.cases {
+            if *scrutinee_type == Type::Int32 && i32::try_from(case.value).is_err() {
+                return Err(semantic_error(
+                    switch.loc.line, switch.loc.column,
+                    format!("case value {} is out of range for switch on int", case.value)
+                ));
+            }
+            if !seen.insert(case.value) {
+                return Err(semantic_error(
+                    switch.loc.line, switch.loc.column,
+                    format!("duplicate case label: {}", case.value)
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// 检查 if 和循环的条件是 boolean 类型
+    ///
+    /// 整数不会隐式转换为 boolean，`while (1)` 需要写成 `while (true)`。
+    fn check_condition(&mut self, condition: &Expr, statement: &str, loc: &SourceLocation) -> cayResult<()> {
+        let cond_type = self.infer_expr_type(condition)?;
+        if cond_type != Type::Bool {
+            let loc = condition.location().unwrap_or(loc);
+            return Err(semantic_error(
+                loc.line, loc.column,
+                format!("Condition of {} must be boolean, got {}", statement, cond_type.source_name())
+            ));
+        }
+        Ok(())
+    }
+
+    /// 检查 break/continue 的跳转目标
+    ///
+    /// 不带标签的 break 跳出最内层的循环或 switch，continue 只能用于循环；
+    /// 带标签时目标是外层同名的标签语句，continue 的标签必须标记循环。
+    fn check_jump_target(&self, jump: &JumpStmt, is_continue: bool) -> cayResult<()> {
+        let keyword = if is_continue { "continue" } else { "break" };
+        let error = |message: String| Err(semantic_error(jump.loc.line, jump.loc.column, message));
+        match &jump.label {
+            Some(label) => {
+                let target = self.jump_targets.iter().rev().find_map(|target| match target {
+                    JumpTarget::Label { name, is_loop } if name == label => Some(*is_loop),
+                    _ => None,
+                });
+                match target {
+                    None => error(format!("Undefined label '{}' in {} statement", label, keyword)),
+                    Some(false) if is_continue => error(format!("continue target '{}' is not a loop", label)),
+                    Some(_) => Ok(()),
+                }
+            }
+            None => {
+                let found = self.jump_targets.iter().any(|target| match target {
+                    JumpTarget::Loop => true,
+                    JumpTarget::Switch => !is_continue,
+                    JumpTarget::Label { .. } => false,
+                });
+                match found {
+                    true => Ok(()),
+                    false if is_continue => error("continue statement outside of loop".to_string()),
+                    false => error("break statement outside of loop or switch".to_string()),
+                }
+            }
+        }
+    }
+
+    fn type_check_for(&mut self, for_stmt: &ForStmt, expected_return: Option<&Type>) -> cayResult<()> {
+        if let Some(init) = &for_stmt.init {
+            self.type_check_statement(init, expected_return)?;
+        }
+        if let Some(condition) = &for_stmt.condition {
+            self.check_condition(condition, "for loop", &for_stmt.loc)?;
+        }
+        if let Some(update) = &for_stmt.update {
+            self.infer_expr_type(update)?;
+        }
+        self.type_check_nested(&for_stmt.body, expected_return);
+        Ok(())
+    }
+
+    fn type_check_for_each(&mut self, for_each: &ForEachStmt, expected_return: Option<&Type>) -> cayResult<()> {
+        self.check_identifier_not_reserved(&for_each.var_name, "变量", &for_each.loc);
+        let iterable_type = self.infer_expr_type(&for_each.iterable)?;
+        let element_type = self.element_type_of(&iterable_type).ok_or_else(|| semantic_error(
+            for_each.loc.line, for_each.loc.column,
+            format!("Cannot iterate over {}: expected an array or an object with size() and get(int) methods", iterable_type)
+        ))?;
+        let var_type = if for_each.var_type == Type::Auto {
+            element_type
+        } else {
+            if !self.types_compatible(&element_type, &for_each.var_type) {
+                return Err(semantic_error(
+                    for_each.loc.line, for_each.loc.column,
+                    format!("Cannot assign {} to {}", element_type, for_each.var_type)
+                ));
+            }
+            for_each.var_type.clone()
+        };
+        self.for_each_types.insert(for_each.id, (iterable_type, var_type.clone()));
+        self.symbol_table.declare(
+            for_each.var_name.clone(),
+            SemanticSymbolInfo {
+                name: for_each.var_name.clone(),
+                symbol_type: var_type,
+                is_final: for_each.is_final,
+                is_initialized: true,
+            }
+        );
+        self.type_check_nested(&for_each.body, expected_return);
+        Ok(())
+    }
+
+    /// for-each 可以遍历的类型的元素类型：数组的元素，或对象的 `int size()` 和 `get(int)` 的返回类型
+    fn element_type_of(&self, iterable: &Type) -> Option<Type> {
+        match iterable {
+            Type::Array(elem) => Some((**elem).clone()),
+            Type::Object(class) => {
+                let instance = |m: &&MethodInfo| !m.is_static;
+                let size = self.type_registry.find_method(class, "size", &[]).filter(instance)?;
+                let get = self.type_registry.find_method(class, "get", &[Type::Int32]).filter(instance)?;
+                (size.return_type == Type::Int32 && !matches!(get.return_type, Type::Void | Type::Never))
+                    .then(|| get.return_type.clone())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// break/continue 可以跳转到的外层语句
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum JumpTarget {
+    Loop,
+    Switch,
+    /// 带标签的语句，`is_loop` 表示标记的是循环（可以作为 continue 的目标）
+    Label { name: String, is_loop: bool },
+}
+
+/// 语句的位置（用于给不带位置的错误补充行号）
+pub(super) fn stmt_location(stmt: &Stmt) -> Option<&SourceLocation> {
+    match stmt {
+        Stmt::VarDecl(var) => Some(&var.loc),
+        Stmt::Expr(expr) => expr.location(),
+        Stmt::Return(ret) => Some(&ret.loc),
+        Stmt::If(if_stmt) => Some(&if_stmt.loc),
+        Stmt::While(while_stmt) => Some(&while_stmt.loc),
+        Stmt::For(for_stmt) => Some(&for_stmt.loc),
+        Stmt::ForEach(for_each) => Some(&for_each.loc),
+        Stmt::DoWhile(do_while) => Some(&do_while.loc),
+        Stmt::Switch(switch) => Some(&switch.loc),
+        Stmt::Block(block) => Some(&block.loc),
+        Stmt::Labeled(labeled) => Some(&labeled.loc),
+        Stmt::Break(jump) | Stmt::Continue(jump) => Some(&jump.loc),
+    }
 }
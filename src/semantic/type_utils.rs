@@ -22,6 +22,9 @@ impl SemanticAnalyzer {
 
         // 基本类型之间的兼容
         match (from, to) {
+            // byte/short 按 byte → short → int 的顺序拓宽
+            (Type::Int8, Type::Int16 | Type::Int32 | Type::Int64 | Type::Float32 | Type::Float64) => true,
+            (Type::Int16, Type::Int32 | Type::Int64 | Type::Float32 | Type::Float64) => true,
             (Type::Int32, Type::Int64) => true,
             (Type::Int32, Type::Float32) => true,
             (Type::Int32, Type::Float64) => true,
@@ -32,9 +35,14 @@ impl SemanticAnalyzer {
             // char 可以赋值给 int (ASCII 码值)
             (Type::Char, Type::Int32) => true,
             (Type::Char, Type::Int64) => true,
-            // 数组类型：检查元素类型兼容性
+            // 数组类型：基本类型元素的存储宽度不同，必须完全一致（int[] 不能当作 long[]）；
+            // 引用类型元素都是指针，允许协变
             (Type::Array(from_elem), Type::Array(to_elem)) => {
-                self.types_compatible(from_elem, to_elem)
+                if from_elem.is_primitive() || to_elem.is_primitive() {
+                    from_elem == to_elem
+                } else {
+                    self.types_compatible(from_elem, to_elem)
+                }
             }
             _ => false,
         }
@@ -46,17 +54,15 @@ impl SemanticAnalyzer {
             (Type::Float64, _) | (_, Type::Float64) => Type::Float64,
             (Type::Float32, _) | (_, Type::Float32) => Type::Float32,
             (Type::Int64, _) | (_, Type::Int64) => Type::Int64,
-            // char 类型在算术运算中提升为 int32
-            (Type::Char, Type::Char) => Type::Int32,
-            (Type::Char, Type::Int32) | (Type::Int32, Type::Char) => Type::Int32,
-            (Type::Int32, Type::Int32) => Type::Int32,
+            // char/byte/short 在算术运算中提升为 int32
+            (l, r) if (l.is_integer() || *l == Type::Char) && (r.is_integer() || *r == Type::Char) => Type::Int32,
             _ => left.clone(),
         }
     }
 
     /// 检查类型是否为数值类型
     pub fn is_numeric_type(ty: &Type) -> bool {
-        matches!(ty, Type::Int32 | Type::Int64 | Type::Float32 | Type::Float64 | Type::Char)
+        matches!(ty, Type::Int8 | Type::Int16 | Type::Int32 | Type::Int64 | Type::Float32 | Type::Float64 | Type::Char)
     }
 
     /// 整数类型提升
@@ -87,7 +93,7 @@ impl SemanticAnalyzer {
 
             // 检查固定参数
             for i in 0..last_idx {
-                let arg_type = self.infer_expr_type(&args[i]).map_err(|e| e.to_string())?;
+                let arg_type = self.infer_expr_type_expecting(&args[i], &params[i].param_type).map_err(|e| e.to_string())?;
                 if !self.types_compatible(&arg_type, &params[i].param_type) {
                     return Err(format!("Argument {} type mismatch: expected {}, got {}",
                         i + 1, params[i].param_type, arg_type));
@@ -102,6 +108,10 @@ impl SemanticAnalyzer {
             };
             for i in last_idx..args.len() {
                 let arg_type = self.infer_expr_type(&args[i]).map_err(|e| e.to_string())?;
+                // 直接传入同类型数组
+                if args.len() == params.len() && arg_type == params[last_idx].param_type {
+                    continue;
+                }
                 if !self.types_compatible(&arg_type, vararg_element_type) {
                     return Err(format!("Varargs argument {} type mismatch: expected {}, got {}",
                         i + 1, vararg_element_type, arg_type));
@@ -114,7 +124,7 @@ impl SemanticAnalyzer {
             }
 
             for (i, (arg, param)) in args.iter().zip(params.iter()).enumerate() {
-                let arg_type = self.infer_expr_type(arg).map_err(|e| e.to_string())?;
+                let arg_type = self.infer_expr_type_expecting(arg, &param.param_type).map_err(|e| e.to_string())?;
                 if !self.types_compatible(&arg_type, &param.param_type) {
                     return Err(format!("Argument {} type mismatch: expected {}, got {}",
                         i + 1, param.param_type, arg_type));
@@ -181,6 +191,26 @@ impl SemanticAnalyzer {
                 }
                 Ok(Type::String)
             }
+            "toUpperCase" | "toLowerCase" | "trim" => {
+                if !args.is_empty() {
+                    return Err(semantic_error(line, column, format!("String.{}() takes no arguments", method_name)));
+                }
+                Ok(Type::String)
+            }
+            "startsWith" | "endsWith" | "contains" | "split" => {
+                if args.len() != 1 {
+                    return Err(semantic_error(line, column, format!("String.{}() takes 1 argument", method_name)));
+                }
+                let arg_type = self.infer_expr_type(&args[0])?;
+                if arg_type != Type::String {
+                    return Err(semantic_error(line, column, format!("Argument of {}() must be string, got {}", method_name, arg_type)));
+                }
+                if method_name == "split" {
+                    Ok(Type::Array(Box::new(Type::String)))
+                } else {
+                    Ok(Type::Bool)
+                }
+            }
             _ => Err(semantic_error(line, column, format!("Unknown String method '{}'", method_name))),
         }
     }
@@ -0,0 +1,146 @@
+//! 源文件管理
+//!
+//! 负责源文件的读取、编码检测和规范化：
+//! - 识别 UTF-8 / UTF-16 (LE/BE) 的 BOM，去除 BOM 并统一解码为 UTF-8
+//! - 非法 UTF-8 输入报告出错的行列位置，而不是笼统的 I/O 错误
+//! - 将 `\r\n` 和单独的 `\r` 统一为 `\n`，保证词法分析的行号与编辑器一致
+//! - 缓存每行起始偏移（`LineIndex`），诊断信息可以快速把字节偏移换算为行列号
+
+use std::borrow::Cow;
+use std::path::Path;
+use crate::error::{cayError, cayResult, lexer_error, SourceLocation};
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16_LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16_BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// 规范化源代码文本：去除开头的 BOM，统一换行符为 `\n`
+///
+/// 输入已经是规范形式时不会复制。
+pub fn normalize_source(text: &str) -> Cow<'_, str> {
+    let text = text.strip_prefix('\u{FEFF}').unwrap_or(text);
+    if !text.contains('\r') {
+        return Cow::Borrowed(text);
+    }
+    Cow::Owned(text.replace("\r\n", "\n").replace('\r', "\n"))
+}
+
+/// 检测编码并把源文件字节解码为规范化的 UTF-8 文本
+pub fn decode_source(bytes: &[u8], file_path: &str) -> cayResult<String> {
+    if let Some(rest) = bytes.strip_prefix(UTF16_LE_BOM) {
+        return decode_utf16(rest, file_path, u16::from_le_bytes);
+    }
+    if let Some(rest) = bytes.strip_prefix(UTF16_BE_BOM) {
+        return decode_utf16(rest, file_path, u16::from_be_bytes);
+    }
+
+    let bytes = bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes);
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Ok(normalize_source(text).into_owned()),
+        Err(e) => {
+            // 定位到非法字节所在的行列
+            let valid = String::from_utf8_lossy(&bytes[..e.valid_up_to()]);
+            let valid = normalize_source(&valid);
+            let index = LineIndex::new(&valid);
+            let loc = index.location(&valid, valid.len());
+            Err(lexer_error(
+                loc.line,
+                loc.column,
+                format!(
+                    "源文件 '{}' 不是有效的 UTF-8 编码 (第 {} 字节)，请将文件另存为 UTF-8",
+                    file_path,
+                    e.valid_up_to()
+                ),
+            ))
+        }
+    }
+}
+
+fn decode_utf16(bytes: &[u8], file_path: &str, to_u16: fn([u8; 2]) -> u16) -> cayResult<String> {
+    if !bytes.len().is_multiple_of(2) {
+        return Err(cayError::Io(format!("源文件 '{}' 不是完整的 UTF-16 编码", file_path)));
+    }
+    let units: Vec<u16> = bytes.chunks_exact(2).map(|c| to_u16([c[0], c[1]])).collect();
+    let text = String::from_utf16(&units)
+        .map_err(|_| cayError::Io(format!("源文件 '{}' 包含无效的 UTF-16 字符", file_path)))?;
+    Ok(normalize_source(&text).into_owned())
+}
+
+/// 读取源文件，自动检测编码并规范化
+pub fn read_source_file(path: impl AsRef<Path>) -> cayResult<String> {
+    let path = path.as_ref();
+    let bytes = std::fs::read(path)
+        .map_err(|e| cayError::Io(format!("无法读取源文件 '{}': {}", path.display(), e)))?;
+    decode_source(&bytes, &path.to_string_lossy())
+}
+
+/// 行起始偏移表，用于把字节偏移换算为行列号
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+        Self { line_starts }
+    }
+
+    /// 行数
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// 字节偏移对应的位置（行列号均从 1 开始，列号按字符计算）
+    pub fn location(&self, text: &str, offset: usize) -> SourceLocation {
+        let line_idx = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let line_start = self.line_starts[line_idx];
+        let column = text
+            .get(line_start..offset)
+            .map(|s| s.chars().count())
+            .unwrap_or(offset - line_start);
+        SourceLocation {
+            line: line_idx + 1,
+            column: column + 1,
+        }
+    }
+
+    /// 第 `line` 行（从 1 开始）的字节范围，不含换行符
+    pub fn line_range(&self, text: &str, line: usize) -> Option<std::ops::Range<usize>> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let end = self.line_starts.get(line).map(|&next| next - 1).unwrap_or(text.len());
+        Some(start..end)
+    }
+}
+
+/// 已规范化的源文件及其行索引
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub name: String,
+    pub text: String,
+    index: LineIndex,
+}
+
+impl SourceFile {
+    pub fn new(name: impl Into<String>, text: &str) -> Self {
+        let text = normalize_source(text).into_owned();
+        let index = LineIndex::new(&text);
+        Self { name: name.into(), text, index }
+    }
+
+    /// 字节偏移对应的位置
+    pub fn location(&self, offset: usize) -> SourceLocation {
+        self.index.location(&self.text, offset)
+    }
+
+    /// 第 `line` 行的文本（从 1 开始）
+    pub fn line_text(&self, line: usize) -> Option<&str> {
+        self.index.line_range(&self.text, line).map(|r| &self.text[r])
+    }
+
+    /// 行数
+    pub fn line_count(&self) -> usize {
+        self.index.line_count()
+    }
+}
@@ -0,0 +1,359 @@
+//! 静态栈使用估算
+//!
+//! 分析生成的 LLVM IR：按每个函数中的 `alloca` 估算栈帧大小，
+//! 再沿调用图计算每个函数在非递归路径上的最坏栈深度，并标出递归调用环。
+//! 与运行时的 `--stack-guard` 互补：静态估算给出无递归部分的上界，
+//! 递归部分的实际深度取决于输入，只能报告为递归。
+//!
+//! 估算是近似值：未计入寄存器溢出和优化器引入的临时空间，
+//! 每个栈帧额外按返回地址和帧指针计 16 字节，帧大小按 16 字节对齐。
+
+use std::collections::{HashMap, HashSet};
+
+/// 每个栈帧的固定开销（返回地址 + 保存的帧指针）
+const FRAME_OVERHEAD: u64 = 16;
+
+/// 单个函数的栈使用估算
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionStack {
+    /// 函数符号名
+    pub name: String,
+    /// 本函数栈帧大小（字节）
+    pub frame_size: u64,
+    /// 非递归路径上的最坏栈深度（字节，包含本函数栈帧）
+    pub worst_case: u64,
+    /// 取得最坏栈深度的调用路径（从本函数开始）
+    pub worst_path: Vec<String>,
+    /// 本函数处于递归环中，或可能调用到递归函数（实际栈深度无上界）
+    pub reaches_recursion: bool,
+}
+
+/// 整个模块的栈使用报告
+#[derive(Debug, Clone, Default)]
+pub struct StackReport {
+    /// 各函数的估算结果，按最坏栈深度从大到小排序
+    pub functions: Vec<FunctionStack>,
+    /// 递归调用环（每个环内的函数按名称排序）
+    pub recursive_cycles: Vec<Vec<String>>,
+}
+
+impl StackReport {
+    /// 查找指定函数的估算结果
+    pub fn function(&self, name: &str) -> Option<&FunctionStack> {
+        self.functions.iter().find(|f| f.name == name)
+    }
+
+    /// 格式化为文本报告
+    pub fn format_report(&self) -> String {
+        let name_width = self.functions.iter()
+            .map(|f| f.name.len())
+            .max()
+            .unwrap_or(0)
+            .max(8);
+
+        let mut out = String::new();
+        out.push_str(&format!("  {:<width$}  {:>10}  {:>12}\n", "函数", "栈帧", "最坏栈深度", width = name_width));
+        for func in &self.functions {
+            let marker = if func.reaches_recursion { "  (递归，实际深度无上界)" } else { "" };
+            out.push_str(&format!("  {:<width$}  {:>8} B  {:>10} B{}\n",
+                func.name, func.frame_size, func.worst_case, marker, width = name_width));
+            if func.worst_path.len() > 1 {
+                out.push_str(&format!("  {:<width$}    路径: {}\n", "", func.worst_path.join(" -> "), width = name_width));
+            }
+        }
+
+        if self.recursive_cycles.is_empty() {
+            out.push_str("\n  未发现递归调用\n");
+        } else {
+            out.push_str("\n  递归调用环:\n");
+            for cycle in &self.recursive_cycles {
+                let mut shown = cycle.clone();
+                shown.push(cycle[0].clone());
+                out.push_str(&format!("    {}\n", shown.join(" -> ")));
+            }
+        }
+        out
+    }
+}
+
+/// IR 中定义的函数：栈帧大小和调用到的函数
+struct FunctionInfo {
+    frame_size: u64,
+    callees: Vec<String>,
+}
+
+/// 分析 LLVM IR 文本，估算栈使用
+pub fn analyze_ir(ir: &str) -> StackReport {
+    let struct_types = parse_struct_types(ir);
+    let functions = parse_functions(ir, &struct_types);
+
+    let mut names: Vec<&String> = functions.keys().collect();
+    names.sort();
+
+    // 调用图中只保留模块内定义的函数（外部 C 库函数的栈使用无法估算）
+    let graph: HashMap<&str, Vec<&str>> = names.iter()
+        .map(|name| {
+            let callees = functions[*name].callees.iter()
+                .filter(|c| functions.contains_key(*c))
+                .map(|c| c.as_str())
+                .collect();
+            (name.as_str(), callees)
+        })
+        .collect();
+
+    let sccs = strongly_connected_components(&names.iter().map(|n| n.as_str()).collect::<Vec<_>>(), &graph);
+    let mut scc_of: HashMap<&str, usize> = HashMap::new();
+    for (idx, scc) in sccs.iter().enumerate() {
+        for name in scc {
+            scc_of.insert(name, idx);
+        }
+    }
+    let is_recursive: Vec<bool> = sccs.iter()
+        .map(|scc| scc.len() > 1 || graph[scc[0]].contains(&scc[0]))
+        .collect();
+
+    let mut recursive_cycles: Vec<Vec<String>> = sccs.iter()
+        .zip(&is_recursive)
+        .filter(|(_, recursive)| **recursive)
+        .map(|(scc, _)| {
+            let mut cycle: Vec<String> = scc.iter().map(|s| s.to_string()).collect();
+            cycle.sort();
+            cycle
+        })
+        .collect();
+    recursive_cycles.sort();
+
+    // Tarjan 算法按逆拓扑序输出强连通分量，被调用者总是先于调用者完成计算
+    let mut results: HashMap<&str, FunctionStack> = HashMap::new();
+    for (idx, scc) in sccs.iter().enumerate() {
+        for name in scc {
+            let frame_size = functions[*name].frame_size;
+            let mut best: Option<&FunctionStack> = None;
+            let mut reaches_recursion = is_recursive[idx];
+            for callee in &graph[name] {
+                // 同一递归环内的调用不计入（递归深度无法静态确定）
+                if scc_of[callee] == idx {
+                    continue;
+                }
+                let callee_result = &results[callee];
+                reaches_recursion |= callee_result.reaches_recursion;
+                if best.is_none_or(|b| callee_result.worst_case > b.worst_case) {
+                    best = Some(callee_result);
+                }
+            }
+
+            let mut worst_path = vec![name.to_string()];
+            let mut worst_case = frame_size;
+            if let Some(best) = best {
+                worst_case += best.worst_case;
+                worst_path.extend(best.worst_path.iter().cloned());
+            }
+            let result = FunctionStack {
+                name: name.to_string(),
+                frame_size,
+                worst_case,
+                worst_path,
+                reaches_recursion,
+            };
+            results.insert(name, result);
+        }
+    }
+
+    let mut functions: Vec<FunctionStack> = results.into_values().collect();
+    functions.sort_by(|a, b| b.worst_case.cmp(&a.worst_case).then_with(|| a.name.cmp(&b.name)));
+
+    StackReport { functions, recursive_cycles }
+}
+
+/// 解析具名结构体类型定义: `%class.Foo = type { i32, i64 }`
+fn parse_struct_types(ir: &str) -> HashMap<String, String> {
+    ir.lines()
+        .filter_map(|line| {
+            let (name, body) = line.split_once(" = type ")?;
+            let name = name.trim();
+            name.starts_with('%').then(|| (name.to_string(), body.trim().to_string()))
+        })
+        .collect()
+}
+
+/// 解析所有函数定义的栈帧大小和调用关系
+fn parse_functions(ir: &str, struct_types: &HashMap<String, String>) -> HashMap<String, FunctionInfo> {
+    let mut functions = HashMap::new();
+    let mut current: Option<(String, FunctionInfo)> = None;
+
+    for line in ir.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("define ") {
+            if let Some(name) = symbol_after_at(trimmed) {
+                current = Some((name, FunctionInfo { frame_size: 0, callees: Vec::new() }));
+            }
+            continue;
+        }
+        if trimmed == "}" {
+            if let Some((name, mut info)) = current.take() {
+                info.frame_size = (info.frame_size + FRAME_OVERHEAD).div_ceil(16) * 16;
+                functions.insert(name, info);
+            }
+            continue;
+        }
+        let Some((_, info)) = current.as_mut() else {
+            continue;
+        };
+
+        if let Some(rest) = trimmed.split_once("= alloca ").map(|(_, r)| r) {
+            let (ty, count) = split_alloca_operands(rest);
+            let (size, align) = type_size_align(ty, struct_types);
+            info.frame_size = info.frame_size.div_ceil(align) * align + size * count;
+        }
+
+        let mut search = trimmed;
+        while let Some(pos) = search.find("call ") {
+            search = &search[pos + 5..];
+            if let Some(callee) = symbol_after_at(search)
+                && !callee.starts_with("llvm.")
+                && !info.callees.contains(&callee)
+            {
+                info.callees.push(callee);
+            }
+        }
+    }
+
+    functions
+}
+
+/// 取出第一个 `@name(` 中的符号名
+fn symbol_after_at(text: &str) -> Option<String> {
+    let start = text.find('@')? + 1;
+    let rest = &text[start..];
+    let end = rest.find('(')?;
+    let name = rest[..end].trim_matches('"');
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// 拆分 alloca 的类型和元素个数: `[16 x i8], align 8` / `i32, i32 4, align 4`
+fn split_alloca_operands(operands: &str) -> (&str, u64) {
+    let mut depth = 0;
+    let mut parts = Vec::new();
+    let mut start = 0;
+    for (i, c) in operands.char_indices() {
+        match c {
+            '[' | '{' | '<' => depth += 1,
+            ']' | '}' | '>' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(operands[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(operands[start..].trim());
+
+    let ty = parts[0];
+    let count = parts.get(1)
+        .filter(|p| !p.starts_with("align"))
+        .and_then(|p| p.split_whitespace().nth(1))
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(1);
+    (ty, count)
+}
+
+/// 计算 LLVM 类型的大小和对齐（x86_64）
+fn type_size_align(ty: &str, struct_types: &HashMap<String, String>) -> (u64, u64) {
+    let ty = ty.trim();
+    if ty.ends_with('*') || ty == "ptr" {
+        return (8, 8);
+    }
+    match ty {
+        "float" => return (4, 4),
+        "double" => return (8, 8),
+        _ => {}
+    }
+    if let Some(bits) = ty.strip_prefix('i').and_then(|b| b.parse::<u64>().ok()) {
+        let size = bits.div_ceil(8).next_power_of_two();
+        return (size, size.min(8));
+    }
+    if let Some(inner) = ty.strip_prefix('[').and_then(|t| t.strip_suffix(']'))
+        && let Some((count, elem)) = inner.split_once(" x ")
+    {
+        let count: u64 = count.trim().parse().unwrap_or(0);
+        let (size, align) = type_size_align(elem, struct_types);
+        return (size * count, align);
+    }
+    if let Some(body) = struct_types.get(ty) {
+        return type_size_align(body, struct_types);
+    }
+    if let Some(inner) = ty.strip_prefix('{').and_then(|t| t.strip_suffix('}')) {
+        let mut offset: u64 = 0;
+        let mut max_align: u64 = 1;
+        for member in inner.split(',').map(str::trim).filter(|m| !m.is_empty()) {
+            let (size, align) = type_size_align(member, struct_types);
+            offset = offset.div_ceil(align) * align + size;
+            max_align = max_align.max(align);
+        }
+        return (offset.div_ceil(max_align) * max_align, max_align);
+    }
+    // 未知类型按指针大小估算
+    (8, 8)
+}
+
+/// Tarjan 强连通分量算法，按逆拓扑序返回各分量
+fn strongly_connected_components<'a>(nodes: &[&'a str], graph: &HashMap<&'a str, Vec<&'a str>>) -> Vec<Vec<&'a str>> {
+    struct Tarjan<'a, 'g> {
+        graph: &'g HashMap<&'a str, Vec<&'a str>>,
+        index: HashMap<&'a str, usize>,
+        lowlink: HashMap<&'a str, usize>,
+        stack: Vec<&'a str>,
+        on_stack: HashSet<&'a str>,
+        counter: usize,
+        result: Vec<Vec<&'a str>>,
+    }
+
+    impl<'a> Tarjan<'a, '_> {
+        fn visit(&mut self, node: &'a str) {
+            self.index.insert(node, self.counter);
+            self.lowlink.insert(node, self.counter);
+            self.counter += 1;
+            self.stack.push(node);
+            self.on_stack.insert(node);
+
+            for &next in &self.graph[node] {
+                if !self.index.contains_key(next) {
+                    self.visit(next);
+                    let low = self.lowlink[node].min(self.lowlink[next]);
+                    self.lowlink.insert(node, low);
+                } else if self.on_stack.contains(next) {
+                    let low = self.lowlink[node].min(self.index[next]);
+                    self.lowlink.insert(node, low);
+                }
+            }
+
+            if self.lowlink[node] == self.index[node] {
+                let mut component = Vec::new();
+                while let Some(top) = self.stack.pop() {
+                    self.on_stack.remove(top);
+                    component.push(top);
+                    if top == node {
+                        break;
+                    }
+                }
+                self.result.push(component);
+            }
+        }
+    }
+
+    let mut tarjan = Tarjan {
+        graph,
+        index: HashMap::new(),
+        lowlink: HashMap::new(),
+        stack: Vec::new(),
+        on_stack: HashSet::new(),
+        counter: 0,
+        result: Vec::new(),
+    };
+    for &node in nodes {
+        if !tarjan.index.contains_key(node) {
+            tarjan.visit(node);
+        }
+    }
+    tarjan.result
+}
@@ -0,0 +1,173 @@
+//! 目标数据布局
+//!
+//! 类型的大小、对齐和指针宽度统一从目标三元组对应的 LLVM datalayout 字符串解析得到，
+//! alloca/load/store 的对齐、数组元素大小和类实例布局都通过这里查询，
+//! 避免在各处硬编码 64 位平台的数值（例如 32 位 ARM 上 i64 对齐为 8，而 i386 上为 4）。
+
+/// 目标三元组对应的 LLVM datalayout 字符串
+pub fn datalayout_for_triple(triple: &str) -> &'static str {
+    let arch = triple.split('-').next().unwrap_or("");
+    match arch {
+        "x86_64" => {
+            if triple.contains("windows") || triple.contains("mingw") {
+                "e-m:w-p270:32:32-p271:32:32-p272:64:64-i64:64-f80:128-n8:16:32:64-S128"
+            } else if triple.contains("apple") || triple.contains("darwin") {
+                "e-m:o-p270:32:32-p271:32:32-p272:64:64-i64:64-f80:128-n8:16:32:64-S128"
+            } else {
+                "e-m:e-p270:32:32-p271:32:32-p272:64:64-i64:64-f80:128-n8:16:32:64-S128"
+            }
+        }
+        "aarch64" | "arm64" => {
+            if triple.contains("apple") || triple.contains("darwin") {
+                "e-m:o-i64:64-i128:128-n32:64-S128"
+            } else {
+                "e-m:e-i8:8:32-i16:16:32-i64:64-i128:128-n32:64-S128"
+            }
+        }
+        "i386" | "i486" | "i586" | "i686" => {
+            if triple.contains("windows") || triple.contains("mingw") {
+                "e-m:x-p:32:32-p270:32:32-p271:32:32-p272:64:64-i64:64-f80:32-n8:16:32-a:0:32-S32"
+            } else {
+                "e-m:e-p:32:32-p270:32:32-p271:32:32-p272:64:64-f64:32:64-f80:32-n8:16:32-S128"
+            }
+        }
+        a if a.starts_with("arm") || a.starts_with("thumb") => "e-m:e-p:32:32-Fi8-i64:64-v128:64:128-a:0:32-n32-S64",
+        "wasm32" => "e-m:e-p:32:32-p10:8:8-p20:8:8-i64:64-n32:64-S128",
+        _ => "e-m:e-p270:32:32-p271:32:32-p272:64:64-i64:64-f80:128-n8:16:32:64-S128",
+    }
+}
+
+/// 解析后的目标数据布局（单位均为字节）
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataLayout {
+    repr: String,
+    pointer_size: u32,
+    pointer_align: u32,
+    /// (位宽, ABI 对齐)，按位宽升序
+    int_aligns: Vec<(u32, u32)>,
+    float_aligns: Vec<(u32, u32)>,
+}
+
+impl DataLayout {
+    /// 解析 LLVM datalayout 字符串，未指定的项使用 LLVM 的默认值
+    pub fn parse(repr: &str) -> Self {
+        let mut layout = DataLayout {
+            repr: repr.to_string(),
+            pointer_size: 8,
+            pointer_align: 8,
+            int_aligns: vec![(1, 1), (8, 1), (16, 2), (32, 4), (64, 4)],
+            float_aligns: vec![(16, 2), (32, 4), (64, 8), (128, 16)],
+        };
+
+        for spec in repr.split('-') {
+            let mut parts = spec.split(':');
+            let head = parts.next().unwrap_or("");
+            let values: Vec<u32> = parts.filter_map(|p| p.parse().ok()).collect();
+            let Some(kind) = head.chars().next() else {
+                continue;
+            };
+            let width = &head[1..];
+            match kind {
+                // 只关心默认地址空间 0 的指针
+                'p' if width.is_empty() || width == "0" => {
+                    if let [size, align, ..] = values[..] {
+                        layout.pointer_size = size / 8;
+                        layout.pointer_align = align / 8;
+                    }
+                }
+                'i' | 'f' => {
+                    let (Ok(bits), Some(&align)) = (width.parse::<u32>(), values.first()) else {
+                        continue;
+                    };
+                    let table = if kind == 'i' { &mut layout.int_aligns } else { &mut layout.float_aligns };
+                    match table.iter_mut().find(|(w, _)| *w == bits) {
+                        Some(entry) => entry.1 = align / 8,
+                        None => table.push((bits, align / 8)),
+                    }
+                    table.sort();
+                }
+                _ => {}
+            }
+        }
+        layout
+    }
+
+    /// 目标三元组对应的数据布局
+    pub fn for_triple(triple: &str) -> Self {
+        Self::parse(datalayout_for_triple(triple))
+    }
+
+    /// 原始 datalayout 字符串（用于 `target datalayout = "..."`）
+    pub fn as_str(&self) -> &str {
+        &self.repr
+    }
+
+    /// 指针大小
+    pub fn pointer_size(&self) -> u32 {
+        self.pointer_size
+    }
+
+    /// LLVM 类型的 ABI 对齐
+    pub fn align_of(&self, llvm_type: &str) -> u32 {
+        if llvm_type.ends_with('*') {
+            return self.pointer_align;
+        }
+        if let Some(elem) = Self::array_element(llvm_type) {
+            return self.align_of(elem.1);
+        }
+        match llvm_type {
+            "half" => Self::lookup(&self.float_aligns, 16),
+            "float" => Self::lookup(&self.float_aligns, 32),
+            "double" => Self::lookup(&self.float_aligns, 64),
+            t => match t.strip_prefix('i').and_then(|w| w.parse::<u32>().ok()) {
+                Some(bits) => Self::lookup(&self.int_aligns, bits),
+                // 结构体等其他类型按指针对齐处理
+                None => self.pointer_align,
+            },
+        }
+    }
+
+    /// LLVM 类型的分配大小（存储大小向上取整到 ABI 对齐，与数组元素间距一致）
+    pub fn size_of(&self, llvm_type: &str) -> u32 {
+        if llvm_type.ends_with('*') {
+            return self.pointer_size;
+        }
+        if let Some((count, elem)) = Self::array_element(llvm_type) {
+            return count * self.size_of(elem);
+        }
+        let store_size = match llvm_type {
+            "void" => return 0,
+            "half" => 2,
+            "float" => 4,
+            "double" => 8,
+            t => match t.strip_prefix('i').and_then(|w| w.parse::<u32>().ok()) {
+                Some(bits) => bits.div_ceil(8),
+                None => return self.pointer_size,
+            },
+        };
+        let align = self.align_of(llvm_type);
+        store_size.div_ceil(align) * align
+    }
+
+    /// 对齐表中查找位宽对应的对齐：没有精确匹配时使用比它大的最小项，再没有则使用最大项
+    fn lookup(table: &[(u32, u32)], bits: u32) -> u32 {
+        table.iter()
+            .find(|(w, _)| *w >= bits)
+            .or(table.last())
+            .map(|(_, align)| *align)
+            .unwrap_or(1)
+    }
+
+    /// 解析 `[N x T]`，返回 (N, T)
+    fn array_element(llvm_type: &str) -> Option<(u32, &str)> {
+        let inner = llvm_type.strip_prefix('[')?.strip_suffix(']')?;
+        let (count, elem) = inner.split_once(" x ")?;
+        Some((count.trim().parse().ok()?, elem.trim()))
+    }
+}
+
+impl Default for DataLayout {
+    fn default() -> Self {
+        Self::for_triple("x86_64-unknown-linux-gnu")
+    }
+}
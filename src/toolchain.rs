@@ -0,0 +1,446 @@
+//! 外部工具链调用
+//!
+//! 统一管理驱动程序对 clang、opt 等外部工具的查找与调用：
+//! - 支持通过 `--llvm-path` 指定工具链目录
+//! - 找不到工具时给出安装提示
+//! - 每次调用都有超时保护，防止工具挂起导致编译卡死
+//! - 捕获工具的 stderr 并在诊断信息中展示
+//! - 用 opt 校验和优化生成的 IR，IR 不合法时给出可读的错误
+//! - 查找随编译器发布的原生运行时库，链接可执行文件时自动使用
+
+use std::env;
+use std::fmt;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::error::exit_codes;
+
+/// 外部工具默认超时时间（秒）
+pub const DEFAULT_TOOL_TIMEOUT_SECS: u64 = 300;
+
+/// 外部工具调用错误
+#[derive(Debug, Clone)]
+pub enum ToolError {
+    /// 找不到工具
+    NotFound { tool: String, hint: String },
+    /// 工具无法启动
+    Spawn { tool: String, message: String },
+    /// 工具运行超时（已被终止）
+    Timeout { tool: String, timeout: Duration },
+    /// 工具返回非零退出码
+    Failed { tool: String, code: Option<i32>, stderr: String },
+    /// IR 没有通过 LLVM 的解析或校验
+    InvalidIr { file: String, details: String },
+}
+
+impl fmt::Display for ToolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ToolError::NotFound { tool, hint } => {
+                write!(f, "找不到 {}。\n  提示: {}", tool, hint)
+            }
+            ToolError::Spawn { tool, message } => {
+                write!(f, "无法启动 {}: {}", tool, message)
+            }
+            ToolError::Timeout { tool, timeout } => {
+                write!(f, "{} 运行超过 {} 秒未结束，已终止。\n  提示: 可使用 --tool-timeout <秒> 调整超时时间",
+                    tool, timeout.as_secs())
+            }
+            ToolError::Failed { tool, code, stderr } => {
+                match code {
+                    Some(code) => write!(f, "{} 执行失败 (退出码: {})", tool, code)?,
+                    None => write!(f, "{} 被信号终止", tool)?,
+                }
+                let stderr = stderr.trim();
+                if !stderr.is_empty() {
+                    write!(f, "\n{}", stderr)?;
+                }
+                if let Some(hint) = diagnose_tool_output(stderr) {
+                    write!(f, "\n  提示: {}", hint)?;
+                }
+                Ok(())
+            }
+            ToolError::InvalidIr { file, details } => {
+                write!(f, "LLVM IR 校验失败: {}\n{}\n  提示: cayc 生成的 IR 不合法属于编译器内部错误，\
+                    请附上该 IR 文件报告问题", file, details)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ToolError {}
+
+impl ToolError {
+    /// 外部工具失败使用 [`exit_codes::TOOL`] 退出码，IR 不合法是代码生成的问题，使用 [`exit_codes::CODEGEN`]
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ToolError::InvalidIr { .. } => exit_codes::CODEGEN,
+            _ => exit_codes::TOOL,
+        }
+    }
+}
+
+/// 根据平台获取 clang 可执行文件名
+fn clang_exe_name() -> &'static str {
+    if cfg!(target_os = "windows") {
+        "clang.exe"
+    } else {
+        "clang"
+    }
+}
+
+/// 根据平台获取 opt 可执行文件名
+fn opt_exe_name() -> &'static str {
+    if cfg!(target_os = "windows") {
+        "opt.exe"
+    } else {
+        "opt"
+    }
+}
+
+/// 根据平台获取 llvm-minimal 下的 clang 路径
+#[cfg(target_os = "windows")]
+fn bundled_clang_path(exe_dir: &Path) -> PathBuf {
+    exe_dir.join("llvm-minimal/bin/clang.exe")
+}
+
+#[cfg(target_os = "linux")]
+fn bundled_clang_path(exe_dir: &Path) -> PathBuf {
+    exe_dir.join("llvm-minimal/bin-linux/clang-21")
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+fn bundled_clang_path(exe_dir: &Path) -> PathBuf {
+    exe_dir.join("llvm-minimal/bin/clang")
+}
+
+/// 当前平台的 clang 安装提示
+fn clang_install_hint() -> String {
+    let install = if cfg!(target_os = "windows") {
+        "从 https://github.com/llvm/llvm-project/releases 安装 LLVM"
+    } else if cfg!(target_os = "macos") {
+        "执行 `xcode-select --install` 或 `brew install llvm`"
+    } else {
+        "执行 `sudo apt install clang lld`（或使用发行版对应的包管理器）"
+    };
+    format!(
+        "请{}，确保 clang 在 PATH 中；或将 llvm-minimal 放在编译器同目录下；也可以使用 --llvm-path <目录> 指定工具链位置",
+        install
+    )
+}
+
+/// 找不到工具时的安装提示，按工具名给出
+fn install_hint(tool: &str) -> String {
+    match tool {
+        "clang" => clang_install_hint(),
+        "opt" | "llc" => format!("{} 随 LLVM 一起发布，请安装完整的 LLVM 工具链，或使用 --llvm-path <目录> 指定工具链位置", tool),
+        "ir2exe" | "cay-ir" | "cayc" => format!("{} 随 Cavvy 编译器一起发布，请确认它与当前程序在同一目录下", tool),
+        _ => format!("请确认 {} 已经安装并且在 PATH 中", tool),
+    }
+}
+
+/// 根据工具的错误输出给出额外提示
+fn diagnose_tool_output(stderr: &str) -> Option<&'static str> {
+    if stderr.contains("lld") && (stderr.contains("not found") || stderr.contains("No such file")) {
+        Some("找不到 lld 链接器，请安装 lld 或在工具链目录中提供 ld.lld")
+    } else if stderr.contains("unable to find library") {
+        Some("链接器找不到所需的库，请使用 -L<path> 添加库搜索路径")
+    } else {
+        None
+    }
+}
+
+/// 在指定的工具链目录中查找工具
+fn find_in_llvm_path(llvm_path: &Path, exe_name: &str) -> Option<PathBuf> {
+    // 允许直接传入 clang 可执行文件路径
+    if llvm_path.is_file() {
+        return Some(llvm_path.to_path_buf());
+    }
+    [llvm_path.join("bin").join(exe_name), llvm_path.join(exe_name)]
+        .into_iter()
+        .find(|candidate| candidate.is_file())
+}
+
+/// 查找 clang 可执行文件
+/// 1. 如果指定了 `--llvm-path`，只在该目录中查找
+/// 2. 尝试直接调用 "clang"（系统 PATH 中）
+/// 3. 尝试查找编译器所在目录下的 llvm-minimal
+/// 4. 如果都找不到，返回带安装提示的错误
+pub fn find_clang(llvm_path: Option<&str>) -> Result<PathBuf, ToolError> {
+    // 1. 用户指定的工具链目录
+    if let Some(dir) = llvm_path {
+        return find_in_llvm_path(Path::new(dir), clang_exe_name()).ok_or_else(|| ToolError::NotFound {
+            tool: "clang".to_string(),
+            hint: format!("--llvm-path 指定的目录 '{}' 中没有 bin/{}", dir, clang_exe_name()),
+        });
+    }
+
+    // 2. 系统 PATH 中的 clang
+    let mut probe = Command::new("clang");
+    probe.arg("--version");
+    if let Ok(output) = run_with_timeout(&mut probe, "clang", Duration::from_secs(10))
+        && output.status.success()
+    {
+        return Ok(PathBuf::from("clang"));
+    }
+
+    // 3. 编译器所在目录下的 llvm-minimal
+    if let Ok(exe_path) = env::current_exe()
+        && let Some(exe_dir) = exe_path.parent()
+    {
+        let bundled_clang = bundled_clang_path(exe_dir);
+        if bundled_clang.exists() {
+            return Ok(bundled_clang);
+        }
+    }
+
+    // 4. 都找不到
+    Err(ToolError::NotFound {
+        tool: "clang 编译器".to_string(),
+        hint: clang_install_hint(),
+    })
+}
+
+/// 查找 LLVM 的 opt 工具，查找顺序与 [`find_clang`] 相同，最后再找 clang 所在的目录
+pub fn find_opt(llvm_path: Option<&str>) -> Result<PathBuf, ToolError> {
+    let not_found = |hint: String| ToolError::NotFound { tool: "opt".to_string(), hint };
+
+    if let Some(dir) = llvm_path {
+        let dir = Path::new(dir);
+        let dir = if dir.is_file() { dir.parent().unwrap_or(dir) } else { dir };
+        return [dir.join("bin").join(opt_exe_name()), dir.join(opt_exe_name())]
+            .into_iter()
+            .find(|candidate| candidate.is_file())
+            .ok_or_else(|| not_found(format!("--llvm-path 指定的目录 '{}' 中没有 bin/{}", dir.display(), opt_exe_name())));
+    }
+
+    let mut probe = Command::new("opt");
+    probe.arg("--version");
+    if let Ok(output) = run_with_timeout(&mut probe, "opt", Duration::from_secs(10))
+        && output.status.success()
+    {
+        return Ok(PathBuf::from("opt"));
+    }
+
+    if let Ok(exe_path) = env::current_exe()
+        && let Some(exe_dir) = exe_path.parent()
+        && let Some(bundled_dir) = bundled_clang_path(exe_dir).parent()
+    {
+        let bundled_opt = bundled_dir.join(opt_exe_name());
+        if bundled_opt.exists() {
+            return Ok(bundled_opt);
+        }
+    }
+
+    Err(not_found(install_hint("opt")))
+}
+
+/// 在后台线程中读取管道的全部内容，避免子进程因管道写满而阻塞
+fn drain<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+/// 带超时地执行外部命令并捕获输出
+///
+/// 超时后子进程会被终止并返回 `ToolError::Timeout`。只终止直接启动的子进程：
+/// 子进程自己启动的进程（如 ir2exe 调用的 clang）不会被一并终止，由它们各自的超时保护结束。
+/// 命令本身返回非零退出码时不视为错误，由调用方检查 `status`。
+pub fn run_with_timeout(cmd: &mut Command, tool: &str, timeout: Duration) -> Result<Output, ToolError> {
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                ToolError::NotFound { tool: tool.to_string(), hint: install_hint(tool) }
+            } else {
+                ToolError::Spawn { tool: tool.to_string(), message: e.to_string() }
+            }
+        })?;
+
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+
+    let start = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) => {
+                if start.elapsed() >= timeout {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(ToolError::Timeout { tool: tool.to_string(), timeout });
+                }
+                thread::sleep(Duration::from_millis(20));
+            }
+            Err(e) => {
+                let _ = child.kill();
+                return Err(ToolError::Spawn { tool: tool.to_string(), message: e.to_string() });
+            }
+        }
+    };
+
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+/// 带超时地执行外部命令，非零退出码视为 `ToolError::Failed`
+pub fn run_tool(cmd: &mut Command, tool: &str, timeout: Duration) -> Result<Output, ToolError> {
+    let output = run_with_timeout(cmd, tool, timeout)?;
+    if !output.status.success() {
+        return Err(ToolError::Failed {
+            tool: tool.to_string(),
+            code: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        });
+    }
+    Ok(output)
+}
+
+/// 用 opt 校验 IR 文件，解析错误和校验错误都以 [`ToolError::InvalidIr`] 返回
+pub fn verify_ir(opt: &Path, ir_file: &str, timeout: Duration) -> Result<(), ToolError> {
+    let mut cmd = Command::new(opt);
+    cmd.arg("-passes=verify").arg("-disable-output").arg(ir_file);
+    run_opt(&mut cmd, ir_file, timeout)
+}
+
+/// 按优化级别（`-O0` ~ `-O3`、`-Os`、`-Oz`）优化 IR，结果写入 `output`
+///
+/// 优先使用 opt，opt 在运行优化之前会先校验输入的模块；找不到 opt 时退回到 `clang -x ir -S -emit-llvm`。
+pub fn optimize_ir(ir_file: &str, output: &str, level: &str, llvm_path: Option<&str>, timeout: Duration) -> Result<(), ToolError> {
+    match find_opt(llvm_path) {
+        Ok(opt) => {
+            let mut cmd = Command::new(opt);
+            cmd.arg(level).arg("-S").arg(ir_file).arg("-o").arg(output);
+            run_opt(&mut cmd, ir_file, timeout)
+        }
+        Err(_) => {
+            let clang = find_clang(llvm_path)?;
+            let mut cmd = Command::new(clang);
+            cmd.arg("-x").arg("ir").arg(ir_file).arg("-S").arg("-emit-llvm").arg(level).arg("-o").arg(output);
+            run_tool(&mut cmd, "clang", timeout).map(|_| ())
+        }
+    }
+}
+
+/// 运行 opt，非零退出码说明输入的 IR 不合法
+fn run_opt(cmd: &mut Command, ir_file: &str, timeout: Duration) -> Result<(), ToolError> {
+    match run_tool(cmd, "opt", timeout) {
+        Ok(_) => Ok(()),
+        Err(ToolError::Failed { stderr, .. }) => Err(ToolError::InvalidIr {
+            file: ir_file.to_string(),
+            details: summarize_ir_errors(&stderr),
+        }),
+        Err(e) => Err(e),
+    }
+}
+
+/// 整理 opt 的错误输出：去掉 `opt: ` 前缀和空行，最多保留前 20 行
+pub fn summarize_ir_errors(stderr: &str) -> String {
+    const MAX_LINES: usize = 20;
+    let lines: Vec<&str> = stderr
+        .lines()
+        .map(|line| line.trim_end())
+        .filter(|line| !line.is_empty())
+        .map(|line| line.strip_prefix("opt: ").unwrap_or(line))
+        .collect();
+    let mut summary: Vec<String> = lines.iter().take(MAX_LINES).map(|line| format!("  {}", line)).collect();
+    if lines.len() > MAX_LINES {
+        summary.push(format!("  ... (还有 {} 行)", lines.len() - MAX_LINES));
+    }
+    summary.join("\n")
+}
+
+/// 解析 `--tool-timeout` 参数（秒）
+pub fn parse_timeout(value: &str) -> Result<Duration, String> {
+    match value.parse::<u64>() {
+        Ok(secs) if secs > 0 => Ok(Duration::from_secs(secs)),
+        _ => Err(format!("无效的超时时间: {} (需要正整数秒数)", value)),
+    }
+}
+
+/// 解析 `--stack-guard=<depth>` 参数（最大调用深度）
+pub fn parse_stack_guard_depth(value: &str) -> Result<u32, String> {
+    match value.parse::<u32>() {
+        Ok(depth) if depth > 0 => Ok(depth),
+        _ => Err(format!("无效的调用深度: {} (需要正整数)", value)),
+    }
+}
+
+/// 解析 `--max-steps` 参数（步数上限）
+pub fn parse_max_steps(value: &str) -> Result<u64, String> {
+    match value.parse::<u64>() {
+        Ok(steps) if steps > 0 => Ok(steps),
+        _ => Err(format!("无效的步数上限: {} (需要正整数)", value)),
+    }
+}
+
+/// 解析 `--jobs` 参数（并行编译的线程数）
+pub fn parse_jobs(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(jobs) if jobs > 0 => Ok(jobs),
+        _ => Err(format!("无效的线程数: {} (需要正整数)", value)),
+    }
+}
+
+/// 解析 `--stack-size` 参数（字节数，支持 K/M/G 后缀，如 `8M`）
+pub fn parse_stack_size(value: &str) -> Result<u64, String> {
+    parse_byte_size(value).ok_or_else(|| format!("无效的栈大小: {} (需要正整数字节数，可带 K/M/G 后缀)", value))
+}
+
+/// 解析 `--max-heap` 参数（字节数，支持 K/M/G 后缀，如 `64M`）
+pub fn parse_max_heap(value: &str) -> Result<u64, String> {
+    parse_byte_size(value).ok_or_else(|| format!("无效的堆上限: {} (需要正整数字节数，可带 K/M/G 后缀)", value))
+}
+
+/// 解析带 K/M/G 后缀的正整数字节数
+fn parse_byte_size(value: &str) -> Option<u64> {
+    let trimmed = value.trim();
+    let (digits, multiplier) = match trimmed.chars().last().map(|c| c.to_ascii_uppercase()) {
+        Some('K') => (&trimmed[..trimmed.len() - 1], 1024),
+        Some('M') => (&trimmed[..trimmed.len() - 1], 1024 * 1024),
+        Some('G') => (&trimmed[..trimmed.len() - 1], 1024 * 1024 * 1024),
+        _ => (trimmed, 1),
+    };
+    digits.parse::<u64>().ok().and_then(|n| n.checked_mul(multiplier)).filter(|&size| size > 0)
+}
+
+/// 原生运行时库（`runtime/` 静态库）的文件名，由 build.rs 复制到编译器所在目录
+pub const RUNTIME_LIBRARY_NAMES: &[&str] = &["libcavvy_runtime.a", "cavvy_runtime.lib"];
+
+/// 查找编译器所在目录中的原生运行时库
+pub fn find_runtime_library() -> Option<PathBuf> {
+    let exe_path = env::current_exe().ok()?;
+    let exe_dir = exe_path.parent()?;
+    RUNTIME_LIBRARY_NAMES
+        .iter()
+        .map(|name| exe_dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
+/// 生成设置主线程栈大小的链接器参数
+///
+/// Windows (MinGW) 和 macOS 由链接器写入可执行文件头；
+/// Linux 上 `-z stack-size` 只写入 PT_GNU_STACK 段，主线程实际栈大小仍受 `ulimit -s` 限制。
+pub fn stack_size_link_flag(target: &str, size: u64) -> String {
+    if target.contains("windows") || target.contains("mingw") {
+        format!("-Wl,--stack,{}", size)
+    } else if target.contains("darwin") || target.contains("macos") {
+        format!("-Wl,-stack_size,0x{:x}", size)
+    } else {
+        format!("-Wl,-z,stack-size={}", size)
+    }
+}
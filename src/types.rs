@@ -1,9 +1,14 @@
 use std::fmt;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use crate::target::DataLayout;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Type {
     Void,
+    Never,  // 不返回的调用（exit）的类型：之后的代码不可达，不能作为值使用
+    Int8,
+    Int16,
     Int32,
     Int64,
     Float32,
@@ -14,17 +19,18 @@ pub enum Type {
     Object(String),
     Array(Box<Type>),
     Function(Box<FunctionType>),
+    Generic(String, Vec<Type>),  // 泛型类实例 Box<int>，语义分析前会被单态化为具体类
     Auto,  // 自动类型推断占位符
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct FunctionType {
     pub params: Vec<Type>,
     pub return_type: Box<Type>,
     pub is_static: bool,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClassInfo {
     pub name: String,
     pub methods: HashMap<String, Vec<MethodInfo>>,  // 支持方法重载：同名方法可以有多个
@@ -38,7 +44,7 @@ pub struct ClassInfo {
 }
 
 /// 构造函数信息
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConstructorInfo {
     pub params: Vec<ParameterInfo>,
     pub is_public: bool,
@@ -46,7 +52,7 @@ pub struct ConstructorInfo {
     pub is_protected: bool,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InterfaceInfo {
     pub name: String,
     pub methods: HashMap<String, MethodInfo>,
@@ -63,9 +69,17 @@ impl ClassInfo {
 
     /// 根据方法名和参数类型查找方法（支持可变参数）
     pub fn find_method(&self, name: &str, arg_types: &[Type]) -> Option<&MethodInfo> {
-        self.methods.get(name)?.iter().find(|m| {
-            Self::match_method_params(&m.params, arg_types)
-        })
+        Self::resolve_overload(self.methods.get(name)?, arg_types)
+    }
+
+    /// 在同名重载中选择与实参类型匹配的方法
+    ///
+    /// 参数类型完全相同的重载优先，其次才考虑隐式转换和可变参数。
+    pub fn resolve_overload<'m>(methods: &'m [MethodInfo], arg_types: &[Type]) -> Option<&'m MethodInfo> {
+        methods.iter()
+            .find(|m| !m.params.iter().any(|p| p.is_varargs)
+                && m.params.iter().map(|p| &p.param_type).eq(arg_types.iter()))
+            .or_else(|| methods.iter().find(|m| Self::match_method_params(&m.params, arg_types)))
     }
 
     /// 匹配方法参数（支持可变参数）
@@ -93,6 +107,10 @@ impl ClassInfo {
                 Type::Array(elem) => elem.as_ref(),
                 _ => &params[last_idx].param_type,
             };
+            // 直接传入同类型数组
+            if arg_types.len() == params.len() && arg_types[last_idx] == params[last_idx].param_type {
+                return true;
+            }
             // 所有剩余参数必须匹配可变参数的元素类型
             for i in last_idx..arg_types.len() {
                 if !Self::types_match(vararg_element_type, &arg_types[i]) {
@@ -128,12 +146,14 @@ impl ClassInfo {
             (Type::Float64, Type::Int32) => true,
             (Type::Float64, Type::Int64) => true,
             (Type::Float64, Type::Float32) => true,
+            // 对象参数可以接收子类实例（与 types_compatible 一致，不做继承检查）
+            (Type::Object(_), Type::Object(_)) => true,
             _ => false,
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MethodInfo {
     pub name: String,
     pub class_name: String,
@@ -146,9 +166,12 @@ pub struct MethodInfo {
     pub is_native: bool,
     pub is_override: bool,  // 标记是否是重写方法
     pub is_final: bool,  // 是否是final方法（禁止重写）
+    /// static native 方法绑定的 C 函数名（`@Symbol` 指定，默认为方法名）
+    #[serde(default)]
+    pub link_name: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FieldInfo {
     pub name: String,
     pub field_type: Type,
@@ -160,11 +183,13 @@ pub struct FieldInfo {
     pub is_const_expr: bool,  // 是否是编译期常量（static final且初始化值为常量）
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ParameterInfo {
     pub name: String,
     pub param_type: Type,
     pub is_varargs: bool,  // 是否为可变参数
+    #[serde(default)]
+    pub is_final: bool,  // 是否为 final 参数（方法体内不可重新赋值）
 }
 
 impl ParameterInfo {
@@ -173,6 +198,7 @@ impl ParameterInfo {
             name,
             param_type,
             is_varargs: false,
+            is_final: false,
         }
     }
 
@@ -182,30 +208,53 @@ impl ParameterInfo {
             name,
             param_type: Type::Array(Box::new(param_type)),
             is_varargs: true,
+            is_final: false,
         }
     }
+
+    /// 标记为 final 参数
+    pub fn with_final(mut self, is_final: bool) -> Self {
+        self.is_final = is_final;
+        self
+    }
 }
 
 impl Type {
-    pub fn size_in_bytes(&self) -> usize {
+    /// 对应的 LLVM 类型（代码生成中所有类型映射都以此为准）
+    pub fn llvm_of(&self) -> String {
         match self {
-            Type::Void => 0,
-            Type::Int32 => 4,
-            Type::Int64 => 8,
-            Type::Float32 => 4,
-            Type::Float64 => 8,
-            Type::Bool => 1,
-            Type::Char => 1,
-            Type::String => 8, // 指针大小
-            Type::Object(_) => 8, // 引用类型
-            Type::Array(_) => 8, // 指针大小
-            Type::Function(_) => 8, // 函数指针
-            Type::Auto => panic!("Cannot get size of auto type - type inference not completed"),
+            Type::Void | Type::Never => "void".to_string(),
+            Type::Int8 => "i8".to_string(),
+            Type::Int16 => "i16".to_string(),
+            Type::Int32 => "i32".to_string(),
+            Type::Int64 => "i64".to_string(),
+            Type::Float32 => "float".to_string(),
+            Type::Float64 => "double".to_string(),
+            Type::Bool => "i1".to_string(),
+            Type::Char => "i32".to_string(),
+            Type::String => "i8*".to_string(),
+            Type::Object(_) => "i8*".to_string(),
+            Type::Array(inner) => format!("{}*", inner.llvm_of()),
+            Type::Function(_) => "i8*".to_string(),
+            Type::Generic(..) => panic!("Type::Generic should have been monomorphized before code generation"),
+            Type::Auto => panic!("Type::Auto should have been resolved before code generation"),
         }
     }
 
+    /// 在目标平台上占用的字节数（作为数组元素或字段时的间距）
+    pub fn size_of(&self, layout: &DataLayout) -> usize {
+        layout.size_of(&self.llvm_of()) as usize
+    }
+
+    /// 在目标平台上的 ABI 对齐
+    pub fn align_of(&self, layout: &DataLayout) -> usize {
+        layout.align_of(&self.llvm_of()) as usize
+    }
+
     pub fn is_primitive(&self) -> bool {
         matches!(self, 
+            Type::Int8 |
+            Type::Int16 |
             Type::Int32 | 
             Type::Int64 | 
             Type::Float32 | 
@@ -216,11 +265,25 @@ impl Type {
     }
 
     pub fn is_reference_type(&self) -> bool {
-        matches!(self, Type::String | Type::Object(_) | Type::Array(_))
+        matches!(self, Type::String | Type::Object(_) | Type::Array(_) | Type::Function(_))
     }
 
     pub fn is_integer(&self) -> bool {
-        matches!(self, Type::Int32 | Type::Int64)
+        matches!(self, Type::Int8 | Type::Int16 | Type::Int32 | Type::Int64)
+    }
+
+    /// 类型在源代码中的写法（`boolean`、`String`、`int[]`），与 Display 的内部名称不同
+    pub fn source_name(&self) -> String {
+        match self {
+            Type::Bool => "boolean".to_string(),
+            Type::String => "String".to_string(),
+            Type::Array(inner) => format!("{}[]", inner.source_name()),
+            Type::Generic(name, args) => {
+                let args: Vec<String> = args.iter().map(Type::source_name).collect();
+                format!("{}<{}>", name, args.join(", "))
+            }
+            _ => self.to_string(),
+        }
     }
 }
 
@@ -228,6 +291,9 @@ impl fmt::Display for Type {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Type::Void => write!(f, "void"),
+            Type::Never => write!(f, "never"),
+            Type::Int8 => write!(f, "byte"),
+            Type::Int16 => write!(f, "short"),
             Type::Int32 => write!(f, "int"),
             Type::Int64 => write!(f, "long"),
             Type::Float32 => write!(f, "float"),
@@ -247,6 +313,16 @@ impl fmt::Display for Type {
                 }
                 write!(f, ") -> {}", func_type.return_type)
             }
+            Type::Generic(name, args) => {
+                write!(f, "{}<", name)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ">")
+            }
             Type::Auto => write!(f, "auto"),
         }
     }
@@ -256,6 +332,14 @@ impl fmt::Display for Type {
 pub struct TypeRegistry {
     pub classes: HashMap<String, ClassInfo>,
     pub interfaces: HashMap<String, InterfaceInfo>,
+    /// 从 .cavi 接口文件导入的类（定义在其他编译单元中）
+    pub imported_classes: HashSet<String>,
+    /// 从 .cavi 接口文件导入的接口（定义在其他编译单元中）
+    pub imported_interfaces: HashSet<String>,
+    /// 编译器内置的类（如 Math），没有源码定义，调用由代码生成直接展开
+    pub builtin_classes: HashSet<String>,
+    /// 编译器内置的全局函数（print、readInt 等）的签名，同名函数按重载保存
+    pub builtin_functions: HashMap<String, Vec<MethodInfo>>,
 }
 
 impl TypeRegistry {
@@ -263,11 +347,40 @@ impl TypeRegistry {
         Self {
             classes: HashMap::new(),
             interfaces: HashMap::new(),
+            imported_classes: HashSet::new(),
+            imported_interfaces: HashSet::new(),
+            builtin_classes: HashSet::new(),
+            builtin_functions: HashMap::new(),
         }
     }
 
+    /// 注册内置全局函数的一个签名
+    pub fn register_builtin_function(&mut self, signature: MethodInfo) {
+        self.builtin_functions.entry(signature.name.clone()).or_default().push(signature);
+    }
+
+    /// 内置全局函数的所有重载签名
+    pub fn builtin_function(&self, name: &str) -> Option<&[MethodInfo]> {
+        self.builtin_functions.get(name).map(Vec::as_slice)
+    }
+
+    /// 注册内置类
+    pub fn register_builtin_class(&mut self, class_info: ClassInfo) {
+        self.builtin_classes.insert(class_info.name.clone());
+        self.classes.insert(class_info.name.clone(), class_info);
+    }
+
+    /// 是否是内置类（用户定义的同名类会遮蔽内置类）
+    pub fn is_builtin_class(&self, name: &str) -> bool {
+        self.builtin_classes.contains(name)
+    }
+
     pub fn register_class(&mut self, class_info: ClassInfo) -> crate::error::cayResult<()> {
         let name = class_info.name.clone();
+        // 用户定义的同名类替换内置类
+        if self.builtin_classes.remove(&name) {
+            self.classes.remove(&name);
+        }
         if self.classes.contains_key(&name) {
             return Err(crate::error::semantic_error(
                 0, 0,
@@ -302,6 +415,15 @@ impl TypeRegistry {
         self.classes.get(name)
     }
 
+    /// 查找字段（支持继承），返回声明该字段的类名和字段信息
+    pub fn find_field(&self, class_name: &str, field_name: &str) -> Option<(&str, &FieldInfo)> {
+        let class_info = self.classes.get(class_name)?;
+        if let Some(field) = class_info.fields.get(field_name) {
+            return Some((class_info.name.as_str(), field));
+        }
+        self.find_field(class_info.parent.as_deref()?, field_name)
+    }
+
     /// 根据类名和方法名获取方法（获取第一个匹配的方法，用于无参数类型信息的情况，支持继承）
     pub fn get_method(&self, class_name: &str, method_name: &str) -> Option<&MethodInfo> {
         if let Some(class_info) = self.classes.get(class_name) {
@@ -316,8 +438,24 @@ impl TypeRegistry {
         None
     }
 
+    /// 获取方法的所有重载：在类本身或最近声明了该方法名的父类中查找
+    pub fn get_overloads(&self, class_name: &str, method_name: &str) -> &[MethodInfo] {
+        match self.classes.get(class_name) {
+            Some(class_info) => match class_info.methods.get(method_name) {
+                Some(methods) => methods,
+                None => class_info.parent.as_deref().map_or(&[], |parent| self.get_overloads(parent, method_name)),
+            },
+            None => &[],
+        }
+    }
+
     /// 根据类名、方法名和参数类型查找方法（支持重载和继承）
+    ///
+    /// `class_name` 为接口名时在接口声明的方法中查找。
     pub fn find_method(&self, class_name: &str, method_name: &str, arg_types: &[Type]) -> Option<&MethodInfo> {
+        if let Some(interface) = self.interfaces.get(class_name) {
+            return interface.find_method(method_name, arg_types);
+        }
         // 首先在当前类中查找
         if let Some(class_info) = self.classes.get(class_name) {
             if let Some(method) = class_info.find_method(method_name, arg_types) {
@@ -359,4 +497,17 @@ impl InterfaceInfo {
     pub fn add_method(&mut self, method: MethodInfo) {
         self.methods.insert(method.name.clone(), method);
     }
+
+    /// 根据方法名和参数类型查找接口方法
+    pub fn find_method(&self, name: &str, arg_types: &[Type]) -> Option<&MethodInfo> {
+        self.methods.get(name)
+            .filter(|m| ClassInfo::match_method_params(&m.params, arg_types))
+    }
+
+    /// 按方法名排序的方法列表，即接口方法表（itable）中的槽位顺序
+    pub fn sorted_methods(&self) -> Vec<&MethodInfo> {
+        let mut methods: Vec<&MethodInfo> = self.methods.values().collect();
+        methods.sort_by(|a, b| a.name.cmp(&b.name));
+        methods
+    }
 }
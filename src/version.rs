@@ -0,0 +1,61 @@
+//! 版本与特性信息
+//!
+//! 提供编译器版本、运行时 ABI 版本、支持的目标平台和可选特性等信息，
+//! 供命令行工具的 `--version --verbose` 以及嵌入方检查兼容性使用。
+
+use crate::codegen::runtime::RUNTIME_ABI_VERSION;
+
+/// 编译器版本（来自 .verinfo）
+const COMPILER_VERSION: &str = env!("CAYC_VERSION");
+
+/// 捆绑的 LLVM 工具链版本（来自 .verinfo）
+const BUNDLED_LLVM_VERSION: Option<&str> = option_env!("LLVM_MINIMAL_VERSION");
+
+/// 支持的目标平台 (--target 名称, 目标三元组)
+pub const SUPPORTED_TARGETS: &[(&str, &str)] = &[
+    ("windows", "x86_64-w64-mingw32"),
+    ("linux", "x86_64-unknown-linux-gnu"),
+    ("macos", "x86_64-apple-darwin"),
+];
+
+/// 可通过 `-f:XX` 开启的可选特性 (名称, 说明)
+pub const OPTIONAL_FEATURES: &[(&str, &str)] = &[
+    ("console_utf8", "Linux/macOS 下启动时调用 setlocale 设置 UTF-8"),
+];
+
+/// 获取编译器版本号
+///
+/// 嵌入方可以用它检查所链接的 cavvy 库版本。
+pub fn cavvy_version() -> &'static str {
+    COMPILER_VERSION
+}
+
+/// 获取运行时 ABI 版本
+pub fn runtime_abi_version() -> u32 {
+    RUNTIME_ABI_VERSION
+}
+
+/// 生成 `--version --verbose` 的详细版本信息
+///
+/// # Arguments
+/// * `tool_name` - 工具名称（如 "Cavvy Compiler"）
+/// * `tool_version` - 工具自身的版本号
+pub fn verbose_version_info(tool_name: &str, tool_version: &str) -> String {
+    let mut info = String::new();
+    info.push_str(&format!("{} v{}\n", tool_name, tool_version));
+    info.push_str(&format!("  编译器版本:     {}\n", cavvy_version()));
+    info.push_str(&format!("  crate 版本:     {}\n", env!("CARGO_PKG_VERSION")));
+    info.push_str(&format!("  运行时 ABI:     {}\n", runtime_abi_version()));
+    info.push_str("  内存管理:       无 GC (calloc 分配，程序结束时由系统回收)\n");
+    info.push_str("  运行时检查:     字符串空指针检查、charAt/substring 越界检查 (默认开启)\n");
+    info.push_str(&format!("  捆绑 LLVM:      {}\n", BUNDLED_LLVM_VERSION.unwrap_or("未知")));
+    info.push_str("  支持的目标平台:\n");
+    for (name, triple) in SUPPORTED_TARGETS {
+        info.push_str(&format!("    {:<10} {}\n", name, triple));
+    }
+    info.push_str("  可选特性:\n");
+    for (name, desc) in OPTIONAL_FEATURES {
+        info.push_str(&format!("    {:<14} {}\n", name, desc));
+    }
+    info
+}
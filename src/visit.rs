@@ -0,0 +1,344 @@
+//! AST 可变遍历
+//!
+//! `VisitMut` 为需要就地改写 AST 的编译阶段（如泛型单态化）提供统一的遍历框架：
+//! 实现者只重写关心的钩子，其余节点由对应的 `walk_*` 函数按默认顺序递归访问。
+//! 重写钩子时调用同名的 `walk_*` 函数即可继续遍历子节点。
+
+use crate::ast::*;
+use crate::error::{cayResult, SourceLocation};
+use crate::types::Type;
+
+pub trait VisitMut {
+    /// 访问一个类型，`loc` 为包含该类型的最近节点位置
+    fn visit_type(&mut self, ty: &mut Type, loc: &SourceLocation) -> cayResult<()> {
+        walk_type(self, ty, loc)
+    }
+
+    /// 访问节点 ID
+    fn visit_id(&mut self, _id: &mut NodeId) {}
+
+    fn visit_class(&mut self, class: &mut ClassDecl) -> cayResult<()> {
+        walk_class(self, class)
+    }
+
+    fn visit_member(&mut self, member: &mut ClassMember) -> cayResult<()> {
+        walk_member(self, member)
+    }
+
+    fn visit_stmt(&mut self, stmt: &mut Stmt) -> cayResult<()> {
+        walk_stmt(self, stmt)
+    }
+
+    fn visit_expr(&mut self, expr: &mut Expr) -> cayResult<()> {
+        walk_expr(self, expr)
+    }
+}
+
+/// 遍历整个程序
+pub fn walk_program<V: VisitMut + ?Sized>(v: &mut V, program: &mut Program) -> cayResult<()> {
+    for class in &mut program.classes {
+        v.visit_class(class)?;
+    }
+    for interface in &mut program.interfaces {
+        for method in &mut interface.methods {
+            walk_method(v, method)?;
+        }
+        v.visit_id(&mut interface.id);
+    }
+    for func in &mut program.top_level_functions {
+        for param in &mut func.params {
+            v.visit_type(&mut param.param_type, &func.loc)?;
+        }
+        v.visit_type(&mut func.return_type, &func.loc)?;
+        walk_block(v, &mut func.body)?;
+        v.visit_id(&mut func.id);
+    }
+    Ok(())
+}
+
+pub fn walk_type<V: VisitMut + ?Sized>(v: &mut V, ty: &mut Type, loc: &SourceLocation) -> cayResult<()> {
+    match ty {
+        Type::Array(inner) => v.visit_type(inner, loc),
+        Type::Generic(_, args) => {
+            for arg in args {
+                v.visit_type(arg, loc)?;
+            }
+            Ok(())
+        }
+        Type::Function(func) => {
+            for param in &mut func.params {
+                v.visit_type(param, loc)?;
+            }
+            v.visit_type(&mut func.return_type, loc)
+        }
+        _ => Ok(()),
+    }
+}
+
+pub fn walk_class<V: VisitMut + ?Sized>(v: &mut V, class: &mut ClassDecl) -> cayResult<()> {
+    for member in &mut class.members {
+        v.visit_member(member)?;
+    }
+    v.visit_id(&mut class.id);
+    Ok(())
+}
+
+pub fn walk_member<V: VisitMut + ?Sized>(v: &mut V, member: &mut ClassMember) -> cayResult<()> {
+    match member {
+        ClassMember::Method(method) => walk_method(v, method),
+        ClassMember::Field(field) => {
+            v.visit_type(&mut field.field_type, &field.loc)?;
+            if let Some(init) = &mut field.initializer {
+                v.visit_expr(init)?;
+            }
+            v.visit_id(&mut field.id);
+            Ok(())
+        }
+        ClassMember::Constructor(ctor) => {
+            for param in &mut ctor.params {
+                v.visit_type(&mut param.param_type, &ctor.loc)?;
+            }
+            match &mut ctor.constructor_call {
+                Some(ConstructorCall::This(args)) | Some(ConstructorCall::Super(args)) => {
+                    for arg in args {
+                        v.visit_expr(arg)?;
+                    }
+                }
+                None => {}
+            }
+            walk_block(v, &mut ctor.body)?;
+            v.visit_id(&mut ctor.id);
+            Ok(())
+        }
+        ClassMember::Destructor(dtor) => {
+            walk_block(v, &mut dtor.body)?;
+            v.visit_id(&mut dtor.id);
+            Ok(())
+        }
+        ClassMember::InstanceInitializer(block) | ClassMember::StaticInitializer(block) => walk_block(v, block),
+    }
+}
+
+pub fn walk_method<V: VisitMut + ?Sized>(v: &mut V, method: &mut MethodDecl) -> cayResult<()> {
+    for param in &mut method.params {
+        v.visit_type(&mut param.param_type, &method.loc)?;
+    }
+    v.visit_type(&mut method.return_type, &method.loc)?;
+    if let Some(body) = &mut method.body {
+        walk_block(v, body)?;
+    }
+    v.visit_id(&mut method.id);
+    Ok(())
+}
+
+pub fn walk_block<V: VisitMut + ?Sized>(v: &mut V, block: &mut Block) -> cayResult<()> {
+    for stmt in &mut block.statements {
+        v.visit_stmt(stmt)?;
+    }
+    v.visit_id(&mut block.id);
+    Ok(())
+}
+
+pub fn walk_stmt<V: VisitMut + ?Sized>(v: &mut V, stmt: &mut Stmt) -> cayResult<()> {
+    match stmt {
+        Stmt::Expr(expr) => v.visit_expr(expr),
+        Stmt::VarDecl(var) => {
+            v.visit_type(&mut var.var_type, &var.loc)?;
+            if let Some(init) = &mut var.initializer {
+                v.visit_expr(init)?;
+            }
+            v.visit_id(&mut var.id);
+            Ok(())
+        }
+        Stmt::Return(ret) => match &mut ret.value {
+            Some(expr) => v.visit_expr(expr),
+            None => Ok(()),
+        },
+        Stmt::If(if_stmt) => {
+            v.visit_expr(&mut if_stmt.condition)?;
+            v.visit_stmt(&mut if_stmt.then_branch)?;
+            if let Some(else_branch) = &mut if_stmt.else_branch {
+                v.visit_stmt(else_branch)?;
+            }
+            v.visit_id(&mut if_stmt.id);
+            Ok(())
+        }
+        Stmt::While(while_stmt) => {
+            v.visit_expr(&mut while_stmt.condition)?;
+            v.visit_stmt(&mut while_stmt.body)?;
+            v.visit_id(&mut while_stmt.id);
+            Ok(())
+        }
+        Stmt::For(for_stmt) => {
+            if let Some(init) = &mut for_stmt.init {
+                v.visit_stmt(init)?;
+            }
+            if let Some(condition) = &mut for_stmt.condition {
+                v.visit_expr(condition)?;
+            }
+            if let Some(update) = &mut for_stmt.update {
+                v.visit_expr(update)?;
+            }
+            v.visit_stmt(&mut for_stmt.body)?;
+            v.visit_id(&mut for_stmt.id);
+            Ok(())
+        }
+        Stmt::ForEach(for_each) => {
+            v.visit_type(&mut for_each.var_type, &for_each.loc)?;
+            v.visit_expr(&mut for_each.iterable)?;
+            v.visit_stmt(&mut for_each.body)?;
+            v.visit_id(&mut for_each.id);
+            Ok(())
+        }
+        Stmt::DoWhile(do_while) => {
+            v.visit_stmt(&mut do_while.body)?;
+            v.visit_expr(&mut do_while.condition)?;
+            v.visit_id(&mut do_while.id);
+            Ok(())
+        }
+        Stmt::Switch(switch) => {
+            v.visit_expr(&mut switch.expr)?;
+            for case in &mut switch.cases {
+                for stmt in &mut case.body {
+                    v.visit_stmt(stmt)?;
+                }
+            }
+            if let Some(default) = &mut switch.default {
+                for stmt in default {
+                    v.visit_stmt(stmt)?;
+                }
+            }
+            v.visit_id(&mut switch.id);
+            Ok(())
+        }
+        Stmt::Block(block) => walk_block(v, block),
+        Stmt::Labeled(labeled) => {
+            v.visit_stmt(&mut labeled.body)?;
+            v.visit_id(&mut labeled.id);
+            Ok(())
+        }
+        Stmt::Break(_) | Stmt::Continue(_) => Ok(()),
+    }
+}
+
+pub fn walk_expr<V: VisitMut + ?Sized>(v: &mut V, expr: &mut Expr) -> cayResult<()> {
+    match expr {
+        Expr::Literal(_) | Expr::Identifier(_) => Ok(()),
+        Expr::Binary(e) => {
+            v.visit_expr(&mut e.left)?;
+            v.visit_expr(&mut e.right)?;
+            v.visit_id(&mut e.id);
+            Ok(())
+        }
+        Expr::Unary(e) => {
+            v.visit_expr(&mut e.operand)?;
+            v.visit_id(&mut e.id);
+            Ok(())
+        }
+        Expr::Call(e) => {
+            v.visit_expr(&mut e.callee)?;
+            for arg in &mut e.args {
+                v.visit_expr(arg)?;
+            }
+            v.visit_id(&mut e.id);
+            Ok(())
+        }
+        Expr::MemberAccess(e) => {
+            v.visit_expr(&mut e.object)?;
+            v.visit_id(&mut e.id);
+            Ok(())
+        }
+        Expr::New(e) => {
+            if let Some(type_args) = &mut e.type_args {
+                for arg in type_args {
+                    v.visit_type(arg, &e.loc)?;
+                }
+            }
+            for arg in &mut e.args {
+                v.visit_expr(arg)?;
+            }
+            v.visit_id(&mut e.id);
+            Ok(())
+        }
+        Expr::Assignment(e) => {
+            v.visit_expr(&mut e.target)?;
+            v.visit_expr(&mut e.value)?;
+            v.visit_id(&mut e.id);
+            Ok(())
+        }
+        Expr::Cast(e) => {
+            v.visit_expr(&mut e.expr)?;
+            v.visit_type(&mut e.target_type, &e.loc)?;
+            v.visit_id(&mut e.id);
+            Ok(())
+        }
+        Expr::ArrayCreation(e) => {
+            v.visit_type(&mut e.element_type, &e.loc)?;
+            for size in &mut e.sizes {
+                v.visit_expr(size)?;
+            }
+            v.visit_id(&mut e.id);
+            Ok(())
+        }
+        Expr::ArrayAccess(e) => {
+            v.visit_expr(&mut e.array)?;
+            v.visit_expr(&mut e.index)?;
+            v.visit_id(&mut e.id);
+            Ok(())
+        }
+        Expr::ArrayInit(e) => {
+            for element in &mut e.elements {
+                v.visit_expr(element)?;
+            }
+            v.visit_id(&mut e.id);
+            Ok(())
+        }
+        Expr::MethodRef(e) => {
+            if let Some(object) = &mut e.object {
+                v.visit_expr(object)?;
+            }
+            v.visit_id(&mut e.id);
+            Ok(())
+        }
+        Expr::Lambda(e) => {
+            for param in &mut e.params {
+                if let Some(ty) = &mut param.param_type {
+                    v.visit_type(ty, &e.loc)?;
+                }
+            }
+            match &mut e.body {
+                LambdaBody::Expr(body) => v.visit_expr(body)?,
+                LambdaBody::Block(block) => walk_block(v, block)?,
+            }
+            v.visit_id(&mut e.id);
+            Ok(())
+        }
+        Expr::Ternary(e) => {
+            v.visit_expr(&mut e.condition)?;
+            v.visit_expr(&mut e.true_branch)?;
+            v.visit_expr(&mut e.false_branch)?;
+            v.visit_id(&mut e.id);
+            Ok(())
+        }
+        Expr::InstanceOf(e) => {
+            v.visit_expr(&mut e.expr)?;
+            v.visit_type(&mut e.target_type, &e.loc)?;
+            v.visit_id(&mut e.id);
+            Ok(())
+        }
+        Expr::NullCoalesce(e) => {
+            v.visit_expr(&mut e.value)?;
+            v.visit_expr(&mut e.default)?;
+            v.visit_id(&mut e.id);
+            Ok(())
+        }
+        Expr::InlineIr(e) => {
+            v.visit_type(&mut e.result_type, &e.loc)?;
+            for arg in &mut e.args {
+                v.visit_expr(arg)?;
+            }
+            v.visit_id(&mut e.id);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! 监视模式（cay-watch）
+//!
+//! 源文件变化后立即重新分析并报告诊断，分析通过时重新编译运行，适合教学和快速试验：
+//! - 变化按预处理后源码的内容哈希判断（与编译缓存相同）：入口文件、它 `#include` 的文件、
+//!   被导入的模块及其包含的文件任一变化都会触发重新分析，只保存而没有修改内容时不会触发
+//! - 诊断增量输出：新出现的错误完整显示，上一轮已经显示过的错误只列出位置和消息，并报告修复了几个
+//!
+//! 本模块只负责分析和比较，轮询文件、编译和运行程序由 `cay-watch` 完成。
+
+use std::collections::HashMap;
+use crate::cache::content_hash;
+use crate::diagnostics::Diagnostic;
+use crate::error::cayResult;
+use crate::modules::{preprocess_module, ModuleLoader};
+use crate::{generics, lexer, semantic};
+
+/// 一次分析的结果
+#[derive(Debug, Clone, Default)]
+pub struct Analysis {
+    /// 分析发现的错误，没有错误时为空
+    pub diagnostics: Vec<Diagnostic>,
+    /// 入口文件和已加载的被导入模块，下一轮据此判断是否有变化
+    pub files: Vec<String>,
+}
+
+impl Analysis {
+    pub fn is_ok(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+}
+
+/// 分析入口文件（预处理、解析、语义分析，不生成代码）
+pub fn analyze(path: &str) -> Analysis {
+    let mut loader = ModuleLoader::new(Some(path));
+    let result = analyze_with_loader(path, &mut loader);
+    let mut files = vec![path.to_string()];
+    files.extend(loader.dependencies().iter().map(|(file, _)| file.clone()));
+    Analysis {
+        diagnostics: result.err().map(|e| Diagnostic::from_error(&e, path)).unwrap_or_default(),
+        files,
+    }
+}
+
+fn analyze_with_loader(path: &str, loader: &mut ModuleLoader) -> cayResult<()> {
+    let source = preprocess_module(path)?;
+    let tokens = lexer::lex(&source)?;
+    let program = loader.parse_program(tokens)?;
+    let program = generics::monomorphize(program)?;
+    semantic::SemanticAnalyzer::new().analyze(&program)
+}
+
+/// 一组文件当前内容的指纹：各文件预处理后源码的内容哈希（无法读取或预处理失败时使用错误消息）
+pub fn fingerprint(files: &[String]) -> u64 {
+    let mut input = String::new();
+    for file in files {
+        let hash = match preprocess_module(file) {
+            Ok(text) => content_hash(text.as_bytes()),
+            Err(e) => content_hash(e.to_string().as_bytes()),
+        };
+        input.push_str(&format!("{:016x} {}\n", hash, file));
+    }
+    content_hash(input.as_bytes())
+}
+
+/// 与上一轮相比的诊断变化
+#[derive(Debug, Default)]
+pub struct DiagnosticDelta {
+    /// 上一轮没有的诊断
+    pub new: Vec<Diagnostic>,
+    /// 上一轮已经报告过、仍然存在的诊断（位置可能因为编辑而移动）
+    pub remaining: Vec<Diagnostic>,
+    /// 上一轮报告过、这一轮消失的诊断个数
+    pub fixed: usize,
+}
+
+impl DiagnosticDelta {
+    /// 比较两轮诊断：文件、错误码和消息相同的诊断视为同一个错误，不比较位置
+    pub fn between(previous: &[Diagnostic], current: &[Diagnostic]) -> Self {
+        let key = |d: &Diagnostic| (d.file.clone(), d.code, d.message.clone());
+        let mut unmatched: HashMap<_, usize> = HashMap::new();
+        for d in previous {
+            *unmatched.entry(key(d)).or_default() += 1;
+        }
+        let mut delta = DiagnosticDelta::default();
+        for d in current {
+            match unmatched.get_mut(&key(d)) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    delta.remaining.push(d.clone());
+                }
+                _ => delta.new.push(d.clone()),
+            }
+        }
+        delta.fixed = unmatched.values().sum();
+        delta
+    }
+
+    /// 渲染变化：新诊断带源码片段，已报告过的诊断每个一行
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for d in &self.new {
+            let source = crate::source::read_source_file(&d.file).ok();
+            out.push_str(&d.render(source.as_deref()));
+            out.push('\n');
+        }
+        for d in &self.remaining {
+            match &d.location {
+                Some(loc) => out.push_str(&format!("仍存在: error[{}] {}:{}: {}\n", d.code, d.file, loc.line, d.message)),
+                None => out.push_str(&format!("仍存在: error[{}] {}: {}\n", d.code, d.file, d.message)),
+            }
+        }
+        if self.fixed > 0 {
+            out.push_str(&format!("已修复 {} 个错误\n", self.fixed));
+        }
+        let total = self.new.len() + self.remaining.len();
+        if total > 0 {
+            out.push_str(&format!("共 {} 个错误（新增 {} 个）\n", total, self.new.len()));
+        }
+        out
+    }
+}
@@ -66,11 +66,17 @@ fn compile_eol_expect_error(source_path: &str) -> Result<String, String> {
 
 /// 编译并运行 EOL 文件，期望执行失败（用于运行时错误测试），返回错误信息
 fn compile_and_run_expect_error(source_path: &str) -> Result<String, String> {
+    compile_and_run_expect_error_with_args(source_path, &[])
+}
+
+/// 同 `compile_and_run_expect_error`，额外向 cayc 传递编译选项
+fn compile_and_run_expect_error_with_args(source_path: &str, cayc_args: &[&str]) -> Result<String, String> {
     let exe_path = source_path.replace(".cay", ".exe");
     let ir_path = source_path.replace(".cay", ".ll");
 
     // 1. 编译 EOL -> EXE (使用 release 版本)
     let output = Command::new("./target/release/cayc.exe")
+        .args(cayc_args)
         .args(&[source_path, &exe_path])
         .output()
         .map_err(|e| format!("Failed to execute cayc: {}", e))?;
@@ -104,6 +110,36 @@ fn compile_and_run_expect_error(source_path: &str) -> Result<String, String> {
     Err("Expected execution to fail, but it succeeded".to_string())
 }
 
+/// 用 cay-run 编译并运行 EOL 文件，向程序传递命令行参数和标准输入，返回标准输出和退出码
+fn run_with_io(source_path: &str, program_args: &[&str], stdin: &str) -> Result<(String, i32), String> {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let mut child = Command::new("./target/release/cay-run.exe")
+        .arg(source_path)
+        .arg("--")
+        .args(program_args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to execute cay-run: {}", e))?;
+    child.stdin.take().unwrap().write_all(stdin.as_bytes())
+        .map_err(|e| format!("Failed to write stdin: {}", e))?;
+    let output = child.wait_with_output()
+        .map_err(|e| format!("Failed to wait for cay-run: {}", e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    Ok((stdout, output.status.code().unwrap_or(-1)))
+}
+
+/// 同 `run_with_io`，标准输入取自与源文件同名的 `.in` 文件
+fn run_with_stdin_fixture(source_path: &str, program_args: &[&str]) -> Result<(String, i32), String> {
+    let fixture = source_path.replace(".cay", ".in");
+    let stdin = fs::read_to_string(&fixture).map_err(|e| format!("Failed to read {}: {}", fixture, e))?;
+    run_with_io(source_path, program_args, &stdin)
+}
+
 #[test]
 fn test_hello_example() {
     let output = compile_and_run_eol("examples/hello.cay").expect("hello.cay should compile and run");
@@ -497,11 +533,11 @@ fn test_string_methods() {
             "substring(0, 5) should be 'Hello', got: {}", output);
     assert!(output.contains("indexOf(World): 7"),
             "indexOf('World') should be 7, got: {}", output);
-    // charAt 返回 ASCII 码值（H=72, W=87）
-    assert!(output.contains("charAt(0): 72"),
-            "charAt(0) should return ASCII 72, got: {}", output);
-    assert!(output.contains("charAt(7): 87"),
-            "charAt(7) should return ASCII 87, got: {}", output);
+    // charAt 返回字符，打印字符本身
+    assert!(output.contains("charAt(0): H"),
+            "charAt(0) should return 'H', got: {}", output);
+    assert!(output.contains("charAt(7): W"),
+            "charAt(7) should return 'W', got: {}", output);
     assert!(output.contains("replace result: Hello, EOL!"),
             "replace result should be 'Hello, EOL!', got: {}", output);
     assert!(output.contains("All tests completed!"),
@@ -514,7 +550,9 @@ fn test_overload() {
     // 测试方法重载 - 注意：EOL 的重载可能通过参数类型推断实现
     assert!(output.contains("Testing method overloading:"),
             "Should show overloading test header, got: {}", output);
-    // 由于 EOL 可能不完全支持方法重载，检查基本输出即可
+    // 类中的 print 重载遮蔽内置 print，调用按参数类型分派到用户方法
+    assert!(output.contains("int: 42") && output.contains("string: Hello") && output.contains("two ints: 10, 20"),
+            "User-defined print overloads should be called, got: {}", output);
     assert!(output.contains("All overload tests completed!"),
             "All overload tests should complete, got: {}", output);
 }
@@ -727,28 +765,6 @@ fn test_method_chaining() {
 
 // ==================== 错误测试 ====================
 
-#[test]
-fn test_error_string_plus_int() {
-    let error = compile_eol_expect_error("examples/errors/error_string_plus_int.cay")
-        .expect("string + int should fail to compile");
-    assert!(
-        error.contains("Cannot add") || error.contains("string") || error.contains("type"),
-        "Should report type error for string + int, got: {}",
-        error
-    );
-}
-
-#[test]
-fn test_error_string_plus_float() {
-    let error = compile_eol_expect_error("examples/errors/error_string_plus_float.cay")
-        .expect("string + float should fail to compile");
-    assert!(
-        error.contains("Cannot add") || error.contains("string") || error.contains("type"),
-        "Should report type error for string + float, got: {}",
-        error
-    );
-}
-
 #[test]
 fn test_error_type_mismatch_assign() {
     let error = compile_eol_expect_error("examples/errors/error_type_mismatch_assign.cay")
@@ -877,9 +893,9 @@ fn test_type_casting_comprehensive() {
             "char to int cast should work, got: {}", output);
     assert!(output.contains("long 2147483647L to int: 2147483647"),
             "long to int cast should work, got: {}", output);
-    assert!(output.contains("double array elements: 1.000000, 2.500000, 3.000000"),
+    assert!(output.contains("double array elements: 1.0, 2.5, 3.0"),
             "Array element type conversion should work, got: {}", output);
-    assert!(output.contains("int 42 explicitly to double: 42.000000"),
+    assert!(output.contains("int 42 explicitly to double: 42.0"),
             "int to double explicit cast should work, got: {}", output);
     assert!(output.contains("double 42.0 explicitly to int: 42"),
             "double to int explicit cast should work, got: {}", output);
@@ -1123,7 +1139,7 @@ fn test_string_replace() {
 #[test]
 fn test_string_charat() {
     let output = compile_and_run_eol("examples/test_string_charat.cay").expect("string charat example should compile and run");
-    assert!(output.contains("charAt(0) = 65") && output.contains("charAt(2) = 67"),
+    assert!(output.contains("charAt(0) = A") && output.contains("charAt(2) = C"),
             "String charAt should work, got: {}", output);
 }
 
@@ -1248,7 +1264,7 @@ fn test_cast_char_to_int() {
 #[test]
 fn test_cast_int_to_char() {
     let output = compile_and_run_eol("examples/test_cast_int_to_char.cay").expect("cast int to char example should compile and run");
-    assert!(output.contains("65") && output.contains("97"),
+    assert!(output.contains("A\na\n"),
             "Cast int to char should work, got: {}", output);
 }
 
@@ -1731,6 +1747,25 @@ fn test_error_modulo_by_zero() {
     );
 }
 
+#[test]
+fn test_error_stack_overflow_guard() {
+    let error = compile_and_run_expect_error_with_args(
+        "examples/errors/error_stack_overflow_guard.cay",
+        &["--stack-guard=1000", "--stack-size", "8M"],
+    ).expect("unbounded recursion should be stopped by the stack guard");
+    assert!(
+        error.contains("sum again: 125250"),
+        "Returning calls should release their depth, got: {}",
+        error
+    );
+    assert!(
+        error.contains("StackOverflow in method Main.forever"),
+        "Should report the overflowing method, got: {}",
+        error
+    );
+    assert!(!error.contains("unreachable"), "Program should exit at the overflow, got: {}", error);
+}
+
 #[test]
 fn test_error_undefined_method() {
     let error = compile_eol_expect_error("examples/errors/error_undefined_method.cay")
@@ -1967,6 +2002,14 @@ fn test_include_pragma_once() {
             "Pragma once test should pass (multiple includes handled correctly), got: {}", output);
 }
 
+#[test]
+fn test_include_statement() {
+    let output = compile_and_run_eol("examples/test_include_statement.cay").expect("include statement should compile and run");
+    assert!(output.contains("square(4) = 16"), "Included helper class should be usable, got: {}", output);
+    assert!(output.contains("Helper.add(1, 2) = 3"), "include and #include should share deduplication, got: {}", output);
+    assert!(output.contains("Include statement test PASSED!"), "Include statement test should pass, got: {}", output);
+}
+
 #[test]
 fn test_error_include_cycle() {
     let error = compile_eol_expect_error("examples/errors/error_include_cycle.cay")
@@ -2013,10 +2056,10 @@ fn test_auto_inference() {
             "auto inference test should start, got: {}", output);
     assert!(output.contains("42"),
             "auto a = 42 should output 42, got: {}", output);
-    assert!(output.contains("1"),  // true is printed as 1
-            "auto flag = true should output 1, got: {}", output);
-    assert!(output.contains("88"),  // 'X' is ASCII 88
-            "auto c = 'X' should output 88, got: {}", output);
+    assert!(output.contains("true"),
+            "auto flag = true should output true, got: {}", output);
+    assert!(output.contains("X\n"),
+            "auto c = 'X' should output X, got: {}", output);
     assert!(output.contains("50"),
             "auto result = a + 8 should output 50, got: {}", output);
 }
@@ -2148,3 +2191,968 @@ fn test_0_4_3_simple() {
     assert!(output.contains("9999"),
             "Should output 9999 (final marker), got: {}", output);
 }
+
+#[test]
+fn test_identifier_collisions() {
+    let output = compile_and_run_eol("examples/test_identifier_collisions.cay").expect("identifier collisions should compile and run");
+    assert!(output.contains("15"),
+            "Should output 15 (printf + this_ptr + strlen), got: {}", output);
+    assert!(output.contains("100"),
+            "Should output 100 (count_static method beside count field), got: {}", output);
+    assert!(output.contains("11"),
+            "Should output 11 (f_i(1) distinct from f(int, int)), got: {}", output);
+}
+
+#[test]
+fn test_error_reserved_identifier() {
+    let error = compile_eol_expect_error("examples/errors/error_reserved_identifier.cay")
+        .expect("reserved identifier should fail to compile");
+    assert!(
+        error.contains("__cay_string_concat") && error.contains("保留"),
+        "Should report reserved identifier error, got: {}",
+        error
+    );
+}
+
+#[test]
+fn test_bom_crlf_source() {
+    let output = compile_and_run_eol("examples/test_bom_crlf.cay").expect("BOM + CRLF source should compile and run");
+    assert!(output.contains("42"),
+            "Should output 42, got: {}", output);
+    assert!(output.contains("BOM and CRLF OK"),
+            "Should output BOM and CRLF OK, got: {}", output);
+}
+
+#[test]
+fn test_var_inference() {
+    let output = compile_and_run_eol("examples/test_var_inference.cay").expect("var inference should compile and run");
+    assert!(output.contains("cavvy"),
+            "Should output cavvy (var from call), got: {}", output);
+    assert!(output.contains("3000000000"),
+            "Should output 3000000000 (long literal), got: {}", output);
+    assert!(output.contains("1.0\n"),
+            "Should output 1.0 (promoted double array), got: {}", output);
+}
+
+#[test]
+fn test_error_var_without_initializer() {
+    let error = compile_eol_expect_error("examples/errors/error_var_without_initializer.cay")
+        .expect("var without initializer should fail to compile");
+    assert!(
+        error.contains("requires an initializer"),
+        "Should report missing initializer error, got: {}",
+        error
+    );
+}
+
+#[test]
+fn test_null_safety_operators() {
+    let output = compile_and_run_eol("examples/test_null_safety.cay").expect("null safety operators should compile and run");
+    assert!(output.contains("none"),
+            "Should output none (?? default), got: {}", output);
+    assert!(output.contains("elvis"),
+            "Should output elvis (?: default), got: {}", output);
+    assert!(output.contains("no label"),
+            "Should output no label (?. on null object), got: {}", output);
+    assert!(output.contains("end of list"),
+            "Should output end of list (chained ?.), got: {}", output);
+    assert_eq!(output.matches("fallback evaluated").count(), 1,
+            "Default should only be evaluated when the value is null, got: {}", output);
+}
+
+#[test]
+fn test_class_field_layout() {
+    let output = compile_and_run_eol("examples/test_class_layout.cay").expect("class layout example should compile and run");
+    assert!(output.contains("circle#c"), "Parent method should read inherited fields, got: {}", output);
+    assert!(output.contains("id: 7"), "Should output int field, got: {}", output);
+    assert!(output.contains("tag: c, unit: m"), "Should output char fields, got: {}", output);
+    assert!(output.contains("12.5"), "Should output double field, got: {}", output);
+    assert!(output.contains("radius: 3000000000"), "Should output long field, got: {}", output);
+    assert!(output.contains("chain: 1 2 3"), "Should support chained field access, got: {}", output);
+}
+
+#[test]
+fn test_virtual_dispatch() {
+    let output = compile_and_run_eol("examples/test_virtual_dispatch.cay").expect("virtual dispatch example should compile and run");
+    assert!(output.contains("...\n2\nWoof\n3\nYip\n4"), "Overridden methods should dispatch on the runtime class, got: {}", output);
+    assert!(output.contains("says: ...\nsays: Woof\nsays: Yip"), "Implicit this calls should dispatch virtually, got: {}", output);
+}
+
+#[test]
+fn test_interface_dispatch() {
+    let output = compile_and_run_eol("examples/test_interfaces.cay").expect("interface example should compile and run");
+    assert!(output.contains("base\n12.0\nsquare\n36.0"), "Interface calls should dispatch to the implementing class, got: {}", output);
+    assert!(output.contains("square\nbase"), "Variables typed as an interface should accept any implementing class, got: {}", output);
+}
+
+#[test]
+fn test_error_interface_not_implemented() {
+    let error = compile_eol_expect_error("examples/errors/error_interface_not_implemented.cay")
+        .expect("missing interface method should fail to compile");
+    assert!(
+        error.contains("must implement method 'double area()' from interface 'Shape'"),
+        "Should report the missing interface method, got: {}",
+        error
+    );
+}
+
+#[test]
+fn test_escape_analysis_stack_allocation() {
+    let output = compile_and_run_eol("examples/test_escape_analysis.cay").expect("escape analysis example should compile and run");
+    assert!(output.contains("stack total: 5000050000"),
+            "Stack-allocated objects should be re-zeroed on every iteration, got: {}", output);
+    assert!(output.contains("returned: 7"), "Returned object should stay valid, got: {}", output);
+    assert!(output.contains("passed: 7"), "Passed object should stay valid, got: {}", output);
+    assert!(output.contains("stored: 9"), "Stored object should stay valid, got: {}", output);
+}
+
+#[test]
+fn test_error_null_safe_primitive() {
+    let error = compile_eol_expect_error("examples/errors/error_null_safe_primitive.cay")
+        .expect("primitive safe navigation without ?? should fail to compile");
+    assert!(
+        error.contains("cannot be null"),
+        "Should report non-nullable safe navigation result, got: {}",
+        error
+    );
+}
+
+#[test]
+fn test_error_chained_comparison() {
+    let error = compile_eol_expect_error("examples/errors/error_chained_comparison.cay")
+        .expect("chained comparison should fail to compile");
+    assert!(
+        error.contains("Chained comparison") && error.contains("a < b && b < c"),
+        "Should report chained comparison with a suggestion, got: {}",
+        error
+    );
+}
+
+#[test]
+fn test_string_equality() {
+    let output = compile_and_run_eol("examples/test_string_equality.cay").expect("string equality example should compile and run");
+    assert!(output.contains("concat equal\nliteral differs\nparam equal"), "String == should compare contents, got: {}", output);
+    assert!(output.contains("null check\nnull differs\ndone"), "Comparisons with null should still work, got: {}", output);
+    assert!(!output.contains("unreachable"), "Equal strings should not compare as different, got: {}", output);
+}
+
+#[test]
+fn test_char_bool_array_io() {
+    let output = compile_and_run_eol("examples/test_char_bool_array_io.cay").expect("char/boolean array example should compile and run");
+    assert!(output.contains("Cavvy\nok!\n"), "char[] should print as a string, got: {}", output);
+    assert!(output.contains("[true, false, true]\n[false, false, true]\n[]\n"), "boolean[] should print its elements, got: {}", output);
+    assert!(output.contains("yvvaC\n2"), "1-byte array elements should round-trip, got: {}", output);
+}
+
+#[test]
+fn test_error_read_chars_type() {
+    let error = compile_eol_expect_error("examples/errors/error_read_chars_type.cay")
+        .expect("readChars with a non-char[] argument should fail to compile");
+    assert!(error.contains("char[]"), "Should report readChars argument type error, got: {}", error);
+}
+
+#[test]
+fn test_switch_long_cases() {
+    let output = compile_and_run_eol("examples/test_switch_long_cases.cay").expect("long case labels example should compile and run");
+    assert!(output.contains("long max\nlong min\n2^32\nten\nminus one\nzero\nother\n"), "long switch should cover the full i64 range, got: {}", output);
+    assert!(output.contains("int min\nint max\nminus one\nfive\nother"), "int switch should sign-extend to i64, got: {}", output);
+}
+
+#[test]
+fn test_error_switch_case_out_of_range() {
+    let error = compile_eol_expect_error("examples/errors/error_switch_case_out_of_range.cay")
+        .expect("case value outside the int range should fail to compile");
+    assert!(error.contains("out of range"), "Should report case value out of range, got: {}", error);
+}
+
+#[test]
+fn test_error_switch_duplicate_case() {
+    let error = compile_eol_expect_error("examples/errors/error_switch_duplicate_case.cay")
+        .expect("duplicate case labels should fail to compile");
+    assert!(error.contains("duplicate case label"), "Should report duplicate case label, got: {}", error);
+}
+
+#[test]
+fn test_final_varargs_params() {
+    let output = compile_and_run_eol("examples/test_final_varargs_params.cay").expect("final/varargs parameter example should compile and run");
+    assert!(output.contains("2.5\n0.0\n"), "double varargs should be packed as double[], got: {}", output);
+    assert!(output.contains("words: final and varargs\n3\nnone:\n0\n"), "String varargs should support .length and empty calls, got: {}", output);
+    assert!(output.contains("6\n7\n30\n3"), "long varargs and array pass-through should work, got: {}", output);
+}
+
+#[test]
+fn test_error_final_parameter() {
+    let error = compile_eol_expect_error("examples/errors/error_final_parameter.cay")
+        .expect("assigning to a final parameter should fail to compile");
+    assert!(error.contains("final variable 'limit'"), "Should report final parameter assignment, got: {}", error);
+}
+
+#[test]
+fn test_generics() {
+    let output = compile_and_run_eol("examples/test_generics.cay").expect("generics example should compile and run");
+    assert!(output.contains("42\nhello\npi\n3.5\n42\n"), "generic classes should be instantiated per type argument, got: {}", output);
+    assert!(output.contains("7\ngeneric\n5\n10000000000"), "generic methods should infer type arguments, got: {}", output);
+}
+
+#[test]
+fn test_error_generic_arity() {
+    let error = compile_eol_expect_error("examples/errors/error_generic_arity.cay")
+        .expect("wrong number of type arguments should fail to compile");
+    assert!(error.contains("expects 2 type argument(s), got 1"), "Should report type argument count mismatch, got: {}", error);
+}
+
+#[test]
+fn test_static_instance_calls() {
+    let output = compile_and_run_eol("examples/test_static_instance_calls.cay").expect("static/instance call example should compile and run");
+    assert!(output.contains("8\n42\n1\n3\n81\n9"), "static methods should be callable via class or object, got: {}", output);
+}
+
+#[test]
+fn test_error_static_call_instance_method() {
+    let error = compile_eol_expect_error("examples/errors/error_static_call_instance_method.cay")
+        .expect("calling an instance method through the class name should fail to compile");
+    assert!(error.contains("non-static method inc()"), "Should report static reference to instance method, got: {}", error);
+}
+
+#[test]
+fn test_error_instance_method_static_context() {
+    let error = compile_eol_expect_error("examples/errors/error_instance_method_static_context.cay")
+        .expect("calling an instance method from a static method without an object should fail to compile");
+    assert!(error.contains("non-static method helper()"), "Should report instance method in static context, got: {}", error);
+}
+
+#[test]
+fn test_math_builtins() {
+    let output = compile_and_run_eol("examples/test_math.cay").expect("Math example should compile and run");
+    assert!(output.contains("4.0\n1024.0\n3.0\n4.0\n3\n"), "Math double functions should be lowered to intrinsics, got: {}", output);
+    assert!(output.contains("5\n7\n1.5\n3\n8\n2.5\n4\n9.0\n"), "abs/min/max should pick overloads by argument type, got: {}", output);
+    assert!(output.contains("random ok\nPI ok\nE ok"), "Math.random and constants should work, got: {}", output);
+}
+
+#[test]
+fn test_error_math_instantiate() {
+    let error = compile_eol_expect_error("examples/errors/error_math_instantiate.cay")
+        .expect("instantiating Math should fail to compile");
+    assert!(error.contains("Cannot instantiate builtin class 'Math'"), "Should reject new Math(), got: {}", error);
+}
+
+#[test]
+fn test_error_math_wrong_args() {
+    let error = compile_eol_expect_error("examples/errors/error_math_wrong_args.cay")
+        .expect("Math.sqrt with a String argument should fail to compile");
+    assert!(error.contains("Method 'sqrt' in class 'Math' cannot be applied"), "Should report Math argument mismatch, got: {}", error);
+}
+
+#[test]
+fn test_error_builtin_arity() {
+    let error = compile_eol_expect_error("examples/errors/error_builtin_arity.cay")
+        .expect("print without arguments should fail to compile");
+    assert!(error.contains("print() expects at least 1 argument, got 0"), "Should report builtin arity, got: {}", error);
+}
+
+#[test]
+fn test_error_builtin_no_args() {
+    let error = compile_eol_expect_error("examples/errors/error_builtin_no_args.cay")
+        .expect("readInt with an argument should fail to compile");
+    assert!(error.contains("readInt() takes no arguments, got 1"), "Should report builtin arity, got: {}", error);
+}
+
+#[test]
+fn test_error_builtin_print_type() {
+    let error = compile_eol_expect_error("examples/errors/error_builtin_print_type.cay")
+        .expect("printing a function value should fail to compile");
+    assert!(error.contains("println() cannot be applied to argument type(s) (fn(int) -> int)"), "Should report unprintable type, got: {}", error);
+}
+
+#[test]
+fn test_string_methods_extended() {
+    let output = compile_and_run_eol("examples/test_string_methods_extended.cay").expect("extended string methods example should compile and run");
+    assert!(output.contains("trim: [Hello, Cavvy World]"), "String trim should work, got: {}", output);
+    assert!(output.contains("HELLO, CAVVY WORLD") && output.contains("hello, cavvy world"),
+            "String case conversion should work, got: {}", output);
+    assert!(output.contains("startsWith Hello: yes") && output.contains("endsWith World: yes")
+            && output.contains("contains Cavvy: yes") && output.contains("contains Java: no"),
+            "String predicates should work, got: {}", output);
+    assert!(output.contains("parts: 4") && output.contains("[cherry]"), "String split should drop trailing empties, got: {}", output);
+    assert!(output.contains("single: 1") && output.contains("chars: 3") && output.contains("KEY -> value"),
+            "String split edge cases should work, got: {}", output);
+}
+
+#[test]
+fn test_error_string_method_arg() {
+    let error = compile_eol_expect_error("examples/errors/error_string_method_arg.cay")
+        .expect("startsWith with an int argument should fail to compile");
+    assert!(error.contains("Argument of startsWith() must be string, got int"), "Should report String method argument type, got: {}", error);
+}
+
+#[test]
+fn test_lambda_closure() {
+    let output = compile_and_run_eol("examples/test_lambda_closure.cay").expect("lambda closure example should compile and run");
+    assert!(output.contains("static closures created") && output.contains("loop closures: 3"),
+            "Capturing lambdas should be created, got: {}", output);
+    assert!(output.contains("scaled closures created"), "Lambdas capturing this should be created, got: {}", output);
+}
+
+#[test]
+fn test_error_lambda_capture_not_final() {
+    let error = compile_eol_expect_error("examples/errors/error_lambda_capture_not_final.cay")
+        .expect("capturing a reassigned local should fail to compile");
+    assert!(error.contains("Local variable 'count' referenced from a lambda expression must be final or effectively final"),
+            "Should report non-effectively-final capture, got: {}", error);
+}
+
+#[test]
+fn test_main_exit_code() {
+    // int main() 的返回值作为退出码，非零退出码按执行失败返回
+    let output = compile_and_run_expect_error("examples/test_main_exit_code.cay")
+        .expect("int main() returning 5 should exit with a non-zero status");
+    assert!(output.contains("runtime error") && output.contains("vowels: 5"), "int main() should run and exit with its return value, got: {}", output);
+}
+
+#[test]
+fn test_error_main_signature() {
+    let error = compile_eol_expect_error("examples/errors/error_main_signature.cay")
+        .expect("main returning String should fail to compile");
+    assert!(error.contains("Unsupported main signature 'public static string main()'"), "Should report unsupported main signature, got: {}", error);
+    assert!(error.contains("accepted forms are 'public static void main()'") && error.contains("'public static int main(String[] args)'"),
+            "Should list accepted main forms, got: {}", error);
+}
+
+#[test]
+fn test_builtin_shadowing() {
+    let output = compile_and_run_eol("examples/test_builtin_shadowing.cay").expect("builtin shadowing example should compile and run");
+    assert!(output.contains("builtin println in main"), "Unshadowed println should use the builtin, got: {}", output);
+    assert!(output.contains("[log] direct call") && output.contains("[log] status = ok"),
+            "Calls inside the class should resolve to its own println, got: {}", output);
+    assert!(output.contains("[audit] login"), "Inherited println should shadow the builtin in subclasses, got: {}", output);
+}
+
+#[test]
+fn test_error_shadowed_builtin_args() {
+    let error = compile_eol_expect_error("examples/errors/error_shadowed_builtin_args.cay")
+        .expect("calling a shadowing print with the wrong argument type should fail to compile");
+    assert!(error.contains("Method 'print' in class 'Console' cannot be applied to given types: argument mismatch"),
+            "Should check arguments against the user method, got: {}", error);
+}
+
+#[test]
+fn test_function_types() {
+    let output = compile_and_run_eol("examples/test_function_types.cay").expect("function types example should compile and run");
+    assert!(output.contains("say: hi") && output.contains("Hello!"), "Void lambdas should be callable, got: {}", output);
+    assert!(output.contains("144") && output.contains("123"), "Static and bound method references should be callable, got: {}", output);
+    assert!(output.contains("positive") && output.contains("non-positive"), "Block lambdas should return through the function type, got: {}", output);
+    assert!(output.contains("2.5\n"), "Lambda without target type should infer its return type, got: {}", output);
+    assert!(output.contains("50") && output.contains("210"), "Higher-order functions should compose closures, got: {}", output);
+}
+
+#[test]
+fn test_error_lambda_return_type() {
+    let error = compile_eol_expect_error("examples/errors/error_lambda_return_type.cay")
+        .expect("lambda returning int for a String function type should fail to compile");
+    assert!(error.contains("Bad return type in lambda expression: int cannot be converted to string"),
+            "Should report lambda return type mismatch, got: {}", error);
+}
+
+#[test]
+fn test_contextual_keywords() {
+    let output = compile_and_run_eol("examples/test_contextual_keywords.cay").expect("contextual keywords example should compile and run");
+    assert!(output.contains("21"), "var/let/auto/fn/record/yield should be usable as variable names, got: {}", output);
+    assert!(output.contains("31"), "var/let/auto should still declare variables, got: {}", output);
+    assert!(output.contains("123") && output.contains("10"), "Contextual keywords should be usable as field and method names, got: {}", output);
+}
+
+/// 调用 cayc 编译，返回进程退出码
+fn cayc_exit_code(args: &[&str]) -> Option<i32> {
+    let output = Command::new("./target/release/cayc.exe")
+        .args(args)
+        .output()
+        .expect("Failed to execute cayc");
+    let _ = fs::remove_file("target/exit_code_test.exe");
+    let _ = fs::remove_file("target/exit_code_test.ll");
+    output.status.code()
+}
+
+#[test]
+fn test_compiler_exit_codes() {
+    // 退出码区分失败阶段：64 用法错误，2 词法/语法错误，3 语义错误，4 代码生成错误
+    assert_eq!(cayc_exit_code(&["--no-such-option", "examples/hello.cay"]), Some(64));
+    assert_eq!(cayc_exit_code(&[]), Some(64));
+    assert_eq!(cayc_exit_code(&["examples/errors/error_unclosed_string.cay", "target/exit_code_test.exe"]), Some(2));
+    assert_eq!(cayc_exit_code(&["examples/errors/error_lambda_return_type.cay", "target/exit_code_test.exe"]), Some(3));
+    assert_eq!(cayc_exit_code(&["examples/errors/error_break_outside_loop.cay", "target/exit_code_test.exe"]), Some(3));
+    assert_eq!(cayc_exit_code(&["examples/errors/error_invalid_cast.cay", "target/exit_code_test.exe"]), Some(4));
+    assert_eq!(cayc_exit_code(&["examples/no_such_file.cay", "target/exit_code_test.exe"]), Some(1));
+    assert_eq!(cayc_exit_code(&["examples/hello.cay", "target/exit_code_test.exe"]), Some(0));
+}
+
+#[test]
+fn test_constant_folding() {
+    let output = compile_and_run_eol("examples/test_constant_folding.cay").expect("constant folding example should compile and run");
+    assert!(output.contains("4096") && output.contains("2147483648") && output.contains("0.75\n"), "static final constants should be folded, got: {}", output);
+    assert!(output.contains("-2147483648"), "Folded int overflow should wrap around, got: {}", output);
+    assert!(output.contains("42"), "Reassigned variables must not be propagated, got: {}", output);
+    assert!(output.contains("size ok") && output.contains("folded"), "Constant conditions should select the live branch, got: {}", output);
+    assert!(!output.contains("unreachable") && !output.contains("never"), "Dead branches should not run, got: {}", output);
+}
+
+#[test]
+fn test_error_execution_limits() {
+    let error = compile_and_run_expect_error_with_args(
+        "examples/errors/error_execution_limits.cay",
+        &["--max-steps", "10000"],
+    ).expect("an infinite loop should be stopped by the step limit");
+    assert!(error.contains("finite loop: 5050"), "Finite loops should run normally, got: {}", error);
+    assert!(
+        error.contains("Execution limit exceeded: more than 10000 steps (in while loop at line 14)"),
+        "Should report the exceeded step limit, got: {}",
+        error
+    );
+
+    let error = compile_and_run_expect_error_with_args(
+        "examples/errors/error_heap_limit.cay",
+        &["--max-heap", "1M"],
+    ).expect("unbounded allocation should be stopped by the heap limit");
+    assert!(error.contains("small allocation ok: 16"), "Small allocations should succeed, got: {}", error);
+    assert!(
+        error.contains("Execution limit exceeded: heap allocation exceeds 1048576 bytes"),
+        "Should report the exceeded heap limit, got: {}",
+        error
+    );
+}
+
+#[test]
+fn test_string_conversion() {
+    let output = compile_and_run_eol("examples/test_string_conversion.cay").expect("string conversion example should compile and run");
+    assert!(output.contains("Pi: 3.14\n"), "String + float should convert, got: {}", output);
+    assert!(output.contains("Value: 42"), "String + int should convert, got: {}", output);
+    assert!(output.contains("Bool: true, false"), "String + boolean should convert, got: {}", output);
+    assert!(output.contains("Char: A"), "String + char should convert, got: {}", output);
+    assert!(output.contains("7 days"), "int + String should convert, got: {}", output);
+    assert!(output.contains("p = (3, 4)"), "Objects should convert through toString(), got: {}", output);
+    assert!(output.contains("q = (1, 0, 0)"), "Overridden toString() should be called, got: {}", output);
+    assert!(output.contains("none = null"), "null objects should convert to null, got: {}", output);
+    assert!(output.contains("plain = Plain{value=7}"), "Classes without toString() print their fields, got: {}", output);
+}
+
+#[test]
+fn test_print_precision() {
+    let output = compile_and_run_eol("examples/test_print_precision.cay").expect("print precision example should compile and run");
+    assert!(output.contains("3.14159265358979\n0.5\n0.1\n0.30000000000000004\n1e+20\n"), "Floats should print in the shortest round-trip form by default, got: {}", output);
+    assert!(output.contains("3.142\n3.14159265358979\n"), "println(value, digits) should only affect one line, got: {}", output);
+    assert!(output.contains("3.14\npi = 3.14\n"), "setPrintPrecision(2) should apply to println and concatenation, got: {}", output);
+    assert!(output.contains("3.1415926536\nhalf = 0.5000000000\n"), "setPrintPrecision(10) should print 10 digits, got: {}", output);
+    assert!(output.contains("0.33333333333333331483"), "Precision above 20 should be clamped, got: {}", output);
+    assert!(output.contains("100000000000000000000.000000"), "Large values should not be truncated, got: {}", output);
+}
+
+#[test]
+fn test_import_modules() {
+    let output = compile_and_run_eol("examples/test_import.cay").expect("import example should compile and run");
+    assert!(output.contains("square(7) = 49"), "Classes from imported files should be usable, got: {}", output);
+    assert!(output.contains("area(3, 4) = 12"), "Classes from imported files should be usable, got: {}", output);
+    assert!(output.contains("calls = 3"), "A module imported twice should be loaded once, got: {}", output);
+}
+
+#[test]
+fn test_error_import_missing() {
+    let error = compile_eol_expect_error("examples/errors/error_import_missing.cay")
+        .expect("importing a missing file should fail to compile");
+    assert!(
+        error.contains("Cannot find module \"modules/no_such_module.cay\""),
+        "Should report the missing module, got: {}",
+        error
+    );
+}
+
+#[test]
+fn test_bits_builtins() {
+    let output = compile_and_run_eol("examples/test_bits.cay").expect("Bits example should compile and run");
+    assert!(output.contains("popcount(0xF0) = 4\npopcount(-1) = 32\npopcount(-1L) = 64\n"), "popcount should count set bits, got: {}", output);
+    assert!(output.contains("leadingZeros(1) = 31\nleadingZeros(0) = 32\nleadingZeros(1L << 40) = 23\n"), "leadingZeros should match Java, got: {}", output);
+    assert!(output.contains("trailingZeros(0xF0) = 4\ntrailingZeros(0L) = 64\ntrailingZeros(1L << 40) = 40\n"), "trailingZeros should match Java, got: {}", output);
+    assert!(output.contains("rotateLeft(0x80000001, 1) = 3\nrotateRight(1, 1) = -2147483648\n"), "rotations should wrap around, got: {}", output);
+    assert!(output.contains("rotateLeft(1, 33) = 2\nrotateLeft(1, -1) = -2147483648\n"), "rotation distance should be taken modulo the width, got: {}", output);
+    assert!(output.contains("hash = -1220669455"), "hash mixing should work, got: {}", output);
+}
+
+#[test]
+fn test_error_bits_wrong_args() {
+    let error = compile_eol_expect_error("examples/errors/error_bits_wrong_args.cay")
+        .expect("Bits.popcount with a double argument should fail to compile");
+    assert!(error.contains("Method 'popcount' in class 'Bits' cannot be applied"), "Should report Bits argument mismatch, got: {}", error);
+}
+
+#[test]
+fn test_exact_narrowing() {
+    let output = compile_and_run_eol("examples/test_exact_narrowing.cay").expect("exact narrowing example should compile and run");
+    assert!(output.contains("toIntExact(2147483647L) = 2147483647"), "Values in range should convert unchanged, got: {}", output);
+    assert!(output.contains("toIntExact(-2147483648L) = -2147483648"), "Values in range should convert unchanged, got: {}", output);
+    assert!(output.contains("toByteExact(-128) = -128"), "Values in range should convert unchanged, got: {}", output);
+    assert!(output.contains("(int) 4294967297L = 1"), "Casts should still truncate silently, got: {}", output);
+}
+
+#[test]
+fn test_error_narrowing_overflow() {
+    let error = compile_and_run_expect_error("examples/errors/error_narrowing_overflow.cay")
+        .expect("toIntExact with an out-of-range value should fail at runtime");
+    assert!(
+        error.contains("toIntExact() value 3000000000 out of int range"),
+        "Should report the out-of-range value, got: {}",
+        error
+    );
+}
+
+#[test]
+fn test_debug_print() {
+    let output = compile_and_run_eol("examples/test_debug_print.cay").expect("debugPrint example should compile and run");
+    assert!(output.contains("[line 33] a * (2 + 1): int = 9"), "Should print source text, type and value, got: {}", output);
+    assert!(output.contains("s + \"!\": String = \"hi!\""), "Strings should be quoted, got: {}", output);
+    assert!(output.contains("missing: String = null"), "Null strings should print null, got: {}", output);
+    assert!(output.contains("words: String[] = [\"a\", null]"), "Arrays should print elementwise, got: {}", output);
+    assert!(output.contains("seg: Segment = Segment{from=Point{x=1, y=2}, to=null, label=\"diag\"}"), "Objects without toString should dump fields, got: {}", output);
+    assert!(output.contains("tag: Tag = #urgent"), "Objects with toString should use it, got: {}", output);
+}
+
+#[test]
+fn test_reflect_print() {
+    let output = compile_and_run_eol("examples/test_reflect_print.cay").expect("reflection printing example should compile and run");
+    assert!(output.contains("Point{x=1, y=2}"), "Objects without toString should print fields, got: {}", output);
+    assert!(output.contains("p = Point{x=1, y=2}"), "String concatenation should use the same format, got: {}", output);
+    assert!(output.contains("Shape{name=triangle, origin=Point{x=1, y=2}, sides=[3, 4, 5]}"), "Nested objects and arrays should expand, got: {}", output);
+    assert!(output.contains("Circle{name=circle, origin=null, sides=null, radius=0.0}"), "The runtime class should be used, got: {}", output);
+    assert!(output.contains("<box>"), "A subclass toString should be called, got: {}", output);
+    assert!(output.contains("[a, b]") && output.contains("[Point{x=1, y=2}, null]"), "Arrays should print elementwise, got: {}", output);
+    assert!(output.contains("cycles are cut off"), "Cycles should be cut off, got: {}", output);
+}
+
+#[test]
+fn test_mutual_class_references() {
+    let output = compile_and_run_eol("examples/test_mutual_class_references.cay").expect("mutually referencing classes should compile and run");
+    assert!(output.contains("9"), "Should follow fields across mutually referencing classes, got: {}", output);
+    assert!(output.contains("1"), "Should read the parent through a subclass field, got: {}", output);
+}
+
+#[test]
+fn test_run_with_stdin() {
+    let (output, code) = run_with_stdin_fixture("examples/test_run_stdin.cay", &["ignored", "--flag"]).expect("cay-run should compile and run");
+    assert_eq!(code, 0, "Program should exit successfully, got: {}", output);
+    assert!(output.contains("count: 4"), "Should read the count from stdin, got: {}", output);
+    assert!(output.contains("sum: 42"), "Should read every number from stdin, got: {}", output);
+
+    let (output, _) = run_with_io("examples/test_run_stdin.cay", &[], "2\n1\n2\n").expect("cay-run should compile and run");
+    assert!(output.contains("sum: 3"), "Should pipe inline stdin, got: {}", output);
+}
+
+#[test]
+fn test_ternary_lazy() {
+    let output = compile_and_run_eol("examples/test_ternary_lazy.cay").expect("ternary example should compile and run");
+    assert!(output.contains("calls: 0"), "Only the taken branch should be evaluated, got: {}", output);
+    assert!(output.contains("sum: 30"), "Should select the right branch values, got: {}", output);
+    assert!(output.contains("5.0\n"), "int and double branches should unify to double, got: {}", output);
+    assert!(output.contains("10000000000"), "int and long branches should unify to long, got: {}", output);
+    assert!(output.contains("big") && output.contains("null"), "A null branch should take the reference type, got: {}", output);
+    assert!(output.contains("sign: 1"), "Nested ternaries should work, got: {}", output);
+}
+
+#[test]
+fn test_super_calls() {
+    let output = compile_and_run_eol("examples/test_super_calls.cay").expect("super example should compile and run");
+    assert!(output.contains("Dog: rex says Woof (not ...)"), "super(args) and super.method() should work, got: {}", output);
+    assert!(output.contains("Dog: pup says Yip, Woof (not ...)"), "super calls should chain through ancestors, got: {}", output);
+    assert!(output.contains("4"), "super.field should read the inherited field, got: {}", output);
+    assert!(output.contains("nobody says ..."), "The parent's no-arg constructor should run implicitly, got: {}", output);
+    assert!(output.contains("cat says ..."), "new should run the matching constructor, got: {}", output);
+}
+
+#[test]
+fn test_this_expression() {
+    let output = compile_and_run_eol("examples/test_this_expression.cay").expect("this example should compile and run");
+    assert!(output.contains("sum: 7"), "this.field = param should assign the fields, got: {}", output);
+    assert!(output.contains("twice: 14"), "this.method() should call the instance method, got: {}", output);
+    assert!(output.contains("x: 13"), "Returning this should return the receiver, got: {}", output);
+    assert!(output.contains("same object"), "this should compare equal to the receiver, got: {}", output);
+}
+
+#[test]
+fn test_instance_field_receivers() {
+    let output = compile_and_run_eol("examples/test_instance_field_receivers.cay").expect("instance field example should compile and run");
+    assert!(output.contains("10\n2.0\n20\n30\n"), "Fields should be written through variables and field chains, got: {}", output);
+    assert!(output.contains("99"), "Fields should be written through array elements, got: {}", output);
+    assert!(output.contains("11\n2.5\n3"), "Methods should update fields through implicit this, got: {}", output);
+}
+
+#[test]
+fn test_byte_short() {
+    let output = compile_and_run_eol("examples/test_byte_short.cay").expect("byte/short example should compile and run");
+    assert!(output.contains("b=100, s=-3000"), "In-range constants should narrow to byte/short, got: {}", output);
+    assert!(output.contains("sum: -2900"), "byte + short should promote to int, got: {}", output);
+    assert!(output.contains("wrapped: -56") && output.contains("cut: 4464"), "Casts should truncate to 8/16 bits, got: {}", output);
+    assert!(output.contains("[-128, 2, -3]") && output.contains("total: -129"), "byte arrays should use 1-byte elements, got: {}", output);
+    assert!(output.contains("[0, 1234, 0]"), "short arrays should use 2-byte elements, got: {}", output);
+    assert!(output.contains("Pixel{level=-1, depth=512}"), "byte/short fields should be stored and printed, got: {}", output);
+    assert!(output.contains("twice: -6000"), "short parameters and returns should work, got: {}", output);
+    assert!(output.contains("counter: -128"), "byte increments should wrap, got: {}", output);
+}
+
+#[test]
+fn test_string_escapes() {
+    let output = compile_and_run_eol("examples/test_string_escapes.cay").expect("string escape example should compile and run");
+    assert!(output.contains("tab:[\t]"), "\\t should produce a tab, got: {}", output);
+    assert!(output.contains("quote:\"hi\" backslash:\\"), "Quote and backslash escapes should work, got: {}", output);
+    assert!(output.contains("unicode:ABC"), "\\uXXXX escapes should work, got: {}", output);
+    assert!(output.contains("accent bytes: 5") && output.contains("emoji bytes: 4"), "\\u escapes should be UTF-8 encoded, got: {}", output);
+    assert!(output.contains("chars:A'"), "Character escapes should work, got: {}", output);
+    assert!(output.contains("one\ntwo"), "\\n should produce a newline, got: {}", output);
+}
+
+#[test]
+fn test_format() {
+    let output = compile_and_run_eol("examples/test_format.cay").expect("format example should compile and run");
+    assert!(output.contains("Ada is 36 years old"), "%s and %d should be substituted, got: {}", output);
+    assert!(output.contains("pi=3.14 sci=1.234568e+04"), "Float precision and %e should work, got: {}", output);
+    assert!(output.contains("[   42] [42   ] [00042] [+42]"), "Width and flags should work, got: {}", output);
+    assert!(output.contains("hex=ff HEX=FF oct=10 alt=0xff"), "Hex and octal conversions should work, got: {}", output);
+    assert!(output.contains("byte hex=ff"), "%x of a byte should use the byte width, got: {}", output);
+    assert!(output.contains("char=Z bool=true 100%"), "%c, %b and %% should work, got: {}", output);
+    assert!(output.contains("(1, 2) -> (3, 4)"), "format() should work inside toString(), got: {}", output);
+    assert!(output.contains("none=null [abc]"), "Null strings and %s precision should work, got: {}", output);
+    assert!(output.contains("count=16"), "print() with a format string should work, got: {}", output);
+}
+
+#[test]
+fn test_empty_array_init() {
+    let output = compile_and_run_eol("examples/test_empty_array_init.cay").expect("empty array initializer example should compile and run");
+    assert!(output.contains("Cavvy 10 2.5 2.5"), "var/let/auto should infer types from initializers, got: {}", output);
+    assert!(output.contains("empty length: 0"), "Typed empty initializer should create an empty array, got: {}", output);
+    assert!(output.contains("words length: 0"), "var with a type annotation should accept {{}}, got: {}", output);
+    assert!(output.contains("rows: 0 2 2"), "Nested empty initializers should use the element type, got: {}", output);
+}
+
+#[test]
+fn test_definite_assignment() {
+    let output = compile_and_run_eol("examples/test_definite_assignment.cay").expect("definite assignment example should compile and run");
+    assert!(output.contains("grade: ABC"), "final variables assigned once per branch should work, got: {}", output);
+    assert!(output.contains("first above 5: 8") && output.contains("first above 50: -1"), "Assignments before break should count, got: {}", output);
+    assert!(output.contains("kind: 4"), "switch with default should assign on every path, got: {}", output);
+    assert!(output.contains("total: 3"), "do-while body should always assign, got: {}", output);
+}
+
+#[test]
+fn test_error_uninitialized_variable() {
+    let error = compile_eol_expect_error("examples/errors/error_uninitialized_variable.cay")
+        .expect("reading an uninitialized variable should fail to compile");
+    assert!(
+        error.contains("Variable 'count' may not have been initialized"),
+        "Should report use before initialization, got: {}",
+        error
+    );
+}
+
+#[test]
+fn test_error_final_assigned_twice() {
+    let error = compile_eol_expect_error("examples/errors/error_final_assigned_twice.cay")
+        .expect("assigning a final variable twice should fail to compile");
+    assert!(
+        error.contains("final variable 'limit': it may already have been assigned"),
+        "Should report the second assignment of a final variable, got: {}",
+        error
+    );
+}
+
+#[test]
+fn test_labeled_break() {
+    let output = compile_and_run_eol("examples/test_labeled_break.cay").expect("labeled break example should compile and run");
+    assert!(output.contains("case 1 breaks") && !output.contains("case 1 falls through"), "break nested in if should exit the switch, got: {}", output);
+    assert!(output.contains("pairs: 00 10 11 20 21 22 "), "continue/break with labels should target the outer loop, got: {}", output);
+    assert!(output.contains("n: 5"), "break with label should exit the loop around a switch, got: {}", output);
+    assert!(output.contains("first square above 20: 5") && output.contains("first square above 99999: -1"), "break with label should exit a block, got: {}", output);
+}
+
+#[test]
+fn test_error_undefined_label() {
+    let error = compile_eol_expect_error("examples/errors/error_undefined_label.cay")
+        .expect("breaking to an undefined label should fail to compile");
+    assert!(
+        error.contains("Undefined label 'outr'"),
+        "Should report the undefined label, got: {}",
+        error
+    );
+}
+
+#[test]
+fn test_error_non_boolean_condition() {
+    let error = compile_eol_expect_error("examples/errors/error_non_boolean_condition.cay")
+        .expect("an int loop condition should fail to compile");
+    assert!(
+        error.contains("Condition of while loop must be boolean, got int"),
+        "Should report the non-boolean condition, got: {}",
+        error
+    );
+}
+
+#[test]
+fn test_read_line() {
+    let (output, code) = run_with_stdin_fixture("examples/test_read_line.cay", &[]).expect("cay-run should compile and run");
+    assert_eq!(code, 0, "Program should exit successfully, got: {}", output);
+    assert!(output.contains("pair: [hello|world]"), "Lines read in another method should stay valid without newlines, got: {}", output);
+    assert!(output.contains("long length: 603") && output.contains("long ends with: END"), "Long lines should not be truncated, got: {}", output);
+    assert!(output.contains("crlf: [windows] 7"), "A trailing \\r\\n should be stripped, got: {}", output);
+    assert!(output.contains("eof: [] 0"), "readLine at EOF should return an empty string, got: {}", output);
+}
+
+#[test]
+fn test_typed_read() {
+    let (output, code) = run_with_stdin_fixture("examples/test_typed_read.cay", &[]).expect("cay-run should compile and run");
+    assert_eq!(code, 0, "Program should exit successfully, got: {}", output);
+    assert!(output.contains("int: 42") && output.contains("long: 10000000000"), "Integer reads should keep their width, got: {}", output);
+    assert!(output.contains("float: 3.0") && output.contains("double: 1.25"), "Floating-point reads should return float and double, got: {}", output);
+    assert!(output.contains("char: B") && output.contains("bool: true false"), "readChar and readBool should parse their input, got: {}", output);
+    assert!(output.contains("widened: 7"), "readInt assigned to long should be widened, got: {}", output);
+}
+
+#[test]
+fn test_file_io() {
+    let output = compile_and_run_eol("examples/test_file_io.cay").expect("file io example should compile and run");
+    assert!(output.contains("exists before: false") && output.contains("exists after: true"), "File.exists should track the file, got: {}", output);
+    assert!(output.contains("write: true") && output.contains("append: true"), "Writes should succeed, got: {}", output);
+    assert!(output.contains("first line\nsecond line\nthird line") || output.contains("first line\r\nsecond line\r\nthird line"),
+        "readAll should return everything written, got: {}", output);
+    assert!(output.contains("length: 34") && output.contains("lines: 3"), "Content should be read byte for byte, got: {}", output);
+    assert!(output.contains("missing is null: true"), "readAll of a missing file should return null, got: {}", output);
+    assert!(output.contains("delete: true") && output.contains("delete again: false") && output.contains("exists end: false"),
+        "delete should remove the file once, got: {}", output);
+}
+
+#[test]
+fn test_main_args() {
+    let (output, code) = run_with_io("examples/test_main_args.cay", &["--sum", "hello world", ""], "").expect("cay-run should compile and run");
+    assert_eq!(code, 3, "main's return value should be the exit code, got: {}", output);
+    assert!(output.contains("argc: 3"), "args should not include the program name, got: {}", output);
+    assert!(output.contains("arg 1: [hello world] length 11") && output.contains("arg 2: [] length 0"),
+        "Arguments should be passed through unchanged, got: {}", output);
+    assert!(output.contains("sum of lengths: 16"), "got: {}", output);
+
+    let (output, code) = run_with_io("examples/test_main_args.cay", &[], "").expect("cay-run should compile and run");
+    assert_eq!(code, 0, "got: {}", output);
+    assert!(output.contains("argc: 0"), "Without arguments args should be empty, got: {}", output);
+}
+
+#[test]
+fn test_exit() {
+    let (output, code) = run_with_io("examples/test_exit.cay", &["abcd"], "").expect("cay-run should compile and run");
+    assert_eq!(code, 0, "got: {}", output);
+    assert!(output.contains("size: 4"), "got: {}", output);
+
+    let (output, code) = run_with_io("examples/test_exit.cay", &[], "").expect("cay-run should compile and run");
+    assert_eq!(code, 2, "exit(2) should set the exit code, got: {}", output);
+    assert!(output.contains("usage: test_exit <text>") && !output.contains("size:"), "got: {}", output);
+
+    let (output, code) = run_with_io("examples/test_exit.cay", &[""], "").expect("cay-run should compile and run");
+    assert_eq!(code, 3, "exit in a called method should end the program, got: {}", output);
+    assert!(output.contains("error: empty input"), "Output without a newline should be flushed before exiting, got: {}", output);
+}
+
+#[test]
+fn test_error_unreachable_after_exit() {
+    let error = compile_eol_expect_error("examples/errors/error_unreachable_after_exit.cay")
+        .expect("a statement after exit() should fail to compile");
+    assert!(
+        error.contains("Unreachable statement"),
+        "Should report the statement after exit() as unreachable, got: {}",
+        error
+    );
+}
+
+#[test]
+fn test_list() {
+    let (output, code) = run_with_io("examples/test_list.cay", &[], "").expect("cay-run should compile and run");
+    assert!(output.contains("removed: write"), "got: {}", output);
+    assert!(output.contains("task: debug\ntask: review") || output.contains("task: debug\r\ntask: review"), "for-each should visit elements in order, got: {}", output);
+    assert!(output.contains("size: 2") && output.contains("index of review: 1"), "got: {}", output);
+    assert!(output.contains("sum of squares: 2870"), "the list should grow past its initial capacity, got: {}", output);
+    assert!(output.contains("odd digits: [3, 1, 1, 5]"), "got: {}", output);
+    assert!(output.contains("Error: List index 4 out of bounds for length 4"), "got: {}", output);
+    assert_eq!(code, 1, "an out-of-range index should end the program, got: {}", output);
+}
+
+#[test]
+fn test_map() {
+    let (output, code) = run_with_io("examples/test_map.cay", &[], "").expect("cay-run should compile and run");
+    assert_eq!(code, 0, "got: {}", output);
+    assert!(output.contains("counts: {the=3, cat=1, and=2, dog=1, bird=1}"), "keys should keep insertion order, got: {}", output);
+    assert!(output.contains("repeated: the x3") && output.contains("repeated: and x2"), "got: {}", output);
+    assert!(output.contains("200 -> Success") && output.contains("418 -> unknown"), "got: {}", output);
+    assert!(output.contains("codes: {200=Success, 500=Server Error, 404=Missing}"), "a removed key should be re-added at the end, got: {}", output);
+    assert!(output.contains("squares left: 3333, sum: 27769446111"), "the table should grow past its initial capacity, got: {}", output);
+}
+
+#[test]
+fn test_error_map_key_type() {
+    let error = compile_eol_expect_error("examples/errors/error_map_key_type.cay")
+        .expect("a Map with double keys should fail to compile");
+    assert!(
+        error.contains("Map keys must be String or int, got double"),
+        "Should reject the unsupported key type, got: {}",
+        error
+    );
+}
+
+#[test]
+fn test_string_builder() {
+    let (output, code) = run_with_io("examples/test_string_builder.cay", &[], "").expect("cay-run should compile and run");
+    assert_eq!(code, 0, "got: {}", output);
+    assert!(output.contains("values: 42 7 true 3 -4"), "each primitive should use its own append overload, got: {}", output);
+    assert!(output.contains("length: 22") && output.contains("snapshot: values: 42 7 true 3 -4\n"), "got: {}", output);
+    assert!(output.contains("squares: 1, 4, 9, 16, 25"), "got: {}", output);
+    assert!(output.contains("big length: 100000") && output.contains("tail: zabcd"), "the buffer should grow past its initial capacity, got: {}", output);
+    assert!(output.contains("null?"), "a null string should be appended as null, got: {}", output);
+}
+
+#[test]
+fn test_char_unicode() {
+    let (output, code) = run_with_io("examples/test_char_unicode.cay", &[], "").expect("cay-run should compile and run");
+    assert_eq!(code, 0, "got: {}", output);
+    assert!(output.contains("next: b") && output.contains("sum: 195") && output.contains("code of A: 65"), "got: {}", output);
+    assert!(output.contains("han: 中 code 20013") && output.contains("emoji: 😀"), "chars should hold full code points, got: {}", output);
+    assert!(output.contains("charAt(1): é code 233"), "charAt should decode the UTF-8 sequence, got: {}", output);
+    assert!(output.contains("codes: 104 233 65533 108 108 111\n"), "charAt at a continuation byte should return U+FFFD, got: {}", output);
+    assert!(output.contains("ß中z\n"), "char arrays should print as UTF-8 text, got: {}", output);
+    assert!(output.contains("kind a: vowel") && output.contains("kind 中: cjk") && output.contains("kind z: other"), "got: {}", output);
+    assert!(output.contains("cast: q66") && output.contains("upper: Z"), "got: {}", output);
+}
+
+#[test]
+fn test_bool_print() {
+    let (output, code) = run_with_io("examples/test_bool_print.cay", &[], "").expect("cay-run should compile and run");
+    assert_eq!(code, 0, "got: {}", output);
+    assert!(output.starts_with("true\nfalse\ntrue false\n"), "booleans should print as true/false, got: {}", output);
+    assert!(output.contains("concat: true false") && output.contains("[true, false, true]"), "got: {}", output);
+    assert!(!output.contains("-1"), "a boolean should not print as an integer, got: {}", output);
+}
+
+#[test]
+fn test_arrays_to_string() {
+    let (output, code) = run_with_io("examples/test_arrays_to_string.cay", &[], "").expect("cay-run should compile and run");
+    assert_eq!(code, 0, "got: {}", output);
+    assert!(output.starts_with("[1, 2, 3]\n[10000000000, -1]\n[1.5, 2.0]\n[true, false]\n[h, i]\n[1, -2]\n"), "each element type should be formatted, got: {}", output);
+    assert!(output.contains("[ann, null, bob]\n[[1, 2], [3, 4, 5]]\n[]\nnull\n"), "nested, empty and null arrays should be formatted, got: {}", output);
+    assert!(output.contains("grid = [[1, 2], [3, 4, 5]], ints = [1, 2, 3]"), "arrays should convert in string concatenation, got: {}", output);
+    assert!(output.contains("length of text: 9"), "got: {}", output);
+}
+
+#[test]
+fn test_error_arrays_to_string_arg() {
+    let error = compile_eol_expect_error("examples/errors/error_arrays_to_string_arg.cay")
+        .expect("Arrays.toString with an int argument should fail to compile");
+    assert!(error.contains("Arrays.toString() expects an array argument, got int"), "Should reject non-array arguments, got: {}", error);
+}
+
+#[test]
+fn test_array_2d_length() {
+    let (output, code) = run_with_io("examples/test_array_2d_length.cay", &[], "").expect("cay-run should compile and run");
+    assert_eq!(code, 0, "got: {}", output);
+    assert!(output.starts_with("rows: 3, cols: 4\n"), "every dimension should have a length, got: {}", output);
+    assert!(output.contains("[[0, 1, 2, 3], [10, 11, 12, 13], [20, 21, 22, 23]]\nsum: 138\n"), "got: {}", output);
+    assert!(output.contains("cube: 2 x 3 x 5\n[0, 0, 0, 0, 7]\n"), "3D arrays should keep lengths at every level, got: {}", output);
+    assert!(output.contains("jagged rows: 3, last row: 0\njagged sum: 6\n"), "got: {}", output);
+    assert!(output.contains("first <= 5 at: 0"), "&& should not evaluate an out-of-bounds right side, got: {}", output);
+}
+
+#[test]
+fn test_error_array_index_out_of_bounds() {
+    let error = compile_and_run_expect_error("examples/errors/error_array_index_out_of_bounds.cay")
+        .expect("out-of-bounds access should fail at runtime");
+    assert!(error.contains("last: 5"), "in-bounds accesses should succeed, got: {}", error);
+    assert!(error.contains("Array index out of bounds: index 3, length 3"), "Should report the index and length, got: {}", error);
+    assert!(!error.contains("unreachable"), "Program should exit at the bad access, got: {}", error);
+}
+
+#[test]
+fn test_array_covariance() {
+    let (output, code) = run_with_io("examples/test_array_covariance.cay", &[], "").expect("cay-run should compile and run");
+    assert_eq!(code, 0, "got: {}", output);
+    assert!(output.starts_with("dog dog \n"), "Dog[] should be usable as Animal[], got: {}", output);
+    assert!(output.contains("3 1.0\n"), "initializers should convert to the declared element type, got: {}", output);
+    assert!(output.contains("sum: 97, 7"), "byte elements should be loaded as bytes, got: {}", output);
+}
+
+#[test]
+fn test_error_array_covariance() {
+    let error = compile_eol_expect_error("examples/errors/error_array_covariance.cay")
+        .expect("assigning int[] to long[] should fail to compile");
+    assert!(error.contains("Cannot assign int[] to long[]"), "Primitive arrays should be invariant, got: {}", error);
+}
+
+#[test]
+fn test_inc_dec_lvalues() {
+    let (output, code) = run_with_io("examples/test_inc_dec_lvalues.cay", &[], "").expect("cay-run should compile and run");
+    assert_eq!(code, 0, "got: {}", output);
+    assert!(output.contains("old = 1\ncounts = [2, 0, 1]\n"), "array elements should be incremented in place, got: {}", output);
+    assert!(output.contains("counts[1] = -1, calls = 1\n"), "the index should be evaluated once, got: {}", output);
+    assert!(output.contains("grid[1][0] = 4\nweights[0] = 1.0\nb\n"), "got: {}", output);
+    assert!(output.contains("hits = 2, before = 3\ntotal = 2\n"), "fields should be incremented in place, got: {}", output);
+}
+
+#[test]
+fn test_negative_division() {
+    let output = compile_and_run_eol("examples/test_negative_division.cay").expect("negative division example should compile and run");
+    let lines: Vec<&str> = output.lines().collect();
+    let expected = [
+        "-3", "-1", "1", "-3", "-1",
+        "-4", "-5", "-18", "-5", "-9", "4", "5", "1",
+        "-976562500", "0", "-25", "-36",
+        "-2147483648", "0", "-1073741824", "0", "-3", "0",
+    ];
+    assert_eq!(lines, expected, "division should truncate toward zero, got: {}", output);
+}
+
+#[test]
+fn test_native_ffi() {
+    let output = compile_and_run_eol("examples/test_native_ffi.cay").expect("native method example should compile and run");
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines, ["7", "4000000000", "8", "246", "2.0", "A", "true"], "static native methods should call C functions, got: {}", output);
+}
+
+#[test]
+fn test_error_native_instance() {
+    let error = compile_eol_expect_error("examples/errors/error_native_instance.cay")
+        .expect("an instance native method outside built-in classes should fail to compile");
+    assert!(error.contains("Native method 'Main.length' must be static"), "Should report the instance native method, got: {}", error);
+}
+
+#[test]
+fn test_inline_ir() {
+    let output = compile_and_run_eol("examples/test_inline_ir.cay").expect("inline IR example should compile and run");
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines, ["7", "-2147483648", "-9223372036854775808", "6", "8", "2.5", "true"], "__llvm__ should splice IR into the function, got: {}", output);
+}
+
+#[test]
+fn test_error_inline_ir_args() {
+    let error = compile_eol_expect_error("examples/errors/error_inline_ir_args.cay")
+        .expect("an inline IR placeholder without an argument should fail to compile");
+    assert!(error.contains("Inline IR template refers to $1, but only 1 argument(s) were given"), "Should report the missing argument, got: {}", error);
+}
+
+#[test]
+fn test_separate_compilation_instance_fields() {
+    // 库和程序分别编译：程序按 .cavi 中的布局分配导入的类的对象并读写其字段
+    let dir = "target/separate_test";
+    fs::create_dir_all(dir).expect("create output directory");
+    let cavi = format!("{}/util.cavi", dir);
+    let (util_ll, util_obj) = (format!("{}/util.ll", dir), format!("{}/util.o", dir));
+    let (app_ll, app_exe) = (format!("{}/app.ll", dir), format!("{}/app.exe", dir));
+    let run = |program: &str, args: &[&str]| {
+        let output = Command::new(program).args(args).output().unwrap_or_else(|e| panic!("Failed to execute {}: {}", program, e));
+        assert!(output.status.success(), "{} {:?} failed: {}", program, args, String::from_utf8_lossy(&output.stderr));
+    };
+    run("./target/release/cay-ir.exe", &["--no-main", "--emit-interface", &cavi, "examples/separate/util.cay", &util_ll]);
+    run("./target/release/cay-ir.exe", &["--import", &cavi, "examples/separate/app.cay", &app_ll]);
+    run("./target/release/ir2exe.exe", &["-c", &util_ll, &util_obj]);
+    run("./target/release/ir2exe.exe", &["--ldflags", &util_obj, &app_ll, &app_exe]);
+
+    let output = Command::new(&app_exe).output().expect("Failed to run the linked program");
+    let stdout = String::from_utf8_lossy(&output.stdout).replace("\r\n", "\n");
+    assert_eq!(stdout, "7\n8\nutil\n99\n16\n7\n", "instance fields of imported classes should be shared across units");
+    let _ = fs::remove_dir_all(dir);
+}